@@ -1,8 +1,12 @@
 //! Groups integration tests for the match circuitry
 
 use circuits::{
-    mpc_circuits::r#match::compute_match,
-    types::{order::Order, r#match::MatchResult},
+    mpc_circuits::r#match::{compute_match, match_orders, match_orders_with_bounds},
+    types::{
+        balance::Balance,
+        order::Order,
+        r#match::{MatchBounds, MatchResult},
+    },
     zk_gadgets::fixed_point::FixedPoint,
     Allocate, Open,
 };
@@ -230,6 +234,202 @@ fn test_match_valid_match(test_args: &IntegrationTestArgs) -> Result<(), String>
     Ok(())
 }
 
+/// Tests that a match on balance capitalized orders is found only when both balances
+/// cover the side of the match their party sends
+fn test_match_orders_balances(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let party_id = test_args.party_id;
+    macro_rules! sel {
+        ($a:expr, $b:expr) => {
+            if party_id == 0 {
+                $a
+            } else {
+                $b
+            }
+        };
+    }
+
+    // Party 0 buys 20 units of the base at 10, party 1 sells 30 units at 5; this executes
+    // 20 units of the base for 150 units of the quote
+    let case: Vec<u64> = vec![
+        1,            /* quote_mint */
+        2,            /* base_mint */
+        sel!(0, 1),   /* side */
+        sel!(10, 5),  /* price */
+        sel!(20, 30), /* amount */
+        0,            /* timestamp */
+    ];
+    let mut my_order: Order = (&case as &[u64]).try_into().unwrap();
+    my_order.price = FixedPoint::from_integer(case[3].to_owned());
+
+    let expected_match = MatchResult {
+        quote_mint: BigUint::from(1u8),
+        base_mint: BigUint::from(2u8),
+        quote_amount: 150,
+        base_amount: 20,
+        direction: 0,
+        execution_price: FixedPoint::from(7.5),
+        max_minus_min_amount: 10,
+        min_amount_order_index: 0,
+    };
+
+    // Each party's balance as (mint, amount), and whether a match is expected
+    let test_cases = vec![
+        // Both balances exactly cover the match
+        (sel!((1u64, 150u64), (2, 20)), true),
+        // The buyer is one unit of the quote short
+        (sel!((1, 149), (2, 20)), false),
+        // The seller is one unit of the base short
+        (sel!((1, 150), (2, 19)), false),
+        // The seller's balance is of the quote mint, which it does not send
+        (sel!((1, 150), (1, 20)), false),
+    ];
+
+    for ((mint, amount), expect_match) in test_cases.into_iter() {
+        let my_balance = Balance {
+            mint: BigUint::from(mint),
+            amount,
+        };
+
+        // Allocate the orders and balances in the network
+        let order1 = my_order
+            .allocate(0 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating order1 in the network: {:?}", err))?;
+        let balance1 = my_balance
+            .allocate(0 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating balance1 in the network: {:?}", err))?;
+        let order2 = my_order
+            .allocate(1 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating order2 in the network: {:?}", err))?;
+        let balance2 = my_balance
+            .allocate(1 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating balance2 in the network: {:?}", err))?;
+
+        let res = match_orders(
+            &order1,
+            &balance1,
+            &order2,
+            &balance2,
+            test_args.mpc_fabric.clone(),
+        )
+        .map_err(|err| format!("Error computing order match: {:?}", err))?
+        .open_and_authenticate(test_args.mpc_fabric.clone())
+        .map_err(|err| format!("Error opening match result: {:?}", err))?;
+
+        if expect_match {
+            check_single_match(&res, &expected_match)?;
+        } else {
+            check_no_match(&res)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that a match is found only when it falls within both parties' minimum fill size
+/// and price band
+fn test_match_orders_bounds(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let party_id = test_args.party_id;
+    macro_rules! sel {
+        ($a:expr, $b:expr) => {
+            if party_id == 0 {
+                $a
+            } else {
+                $b
+            }
+        };
+    }
+
+    // The same orders as in `test_match_orders_balances`, executing 20 units of the base
+    // at 7.5, capitalized by balances that exactly cover the match
+    let case: Vec<u64> = vec![
+        1,            /* quote_mint */
+        2,            /* base_mint */
+        sel!(0, 1),   /* side */
+        sel!(10, 5),  /* price */
+        sel!(20, 30), /* amount */
+        0,            /* timestamp */
+    ];
+    let mut my_order: Order = (&case as &[u64]).try_into().unwrap();
+    my_order.price = FixedPoint::from_integer(case[3].to_owned());
+    let my_balance = Balance {
+        mint: BigUint::from(sel!(1u64, 2u64)),
+        amount: sel!(150, 20),
+    };
+
+    let expected_match = MatchResult {
+        quote_mint: BigUint::from(1u8),
+        base_mint: BigUint::from(2u8),
+        quote_amount: 150,
+        base_amount: 20,
+        direction: 0,
+        execution_price: FixedPoint::from(7.5),
+        max_minus_min_amount: 10,
+        min_amount_order_index: 0,
+    };
+
+    // Each party's bounds as (min fill size, min price, max price), and whether a match
+    // is expected
+    let test_cases = vec![
+        // Neither party bounds the match
+        (sel!((0, 0., 100.), (0, 0., 100.)), true),
+        // The bounds are met exactly
+        (sel!((20, 7.5, 7.5), (20, 7.5, 7.5)), true),
+        // The seller requires a larger fill than the buyer offers
+        (sel!((0, 0., 100.), (21, 0., 100.)), false),
+        // The execution price is above the buyer's band
+        (sel!((0, 0., 7.), (0, 0., 100.)), false),
+        // The execution price is below the seller's band
+        (sel!((0, 0., 100.), (0, 8., 100.)), false),
+    ];
+
+    for ((min_fill_size, min_price, max_price), expect_match) in test_cases.into_iter() {
+        let my_bounds = MatchBounds::unbounded()
+            .with_min_fill_size(min_fill_size)
+            .with_price_range(FixedPoint::from(min_price), FixedPoint::from(max_price));
+
+        // Allocate the orders, balances, and bounds in the network
+        let order1 = my_order
+            .allocate(0 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating order1 in the network: {:?}", err))?;
+        let balance1 = my_balance
+            .allocate(0 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating balance1 in the network: {:?}", err))?;
+        let bounds1 = my_bounds
+            .allocate(0 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating bounds1 in the network: {:?}", err))?;
+        let order2 = my_order
+            .allocate(1 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating order2 in the network: {:?}", err))?;
+        let balance2 = my_balance
+            .allocate(1 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating balance2 in the network: {:?}", err))?;
+        let bounds2 = my_bounds
+            .allocate(1 /* owning_party */, test_args.mpc_fabric.clone())
+            .map_err(|err| format!("Error allocating bounds2 in the network: {:?}", err))?;
+
+        let res = match_orders_with_bounds(
+            &order1,
+            &balance1,
+            &bounds1,
+            &order2,
+            &balance2,
+            &bounds2,
+            test_args.mpc_fabric.clone(),
+        )
+        .map_err(|err| format!("Error computing order match: {:?}", err))?
+        .open_and_authenticate(test_args.mpc_fabric.clone())
+        .map_err(|err| format!("Error opening match result: {:?}", err))?;
+
+        if expect_match {
+            check_single_match(&res, &expected_match)?;
+        } else {
+            check_no_match(&res)?;
+        }
+    }
+
+    Ok(())
+}
+
 // Take inventory
 inventory::submit!(TestWrapper(IntegrationTest {
     name: "mpc_circuits::test_match_no_match",
@@ -240,3 +440,13 @@ inventory::submit!(TestWrapper(IntegrationTest {
     name: "mpc_circuits::test_match_valid_match",
     test_fn: test_match_valid_match
 }));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "mpc_circuits::test_match_orders_balances",
+    test_fn: test_match_orders_balances
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "mpc_circuits::test_match_orders_bounds",
+    test_fn: test_match_orders_bounds
+}));
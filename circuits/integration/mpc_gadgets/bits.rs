@@ -1,6 +1,6 @@
 //! Groups integration tests for bitwise operating MPC gadgets
 
-use circuits::mpc_gadgets::bits::{bit_add, bit_lt, bit_xor, to_bits_le};
+use circuits::mpc_gadgets::bits::{batch_to_bits_le, bit_add, bit_lt, bit_xor, to_bits_le};
 use curve25519_dalek::scalar::Scalar;
 use integration_helpers::types::IntegrationTest;
 use mpc_ristretto::{
@@ -207,6 +207,37 @@ fn test_to_bits_le(test_args: &IntegrationTestArgs) -> Result<(), String> {
     Ok(())
 }
 
+/// Tests the batched to_bits_le gadget on a mix of shared and public values
+fn test_batch_to_bits_le(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let mut rng = thread_rng();
+    let values = vec![rng.next_u32() as u64, 0, rng.next_u32() as u64];
+
+    // Party 0 shares the first two values, the third is public
+    let mut shared_values = test_args
+        .borrow_fabric()
+        .batch_allocate_private_u64s(0 /* owning_party */, &values[..2])
+        .map_err(|err| format!("Error sharing values: {:?}", err))?;
+    shared_values.push(test_args.borrow_fabric().allocate_public_u64(values[2]));
+
+    let shared_bits = batch_to_bits_le::<64, _, _>(&shared_values, test_args.mpc_fabric.clone())
+        .map_err(|err| format!("Error in batch_to_bits_le(): {:?}", err))?;
+
+    // Open the original values so that both parties know the expected bits
+    let opened_values = AuthenticatedScalar::batch_open_and_authenticate(&shared_values)
+        .map_err(|err| format!("Error opening values: {:?}", err))?;
+
+    for (bits, value) in shared_bits.iter().zip(opened_values.iter()) {
+        let opened_bits = AuthenticatedScalar::batch_open_and_authenticate(&bits[..64])
+            .map_err(|err| format!("Error opening shared bits: {:?}", err))?;
+        check_equal_vec(
+            &opened_bits,
+            &u64_to_bits_le(scalar_to_u64(&value.to_scalar())),
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Tests the bitwise less than comparator
 fn test_bit_lt(test_args: &IntegrationTestArgs) -> Result<(), String> {
     // Test equal values
@@ -295,6 +326,11 @@ inventory::submit!(TestWrapper(IntegrationTest {
     test_fn: test_to_bits_le
 }));
 
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "mpc_gadgets::test_batch_to_bits_le",
+    test_fn: test_batch_to_bits_le
+}));
+
 inventory::submit!(TestWrapper(IntegrationTest {
     name: "mpc_gadgets::test_bit_lt",
     test_fn: test_bit_lt
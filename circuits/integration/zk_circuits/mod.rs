@@ -1,3 +1,4 @@
 //! Groups integration tests for zero-knowledge circuits
 
+pub mod valid_match_encryption;
 pub mod valid_match_mpc;
@@ -0,0 +1,367 @@
+//! Groups integration tests for the multiprover VALID MATCH ENCRYPTION circuit
+
+use circuits::{
+    mpc::SharedFabric,
+    native_helpers::compute_note_commitment,
+    types::{
+        fee::Fee,
+        note::{AuthenticatedNote, Note, NoteType},
+        order::OrderSide,
+        r#match::AuthenticatedMatchResult,
+    },
+    zk_circuits::valid_match_encryption::{
+        ValidMatchEncryptionMpc, ValidMatchEncryptionMpcWitness, ValidMatchEncryptionStatement,
+    },
+    zk_gadgets::{
+        elgamal::{ElGamalCiphertext, DEFAULT_ELGAMAL_GENERATOR},
+        fixed_point::{AuthenticatedFixedPoint, FixedPoint},
+    },
+    LinkableCommitment,
+};
+use crypto::fields::{biguint_to_scalar, prime_field_to_scalar, scalar_to_biguint};
+use curve25519_dalek::scalar::Scalar;
+use integration_helpers::{
+    mpc_network::field::get_ristretto_group_modulus, types::IntegrationTest,
+};
+use mpc_bulletproof::r1cs_mpc::{MultiproverError, R1CSError};
+use mpc_ristretto::{beaver::SharedValueSource, mpc_scalar::scalar_to_u64, network::MpcNetwork};
+use num_bigint::BigUint;
+
+use crate::{zk_gadgets::multiprover_prove_and_verify, IntegrationTestArgs, TestWrapper};
+
+/// The bitlength of the ElGamal randomness
+const ELGAMAL_BITS: usize = 3;
+/// The protocol fee
+const PROTOCOL_FEE: f32 = 0.01;
+/// The hashed wallet randomness of the first party
+const PARTY0_RANDOMNESS_HASH: u64 = 0xdead;
+/// The hashed wallet randomness of the second party
+const PARTY1_RANDOMNESS_HASH: u64 = 0xbeef;
+
+/// The plaintext data both relayers build the witness and statement from
+///
+/// Every value is deterministic so that both relayers construct the same statement
+struct MatchEncryptionData {
+    /// The fee of the first party
+    party0_fee: Fee,
+    /// The fee of the second party
+    party1_fee: Fee,
+    /// The notes, in the order party0, party1, relayer0, relayer1, protocol
+    notes: [Note; 5],
+    /// The randomness of each ElGamal encryption
+    elgamal_randomness: Vec<Scalar>,
+    /// The statement of the circuit
+    statement: ValidMatchEncryptionStatement,
+}
+
+/// Build the notes, encryptions, and statement of a match in which the second party
+/// buys 200 units of the base token for 300 units of the quote token
+fn dummy_match_data() -> MatchEncryptionData {
+    let (quote_mint, base_mint) = (BigUint::from(1u8), BigUint::from(1u8));
+    let (quote_amount, base_amount) = (300u64, 200u64);
+    let (buy, sell) = (OrderSide::Buy, OrderSide::Sell);
+
+    let party0_fee = Fee {
+        settle_key: BigUint::from(42u64),
+        gas_addr: BigUint::from(10u64),
+        gas_token_amount: 2,
+        percentage_fee: FixedPoint::from(0.01),
+    };
+    let party1_fee = Fee {
+        settle_key: BigUint::from(1729u64),
+        gas_addr: BigUint::from(10u64),
+        gas_token_amount: 2,
+        percentage_fee: FixedPoint::from(0.01),
+    };
+
+    // The share of each volume paid to the parties, their relayers, and the protocol
+    let relayer_fee_fraction = party0_fee.percentage_fee;
+    let protocol_fee_fraction = FixedPoint::from(PROTOCOL_FEE);
+    let party_fee_fraction =
+        FixedPoint::from_integer(1u64) - relayer_fee_fraction - protocol_fee_fraction;
+    let share =
+        |fraction: FixedPoint, amount: u64| scalar_to_u64(&(fraction * amount.into()).floor());
+
+    let party0_randomness = BigUint::from(PARTY0_RANDOMNESS_HASH);
+    let party1_randomness = BigUint::from(PARTY1_RANDOMNESS_HASH);
+    let note =
+        |volume1, direction1, volume2, direction2, fee: &Fee, fee_direction, type_, randomness| {
+            Note {
+                mint1: base_mint.clone(),
+                volume1,
+                direction1,
+                mint2: quote_mint.clone(),
+                volume2,
+                direction2,
+                fee_mint: fee.gas_addr.clone(),
+                fee_volume: fee.gas_token_amount,
+                fee_direction,
+                type_,
+                randomness,
+            }
+        };
+
+    let party0_note = note(
+        base_amount,
+        sell,
+        share(party_fee_fraction, quote_amount),
+        buy,
+        &party0_fee,
+        sell,
+        NoteType::Match,
+        party0_randomness.clone(),
+    );
+    let party1_note = note(
+        share(party_fee_fraction, base_amount),
+        buy,
+        quote_amount,
+        sell,
+        &party1_fee,
+        sell,
+        NoteType::Match,
+        party1_randomness.clone(),
+    );
+    let relayer0_note = note(
+        0,
+        buy,
+        share(relayer_fee_fraction, quote_amount),
+        buy,
+        &party0_fee,
+        buy,
+        NoteType::InternalTransfer,
+        party0_randomness.clone() + 1u64,
+    );
+    let relayer1_note = note(
+        share(relayer_fee_fraction, base_amount),
+        buy,
+        0,
+        buy,
+        &party1_fee,
+        buy,
+        NoteType::InternalTransfer,
+        party1_randomness.clone() + 1u64,
+    );
+    let protocol_note = Note {
+        fee_mint: 0u8.into(),
+        fee_volume: 0,
+        ..note(
+            share(protocol_fee_fraction, base_amount),
+            buy,
+            share(protocol_fee_fraction, quote_amount),
+            buy,
+            &party0_fee,
+            buy,
+            NoteType::InternalTransfer,
+            party0_randomness + party1_randomness,
+        )
+    };
+
+    // Encrypt under fixed keys and randomness
+    let pk_settle = (1..=5u64)
+        .map(|i| Scalar::from(1000 + i))
+        .collect::<Vec<_>>();
+    let elgamal_randomness = (0..9u64)
+        .map(|i| Scalar::from(i % 7 + 1))
+        .collect::<Vec<_>>();
+    let encrypt = |i: usize, message: &BigUint, pubkey: &Scalar| {
+        elgamal_encrypt(message, pubkey, &elgamal_randomness[i])
+    };
+
+    let protocol_pk = &pk_settle[4];
+    let statement = ValidMatchEncryptionStatement {
+        party0_note_commit: note_commitment(&party0_note, pk_settle[0]),
+        party1_note_commit: note_commitment(&party1_note, pk_settle[1]),
+        relayer0_note_commit: note_commitment(&relayer0_note, pk_settle[2]),
+        relayer1_note_commit: note_commitment(&relayer1_note, pk_settle[3]),
+        protocol_note_commit: note_commitment(&protocol_note, pk_settle[4]),
+        pk_settle_party0: pk_settle[0],
+        pk_settle_party1: pk_settle[1],
+        pk_settle_relayer0: pk_settle[2],
+        pk_settle_relayer1: pk_settle[3],
+        pk_settle_protocol: pk_settle[4],
+        protocol_fee: FixedPoint::from(PROTOCOL_FEE),
+        volume1_ciphertext1: encrypt(0, &party0_note.volume1.into(), &pk_settle[0]),
+        volume2_ciphertext1: encrypt(1, &party0_note.volume2.into(), &pk_settle[0]),
+        volume1_ciphertext2: encrypt(2, &party1_note.volume1.into(), &pk_settle[1]),
+        volume2_ciphertext2: encrypt(3, &party1_note.volume2.into(), &pk_settle[1]),
+        mint1_protocol_ciphertext: encrypt(4, &protocol_note.mint1, protocol_pk),
+        volume1_protocol_ciphertext: encrypt(5, &protocol_note.volume1.into(), protocol_pk),
+        mint2_protocol_ciphertext: encrypt(6, &protocol_note.mint2, protocol_pk),
+        volume2_protocol_ciphertext: encrypt(7, &protocol_note.volume2.into(), protocol_pk),
+        randomness_protocol_ciphertext: encrypt(8, &protocol_note.randomness, protocol_pk),
+    };
+
+    MatchEncryptionData {
+        party0_fee,
+        party1_fee,
+        notes: [
+            party0_note,
+            party1_note,
+            relayer0_note,
+            relayer1_note,
+            protocol_note,
+        ],
+        elgamal_randomness,
+        statement,
+    }
+}
+
+/// Compute the commitment to a note as a scalar
+fn note_commitment(note: &Note, pk_settle: Scalar) -> Scalar {
+    prime_field_to_scalar(&compute_note_commitment(note, pk_settle))
+}
+
+/// ElGamal encrypt a message under the given key and randomness
+fn elgamal_encrypt(message: &BigUint, pubkey: &Scalar, randomness: &Scalar) -> ElGamalCiphertext {
+    let field_mod = get_ristretto_group_modulus();
+    let randomness = scalar_to_biguint(randomness);
+
+    let partial_shared_secret =
+        scalar_to_biguint(&DEFAULT_ELGAMAL_GENERATOR).modpow(&randomness, &field_mod);
+    let shared_secret = scalar_to_biguint(pubkey).modpow(&randomness, &field_mod);
+    let encrypted_message = (shared_secret * message) % field_mod;
+
+    ElGamalCiphertext {
+        partial_shared_secret: biguint_to_scalar(&partial_shared_secret),
+        encrypted_message: biguint_to_scalar(&encrypted_message),
+    }
+}
+
+/// Share a note owned by the first party in the MPC network
+fn share_note<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    note: &Note,
+    fabric: SharedFabric<N, S>,
+) -> Result<AuthenticatedNote<N, S>, String> {
+    let values = [
+        biguint_to_scalar(&note.mint1),
+        Scalar::from(note.volume1),
+        Scalar::from(note.direction1 as u8),
+        biguint_to_scalar(&note.mint2),
+        Scalar::from(note.volume2),
+        Scalar::from(note.direction2 as u8),
+        biguint_to_scalar(&note.fee_mint),
+        Scalar::from(note.fee_volume),
+        Scalar::from(note.fee_direction as u8),
+        Scalar::from(note.type_ as u8),
+        biguint_to_scalar(&note.randomness),
+    ];
+    let shared = fabric
+        .borrow_fabric()
+        .batch_allocate_private_scalars(0 /* owning_party */, &values)
+        .map_err(|err| format!("Error sharing note: {:?}", err))?;
+
+    Ok(AuthenticatedNote {
+        mint1: shared[0].to_owned(),
+        volume1: shared[1].to_owned(),
+        direction1: shared[2].to_owned(),
+        mint2: shared[3].to_owned(),
+        volume2: shared[4].to_owned(),
+        direction2: shared[5].to_owned(),
+        fee_mint: shared[6].to_owned(),
+        fee_volume: shared[7].to_owned(),
+        fee_direction: shared[8].to_owned(),
+        type_: shared[9].to_owned(),
+        randomness: shared[10].to_owned(),
+    })
+}
+
+/// Both parties call this to share the match, notes, and encryption randomness, and to
+/// build their own view of the witness
+fn setup_witness<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    data: &MatchEncryptionData,
+    party_id: u64,
+    fabric: SharedFabric<N, S>,
+) -> Result<ValidMatchEncryptionMpcWitness<N, S>, String> {
+    let match_values = [
+        Scalar::from(1u8), // quote mint
+        Scalar::from(1u8), // base mint
+        Scalar::from(300u64),
+        Scalar::from(200u64),
+        Scalar::one(), // direction
+        Scalar::from(10u64),
+        Scalar::zero(),
+    ];
+    let shared_match = fabric
+        .borrow_fabric()
+        .batch_allocate_private_scalars(0 /* owning_party */, &match_values)
+        .map_err(|err| format!("Error sharing match result: {:?}", err))?;
+    let match_res = AuthenticatedMatchResult {
+        quote_mint: shared_match[0].to_owned(),
+        base_mint: shared_match[1].to_owned(),
+        quote_amount: shared_match[2].to_owned(),
+        base_amount: shared_match[3].to_owned(),
+        direction: shared_match[4].to_owned(),
+        execution_price: AuthenticatedFixedPoint::from_integer(Scalar::from(2u64), fabric.clone()),
+        max_minus_min_amount: shared_match[5].to_owned(),
+        min_amount_order_index: shared_match[6].to_owned(),
+    };
+
+    let elgamal_randomness = fabric
+        .borrow_fabric()
+        .batch_allocate_private_scalars(0 /* owning_party */, &data.elgamal_randomness)
+        .map_err(|err| format!("Error sharing encryption randomness: {:?}", err))?;
+
+    let (my_fee, my_randomness_hash) = if party_id == 0 {
+        (data.party0_fee.clone(), PARTY0_RANDOMNESS_HASH)
+    } else {
+        (data.party1_fee.clone(), PARTY1_RANDOMNESS_HASH)
+    };
+    let [party0_note, party1_note, relayer0_note, relayer1_note, protocol_note] = &data.notes;
+
+    Ok(ValidMatchEncryptionMpcWitness {
+        my_fee: my_fee.into(),
+        my_randomness_hash: LinkableCommitment::new(Scalar::from(my_randomness_hash)),
+        match_res: match_res.into(),
+        party0_note: share_note(party0_note, fabric.clone())?,
+        party1_note: share_note(party1_note, fabric.clone())?,
+        relayer0_note: share_note(relayer0_note, fabric.clone())?,
+        relayer1_note: share_note(relayer1_note, fabric.clone())?,
+        protocol_note: share_note(protocol_note, fabric)?,
+        elgamal_randomness,
+    })
+}
+
+/// Tests that the relayers collaboratively prove a valid encryption of a match
+fn test_valid_match_encryption_valid(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let data = dummy_match_data();
+    let witness = setup_witness(&data, test_args.party_id, test_args.mpc_fabric.clone())?;
+
+    multiprover_prove_and_verify::<'_, _, _, ValidMatchEncryptionMpc<'_, ELGAMAL_BITS, _, _>>(
+        witness,
+        data.statement,
+        test_args.mpc_fabric.clone(),
+    )
+    .map_err(|err| format!("Error proving and verifying: {:?}", err))
+}
+
+/// Tests that a proof over a note that does not match its committed value fails to verify
+fn test_valid_match_encryption_invalid_note(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    // Tamper with the first party's note after the statement has committed to it
+    let mut data = dummy_match_data();
+    data.notes[0].volume1 += 1;
+    let witness = setup_witness(&data, test_args.party_id, test_args.mpc_fabric.clone())?;
+
+    let res = multiprover_prove_and_verify::<
+        '_,
+        _,
+        _,
+        ValidMatchEncryptionMpc<'_, ELGAMAL_BITS, _, _>,
+    >(witness, data.statement, test_args.mpc_fabric.clone());
+
+    if let Err(MultiproverError::ProverError(R1CSError::VerificationError)) = res {
+        Ok(())
+    } else {
+        Err(format!("Expected verification error, got {:?}", res))
+    }
+}
+
+// Take inventory
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_circuits::valid_match_encryption::test_valid_match_encryption_valid",
+    test_fn: test_valid_match_encryption_valid
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_circuits::valid_match_encryption::test_valid_match_encryption_invalid_note",
+    test_fn: test_valid_match_encryption_invalid_note
+}));
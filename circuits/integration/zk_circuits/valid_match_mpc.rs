@@ -6,7 +6,7 @@ use circuits::{
     mpc::SharedFabric,
     types::{
         balance::Balance,
-        order::{Order, OrderSide},
+        order::{MatchConstraints, Order, OrderSide, TimeInForce},
         r#match::AuthenticatedMatchResult,
     },
     zk_circuits::valid_match_mpc::{
@@ -135,6 +135,8 @@ fn test_valid_match_mpc_valid(test_args: &IntegrationTestArgs) -> Result<(), Str
             price: FixedPoint::from_integer(my_order[3]),
             amount: my_order[4],
             timestamp,
+            time_in_force: TimeInForce::GoodTilCancelled,
+            constraints: MatchConstraints::default(),
         },
         Balance {
             mint: my_balance_mint,
@@ -1,6 +1,6 @@
 //! Groups gadgets around arithemtic integration tests
 use circuits::zk_gadgets::arithmetic::{
-    ExpGadgetStatement, MultiproverExpGadget, MultiproverExpWitness,
+    ExpGadgetStatement, MultiproverExpGadget, MultiproverExpWitness, MultiproverPrivateExpGadget,
 };
 use crypto::fields::{bigint_to_scalar, scalar_to_bigint};
 use curve25519_dalek::scalar::Scalar;
@@ -86,6 +86,59 @@ fn test_exp_multiprover_invalid(test_args: &IntegrationTestArgs) -> Result<(), S
     }
 }
 
+/// Tests the private exponentiation gadget on a shared base and exponent
+fn test_private_exp_multiprover(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    // Party 0 chooses the base, party 1 chooses the exponent
+    let mut rng = OsRng {};
+    let shared_base = test_args
+        .borrow_fabric()
+        .allocate_private_scalar(0 /* owning_party */, Scalar::random(&mut rng))
+        .map_err(|err| format!("Error sharing base: {:?}", err))?;
+    let shared_exp = test_args
+        .borrow_fabric()
+        .allocate_private_u64(1 /* owning_party */, rng.next_u32() as u64)
+        .map_err(|err| format!("Error sharing exponent: {:?}", err))?;
+
+    // Compute the expected result
+    let base_open = scalar_to_bigint(&shared_base.open_and_authenticate().unwrap().to_scalar());
+    let exp_open = scalar_to_bigint(&shared_exp.open_and_authenticate().unwrap().to_scalar());
+
+    let expected_res = base_open.modpow(&exp_open, &get_ristretto_group_modulus().into());
+    let expected_scalar = bigint_to_scalar(&expected_res);
+
+    multiprover_prove_and_verify::<'_, _, _, MultiproverPrivateExpGadget<'_, 32, _, _>>(
+        (shared_base, shared_exp),
+        expected_scalar,
+        test_args.mpc_fabric.clone(),
+    )
+    .map_err(|err| format!("Error proving and verifying: {:?}", err))
+}
+
+/// Tests the private exponentiation gadget on an incorrect expected output
+fn test_private_exp_multiprover_invalid(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let mut rng = OsRng {};
+    let shared_base = test_args
+        .borrow_fabric()
+        .allocate_private_scalar(0 /* owning_party */, Scalar::random(&mut rng))
+        .map_err(|err| format!("Error sharing base: {:?}", err))?;
+    let shared_exp = test_args
+        .borrow_fabric()
+        .allocate_private_u64(1 /* owning_party */, rng.next_u32() as u64)
+        .map_err(|err| format!("Error sharing exponent: {:?}", err))?;
+
+    let res = multiprover_prove_and_verify::<'_, _, _, MultiproverPrivateExpGadget<'_, 32, _, _>>(
+        (shared_base, shared_exp),
+        Scalar::from(5u64), // Incorrect output
+        test_args.mpc_fabric.clone(),
+    );
+
+    if let Err(MultiproverError::ProverError(R1CSError::VerificationError)) = res {
+        Ok(())
+    } else {
+        Err(format!("Expected verification error, got {:?}", res))
+    }
+}
+
 // Take inventory
 inventory::submit!(TestWrapper(IntegrationTest {
     name: "zk_gadgets::arithmetic::test_exp_multiprover",
@@ -96,3 +149,13 @@ inventory::submit!(TestWrapper(IntegrationTest {
     name: "zk_gadgets::arithmetic::test_exp_multiprover_invalid",
     test_fn: test_exp_multiprover_invalid
 }));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::arithmetic::test_private_exp_multiprover",
+    test_fn: test_private_exp_multiprover
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::arithmetic::test_private_exp_multiprover_invalid",
+    test_fn: test_private_exp_multiprover_invalid
+}));
@@ -1 +1,140 @@
+//! Groups integration tests for the multiprover comparator gadgets
 
+use circuits::zk_gadgets::comparators::{
+    MultiproverEqZeroGadget, MultiproverGreaterThanEqGadget, MultiproverGreaterThanEqWitness,
+};
+use curve25519_dalek::scalar::Scalar;
+use integration_helpers::types::IntegrationTest;
+use mpc_bulletproof::r1cs_mpc::{MultiproverError, R1CSError};
+use rand_core::{OsRng, RngCore};
+
+use crate::{IntegrationTestArgs, TestWrapper};
+
+use super::multiprover_prove_and_verify;
+
+/// Tests the equal zero gadget on zero and non-zero inputs
+fn test_eq_zero(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let mut rng = OsRng {};
+    for (value, expected) in [
+        (Scalar::zero(), true),
+        (Scalar::from(rng.next_u32()) + Scalar::one(), false),
+    ] {
+        let shared_value = test_args
+            .borrow_fabric()
+            .allocate_private_scalar(0 /* owning_party */, value)
+            .map_err(|err| format!("Error sharing value: {:?}", err))?;
+
+        multiprover_prove_and_verify::<'_, _, _, MultiproverEqZeroGadget<'_, 64, _, _>>(
+            shared_value,
+            expected,
+            test_args.mpc_fabric.clone(),
+        )
+        .map_err(|err| format!("Error proving and verifying: {:?}", err))?;
+    }
+
+    Ok(())
+}
+
+/// Tests that the equal zero gadget fails to verify an incorrect output
+fn test_eq_zero_invalid(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let mut rng = OsRng {};
+    let shared_value = test_args
+        .borrow_fabric()
+        .allocate_private_u64(0 /* owning_party */, rng.next_u32() as u64 + 1)
+        .map_err(|err| format!("Error sharing value: {:?}", err))?;
+
+    let res = multiprover_prove_and_verify::<'_, _, _, MultiproverEqZeroGadget<'_, 64, _, _>>(
+        shared_value,
+        true, /* statement */
+        test_args.mpc_fabric.clone(),
+    );
+
+    if let Err(MultiproverError::ProverError(R1CSError::VerificationError)) = res {
+        Ok(())
+    } else {
+        Err(format!("Expected verification error, got {:?}", res))
+    }
+}
+
+/// Tests the greater than or equal to gadget on a pair of values owned by different parties
+fn test_greater_than_eq(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    // Each party samples its own value, so a >= b holds only by construction
+    let mut rng = OsRng {};
+    let a = (1u64 << 32) + rng.next_u32() as u64;
+    let b = rng.next_u32() as u64;
+
+    for (a, b) in [(a, b), (42, 42)] {
+        let shared_a = test_args
+            .borrow_fabric()
+            .allocate_private_u64(0 /* owning_party */, a)
+            .map_err(|err| format!("Error sharing a: {:?}", err))?;
+        let shared_b = test_args
+            .borrow_fabric()
+            .allocate_private_u64(1 /* owning_party */, b)
+            .map_err(|err| format!("Error sharing b: {:?}", err))?;
+
+        multiprover_prove_and_verify::<'_, _, _, MultiproverGreaterThanEqGadget<'_, 64, _, _>>(
+            MultiproverGreaterThanEqWitness {
+                a: shared_a,
+                b: shared_b,
+            },
+            (),
+            test_args.mpc_fabric.clone(),
+        )
+        .map_err(|err| format!("Error proving and verifying: {:?}", err))?;
+    }
+
+    Ok(())
+}
+
+/// Tests the greater than or equal to gadget when a < b
+fn test_greater_than_eq_invalid(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let mut rng = OsRng {};
+    let a = rng.next_u32() as u64;
+    let b = (1u64 << 32) + rng.next_u32() as u64;
+
+    let shared_a = test_args
+        .borrow_fabric()
+        .allocate_private_u64(0 /* owning_party */, a)
+        .map_err(|err| format!("Error sharing a: {:?}", err))?;
+    let shared_b = test_args
+        .borrow_fabric()
+        .allocate_private_u64(1 /* owning_party */, b)
+        .map_err(|err| format!("Error sharing b: {:?}", err))?;
+
+    let res = multiprover_prove_and_verify::<'_, _, _, MultiproverGreaterThanEqGadget<'_, 64, _, _>>(
+        MultiproverGreaterThanEqWitness {
+            a: shared_a,
+            b: shared_b,
+        },
+        (),
+        test_args.mpc_fabric.clone(),
+    );
+
+    if let Err(MultiproverError::ProverError(R1CSError::VerificationError)) = res {
+        Ok(())
+    } else {
+        Err(format!("Expected verification error, got {:?}", res))
+    }
+}
+
+// Take inventory
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::comparators::test_eq_zero",
+    test_fn: test_eq_zero
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::comparators::test_eq_zero_invalid",
+    test_fn: test_eq_zero_invalid
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::comparators::test_greater_than_eq",
+    test_fn: test_greater_than_eq
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::comparators::test_greater_than_eq_invalid",
+    test_fn: test_greater_than_eq_invalid
+}));
@@ -0,0 +1,119 @@
+//! Groups integration tests for the multiprover ElGamal encryption gadget
+
+use circuits::zk_gadgets::elgamal::{
+    AuthenticatedElGamalWitness, ElGamalStatement, MultiproverElGamalGadget,
+    DEFAULT_ELGAMAL_GENERATOR,
+};
+use crypto::fields::{biguint_to_scalar, scalar_to_biguint};
+use curve25519_dalek::scalar::Scalar;
+use integration_helpers::{
+    mpc_network::{
+        field::get_ristretto_group_modulus, mocks::PartyIDBeaverSource, share_plaintext_scalar,
+    },
+    types::IntegrationTest,
+};
+use mpc_bulletproof::r1cs_mpc::{MultiproverError, R1CSError};
+use mpc_ristretto::network::QuicTwoPartyNet;
+use rand_core::{OsRng, RngCore};
+
+use crate::{IntegrationTestArgs, TestWrapper};
+
+use super::multiprover_prove_and_verify;
+
+/// The bitlength of the randomness used in the tests
+const RANDOMNESS_BITS: usize = 16;
+
+/// The witness type of the ElGamal gadget over the integration test network
+type ElGamalTestWitness = AuthenticatedElGamalWitness<QuicTwoPartyNet, PartyIDBeaverSource>;
+
+/// Shares a random witness and public key, and computes the expected ciphertext natively
+fn setup_encryption(
+    test_args: &IntegrationTestArgs,
+) -> Result<(ElGamalTestWitness, ElGamalStatement), String> {
+    // Party 0 chooses the randomness and the public key, party 1 chooses the plaintext
+    let mut rng = OsRng {};
+    let randomness = test_args
+        .borrow_fabric()
+        .allocate_private_u64(
+            0, /* owning_party */
+            rng.next_u64() % (1 << RANDOMNESS_BITS),
+        )
+        .map_err(|err| format!("Error sharing randomness: {:?}", err))?;
+    let plaintext = test_args
+        .borrow_fabric()
+        .allocate_private_scalar(1 /* owning_party */, Scalar::random(&mut rng))
+        .map_err(|err| format!("Error sharing plaintext: {:?}", err))?;
+    let pub_key = share_plaintext_scalar(
+        Scalar::random(&mut rng),
+        0, /* owning_party */
+        test_args.mpc_fabric.0.clone(),
+    );
+
+    // Compute the expected encryption
+    let field_mod = get_ristretto_group_modulus();
+    let randomness_open =
+        scalar_to_biguint(&randomness.open_and_authenticate().unwrap().to_scalar());
+    let plaintext_open = scalar_to_biguint(&plaintext.open_and_authenticate().unwrap().to_scalar());
+
+    let ciphertext_1 =
+        scalar_to_biguint(&DEFAULT_ELGAMAL_GENERATOR).modpow(&randomness_open, &field_mod);
+    let shared_secret = scalar_to_biguint(&pub_key).modpow(&randomness_open, &field_mod);
+    let ciphertext_2 = (shared_secret * plaintext_open) % &field_mod;
+
+    Ok((
+        AuthenticatedElGamalWitness {
+            randomness,
+            plaintext,
+        },
+        ElGamalStatement {
+            pub_key,
+            generator: *DEFAULT_ELGAMAL_GENERATOR,
+            expected_ciphertext: (
+                biguint_to_scalar(&ciphertext_1),
+                biguint_to_scalar(&ciphertext_2),
+            ),
+        },
+    ))
+}
+
+/// Tests the ElGamal gadget on a shared plaintext and randomness
+fn test_elgamal_multiprover(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let (witness, statement) = setup_encryption(test_args)?;
+
+    multiprover_prove_and_verify::<'_, _, _, MultiproverElGamalGadget<'_, RANDOMNESS_BITS, _, _>>(
+        witness,
+        statement,
+        test_args.mpc_fabric.clone(),
+    )
+    .map_err(|err| format!("Error proving and verifying: {:?}", err))
+}
+
+/// Tests the ElGamal gadget against a ciphertext of a different message
+fn test_elgamal_multiprover_invalid(test_args: &IntegrationTestArgs) -> Result<(), String> {
+    let (witness, mut statement) = setup_encryption(test_args)?;
+    statement.expected_ciphertext.1 += Scalar::one();
+
+    let res = multiprover_prove_and_verify::<
+        '_,
+        _,
+        _,
+        MultiproverElGamalGadget<'_, RANDOMNESS_BITS, _, _>,
+    >(witness, statement, test_args.mpc_fabric.clone());
+
+    if let Err(MultiproverError::ProverError(R1CSError::VerificationError)) = res {
+        Ok(())
+    } else {
+        Err(format!("Expected verification error, got {:?}", res))
+    }
+}
+
+// Take inventory
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::elgamal::test_elgamal_multiprover",
+    test_fn: test_elgamal_multiprover
+}));
+
+inventory::submit!(TestWrapper(IntegrationTest {
+    name: "zk_gadgets::elgamal::test_elgamal_multiprover_invalid",
+    test_fn: test_elgamal_multiprover_invalid
+}));
@@ -2,6 +2,7 @@
 mod arithmetic;
 pub mod bits;
 mod comparators;
+mod elgamal;
 mod poseidon;
 
 use circuits::{mpc::SharedFabric, MultiProverCircuit, Open};
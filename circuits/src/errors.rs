@@ -35,6 +35,8 @@ pub enum ProverError {
     Collaborative(MultiproverError),
     /// An error that occurs from an R1CS error directly
     R1CS(R1CSError),
+    /// The circuit does not implement `apply_constraints`, so it cannot be dry run
+    DryRunUnsupported,
 }
 
 impl Display for ProverError {
@@ -65,3 +67,29 @@ impl Display for TypeConversionError {
         write!(f, "{:?}", self.0)
     }
 }
+
+/// Represents an error loading or verifying a prover parameters bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamsBundleError {
+    /// The bundle could not be read or written
+    Io(String),
+    /// The bundle could not be parsed
+    Parse(String),
+    /// The bundle is of a format version this build does not support
+    UnsupportedVersion(u32),
+    /// The bundle's hash does not match the hash it is addressed by or expected to have
+    HashMismatch {
+        /// The expected hash
+        expected: String,
+        /// The hash of the bundle
+        actual: String,
+    },
+    /// The bundle's parameters differ from those the circuits are built with
+    ParamsMismatch,
+}
+
+impl Display for ParamsBundleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{:?}", self)
+    }
+}
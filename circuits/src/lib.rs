@@ -21,11 +21,13 @@ use mpc_ristretto::{
 };
 
 use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
 
 pub mod errors;
 pub mod mpc;
 pub mod mpc_circuits;
 pub mod mpc_gadgets;
+pub mod params;
 pub mod types;
 pub mod zk_circuits;
 pub mod zk_gadgets;
@@ -42,6 +44,8 @@ pub(crate) const POSITIVE_SCALAR_MAX_BITS: usize = 251;
 pub(crate) const SCALAR_MAX_BITS: usize = 253;
 /// The seed for a fiat-shamir transcript
 pub(crate) const TRANSCRIPT_SEED: &str = "merlin seed";
+/// The seed for the transcript of a circuit that is dry run rather than proven
+pub(crate) const DRY_RUN_TRANSCRIPT_SEED: &str = "dry run seed";
 
 // ----------
 // | Macros |
@@ -311,6 +315,27 @@ impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> CommitSharedProver<N, S
     }
 }
 
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> CommitSharedProver<N, S>
+    for LinkableCommitment
+{
+    type SharedVarType = MpcVariable<N, S>;
+    type CommitType = AuthenticatedCompressedRistretto<N, S>;
+    type ErrorType = MpcError;
+
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        owning_party: u64,
+        _rng: &mut R,
+        prover: &mut MpcProver<N, S>,
+    ) -> Result<(Self::SharedVarType, Self::CommitType), Self::ErrorType> {
+        let (mut comms, mut vars) = prover
+            .batch_commit(owning_party, &[self.val], &[self.randomness])
+            .map_err(|err| MpcError::SharingError(err.to_string()))?;
+
+        Ok((vars.remove(0), comms.remove(0)))
+    }
+}
+
 // ----------
 // | Traits |
 // ----------
@@ -507,6 +532,71 @@ pub trait SingleProverCircuit {
         proof: R1CSProof,
         verifier: Verifier,
     ) -> Result<(), VerifierError>;
+
+    /// Commit to the witness and statement and apply the circuit's constraints, without
+    /// proving the statement
+    ///
+    /// Returns the commitment to the witness. A circuit must implement this to be dry run
+    /// by `check_satisfiability` and `constraint_profile`
+    fn apply_constraints(
+        _witness: Self::Witness,
+        _statement: Self::Statement,
+        _prover: &mut Prover,
+    ) -> Result<Self::WitnessCommitment, ProverError> {
+        Err(ProverError::DryRunUnsupported)
+    }
+
+    /// Check whether the witness and statement satisfy the circuit's constraints
+    ///
+    /// This is considerably cheaper than generating a proof, and may be used to sanity
+    /// check a witness before it is handed to the prover. A witness that cannot be
+    /// allocated in the circuit does not satisfy it
+    fn check_satisfiability(
+        witness: Self::Witness,
+        statement: Self::Statement,
+    ) -> Result<bool, ProverError> {
+        match Self::constraint_profile(witness, statement) {
+            Ok(profile) => Ok(profile.satisfied),
+            Err(ProverError::R1CS(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Apply the circuit's constraints to the witness and statement, counting the
+    /// constraints and multipliers the circuit allocates and checking whether they are
+    /// satisfied, without proving the statement
+    fn constraint_profile(
+        witness: Self::Witness,
+        statement: Self::Statement,
+    ) -> Result<ConstraintProfile, ProverError> {
+        let mut transcript = Transcript::new(DRY_RUN_TRANSCRIPT_SEED.as_bytes());
+        let pc_gens = PedersenGens::default();
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+        Self::apply_constraints(witness, statement, &mut prover)?;
+        let metrics = prover.metrics();
+        Ok(ConstraintProfile {
+            multipliers: metrics.multipliers,
+            constraints: metrics.constraints,
+            bp_gens_capacity: Self::BP_GENS_CAPACITY,
+            satisfied: prover.constraints_satisfied(),
+        })
+    }
+}
+
+/// The size of a circuit as applied to a witness and statement, and whether the witness
+/// satisfies it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintProfile {
+    /// The number of multiplication gates the circuit allocates
+    pub multipliers: usize,
+    /// The number of linear constraints the circuit applies
+    pub constraints: usize,
+    /// The number of bulletproof generators the circuit is proven with; a proof can only
+    /// be generated if this is at least the number of multipliers
+    pub bp_gens_capacity: usize,
+    /// Whether the witness and statement satisfy the constraints
+    pub satisfied: bool,
 }
 
 /// Defines the abstraction of a Circuit that is evaluated in a multiprover setting
@@ -626,7 +716,7 @@ pub mod native_helpers {
             biguint_to_prime_field, prime_field_to_scalar, scalar_to_prime_field,
             DalekRistrettoField,
         },
-        hash::default_poseidon_params,
+        hash::{default_poseidon_params, poseidon_params_for_arity},
     };
     use curve25519_dalek::scalar::Scalar;
     use itertools::Itertools;
@@ -642,6 +732,16 @@ pub mod native_helpers {
         prime_field_to_scalar(&out)
     }
 
+    /// Compute the Poseidon hash of the given values with the permutation best suited to
+    /// their number, see `PoseidonSpongeParameters::for_arity`
+    pub fn compute_poseidon_hash_for_arity(values: &[Scalar]) -> Scalar {
+        let mut hasher = PoseidonSponge::new(&poseidon_params_for_arity(values.len()));
+        hasher.absorb(&values.iter().map(scalar_to_prime_field).collect_vec());
+
+        let out: DalekRistrettoField = hasher.squeeze_field_elements(1 /* num_elements */)[0];
+        prime_field_to_scalar(&out)
+    }
+
     /// Compute the commitment to a wallet
     pub fn compute_wallet_commitment<
         const MAX_BALANCES: usize,
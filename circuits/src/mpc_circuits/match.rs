@@ -10,11 +10,12 @@ use crate::{
     mpc::SharedFabric,
     mpc_gadgets::{
         arithmetic::product,
-        comparators::{cond_select_vec, eq, less_than_equal, min, ne},
+        comparators::{batch_eq, cond_select, cond_select_vec, eq, less_than_equal, min},
     },
     types::{
+        balance::AuthenticatedBalance,
         order::AuthenticatedOrder,
-        r#match::{AuthenticatedMatchResult, MATCH_SIZE_SCALARS},
+        r#match::{AuthenticatedMatchBounds, AuthenticatedMatchResult, MATCH_SIZE_SCALARS},
     },
     zk_gadgets::fixed_point::AuthenticatedFixedPoint,
 };
@@ -27,21 +28,91 @@ pub fn compute_match<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
     order2: &AuthenticatedOrder<N, S>,
     fabric: SharedFabric<N, S>,
 ) -> Result<AuthenticatedMatchResult<N, S>, MpcError> {
-    // Check that the crossing orders are for the same asset pair
-    let equal_mint1 = eq::<64, _, _>(&order1.base_mint, &order2.base_mint, fabric.clone())?;
-    let equal_mint2 = eq::<64, _, _>(&order1.quote_mint, &order2.quote_mint, fabric.clone())?;
+    let (balances, bounds) = (None, None);
+    match_orders_impl(order1, order2, balances, bounds, fabric)
+}
 
-    // Check that the sell side price is below the buy side
-    let price_overlap = price_overlap(order1, order2, fabric.clone())?;
+/// Executes a match computation on two orders and the balances that capitalize them
+///
+/// In addition to the checks in `compute_match`, each party's balance must be of the
+/// mint that party sells, and must cover the amount of it exchanged. If either balance
+/// falls short, the values are opened to a zero'd list
+pub fn match_orders<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    order1: &AuthenticatedOrder<N, S>,
+    balance1: &AuthenticatedBalance<N, S>,
+    order2: &AuthenticatedOrder<N, S>,
+    balance2: &AuthenticatedBalance<N, S>,
+    fabric: SharedFabric<N, S>,
+) -> Result<AuthenticatedMatchResult<N, S>, MpcError> {
+    match_orders_impl(
+        order1,
+        order2,
+        Some((balance1, balance2)),
+        None, /* bounds */
+        fabric,
+    )
+}
 
-    // Check that the orders are on opposite sides of the book
-    let opposite_sides = ne::<64, _, _>(&order1.side, &order2.side, fabric.clone())?;
+/// Executes a match computation on two orders, the balances that capitalize them, and the
+/// bounds each party places on the match
+///
+/// In addition to the checks in `match_orders`, the amount of the base exchanged must be at
+/// least each party's minimum fill size, and the execution price must fall within each
+/// party's price band. If either party's bounds are violated, the values are opened to a
+/// zero'd list
+#[allow(clippy::too_many_arguments)]
+pub fn match_orders_with_bounds<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    order1: &AuthenticatedOrder<N, S>,
+    balance1: &AuthenticatedBalance<N, S>,
+    bounds1: &AuthenticatedMatchBounds<N, S>,
+    order2: &AuthenticatedOrder<N, S>,
+    balance2: &AuthenticatedBalance<N, S>,
+    bounds2: &AuthenticatedMatchBounds<N, S>,
+    fabric: SharedFabric<N, S>,
+) -> Result<AuthenticatedMatchResult<N, S>, MpcError> {
+    match_orders_impl(
+        order1,
+        order2,
+        Some((balance1, balance2)),
+        Some((bounds1, bounds2)),
+        fabric,
+    )
+}
 
-    // Aggregate all the checks into a single boolean, each check should be equal to 1 for a valid match
-    let aggregate_check = product(
-        &[equal_mint1, equal_mint2, price_overlap, opposite_sides],
+/// The match computation shared by `compute_match`, `match_orders`, and
+/// `match_orders_with_bounds`
+#[allow(clippy::type_complexity)]
+fn match_orders_impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    order1: &AuthenticatedOrder<N, S>,
+    order2: &AuthenticatedOrder<N, S>,
+    balances: Option<(&AuthenticatedBalance<N, S>, &AuthenticatedBalance<N, S>)>,
+    bounds: Option<(
+        &AuthenticatedMatchBounds<N, S>,
+        &AuthenticatedMatchBounds<N, S>,
+    )>,
+    fabric: SharedFabric<N, S>,
+) -> Result<AuthenticatedMatchResult<N, S>, MpcError> {
+    // Check that the crossing orders are for the same asset pair, and that they are on
+    // opposite sides of the book; the equality checks are batched into a single decomposition
+    let equalities = batch_eq::<64, _, _>(
+        &[
+            order1.base_mint.clone(),
+            order1.quote_mint.clone(),
+            order1.side.clone(),
+        ],
+        &[
+            order2.base_mint.clone(),
+            order2.quote_mint.clone(),
+            order2.side.clone(),
+        ],
         fabric.clone(),
     )?;
+    let equal_mint1 = equalities[0].clone();
+    let equal_mint2 = equalities[1].clone();
+    let opposite_sides = Scalar::one() - &equalities[2];
+
+    // Check that the sell side price is below the buy side
+    let price_overlap = price_overlap(order1, order2, fabric.clone())?;
 
     // Compute the amount and execution price that will be swapped if the above checks pass
     let (min_index, min_base_amount) =
@@ -60,6 +131,31 @@ pub fn compute_match<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
     let quote_exchanged_fp = min_base_amount.clone() * &execution_price;
     let quote_exchanged = quote_exchanged_fp.as_integer(fabric.clone())?;
 
+    // Aggregate all the checks into a single boolean, each check should be equal to 1 for a valid match
+    let mut checks = vec![equal_mint1, equal_mint2, price_overlap, opposite_sides];
+    if let Some((balance1, balance2)) = balances {
+        for (order, balance) in [(order1, balance1), (order2, balance2)] {
+            checks.extend(balance_covers_order(
+                order,
+                balance,
+                &min_base_amount,
+                &quote_exchanged,
+                fabric.clone(),
+            )?);
+        }
+    }
+    if let Some((bounds1, bounds2)) = bounds {
+        for bounds in [bounds1, bounds2] {
+            checks.extend(match_within_bounds(
+                bounds,
+                &min_base_amount,
+                &execution_price,
+                fabric.clone(),
+            )?);
+        }
+    }
+    let aggregate_check = product(&checks, fabric.clone())?;
+
     // Zero out the orders if any of the initial checks failed
     let masked_output = cond_select_vec(
         &aggregate_check,
@@ -93,6 +189,52 @@ pub fn compute_match<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
     })
 }
 
+/// Computes whether a balance capitalizes its party's side of a match
+///
+/// The seller of the base sends `base_amount` of the base mint, and the buyer sends
+/// `quote_amount` of the quote mint. Returns two booleans encoded as AuthenticatedScalars;
+/// whether the balance is of the mint sent, and whether it covers the amount sent
+fn balance_covers_order<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    order: &AuthenticatedOrder<N, S>,
+    balance: &AuthenticatedBalance<N, S>,
+    base_amount: &AuthenticatedScalar<N, S>,
+    quote_amount: &AuthenticatedScalar<N, S>,
+    fabric: SharedFabric<N, S>,
+) -> Result<[AuthenticatedScalar<N, S>; 2], MpcError> {
+    // The side is 1 for a sell order, in which case the base is sent
+    let mint_sent = cond_select(&order.side, &order.base_mint, &order.quote_mint)?;
+    let amount_sent = cond_select(&order.side, base_amount, quote_amount)?;
+
+    let mint_matches = eq::<64, _, _>(&balance.mint, &mint_sent, fabric.clone())?;
+    let amount_covered = less_than_equal::<64, _, _>(&amount_sent, &balance.amount, fabric)?;
+
+    Ok([mint_matches, amount_covered])
+}
+
+/// Computes whether a match falls within the bounds a party places on it
+///
+/// Returns three booleans encoded as AuthenticatedScalars; whether the base exchanged is at
+/// least the minimum fill size, and whether the execution price is at least the minimum
+/// price and at most the maximum price
+fn match_within_bounds<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    bounds: &AuthenticatedMatchBounds<N, S>,
+    base_amount: &AuthenticatedScalar<N, S>,
+    execution_price: &AuthenticatedFixedPoint<N, S>,
+    fabric: SharedFabric<N, S>,
+) -> Result<[AuthenticatedScalar<N, S>; 3], MpcError> {
+    let fill_covered =
+        less_than_equal::<64, _, _>(&bounds.min_fill_size, base_amount, fabric.clone())?;
+    let above_min_price = less_than_equal::<64, _, _>(
+        &bounds.min_price.repr,
+        &execution_price.repr,
+        fabric.clone(),
+    )?;
+    let below_max_price =
+        less_than_equal::<64, _, _>(&execution_price.repr, &bounds.max_price.repr, fabric)?;
+
+    Ok([fill_covered, above_min_price, below_max_price])
+}
+
 /// Computes whether the prices of two orders overlap
 ///
 /// Returns the result as a boolean encoded as an AuthenticatedScalar
@@ -145,57 +145,146 @@ pub fn to_bits_le<const D: usize, N: MpcNetwork + Send, S: SharedValueSource<Sca
     x: &AuthenticatedScalar<N, S>,
     fabric: SharedFabric<N, S>,
 ) -> Result<Vec<AuthenticatedScalar<N, S>>, MpcError> {
+    Ok(batch_to_bits_le::<D, N, S>(&[x.clone()], fabric)?
+        .pop()
+        .unwrap())
+}
+
+/// Decomposes each of the inputs into its `m` least significant bits
+///
+/// Equivalent to calling `to_bits_le` on each input, but the shared random bits that blind
+/// the inputs are drawn from the pre-processing functionality in a single batch, the blinded
+/// values are opened in a single round of communication, and the bitwise additions that
+/// unblind them share one round of multiplication per bit position
+pub fn batch_to_bits_le<const D: usize, N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    x: &[AuthenticatedScalar<N, S>],
+    fabric: SharedFabric<N, S>,
+) -> Result<Vec<Vec<AuthenticatedScalar<N, S>>>, MpcError> {
     assert!(
         D < SCALAR_MAX_BITS,
         "Can only support scalars of up to {:?} bits",
         SCALAR_MAX_BITS
     );
-    // If x is public, compute the bits locally
-    if x.is_public() {
-        return Ok(scalar_to_bits_le(&x.to_scalar())
-            .iter()
-            .map(|bit| fabric.borrow_fabric().allocate_public_scalar(*bit))
-            .collect::<Vec<_>>());
+
+    // Public values have their bits computed locally, only the shared values are blinded
+    let mut result = vec![Vec::new(); x.len()];
+    let mut shared_indices = Vec::new();
+    for (i, val) in x.iter().enumerate() {
+        if val.is_public() {
+            result[i] = scalar_to_bits_le(&val.to_scalar())
+                .iter()
+                .map(|bit| fabric.borrow_fabric().allocate_public_scalar(*bit))
+                .collect::<Vec<_>>();
+        } else {
+            shared_indices.push(i);
+        }
+    }
+
+    if shared_indices.is_empty() {
+        return Ok(result);
     }
 
-    // Sample a random batch of bits and create a random m-bit shared scalar
-    let random_bits =
-        fabric
-            .borrow_fabric()
-            .allocate_random_shared_bit_batch(
-                cmp::min(D, BLINDING_FACTOR_MAX_BITS), /* num_scalars */
-            );
-    let random_scalar = scalar_from_bits_le(&random_bits);
-
-    // Pop a random scalar to fill in the top k - m bits
-    let random_upper_bits = if D < SCALAR_MAX_BITS {
-        fabric.borrow_fabric().allocate_random_shared_scalar() * scalar_2_to_m(D)
-    } else {
-        fabric.borrow_fabric().allocate_zero()
-    };
-
-    // This value is used to blind the opening so that the opened value is distributed uniformly at
-    // random over the scalar field
-    let blinding_factor = &random_upper_bits + &random_scalar;
+    // Sample a single batch of random bits, and create a random m-bit shared scalar from
+    // each chunk of the batch
+    let n_random_bits = cmp::min(D, BLINDING_FACTOR_MAX_BITS);
+    let random_bits = fabric
+        .borrow_fabric()
+        .allocate_random_shared_bit_batch(n_random_bits * shared_indices.len())
+        .chunks(n_random_bits)
+        .map(|chunk| chunk.to_vec())
+        .collect::<Vec<_>>();
+
+    let mut blinded_values = Vec::with_capacity(shared_indices.len());
+    for (i, bits) in shared_indices.iter().zip(random_bits.iter()) {
+        let random_scalar = scalar_from_bits_le(bits);
+
+        // Pop a random scalar to fill in the top k - m bits
+        let random_upper_bits =
+            fabric.borrow_fabric().allocate_random_shared_scalar() * scalar_2_to_m(D);
+
+        // This value is used to blind the opening so that the opened value is distributed
+        // uniformly at random over the scalar field
+        let blinding_factor = &random_upper_bits + &random_scalar;
+
+        // TODO: Fix this offset
+        blinded_values.push(&x[*i] - &blinding_factor + scalar_2_to_m(D + 1));
+    }
 
     // TODO: Do we need to `open_and_authenticate`?
-    // TODO: Fix this offset
-    let blinded_value = x - &blinding_factor + scalar_2_to_m(D + 1);
-    let blinded_value_open = blinded_value.open().map_err(|_| {
-        MpcError::OpeningError("error opening blinded value while truncating".to_string())
+    let blinded_values_open = AuthenticatedScalar::batch_open(&blinded_values).map_err(|_| {
+        MpcError::OpeningError("error opening blinded values while truncating".to_string())
     })?;
 
-    // Convert to bits
-    let blinded_value_bits = fabric
-        .borrow_fabric()
-        .batch_allocate_public_scalar(&scalar_to_bits_le(&blinded_value_open.to_scalar()));
-    let (bits, _) = bit_add(
-        &resize_bitvector_to_length(blinded_value_bits, D, fabric.clone()),
-        &resize_bitvector_to_length(random_bits, D, fabric.clone()),
-        fabric,
-    );
+    // Convert to bits and unblind
+    let blinded_value_bits = blinded_values_open
+        .iter()
+        .map(|val| {
+            let bits = fabric
+                .borrow_fabric()
+                .batch_allocate_public_scalar(&scalar_to_bits_le(&val.to_scalar()));
+            resize_bitvector_to_length(bits, D, fabric.clone())
+        })
+        .collect::<Vec<_>>();
+    let random_bits = random_bits
+        .into_iter()
+        .map(|bits| resize_bitvector_to_length(bits, D, fabric.clone()))
+        .collect::<Vec<_>>();
+
+    let unblinded_bits = batch_bit_add(&blinded_value_bits, &random_bits, fabric)?;
+    for (i, bits) in shared_indices.into_iter().zip(unblinded_bits.into_iter()) {
+        result[i] = bits;
+    }
+
+    Ok(result)
+}
+
+/// Adds each pair of bitwise representations, dropping the carry out
+///
+/// The additions proceed in lockstep, so that the multiplications propagating each
+/// addition's carry through a given bit position are evaluated in a single batch
+fn batch_bit_add<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    a: &[Vec<AuthenticatedScalar<N, S>>],
+    b: &[Vec<AuthenticatedScalar<N, S>>],
+    fabric: SharedFabric<N, S>,
+) -> Result<Vec<Vec<AuthenticatedScalar<N, S>>>, MpcError> {
+    assert_eq!(a.len(), b.len(), "batch_bit_add takes equal length batches");
+    if a.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let n_bits = a[0].len();
+    let mut results = vec![Vec::with_capacity(n_bits); a.len()];
+    let mut carries = fabric.borrow_fabric().allocate_zeros(a.len());
+
+    for i in 0..n_bits {
+        let a_xor_b = a
+            .iter()
+            .zip(b.iter())
+            .map(|(a_bits, b_bits)| bit_xor(&a_bits[i], &b_bits[i]))
+            .collect::<Vec<_>>();
+
+        // The carries are public zeros in the first position, so their product with the xor
+        // may be computed locally
+        let xor_times_carry = if carries.iter().all(|carry| carry.is_public()) {
+            a_xor_b
+                .iter()
+                .zip(carries.iter())
+                .map(|(xor, carry)| xor * carry)
+                .collect::<Vec<_>>()
+        } else {
+            AuthenticatedScalar::batch_mul(&a_xor_b, &carries)
+                .map_err(|err| MpcError::ArithmeticError(err.to_string()))?
+        };
+
+        for (j, (xor, product)) in a_xor_b.iter().zip(xor_times_carry.iter()).enumerate() {
+            // The out bit in this position is A \xor B \xor carry
+            results[j].push(xor + &carries[j] - Scalar::from(2u64) * product);
+            // The carry bit from this depth of the adder
+            carries[j] = &a[j][i] * &b[j][i] + product;
+        }
+    }
 
-    Ok(bits)
+    Ok(results)
 }
 
 /// Given two bitwise representations, computes whether the first is less than the second
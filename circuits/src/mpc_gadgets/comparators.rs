@@ -11,7 +11,7 @@ use crate::{errors::MpcError, mpc::SharedFabric};
 
 use super::{
     arithmetic::product,
-    bits::{bit_xor, scalar_from_bits_le, scalar_to_bits_le, to_bits_le},
+    bits::{batch_to_bits_le, bit_xor, scalar_from_bits_le, scalar_to_bits_le, to_bits_le},
     modulo::truncate,
 };
 
@@ -60,6 +60,40 @@ pub fn eq<const D: usize, N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
     eq_zero::<D, N, S>(&(a - b), fabric)
 }
 
+/// Implements the comparator a == 0 for each of the inputs
+///
+/// The inputs are decomposed into bits in a single batch, so the comparisons share their
+/// rounds of communication rather than running one after another
+///
+/// D represents the bitlength of the inputs
+pub fn batch_eq_zero<const D: usize, N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    a: &[AuthenticatedScalar<N, S>],
+    fabric: SharedFabric<N, S>,
+) -> Result<Vec<AuthenticatedScalar<N, S>>, MpcError> {
+    batch_to_bits_le::<D, N, S>(a, fabric.clone())?
+        .iter()
+        .map(|bits| Ok(Scalar::one() - kary_or(bits, fabric.clone())?))
+        .collect()
+}
+
+/// Implements the comparator a_i == b_i for each pair of inputs
+///
+/// D represents the bitlength of the inputs
+pub fn batch_eq<const D: usize, N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+    a: &[AuthenticatedScalar<N, S>],
+    b: &[AuthenticatedScalar<N, S>],
+    fabric: SharedFabric<N, S>,
+) -> Result<Vec<AuthenticatedScalar<N, S>>, MpcError> {
+    assert_eq!(a.len(), b.len(), "batch_eq requires equal length vectors");
+    let differences = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a_val, b_val)| a_val - b_val)
+        .collect::<Vec<_>>();
+
+    batch_eq_zero::<D, N, S>(&differences, fabric)
+}
+
 /// Implements the comparator a != b
 ///
 /// D represents the bitlength of the inputs
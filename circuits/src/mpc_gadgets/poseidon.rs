@@ -14,7 +14,10 @@ use std::cell::Ref;
 
 use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
 use ark_ff::PrimeField;
-use crypto::{fields::prime_field_to_scalar, hash::default_poseidon_params};
+use crypto::{
+    fields::prime_field_to_scalar,
+    hash::{default_poseidon_params, poseidon_params_for_arity},
+};
 use curve25519_dalek::scalar::Scalar;
 use mpc_ristretto::{
     authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource, network::MpcNetwork,
@@ -146,6 +149,19 @@ impl PoseidonSpongeParameters {
         }
     }
 
+    /// Construct the parameters of the sponge best suited to hashing `arity` elements
+    ///
+    /// This selects between the t = 3, t = 5 and t = 9 permutations, see
+    /// `crypto::hash::poseidon_params_for_arity`
+    pub fn for_arity(arity: usize) -> Self {
+        convert_poseidon_params(poseidon_params_for_arity(arity))
+    }
+
+    /// The width of the permutation's state
+    pub fn width(&self) -> usize {
+        self.rate + self.capacity
+    }
+
     /// Fetch the round constants for a given round
     pub fn get_round_constant(&self, round_index: usize) -> &Vec<Scalar> {
         &self.round_constants[round_index]
@@ -196,6 +212,11 @@ impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> AuthenticatedPoseidonHa
         }
     }
 
+    /// Construct a sponge hasher parameterized for an input of `arity` elements
+    pub fn new_for_arity(arity: usize, fabric: SharedFabric<N, S>) -> Self {
+        Self::new(&PoseidonSpongeParameters::for_arity(arity), fabric)
+    }
+
     /// Borrow a reference to the shared fabric
     fn borrow_fabric(&self) -> Ref<MpcFabric<N, S>> {
         self.fabric.borrow_fabric()
@@ -327,7 +348,10 @@ impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> AuthenticatedPoseidonHa
 #[cfg(test)]
 mod poseidon_tests {
     use ark_crypto_primitives::sponge::{poseidon::PoseidonSponge, CryptographicSponge};
-    use crypto::{fields::DalekRistrettoField, hash::default_poseidon_params};
+    use crypto::{
+        fields::DalekRistrettoField,
+        hash::{default_poseidon_params, poseidon_params_t9},
+    };
     use integration_helpers::mpc_network::mock_mpc_fabric;
     use rand::{thread_rng, Rng, RngCore};
 
@@ -391,4 +415,37 @@ mod poseidon_tests {
         let native_squeezed = native_poseidon.squeeze().to_scalar();
         assert!(compare_scalar_to_felt(&native_squeezed, &arkworks_squeezed));
     }
+
+    /// Tests the wide (t = 9) parameters against the Arkworks implementation, absorbing
+    /// enough elements to span several permutations
+    #[test]
+    fn test_wide_params_against_arkworks() {
+        let native_parameters = PoseidonSpongeParameters::for_arity(12 /* arity */);
+        assert_eq!(native_parameters.width(), 9);
+
+        let mut rng = thread_rng();
+        let random_vec = (0..20).map(|_| rng.next_u64()).collect::<Vec<_>>();
+
+        let mut arkworks_poseidon = PoseidonSponge::new(&poseidon_params_t9());
+        for random_elem in random_vec.iter() {
+            arkworks_poseidon.absorb(&DalekRistrettoField::from(*random_elem as i128));
+        }
+        let arkworks_squeezed: DalekRistrettoField =
+            arkworks_poseidon.squeeze_field_elements(1 /* num_elements */)[0];
+
+        let mock_fabric = mock_mpc_fabric(0 /* party_id */);
+        let mut native_poseidon =
+            AuthenticatedPoseidonHasher::new(&native_parameters, SharedFabric(mock_fabric.clone()));
+        for random_elem in random_vec.iter() {
+            native_poseidon.absorb(
+                &mock_fabric
+                    .as_ref()
+                    .borrow()
+                    .allocate_public_u64(*random_elem),
+            )
+        }
+
+        let native_squeezed = native_poseidon.squeeze().to_scalar();
+        assert!(compare_scalar_to_felt(&native_squeezed, &arkworks_squeezed));
+    }
 }
@@ -0,0 +1,170 @@
+//! Defines the prover parameters bundle; the transcript seed, Pedersen generators, and
+//! Poseidon parameters that every party to a proof must share
+//!
+//! A bundle is addressed by the SHA-256 hash of its canonical (JSON) serialization, and is
+//! distributed as a file named `<hash>.json`. Parties that agree on a hash agree on the
+//! setup; the loader rejects a bundle whose contents do not hash to its file name, and
+//! a bundle may be verified against an expected hash before it is used
+
+use std::{fs, path::Path};
+
+use crypto::params::{builtin_poseidon_params, PoseidonParams};
+use hmac_sha256::Hash;
+use mpc_bulletproof::PedersenGens;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::ParamsBundleError, TRANSCRIPT_SEED};
+
+/// The format version of bundles written by this build
+pub const PARAMS_BUNDLE_VERSION: u32 = 1;
+/// The extension of a bundle file
+const PARAMS_BUNDLE_EXTENSION: &str = "json";
+
+/// The parameters that proofs in the relayer are generated and verified under
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamsBundle {
+    /// The format version of the bundle
+    pub version: u32,
+    /// The seed of the Fiat-Shamir transcript
+    pub transcript_seed: String,
+    /// The compressed Pedersen generator that values are committed under
+    pub pedersen_base: Vec<u8>,
+    /// The compressed Pedersen generator that blinders are committed under
+    pub pedersen_blinding_base: Vec<u8>,
+    /// The parameters of each Poseidon permutation, narrowest first
+    pub poseidon: Vec<PoseidonParams>,
+}
+
+impl ParamsBundle {
+    /// The bundle of the parameters this build's circuits are compiled against
+    pub fn builtin() -> Self {
+        let pc_gens = PedersenGens::default();
+        Self {
+            version: PARAMS_BUNDLE_VERSION,
+            transcript_seed: TRANSCRIPT_SEED.to_string(),
+            pedersen_base: pc_gens.B.compress().to_bytes().to_vec(),
+            pedersen_blinding_base: pc_gens.B_blinding.compress().to_bytes().to_vec(),
+            poseidon: builtin_poseidon_params(),
+        }
+    }
+
+    /// The canonical serialization of the bundle, the preimage of its hash
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    /// The hex encoded SHA-256 hash that the bundle is addressed by
+    pub fn hash(&self) -> String {
+        Hash::hash(&self.to_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Load a bundle from a file
+    ///
+    /// If the file is named by a hash, the bundle's contents must hash to it
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ParamsBundleError> {
+        let path = path.as_ref();
+        let contents = fs::read(path).map_err(|err| ParamsBundleError::Io(err.to_string()))?;
+        let bundle: Self = serde_json::from_slice(&contents)
+            .map_err(|err| ParamsBundleError::Parse(err.to_string()))?;
+        if bundle.version != PARAMS_BUNDLE_VERSION {
+            return Err(ParamsBundleError::UnsupportedVersion(bundle.version));
+        }
+
+        if let Some(address) = path.file_stem().and_then(|stem| stem.to_str())
+            && is_hash(address)
+        {
+            bundle.check_hash(address)?;
+        }
+
+        Ok(bundle)
+    }
+
+    /// Write the bundle into a directory under its hash, returning the path written to
+    pub fn write_to_dir<P: AsRef<Path>>(&self, dir: P) -> Result<String, ParamsBundleError> {
+        let path = dir
+            .as_ref()
+            .join(format!("{}.{}", self.hash(), PARAMS_BUNDLE_EXTENSION));
+        fs::write(&path, self.to_bytes()).map_err(|err| ParamsBundleError::Io(err.to_string()))?;
+
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    /// Verify that the bundle hashes to the expected hash, if one is given, and that its
+    /// parameters are those the circuits are compiled against
+    pub fn verify(&self, expected_hash: Option<&str>) -> Result<(), ParamsBundleError> {
+        if let Some(expected) = expected_hash {
+            self.check_hash(expected)?;
+        }
+
+        if *self != Self::builtin() {
+            return Err(ParamsBundleError::ParamsMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the bundle hashes to the given hash
+    fn check_hash(&self, expected: &str) -> Result<(), ParamsBundleError> {
+        let actual = self.hash();
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(ParamsBundleError::HashMismatch {
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a string is a hex encoded SHA-256 hash
+fn is_hash(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use num_bigint::BigUint;
+
+    use crate::errors::ParamsBundleError;
+
+    use super::ParamsBundle;
+
+    /// Tests that a bundle written under its hash loads and verifies, and that a bundle
+    /// whose contents no longer match its address or the built-in parameters is rejected
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = env::temp_dir().join(format!("params-bundle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let bundle = ParamsBundle::builtin();
+        let path = bundle.write_to_dir(&dir).unwrap();
+        let loaded = ParamsBundle::load(&path).unwrap();
+        assert_eq!(loaded, bundle);
+        assert!(loaded.verify(Some(&bundle.hash())).is_ok());
+        assert!(matches!(
+            loaded.verify(Some(&"0".repeat(64))),
+            Err(ParamsBundleError::HashMismatch { .. })
+        ));
+
+        // Tamper with a round constant in place
+        let mut tampered = bundle.clone();
+        tampered.poseidon[0].round_constants[0][0] += BigUint::from(1u8);
+        fs::write(&path, tampered.to_bytes()).unwrap();
+        assert!(matches!(
+            ParamsBundle::load(&path),
+            Err(ParamsBundleError::HashMismatch { .. })
+        ));
+        assert_eq!(
+            tampered.verify(None),
+            Err(ParamsBundleError::ParamsMismatch)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
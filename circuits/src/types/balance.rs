@@ -1,6 +1,6 @@
 //! Groups base and derived types for the `Balance` object
 
-use crypto::fields::biguint_to_scalar;
+use crypto::fields::{biguint_to_scalar, scalar_to_biguint};
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use mpc_bulletproof::{
     r1cs::{Prover, Variable, Verifier},
@@ -8,7 +8,8 @@ use mpc_bulletproof::{
 };
 use mpc_ristretto::{
     authenticated_ristretto::AuthenticatedCompressedRistretto,
-    authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource, network::MpcNetwork,
+    authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource,
+    mpc_scalar::scalar_to_u64, network::MpcNetwork,
 };
 use num_bigint::BigUint;
 use rand_core::{CryptoRng, RngCore};
@@ -112,6 +113,15 @@ impl From<Balance> for LinkableBalanceCommitment {
     }
 }
 
+impl From<LinkableBalanceCommitment> for Balance {
+    fn from(balance: LinkableBalanceCommitment) -> Self {
+        Self {
+            mint: scalar_to_biguint(&balance.mint.val),
+            amount: scalar_to_u64(&balance.amount.val),
+        }
+    }
+}
+
 impl CommitProver for LinkableBalanceCommitment {
     type VarType = BalanceVar;
     type CommitType = CommittedBalance;
@@ -194,6 +194,17 @@ impl From<Fee> for LinkableFeeCommitment {
     }
 }
 
+impl From<LinkableFeeCommitment> for Fee {
+    fn from(fee: LinkableFeeCommitment) -> Self {
+        Self {
+            settle_key: scalar_to_biguint(&fee.settle_key.val),
+            gas_addr: scalar_to_biguint(&fee.gas_addr.val),
+            gas_token_amount: scalar_to_u64(&fee.gas_token_amount.val),
+            percentage_fee: fee.percentage_fee.into(),
+        }
+    }
+}
+
 impl CommitProver for LinkableFeeCommitment {
     type VarType = FeeVar;
     type CommitType = CommittedFee;
@@ -469,6 +480,58 @@ impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> CommitSharedProver<N, S
     }
 }
 
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> CommitSharedProver<N, S>
+    for LinkableFeeCommitment
+{
+    type SharedVarType = AuthenticatedFeeVar<N, S>;
+    type CommitType = AuthenticatedCommittedFee<N, S>;
+    type ErrorType = MpcError;
+
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        owning_party: u64,
+        _rng: &mut R,
+        prover: &mut MpcProver<N, S>,
+    ) -> Result<(Self::SharedVarType, Self::CommitType), Self::ErrorType> {
+        let (shared_comm, shared_vars) = prover
+            .batch_commit(
+                owning_party,
+                &[
+                    self.settle_key.val,
+                    self.gas_addr.val,
+                    self.gas_token_amount.val,
+                    self.percentage_fee.repr.val,
+                ],
+                &[
+                    self.settle_key.randomness,
+                    self.gas_addr.randomness,
+                    self.gas_token_amount.randomness,
+                    self.percentage_fee.repr.randomness,
+                ],
+            )
+            .map_err(|err| MpcError::SharingError(err.to_string()))?;
+
+        Ok((
+            AuthenticatedFeeVar {
+                settle_key: shared_vars[0].to_owned(),
+                gas_addr: shared_vars[1].to_owned(),
+                gas_token_amount: shared_vars[2].to_owned(),
+                percentage_fee: AuthenticatedFixedPointVar {
+                    repr: shared_vars[3].to_owned().into(),
+                },
+            },
+            AuthenticatedCommittedFee {
+                settle_key: shared_comm[0].to_owned(),
+                gas_addr: shared_comm[1].to_owned(),
+                gas_token_amount: shared_comm[2].to_owned(),
+                percentage_fee: AuthenticatedCommittedFixedPoint {
+                    repr: shared_comm[3].to_owned(),
+                },
+            },
+        ))
+    }
+}
+
 /// Represents a fee that has been committed to in a multi-prover constraint system
 #[derive(Clone, Debug)]
 pub struct AuthenticatedCommittedFee<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
@@ -8,7 +8,7 @@ use crate::{
         AuthenticatedLinkableFixedPointCommitment, CommittedFixedPoint, FixedPoint, FixedPointVar,
         LinkableFixedPointCommitment,
     },
-    AuthenticatedLinkableCommitment, CommitProver, CommitSharedProver, CommitVerifier,
+    Allocate, AuthenticatedLinkableCommitment, CommitProver, CommitSharedProver, CommitVerifier,
     LinkableCommitment, Open,
 };
 use crypto::fields::biguint_to_scalar;
@@ -224,7 +224,7 @@ impl CommitVerifier for CommittedMatchResult {
 }
 
 /// A match result that may be linked across proofs
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LinkableMatchResultCommitment {
     /// The mint of the order token in the asset pair being matched
     pub quote_mint: LinkableCommitment,
@@ -872,3 +872,111 @@ impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Open<N, S>
         })
     }
 }
+
+/// The bounds a party places on the match of its order, enforced in the match MPC
+///
+/// The bounds are derived by the relayer from the order's match constraints and are not
+/// committed to in the circuits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchBounds {
+    /// The smallest amount of the base token the match may exchange
+    pub min_fill_size: u64,
+    /// The lowest execution price the match may clear at
+    pub min_price: FixedPoint,
+    /// The highest execution price the match may clear at
+    pub max_price: FixedPoint,
+}
+
+impl MatchBounds {
+    /// The fixed point representation of the highest price a bound may take; the bounds
+    /// are compared against the execution price over 64 bits, so the difference of two
+    /// prices must fit in 63 bits
+    const MAX_PRICE_REPR: u64 = 1 << 62;
+
+    /// Bounds that admit any match
+    pub fn unbounded() -> Self {
+        Self {
+            min_fill_size: 0,
+            min_price: FixedPoint::from(0u64),
+            max_price: FixedPoint::from(Self::MAX_PRICE_REPR),
+        }
+    }
+
+    /// Bounds the execution price to the given range, clamped to the largest price the
+    /// match MPC can compare against
+    pub fn with_price_range(mut self, min_price: FixedPoint, max_price: FixedPoint) -> Self {
+        let clamp = |price: FixedPoint| {
+            FixedPoint::from(u64::min(scalar_to_u64(&price.repr), Self::MAX_PRICE_REPR))
+        };
+        self.min_price = clamp(min_price);
+        self.max_price = clamp(max_price);
+
+        self
+    }
+
+    /// Bounds the amount of the base token exchanged from below
+    pub fn with_min_fill_size(mut self, min_fill_size: u64) -> Self {
+        self.min_fill_size = min_fill_size;
+        self
+    }
+}
+
+impl Default for MatchBounds {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+/// Represents the bounds on a match that have been allocated in an MPC network
+#[derive(Debug)]
+pub struct AuthenticatedMatchBounds<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The smallest amount of the base token the match may exchange
+    pub min_fill_size: AuthenticatedScalar<N, S>,
+    /// The lowest execution price the match may clear at
+    pub min_price: AuthenticatedFixedPoint<N, S>,
+    /// The highest execution price the match may clear at
+    pub max_price: AuthenticatedFixedPoint<N, S>,
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Clone for AuthenticatedMatchBounds<N, S> {
+    fn clone(&self) -> Self {
+        Self {
+            min_fill_size: self.min_fill_size.clone(),
+            min_price: self.min_price.clone(),
+            max_price: self.max_price.clone(),
+        }
+    }
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Allocate<N, S> for MatchBounds {
+    type SharedType = AuthenticatedMatchBounds<N, S>;
+    type ErrorType = MpcError;
+
+    fn allocate(
+        &self,
+        owning_party: u64,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<Self::SharedType, Self::ErrorType> {
+        let shared_values = fabric
+            .borrow_fabric()
+            .batch_allocate_private_scalars(
+                owning_party,
+                &[
+                    Scalar::from(self.min_fill_size),
+                    self.min_price.repr,
+                    self.max_price.repr,
+                ],
+            )
+            .map_err(|err| MpcError::SharingError(err.to_string()))?;
+
+        Ok(Self::SharedType {
+            min_fill_size: shared_values[0].to_owned(),
+            min_price: AuthenticatedFixedPoint {
+                repr: shared_values[1].to_owned(),
+            },
+            max_price: AuthenticatedFixedPoint {
+                repr: shared_values[2].to_owned(),
+            },
+        })
+    }
+}
@@ -3,16 +3,25 @@
 
 use crypto::fields::biguint_to_scalar;
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
-use mpc_bulletproof::r1cs::{Prover, Variable, Verifier};
+use itertools::Itertools;
+use mpc_bulletproof::{
+    r1cs::{Prover, Variable, Verifier},
+    r1cs_mpc::{MpcLinearCombination, MpcProver, MpcVariable},
+};
+use mpc_ristretto::{
+    authenticated_ristretto::AuthenticatedCompressedRistretto,
+    authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource, network::MpcNetwork,
+};
 use num_bigint::BigUint;
+use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 
-use crate::{CommitProver, CommitVerifier};
+use crate::{errors::MpcError, CommitProver, CommitSharedProver, CommitVerifier};
 
 use super::order::OrderSide;
 
 /// Represents the various ways in which a Note may be generated
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum NoteType {
     /// A note generated by an internal transfer within the darkpool
     InternalTransfer = 0,
@@ -21,7 +30,7 @@ pub enum NoteType {
 }
 
 /// Represents a note base type
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Note {
     /// The first mint exchanged via the note
     pub mint1: BigUint,
@@ -218,3 +227,234 @@ impl CommitVerifier for CommittedNote {
         })
     }
 }
+
+/// Represents a note with values that have been allocated in an MPC network
+#[derive(Clone, Debug)]
+pub struct AuthenticatedNote<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The first mint exchanged via the note
+    pub mint1: AuthenticatedScalar<N, S>,
+    /// The volume of the first mint exchanged
+    pub volume1: AuthenticatedScalar<N, S>,
+    /// The direction of the first mint
+    pub direction1: AuthenticatedScalar<N, S>,
+    /// The second mint exchanged via the note
+    pub mint2: AuthenticatedScalar<N, S>,
+    /// The volume of the second mint exchanged
+    pub volume2: AuthenticatedScalar<N, S>,
+    /// The direction of the second mint
+    pub direction2: AuthenticatedScalar<N, S>,
+    /// The mint of the relayer fee paid on contract interaction
+    pub fee_mint: AuthenticatedScalar<N, S>,
+    /// The volume of the relayer fee paid on contract interaction
+    pub fee_volume: AuthenticatedScalar<N, S>,
+    /// The direction of the relayer fee, will be Buy for the relayer's note
+    pub fee_direction: AuthenticatedScalar<N, S>,
+    /// The note type, indicating whether this note was generated by transfer
+    /// or by match
+    pub type_: AuthenticatedScalar<N, S>,
+    /// The randomness included with the note, used to authenticate notes
+    pub randomness: AuthenticatedScalar<N, S>,
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> From<AuthenticatedNote<N, S>>
+    for Vec<AuthenticatedScalar<N, S>>
+{
+    fn from(note: AuthenticatedNote<N, S>) -> Self {
+        vec![
+            note.mint1,
+            note.volume1,
+            note.direction1,
+            note.mint2,
+            note.volume2,
+            note.direction2,
+            note.fee_mint,
+            note.fee_volume,
+            note.fee_direction,
+            note.type_,
+            note.randomness,
+        ]
+    }
+}
+
+/// Represents a note that has been allocated in an MPC network and committed to in
+/// a multi-prover constraint system
+#[derive(Debug)]
+pub struct AuthenticatedNoteVar<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The first mint exchanged via the note
+    pub mint1: MpcVariable<N, S>,
+    /// The volume of the first mint exchanged
+    pub volume1: MpcVariable<N, S>,
+    /// The direction of the first mint
+    pub direction1: MpcVariable<N, S>,
+    /// The second mint exchanged via the note
+    pub mint2: MpcVariable<N, S>,
+    /// The volume of the second mint exchanged
+    pub volume2: MpcVariable<N, S>,
+    /// The direction of the second mint
+    pub direction2: MpcVariable<N, S>,
+    /// The mint of the relayer fee paid on contract interaction
+    pub fee_mint: MpcVariable<N, S>,
+    /// The volume of the relayer fee paid on contract interaction
+    pub fee_volume: MpcVariable<N, S>,
+    /// The direction of the relayer fee, will be Buy for the relayer's note
+    pub fee_direction: MpcVariable<N, S>,
+    /// The note type, indicating whether this note was generated by transfer
+    /// or by match
+    pub type_: MpcVariable<N, S>,
+    /// The randomness included with the note, used to authenticate notes
+    pub randomness: MpcVariable<N, S>,
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Clone for AuthenticatedNoteVar<N, S> {
+    fn clone(&self) -> Self {
+        Self {
+            mint1: self.mint1.clone(),
+            volume1: self.volume1.clone(),
+            direction1: self.direction1.clone(),
+            mint2: self.mint2.clone(),
+            volume2: self.volume2.clone(),
+            direction2: self.direction2.clone(),
+            fee_mint: self.fee_mint.clone(),
+            fee_volume: self.fee_volume.clone(),
+            fee_direction: self.fee_direction.clone(),
+            type_: self.type_.clone(),
+            randomness: self.randomness.clone(),
+        }
+    }
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> From<AuthenticatedNoteVar<N, S>>
+    for Vec<MpcLinearCombination<N, S>>
+{
+    fn from(note: AuthenticatedNoteVar<N, S>) -> Self {
+        vec![
+            note.mint1.into(),
+            note.volume1.into(),
+            note.direction1.into(),
+            note.mint2.into(),
+            note.volume2.into(),
+            note.direction2.into(),
+            note.fee_mint.into(),
+            note.fee_volume.into(),
+            note.fee_direction.into(),
+            note.type_.into(),
+            note.randomness.into(),
+        ]
+    }
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> CommitSharedProver<N, S>
+    for AuthenticatedNote<N, S>
+{
+    type SharedVarType = AuthenticatedNoteVar<N, S>;
+    type CommitType = AuthenticatedCommittedNote<N, S>;
+    type ErrorType = MpcError;
+
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        _owning_party: u64,
+        rng: &mut R,
+        prover: &mut MpcProver<N, S>,
+    ) -> Result<(Self::SharedVarType, Self::CommitType), Self::ErrorType> {
+        let values: Vec<AuthenticatedScalar<N, S>> = self.clone().into();
+        let blinders = (0..values.len()).map(|_| Scalar::random(rng)).collect_vec();
+        let (shared_comm, shared_vars) = prover
+            .batch_commit_preshared(&values, &blinders)
+            .map_err(|err| MpcError::SharingError(err.to_string()))?;
+
+        Ok((
+            AuthenticatedNoteVar {
+                mint1: shared_vars[0].to_owned(),
+                volume1: shared_vars[1].to_owned(),
+                direction1: shared_vars[2].to_owned(),
+                mint2: shared_vars[3].to_owned(),
+                volume2: shared_vars[4].to_owned(),
+                direction2: shared_vars[5].to_owned(),
+                fee_mint: shared_vars[6].to_owned(),
+                fee_volume: shared_vars[7].to_owned(),
+                fee_direction: shared_vars[8].to_owned(),
+                type_: shared_vars[9].to_owned(),
+                randomness: shared_vars[10].to_owned(),
+            },
+            AuthenticatedCommittedNote {
+                mint1: shared_comm[0].to_owned(),
+                volume1: shared_comm[1].to_owned(),
+                direction1: shared_comm[2].to_owned(),
+                mint2: shared_comm[3].to_owned(),
+                volume2: shared_comm[4].to_owned(),
+                direction2: shared_comm[5].to_owned(),
+                fee_mint: shared_comm[6].to_owned(),
+                fee_volume: shared_comm[7].to_owned(),
+                fee_direction: shared_comm[8].to_owned(),
+                type_: shared_comm[9].to_owned(),
+                randomness: shared_comm[10].to_owned(),
+            },
+        ))
+    }
+}
+
+/// Represents a note that has been committed to in a multi-prover constraint system
+#[derive(Clone, Debug)]
+pub struct AuthenticatedCommittedNote<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The first mint exchanged via the note
+    pub mint1: AuthenticatedCompressedRistretto<N, S>,
+    /// The volume of the first mint exchanged
+    pub volume1: AuthenticatedCompressedRistretto<N, S>,
+    /// The direction of the first mint
+    pub direction1: AuthenticatedCompressedRistretto<N, S>,
+    /// The second mint exchanged via the note
+    pub mint2: AuthenticatedCompressedRistretto<N, S>,
+    /// The volume of the second mint exchanged
+    pub volume2: AuthenticatedCompressedRistretto<N, S>,
+    /// The direction of the second mint
+    pub direction2: AuthenticatedCompressedRistretto<N, S>,
+    /// The mint of the relayer fee paid on contract interaction
+    pub fee_mint: AuthenticatedCompressedRistretto<N, S>,
+    /// The volume of the relayer fee paid on contract interaction
+    pub fee_volume: AuthenticatedCompressedRistretto<N, S>,
+    /// The direction of the relayer fee, will be Buy for the relayer's note
+    pub fee_direction: AuthenticatedCompressedRistretto<N, S>,
+    /// The note type, indicating whether this note was generated by transfer
+    /// or by match
+    pub type_: AuthenticatedCompressedRistretto<N, S>,
+    /// The randomness included with the note, used to authenticate notes
+    pub randomness: AuthenticatedCompressedRistretto<N, S>,
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> From<AuthenticatedCommittedNote<N, S>>
+    for Vec<AuthenticatedCompressedRistretto<N, S>>
+{
+    fn from(commit: AuthenticatedCommittedNote<N, S>) -> Self {
+        vec![
+            commit.mint1,
+            commit.volume1,
+            commit.direction1,
+            commit.mint2,
+            commit.volume2,
+            commit.direction2,
+            commit.fee_mint,
+            commit.fee_volume,
+            commit.fee_direction,
+            commit.type_,
+            commit.randomness,
+        ]
+    }
+}
+
+impl From<&[CompressedRistretto]> for CommittedNote {
+    fn from(commitments: &[CompressedRistretto]) -> Self {
+        Self {
+            mint1: commitments[0],
+            volume1: commitments[1],
+            direction1: commitments[2],
+            mint2: commitments[3],
+            volume2: commitments[4],
+            direction2: commitments[5],
+            fee_mint: commitments[6],
+            fee_volume: commitments[7],
+            fee_direction: commitments[8],
+            type_: commitments[9],
+            randomness: commitments[10],
+        }
+    }
+}
@@ -40,6 +40,18 @@ pub struct Order {
     pub amount: u64,
     /// A timestamp indicating when the order was placed, set by the user
     pub timestamp: u64,
+    /// How long the order remains eligible for matching
+    ///
+    /// The time in force is enforced by the relayer and is not committed to in the circuits,
+    /// so an order recovered from its commitment is good 'til cancelled
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    /// The constraints on the matches the order may take part in
+    ///
+    /// Like the time in force, the constraints are enforced by the relayer and are not
+    /// committed to in the circuits, so an order recovered from its commitment is unconstrained
+    #[serde(default)]
+    pub constraints: MatchConstraints,
 }
 
 /// Convert a vector of u64s to an Order
@@ -73,10 +85,54 @@ impl TryFrom<&[u64]> for Order {
             price: Scalar::from(value[3]).into(),
             amount: value[4],
             timestamp: value[5],
+            time_in_force: TimeInForce::default(),
+            constraints: MatchConstraints::default(),
         })
     }
 }
 
+/// How long an order remains eligible for matching before the relayer cancels it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeInForce {
+    /// Good 'til cancelled, the order remains open until it is filled or cancelled
+    GoodTilCancelled,
+    /// Good 'til date, the order expires at the given unix timestamp, in seconds
+    GoodTilDate(u64),
+    /// Immediate or cancel, the order is eligible for a single match attempt and is
+    /// cancelled once the relayer's first match MPC on it concludes, whether or not it
+    /// matched
+    ImmediateOrCancel,
+}
+
+impl TimeInForce {
+    /// The unix timestamp, in seconds, at which the order expires, if it expires at a
+    /// fixed time
+    pub fn expiry(&self) -> Option<u64> {
+        match self {
+            TimeInForce::GoodTilDate(expiry) => Some(*expiry),
+            TimeInForce::GoodTilCancelled | TimeInForce::ImmediateOrCancel => None,
+        }
+    }
+}
+
+// Default for an order is to rest until cancelled
+impl Default for TimeInForce {
+    fn default() -> Self {
+        TimeInForce::GoodTilCancelled
+    }
+}
+
+/// Per-order constraints on the matches an order may take part in, falling back to the
+/// relayer's defaults where unset
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchConstraints {
+    /// The smallest amount of the base token the order may be filled with in a single match
+    pub min_fill_size: Option<u64>,
+    /// The furthest, in basis points, that the execution price may stray from the median
+    /// price report of the pair
+    pub max_slippage_bps: Option<u16>,
+}
+
 /// The side of the market a given order is on
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderSide {
@@ -296,6 +352,8 @@ impl From<LinkableOrderCommitment> for Order {
             price: order.price.into(),
             amount: scalar_to_u64(&order.amount.val),
             timestamp: scalar_to_u64(&order.timestamp.val),
+            time_in_force: TimeInForce::default(),
+            constraints: MatchConstraints::default(),
         }
     }
 }
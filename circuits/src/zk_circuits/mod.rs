@@ -26,7 +26,7 @@ mod test_helpers {
             balance::Balance,
             fee::Fee,
             keychain::{KeyChain, NUM_KEYS},
-            order::{Order, OrderSide},
+            order::{MatchConstraints, Order, OrderSide, TimeInForce},
             wallet::Wallet,
         },
         zk_gadgets::{fixed_point::FixedPoint, merkle::merkle_test::get_opening_indices},
@@ -61,6 +61,8 @@ mod test_helpers {
                 price: FixedPoint::from(5.),
                 amount: 1,
                 timestamp: TIMESTAMP,
+                time_in_force: TimeInForce::GoodTilCancelled,
+                constraints: MatchConstraints::default(),
             },
             Order {
                 quote_mint: 1u8.into(),
@@ -69,6 +71,8 @@ mod test_helpers {
                 price: FixedPoint::from(2.),
                 amount: 10,
                 timestamp: TIMESTAMP,
+                time_in_force: TimeInForce::GoodTilCancelled,
+                constraints: MatchConstraints::default(),
             }
         ];
         pub(crate) static ref INITIAL_FEES: [Fee; MAX_FEES] = [Fee {
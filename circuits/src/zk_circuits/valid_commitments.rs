@@ -30,11 +30,12 @@ use crate::{
     },
     zk_gadgets::{
         commitments::{NullifierGadget, WalletCommitGadget},
-        comparators::{EqVecGadget, GreaterThanEqGadget},
+        comparators::EqVecGadget,
         merkle::{
             MerkleOpening, MerkleOpeningCommitment, MerkleOpeningVar, PoseidonMerkleHashGadget,
         },
         poseidon::PoseidonHashGadget,
+        range::RangeCheckGadget,
         select::CondSelectGadget,
     },
     CommitProver, CommitVerifier, LinkableCommitment, SingleProverCircuit,
@@ -53,6 +54,17 @@ impl<const MAX_BALANCES: usize, const MAX_ORDERS: usize, const MAX_FEES: usize>
 where
     [(); MAX_BALANCES + MAX_ORDERS + MAX_FEES]: Sized,
 {
+    /// Checks whether the given witness and statement satisfy the VALID COMMITMENTS constraints
+    ///
+    /// This is considerably cheaper than generating a proof, and may be used to sanity check a
+    /// witness before it is handed to the prover
+    pub fn constraints_satisfied(
+        witness: ValidCommitmentsWitness<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
+        statement: ValidCommitmentsStatement,
+    ) -> bool {
+        Self::check_satisfiability(witness, statement).unwrap_or(false)
+    }
+
     /// Apply the constraints for the VALID COMMITMENTS circuitry
     pub fn circuit<CS: RandomizableConstraintSystem>(
         witness: ValidCommitmentsWitnessVar<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
@@ -98,7 +110,7 @@ where
         // Verify that the given fee balance is the same mint as the committed fee
         cs.constrain(witness.fee.gas_addr - witness.fee_balance.mint);
         // Constrain the given fee balance to be larger than the fixed fee
-        GreaterThanEqGadget::<64 /* bitlength */>::constrain_greater_than_eq(
+        RangeCheckGadget::<64 /* bitlength */>::constrain_greater_than_eq(
             witness.fee_balance.amount,
             witness.fee.gas_token_amount,
             cs,
@@ -422,19 +434,29 @@ where
 
     const BP_GENS_CAPACITY: usize = 32768;
 
-    fn prove(
+    fn apply_constraints(
         witness: Self::Witness,
         statement: Self::Statement,
-        mut prover: Prover,
-    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        prover: &mut Prover,
+    ) -> Result<Self::WitnessCommitment, ProverError> {
         // Commit to the witness
         let mut rng = OsRng {};
-        let (witness_var, witness_commit) = witness.commit_prover(&mut rng, &mut prover).unwrap();
-        let (statement_var, _) = statement.commit_prover(&mut rng, &mut prover).unwrap();
+        let (witness_var, witness_commit) = witness.commit_prover(&mut rng, prover).unwrap();
+        let (statement_var, _) = statement.commit_prover(&mut rng, prover).unwrap();
 
         // Apply the constraints
-        ValidCommitments::circuit(witness_var, statement_var, &mut prover)
-            .map_err(ProverError::R1CS)?;
+        ValidCommitments::circuit(witness_var, statement_var, prover).map_err(ProverError::R1CS)?;
+
+        Ok(witness_commit)
+    }
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: Prover,
+    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        // Commit to the witness and statement and apply the constraints
+        let witness_commit = Self::apply_constraints(witness, statement, &mut prover)?;
 
         // Prove the statement
         let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
@@ -469,8 +491,6 @@ where
 mod valid_commitments_test {
     use crypto::fields::prime_field_to_scalar;
     use curve25519_dalek::scalar::Scalar;
-    use merlin::Transcript;
-    use mpc_bulletproof::{r1cs::Prover, PedersenGens};
     use num_bigint::BigUint;
     use rand_core::{OsRng, RngCore};
 
@@ -481,43 +501,20 @@ mod valid_commitments_test {
         test_helpers::bulletproof_prove_and_verify,
         types::{
             balance::Balance,
-            order::{Order, OrderSide},
+            order::{MatchConstraints, Order, OrderSide, TimeInForce},
         },
         zk_circuits::test_helpers::{
             create_wallet_opening, SizedWallet, INITIAL_WALLET, MAX_BALANCES, MAX_FEES, MAX_ORDERS,
             PRIVATE_KEYS,
         },
         zk_gadgets::{fixed_point::FixedPoint, merkle::MerkleOpening},
-        CommitProver, LinkableCommitment,
+        LinkableCommitment,
     };
 
     use super::{ValidCommitments, ValidCommitmentsStatement, ValidCommitmentsWitness};
 
     const MERKLE_HEIGHT: usize = 3;
 
-    // -----------
-    // | Helpers |
-    // -----------
-
-    /// Checks whether the given witness and statement satisfy the circuit, without proving or verifying
-    fn constraints_satisfied(
-        witness: ValidCommitmentsWitness<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
-        statement: ValidCommitmentsStatement,
-    ) -> bool {
-        // Build a prover
-        let mut prover_transcript = Transcript::new("test".as_bytes());
-        let pc_gens = PedersenGens::default();
-        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
-
-        // Commit to the witness
-        let mut rng = OsRng {};
-        let (witness_var, _) = witness.commit_prover(&mut rng, &mut prover).unwrap();
-        let (statement_var, _) = statement.commit_prover(&mut rng, &mut prover).unwrap();
-
-        ValidCommitments::circuit(witness_var, statement_var, &mut prover).unwrap();
-        prover.constraints_satisfied()
-    }
-
     // ---------
     // | Tests |
     // ---------
@@ -599,7 +596,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Test the case in which the prover gives a balance that is not in the wallet
@@ -644,7 +641,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Tests the case in which the prover sends a fee balance that is not part of the wallet
@@ -689,7 +686,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Tests the case in which the prover sends an invalid order, not part of the wallet
@@ -703,6 +700,8 @@ mod valid_commitments_test {
             price: FixedPoint::from(10.),
             amount: 15,
             timestamp: 0,
+            time_in_force: TimeInForce::GoodTilCancelled,
+            constraints: MatchConstraints::default(),
         };
         let balance = wallet.balances[0].to_owned();
         let fee_balance = wallet.balances[0].to_owned();
@@ -736,7 +735,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Tests with a fee that is not part of the given wallet
@@ -779,7 +778,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Test the case in which the prover submits a balance for a mint different
@@ -820,7 +819,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Tests the case in which the given fee balance tuple has a different mint than
@@ -861,7 +860,7 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 
     /// Tests the case in which the fee balance is not sufficient to pay the fee
@@ -904,6 +903,6 @@ mod valid_commitments_test {
             pk_settle: wallet.keys.pk_settle,
         };
 
-        assert!(!constraints_satisfied(witness, statement));
+        assert!(!ValidCommitments::constraints_satisfied(witness, statement));
     }
 }
@@ -9,33 +9,55 @@
 //! See the whitepaper (https://renegade.fi/whitepaper.pdf) appendix A.7
 //! for a formal specification
 
+use std::{borrow::Borrow, marker::PhantomData};
+
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use itertools::Itertools;
 use mpc_bulletproof::{
     r1cs::{ConstraintSystem, Prover, R1CSProof, RandomizableConstraintSystem, Variable, Verifier},
-    r1cs_mpc::R1CSError,
+    r1cs_mpc::{
+        MpcLinearCombination, MpcProver, MpcRandomizableConstraintSystem, MpcVariable, R1CSError,
+        SharedR1CSProof,
+    },
     BulletproofGens,
 };
+use mpc_ristretto::{
+    authenticated_ristretto::AuthenticatedCompressedRistretto,
+    authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource, network::MpcNetwork,
+};
 use rand_core::OsRng;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    errors::{ProverError, VerifierError},
+    errors::{MpcError, ProverError, VerifierError},
+    mpc::SharedFabric,
     types::{
-        fee::{CommittedFee, FeeVar, LinkableFeeCommitment},
-        note::{CommittedNote, Note, NoteVar},
-        r#match::{CommittedMatchResult, LinkableMatchResultCommitment, MatchResultVar},
+        fee::{
+            AuthenticatedCommittedFee, AuthenticatedFeeVar, CommittedFee, FeeVar,
+            LinkableFeeCommitment,
+        },
+        note::{
+            AuthenticatedCommittedNote, AuthenticatedNote, AuthenticatedNoteVar, CommittedNote,
+            Note, NoteVar,
+        },
+        r#match::{
+            AuthenticatedCommittedMatchResult, AuthenticatedLinkableMatchResultCommitment,
+            AuthenticatedMatchResultVar, CommittedMatchResult, LinkableMatchResultCommitment,
+            MatchResultVar,
+        },
     },
     zk_gadgets::{
-        commitments::NoteCommitmentGadget,
-        comparators::EqGadget,
+        commitments::{MultiproverNoteCommitmentGadget, NoteCommitmentGadget},
+        comparators::{EqGadget, MultiproverEqGadget},
         elgamal::{
-            ElGamalCiphertext, ElGamalCiphertextVar, ElGamalGadget, DEFAULT_ELGAMAL_GENERATOR,
+            AuthenticatedElGamalCiphertextVar, ElGamalCiphertext, ElGamalCiphertextVar,
+            ElGamalGadget, MultiproverElGamalGadget, DEFAULT_ELGAMAL_GENERATOR,
         },
-        fixed_point::{FixedPoint, FixedPointVar},
-        select::CondSelectGadget,
+        fixed_point::{AuthenticatedFixedPointVar, CommittedFixedPoint, FixedPoint, FixedPointVar},
+        select::{CondSelectGadget, MultiproverCondSelectGadget},
     },
-    CommitProver, CommitVerifier, LinkableCommitment, SingleProverCircuit,
+    CommitProver, CommitSharedProver, CommitVerifier, LinkableCommitment, MultiProverCircuit, Open,
+    SingleProverCircuit,
 };
 
 /// The number of encryptions that are actively verified by the circuit
@@ -534,7 +556,7 @@ impl<const SCALAR_BITS: usize> ValidMatchEncryption<SCALAR_BITS> {
 }
 
 /// The witness type for the VALID MATCH ENCRYPTION circuit
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ValidMatchEncryptionWitness {
     /// The result of the match process; a completed match
     pub match_res: LinkableMatchResultCommitment,
@@ -948,18 +970,29 @@ impl<const SCALAR_BITS: usize> SingleProverCircuit for ValidMatchEncryption<SCAL
 
     const BP_GENS_CAPACITY: usize = 65536;
 
-    fn prove(
+    fn apply_constraints(
         witness: Self::Witness,
         statement: Self::Statement,
-        mut prover: Prover,
-    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        prover: &mut Prover,
+    ) -> Result<Self::WitnessCommitment, ProverError> {
         // Commit to the witness and statement
         let mut rng = OsRng {};
-        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, &mut prover).unwrap();
-        let (statement_var, _) = statement.commit_prover(&mut rng, &mut prover).unwrap();
+        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, prover).unwrap();
+        let (statement_var, _) = statement.commit_prover(&mut rng, prover).unwrap();
 
         // Apply the constraints
-        Self::circuit(witness_var, statement_var, &mut prover).map_err(ProverError::R1CS)?;
+        Self::circuit(witness_var, statement_var, prover).map_err(ProverError::R1CS)?;
+
+        Ok(witness_comm)
+    }
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: Prover,
+    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        // Commit to the witness and statement and apply the constraints
+        let witness_comm = Self::apply_constraints(witness, statement, &mut prover)?;
 
         // Prove the statement
         let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
@@ -989,6 +1022,987 @@ impl<const SCALAR_BITS: usize> SingleProverCircuit for ValidMatchEncryption<SCAL
     }
 }
 
+/// The bitlength used to compute equality hints in the multiprover circuit
+///
+/// The values compared are note volumes and sums of boolean constraints, the
+/// difference of two such values is zero mod 2^128 only if it is zero
+const MPC_EQ_BITS: usize = 128;
+
+/// A multiprover implementation of the VALID MATCH ENCRYPTION circuit
+///
+/// Applies the same constraints as `ValidMatchEncryption`; the relayers prove the
+/// statement collaboratively over the shared match result, and the resulting proof
+/// is verified by the single-prover verifier
+pub struct ValidMatchEncryptionMpc<
+    'a,
+    const SCALAR_BITS: usize,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<
+        'a,
+        const SCALAR_BITS: usize,
+        N: 'a + MpcNetwork + Send,
+        S: 'a + SharedValueSource<Scalar>,
+    > ValidMatchEncryptionMpc<'a, SCALAR_BITS, N, S>
+{
+    /// Implements the circuitry for the VALID MATCH ENCRYPTION circuit
+    pub fn circuit<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        witness: ValidMatchEncryptionMpcWitnessVar<N, S>,
+        statement: ValidMatchEncryptionMpcStatementVar<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<(), ProverError> {
+        Self::check_encryptions(&witness, &statement, fabric.clone(), cs)?;
+        Self::validate_notes(&witness, &statement, fabric.clone(), cs)?;
+        Self::validate_note_commitments(&witness, &statement, fabric, cs)
+    }
+
+    /// Checks the ciphertexts that must be validated by the circuit
+    fn check_encryptions<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        witness: &ValidMatchEncryptionMpcWitnessVar<N, S>,
+        statement: &ValidMatchEncryptionMpcStatementVar<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<(), ProverError> {
+        let encryptions = [
+            (
+                &witness.party0_note.volume1,
+                &statement.pk_settle_party0,
+                &statement.volume1_ciphertext1,
+            ),
+            (
+                &witness.party0_note.volume2,
+                &statement.pk_settle_party0,
+                &statement.volume2_ciphertext1,
+            ),
+            (
+                &witness.party1_note.volume1,
+                &statement.pk_settle_party1,
+                &statement.volume1_ciphertext2,
+            ),
+            (
+                &witness.party1_note.volume2,
+                &statement.pk_settle_party1,
+                &statement.volume2_ciphertext2,
+            ),
+            (
+                &witness.protocol_note.mint1,
+                &statement.pk_settle_protocol,
+                &statement.mint1_protocol_ciphertext,
+            ),
+            (
+                &witness.protocol_note.volume1,
+                &statement.pk_settle_protocol,
+                &statement.volume1_protocol_ciphertext,
+            ),
+            (
+                &witness.protocol_note.mint2,
+                &statement.pk_settle_protocol,
+                &statement.mint2_protocol_ciphertext,
+            ),
+            (
+                &witness.protocol_note.volume2,
+                &statement.pk_settle_protocol,
+                &statement.volume2_protocol_ciphertext,
+            ),
+            (
+                &witness.protocol_note.randomness,
+                &statement.pk_settle_protocol,
+                &statement.randomness_protocol_ciphertext,
+            ),
+        ];
+
+        for ((plaintext, pub_key, ciphertext), randomness) in encryptions
+            .into_iter()
+            .zip(witness.elgamal_randomness.iter())
+        {
+            let expected_ciphertext = MultiproverElGamalGadget::<'a, SCALAR_BITS, N, S>::encrypt(
+                *DEFAULT_ELGAMAL_GENERATOR,
+                randomness.clone(),
+                plaintext.clone(),
+                pub_key.clone(),
+                fabric.clone(),
+                cs,
+            )?;
+            cs.constrain(expected_ciphertext.0 - ciphertext.partial_shared_secret.clone());
+            cs.constrain(expected_ciphertext.1 - ciphertext.encrypted_message.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Check that the notes in the witness are properly formed given the match result
+    /// and committed fees
+    fn validate_notes<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        witness: &ValidMatchEncryptionMpcWitnessVar<N, S>,
+        statement: &ValidMatchEncryptionMpcStatementVar<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<(), ProverError> {
+        let one = MpcVariable::one(fabric.0.clone());
+
+        // Mux between the constraint sets for the two directions of the match and assert
+        // that the selected set is satisfied
+        let party0_buy_constraints =
+            Self::party0_buy_base_constraints_satisfied(witness, statement, fabric.clone(), cs)?;
+        let party0_sell_constraints =
+            Self::party1_buy_base_constraints_satisfied(witness, statement, fabric.clone(), cs)?;
+
+        let selected_constraint_set = MultiproverCondSelectGadget::select(
+            party0_sell_constraints.into(),
+            party0_buy_constraints.into(),
+            witness.match_res.direction.clone().into(),
+            fabric.clone(),
+            cs,
+        )?;
+        cs.constrain(
+            MpcLinearCombination::from_scalar(Scalar::one(), fabric.0.clone())
+                - selected_constraint_set,
+        );
+
+        // Validate that the protocol note volumes are properly formed
+        let protocol_expected_v1 = statement
+            .protocol_fee
+            .mul_integer(witness.match_res.base_amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric.clone(), cs)?;
+        let protocol_expected_v2 = statement
+            .protocol_fee
+            .mul_integer(witness.match_res.quote_amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric, cs)?;
+
+        cs.constrain(&protocol_expected_v1 - &witness.protocol_note.volume1);
+        cs.constrain(&protocol_expected_v2 - &witness.protocol_note.volume2);
+
+        // The base mint in each note
+        let match_res = &witness.match_res;
+        cs.constrain(&match_res.base_mint - &witness.party0_note.mint1);
+        cs.constrain(&match_res.base_mint - &witness.party1_note.mint1);
+        cs.constrain(&match_res.base_mint - &witness.relayer0_note.mint1);
+        cs.constrain(&match_res.base_mint - &witness.relayer1_note.mint1);
+        cs.constrain(&match_res.base_mint - &witness.protocol_note.mint1);
+
+        // The quote mint in each note
+        cs.constrain(&match_res.quote_mint - &witness.party0_note.mint2);
+        cs.constrain(&match_res.quote_mint - &witness.party1_note.mint2);
+        cs.constrain(&match_res.quote_mint - &witness.relayer0_note.mint2);
+        cs.constrain(&match_res.quote_mint - &witness.relayer1_note.mint2);
+        cs.constrain(&match_res.quote_mint - &witness.protocol_note.mint2);
+
+        // The first direction in each note
+        cs.constrain(&witness.party0_note.direction1 - &match_res.direction);
+        cs.constrain(&witness.party1_note.direction1 - &one + match_res.direction.clone());
+        cs.constrain(witness.relayer0_note.direction1.clone().into());
+        cs.constrain(witness.relayer1_note.direction1.clone().into());
+        cs.constrain(witness.protocol_note.direction1.clone().into());
+
+        // The second direction in each note
+        cs.constrain(&witness.party0_note.direction2 - &one + match_res.direction.clone());
+        cs.constrain(&witness.party1_note.direction2 - &match_res.direction);
+        cs.constrain(witness.relayer0_note.direction2.clone().into());
+        cs.constrain(witness.relayer1_note.direction2.clone().into());
+        cs.constrain(witness.protocol_note.direction2.clone().into());
+
+        // The gas fee mint in each note
+        cs.constrain(&witness.party0_note.fee_mint - &witness.party0_fee.gas_addr);
+        cs.constrain(&witness.party1_note.fee_mint - &witness.party1_fee.gas_addr);
+        cs.constrain(&witness.relayer0_note.fee_mint - &witness.party0_fee.gas_addr);
+        cs.constrain(&witness.relayer1_note.fee_mint - &witness.party1_fee.gas_addr);
+        cs.constrain(witness.protocol_note.fee_mint.clone().into());
+
+        // The gas amount in each note
+        cs.constrain(&witness.party0_note.fee_volume - &witness.party0_fee.gas_token_amount);
+        cs.constrain(&witness.party1_note.fee_volume - &witness.party1_fee.gas_token_amount);
+        cs.constrain(&witness.relayer0_note.fee_volume - &witness.party0_fee.gas_token_amount);
+        cs.constrain(&witness.relayer1_note.fee_volume - &witness.party1_fee.gas_token_amount);
+        cs.constrain(witness.protocol_note.fee_volume.clone().into());
+
+        // The fee direction in each note
+        cs.constrain(&witness.party0_note.fee_direction - &one);
+        cs.constrain(&witness.party1_note.fee_direction - &one);
+        cs.constrain(witness.relayer0_note.fee_direction.clone().into());
+        cs.constrain(witness.relayer1_note.fee_direction.clone().into());
+        cs.constrain(witness.protocol_note.fee_direction.clone().into());
+
+        // The match vs transfer flag for each note
+        cs.constrain(&witness.party0_note.type_ - &one);
+        cs.constrain(&witness.party1_note.type_ - &one);
+        cs.constrain(witness.relayer0_note.type_.clone().into());
+        cs.constrain(witness.relayer1_note.type_.clone().into());
+        cs.constrain(witness.protocol_note.type_.clone().into());
+
+        // The randomness of each note
+        cs.constrain(&witness.party0_note.randomness - &witness.party0_randomness_hash); // r_1
+        cs.constrain(&witness.party1_note.randomness - &witness.party1_randomness_hash); // r_2
+        cs.constrain(
+            &witness.relayer0_note.randomness - &witness.party0_randomness_hash - one.clone(),
+        ); // r_1 + 1
+        cs.constrain(
+            &witness.relayer1_note.randomness - &witness.party1_randomness_hash - one.clone(),
+        ); // r_2 + 1
+        cs.constrain(
+            &witness.protocol_note.randomness
+                - &witness.party0_randomness_hash
+                - witness.party1_randomness_hash.clone(),
+        ); // r_1 + r_2
+
+        Ok(())
+    }
+
+    /// Creates the constraints that will be enforced if party 0 is buying the base asset
+    /// and party 1 buys the quote asset
+    ///
+    /// Returns a boolean representing the satisfiability of the constraints needed when
+    /// the match goes in this direction
+    fn party0_buy_base_constraints_satisfied<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        witness: &ValidMatchEncryptionMpcWitnessVar<N, S>,
+        statement: &ValidMatchEncryptionMpcStatementVar<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        let match_res = &witness.match_res;
+
+        // Party0's note receives the base amount exchanged minus the fees
+        let party0_base_expected = Self::net_of_fees(
+            &witness.party0_fee.percentage_fee,
+            &statement.protocol_fee,
+            &match_res.base_amount,
+            fabric.clone(),
+            cs,
+        )?;
+        let p0_mint1_constraint = Self::eq(
+            &witness.party0_note.volume1,
+            &party0_base_expected,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Party0's note sends the quote amount exchanged
+        let p0_mint2_constraint = Self::eq(
+            &witness.party0_note.volume2,
+            &match_res.quote_amount,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Party1's note sends the base amount exchanged
+        let p1_mint1_constraint = Self::eq(
+            &witness.party1_note.volume1,
+            &match_res.base_amount,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Party1's note receives the quote amount exchanged minus the fees
+        let party1_quote_expected = Self::net_of_fees(
+            &witness.party1_fee.percentage_fee,
+            &statement.protocol_fee,
+            &match_res.quote_amount,
+            fabric.clone(),
+            cs,
+        )?;
+        let p1_mint2_constraint = Self::eq(
+            &witness.party1_note.volume2,
+            &party1_quote_expected,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Relayer0's note receives the base amount fee, and none of the quote
+        let relayer0_mint1_expected = witness
+            .party0_fee
+            .percentage_fee
+            .mul_integer(match_res.base_amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric.clone(), cs)?;
+        let relayer0_mint1_constraint = Self::eq(
+            &witness.relayer0_note.volume1,
+            &relayer0_mint1_expected,
+            fabric.clone(),
+            cs,
+        )?;
+        let relayer0_mint2_constraint =
+            Self::eq_zero(&witness.relayer0_note.volume2, fabric.clone(), cs)?;
+
+        // Relayer1's note receives none of the base, and the quote amount fee
+        let relayer1_mint1_constraint =
+            Self::eq_zero(&witness.relayer1_note.volume1, fabric.clone(), cs)?;
+        let relayer1_mint2_expected = witness
+            .party1_fee
+            .percentage_fee
+            .mul_integer(match_res.quote_amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric.clone(), cs)?;
+        let relayer1_mint2_constraint = Self::eq(
+            &witness.relayer1_note.volume2,
+            &relayer1_mint2_expected,
+            fabric.clone(),
+            cs,
+        )?;
+
+        Self::all_satisfied(
+            &[
+                p0_mint1_constraint,
+                p0_mint2_constraint,
+                p1_mint1_constraint,
+                p1_mint2_constraint,
+                relayer0_mint1_constraint,
+                relayer0_mint2_constraint,
+                relayer1_mint1_constraint,
+                relayer1_mint2_constraint,
+            ],
+            fabric,
+            cs,
+        )
+    }
+
+    /// Creates the constraints that will be enforced if party 0 is buying the quote asset
+    /// and party 1 buys the base asset
+    ///
+    /// Returns a boolean representing the satisfiability of the constraints needed when
+    /// the match goes in this direction
+    fn party1_buy_base_constraints_satisfied<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        witness: &ValidMatchEncryptionMpcWitnessVar<N, S>,
+        statement: &ValidMatchEncryptionMpcStatementVar<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        let match_res = &witness.match_res;
+
+        // Party0's note sends the base amount exchanged
+        let p0_mint1_constraint = Self::eq(
+            &witness.party0_note.volume1,
+            &match_res.base_amount,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Party0's note receives the quote amount exchanged minus the fees
+        let party0_quote_expected = Self::net_of_fees(
+            &witness.party0_fee.percentage_fee,
+            &statement.protocol_fee,
+            &match_res.quote_amount,
+            fabric.clone(),
+            cs,
+        )?;
+        let p0_mint2_constraint = Self::eq(
+            &witness.party0_note.volume2,
+            &party0_quote_expected,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Party1's note receives the base amount exchanged minus the fees
+        let party1_base_expected = Self::net_of_fees(
+            &witness.party1_fee.percentage_fee,
+            &statement.protocol_fee,
+            &match_res.base_amount,
+            fabric.clone(),
+            cs,
+        )?;
+        let p1_mint1_constraint = Self::eq(
+            &witness.party1_note.volume1,
+            &party1_base_expected,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Party1's note sends the quote amount exchanged
+        let p1_mint2_constraint = Self::eq(
+            &witness.party1_note.volume2,
+            &match_res.quote_amount,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Relayer0's note receives none of the base, and the quote amount fee
+        let relayer0_mint1_constraint =
+            Self::eq_zero(&witness.relayer0_note.volume1, fabric.clone(), cs)?;
+        let relayer0_mint2_expected = witness
+            .party0_fee
+            .percentage_fee
+            .mul_integer(match_res.quote_amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric.clone(), cs)?;
+        let relayer0_mint2_constraint = Self::eq(
+            &witness.relayer0_note.volume2,
+            &relayer0_mint2_expected,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Relayer1's note receives the base amount fee, and none of the quote
+        let relayer1_mint1_expected = witness
+            .party1_fee
+            .percentage_fee
+            .mul_integer(match_res.base_amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric.clone(), cs)?;
+        let relayer1_mint1_constraint = Self::eq(
+            &witness.relayer1_note.volume1,
+            &relayer1_mint1_expected,
+            fabric.clone(),
+            cs,
+        )?;
+        let relayer1_mint2_constraint =
+            Self::eq_zero(&witness.relayer1_note.volume2, fabric.clone(), cs)?;
+
+        Self::all_satisfied(
+            &[
+                p0_mint1_constraint,
+                p0_mint2_constraint,
+                p1_mint1_constraint,
+                p1_mint2_constraint,
+                relayer0_mint1_constraint,
+                relayer0_mint2_constraint,
+                relayer1_mint1_constraint,
+                relayer1_mint2_constraint,
+            ],
+            fabric,
+            cs,
+        )
+    }
+
+    /// Computes the amount of a transfer that a party receives once the relayer and
+    /// protocol fees are taken out
+    fn net_of_fees<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        relayer_fee: &AuthenticatedFixedPointVar<N, S>,
+        protocol_fee: &AuthenticatedFixedPointVar<N, S>,
+        amount: &MpcVariable<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        let one = MpcLinearCombination::from_scalar(Scalar::one(), fabric.0.clone());
+        let net_of_relayer_fee = &one - relayer_fee;
+        let net_fraction = &net_of_relayer_fee - protocol_fee;
+
+        net_fraction
+            .mul_integer(amount.clone(), cs)
+            .map_err(ProverError::Collaborative)?
+            .floor(fabric, cs)
+    }
+
+    /// Computes whether two values are equal
+    fn eq<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        a: &MpcVariable<N, S>,
+        b: &MpcVariable<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        MultiproverEqGadget::<'a, MPC_EQ_BITS, N, S>::eq(a.clone(), b.clone(), fabric, cs)
+    }
+
+    /// Computes whether a value is equal to zero
+    fn eq_zero<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        a: &MpcVariable<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        let zero = MpcLinearCombination::from_scalar(Scalar::zero(), fabric.0.clone());
+        MultiproverEqGadget::<'a, MPC_EQ_BITS, N, S>::eq(a.clone().into(), zero, fabric, cs)
+    }
+
+    /// Takes the AND of a set of boolean constraints, by checking that their sum equals
+    /// the number of constraints
+    fn all_satisfied<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        constraints: &[MpcVariable<N, S>],
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        let sum = constraints.iter().fold(
+            MpcLinearCombination::from_scalar(Scalar::zero(), fabric.0.clone()),
+            |acc, constraint| acc + constraint.clone(),
+        );
+        let n_constraints = MpcLinearCombination::from_scalar(
+            Scalar::from(constraints.len() as u64),
+            fabric.0.clone(),
+        );
+
+        MultiproverEqGadget::<'a, MPC_EQ_BITS, N, S>::eq(sum, n_constraints, fabric, cs)
+    }
+
+    /// Validate the commitments to the notes are properly constructed
+    fn validate_note_commitments<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        witness: &ValidMatchEncryptionMpcWitnessVar<N, S>,
+        statement: &ValidMatchEncryptionMpcStatementVar<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<(), ProverError> {
+        let notes = [
+            (
+                &witness.party0_note,
+                &statement.pk_settle_party0,
+                &statement.party0_note_commit,
+            ),
+            (
+                &witness.party1_note,
+                &statement.pk_settle_party1,
+                &statement.party1_note_commit,
+            ),
+            (
+                &witness.relayer0_note,
+                &statement.pk_settle_relayer0,
+                &statement.relayer0_note_commit,
+            ),
+            (
+                &witness.relayer1_note,
+                &statement.pk_settle_relayer1,
+                &statement.relayer1_note_commit,
+            ),
+            (
+                &witness.protocol_note,
+                &statement.pk_settle_protocol,
+                &statement.protocol_note_commit,
+            ),
+        ];
+
+        for (note, pk_settle, note_commit) in notes.into_iter() {
+            let note_commit_res = MultiproverNoteCommitmentGadget::note_commit(
+                note,
+                pk_settle.clone(),
+                fabric.clone(),
+                cs,
+            )?;
+            cs.constrain(MpcLinearCombination::from(note_commit.clone()) - note_commit_res);
+        }
+
+        Ok(())
+    }
+
+    /// Commit to the statement as public variables in the constraint system, in the order
+    /// of `ValidMatchEncryptionStatement`'s single-prover commitment
+    fn commit_statement(
+        statement: &ValidMatchEncryptionStatement,
+        prover: &mut MpcProver<N, S>,
+    ) -> ValidMatchEncryptionMpcStatementVar<N, S> {
+        let (_, party0_note_commit_var) = prover.commit_public(statement.party0_note_commit);
+        let (_, party1_note_commit_var) = prover.commit_public(statement.party1_note_commit);
+        let (_, relayer0_note_commit_var) = prover.commit_public(statement.relayer0_note_commit);
+        let (_, relayer1_note_commit_var) = prover.commit_public(statement.relayer1_note_commit);
+        let (_, protocol_note_commit_var) = prover.commit_public(statement.protocol_note_commit);
+        let (_, pk_settle_party0_var) = prover.commit_public(statement.pk_settle_party0);
+        let (_, pk_settle_party1_var) = prover.commit_public(statement.pk_settle_party1);
+        let (_, pk_settle_relayer0_var) = prover.commit_public(statement.pk_settle_relayer0);
+        let (_, pk_settle_relayer1_var) = prover.commit_public(statement.pk_settle_relayer1);
+        let (_, pk_settle_protocol_var) = prover.commit_public(statement.pk_settle_protocol);
+        let (_, protocol_fee_var) = prover.commit_public(statement.protocol_fee.repr);
+
+        ValidMatchEncryptionMpcStatementVar {
+            party0_note_commit: party0_note_commit_var,
+            party1_note_commit: party1_note_commit_var,
+            relayer0_note_commit: relayer0_note_commit_var,
+            relayer1_note_commit: relayer1_note_commit_var,
+            protocol_note_commit: protocol_note_commit_var,
+            pk_settle_party0: pk_settle_party0_var,
+            pk_settle_party1: pk_settle_party1_var,
+            pk_settle_relayer0: pk_settle_relayer0_var,
+            pk_settle_relayer1: pk_settle_relayer1_var,
+            pk_settle_protocol: pk_settle_protocol_var,
+            protocol_fee: AuthenticatedFixedPointVar {
+                repr: protocol_fee_var.into(),
+            },
+            volume1_ciphertext1: statement.volume1_ciphertext1.commit_public_shared(prover),
+            volume2_ciphertext1: statement.volume2_ciphertext1.commit_public_shared(prover),
+            volume1_ciphertext2: statement.volume1_ciphertext2.commit_public_shared(prover),
+            volume2_ciphertext2: statement.volume2_ciphertext2.commit_public_shared(prover),
+            mint1_protocol_ciphertext: statement
+                .mint1_protocol_ciphertext
+                .commit_public_shared(prover),
+            volume1_protocol_ciphertext: statement
+                .volume1_protocol_ciphertext
+                .commit_public_shared(prover),
+            mint2_protocol_ciphertext: statement
+                .mint2_protocol_ciphertext
+                .commit_public_shared(prover),
+            volume2_protocol_ciphertext: statement
+                .volume2_protocol_ciphertext
+                .commit_public_shared(prover),
+            randomness_protocol_ciphertext: statement
+                .randomness_protocol_ciphertext
+                .commit_public_shared(prover),
+        }
+    }
+}
+
+/// The witness type for the multiprover VALID MATCH ENCRYPTION circuit
+///
+/// Each relayer holds only its own party's fee and randomness hash; the match
+/// result, the notes, and the encryption randomness are shared between the relayers
+#[derive(Debug)]
+pub struct ValidMatchEncryptionMpcWitness<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The fee of the local party, committed to in the match process
+    pub my_fee: LinkableFeeCommitment,
+    /// The hashed randomness of the local party's wallet, used as note randomness
+    pub my_randomness_hash: LinkableCommitment,
+    /// The result of the match process; a completed match
+    pub match_res: AuthenticatedLinkableMatchResultCommitment<N, S>,
+    /// The note of exchange for the first party
+    pub party0_note: AuthenticatedNote<N, S>,
+    /// The note of exchange for the second party
+    pub party1_note: AuthenticatedNote<N, S>,
+    /// The transfer note for the first relayer's fee
+    pub relayer0_note: AuthenticatedNote<N, S>,
+    /// The transfer note for the second relayer's fee
+    pub relayer1_note: AuthenticatedNote<N, S>,
+    /// The transfer note for the protocol fee
+    pub protocol_note: AuthenticatedNote<N, S>,
+    /// The randomness used in the ElGamal encryptions, `NUM_ENCRYPTIONS` values
+    pub elgamal_randomness: Vec<AuthenticatedScalar<N, S>>,
+}
+
+/// Explicit clone implementation to remove the bounds on generics N, S to be `Clone`
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Clone
+    for ValidMatchEncryptionMpcWitness<N, S>
+{
+    fn clone(&self) -> Self {
+        Self {
+            my_fee: self.my_fee.clone(),
+            my_randomness_hash: self.my_randomness_hash.clone(),
+            match_res: self.match_res.clone(),
+            party0_note: self.party0_note.clone(),
+            party1_note: self.party1_note.clone(),
+            relayer0_note: self.relayer0_note.clone(),
+            relayer1_note: self.relayer1_note.clone(),
+            protocol_note: self.protocol_note.clone(),
+            elgamal_randomness: self.elgamal_randomness.clone(),
+        }
+    }
+}
+
+/// A witness for the multiprover VALID MATCH ENCRYPTION circuit that has been
+/// allocated in a multi-prover constraint system
+#[derive(Debug)]
+pub struct ValidMatchEncryptionMpcWitnessVar<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The result of the match process; a completed match
+    pub match_res: AuthenticatedMatchResultVar<N, S>,
+    /// The first party's fee committed to in the match process
+    pub party0_fee: AuthenticatedFeeVar<N, S>,
+    /// The second party's fee committed to in the match process
+    pub party1_fee: AuthenticatedFeeVar<N, S>,
+    /// The hashed randomness of the first party, used as note randomness
+    pub party0_randomness_hash: MpcVariable<N, S>,
+    /// The hashed randomness of the second party, used as note randomness
+    pub party1_randomness_hash: MpcVariable<N, S>,
+    /// The note of exchange for the first party
+    pub party0_note: AuthenticatedNoteVar<N, S>,
+    /// The note of exchange for the second party
+    pub party1_note: AuthenticatedNoteVar<N, S>,
+    /// The transfer note for the first relayer's fee
+    pub relayer0_note: AuthenticatedNoteVar<N, S>,
+    /// The transfer note for the second relayer's fee
+    pub relayer1_note: AuthenticatedNoteVar<N, S>,
+    /// The transfer note for the protocol fee
+    pub protocol_note: AuthenticatedNoteVar<N, S>,
+    /// The randomness used in the ElGamal encryptions to generate shared secrets
+    pub elgamal_randomness: Vec<MpcVariable<N, S>>,
+}
+
+/// A commitment to the multiprover VALID MATCH ENCRYPTION witness that has been
+/// allocated in an MPC network
+#[derive(Clone, Debug)]
+pub struct ValidMatchEncryptionCommitmentShared<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>
+{
+    /// The result of the match process; a completed match
+    pub match_res: AuthenticatedCommittedMatchResult<N, S>,
+    /// The first party's fee committed to in the match process
+    pub party0_fee: AuthenticatedCommittedFee<N, S>,
+    /// The second party's fee committed to in the match process
+    pub party1_fee: AuthenticatedCommittedFee<N, S>,
+    /// The hashed randomness of the first party, used as note randomness
+    pub party0_randomness_hash: AuthenticatedCompressedRistretto<N, S>,
+    /// The hashed randomness of the second party, used as note randomness
+    pub party1_randomness_hash: AuthenticatedCompressedRistretto<N, S>,
+    /// The note of exchange for the first party
+    pub party0_note: AuthenticatedCommittedNote<N, S>,
+    /// The note of exchange for the second party
+    pub party1_note: AuthenticatedCommittedNote<N, S>,
+    /// The transfer note for the first relayer's fee
+    pub relayer0_note: AuthenticatedCommittedNote<N, S>,
+    /// The transfer note for the second relayer's fee
+    pub relayer1_note: AuthenticatedCommittedNote<N, S>,
+    /// The transfer note for the protocol fee
+    pub protocol_note: AuthenticatedCommittedNote<N, S>,
+    /// The randomness used in the ElGamal encryptions to generate shared secrets
+    pub elgamal_randomness: Vec<AuthenticatedCompressedRistretto<N, S>>,
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>
+    From<ValidMatchEncryptionCommitmentShared<N, S>>
+    for Vec<AuthenticatedCompressedRistretto<N, S>>
+{
+    fn from(commit: ValidMatchEncryptionCommitmentShared<N, S>) -> Self {
+        Into::<Vec<_>>::into(commit.match_res)
+            .into_iter()
+            .chain(Into::<Vec<_>>::into(commit.party0_fee).into_iter())
+            .chain(Into::<Vec<_>>::into(commit.party1_fee).into_iter())
+            .chain(vec![
+                commit.party0_randomness_hash,
+                commit.party1_randomness_hash,
+            ])
+            .chain(Into::<Vec<_>>::into(commit.party0_note).into_iter())
+            .chain(Into::<Vec<_>>::into(commit.party1_note).into_iter())
+            .chain(Into::<Vec<_>>::into(commit.relayer0_note).into_iter())
+            .chain(Into::<Vec<_>>::into(commit.relayer1_note).into_iter())
+            .chain(Into::<Vec<_>>::into(commit.protocol_note).into_iter())
+            .chain(commit.elgamal_randomness.into_iter())
+            .collect_vec()
+    }
+}
+
+impl From<&[CompressedRistretto]> for ValidMatchEncryptionWitnessCommitment {
+    fn from(commitments: &[CompressedRistretto]) -> Self {
+        Self {
+            match_res: CommittedMatchResult {
+                quote_mint: commitments[0],
+                base_mint: commitments[1],
+                quote_amount: commitments[2],
+                base_amount: commitments[3],
+                direction: commitments[4],
+                execution_price: CommittedFixedPoint {
+                    repr: commitments[5],
+                },
+                max_minus_min_amount: commitments[6],
+                min_amount_order_index: commitments[7],
+            },
+            party0_fee: CommittedFee {
+                settle_key: commitments[8],
+                gas_addr: commitments[9],
+                gas_token_amount: commitments[10],
+                percentage_fee: CommittedFixedPoint {
+                    repr: commitments[11],
+                },
+            },
+            party1_fee: CommittedFee {
+                settle_key: commitments[12],
+                gas_addr: commitments[13],
+                gas_token_amount: commitments[14],
+                percentage_fee: CommittedFixedPoint {
+                    repr: commitments[15],
+                },
+            },
+            party0_randomness_hash: commitments[16],
+            party1_randomness_hash: commitments[17],
+            party0_note: CommittedNote::from(&commitments[18..29]),
+            party1_note: CommittedNote::from(&commitments[29..40]),
+            relayer0_note: CommittedNote::from(&commitments[40..51]),
+            relayer1_note: CommittedNote::from(&commitments[51..62]),
+            protocol_note: CommittedNote::from(&commitments[62..73]),
+            elgamal_randomness: commitments[73..73 + NUM_ENCRYPTIONS].try_into().unwrap(),
+        }
+    }
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Open<N, S>
+    for ValidMatchEncryptionCommitmentShared<N, S>
+{
+    type OpenOutput = ValidMatchEncryptionWitnessCommitment;
+    type Error = MpcError;
+
+    fn open(self, _: SharedFabric<N, S>) -> Result<Self::OpenOutput, Self::Error> {
+        let all_commitments: Vec<AuthenticatedCompressedRistretto<N, S>> = self.into();
+        let opened_values: Vec<CompressedRistretto> =
+            AuthenticatedCompressedRistretto::batch_open(&all_commitments)
+                .map_err(|err| MpcError::SharingError(err.to_string()))?
+                .into_iter()
+                .map(|val| val.value())
+                .collect();
+
+        Ok(Into::<ValidMatchEncryptionWitnessCommitment>::into(
+            opened_values.borrow(),
+        ))
+    }
+
+    fn open_and_authenticate(self, _: SharedFabric<N, S>) -> Result<Self::OpenOutput, Self::Error> {
+        let all_commitments: Vec<AuthenticatedCompressedRistretto<N, S>> = self.into();
+        let opened_values: Vec<CompressedRistretto> =
+            AuthenticatedCompressedRistretto::batch_open_and_authenticate(&all_commitments)
+                .map_err(|err| MpcError::SharingError(err.to_string()))?
+                .into_iter()
+                .map(|val| val.value())
+                .collect();
+
+        Ok(Into::<ValidMatchEncryptionWitnessCommitment>::into(
+            opened_values.borrow(),
+        ))
+    }
+}
+
+/// The statement type for the multiprover VALID MATCH ENCRYPTION circuit, allocated
+/// in a multi-prover constraint system
+#[derive(Debug)]
+pub struct ValidMatchEncryptionMpcStatementVar<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The commitment to the first party's note
+    pub party0_note_commit: MpcVariable<N, S>,
+    /// The commitment to the second party's note
+    pub party1_note_commit: MpcVariable<N, S>,
+    /// The commitment to the first relayer's note
+    pub relayer0_note_commit: MpcVariable<N, S>,
+    /// The commitment to the second relayer's note
+    pub relayer1_note_commit: MpcVariable<N, S>,
+    /// The commitment to the protocol's note
+    pub protocol_note_commit: MpcVariable<N, S>,
+    /// The public settle key of the first party's wallet
+    pub pk_settle_party0: MpcVariable<N, S>,
+    /// The public settle key of the second party's wallet
+    pub pk_settle_party1: MpcVariable<N, S>,
+    /// The public settle key of the first relayer
+    pub pk_settle_relayer0: MpcVariable<N, S>,
+    /// The public settle key of the second relayer
+    pub pk_settle_relayer1: MpcVariable<N, S>,
+    /// The public settle key of the protocol
+    pub pk_settle_protocol: MpcVariable<N, S>,
+    /// The global protocol fee
+    pub protocol_fee: AuthenticatedFixedPointVar<N, S>,
+    /// Encryption of the exchanged volume of mint1 under the first party's key
+    pub volume1_ciphertext1: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the exchanged volume of mint2 under the first party's key
+    pub volume2_ciphertext1: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the exchanged volume of mint1 under the second party's key
+    pub volume1_ciphertext2: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the exchanged volume of mint2 under the second party's key
+    pub volume2_ciphertext2: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the first mint under the protocol's public key
+    pub mint1_protocol_ciphertext: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the first mint's exchanged volume under the protocol's key
+    pub volume1_protocol_ciphertext: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the second mint under the protocol's public key
+    pub mint2_protocol_ciphertext: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the second mint's exchanged volume under the protocol's key
+    pub volume2_protocol_ciphertext: AuthenticatedElGamalCiphertextVar<N, S>,
+    /// Encryption of the protocol note's randomness under the protocol's key
+    pub randomness_protocol_ciphertext: AuthenticatedElGamalCiphertextVar<N, S>,
+}
+
+impl<
+        'a,
+        const SCALAR_BITS: usize,
+        N: 'a + MpcNetwork + Send,
+        S: 'a + SharedValueSource<Scalar>,
+    > MultiProverCircuit<'a, N, S> for ValidMatchEncryptionMpc<'a, SCALAR_BITS, N, S>
+{
+    type Statement = ValidMatchEncryptionStatement;
+    type Witness = ValidMatchEncryptionMpcWitness<N, S>;
+    type WitnessCommitment = ValidMatchEncryptionCommitmentShared<N, S>;
+
+    const BP_GENS_CAPACITY: usize = 65536;
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: MpcProver<'a, '_, '_, N, S>,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<(Self::WitnessCommitment, SharedR1CSProof<N, S>), ProverError> {
+        // Commit to the witness in the order of the single-prover circuit; each party's
+        // fee and randomness hash are committed by the party that holds them
+        let mut rng = OsRng {};
+        let (match_res_var, match_res_comm) = witness
+            .match_res
+            .commit(0 /* owning_party */, &mut rng, &mut prover)
+            .map_err(ProverError::Mpc)?;
+        let (party0_fee_var, party0_fee_comm) = witness
+            .my_fee
+            .commit(0 /* owning_party */, &mut rng, &mut prover)
+            .map_err(ProverError::Mpc)?;
+        let (party1_fee_var, party1_fee_comm) = witness
+            .my_fee
+            .commit(1 /* owning_party */, &mut rng, &mut prover)
+            .map_err(ProverError::Mpc)?;
+        let (party0_randomness_hash_var, party0_randomness_hash_comm) = witness
+            .my_randomness_hash
+            .commit(0 /* owning_party */, &mut rng, &mut prover)
+            .map_err(ProverError::Mpc)?;
+        let (party1_randomness_hash_var, party1_randomness_hash_comm) = witness
+            .my_randomness_hash
+            .commit(1 /* owning_party */, &mut rng, &mut prover)
+            .map_err(ProverError::Mpc)?;
+
+        let mut note_vars = Vec::with_capacity(5);
+        let mut note_comms = Vec::with_capacity(5);
+        for note in [
+            &witness.party0_note,
+            &witness.party1_note,
+            &witness.relayer0_note,
+            &witness.relayer1_note,
+            &witness.protocol_note,
+        ] {
+            let (note_var, note_comm) = note
+                .commit(0 /* owning_party */, &mut rng, &mut prover)
+                .map_err(ProverError::Mpc)?;
+            note_vars.push(note_var);
+            note_comms.push(note_comm);
+        }
+
+        let randomness_blinders = (0..witness.elgamal_randomness.len())
+            .map(|_| Scalar::random(&mut rng))
+            .collect_vec();
+        let (randomness_comms, randomness_vars) = prover
+            .batch_commit_preshared(&witness.elgamal_randomness, &randomness_blinders)
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+
+        let mut note_vars = note_vars.into_iter();
+        let witness_var = ValidMatchEncryptionMpcWitnessVar {
+            match_res: match_res_var,
+            party0_fee: party0_fee_var,
+            party1_fee: party1_fee_var,
+            party0_randomness_hash: party0_randomness_hash_var,
+            party1_randomness_hash: party1_randomness_hash_var,
+            party0_note: note_vars.next().unwrap(),
+            party1_note: note_vars.next().unwrap(),
+            relayer0_note: note_vars.next().unwrap(),
+            relayer1_note: note_vars.next().unwrap(),
+            protocol_note: note_vars.next().unwrap(),
+            elgamal_randomness: randomness_vars,
+        };
+        let statement_var = Self::commit_statement(&statement, &mut prover);
+
+        // Apply the constraints
+        Self::circuit(witness_var, statement_var, fabric, &mut prover)?;
+
+        // Prove the statement
+        let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
+        let proof = prover.prove(&bp_gens).map_err(ProverError::Collaborative)?;
+
+        let mut note_comms = note_comms.into_iter();
+        Ok((
+            ValidMatchEncryptionCommitmentShared {
+                match_res: match_res_comm,
+                party0_fee: party0_fee_comm,
+                party1_fee: party1_fee_comm,
+                party0_randomness_hash: party0_randomness_hash_comm,
+                party1_randomness_hash: party1_randomness_hash_comm,
+                party0_note: note_comms.next().unwrap(),
+                party1_note: note_comms.next().unwrap(),
+                relayer0_note: note_comms.next().unwrap(),
+                relayer1_note: note_comms.next().unwrap(),
+                protocol_note: note_comms.next().unwrap(),
+                elgamal_randomness: randomness_comms,
+            },
+            proof,
+        ))
+    }
+
+    fn verify(
+        witness_commitment: ValidMatchEncryptionWitnessCommitment,
+        statement: Self::Statement,
+        proof: R1CSProof,
+        verifier: Verifier,
+    ) -> Result<(), VerifierError> {
+        // The constraints are identical to the single-prover circuit's
+        <ValidMatchEncryption<SCALAR_BITS> as SingleProverCircuit>::verify(
+            witness_commitment,
+            statement,
+            proof,
+            verifier,
+        )
+    }
+}
+
 #[cfg(test)]
 mod valid_match_encryption_tests {
 
@@ -21,13 +21,16 @@ use mpc_bulletproof::{
     BulletproofGens,
 };
 use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     errors::{ProverError, VerifierError},
     mpc_gadgets::poseidon::PoseidonSpongeParameters,
     types::{
+        deserialize_array,
         note::{CommittedNote, Note, NoteType, NoteVar},
         order::OrderVar,
+        serialize_array,
         wallet::{CommittedWallet, Wallet, WalletVar},
     },
     zk_gadgets::{
@@ -316,7 +319,7 @@ where
 }
 
 /// The witness type for the VALID SETTLE circuit
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ValidSettleWitness<
     const MAX_BALANCES: usize,
     const MAX_ORDERS: usize,
@@ -472,7 +475,7 @@ where
 }
 
 /// The statement type for VALID SETTLE
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ValidSettleStatement<
     const MAX_BALANCES: usize,
     const MAX_ORDERS: usize,
@@ -484,6 +487,10 @@ pub struct ValidSettleStatement<
     /// The commitment to the updated wallet
     pub post_wallet_commit: Scalar,
     /// The encryption of the updated wallet
+    #[serde(
+        serialize_with = "serialize_array",
+        deserialize_with = "deserialize_array"
+    )]
     pub post_wallet_ciphertext:
         [ElGamalCiphertext; 2 * MAX_BALANCES + 8 * MAX_ORDERS + 4 * MAX_FEES + 5],
     /// The wallet spend nullifier of the pre-wallet
@@ -619,22 +626,34 @@ where
 
     const BP_GENS_CAPACITY: usize = 32768;
 
-    fn prove(
+    fn apply_constraints(
         witness: Self::Witness,
         statement: Self::Statement,
-        mut prover: Prover,
-    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        prover: &mut Prover,
+    ) -> Result<Self::WitnessCommitment, ProverError> {
         // Commit to the witness and statement
         let mut rng = OsRng {};
-        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, &mut prover).unwrap();
-        let (statement_var, _) = statement.commit_prover(&mut rng, &mut prover).unwrap();
+        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, prover).unwrap();
+        let (statement_var, _) = statement.commit_prover(&mut rng, prover).unwrap();
 
         // Apply the constraints
-        Self::circuit(witness_var, statement_var, &mut prover).map_err(ProverError::R1CS)?;
+        Self::circuit(witness_var, statement_var, prover).map_err(ProverError::R1CS)?;
+
+        Ok(witness_comm)
+    }
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: Prover,
+    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        // Commit to the witness and statement and apply the constraints
+        let witness_comm = Self::apply_constraints(witness, statement, &mut prover)?;
 
         // Prove the statement
         let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
         let proof = prover.prove(&bp_gens).map_err(ProverError::R1CS)?;
+
         Ok((witness_comm, proof))
     }
 
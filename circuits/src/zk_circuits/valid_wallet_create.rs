@@ -223,21 +223,31 @@ where
 
     const BP_GENS_CAPACITY: usize = 10000;
 
-    fn prove(
+    fn apply_constraints(
         witness: Self::Witness,
         statement: Self::Statement,
-        mut prover: Prover,
-    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        prover: &mut Prover,
+    ) -> Result<Self::WitnessCommitment, ProverError> {
         // Commit to the witness
         let mut rng = OsRng {};
-        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, &mut prover).unwrap();
+        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, prover).unwrap();
 
         // Commit to the statement
         let wallet_commitment_var = prover.commit_public(statement.wallet_commitment);
 
         // Apply the constraints
-        Self::circuit(&mut prover, wallet_commitment_var, witness_var)
-            .map_err(ProverError::R1CS)?;
+        Self::circuit(prover, wallet_commitment_var, witness_var).map_err(ProverError::R1CS)?;
+
+        Ok(witness_comm)
+    }
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: Prover,
+    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        // Commit to the witness and statement and apply the constraints
+        let witness_comm = Self::apply_constraints(witness, statement, &mut prover)?;
 
         // Prove the statement
         let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
@@ -286,6 +296,7 @@ mod test_valid_wallet_create {
     use crate::{
         test_helpers::bulletproof_prove_and_verify, types::fee::Fee,
         zk_circuits::test_helpers::PUBLIC_KEYS, zk_gadgets::fixed_point::FixedPoint,
+        SingleProverCircuit,
     };
 
     use super::{
@@ -367,4 +378,35 @@ mod test_valid_wallet_create {
         >(witness, statement);
         assert!(res.is_ok());
     }
+
+    /// Tests that a dry run sizes the circuit and checks the witness without proving
+    #[test]
+    fn test_constraint_profile() {
+        let mut rng = OsRng {};
+        let fees = (0..MAX_FEES).map(|_| random_fee(&mut rng)).collect_vec();
+
+        let witness = ValidWalletCreateWitness {
+            fees: fees.try_into().unwrap(),
+            keys: *PUBLIC_KEYS,
+            wallet_randomness: Scalar::random(&mut rng),
+        };
+        let statement = ValidWalletCreateStatement {
+            wallet_commitment: compute_commitment(&witness),
+        };
+
+        type Circuit = ValidWalletCreate<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
+        let profile = Circuit::constraint_profile(witness.clone(), statement).unwrap();
+        assert!(profile.satisfied);
+        assert!(profile.multipliers > 0);
+        assert!(profile.multipliers <= profile.bp_gens_capacity);
+
+        // A commitment to a different wallet is not satisfied, but the circuit is the same size
+        let statement = ValidWalletCreateStatement {
+            wallet_commitment: Scalar::random(&mut rng),
+        };
+        let invalid_profile = Circuit::constraint_profile(witness.clone(), statement).unwrap();
+        assert!(!invalid_profile.satisfied);
+        assert_eq!(invalid_profile.multipliers, profile.multipliers);
+        assert!(!Circuit::check_satisfiability(witness, statement).unwrap());
+    }
 }
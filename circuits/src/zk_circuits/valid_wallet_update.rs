@@ -18,6 +18,7 @@ use mpc_bulletproof::{
     BulletproofGens,
 };
 use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     errors::{ProverError, VerifierError},
@@ -169,6 +170,7 @@ where
     ///     2. The internal and external transfers are applied properly and result
     ///        in non-negative balances
     ///     3. The user has the funds to cover the transfers
+    ///     4. The transfer amounts are non-negative 64 bit values
     pub(crate) fn validate_transfers<CS: RandomizableConstraintSystem>(
         new_wallet: &WalletVar<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
         old_wallet: &WalletVar<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
@@ -179,6 +181,17 @@ where
         // Enforce all balance mints to be unique or 0
         Self::constrain_unique_balance_mints(new_wallet, cs);
 
+        // Bound the transfer amounts; a transfer of a negative amount would otherwise
+        // move funds in the opposite direction of the one constrained below
+        GreaterThanEqZeroGadget::<64 /* bitwidth */>::constrain_greater_than_zero(
+            internal_transfer.1,
+            cs,
+        );
+        GreaterThanEqZeroGadget::<64 /* bitwidth */>::constrain_greater_than_zero(
+            external_transfer.1,
+            cs,
+        );
+
         // Apply the transfers to the old balances, ensure that the new balances are properly computed
 
         // The external transfer term; negate the amount if the direction is 1 (withdraw)
@@ -394,7 +407,7 @@ where
 }
 
 /// The witness type for VALID WALLET UPDATE
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ValidWalletUpdateWitness<
     const MAX_BALANCES: usize,
     const MAX_ORDERS: usize,
@@ -522,7 +535,7 @@ where
 }
 
 /// The statement type for VALID WALLET UPDATE
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ValidWalletUpdateStatement {
     /// The timestamp (user set) of the request, used for order timestamping
     pub timestamp: Scalar,
@@ -552,14 +565,14 @@ where
 
     const BP_GENS_CAPACITY: usize = 32768;
 
-    fn prove(
+    fn apply_constraints(
         witness: Self::Witness,
         statement: Self::Statement,
-        mut prover: Prover,
-    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        prover: &mut Prover,
+    ) -> Result<Self::WitnessCommitment, ProverError> {
         // Commit to the witness
         let mut rng = OsRng {};
-        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, &mut prover).unwrap();
+        let (witness_var, witness_comm) = witness.commit_prover(&mut rng, prover).unwrap();
 
         // Commit to the statement
         let timestamp_var = prover.commit_public(statement.timestamp);
@@ -586,10 +599,21 @@ where
                 external_transfer_volume,
                 external_transfer_direction,
             ),
-            &mut prover,
+            prover,
         )
         .map_err(ProverError::R1CS)?;
 
+        Ok(witness_comm)
+    }
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: Prover,
+    ) -> Result<(Self::WitnessCommitment, R1CSProof), ProverError> {
+        // Commit to the witness and statement and apply the constraints
+        let witness_comm = Self::apply_constraints(witness, statement, &mut prover)?;
+
         // Prove the statement
         let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
         let proof = prover.prove(&bp_gens).map_err(ProverError::R1CS)?;
@@ -1752,4 +1776,76 @@ mod valid_wallet_update_tests {
 
         assert!(!prover.constraints_satisfied());
     }
+
+    /// Tests that an internal transfer of a negative amount, which would credit the
+    /// sender's balance, is rejected by the transfer bounds
+    #[test]
+    fn test_invalid_internal_transfer_negative_amount() {
+        let mut rng = OsRng {};
+
+        let timestamp = TIMESTAMP + 1;
+        let mut initial_wallet = INITIAL_WALLET.clone();
+        let mut new_wallet = initial_wallet.clone();
+
+        new_wallet.orders[1].timestamp = timestamp;
+        new_wallet.randomness = initial_wallet.randomness + Scalar::from(2u32);
+        initial_wallet.orders[1] = Order::default();
+
+        // Invalid, the sender's balance increases by the negated transfer amount
+        let internal_transfer_mint = new_wallet.balances[1].mint.clone();
+        new_wallet.balances[1].amount += 1;
+
+        // Create a mock Merkle opening for the old wallet
+        let random_index = rng.next_u32() % (2u32.pow(MERKLE_HEIGHT.try_into().unwrap()));
+        let (mock_root, mock_opening, mock_opening_indices) = create_wallet_opening(
+            &initial_wallet,
+            MERKLE_HEIGHT,
+            random_index as usize,
+            &mut rng,
+        );
+
+        let witness = ValidWalletUpdateWitness {
+            wallet1: initial_wallet.clone(),
+            wallet2: new_wallet.clone(),
+            wallet1_opening: MerkleOpening {
+                elems: mock_opening,
+                indices: mock_opening_indices,
+            },
+            internal_transfer: (biguint_to_scalar(&internal_transfer_mint), Scalar::zero()),
+        };
+
+        let statement = ValidWalletUpdateStatement {
+            timestamp: Scalar::from(timestamp),
+            pk_root: new_wallet.keys.pk_root,
+            new_wallet_commitment: Scalar::zero(),
+            wallet_spend_nullifier: Scalar::zero(),
+            wallet_match_nullifier: Scalar::zero(),
+            merkle_root: mock_root,
+            external_transfer: (Scalar::zero(), Scalar::zero(), Scalar::zero()),
+        };
+
+        let mut prover_transcript = Transcript::new("test".as_bytes());
+        let pc_gens = PedersenGens::default();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+        let (mut witness_var, statement_vars) =
+            commit_witness_statement(witness, statement, &mut prover);
+
+        // Transfer an amount of -1
+        let negative_one = prover.commit_public(Scalar::one().neg());
+        witness_var.internal_transfer.1 = negative_one;
+
+        ValidWalletUpdate::validate_transfers(
+            &witness_var.wallet2,
+            &witness_var.wallet1,
+            witness_var.internal_transfer,
+            (
+                statement_vars[6].to_owned(),
+                statement_vars[7].to_owned(),
+                statement_vars[8].to_owned(),
+            ),
+            &mut prover,
+        );
+
+        assert!(!prover.constraints_satisfied());
+    }
 }
@@ -13,7 +13,7 @@ use mpc_bulletproof::r1cs::{
 };
 use mpc_bulletproof::r1cs_mpc::{
     MpcConstraintSystem, MpcLinearCombination, MpcProver, MpcRandomizableConstraintSystem,
-    R1CSError, SharedR1CSProof,
+    MpcVariable, R1CSError, SharedR1CSProof,
 };
 use mpc_bulletproof::BulletproofGens;
 use mpc_ristretto::authenticated_ristretto::AuthenticatedCompressedRistretto;
@@ -27,9 +27,13 @@ use crate::errors::{MpcError, ProverError, VerifierError};
 use crate::mpc::SharedFabric;
 use crate::{MultiProverCircuit, SingleProverCircuit};
 
-use super::bits::ToBitsGadget;
-use super::comparators::{EqZeroGadget, LessThanGadget};
-use super::select::CondSelectGadget;
+use super::bits::{MultiproverToBitsGadget, ToBitsGadget};
+use super::comparators::{EqZeroGadget, LessThanGadget, MultiproverEqZeroGadget};
+use super::select::{CondSelectGadget, MultiproverCondSelectGadget};
+
+/// The bitlength of a sum of exponent bits; the exponent of a private
+/// exponentiation has fewer than 2^8 bits
+const BIT_SUM_BITS: usize = 8;
 
 // -------------------------
 // | Single Prover Gadgets |
@@ -520,6 +524,223 @@ impl<'a, N: MpcNetwork + Send, S: SharedValueSource<Scalar>> MultiProverCircuit<
     }
 }
 
+/// A multiprover implementation of the private exponentiation gadget; computes x^\alpha
+/// for a shared exponent
+///
+/// Applies the same constraints as the single-prover `PrivateExpGadget`
+pub struct MultiproverPrivateExpGadget<
+    'a,
+    const ALPHA_BITS: usize,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<'a, const ALPHA_BITS: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiproverPrivateExpGadget<'a, ALPHA_BITS, N, S>
+{
+    /// Compute x^\alpha where `x` is public and alpha is shared
+    pub fn exp_private_fixed_base<L, CS>(
+        x: Scalar,
+        alpha: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcLinearCombination<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        let alpha_bits =
+            MultiproverToBitsGadget::<'a, ALPHA_BITS, N, S>::to_bits(alpha, fabric.clone(), cs)?;
+        Self::exp_private_fixed_base_impl(x, &alpha_bits, fabric, cs)
+    }
+
+    /// Compute x^\alpha where both x and alpha are shared values
+    pub fn exp_private<L, CS>(
+        x: L,
+        alpha: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcLinearCombination<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        let alpha_bits =
+            MultiproverToBitsGadget::<'a, ALPHA_BITS, N, S>::to_bits(alpha, fabric.clone(), cs)?;
+        Self::exp_private_impl(x.into(), &alpha_bits, fabric, cs)
+    }
+
+    /// An implementation helper for fixed base exponentiation that assumes a bit decomposition of
+    /// the exponent is passed in
+    fn exp_private_fixed_base_impl<CS>(
+        x: Scalar,
+        alpha_bits: &[MpcLinearCombination<N, S>],
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcLinearCombination<N, S>, ProverError>
+    where
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        let one = MpcLinearCombination::from_scalar(Scalar::one(), fabric.0.clone());
+        if alpha_bits.is_empty() {
+            return Ok(one);
+        }
+
+        // Check whether all bits are zero
+        let alpha_zero = Self::all_bits_zero(alpha_bits, fabric.clone(), cs)?;
+        let is_odd = alpha_bits[0].clone();
+
+        // Recursive call
+        let recursive_result =
+            Self::exp_private_fixed_base_impl(x, &alpha_bits[1..], fabric.clone(), cs)?;
+        let (_, _, recursive_doubled) = cs
+            .multiply(&recursive_result, &recursive_result)
+            .map_err(ProverError::Collaborative)?;
+
+        // If the value is odd multiply by an extra copy of `x`
+        let recursive_plus_one = x * &recursive_doubled;
+
+        // Mux between the two results depending on whether the current exponent is odd or even
+        let odd_bit_selection = MultiproverCondSelectGadget::select(
+            recursive_plus_one,
+            recursive_doubled.into(),
+            is_odd,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Mask the value of the output if alpha is already zero
+        MultiproverCondSelectGadget::select(one, odd_bit_selection, alpha_zero.into(), fabric, cs)
+    }
+
+    /// An implementation helper that assumes a bit decomposition of the exponent
+    fn exp_private_impl<CS>(
+        x: MpcLinearCombination<N, S>,
+        alpha_bits: &[MpcLinearCombination<N, S>],
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcLinearCombination<N, S>, ProverError>
+    where
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        let one = MpcLinearCombination::from_scalar(Scalar::one(), fabric.0.clone());
+        if alpha_bits.is_empty() {
+            return Ok(one);
+        }
+
+        // Check whether all bits are zero
+        let alpha_zero = Self::all_bits_zero(alpha_bits, fabric.clone(), cs)?;
+        let is_odd = alpha_bits[0].clone();
+
+        // Recursive call
+        let recursive_result =
+            Self::exp_private_impl(x.clone(), &alpha_bits[1..], fabric.clone(), cs)?;
+        let (_, _, recursive_doubled) = cs
+            .multiply(&recursive_result, &recursive_result)
+            .map_err(ProverError::Collaborative)?;
+
+        // If the value is odd multiply by an extra copy of `x`
+        let (_, _, recursive_plus_one) = cs
+            .multiply(&x, &recursive_doubled.clone().into())
+            .map_err(ProverError::Collaborative)?;
+
+        // Mux between the two results depending on whether the current exponent is odd or even
+        let odd_bit_selection = MultiproverCondSelectGadget::select(
+            recursive_plus_one.into(),
+            recursive_doubled.into(),
+            is_odd,
+            fabric.clone(),
+            cs,
+        )?;
+
+        // Mask the value of the output if alpha is already zero
+        MultiproverCondSelectGadget::select(one, odd_bit_selection, alpha_zero.into(), fabric, cs)
+    }
+
+    /// Returns whether all the given bits are zero, it is assumed that these values
+    /// are constrained to be binary elsewhere in the circuit
+    fn all_bits_zero<CS>(
+        bits: &[MpcLinearCombination<N, S>],
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError>
+    where
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        // Add all the bits
+        let mut bit_sum = MpcLinearCombination::default();
+        for bit in bits.iter() {
+            bit_sum += bit.clone();
+        }
+
+        MultiproverEqZeroGadget::<'a, BIT_SUM_BITS, N, S>::eq_zero(bit_sum, fabric, cs)
+    }
+}
+
+impl<'a, const ALPHA_BITS: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiProverCircuit<'a, N, S> for MultiproverPrivateExpGadget<'a, ALPHA_BITS, N, S>
+{
+    /// Expected output
+    type Statement = Scalar;
+    /// (x, \alpha)
+    type Witness = (AuthenticatedScalar<N, S>, AuthenticatedScalar<N, S>);
+    type WitnessCommitment = Vec<AuthenticatedCompressedRistretto<N, S>>;
+
+    const BP_GENS_CAPACITY: usize = 4096;
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: MpcProver<'a, '_, '_, N, S>,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<
+        (
+            Vec<AuthenticatedCompressedRistretto<N, S>>,
+            SharedR1CSProof<N, S>,
+        ),
+        ProverError,
+    > {
+        // Commit to `x` and `\alpha`
+        let mut rng = OsRng {};
+        let (x_comm, x_var) = prover
+            .commit_preshared(&witness.0, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+        let (alpha_comm, alpha_var) = prover
+            .commit_preshared(&witness.1, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+
+        // Commit to the expected output
+        let (_, expected_out) = prover.commit_public(statement);
+
+        // Apply the constraints
+        let res = Self::exp_private(x_var, alpha_var, fabric, &mut prover)?;
+        prover.constrain(res - expected_out);
+
+        // Prove the statement
+        let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
+        let proof = prover.prove(&bp_gens).map_err(ProverError::Collaborative)?;
+
+        Ok((vec![x_comm, alpha_comm], proof))
+    }
+
+    fn verify(
+        witness_commitments: Vec<CompressedRistretto>,
+        statement: Self::Statement,
+        proof: R1CSProof,
+        verifier: Verifier,
+    ) -> Result<(), VerifierError> {
+        PrivateExpGadget::<ALPHA_BITS>::verify(
+            (witness_commitments[0], witness_commitments[1]),
+            statement,
+            proof,
+            verifier,
+        )
+    }
+}
+
 #[cfg(test)]
 mod arithmetic_tests {
     use crypto::fields::{bigint_to_scalar, biguint_to_scalar, scalar_to_biguint};
@@ -1,17 +1,25 @@
 //! Groups logic for computing wallet commitments and nullifiers inside of a circuit
 
+use std::marker::PhantomData;
+
 use curve25519_dalek::scalar::Scalar;
 use mpc_bulletproof::{
     r1cs::{LinearCombination, RandomizableConstraintSystem, Variable},
-    r1cs_mpc::R1CSError,
+    r1cs_mpc::{MpcLinearCombination, MpcRandomizableConstraintSystem, MpcVariable, R1CSError},
 };
+use mpc_ristretto::{beaver::SharedValueSource, network::MpcNetwork};
 
 use crate::{
+    errors::ProverError,
+    mpc::SharedFabric,
     mpc_gadgets::poseidon::PoseidonSpongeParameters,
-    types::{note::NoteVar, wallet::WalletVar},
+    types::{
+        note::{AuthenticatedNoteVar, NoteVar},
+        wallet::WalletVar,
+    },
 };
 
-use super::poseidon::PoseidonHashGadget;
+use super::poseidon::{MultiproverPoseidonHashGadget, PoseidonHashGadget};
 
 /// A gadget for computing wallet commitments
 #[derive(Clone, Debug)]
@@ -120,6 +128,37 @@ impl NoteCommitmentGadget {
     }
 }
 
+/// A multiprover version of the note commitment gadget
+pub struct MultiproverNoteCommitmentGadget<
+    'a,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<'a, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiproverNoteCommitmentGadget<'a, N, S>
+{
+    /// Computes a commitment to a given note
+    pub fn note_commit<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        note: &AuthenticatedNoteVar<N, S>,
+        recipient_pub_key: MpcVariable<N, S>,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcLinearCombination<N, S>, ProverError> {
+        // Create a new hash gadget
+        let hash_params = PoseidonSpongeParameters::default();
+        let mut hasher = MultiproverPoseidonHashGadget::new(hash_params, fabric);
+
+        let mut hash_input: Vec<MpcLinearCombination<N, S>> = note.clone().into();
+        hash_input.push(recipient_pub_key.into());
+        hasher.batch_absorb(&hash_input, cs)?;
+        hasher.squeeze(cs)
+    }
+}
+
 /// A gadget for computing the nullifier of a wallet
 #[derive(Clone, Debug)]
 pub struct NullifierGadget {}
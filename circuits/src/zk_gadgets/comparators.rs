@@ -9,17 +9,26 @@ use mpc_bulletproof::{
         ConstraintSystem, LinearCombination, Prover, R1CSProof, RandomizableConstraintSystem,
         Variable, Verifier,
     },
-    r1cs_mpc::{MpcLinearCombination, MpcRandomizableConstraintSystem},
+    r1cs_mpc::{
+        MpcLinearCombination, MpcProver, MpcRandomizableConstraintSystem, MpcVariable,
+        SharedR1CSProof,
+    },
     BulletproofGens,
 };
-use mpc_ristretto::{beaver::SharedValueSource, network::MpcNetwork};
+use mpc_ristretto::{
+    authenticated_ristretto::AuthenticatedCompressedRistretto,
+    authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource, network::MpcNetwork,
+};
 use rand_core::OsRng;
 
 use crate::{
-    errors::{ProverError, VerifierError},
+    errors::{MpcError, ProverError, VerifierError},
     mpc::SharedFabric,
-    mpc_gadgets::bits::{scalar_to_bits_le, to_bits_le},
-    SingleProverCircuit, POSITIVE_SCALAR_MAX_BITS,
+    mpc_gadgets::{
+        bits::{scalar_to_bits_le, to_bits_le},
+        comparators::eq_zero,
+    },
+    MultiProverCircuit, SingleProverCircuit, POSITIVE_SCALAR_MAX_BITS,
 };
 
 /// A gadget that returns whether a value is equal to zero
@@ -136,6 +145,167 @@ impl EqGadget {
     }
 }
 
+/// A multiprover version of the equal zero gadget
+///
+/// `D` is the bitlength of the input; whether the input is zero is computed in the
+/// MPC from its `D` low bits, so the input must be zero mod 2^D only if it is zero
+pub struct MultiproverEqZeroGadget<
+    'a,
+    const D: usize,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiproverEqZeroGadget<'a, D, N, S>
+{
+    /// Computes whether the given input is equal to zero
+    ///
+    /// Applies the same constraints as the single-prover `EqZeroGadget`; the
+    /// output and the inverse are computed in the MPC so that neither party learns them
+    pub fn eq_zero<L, CS>(
+        val: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        // Compute the output and the inverse of the value in the MPC
+        let val_lc: MpcLinearCombination<N, S> = val.into();
+        let val_eval = cs.eval(&val_lc).map_err(ProverError::Collaborative)?;
+        let is_zero = eq_zero::<D, N, S>(&val_eval, fabric.clone()).map_err(ProverError::Mpc)?;
+        let inverse = Self::masked_inverse(&val_eval, &is_zero, fabric.clone())?;
+
+        // Constrain the inverse to be computed correctly and such that
+        //  is_zero == 1 - inv * val
+        let is_zero_var = cs.allocate(Some(is_zero)).map_err(ProverError::R1CS)?;
+        let inv_var = cs.allocate(Some(inverse)).map_err(ProverError::R1CS)?;
+        let (_, _, val_times_inv) = cs
+            .multiply(&val_lc, &inv_var.into())
+            .map_err(ProverError::Collaborative)?;
+        cs.constrain(&is_zero_var - MpcVariable::one(fabric.0.clone()) + val_times_inv);
+
+        // Constrain the input times the output to equal zero, see `EqZeroGadget`
+        let (_, _, in_times_out) = cs
+            .multiply(&val_lc, &is_zero_var.clone().into())
+            .map_err(ProverError::Collaborative)?;
+        cs.constrain(in_times_out.into());
+
+        Ok(is_zero_var)
+    }
+
+    /// Computes the inverse of a shared value, or zero if the value is zero
+    ///
+    /// The value is shifted by `is_zero` so that it is always invertible, and blinded
+    /// by a random shared scalar before it is opened; the opened value is uniformly
+    /// distributed over the non-zero scalars
+    fn masked_inverse(
+        val: &AuthenticatedScalar<N, S>,
+        is_zero: &AuthenticatedScalar<N, S>,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<AuthenticatedScalar<N, S>, ProverError> {
+        let blinder = fabric.borrow_fabric().allocate_random_shared_scalar();
+        let blinded_value = &(val + is_zero) * &blinder;
+        let blinded_open = blinded_value.open_and_authenticate().map_err(|err| {
+            ProverError::Mpc(MpcError::OpeningError(format!(
+                "error opening blinded value while inverting: {:?}",
+                err
+            )))
+        })?;
+
+        let shifted_inverse = blinded_open.to_scalar().invert() * &blinder;
+        Ok(&shifted_inverse * &(Scalar::one() - is_zero))
+    }
+}
+
+impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiProverCircuit<'a, N, S> for MultiproverEqZeroGadget<'a, D, N, S>
+{
+    type Statement = bool;
+    type Witness = AuthenticatedScalar<N, S>;
+    type WitnessCommitment = AuthenticatedCompressedRistretto<N, S>;
+
+    const BP_GENS_CAPACITY: usize = 32;
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: MpcProver<'a, '_, '_, N, S>,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<
+        (
+            AuthenticatedCompressedRistretto<N, S>,
+            SharedR1CSProof<N, S>,
+        ),
+        ProverError,
+    > {
+        // Commit to the witness
+        let mut rng = OsRng {};
+        let (witness_comm, witness_var) = prover
+            .commit_preshared(&witness, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+
+        // Commit to the statement
+        let (_, expected_var) = prover.commit_public(Scalar::from(statement as u8));
+
+        // Test equality to zero and constrain this to be expected
+        let eq_zero: MpcLinearCombination<N, S> =
+            Self::eq_zero(witness_var, fabric, &mut prover)?.into();
+        prover.constrain(eq_zero - expected_var);
+
+        // Prove the statement
+        let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
+        let proof = prover.prove(&bp_gens).map_err(ProverError::Collaborative)?;
+
+        Ok((witness_comm, proof))
+    }
+
+    fn verify(
+        witness_commitment: CompressedRistretto,
+        statement: Self::Statement,
+        proof: R1CSProof,
+        verifier: Verifier,
+    ) -> Result<(), VerifierError> {
+        EqZeroGadget::verify(witness_commitment, statement, proof, verifier)
+    }
+}
+
+/// A multiprover version of the equality gadget
+///
+/// `D` is the bitlength of the difference of the inputs
+pub struct MultiproverEqGadget<
+    'a,
+    const D: usize,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiproverEqGadget<'a, D, N, S>
+{
+    /// Computes a == b; returns 1 if true, otherwise 0
+    pub fn eq<L, CS>(
+        a: L,
+        b: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        MultiproverEqZeroGadget::<'a, D, N, S>::eq_zero(a.into() - b.into(), fabric, cs)
+    }
+}
+
 /// Returns 1 if a_i = b_i for all i, otherwise 0
 #[derive(Clone, Debug)]
 pub struct EqVecGadget {}
@@ -325,6 +495,23 @@ pub struct MultiproverGreaterThanEqZeroGadget<
 impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
     MultiproverGreaterThanEqZeroGadget<'a, D, N, S>
 {
+    /// Evaluate the condition x >= 0; returns 1 if true, otherwise 0
+    pub fn greater_than_zero<L, CS>(
+        x: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        // If we can reconstruct the value without the highest bit, the value is non-negative
+        // The difference below is zero mod 2^D only if it is zero: it is either a negated
+        // multiple of 2^D, or congruent to the (odd) field modulus mod 2^D
+        let bit_reconstructed = Self::bit_decompose_reconstruct(x.clone(), fabric.clone(), cs)?;
+        MultiproverEqZeroGadget::<'a, D, N, S>::eq_zero(bit_reconstructed - x.into(), fabric, cs)
+    }
+
     /// Constrains the input value to be greater than or equal to zero implicitly
     /// by bit-decomposing the value and re-composing it thereafter
     pub fn constrain_greater_than_zero<L, CS>(
@@ -502,6 +689,24 @@ pub struct MultiproverGreaterThanEqGadget<
 impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
     MultiproverGreaterThanEqGadget<'a, D, N, S>
 {
+    /// Evaluates the comparator a >= b; returns 1 if true, otherwise 0
+    pub fn greater_than_eq<L, CS>(
+        a: L,
+        b: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        MultiproverGreaterThanEqZeroGadget::<'a, D, N, S>::greater_than_zero(
+            a.into() - b.into(),
+            fabric,
+            cs,
+        )
+    }
+
     /// Constrain the relation a >= b
     pub fn constrain_greater_than_eq<L, CS>(
         a: L,
@@ -521,6 +726,121 @@ impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Sc
     }
 }
 
+/// The witness for the statement a >= b in a multiprover setting; used for testing
+///
+/// Here, both `a` and `b` are shared variables
+#[allow(missing_docs, clippy::missing_docs_in_private_items)]
+#[derive(Clone, Debug)]
+pub struct MultiproverGreaterThanEqWitness<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    pub a: AuthenticatedScalar<N, S>,
+    pub b: AuthenticatedScalar<N, S>,
+}
+
+impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiProverCircuit<'a, N, S> for MultiproverGreaterThanEqGadget<'a, D, N, S>
+{
+    type Statement = ();
+    type Witness = MultiproverGreaterThanEqWitness<N, S>;
+    type WitnessCommitment = Vec<AuthenticatedCompressedRistretto<N, S>>;
+
+    const BP_GENS_CAPACITY: usize = 64;
+
+    fn prove(
+        witness: Self::Witness,
+        _: Self::Statement,
+        mut prover: MpcProver<'a, '_, '_, N, S>,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<
+        (
+            Vec<AuthenticatedCompressedRistretto<N, S>>,
+            SharedR1CSProof<N, S>,
+        ),
+        ProverError,
+    > {
+        // Commit to the witness
+        let mut rng = OsRng {};
+        let (a_comm, a_var) = prover
+            .commit_preshared(&witness.a, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+        let (b_comm, b_var) = prover
+            .commit_preshared(&witness.b, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+
+        // Apply the constraints
+        Self::constrain_greater_than_eq(a_var, b_var, fabric, &mut prover)?;
+
+        // Prove the statement
+        let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
+        let proof = prover.prove(&bp_gens).map_err(ProverError::Collaborative)?;
+
+        Ok((vec![a_comm, b_comm], proof))
+    }
+
+    fn verify(
+        witness_commitment: Vec<CompressedRistretto>,
+        statement: Self::Statement,
+        proof: R1CSProof,
+        verifier: Verifier,
+    ) -> Result<(), VerifierError> {
+        GreaterThanEqGadget::<D>::verify(witness_commitment, statement, proof, verifier)
+    }
+}
+
+/// A multiprover variant of the LessThanGadget
+///
+/// `D` is the bitlength of the inputs
+pub struct MultiproverLessThanGadget<
+    'a,
+    const D: usize,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<'a, const D: usize, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
+    MultiproverLessThanGadget<'a, D, N, S>
+{
+    /// Compute the boolean a < b; returns 1 if true, otherwise 0
+    pub fn less_than<L, CS>(
+        a: L,
+        b: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcLinearCombination<N, S>, ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        let a_geq_b: MpcLinearCombination<N, S> =
+            MultiproverGreaterThanEqGadget::<'a, D, N, S>::greater_than_eq(
+                a,
+                b,
+                fabric.clone(),
+                cs,
+            )?
+            .into();
+        Ok(MpcLinearCombination::from_scalar(Scalar::one(), fabric.0) - a_geq_b)
+    }
+
+    /// Constrain a to be less than b
+    pub fn constrain_less_than<L, CS>(
+        a: L,
+        b: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<(), ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        let lt_result = Self::less_than(a, b, fabric.clone(), cs)?;
+        cs.constrain(MpcLinearCombination::from_scalar(Scalar::one(), fabric.0) - lt_result);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod comparators_test {
     use std::{cmp, ops::Neg};
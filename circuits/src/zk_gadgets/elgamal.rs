@@ -1,5 +1,7 @@
 //! Implements the ZK gadgetry for ElGamal encryption
 
+use std::marker::PhantomData;
+
 use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use lazy_static::lazy_static;
 use mpc_bulletproof::{
@@ -7,18 +9,26 @@ use mpc_bulletproof::{
         ConstraintSystem, LinearCombination, Prover, R1CSProof, RandomizableConstraintSystem,
         Variable, Verifier,
     },
-    r1cs_mpc::R1CSError,
+    r1cs_mpc::{
+        MpcLinearCombination, MpcProver, MpcRandomizableConstraintSystem, MpcVariable, R1CSError,
+        SharedR1CSProof,
+    },
     BulletproofGens,
 };
+use mpc_ristretto::{
+    authenticated_ristretto::AuthenticatedCompressedRistretto,
+    authenticated_scalar::AuthenticatedScalar, beaver::SharedValueSource, network::MpcNetwork,
+};
 use rand_core::OsRng;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    errors::{ProverError, VerifierError},
-    CommitProver, CommitVerifier, SingleProverCircuit,
+    errors::{MpcError, ProverError, VerifierError},
+    mpc::SharedFabric,
+    CommitProver, CommitVerifier, MultiProverCircuit, SingleProverCircuit,
 };
 
-use super::arithmetic::PrivateExpGadget;
+use super::arithmetic::{MultiproverPrivateExpGadget, PrivateExpGadget};
 
 lazy_static! {
     /// We use the generator 2 here as per the same field configured in Arkworks:
@@ -66,6 +76,63 @@ impl<const SCALAR_BITS: usize> ElGamalGadget<SCALAR_BITS> {
     }
 }
 
+/// A multiprover version of the ElGamal gadget, encrypts a shared plaintext under
+/// shared randomness
+pub struct MultiproverElGamalGadget<
+    'a,
+    const SCALAR_BITS: usize,
+    N: 'a + MpcNetwork + Send,
+    S: 'a + SharedValueSource<Scalar>,
+> {
+    /// Phantom
+    _phantom: &'a PhantomData<(N, S)>,
+}
+
+impl<
+        'a,
+        const SCALAR_BITS: usize,
+        N: 'a + MpcNetwork + Send,
+        S: 'a + SharedValueSource<Scalar>,
+    > MultiproverElGamalGadget<'a, SCALAR_BITS, N, S>
+{
+    /// Encrypts the given value with the given key and randomness in the constraint system
+    pub fn encrypt<L, CS>(
+        generator: Scalar,
+        randomness: L,
+        plaintext: L,
+        pub_key: L,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<(MpcLinearCombination<N, S>, MpcLinearCombination<N, S>), ProverError>
+    where
+        L: Into<MpcLinearCombination<N, S>> + Clone,
+        CS: MpcRandomizableConstraintSystem<'a, N, S>,
+    {
+        // Take the generator raised to the randomness, so that the secret key holder may
+        // reconstruct the shared secret
+        let ciphertext1 =
+            MultiproverPrivateExpGadget::<'a, SCALAR_BITS, N, S>::exp_private_fixed_base(
+                generator,
+                randomness.clone(),
+                fabric.clone(),
+                cs,
+            )?;
+
+        // Raise the public key to the randomness and use this to encrypt the value
+        let partial_shared_secret =
+            MultiproverPrivateExpGadget::<'a, SCALAR_BITS, N, S>::exp_private(
+                pub_key, randomness, fabric, cs,
+            )?;
+
+        // Blind the plaintext using the shared secret
+        let (_, _, blinded_plaintext) = cs
+            .multiply(&partial_shared_secret, &plaintext.into())
+            .map_err(ProverError::Collaborative)?;
+
+        Ok((ciphertext1, blinded_plaintext.into()))
+    }
+}
+
 /// A type representing an ElGamal ciphertext
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct ElGamalCiphertext {
@@ -89,6 +156,20 @@ impl ElGamalCiphertext {
             encrypted_message: encrypted_message_var,
         }
     }
+
+    /// Commit to the ciphertext as a public input in a multi-prover constraint system
+    pub fn commit_public_shared<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
+        &self,
+        prover: &mut MpcProver<N, S>,
+    ) -> AuthenticatedElGamalCiphertextVar<N, S> {
+        let (_, partial_shared_secret_var) = prover.commit_public(self.partial_shared_secret);
+        let (_, encrypted_message_var) = prover.commit_public(self.encrypted_message);
+
+        AuthenticatedElGamalCiphertextVar {
+            partial_shared_secret: partial_shared_secret_var,
+            encrypted_message: encrypted_message_var,
+        }
+    }
 }
 
 /// An ElGamal ciphertext that has been allocated in a constraint system
@@ -100,6 +181,26 @@ pub struct ElGamalCiphertextVar {
     pub encrypted_message: Variable,
 }
 
+/// An ElGamal ciphertext that has been allocated in a multi-prover constraint system
+#[derive(Debug)]
+pub struct AuthenticatedElGamalCiphertextVar<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The shared secret; the generator raised to the randomness
+    pub partial_shared_secret: MpcVariable<N, S>,
+    /// The encrypted value; the pubkey raised to the randomness, multiplied with the message
+    pub encrypted_message: MpcVariable<N, S>,
+}
+
+impl<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> Clone
+    for AuthenticatedElGamalCiphertextVar<N, S>
+{
+    fn clone(&self) -> Self {
+        Self {
+            partial_shared_secret: self.partial_shared_secret.clone(),
+            encrypted_message: self.encrypted_message.clone(),
+        }
+    }
+}
+
 /// An ElGamal ciphertext that has been committed to by a prover
 #[derive(Clone, Debug)]
 pub struct ElGamalCiphertextCommitment {
@@ -312,6 +413,91 @@ impl<const SCALAR_BITS: usize> SingleProverCircuit for ElGamalGadget<SCALAR_BITS
     }
 }
 
+/// A witness for the ElGamal encryption circuit in which the randomness and the plaintext
+/// are secret shared between the provers
+#[derive(Clone, Debug)]
+pub struct AuthenticatedElGamalWitness<N: MpcNetwork + Send, S: SharedValueSource<Scalar>> {
+    /// The randomness used to create the shared secret
+    pub randomness: AuthenticatedScalar<N, S>,
+    /// The plaintext message encrypted under the key
+    pub plaintext: AuthenticatedScalar<N, S>,
+}
+
+impl<
+        'a,
+        const SCALAR_BITS: usize,
+        N: 'a + MpcNetwork + Send,
+        S: 'a + SharedValueSource<Scalar>,
+    > MultiProverCircuit<'a, N, S> for MultiproverElGamalGadget<'a, SCALAR_BITS, N, S>
+{
+    type Witness = AuthenticatedElGamalWitness<N, S>;
+    type WitnessCommitment = Vec<AuthenticatedCompressedRistretto<N, S>>;
+    type Statement = ElGamalStatement;
+
+    const BP_GENS_CAPACITY: usize = 1024;
+
+    fn prove(
+        witness: Self::Witness,
+        statement: Self::Statement,
+        mut prover: MpcProver<'a, '_, '_, N, S>,
+        fabric: SharedFabric<N, S>,
+    ) -> Result<
+        (
+            Vec<AuthenticatedCompressedRistretto<N, S>>,
+            SharedR1CSProof<N, S>,
+        ),
+        ProverError,
+    > {
+        // Commit to the witness
+        let mut rng = OsRng {};
+        let (randomness_comm, randomness_var) = prover
+            .commit_preshared(&witness.randomness, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+        let (plaintext_comm, plaintext_var) = prover
+            .commit_preshared(&witness.plaintext, Scalar::random(&mut rng))
+            .map_err(|err| ProverError::Mpc(MpcError::SharingError(err.to_string())))?;
+
+        // Commit to the statement
+        let (_, pub_key_var) = prover.commit_public(statement.pub_key);
+        let (_, expected_partial_secret_var) =
+            prover.commit_public(statement.expected_ciphertext.0);
+        let (_, expected_message_var) = prover.commit_public(statement.expected_ciphertext.1);
+
+        // Apply the constraints
+        let res = Self::encrypt(
+            statement.generator,
+            randomness_var,
+            plaintext_var,
+            pub_key_var,
+            fabric,
+            &mut prover,
+        )?;
+
+        prover.constrain(res.0 - expected_partial_secret_var);
+        prover.constrain(res.1 - expected_message_var);
+
+        // Prove the statement
+        let bp_gens = BulletproofGens::new(Self::BP_GENS_CAPACITY, 1 /* party_capacity */);
+        let proof = prover.prove(&bp_gens).map_err(ProverError::Collaborative)?;
+
+        Ok((vec![randomness_comm, plaintext_comm], proof))
+    }
+
+    fn verify(
+        witness_commitments: Vec<CompressedRistretto>,
+        statement: Self::Statement,
+        proof: R1CSProof,
+        verifier: Verifier,
+    ) -> Result<(), VerifierError> {
+        let witness_commitment = ElGamalWitnessCommitment {
+            randomness: witness_commitments[0],
+            plaintext: witness_commitments[1],
+        };
+
+        ElGamalGadget::<SCALAR_BITS>::verify(witness_commitment, statement, proof, verifier)
+    }
+}
+
 #[cfg(test)]
 mod elgamal_tests {
     use crypto::fields::{biguint_to_scalar, scalar_to_biguint};
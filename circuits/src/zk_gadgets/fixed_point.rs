@@ -21,19 +21,31 @@ use mpc_ristretto::{
 };
 use num_bigint::BigUint;
 use rand_core::{CryptoRng, RngCore};
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de::Error as SerdeErr, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
-    errors::MpcError, mpc::SharedFabric, mpc_gadgets::modulo::shift_right, Allocate,
-    AuthenticatedLinkableCommitment, CommitProver, CommitSharedProver, CommitVerifier,
+    errors::{MpcError, ProverError},
+    mpc::SharedFabric,
+    mpc_gadgets::modulo::shift_right,
+    Allocate, AuthenticatedLinkableCommitment, CommitProver, CommitSharedProver, CommitVerifier,
     LinkableCommitment,
 };
 
-use super::{arithmetic::DivRemGadget, comparators::EqGadget};
+use super::{
+    arithmetic::DivRemGadget,
+    comparators::{EqGadget, GreaterThanEqZeroGadget, MultiproverLessThanGadget},
+};
 
 /// The default fixed point decimal precision in bits
 /// i.e. the number of bits allocated to a fixed point's decimal
-pub(crate) const DEFAULT_PRECISION: usize = 32;
+pub const DEFAULT_PRECISION: usize = 32;
+/// The bitlength of the representation of a fixed point divisor and of the division's
+/// remainder, i.e. a divisor's representation must be less than 2^64, so its value less
+/// than 2^(64 - M) = 2^32
+pub(crate) const DIVISION_OPERAND_BITS: usize = 64;
+/// The bitlength of the representation of a fixed point quotient, i.e. a quotient's value
+/// must be less than 2^(96 - M) = 2^64
+pub(crate) const DIVISION_QUOTIENT_BITS: usize = DIVISION_OPERAND_BITS + DEFAULT_PRECISION;
 
 lazy_static! {
     /// The shift used to generate a scalar representation from a fixed point
@@ -79,6 +91,18 @@ impl FixedPoint {
         }
     }
 
+    /// Create a new fixed point representation from an f64, rounding down to the nearest
+    /// representable value
+    ///
+    /// Unlike `from_f32_round_down`, this keeps the full 32 bits of the fractional part for
+    /// values whose integer part fits in the remaining bits of an f64 mantissa
+    pub fn from_f64_round_down(val: f64) -> Self {
+        let shifted_val = val * (2u64.pow(DEFAULT_PRECISION as u32) as f64);
+        Self {
+            repr: Scalar::from(shifted_val.floor() as u64),
+        }
+    }
+
     /// Commit to the fixed point variable as a public input in a given constraint system
     pub fn commit_public<CS: RandomizableConstraintSystem>(&self, cs: &mut CS) -> FixedPointVar {
         let repr = cs.commit_public(self.repr);
@@ -229,7 +253,16 @@ impl<'de> Deserialize<'de> for FixedPoint {
     where
         D: Deserializer<'de>,
     {
+        // Check the precision here rather than in `From<f32>`, which panics on values that
+        // cannot be represented exactly
         let val_f32 = f32::deserialize(deserializer)?;
+        let shifted_val = val_f32 * (2u64.pow(DEFAULT_PRECISION as u32) as f32);
+        if !shifted_val.is_finite() || shifted_val != shifted_val.floor() {
+            return Err(SerdeErr::custom(
+                "value exceeds precision of constraint system",
+            ));
+        }
+
         Ok(FixedPoint::from(val_f32))
     }
 }
@@ -462,6 +495,44 @@ impl FixedPointVar {
         }
     }
 
+    /// Division of two fixed point variables, rounding toward zero
+    ///
+    /// For z = x / y with representations x' = x * 2^M and y' = y * 2^M, the gadget
+    /// witnesses z' = floor(x' * 2^M / y') and a remainder r, and constrains
+    /// z' * y' + r == x' * 2^M with 0 <= r < y'. The quotient and remainder are range
+    /// checked so that the relation holds over the integers, not just modulo the field
+    ///
+    /// Error bound: the result is the exact quotient of the represented operands rounded
+    /// down to the nearest multiple of 2^-M, so it is at most 2^-M (~2.3e-10) below the
+    /// exact quotient, and never above it
+    ///
+    /// Both operands must be non-negative, the divisor less than 2^32 and the quotient less
+    /// than 2^64, see `DIVISION_OPERAND_BITS` and `DIVISION_QUOTIENT_BITS`; a zero divisor
+    /// leaves the constraints unsatisfiable
+    pub fn div_fixed_point<CS: RandomizableConstraintSystem>(
+        &self,
+        rhs: &Self,
+        cs: &mut CS,
+    ) -> FixedPointVar {
+        // Shift the dividend up by 2^M so that the quotient retains M bits of precision
+        let (quotient, remainder) = DivRemGadget::<DIVISION_OPERAND_BITS>::div_rem(
+            *TWO_TO_M_SCALAR * self.repr.clone(),
+            rhs.repr.clone(),
+            cs,
+        );
+
+        GreaterThanEqZeroGadget::<DIVISION_OPERAND_BITS>::constrain_greater_than_zero(
+            remainder, cs,
+        );
+        GreaterThanEqZeroGadget::<DIVISION_QUOTIENT_BITS>::constrain_greater_than_zero(
+            quotient, cs,
+        );
+
+        Self {
+            repr: quotient.into(),
+        }
+    }
+
     /// Multiplication with an integer value
     ///
     /// This needs no reduction step as this is implicitly done by *not* converting the integer to a fixed-point
@@ -792,6 +863,42 @@ impl<'a, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
         let shifted_rhs = *TWO_TO_M_SCALAR * rhs;
         cs.constrain(self.repr.clone() - shifted_rhs);
     }
+
+    /// Computes the closest integral value less than the given fixed point variable and
+    /// constrains this value to be correctly computed
+    ///
+    /// Applies the same constraints as `FixedPointVar::floor`; the divisor is public, so
+    /// the quotient is computed in the MPC by shifting the shared representation
+    pub fn floor<CS: MpcRandomizableConstraintSystem<'a, N, S>>(
+        &self,
+        fabric: SharedFabric<N, S>,
+        cs: &mut CS,
+    ) -> Result<MpcVariable<N, S>, ProverError> {
+        let repr_eval = cs.eval(&self.repr).map_err(ProverError::Collaborative)?;
+        let quotient = shift_right::<DEFAULT_PRECISION, _, _>(&repr_eval, fabric.clone())
+            .map_err(ProverError::Mpc)?;
+        let remainder = &repr_eval - &(*TWO_TO_M_SCALAR * &quotient);
+
+        let quotient_var = cs.allocate(Some(quotient)).map_err(ProverError::R1CS)?;
+        let remainder_var = cs.allocate(Some(remainder)).map_err(ProverError::R1CS)?;
+
+        // Constrain repr == 2^M * q + r
+        let divisor = MpcLinearCombination::from_scalar(*TWO_TO_M_SCALAR, fabric.0.clone());
+        let (_, _, divisor_times_quotient) = cs
+            .multiply(&divisor, &quotient_var.clone().into())
+            .map_err(ProverError::Collaborative)?;
+        cs.constrain(self.repr.clone() - divisor_times_quotient - remainder_var.clone());
+
+        // Constrain r < 2^M
+        MultiproverLessThanGadget::<'a, DEFAULT_PRECISION, N, S>::constrain_less_than(
+            remainder_var.into(),
+            divisor,
+            fabric,
+            cs,
+        )?;
+
+        Ok(quotient_var)
+    }
 }
 
 /// Explicit clone implementation to remove the bounds on generics N, S to be `Clone`
@@ -1030,17 +1137,57 @@ mod fixed_point_tests {
     use crypto::fields::{scalar_to_bigdecimal, scalar_to_bigint};
     use curve25519_dalek::scalar::Scalar;
     use integration_helpers::mpc_network::field::get_ristretto_group_modulus;
+    use itertools::Itertools;
     use merlin::Transcript;
     use mpc_bulletproof::{
-        r1cs::{ConstraintSystem, Prover},
-        PedersenGens,
+        r1cs::{ConstraintSystem, Prover, R1CSError, Verifier},
+        BulletproofGens, PedersenGens,
     };
     use num_bigint::{BigInt, ToBigInt};
-    use rand::{thread_rng, Rng, RngCore};
+    use rand::{rngs::StdRng, thread_rng, Rng, RngCore, SeedableRng};
+    use rand_core::OsRng;
+
+    use crate::{zk_gadgets::fixed_point::DEFAULT_PRECISION, CommitProver, CommitVerifier};
+
+    use super::{FixedPoint, FixedPointVar};
+
+    /// The seed for the division operands drawn at random, fixed so that a failing case
+    /// reproduces
+    const DIV_TEST_SEED: u64 = 0xd1d;
+    /// The number of generators to prove a single division with
+    const DIV_BP_GENS_CAPACITY: usize = 512;
+
+    /// Prove and verify that `dividend / divisor` evaluates to `expected` in the division
+    /// gadget, with both operands hidden
+    fn prove_div(
+        dividend: FixedPoint,
+        divisor: FixedPoint,
+        expected: FixedPoint,
+    ) -> Result<(), R1CSError> {
+        let mut rng = OsRng {};
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(DIV_BP_GENS_CAPACITY, 1 /* party_capacity */);
+
+        // Prove
+        let mut prover_transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+        let (dividend_var, dividend_comm) = dividend.commit_prover(&mut rng, &mut prover).unwrap();
+        let (divisor_var, divisor_comm) = divisor.commit_prover(&mut rng, &mut prover).unwrap();
+
+        let res = dividend_var.div_fixed_point(&divisor_var, &mut prover);
+        res.constraint_equal(expected.commit_public(&mut prover), &mut prover);
+        let proof = prover.prove(&bp_gens)?;
 
-    use crate::zk_gadgets::fixed_point::DEFAULT_PRECISION;
+        // Verify
+        let mut verifier_transcript = Transcript::new("test".as_bytes());
+        let mut verifier = Verifier::new(&pc_gens, &mut verifier_transcript);
+        let dividend_var = dividend_comm.commit_verifier(&mut verifier).unwrap();
+        let divisor_var = divisor_comm.commit_verifier(&mut verifier).unwrap();
 
-    use super::FixedPointVar;
+        let res = dividend_var.div_fixed_point(&divisor_var, &mut verifier);
+        res.constraint_equal(expected.commit_public(&mut verifier), &mut verifier);
+        verifier.verify(&proof, &bp_gens)
+    }
 
     /// Tests that converting to and from f32 works properly
     #[test]
@@ -1225,4 +1372,79 @@ mod fixed_point_tests {
             assert!((&res_repr - expected_repr) / &res_repr < BigDecimal::from_f32(0.01).unwrap())
         }
     }
+
+    /// Divide the fixed point values with the given representations in the division
+    /// gadget, returning the representation of the quotient and whether the constraints
+    /// are satisfied
+    fn div_reprs(dividend: u64, divisor: u64) -> (Scalar, bool) {
+        let mut prover_transcript = Transcript::new("test".as_bytes());
+        let pc_gens = PedersenGens::default();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let dividend_var = FixedPoint::from(Scalar::from(dividend)).commit_public(&mut prover);
+        let divisor_var = FixedPoint::from(Scalar::from(divisor)).commit_public(&mut prover);
+        let res_var = dividend_var.div_fixed_point(&divisor_var, &mut prover);
+
+        (prover.eval(&res_var.repr), prover.constraints_satisfied())
+    }
+
+    /// Tests dividing fixed point values against exact integer math on their
+    /// representations, on edge cases and on operands drawn from a seeded generator; the
+    /// quotient must be the exact quotient rounded down to a multiple of 2^-M
+    #[test]
+    fn test_div() {
+        let n_tests = 100;
+        let one = 1u64 << DEFAULT_PRECISION;
+        let edge_cases = [
+            (6 * one, 3 * one),   // zero remainder
+            (0, 3 * one),         // zero dividend
+            (7 * one + 5, one),   // divisor of one
+            (u64::MAX, one),      // maximum dividend, divisor of one
+            (u64::MAX, u64::MAX), // maximum operands
+            (one, u64::MAX),      // maximum divisor
+            (u64::MAX, 1),        // minimum divisor, maximum quotient
+        ];
+
+        let mut rng = StdRng::seed_from_u64(DIV_TEST_SEED);
+        let random_cases = (0..n_tests)
+            .map(|_| (rng.gen::<u64>(), rng.gen_range(1..=u64::MAX)))
+            .collect_vec();
+
+        for (dividend, divisor) in edge_cases.into_iter().chain(random_cases) {
+            let expected = ((dividend as u128) << DEFAULT_PRECISION) / (divisor as u128);
+            let (res, satisfied) = div_reprs(dividend, divisor);
+
+            assert!(satisfied, "{dividend} / {divisor} unsatisfied");
+            assert_eq!(res, Scalar::from(expected), "{dividend} / {divisor}");
+        }
+    }
+
+    /// Tests that a division proves with the rounded down quotient, and does not prove with
+    /// any other quotient or with a zero divisor
+    #[test]
+    fn test_div_prove() {
+        let dividend = FixedPoint::from(1f32);
+        let divisor = FixedPoint::from(3f32);
+
+        // 1 / 3 rounded down to the nearest multiple of 2^-M
+        let expected = FixedPoint::from(Scalar::from((1u64 << DEFAULT_PRECISION) / 3));
+        assert!(prove_div(dividend, divisor, expected).is_ok());
+
+        let rounded_up = FixedPoint::from(expected.repr + Scalar::one());
+        assert!(prove_div(dividend, divisor, rounded_up).is_err());
+
+        let zero = FixedPoint::from(0f32);
+        assert!(prove_div(dividend, zero, zero).is_err());
+    }
+
+    /// Tests that deserializing a value the fixed point type cannot represent returns an
+    /// error rather than panicking
+    #[test]
+    fn test_deserialize_imprecise() {
+        let res: Result<FixedPoint, _> = serde_json::from_str("1e-20");
+        assert!(res.is_err());
+
+        let res: FixedPoint = serde_json::from_str("0.5").unwrap();
+        assert_eq!(res.repr, Scalar::from(1u64 << (DEFAULT_PRECISION - 1)));
+    }
 }
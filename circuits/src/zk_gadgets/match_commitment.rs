@@ -0,0 +1,140 @@
+//! Groups logic for committing to a match result ahead of its settlement
+//!
+//! The commitment is a Pedersen commitment to each element of the match tuple, blinded by
+//! the randomness held in the match's `LinkableMatchResultCommitment`. These are exactly the
+//! commitments that the witness commitment of `VALID MATCH ENCRYPTION` carries for the match,
+//! so the commitment may be posted as soon as the MPC completes, and is opened without
+//! revealing the match by the proof of `VALID MATCH ENCRYPTION` once it is generated.
+//! No in-circuit opening is needed: the verifier checks that the proof's witness
+//! commitments equal the posted commitment
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    types::r#match::{CommittedMatchResult, LinkableMatchResultCommitment, MATCH_SIZE_SCALARS},
+    LinkableCommitment,
+};
+
+/// A Pedersen commitment to each element of a match tuple, in the order the tuple is
+/// serialized in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchCommitment {
+    /// The commitments to the elements of the match tuple
+    pub commitments: [CompressedRistretto; MATCH_SIZE_SCALARS],
+}
+
+impl MatchCommitment {
+    /// Commit to a match result under the blinders of its linkable commitment
+    pub fn new(match_res: &LinkableMatchResultCommitment) -> Self {
+        Self {
+            commitments: match_elements(match_res).map(|elem| elem.compute_commitment()),
+        }
+    }
+
+    /// Whether the given values and blinders open the commitment
+    pub fn verify_opening(&self, match_res: &LinkableMatchResultCommitment) -> bool {
+        *self == Self::new(match_res)
+    }
+
+    /// Whether a proof's witness commitment to a match result commits to the same match under
+    /// the same blinders, i.e. whether the proof opens the commitment
+    pub fn is_opened_by(&self, witness_commitment: &CommittedMatchResult) -> bool {
+        *self == Self::from(witness_commitment)
+    }
+}
+
+impl From<&CommittedMatchResult> for MatchCommitment {
+    fn from(committed: &CommittedMatchResult) -> Self {
+        Self {
+            commitments: [
+                committed.quote_mint,
+                committed.base_mint,
+                committed.quote_amount,
+                committed.base_amount,
+                committed.direction,
+                committed.execution_price.repr,
+                committed.max_minus_min_amount,
+                committed.min_amount_order_index,
+            ],
+        }
+    }
+}
+
+/// The linkable commitments to the elements of a match tuple, in serialization order
+fn match_elements(
+    match_res: &LinkableMatchResultCommitment,
+) -> [LinkableCommitment; MATCH_SIZE_SCALARS] {
+    [
+        match_res.quote_mint,
+        match_res.base_mint,
+        match_res.quote_amount,
+        match_res.base_amount,
+        match_res.direction,
+        match_res.execution_price.repr,
+        match_res.max_minus_min_amount,
+        match_res.min_amount_order_index,
+    ]
+}
+
+#[cfg(test)]
+mod match_commitment_tests {
+    use curve25519_dalek::scalar::Scalar;
+    use merlin::Transcript;
+    use mpc_bulletproof::{r1cs::Prover, PedersenGens};
+    use num_bigint::BigUint;
+    use rand_core::OsRng;
+
+    use crate::{
+        types::r#match::{LinkableMatchResultCommitment, MatchResult},
+        zk_gadgets::fixed_point::FixedPoint,
+        CommitProver,
+    };
+
+    use super::MatchCommitment;
+
+    /// Build a match result to commit to
+    fn dummy_match() -> MatchResult {
+        MatchResult {
+            quote_mint: BigUint::from(1u8),
+            base_mint: BigUint::from(2u8),
+            quote_amount: 200,
+            base_amount: 10,
+            direction: 1,
+            execution_price: FixedPoint::from(20u64),
+            max_minus_min_amount: 5,
+            min_amount_order_index: 0,
+        }
+    }
+
+    /// Tests that a proof committing to the linked match result opens the match commitment
+    #[test]
+    fn test_open_match_commitment() {
+        let linkable_match: LinkableMatchResultCommitment = dummy_match().into();
+        let commitment = MatchCommitment::new(&linkable_match);
+        assert!(commitment.verify_opening(&linkable_match));
+
+        let mut rng = OsRng {};
+        let pc_gens = PedersenGens::default();
+        let mut transcript = Transcript::new(b"test");
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (_, match_comm) = linkable_match.commit_prover(&mut rng, &mut prover).unwrap();
+        assert!(commitment.is_opened_by(&match_comm));
+
+        // A different match under the same blinders does not open the commitment
+        let mut wrong_match = linkable_match;
+        wrong_match.base_amount.val += Scalar::one();
+        let (_, wrong_comm) = wrong_match.commit_prover(&mut rng, &mut prover).unwrap();
+        assert!(!commitment.is_opened_by(&wrong_comm));
+    }
+
+    /// Tests that the same match under fresh blinders does not open the commitment
+    #[test]
+    fn test_reblinded_match() {
+        let match_res = dummy_match();
+        let commitment = MatchCommitment::new(&match_res.clone().into());
+
+        let reblinded: LinkableMatchResultCommitment = match_res.into();
+        assert!(!commitment.verify_opening(&reblinded));
+    }
+}
@@ -21,7 +21,7 @@ use crate::{
     CommitProver, CommitVerifier, SingleProverCircuit,
 };
 
-use super::poseidon::PoseidonHashGadget;
+use super::{poseidon::PoseidonHashGadget, select::CondSelectGadget};
 
 /// A type alias for readability
 pub type MerkleRoot = Scalar;
@@ -268,6 +268,244 @@ impl CommitVerifier for MerkleOpeningCommitment {
     }
 }
 
+/// The single-prover batch insertion gadget, computes the Merkle root of a tree after
+/// inserting a set of prehashed leaves at consecutive indices
+///
+/// The inserted leaves are hashed up the tree together, so internal nodes shared
+/// between the leaves' paths are hashed once rather than once per leaf
+pub struct PoseidonMerkleBatchInsertionGadget {}
+
+impl PoseidonMerkleBatchInsertionGadget {
+    /// Compute the root of the tree given a set of prehashed leaves at consecutive
+    /// indices and the batch opening of the range they occupy
+    pub fn compute_root_prehashed<L, CS>(
+        leaves: Vec<L>,
+        opening: MerkleBatchOpeningVar,
+        cs: &mut CS,
+    ) -> Result<LinearCombination, R1CSError>
+    where
+        L: Into<LinearCombination> + Clone,
+        CS: RandomizableConstraintSystem,
+    {
+        assert!(
+            !leaves.is_empty(),
+            "batch insertion requires at least one leaf"
+        );
+
+        let mut current_level: Vec<LinearCombination> =
+            leaves.into_iter().map(Into::into).collect_vec();
+        for ((sister_node, boundary_node), index_bit) in opening
+            .elems
+            .into_iter()
+            .zip(opening.boundary_elems.into_iter())
+            .zip(opening.indices.into_iter())
+        {
+            let padded_level =
+                Self::pad_level(current_level, sister_node, boundary_node, index_bit, cs);
+            current_level = padded_level
+                .chunks(2)
+                .map(|children| {
+                    PoseidonMerkleHashGadget::hash_internal_nodes(&children[0], &children[1], cs)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        // The range begins at index zero at the root level, so the first node is the root
+        Ok(current_level.swap_remove(0))
+    }
+
+    /// Prove the insertion of a set of prehashed leaves into consecutive empty slots
+    /// of the tree
+    ///
+    /// The same opening is used to compute the root both before and after the insertion,
+    /// constraining the former to the old root and the latter to the new root
+    pub fn compute_and_constrain_insertion<L, CS>(
+        leaves: Vec<L>,
+        empty_leaf: Scalar,
+        opening: MerkleBatchOpeningVar,
+        old_root: L,
+        new_root: L,
+        cs: &mut CS,
+    ) -> Result<(), R1CSError>
+    where
+        L: Into<LinearCombination> + Clone,
+        CS: RandomizableConstraintSystem,
+    {
+        let empty_leaves = vec![LinearCombination::from(empty_leaf); leaves.len()];
+        let computed_old_root = Self::compute_root_prehashed(empty_leaves, opening.clone(), cs)?;
+        cs.constrain(old_root.into() - computed_old_root);
+
+        let computed_new_root = Self::compute_root_prehashed(leaves, opening, cs)?;
+        cs.constrain(new_root.into() - computed_new_root);
+
+        Ok(())
+    }
+
+    /// Pads the nodes of the current level so that they may be hashed pairwise into the
+    /// nodes of the next level
+    ///
+    /// If the range begins on a left hand child, the sister node is the right hand neighbor
+    /// of the range; otherwise it is the left hand neighbor. If the padded range is still of
+    /// odd length, the boundary node to its right is appended
+    fn pad_level<CS: RandomizableConstraintSystem>(
+        nodes: Vec<LinearCombination>,
+        sister_node: Variable,
+        boundary_node: Variable,
+        index_bit: Variable,
+        cs: &mut CS,
+    ) -> Vec<LinearCombination> {
+        let n_nodes = nodes.len();
+        let sister_lc: LinearCombination = sister_node.into();
+        let index_bit_lc: LinearCombination = index_bit.into();
+
+        let mut padded = Vec::with_capacity(n_nodes + 2);
+        for i in 0..n_nodes + 1 {
+            // If index_bit == 1 { [sister, nodes..] } else { [nodes.., sister] }
+            let right_child_start = if i == 0 {
+                sister_lc.clone()
+            } else {
+                nodes[i - 1].clone()
+            };
+            let left_child_start = if i == n_nodes {
+                sister_lc.clone()
+            } else {
+                nodes[i].clone()
+            };
+
+            padded.push(CondSelectGadget::select(
+                right_child_start,
+                left_child_start,
+                index_bit_lc.clone(),
+                cs,
+            ));
+        }
+
+        if padded.len() % 2 == 1 {
+            padded.push(boundary_node.into());
+        }
+
+        padded
+    }
+}
+
+/// An opening of a range of consecutive leaves, giving the nodes adjacent to
+/// the range at each level of the tree
+///
+/// At each level the range of nodes is padded by the sister node on the side
+/// given by the opening index, and by the boundary node on the right if the
+/// padded range has odd length. Boundary nodes at levels that do not need one
+/// are ignored
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleBatchOpening {
+    /// The sister node of the range at each level, from the leaves to the root
+    pub elems: Vec<Scalar>,
+    /// The node to the right of the padded range at each level, from the leaves
+    /// to the root
+    pub boundary_elems: Vec<Scalar>,
+    /// The little-endian bit decomposition of the index of the first leaf in the
+    /// range; 0 indicating that the range begins on a left hand child at the
+    /// given level, 1 indicating that it begins on a right hand child
+    pub indices: Vec<Scalar>,
+}
+
+/// A batch Merkle opening that has been allocated in a constraint system
+#[derive(Clone, Debug)]
+pub struct MerkleBatchOpeningVar {
+    /// The sister node of the range at each level, from the leaves to the root
+    pub elems: Vec<Variable>,
+    /// The node to the right of the padded range at each level, from the leaves
+    /// to the root
+    pub boundary_elems: Vec<Variable>,
+    /// The little-endian bit decomposition of the index of the first leaf in the
+    /// range
+    pub indices: Vec<Variable>,
+}
+
+/// A commitment to a batch Merkle opening in a constraint system
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleBatchOpeningCommitment {
+    /// The sister node of the range at each level, from the leaves to the root
+    pub elems: Vec<CompressedRistretto>,
+    /// The node to the right of the padded range at each level, from the leaves
+    /// to the root
+    pub boundary_elems: Vec<CompressedRistretto>,
+    /// The little-endian bit decomposition of the index of the first leaf in the
+    /// range
+    pub indices: Vec<CompressedRistretto>,
+}
+
+impl CommitProver for MerkleBatchOpening {
+    type VarType = MerkleBatchOpeningVar;
+    type CommitType = MerkleBatchOpeningCommitment;
+    type ErrorType = ();
+
+    fn commit_prover<R: rand_core::RngCore + rand_core::CryptoRng>(
+        &self,
+        rng: &mut R,
+        prover: &mut Prover,
+    ) -> Result<(Self::VarType, Self::CommitType), Self::ErrorType> {
+        let (elem_comms, elem_vars): (Vec<CompressedRistretto>, Vec<Variable>) = self
+            .elems
+            .iter()
+            .map(|elem| prover.commit(*elem, Scalar::random(rng)))
+            .unzip();
+
+        let (boundary_comms, boundary_vars): (Vec<CompressedRistretto>, Vec<Variable>) = self
+            .boundary_elems
+            .iter()
+            .map(|elem| prover.commit(*elem, Scalar::random(rng)))
+            .unzip();
+
+        let (index_comms, index_vars): (Vec<CompressedRistretto>, Vec<Variable>) = self
+            .indices
+            .iter()
+            .map(|index| prover.commit(*index, Scalar::random(rng)))
+            .unzip();
+
+        Ok((
+            MerkleBatchOpeningVar {
+                elems: elem_vars,
+                boundary_elems: boundary_vars,
+                indices: index_vars,
+            },
+            MerkleBatchOpeningCommitment {
+                elems: elem_comms,
+                boundary_elems: boundary_comms,
+                indices: index_comms,
+            },
+        ))
+    }
+}
+
+impl CommitVerifier for MerkleBatchOpeningCommitment {
+    type VarType = MerkleBatchOpeningVar;
+    type ErrorType = ();
+
+    fn commit_verifier(&self, verifier: &mut Verifier) -> Result<Self::VarType, Self::ErrorType> {
+        let elem_vars = self
+            .elems
+            .iter()
+            .map(|elem| verifier.commit(*elem))
+            .collect_vec();
+        let boundary_vars = self
+            .boundary_elems
+            .iter()
+            .map(|elem| verifier.commit(*elem))
+            .collect_vec();
+        let index_vars = self
+            .indices
+            .iter()
+            .map(|index| verifier.commit(*index))
+            .collect_vec();
+
+        Ok(MerkleBatchOpeningVar {
+            elems: elem_vars,
+            boundary_elems: boundary_vars,
+            indices: index_vars,
+        })
+    }
+}
+
 /// The witness to the statement defined by the Merkle gadget; that is one of
 /// Merkle inclusion
 #[derive(Clone, Debug)]
@@ -422,7 +660,7 @@ pub(crate) mod merkle_test {
     use ark_crypto_primitives::{
         crh::{
             poseidon::{TwoToOneCRH, CRH},
-            CRHScheme,
+            CRHScheme, TwoToOneCRHScheme,
         },
         merkle_tree::{Config, IdentityDigestConverter, MerkleTree},
     };
@@ -432,7 +670,12 @@ pub(crate) mod merkle_test {
     };
     use curve25519_dalek::scalar::Scalar;
     use itertools::Itertools;
-    use mpc_bulletproof::r1cs_mpc::R1CSError;
+    use merlin::Transcript;
+    use mpc_bulletproof::{
+        r1cs::{ConstraintSystem, Prover, Verifier},
+        r1cs_mpc::R1CSError,
+        BulletproofGens, PedersenGens,
+    };
     use rand::{thread_rng, Rng};
     use rand_core::OsRng;
 
@@ -440,9 +683,16 @@ pub(crate) mod merkle_test {
         errors::VerifierError,
         test_helpers::bulletproof_prove_and_verify,
         zk_gadgets::merkle::{MerkleOpening, PoseidonMerkleHashGadget},
+        CommitProver, CommitVerifier,
+    };
+
+    use super::{
+        MerkleBatchOpening, MerkleBatchOpeningVar, MerkleOpeningVar, MerkleStatement,
+        MerkleWitness, PoseidonMerkleBatchInsertionGadget,
     };
 
-    use super::{MerkleStatement, MerkleWitness};
+    /// The number of generators to prove a batch insertion with
+    const BATCH_INSERTION_BP_GENS_CAPACITY: usize = 32768;
 
     struct MerkleConfig {}
     impl Config for MerkleConfig {
@@ -477,6 +727,125 @@ pub(crate) mod merkle_test {
         }
     }
 
+    /// Build the levels of a Merkle tree from its leaves, from the leaves to the root
+    fn build_tree_levels(leaves: Vec<Scalar>) -> Vec<Vec<Scalar>> {
+        let arkworks_params = default_poseidon_params();
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next_level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|children| {
+                    let parent = TwoToOneCRH::<DalekRistrettoField>::evaluate(
+                        &arkworks_params,
+                        scalar_to_prime_field(&children[0]),
+                        scalar_to_prime_field(&children[1]),
+                    )
+                    .unwrap();
+                    prime_field_to_scalar(&parent)
+                })
+                .collect_vec();
+            levels.push(next_level);
+        }
+
+        levels
+    }
+
+    /// Build a batch opening for `n_leaves` leaves starting at `start_index` from the
+    /// levels of a tree
+    fn get_batch_opening(
+        levels: &[Vec<Scalar>],
+        start_index: usize,
+        n_leaves: usize,
+    ) -> MerkleBatchOpening {
+        let tree_height = levels.len() - 1;
+        let mut elems = Vec::with_capacity(tree_height);
+        let mut boundary_elems = Vec::with_capacity(tree_height);
+
+        // Nodes outside of the tree are never hashed into the root, so any value suffices
+        let mut n_nodes = n_leaves;
+        for (height, level) in levels.iter().take(tree_height).enumerate() {
+            let range_start = start_index >> height;
+            let node_at = |index: usize| level.get(index).copied().unwrap_or_else(Scalar::zero);
+
+            if range_start % 2 == 0 {
+                elems.push(node_at(range_start + n_nodes));
+                boundary_elems.push(node_at(range_start + n_nodes + 1));
+            } else {
+                elems.push(node_at(range_start - 1));
+                boundary_elems.push(node_at(range_start + n_nodes));
+            }
+
+            n_nodes = n_nodes / 2 + 1;
+        }
+
+        MerkleBatchOpening {
+            elems,
+            boundary_elems,
+            indices: get_opening_indices(start_index, tree_height),
+        }
+    }
+
+    /// Prove and verify the insertion of `leaves` at consecutive indices given the
+    /// old and new roots of the tree
+    fn prove_batch_insertion(
+        leaves: Vec<Scalar>,
+        empty_leaf: Scalar,
+        opening: MerkleBatchOpening,
+        old_root: Scalar,
+        new_root: Scalar,
+    ) -> Result<(), R1CSError> {
+        let mut rng = OsRng {};
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(
+            BATCH_INSERTION_BP_GENS_CAPACITY,
+            1, /* party_capacity */
+        );
+
+        // Prove
+        let mut prover_transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+        let (leaf_comms, leaf_vars): (Vec<_>, Vec<_>) = leaves
+            .iter()
+            .map(|leaf| prover.commit(*leaf, Scalar::random(&mut rng)))
+            .unzip();
+        let (opening_var, opening_comm) = opening.commit_prover(&mut rng, &mut prover).unwrap();
+        let old_root_var = prover.commit_public(old_root);
+        let new_root_var = prover.commit_public(new_root);
+
+        PoseidonMerkleBatchInsertionGadget::compute_and_constrain_insertion(
+            leaf_vars,
+            empty_leaf,
+            opening_var,
+            old_root_var,
+            new_root_var,
+            &mut prover,
+        )?;
+        let proof = prover.prove(&bp_gens)?;
+
+        // Verify
+        let mut verifier_transcript = Transcript::new("test".as_bytes());
+        let mut verifier = Verifier::new(&pc_gens, &mut verifier_transcript);
+        let leaf_vars = leaf_comms
+            .iter()
+            .map(|comm| verifier.commit(*comm))
+            .collect_vec();
+        let opening_var = opening_comm.commit_verifier(&mut verifier).unwrap();
+        let old_root_var = verifier.commit_public(old_root);
+        let new_root_var = verifier.commit_public(new_root);
+
+        PoseidonMerkleBatchInsertionGadget::compute_and_constrain_insertion(
+            leaf_vars,
+            empty_leaf,
+            opening_var,
+            old_root_var,
+            new_root_var,
+            &mut verifier,
+        )?;
+        verifier.verify(&proof, &bp_gens)
+    }
+
     #[test]
     #[allow(non_upper_case_globals)]
     fn test_against_arkworks() {
@@ -598,4 +967,111 @@ pub(crate) mod merkle_test {
         let res = bulletproof_prove_and_verify::<PoseidonMerkleHashGadget>(witness, statement);
         assert_eq!(res, Err(VerifierError::R1CS(R1CSError::VerificationError)));
     }
+
+    /// Tests inserting a batch of leaves at a random range of consecutive indices
+    #[test]
+    fn test_batch_insertion() {
+        let mut rng = OsRng {};
+        let tree_height = 4;
+        let n_leaves = 3;
+        let empty_leaf = Scalar::zero();
+
+        // Fill the tree up to a random index, the remaining leaves are empty
+        let start_index = thread_rng().gen_range(0..=(1 << tree_height) - n_leaves);
+        let mut leaves = (0..start_index)
+            .map(|_| Scalar::random(&mut rng))
+            .collect_vec();
+        leaves.resize(1 << tree_height, empty_leaf);
+
+        let old_levels = build_tree_levels(leaves.clone());
+        let opening = get_batch_opening(&old_levels, start_index, n_leaves);
+
+        let inserted_leaves = (0..n_leaves)
+            .map(|_| Scalar::random(&mut rng))
+            .collect_vec();
+        leaves[start_index..start_index + n_leaves].copy_from_slice(&inserted_leaves);
+        let new_levels = build_tree_levels(leaves);
+
+        let old_root = old_levels.last().unwrap()[0];
+        let new_root = new_levels.last().unwrap()[0];
+        prove_batch_insertion(inserted_leaves, empty_leaf, opening, old_root, new_root).unwrap();
+    }
+
+    /// Tests that a batch insertion does not verify against an incorrect new root
+    #[test]
+    fn test_batch_insertion_invalid_root() {
+        let mut rng = OsRng {};
+        let tree_height = 4;
+        let n_leaves = 3;
+        let empty_leaf = Scalar::zero();
+
+        let start_index = thread_rng().gen_range(0..=(1 << tree_height) - n_leaves);
+        let mut leaves = (0..start_index)
+            .map(|_| Scalar::random(&mut rng))
+            .collect_vec();
+        leaves.resize(1 << tree_height, empty_leaf);
+
+        let old_levels = build_tree_levels(leaves);
+        let opening = get_batch_opening(&old_levels, start_index, n_leaves);
+        let inserted_leaves = (0..n_leaves)
+            .map(|_| Scalar::random(&mut rng))
+            .collect_vec();
+
+        let old_root = old_levels.last().unwrap()[0];
+        let res = prove_batch_insertion(
+            inserted_leaves,
+            empty_leaf,
+            opening,
+            old_root,
+            Scalar::random(&mut rng), /* new_root */
+        );
+        assert_eq!(res, Err(R1CSError::VerificationError));
+    }
+
+    /// Tests that computing the root of a batch uses fewer multipliers than computing
+    /// the root from each leaf independently
+    #[test]
+    fn test_batch_insertion_shares_hashes() {
+        let tree_height = 8;
+        let n_leaves = 4;
+
+        let mut prover_transcript = Transcript::new("test".as_bytes());
+        let pc_gens = PedersenGens::default();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let mut allocate_zeros = |n: usize| {
+            (0..n)
+                .map(|_| prover.allocate(Some(Scalar::zero())).unwrap())
+                .collect_vec()
+        };
+        let leaves = allocate_zeros(n_leaves);
+        let independent_openings = (0..n_leaves)
+            .map(|_| MerkleOpeningVar {
+                elems: allocate_zeros(tree_height),
+                indices: allocate_zeros(tree_height),
+            })
+            .collect_vec();
+        let batch_opening = MerkleBatchOpeningVar {
+            elems: allocate_zeros(tree_height),
+            boundary_elems: allocate_zeros(tree_height),
+            indices: allocate_zeros(tree_height),
+        };
+
+        let multipliers_pre = prover.num_multipliers();
+        for (leaf, opening) in leaves.iter().zip(independent_openings.into_iter()) {
+            PoseidonMerkleHashGadget::compute_root_prehashed(*leaf, opening, &mut prover).unwrap();
+        }
+        let independent_multipliers = prover.num_multipliers() - multipliers_pre;
+
+        let multipliers_pre = prover.num_multipliers();
+        PoseidonMerkleBatchInsertionGadget::compute_root_prehashed(
+            leaves,
+            batch_opening,
+            &mut prover,
+        )
+        .unwrap();
+        let batch_multipliers = prover.num_multipliers() - multipliers_pre;
+
+        assert!(batch_multipliers < independent_multipliers);
+    }
 }
@@ -7,7 +7,9 @@ pub mod edwards;
 pub mod elgamal;
 pub mod fixed_point;
 pub mod gates;
+pub mod match_commitment;
 pub mod merkle;
 pub mod nonnative;
 pub mod poseidon;
+pub mod range;
 pub mod select;
@@ -72,6 +72,68 @@ where
     (div_var, rem_var)
 }
 
+/// Reduce a set of word-sized direct products into a list of words
+///
+/// Each product is given as the shift bin it belongs to, i.e. the `k` such that
+/// the product is prefixed by 2^{k * WORD_SIZE}, its linear combination in the
+/// constraint system, and its natively computed value
+///
+/// Each product is split into a high and low word by allocating only the high
+/// word; the low word is left implicit as `product - 2^WORD_SIZE * high`. The
+/// low word is accumulated into the product's own bin and the high word into
+/// the next bin, after which a single carry propagation pass reduces each bin
+///
+/// Returns the reduced words and the carry out of the final bin
+fn reduce_word_products<CS: RandomizableConstraintSystem>(
+    products: Vec<(usize, LinearCombination, BigUint)>,
+    n_result_words: usize,
+    cs: &mut CS,
+) -> (Vec<LinearCombination>, Variable) {
+    let word_modulus = biguint_to_scalar(&BIGINT_2_TO_WORD_SIZE);
+
+    // Accumulate the split products into their shift bins, tracking the value of
+    // each bin natively alongside its linear combination
+    let mut bins = vec![LinearCombination::default(); n_result_words];
+    let mut bin_values = vec![BIGINT_ZERO.clone(); n_result_words];
+    for (shift_index, product, product_value) in products.into_iter() {
+        assert!(
+            product_value.bits() <= (2 * WORD_SIZE) as u64,
+            "value too large for word reduction"
+        );
+
+        let high_value = &product_value >> WORD_SIZE;
+        let low_value = &product_value & &*BIGINT_WORD_MASK;
+        let high_var = cs.allocate(Some(biguint_to_scalar(&high_value))).unwrap();
+
+        bins[shift_index] += product - word_modulus * high_var;
+        bin_values[shift_index] += low_value;
+        bins[shift_index + 1] += high_var;
+        bin_values[shift_index + 1] += high_value;
+    }
+
+    // Propagate carries through the bins, reducing each to a single word
+    let mut carry = Variable::Zero();
+    let mut carry_value = BIGINT_ZERO.clone();
+    let mut res_words = Vec::with_capacity(n_result_words);
+    for (bin, bin_value) in bins.into_iter().zip(bin_values.into_iter()) {
+        let summed_value = bin_value + &carry_value;
+        let div_value = &summed_value >> WORD_SIZE;
+        let rem_value = &summed_value & &*BIGINT_WORD_MASK;
+
+        let div_var = cs.allocate(Some(biguint_to_scalar(&div_value))).unwrap();
+        let rem_var = cs.allocate(Some(biguint_to_scalar(&rem_value))).unwrap();
+
+        // Constrain the reduction to be correct, i.e. bin + carry = div * 2^WORD_SIZE + rem
+        cs.constrain(bin + carry - (word_modulus * div_var + rem_var));
+
+        carry = div_var;
+        carry_value = div_value;
+        res_words.push(rem_var.into());
+    }
+
+    (res_words, carry)
+}
+
 /// Convert a `BigUint` to a list of scalar words
 fn bigint_to_scalar_words(mut val: BigUint) -> Vec<Scalar> {
     let mut words = Vec::new();
@@ -614,48 +676,41 @@ impl NonNativeElementVar {
         );
         let n_result_words = lhs.words.len() + rhs.words.len();
 
+        // Evaluate each word of the operands once up front, the witness values of the
+        // products are then computed natively rather than re-evaluating each product
+        let lhs_values = lhs
+            .words
+            .iter()
+            .map(|word| scalar_to_biguint(&cs.eval(word)))
+            .collect_vec();
+        let rhs_values = rhs
+            .words
+            .iter()
+            .map(|word| scalar_to_biguint(&cs.eval(word)))
+            .collect_vec();
+
         // Both lhs and rhs are represented as:
         //  x = x_1 + 2^WORD_SIZE * x_2 + ... + 2^(num_words * WORD_SIZE) * x_num_words
         // To multiply the values, we take the direct product of each pair of terms
-        // between `lhs` and `rhs`, storing both the term and the carry from reducing
-        // each term in one of the buckets below; depending on the shift (2^k) applied
-        // to the result
-        //
-        // The maximum shift is 2^{2 * num_words} as (2^k - 1)(2^k - 1) = 2^2k - 2^{k+1} - 1 < 2^2k
-        let mut terms = vec![Vec::new(); n_result_words];
-        let mut carries = vec![Vec::new(); n_result_words + 1];
-
-        for (lhs_index, lhs_word) in lhs.word_iterator().enumerate().take(lhs.words.len()) {
-            for (rhs_index, rhs_word) in rhs.word_iterator().enumerate().take(rhs.words.len()) {
-                // Compute the term and reduce it modulo the field
+        // between `lhs` and `rhs` and tag it with the shift (2^k) applied to it
+        let mut products = Vec::with_capacity(lhs.words.len() * rhs.words.len());
+        for (lhs_index, (lhs_word, lhs_value)) in
+            lhs.words.iter().zip(lhs_values.iter()).enumerate()
+        {
+            for (rhs_index, (rhs_word, rhs_value)) in
+                rhs.words.iter().zip(rhs_values.iter()).enumerate()
+            {
                 let (_, _, term_direct_product) =
                     cs.multiply(lhs_word.to_owned(), rhs_word.to_owned());
-                let (term_carry, term) =
-                    div_rem_word(term_direct_product, &BIGINT_2_TO_WORD_SIZE, cs);
-
-                // Place the term and the carry in the shift bin corresponding to the value k such that
-                // this term is prefixed with 2^k in the expanded representation described above
-                let shift_index = lhs_index + rhs_index;
-                terms[shift_index].push(term);
-                carries[shift_index + 1].push(term_carry);
+                products.push((
+                    lhs_index + rhs_index,
+                    LinearCombination::from(term_direct_product),
+                    lhs_value * rhs_value,
+                ));
             }
         }
 
-        // Now reduce each term into a single word
-        let mut carry = Variable::Zero();
-        let mut res_words = Vec::with_capacity(n_result_words);
-        for word_index in 0..n_result_words {
-            // Sum all the terms and carries at the given word index
-            let mut summed_word: LinearCombination = carry.into();
-            for word_term in terms[word_index].iter().chain(carries[word_index].iter()) {
-                summed_word += *word_term;
-            }
-
-            // Reduce this sum and add any carry to the next term's carries
-            let div_rem_res = div_rem_word(summed_word, &BIGINT_2_TO_WORD_SIZE, cs);
-            carry = div_rem_res.0;
-            res_words.push(div_rem_res.1.into());
-        }
+        let (mut res_words, carry) = reduce_word_products(products, n_result_words, cs);
         res_words.push(carry.into());
 
         Self {
@@ -685,47 +740,29 @@ impl NonNativeElementVar {
         let rhs_words = bigint_to_scalar_words(rhs.clone());
         let n_result_words = rhs_words.len() + lhs.words.len();
 
-        // Both lhs and rhs are represented as:
-        //  x = x_1 + 2^WORD_SIZE * x_2 + ... + 2^(num_words * WORD_SIZE) * x_num_words
-        // To multiply the values, we take the direct product of each pair of terms
-        // between `lhs` and `rhs`, storing both the term and the carry from reducing
-        // each term in one of the buckets below; depending on the shift (2^k) applied
-        // to the result
-        //
-        // The maximum shift is 2^{2 * num_words} as (2^k - 1)(2^k - 1) = 2^2k - 2^{k+1} - 1 < 2^2k
-        let mut terms = vec![Vec::new(); n_result_words];
-        let mut carries = vec![Vec::new(); n_result_words];
-
-        for (lhs_index, lhs_word) in lhs.words.iter().enumerate() {
+        // Evaluate each word of the non-native operand once up front
+        let lhs_values = lhs
+            .words
+            .iter()
+            .map(|word| scalar_to_biguint(&cs.eval(word)))
+            .collect_vec();
+
+        // The direct product of each pair of words is linear in the non-native
+        // operand, so no multiplication gates are needed to form the terms
+        let mut products = Vec::with_capacity(lhs.words.len() * rhs_words.len());
+        for (lhs_index, (lhs_word, lhs_value)) in
+            lhs.words.iter().zip(lhs_values.iter()).enumerate()
+        {
             for (rhs_index, rhs_word) in rhs_words.iter().enumerate() {
-                // Compute the term and reduce it modulo the field
-                let term_direct_product = lhs_word.to_owned() * rhs_word.to_owned();
-                let (term_carry, term) =
-                    div_rem_word(term_direct_product, &BIGINT_2_TO_WORD_SIZE, cs);
-
-                // Place the term and the carry in the shift bin corresponding to the value k such that
-                // this term is prefixed with 2^k in the expanded representation described above
-                let shift_index = lhs_index + rhs_index;
-                terms[shift_index].push(term);
-                carries[shift_index + 1].push(term_carry);
+                products.push((
+                    lhs_index + rhs_index,
+                    lhs_word.to_owned() * *rhs_word,
+                    lhs_value * scalar_to_biguint(rhs_word),
+                ));
             }
         }
 
-        // Now reduce each term into a single word
-        let mut carry = Variable::Zero();
-        let mut res_words = Vec::with_capacity(n_result_words);
-        for word_index in 0..n_result_words {
-            // Sum all the terms and carries at the given word index
-            let mut summed_word: LinearCombination = carry.into();
-            for word_term in terms[word_index].iter().chain(carries[word_index].iter()) {
-                summed_word += *word_term;
-            }
-
-            // Reduce this sum and add any carry to the next term's carries
-            let div_rem_res = div_rem_word(summed_word, &BIGINT_2_TO_WORD_SIZE, cs);
-            carry = div_rem_res.0;
-            res_words.push(div_rem_res.1.into());
-        }
+        let (res_words, _) = reduce_word_products(products, n_result_words, cs);
 
         Self {
             words: res_words,
@@ -1345,7 +1382,7 @@ mod nonnative_tests {
 
         // Use the curve25519 field modulus
         let modulus: BigUint = (BigUint::from(1u8) << 255) - 19u8;
-        let field_mod = FieldMod::from_modulus(modulus.clone());
+        let field_mod = FieldMod::from_modulus(modulus);
 
         let mut prover_transcript = Transcript::new(TRANSCRIPT_SEED.as_bytes());
         let pc_gens = PedersenGens::default();
@@ -1499,6 +1536,49 @@ mod nonnative_tests {
         }
     }
 
+    /// Tests that multiplying two elements of a 256-bit field stays within the
+    /// constraint budget of the lazily reduced multiplication gadget
+    #[test]
+    fn test_mul_constraint_count() {
+        let mut rng = OsRng {};
+        let modulus = BigUint::from(1u8) << 256;
+        let field_mod = FieldMod::from_modulus(modulus);
+
+        let mut prover_transcript = Transcript::new(TRANSCRIPT_SEED.as_bytes());
+        let pc_gens = PedersenGens::default();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let lhs = NonNativeElementVar::from_bigint(
+            random_biguint(&mut rng),
+            field_mod.clone(),
+            &mut prover,
+        );
+        let rhs =
+            NonNativeElementVar::from_bigint(random_biguint(&mut rng), field_mod, &mut prover);
+
+        let n_products = lhs.words.len() * rhs.words.len();
+        let n_result_words = lhs.words.len() + rhs.words.len();
+        assert_eq!(n_products, 9);
+
+        let constraints_pre = prover.num_constraints();
+        let multipliers_pre = prover.num_multipliers();
+        let res = NonNativeElementVar::mul_unreduced(&lhs, &rhs, &mut prover);
+        let new_constraints = prover.num_constraints() - constraints_pre;
+        let new_multipliers = prover.num_multipliers() - multipliers_pre;
+
+        // Each product costs one multiplication gate (two linear constraints) and a
+        // single allocated high word, each result word costs two allocations and one
+        // constraint
+        let max_constraints = 2 * n_products + n_result_words;
+        let max_multipliers = n_products + (n_products + 2 * n_result_words + 1) / 2;
+        assert!(new_constraints <= max_constraints);
+        assert!(new_multipliers <= max_multipliers);
+
+        // The unreduced result should hold the full product of the operands
+        let expected = lhs.as_bigint(&prover) * rhs.as_bigint(&prover);
+        assert_eq!(res.as_bigint(&prover), expected);
+    }
+
     /// Tests multiplying a non-native field element with a bigint
     #[test]
     fn test_mul_bigint() {
@@ -66,6 +66,14 @@ impl PoseidonHashGadget {
         }
     }
 
+    /// Construct a hash gadget parameterized for an input of `arity` elements
+    ///
+    /// Long inputs are hashed with the widest permutation, which absorbs eight elements
+    /// per permutation rather than two
+    pub fn new_for_arity(arity: usize) -> Self {
+        Self::new(PoseidonSpongeParameters::for_arity(arity))
+    }
+
     /// Hashes the given input and constraints the result to equal the expected output
     pub fn hash<L, CS>(
         &mut self,
@@ -392,6 +400,11 @@ impl<'a, N: 'a + MpcNetwork + Send, S: 'a + SharedValueSource<Scalar>>
         }
     }
 
+    /// Construct a hash gadget parameterized for an input of `arity` elements
+    pub fn new_for_arity(arity: usize, fabric: SharedFabric<N, S>) -> Self {
+        Self::new(PoseidonSpongeParameters::for_arity(arity), fabric)
+    }
+
     /// Hashes the payload and then constrains the squeezed output to be the provided
     /// expected output.
     pub fn hash<L, CS>(
@@ -670,7 +683,7 @@ mod single_prover_test {
     use ark_crypto_primitives::sponge::{poseidon::PoseidonSponge, CryptographicSponge};
     use crypto::{
         fields::{prime_field_to_scalar, DalekRistrettoField},
-        hash::default_poseidon_params,
+        hash::{default_poseidon_params, poseidon_params_for_arity},
     };
     use curve25519_dalek::scalar::Scalar;
     use itertools::Itertools;
@@ -714,6 +727,32 @@ mod single_prover_test {
         .unwrap();
     }
 
+    /// Tests hashing a wallet-sized input with the widest parameters
+    #[test]
+    fn test_single_prover_wide_hash() {
+        let mut rng = OsRng {};
+        let n = 24;
+        let random_elems = (0..n).map(|_| rng.next_u64()).collect_vec();
+
+        let mut arkworks_hasher = PoseidonSponge::new(&poseidon_params_for_arity(n));
+        for elem in random_elems.iter() {
+            arkworks_hasher.absorb(&DalekRistrettoField::from(*elem as i128));
+        }
+        let expected_result: DalekRistrettoField =
+            arkworks_hasher.squeeze_field_elements(1 /* num_elements */)[0];
+
+        bulletproof_prove_and_verify::<PoseidonHashGadget>(
+            PoseidonGadgetWitness {
+                preimage: random_elems.into_iter().map(Scalar::from).collect_vec(),
+            },
+            PoseidonGadgetStatement {
+                expected_out: prime_field_to_scalar(&expected_result),
+                params: PoseidonSpongeParameters::for_arity(n),
+            },
+        )
+        .unwrap();
+    }
+
     /// Tests the case in which the pre-image is not correct
     #[test]
     fn test_single_prover_hash_failure() {
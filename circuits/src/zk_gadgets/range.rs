@@ -0,0 +1,146 @@
+//! Groups gadgets for range checks on 32, 64, and 128 bit values
+//!
+//! A value is constrained to a `D` bit range by allocating its low `D` bits, constraining
+//! each to be boolean, and constraining the value to be reconstructed from them
+
+use curve25519_dalek::scalar::Scalar;
+use mpc_bulletproof::r1cs::{LinearCombination, RandomizableConstraintSystem, Variable};
+
+use crate::mpc_gadgets::bits::scalar_to_bits_le;
+
+/// A gadget that constrains values to a `D` bit range
+///
+/// `D` must be one of 32, 64, or 128
+#[derive(Clone, Debug)]
+pub struct RangeCheckGadget<const D: usize> {}
+impl<const D: usize> RangeCheckGadget<D> {
+    /// Constrain the value to lie in [0, 2^D)
+    pub fn constrain_in_range<CS: RandomizableConstraintSystem>(x: Variable, cs: &mut CS) {
+        Self::constrain_decomposition(x.into(), cs)
+    }
+
+    /// Constrain the values to satisfy a >= b, where a - b is at most D bits
+    pub fn constrain_greater_than_eq<CS: RandomizableConstraintSystem>(
+        a: Variable,
+        b: Variable,
+        cs: &mut CS,
+    ) {
+        Self::constrain_decomposition(a - b, cs)
+    }
+
+    /// Allocate the low D bits of the value and constrain them to reconstruct it
+    fn constrain_decomposition<CS: RandomizableConstraintSystem>(
+        value: LinearCombination,
+        cs: &mut CS,
+    ) {
+        assert!(
+            matches!(D, 32 | 64 | 128),
+            "range checks are supported for 32, 64, or 128 bits, got {:?}",
+            D
+        );
+
+        let bits = scalar_to_bits_le(&cs.eval(&value))[..D]
+            .iter()
+            .map(|bit| cs.allocate(Some(*bit)).unwrap())
+            .collect::<Vec<_>>();
+        Self::constrain_bits(value, &bits, cs)
+    }
+
+    /// Constrain the allocated bits to be boolean, and to reconstruct the value
+    ///
+    /// If the value can be reconstructed from D boolean bits, it lies in the range
+    fn constrain_bits<CS: RandomizableConstraintSystem>(
+        value: LinearCombination,
+        bits: &[Variable],
+        cs: &mut CS,
+    ) {
+        let mut res = LinearCombination::default();
+        for bit in bits.iter().rev() {
+            // b * (1 - b) == 0
+            let (_, _, bit_times_complement) = cs.multiply((*bit).into(), Variable::One() - *bit);
+            cs.constrain(bit_times_complement.into());
+
+            res = res * Scalar::from(2u64) + *bit;
+        }
+
+        cs.constrain(res - value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+    use merlin::Transcript;
+    use mpc_bulletproof::{
+        r1cs::{ConstraintSystem, Prover},
+        PedersenGens,
+    };
+    use rand_core::OsRng;
+
+    use super::RangeCheckGadget;
+
+    /// Tests range checks at each supported width on values at and beyond the bounds
+    #[test]
+    fn test_range_check() {
+        let mut rng = OsRng {};
+        let pc_gens = PedersenGens::default();
+
+        let mut transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (_, x) = prover.commit(Scalar::from(u32::MAX), Scalar::random(&mut rng));
+        RangeCheckGadget::<32>::constrain_in_range(x, &mut prover);
+        let (_, x) = prover.commit(Scalar::from(u64::MAX), Scalar::random(&mut rng));
+        RangeCheckGadget::<64>::constrain_in_range(x, &mut prover);
+        let (_, x) = prover.commit(Scalar::from(u128::MAX), Scalar::random(&mut rng));
+        RangeCheckGadget::<128>::constrain_in_range(x, &mut prover);
+        assert!(prover.constraints_satisfied());
+
+        // 2^64 fits in 128 bits but not in 64
+        let mut transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (_, y) = prover.commit(Scalar::from(1u128 << 64), Scalar::random(&mut rng));
+        RangeCheckGadget::<64>::constrain_in_range(y, &mut prover);
+        assert!(!prover.constraints_satisfied());
+    }
+
+    /// Tests that a decomposition into non-boolean "bits" does not satisfy the range check,
+    /// even though it reconstructs the value
+    #[test]
+    fn test_non_boolean_bits() {
+        let mut rng = OsRng {};
+        let pc_gens = PedersenGens::default();
+        let mut transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+        // 2^32 does not fit in 32 bits, but is reconstructed by a low "bit" of 2^32
+        let value = Scalar::from(1u64 << 32);
+        let (_, x) = prover.commit(value, Scalar::random(&mut rng));
+        let mut bits = vec![prover.allocate(Some(value)).unwrap()];
+        bits.extend((1..32).map(|_| prover.allocate(Some(Scalar::zero())).unwrap()));
+
+        RangeCheckGadget::<32>::constrain_bits(x.into(), &bits, &mut prover);
+        assert!(!prover.constraints_satisfied());
+    }
+
+    /// Tests the comparator on valid and invalid inputs
+    #[test]
+    fn test_greater_than_eq() {
+        let mut rng = OsRng {};
+        let pc_gens = PedersenGens::default();
+
+        let mut transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (_, a) = prover.commit(Scalar::from(10u64), Scalar::random(&mut rng));
+        let (_, b) = prover.commit(Scalar::from(7u64), Scalar::random(&mut rng));
+        RangeCheckGadget::<64>::constrain_greater_than_eq(a, b, &mut prover);
+        RangeCheckGadget::<64>::constrain_greater_than_eq(a, a, &mut prover);
+        assert!(prover.constraints_satisfied());
+
+        let mut transcript = Transcript::new("test".as_bytes());
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (_, a) = prover.commit(Scalar::from(7u64), Scalar::random(&mut rng));
+        let (_, b) = prover.commit(Scalar::from(10u64), Scalar::random(&mut rng));
+        RangeCheckGadget::<64>::constrain_greater_than_eq(a, b, &mut prover);
+        assert!(!prover.constraints_satisfied());
+    }
+}
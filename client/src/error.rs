@@ -0,0 +1,46 @@
+//! Groups error types originating from the relayer client
+
+use std::fmt::Display;
+
+use darkpool_relayer::error::ErrorCode;
+
+/// The core error type for the relayer client
+#[derive(Clone, Debug)]
+pub enum ClientError {
+    /// The request could not be sent, or its response could not be read
+    Http(String),
+    /// The relayer rejected the request, or failed to serve it
+    Api {
+        /// The HTTP status of the response
+        status: u16,
+        /// The machine-readable classification of the failure
+        code: ErrorCode,
+        /// A human-readable description of the failure
+        message: String,
+    },
+    /// The relayer answered with an error status and a body that is not an API error,
+    /// e.g. from a proxy in front of the relayer; the status code and response body
+    Status(u16, String),
+    /// A request or response body could not be (de)serialized
+    Serde(String),
+    /// The websocket connection failed, or was closed by the client
+    Websocket(String),
+    /// The relayer URL is invalid
+    Url(String),
+}
+
+impl ClientError {
+    /// The code of the failure, if the relayer classified it
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            ClientError::Api { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -0,0 +1,369 @@
+//! A client for the relayer's HTTP API
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use darkpool_relayer::{
+    api_server::auth::{
+        sign_request, API_KEY_HEADER, NONCE_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    },
+    external_api::http::{
+        order_book::{GetNetworkOrderByIdResponse, GetNetworkOrdersResponse},
+        price_report::{
+            GetExchangeHealthStatesRequest, GetExchangeHealthStatesResponse,
+            GetSignedPriceReportRequest, GetSignedPriceReportResponse, SignedPriceReport,
+        },
+        wallet::{
+            CreateOrderRequest, CreateOrderResponse, ExternalTransferRequest, GetWalletResponse,
+            WalletUpdateResponse,
+        },
+        ApiErrorResponse, PingResponse,
+    },
+    price_reporter::tokens::Token,
+};
+use reqwest::{Method, RequestBuilder, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::error::ClientError;
+
+// ----------
+// | Routes |
+// ----------
+
+/// The route to check that the relayer is up
+const PING_ROUTE: &str = "/v0/ping";
+/// The route to fetch a page of the network order book
+const GET_NETWORK_ORDERS_ROUTE: &str = "/v0/order_book/orders";
+/// The route to check the health of a pair's price feeds
+const EXCHANGE_HEALTH_ROUTE: &str = "/v0/exchange/health_check";
+/// The route to fetch the latest signed median price of a pair
+const SIGNED_PRICE_REPORT_ROUTE: &str = "/v0/price_report/signed";
+
+/// The query param giving the order ID after which to begin a page
+const CURSOR_QUERY_PARAM: &str = "cursor";
+/// The query param giving the maximum number of orders in a page
+const LIMIT_QUERY_PARAM: &str = "limit";
+
+/// The route to fetch a single network order
+fn network_order_route(order_id: &Uuid) -> String {
+    format!("{GET_NETWORK_ORDERS_ROUTE}/{order_id}")
+}
+
+/// The route to fetch a wallet
+fn wallet_route(wallet_id: &Uuid) -> String {
+    format!("/v0/wallet/{wallet_id}")
+}
+
+// ---------------
+// | Credentials |
+// ---------------
+
+/// An API key that the client signs its requests with
+#[derive(Clone)]
+pub struct ApiCredentials {
+    /// The identifier of the key
+    pub key_id: String,
+    /// The secret requests are signed with
+    pub secret: Vec<u8>,
+}
+
+impl std::fmt::Debug for ApiCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Elide the secret so that it does not end up in logs
+        f.debug_struct("ApiCredentials")
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl ApiCredentials {
+    /// Constructor, the secret is given base64 encoded as it is configured on the relayer
+    pub fn new(key_id: String, secret: &str) -> Result<Self, ClientError> {
+        let secret = base64::decode(secret)
+            .map_err(|err| ClientError::Serde(format!("invalid api key secret: {err}")))?;
+        Ok(Self { key_id, secret })
+    }
+
+    /// The authentication headers of a request with the given method, path and query,
+    /// and body
+    pub fn headers(
+        &self,
+        method: &Method,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> Vec<(&'static str, String)> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let nonce = Uuid::new_v4().to_string();
+        let signature = sign_request(
+            &self.secret,
+            method,
+            path_and_query,
+            timestamp,
+            &nonce,
+            body,
+        );
+
+        vec![
+            (API_KEY_HEADER, self.key_id.clone()),
+            (TIMESTAMP_HEADER, timestamp.to_string()),
+            (NONCE_HEADER, nonce),
+            (SIGNATURE_HEADER, signature),
+        ]
+    }
+}
+
+// ----------
+// | Client |
+// ----------
+
+/// A client for the relayer's HTTP API
+#[derive(Clone, Debug)]
+pub struct RelayerClient {
+    /// The base URL of the relayer's HTTP API, e.g. `http://localhost:3000`
+    base_url: Url,
+    /// The credentials requests are signed with, if the relayer requires them
+    credentials: Option<ApiCredentials>,
+    /// The underlying HTTP client
+    http_client: reqwest::Client,
+}
+
+impl RelayerClient {
+    /// Create a client for the relayer at the given base URL
+    pub fn new(base_url: &str, credentials: Option<ApiCredentials>) -> Result<Self, ClientError> {
+        let base_url = Url::parse(base_url).map_err(|err| ClientError::Url(err.to_string()))?;
+        Ok(Self {
+            base_url,
+            credentials,
+            http_client: reqwest::Client::new(),
+        })
+    }
+
+    /// Check that the relayer is up
+    pub async fn ping(&self) -> Result<PingResponse, ClientError> {
+        self.send::<(), _>(Method::GET, PING_ROUTE, &[], None).await
+    }
+
+    /// Fetch a page of the network order book
+    ///
+    /// The page begins after the `cursor` order if one is given; pass the response's
+    /// `next_cursor` to fetch the following page
+    pub async fn get_order_book(
+        &self,
+        cursor: Option<Uuid>,
+        limit: Option<usize>,
+    ) -> Result<GetNetworkOrdersResponse, ClientError> {
+        let mut query = Vec::new();
+        if let Some(cursor) = cursor {
+            query.push((CURSOR_QUERY_PARAM, cursor.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push((LIMIT_QUERY_PARAM, limit.to_string()));
+        }
+
+        self.send::<(), _>(Method::GET, GET_NETWORK_ORDERS_ROUTE, &query, None)
+            .await
+    }
+
+    /// Fetch a single order from the network order book
+    pub async fn get_network_order(
+        &self,
+        order_id: &Uuid,
+    ) -> Result<GetNetworkOrderByIdResponse, ClientError> {
+        self.send::<(), _>(Method::GET, &network_order_route(order_id), &[], None)
+            .await
+    }
+
+    /// Fetch the median price of a pair and the health of each exchange that feeds it
+    pub async fn get_price(
+        &self,
+        base_token: Token,
+        quote_token: Token,
+    ) -> Result<GetExchangeHealthStatesResponse, ClientError> {
+        let req = GetExchangeHealthStatesRequest {
+            base_token,
+            quote_token,
+        };
+        self.send(Method::POST, EXCHANGE_HEALTH_ROUTE, &[], Some(&req))
+            .await
+    }
+
+    /// Fetch the latest median price of a pair signed by the relayer's cluster key
+    ///
+    /// The signature is not checked here; call `SignedPriceReport::verify` with the
+    /// cluster the caller expects the price to come from
+    pub async fn get_signed_price(
+        &self,
+        base_token: Token,
+        quote_token: Token,
+    ) -> Result<SignedPriceReport, ClientError> {
+        let req = GetSignedPriceReportRequest {
+            base_token,
+            quote_token,
+        };
+        let resp: GetSignedPriceReportResponse = self
+            .send(Method::POST, SIGNED_PRICE_REPORT_ROUTE, &[], Some(&req))
+            .await?;
+        Ok(resp.report)
+    }
+
+    /// Fetch a wallet managed by the relayer
+    pub async fn get_wallet(&self, wallet_id: &Uuid) -> Result<GetWalletResponse, ClientError> {
+        self.send::<(), _>(Method::GET, &wallet_route(wallet_id), &[], None)
+            .await
+    }
+
+    /// Place a new order in a wallet
+    ///
+    /// The update's progress is streamed on the returned topic, see
+    /// `RelayerWebsocket::watch_wallet_update`
+    pub async fn create_order(
+        &self,
+        wallet_id: &Uuid,
+        req: &CreateOrderRequest,
+    ) -> Result<CreateOrderResponse, ClientError> {
+        let route = format!("{}/orders", wallet_route(wallet_id));
+        self.send(Method::POST, &route, &[], Some(req)).await
+    }
+
+    /// Deposit into a wallet's balance
+    pub async fn deposit(
+        &self,
+        wallet_id: &Uuid,
+        req: &ExternalTransferRequest,
+    ) -> Result<WalletUpdateResponse, ClientError> {
+        let route = format!("{}/deposit", wallet_route(wallet_id));
+        self.send(Method::POST, &route, &[], Some(req)).await
+    }
+
+    /// Withdraw from a wallet's balance
+    pub async fn withdraw(
+        &self,
+        wallet_id: &Uuid,
+        req: &ExternalTransferRequest,
+    ) -> Result<WalletUpdateResponse, ClientError> {
+        let route = format!("{}/withdraw", wallet_route(wallet_id));
+        self.send(Method::POST, &route, &[], Some(req)).await
+    }
+
+    /// Send a request to the relayer, signing it if the client holds credentials, and
+    /// parse the response
+    async fn send<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        query: &[(&str, String)],
+        body: Option<&Req>,
+    ) -> Result<Resp, ClientError> {
+        let mut url = self
+            .base_url
+            .join(route)
+            .map_err(|err| ClientError::Url(err.to_string()))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        let body = match body {
+            Some(body) => {
+                serde_json::to_vec(body).map_err(|err| ClientError::Serde(err.to_string()))?
+            }
+            None => Vec::new(),
+        };
+
+        let mut request = self.http_client.request(method.clone(), url.clone());
+        request = self.sign(request, &method, &url, &body);
+        if !body.is_empty() {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| ClientError::Http(err.to_string()))?;
+        let status = response.status();
+        let response_body = response
+            .bytes()
+            .await
+            .map_err(|err| ClientError::Http(err.to_string()))?;
+        if !status.is_success() {
+            return Err(parse_error_response(status.as_u16(), &response_body));
+        }
+
+        serde_json::from_slice(&response_body).map_err(|err| ClientError::Serde(err.to_string()))
+    }
+
+    /// Attach the authentication headers to a request, if the client holds credentials
+    fn sign(
+        &self,
+        mut request: RequestBuilder,
+        method: &Method,
+        url: &Url,
+        body: &[u8],
+    ) -> RequestBuilder {
+        if let Some(credentials) = self.credentials.as_ref() {
+            for (header, value) in credentials.headers(method, &path_and_query(url), body) {
+                request = request.header(header, value);
+            }
+        }
+
+        request
+    }
+}
+
+/// Parse the body of an error response, falling back to the raw body if it is not an
+/// API error
+pub(crate) fn parse_error_response(status: u16, body: &[u8]) -> ClientError {
+    match serde_json::from_slice::<ApiErrorResponse>(body) {
+        Ok(ApiErrorResponse { code, message }) => ClientError::Api {
+            status,
+            code,
+            message,
+        },
+        Err(_) => ClientError::Status(status, String::from_utf8_lossy(body).to_string()),
+    }
+}
+
+/// The path and query of a URL, in the form the relayer verifies signatures over
+pub(crate) fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use darkpool_relayer::error::ErrorCode;
+    use reqwest::Url;
+
+    use crate::error::ClientError;
+
+    use super::{parse_error_response, path_and_query};
+
+    /// Tests that signatures cover the query as well as the path
+    #[test]
+    fn test_path_and_query() {
+        let url = Url::parse("http://localhost:3000/v0/order_book/orders?limit=10").unwrap();
+        assert_eq!(path_and_query(&url), "/v0/order_book/orders?limit=10");
+
+        let url = Url::parse("ws://localhost:4000").unwrap();
+        assert_eq!(path_and_query(&url), "/");
+    }
+
+    /// Tests that error bodies are parsed into their code, and that other bodies are kept
+    #[test]
+    fn test_parse_error_response() {
+        let body = br#"{"code":"NOT_FOUND","message":"wallet not found"}"#;
+        assert_eq!(
+            parse_error_response(404, body).error_code(),
+            Some(ErrorCode::NotFound)
+        );
+
+        let err = parse_error_response(502, b"Bad Gateway");
+        assert!(matches!(err, ClientError::Status(502, body) if body == "Bad Gateway"));
+    }
+}
@@ -0,0 +1,52 @@
+//! A typed client for the relayer's HTTP and websocket APIs
+//!
+//! External takers use `RelayerClient` to read the order book and prices and to submit
+//! wallet updates, and `RelayerWebsocket` to stream prices and watch handshakes and wallet
+//! updates as they progress. Both sign their requests when given API credentials.
+//!
+//! The request and response types are those the relayer itself serves, re-exported from
+//! the relayer crate under `types`, so that the client and server cannot drift apart
+#![deny(missing_docs)]
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(unsafe_code)]
+
+pub mod error;
+pub mod http;
+pub mod websocket;
+
+pub use darkpool_relayer::{error::ErrorCode, external_api as types};
+pub use http::{ApiCredentials, RelayerClient};
+pub use websocket::RelayerWebsocket;
+
+/// The topics the relayer publishes to, for use with `RelayerWebsocket::subscribe`
+pub mod topics {
+    use darkpool_relayer::price_reporter::{exchanges::Exchange, tokens::Token};
+
+    pub use darkpool_relayer::types::{
+        wallet_update_topic, EXCHANGE_HEALTH_TOPIC, HANDSHAKE_STATUS_TOPIC,
+        ORDER_STATE_CHANGE_TOPIC, SETTLEMENT_STATUS_TOPIC,
+    };
+
+    /// The topic the median price of a pair is published to
+    pub fn median_price_topic(base_token: &Token, quote_token: &Token) -> String {
+        format!(
+            "median-price-report-{}-{}",
+            base_token.get_addr(),
+            quote_token.get_addr()
+        )
+    }
+
+    /// The topic a single exchange's price of a pair is published to
+    pub fn exchange_price_topic(
+        exchange: Exchange,
+        base_token: &Token,
+        quote_token: &Token,
+    ) -> String {
+        format!(
+            "{}-price-report-{}-{}",
+            exchange,
+            base_token.get_addr(),
+            quote_token.get_addr()
+        )
+    }
+}
@@ -0,0 +1,258 @@
+//! A client for the relayer's websocket API that reconnects when the connection drops
+//!
+//! The connection is held by a background task, which tracks the client's subscriptions
+//! so that it can restore them on each reconnect. Events published while the connection
+//! is down are missed; a client that cannot miss an update should re-read the relevant
+//! state over HTTP once an event resumes the stream
+
+use std::{collections::BTreeSet, time::Duration};
+
+use darkpool_relayer::{
+    external_api::websocket::SubscriptionMessage, price_reporter::tokens::Token,
+    types::SystemBusMessageWithTopic,
+};
+use futures::{SinkExt, StreamExt};
+use reqwest::{Method, Url};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::sleep,
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Message},
+};
+use uuid::Uuid;
+
+use crate::{
+    error::ClientError,
+    http::{path_and_query, ApiCredentials},
+    topics::{median_price_topic, wallet_update_topic, HANDSHAKE_STATUS_TOPIC},
+};
+
+/// The delay before the first reconnect attempt, doubled on each consecutive failure
+const RECONNECT_BASE_DELAY_MS: u64 = 500;
+/// The longest delay between reconnect attempts
+const RECONNECT_MAX_DELAY_MS: u64 = 30_000; // 30 seconds
+
+/// A subscription to the relayer's websocket API, reconnected as needed
+#[derive(Debug)]
+pub struct RelayerWebsocket {
+    /// The queue of subscription changes sent to the connection task
+    command_sender: UnboundedSender<SubscriptionMessage>,
+    /// The events received on the client's subscriptions
+    event_receiver: UnboundedReceiver<SystemBusMessageWithTopic>,
+}
+
+impl RelayerWebsocket {
+    /// Connect to the relayer's websocket API at the given URL, e.g. `ws://localhost:4000`
+    ///
+    /// Returns once the connection task is started; the task connects, and reconnects
+    /// after any failure, until the client is dropped
+    pub fn connect(url: &str, credentials: Option<ApiCredentials>) -> Result<Self, ClientError> {
+        let url = Url::parse(url).map_err(|err| ClientError::Url(err.to_string()))?;
+        let (command_sender, command_receiver) = unbounded_channel();
+        let (event_sender, event_receiver) = unbounded_channel();
+
+        tokio::spawn(
+            ConnectionTask {
+                url,
+                credentials,
+                subscriptions: BTreeSet::new(),
+                command_receiver,
+                event_sender,
+            }
+            .run(),
+        );
+
+        Ok(Self {
+            command_sender,
+            event_receiver,
+        })
+    }
+
+    /// Subscribe to a topic, the subscription is restored on each reconnect
+    pub fn subscribe(&self, topic: String) -> Result<(), ClientError> {
+        self.command_sender
+            .send(SubscriptionMessage::Subscribe { topic })
+            .map_err(|err| ClientError::Websocket(err.to_string()))
+    }
+
+    /// Unsubscribe from a topic
+    pub fn unsubscribe(&self, topic: String) -> Result<(), ClientError> {
+        self.command_sender
+            .send(SubscriptionMessage::Unsubscribe { topic })
+            .map_err(|err| ClientError::Websocket(err.to_string()))
+    }
+
+    /// Stream the median price of a pair
+    pub fn stream_prices(
+        &self,
+        base_token: &Token,
+        quote_token: &Token,
+    ) -> Result<(), ClientError> {
+        self.subscribe(median_price_topic(base_token, quote_token))
+    }
+
+    /// Watch the relayer's handshakes as they start, complete, and time out
+    pub fn watch_handshakes(&self) -> Result<(), ClientError> {
+        self.subscribe(HANDSHAKE_STATUS_TOPIC.to_string())
+    }
+
+    /// Watch the progress of the updates to a wallet
+    pub fn watch_wallet_update(&self, wallet_id: &Uuid) -> Result<(), ClientError> {
+        self.subscribe(wallet_update_topic(wallet_id))
+    }
+
+    /// The next event on any of the client's subscriptions, `None` once the connection
+    /// task has stopped
+    pub async fn next_event(&mut self) -> Option<SystemBusMessageWithTopic> {
+        self.event_receiver.recv().await
+    }
+}
+
+/// The task holding the websocket connection
+struct ConnectionTask {
+    /// The URL of the relayer's websocket API
+    url: Url,
+    /// The credentials the connection is authenticated with, if any
+    credentials: Option<ApiCredentials>,
+    /// The topics subscribed to, restored on each reconnect
+    subscriptions: BTreeSet<String>,
+    /// The queue of subscription changes from the client
+    command_receiver: UnboundedReceiver<SubscriptionMessage>,
+    /// The queue of events to the client
+    event_sender: UnboundedSender<SystemBusMessageWithTopic>,
+}
+
+impl ConnectionTask {
+    /// Hold a connection open until the client is dropped, reconnecting with backoff
+    async fn run(mut self) {
+        let mut failures = 0;
+        loop {
+            match self.run_connection().await {
+                // The client was dropped
+                Ok(()) => return,
+                Err(ConnectionError::Connect) => failures += 1,
+                // The connection was established before it dropped, begin backing off anew
+                Err(ConnectionError::Dropped) => failures = 1,
+            }
+
+            sleep(reconnect_delay(failures)).await;
+            if self.event_sender.is_closed() {
+                return;
+            }
+        }
+    }
+
+    /// Connect, restore the subscriptions, and forward events until the connection drops
+    /// or the client is dropped
+    async fn run_connection(&mut self) -> Result<(), ConnectionError> {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|_| ConnectionError::Connect)?;
+        if let Some(credentials) = self.credentials.as_ref() {
+            for (header, value) in
+                credentials.headers(&Method::GET, &path_and_query(&self.url), &[])
+            {
+                let value = HeaderValue::from_str(&value).map_err(|_| ConnectionError::Connect)?;
+                request.headers_mut().insert(header, value);
+            }
+        }
+
+        let (stream, _) = connect_async(request)
+            .await
+            .map_err(|_| ConnectionError::Connect)?;
+        let (mut write_stream, mut read_stream) = stream.split();
+
+        for topic in self.subscriptions.iter() {
+            let message = SubscriptionMessage::Subscribe {
+                topic: topic.clone(),
+            };
+            write_stream
+                .send(encode(&message)?)
+                .await
+                .map_err(|_| ConnectionError::Dropped)?;
+        }
+
+        loop {
+            tokio::select! {
+                command = self.command_receiver.recv() => {
+                    let command = match command {
+                        Some(command) => command,
+                        None => return Ok(()),
+                    };
+
+                    match &command {
+                        SubscriptionMessage::Subscribe { topic } => {
+                            self.subscriptions.insert(topic.clone());
+                        }
+                        SubscriptionMessage::Unsubscribe { topic } => {
+                            self.subscriptions.remove(topic);
+                        }
+                    }
+                    write_stream
+                        .send(encode(&command)?)
+                        .await
+                        .map_err(|_| ConnectionError::Dropped)?;
+                },
+
+                message = read_stream.next() => {
+                    let message = match message {
+                        Some(Ok(message)) => message,
+                        Some(Err(_)) => return Err(ConnectionError::Dropped),
+                        None => return Err(ConnectionError::Dropped),
+                    };
+
+                    // Subscription acknowledgements and control frames are not forwarded
+                    let event = match message {
+                        Message::Text(text) => serde_json::from_str::<SystemBusMessageWithTopic>(&text).ok(),
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        if self.event_sender.send(event).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The ways a connection ends
+enum ConnectionError {
+    /// The connection could not be established
+    Connect,
+    /// The connection was established, then dropped
+    Dropped,
+}
+
+/// Encode a subscription message as a websocket frame
+fn encode(message: &SubscriptionMessage) -> Result<Message, ConnectionError> {
+    serde_json::to_string(message)
+        .map(Message::Text)
+        .map_err(|_| ConnectionError::Connect)
+}
+
+/// The delay before the next reconnect after the given number of consecutive failures
+fn reconnect_delay(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    Duration::from_millis((RECONNECT_BASE_DELAY_MS << exponent).min(RECONNECT_MAX_DELAY_MS))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::reconnect_delay;
+
+    /// Tests that reconnects back off exponentially up to the maximum delay
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(reconnect_delay(1), Duration::from_millis(500));
+        assert_eq!(reconnect_delay(3), Duration::from_millis(2_000));
+        assert_eq!(reconnect_delay(100), Duration::from_secs(30));
+    }
+}
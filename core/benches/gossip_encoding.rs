@@ -0,0 +1,117 @@
+//! Benchmarks the encodings of heartbeats sent between peers
+//!
+//! Compares the JSON encoding used by protocol versions before `0.2.0` against the binary
+//! encoding negotiated from `0.2.0` onwards. The size of each encoded heartbeat is printed
+//! before it is benchmarked, as the bandwidth of the heartbeat path is the quantity the
+//! binary encoding reduces; the benchmarks measure the time spent encoding and decoding
+
+use std::collections::{HashMap, HashSet};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use darkpool_relayer::{
+    gossip::types::{ClusterId, PeerInfo, WrappedPeerId},
+    gossip_api::{
+        codec::{decode_message, encode_message, WireEncoding},
+        gossip::{AuthenticatedGossipRequest, GossipRequest},
+        heartbeat::HeartbeatMessage,
+    },
+    state::wallet::WalletMetadata,
+};
+use ed25519_dalek::Keypair as SigKeypair;
+use libp2p::{identity::Keypair, Multiaddr, PeerId};
+use rand_core::OsRng;
+use uuid::Uuid;
+
+/// The numbers of known peers, orders, and wallets in each benchmarked heartbeat
+const HEARTBEAT_SIZES: [usize; 3] = [10, 100, 1_000];
+/// The maximum size of a decoded message
+const MAX_MESSAGE_SIZE: usize = 1_000_000_000;
+
+/// Build a signed heartbeat holding `n` known peers, orders, and managed wallets
+fn build_heartbeat(n: usize) -> AuthenticatedGossipRequest {
+    let cluster_keypair = SigKeypair::generate(&mut OsRng {});
+    let cluster_id = ClusterId::new(&cluster_keypair.public);
+    let addr: Multiaddr = "/ip4/127.0.0.1/tcp/8000".parse().unwrap();
+
+    let known_peers = (0..n)
+        .map(|_| {
+            let peer_id = WrappedPeerId(PeerId::random());
+            let info = PeerInfo::new_with_cluster_secret_key(
+                peer_id,
+                cluster_id.clone(),
+                addr.clone(),
+                &cluster_keypair,
+            );
+            (peer_id.to_string(), info)
+        })
+        .collect();
+    let managed_wallets = (0..n)
+        .map(|_| {
+            let metadata = WalletMetadata {
+                replicas: HashSet::from([WrappedPeerId(PeerId::random())]),
+                version: 1,
+                auto_resubmit: HashMap::new(),
+            };
+            (Uuid::new_v4(), metadata)
+        })
+        .collect();
+
+    let mut heartbeat = HeartbeatMessage {
+        managed_wallets,
+        known_peers,
+        orders: (0..n)
+            .map(|_| (Uuid::new_v4(), cluster_id.clone()))
+            .collect(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        sequence: 0,
+        timestamp: 0,
+        public_key: Vec::new(),
+        signature: Vec::new(),
+    };
+    let keypair = Keypair::generate_ed25519();
+    heartbeat.sign(1 /* sequence */, 1_000 /* timestamp */, &keypair);
+
+    AuthenticatedGossipRequest {
+        sig: Vec::new(),
+        body: GossipRequest::Heartbeat(heartbeat),
+    }
+}
+
+/// Benchmark encoding and decoding heartbeats of increasing size in each encoding
+fn bench_heartbeat_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heartbeat_encoding");
+
+    for n in HEARTBEAT_SIZES.iter() {
+        let heartbeat = build_heartbeat(*n);
+        for (name, encoding) in [
+            ("json", WireEncoding::Json),
+            ("binary", WireEncoding::Binary),
+        ] {
+            let encoded = encode_message(encoding, &heartbeat).unwrap();
+            println!(
+                "{name} heartbeat of {n} peers, orders, and wallets: {} bytes",
+                encoded.len()
+            );
+            group.throughput(Throughput::Bytes(encoded.len() as u64));
+
+            group.bench_with_input(BenchmarkId::new(format!("{name}_encode"), n), n, |b, _| {
+                b.iter(|| encode_message(encoding, &heartbeat).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new(format!("{name}_decode"), n), n, |b, _| {
+                b.iter(|| {
+                    decode_message::<AuthenticatedGossipRequest>(
+                        encoding,
+                        &encoded,
+                        MAX_MESSAGE_SIZE,
+                    )
+                    .unwrap()
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_heartbeat_encoding);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+//! Benchmarks the throughput of handshake jobs contending on the shared handshake cache
+//!
+//! Compares the single `RwLock`-guarded cache the executor previously shared between jobs
+//! against the sharded cache it now uses. Each job performs the cache operations of a
+//! handshake: a lookup when choosing a pair, marking the pair invisible for the MPC, and
+//! marking it completed once matched
+
+use std::{sync::Arc, time::Duration};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use darkpool_relayer::{
+    clock::system_clock,
+    handshake::handshake_cache::{HandshakeCache, ShardedHandshakeCache, HANDSHAKE_CACHE_SHARDS},
+};
+use tokio::{runtime::Builder as RuntimeBuilder, sync::RwLock};
+
+/// The capacity of the cache in each benchmark
+const CACHE_SIZE: usize = 1000;
+/// The numbers of concurrent jobs to benchmark
+const NUM_JOBS: [u64; 3] = [100, 1_000, 10_000];
+
+/// Run the cache operations of a single handshake job against the baseline cache
+async fn locked_job(cache: Arc<RwLock<HandshakeCache<u64>>>, job: u64) {
+    let (o1, o2) = (job, job + 1);
+    if cache.read().await.contains(o1, o2) {
+        return;
+    }
+
+    cache
+        .write()
+        .await
+        .mark_invisible(o1, o2, Duration::from_secs(30));
+    cache.write().await.mark_completed(o1, o2);
+}
+
+/// Run the cache operations of a single handshake job against the sharded cache
+async fn sharded_job(cache: Arc<ShardedHandshakeCache<u64>>, job: u64) {
+    let (o1, o2) = (job, job + 1);
+    if cache.contains(o1, o2) {
+        return;
+    }
+
+    cache.mark_invisible(o1, o2, Duration::from_secs(30));
+    cache.mark_completed(o1, o2);
+}
+
+/// Benchmark both caches under an increasing number of concurrent jobs
+fn bench_handshake_dispatch(c: &mut Criterion) {
+    let runtime = RuntimeBuilder::new_multi_thread().build().unwrap();
+    let mut group = c.benchmark_group("handshake_dispatch");
+
+    for num_jobs in NUM_JOBS.iter() {
+        group.throughput(Throughput::Elements(*num_jobs));
+
+        group.bench_with_input(BenchmarkId::new("rwlock", num_jobs), num_jobs, |b, n| {
+            b.to_async(&runtime).iter(|| async move {
+                let cache = Arc::new(RwLock::new(HandshakeCache::new(CACHE_SIZE, system_clock())));
+                let handles = (0..*n)
+                    .map(|job| tokio::spawn(locked_job(cache.clone(), job)))
+                    .collect::<Vec<_>>();
+                for handle in handles.into_iter() {
+                    handle.await.unwrap();
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("sharded", num_jobs), num_jobs, |b, n| {
+            b.to_async(&runtime).iter(|| async move {
+                let cache = Arc::new(ShardedHandshakeCache::new(
+                    CACHE_SIZE,
+                    HANDSHAKE_CACHE_SHARDS,
+                    system_clock(),
+                ));
+                let handles = (0..*n)
+                    .map(|job| tokio::spawn(sharded_job(cache.clone(), job)))
+                    .collect::<Vec<_>>();
+                for handle in handles.into_iter() {
+                    handle.await.unwrap();
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_handshake_dispatch);
+criterion_main!(benches);
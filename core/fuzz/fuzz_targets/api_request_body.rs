@@ -0,0 +1,42 @@
+//! Fuzzes the deserialization of HTTP API request bodies
+#![no_main]
+
+use darkpool_relayer::external_api::{
+    http::{
+        admin::UpdateClusterAccessRequest,
+        deserialize_request_body,
+        price_report::GetExchangeHealthStatesRequest,
+        wallet::{CreateOrderRequest, ExternalTransferRequest, SetAutoResubmitRequest},
+    },
+    EmptyRequestResponse,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte selects the request type, the rest of the input is the body
+    let (selector, body) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    match selector % 6 {
+        0 => {
+            let _ = deserialize_request_body::<EmptyRequestResponse>(body);
+        }
+        1 => {
+            let _ = deserialize_request_body::<CreateOrderRequest>(body);
+        }
+        2 => {
+            let _ = deserialize_request_body::<ExternalTransferRequest>(body);
+        }
+        3 => {
+            let _ = deserialize_request_body::<GetExchangeHealthStatesRequest>(body);
+        }
+        4 => {
+            let _ = deserialize_request_body::<SetAutoResubmitRequest>(body);
+        }
+        _ => {
+            let _ = deserialize_request_body::<UpdateClusterAccessRequest>(body);
+        }
+    }
+});
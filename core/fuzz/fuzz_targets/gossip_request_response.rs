@@ -0,0 +1,20 @@
+//! Fuzzes the deserialization of gossip requests and responses read off of a substream
+#![no_main]
+
+use darkpool_relayer::gossip_api::gossip::{
+    AuthenticatedGossipRequest, AuthenticatedGossipResponse,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Both types are read from the same length-prefixed frame, so every input is tried as each
+    if let Ok(request) = serde_json::from_slice::<AuthenticatedGossipRequest>(data) {
+        let _ = request.body.requires_cluster_auth();
+        let _ = serde_json::to_vec(&request);
+    }
+
+    if let Ok(response) = serde_json::from_slice::<AuthenticatedGossipResponse>(data) {
+        let _ = response.body.requires_cluster_auth();
+        let _ = serde_json::to_vec(&response);
+    }
+});
@@ -0,0 +1,19 @@
+//! Fuzzes the deserialization of pubsub messages received from the gossip network
+#![no_main]
+
+use std::convert::TryFrom;
+
+use darkpool_relayer::gossip_api::cluster_encryption::PubsubEnvelope;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Re-serialize any message that parses, the sender's bytes are hashed for cluster
+    // authentication in the same way
+    if let Ok(envelope) = PubsubEnvelope::try_from(data.to_vec()) {
+        if let PubsubEnvelope::Plaintext(message) = &envelope {
+            let _ = message.body.requires_cluster_auth();
+            let _ = message.body.requires_encryption();
+        }
+        let _: Vec<u8> = envelope.into();
+    }
+});
@@ -0,0 +1,451 @@
+//! Authenticates requests to the HTTP and websocket APIs
+//!
+//! Each API key is configured with a secret and a permission scope. A client signs a
+//! request by computing an HMAC-SHA256, keyed by the secret, over the canonical form:
+//!     <method>\n<path and query>\n<timestamp>\n<nonce>\n<body>
+//! and attaching the key ID, timestamp (in milliseconds since the unix epoch), nonce, and
+//! base64 encoded signature as headers. A request is rejected if its timestamp is outside
+//! of the allowed skew, or if its nonce has already been used by the same key within that
+//! window
+//!
+//! If no API keys are configured, public and read-only routes are served without
+//! authentication, and wallet-mutating and admin routes are refused outright
+
+use hmac_sha256::HMAC;
+use hyper::{HeaderMap, Method, StatusCode};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::clock::SharedClock;
+
+use super::error::ApiServerError;
+
+/// The header carrying the ID of the API key a request is signed with
+pub const API_KEY_HEADER: &str = "x-renegade-api-key";
+/// The header carrying the time a request was signed at, in milliseconds since the epoch
+pub const TIMESTAMP_HEADER: &str = "x-renegade-timestamp";
+/// The header carrying the nonce a request was signed with
+pub const NONCE_HEADER: &str = "x-renegade-nonce";
+/// The header carrying the base64 encoded signature of a request
+pub const SIGNATURE_HEADER: &str = "x-renegade-signature";
+
+/// The maximum difference between a request's timestamp and the local clock
+const MAX_TIMESTAMP_SKEW: Duration = Duration::from_secs(30);
+/// The maximum length of a request nonce
+const MAX_NONCE_LEN: usize = 64;
+
+/// Error message emitted when a request is missing an authentication header
+const ERR_MISSING_HEADER: &str = "missing authentication header";
+/// Error message emitted when a request is signed with an unknown API key
+const ERR_UNKNOWN_KEY: &str = "unknown api key";
+/// Error message emitted when a request's timestamp is malformed or outside the allowed skew
+const ERR_INVALID_TIMESTAMP: &str = "request timestamp is invalid or expired";
+/// Error message emitted when a request's nonce is malformed
+const ERR_INVALID_NONCE: &str = "request nonce is invalid";
+/// Error message emitted when a request's signature does not verify
+const ERR_INVALID_SIGNATURE: &str = "invalid request signature";
+/// Error message emitted when a request's nonce has already been used
+const ERR_REPLAYED_NONCE: &str = "request nonce has already been used";
+/// Error message emitted when an API key is not scoped for a route
+const ERR_INSUFFICIENT_PERMISSION: &str = "api key does not have permission for this route";
+/// Error message emitted when a route that requires a key is requested with none configured
+const ERR_NO_KEYS_CONFIGURED: &str = "no api keys are configured, this route is disabled";
+
+// ---------
+// | Types |
+// ---------
+
+/// The permission scope required by a route, and granted to an API key
+///
+/// Scopes are ordered; a key may access any route that requires at most its own scope
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiPermission {
+    /// The route is served without authentication
+    Public,
+    /// The route reads relayer or wallet state
+    ReadOnly,
+    /// The route updates a wallet
+    WalletMutating,
+    /// The route administers the relayer
+    Admin,
+}
+
+impl FromStr for ApiPermission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::ReadOnly),
+            "wallet" => Ok(Self::WalletMutating),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!(
+                "unknown api key permission {s}, expected one of read, wallet, admin"
+            )),
+        }
+    }
+}
+
+/// An API key that requests may be signed with
+#[derive(Clone)]
+pub struct ApiKey {
+    /// The identifier of the key, sent in the clear with each request
+    pub id: String,
+    /// The secret that requests are signed with
+    pub secret: Vec<u8>,
+    /// The permission scope granted to the key
+    pub permission: ApiPermission,
+}
+
+impl Debug for ApiKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        // Elide the secret so that it does not end up in logs
+        f.debug_struct("ApiKey")
+            .field("id", &self.id)
+            .field("permission", &self.permission)
+            .finish()
+    }
+}
+
+impl FromStr for ApiKey {
+    type Err = String;
+
+    /// Parse a key of the form `<key_id>:<base64 secret>:<permission>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() != 3 {
+            return Err(format!(
+                "api key must be of the form <key_id>:<base64 secret>:<permission>, got {s}"
+            ));
+        }
+
+        let id = parts[0].to_string();
+        if id.is_empty() {
+            return Err("api key ID must be non-empty".to_string());
+        }
+
+        let secret = base64::decode(parts[1])
+            .map_err(|err| format!("api key {id} has an invalid secret: {err}"))?;
+        if secret.is_empty() {
+            return Err(format!("api key {id} has an empty secret"));
+        }
+
+        let permission = ApiPermission::from_str(parts[2])?;
+        Ok(Self {
+            id,
+            secret,
+            permission,
+        })
+    }
+}
+
+/// Sign a request with the given secret, returns the base64 encoded signature
+pub fn sign_request(
+    secret: &[u8],
+    method: &Method,
+    path_and_query: &str,
+    timestamp: u64,
+    nonce: &str,
+    body: &[u8],
+) -> String {
+    let mut payload = format!("{method}\n{path_and_query}\n{timestamp}\n{nonce}\n").into_bytes();
+    payload.extend_from_slice(body);
+
+    base64::encode(HMAC::mac(&payload, secret))
+}
+
+/// Compare two byte strings in time independent of where they first differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+// -----------------
+// | Authenticator |
+// -----------------
+
+/// The nonces used by each key within the timestamp skew window
+#[derive(Debug, Default)]
+struct SeenNonces {
+    /// The (key ID, nonce) pairs that have been used
+    nonces: HashSet<(String, String)>,
+    /// The used pairs in the order they were recorded, with the time they expire at
+    expiries: VecDeque<(Duration, (String, String))>,
+}
+
+impl SeenNonces {
+    /// Record a nonce, returns false if it has already been used by the key
+    fn record(&mut self, key_id: &str, nonce: &str, now: Duration) -> bool {
+        // Prune the nonces whose requests would now be rejected by their timestamp anyway
+        while self
+            .expiries
+            .front()
+            .map_or(false, |(expiry, _)| *expiry <= now)
+        {
+            let (_, pair) = self.expiries.pop_front().unwrap();
+            self.nonces.remove(&pair);
+        }
+
+        let pair = (key_id.to_string(), nonce.to_string());
+        if !self.nonces.insert(pair.clone()) {
+            return false;
+        }
+
+        // A request may be signed up to the skew window in the future, so its nonce is held
+        // for twice the window
+        self.expiries
+            .push_back((now + 2 * MAX_TIMESTAMP_SKEW, pair));
+        true
+    }
+}
+
+/// Verifies the signatures and permission scopes of API requests
+#[derive(Clone)]
+pub struct ApiAuthenticator {
+    /// The configured API keys, indexed by ID
+    keys: Arc<HashMap<String, ApiKey>>,
+    /// The nonces used within the skew window, for replay protection
+    seen_nonces: Arc<Mutex<SeenNonces>>,
+    /// The clock that request timestamps are checked against
+    clock: SharedClock,
+}
+
+impl Debug for ApiAuthenticator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ApiAuthenticator")
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ApiAuthenticator {
+    /// Constructor
+    pub fn new(keys: Vec<ApiKey>, clock: SharedClock) -> Self {
+        let keys = keys.into_iter().map(|key| (key.id.clone(), key)).collect();
+        Self {
+            keys: Arc::new(keys),
+            seen_nonces: Arc::new(Mutex::new(SeenNonces::default())),
+            clock,
+        }
+    }
+
+    /// Whether authentication is enabled, i.e. whether any API keys are configured
+    pub fn enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Authenticate a request against the permission its route requires
+    ///
+    /// Returns an HTTP 401 error if the request is not validly signed, and an HTTP 403 error
+    /// if the key it is signed with is not scoped for the route
+    ///
+    /// With no keys configured, routes above the read-only scope fail closed with an HTTP
+    /// 403 error, rather than being served to any caller
+    pub fn authenticate(
+        &self,
+        required: ApiPermission,
+        method: &Method,
+        path_and_query: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), ApiServerError> {
+        if required == ApiPermission::Public {
+            return Ok(());
+        }
+
+        if !self.enabled() {
+            if required <= ApiPermission::ReadOnly {
+                return Ok(());
+            }
+
+            return Err(ApiServerError::HttpStatusCode(
+                StatusCode::FORBIDDEN,
+                ERR_NO_KEYS_CONFIGURED.to_string(),
+            ));
+        }
+
+        let key_id = Self::get_header(headers, API_KEY_HEADER)?;
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| Self::unauthorized(ERR_UNKNOWN_KEY))?;
+
+        // Check the timestamp against the local clock
+        let timestamp: u64 = Self::get_header(headers, TIMESTAMP_HEADER)?
+            .parse()
+            .map_err(|_| Self::unauthorized(ERR_INVALID_TIMESTAMP))?;
+        let now = self.clock.unix_time();
+        let skew = Duration::from_millis(timestamp)
+            .checked_sub(now)
+            .unwrap_or_else(|| now - Duration::from_millis(timestamp));
+        if skew > MAX_TIMESTAMP_SKEW {
+            return Err(Self::unauthorized(ERR_INVALID_TIMESTAMP));
+        }
+
+        let nonce = Self::get_header(headers, NONCE_HEADER)?;
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Err(Self::unauthorized(ERR_INVALID_NONCE));
+        }
+
+        // Verify the signature
+        let signature = Self::get_header(headers, SIGNATURE_HEADER)?;
+        let expected = sign_request(&key.secret, method, path_and_query, timestamp, nonce, body);
+        if !constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+            return Err(Self::unauthorized(ERR_INVALID_SIGNATURE));
+        }
+
+        if key.permission < required {
+            return Err(ApiServerError::HttpStatusCode(
+                StatusCode::FORBIDDEN,
+                ERR_INSUFFICIENT_PERMISSION.to_string(),
+            ));
+        }
+
+        // Record the nonce only once the request is known to be validly signed, so that
+        // unauthenticated requests cannot fill the nonce cache
+        if !self
+            .seen_nonces
+            .lock()
+            .expect("nonce cache lock poisoned")
+            .record(key_id, nonce, now)
+        {
+            return Err(Self::unauthorized(ERR_REPLAYED_NONCE));
+        }
+
+        Ok(())
+    }
+
+    /// Read an authentication header from a request
+    fn get_header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, ApiServerError> {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Self::unauthorized(&format!("{ERR_MISSING_HEADER}: {name}")))
+    }
+
+    /// Build an HTTP 401 error with the given message
+    fn unauthorized(message: &str) -> ApiServerError {
+        ApiServerError::HttpStatusCode(StatusCode::UNAUTHORIZED, message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{HeaderMap, Method, StatusCode};
+    use std::{str::FromStr, sync::Arc, time::Duration};
+
+    use crate::{api_server::error::ApiServerError, clock::ManualClock};
+
+    use super::{
+        sign_request, ApiAuthenticator, ApiKey, ApiPermission, API_KEY_HEADER, NONCE_HEADER,
+        SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    };
+
+    /// The secret of the key used in tests
+    const SECRET: &[u8] = b"secret";
+    /// The path requests are made to in tests
+    const PATH: &str = "/v0/wallet/1/orders";
+
+    /// Build the headers of a request signed by the given key
+    fn signed_headers(key_id: &str, timestamp: u64, nonce: &str, body: &[u8]) -> HeaderMap {
+        let signature = sign_request(SECRET, &Method::POST, PATH, timestamp, nonce, body);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, key_id.parse().unwrap());
+        headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers.insert(NONCE_HEADER, nonce.parse().unwrap());
+        headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    /// Extract the status code of an authentication result
+    fn status(res: Result<(), ApiServerError>) -> Option<StatusCode> {
+        match res {
+            Ok(()) => None,
+            Err(ApiServerError::HttpStatusCode(status, _)) => Some(status),
+            Err(err) => panic!("unexpected error {err}"),
+        }
+    }
+
+    /// Tests that validly signed requests are accepted once, and that replays, stale
+    /// timestamps, tampered bodies, and out of scope routes are rejected
+    #[test]
+    fn test_authenticate() {
+        let clock = ManualClock::new(Duration::from_secs(1_000_000));
+        let key = ApiKey::from_str(&format!("reader:{}:read", base64::encode(SECRET))).unwrap();
+        let authenticator = ApiAuthenticator::new(vec![key], Arc::new(clock.clone()));
+
+        let now = 1_000_000_000;
+        let body = b"{}";
+        let headers = signed_headers("reader", now, "nonce1", body);
+        let authenticate = |headers: &HeaderMap, body: &[u8], required| {
+            authenticator.authenticate(required, &Method::POST, PATH, headers, body)
+        };
+
+        assert_eq!(
+            status(authenticate(&headers, body, ApiPermission::ReadOnly)),
+            None
+        );
+        assert_eq!(
+            status(authenticate(&headers, body, ApiPermission::ReadOnly)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+
+        // A tampered body fails to verify
+        let headers = signed_headers("reader", now, "nonce2", body);
+        assert_eq!(
+            status(authenticate(
+                &headers,
+                b"{\"a\":1}",
+                ApiPermission::ReadOnly
+            )),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+
+        // A read-only key may not access wallet-mutating routes
+        assert_eq!(
+            status(authenticate(&headers, body, ApiPermission::WalletMutating)),
+            Some(StatusCode::FORBIDDEN)
+        );
+
+        // A stale timestamp is rejected
+        clock.advance(Duration::from_secs(60));
+        let headers = signed_headers("reader", now, "nonce3", body);
+        assert_eq!(
+            status(authenticate(&headers, body, ApiPermission::ReadOnly)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+
+        // Public routes and unknown keys
+        assert_eq!(
+            status(authenticate(&HeaderMap::new(), body, ApiPermission::Public)),
+            None
+        );
+        let headers = signed_headers("unknown", now + 60_000, "nonce4", body);
+        assert_eq!(
+            status(authenticate(&headers, body, ApiPermission::ReadOnly)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    /// Tests parsing API keys from their config representation
+    #[test]
+    fn test_parse_api_key() {
+        let key = ApiKey::from_str("trader:c2VjcmV0:wallet").unwrap();
+        assert_eq!(key.id, "trader");
+        assert_eq!(key.secret, SECRET.to_vec());
+        assert_eq!(key.permission, ApiPermission::WalletMutating);
+
+        assert!(ApiKey::from_str("trader:c2VjcmV0").is_err());
+        assert!(ApiKey::from_str("trader:c2VjcmV0:write").is_err());
+        assert!(ApiKey::from_str(":c2VjcmV0:read").is_err());
+        assert!(ApiKey::from_str("trader:!!:read").is_err());
+    }
+}
@@ -4,6 +4,17 @@ use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use hyper::StatusCode;
 
+use crate::{
+    error::{ErrorCode, ErrorCoded},
+    external_api::http::ApiErrorResponse,
+    job_queue::JobQueueError,
+};
+
+/// The error message returned when a worker's job queue is full
+const ERR_QUEUE_FULL: &str = "relayer is overloaded, try again later";
+/// The error message returned when a worker's job queue has closed
+const ERR_QUEUE_CLOSED: &str = "worker is not running";
+
 /// The error type for errors that occur during ApiServer execution
 #[derive(Clone, Debug)]
 pub enum ApiServerError {
@@ -17,8 +28,66 @@ pub enum ApiServerError {
     WebsocketServerFailure(String),
 }
 
+impl std::error::Error for ApiServerError {}
 impl Display for ApiServerError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         write!(f, "{:?}", self)
     }
 }
+
+impl ApiServerError {
+    /// The HTTP status that the error is returned to the client with
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiServerError::HttpStatusCode(status, _) => *status,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The body of the response that the error is returned to the client with
+    pub fn to_response_body(&self) -> ApiErrorResponse {
+        let message = match self {
+            ApiServerError::HttpStatusCode(_, message) => message.clone(),
+            _ => self.to_string(),
+        };
+
+        ApiErrorResponse {
+            code: self.error_code(),
+            message,
+        }
+    }
+}
+
+impl<T> From<JobQueueError<T>> for ApiServerError {
+    fn from(err: JobQueueError<T>) -> Self {
+        match err {
+            JobQueueError::Full(_) => ApiServerError::HttpStatusCode(
+                StatusCode::TOO_MANY_REQUESTS,
+                ERR_QUEUE_FULL.to_string(),
+            ),
+            JobQueueError::Closed(_) => ApiServerError::HttpStatusCode(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ERR_QUEUE_CLOSED.to_string(),
+            ),
+        }
+    }
+}
+
+impl ErrorCoded for ApiServerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ApiServerError::HttpStatusCode(status, _) => match *status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorCode::Unauthorized,
+                StatusCode::NOT_FOUND => ErrorCode::NotFound,
+                StatusCode::CONFLICT => ErrorCode::Conflict,
+                StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+                StatusCode::SERVICE_UNAVAILABLE => ErrorCode::Config,
+                status if status.is_client_error() => ErrorCode::BadRequest,
+                _ => ErrorCode::Internal,
+            },
+            ApiServerError::HttpServerFailure(_) => ErrorCode::Network,
+            ApiServerError::Setup(_) => ErrorCode::Setup,
+            ApiServerError::WebsocketServerFailure(_) => ErrorCode::Network,
+        }
+    }
+}
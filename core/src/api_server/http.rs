@@ -11,46 +11,91 @@ use std::{
     convert::Infallible,
     net::SocketAddr,
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use uuid::Uuid;
 
 use crate::{
-    external_api::{http::PingResponse, EmptyRequestResponse},
+    clock::system_clock,
+    external_api::{
+        http::{
+            identity::{GetIdentityResponse, IdentityAttestation},
+            InfoResponse, PingResponse,
+        },
+        EmptyRequestResponse,
+    },
     gossip::types::{ClusterId, WrappedPeerId},
     state::RelayerState,
+    worker::WorkerCommand,
 };
 
 use self::{
+    admin::{
+        AdminShutdownHandler, ControlWorkerHandler, DryRunProofHandler, ExportOrderBookHandler,
+        GetAuditLogHandler, GetClusterAccessHandler, GetDeadLettersHandler, GetFeatureFlagsHandler,
+        GetLogFilterHandler, GetSettlementHandler, GetSettlementsHandler,
+        GetSystemBusMetricsHandler, GetWorkerStatusHandler, RecoverWalletHandler,
+        UpdateClusterAccessHandler, UpdateFeatureFlagHandler, UpdateLogFilterHandler,
+        ADMIN_SHUTDOWN_ROUTE, CLUSTER_ACCESS_ROUTE, DRY_RUN_PROOF_ROUTE, EXPORT_ORDER_BOOK_ROUTE,
+        FEATURE_FLAGS_ROUTE, GET_AUDIT_LOG_ROUTE, GET_DEAD_LETTERS_ROUTE, GET_SETTLEMENTS_ROUTE,
+        GET_SETTLEMENT_ROUTE, LOG_FILTER_ROUTE, PAUSE_WORKER_ROUTE, RECOVER_WALLET_ROUTE,
+        RESUME_WORKER_ROUTE, SYSTEM_BUS_METRICS_ROUTE, WORKER_STATUS_ROUTE,
+    },
+    handshake::{
+        CancelHandshakeHandler, GetHandshakeHandler, GetHandshakesHandler, CANCEL_HANDSHAKE_ROUTE,
+        GET_HANDSHAKES_ROUTE, GET_HANDSHAKE_ROUTE,
+    },
     network::{
-        GetClusterInfoHandler, GetNetworkTopologyHandler, GetPeerInfoHandler,
-        GET_CLUSTER_INFO_ROUTE, GET_NETWORK_TOPOLOGY_ROUTE, GET_PEER_INFO_ROUTE,
+        GetClusterInfoHandler, GetNetworkTopologyHandler, GetNetworkVersionsHandler,
+        GetPeerInfoHandler, GET_CLUSTER_INFO_ROUTE, GET_NETWORK_TOPOLOGY_ROUTE,
+        GET_NETWORK_VERSIONS_ROUTE, GET_PEER_INFO_ROUTE,
     },
     order_book::{
-        GetNetworkOrderByIdHandler, GetNetworkOrdersHandler, GET_NETWORK_ORDERS_ROUTE,
-        GET_NETWORK_ORDER_BY_ID_ROUTE,
+        CrossPreviewHandler, GetLiquidityHandler, GetNetworkOrderByIdHandler,
+        GetNetworkOrdersHandler, CROSS_PREVIEW_ROUTE, GET_LIQUIDITY_ROUTE,
+        GET_NETWORK_ORDERS_ROUTE, GET_NETWORK_ORDER_BY_ID_ROUTE,
+    },
+    price_report::{
+        ExchangeHealthStatesHandler, PriceCandlesHandler, PriceHistoryHandler,
+        SignedPriceReportHandler, EXCHANGE_HEALTH_ROUTE, PRICE_CANDLES_ROUTE, PRICE_HISTORY_ROUTE,
+        SIGNED_PRICE_REPORT_ROUTE,
     },
-    price_report::{ExchangeHealthStatesHandler, EXCHANGE_HEALTH_ROUTE},
     wallet::{
         GetBalanceByMintHandler, GetBalancesHandler, GetFeesHandler, GetOrderByIdHandler,
-        GetOrdersHandler, GetWalletHandler, GET_BALANCES_ROUTE, GET_BALANCE_BY_MINT_ROUTE,
-        GET_FEES_ROUTE, GET_ORDERS_ROUTE, GET_ORDER_BY_ID_ROUTE, GET_WALLET_ROUTE,
+        GetOrderSlotsHandler, GetOrdersHandler, GetWalletHandler, SetAutoResubmitHandler,
+        GET_BALANCES_ROUTE, GET_BALANCE_BY_MINT_ROUTE, GET_FEES_ROUTE, GET_ORDERS_ROUTE,
+        GET_ORDER_BY_ID_ROUTE, GET_ORDER_SLOTS_ROUTE, GET_WALLET_ROUTE, SET_AUTO_RESUBMIT_ROUTE,
+    },
+    wallet_update::{
+        CreateOrderHandler, DepositHandler, WalletUpdater, WithdrawHandler, CREATE_ORDER_ROUTE,
+        DEPOSIT_ROUTE, WITHDRAW_ROUTE,
     },
 };
 
 use super::{
+    auth::ApiPermission,
     error::ApiServerError,
+    rate_limit::RateLimiter,
     router::{Router, TypedHandler, UrlParams},
     worker::ApiServerConfig,
 };
 
+mod admin;
+mod handshake;
 mod network;
 mod order_book;
 mod price_report;
 mod wallet;
+mod wallet_update;
 
 /// Health check
 const PING_ROUTE: &str = "/v0/ping";
+/// Returns the relayer's identity and feature flags
+const INFO_ROUTE: &str = "/v0/info";
+/// Returns the relayer's identity attestation, signed by the cluster key
+const IDENTITY_ROUTE: &str = "/v0/identity";
+/// The window over which the cross preview rate limit is counted
+const CROSS_PREVIEW_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
 
 // ------------------
 // | Error Messages |
@@ -66,6 +111,10 @@ const ERR_WALLET_ID_PARSE: &str = "could not parse wallet id";
 const ERR_CLUSTER_ID_PARSE: &str = "could not parse cluster id";
 /// Error message displayed when a given peer ID is not parsable
 const ERR_PEER_ID_PARSE: &str = "could not parse peer id";
+/// Error message displayed when a given request ID is not parsable
+const ERR_REQUEST_ID_PARSE: &str = "could not parse request id";
+/// Error message displayed when the identity attestation cannot be signed
+const ERR_IDENTITY_SIGN: &str = "could not sign identity attestation";
 
 // ----------------
 // | URL Captures |
@@ -81,6 +130,10 @@ const ORDER_ID_URL_PARAM: &str = "order_id";
 const CLUSTER_ID_URL_PARAM: &str = "cluster_id";
 /// The :peer_id param in a URL
 const PEER_ID_URL_PARAM: &str = "peer_id";
+/// The :request_id param in a URL
+const REQUEST_ID_URL_PARAM: &str = "request_id";
+/// The :worker param in a URL
+const WORKER_URL_PARAM: &str = "worker";
 
 /// A helper to parse out a mint from a URL param
 fn parse_mint_from_params(params: &UrlParams) -> Result<BigUint, ApiServerError> {
@@ -111,6 +164,20 @@ fn parse_order_id_from_params(params: &UrlParams) -> Result<Uuid, ApiServerError
         })
 }
 
+/// A helper to parse out a handshake request ID from a URL param
+fn parse_request_id_from_params(params: &UrlParams) -> Result<Uuid, ApiServerError> {
+    params
+        .get(REQUEST_ID_URL_PARAM)
+        .unwrap()
+        .parse()
+        .map_err(|_| {
+            ApiServerError::HttpStatusCode(
+                StatusCode::BAD_REQUEST,
+                ERR_REQUEST_ID_PARSE.to_string(),
+            )
+        })
+}
+
 /// A helper to parse out a cluster ID from a URL param
 fn parse_cluster_id_from_params(params: &UrlParams) -> Result<ClusterId, ApiServerError> {
     params
@@ -157,22 +224,69 @@ impl HttpServer {
     /// Build a router and register routes on it
     fn build_router(config: &ApiServerConfig, global_state: RelayerState) -> Router {
         // Build the router and register its routes
-        let mut router = Router::new();
+        let mut router = Router::new(config.api_authenticator.clone());
 
         // The "/exchangeHealthStates" route
         router.add_route(
             Method::POST,
             EXCHANGE_HEALTH_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             ExchangeHealthStatesHandler::new(config.clone()),
         );
 
+        // The "/price_report/signed" route
+        router.add_route(
+            Method::POST,
+            SIGNED_PRICE_REPORT_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            SignedPriceReportHandler::new(config.clone()),
+        );
+
+        // The "/price_report/history" route
+        router.add_route(
+            Method::POST,
+            PRICE_HISTORY_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            PriceHistoryHandler::new(config.clone()),
+        );
+
+        // The "/price_report/candles" route
+        router.add_route(
+            Method::POST,
+            PRICE_CANDLES_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            PriceCandlesHandler::new(config.clone()),
+        );
+
         // The "/ping" route
-        router.add_route(Method::GET, PING_ROUTE.to_string(), PingHandler::new());
+        router.add_route(
+            Method::GET,
+            PING_ROUTE.to_string(),
+            ApiPermission::Public,
+            PingHandler::new(),
+        );
+
+        // The "/info" route
+        router.add_route(
+            Method::GET,
+            INFO_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            InfoHandler::new(global_state.clone()),
+        );
+
+        // The "/identity" route
+        router.add_route(
+            Method::GET,
+            IDENTITY_ROUTE.to_string(),
+            ApiPermission::Public,
+            IdentityHandler::new(config.clone(), global_state.clone()),
+        );
 
         // The "/wallet/:id" route
         router.add_route(
             Method::GET,
             GET_WALLET_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetWalletHandler::new(global_state.clone()),
         );
 
@@ -180,20 +294,65 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_ORDERS_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetOrdersHandler::new(global_state.clone()),
         );
 
+        // The "POST /wallet/:id/orders" route; it and the other wallet update routes
+        // share an updater so that a wallet has at most one update in flight
+        let wallet_updater = WalletUpdater::new(config, global_state.clone());
+        router.add_route(
+            Method::POST,
+            CREATE_ORDER_ROUTE.to_string(),
+            ApiPermission::WalletMutating,
+            CreateOrderHandler::new(wallet_updater.clone()),
+        );
+
+        // The "/wallet/:id/deposit" route
+        router.add_route(
+            Method::POST,
+            DEPOSIT_ROUTE.to_string(),
+            ApiPermission::WalletMutating,
+            DepositHandler::new(wallet_updater.clone()),
+        );
+
+        // The "/wallet/:id/withdraw" route
+        router.add_route(
+            Method::POST,
+            WITHDRAW_ROUTE.to_string(),
+            ApiPermission::WalletMutating,
+            WithdrawHandler::new(wallet_updater),
+        );
+
         // The "/wallet/:id/orders/:id" route
         router.add_route(
             Method::GET,
             GET_ORDER_BY_ID_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetOrderByIdHandler::new(global_state.clone()),
         );
 
+        // The "/wallet/:id/slots" route
+        router.add_route(
+            Method::GET,
+            GET_ORDER_SLOTS_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            GetOrderSlotsHandler::new(global_state.clone()),
+        );
+
+        // The "/wallet/:id/orders/:id/auto_resubmit" route
+        router.add_route(
+            Method::POST,
+            SET_AUTO_RESUBMIT_ROUTE.to_string(),
+            ApiPermission::WalletMutating,
+            SetAutoResubmitHandler::new(global_state.clone()),
+        );
+
         // The "/wallet/:id/balances" route
         router.add_route(
             Method::GET,
             GET_BALANCES_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetBalancesHandler::new(global_state.clone()),
         );
 
@@ -201,6 +360,7 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_BALANCE_BY_MINT_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetBalanceByMintHandler::new(global_state.clone()),
         );
 
@@ -208,6 +368,7 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_FEES_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetFeesHandler::new(global_state.clone()),
         );
 
@@ -215,6 +376,7 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_NETWORK_ORDERS_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetNetworkOrdersHandler::new(global_state.clone()),
         );
 
@@ -222,13 +384,38 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_NETWORK_ORDER_BY_ID_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetNetworkOrderByIdHandler::new(global_state.clone()),
         );
 
+        // The "/liquidity/:base/:quote" route
+        router.add_route(
+            Method::GET,
+            GET_LIQUIDITY_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            GetLiquidityHandler::new(global_state.clone()),
+        );
+
+        // The "/preview/cross" route
+        router.add_route(
+            Method::POST,
+            CROSS_PREVIEW_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            CrossPreviewHandler::new(
+                global_state.clone(),
+                RateLimiter::new(
+                    config.cross_preview_rate_limit,
+                    CROSS_PREVIEW_RATE_LIMIT_WINDOW,
+                    system_clock(),
+                ),
+            ),
+        );
+
         // The "/network" route
         router.add_route(
             Method::GET,
             GET_NETWORK_TOPOLOGY_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetNetworkTopologyHandler::new(global_state.clone()),
         );
 
@@ -236,6 +423,7 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_CLUSTER_INFO_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
             GetClusterInfoHandler::new(global_state.clone()),
         );
 
@@ -243,7 +431,189 @@ impl HttpServer {
         router.add_route(
             Method::GET,
             GET_PEER_INFO_ROUTE.to_string(),
-            GetPeerInfoHandler::new(global_state),
+            ApiPermission::ReadOnly,
+            GetPeerInfoHandler::new(global_state.clone()),
+        );
+
+        // The "/network/versions" route
+        router.add_route(
+            Method::GET,
+            GET_NETWORK_VERSIONS_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            GetNetworkVersionsHandler::new(global_state.clone()),
+        );
+
+        // The "GET /handshakes" route
+        router.add_route(
+            Method::GET,
+            GET_HANDSHAKES_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            GetHandshakesHandler::new(config.handshake_state_index.clone()),
+        );
+
+        // The "GET /handshakes/:request_id" route
+        router.add_route(
+            Method::GET,
+            GET_HANDSHAKE_ROUTE.to_string(),
+            ApiPermission::ReadOnly,
+            GetHandshakeHandler::new(config.handshake_state_index.clone()),
+        );
+
+        // The "POST /handshakes/:request_id/cancel" route
+        router.add_route(
+            Method::POST,
+            CANCEL_HANDSHAKE_ROUTE.to_string(),
+            ApiPermission::Admin,
+            CancelHandshakeHandler::new(
+                config.handshake_state_index.clone(),
+                config.handshake_manager_work_queue.clone(),
+            ),
+        );
+
+        // The "/admin/shutdown" route, served only to local callers
+        router.add_local_route(
+            Method::POST,
+            ADMIN_SHUTDOWN_ROUTE.to_string(),
+            ApiPermission::Admin,
+            AdminShutdownHandler::new(global_state.clone(), config.shutdown_channel.clone()),
+        );
+
+        // The "/admin/proof_manager/dead_letters" route
+        router.add_route(
+            Method::GET,
+            GET_DEAD_LETTERS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetDeadLettersHandler::new(config.dead_letter_queue.clone()),
+        );
+
+        // The "/admin/proof_manager/dry_run" route, a developer tool served only in debug mode
+        if config.debug {
+            router.add_route(
+                Method::POST,
+                DRY_RUN_PROOF_ROUTE.to_string(),
+                ApiPermission::Admin,
+                DryRunProofHandler::new(),
+            );
+        }
+
+        // The "GET /admin/cluster_access" route
+        router.add_route(
+            Method::GET,
+            CLUSTER_ACCESS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetClusterAccessHandler::new(global_state.clone()),
+        );
+
+        // The "POST /admin/cluster_access" route
+        router.add_route(
+            Method::POST,
+            CLUSTER_ACCESS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            UpdateClusterAccessHandler::new(global_state.clone()),
+        );
+
+        // The "GET /admin/feature_flags" route
+        router.add_route(
+            Method::GET,
+            FEATURE_FLAGS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetFeatureFlagsHandler::new(global_state.clone()),
+        );
+
+        // The "POST /admin/feature_flags" route
+        router.add_route(
+            Method::POST,
+            FEATURE_FLAGS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            UpdateFeatureFlagHandler::new(global_state.clone()),
+        );
+
+        // The "/admin/system_bus" route
+        router.add_route(
+            Method::GET,
+            SYSTEM_BUS_METRICS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetSystemBusMetricsHandler::new(config.system_bus.clone()),
+        );
+
+        // The "/admin/workers" route
+        router.add_route(
+            Method::GET,
+            WORKER_STATUS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetWorkerStatusHandler::new(global_state.clone()),
+        );
+
+        // The "/admin/workers/:worker/pause" route
+        router.add_route(
+            Method::POST,
+            PAUSE_WORKER_ROUTE.to_string(),
+            ApiPermission::Admin,
+            ControlWorkerHandler::new(WorkerCommand::Pause, config.worker_control_channel.clone()),
+        );
+
+        // The "/admin/workers/:worker/resume" route
+        router.add_route(
+            Method::POST,
+            RESUME_WORKER_ROUTE.to_string(),
+            ApiPermission::Admin,
+            ControlWorkerHandler::new(WorkerCommand::Resume, config.worker_control_channel.clone()),
+        );
+
+        // The "GET /admin/settlements" route
+        router.add_route(
+            Method::GET,
+            GET_SETTLEMENTS_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetSettlementsHandler::new(global_state.clone()),
+        );
+
+        // The "GET /admin/settlements/:request_id" route
+        router.add_route(
+            Method::GET,
+            GET_SETTLEMENT_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetSettlementHandler::new(global_state.clone()),
+        );
+
+        // The "GET /admin/log_filter" route
+        router.add_route(
+            Method::GET,
+            LOG_FILTER_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetLogFilterHandler::new(config.log_filter_handle.clone()),
+        );
+
+        // The "POST /admin/log_filter" route
+        router.add_route(
+            Method::POST,
+            LOG_FILTER_ROUTE.to_string(),
+            ApiPermission::Admin,
+            UpdateLogFilterHandler::new(config.log_filter_handle.clone()),
+        );
+
+        // The "/admin/order_book/export" route
+        router.add_route(
+            Method::POST,
+            EXPORT_ORDER_BOOK_ROUTE.to_string(),
+            ApiPermission::Admin,
+            ExportOrderBookHandler::new(config.order_book_exporter.clone()),
+        );
+
+        // The "GET /admin/audit_log" route
+        router.add_route(
+            Method::GET,
+            GET_AUDIT_LOG_ROUTE.to_string(),
+            ApiPermission::Admin,
+            GetAuditLogHandler::new(config.audit_log.clone()),
+        );
+
+        // The "/admin/wallets/recover" route
+        router.add_route(
+            Method::POST,
+            RECOVER_WALLET_ROUTE.to_string(),
+            ApiPermission::Admin,
+            RecoverWalletHandler::new(global_state, config.starknet_client.clone()),
         );
 
         router
@@ -256,11 +626,14 @@ impl HttpServer {
         // Clone self and move it into each layer of the callback so that each
         // scope has its own copy of self
         let self_clone = self.clone();
-        let make_service = make_service_fn(move |_: &AddrStream| {
+        let make_service = make_service_fn(move |conn: &AddrStream| {
             let self_clone = self_clone.clone();
+            let remote_addr = conn.remote_addr();
             async move {
-                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                Ok::<_, Infallible>(service_fn(move |mut req: Request<Body>| {
                     let self_clone = self_clone.clone();
+                    // Attach the caller's address for routes served only to local callers
+                    req.extensions_mut().insert(remote_addr);
                     async move { Ok::<_, HyperError>(self_clone.serve_request(req).await) }
                 }))
             }
@@ -311,3 +684,95 @@ impl TypedHandler for PingHandler {
         Ok(PingResponse { timestamp })
     }
 }
+
+/// Handler for the info route, returns the relayer's identity and feature flags
+#[derive(Clone, Debug)]
+pub struct InfoHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl InfoHandler {
+    /// Create a new handler for "/info"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for InfoHandler {
+    type Request = EmptyRequestResponse;
+    type Response = InfoResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(InfoResponse {
+            peer_id: self.global_state.local_peer_id(),
+            cluster_id: self.global_state.local_cluster_id.clone(),
+            feature_flags: self.global_state.feature_flags().snapshot(),
+        })
+    }
+}
+
+/// Handler for the identity route, returns an attestation of the relayer's identity
+/// signed by the cluster key
+#[derive(Clone, Debug)]
+pub struct IdentityHandler {
+    /// The API server config, holds the cluster keypair and the advertised contact info
+    config: ApiServerConfig,
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl IdentityHandler {
+    /// Create a new handler for "/identity"
+    pub fn new(config: ApiServerConfig, global_state: RelayerState) -> Self {
+        Self {
+            config,
+            global_state,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for IdentityHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetIdentityResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let features = self
+            .global_state
+            .feature_flags()
+            .snapshot()
+            .into_iter()
+            .filter_map(|(flag, enabled)| enabled.then_some(flag))
+            .collect();
+        let issued_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let attestation = IdentityAttestation {
+            cluster_id: self.global_state.local_cluster_id.clone(),
+            peer_id: self.global_state.local_peer_id(),
+            relayer_version: self.config.relayer_version.clone(),
+            features,
+            contact_info: self.config.contact_info.clone(),
+            issued_at,
+        };
+
+        GetIdentityResponse::sign(attestation, &self.config.cluster_keypair).map_err(|_| {
+            ApiServerError::HttpStatusCode(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ERR_IDENTITY_SIGN.to_string(),
+            )
+        })
+    }
+}
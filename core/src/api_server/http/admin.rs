@@ -0,0 +1,808 @@
+//! Groups relayer administration API handlers and types
+
+use async_trait::async_trait;
+use hyper::StatusCode;
+use tokio::sync::{mpsc::UnboundedSender as TokioSender, oneshot};
+use tracing::log;
+
+use crate::{
+    api_server::{
+        error::ApiServerError,
+        router::{TypedHandler, UrlParams},
+    },
+    external_api::{
+        http::admin::{
+            AdminShutdownResponse, ClusterAccessResponse, ControlWorkerResponse,
+            DryRunProofRequest, DryRunProofResponse, ExportOrderBookResponse, FeatureFlagsResponse,
+            GetAuditLogResponse, GetDeadLettersResponse, GetSettlementResponse,
+            GetSettlementsResponse, LogFilterResponse, RecoverWalletRequest, RecoverWalletResponse,
+            SystemBusMetricsResponse, UpdateClusterAccessRequest, UpdateFeatureFlagRequest,
+            UpdateLogFilterRequest, WorkerStatusResponse,
+        },
+        EmptyRequestResponse,
+    },
+    logging::{LogFilterHandle, LoggingError},
+    proof_generation::{dead_letter::DeadLetterQueue, proof_manager::ProofManager},
+    recovery::{recover_wallet, RecoveryError},
+    starknet_client::client::StarknetClient,
+    state::{
+        audit::AuditLog, cluster_access::ClusterAccessPolicy, export::OrderBookExporter,
+        RelayerState,
+    },
+    system_bus::SystemBus,
+    types::SystemBusMessage,
+    worker::{WorkerCommand, WorkerControlMessage},
+};
+
+use super::{parse_request_id_from_params, WORKER_URL_PARAM};
+
+// ---------------
+// | HTTP Routes |
+// ---------------
+
+/// Drains and shuts down the relayer
+pub(super) const ADMIN_SHUTDOWN_ROUTE: &str = "/v0/admin/shutdown";
+/// Returns the proof jobs abandoned by the proof manager
+pub(super) const GET_DEAD_LETTERS_ROUTE: &str = "/v0/admin/proof_manager/dead_letters";
+/// Dry runs a proof job, returning the size of its circuit and whether its witness
+/// satisfies it; only served in debug mode
+pub(super) const DRY_RUN_PROOF_ROUTE: &str = "/v0/admin/proof_manager/dry_run";
+/// Returns or replaces the policy on which clusters the relayer handshakes with
+pub(super) const CLUSTER_ACCESS_ROUTE: &str = "/v0/admin/cluster_access";
+/// Returns or toggles the feature flags
+pub(super) const FEATURE_FLAGS_ROUTE: &str = "/v0/admin/feature_flags";
+/// Returns the status of the relayer's workers, e.g. those restarting or left down
+pub(super) const WORKER_STATUS_ROUTE: &str = "/v0/admin/workers";
+/// Cancels a worker and leaves it down until it is resumed, e.g. for maintenance of an
+/// upstream dependency
+pub(super) const PAUSE_WORKER_ROUTE: &str = "/v0/admin/workers/:worker/pause";
+/// Re-allocates and starts a paused worker
+pub(super) const RESUME_WORKER_ROUTE: &str = "/v0/admin/workers/:worker/resume";
+/// Returns or replaces the log filter
+pub(super) const LOG_FILTER_ROUTE: &str = "/v0/admin/log_filter";
+/// Returns the lag of every system bus subscriber
+pub(super) const SYSTEM_BUS_METRICS_ROUTE: &str = "/v0/admin/system_bus";
+/// Exports a dump of the order book
+pub(super) const EXPORT_ORDER_BOOK_ROUTE: &str = "/v0/admin/order_book/export";
+/// Returns the progress of recent match settlements
+pub(super) const GET_SETTLEMENTS_ROUTE: &str = "/v0/admin/settlements";
+/// Returns the progress of a single match settlement, by handshake request ID
+pub(super) const GET_SETTLEMENT_ROUTE: &str = "/v0/admin/settlements/:request_id";
+/// Recovers a wallet from chain and registers it as managed
+pub(super) const RECOVER_WALLET_ROUTE: &str = "/v0/admin/wallets/recover";
+/// Returns recent events from the audit log
+pub(super) const GET_AUDIT_LOG_ROUTE: &str = "/v0/admin/audit_log";
+
+// ----------------
+// | Query Params |
+// ----------------
+
+/// The query param filtering audit events by topic
+const TOPIC_QUERY_PARAM: &str = "topic";
+/// The query param giving the maximum number of audit events to return
+const LIMIT_QUERY_PARAM: &str = "limit";
+
+/// The number of audit events returned when no limit is given
+const DEFAULT_AUDIT_EVENTS_LIMIT: usize = 100;
+/// The maximum number of audit events that may be requested at once
+const MAX_AUDIT_EVENTS_LIMIT: usize = 1_000;
+
+// ------------------
+// | Error Messages |
+// ------------------
+
+/// Error message displayed when a settlement is not in the settlement log
+const ERR_SETTLEMENT_NOT_FOUND: &str = "settlement not found";
+/// Error message displayed when the coordinator cannot be signalled to shut down
+const ERR_SHUTDOWN_SIGNAL: &str = "could not signal shutdown";
+/// Error message displayed when the coordinator does not respond to a worker command
+const ERR_WORKER_CONTROL: &str = "coordinator did not apply the worker command";
+/// Error message displayed when an export is requested but exports are not configured
+const ERR_EXPORT_NOT_CONFIGURED: &str = "order book export is not configured";
+/// Error message displayed when the log filter is requested but logs are captured by the TUI
+const ERR_LOG_FILTER_NOT_CONFIGURED: &str = "log filter is not configurable in debug mode";
+/// Error message displayed when the audit log is queried but auditing is not configured
+const ERR_AUDIT_LOG_NOT_CONFIGURED: &str = "audit log is not configured";
+/// Error message displayed when the `limit` query param is not a valid number of events
+const ERR_LIMIT_PARSE: &str = "could not parse limit";
+
+// ------------------
+// | Route Handlers |
+// ------------------
+
+/// Handler for the POST /admin/shutdown route
+///
+/// Signals the coordinator to drain the relayer and shut down; the request returns
+/// immediately rather than waiting for the relayer to exit. The route is served only to
+/// admin callers connected over the loopback interface
+#[derive(Clone, Debug)]
+pub struct AdminShutdownHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+    /// The channel on which to signal the coordinator to begin shutdown
+    shutdown_channel: TokioSender<()>,
+}
+
+impl AdminShutdownHandler {
+    /// Create a new handler for "/admin/shutdown"
+    pub fn new(global_state: RelayerState, shutdown_channel: TokioSender<()>) -> Self {
+        Self {
+            global_state,
+            shutdown_channel,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for AdminShutdownHandler {
+    type Request = EmptyRequestResponse;
+    type Response = AdminShutdownResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        // Repeated requests are harmless, the coordinator only drains once
+        self.shutdown_channel.send(()).map_err(|_| {
+            ApiServerError::HttpStatusCode(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ERR_SHUTDOWN_SIGNAL.to_string(),
+            )
+        })?;
+
+        Ok(AdminShutdownResponse {
+            in_flight_mpcs: self.global_state.num_in_flight_mpcs(),
+        })
+    }
+}
+
+/// Handler for the GET /admin/proof_manager/dead_letters route
+#[derive(Clone, Debug)]
+pub struct GetDeadLettersHandler {
+    /// The queue of proof jobs abandoned by the proof manager
+    dead_letter_queue: DeadLetterQueue,
+}
+
+impl GetDeadLettersHandler {
+    /// Create a new handler for "/admin/proof_manager/dead_letters"
+    pub fn new(dead_letter_queue: DeadLetterQueue) -> Self {
+        Self { dead_letter_queue }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetDeadLettersHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetDeadLettersResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(GetDeadLettersResponse {
+            dead_letters: self.dead_letter_queue.get_dead_letters(),
+        })
+    }
+}
+
+/// Handler for the POST /admin/proof_manager/dry_run route
+///
+/// Applies the constraints of the job's circuit without generating a proof, so that a
+/// witness may be checked, and a circuit sized, in a fraction of the time a proof takes
+#[derive(Clone, Debug, Default)]
+pub struct DryRunProofHandler;
+
+impl DryRunProofHandler {
+    /// Create a new handler for "/admin/proof_manager/dry_run"
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl TypedHandler for DryRunProofHandler {
+    type Request = DryRunProofRequest;
+    type Response = DryRunProofResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let statement = req.job.statement_name().to_string();
+
+        // Applying a circuit's constraints is CPU bound, keep it off the async runtime
+        let profile = tokio::task::spawn_blocking(move || ProofManager::dry_run(req.job))
+            .await
+            .map_err(|err| {
+                ApiServerError::HttpStatusCode(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            })?
+            .map_err(|err| {
+                ApiServerError::HttpStatusCode(StatusCode::BAD_REQUEST, err.to_string())
+            })?;
+
+        Ok(DryRunProofResponse {
+            statement,
+            multipliers: profile.multipliers,
+            constraints: profile.constraints,
+            bp_gens_capacity: profile.bp_gens_capacity,
+            satisfied: profile.satisfied,
+        })
+    }
+}
+
+/// Handler for the GET /admin/cluster_access route
+#[derive(Clone, Debug)]
+pub struct GetClusterAccessHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl GetClusterAccessHandler {
+    /// Create a new handler for "GET /admin/cluster_access"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetClusterAccessHandler {
+    type Request = EmptyRequestResponse;
+    type Response = ClusterAccessResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let policy = self.global_state.read_cluster_access().await.clone();
+        Ok(ClusterAccessResponse { policy })
+    }
+}
+
+/// Handler for the POST /admin/cluster_access route
+///
+/// Replaces the policy wholesale; handshakes already past negotiation are unaffected
+#[derive(Clone, Debug)]
+pub struct UpdateClusterAccessHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl UpdateClusterAccessHandler {
+    /// Create a new handler for "POST /admin/cluster_access"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for UpdateClusterAccessHandler {
+    type Request = UpdateClusterAccessRequest;
+    type Response = ClusterAccessResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let policy = ClusterAccessPolicy::new(req.allowlist, req.denylist);
+        *self.global_state.write_cluster_access().await = policy.clone();
+
+        Ok(ClusterAccessResponse { policy })
+    }
+}
+
+/// Handler for the GET /admin/feature_flags route
+#[derive(Clone, Debug)]
+pub struct GetFeatureFlagsHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl GetFeatureFlagsHandler {
+    /// Create a new handler for "GET /admin/feature_flags"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetFeatureFlagsHandler {
+    type Request = EmptyRequestResponse;
+    type Response = FeatureFlagsResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(FeatureFlagsResponse {
+            flags: self.global_state.feature_flags().snapshot(),
+        })
+    }
+}
+
+/// Handler for the POST /admin/feature_flags route
+#[derive(Clone, Debug)]
+pub struct UpdateFeatureFlagHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl UpdateFeatureFlagHandler {
+    /// Create a new handler for "POST /admin/feature_flags"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for UpdateFeatureFlagHandler {
+    type Request = UpdateFeatureFlagRequest;
+    type Response = FeatureFlagsResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let feature_flags = self.global_state.feature_flags();
+        let previous = feature_flags.set(req.flag, req.enabled);
+        if previous != req.enabled {
+            log::info!("feature flag {} set to {}", req.flag, req.enabled);
+        }
+
+        Ok(FeatureFlagsResponse {
+            flags: feature_flags.snapshot(),
+        })
+    }
+}
+
+/// Handler for the GET /admin/workers route
+#[derive(Clone, Debug)]
+pub struct GetWorkerStatusHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl GetWorkerStatusHandler {
+    /// Create a new handler for "GET /admin/workers"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetWorkerStatusHandler {
+    type Request = EmptyRequestResponse;
+    type Response = WorkerStatusResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(WorkerStatusResponse {
+            workers: self.global_state.worker_statuses(),
+        })
+    }
+}
+
+/// Handler for the POST /admin/workers/:worker/pause and POST /admin/workers/:worker/resume
+/// routes
+///
+/// The request returns once the coordinator has applied the command, so that a paused
+/// worker has exited by the time the response is received
+#[derive(Clone, Debug)]
+pub struct ControlWorkerHandler {
+    /// The command the route applies to the worker
+    command: WorkerCommand,
+    /// The channel on which to ask the coordinator to apply the command
+    worker_control_channel: TokioSender<WorkerControlMessage>,
+}
+
+impl ControlWorkerHandler {
+    /// Create a new handler for "/admin/workers/:worker/pause" or
+    /// "/admin/workers/:worker/resume"
+    pub fn new(
+        command: WorkerCommand,
+        worker_control_channel: TokioSender<WorkerControlMessage>,
+    ) -> Self {
+        Self {
+            command,
+            worker_control_channel,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for ControlWorkerHandler {
+    type Request = EmptyRequestResponse;
+    type Response = ControlWorkerResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let worker = params.get(WORKER_URL_PARAM).unwrap().clone();
+        let (response_sender, response_receiver) = oneshot::channel();
+        self.worker_control_channel
+            .send(WorkerControlMessage {
+                worker: worker.clone(),
+                command: self.command,
+                response_channel: response_sender,
+            })
+            .map_err(|_| {
+                ApiServerError::HttpStatusCode(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ERR_WORKER_CONTROL.to_string(),
+                )
+            })?;
+
+        let status = response_receiver
+            .await
+            .map_err(|_| {
+                ApiServerError::HttpStatusCode(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ERR_WORKER_CONTROL.to_string(),
+                )
+            })?
+            .map_err(|err| ApiServerError::HttpStatusCode(StatusCode::BAD_REQUEST, err))?;
+        log::info!("worker {worker} is {status:?}");
+
+        Ok(ControlWorkerResponse { worker, status })
+    }
+}
+
+/// Handler for the GET /admin/settlements route
+#[derive(Clone, Debug)]
+pub struct GetSettlementsHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl GetSettlementsHandler {
+    /// Create a new handler for "GET /admin/settlements"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetSettlementsHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetSettlementsResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(GetSettlementsResponse {
+            settlements: self.global_state.settlement_records(),
+        })
+    }
+}
+
+/// Handler for the GET /admin/settlements/:request_id route
+#[derive(Clone, Debug)]
+pub struct GetSettlementHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl GetSettlementHandler {
+    /// Create a new handler for "GET /admin/settlements/:request_id"
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetSettlementHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetSettlementResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let request_id = parse_request_id_from_params(&params)?;
+        let settlement = self
+            .global_state
+            .get_settlement_record(&request_id)
+            .ok_or_else(|| {
+                ApiServerError::HttpStatusCode(
+                    StatusCode::NOT_FOUND,
+                    ERR_SETTLEMENT_NOT_FOUND.to_string(),
+                )
+            })?;
+
+        Ok(GetSettlementResponse { settlement })
+    }
+}
+
+/// A helper to unwrap the log filter handle, which is absent when the TUI captures logs
+fn log_filter_handle(handle: &Option<LogFilterHandle>) -> Result<&LogFilterHandle, ApiServerError> {
+    handle.as_ref().ok_or_else(|| {
+        ApiServerError::HttpStatusCode(
+            StatusCode::BAD_REQUEST,
+            ERR_LOG_FILTER_NOT_CONFIGURED.to_string(),
+        )
+    })
+}
+
+/// Handler for the GET /admin/log_filter route
+#[derive(Clone, Debug)]
+pub struct GetLogFilterHandler {
+    /// The handle on the log filter, `None` if logs are captured by the TUI
+    log_filter_handle: Option<LogFilterHandle>,
+}
+
+impl GetLogFilterHandler {
+    /// Create a new handler for "GET /admin/log_filter"
+    pub fn new(log_filter_handle: Option<LogFilterHandle>) -> Self {
+        Self { log_filter_handle }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetLogFilterHandler {
+    type Request = EmptyRequestResponse;
+    type Response = LogFilterResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(LogFilterResponse {
+            filter: log_filter_handle(&self.log_filter_handle)?.filter(),
+        })
+    }
+}
+
+/// Handler for the POST /admin/log_filter route
+///
+/// Replaces the log filter until the relayer restarts, at which point the configured
+/// filter applies again
+#[derive(Clone, Debug)]
+pub struct UpdateLogFilterHandler {
+    /// The handle on the log filter, `None` if logs are captured by the TUI
+    log_filter_handle: Option<LogFilterHandle>,
+}
+
+impl UpdateLogFilterHandler {
+    /// Create a new handler for "POST /admin/log_filter"
+    pub fn new(log_filter_handle: Option<LogFilterHandle>) -> Self {
+        Self { log_filter_handle }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for UpdateLogFilterHandler {
+    type Request = UpdateLogFilterRequest;
+    type Response = LogFilterResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let handle = log_filter_handle(&self.log_filter_handle)?;
+        handle.set_filter(&req.filter).map_err(|err| {
+            let status = match err {
+                LoggingError::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+                LoggingError::Setup(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            ApiServerError::HttpStatusCode(status, err.to_string())
+        })?;
+        log::info!("log filter set to {}", req.filter);
+
+        Ok(LogFilterResponse {
+            filter: handle.filter(),
+        })
+    }
+}
+
+/// Handler for the GET /admin/system_bus route
+#[derive(Clone, Debug)]
+pub struct GetSystemBusMetricsHandler {
+    /// The system bus whose subscribers are reported on
+    system_bus: SystemBus<SystemBusMessage>,
+}
+
+impl GetSystemBusMetricsHandler {
+    /// Create a new handler for "GET /admin/system_bus"
+    pub fn new(system_bus: SystemBus<SystemBusMessage>) -> Self {
+        Self { system_bus }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetSystemBusMetricsHandler {
+    type Request = EmptyRequestResponse;
+    type Response = SystemBusMetricsResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        Ok(SystemBusMetricsResponse {
+            subscribers: self.system_bus.metrics(),
+        })
+    }
+}
+
+/// Handler for the POST /admin/order_book/export route
+///
+/// Exports a dump of the order book to the configured destination, outside of the
+/// export schedule
+#[derive(Clone, Debug)]
+pub struct ExportOrderBookHandler {
+    /// The order book exporter, `None` if exports are not configured
+    exporter: Option<OrderBookExporter>,
+}
+
+impl ExportOrderBookHandler {
+    /// Create a new handler for "/admin/order_book/export"
+    pub fn new(exporter: Option<OrderBookExporter>) -> Self {
+        Self { exporter }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for ExportOrderBookHandler {
+    type Request = EmptyRequestResponse;
+    type Response = ExportOrderBookResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let exporter = self.exporter.as_ref().ok_or_else(|| {
+            ApiServerError::HttpStatusCode(
+                StatusCode::BAD_REQUEST,
+                ERR_EXPORT_NOT_CONFIGURED.to_string(),
+            )
+        })?;
+
+        let summary = exporter.export().await.map_err(|err| {
+            ApiServerError::HttpStatusCode(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+        log::info!(
+            "exported {} orders to {}",
+            summary.header.num_orders,
+            summary.location
+        );
+
+        Ok(ExportOrderBookResponse {
+            location: summary.location,
+            schema_version: summary.header.schema_version,
+            num_orders: summary.header.num_orders,
+        })
+    }
+}
+
+/// Handler for the POST /admin/wallets/recover route
+///
+/// Recovers the latest state of a wallet from chain given its secret keys, and registers
+/// it as a managed wallet
+#[derive(Clone, Debug)]
+pub struct RecoverWalletHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+    /// The client used to scan the contract's events
+    starknet_client: StarknetClient,
+}
+
+impl RecoverWalletHandler {
+    /// Create a new handler for "/admin/wallets/recover"
+    pub fn new(global_state: RelayerState, starknet_client: StarknetClient) -> Self {
+        Self {
+            global_state,
+            starknet_client,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for RecoverWalletHandler {
+    type Request = RecoverWalletRequest;
+    type Response = RecoverWalletResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let wallet = recover_wallet(&self.starknet_client, req.secret_keys)
+            .await
+            .map_err(|err| {
+                let status = match err {
+                    RecoveryError::NotFound => StatusCode::NOT_FOUND,
+                    RecoveryError::NoJsonRpc | RecoveryError::Keychain(_) => {
+                        StatusCode::BAD_REQUEST
+                    }
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                ApiServerError::HttpStatusCode(status, err.to_string())
+            })?;
+
+        let leaf_index = wallet
+            .merkle_proof
+            .as_ref()
+            .map(|path| path.leaf_index.clone())
+            .unwrap_or_default();
+        log::info!(
+            "recovered wallet {} from leaf {leaf_index}",
+            wallet.wallet_id
+        );
+
+        self.global_state.add_wallets(vec![wallet.clone()]).await;
+        Ok(RecoverWalletResponse {
+            leaf_index,
+            wallet: wallet.into(),
+        })
+    }
+}
+
+/// Handler for the GET /admin/audit_log route
+///
+/// Returns the most recent audit events, optionally filtered to a single topic
+#[derive(Clone, Debug)]
+pub struct GetAuditLogHandler {
+    /// The audit log, `None` if auditing is not configured
+    audit_log: Option<AuditLog>,
+}
+
+impl GetAuditLogHandler {
+    /// Create a new handler for "GET /admin/audit_log"
+    pub fn new(audit_log: Option<AuditLog>) -> Self {
+        Self { audit_log }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetAuditLogHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetAuditLogResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let audit_log = self.audit_log.as_ref().ok_or_else(|| {
+            ApiServerError::HttpStatusCode(
+                StatusCode::BAD_REQUEST,
+                ERR_AUDIT_LOG_NOT_CONFIGURED.to_string(),
+            )
+        })?;
+
+        let limit = match params.get(LIMIT_QUERY_PARAM) {
+            Some(limit) => match limit.parse::<usize>() {
+                Ok(limit) if limit > 0 && limit <= MAX_AUDIT_EVENTS_LIMIT => limit,
+                _ => {
+                    return Err(ApiServerError::HttpStatusCode(
+                        StatusCode::BAD_REQUEST,
+                        ERR_LIMIT_PARSE.to_string(),
+                    ))
+                }
+            },
+            None => DEFAULT_AUDIT_EVENTS_LIMIT,
+        };
+        let topic = params.get(TOPIC_QUERY_PARAM).map(String::as_str);
+
+        Ok(GetAuditLogResponse {
+            events: audit_log.recent_events(topic, limit),
+        })
+    }
+}
@@ -0,0 +1,177 @@
+//! Groups API routes and handlers for in-flight handshake API operations
+
+use async_trait::async_trait;
+use hyper::StatusCode;
+use itertools::Itertools;
+
+use crate::{
+    api_server::{
+        error::ApiServerError,
+        router::{TypedHandler, UrlParams},
+    },
+    external_api::{
+        http::handshake::{CancelHandshakeResponse, GetHandshakeResponse, GetHandshakesResponse},
+        types::Handshake,
+        EmptyRequestResponse,
+    },
+    handshake::{jobs::HandshakeExecutionJob, state::HandshakeStateIndex},
+    job_queue::JobQueueSender,
+};
+
+use super::parse_request_id_from_params;
+
+// ------------------
+// | Error Messages |
+// ------------------
+
+/// Error displayed when a requested handshake is not in flight
+const ERR_HANDSHAKE_NOT_FOUND: &str = "handshake not found";
+
+// ---------------
+// | HTTP Routes |
+// ---------------
+
+/// Returns the handshakes in flight with peers
+pub(super) const GET_HANDSHAKES_ROUTE: &str = "/v0/handshakes";
+/// Returns a single in-flight handshake by its request ID
+pub(super) const GET_HANDSHAKE_ROUTE: &str = "/v0/handshakes/:request_id";
+/// Cancels an in-flight handshake
+pub(super) const CANCEL_HANDSHAKE_ROUTE: &str = "/v0/handshakes/:request_id/cancel";
+
+// ----------------------
+// | Handshake Handlers |
+// ----------------------
+
+/// Handler for the GET /handshakes route
+#[derive(Clone, Debug)]
+pub struct GetHandshakesHandler {
+    /// The handshake manager's index of in-flight handshakes
+    handshake_state_index: HandshakeStateIndex,
+}
+
+impl GetHandshakesHandler {
+    /// Create a new handler for "GET /handshakes"
+    pub fn new(handshake_state_index: HandshakeStateIndex) -> Self {
+        Self {
+            handshake_state_index,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetHandshakesHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetHandshakesResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let handshakes = self
+            .handshake_state_index
+            .list_handshakes()
+            .into_iter()
+            .map(Handshake::from)
+            .collect_vec();
+
+        Ok(GetHandshakesResponse { handshakes })
+    }
+}
+
+/// Handler for the GET /handshakes/:request_id route
+#[derive(Clone, Debug)]
+pub struct GetHandshakeHandler {
+    /// The handshake manager's index of in-flight handshakes
+    handshake_state_index: HandshakeStateIndex,
+}
+
+impl GetHandshakeHandler {
+    /// Create a new handler for "GET /handshakes/:request_id"
+    pub fn new(handshake_state_index: HandshakeStateIndex) -> Self {
+        Self {
+            handshake_state_index,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetHandshakeHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetHandshakeResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let request_id = parse_request_id_from_params(&params)?;
+        let state = self
+            .handshake_state_index
+            .get_state(&request_id)
+            .ok_or_else(|| {
+                ApiServerError::HttpStatusCode(
+                    StatusCode::NOT_FOUND,
+                    ERR_HANDSHAKE_NOT_FOUND.to_string(),
+                )
+            })?;
+
+        Ok(GetHandshakeResponse {
+            handshake: state.into(),
+        })
+    }
+}
+
+/// Handler for the POST /handshakes/:request_id/cancel route
+#[derive(Clone, Debug)]
+pub struct CancelHandshakeHandler {
+    /// The handshake manager's index of in-flight handshakes
+    handshake_state_index: HandshakeStateIndex,
+    /// The priority job queue of the handshake manager
+    handshake_manager_work_queue: JobQueueSender<HandshakeExecutionJob>,
+}
+
+impl CancelHandshakeHandler {
+    /// Create a new handler for "POST /handshakes/:request_id/cancel"
+    pub fn new(
+        handshake_state_index: HandshakeStateIndex,
+        handshake_manager_work_queue: JobQueueSender<HandshakeExecutionJob>,
+    ) -> Self {
+        Self {
+            handshake_state_index,
+            handshake_manager_work_queue,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for CancelHandshakeHandler {
+    type Request = EmptyRequestResponse;
+    type Response = CancelHandshakeResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let request_id = parse_request_id_from_params(&params)?;
+        let state = self
+            .handshake_state_index
+            .get_state(&request_id)
+            .ok_or_else(|| {
+                ApiServerError::HttpStatusCode(
+                    StatusCode::NOT_FOUND,
+                    ERR_HANDSHAKE_NOT_FOUND.to_string(),
+                )
+            })?;
+
+        // The handshake manager tears the handshake down on its priority lane, the same
+        // path a nullifier shootdown takes
+        self.handshake_manager_work_queue
+            .send(HandshakeExecutionJob::CancelHandshake { request_id })?;
+
+        Ok(CancelHandshakeResponse {
+            handshake: state.into(),
+        })
+    }
+}
@@ -12,11 +12,14 @@ use crate::{
         router::{TypedHandler, UrlParams},
     },
     external_api::{
-        http::network::{GetClusterInfoResponse, GetNetworkTopologyResponse, GetPeerInfoResponse},
-        types::{Cluster, Peer},
+        http::network::{
+            GetClusterInfoResponse, GetNetworkTopologyResponse, GetNetworkVersionsResponse,
+            GetPeerInfoResponse,
+        },
+        types::{Cluster, Peer, PeerAuth},
         EmptyRequestResponse,
     },
-    state::RelayerState,
+    state::{versions::RELAYER_VERSION, RelayerState},
 };
 
 use super::{parse_cluster_id_from_params, parse_peer_id_from_params};
@@ -38,6 +41,8 @@ pub(super) const GET_NETWORK_TOPOLOGY_ROUTE: &str = "/v0/network";
 pub(super) const GET_CLUSTER_INFO_ROUTE: &str = "/v0/network/clusters/:cluster_id";
 /// Returns the peer info for a given peer
 pub(super) const GET_PEER_INFO_ROUTE: &str = "/v0/network/peers/:peer_id";
+/// Returns the distribution of software versions across known peers
+pub(super) const GET_NETWORK_VERSIONS_ROUTE: &str = "/v0/network/versions";
 
 // ------------------
 // | Route Handlers |
@@ -173,7 +178,18 @@ impl TypedHandler for GetPeerInfoHandler {
             .get_peer_info(&peer_id)
             .await
         {
-            Ok(GetPeerInfoResponse { peer: info.into() })
+            let auth = self
+                .global_state
+                .get_peer_connection_auth(&peer_id)
+                .map(|auth| {
+                    let events = self.global_state.get_peer_auth_events(&peer_id);
+                    PeerAuth::from((auth, events))
+                });
+
+            Ok(GetPeerInfoResponse {
+                peer: info.into(),
+                auth,
+            })
         } else {
             Err(ApiServerError::HttpStatusCode(
                 StatusCode::NOT_FOUND,
@@ -182,3 +198,52 @@ impl TypedHandler for GetPeerInfoHandler {
         }
     }
 }
+
+/// Handler for the GET "/network/versions" route
+#[derive(Clone, Debug)]
+pub struct GetNetworkVersionsHandler {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+}
+
+impl GetNetworkVersionsHandler {
+    /// Constructor
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetNetworkVersionsHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetNetworkVersionsResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let cluster_peers = self
+            .global_state
+            .read_peer_index()
+            .await
+            .get_all_cluster_peers(&self.global_state.local_cluster_id)
+            .await;
+
+        let locked_versions = self.global_state.read_peer_versions().await;
+        let versions = locked_versions
+            .distribution()
+            .into_iter()
+            .map(|(version, count)| (version.to_string(), count))
+            .collect();
+        let cluster_majority = locked_versions
+            .majority_version(&cluster_peers)
+            .map(|version| version.to_string());
+
+        Ok(GetNetworkVersionsResponse {
+            local_version: RELAYER_VERSION.to_string(),
+            cluster_majority,
+            versions,
+        })
+    }
+}
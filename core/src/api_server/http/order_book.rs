@@ -5,20 +5,28 @@
 // ---------------
 
 use async_trait::async_trait;
+use circuits::types::order::{Order, OrderSide};
+use crypto::fields::biguint_to_scalar;
 use hyper::StatusCode;
 use itertools::Itertools;
+use num_bigint::BigUint;
 
 use crate::{
     api_server::{
         error::ApiServerError,
+        rate_limit::RateLimiter,
         router::{TypedHandler, UrlParams},
     },
     external_api::{
-        http::order_book::{GetNetworkOrderByIdResponse, GetNetworkOrdersResponse},
+        http::order_book::{
+            CrossPreviewRequest, CrossPreviewResponse, GetLiquidityResponse,
+            GetNetworkOrderByIdResponse, GetNetworkOrdersResponse, SideLiquidity,
+        },
         types::NetworkOrder,
         EmptyRequestResponse,
     },
-    state::RelayerState,
+    handshake::size_bucket::{buckets_overlap, size_bucket},
+    state::{NetworkOrderState, OrderBookFilter, OrderIdentifier, RelayerState},
 };
 
 use super::parse_order_id_from_params;
@@ -29,15 +37,200 @@ use super::parse_order_id_from_params;
 
 /// Error displayed when an order cannot be found in the network order book
 const ERR_ORDER_NOT_FOUND: &str = "order not found in network order book";
+/// Error displayed when the `state` query param does not name an order state
+const ERR_STATE_PARSE: &str = "could not parse order state";
+/// Error displayed when the `local` query param is not a boolean
+const ERR_LOCAL_PARSE: &str = "could not parse local, expected true or false";
+/// Error displayed when the `nullifier` query param is not a decimal integer
+const ERR_NULLIFIER_PARSE: &str = "could not parse nullifier";
+/// Error displayed when the `cursor` query param is not an order ID
+const ERR_CURSOR_PARSE: &str = "could not parse cursor";
+/// Error displayed when the `limit` query param is not a valid page size
+const ERR_LIMIT_PARSE: &str = "could not parse limit";
+/// Error displayed when the `:base` or `:quote` URL param is not a mint
+const ERR_PAIR_PARSE: &str = "could not parse base or quote mint";
+/// Error displayed when the cross preview rate limit is exhausted
+const ERR_RATE_LIMITED: &str = "too many cross preview requests, try again later";
+
+// ----------------
+// | Query Params |
+// ----------------
+
+/// The query param filtering orders by state
+const STATE_QUERY_PARAM: &str = "state";
+/// The query param filtering orders by managing cluster
+const CLUSTER_QUERY_PARAM: &str = "cluster";
+/// The query param filtering orders by whether they are locally managed
+const LOCAL_QUERY_PARAM: &str = "local";
+/// The query param filtering orders by wallet match nullifier
+const NULLIFIER_QUERY_PARAM: &str = "nullifier";
+/// The query param giving the order ID after which to begin the page
+const CURSOR_QUERY_PARAM: &str = "cursor";
+/// The query param giving the maximum number of orders to return
+const LIMIT_QUERY_PARAM: &str = "limit";
+
+/// The number of orders returned per page when no limit is given
+const DEFAULT_PAGE_SIZE: usize = 100;
+/// The maximum number of orders that may be requested in a single page
+const MAX_PAGE_SIZE: usize = 1_000;
+
+/// The :base param in a URL
+const BASE_URL_PARAM: &str = "base";
+/// The :quote param in a URL
+const QUOTE_URL_PARAM: &str = "quote";
+/// The minimum number of orders a size bucket must hold to be reported, smaller buckets
+/// would come close to revealing individual orders
+const MIN_REPORTED_BUCKET_COUNT: usize = 3;
 
 // ---------------
 // | HTTP Routes |
 // ---------------
 
-/// Returns all known network orders
+/// Returns a page of known network orders, optionally filtered by the query params
 pub(super) const GET_NETWORK_ORDERS_ROUTE: &str = "/v0/order_book/orders";
 /// Returns the network order information of the specified order
 pub(super) const GET_NETWORK_ORDER_BY_ID_ROUTE: &str = "/v0/order_book/orders/:order_id";
+/// Returns an indication of the liquidity available for a pair
+pub(super) const GET_LIQUIDITY_ROUTE: &str = "/v0/liquidity/:base/:quote";
+/// Returns whether counter-orders to a prospective order plausibly exist
+pub(super) const CROSS_PREVIEW_ROUTE: &str = "/v0/preview/cross";
+
+// -----------
+// | Helpers |
+// -----------
+
+/// Build a `400 Bad Request` error with the given message
+fn bad_request(message: &str) -> ApiServerError {
+    ApiServerError::HttpStatusCode(StatusCode::BAD_REQUEST, message.to_string())
+}
+
+/// Parse an order state from its name, case insensitive
+///
+/// The `Matched` variant's payload is ignored by the filter, so a placeholder is used
+fn parse_order_state(state: &str) -> Option<NetworkOrderState> {
+    match state.to_lowercase().as_str() {
+        "received" => Some(NetworkOrderState::Received),
+        "verified" => Some(NetworkOrderState::Verified),
+        "matched" => Some(NetworkOrderState::Matched {
+            by_local_node: false,
+        }),
+        "cancelled" => Some(NetworkOrderState::Cancelled),
+        "pruned" => Some(NetworkOrderState::Pruned),
+        _ => None,
+    }
+}
+
+/// Parse an order book filter from the query params of a request
+fn parse_filter_from_params(params: &UrlParams) -> Result<OrderBookFilter, ApiServerError> {
+    let state = params
+        .get(STATE_QUERY_PARAM)
+        .map(|state| parse_order_state(state).ok_or_else(|| bad_request(ERR_STATE_PARSE)))
+        .transpose()?;
+    let cluster = params
+        .get(CLUSTER_QUERY_PARAM)
+        .map(|cluster| cluster.parse().unwrap());
+    let local = params
+        .get(LOCAL_QUERY_PARAM)
+        .map(|local| {
+            local
+                .parse::<bool>()
+                .map_err(|_| bad_request(ERR_LOCAL_PARSE))
+        })
+        .transpose()?;
+    let match_nullifier = params
+        .get(NULLIFIER_QUERY_PARAM)
+        .map(|nullifier| {
+            nullifier
+                .parse::<BigUint>()
+                .map(|nullifier| biguint_to_scalar(&nullifier))
+                .map_err(|_| bad_request(ERR_NULLIFIER_PARSE))
+        })
+        .transpose()?;
+
+    Ok(OrderBookFilter {
+        state,
+        cluster,
+        local,
+        match_nullifier,
+    })
+}
+
+/// Parse the pagination cursor and page size from the query params of a request
+fn parse_page_from_params(
+    params: &UrlParams,
+) -> Result<(Option<OrderIdentifier>, usize), ApiServerError> {
+    let cursor = params
+        .get(CURSOR_QUERY_PARAM)
+        .map(|cursor| cursor.parse().map_err(|_| bad_request(ERR_CURSOR_PARSE)))
+        .transpose()?;
+    let limit = match params.get(LIMIT_QUERY_PARAM) {
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 && limit <= MAX_PAGE_SIZE => limit,
+            _ => return Err(bad_request(ERR_LIMIT_PARSE)),
+        },
+        None => DEFAULT_PAGE_SIZE,
+    };
+
+    Ok((cursor, limit))
+}
+
+/// Parse the base and quote mints from the URL params of a request
+fn parse_pair_from_params(params: &UrlParams) -> Result<(BigUint, BigUint), ApiServerError> {
+    let base = params
+        .get(BASE_URL_PARAM)
+        .unwrap()
+        .parse()
+        .map_err(|_| bad_request(ERR_PAIR_PARSE))?;
+    let quote = params
+        .get(QUOTE_URL_PARAM)
+        .unwrap()
+        .parse()
+        .map_err(|_| bad_request(ERR_PAIR_PARSE))?;
+
+    Ok((base, quote))
+}
+
+/// Remove the size buckets that hold too few orders to be reported
+fn coarsen_side(mut side: SideLiquidity) -> SideLiquidity {
+    side.size_buckets
+        .retain(|_, count| *count >= MIN_REPORTED_BUCKET_COUNT);
+    side
+}
+
+/// Collect the verified orders managed by the local cluster on the given pair, along with
+/// the number of verified orders from other clusters, whose pair cannot be known
+async fn local_orders_on_pair(
+    global_state: &RelayerState,
+    base_mint: &BigUint,
+    quote_mint: &BigUint,
+) -> (Vec<Order>, usize) {
+    let mut orders = Vec::new();
+    let mut unattributed_order_count = 0;
+
+    let locked_order_book = global_state.read_order_book().await;
+    for order_id in locked_order_book.get_verified_orders().await.iter() {
+        let order_info = match locked_order_book.get_order_info(order_id).await {
+            Some(info) => info,
+            None => continue,
+        };
+
+        if !order_info.local {
+            unattributed_order_count += 1;
+            continue;
+        }
+
+        // Local orders without a witness cannot be attributed to a pair
+        let order: Order = match order_info.valid_commit_witness {
+            Some(witness) => witness.order.into(),
+            None => continue,
+        };
+        if order.base_mint == *base_mint && order.quote_mint == *quote_mint {
+            orders.push(order);
+        }
+    }
+
+    (orders, unattributed_order_count)
+}
 
 // ----------------------
 // | Order Book Routers |
@@ -65,20 +258,23 @@ impl TypedHandler for GetNetworkOrdersHandler {
     async fn handle_typed(
         &self,
         _req: Self::Request,
-        _params: UrlParams,
+        params: UrlParams,
     ) -> Result<Self::Response, ApiServerError> {
-        let orders: Vec<NetworkOrder> = self
+        let filter = parse_filter_from_params(&params)?;
+        let (cursor, limit) = parse_page_from_params(&params)?;
+
+        let (orders, next_cursor) = self
             .global_state
             .read_order_book()
             .await
-            .get_order_book_snapshot()
-            .await
-            .values()
-            .cloned()
-            .map(|order| order.into())
-            .collect_vec();
+            .get_orders_page(&filter, cursor, limit)
+            .await;
+        let orders: Vec<NetworkOrder> = orders.into_iter().map(|order| order.into()).collect_vec();
 
-        Ok(GetNetworkOrdersResponse { orders })
+        Ok(GetNetworkOrdersResponse {
+            orders,
+            next_cursor,
+        })
     }
 }
 
@@ -125,3 +321,126 @@ impl TypedHandler for GetNetworkOrderByIdHandler {
         }
     }
 }
+
+/// Handler for the GET /liquidity/:base/:quote route
+///
+/// Only orders managed by the local cluster have a plaintext pair and size; orders from
+/// other clusters are hidden behind their validity proofs, so they are counted in aggregate
+#[derive(Clone, Debug)]
+pub struct GetLiquidityHandler {
+    /// A copy of the relayer-global state
+    pub global_state: RelayerState,
+}
+
+impl GetLiquidityHandler {
+    /// Constructor
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetLiquidityHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetLiquidityResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let (base_mint, quote_mint) = parse_pair_from_params(&params)?;
+        let (orders, unattributed_order_count) =
+            local_orders_on_pair(&self.global_state, &base_mint, &quote_mint).await;
+
+        let mut buy = SideLiquidity::default();
+        let mut sell = SideLiquidity::default();
+        for order in orders.iter() {
+            let side = match order.side {
+                OrderSide::Buy => &mut buy,
+                OrderSide::Sell => &mut sell,
+            };
+            side.order_count += 1;
+            *side
+                .size_buckets
+                .entry(size_bucket(order.amount))
+                .or_default() += 1;
+        }
+
+        Ok(GetLiquidityResponse {
+            base_mint,
+            quote_mint,
+            buy: coarsen_side(buy),
+            sell: coarsen_side(sell),
+            unattributed_order_count,
+        })
+    }
+}
+
+/// Handler for the POST /preview/cross route
+///
+/// Reports whether verified counter-orders to a prospective order plausibly exist, without
+/// brokering any MPC. Only local orders have a plaintext pair and size, so only they are
+/// considered. The answer is coarsened as the liquidity route is; counter-orders are
+/// bucketed by size, and buckets holding too few orders are not counted. Requests are rate
+/// limited across all callers, so that the book cannot be swept by repeated previews
+#[derive(Clone, Debug)]
+pub struct CrossPreviewHandler {
+    /// A copy of the relayer-global state
+    pub global_state: RelayerState,
+    /// The rate limiter shared by all callers of the route
+    pub rate_limiter: RateLimiter,
+}
+
+impl CrossPreviewHandler {
+    /// Constructor
+    pub fn new(global_state: RelayerState, rate_limiter: RateLimiter) -> Self {
+        Self {
+            global_state,
+            rate_limiter,
+        }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for CrossPreviewHandler {
+    type Request = CrossPreviewRequest;
+    type Response = CrossPreviewResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        if !self.rate_limiter.try_acquire() {
+            return Err(ApiServerError::HttpStatusCode(
+                StatusCode::TOO_MANY_REQUESTS,
+                ERR_RATE_LIMITED.to_string(),
+            ));
+        }
+
+        let (orders, _) =
+            local_orders_on_pair(&self.global_state, &req.base_mint, &req.quote_mint).await;
+
+        // Bucket the counter-orders whose size plausibly overlaps the prospective order's
+        let taker_bucket = size_bucket(req.amount);
+        let mut counter_side = SideLiquidity::default();
+        for order in orders.iter().filter(|order| order.side != req.side) {
+            let bucket = size_bucket(order.amount);
+            if buckets_overlap(bucket, taker_bucket) {
+                counter_side.order_count += 1;
+                *counter_side.size_buckets.entry(bucket).or_default() += 1;
+            }
+        }
+
+        let size_bucket = coarsen_side(counter_side)
+            .size_buckets
+            .keys()
+            .next_back()
+            .copied();
+        Ok(CrossPreviewResponse {
+            plausible: size_bucket.is_some(),
+            size_bucket,
+        })
+    }
+}
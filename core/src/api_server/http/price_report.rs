@@ -2,6 +2,7 @@
 
 use async_trait::async_trait;
 use crossbeam::channel;
+use hyper::StatusCode;
 
 use crate::{
     api_server::{
@@ -10,9 +11,11 @@ use crate::{
         worker::ApiServerConfig,
     },
     external_api::http::price_report::{
-        GetExchangeHealthStatesRequest, GetExchangeHealthStatesResponse,
+        GetExchangeHealthStatesRequest, GetExchangeHealthStatesResponse, GetPriceCandlesRequest,
+        GetPriceCandlesResponse, GetPriceHistoryRequest, GetPriceHistoryResponse,
+        GetSignedPriceReportRequest, GetSignedPriceReportResponse,
     },
-    price_reporter::jobs::PriceReporterManagerJob,
+    price_reporter::{history::MAX_HISTORY_POINTS, jobs::PriceReporterManagerJob},
 };
 
 // ---------------
@@ -21,6 +24,21 @@ use crate::{
 
 /// Exchange health check route
 pub(super) const EXCHANGE_HEALTH_ROUTE: &str = "/v0/exchange/health_check";
+/// Route to fetch the latest median price of a pair signed by the cluster key
+pub(super) const SIGNED_PRICE_REPORT_ROUTE: &str = "/v0/price_report/signed";
+/// Route to fetch the recent midpoint history of a pair
+pub(super) const PRICE_HISTORY_ROUTE: &str = "/v0/price_report/history";
+/// Route to fetch the OHLC candles of a pair
+pub(super) const PRICE_CANDLES_ROUTE: &str = "/v0/price_report/candles";
+
+// ------------------
+// | Error Messages |
+// ------------------
+
+/// Error message displayed when no signed price report exists for a pair
+const ERR_NO_SIGNED_REPORT: &str = "no signed price report for pair, price signing may be disabled";
+/// Error message displayed when a candle range ends before it starts
+const ERR_INVALID_CANDLE_RANGE: &str = "candle range must end after it starts";
 
 // ------------------
 // | Route Handlers |
@@ -58,21 +76,166 @@ impl TypedHandler for ExchangeHealthStatesHandler {
                 base_token: req.base_token.clone(),
                 quote_token: req.quote_token.clone(),
                 channel: price_reporter_state_sender,
-            })
-            .unwrap();
+            })?;
         let (exchange_connection_state_sender, exchange_connection_state_receiver) =
             channel::unbounded();
         self.config
             .price_reporter_work_queue
             .send(PriceReporterManagerJob::PeekAllExchanges {
+                base_token: req.base_token.clone(),
+                quote_token: req.quote_token.clone(),
+                channel: exchange_connection_state_sender,
+            })?;
+        let (exchange_health_sender, exchange_health_receiver) = channel::unbounded();
+        self.config.price_reporter_work_queue.send(
+            PriceReporterManagerJob::PeekExchangeHealth {
                 base_token: req.base_token,
                 quote_token: req.quote_token,
-                channel: exchange_connection_state_sender,
-            })
-            .unwrap();
+                channel: exchange_health_sender,
+            },
+        )?;
+        let all_exchange_states = exchange_connection_state_receiver.recv().unwrap();
         Ok(GetExchangeHealthStatesResponse {
             median: price_reporter_state_receiver.recv().unwrap(),
-            all_exchanges: exchange_connection_state_receiver.recv().unwrap(),
+            all_exchanges: all_exchange_states.exchanges,
+            decentralized_reference: all_exchange_states.decentralized_reference,
+            exchange_health: exchange_health_receiver.recv().unwrap(),
+        })
+    }
+}
+
+/// Handler for the signed price report route, returns the latest median of a pair
+/// signed by the cluster key
+#[derive(Clone, Debug)]
+pub(crate) struct SignedPriceReportHandler {
+    /// The config for the API server
+    config: ApiServerConfig,
+}
+
+impl SignedPriceReportHandler {
+    /// Create a new handler for "/price_report/signed"
+    pub fn new(config: ApiServerConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for SignedPriceReportHandler {
+    type Request = GetSignedPriceReportRequest;
+    type Response = GetSignedPriceReportResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let (signed_report_sender, signed_report_receiver) = channel::unbounded();
+        self.config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::PeekSignedMedian {
+                base_token: req.base_token,
+                quote_token: req.quote_token,
+                channel: signed_report_sender,
+            })?;
+
+        let report = signed_report_receiver.recv().unwrap().ok_or_else(|| {
+            ApiServerError::HttpStatusCode(StatusCode::NOT_FOUND, ERR_NO_SIGNED_REPORT.to_string())
+        })?;
+        Ok(GetSignedPriceReportResponse { report })
+    }
+}
+
+/// Handler for the price history route, returns the recent midpoint history of the
+/// median and of each exchange for a pair
+#[derive(Clone, Debug)]
+pub(crate) struct PriceHistoryHandler {
+    /// The config for the API server
+    config: ApiServerConfig,
+}
+
+impl PriceHistoryHandler {
+    /// Create a new handler for "/price_report/history"
+    pub fn new(config: ApiServerConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for PriceHistoryHandler {
+    type Request = GetPriceHistoryRequest;
+    type Response = GetPriceHistoryResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let (history_sender, history_receiver) = channel::unbounded();
+        self.config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::PeekPriceHistory {
+                base_token: req.base_token,
+                quote_token: req.quote_token,
+                since: req.since.unwrap_or_default(),
+                limit: req.limit.unwrap_or(MAX_HISTORY_POINTS),
+                channel: history_sender,
+            })?;
+
+        Ok(GetPriceHistoryResponse {
+            history: history_receiver.recv().unwrap(),
+        })
+    }
+}
+
+/// Handler for the price candles route, returns the OHLC candles of the median or of a
+/// single exchange for a pair
+#[derive(Clone, Debug)]
+pub(crate) struct PriceCandlesHandler {
+    /// The config for the API server
+    config: ApiServerConfig,
+}
+
+impl PriceCandlesHandler {
+    /// Create a new handler for "/price_report/candles"
+    pub fn new(config: ApiServerConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for PriceCandlesHandler {
+    type Request = GetPriceCandlesRequest;
+    type Response = GetPriceCandlesResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        _params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let start = req.start.unwrap_or_default();
+        let end = req.end.unwrap_or(u64::MAX);
+        if end <= start {
+            return Err(ApiServerError::HttpStatusCode(
+                StatusCode::BAD_REQUEST,
+                ERR_INVALID_CANDLE_RANGE.to_string(),
+            ));
+        }
+
+        let (candles_sender, candles_receiver) = channel::unbounded();
+        self.config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::PeekPriceCandles {
+                base_token: req.base_token,
+                quote_token: req.quote_token,
+                exchange: req.exchange,
+                interval: req.interval,
+                start,
+                end,
+                channel: candles_sender,
+            })?;
+
+        Ok(GetPriceCandlesResponse {
+            candles: candles_receiver.recv().unwrap(),
         })
     }
 }
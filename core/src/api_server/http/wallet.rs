@@ -11,12 +11,13 @@ use crate::{
     external_api::{
         http::wallet::{
             GetBalanceByMintResponse, GetBalancesResponse, GetFeesResponse, GetOrderByIdResponse,
-            GetOrdersResponse, GetWalletResponse,
+            GetOrderSlotsResponse, GetOrdersResponse, GetWalletResponse, SetAutoResubmitRequest,
         },
         types::{Balance, Wallet},
         EmptyRequestResponse,
     },
     state::RelayerState,
+    MAX_ORDERS,
 };
 
 use super::{parse_mint_from_params, parse_order_id_from_params, parse_wallet_id_from_params};
@@ -31,6 +32,11 @@ pub(super) const GET_WALLET_ROUTE: &str = "/v0/wallet/:wallet_id";
 pub(super) const GET_ORDERS_ROUTE: &str = "/v0/wallet/:wallet_id/orders";
 /// Returns a single order by the given identifier
 pub(super) const GET_ORDER_BY_ID_ROUTE: &str = "/v0/wallet/:wallet_id/orders/:order_id";
+/// Returns the occupancy of the order slots within a given wallet
+pub(super) const GET_ORDER_SLOTS_ROUTE: &str = "/v0/wallet/:wallet_id/slots";
+/// Opts an order into or out of automatic resubmission after cancellation
+pub(super) const SET_AUTO_RESUBMIT_ROUTE: &str =
+    "/v0/wallet/:wallet_id/orders/:order_id/auto_resubmit";
 /// Returns the balances within a given wallet
 pub(super) const GET_BALANCES_ROUTE: &str = "/v0/wallet/:wallet_id/balances";
 /// Returns the balance associated with the given mint
@@ -84,6 +90,7 @@ impl TypedHandler for GetWalletHandler {
             .await
         {
             Ok(GetWalletResponse {
+                lifecycle: self.global_state.wallet_manager().lifecycle(&wallet_id),
                 wallet: wallet.into(),
             })
         } else {
@@ -194,6 +201,90 @@ impl TypedHandler for GetOrderByIdHandler {
     }
 }
 
+/// Handler for the GET /wallet/:id/slots route
+#[derive(Clone, Debug)]
+pub struct GetOrderSlotsHandler {
+    /// A copy of the relayer-global state
+    pub global_state: RelayerState,
+}
+
+impl GetOrderSlotsHandler {
+    /// Constructor
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for GetOrderSlotsHandler {
+    type Request = EmptyRequestResponse;
+    type Response = GetOrderSlotsResponse;
+
+    async fn handle_typed(
+        &self,
+        _req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let wallet_id = parse_wallet_id_from_params(&params)?;
+        if let Some(slots) = self.global_state.get_order_slots(&wallet_id).await {
+            Ok(GetOrderSlotsResponse {
+                capacity: MAX_ORDERS,
+                free: MAX_ORDERS.saturating_sub(slots.len()),
+                slots,
+            })
+        } else {
+            Err(ApiServerError::HttpStatusCode(
+                StatusCode::NOT_FOUND,
+                ERR_WALLET_NOT_FOUND.to_string(),
+            ))
+        }
+    }
+}
+
+/// Handler for the POST /wallet/:id/orders/:id/auto_resubmit route
+#[derive(Clone, Debug)]
+pub struct SetAutoResubmitHandler {
+    /// A copy of the relayer-global state
+    pub global_state: RelayerState,
+}
+
+impl SetAutoResubmitHandler {
+    /// Constructor
+    pub fn new(global_state: RelayerState) -> Self {
+        Self { global_state }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for SetAutoResubmitHandler {
+    type Request = SetAutoResubmitRequest;
+    type Response = EmptyRequestResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let wallet_id = parse_wallet_id_from_params(&params)?;
+        let order_id = parse_order_id_from_params(&params)?;
+
+        if self
+            .global_state
+            .read_wallet_index()
+            .await
+            .set_auto_resubmit(&wallet_id, &order_id, req.enabled)
+            .await
+        {
+            Ok(EmptyRequestResponse)
+        } else {
+            Err(ApiServerError::HttpStatusCode(
+                StatusCode::NOT_FOUND,
+                ERR_ORDER_NOT_FOUND.to_string(),
+            ))
+        }
+    }
+}
+
 // --------------------------
 // | Balance Route Handlers |
 // --------------------------
@@ -0,0 +1,1019 @@
+//! Groups handlers for user-initiated wallet updates; placing orders, depositing, and
+//! withdrawing
+//!
+//! The relayer must hold the wallet's root key to update it. A request is authenticated
+//! and validated synchronously, after which the update is proven, submitted on-chain, and
+//! applied to the local wallet in the background. The update's progress is streamed to
+//! websocket subscribers of the wallet's update topic
+
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use circuits::{
+    types::{balance::Balance, order::Order as IndexedOrder},
+    zk_circuits::valid_wallet_update::{ValidWalletUpdateStatement, ValidWalletUpdateWitness},
+};
+use crypto::fields::{biguint_to_scalar, starknet_felt_to_biguint};
+use curve25519_dalek::scalar::Scalar;
+use hyper::StatusCode;
+use num_bigint::BigUint;
+use rand_core::OsRng;
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender as TokioSender},
+    oneshot,
+};
+use tracing::log;
+use uuid::Uuid;
+
+use crate::{
+    api_server::{
+        error::ApiServerError,
+        router::{TypedHandler, UrlParams},
+        worker::ApiServerConfig,
+    },
+    error::{ErrorCode, ErrorCoded},
+    external_api::http::wallet::{
+        CreateOrderRequest, CreateOrderResponse, ExternalTransferRequest, WalletUpdateResponse,
+    },
+    job_queue::JobQueueSender,
+    keychain::{KeychainError, RootKeyManager},
+    price_reporter::tokens::{validate_pair, Token},
+    proof_generation::jobs::{
+        ProofJob, ProofJobPriority, ProofManagerJob, ValidWalletUpdateBundle,
+    },
+    recovery::encrypt_wallet,
+    starknet_client::{client::StarknetClient, transaction_manager::TransactionFailedJob},
+    state::{
+        wallet::{OrderEvictionPolicy, Wallet, WalletDelta, WalletDeltaError, WalletIdentifier},
+        wallet_manager::{WalletLifecycle, WalletTaskError},
+        OrderIdentifier, RelayerState,
+    },
+    system_bus::SystemBus,
+    types::{wallet_update_topic, SystemBusMessage, WalletUpdateStatus},
+    MAX_BALANCES, MAX_ORDERS,
+};
+
+use super::parse_wallet_id_from_params;
+
+// ---------------
+// | HTTP Routes |
+// ---------------
+
+/// Places a new order in the given wallet
+pub(super) const CREATE_ORDER_ROUTE: &str = "/v0/wallet/:wallet_id/orders";
+/// Deposits into a balance of the given wallet
+pub(super) const DEPOSIT_ROUTE: &str = "/v0/wallet/:wallet_id/deposit";
+/// Withdraws from a balance of the given wallet
+pub(super) const WITHDRAW_ROUTE: &str = "/v0/wallet/:wallet_id/withdraw";
+
+// ------------------
+// | Error Messages |
+// ------------------
+
+/// Error message displayed when a wallet cannot be found
+const ERR_WALLET_NOT_FOUND: &str = "wallet not found";
+/// Error message displayed when the relayer does not hold a wallet's root key
+const ERR_NO_ROOT_KEY: &str =
+    "neither the relayer nor its external signer holds the root key for this wallet";
+/// Error message displayed when a request's authentication digest is invalid
+const ERR_INVALID_AUTH: &str = "invalid authentication digest";
+/// Error message displayed when a wallet already has an update in flight
+const ERR_UPDATE_IN_FLIGHT: &str = "wallet has an update in flight";
+/// Error message displayed when a wallet is frozen after its state was found corrupt
+const ERR_WALLET_FROZEN: &str = "wallet is frozen, recover it to resume updates";
+/// Error message displayed when an amount does not fit in a u64
+const ERR_AMOUNT_OVERFLOW: &str = "amount exceeds the maximum balance";
+/// Error message displayed when the relayer cannot reach a JSON-RPC node
+const ERR_NO_JSONRPC: &str = "no JSON-RPC node configured to recover the wallet's Merkle path";
+/// Error message displayed when a wallet has no free order slot
+const ERR_ORDERS_FULL: &str = "wallet has no free order slot";
+/// Error message displayed when a wallet has no free balance slot
+const ERR_BALANCES_FULL: &str = "wallet has no free balance slot";
+/// Error message displayed when a withdrawal exceeds the balance
+const ERR_INSUFFICIENT_BALANCE: &str = "insufficient balance";
+
+/// The direction of a deposit in an external transfer
+const DEPOSIT_DIRECTION: u64 = 0;
+/// The direction of a withdrawal in an external transfer
+const WITHDRAW_DIRECTION: u64 = 1;
+
+// -----------
+// | Helpers |
+// -----------
+
+/// Build an error with the given status code and message
+fn http_error(status: StatusCode, message: &str) -> ApiServerError {
+    ApiServerError::HttpStatusCode(status, message.to_string())
+}
+
+/// Convert a requested amount into the wallet's native width
+fn parse_amount(amount: &BigUint) -> Result<u64, ApiServerError> {
+    u64::try_from(amount).map_err(|_| http_error(StatusCode::BAD_REQUEST, ERR_AMOUNT_OVERFLOW))
+}
+
+/// Build a delta against the given wallet with no changes but a bumped version and
+/// fresh randomness
+///
+/// The match nullifier is computed from the randomness plus one, so the randomness is
+/// advanced by two to keep the new wallet's nullifiers disjoint from the old wallet's
+fn empty_delta(wallet: &Wallet) -> WalletDelta {
+    WalletDelta {
+        version: wallet.metadata.version + 1,
+        updated_orders: Default::default(),
+        removed_orders: Vec::new(),
+        updated_balances: Default::default(),
+        removed_balances: Vec::new(),
+        fees: None,
+        randomness: wallet.randomness.clone() + 2u8,
+    }
+}
+
+// ------------------
+// | Update Tracker |
+// ------------------
+
+/// Validates, authenticates, and executes wallet updates, shared between the handlers
+#[derive(Clone, Debug)]
+pub(super) struct WalletUpdater {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+    /// The queue on which to request proofs of `VALID WALLET UPDATE`
+    proof_manager_queue: JobQueueSender<ProofManagerJob>,
+    /// The client used to submit updates on-chain
+    starknet_client: StarknetClient,
+    /// The bus on which update progress is published
+    system_bus: SystemBus<SystemBusMessage>,
+    /// The policy applied when an order is placed in a wallet with no free order slot
+    order_eviction_policy: OrderEvictionPolicy,
+    /// Authorizes root operations, the expected authentication digest of a request
+    root_key_manager: RootKeyManager,
+    /// The wallets with an update in flight; a wallet may only have one update in flight
+    /// as each update is built against the wallet's current version
+    in_flight: Arc<Mutex<HashSet<WalletIdentifier>>>,
+}
+
+impl WalletUpdater {
+    /// Constructor
+    pub(super) fn new(config: &ApiServerConfig, global_state: RelayerState) -> Self {
+        Self {
+            global_state,
+            proof_manager_queue: config.proof_generation_work_queue.clone(),
+            starknet_client: config.starknet_client.clone(),
+            system_bus: config.system_bus.clone(),
+            order_eviction_policy: config.order_eviction_policy,
+            root_key_manager: config.root_key_manager.clone(),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Fetch a wallet and authenticate a request against its root key
+    ///
+    /// The expected digest is the hash of the root key, the wallet's version, and the
+    /// request payload; it is computed locally if the relayer holds the root key, and by
+    /// the external signer otherwise
+    async fn authenticate(
+        &self,
+        wallet_id: &WalletIdentifier,
+        payload: &[Scalar],
+        auth: &BigUint,
+    ) -> Result<Wallet, ApiServerError> {
+        let wallet = self
+            .global_state
+            .read_wallet_index()
+            .await
+            .get_wallet(wallet_id)
+            .await
+            .ok_or_else(|| http_error(StatusCode::NOT_FOUND, ERR_WALLET_NOT_FOUND))?;
+        let expected_auth = self
+            .root_key_manager
+            .authorize(&wallet, payload)
+            .await
+            .map_err(|err| match err {
+                KeychainError::NoRootKey => http_error(StatusCode::FORBIDDEN, ERR_NO_ROOT_KEY),
+                _ => ApiServerError::HttpStatusCode(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    err.to_string(),
+                ),
+            })?;
+        if expected_auth != biguint_to_scalar(auth) {
+            return Err(http_error(StatusCode::UNAUTHORIZED, ERR_INVALID_AUTH));
+        }
+
+        Ok(wallet)
+    }
+
+    /// Choose an order to cancel to free a slot in a full wallet, per the eviction policy
+    async fn choose_eviction(
+        &self,
+        wallet_id: &WalletIdentifier,
+    ) -> Result<OrderIdentifier, ApiServerError> {
+        let slots = self
+            .global_state
+            .get_order_slots(wallet_id)
+            .await
+            .ok_or_else(|| http_error(StatusCode::NOT_FOUND, ERR_WALLET_NOT_FOUND))?;
+
+        self.order_eviction_policy
+            .choose_eviction(&slots)
+            .ok_or_else(|| http_error(StatusCode::BAD_REQUEST, ERR_ORDERS_FULL))
+    }
+
+    /// Begin executing a validated update in the background, returning its task ID
+    fn start_update(
+        &self,
+        wallet: Wallet,
+        delta: WalletDelta,
+        external_transfer: (Scalar, Scalar, Scalar),
+    ) -> Result<Uuid, ApiServerError> {
+        if let Some(WalletLifecycle::Frozen { .. }) = self
+            .global_state
+            .wallet_manager()
+            .lifecycle(&wallet.wallet_id)
+        {
+            return Err(http_error(StatusCode::CONFLICT, ERR_WALLET_FROZEN));
+        }
+        if !self.in_flight.lock().unwrap().insert(wallet.wallet_id) {
+            return Err(http_error(StatusCode::CONFLICT, ERR_UPDATE_IN_FLIGHT));
+        }
+
+        let task_id = Uuid::new_v4();
+        let self_clone = self.clone();
+        tokio::spawn(async move {
+            let wallet_id = wallet.wallet_id;
+            let (failure_sender, mut failure_receiver) = unbounded_channel();
+
+            // The update waits behind any other task mutating the wallet
+            let res = self_clone
+                .global_state
+                .wallet_manager()
+                .run_task(
+                    &wallet_id,
+                    self_clone.execute_update(
+                        task_id,
+                        wallet,
+                        delta,
+                        external_transfer,
+                        failure_sender,
+                    ),
+                )
+                .await
+                .map_err(|err| match err {
+                    WalletTaskError::Task(err) => err,
+                    WalletTaskError::Frozen(_) => {
+                        (ErrorCode::Conflict, ERR_WALLET_FROZEN.to_string())
+                    }
+                    WalletTaskError::NotFound(_) => {
+                        (ErrorCode::NotFound, ERR_WALLET_NOT_FOUND.to_string())
+                    }
+                });
+            let status = match res {
+                Ok(tx_hash) => WalletUpdateStatus::Submitted { tx_hash },
+                Err((code, reason)) => {
+                    log::error!(
+                        "wallet update {task_id} on wallet {wallet_id} failed ({code}): {reason}"
+                    );
+                    WalletUpdateStatus::Failed { code, reason }
+                }
+            };
+
+            self_clone.in_flight.lock().unwrap().remove(&wallet_id);
+            self_clone.publish_status(wallet_id, task_id, status);
+
+            // The transaction manager sends a job if the transaction fails after it was
+            // broadcast, and drops the queue without one once the transaction is accepted
+            if let Some(job) = failure_receiver.recv().await {
+                log::error!(
+                    "wallet update {task_id} on wallet {wallet_id} was rejected: {}",
+                    job.reason
+                );
+                self_clone.publish_status(
+                    wallet_id,
+                    task_id,
+                    WalletUpdateStatus::Failed {
+                        code: ErrorCode::TransactionRejected,
+                        reason: job.reason,
+                    },
+                );
+            }
+        });
+
+        Ok(task_id)
+    }
+
+    /// Prove and submit an update, then apply it to the local wallet, returning the hash
+    /// of the submitted transaction, or the code and reason of the failure
+    async fn execute_update(
+        &self,
+        task_id: Uuid,
+        wallet: Wallet,
+        delta: WalletDelta,
+        external_transfer: (Scalar, Scalar, Scalar),
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<String, (ErrorCode, String)> {
+        let wallet_id = wallet.wallet_id;
+        self.publish_status(wallet_id, task_id, WalletUpdateStatus::Proving);
+
+        let mut new_wallet = wallet.clone();
+        delta.apply(&mut new_wallet);
+
+        // Prefer the authentication path kept fresh by the chain listener
+        let merkle_path = match wallet.merkle_proof.clone() {
+            Some(path) => path,
+            None if !self.starknet_client.jsonrpc_enabled() => {
+                return Err((ErrorCode::Config, ERR_NO_JSONRPC.to_string()))
+            }
+            None => self
+                .global_state
+                .build_merkle_authentication_path(
+                    &wallet,
+                    self.starknet_client.config.contract_addr.clone(),
+                    self.starknet_client.get_jsonrpc_client(),
+                )
+                .await
+                .map_err(|err| (err.error_code(), err.to_string()))?,
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("negative timestamp")
+            .as_secs();
+        let statement = ValidWalletUpdateStatement {
+            timestamp: Scalar::from(timestamp),
+            pk_root: wallet.public_keys.pk_root,
+            new_wallet_commitment: new_wallet.get_commitment(),
+            wallet_spend_nullifier: wallet.get_spend_nullifier(),
+            wallet_match_nullifier: wallet.get_match_nullifier(),
+            merkle_root: merkle_path.compute_root(),
+            external_transfer,
+        };
+        let ciphertext = encrypt_wallet(&new_wallet, &mut OsRng {});
+        let witness = ValidWalletUpdateWitness {
+            wallet1: wallet.clone().into(),
+            wallet2: new_wallet.into(),
+            wallet1_opening: merkle_path.into(),
+            internal_transfer: (Scalar::zero(), Scalar::zero()),
+        };
+
+        let (response_sender, response_receiver) = oneshot::channel();
+        self.proof_manager_queue
+            .send(ProofManagerJob {
+                type_: ProofJob::ValidWalletUpdate { witness, statement },
+                priority: ProofJobPriority::UserInitiated,
+                cancellation: None,
+                response_channel: response_sender,
+            })
+            .map_err(|err| (ErrorCode::Internal, err.to_string()))?;
+        let bundle: ValidWalletUpdateBundle = response_receiver
+            .await
+            .map_err(|_| {
+                (
+                    ErrorCode::Proof,
+                    "proof of VALID WALLET UPDATE was abandoned".to_string(),
+                )
+            })?
+            .into();
+
+        self.publish_status(wallet_id, task_id, WalletUpdateStatus::Submitting);
+        let tx_hash = self
+            .starknet_client
+            .update_wallet(&bundle.statement, &ciphertext, failure_queue)
+            .await
+            .map_err(|err| (err.error_code(), err.to_string()))?;
+
+        // Apply the update locally; cluster peers pick up the delta through replica repair
+        self.global_state
+            .apply_wallet_deltas(&wallet_id, vec![delta])
+            .await
+            .map_err(|err| {
+                let code = match err {
+                    WalletDeltaError::MissingWallet(_) => ErrorCode::NotFound,
+                    WalletDeltaError::VersionGap { .. } => ErrorCode::Conflict,
+                };
+                (code, err.to_string())
+            })?;
+
+        Ok(format!(
+            "0x{}",
+            starknet_felt_to_biguint(&tx_hash).to_str_radix(16)
+        ))
+    }
+
+    /// Publish the progress of an update to the wallet's update topic
+    fn publish_status(
+        &self,
+        wallet_id: WalletIdentifier,
+        task_id: Uuid,
+        status: WalletUpdateStatus,
+    ) {
+        self.system_bus.publish(
+            wallet_update_topic(&wallet_id),
+            SystemBusMessage::WalletUpdateProgress {
+                wallet_id,
+                task_id,
+                status,
+            },
+        );
+    }
+}
+
+// --------------------------------
+// | Wallet Update Route Handlers |
+// --------------------------------
+
+/// Handler for the POST /wallet/:id/orders route
+#[derive(Clone, Debug)]
+pub struct CreateOrderHandler {
+    /// The shared wallet update executor
+    updater: WalletUpdater,
+}
+
+impl CreateOrderHandler {
+    /// Constructor
+    pub(super) fn new(updater: WalletUpdater) -> Self {
+        Self { updater }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for CreateOrderHandler {
+    type Request = CreateOrderRequest;
+    type Response = CreateOrderResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let wallet_id = parse_wallet_id_from_params(&params)?;
+        let order = req.order;
+        let payload = [
+            biguint_to_scalar(&order.quote_mint),
+            biguint_to_scalar(&order.base_mint),
+            order.side.into(),
+            order.price.into(),
+            biguint_to_scalar(&order.amount),
+            Scalar::from(order.timestamp),
+        ];
+        let wallet = self
+            .updater
+            .authenticate(&wallet_id, &payload, &req.auth)
+            .await?;
+
+        validate_pair(
+            &Token::from_mint(&order.base_mint),
+            &Token::from_mint(&order.quote_mint),
+        )
+        .map_err(|err| http_error(StatusCode::BAD_REQUEST, &err))?;
+
+        // An order that would expire before it could be matched is refused outright
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("negative timestamp")
+            .as_secs();
+        if let Some(expiry) = order.time_in_force.expiry() && expiry <= now {
+            return Err(http_error(
+                StatusCode::BAD_REQUEST,
+                &format!("order expiry {expiry} is in the past"),
+            ));
+        }
+
+        // As is an order that no match could fill
+        let amount = parse_amount(&order.amount)?;
+        if let Some(min_fill_size) = order.constraints.min_fill_size && min_fill_size > amount {
+            return Err(http_error(
+                StatusCode::BAD_REQUEST,
+                &format!("minimum fill size {min_fill_size} exceeds the order amount"),
+            ));
+        }
+        if let Some(max_slippage_bps) = order.constraints.max_slippage_bps
+            && max_slippage_bps > 10_000
+        {
+            return Err(http_error(
+                StatusCode::BAD_REQUEST,
+                &format!("maximum slippage {max_slippage_bps} bps exceeds 10000 bps"),
+            ));
+        }
+
+        let mut delta = empty_delta(&wallet);
+        let evicted_order = if wallet.orders.len() >= MAX_ORDERS {
+            let evicted = self.updater.choose_eviction(&wallet_id).await?;
+            delta.removed_orders.push(evicted);
+            Some(evicted)
+        } else {
+            None
+        };
+
+        let order_id = Uuid::new_v4();
+        delta.updated_orders.insert(
+            order_id,
+            IndexedOrder {
+                quote_mint: order.quote_mint,
+                base_mint: order.base_mint,
+                side: order.side,
+                price: order.price,
+                amount,
+                timestamp: order.timestamp,
+                time_in_force: order.time_in_force,
+                constraints: order.constraints,
+            },
+        );
+
+        let task_id = self.updater.start_update(
+            wallet,
+            delta,
+            (Scalar::zero(), Scalar::zero(), Scalar::zero()),
+        )?;
+        Ok(CreateOrderResponse {
+            order_id,
+            task_id,
+            topic: wallet_update_topic(&wallet_id),
+            evicted_order,
+        })
+    }
+}
+
+/// Handler for the POST /wallet/:id/deposit route
+#[derive(Clone, Debug)]
+pub struct DepositHandler {
+    /// The shared wallet update executor
+    updater: WalletUpdater,
+}
+
+impl DepositHandler {
+    /// Constructor
+    pub(super) fn new(updater: WalletUpdater) -> Self {
+        Self { updater }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for DepositHandler {
+    type Request = ExternalTransferRequest;
+    type Response = WalletUpdateResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let wallet_id = parse_wallet_id_from_params(&params)?;
+        let payload = [biguint_to_scalar(&req.mint), biguint_to_scalar(&req.amount)];
+        let wallet = self
+            .updater
+            .authenticate(&wallet_id, &payload, &req.auth)
+            .await?;
+
+        let amount = parse_amount(&req.amount)?;
+        let new_amount = match wallet.balances.get(&req.mint) {
+            Some(balance) => balance.amount.checked_add(amount),
+            None if wallet.balances.len() >= MAX_BALANCES => {
+                return Err(http_error(StatusCode::BAD_REQUEST, ERR_BALANCES_FULL))
+            }
+            None => Some(amount),
+        }
+        .ok_or_else(|| http_error(StatusCode::BAD_REQUEST, ERR_AMOUNT_OVERFLOW))?;
+
+        let mut delta = empty_delta(&wallet);
+        delta.updated_balances.insert(
+            req.mint.clone(),
+            Balance {
+                mint: req.mint.clone(),
+                amount: new_amount,
+            },
+        );
+
+        let external_transfer = (
+            biguint_to_scalar(&req.mint),
+            Scalar::from(amount),
+            Scalar::from(DEPOSIT_DIRECTION),
+        );
+        let task_id = self
+            .updater
+            .start_update(wallet, delta, external_transfer)?;
+        Ok(WalletUpdateResponse {
+            task_id,
+            topic: wallet_update_topic(&wallet_id),
+        })
+    }
+}
+
+/// Handler for the POST /wallet/:id/withdraw route
+#[derive(Clone, Debug)]
+pub struct WithdrawHandler {
+    /// The shared wallet update executor
+    updater: WalletUpdater,
+}
+
+impl WithdrawHandler {
+    /// Constructor
+    pub(super) fn new(updater: WalletUpdater) -> Self {
+        Self { updater }
+    }
+}
+
+#[async_trait]
+impl TypedHandler for WithdrawHandler {
+    type Request = ExternalTransferRequest;
+    type Response = WalletUpdateResponse;
+
+    async fn handle_typed(
+        &self,
+        req: Self::Request,
+        params: UrlParams,
+    ) -> Result<Self::Response, ApiServerError> {
+        let wallet_id = parse_wallet_id_from_params(&params)?;
+        let payload = [biguint_to_scalar(&req.mint), biguint_to_scalar(&req.amount)];
+        let wallet = self
+            .updater
+            .authenticate(&wallet_id, &payload, &req.auth)
+            .await?;
+
+        let amount = parse_amount(&req.amount)?;
+        let remaining = wallet
+            .balances
+            .get(&req.mint)
+            .and_then(|balance| balance.amount.checked_sub(amount))
+            .ok_or_else(|| http_error(StatusCode::BAD_REQUEST, ERR_INSUFFICIENT_BALANCE))?;
+
+        // An emptied balance is removed to free its slot
+        let mut delta = empty_delta(&wallet);
+        if remaining == 0 {
+            delta.removed_balances.push(req.mint.clone());
+        } else {
+            delta.updated_balances.insert(
+                req.mint.clone(),
+                Balance {
+                    mint: req.mint.clone(),
+                    amount: remaining,
+                },
+            );
+        }
+
+        let external_transfer = (
+            biguint_to_scalar(&req.mint),
+            Scalar::from(amount),
+            Scalar::from(WITHDRAW_DIRECTION),
+        );
+        let task_id = self
+            .updater
+            .start_update(wallet, delta, external_transfer)?;
+        Ok(WalletUpdateResponse {
+            task_id,
+            topic: wallet_update_topic(&wallet_id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        fmt::Debug,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use circuits::{
+        types::{
+            balance::Balance,
+            order::{MatchConstraints, OrderSide, TimeInForce},
+        },
+        zk_gadgets::fixed_point::FixedPoint,
+    };
+    use crypto::fields::{biguint_to_scalar, scalar_to_biguint};
+    use curve25519_dalek::scalar::Scalar;
+    use hyper::StatusCode;
+    use num_bigint::BigUint;
+    use tokio::{sync::Notify, time::timeout};
+    use uuid::Uuid;
+
+    use crate::{
+        api_server::{
+            error::ApiServerError,
+            http::WALLET_ID_URL_PARAM,
+            router::{TypedHandler, UrlParams},
+        },
+        error::ErrorCode,
+        external_api::{
+            http::wallet::{CreateOrderRequest, ExternalTransferRequest},
+            types::{Order, OrderType},
+        },
+        job_queue::{job_queue, DEFAULT_JOB_QUEUE_CAPACITY, PROOF_MANAGER_QUEUE},
+        keychain::{self, RootKeyManager},
+        price_reporter::tokens::Token,
+        proof_generation::proof_cache::ProofCache,
+        starknet_client::{
+            client::{StarknetClient, StarknetClientConfig},
+            ChainId,
+        },
+        state::{
+            cluster_access::ClusterAccessPolicy,
+            feature_flags::FeatureFlags,
+            wallet::{OrderEvictionPolicy, Wallet, WalletMetadata},
+            RelayerState,
+        },
+        system_bus::SystemBus,
+        types::{wallet_update_topic, SystemBusMessage, WalletUpdateStatus},
+    };
+
+    use super::{CreateOrderHandler, DepositHandler, WalletUpdater, WithdrawHandler};
+
+    /// The maximum time to wait for an update to publish its progress
+    const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Get the mint of a token by its ticker
+    fn mint(ticker: &str) -> BigUint {
+        let addr = Token::_from_ticker(ticker).get_addr().to_string();
+        BigUint::parse_bytes(&addr.as_bytes()[2..], 16).unwrap()
+    }
+
+    /// Build a wallet whose root key the relayer holds, with a balance of 100 USDC and no
+    /// authentication path
+    fn test_wallet() -> Wallet {
+        let (public_keys, secret_keys) = keychain::derive_keychain(Scalar::from(7u64));
+        let usdc = mint("USDC");
+
+        Wallet {
+            wallet_id: Uuid::new_v4(),
+            orders: HashMap::new(),
+            balances: HashMap::from([(
+                usdc.clone(),
+                Balance {
+                    mint: usdc,
+                    amount: 100,
+                },
+            )]),
+            fees: Vec::new(),
+            public_keys,
+            secret_keys,
+            randomness: BigUint::from(0u8),
+            metadata: WalletMetadata {
+                replicas: HashSet::new(),
+                version: 0,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: Default::default(),
+        }
+    }
+
+    /// Build an updater over the given wallet, with no JSON-RPC node to recover a
+    /// wallet's authentication path from
+    fn setup_updater(wallet: Wallet) -> WalletUpdater {
+        let system_bus = SystemBus::new();
+        let global_state = RelayerState::initialize_global_state(
+            false, /* debug */
+            vec![wallet],
+            "cluster".parse().unwrap(),
+            ClusterAccessPolicy::default(),
+            system_bus.clone(),
+            ProofCache::new(None /* cache_dir */).unwrap(),
+            FeatureFlags::new(&HashMap::new()),
+        );
+        let starknet_client = StarknetClient::new(StarknetClientConfig {
+            chain: ChainId::AlphaGoerli,
+            contract_addr: "0x1".to_string(),
+            starknet_json_rpc_addr: None,
+            infura_api_key: None,
+            starknet_pkey: None,
+            starknet_account_addr: None,
+        });
+        let (proof_manager_queue, _) = job_queue(PROOF_MANAGER_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+
+        WalletUpdater {
+            global_state,
+            proof_manager_queue,
+            starknet_client,
+            system_bus,
+            order_eviction_policy: OrderEvictionPolicy::Reject,
+            root_key_manager: RootKeyManager::default(),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// The URL params addressing the given wallet
+    fn wallet_params(wallet: &Wallet) -> UrlParams {
+        HashMap::from([(
+            WALLET_ID_URL_PARAM.to_string(),
+            wallet.wallet_id.to_string(),
+        )])
+    }
+
+    /// Compute the authentication digest of a request on a wallet
+    fn auth(wallet: &Wallet, payload: &[Scalar]) -> BigUint {
+        let sk_root = wallet.secret_keys.sk_root.unwrap();
+        scalar_to_biguint(&keychain::root_authorization(
+            sk_root,
+            wallet.metadata.version,
+            payload,
+        ))
+    }
+
+    /// Build an authenticated deposit or withdrawal request
+    fn transfer_request(
+        wallet: &Wallet,
+        mint: BigUint,
+        amount: BigUint,
+    ) -> ExternalTransferRequest {
+        let payload = [biguint_to_scalar(&mint), biguint_to_scalar(&amount)];
+        ExternalTransferRequest {
+            auth: auth(wallet, &payload),
+            mint,
+            amount,
+        }
+    }
+
+    /// Build an authenticated request to place a WETH/USDC order, modified by `modify`
+    fn order_request(wallet: &Wallet, modify: impl FnOnce(&mut Order)) -> CreateOrderRequest {
+        let mut order = Order {
+            id: Uuid::new_v4(),
+            quote_mint: mint("USDC"),
+            base_mint: mint("WETH"),
+            side: OrderSide::Buy,
+            type_: OrderType::Limit,
+            price: FixedPoint::from(10f32),
+            amount: BigUint::from(5u8),
+            timestamp: 0,
+            time_in_force: TimeInForce::GoodTilCancelled,
+            constraints: MatchConstraints::default(),
+        };
+        modify(&mut order);
+
+        let payload = [
+            biguint_to_scalar(&order.quote_mint),
+            biguint_to_scalar(&order.base_mint),
+            order.side.into(),
+            order.price.into(),
+            biguint_to_scalar(&order.amount),
+            Scalar::from(order.timestamp),
+        ];
+        CreateOrderRequest {
+            auth: auth(wallet, &payload),
+            order,
+        }
+    }
+
+    /// Assert that a request was rejected with the given status code
+    fn assert_rejected<T: Debug>(res: Result<T, ApiServerError>, expected: StatusCode) {
+        match res {
+            Err(ApiServerError::HttpStatusCode(status, _)) if status == expected => {}
+            res => panic!("expected {expected}, got {res:?}"),
+        }
+    }
+
+    /// Tests that requests with a bad authentication digest, or on an unknown wallet, are
+    /// rejected
+    #[tokio::test]
+    async fn test_authentication() {
+        let wallet = test_wallet();
+        let handler = DepositHandler::new(setup_updater(wallet.clone()));
+
+        let mut req = transfer_request(&wallet, mint("USDC"), BigUint::from(1u8));
+        req.auth += 1u8;
+        let res = handler.handle_typed(req, wallet_params(&wallet)).await;
+        assert_rejected(res, StatusCode::UNAUTHORIZED);
+
+        let unknown_wallet = test_wallet();
+        let req = transfer_request(&unknown_wallet, mint("USDC"), BigUint::from(1u8));
+        let res = handler
+            .handle_typed(req, wallet_params(&unknown_wallet))
+            .await;
+        assert_rejected(res, StatusCode::NOT_FOUND);
+    }
+
+    /// Tests that orders that are expired, unfillable, or on an invalid pair are refused
+    #[tokio::test]
+    async fn test_create_order_validation() {
+        let wallet = test_wallet();
+        let handler = CreateOrderHandler::new(setup_updater(wallet.clone()));
+
+        let invalid_orders: Vec<Box<dyn FnOnce(&mut Order)>> = vec![
+            Box::new(|order| order.time_in_force = TimeInForce::GoodTilDate(1)),
+            Box::new(|order| order.constraints.min_fill_size = Some(6)),
+            Box::new(|order| order.constraints.max_slippage_bps = Some(10_001)),
+            Box::new(|order| order.base_mint = mint("USDC")),
+            Box::new(|order| order.amount = BigUint::from(u64::MAX) + 1u8),
+        ];
+        for modify in invalid_orders.into_iter() {
+            let req = order_request(&wallet, modify);
+            let res = handler.handle_typed(req, wallet_params(&wallet)).await;
+            assert_rejected(res, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    /// Tests that withdrawals beyond the balance and deposits that overflow it are refused
+    #[tokio::test]
+    async fn test_external_transfer_validation() {
+        let wallet = test_wallet();
+        let updater = setup_updater(wallet.clone());
+
+        let withdraw_handler = WithdrawHandler::new(updater.clone());
+        for (mint, amount) in [(mint("USDC"), 101u64), (mint("WETH"), 1u64)] {
+            let req = transfer_request(&wallet, mint, BigUint::from(amount));
+            let res = withdraw_handler
+                .handle_typed(req, wallet_params(&wallet))
+                .await;
+            assert_rejected(res, StatusCode::BAD_REQUEST);
+        }
+
+        let deposit_handler = DepositHandler::new(updater);
+        let req = transfer_request(&wallet, mint("USDC"), BigUint::from(u64::MAX));
+        let res = deposit_handler
+            .handle_typed(req, wallet_params(&wallet))
+            .await;
+        assert_rejected(res, StatusCode::BAD_REQUEST);
+    }
+
+    /// Tests that a wallet holds one update in flight at a time, that the update's progress
+    /// is published as it runs, and that a frozen wallet refuses updates
+    #[tokio::test]
+    async fn test_update_lifecycle() {
+        let wallet = test_wallet();
+        let wallet_id = wallet.wallet_id;
+        let updater = setup_updater(wallet.clone());
+        let handler = DepositHandler::new(updater.clone());
+        let mut status_reader = updater
+            .system_bus
+            .subscribe(wallet_update_topic(&wallet_id));
+
+        // Hold the wallet's task queue so that the first update stays in flight
+        let release = Arc::new(Notify::new());
+        let release_clone = release.clone();
+        let global_state = updater.global_state.clone();
+        let blocking_task = tokio::spawn(async move {
+            global_state
+                .wallet_manager()
+                .run_task(&wallet_id, async {
+                    release_clone.notified().await;
+                    Ok::<_, ()>(())
+                })
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let req = transfer_request(&wallet, mint("USDC"), BigUint::from(1u8));
+        let res = handler
+            .handle_typed(req.clone(), wallet_params(&wallet))
+            .await
+            .unwrap();
+        assert_eq!(res.topic, wallet_update_topic(&wallet_id));
+
+        let second_res = handler
+            .handle_typed(req.clone(), wallet_params(&wallet))
+            .await;
+        assert_rejected(second_res, StatusCode::CONFLICT);
+
+        // Once released, the update begins proving, then fails for want of an
+        // authentication path
+        release.notify_one();
+        blocking_task.await.unwrap().unwrap();
+
+        let mut statuses = Vec::new();
+        for _ in 0..2 {
+            match timeout(STATUS_TIMEOUT, status_reader.next_message())
+                .await
+                .unwrap()
+            {
+                SystemBusMessage::WalletUpdateProgress {
+                    task_id, status, ..
+                } => {
+                    assert_eq!(task_id, res.task_id);
+                    statuses.push(status);
+                }
+                message => panic!("unexpected message {message:?}"),
+            }
+        }
+        assert!(matches!(statuses[0], WalletUpdateStatus::Proving));
+        assert!(matches!(
+            statuses[1],
+            WalletUpdateStatus::Failed {
+                code: ErrorCode::Config,
+                ..
+            }
+        ));
+
+        // The failed update is no longer in flight, and the wallet is unchanged
+        assert!(updater.in_flight.lock().unwrap().is_empty());
+        let stored_wallet = updater
+            .global_state
+            .read_wallet_index()
+            .await
+            .get_wallet(&wallet_id)
+            .await
+            .unwrap();
+        assert_eq!(stored_wallet.metadata.version, 0);
+
+        // A frozen wallet refuses updates
+        updater
+            .global_state
+            .wallet_manager()
+            .freeze(&wallet_id, "corrupt".to_string());
+        let res = handler.handle_typed(req, wallet_params(&wallet)).await;
+        assert_rejected(res, StatusCode::CONFLICT);
+    }
+}
@@ -1,7 +1,9 @@
 //! Defines the server for the publicly facing API (both HTTP and websocket)
 //! that the relayer exposes
+pub mod auth;
 pub mod error;
 mod http;
+mod rate_limit;
 mod router;
 mod websocket;
 pub mod worker;
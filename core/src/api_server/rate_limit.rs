@@ -0,0 +1,78 @@
+//! A rate limiter for API routes that are expensive to serve or that leak information
+//! in aggregate, shared by every caller of the route
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::clock::SharedClock;
+
+/// Admits at most `limit` requests in each fixed window of time
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    /// The number of requests admitted per window
+    limit: u32,
+    /// The length of a window
+    window: Duration,
+    /// The clock that windows are measured against
+    clock: SharedClock,
+    /// The start of the current window, and the number of requests admitted in it
+    current: Arc<Mutex<(Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// Constructor
+    pub fn new(limit: u32, window: Duration, clock: SharedClock) -> Self {
+        let now = clock.now();
+        Self {
+            limit,
+            window,
+            clock,
+            current: Arc::new(Mutex::new((now, 0))),
+        }
+    }
+
+    /// Admit a request if the current window has capacity left
+    pub fn try_acquire(&self) -> bool {
+        let now = self.clock.now();
+        let mut current = self.current.lock().unwrap();
+        let (window_start, count) = &mut *current;
+        if now.duration_since(*window_start) >= self.window {
+            *window_start = now;
+            *count = 0;
+        }
+
+        if *count >= self.limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::clock::ManualClock;
+
+    use super::RateLimiter;
+
+    /// Tests that requests beyond the limit are refused until the window rolls over
+    #[test]
+    fn test_window_limit() {
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+        let limiter = RateLimiter::new(2, Duration::from_secs(60), Arc::new(clock.clone()));
+
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        clock.advance(Duration::from_secs(59));
+        assert!(!limiter.try_acquire());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter.try_acquire());
+    }
+}
@@ -1,54 +1,48 @@
 //! Abstracts routing logic from the HTTP server
 
-use std::collections::HashMap;
+use std::{collections::HashMap, net::SocketAddr};
 
 use async_trait::async_trait;
-use hyper::{Body, Method, Request, Response, StatusCode};
+use hyper::{header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
 use matchit::Router as MatchRouter;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tracing::log;
+use url::form_urlencoded;
 
-use super::error::ApiServerError;
+use crate::external_api::http::deserialize_request_body;
 
-/// A type alias for URL generic params maps, i.e. /path/to/resource/:id
+use super::{
+    auth::{ApiAuthenticator, ApiPermission},
+    error::ApiServerError,
+};
+
+/// A type alias for URL generic params maps, i.e. /path/to/resource/:id, along with any
+/// query string params, i.e. /path/to/resource?key=value
 pub(super) type UrlParams = HashMap<String, String>;
 
 // -----------
 // | Helpers |
 // -----------
 
-/// Builds an empty HTTP 400 (Bad Request) response
+/// Builds an HTTP 400 (Bad Request) response
 pub(super) fn build_400_response(err: String) -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::BAD_REQUEST)
-        .body(Body::from(err))
-        .unwrap()
+    build_error_response(ApiServerError::HttpStatusCode(StatusCode::BAD_REQUEST, err))
 }
 
-/// Builds an empty HTTP 404 (Not Found) response
+/// Builds an HTTP 404 (Not Found) response
 pub(super) fn build_404_response(err: String) -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::NOT_FOUND)
-        .body(Body::from(err))
-        .unwrap()
-}
-
-/// Builds an empty HTTP 500 (Internal Server Error) response
-pub(super) fn build_500_response(err: String) -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::INTERNAL_SERVER_ERROR)
-        .body(Body::from(err))
-        .unwrap()
+    build_error_response(ApiServerError::HttpStatusCode(StatusCode::NOT_FOUND, err))
 }
 
-/// Builds an empty HTTP XXX response
-pub(super) fn build_response_from_status_code(
-    status_code: StatusCode,
-    err: String,
-) -> Response<Body> {
+/// Builds the response to a failed request, the body holds the error's code and message
+pub(super) fn build_error_response(err: ApiServerError) -> Response<Body> {
     Response::builder()
-        .status(status_code)
-        .body(Body::from(err))
+        .status(err.status_code())
+        .header(CONTENT_TYPE, "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(Body::from(
+            serde_json::to_vec(&err.to_response_body()).unwrap(),
+        ))
         .unwrap()
 }
 
@@ -94,23 +88,15 @@ impl<
 {
     async fn handle(&self, req: Request<Body>, url_params: UrlParams) -> Response<Body> {
         // Deserialize the request into the request type, return HTTP 400 if deserialization fails
-        let req_body_bytes = hyper::body::to_bytes(req.into_body()).await;
-        if let Err(e) = req_body_bytes {
-            return build_400_response(e.to_string());
-        }
+        let req_body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+            Ok(bytes) => bytes,
+            Err(e) => return build_400_response(e.to_string()),
+        };
 
-        let mut unwrapped: &[u8] = &req_body_bytes.unwrap(); // Necessary to explicitly hold temporary value
-        if unwrapped.is_empty() {
-            // If no HTTP body data was passed, replace the data with "null". Serde expects "null" as
-            // the serialized version of an empty struct
-            unwrapped = "null".as_bytes();
-        }
-        let deserialized = serde_json::from_reader(unwrapped);
-        if let Err(e) = deserialized {
-            return build_400_response(e.to_string());
-        }
-
-        let req_body: Req = deserialized.unwrap();
+        let req_body: Req = match deserialize_request_body(&req_body_bytes) {
+            Ok(body) => body,
+            Err(e) => return build_400_response(e.to_string()),
+        };
 
         // Forward to the typed handler
         let res = self.handle_typed(req_body, url_params).await;
@@ -125,28 +111,40 @@ impl<
                 .body(Body::from(serde_json::to_vec(&resp).unwrap()))
                 .unwrap()
         } else {
-            let err = res.err().unwrap();
-            match err.clone() {
-                ApiServerError::HttpStatusCode(status, message) => {
-                    build_response_from_status_code(status, message)
-                }
-                _ => build_500_response(err.to_string()),
-            }
+            build_error_response(res.err().unwrap())
         }
     }
 }
 
+/// Error message emitted when a local-only route is requested from a remote address
+const ERR_LOCAL_ONLY: &str = "this route is only served to local callers";
+
+/// A handler attached to a route, along with the access the route requires
+struct Route {
+    /// The permission requests to the route must be signed with
+    permission: ApiPermission,
+    /// Whether the route is served only to callers connected over the loopback interface
+    local_only: bool,
+    /// The handler the route dispatches to
+    handler: Box<dyn Handler>,
+}
+
 /// Wrapper around a matchit router that allows different HTTP request types to be matches
 pub struct Router {
     /// The underlying router
-    router: MatchRouter<Box<dyn Handler>>,
+    router: MatchRouter<Route>,
+    /// The authenticator that requests are checked against before being dispatched
+    authenticator: ApiAuthenticator,
 }
 
 impl Router {
     /// Create a new router with no routes established
-    pub fn new() -> Self {
+    pub fn new(authenticator: ApiAuthenticator) -> Self {
         let router = MatchRouter::new();
-        Self { router }
+        Self {
+            router,
+            authenticator,
+        }
     }
 
     /// Helper to build a routable path from a method and a concrete route
@@ -166,16 +164,112 @@ impl Router {
         format!("/{}{}", method_str, route)
     }
 
-    /// Add a route to the router
-    pub fn add_route<H: Handler + 'static>(&mut self, method: Method, route: String, handler: H) {
+    /// Add a route to the router, requests to the route must be signed by a key with at
+    /// least the given permission
+    pub fn add_route<H: Handler + 'static>(
+        &mut self,
+        method: Method,
+        route: String,
+        permission: ApiPermission,
+        handler: H,
+    ) {
+        self.insert_route(
+            method, route, permission, false, /* local_only */
+            handler,
+        )
+    }
+
+    /// Add a route that is served only to callers connected over the loopback interface,
+    /// whose requests must also be signed by a key with at least the given permission
+    ///
+    /// The caller's address is read from the request's extensions, where the server
+    /// places it; a request without one is treated as remote
+    pub fn add_local_route<H: Handler + 'static>(
+        &mut self,
+        method: Method,
+        route: String,
+        permission: ApiPermission,
+        handler: H,
+    ) {
+        self.insert_route(
+            method, route, permission, true, /* local_only */
+            handler,
+        )
+    }
+
+    /// Attach a handler to a route
+    fn insert_route<H: Handler + 'static>(
+        &mut self,
+        method: Method,
+        route: String,
+        permission: ApiPermission,
+        local_only: bool,
+        handler: H,
+    ) {
         log::debug!("Attached handler to route {route} with method {method}");
         let full_route = Self::create_full_route(method, route);
 
         self.router
-            .insert(full_route, Box::new(handler))
+            .insert(
+                full_route,
+                Route {
+                    permission,
+                    local_only,
+                    handler: Box::new(handler),
+                },
+            )
             .expect("error attaching handler to route");
     }
 
+    /// Whether a request was made by a caller connected over the loopback interface
+    fn is_local(req: &Request<Body>) -> bool {
+        req.extensions()
+            .get::<SocketAddr>()
+            .map_or(false, |addr| addr.ip().is_loopback())
+    }
+
+    /// Authenticate a request against the permission its route requires
+    ///
+    /// The body is buffered to verify the signature over it, so the request is rebuilt
+    /// from its parts for the handler
+    async fn authenticate(
+        &self,
+        permission: ApiPermission,
+        req: Request<Body>,
+    ) -> Result<Request<Body>, Response<Body>> {
+        // Without keys there is no signature to verify; the authenticator decides from the
+        // route's permission alone whether to serve it
+        if permission == ApiPermission::Public || !self.authenticator.enabled() {
+            return self
+                .authenticator
+                .authenticate(permission, req.method(), "", req.headers(), &[])
+                .map(|_| req)
+                .map_err(build_error_response);
+        }
+
+        let (parts, body) = req.into_parts();
+        let body_bytes = hyper::body::to_bytes(body)
+            .await
+            .map_err(|err| build_400_response(err.to_string()))?;
+
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or_else(|| parts.uri.path());
+        self.authenticator
+            .authenticate(
+                permission,
+                &parts.method,
+                path_and_query,
+                &parts.headers,
+                &body_bytes,
+            )
+            .map_err(build_error_response)?;
+
+        Ok(Request::from_parts(parts, Body::from(body_bytes)))
+    }
+
     /// Route a request to a handler
     pub async fn handle_req(
         &self,
@@ -188,18 +282,142 @@ impl Router {
 
         // Dispatch to handler
         if let Ok(matched_path) = self.router.at(&full_route) {
-            let handler = matched_path.value;
+            let route = matched_path.value;
             let params = matched_path.params;
 
+            if route.local_only && !Self::is_local(&req) {
+                return build_error_response(ApiServerError::HttpStatusCode(
+                    StatusCode::FORBIDDEN,
+                    ERR_LOCAL_ONLY.to_string(),
+                ));
+            }
+
+            let req = match self.authenticate(route.permission, req).await {
+                Ok(req) => req,
+                Err(resp) => return resp,
+            };
+
             // Clone the params to take ownership
             let mut params_map = HashMap::with_capacity(params.len());
             for (key, value) in params.iter() {
                 params_map.insert(key.to_string(), value.to_string());
             }
 
-            handler.as_ref().handle(req, params_map).await
+            // Merge in any query string params, URL captures take precedence on a name collision
+            if let Some(query) = req.uri().query() {
+                for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                    params_map
+                        .entry(key.into_owned())
+                        .or_insert_with(|| value.into_owned());
+                }
+            }
+
+            route.handler.as_ref().handle(req, params_map).await
         } else {
             build_404_response(format!("Route {route} for method {method} not found"))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, str::FromStr};
+
+    use async_trait::async_trait;
+    use hyper::{Body, Method, Request, Response, StatusCode};
+
+    use crate::{
+        api_server::auth::{ApiAuthenticator, ApiKey, ApiPermission},
+        clock::system_clock,
+    };
+
+    use super::{Handler, Router, UrlParams};
+
+    /// A handler that serves every request with an empty body
+    struct EmptyHandler;
+
+    #[async_trait]
+    impl Handler for EmptyHandler {
+        async fn handle(&self, _req: Request<Body>, _url_params: UrlParams) -> Response<Body> {
+            Response::new(Body::empty())
+        }
+    }
+
+    /// Build a router with a route at each permission scope
+    fn build_router(keys: Vec<ApiKey>) -> Router {
+        let mut router = Router::new(ApiAuthenticator::new(keys, system_clock()));
+        for (route, permission) in [
+            ("/public", ApiPermission::Public),
+            ("/read", ApiPermission::ReadOnly),
+            ("/wallet", ApiPermission::WalletMutating),
+            ("/admin", ApiPermission::Admin),
+        ] {
+            router.add_route(Method::POST, route.to_string(), permission, EmptyHandler);
+        }
+
+        router
+    }
+
+    /// Send an unsigned request to a route and return the response's status
+    async fn status(router: &Router, route: &str) -> StatusCode {
+        let req = Request::post(route).body(Body::empty()).unwrap();
+        router
+            .handle_req(Method::POST, route.to_string(), req)
+            .await
+            .status()
+    }
+
+    /// Tests that wallet-mutating and admin routes are refused when no API keys are
+    /// configured, rather than served unauthenticated
+    #[tokio::test]
+    async fn test_fail_closed_without_keys() {
+        let router = build_router(vec![]);
+        assert_eq!(status(&router, "/public").await, StatusCode::OK);
+        assert_eq!(status(&router, "/read").await, StatusCode::OK);
+        assert_eq!(status(&router, "/wallet").await, StatusCode::FORBIDDEN);
+        assert_eq!(status(&router, "/admin").await, StatusCode::FORBIDDEN);
+    }
+
+    /// Tests that unsigned requests to authenticated routes are rejected when keys are
+    /// configured
+    #[tokio::test]
+    async fn test_unsigned_with_keys() {
+        let key = ApiKey::from_str("admin:c2VjcmV0:admin").unwrap();
+        let router = build_router(vec![key]);
+        assert_eq!(status(&router, "/public").await, StatusCode::OK);
+        assert_eq!(status(&router, "/read").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&router, "/admin").await, StatusCode::UNAUTHORIZED);
+    }
+
+    /// Tests that a local-only route is refused to remote callers and to requests that
+    /// carry no caller address
+    #[tokio::test]
+    async fn test_local_only_route() {
+        let mut router = Router::new(ApiAuthenticator::new(vec![], system_clock()));
+        router.add_local_route(
+            Method::POST,
+            "/local".to_string(),
+            ApiPermission::ReadOnly,
+            EmptyHandler,
+        );
+
+        let send_from = |addr: Option<&str>| {
+            let mut req = Request::post("/local").body(Body::empty()).unwrap();
+            if let Some(addr) = addr {
+                req.extensions_mut()
+                    .insert(addr.parse::<SocketAddr>().unwrap());
+            }
+            router.handle_req(Method::POST, "/local".to_string(), req)
+        };
+
+        assert_eq!(
+            send_from(Some("127.0.0.1:9000")).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send_from(Some("10.0.0.1:9000")).await.status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(send_from(None).await.status(), StatusCode::FORBIDDEN);
+    }
+}
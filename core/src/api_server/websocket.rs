@@ -1,25 +1,63 @@
 //! Groups logic for managing websocket connections
+//!
+//! Clients subscribe to system bus topics, and to `price_report.<base>.<quote>` topics that
+//! stream a pair's median price reports directly from the price reporter. A subscription
+//! to a price report topic is answered with a snapshot of the pair's price reporter state.
+//! The server pings each connection periodically and hangs up on connections that stop
+//! answering, releasing their subscriptions
 
-use std::net::SocketAddr;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 
 use crossbeam::channel;
-use futures::{stream::SplitSink, SinkExt, StreamExt};
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, Stream, StreamExt,
+};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_stream::StreamMap;
-use tokio_tungstenite::{accept_async, WebSocketStream};
-use tungstenite::Message;
+use tokio_tungstenite::{accept_hdr_async, WebSocketStream};
+use tracing::log;
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    Message,
+};
 
 use crate::{
-    external_api::websocket::{SubscriptionMessage, SubscriptionResponse},
-    price_reporter::{jobs::PriceReporterManagerJob, tokens::Token},
-    system_bus::{SystemBus, TopicReader},
+    external_api::websocket::{PriceReportSnapshot, SubscriptionMessage, SubscriptionResponse},
+    price_reporter::{
+        jobs::PriceReporterManagerJob,
+        manager::PriceReporterListenerID,
+        reporter::PriceReporterState,
+        tokens::{validate_pair, Token},
+    },
+    system_bus::SystemBus,
     types::{SystemBusMessage, SystemBusMessageWithTopic},
 };
 
-use super::{error::ApiServerError, worker::ApiServerConfig};
+use super::{auth::ApiPermission, error::ApiServerError, worker::ApiServerConfig};
 
 /// The dummy stream used to seed the websocket subscriptions `StreamMap`
 const DUMMY_SUBSCRIPTION_TOPIC: &str = "dummy-topic";
+/// The prefix of topics that stream a pair's median price reports, the full topic is of
+/// the form `price_report.<base addr>.<quote addr>`
+const PRICE_REPORT_TOPIC_PREFIX: &str = "price_report";
+/// The interval at which the server pings each connection
+const WEBSOCKET_PING_INTERVAL_MS: u64 = 30_000; // 30 seconds
+/// The time without any message from the client after which its connection is presumed dead
+const WEBSOCKET_PONG_TIMEOUT_MS: u64 = 90_000; // 90 seconds
+
+/// A stream of events that a websocket client is subscribed to
+type SubscriptionStream = Pin<Box<dyn Stream<Item = SystemBusMessage> + Send>>;
+/// The subscriptions a websocket connection has open, keyed by topic
+type ClientSubscriptions = StreamMap<String, SubscriptionStream>;
+/// The price reporter listener registered for each of a connection's price report
+/// subscriptions, keyed by topic
+type PriceListeners = HashMap<String, (Token, Token, PriceReporterListenerID)>;
 
 /// A wrapper around request handling and task management
 #[derive(Clone)]
@@ -62,15 +100,41 @@ impl WebsocketServer {
 
     /// Handle a websocket connection
     async fn handle_connection(&self, stream: TcpStream) -> Result<(), ApiServerError> {
-        // Accept the websocket upgrade and split into read/write streams
-        let websocket_stream = accept_async(stream)
+        // Authenticate and accept the websocket upgrade, then split into read/write streams.
+        // The upgrade request has no body, so its signature is taken over an empty body
+        let authenticator = self.config.api_authenticator.clone();
+        let authenticate_upgrade = move |req: &Request, resp: Response| {
+            let path_and_query = req
+                .uri()
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or_else(|| req.uri().path());
+            match authenticator.authenticate(
+                ApiPermission::ReadOnly,
+                req.method(),
+                path_and_query,
+                req.headers(),
+                &[],
+            ) {
+                Ok(()) => Ok(resp),
+                Err(err) => {
+                    let body = serde_json::to_string(&err.to_response_body()).unwrap();
+                    let mut err_resp = ErrorResponse::new(Some(body));
+                    *err_resp.status_mut() = err.status_code();
+                    Err(err_resp)
+                }
+            }
+        };
+
+        let websocket_stream = accept_hdr_async(stream, authenticate_upgrade)
             .await
             .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
         let (mut write_stream, mut read_stream) = websocket_stream.split();
 
         // The websocket client will add subscriptions throughout the communication; this tracks the
         // active subscriptions that the local connection has open
-        let mut subscriptions = StreamMap::new();
+        let mut subscriptions: ClientSubscriptions = StreamMap::new();
+        let mut price_listeners = PriceListeners::new();
 
         // The `StreamMap` future implementation will return `Poll::Ready(None)` if no streams are
         // registered, indicating that the mapped stream is empty. We would prefer it to return
@@ -78,30 +142,72 @@ impl WebsocketServer {
         let dummy_reader = self
             .system_bus
             .subscribe(DUMMY_SUBSCRIPTION_TOPIC.to_string());
-        subscriptions.insert(DUMMY_SUBSCRIPTION_TOPIC.to_string(), dummy_reader);
+        subscriptions.insert(DUMMY_SUBSCRIPTION_TOPIC.to_string(), Box::pin(dummy_reader));
+
+        let res = self
+            .connection_loop(
+                &mut read_stream,
+                &mut write_stream,
+                &mut subscriptions,
+                &mut price_listeners,
+            )
+            .await;
+
+        // Release the price reporters the connection streamed from, however it ended
+        for (_, (base_token, quote_token, id)) in price_listeners.drain() {
+            self.drop_price_listener(base_token, quote_token, id);
+        }
+
+        res
+    }
+
+    /// The listener loop of a websocket connection, returns when the connection closes or
+    /// stops answering pings
+    async fn connection_loop(
+        &self,
+        read_stream: &mut SplitStream<WebSocketStream<TcpStream>>,
+        write_stream: &mut SplitSink<WebSocketStream<TcpStream>, Message>,
+        subscriptions: &mut ClientSubscriptions,
+        price_listeners: &mut PriceListeners,
+    ) -> Result<(), ApiServerError> {
+        let mut ping_interval =
+            tokio::time::interval(Duration::from_millis(WEBSOCKET_PING_INTERVAL_MS));
+        let mut last_heard = Instant::now();
 
-        // Begin the listener loop
         loop {
             tokio::select! {
                 // Next subscription event from the system bus
                 Some((topic, event)) = subscriptions.next() => {
-                    self.push_subscribed_event(topic, event, &mut write_stream).await?;
+                    self.push_subscribed_event(topic, event, write_stream).await?;
+                }
+
+                // Ping the client, hanging up if it has gone quiet
+                _ = ping_interval.tick() => {
+                    if last_heard.elapsed() > Duration::from_millis(WEBSOCKET_PONG_TIMEOUT_MS) {
+                        log::info!("closing unresponsive websocket connection");
+                        break;
+                    }
+
+                    write_stream
+                        .send(Message::Ping(Vec::new()))
+                        .await
+                        .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
                 }
 
                 // Next message from the client side of the websocket
                 message = read_stream.next() => {
                     match message {
                         Some(msg) => {
-                            if let Err(e) = msg {
-                                return Err(ApiServerError::WebsocketServerFailure(e.to_string()));
-                            }
+                            let message_unwrapped = msg
+                                .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
 
-                            let message_unwrapped = msg.unwrap();
+                            // Any message from the client, including a pong, shows it is alive
+                            last_heard = Instant::now();
                             match message_unwrapped {
                                 Message::Close(_) => break,
                                 _ => {
                                     let bus_clone = self.system_bus.clone();
-                                    self.handle_incoming_ws_message(message_unwrapped, &mut subscriptions, &mut write_stream, bus_clone).await?;
+                                    self.handle_incoming_ws_message(message_unwrapped, subscriptions, price_listeners, write_stream, bus_clone).await?;
                                 }
                             };
                         }
@@ -121,50 +227,101 @@ impl WebsocketServer {
     async fn handle_incoming_ws_message(
         &self,
         message: Message,
-        client_subscriptions: &mut StreamMap<String, TopicReader<SystemBusMessage>>,
+        client_subscriptions: &mut ClientSubscriptions,
+        price_listeners: &mut PriceListeners,
         write_stream: &mut SplitSink<WebSocketStream<TcpStream>, Message>,
         system_bus: SystemBus<SystemBusMessage>,
     ) -> Result<(), ApiServerError> {
         if let Message::Text(msg_text) = message {
             // Deserialize the message body and dispatch to a handler for a response
             let deserialized: Result<SubscriptionMessage, _> = serde_json::from_str(&msg_text);
+            let mut snapshot = None;
             let resp = match deserialized {
                 Ok(message_body) => {
-                    let response = self
-                        .handle_subscription_message(message_body, client_subscriptions, system_bus)
-                        .await;
-                    let response_serialized = serde_json::to_string(&response)
-                        .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
+                    match self
+                        .handle_subscription_message(
+                            message_body,
+                            client_subscriptions,
+                            price_listeners,
+                            system_bus,
+                        )
+                        .await
+                    {
+                        Ok((response, price_snapshot)) => {
+                            snapshot = price_snapshot;
+                            let response_serialized =
+                                serde_json::to_string(&response).map_err(|err| {
+                                    ApiServerError::WebsocketServerFailure(err.to_string())
+                                })?;
 
-                    Message::Text(response_serialized)
+                            Message::Text(response_serialized)
+                        }
+
+                        Err(e) => Message::Text(format!("Invalid request: {}", e)),
+                    }
                 }
 
                 Err(e) => Message::Text(format!("Invalid request: {}", e)),
             };
 
-            // Write the response onto the websocket
+            // Write the response onto the websocket, followed by the price snapshot if the
+            // client subscribed to a price report topic
             write_stream
                 .send(resp)
                 .await
                 .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
+
+            if let Some(snapshot) = snapshot {
+                let snapshot_serialized = serde_json::to_string(&snapshot)
+                    .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
+                write_stream
+                    .send(Message::Text(snapshot_serialized))
+                    .await
+                    .map_err(|err| ApiServerError::WebsocketServerFailure(err.to_string()))?;
+            }
         }
 
         Ok(())
     }
 
     /// Handles an incoming subscribe/unsubscribe message
+    ///
+    /// Returns the connection's subscriptions after the update, and a snapshot of the
+    /// pair's price reporter if the client subscribed to a price report topic
     async fn handle_subscription_message(
         &self,
         message: SubscriptionMessage,
-        client_subscriptions: &mut StreamMap<String, TopicReader<SystemBusMessage>>,
+        client_subscriptions: &mut ClientSubscriptions,
+        price_listeners: &mut PriceListeners,
         system_bus: SystemBus<SystemBusMessage>,
-    ) -> SubscriptionResponse {
+    ) -> Result<(SubscriptionResponse, Option<PriceReportSnapshot>), String> {
         // Update local subscriptions
+        let mut snapshot = None;
         match message {
+            SubscriptionMessage::Subscribe { topic } if is_price_report_topic(&topic) => {
+                let (base_token, quote_token) = parse_price_report_topic(&topic)?;
+                if !client_subscriptions.contains_key(&topic) {
+                    let median_stream = self.subscribe_price_report(
+                        &topic,
+                        base_token.clone(),
+                        quote_token.clone(),
+                        price_listeners,
+                    )?;
+                    client_subscriptions.insert(topic.clone(), median_stream);
+                }
+
+                snapshot = Some(PriceReportSnapshot {
+                    state: self.peek_median(base_token, quote_token)?,
+                    topic,
+                });
+            }
             SubscriptionMessage::Subscribe { topic } => {
                 // Register the topic subscription
-                let topic_reader = system_bus.subscribe(topic.clone());
-                client_subscriptions.insert(topic.clone(), topic_reader);
+                let topic_reader = system_bus.subscribe_with_config(
+                    topic.clone(),
+                    self.config.websocket_subscription_config,
+                );
+                client_subscriptions.insert(topic.clone(), Box::pin(topic_reader));
                 // If the topic is a *-price-report-*, then parse the tokens, send a
                 // StartPriceReporter job, and await until confirmed
                 let topic_split: Vec<&str> = topic.split('-').collect();
@@ -180,21 +337,114 @@ impl WebsocketServer {
                             id: None, // TODO: Store an ID for later teardown
                             channel: channel_sender,
                         })
-                        .unwrap();
-                    channel_receiver.recv().unwrap();
+                        .map_err(|err| err.to_string())?;
+                    channel_receiver.recv().map_err(|err| err.to_string())?;
                 }
             }
             SubscriptionMessage::Unsubscribe { topic } => {
                 client_subscriptions.remove(&topic);
+                if let Some((base_token, quote_token, id)) = price_listeners.remove(&topic) {
+                    self.drop_price_listener(base_token, quote_token, id);
+                }
             }
         };
 
-        SubscriptionResponse {
+        let response = SubscriptionResponse {
             subscriptions: client_subscriptions
                 .keys()
                 .cloned()
                 .filter(|key| DUMMY_SUBSCRIPTION_TOPIC.to_string().ne(key))
                 .collect(),
+        };
+
+        Ok((response, snapshot))
+    }
+
+    /// Bridge a pair's median price reports onto a subscription stream
+    ///
+    /// A listener is registered with the price reporter manager so that the pair's price
+    /// reporter is kept alive while the connection streams from it
+    fn subscribe_price_report(
+        &self,
+        topic: &str,
+        base_token: Token,
+        quote_token: Token,
+        price_listeners: &mut PriceListeners,
+    ) -> Result<SubscriptionStream, String> {
+        let id = PriceReporterListenerID::new_v4();
+        let (start_sender, start_receiver) = channel::unbounded();
+        self.config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::StartPriceReporter {
+                base_token: base_token.clone(),
+                quote_token: quote_token.clone(),
+                id: Some(id),
+                channel: start_sender,
+            })
+            .map_err(|err| err.to_string())?;
+        start_receiver.recv().map_err(|err| err.to_string())?;
+        price_listeners.insert(
+            topic.to_string(),
+            (base_token.clone(), quote_token.clone(), id),
+        );
+
+        let (receiver_sender, receiver_receiver) = channel::unbounded();
+        self.config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::CreateNewMedianReceiver {
+                base_token,
+                quote_token,
+                channel: receiver_sender,
+            })
+            .map_err(|err| err.to_string())?;
+        let median_receiver = receiver_receiver.recv().map_err(|err| err.to_string())?;
+
+        Ok(Box::pin(
+            median_receiver.map(SystemBusMessage::PriceReportMedian),
+        ))
+    }
+
+    /// Peek the state of a pair's price reporter
+    fn peek_median(
+        &self,
+        base_token: Token,
+        quote_token: Token,
+    ) -> Result<PriceReporterState, String> {
+        let (sender, receiver) = channel::unbounded();
+        self.config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::PeekMedian {
+                base_token,
+                quote_token,
+                channel: sender,
+            })
+            .map_err(|err| err.to_string())?;
+
+        receiver.recv().map_err(|err| err.to_string())
+    }
+
+    /// Drop a connection's listener on a pair's price reporter
+    fn drop_price_listener(
+        &self,
+        base_token: Token,
+        quote_token: Token,
+        id: PriceReporterListenerID,
+    ) {
+        let (sender, receiver) = channel::unbounded();
+        let res = self
+            .config
+            .price_reporter_work_queue
+            .send(PriceReporterManagerJob::DropListenerID {
+                base_token,
+                quote_token,
+                id,
+                channel: sender,
+            })
+            .map_err(|err| err.to_string())
+            .and_then(|_| receiver.recv().map_err(|err| err.to_string()));
+
+        if let Err(e) = res {
+            log::warn!("error dropping price reporter listener {id}: {e}");
         }
     }
 
@@ -219,3 +469,49 @@ impl WebsocketServer {
         Ok(())
     }
 }
+
+/// Whether the given topic is a price report topic
+fn is_price_report_topic(topic: &str) -> bool {
+    topic.split('.').next() == Some(PRICE_REPORT_TOPIC_PREFIX)
+}
+
+/// Parse the pair from a price report topic of the form `price_report.<base>.<quote>`
+fn parse_price_report_topic(topic: &str) -> Result<(Token, Token), String> {
+    let mut parts = topic.split('.').skip(1);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(base), Some(quote), None) => {
+            let (base_token, quote_token) = (Token::from_addr(base), Token::from_addr(quote));
+            validate_pair(&base_token, &quote_token).map(|_| (base_token, quote_token))
+        }
+        _ => Err(format!(
+            "expected a topic of the form {PRICE_REPORT_TOPIC_PREFIX}.<base>.<quote>"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::price_reporter::tokens::Token;
+
+    use super::{is_price_report_topic, parse_price_report_topic};
+
+    /// Tests parsing the pair from price report topics
+    #[test]
+    fn test_parse_price_report_topic() {
+        let weth = Token::_from_ticker("WETH");
+        let usdc = Token::_from_ticker("USDC");
+
+        let topic = format!("price_report.{}.{}", weth.get_addr(), usdc.get_addr());
+        assert!(is_price_report_topic(&topic));
+        let (base, quote) = parse_price_report_topic(&topic).unwrap();
+        assert_eq!((base, quote), (weth.clone(), usdc.clone()));
+
+        // Not a price report topic
+        assert!(!is_price_report_topic("handshakes"));
+
+        // Malformed topics and invalid pairs
+        let inverted = format!("price_report.{}.{}", usdc.get_addr(), weth.get_addr());
+        assert!(parse_price_report_topic(&inverted).is_err());
+        assert!(parse_price_report_topic("price_report.0x1").is_err());
+    }
+}
@@ -1,8 +1,11 @@
 //! Defines the implementation of the `Worker` trait for the ApiServer
 
-use crossbeam::channel::Sender as CrossbeamSender;
+use ed25519_dalek::Keypair;
 use futures::executor::block_on;
-use std::thread::{self, JoinHandle};
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
 use tokio::{
     runtime::{Builder as TokioBuilder, Runtime},
     sync::mpsc::UnboundedSender as TokioSender,
@@ -10,12 +13,25 @@ use tokio::{
 };
 
 use crate::{
-    price_reporter::jobs::PriceReporterManagerJob, proof_generation::jobs::ProofManagerJob,
-    state::RelayerState, system_bus::SystemBus, types::SystemBusMessage, worker::Worker,
+    handshake::{jobs::HandshakeExecutionJob, state::HandshakeStateIndex},
+    job_queue::JobQueueSender,
+    keychain::RootKeyManager,
+    logging::LogFilterHandle,
+    price_reporter::jobs::PriceReporterManagerJob,
+    proof_generation::{dead_letter::DeadLetterQueue, jobs::ProofManagerJob},
+    starknet_client::client::StarknetClient,
+    state::{
+        audit::AuditLog, export::OrderBookExporter, wallet::OrderEvictionPolicy, RelayerState,
+    },
+    system_bus::{SubscriptionConfig, SystemBus},
+    types::SystemBusMessage,
+    worker::{Worker, WorkerControlMessage},
     CancelChannel,
 };
 
-use super::{error::ApiServerError, http::HttpServer, websocket::WebsocketServer};
+use super::{
+    auth::ApiAuthenticator, error::ApiServerError, http::HttpServer, websocket::WebsocketServer,
+};
 
 /// The number of threads backing the HTTP server
 const API_SERVER_NUM_THREADS: usize = 2;
@@ -43,16 +59,55 @@ pub struct ApiServerConfig {
     pub http_port: u16,
     /// The port that the websocket server should listen on
     pub websocket_port: u16,
+    /// The authenticator that HTTP requests and websocket upgrades are checked against
+    pub api_authenticator: ApiAuthenticator,
+    /// The cluster keypair, used to sign the relayer's identity attestation
+    pub cluster_keypair: Arc<Keypair>,
+    /// The software version of the relayer, advertised in its identity attestation
+    pub relayer_version: String,
+    /// Contact information for the relayer's operator, advertised in its identity attestation
+    pub contact_info: Option<String>,
+    /// The policy applied when an order is placed in a wallet with no free order slot
+    pub order_eviction_policy: OrderEvictionPolicy,
+    /// The number of cross preview requests served per minute, across all callers
+    pub cross_preview_rate_limit: u32,
+    /// Authorizes root operations on wallets, with an external signer for wallets whose
+    /// root key the relayer does not hold
+    pub root_key_manager: RootKeyManager,
     /// The worker job queue for the PriceReporterManager
-    pub price_reporter_work_queue: TokioSender<PriceReporterManagerJob>,
+    pub price_reporter_work_queue: JobQueueSender<PriceReporterManagerJob>,
     /// The worker job queue for the ProofGenerationManager
-    pub proof_generation_work_queue: CrossbeamSender<ProofManagerJob>,
+    pub proof_generation_work_queue: JobQueueSender<ProofManagerJob>,
+    /// The queue of proof jobs abandoned by the proof manager, exposed on the admin API
+    pub dead_letter_queue: DeadLetterQueue,
+    /// The order book exporter, exposed on the admin API; `None` if exports are not configured
+    pub order_book_exporter: Option<OrderBookExporter>,
+    /// The audit log, queried on the admin API; `None` if auditing is not configured
+    pub audit_log: Option<AuditLog>,
+    /// The starknet client, used to submit wallet updates on-chain
+    pub starknet_client: StarknetClient,
     /// The relayer-global state
     pub global_state: RelayerState,
+    /// The handshake manager's index of in-flight handshakes
+    pub handshake_state_index: HandshakeStateIndex,
+    /// The priority job queue of the handshake manager, on which handshake cancellations
+    /// are sent
+    pub handshake_manager_work_queue: JobQueueSender<HandshakeExecutionJob>,
     /// The system pubsub bus that all workers have access to
     /// The ApiServer uses this bus to forward internal events onto open
     /// websocket connections
     pub system_bus: SystemBus<SystemBusMessage>,
+    /// The buffer size and overflow policy of each websocket subscription
+    pub websocket_subscription_config: SubscriptionConfig,
+    /// The handle on the log filter, exposed on the admin API; `None` if logs are captured
+    /// by the debug TUI
+    pub log_filter_handle: Option<LogFilterHandle>,
+    /// Whether the relayer is in debug mode, in which the admin API serves developer routes
+    pub debug: bool,
+    /// The channel on which to signal the coordinator to drain and shut down the relayer
+    pub shutdown_channel: TokioSender<()>,
+    /// The channel on which to ask the coordinator to pause or resume a worker
+    pub worker_control_channel: TokioSender<WorkerControlMessage>,
     /// The channel to receive cancellation signals on from the coordinator
     pub cancel_channel: CancelChannel,
 }
@@ -123,8 +178,20 @@ impl Worker for ApiServer {
         true
     }
 
+    fn recover(self) -> Self
+    where
+        Self: Sized,
+    {
+        // The servers hold no state beyond their config, so a fresh server replaces them
+        Self::new(self.config).unwrap()
+    }
+
     fn cleanup(&mut self) -> Result<(), Self::Error> {
-        drop(self.server_runtime.take());
+        // The coordinator cleans up from within its own runtime, where a runtime may not
+        // be dropped with a blocking shutdown
+        if let Some(runtime) = self.server_runtime.take() {
+            runtime.shutdown_background();
+        }
         Ok(())
     }
 }
@@ -0,0 +1,114 @@
+//! Runs the matching and pricing stack against synthetic order flow and a recorded price
+//! feed, with no network and no chain, and prints a summary of the run as JSON
+//!
+//! A run is reproducible from its seed; see `darkpool_relayer::simulation` for what the
+//! seed does and does not fix
+#![deny(unsafe_code)]
+#![deny(clippy::missing_docs_in_private_items)]
+
+use std::process::exit;
+
+use clap::Parser;
+
+use darkpool_relayer::simulation::{harness::Simulation, SimulationConfig};
+
+/// The command line arguments of the simulation binary
+#[derive(Debug, Parser)]
+#[clap(author, version, about, long_about = None)]
+struct SimulateArgs {
+    /// The file holding the recorded price feed to replay, as JSON-serialized price
+    /// reports, one per line
+    #[clap(long, value_parser)]
+    price_feed: String,
+    /// The seed that every random choice in the run is drawn from
+    #[clap(long, value_parser, default_value = "0")]
+    seed: u64,
+    /// The number of relayers to simulate, each a cluster of one
+    #[clap(long, value_parser, default_value = "4")]
+    relayers: usize,
+    /// The number of orders each relayer manages before the first round
+    #[clap(long, value_parser, default_value = "2")]
+    initial_orders: usize,
+    /// The number of orders placed across the network in each round
+    #[clap(long, value_parser, default_value = "1")]
+    orders_per_round: usize,
+    /// The number of rounds to run; if unset, rounds are run until the feed is exhausted
+    #[clap(long, value_parser)]
+    rounds: Option<u64>,
+    /// The simulated time that elapses between rounds, in milliseconds; the default
+    /// exceeds the handshake interval, so that each relayer schedules a handshake a round
+    #[clap(long, value_parser, default_value = "2500")]
+    round_interval_ms: u64,
+    /// The wall time given to each round for its handshakes and settlements to run, in
+    /// milliseconds
+    #[clap(long, value_parser, default_value = "2000")]
+    round_wall_time_ms: u64,
+    /// The maximum distance of a synthetic order's price from the median, in basis points
+    #[clap(long, value_parser, default_value = "25")]
+    spread_bps: u64,
+    /// The maximum size of a synthetic order, in units of the base token
+    #[clap(long, value_parser, default_value = "100")]
+    max_order_amount: u64,
+    /// The fee the mock chain charges every settlement at least, in wei
+    #[clap(long, value_parser, default_value = "1000000000000")]
+    base_fee: u64,
+    /// The most the mock chain charges a settlement above the base fee, in wei
+    #[clap(long, value_parser, default_value = "100000000000")]
+    fee_jitter: u64,
+    /// The fraction of fee estimates the mock chain fails as congested
+    #[clap(long, value_parser, default_value = "0")]
+    congestion_rate: f64,
+    /// The highest fee a relayer pays to settle a match, in wei
+    #[clap(long, value_parser)]
+    max_settlement_fee: Option<u64>,
+    /// The amount of time a match MPC may run before it is abandoned, in milliseconds
+    #[clap(long, value_parser, default_value = "60000")]
+    mpc_timeout_ms: u64,
+    /// The maximum number of match MPCs each relayer runs at once
+    #[clap(long, value_parser, default_value = "2")]
+    max_concurrent_mpcs: usize,
+    /// Whether relayers request a size bucket check when proposing a match
+    #[clap(long, value_parser)]
+    size_bucket_check: bool,
+}
+
+impl From<SimulateArgs> for SimulationConfig {
+    fn from(args: SimulateArgs) -> Self {
+        Self {
+            seed: args.seed,
+            price_feed: args.price_feed,
+            relayers: args.relayers,
+            initial_orders: args.initial_orders,
+            orders_per_round: args.orders_per_round,
+            rounds: args.rounds,
+            round_interval_ms: args.round_interval_ms,
+            round_wall_time_ms: args.round_wall_time_ms,
+            spread_bps: args.spread_bps,
+            max_order_amount: args.max_order_amount,
+            base_fee: args.base_fee,
+            fee_jitter: args.fee_jitter,
+            congestion_rate: args.congestion_rate,
+            max_settlement_fee: args.max_settlement_fee,
+            mpc_timeout_ms: args.mpc_timeout_ms,
+            max_concurrent_mpcs: args.max_concurrent_mpcs,
+            size_bucket_check: args.size_bucket_check,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let config: SimulationConfig = SimulateArgs::parse().into();
+    let report = match Simulation::new(config) {
+        Ok(simulation) => simulation.run().await,
+        Err(err) => Err(err),
+    };
+
+    match report {
+        Ok(report) => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Err(err) => {
+            eprintln!("simulation failed: {err}");
+            exit(1);
+        }
+    }
+}
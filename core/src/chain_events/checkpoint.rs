@@ -0,0 +1,203 @@
+//! Checkpoints the blocks that the on-chain event listener has processed
+//!
+//! A block is checkpointed once all of its events are applied, along with its hash and
+//! the state of the local Merkle tree mirror after the block. A restarted relayer resumes
+//! polling from the block after the latest checkpoint, so that events emitted while it was
+//! down are neither missed nor applied twice.
+//!
+//! The most recent `REORG_WINDOW_BLOCKS` checkpoints are kept for re-org detection; if the
+//! hash of a checkpointed block no longer matches the chain, the blocks after the newest
+//! block that still matches were abandoned and their effects are rolled back.
+//!
+//! The checkpoint is rewritten in full on every change; a temporary file is written and
+//! then renamed over the checkpoint so that a crash mid-write never leaves a torn file
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use circuits::types::wallet::Nullifier;
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+
+use super::error::OnChainEventListenerError;
+
+/// The number of recently processed blocks that are checkpointed for re-org detection
+pub(super) const REORG_WINDOW_BLOCKS: usize = 128;
+
+/// A block whose events the listener has applied
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointBlock {
+    /// The number of the block
+    pub number: u64,
+    /// The hash of the block when its events were applied
+    pub hash: Scalar,
+    /// The number of leaves in the Merkle tree mirror after the block
+    pub merkle_leaves: u64,
+    /// The number of on-chain roots the Merkle tree mirror had observed after the block
+    pub merkle_roots_observed: u64,
+    /// The match nullifiers spent in the block
+    pub spent_nullifiers: Vec<Nullifier>,
+}
+
+impl CheckpointBlock {
+    /// Create a checkpoint for a block, the Merkle state is filled in once the block's
+    /// events are applied
+    pub fn new(number: u64, hash: Scalar) -> Self {
+        Self {
+            number,
+            hash,
+            merkle_leaves: 0,
+            merkle_roots_observed: 0,
+            spent_nullifiers: Vec::new(),
+        }
+    }
+}
+
+/// The checkpointed blocks, persisted to a file if one is configured
+#[derive(Clone, Debug)]
+pub struct ChainCheckpoint {
+    /// The file the checkpoint is persisted to; the checkpoint is held only in memory
+    /// if unset
+    path: Option<String>,
+    /// The most recently checkpointed blocks, oldest first
+    blocks: Arc<Mutex<VecDeque<CheckpointBlock>>>,
+}
+
+impl ChainCheckpoint {
+    /// Open the checkpoint at the given path, reading the blocks checkpointed by a
+    /// previous run
+    pub fn open(path: Option<String>) -> Result<Self, OnChainEventListenerError> {
+        let blocks = match &path {
+            Some(path) if Path::new(path).exists() => {
+                let contents = fs::read(path)
+                    .map_err(|err| OnChainEventListenerError::Checkpoint(err.to_string()))?;
+                serde_json::from_slice(&contents)
+                    .map_err(|err| OnChainEventListenerError::Checkpoint(err.to_string()))?
+            }
+            _ => VecDeque::new(),
+        };
+
+        Ok(Self {
+            path,
+            blocks: Arc::new(Mutex::new(blocks)),
+        })
+    }
+
+    /// The most recently checkpointed block, if any
+    pub fn latest(&self) -> Option<CheckpointBlock> {
+        self.blocks.lock().unwrap().back().cloned()
+    }
+
+    /// The checkpointed blocks, newest first
+    pub fn recent_blocks(&self) -> Vec<CheckpointBlock> {
+        self.blocks.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// Checkpoint a block whose events have all been applied
+    ///
+    /// A block checkpointed again replaces its earlier checkpoint
+    pub fn record(&self, block: CheckpointBlock) -> Result<(), OnChainEventListenerError> {
+        let mut locked_blocks = self.blocks.lock().unwrap();
+        while locked_blocks
+            .back()
+            .map(|latest| latest.number >= block.number)
+            .unwrap_or(false)
+        {
+            locked_blocks.pop_back();
+        }
+
+        locked_blocks.push_back(block);
+        while locked_blocks.len() > REORG_WINDOW_BLOCKS {
+            locked_blocks.pop_front();
+        }
+
+        self.flush(&locked_blocks)
+    }
+
+    /// Drop the checkpoints of the blocks after the given block, returning them oldest
+    /// first; all checkpoints are dropped if no block is given
+    pub fn rollback(
+        &self,
+        fork_block: Option<u64>,
+    ) -> Result<Vec<CheckpointBlock>, OnChainEventListenerError> {
+        let mut locked_blocks = self.blocks.lock().unwrap();
+        let retained = locked_blocks
+            .iter()
+            .take_while(|block| Some(block.number) <= fork_block)
+            .count();
+        let abandoned = locked_blocks.split_off(retained).into_iter().collect();
+
+        self.flush(&locked_blocks)?;
+        Ok(abandoned)
+    }
+
+    /// Persist the checkpoint, replacing the previous contents of the checkpoint file
+    fn flush(&self, blocks: &VecDeque<CheckpointBlock>) -> Result<(), OnChainEventListenerError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let serialized = serde_json::to_vec(blocks)
+            .map_err(|err| OnChainEventListenerError::Checkpoint(err.to_string()))?;
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, serialized)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|err| OnChainEventListenerError::Checkpoint(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::{ChainCheckpoint, CheckpointBlock, REORG_WINDOW_BLOCKS};
+
+    /// Tests that the checkpoint keeps only the blocks in the re-org window, and that a
+    /// rollback drops the blocks after the fork
+    #[test]
+    fn test_record_and_rollback() {
+        let checkpoint = ChainCheckpoint::open(None).unwrap();
+        for number in 0..(REORG_WINDOW_BLOCKS as u64 + 10) {
+            checkpoint
+                .record(CheckpointBlock::new(number, Scalar::from(number)))
+                .unwrap();
+        }
+
+        let recent_blocks = checkpoint.recent_blocks();
+        assert_eq!(recent_blocks.len(), REORG_WINDOW_BLOCKS);
+        assert_eq!(recent_blocks[0].number, REORG_WINDOW_BLOCKS as u64 + 9);
+
+        let abandoned = checkpoint
+            .rollback(Some(REORG_WINDOW_BLOCKS as u64 + 7))
+            .unwrap();
+        assert_eq!(
+            abandoned
+                .iter()
+                .map(|block| block.number)
+                .collect::<Vec<_>>(),
+            vec![
+                REORG_WINDOW_BLOCKS as u64 + 8,
+                REORG_WINDOW_BLOCKS as u64 + 9
+            ]
+        );
+        assert_eq!(
+            checkpoint.latest().unwrap().number,
+            REORG_WINDOW_BLOCKS as u64 + 7
+        );
+
+        // Re-checkpointing an earlier block replaces the checkpoints after it
+        checkpoint
+            .record(CheckpointBlock::new(15, Scalar::one()))
+            .unwrap();
+        assert_eq!(checkpoint.latest().unwrap().hash, Scalar::one());
+        assert_eq!(checkpoint.recent_blocks().len(), 6);
+
+        checkpoint.rollback(None).unwrap();
+        assert!(checkpoint.latest().is_none());
+    }
+}
@@ -2,11 +2,18 @@
 
 use std::fmt::Display;
 
+use crate::{
+    error::{ErrorCode, ErrorCoded},
+    proof_generation::error::ProofManagerError,
+};
+
 /// The error type that the event listener emits
 #[derive(Clone, Debug)]
 pub enum OnChainEventListenerError {
+    /// An error reading or persisting the listener's block checkpoint
+    Checkpoint(String),
     /// An error generating a proof
-    ProofGeneration(String),
+    ProofGeneration(ProofManagerError),
     /// An RPC error with the StarkNet provider
     Rpc(String),
     /// An error sending a message to another worker in the local node
@@ -15,8 +22,21 @@ pub enum OnChainEventListenerError {
     Setup(String),
 }
 
+impl std::error::Error for OnChainEventListenerError {}
 impl Display for OnChainEventListenerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:?}")
     }
 }
+
+impl ErrorCoded for OnChainEventListenerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            OnChainEventListenerError::Checkpoint(_) => ErrorCode::Storage,
+            OnChainEventListenerError::ProofGeneration(err) => err.error_code(),
+            OnChainEventListenerError::Rpc(_) => ErrorCode::Chain,
+            OnChainEventListenerError::SendMessage(_) => ErrorCode::Internal,
+            OnChainEventListenerError::Setup(_) => ErrorCode::Setup,
+        }
+    }
+}
@@ -1,11 +1,10 @@
 //! Defines the core implementation of the on-chain event listener
 
 use std::{
-    collections::HashMap,
-    str::FromStr,
+    collections::{HashMap, HashSet},
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread::JoinHandle,
     time::Duration,
@@ -16,15 +15,14 @@ use circuits::{
     zk_gadgets::merkle::MerkleOpening,
 };
 
-use crossbeam::channel::Sender as CrossbeamSender;
 use crypto::fields::{starknet_felt_to_biguint, starknet_felt_to_scalar, starknet_felt_to_u64};
 use curve25519_dalek::scalar::Scalar;
 use starknet::core::{types::FieldElement as StarknetFieldElement, utils::get_selector_from_name};
 use starknet_providers::jsonrpc::{
-    models::{BlockId, EmittedEvent, ErrorCode, EventFilter},
-    HttpTransport, JsonRpcClient, JsonRpcClientError, RpcError,
+    models::{BlockId, EmittedEvent, EventFilter, MaybePendingBlockWithTxHashes},
+    HttpTransport, JsonRpcClient,
 };
-use tokio::sync::{mpsc::UnboundedSender as TokioSender, oneshot};
+use tokio::sync::oneshot;
 use tokio::time::{sleep_until, Instant};
 use tracing::log;
 
@@ -34,7 +32,12 @@ use crate::{
         orderbook_management::{OrderBookManagementMessage, ORDER_BOOK_TOPIC},
     },
     handshake::jobs::HandshakeExecutionJob,
-    proof_generation::jobs::{ProofJob, ProofManagerJob, ValidCommitmentsBundle},
+    job_queue::{JobQueueError, JobQueueSender},
+    keychain,
+    proof_generation::{
+        error::ProofManagerError,
+        jobs::{ProofJob, ProofJobPriority, ProofManagerJob, ValidCommitmentsBundle},
+    },
     starknet_client::client::StarknetClient,
     state::{
         wallet::{MerkleAuthenticationPath, Wallet},
@@ -43,24 +46,31 @@ use crate::{
     CancelChannel,
 };
 
-use super::error::OnChainEventListenerError;
+use super::{
+    checkpoint::{ChainCheckpoint, CheckpointBlock, REORG_WINDOW_BLOCKS},
+    error::OnChainEventListenerError,
+};
 
 // -------------
 // | Constants |
 // -------------
 
 /// The chunk size to request paginated events in
-const EVENT_CHUNK_SIZE: u64 = 100;
+pub(crate) const EVENT_CHUNK_SIZE: u64 = 100;
 /// The interval at which the worker should poll for new contract events
 const EVENTS_POLL_INTERVAL_MS: u64 = 5_000; // 5 seconds
 
 lazy_static! {
     /// The event selector for a Merkle root update
     static ref MERKLE_ROOT_CHANGED_EVENT_SELECTOR: StarknetFieldElement = get_selector_from_name("Merkle_root_changed").unwrap();
+    /// The event selector for a Merkle leaf insertion
+    pub(crate) static ref MERKLE_VALUE_INSERTED_EVENT_SELECTOR: StarknetFieldElement = get_selector_from_name("Merkle_value_inserted").unwrap();
     /// The event selector for a Merkle internal node change
     static ref MERKLE_NODE_CHANGED_EVENT_SELECTOR: StarknetFieldElement = get_selector_from_name("Merkle_internal_node_changed").unwrap();
     /// The event selector for a nullifier spend
     static ref NULLIFIER_SPENT_EVENT_SELECTOR: StarknetFieldElement = get_selector_from_name("Nullifier_spent").unwrap();
+    /// The event selector for the posting of a wallet ciphertext
+    pub(crate) static ref WALLET_CIPHERTEXT_POSTED_EVENT_SELECTOR: StarknetFieldElement = get_selector_from_name("Wallet_ciphertext_posted").unwrap();
 }
 
 // ----------
@@ -74,13 +84,19 @@ pub struct OnChainEventListenerConfig {
     pub starknet_client: StarknetClient,
     /// A copy of the relayer global state
     pub global_state: RelayerState,
-    /// A sender to the handshake manager's job queue, used to enqueue
-    /// MPC shootdown jobs
-    pub handshake_manager_job_queue: TokioSender<HandshakeExecutionJob>,
+    /// A sender to the handshake manager's priority job queue, used to enqueue
+    /// MPC shootdown jobs ahead of standard handshake jobs
+    pub handshake_manager_job_queue: JobQueueSender<HandshakeExecutionJob>,
     /// The worker job queue for the ProofGenerationManager
-    pub proof_generation_work_queue: CrossbeamSender<ProofManagerJob>,
+    pub proof_generation_work_queue: JobQueueSender<ProofManagerJob>,
     /// The work queue for the network manager, used to send outbound gossip messages
-    pub network_manager_work_queue: TokioSender<GossipOutbound>,
+    pub network_manager_work_queue: JobQueueSender<GossipOutbound>,
+    /// Whether to replay the contract's Merkle events from before the starting block at
+    /// startup; if not, the local tree mirror holds only insertions seen while running
+    pub backfill: bool,
+    /// The file that the last processed blocks are checkpointed to, polling resumes from
+    /// the checkpoint on restart; the checkpoint is held only in memory if unset
+    pub checkpoint_file: Option<String>,
     /// The channel on which the coordinator may send a cancel signal
     pub cancel_channel: CancelChannel,
 }
@@ -116,8 +132,13 @@ pub struct OnChainEventListenerExecutor {
     start_block: u64,
     /// The latest block for which the local node has updated Merkle state
     merkle_last_consistent_block: Arc<AtomicU64>,
-    /// The event pagination token
-    pagination_token: Arc<AtomicU64>,
+    /// The next block to poll events from
+    next_block: Arc<AtomicU64>,
+    /// The checkpoint of the blocks whose events have been applied
+    checkpoint: ChainCheckpoint,
+    /// The nullifiers whose spends were abandoned by a re-org, the orders cancelled by each
+    /// are restored unless the spend is included again by the time polling catches up
+    abandoned_spends: Arc<Mutex<HashSet<Nullifier>>>,
     /// A copy of the config that the executor maintains
     config: OnChainEventListenerConfig,
     /// A copy of the relayer-global state
@@ -126,16 +147,19 @@ pub struct OnChainEventListenerExecutor {
 
 impl OnChainEventListenerExecutor {
     /// Create a new executor
-    pub fn new(config: OnChainEventListenerConfig) -> Self {
+    pub fn new(config: OnChainEventListenerConfig) -> Result<Self, OnChainEventListenerError> {
         let global_state = config.global_state.clone();
+        let checkpoint = ChainCheckpoint::open(config.checkpoint_file.clone())?;
 
-        Self {
+        Ok(Self {
             config,
             start_block: 0,
             merkle_last_consistent_block: Arc::new(0.into()),
-            pagination_token: Arc::new(0.into()),
+            next_block: Arc::new(0.into()),
+            checkpoint,
+            abandoned_spends: Arc::new(Mutex::new(HashSet::new())),
             global_state,
-        }
+        })
     }
 
     /// Helper to fetch the RPC client in the executor's config
@@ -150,30 +174,46 @@ impl OnChainEventListenerExecutor {
 
     /// The main execution loop for the executor
     pub async fn execute(mut self) -> OnChainEventListenerError {
-        // Get the current block number to start from
-        let starting_block_number = self.get_block_number().await;
-        if starting_block_number.is_err() {
-            return starting_block_number.err().unwrap();
+        // Roll back any checkpointed blocks that were abandoned while the relayer was down
+        if let Err(e) = self.check_for_reorg().await {
+            return e;
         }
 
-        self.start_block = starting_block_number.unwrap();
+        // Resume from the block after the checkpoint, replaying the events missed while the
+        // relayer was down; without a checkpoint, start from the current block
+        let (start_block, last_consistent_block) = match self.checkpoint.latest() {
+            Some(block) => (block.number + 1, block.number),
+            None => match self.get_block_number().await {
+                Ok(block_number) => (block_number, block_number),
+                Err(e) => return e,
+            },
+        };
+
+        self.start_block = start_block;
+        self.next_block.store(start_block, Ordering::Relaxed);
         self.merkle_last_consistent_block
-            .store(self.start_block, Ordering::Relaxed);
+            .store(last_consistent_block, Ordering::Relaxed);
         log::info!(
-            "Starting on-chain event listener with current block {}",
+            "Starting on-chain event listener from block {}",
             self.start_block
         );
 
-        // Poll for new events in a loop
+        // Build the local mirror of the commitment tree from the events before the
+        // starting block, the polling loop picks up the rest
+        if !self.config.backfill {
+            log::info!("Merkle event backfill disabled, skipping replay");
+        } else if let Err(e) = self.replay_merkle_events().await {
+            log::error!("error replaying Merkle tree events, tree mirror is incomplete: {e}");
+        }
+
+        // Poll for new events in a loop; polls run one at a time so that blocks are
+        // applied and checkpointed in order
         loop {
             // Sleep for some time then re-poll events
             sleep_until(Instant::now() + Duration::from_millis(EVENTS_POLL_INTERVAL_MS)).await;
-            let mut self_clone = self.clone();
-            tokio::spawn(async move {
-                if let Err(e) = self_clone.poll_contract_events().await {
-                    log::error!("error polling events: {e}");
-                };
-            });
+            if let Err(e) = self.poll_contract_events().await {
+                log::error!("error polling events: {e}");
+            };
         }
     }
 
@@ -185,77 +225,299 @@ impl OnChainEventListenerExecutor {
             .map_err(|err| OnChainEventListenerError::Rpc(err.to_string()))
     }
 
+    /// Get the hash of the StarkNet block with the given number
+    async fn get_block_hash(&self, block_number: u64) -> Result<Scalar, OnChainEventListenerError> {
+        let block = self
+            .rpc_client()
+            .get_block_with_tx_hashes(&BlockId::Number(block_number))
+            .await
+            .map_err(|err| OnChainEventListenerError::Rpc(err.to_string()))?;
+
+        match block {
+            MaybePendingBlockWithTxHashes::Block(block) => {
+                Ok(starknet_felt_to_scalar(&block.block_hash))
+            }
+            MaybePendingBlockWithTxHashes::PendingBlock(_) => Err(OnChainEventListenerError::Rpc(
+                format!("block {block_number} is pending"),
+            )),
+        }
+    }
+
+    /// Replay the contract's leaf insertion and root change events from before the
+    /// starting block into the local mirror of the commitment tree
+    async fn replay_merkle_events(&self) -> Result<(), OnChainEventListenerError> {
+        if self.start_block == 0 {
+            return Ok(());
+        }
+
+        let filter = EventFilter {
+            from_block: None,
+            to_block: Some(BlockId::Number(self.start_block - 1)),
+            address: Some(self.contract_address()),
+            keys: Some(vec![
+                *MERKLE_VALUE_INSERTED_EVENT_SELECTOR,
+                *MERKLE_ROOT_CHANGED_EVENT_SELECTOR,
+            ]),
+        };
+
+        let mut pagination_token = Some("0".to_string());
+        while pagination_token.is_some() {
+            let events_batch = self
+                .rpc_client()
+                .get_events(filter.clone(), pagination_token, EVENT_CHUNK_SIZE)
+                .await
+                .map_err(|err| OnChainEventListenerError::Rpc(err.to_string()))?;
+
+            for event in events_batch.events.iter() {
+                if event.keys[0] == *MERKLE_VALUE_INSERTED_EVENT_SELECTOR {
+                    self.handle_value_inserted(event).await;
+                } else {
+                    self.handle_onchain_root(event).await;
+                }
+            }
+
+            pagination_token = events_batch.continuation_token;
+        }
+
+        let locked_tree = self.global_state.read_merkle_tree().await;
+        log::info!(
+            "replayed {} Merkle tree insertions, tree mirror consistent: {}",
+            locked_tree.num_leaves(),
+            locked_tree.is_consistent()
+        );
+
+        Ok(())
+    }
+
     /// Poll for new contract events
-    async fn poll_contract_events(&mut self) -> Result<(), OnChainEventListenerError> {
+    ///
+    /// The events of each block are applied in order, and the block checkpointed once
+    /// they all are; a poll that fails part way resumes from the first block that was
+    /// not checkpointed
+    async fn poll_contract_events(&self) -> Result<(), OnChainEventListenerError> {
         log::debug!("polling for events...");
-        loop {
-            let (events, more_pages) = self.fetch_next_events_page().await?;
-            for event in events.into_iter() {
-                self.handle_event(event).await?;
+        self.check_for_reorg().await?;
+
+        let head = self.get_block_number().await?;
+        let from_block = self.next_block.load(Ordering::Relaxed);
+        if from_block > head {
+            return Ok(());
+        }
+
+        let mut current_block: Option<CheckpointBlock> = None;
+        for event in self.fetch_events(from_block, head).await?.into_iter() {
+            if current_block.as_ref().map(|block| block.number) != Some(event.block_number) {
+                if let Some(block) = current_block.take() {
+                    self.checkpoint_block(block).await?;
+                }
+
+                current_block = Some(CheckpointBlock::new(
+                    event.block_number,
+                    starknet_felt_to_scalar(&event.block_hash),
+                ));
             }
 
-            if !more_pages {
-                break;
+            if event.keys[0] == *NULLIFIER_SPENT_EVENT_SELECTOR {
+                let nullifier = starknet_felt_to_scalar(&event.data[0]);
+                current_block
+                    .as_mut()
+                    .unwrap()
+                    .spent_nullifiers
+                    .push(nullifier);
             }
+
+            self.handle_event(event).await?;
         }
 
+        // Checkpoint the head even if it emitted no events, so that a re-org of it is
+        // detected on the next poll
+        let head_block = match current_block {
+            Some(block) if block.number == head => block,
+            last_block => {
+                if let Some(block) = last_block {
+                    self.checkpoint_block(block).await?;
+                }
+
+                CheckpointBlock::new(head, self.get_block_hash(head).await?)
+            }
+        };
+        self.checkpoint_block(head_block).await?;
+
+        self.restore_abandoned_spends().await;
         Ok(())
     }
 
-    /// Fetch the next page of events from the contract
-    ///
-    /// Returns the events in the next page and a boolean indicating whether
-    /// the caller should continue paging
-    async fn fetch_next_events_page(
-        &mut self,
-    ) -> Result<(Vec<EmittedEvent>, bool), OnChainEventListenerError> {
+    /// Fetch the contract's events emitted in the given range of blocks, inclusive
+    async fn fetch_events(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<EmittedEvent>, OnChainEventListenerError> {
         let filter = EventFilter {
-            from_block: Some(BlockId::Number(self.start_block)),
-            to_block: None,
+            from_block: Some(BlockId::Number(from_block)),
+            to_block: Some(BlockId::Number(to_block)),
             address: Some(self.contract_address()),
             keys: None,
         };
 
-        let pagination_token = self.pagination_token.load(Ordering::Relaxed).to_string();
-        let resp = self
-            .rpc_client()
-            .get_events(filter, Some(pagination_token), EVENT_CHUNK_SIZE)
-            .await;
+        let mut events = Vec::new();
+        let mut pagination_token = Some("0".to_string());
+        while pagination_token.is_some() {
+            let events_batch = self
+                .rpc_client()
+                .get_events(filter.clone(), pagination_token, EVENT_CHUNK_SIZE)
+                .await
+                .map_err(|err| OnChainEventListenerError::Rpc(err.to_string()))?;
+
+            events.extend(events_batch.events);
+            pagination_token = events_batch.continuation_token;
+        }
 
-        // If the error is an unknown continuation token, ignore it and stop paging
-        if let Err(JsonRpcClientError::RpcError(RpcError::Code(
-            ErrorCode::InvalidContinuationToken,
-        ))) = resp
+        Ok(events)
+    }
+
+    /// Checkpoint a block whose events have all been applied, recording the state of the
+    /// Merkle tree mirror after the block
+    async fn checkpoint_block(
+        &self,
+        mut block: CheckpointBlock,
+    ) -> Result<(), OnChainEventListenerError> {
         {
-            return Ok((Vec::new(), false));
+            let locked_tree = self.global_state.read_merkle_tree().await;
+            block.merkle_leaves = locked_tree.num_leaves();
+            block.merkle_roots_observed = locked_tree.roots_observed();
+        } // locked_tree released
+
+        let next_block = block.number + 1;
+        self.checkpoint.record(block)?;
+        self.next_block.store(next_block, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Check the checkpointed blocks against the chain, rolling back the effects of any
+    /// blocks that a re-org has abandoned
+    ///
+    /// The newest checkpointed block whose hash still matches the chain is the fork
+    /// point; the Merkle tree mirror is rolled back to its state after the fork point and
+    /// polling resumes from the block after it
+    async fn check_for_reorg(&self) -> Result<(), OnChainEventListenerError> {
+        let recent_blocks = self.checkpoint.recent_blocks();
+        let mut fork_block = None;
+        for block in recent_blocks.iter() {
+            if self.get_block_hash(block.number).await? == block.hash {
+                fork_block = Some(block.clone());
+                break;
+            }
         }
 
-        // Otherwise, propagate the error
-        let resp = resp.map_err(|err| OnChainEventListenerError::Rpc(err.to_string()))?;
-
-        // Update the executor held continuation token used across calls to `getEvents`
-        if let Some(pagination_token) = resp.continuation_token.clone() {
-            let parsed_token = u64::from_str(&pagination_token).unwrap();
-            self.pagination_token.store(parsed_token, Ordering::Relaxed);
-        } else {
-            // If no explicit pagination token is given, increment the pagination token by the
-            // number of events received. Ideally the API would do this, but it simply returns None
-            // to indicate no more pages are ready. We would like to persist this token across polls
-            // to getEvents.
-            self.pagination_token
-                .fetch_add(resp.events.len() as u64, Ordering::Relaxed);
+        // Nothing to roll back if the newest checkpointed block is still on the chain
+        let newest_block = recent_blocks.first().map(|block| block.number);
+        if newest_block.is_none() || fork_block.as_ref().map(|block| block.number) == newest_block {
+            return Ok(());
+        }
+
+        let abandoned_blocks = self
+            .checkpoint
+            .rollback(fork_block.as_ref().map(|block| block.number))?;
+        match fork_block {
+            Some(block) => {
+                log::warn!(
+                    "chain re-org abandoned {} blocks, rolling back to block {}",
+                    abandoned_blocks.len(),
+                    block.number
+                );
+                self.global_state
+                    .write_merkle_tree()
+                    .await
+                    .rollback(block.merkle_leaves, block.merkle_roots_observed);
+                self.next_block.store(block.number + 1, Ordering::Relaxed);
+                self.merkle_last_consistent_block
+                    .fetch_min(block.number, Ordering::Relaxed);
+            }
+            None => {
+                // The mirror cannot be rolled back past the window, its consistency check
+                // flags the divergence if the abandoned blocks inserted leaves
+                let oldest_block = abandoned_blocks[0].number;
+                log::error!(
+                    "chain re-org deeper than the {REORG_WINDOW_BLOCKS} checkpointed blocks, replaying from block {oldest_block} without rollback"
+                );
+                self.next_block.store(oldest_block, Ordering::Relaxed);
+                self.merkle_last_consistent_block
+                    .fetch_min(oldest_block.saturating_sub(1), Ordering::Relaxed);
+            }
         }
 
-        let continue_paging = resp.continuation_token.is_some();
-        Ok((resp.events, continue_paging))
+        self.abandoned_spends.lock().unwrap().extend(
+            abandoned_blocks
+                .into_iter()
+                .flat_map(|block| block.spent_nullifiers),
+        );
+        Ok(())
+    }
+
+    /// Restore the orders cancelled by spends that a re-org abandoned and that the chain
+    /// has not included again since
+    async fn restore_abandoned_spends(&self) {
+        let abandoned_spends = {
+            let mut locked_spends = self.abandoned_spends.lock().unwrap();
+            if locked_spends.is_empty() {
+                return;
+            }
+
+            let spent_again = self
+                .checkpoint
+                .recent_blocks()
+                .into_iter()
+                .flat_map(|block| block.spent_nullifiers)
+                .collect::<HashSet<_>>();
+            locked_spends
+                .drain()
+                .filter(|nullifier| !spent_again.contains(nullifier))
+                .collect::<Vec<_>>()
+        }; // locked_spends released
+
+        for nullifier in abandoned_spends.into_iter() {
+            for wallet_id in self
+                .global_state
+                .get_wallets_by_match_nullifier(nullifier)
+                .await
+            {
+                let self_clone = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = self_clone
+                        .global_state
+                        .restore_wallet_orders(
+                            &wallet_id,
+                            nullifier,
+                            self_clone
+                                .config
+                                .starknet_client
+                                .config
+                                .contract_addr
+                                .clone(),
+                            self_clone.rpc_client(),
+                            &self_clone.config.proof_generation_work_queue,
+                            &self_clone.config.network_manager_work_queue,
+                        )
+                        .await
+                    {
+                        log::error!("error restoring orders for wallet {wallet_id}: {e}");
+                    }
+                });
+            }
+        }
     }
 
     /// Handle an event from the contract
     async fn handle_event(&self, event: EmittedEvent) -> Result<(), OnChainEventListenerError> {
         // Dispatch based on key
         let key = event.keys[0];
-        if key == *MERKLE_ROOT_CHANGED_EVENT_SELECTOR {
+        if key == *MERKLE_VALUE_INSERTED_EVENT_SELECTOR {
+            self.handle_value_inserted(&event).await;
+        } else if key == *MERKLE_ROOT_CHANGED_EVENT_SELECTOR {
             log::info!("Handling merkle root update event");
+            self.handle_onchain_root(&event).await;
 
             // Skip this event if all Merkle events for this block have been consumed
             let last_consistent_block = self.merkle_last_consistent_block.load(Ordering::Relaxed);
@@ -279,25 +541,102 @@ impl OnChainEventListenerExecutor {
         Ok(())
     }
 
+    /// Insert the leaf from a value inserted event into the local tree mirror
+    async fn handle_value_inserted(&self, event: &EmittedEvent) {
+        let index = starknet_felt_to_u64(&event.data[0]);
+        let value = starknet_felt_to_scalar(&event.data[1]);
+        if let Err(e) = self
+            .global_state
+            .write_merkle_tree()
+            .await
+            .insert(index, value)
+        {
+            log::error!("error inserting into Merkle tree mirror: {e}");
+        }
+    }
+
+    /// Record the root from a root changed event, checking the local tree mirror against it
+    async fn handle_onchain_root(&self, event: &EmittedEvent) {
+        let root = starknet_felt_to_scalar(&event.data[0]);
+        if !self
+            .global_state
+            .write_merkle_tree()
+            .await
+            .observe_onchain_root(root)
+        {
+            log::warn!("Merkle tree mirror diverged from the on-chain root");
+        }
+    }
+
     /// Handle a nullifier spent event
     async fn handle_nullifier_spent(
         &self,
         nullifier: Nullifier,
     ) -> Result<(), OnChainEventListenerError> {
-        // Send an MPC shootdown request to the handshake manager
-        self.config
-            .handshake_manager_job_queue
-            .send(HandshakeExecutionJob::MpcShootdown {
-                match_nullifier: nullifier,
-            })
-            .map_err(|err| OnChainEventListenerError::SendMessage(err.to_string()))?;
-
-        // Nullify any orders that used this nullifier in their validity proof
-        self.config.global_state.nullify_orders(nullifier).await;
+        // Request an MPC shootdown from the handshake manager; a full handshake queue must
+        // not hold up the cancellation below, so the request is retried in the background
+        self.enqueue_mpc_shootdown(nullifier);
+
+        // Cancel any orders that used this nullifier in their validity proof. The shootdown
+        // above is only enqueued, so a handshake on them may still be in flight; it fails
+        // at settlement once the nullifier is seen as spent
+        let wallet_ids = self.config.global_state.nullify_orders(nullifier).await;
+
+        // The spend means a new version of each affected local wallet is on-chain, re-prove
+        // its orders so that they may be matched again
+        for wallet_id in wallet_ids.into_iter() {
+            let self_clone = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = self_clone
+                    .global_state
+                    .reprove_wallet_orders(
+                        &wallet_id,
+                        nullifier,
+                        self_clone
+                            .config
+                            .starknet_client
+                            .config
+                            .contract_addr
+                            .clone(),
+                        self_clone.rpc_client(),
+                        &self_clone.config.proof_generation_work_queue,
+                        &self_clone.config.network_manager_work_queue,
+                    )
+                    .await
+                {
+                    log::error!("error re-proving orders for wallet {wallet_id}: {e}");
+                }
+            });
+        }
 
         Ok(())
     }
 
+    /// Enqueue an MPC shootdown for the given nullifier
+    ///
+    /// A shootdown is never dropped for want of room; if the handshake queue is full the
+    /// job waits in the background until a slot frees up
+    fn enqueue_mpc_shootdown(&self, nullifier: Nullifier) {
+        let job = HandshakeExecutionJob::MpcShootdown {
+            match_nullifier: nullifier,
+        };
+        let job = match self.config.handshake_manager_job_queue.send(job) {
+            Ok(()) => return,
+            Err(JobQueueError::Full(job)) => job,
+            Err(JobQueueError::Closed(_)) => {
+                log::error!("handshake queue closed, dropping MPC shootdown for {nullifier:?}");
+                return;
+            }
+        };
+
+        let job_queue = self.config.handshake_manager_job_queue.clone();
+        tokio::spawn(async move {
+            if job_queue.send_waiting(job).await.is_err() {
+                log::error!("handshake queue closed, dropping MPC shootdown for {nullifier:?}");
+            }
+        });
+    }
+
     /// Handle a root change event
     async fn handle_root_changed(
         &self,
@@ -435,16 +774,21 @@ impl OnChainEventListenerExecutor {
                 statement: ValidCommitmentsStatement {
                     nullifier: wallet_match_nullifier,
                     merkle_root: new_root,
-                    pk_settle: wallet.public_keys.pk_settle,
+                    pk_settle: keychain::settle_key(&wallet),
                 },
             };
 
+            // Re-proving a whole order book can outrun the proof queue; wait for room rather
+            // than dropping an order's proof
             self.config
                 .proof_generation_work_queue
-                .send(ProofManagerJob {
+                .send_waiting(ProofManagerJob {
                     type_: job,
+                    priority: ProofJobPriority::Background,
+                    cancellation: None,
                     response_channel: response_sender,
                 })
+                .await
                 .map_err(|err| OnChainEventListenerError::SendMessage(err.to_string()))?;
 
             proof_response_channels.insert(order_id, response_receiver);
@@ -454,9 +798,11 @@ impl OnChainEventListenerExecutor {
         // Await proof responses for all orders
         // TODO: Gossip the new proof to all cluster peers
         for (order_id, channel) in proof_response_channels.into_iter() {
-            let proof = channel
-                .await
-                .map_err(|err| OnChainEventListenerError::ProofGeneration(err.to_string()))?;
+            let proof = channel.await.map_err(|err| {
+                OnChainEventListenerError::ProofGeneration(ProofManagerError::RecvError(
+                    err.to_string(),
+                ))
+            })?;
 
             self.update_order_proof(*order_id, proof.into()).await?;
         }
@@ -482,6 +828,8 @@ impl OnChainEventListenerExecutor {
             order_id,
             cluster,
             proof,
+            ioi: self.global_state.get_publishable_ioi(&order_id).await,
+            time_in_force: self.global_state.get_order_time_in_force(&order_id).await,
         };
 
         self.config
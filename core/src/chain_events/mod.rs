@@ -1,5 +1,6 @@
 //! Defines and implements the worker that listens for on-chain events
 
+mod checkpoint;
 pub mod error;
 pub mod listener;
 pub mod worker;
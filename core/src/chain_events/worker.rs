@@ -20,7 +20,7 @@ impl Worker for OnChainEventListener {
         Self: Sized,
     {
         let executor = if config.enabled() {
-            Some(OnChainEventListenerExecutor::new(config.clone()))
+            Some(OnChainEventListenerExecutor::new(config.clone())?)
         } else {
             None
         };
@@ -0,0 +1,150 @@
+//! Defines the source of time used by workers
+//!
+//! Workers read the time and sleep through a `Clock` rather than calling the wall-clock and
+//! timer APIs directly. The relayer runs on the `SystemClock`; tests may instead inject a
+//! `ManualClock`, which only moves when advanced, so that invisibility windows, cache TTLs,
+//! and scheduler intervals elapse exactly when a test says they do
+
+use futures::future::BoxFuture;
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::watch;
+
+/// A source of monotonic and wall-clock time, and of timers measured against it
+pub trait Clock: Debug + Send + Sync {
+    /// The current monotonic time
+    fn now(&self) -> Instant;
+    /// The current wall-clock time, as the duration since the unix epoch
+    fn unix_time(&self) -> Duration;
+    /// Sleep until the given duration has elapsed on this clock
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// The current wall-clock time, in seconds since the unix epoch
+    fn unix_secs(&self) -> u64 {
+        self.unix_time().as_secs()
+    }
+}
+
+/// A clock shared between the threads of a worker
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Build a shared handle to the system clock
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// The clock backed by the operating system's clocks and the tokio timer
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn unix_time(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("negative timestamp")
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock that stands still until it is advanced
+///
+/// Clones share the underlying time, so advancing any clone advances every clone and
+/// wakes any sleep whose deadline has been reached
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    /// The monotonic time at which the clock was created
+    start: Instant,
+    /// The wall-clock time at which the clock was created
+    start_unix_time: Duration,
+    /// The time the clock has been advanced by since it was created
+    elapsed: Arc<watch::Sender<Duration>>,
+}
+
+impl ManualClock {
+    /// Create a clock that reads the given wall-clock time until advanced
+    pub fn new(start_unix_time: Duration) -> Self {
+        let (elapsed, _) = watch::channel(Duration::ZERO);
+        Self {
+            start: Instant::now(),
+            start_unix_time,
+            elapsed: Arc::new(elapsed),
+        }
+    }
+
+    /// Advance the clock by the given duration
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    /// The time the clock has been advanced by since it was created
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn unix_time(&self) -> Duration {
+        self.start_unix_time + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let mut elapsed = self.elapsed.subscribe();
+        let deadline = *elapsed.borrow() + duration;
+        Box::pin(async move {
+            while *elapsed.borrow_and_update() < deadline {
+                // The sender is held by the clock; if every clone is dropped time stops
+                if elapsed.changed().await.is_err() {
+                    futures::future::pending::<()>().await;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use std::time::Duration;
+
+    use super::{Clock, ManualClock};
+
+    /// Tests that a manual clock only moves when advanced
+    #[test]
+    fn test_manual_advance() {
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.unix_secs(), 1_000);
+
+        clock.clone().advance(Duration::from_secs(5));
+        assert_eq!(clock.now() - start, Duration::from_secs(5));
+        assert_eq!(clock.unix_secs(), 1_005);
+    }
+
+    /// Tests that a sleep on a manual clock completes once its deadline is reached
+    #[tokio::test]
+    async fn test_manual_sleep() {
+        let clock = ManualClock::new(Duration::ZERO);
+        let mut sleep = clock.sleep(Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(9));
+        assert!((&mut sleep).now_or_never().is_none());
+
+        clock.advance(Duration::from_secs(1));
+        sleep.await;
+    }
+}
@@ -1,21 +1,52 @@
 //! Groups configurations used throughout the relayer passed to the CLI
 
-use clap::Parser;
+use circuits::params::ParamsBundle;
+use clap::{Parser, Subcommand};
 use ed25519_dalek::{Digest, Keypair, Sha512, SignatureError};
-use libp2p::{Multiaddr, PeerId};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
 use rand_core::OsRng;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, HashSet},
     env::{self},
     fs,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
 };
 use toml::{value::Map, Value};
 
 use crate::{
+    api_server::auth::ApiKey,
     error::CoordinatorError,
-    gossip::types::{ClusterId, WrappedPeerId},
+    fee_schedule::FeeSchedule,
+    gossip::{
+        erasure::ErasureCoding,
+        types::{ClusterId, WrappedPeerId},
+    },
+    gossip_api::gossip::GossipRequestType,
+    job_queue::JobQueueCapacities,
+    logging::{LogConfig, LogFormat, DEFAULT_LOG_FILTER, LOG_FILTER_ENV_VAR},
+    network_manager::{
+        discovery::dns_seed_addr,
+        key_rotation::ClusterKeyRotationConfig,
+        rate_limit::{GossipRateLimitConfig, TokenBucketConfig},
+    },
+    price_reporter::{
+        breaker::CircuitBreakerConfig,
+        exchanges::{Exchange, ExchangeRegion, UniswapFeeTier},
+        history::PriceHistoryConfig,
+        quarantine::DeviationQuarantineConfig,
+    },
     starknet_client::ChainId,
-    state::wallet::Wallet,
+    state::{
+        audit::{default_audit_topics, AuditLogConfig},
+        export::ExportDestination,
+        feature_flags::FeatureFlag,
+        wallet::{OrderEvictionPolicy, Wallet},
+    },
+    system_bus::{OverflowPolicy, SubscriptionConfig},
 };
 
 /// The default version of the node
@@ -30,6 +61,10 @@ const CONFIG_FILE_ARG: &str = "--config-file";
 #[clap(author, version, about, long_about = None)]
 #[rustfmt::skip]
 struct Cli {
+    /// A command to run in place of the relayer
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     // ---------------
     // | Config File |
     // ---------------
@@ -54,12 +89,73 @@ struct Cli {
     /// The bootstrap servers that the peer should dial initially
     #[clap(short, long, value_parser)]
     pub bootstrap_servers: Option<Vec<String>>,
+    /// DNS seed names to discover peers through, each resolved via its `_dnsaddr` TXT records
+    #[clap(long, value_parser)]
+    pub dns_seeds: Option<Vec<String>>,
+    /// The maximum number of outbound connections to open to peers found through discovery
+    #[clap(long, value_parser, default_value = "50")]
+    pub max_outbound_connections: usize,
+    /// Whether to relay connections for peers that cannot be dialed directly, e.g. those behind a NAT
+    #[clap(long, value_parser)]
+    pub relay_server: bool,
+    /// The relays to listen through if the local node cannot be dialed directly, each a multiaddr
+    /// ending in the relay's peer ID, e.g. `/ip4/1.2.3.4/tcp/8000/p2p/<peer_id>`
+    #[clap(long, value_parser)]
+    pub relay_addrs: Option<Vec<String>>,
+    /// If set, the only clusters whose orders the local node handshakes on
+    #[clap(long, value_parser)]
+    pub cluster_allowlist: Option<Vec<String>>,
+    /// Clusters whose orders the local node never handshakes on
+    #[clap(long, value_parser)]
+    pub cluster_denylist: Option<Vec<String>>,
     /// The cluster private key to use
     #[clap(long = "cluster-private-key", value_parser)]
     pub cluster_private_key: Option<String>,
     /// The cluster public key to use
     #[clap(long = "cluster-public-key", value_parser)]
     pub cluster_public_key: Option<String>,
+    /// The failure domain (e.g. region or availability zone) the local node runs in
+    #[clap(long, value_parser)]
+    pub zone: Option<String>,
+    /// The number of cluster peers each wallet is replicated to, defaults to every peer
+    #[clap(long, value_parser)]
+    pub replication_factor: Option<usize>,
+    /// Replicate wallets across the cluster as erasure-coded shards, given as `k:n`; each wallet
+    /// is encoded into `n` shards, any `k` of which reassemble it
+    #[clap(long, value_parser)]
+    pub erasure_coding: Option<String>,
+    /// Per-peer rate limits on inbound gossip requests, each of the form
+    /// `request_type:burst:per_second`, e.g. `heartbeat:20:2`. Request types are `bootstrap`,
+    /// `heartbeat`, `handshake`, `order_info`, `order_book_snapshot`, `replication`,
+    /// `validity`, and `batch`; types not given take their default limits
+    #[clap(long, value_parser)]
+    pub gossip_rate_limit: Option<Vec<String>>,
+    /// The number of requests a peer may have dropped for exceeding its rate limits, net of
+    /// one forgiven per second, before it is banned
+    #[clap(long, value_parser, default_value = "100")]
+    pub gossip_ban_threshold: f64,
+    /// The duration of a peer's first ban in seconds, each further ban lasts twice as long
+    #[clap(long, value_parser, default_value = "60")]
+    pub gossip_ban_duration_secs: u64,
+    /// The interval in seconds at which the cluster rotates the key it signs gossip with; if
+    /// unset the local node rotates only when a cluster peer announces a rotation
+    ///
+    /// Only a node that rotates on a schedule keeps the cluster keypair as the master key
+    /// after the first rotation, this should be set on a single node of the cluster
+    #[clap(long, value_parser)]
+    pub cluster_key_rotation_interval_secs: Option<u64>,
+    /// The period in seconds after a cluster key rotation in which signatures under the
+    /// previous key are still accepted
+    #[clap(long, value_parser, default_value = "300")]
+    pub cluster_key_grace_period_secs: u64,
+    /// The number of minor versions the local node may fall behind its cluster's majority
+    /// version before a warning is logged
+    #[clap(long, value_parser, default_value = "1")]
+    pub max_version_lag: u64,
+    /// Contact information for the operator of the local node, advertised in its identity
+    /// attestation
+    #[clap(long, value_parser)]
+    pub contact_info: Option<String>,
 
     // ----------------------------
     // | Local Node Configuration |
@@ -73,18 +169,150 @@ struct Cli {
     /// The port to listen on for the externally facing websocket API
     #[clap(long, value_parser, default_value = "4000")]
     pub websocket_port: u16,
+    /// The API keys that requests to the HTTP and websocket APIs must be signed with, each of
+    /// the form `key_id:base64_secret:permission` where permission is one of `read`, `wallet`,
+    /// or `admin`; if none are given, only public and read-only routes are served
+    #[clap(long, value_parser)]
+    pub api_key: Option<Vec<String>>,
+    /// The number of events buffered for each websocket subscription before the overflow
+    /// policy applies
+    #[clap(long, value_parser, default_value = "64")]
+    pub websocket_buffer_size: usize,
+    /// The capacities of the workers' job queues, each of the form `queue=capacity`, e.g.
+    /// `network=4096`; queues not set hold 1024 jobs
+    #[clap(long, value_parser)]
+    pub job_queue_capacity: Option<Vec<String>>,
+    /// What a websocket subscription whose buffer is full does with a new event;
+    /// `drop_oldest` to skip ahead, or `drop_newest` to drop the new event
+    #[clap(long, value_parser, default_value = "drop_oldest")]
+    pub websocket_overflow_policy: String,
+    /// What to do when an order is placed in a wallet with no free order slot; `reject` the
+    /// order, or `evict_oldest` to cancel the wallet's oldest unmatched order
+    #[clap(long, value_parser, default_value = "reject")]
+    pub order_eviction_policy: String,
+    /// The number of cross preview requests the API server serves per minute, across all
+    /// callers
+    #[clap(long, value_parser, default_value = "60")]
+    pub cross_preview_rate_limit: u32,
     /// Flag to disable the API server
     #[clap(long, value_parser)]
     pub disable_api_server: bool,
     /// Flag to disable the price reporter
     #[clap(long, value_parser)]
     pub disable_price_reporter: bool,
+    /// Flag to sign each median price the price reporter publishes with the cluster key
+    #[clap(long, value_parser)]
+    pub sign_price_reports: bool,
+    /// Flag to run the handshake manager receive-only; it answers peers' match proposals but
+    /// schedules no handshakes of its own. May be toggled at runtime as the
+    /// `handshake_receive_only` feature flag
+    #[clap(long, value_parser)]
+    pub handshake_receive_only: bool,
+    /// Flag to run the gossip server listen-only; it answers peers outside the cluster but
+    /// does not heartbeat them. May be toggled at runtime as the `gossip_listen_only` feature
+    /// flag
+    #[clap(long, value_parser)]
+    pub gossip_listen_only: bool,
+    /// Flag to disable the chain listener's startup replay of historical Merkle events
+    #[clap(long, value_parser)]
+    pub disable_chain_backfill: bool,
+    /// Per-pair price circuit breaker thresholds, each of the form
+    /// `BASE-QUOTE:max_move:window_ms:min_confirmations`, e.g. `WETH-USDC:0.05:10000:2`
+    #[clap(long, value_parser)]
+    pub price_circuit_breaker: Option<Vec<String>>,
+    /// The deviation, in basis points, of an exchange's midpoint from the median of the other
+    /// exchanges beyond which the exchange is considered deviant
+    #[clap(long, value_parser, default_value = "100")]
+    pub price_deviation_max_bps: u32,
+    /// The number of seconds an exchange may deviate before it is quarantined and excluded
+    /// from the median
+    #[clap(long, value_parser, default_value = "10")]
+    pub price_deviation_window_secs: u64,
+    /// The minimum number of seconds a deviant exchange is quarantined for
+    #[clap(long, value_parser, default_value = "60")]
+    pub price_quarantine_secs: u64,
+    /// The number of hours of per-exchange and median price history the price reporter
+    /// retains for charting
+    #[clap(long, value_parser, default_value = "24")]
+    pub price_history_retention_hours: u64,
+    /// The region the relayer is deployed in, one of `global` or `us`; the price reporter only
+    /// streams from exchanges that serve the region
+    #[clap(long, value_parser, default_value = "global")]
+    pub exchange_region: String,
+    /// The exchanges the price reporter may stream from, e.g. `coinbase`; defaults to every
+    /// exchange that serves the region
+    #[clap(long, value_parser)]
+    pub exchange_allowlist: Option<Vec<String>>,
+    /// Feature flag overrides, each of the form `name=true` or `name=false`, e.g.
+    /// `internal_crossing=true`
+    #[clap(long, value_parser)]
+    pub feature_flag: Option<Vec<String>>,
     /// Whether or not to run the relayer in debug mode
     #[clap(short, long, value_parser)]
     pub debug: bool,
+    /// The format logs are written to stdout in; `text`, or `json` for log aggregation
+    #[clap(long, value_parser, default_value = "text")]
+    pub log_format: String,
+    /// The log filter directives, e.g. `info,darkpool_relayer::gossip=debug`; defaults to the
+    /// `RUST_LOG` environment variable if set, otherwise `info`. May be changed at runtime
+    /// through the admin API
+    #[clap(long, value_parser)]
+    pub log_filter: Option<String>,
     /// The software version of the relayer
     #[clap(short, long, value_parser)]
     pub version: Option<String>,
+    /// The amount of time a match MPC may run before it is abandoned, in milliseconds
+    #[clap(long, value_parser, default_value = "300000")]
+    pub mpc_timeout_ms: u64,
+    /// Whether to ask peers to compare order size buckets before brokering a match MPC
+    #[clap(long, value_parser)]
+    pub size_bucket_check: bool,
+    /// Propose matches on order pairs that indications of interest or cluster access show
+    /// cannot be matched, so that declined pairs reveal nothing of the local orders
+    #[clap(long, value_parser)]
+    pub disable_handshake_prescreen: bool,
+    /// The maximum number of match MPCs to run at once across all peers, further MPCs
+    /// queue until one completes
+    #[clap(long, value_parser, default_value = "8")]
+    pub max_concurrent_mpcs: usize,
+    /// The maximum number of match MPCs to run at once against a single peer
+    #[clap(long, value_parser, default_value = "2")]
+    pub max_concurrent_mpcs_per_peer: usize,
+    /// The highest fee, in wei, the relayer pays to settle a match; a settlement whose
+    /// estimated fee exceeds it is held back until the network is less congested
+    #[clap(long, value_parser)]
+    pub max_settlement_fee: Option<u64>,
+    /// Broker handshakes between orders managed by other clusters, charging the given share
+    /// of each managing relayer's fee in basis points
+    #[clap(long, value_parser)]
+    pub broker_fee_bps: Option<u16>,
+    /// The largest share of the local relayer fee, in basis points, paid to a peer that
+    /// brokers a match on a local order; brokered matches asking more are declined
+    #[clap(long, value_parser, default_value = "0")]
+    pub max_broker_fee_bps: u16,
+    /// The smallest amount of the base token an order is filled with in a single match, for
+    /// orders that do not set their own minimum fill size
+    #[clap(long, value_parser)]
+    pub default_min_fill_size: Option<u64>,
+    /// The furthest, in basis points, a match's execution price may stray from the median
+    /// price report, for orders that do not set their own maximum slippage
+    #[clap(long, value_parser)]
+    pub default_max_slippage_bps: Option<u16>,
+    /// The fraction of stored witnesses to check for constraint satisfaction at startup
+    #[clap(long, value_parser, default_value = "0")]
+    pub witness_check_sample_rate: f64,
+    /// The prover parameters bundle to prove and verify under, defaults to the parameters
+    /// built into the relayer
+    #[clap(long, value_parser)]
+    pub params_bundle: Option<String>,
+    /// The hash the prover parameters bundle must have, the relayer refuses to start if the
+    /// bundle hashes otherwise
+    #[clap(long, value_parser)]
+    pub expected_params_hash: Option<String>,
+    /// The seed for worker randomness, only available in test builds
+    #[cfg(feature = "deterministic-rng")]
+    #[clap(long, value_parser)]
+    pub rng_seed: Option<u64>,
 
     // -----------
     // | Secrets |
@@ -98,16 +326,122 @@ struct Cli {
     /// The Ethereum RPC node websocket address to dial for on-chain data
     #[clap(long = "eth-websocket", value_parser)]
     pub eth_websocket_addr: Option<String>,
+    /// The UniswapV3 fee tier to read prices from, one of `0.05%`, `0.3%` or `1%`; defaults to
+    /// the pool with the highest TVL
+    #[clap(long, value_parser)]
+    pub uniswap_fee_tier: Option<String>,
+    /// The window over which the UniswapV3 reference TWAP is computed, in seconds
+    #[clap(long, value_parser, default_value = "1800")]
+    pub uniswap_twap_window_secs: u32,
     /// The HTTP addressable StarkNet JSON-RPC node
     #[clap(long = "starknet-gateway", value_parser)]
     pub starknet_jsonrpc_node: Option<String>,
+    /// The address of the on-chain token registry contract to read listed tokens from
+    #[clap(long, value_parser)]
+    pub token_registry_address: Option<String>,
+    /// A JSON file of ERC-20 tokens to list on top of the built-in and on-chain token
+    /// definitions; re-read on SIGHUP or when the file changes
+    #[clap(long, value_parser)]
+    pub token_remap_file: Option<String>,
     /// The StarkNet private key used to send transactions
     #[clap(long = "starknet-pkey", value_parser)]
     pub starknet_private_key: Option<String>,
+    /// The address of the StarkNet account that the private key signs for
+    #[clap(long = "starknet-account", value_parser)]
+    pub starknet_account_address: Option<String>,
     /// A file holding a json representation of the wallets the local node
     /// should manage
     #[clap(short, long, value_parser)]
     pub wallet_file: Option<String>,
+    /// The file that matches are journaled to ahead of settlement, so that a restarted
+    /// relayer can resume them
+    #[clap(long, value_parser)]
+    pub settlement_journal_file: Option<String>,
+    /// The file that order pairs the handshake manager has completed are persisted to, so that
+    /// a restarted relayer does not re-attempt matches on them
+    #[clap(long, value_parser)]
+    pub handshake_cache_file: Option<String>,
+    /// The directory that proofs of `VALID COMMITMENTS` are cached in across restarts; the
+    /// cache holds wallet secrets and should be readable only by the relayer
+    #[clap(long, value_parser)]
+    pub proof_cache_dir: Option<String>,
+    /// The file that proof jobs are journaled to, so that a restarted relayer re-runs the
+    /// jobs it had not finished; the journal holds wallet secrets and should be readable
+    /// only by the relayer
+    #[clap(long, value_parser)]
+    pub proof_journal_file: Option<String>,
+    /// The file that the on-chain event listener checkpoints the last block it processed
+    /// to, so that a restarted relayer replays the events it missed
+    #[clap(long, value_parser)]
+    pub chain_checkpoint_file: Option<String>,
+    /// The directory that dumps of the order book are exported to
+    #[clap(long, value_parser)]
+    pub order_book_export_dir: Option<String>,
+    /// The directory that the price reporter's sampled price history is flushed to, so that
+    /// it survives a restart; history is held only in memory if unset
+    #[clap(long, value_parser)]
+    pub price_history_dir: Option<String>,
+    /// The object store endpoint that dumps of the order book are `PUT` to
+    #[clap(long, value_parser)]
+    pub order_book_export_endpoint: Option<String>,
+    /// The interval between scheduled order book exports, in seconds; if unset the order
+    /// book is only exported on request through the admin API
+    #[clap(long, value_parser)]
+    pub order_book_export_interval_secs: Option<u64>,
+    /// The URL of an external signing service holding the root keys of wallets the relayer
+    /// does not hold the root key of, e.g. one fronting a hardware wallet
+    #[clap(long, value_parser)]
+    pub external_root_signer: Option<String>,
+    /// The file that the audit log of system bus messages is appended to; auditing is
+    /// disabled if unset
+    #[clap(long, value_parser)]
+    pub audit_log_file: Option<String>,
+    /// The system bus topics recorded in the audit log, e.g. `handshakes` or
+    /// `wallet-updates-*`; defaults to handshakes, settlements, and wallet updates
+    #[clap(long, value_parser)]
+    pub audit_log_topics: Option<Vec<String>>,
+    /// The size in bytes past which the active audit log file is rotated, 64 MiB by default
+    #[clap(long, value_parser, default_value = "67108864")]
+    pub audit_log_max_file_bytes: u64,
+    /// The number of audit log files kept, including the active file
+    #[clap(long, value_parser, default_value = "8")]
+    pub audit_log_max_files: usize,
+    /// A JSON file of the protocol's default fee, settle key, and per-pair and per-cluster
+    /// fee overrides; the protocol charges a flat 2bp fee if unset
+    #[clap(long, value_parser)]
+    pub fee_schedule_file: Option<String>,
+    /// Read the default protocol fee and settle key from the darkpool contract at startup,
+    /// in place of those in the fee schedule file
+    #[clap(long, value_parser)]
+    pub fee_schedule_from_chain: bool,
+}
+
+/// Commands the relayer binary runs in place of the relayer itself
+#[derive(Clone, Debug, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+pub enum Command {
+    /// Print the hash of the configured prover parameters bundle and exit
+    ParamsHash {
+        /// A directory to write the bundle into, under its hash
+        #[clap(long, value_parser)]
+        out_dir: Option<String>,
+    },
+    /// Manage the relayer's wallets
+    Wallet {
+        /// The wallet command to run
+        #[clap(subcommand)]
+        command: WalletCommand,
+    },
+}
+
+/// Commands on the relayer's wallets
+#[derive(Clone, Debug, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+pub enum WalletCommand {
+    /// Recover a wallet from chain and add it to the wallet file, printing the wallet
+    Recover {
+        /// A file holding the JSON encoded secret keys of the wallet
+        #[clap(long, value_parser)]
+        keys_file: String,
+    },
 }
 
 /// Defines the system config for the relayer
@@ -121,36 +455,172 @@ pub struct RelayerConfig {
     pub contract_address: String,
     /// Bootstrap servers that the peer should connect to
     pub bootstrap_servers: Vec<(WrappedPeerId, Multiaddr)>,
+    /// The DNS seeds to discover peers through, as `/dnsaddr` multiaddrs
+    pub dns_seeds: Vec<Multiaddr>,
+    /// The maximum number of outbound connections to peers found through discovery
+    pub max_outbound_connections: usize,
+    /// Whether the local node relays connections for peers that cannot be dialed directly
+    pub relay_server: bool,
+    /// The relays the local node listens through, each ending in the relay's `/p2p` peer ID
+    pub relay_addrs: Vec<Multiaddr>,
+    /// The per-peer rate limits on inbound gossip requests and the ban policy for peers
+    /// that exceed them
+    pub gossip_rate_limits: GossipRateLimitConfig,
+    /// The schedule on which the cluster rotates the key it signs gossip with
+    pub cluster_key_rotation: ClusterKeyRotationConfig,
+    /// If set, the only counterparty clusters the local node handshakes with
+    pub cluster_allowlist: Option<Vec<ClusterId>>,
+    /// Counterparty clusters the local node never handshakes with
+    pub cluster_denylist: Vec<ClusterId>,
     /// The port to listen on for libp2p
     pub p2p_port: u16,
     /// The port to listen on for the externally facing HTTP API
     pub http_port: u16,
     /// The port to listen on for the externally facing websocket API
     pub websocket_port: u16,
+    /// The API keys that requests to the HTTP and websocket APIs must be signed with
+    pub api_keys: Vec<ApiKey>,
+    /// The buffer size and overflow policy of each websocket subscription
+    pub websocket_subscription_config: SubscriptionConfig,
+    /// The capacity of each worker's job queue
+    pub job_queue_capacities: JobQueueCapacities,
+    /// The policy applied when an order is placed in a wallet with no free order slot
+    pub order_eviction_policy: OrderEvictionPolicy,
+    /// The number of cross preview requests the API server serves per minute
+    pub cross_preview_rate_limit: u32,
     /// Whether to disable the API server on the local node if, for example,
     /// the local node is an MPC-only node
     pub disable_api_server: bool,
     /// Whether to disable the price reporter if e.g. we are streaming from a dedicated
     /// external API gateway node in the cluster
     pub disable_price_reporter: bool,
+    /// Whether to sign each published median price with the cluster key
+    pub sign_price_reports: bool,
+    /// Whether to skip the chain listener's replay of historical Merkle events at startup
+    pub disable_chain_backfill: bool,
+    /// The price circuit breaker thresholds for each (base, quote) ticker pair
+    pub price_circuit_breakers: HashMap<(String, String), CircuitBreakerConfig>,
+    /// The thresholds at which a deviant exchange is quarantined from the median
+    pub price_deviation_quarantine: DeviationQuarantineConfig,
+    /// The retention and on-disk location of the price reporter's price history
+    pub price_history: PriceHistoryConfig,
+    /// The region the relayer is deployed in, which restricts the exchanges the price
+    /// reporter streams from
+    pub exchange_region: ExchangeRegion,
+    /// The exchanges the price reporter may stream from, or `None` to use every exchange
+    /// that serves the region
+    pub exchange_allowlist: Option<HashSet<Exchange>>,
+    /// The feature flags set in the config, flags not set take their defaults
+    pub feature_flags: HashMap<FeatureFlag, bool>,
     /// The wallet IDs to manage locally
     pub wallets: Vec<Wallet>,
+    /// The file the wallets were read from, a snapshot of the managed wallets is
+    /// written back to this file when the relayer shuts down
+    pub wallet_file: Option<String>,
+    /// The file that pending settlements are journaled to
+    pub settlement_journal_file: Option<String>,
+    /// The file that completed order pairs in the handshake cache are persisted to
+    pub handshake_cache_file: Option<String>,
+    /// The directory that proofs of `VALID COMMITMENTS` are cached in
+    pub proof_cache_dir: Option<String>,
+    /// The file that proof jobs are journaled to
+    pub proof_journal_file: Option<String>,
+    /// The file that the on-chain event listener's checkpoint is persisted to
+    pub chain_checkpoint_file: Option<String>,
+    /// Where dumps of the order book are exported to, exports are disabled if `None`
+    pub order_book_export: Option<ExportDestination>,
+    /// The URL of the external signer authorizing root operations, if one is configured
+    pub external_root_signer: Option<Url>,
+    /// The interval between scheduled order book exports, or `None` to only export on
+    /// request
+    pub order_book_export_interval: Option<Duration>,
+    /// The configuration of the audit log, auditing is disabled if `None`
+    pub audit_log: Option<AuditLogConfig>,
+    /// The schedule of protocol fees charged on matches
+    pub fee_schedule: FeeSchedule,
+    /// Whether to refresh the fee schedule's default fee and settle key from the contract
+    pub fee_schedule_from_chain: bool,
     /// The cluster keypair
     pub cluster_keypair: Keypair,
     /// The cluster ID, a parsed version of the cluster's pubkey
     pub cluster_id: ClusterId,
+    /// The failure domain the local node runs in, advertised to peers so that
+    /// wallets are replicated across zones
+    pub zone: Option<String>,
+    /// The number of cluster peers each wallet should be replicated to, or `None`
+    /// to replicate every wallet to every cluster peer
+    pub replication_factor: Option<usize>,
+    /// The erasure code wallets are sharded with across the cluster, or `None` to replicate
+    /// wallets only in full
+    pub erasure_coding: Option<ErasureCoding>,
+    /// The number of minor versions the local node may fall behind its cluster's majority
+    /// version before a warning is logged
+    pub max_version_lag: u64,
+    /// Contact information for the operator of the local node, advertised in its identity
+    /// attestation
+    pub contact_info: Option<String>,
     /// The Coinbase API key to use for price streaming
     pub coinbase_api_key: Option<String>,
     /// The Coinbase API secret to use for price streaming
     pub coinbase_api_secret: Option<String>,
     /// The StarkNet JSON-RPC API gateway
     pub starknet_jsonrpc_node: Option<String>,
+    /// The address of the on-chain token registry contract, merged with the local
+    /// token definitions by the price reporter
+    pub token_registry_address: Option<String>,
+    /// The operator's token remap file, merged over the token registry by the price reporter
+    pub token_remap_file: Option<String>,
     /// The StarkNet private key used for signing transactions
     pub starknet_private_key: Option<String>,
+    /// The address of the StarkNet account that the private key signs for
+    pub starknet_account_address: Option<String>,
     /// The Ethereum RPC node websocket address to dial for on-chain data
     pub eth_websocket_addr: Option<String>,
+    /// The UniswapV3 fee tier to read prices from, or `None` to use the pool with the
+    /// highest TVL
+    pub uniswap_fee_tier: Option<UniswapFeeTier>,
+    /// The window over which the UniswapV3 reference TWAP is computed, in seconds
+    pub uniswap_twap_window_secs: u32,
+    /// The amount of time a match MPC may run before the handshake manager abandons it
+    /// and retries the order pair
+    pub mpc_timeout_ms: u64,
+    /// Whether the local peer commits to its order's size bucket when proposing a match,
+    /// so that grossly mismatched order pairs are abandoned before the MPC
+    pub size_bucket_check: bool,
+    /// Whether to drop order pairs that public order metadata shows cannot be matched
+    /// before proposing a match on them
+    pub handshake_prescreen: bool,
+    /// The maximum number of match MPCs run at once across all peers
+    pub max_concurrent_mpcs: usize,
+    /// The maximum number of match MPCs run at once against a single peer
+    pub max_concurrent_mpcs_per_peer: usize,
+    /// The highest fee the relayer pays to settle a match, or `None` to pay any fee
+    pub max_settlement_fee: Option<u64>,
+    /// The share of the managing relayers' fees charged to broker a handshake between
+    /// foreign orders, or `None` if the relayer does not broker handshakes
+    pub broker_fee_bps: Option<u16>,
+    /// The largest share of the local relayer fee paid to the broker of a match
+    pub max_broker_fee_bps: u16,
+    /// The minimum fill size of orders that do not set their own, `None` if unbounded
+    pub default_min_fill_size: Option<u64>,
+    /// The maximum slippage from the median price, in basis points, of orders that do not
+    /// set their own, `None` if unbounded
+    pub default_max_slippage_bps: Option<u16>,
+    /// The fraction of stored `VALID COMMITMENTS` witnesses that are checked for
+    /// constraint satisfaction during the startup integrity pass
+    pub witness_check_sample_rate: f64,
+    /// The prover parameters bundle, either loaded from file or built in
+    pub params_bundle: ParamsBundle,
+    /// The hash the prover parameters bundle is verified against at startup, if any
+    pub expected_params_hash: Option<String>,
+    /// The seed injected into worker randomness, always `None` outside of test builds
+    pub rng_seed: Option<u64>,
     /// Whether or not the relayer is in debug mode
     pub debug: bool,
+    /// The format and filter of the relayer's log capture
+    pub log_config: LogConfig,
+    /// The command to run in place of the relayer, if any
+    pub command: Option<Command>,
 }
 
 /// A custom clone implementation specifically for the cluster keypair which does not
@@ -162,20 +632,79 @@ impl Clone for RelayerConfig {
             chain_id: self.chain_id,
             contract_address: self.contract_address.clone(),
             bootstrap_servers: self.bootstrap_servers.clone(),
+            dns_seeds: self.dns_seeds.clone(),
+            max_outbound_connections: self.max_outbound_connections,
+            relay_server: self.relay_server,
+            relay_addrs: self.relay_addrs.clone(),
+            gossip_rate_limits: self.gossip_rate_limits.clone(),
+            cluster_key_rotation: self.cluster_key_rotation,
+            cluster_allowlist: self.cluster_allowlist.clone(),
+            cluster_denylist: self.cluster_denylist.clone(),
             p2p_port: self.p2p_port,
             http_port: self.http_port,
             websocket_port: self.websocket_port,
+            api_keys: self.api_keys.clone(),
+            websocket_subscription_config: self.websocket_subscription_config,
+            job_queue_capacities: self.job_queue_capacities.clone(),
+            order_eviction_policy: self.order_eviction_policy,
+            cross_preview_rate_limit: self.cross_preview_rate_limit,
             disable_api_server: self.disable_api_server,
             disable_price_reporter: self.disable_price_reporter,
+            sign_price_reports: self.sign_price_reports,
+            disable_chain_backfill: self.disable_chain_backfill,
+            price_circuit_breakers: self.price_circuit_breakers.clone(),
+            price_deviation_quarantine: self.price_deviation_quarantine,
+            price_history: self.price_history.clone(),
+            exchange_region: self.exchange_region,
+            exchange_allowlist: self.exchange_allowlist.clone(),
+            feature_flags: self.feature_flags.clone(),
             wallets: self.wallets.clone(),
+            wallet_file: self.wallet_file.clone(),
+            settlement_journal_file: self.settlement_journal_file.clone(),
+            handshake_cache_file: self.handshake_cache_file.clone(),
+            proof_cache_dir: self.proof_cache_dir.clone(),
+            proof_journal_file: self.proof_journal_file.clone(),
+            chain_checkpoint_file: self.chain_checkpoint_file.clone(),
+            order_book_export: self.order_book_export.clone(),
+            external_root_signer: self.external_root_signer.clone(),
+            order_book_export_interval: self.order_book_export_interval,
+            audit_log: self.audit_log.clone(),
+            fee_schedule: self.fee_schedule.clone(),
+            fee_schedule_from_chain: self.fee_schedule_from_chain,
             cluster_keypair: Keypair::from_bytes(&self.cluster_keypair.to_bytes()).unwrap(),
             cluster_id: self.cluster_id.clone(),
+            zone: self.zone.clone(),
+            replication_factor: self.replication_factor,
+            erasure_coding: self.erasure_coding,
+            max_version_lag: self.max_version_lag,
+            contact_info: self.contact_info.clone(),
             coinbase_api_key: self.coinbase_api_key.clone(),
             coinbase_api_secret: self.coinbase_api_secret.clone(),
             starknet_jsonrpc_node: self.starknet_jsonrpc_node.clone(),
+            token_registry_address: self.token_registry_address.clone(),
+            token_remap_file: self.token_remap_file.clone(),
             starknet_private_key: self.starknet_private_key.clone(),
+            starknet_account_address: self.starknet_account_address.clone(),
             eth_websocket_addr: self.eth_websocket_addr.clone(),
+            uniswap_fee_tier: self.uniswap_fee_tier,
+            uniswap_twap_window_secs: self.uniswap_twap_window_secs,
+            mpc_timeout_ms: self.mpc_timeout_ms,
+            size_bucket_check: self.size_bucket_check,
+            handshake_prescreen: self.handshake_prescreen,
+            max_concurrent_mpcs: self.max_concurrent_mpcs,
+            max_concurrent_mpcs_per_peer: self.max_concurrent_mpcs_per_peer,
+            max_settlement_fee: self.max_settlement_fee,
+            broker_fee_bps: self.broker_fee_bps,
+            max_broker_fee_bps: self.max_broker_fee_bps,
+            default_min_fill_size: self.default_min_fill_size,
+            default_max_slippage_bps: self.default_max_slippage_bps,
+            witness_check_sample_rate: self.witness_check_sample_rate,
+            params_bundle: self.params_bundle.clone(),
+            expected_params_hash: self.expected_params_hash.clone(),
+            rng_seed: self.rng_seed,
             debug: self.debug,
+            log_config: self.log_config.clone(),
+            command: self.command.clone(),
         }
     }
 }
@@ -239,6 +768,16 @@ pub fn parse_command_line_args() -> Result<RelayerConfig, CoordinatorError> {
         parsed_bootstrap_addrs.push((WrappedPeerId(peer_id), parsed_addr));
     }
 
+    // The receive-only and listen-only worker modes are held as feature flags so that they
+    // may be toggled at runtime, the command line flags override the config's flag values
+    let mut feature_flags = parse_feature_flags(&cli_args.feature_flag.unwrap_or_default())?;
+    if cli_args.handshake_receive_only {
+        feature_flags.insert(FeatureFlag::HandshakeReceiveOnly, true);
+    }
+    if cli_args.gossip_listen_only {
+        feature_flags.insert(FeatureFlag::GossipListenOnly, true);
+    }
+
     let config = RelayerConfig {
         version: cli_args
             .version
@@ -246,25 +785,429 @@ pub fn parse_command_line_args() -> Result<RelayerConfig, CoordinatorError> {
         chain_id: cli_args.chain_id,
         contract_address: cli_args.contract_address,
         bootstrap_servers: parsed_bootstrap_addrs,
+        dns_seeds: parse_dns_seeds(&cli_args.dns_seeds.unwrap_or_default())?,
+        max_outbound_connections: cli_args.max_outbound_connections,
+        relay_server: cli_args.relay_server,
+        relay_addrs: parse_relay_addrs(&cli_args.relay_addrs.unwrap_or_default())?,
+        gossip_rate_limits: GossipRateLimitConfig {
+            limits: parse_gossip_rate_limits(&cli_args.gossip_rate_limit.unwrap_or_default())?,
+            ban_threshold: cli_args.gossip_ban_threshold,
+            ban_duration: Duration::from_secs(cli_args.gossip_ban_duration_secs),
+        },
+        cluster_key_rotation: ClusterKeyRotationConfig {
+            rotation_interval: cli_args
+                .cluster_key_rotation_interval_secs
+                .map(Duration::from_secs),
+            grace_period: Duration::from_secs(cli_args.cluster_key_grace_period_secs),
+        },
+        cluster_allowlist: cli_args
+            .cluster_allowlist
+            .map(|clusters| parse_cluster_ids(&clusters)),
+        cluster_denylist: parse_cluster_ids(&cli_args.cluster_denylist.unwrap_or_default()),
         p2p_port: cli_args.p2p_port,
         http_port: cli_args.http_port,
         websocket_port: cli_args.websocket_port,
+        api_keys: parse_api_keys(&cli_args.api_key.unwrap_or_default())?,
+        websocket_subscription_config: SubscriptionConfig {
+            buffer_size: cli_args.websocket_buffer_size,
+            overflow_policy: OverflowPolicy::from_str(&cli_args.websocket_overflow_policy)
+                .map_err(CoordinatorError::ConfigParse)?,
+        },
+        job_queue_capacities: parse_job_queue_capacities(
+            &cli_args.job_queue_capacity.unwrap_or_default(),
+        )?,
+        order_eviction_policy: OrderEvictionPolicy::from_str(&cli_args.order_eviction_policy)
+            .map_err(CoordinatorError::ConfigParse)?,
+        cross_preview_rate_limit: cli_args.cross_preview_rate_limit,
         disable_api_server: cli_args.disable_api_server,
         disable_price_reporter: cli_args.disable_price_reporter,
-        wallets: parse_wallet_file(cli_args.wallet_file)?,
+        sign_price_reports: cli_args.sign_price_reports,
+        disable_chain_backfill: cli_args.disable_chain_backfill,
+        price_circuit_breakers: parse_circuit_breakers(
+            &cli_args.price_circuit_breaker.unwrap_or_default(),
+        )?,
+        price_deviation_quarantine: DeviationQuarantineConfig {
+            max_deviation_bps: cli_args.price_deviation_max_bps,
+            window_ms: Duration::from_secs(cli_args.price_deviation_window_secs).as_millis(),
+            quarantine_ms: Duration::from_secs(cli_args.price_quarantine_secs).as_millis(),
+        },
+        price_history: PriceHistoryConfig {
+            dir: cli_args.price_history_dir,
+            retention_ms: Duration::from_secs(cli_args.price_history_retention_hours * 3600)
+                .as_millis() as u64,
+        },
+        exchange_region: ExchangeRegion::from_str(&cli_args.exchange_region)
+            .map_err(CoordinatorError::ConfigParse)?,
+        exchange_allowlist: cli_args
+            .exchange_allowlist
+            .map(|exchanges| parse_exchange_allowlist(&exchanges))
+            .transpose()?,
+        feature_flags,
+        wallets: parse_wallet_file(cli_args.wallet_file.clone())?,
+        wallet_file: cli_args.wallet_file,
+        settlement_journal_file: cli_args.settlement_journal_file,
+        handshake_cache_file: cli_args.handshake_cache_file,
+        proof_cache_dir: cli_args.proof_cache_dir,
+        proof_journal_file: cli_args.proof_journal_file,
+        chain_checkpoint_file: cli_args.chain_checkpoint_file,
+        order_book_export: parse_export_destination(
+            cli_args.order_book_export_dir,
+            cli_args.order_book_export_endpoint,
+        )?,
+        order_book_export_interval: cli_args
+            .order_book_export_interval_secs
+            .map(Duration::from_secs),
+        audit_log: cli_args.audit_log_file.map(|path| AuditLogConfig {
+            path,
+            topics: cli_args
+                .audit_log_topics
+                .unwrap_or_else(default_audit_topics),
+            max_file_bytes: cli_args.audit_log_max_file_bytes,
+            max_files: cli_args.audit_log_max_files,
+        }),
+        fee_schedule: cli_args
+            .fee_schedule_file
+            .map(|path| FeeSchedule::from_file(&path))
+            .transpose()
+            .map_err(|err| CoordinatorError::ConfigParse(err.to_string()))?
+            .unwrap_or_default(),
+        fee_schedule_from_chain: cli_args.fee_schedule_from_chain,
+        external_root_signer: cli_args
+            .external_root_signer
+            .map(|url| {
+                Url::parse(&url).map_err(|err| {
+                    CoordinatorError::ConfigParse(format!(
+                        "invalid external root signer URL: {}",
+                        err
+                    ))
+                })
+            })
+            .transpose()?,
         cluster_keypair: keypair,
         cluster_id,
+        zone: cli_args.zone,
+        replication_factor: cli_args.replication_factor,
+        erasure_coding: cli_args
+            .erasure_coding
+            .map(|code| ErasureCoding::from_str(&code))
+            .transpose()
+            .map_err(CoordinatorError::ConfigParse)?,
+        max_version_lag: cli_args.max_version_lag,
+        contact_info: cli_args.contact_info,
         coinbase_api_key: cli_args.coinbase_api_key,
         coinbase_api_secret: cli_args.coinbase_api_secret,
         starknet_jsonrpc_node: cli_args.starknet_jsonrpc_node,
+        token_registry_address: cli_args.token_registry_address,
+        token_remap_file: cli_args.token_remap_file,
         starknet_private_key: cli_args.starknet_private_key,
+        starknet_account_address: cli_args.starknet_account_address,
         eth_websocket_addr: cli_args.eth_websocket_addr,
+        uniswap_fee_tier: cli_args
+            .uniswap_fee_tier
+            .map(|fee_tier| UniswapFeeTier::from_str(&fee_tier))
+            .transpose()
+            .map_err(CoordinatorError::ConfigParse)?,
+        uniswap_twap_window_secs: parse_twap_window(cli_args.uniswap_twap_window_secs)?,
+        mpc_timeout_ms: cli_args.mpc_timeout_ms,
+        size_bucket_check: cli_args.size_bucket_check,
+        handshake_prescreen: !cli_args.disable_handshake_prescreen,
+        max_concurrent_mpcs: parse_concurrency_limit(
+            "max-concurrent-mpcs",
+            cli_args.max_concurrent_mpcs,
+        )?,
+        max_concurrent_mpcs_per_peer: parse_concurrency_limit(
+            "max-concurrent-mpcs-per-peer",
+            cli_args.max_concurrent_mpcs_per_peer,
+        )?,
+        max_settlement_fee: cli_args.max_settlement_fee,
+        broker_fee_bps: cli_args
+            .broker_fee_bps
+            .map(|fee_bps| parse_bps("broker-fee-bps", fee_bps))
+            .transpose()?,
+        max_broker_fee_bps: parse_bps("max-broker-fee-bps", cli_args.max_broker_fee_bps)?,
+        default_min_fill_size: cli_args.default_min_fill_size,
+        default_max_slippage_bps: cli_args
+            .default_max_slippage_bps
+            .map(|slippage_bps| parse_bps("default-max-slippage-bps", slippage_bps))
+            .transpose()?,
+        witness_check_sample_rate: parse_sample_rate(
+            "witness-check-sample-rate",
+            cli_args.witness_check_sample_rate,
+        )?,
+        params_bundle: parse_params_bundle(cli_args.params_bundle)?,
+        expected_params_hash: cli_args.expected_params_hash,
+        #[cfg(feature = "deterministic-rng")]
+        rng_seed: cli_args.rng_seed,
+        #[cfg(not(feature = "deterministic-rng"))]
+        rng_seed: None,
         debug: cli_args.debug,
+        log_config: parse_log_config(&cli_args.log_format, cli_args.log_filter)?,
+        command: cli_args.command,
     };
 
     Ok(config)
 }
 
+/// Load the prover parameters bundle from file, or use the built in parameters if no
+/// file is given
+fn parse_params_bundle(file_name: Option<String>) -> Result<ParamsBundle, CoordinatorError> {
+    match file_name {
+        Some(file_name) => ParamsBundle::load(file_name)
+            .map_err(|err| CoordinatorError::ConfigParse(err.to_string())),
+        None => Ok(ParamsBundle::builtin()),
+    }
+}
+
+/// Validate an MPC concurrency limit, a limit of zero would never run a match
+fn parse_concurrency_limit(name: &str, limit: usize) -> Result<usize, CoordinatorError> {
+    if limit == 0 {
+        return Err(CoordinatorError::ConfigParse(format!(
+            "--{} must be at least 1",
+            name
+        )));
+    }
+
+    Ok(limit)
+}
+
+/// Validate a share given in basis points, which may not exceed the whole
+fn parse_bps(name: &str, bps: u16) -> Result<u16, CoordinatorError> {
+    if bps > 10_000 {
+        return Err(CoordinatorError::ConfigParse(format!(
+            "--{} must be at most 10000",
+            name
+        )));
+    }
+
+    Ok(bps)
+}
+
+/// Validate a sampling rate, which must be a fraction between zero and one
+fn parse_sample_rate(name: &str, rate: f64) -> Result<f64, CoordinatorError> {
+    if !(0. ..=1.).contains(&rate) {
+        return Err(CoordinatorError::ConfigParse(format!(
+            "--{} must be between 0 and 1",
+            name
+        )));
+    }
+
+    Ok(rate)
+}
+
+/// Parse a list of cluster IDs from their string representations
+fn parse_cluster_ids(clusters: &[String]) -> Vec<ClusterId> {
+    clusters
+        .iter()
+        .map(|cluster| ClusterId::from_str(cluster).unwrap())
+        .collect()
+}
+
+/// Parse DNS seed names into the `/dnsaddr` multiaddrs they are dialed at
+fn parse_dns_seeds(seeds: &[String]) -> Result<Vec<Multiaddr>, CoordinatorError> {
+    seeds
+        .iter()
+        .map(|seed| {
+            if seed.is_empty() || seed.contains('/') {
+                return Err(CoordinatorError::ConfigParse(format!(
+                    "invalid DNS seed: {}",
+                    seed
+                )));
+            }
+
+            Ok(dns_seed_addr(seed))
+        })
+        .collect()
+}
+
+/// Parse the multiaddrs of relays to listen through, each must end in the relay's peer ID
+fn parse_relay_addrs(addrs: &[String]) -> Result<Vec<Multiaddr>, CoordinatorError> {
+    addrs
+        .iter()
+        .map(|addr| {
+            let parsed = Multiaddr::from_str(addr)
+                .map_err(|err| CoordinatorError::ConfigParse(err.to_string()))?;
+            if !matches!(parsed.iter().last(), Some(Protocol::P2p(_))) {
+                return Err(CoordinatorError::ConfigParse(format!(
+                    "relay address must end in the relay's peer ID: {}",
+                    addr
+                )));
+            }
+
+            Ok(parsed)
+        })
+        .collect()
+}
+
+/// Parse API keys of the form `key_id:base64_secret:permission`
+fn parse_api_keys(keys: &[String]) -> Result<Vec<ApiKey>, CoordinatorError> {
+    let mut res: Vec<ApiKey> = Vec::with_capacity(keys.len());
+    for key in keys.iter() {
+        let parsed = ApiKey::from_str(key).map_err(CoordinatorError::ConfigParse)?;
+        if res.iter().any(|existing| existing.id == parsed.id) {
+            return Err(CoordinatorError::ConfigParse(format!(
+                "duplicate api key ID: {}",
+                parsed.id
+            )));
+        }
+
+        res.push(parsed);
+    }
+
+    Ok(res)
+}
+
+/// Parse per-pair circuit breaker thresholds of the form
+/// `BASE-QUOTE:max_move:window_ms:min_confirmations`
+fn parse_circuit_breakers(
+    breakers: &[String],
+) -> Result<HashMap<(String, String), CircuitBreakerConfig>, CoordinatorError> {
+    let mut res = HashMap::new();
+    for breaker in breakers.iter() {
+        let parse_err =
+            || CoordinatorError::ConfigParse(format!("invalid circuit breaker: {}", breaker));
+
+        let parts = breaker.split(':').collect::<Vec<_>>();
+        if parts.len() != 4 {
+            return Err(parse_err());
+        }
+        let (base, quote) = parts[0].split_once('-').ok_or_else(parse_err)?;
+
+        let config = CircuitBreakerConfig {
+            max_move: parts[1].parse().map_err(|_| parse_err())?,
+            window_ms: parts[2].parse().map_err(|_| parse_err())?,
+            min_confirmations: parts[3].parse().map_err(|_| parse_err())?,
+        };
+        res.insert((base.to_string(), quote.to_string()), config);
+    }
+
+    Ok(res)
+}
+
+/// Parse the exchanges the price reporter may stream from
+fn parse_exchange_allowlist(exchanges: &[String]) -> Result<HashSet<Exchange>, CoordinatorError> {
+    exchanges
+        .iter()
+        .map(|exchange| Exchange::from_str(exchange).map_err(CoordinatorError::ConfigParse))
+        .collect()
+}
+
+/// Parse per-peer gossip rate limits of the form `request_type:burst:per_second`
+fn parse_gossip_rate_limits(
+    limits: &[String],
+) -> Result<HashMap<GossipRequestType, TokenBucketConfig>, CoordinatorError> {
+    let mut res = HashMap::new();
+    for limit in limits.iter() {
+        let parse_err =
+            || CoordinatorError::ConfigParse(format!("invalid gossip rate limit: {}", limit));
+
+        let parts = limit.split(':').collect::<Vec<_>>();
+        if parts.len() != 3 {
+            return Err(parse_err());
+        }
+
+        let request_type =
+            GossipRequestType::from_str(parts[0]).map_err(CoordinatorError::ConfigParse)?;
+        let config = TokenBucketConfig {
+            burst: parts[1].parse().map_err(|_| parse_err())?,
+            per_second: parts[2].parse().map_err(|_| parse_err())?,
+        };
+        res.insert(request_type, config);
+    }
+
+    Ok(res)
+}
+
+/// Parse feature flag overrides of the form `name=true` or `name=false`
+fn parse_feature_flags(flags: &[String]) -> Result<HashMap<FeatureFlag, bool>, CoordinatorError> {
+    let mut res = HashMap::new();
+    for flag in flags.iter() {
+        let (name, enabled) = flag.split_once('=').ok_or_else(|| {
+            CoordinatorError::ConfigParse(format!("invalid feature flag: {}", flag))
+        })?;
+        let flag = FeatureFlag::from_str(name).map_err(CoordinatorError::ConfigParse)?;
+        let enabled = enabled.parse().map_err(|_| {
+            CoordinatorError::ConfigParse(format!("invalid value for {}: {}", name, enabled))
+        })?;
+        res.insert(flag, enabled);
+    }
+
+    Ok(res)
+}
+
+/// Parse job queue capacities of the form `queue=capacity`
+fn parse_job_queue_capacities(
+    capacities: &[String],
+) -> Result<JobQueueCapacities, CoordinatorError> {
+    let mut res = JobQueueCapacities::default();
+    for entry in capacities.iter() {
+        let (queue, capacity) = entry.split_once('=').ok_or_else(|| {
+            CoordinatorError::ConfigParse(format!("invalid job queue capacity: {}", entry))
+        })?;
+        let capacity = capacity.parse().map_err(|_| {
+            CoordinatorError::ConfigParse(format!("invalid capacity for {}: {}", queue, capacity))
+        })?;
+        res.set_capacity(queue, capacity)
+            .map_err(CoordinatorError::ConfigParse)?;
+    }
+
+    Ok(res)
+}
+
+/// Parse the destination of order book exports from the export directory or object store
+/// endpoint, at most one of which may be set
+fn parse_export_destination(
+    dir: Option<String>,
+    endpoint: Option<String>,
+) -> Result<Option<ExportDestination>, CoordinatorError> {
+    match (dir, endpoint) {
+        (Some(_), Some(_)) => Err(CoordinatorError::ConfigParse(
+            "only one of an order book export directory and endpoint may be set".to_string(),
+        )),
+        (Some(dir), None) => Ok(Some(ExportDestination::Directory(PathBuf::from(dir)))),
+        (None, Some(mut endpoint)) => {
+            // Dumps are named relative to the endpoint, so it must be a directory-like path
+            if !endpoint.ends_with('/') {
+                endpoint.push('/');
+            }
+            let endpoint = Url::parse(&endpoint).map_err(|err| {
+                CoordinatorError::ConfigParse(format!(
+                    "invalid order book export endpoint: {}",
+                    err
+                ))
+            })?;
+            Ok(Some(ExportDestination::ObjectStore(endpoint)))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Validate the UniswapV3 TWAP window; the pool oracle cannot average over an empty window
+fn parse_twap_window(window_secs: u32) -> Result<u32, CoordinatorError> {
+    if window_secs == 0 {
+        return Err(CoordinatorError::ConfigParse(
+            "the UniswapV3 TWAP window must be non-zero".to_string(),
+        ));
+    }
+
+    Ok(window_secs)
+}
+
+/// Parse the log format and filter, falling back to the environment and then the default
+/// filter if none is configured
+fn parse_log_config(format: &str, filter: Option<String>) -> Result<LogConfig, CoordinatorError> {
+    let filter = filter
+        .or_else(|| env::var(LOG_FILTER_ENV_VAR).ok())
+        .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string());
+    LogConfig::parse_filter(&filter).map_err(|err| {
+        CoordinatorError::ConfigParse(format!("invalid log filter {}: {}", filter, err))
+    })?;
+
+    Ok(LogConfig {
+        format: LogFormat::from_str(format).map_err(CoordinatorError::ConfigParse)?,
+        filter,
+    })
+}
+
 /// Parse args from a config file
 fn config_file_args(cli_args: &[String]) -> Result<Vec<String>, CoordinatorError> {
     // Find a match for the config file argument
@@ -1,8 +1,183 @@
 //! Groups top-level errors useful throughout the relayer
+//!
+//! Each worker defines its own error enum; these are wrapped in a `WorkerError` when
+//! they cross a worker boundary, so that the coordinator holds the typed error rather
+//! than its string form. Every error maps onto an `ErrorCode`, which is the stable,
+//! machine-readable form surfaced to API clients and in failure events on the system bus
 
 use std::error::Error;
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_server::error::ApiServerError, chain_events::error::OnChainEventListenerError,
+    gossip::errors::GossipError, handshake::error::HandshakeManagerError,
+    network_manager::error::NetworkManagerError, price_reporter::errors::PriceReporterManagerError,
+    proof_generation::error::ProofManagerError,
+};
+
+// ---------------
+// | Error Codes |
+// ---------------
+
+/// A machine-readable classification of an error
+///
+/// Codes are serialized in screaming snake case, e.g. `NOT_FOUND`, and are stable across
+/// releases; clients should match on the code rather than the message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request was malformed or failed validation
+    BadRequest,
+    /// The operation was cancelled, e.g. by the coordinator during shutdown
+    Cancelled,
+    /// A call to the StarkNet sequencer or a JSON-RPC node failed
+    Chain,
+    /// The relayer is misconfigured for the operation
+    Config,
+    /// The operation conflicts with one already in progress
+    Conflict,
+    /// An unexpected internal failure, e.g. a closed channel between workers
+    Internal,
+    /// A message from a peer could not be parsed
+    InvalidMessage,
+    /// A failure in the p2p network or a network-facing server
+    Network,
+    /// A referenced resource does not exist
+    NotFound,
+    /// A proof could not be generated or failed verification
+    Proof,
+    /// The caller has exceeded its rate limit
+    RateLimited,
+    /// A worker could not be set up
+    Setup,
+    /// A failure reading or writing persisted state, e.g. a journal or cache
+    Storage,
+    /// The operation did not complete in time
+    Timeout,
+    /// A submitted transaction was rejected by the sequencer
+    TransactionRejected,
+    /// The caller could not be authenticated, or lacks permission for the operation
+    Unauthorized,
+}
+
+impl ErrorCode {
+    /// The serialized form of the code
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::Cancelled => "CANCELLED",
+            ErrorCode::Chain => "CHAIN",
+            ErrorCode::Config => "CONFIG",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::Internal => "INTERNAL",
+            ErrorCode::InvalidMessage => "INVALID_MESSAGE",
+            ErrorCode::Network => "NETWORK",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Proof => "PROOF",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::Setup => "SETUP",
+            ErrorCode::Storage => "STORAGE",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::TransactionRejected => "TRANSACTION_REJECTED",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An error that can be classified by an `ErrorCode`
+pub trait ErrorCoded {
+    /// The code classifying the error
+    fn error_code(&self) -> ErrorCode;
+}
+
+// -----------------
+// | Worker Errors |
+// -----------------
+
+/// The error of any worker, carried across worker boundaries in its typed form
+#[derive(Clone, Debug)]
+pub enum WorkerError {
+    /// An error in the API server
+    ApiServer(ApiServerError),
+    /// An error in the on-chain event listener
+    ChainEvents(OnChainEventListenerError),
+    /// An error in the gossip server
+    Gossip(GossipError),
+    /// An error in the handshake manager
+    Handshake(HandshakeManagerError),
+    /// An error in the network manager
+    NetworkManager(NetworkManagerError),
+    /// An error in the price reporter manager
+    PriceReporter(PriceReporterManagerError),
+    /// An error in the proof generation manager
+    ProofManager(ProofManagerError),
+}
+
+impl Error for WorkerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WorkerError::ApiServer(err) => Some(err),
+            WorkerError::ChainEvents(err) => Some(err),
+            WorkerError::Gossip(err) => Some(err),
+            WorkerError::Handshake(err) => Some(err),
+            WorkerError::NetworkManager(err) => Some(err),
+            WorkerError::PriceReporter(err) => Some(err),
+            WorkerError::ProofManager(err) => Some(err),
+        }
+    }
+}
+
+impl Display for WorkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl ErrorCoded for WorkerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            WorkerError::ApiServer(err) => err.error_code(),
+            WorkerError::ChainEvents(err) => err.error_code(),
+            WorkerError::Gossip(err) => err.error_code(),
+            WorkerError::Handshake(err) => err.error_code(),
+            WorkerError::NetworkManager(err) => err.error_code(),
+            WorkerError::PriceReporter(err) => err.error_code(),
+            WorkerError::ProofManager(err) => err.error_code(),
+        }
+    }
+}
+
+/// Implement the conversion from a worker's error into a `WorkerError`
+macro_rules! impl_from_worker_error {
+    ($variant:ident, $err:ty) => {
+        impl From<$err> for WorkerError {
+            fn from(err: $err) -> Self {
+                WorkerError::$variant(err)
+            }
+        }
+    };
+}
+
+impl_from_worker_error!(ApiServer, ApiServerError);
+impl_from_worker_error!(ChainEvents, OnChainEventListenerError);
+impl_from_worker_error!(Gossip, GossipError);
+impl_from_worker_error!(Handshake, HandshakeManagerError);
+impl_from_worker_error!(NetworkManager, NetworkManagerError);
+impl_from_worker_error!(PriceReporter, PriceReporterManagerError);
+impl_from_worker_error!(ProofManager, ProofManagerError);
+
+// ---------------------
+// | Coordinator Error |
+// ---------------------
+
 /// An error type at the coordinator level
 #[derive(Clone, Debug)]
 pub enum CoordinatorError {
@@ -14,11 +189,71 @@ pub enum CoordinatorError {
     ConfigParse(String),
     /// Failure to initialize the on-chain state index
     StateInit(String),
+    /// The prover parameters bundle failed verification
+    ParamsVerification(String),
+    /// A wallet could not be recovered from chain
+    WalletRecovery(String),
+    /// A worker failed to start or to clean up after a failure
+    Worker {
+        /// The name of the worker
+        worker: String,
+        /// The worker's error
+        source: WorkerError,
+    },
+}
+
+impl Error for CoordinatorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CoordinatorError::Worker { source, .. } => Some(source),
+            _ => None,
+        }
+    }
 }
 
-impl Error for CoordinatorError {}
 impl Display for CoordinatorError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+impl ErrorCoded for CoordinatorError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            CoordinatorError::Recovery(_) => ErrorCode::Internal,
+            CoordinatorError::CancelSend(_) => ErrorCode::Internal,
+            CoordinatorError::ConfigParse(_) => ErrorCode::Config,
+            CoordinatorError::StateInit(_) => ErrorCode::Chain,
+            CoordinatorError::ParamsVerification(_) => ErrorCode::Config,
+            CoordinatorError::WalletRecovery(_) => ErrorCode::Chain,
+            CoordinatorError::Worker { source, .. } => source.error_code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gossip::errors::GossipError, handshake::error::HandshakeManagerError};
+
+    use super::{CoordinatorError, ErrorCode, ErrorCoded, WorkerError};
+
+    /// Tests that codes serialize in their documented form and that a coordinator error
+    /// takes the code of the worker error it carries
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::TransactionRejected).unwrap(),
+            format!("\"{}\"", ErrorCode::TransactionRejected.as_str())
+        );
+
+        let err = CoordinatorError::Worker {
+            worker: "handshake-manager".to_string(),
+            source: WorkerError::from(HandshakeManagerError::MpcTimeout),
+        };
+        assert_eq!(err.error_code(), ErrorCode::Timeout);
+        assert!(std::error::Error::source(&err).is_some());
+
+        let err: WorkerError = GossipError::Cancelled("shutdown".to_string()).into();
+        assert_eq!(err.error_code(), ErrorCode::Cancelled);
+    }
+}
@@ -0,0 +1,173 @@
+//! Groups API type definitions for relayer administration
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    external_api::types::Wallet,
+    gossip::types::ClusterId,
+    proof_generation::{dead_letter::DeadLetter, jobs::ProofJob},
+    state::{
+        audit::AuditEvent, cluster_access::ClusterAccessPolicy, feature_flags::FeatureFlag,
+        settlements::SettlementRecord, wallet::PrivateKeyChain,
+    },
+    system_bus::SubscriberMetrics,
+    worker::WorkerStatus,
+};
+
+/// The response type to a request to shut down the relayer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AdminShutdownResponse {
+    /// The number of match MPCs the relayer will finish before exiting
+    pub in_flight_mpcs: usize,
+}
+
+/// The response type to fetch the proof jobs the proof manager has abandoned
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetDeadLettersResponse {
+    /// The abandoned jobs, oldest first
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+/// The request type to dry run a proof job against its circuit
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DryRunProofRequest {
+    /// The job to dry run, in the form it is submitted to the proof manager
+    pub job: ProofJob,
+}
+
+/// The response type to dry run a proof job
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DryRunProofResponse {
+    /// The name of the statement the job proves, e.g. `VALID COMMITMENTS`
+    pub statement: String,
+    /// The number of multiplication gates the circuit allocates
+    pub multipliers: usize,
+    /// The number of linear constraints the circuit applies
+    pub constraints: usize,
+    /// The number of bulletproof generators the circuit is proven with
+    pub bp_gens_capacity: usize,
+    /// Whether the job's witness and statement satisfy the circuit's constraints
+    pub satisfied: bool,
+}
+
+/// The request type to replace the cluster access policy
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdateClusterAccessRequest {
+    /// If set, the only clusters the relayer handshakes with
+    pub allowlist: Option<Vec<ClusterId>>,
+    /// The clusters the relayer never handshakes with
+    #[serde(default)]
+    pub denylist: Vec<ClusterId>,
+}
+
+/// The response type to fetch or replace the cluster access policy
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClusterAccessResponse {
+    /// The policy in effect
+    pub policy: ClusterAccessPolicy,
+}
+
+/// The request type to enable or disable a feature flag
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdateFeatureFlagRequest {
+    /// The flag to set
+    pub flag: FeatureFlag,
+    /// Whether the flag should be enabled
+    pub enabled: bool,
+}
+
+/// The response type to fetch or update the feature flags
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeatureFlagsResponse {
+    /// The state of every feature flag
+    pub flags: BTreeMap<FeatureFlag, bool>,
+}
+
+/// The request type to replace the log filter
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdateLogFilterRequest {
+    /// The new filter directives, e.g. `info,darkpool_relayer::gossip=debug`
+    pub filter: String,
+}
+
+/// The response type to fetch or update the log filter
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogFilterResponse {
+    /// The directives of the current filter
+    pub filter: String,
+}
+
+/// The response type to fetch the status of the relayer's workers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkerStatusResponse {
+    /// The status of each worker that has failed or been paused since startup; workers
+    /// absent from the map have run uninterrupted
+    pub workers: BTreeMap<String, WorkerStatus>,
+}
+
+/// The response type to pause or resume a worker
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ControlWorkerResponse {
+    /// The name of the worker
+    pub worker: String,
+    /// The status of the worker once the command is applied
+    pub status: WorkerStatus,
+}
+
+/// The response type to fetch the progress of recent match settlements
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSettlementsResponse {
+    /// The latest record of each settlement, most recently updated first
+    pub settlements: Vec<SettlementRecord>,
+}
+
+/// The response type to fetch the progress of a single match settlement
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSettlementResponse {
+    /// The latest record of the settlement, holding its transaction hash once submitted
+    pub settlement: SettlementRecord,
+}
+
+/// The response type to fetch recent events from the audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetAuditLogResponse {
+    /// The most recent events matching the query, newest first
+    pub events: Vec<AuditEvent>,
+}
+
+/// The response type to fetch the lag of every system bus subscriber
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemBusMetricsResponse {
+    /// The lag of each subscriber, e.g. each websocket subscription
+    pub subscribers: Vec<SubscriberMetrics>,
+}
+
+/// The response type to an ad hoc export of the order book
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportOrderBookResponse {
+    /// The path or URL the dump was written to
+    pub location: String,
+    /// The version of the schema the dump was written with
+    pub schema_version: u32,
+    /// The number of orders in the dump
+    pub num_orders: usize,
+}
+
+/// The request type to recover a wallet from chain
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecoverWalletRequest {
+    /// The secret keys of the wallet; `sk_view` decrypts the wallet, the rest are held by
+    /// the relayer once the wallet is managed
+    pub secret_keys: PrivateKeyChain,
+}
+
+/// The response type to a wallet recovery
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecoverWalletResponse {
+    /// The index of the leaf holding the recovered wallet's commitment
+    pub leaf_index: BigUint,
+    /// The recovered wallet
+    pub wallet: Wallet,
+}
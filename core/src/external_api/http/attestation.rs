@@ -0,0 +1,128 @@
+//! Groups the signing logic shared by every document the relayer attests to with the
+//! cluster key
+//!
+//! Each attestation type is signed under its own ed25519ph context, so a signature over
+//! one kind of attestation never verifies as another kind, even if the two happen to
+//! serialize to the same bytes
+
+use ed25519_dalek::{Digest, Keypair, Sha512, Signature, SignatureError};
+use serde::{Deserialize, Serialize};
+
+use crate::gossip::types::ClusterId;
+
+/// A document that a relayer signs with its cluster's private key
+pub trait Attestation: Serialize {
+    /// The ed25519ph context the attestation is signed under, unique to each type
+    const CONTEXT: &'static [u8];
+
+    /// The cluster whose key signs the attestation
+    fn cluster_id(&self) -> &ClusterId;
+
+    /// The digest of the attestation that the cluster key signs
+    fn digest(&self) -> Sha512 {
+        let mut hash_digest = Sha512::new();
+        hash_digest.update(&serde_json::to_vec(self).unwrap());
+        hash_digest
+    }
+}
+
+/// An attestation along with its signature under the cluster's private key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedAttestation<A> {
+    /// The attestation
+    pub attestation: A,
+    /// The signature of the attestation under the cluster's private key
+    pub signature: Vec<u8>,
+}
+
+impl<A: Attestation> SignedAttestation<A> {
+    /// Sign an attestation with the cluster keypair
+    pub fn sign(attestation: A, cluster_keypair: &Keypair) -> Result<Self, SignatureError> {
+        let sig = cluster_keypair.sign_prehashed(attestation.digest(), Some(A::CONTEXT))?;
+        Ok(Self {
+            attestation,
+            signature: sig.to_bytes().to_vec(),
+        })
+    }
+
+    /// Verify that the attestation was signed by the key of the cluster it names, and
+    /// that the cluster is the one the caller expects
+    pub fn verify(&self, expected_cluster: &ClusterId) -> Result<(), SignatureError> {
+        let cluster_id = self.attestation.cluster_id();
+        if cluster_id != expected_cluster {
+            return Err(SignatureError::new());
+        }
+
+        let sig = Signature::from_bytes(&self.signature)?;
+        let pubkey = cluster_id.get_public_key()?;
+        pubkey.verify_prehashed(self.attestation.digest(), Some(A::CONTEXT), &sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Keypair;
+    use rand_core::OsRng;
+    use serde::Serialize;
+
+    use crate::gossip::types::ClusterId;
+
+    use super::{Attestation, SignedAttestation};
+
+    /// An attestation signed under one context
+    #[derive(Clone, Debug, Serialize)]
+    struct FirstAttestation {
+        /// The signing cluster
+        cluster_id: ClusterId,
+    }
+
+    impl Attestation for FirstAttestation {
+        const CONTEXT: &'static [u8] = b"renegade-test-first";
+
+        fn cluster_id(&self) -> &ClusterId {
+            &self.cluster_id
+        }
+    }
+
+    /// An attestation that serializes identically to `FirstAttestation`, signed under
+    /// a different context
+    #[derive(Clone, Debug, Serialize)]
+    struct SecondAttestation {
+        /// The signing cluster
+        cluster_id: ClusterId,
+    }
+
+    impl Attestation for SecondAttestation {
+        const CONTEXT: &'static [u8] = b"renegade-test-second";
+
+        fn cluster_id(&self) -> &ClusterId {
+            &self.cluster_id
+        }
+    }
+
+    /// Tests that a signature over one attestation type does not verify as another
+    /// type with the same serialization
+    #[test]
+    fn test_domain_separation() {
+        let mut rng = OsRng {};
+        let keypair = Keypair::generate(&mut rng);
+        let cluster_id = ClusterId::new(&keypair.public);
+
+        let first = SignedAttestation::sign(
+            FirstAttestation {
+                cluster_id: cluster_id.clone(),
+            },
+            &keypair,
+        )
+        .unwrap();
+        assert!(first.verify(&cluster_id).is_ok());
+
+        let second = SignedAttestation {
+            attestation: SecondAttestation {
+                cluster_id: cluster_id.clone(),
+            },
+            signature: first.signature,
+        };
+        assert!(second.verify(&cluster_id).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! Groups API types for in-flight handshake API operations
+
+use serde::{Deserialize, Serialize};
+
+use crate::external_api::types::Handshake;
+
+/// The response type to fetch the handshakes in flight with peers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetHandshakesResponse {
+    /// The in-flight handshakes
+    pub handshakes: Vec<Handshake>,
+}
+
+/// The response type to fetch a single in-flight handshake by its request ID
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetHandshakeResponse {
+    /// The requested handshake
+    pub handshake: Handshake,
+}
+
+/// The response type to cancel an in-flight handshake
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CancelHandshakeResponse {
+    /// The handshake as it was when the cancellation was requested; the handshake is
+    /// torn down asynchronously by the handshake manager
+    pub handshake: Handshake,
+}
@@ -0,0 +1,82 @@
+//! Groups API type definitions for the relayer's identity attestation
+//!
+//! The attestation is signed with the cluster's private key. The cluster ID is the
+//! base64 encoded cluster public key, so a client that knows which cluster it expects
+//! to match with can validate the document without any other key material
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gossip::types::{ClusterId, WrappedPeerId},
+    state::feature_flags::FeatureFlag,
+};
+
+use super::attestation::{Attestation, SignedAttestation};
+
+/// The claims a relayer makes about itself
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityAttestation {
+    /// The cluster the relayer belongs to
+    pub cluster_id: ClusterId,
+    /// The peer ID of the relayer
+    pub peer_id: WrappedPeerId,
+    /// The software version the relayer runs
+    pub relayer_version: String,
+    /// The features the relayer has enabled
+    pub features: Vec<FeatureFlag>,
+    /// Contact information for the relayer's operator, if configured
+    pub contact_info: Option<String>,
+    /// The time at which the attestation was signed, in milliseconds since the epoch
+    pub issued_at: u64,
+}
+
+impl Attestation for IdentityAttestation {
+    const CONTEXT: &'static [u8] = b"renegade-identity-attestation";
+
+    fn cluster_id(&self) -> &ClusterId {
+        &self.cluster_id
+    }
+}
+
+/// The response type to fetch the relayer's signed identity attestation
+pub type GetIdentityResponse = SignedAttestation<IdentityAttestation>;
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Keypair;
+    use rand_core::OsRng;
+
+    use crate::{
+        gossip::types::{ClusterId, WrappedPeerId},
+        state::feature_flags::FeatureFlag,
+    };
+
+    use super::{GetIdentityResponse, IdentityAttestation};
+
+    /// Tests that a signed attestation verifies against its own cluster, and fails to
+    /// verify once tampered with or against a different cluster
+    #[test]
+    fn test_sign_verify() {
+        let mut rng = OsRng {};
+        let keypair = Keypair::generate(&mut rng);
+        let cluster_id = ClusterId::new(&keypair.public);
+
+        let attestation = IdentityAttestation {
+            cluster_id: cluster_id.clone(),
+            peer_id: WrappedPeerId::random(),
+            relayer_version: "0.1.0".to_string(),
+            features: vec![FeatureFlag::InternalCrossing],
+            contact_info: Some("ops@example.com".to_string()),
+            issued_at: 0,
+        };
+        let response = GetIdentityResponse::sign(attestation, &keypair).unwrap();
+        assert!(response.verify(&cluster_id).is_ok());
+
+        let other_cluster = ClusterId::new(&Keypair::generate(&mut rng).public);
+        assert!(response.verify(&other_cluster).is_err());
+
+        let mut tampered = response;
+        tampered.attestation.relayer_version = "0.2.0".to_string();
+        assert!(tampered.verify(&cluster_id).is_err());
+    }
+}
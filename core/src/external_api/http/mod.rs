@@ -1,15 +1,60 @@
 //! Groups API types for the HTTP API
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+use crate::{
+    error::ErrorCode,
+    gossip::types::{ClusterId, WrappedPeerId},
+    state::feature_flags::FeatureFlag,
+};
+
+pub mod admin;
+pub mod attestation;
+pub mod handshake;
+pub mod identity;
 pub mod network;
 pub mod order_book;
 pub mod price_report;
 pub mod wallet;
 
+/// The body of an HTTP response to a request that failed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiErrorResponse {
+    /// The machine-readable classification of the failure
+    pub code: ErrorCode,
+    /// A human-readable description of the failure
+    pub message: String,
+}
+
 /// A ping response
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PingResponse {
     /// The timestamp when the response is sent
     pub timestamp: u128,
 }
+
+/// The response type to fetch the relayer's identity and the features it has enabled
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InfoResponse {
+    /// The peer ID of the relayer
+    pub peer_id: WrappedPeerId,
+    /// The cluster the relayer belongs to
+    pub cluster_id: ClusterId,
+    /// The state of every feature flag
+    pub feature_flags: BTreeMap<FeatureFlag, bool>,
+}
+
+/// Deserialize the body of an HTTP request into the request type
+///
+/// An empty body is read as `null`, which serde expects as the serialized form of an
+/// empty struct
+pub fn deserialize_request_body<Req: DeserializeOwned>(
+    body: &[u8],
+) -> Result<Req, serde_json::Error> {
+    if body.is_empty() {
+        return serde_json::from_slice(b"null");
+    }
+
+    serde_json::from_slice(body)
+}
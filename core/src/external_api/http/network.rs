@@ -1,8 +1,10 @@
 //! Groups API type definitions for peer-to-peer network API operations
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-use crate::external_api::types::{Cluster, Network, Peer};
+use crate::external_api::types::{Cluster, Network, Peer, PeerAuth};
 
 /// The response type to fetch the entire known network topology
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -23,4 +25,19 @@ pub struct GetClusterInfoResponse {
 pub struct GetPeerInfoResponse {
     /// The requested peer
     pub peer: Peer,
+    /// The authentication details of the peer's connection, if the local node
+    /// has connected to the peer
+    pub auth: Option<PeerAuth>,
+}
+
+/// The response type to fetch the distribution of software versions across known peers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetNetworkVersionsResponse {
+    /// The version the local relayer runs
+    pub local_version: String,
+    /// The version most commonly run in the local cluster, if any cluster peer has
+    /// reported its version
+    pub cluster_majority: Option<String>,
+    /// The number of known peers, the local peer included, running each version
+    pub versions: HashMap<String, usize>,
 }
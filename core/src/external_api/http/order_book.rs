@@ -1,14 +1,20 @@
 //! Groups API types for order book API operations
 
+use circuits::types::order::OrderSide;
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
 
 use crate::external_api::types::NetworkOrder;
 
-/// The response type to fetch all the known orders in the network
+/// The response type to fetch a page of the known orders in the network
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetNetworkOrdersResponse {
     /// The orders known to the local peer
     pub orders: Vec<NetworkOrder>,
+    /// The cursor to pass to fetch the next page, `None` if this is the last page
+    pub next_cursor: Option<Uuid>,
 }
 
 /// The response type to fetch a given network order by its ID
@@ -17,3 +23,52 @@ pub struct GetNetworkOrderByIdResponse {
     /// The requested network order
     pub order: NetworkOrder,
 }
+
+/// The coarsened liquidity on one side of a pair
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SideLiquidity {
+    /// The number of verified orders on this side
+    pub order_count: usize,
+    /// The number of orders in each size bucket, keyed by the bit length of the order
+    /// amount; buckets holding too few orders to report privately are omitted
+    pub size_buckets: BTreeMap<u8, usize>,
+}
+
+/// The response type to fetch an indication of the liquidity available for a pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetLiquidityResponse {
+    /// The mint of the base token
+    pub base_mint: BigUint,
+    /// The mint of the quote token
+    pub quote_mint: BigUint,
+    /// The liquidity of verified orders buying the base token
+    pub buy: SideLiquidity,
+    /// The liquidity of verified orders selling the base token
+    pub sell: SideLiquidity,
+    /// The number of verified orders from other clusters; the pair of these orders is
+    /// hidden by their validity proofs, so they are counted across all pairs
+    pub unattributed_order_count: usize,
+}
+
+/// The request type to preview whether a prospective order would cross the local book
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrossPreviewRequest {
+    /// The mint of the base token
+    pub base_mint: BigUint,
+    /// The mint of the quote token
+    pub quote_mint: BigUint,
+    /// The side of the prospective order
+    pub side: OrderSide,
+    /// The size of the prospective order
+    pub amount: u64,
+}
+
+/// The response type to preview whether a prospective order would cross the local book
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrossPreviewResponse {
+    /// Whether verified counter-orders of a comparable size plausibly exist
+    pub plausible: bool,
+    /// The largest size bucket holding enough comparable counter-orders to be reported,
+    /// keyed by the bit length of the order amount
+    pub size_bucket: Option<u8>,
+}
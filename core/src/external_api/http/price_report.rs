@@ -1,15 +1,27 @@
 //! Groups price reporting API types
+//!
+//! A relayer configured to do so signs each median it publishes with the cluster's
+//! private key, so that a consumer may prove that a midpoint came from the relayer. As
+//! with the identity attestation, the cluster ID is the cluster's public key, so the
+//! signature is verified without any other key material
 
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::price_reporter::{
-    exchanges::{Exchange, ExchangeConnectionState},
-    reporter::PriceReporterState,
-    tokens::Token,
+use crate::{
+    gossip::types::ClusterId,
+    price_reporter::{
+        exchanges::{Exchange, ExchangeConnectionState},
+        health::ExchangeHealthReport,
+        history::{CandleInterval, PairPriceHistory, PriceCandle},
+        reporter::{DecentralizedReferencePrice, PriceReporterState},
+        tokens::Token,
+    },
 };
 
+use super::attestation::{Attestation, SignedAttestation};
+
 /// A request to get the health of each exchange for a given token pair
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetExchangeHealthStatesRequest {
@@ -26,4 +38,146 @@ pub struct GetExchangeHealthStatesResponse {
     pub median: PriceReporterState,
     /// The map of all ExchangeConnectionState corresponding to each individual exchange
     pub all_exchanges: HashMap<Exchange, ExchangeConnectionState>,
+    /// The UniswapV3 TWAP and the deviation of the median from it, if UniswapV3 supports
+    /// the token pair
+    pub decentralized_reference: Option<DecentralizedReferencePrice>,
+    /// The health score of each individual exchange; unhealthy exchanges are excluded
+    /// from the median
+    pub exchange_health: HashMap<Exchange, ExchangeHealthReport>,
+}
+
+/// The claims a relayer makes about a median price it published
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PriceAttestation {
+    /// The cluster that signed the price
+    pub cluster_id: ClusterId,
+    /// The base token
+    pub base_token: Token,
+    /// The quote token
+    pub quote_token: Token,
+    /// The median midpoint price
+    pub midpoint_price: f64,
+    /// The exchanges whose prices the median was taken over, sorted
+    pub exchanges: Vec<Exchange>,
+    /// The local time at which the median was computed, in milliseconds since the epoch
+    pub timestamp: u64,
+}
+
+impl Attestation for PriceAttestation {
+    const CONTEXT: &'static [u8] = b"renegade-price-report";
+
+    fn cluster_id(&self) -> &ClusterId {
+        &self.cluster_id
+    }
+}
+
+/// A median price signed by the cluster key
+pub type SignedPriceReport = SignedAttestation<PriceAttestation>;
+
+/// A request to get the latest signed median price of a token pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSignedPriceReportRequest {
+    /// The base token
+    pub base_token: Token,
+    /// The quote token
+    pub quote_token: Token,
+}
+
+/// A response containing the latest signed median price of a token pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSignedPriceReportResponse {
+    /// The signed report
+    pub report: SignedPriceReport,
+}
+
+/// A request to get the recent midpoint history of a token pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetPriceHistoryRequest {
+    /// The base token
+    pub base_token: Token,
+    /// The quote token
+    pub quote_token: Token,
+    /// The time, in milliseconds since the epoch, from which to return history; defaults
+    /// to all retained history
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// The maximum number of points of each series to return, at most 1000
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// A response containing the recent midpoint history of a token pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetPriceHistoryResponse {
+    /// The sampled midpoints of the median and of each exchange, oldest first
+    pub history: PairPriceHistory,
+}
+
+/// A request to get the OHLC candles of a token pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetPriceCandlesRequest {
+    /// The base token
+    pub base_token: Token,
+    /// The quote token
+    pub quote_token: Token,
+    /// The exchange to build candles for; defaults to the median across exchanges
+    #[serde(default)]
+    pub exchange: Option<Exchange>,
+    /// The interval each candle spans
+    pub interval: CandleInterval,
+    /// The start of the range, in milliseconds since the epoch; defaults to the start of
+    /// retained history
+    #[serde(default)]
+    pub start: Option<u64>,
+    /// The end (exclusive) of the range, in milliseconds since the epoch; defaults to now
+    #[serde(default)]
+    pub end: Option<u64>,
+}
+
+/// A response containing the OHLC candles of a token pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetPriceCandlesResponse {
+    /// The candles, oldest first; intervals without a sampled midpoint have no candle,
+    /// and at most the 1440 most recent candles are returned
+    pub candles: Vec<PriceCandle>,
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Keypair;
+    use rand_core::OsRng;
+
+    use crate::{
+        gossip::types::ClusterId,
+        price_reporter::{exchanges::Exchange, tokens::Token},
+    };
+
+    use super::{PriceAttestation, SignedPriceReport};
+
+    /// Tests that a signed price verifies against its own cluster, and fails to verify
+    /// once tampered with or against a different cluster
+    #[test]
+    fn test_sign_verify() {
+        let mut rng = OsRng {};
+        let keypair = Keypair::generate(&mut rng);
+        let cluster_id = ClusterId::new(&keypair.public);
+
+        let attestation = PriceAttestation {
+            cluster_id: cluster_id.clone(),
+            base_token: Token::_from_ticker("WETH"),
+            quote_token: Token::_from_ticker("USDC"),
+            midpoint_price: 1_500.,
+            exchanges: vec![Exchange::Binance, Exchange::Coinbase, Exchange::Kraken],
+            timestamp: 0,
+        };
+        let report = SignedPriceReport::sign(attestation, &keypair).unwrap();
+        assert!(report.verify(&cluster_id).is_ok());
+
+        let other_cluster = ClusterId::new(&Keypair::generate(&mut rng).public);
+        assert!(report.verify(&other_cluster).is_err());
+
+        let mut tampered = report;
+        tampered.attestation.midpoint_price = 1_501.;
+        assert!(tampered.verify(&cluster_id).is_err());
+    }
 }
@@ -1,14 +1,21 @@
 //! Groups API type definitions for wallet API operations
 
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::external_api::types::{Balance, Fee, Order, Wallet};
+use crate::{
+    external_api::types::{Balance, Fee, Order, Wallet},
+    state::{wallet::OrderSlot, wallet_manager::WalletLifecycle},
+};
 
 /// The response type to get a wallet's information
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetWalletResponse {
     /// The wallet requested by the client
     pub wallet: Wallet,
+    /// The stage of its lifecycle the wallet is in on the local relayer
+    pub lifecycle: Option<WalletLifecycle>,
 }
 
 /// The response type to get a wallet's orders
@@ -25,6 +32,24 @@ pub struct GetOrderByIdResponse {
     pub order: Order,
 }
 
+/// The response type to get the occupancy of a wallet's order slots
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetOrderSlotsResponse {
+    /// The number of order slots in a wallet
+    pub capacity: usize,
+    /// The number of free order slots
+    pub free: usize,
+    /// The orders occupying the wallet's slots, oldest first
+    pub slots: Vec<OrderSlot>,
+}
+
+/// The request type to opt an order into or out of automatic resubmission
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SetAutoResubmitRequest {
+    /// Whether the order should be resubmitted when a wallet update cancels it
+    pub enabled: bool,
+}
+
 /// The response type to get a wallet's balances
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetBalancesResponse {
@@ -45,3 +70,53 @@ pub struct GetFeesResponse {
     /// The fees in a given wallet
     pub fees: Vec<Fee>,
 }
+
+// ------------------
+// | Wallet Updates |
+// ------------------
+//
+// Each update request carries an `auth` field, the Poseidon hash of the wallet's root
+// secret key, the wallet's current version, and the request's fields in the order they
+// are declared
+
+/// The request type to place a new order in a wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateOrderRequest {
+    /// The order to place, its `id` is ignored and assigned by the relayer
+    pub order: Order,
+    /// The authentication digest of the request
+    pub auth: BigUint,
+}
+
+/// The response type to place a new order in a wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateOrderResponse {
+    /// The identifier assigned to the new order
+    pub order_id: Uuid,
+    /// The identifier of the wallet update that places the order
+    pub task_id: Uuid,
+    /// The websocket topic on which the update's progress is streamed
+    pub topic: String,
+    /// The order cancelled to free a slot for the new order, if the wallet was full
+    pub evicted_order: Option<Uuid>,
+}
+
+/// The request type to deposit into or withdraw from a wallet's balance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExternalTransferRequest {
+    /// The ERC-20 address of the token to transfer
+    pub mint: BigUint,
+    /// The amount of the token to transfer
+    pub amount: BigUint,
+    /// The authentication digest of the request
+    pub auth: BigUint,
+}
+
+/// The response type to a deposit or withdrawal
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalletUpdateResponse {
+    /// The identifier of the wallet update
+    pub task_id: Uuid,
+    /// The websocket topic on which the update's progress is streamed
+    pub topic: String,
+}
@@ -9,7 +9,7 @@ use circuits::{
     types::{
         balance::Balance as IndexedBalance,
         fee::Fee as IndexedFee,
-        order::{Order as IndexedOrder, OrderSide},
+        order::{MatchConstraints, Order as IndexedOrder, OrderSide, TimeInForce},
     },
     zk_gadgets::fixed_point::FixedPoint,
 };
@@ -21,9 +21,14 @@ use uuid::Uuid;
 
 use crate::{
     gossip::types::PeerInfo as IndexedPeerInfo,
+    handshake::state::{HandshakeState, State as IndexedHandshakeStatus},
     state::{
-        wallet::Wallet as IndexedWallet, NetworkOrder as IndexedNetworkOrder, NetworkOrderState,
-        OrderIdentifier,
+        peer_auth::{
+            ClusterAuthStatus, PeerAuthEvent as IndexedPeerAuthEvent, PeerAuthEventKind,
+            PeerConnectionAuth,
+        },
+        wallet::Wallet as IndexedWallet,
+        NetworkOrder as IndexedNetworkOrder, NetworkOrderState, OrderIdentifier,
     },
 };
 
@@ -122,6 +127,13 @@ pub struct Order {
     pub amount: BigUint,
     /// The timestamp this order was placed at
     pub timestamp: u64,
+    /// How long the order remains eligible for matching, good 'til cancelled if unset
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    /// The constraints on the matches the order may take part in, the relayer's defaults
+    /// if unset
+    #[serde(default)]
+    pub constraints: MatchConstraints,
 }
 
 impl From<(OrderIdentifier, IndexedOrder)> for Order {
@@ -135,6 +147,8 @@ impl From<(OrderIdentifier, IndexedOrder)> for Order {
             price: order.price,
             amount: BigUint::from(order.amount),
             timestamp: order.timestamp,
+            time_in_force: order.time_in_force,
+            constraints: order.constraints,
         }
     }
 }
@@ -340,6 +354,8 @@ pub struct Peer {
     pub cluster_id: String,
     /// The dialable, libp2p address of the peer
     pub addr: String,
+    /// The failure domain the peer runs in, if it advertises one
+    pub zone: Option<String>,
 }
 
 impl From<IndexedPeerInfo> for Peer {
@@ -348,6 +364,131 @@ impl From<IndexedPeerInfo> for Peer {
             id: peer_info.get_peer_id().to_string(),
             cluster_id: peer_info.get_cluster_id().to_string(),
             addr: peer_info.get_addr().to_string(),
+            zone: peer_info.get_zone(),
+        }
+    }
+}
+
+/// The authentication details of a peer's connection, as recorded in the local
+/// node's audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerAuth {
+    /// The security protocol that authenticated the transport
+    pub transport_security: String,
+    /// The remote address of the most recent connection to the peer
+    pub remote_addr: Option<String>,
+    /// Whether the peer is currently connected to the local node
+    pub connected: bool,
+    /// The protocol version the peer reported
+    pub protocol_version: Option<String>,
+    /// The agent version the peer reported
+    pub agent_version: Option<String>,
+    /// Whether the peer's reported public key matches its peer ID
+    pub identity_key_matches: Option<bool>,
+    /// The result of verifying the peer's cluster membership
+    pub cluster_auth: ClusterAuthStatus,
+    /// The timestamp (in seconds) at which these details were last updated
+    pub last_updated: u64,
+    /// The recent audit log events pertaining to the peer, oldest first
+    pub events: Vec<PeerAuthEvent>,
+}
+
+impl From<(PeerConnectionAuth, Vec<IndexedPeerAuthEvent>)> for PeerAuth {
+    fn from((auth, events): (PeerConnectionAuth, Vec<IndexedPeerAuthEvent>)) -> Self {
+        Self {
+            transport_security: auth.transport_security,
+            remote_addr: auth.remote_addr,
+            connected: auth.connected,
+            protocol_version: auth.protocol_version,
+            agent_version: auth.agent_version,
+            identity_key_matches: auth.identity_key_matches,
+            cluster_auth: auth.cluster_auth,
+            last_updated: auth.last_updated,
+            events: events.into_iter().map(PeerAuthEvent::from).collect_vec(),
+        }
+    }
+}
+
+/// A single audit log event on a peer's connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerAuthEvent {
+    /// The timestamp (in seconds) at which the event was recorded
+    pub timestamp: u64,
+    /// The event
+    pub kind: PeerAuthEventKind,
+    /// A human readable description of the event
+    pub description: String,
+}
+
+impl From<IndexedPeerAuthEvent> for PeerAuthEvent {
+    fn from(event: IndexedPeerAuthEvent) -> Self {
+        Self {
+            timestamp: event.timestamp,
+            description: event.kind.to_string(),
+            kind: event.kind,
+        }
+    }
+}
+
+// -----------------------
+// | Handshake API Types |
+// -----------------------
+
+/// An in-flight handshake between the local node and a peer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Handshake {
+    /// The request ID that identifies the handshake between the peers
+    pub request_id: Uuid,
+    /// The peer that the handshake is executed with
+    pub peer_id: String,
+    /// The identifier of the local order in the pair
+    pub local_order_id: OrderIdentifier,
+    /// The identifier of the peer's order in the pair
+    pub peer_order_id: OrderIdentifier,
+    /// Whether a third peer brokered the handshake
+    pub brokered: bool,
+    /// The stage the handshake is in
+    pub status: HandshakeStatus,
+}
+
+impl From<HandshakeState> for Handshake {
+    fn from(state: HandshakeState) -> Self {
+        Self {
+            request_id: state.request_id,
+            peer_id: state.peer_id.to_string(),
+            local_order_id: state.local_order_id,
+            peer_order_id: state.peer_order_id,
+            brokered: state.broker.is_some(),
+            status: state.state.into(),
+        }
+    }
+}
+
+/// The stage a handshake is in
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandshakeStatus {
+    /// The peers are negotiating the order pair to match
+    OrderNegotiation,
+    /// The match MPC is running
+    MatchInProgress,
+    /// The handshake completed
+    Completed,
+    /// The handshake failed or was cancelled
+    Error {
+        /// A description of the failure
+        message: String,
+    },
+}
+
+impl From<IndexedHandshakeStatus> for HandshakeStatus {
+    fn from(status: IndexedHandshakeStatus) -> Self {
+        match status {
+            IndexedHandshakeStatus::OrderNegotiation => HandshakeStatus::OrderNegotiation,
+            IndexedHandshakeStatus::MatchInProgress => HandshakeStatus::MatchInProgress,
+            IndexedHandshakeStatus::Completed => HandshakeStatus::Completed,
+            IndexedHandshakeStatus::Error(err) => HandshakeStatus::Error {
+                message: err.to_string(),
+            },
         }
     }
 }
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::price_reporter::reporter::PriceReporterState;
+
 /// A message type that indicates the client would like to either subscribe or unsubscribe
 /// from a given topic
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,3 +28,13 @@ pub struct SubscriptionResponse {
     /// The subscriptions that remain after applying the requested update
     pub subscriptions: Vec<String>,
 }
+
+/// The state of a pair's price reporter, pushed to a client when it subscribes to the
+/// pair's price report topic so that it need not wait for the median to next move
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PriceReportSnapshot {
+    /// The price report topic subscribed to
+    pub topic: String,
+    /// The state of the pair's price reporter at the time of subscription
+    pub state: PriceReporterState,
+}
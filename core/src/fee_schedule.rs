@@ -0,0 +1,362 @@
+//! The schedule of fees the protocol takes on a match
+//!
+//! The protocol fee defaults to a single network-wide value, which may be overridden for
+//! individual asset pairs or for the orders of individual clusters. The schedule is read
+//! from a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "default_fee": 0.0002,
+//!     "protocol_settle_key": "0x0",
+//!     "pairs": [{ "base_mint": "0x...", "quote_mint": "0x...", "fee": 0.0001 }],
+//!     "clusters": [{ "cluster_id": "...", "fee": 0.0001 }]
+//! }
+//! ```
+//!
+//! and the default fee and settle key may be refreshed from the darkpool contract, so that
+//! the values proven in `VALID MATCH ENCRYPTION` are those the contract checks against.
+//!
+//! Both managing relayers of a match must resolve the same fee, so resolution depends only on
+//! the pair and the (unordered) set of clusters managing the match: a pair override takes
+//! precedence, otherwise the lowest override of the managing clusters applies, otherwise the
+//! default fee
+
+use std::{collections::HashMap, fmt::Display, fs, str::FromStr};
+
+use circuits::{
+    zk_circuits::valid_match_encryption::ValidMatchEncryptionStatement,
+    zk_gadgets::fixed_point::FixedPoint,
+};
+use crypto::fields::{biguint_to_scalar, starknet_felt_to_biguint};
+use num_bigint::BigUint;
+use serde::Deserialize;
+use starknet::core::{
+    types::{BlockId, CallFunction, FieldElement as StarknetFieldElement},
+    utils::get_selector_from_name,
+};
+use starknet_providers::Provider;
+
+use crate::{gossip::types::ClusterId, starknet_client::client::StarknetClient};
+
+/// The protocol fee charged when no override applies, absent a configured default
+const DEFAULT_PROTOCOL_FEE: f64 = 0.0002;
+/// The darkpool contract's view function returning the protocol fee and settle key
+const PROTOCOL_FEE_FUNCTION: &str = "get_protocol_fee";
+
+/// The error type emitted when loading or checking against the fee schedule
+#[derive(Clone, Debug)]
+pub enum FeeScheduleError {
+    /// Error reading or parsing the fee schedule file
+    Parse(String),
+    /// Error reading the fee schedule from the contract
+    Chain(String),
+    /// A statement was built against a fee or settle key the schedule does not resolve to
+    Mismatch(String),
+}
+
+impl Display for FeeScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The fee schedule file as it is written on disk
+#[derive(Debug, Default, Deserialize)]
+struct FeeScheduleFile {
+    /// The fee charged when no override applies
+    #[serde(default)]
+    default_fee: Option<f64>,
+    /// The hex encoded settle key of the protocol wallet
+    #[serde(default)]
+    protocol_settle_key: Option<String>,
+    /// The per-pair fee overrides
+    #[serde(default)]
+    pairs: Vec<PairFeeOverride>,
+    /// The per-cluster fee overrides
+    #[serde(default)]
+    clusters: Vec<ClusterFeeOverride>,
+}
+
+/// A fee override for a single asset pair
+#[derive(Debug, Deserialize)]
+struct PairFeeOverride {
+    /// The hex encoded mint of the base token
+    base_mint: String,
+    /// The hex encoded mint of the quote token
+    quote_mint: String,
+    /// The protocol fee charged on the pair
+    fee: f64,
+}
+
+/// A fee override for the orders managed by a single cluster
+#[derive(Debug, Deserialize)]
+struct ClusterFeeOverride {
+    /// The cluster the override applies to
+    cluster_id: String,
+    /// The protocol fee charged on matches the cluster manages
+    fee: f64,
+}
+
+/// The fees the protocol takes on a match and the key its fee notes are settled under
+#[derive(Clone, Debug)]
+pub struct FeeSchedule {
+    /// The fee charged when no override applies
+    default_fee: FixedPoint,
+    /// The public settle key of the protocol wallet
+    protocol_settle_key: BigUint,
+    /// Fee overrides keyed by (base mint, quote mint)
+    pair_fees: HashMap<(BigUint, BigUint), FixedPoint>,
+    /// Fee overrides keyed by the cluster managing an order in the match
+    cluster_fees: HashMap<ClusterId, FixedPoint>,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self::new(
+            FixedPoint::from_f64_round_down(DEFAULT_PROTOCOL_FEE),
+            BigUint::from(0u8),
+        )
+    }
+}
+
+impl FeeSchedule {
+    /// Construct a schedule charging the given fee on every match
+    pub fn new(default_fee: FixedPoint, protocol_settle_key: BigUint) -> Self {
+        Self {
+            default_fee,
+            protocol_settle_key,
+            pair_fees: HashMap::new(),
+            cluster_fees: HashMap::new(),
+        }
+    }
+
+    /// Read a fee schedule from the given JSON file
+    pub fn from_file(path: &str) -> Result<Self, FeeScheduleError> {
+        let file_data =
+            fs::read_to_string(path).map_err(|err| FeeScheduleError::Parse(err.to_string()))?;
+        let file: FeeScheduleFile = serde_json::from_str(&file_data)
+            .map_err(|err| FeeScheduleError::Parse(err.to_string()))?;
+
+        let mut schedule = Self::default();
+        if let Some(fee) = file.default_fee {
+            schedule.default_fee = parse_fee(fee)?;
+        }
+        if let Some(key) = file.protocol_settle_key.as_ref() {
+            schedule.protocol_settle_key = parse_hex(key)?;
+        }
+
+        for pair in file.pairs.iter() {
+            schedule.set_pair_fee(
+                parse_hex(&pair.base_mint)?,
+                parse_hex(&pair.quote_mint)?,
+                parse_fee(pair.fee)?,
+            );
+        }
+
+        for cluster in file.clusters.iter() {
+            schedule.set_cluster_fee(
+                ClusterId::from_str(&cluster.cluster_id).unwrap(),
+                parse_fee(cluster.fee)?,
+            );
+        }
+
+        Ok(schedule)
+    }
+
+    /// Replace the default fee and protocol settle key with those the contract holds
+    pub async fn refresh_from_chain(
+        &mut self,
+        starknet_client: &StarknetClient,
+    ) -> Result<(), FeeScheduleError> {
+        let call = CallFunction {
+            contract_address: starknet_client.contract_address,
+            entry_point_selector: get_selector_from_name(PROTOCOL_FEE_FUNCTION).unwrap(),
+            calldata: vec![],
+        };
+
+        let res = starknet_client
+            .get_gateway_client()
+            .call_contract(call, BlockId::Pending)
+            .await
+            .map_err(|err| FeeScheduleError::Chain(err.to_string()))?;
+
+        let (fee, key) = parse_fee_response(&res.result)?;
+        self.default_fee = fee;
+        self.protocol_settle_key = key;
+        Ok(())
+    }
+
+    /// Override the fee charged on the given pair
+    pub fn set_pair_fee(&mut self, base_mint: BigUint, quote_mint: BigUint, fee: FixedPoint) {
+        self.pair_fees.insert((base_mint, quote_mint), fee);
+    }
+
+    /// Override the fee charged on matches managed by the given cluster
+    pub fn set_cluster_fee(&mut self, cluster_id: ClusterId, fee: FixedPoint) {
+        self.cluster_fees.insert(cluster_id, fee);
+    }
+
+    /// The public settle key of the protocol wallet
+    pub fn protocol_settle_key(&self) -> &BigUint {
+        &self.protocol_settle_key
+    }
+
+    /// Resolve the protocol fee charged on a match of the given pair, managed by the
+    /// given clusters
+    pub fn protocol_fee(
+        &self,
+        base_mint: &BigUint,
+        quote_mint: &BigUint,
+        clusters: &[ClusterId],
+    ) -> FixedPoint {
+        if let Some(fee) = self.pair_fees.get(&(base_mint.clone(), quote_mint.clone())) {
+            return *fee;
+        }
+
+        clusters
+            .iter()
+            .filter_map(|cluster| self.cluster_fees.get(cluster))
+            .min_by(|a, b| a.to_f64().total_cmp(&b.to_f64()))
+            .copied()
+            .unwrap_or(self.default_fee)
+    }
+
+    /// Check that a statement of `VALID MATCH ENCRYPTION` commits to the fee and settle key
+    /// the schedule resolves for the match
+    pub fn validate_statement(
+        &self,
+        statement: &ValidMatchEncryptionStatement,
+        base_mint: &BigUint,
+        quote_mint: &BigUint,
+        clusters: &[ClusterId],
+    ) -> Result<(), FeeScheduleError> {
+        let expected_fee = self.protocol_fee(base_mint, quote_mint, clusters);
+        if statement.protocol_fee != expected_fee {
+            return Err(FeeScheduleError::Mismatch(format!(
+                "statement protocol fee {} does not match scheduled fee {}",
+                statement.protocol_fee.to_f64(),
+                expected_fee.to_f64()
+            )));
+        }
+
+        if statement.pk_settle_protocol != biguint_to_scalar(&self.protocol_settle_key) {
+            return Err(FeeScheduleError::Mismatch(
+                "statement protocol settle key does not match the schedule".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a fee from its decimal representation, fees are a fraction of the match
+fn parse_fee(fee: f64) -> Result<FixedPoint, FeeScheduleError> {
+    if !(0.0..1.0).contains(&fee) {
+        return Err(FeeScheduleError::Parse(format!(
+            "fee {fee} must be in the range [0, 1)"
+        )));
+    }
+
+    Ok(FixedPoint::from_f64_round_down(fee))
+}
+
+/// Parse a hex encoded mint or key
+fn parse_hex(val: &str) -> Result<BigUint, FeeScheduleError> {
+    BigUint::parse_bytes(val.trim_start_matches("0x").as_bytes(), 16 /* radix */)
+        .ok_or_else(|| FeeScheduleError::Parse(format!("invalid hex value: {val}")))
+}
+
+/// Parse the contract's fee response of the form `[protocol_fee_repr, protocol_settle_key]`,
+/// where the fee is given in its fixed point representation
+fn parse_fee_response(
+    felts: &[StarknetFieldElement],
+) -> Result<(FixedPoint, BigUint), FeeScheduleError> {
+    if felts.len() != 2 {
+        return Err(FeeScheduleError::Chain(format!(
+            "expected 2 felts in fee response, got {}",
+            felts.len()
+        )));
+    }
+
+    let fee = FixedPoint::from(biguint_to_scalar(&starknet_felt_to_biguint(&felts[0])));
+    if !(0.0..1.0).contains(&fee.to_f64()) {
+        return Err(FeeScheduleError::Chain(format!(
+            "contract fee {} out of range",
+            fee.to_f64()
+        )));
+    }
+
+    Ok((fee, starknet_felt_to_biguint(&felts[1])))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use num_bigint::BigUint;
+
+    use crate::gossip::types::ClusterId;
+
+    use super::{parse_fee, FeeSchedule};
+
+    /// Build a cluster ID from its name
+    fn cluster(name: &str) -> ClusterId {
+        ClusterId::from_str(name).unwrap()
+    }
+
+    /// Build a schedule with a 2bp default, a pair override, and two cluster overrides
+    fn test_schedule() -> FeeSchedule {
+        let mut schedule = FeeSchedule::default();
+        schedule.set_pair_fee(
+            BigUint::from(1u8),
+            BigUint::from(2u8),
+            parse_fee(0.0001).unwrap(),
+        );
+        schedule.set_cluster_fee(cluster("cluster-a"), parse_fee(0.0005).unwrap());
+        schedule.set_cluster_fee(cluster("cluster-b"), parse_fee(0.0).unwrap());
+
+        schedule
+    }
+
+    /// Tests that a pair override takes precedence over cluster overrides
+    #[test]
+    fn test_pair_override() {
+        let schedule = test_schedule();
+        let fee = schedule.protocol_fee(
+            &BigUint::from(1u8),
+            &BigUint::from(2u8),
+            &[cluster("cluster-b")],
+        );
+
+        assert_eq!(fee, parse_fee(0.0001).unwrap());
+    }
+
+    /// Tests that the lowest override of the managing clusters applies, regardless of order
+    #[test]
+    fn test_cluster_override() {
+        let schedule = test_schedule();
+        let (base, quote) = (BigUint::from(3u8), BigUint::from(4u8));
+        let clusters = [cluster("cluster-a"), cluster("cluster-b")];
+        let reversed = [cluster("cluster-b"), cluster("cluster-a")];
+
+        assert_eq!(
+            schedule.protocol_fee(&base, &quote, &clusters),
+            parse_fee(0.0).unwrap()
+        );
+        assert_eq!(
+            schedule.protocol_fee(&base, &quote, &clusters),
+            schedule.protocol_fee(&base, &quote, &reversed)
+        );
+        assert_eq!(
+            schedule.protocol_fee(&base, &quote, &[cluster("cluster-c")]),
+            FeeSchedule::default().default_fee
+        );
+    }
+
+    /// Tests that fees outside of [0, 1) are rejected
+    #[test]
+    fn test_invalid_fee() {
+        assert!(parse_fee(-0.1).is_err());
+        assert!(parse_fee(1.0).is_err());
+    }
+}
@@ -1,23 +1,33 @@
 //! Groups handlers for gossiping about cluster management events
 
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use itertools::Itertools;
+use tracing::log;
+
 use crate::{
     gossip_api::{
         cluster_management::{
-            ClusterJoinMessage, ClusterManagementMessage, ReplicateRequestBody, ReplicatedMessage,
-            ValidityProofRequest,
+            ClusterJoinMessage, ClusterManagementMessage, LeaderElectedMessage,
+            ReplicaRepairRequest, ReplicaRepairResponse, ReplicateRequestBody, ReplicateShardsBody,
+            ReplicatedMessage, ValidityProofRequest, WalletShard, WalletShardRequest,
+            WalletShardResponse,
         },
         gossip::{GossipOutbound, GossipRequest, PubsubMessage},
     },
     proof_generation::jobs::ValidCommitmentsBundle,
     state::{
         wallet::{Wallet, WalletIdentifier},
+        wallet_shards::ShardAssignment,
         OrderIdentifier,
     },
 };
 
 use super::{
+    erasure::ErasureCoding,
     errors::GossipError,
     jobs::ClusterManagementJob,
+    replication::{assign_shard_holders, select_replicas},
     server::GossipProtocolExecutor,
     types::{ClusterId, PeerInfo, WrappedPeerId},
 };
@@ -38,6 +48,26 @@ impl GossipProtocolExecutor {
                 self.handle_replicate_request(req).await?;
             }
 
+            ClusterManagementJob::ReplicaRepairRequest(req) => {
+                self.handle_replica_repair_request(req).await?;
+            }
+
+            ClusterManagementJob::ReplicaRepairResponse(resp) => {
+                self.handle_replica_repair_response(resp).await?;
+            }
+
+            ClusterManagementJob::ReplicateShards(req) => {
+                self.handle_replicate_shards(req).await;
+            }
+
+            ClusterManagementJob::WalletShardRequest(req) => {
+                self.handle_wallet_shard_request(req).await?;
+            }
+
+            ClusterManagementJob::WalletShardResponse(resp) => {
+                self.handle_wallet_shard_response(resp).await?;
+            }
+
             ClusterManagementJob::AddWalletReplica { wallet_id, peer_id } => {
                 self.handle_add_replica_job(peer_id, wallet_id).await
             }
@@ -47,7 +77,15 @@ impl GossipProtocolExecutor {
             }
 
             ClusterManagementJob::UpdateValidityProof(order_id, proof) => {
-                self.handle_updated_validity_proof(order_id, proof).await;
+                self.handle_updated_validity_proof(order_id, proof).await?;
+            }
+
+            ClusterManagementJob::ElectLeader => {
+                self.handle_elect_leader_job().await?;
+            }
+
+            ClusterManagementJob::LeaderElected(cluster_id, announcement) => {
+                self.handle_leader_elected(cluster_id, announcement).await;
             }
         }
 
@@ -70,14 +108,17 @@ impl GossipProtocolExecutor {
         self.add_peer_to_cluster(message.peer_id, message.peer_info, cluster_id)
             .await?;
 
-        // Request that the peer replicate all locally replicated wallets
-        let wallets = self
-            .global_state
-            .read_wallet_index()
-            .await
-            .get_all_wallets()
-            .await;
-        self.send_replicate_request(message.peer_id, wallets)
+        // The leader re-announces itself so that the joining peer learns of the current term
+        // rather than electing a leader of its own
+        let (leader, term) = {
+            let locked_leadership = self.global_state.read_cluster_leadership().await;
+            (locked_leadership.leader(), locked_leadership.term())
+        }; // locked_leadership released
+        if leader == Some(self.global_state.local_peer_id) {
+            self.announce_leader(self.global_state.local_peer_id, term)?;
+        }
+
+        Ok(())
     }
 
     /// Add a peer to the given cluster
@@ -93,16 +134,375 @@ impl GossipProtocolExecutor {
         }
 
         // Add the peer to the known peers index
+        let peer_zone = peer_info.get_zone();
         self.global_state.add_single_peer(peer_id, peer_info).await;
 
-        // Request that the peer replicate all locally replicated wallets
+        // Only the leader replicates wallets to the new peer, followers would send the same
+        // wallets redundantly
+        if !self.global_state.is_local_cluster_leader().await {
+            return Ok(());
+        }
+
+        let wallets = self
+            .global_state
+            .read_wallet_index()
+            .await
+            .get_all_wallets()
+            .await;
+
+        // Without a replication factor the peer replicates every wallet, unless the cluster
+        // replicates wallets as erasure-coded shards. Otherwise the peer replicates the wallets
+        // that are under-replicated, or that have no replica in the peer's zone
+        let wallets = match self.config.replication_factor {
+            None if self.config.erasure_coding.is_some() => Vec::new(),
+            None => wallets,
+            Some(replication_factor) => {
+                let zones = self.cluster_peer_zones().await;
+                wallets
+                    .into_iter()
+                    .filter(|wallet| {
+                        let replicas = &wallet.metadata.replicas;
+                        let zone_uncovered = peer_zone.as_ref().map_or(false, |zone| {
+                            !replicas.iter().any(|replica| {
+                                zones.get(replica).and_then(|z| z.as_ref()) == Some(zone)
+                            })
+                        });
+
+                        replicas.len() < replication_factor || zone_uncovered
+                    })
+                    .collect()
+            }
+        };
+        self.send_replicate_request(peer_id, wallets)?;
+
+        // Re-encode the wallets' shards to place one on the new peer
+        self.distribute_wallet_shards().await
+    }
+
+    /// Re-replicate any wallets that have fallen below the replication factor, e.g. after
+    /// a cluster peer has expired
+    ///
+    /// Replacement replicas are chosen from the cluster peers that do not already hold the
+    /// wallet, preferring peers in zones that the wallet's remaining replicas do not cover
+    pub(super) async fn rereplicate_wallets(&self) -> Result<(), GossipError> {
+        // Without a replication factor every cluster peer already replicates every wallet
+        let replication_factor = match self.config.replication_factor {
+            Some(factor) => factor,
+            None => return Ok(()),
+        };
+
+        if !self.global_state.is_local_cluster_leader().await {
+            return Ok(());
+        }
+
+        let zones = self.cluster_peer_zones().await;
         let wallets = self
             .global_state
             .read_wallet_index()
             .await
             .get_all_wallets()
             .await;
-        self.send_replicate_request(peer_id, wallets)
+
+        let mut requests: HashMap<WrappedPeerId, Vec<Wallet>> = HashMap::new();
+        for wallet in wallets.into_iter() {
+            let replicas = &wallet.metadata.replicas;
+            if replicas.len() >= replication_factor {
+                continue;
+            }
+
+            let covered_zones = replicas
+                .iter()
+                .filter_map(|replica| zones.get(replica).cloned().flatten())
+                .collect::<HashSet<_>>();
+            let candidates = zones
+                .iter()
+                .filter(|(peer_id, _)| !replicas.contains(peer_id))
+                .map(|(peer_id, zone)| (*peer_id, zone.clone()))
+                .collect_vec();
+
+            let new_replicas = select_replicas(
+                &candidates,
+                &covered_zones,
+                replication_factor - replicas.len(),
+            );
+            if !new_replicas.is_empty() {
+                log::info!(
+                    "re-replicating wallet {} to {} peers",
+                    wallet.wallet_id,
+                    new_replicas.len()
+                );
+            }
+
+            for peer_id in new_replicas.into_iter() {
+                requests.entry(peer_id).or_default().push(wallet.clone());
+            }
+        }
+
+        for (peer_id, wallets) in requests.into_iter() {
+            self.send_replicate_request(peer_id, wallets)?;
+        }
+
+        Ok(())
+    }
+
+    // -----------------------
+    // | Erasure-Coded Shards |
+    // -----------------------
+
+    /// Encode the locally held wallets into shards and distribute them across the cluster
+    ///
+    /// A wallet is re-encoded if it has not yet been sharded, if it has changed since it was
+    /// last sharded, or if one of its shard holders has left the cluster. Only the leader
+    /// encodes wallets, so that the cluster agrees on a single assignment of each wallet's shards
+    pub(super) async fn distribute_wallet_shards(&self) -> Result<(), GossipError> {
+        let code = match self.config.erasure_coding {
+            Some(code) => code,
+            None => return Ok(()),
+        };
+
+        if !self.global_state.is_local_cluster_leader().await {
+            return Ok(());
+        }
+
+        let zones = self.cluster_peer_zones().await;
+        let candidates = zones
+            .iter()
+            .map(|(peer_id, zone)| (*peer_id, zone.clone()))
+            .collect_vec();
+        let wallets = self
+            .global_state
+            .read_wallet_index()
+            .await
+            .get_all_wallets()
+            .await;
+
+        let mut requests: HashMap<WrappedPeerId, Vec<WalletShard>> = HashMap::new();
+        {
+            let mut locked_shards = self.global_state.write_wallet_shards().await;
+            for wallet in wallets.into_iter() {
+                let up_to_date =
+                    locked_shards
+                        .get_assignment(&wallet.wallet_id)
+                        .map_or(false, |assignment| {
+                            assignment.version == wallet.metadata.version
+                                && assignment
+                                    .holders
+                                    .iter()
+                                    .all(|holder| zones.contains_key(holder))
+                        });
+                if up_to_date {
+                    continue;
+                }
+
+                let holders = assign_shard_holders(&candidates, code.total_shards);
+                if holders.is_empty() {
+                    continue;
+                }
+
+                let replicas = wallet.metadata.replicas.iter().copied().collect_vec();
+                for shard in code.encode(&wallet, holders.clone(), replicas).into_iter() {
+                    requests
+                        .entry(shard.holders[shard.index])
+                        .or_default()
+                        .push(shard);
+                }
+
+                locked_shards.set_assignment(
+                    wallet.wallet_id,
+                    ShardAssignment {
+                        version: wallet.metadata.version,
+                        holders,
+                    },
+                );
+            }
+        } // locked_shards released
+
+        // The local peer stores its own shards directly
+        let local_peer_id = self.global_state.local_peer_id;
+        for (peer_id, shards) in requests.into_iter() {
+            if peer_id == local_peer_id {
+                self.global_state
+                    .write_wallet_shards()
+                    .await
+                    .add_shards(shards);
+                continue;
+            }
+
+            self.network_channel
+                .send(GossipOutbound::Request {
+                    peer_id,
+                    message: GossipRequest::ReplicateShards(ReplicateShardsBody { shards }),
+                })
+                .map_err(|err| GossipError::SendMessage(err.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Reassemble the wallets that no remaining cluster peer holds in full from their shards,
+    /// e.g. after the last full replica of a wallet has expired
+    ///
+    /// Of a wallet's shard holders still in the cluster, the one with the lowest peer ID
+    /// reassembles the wallet, so that the cluster does not reassemble it redundantly
+    pub(super) async fn reassemble_orphaned_wallets(&self) -> Result<(), GossipError> {
+        let cluster_peers = self
+            .cluster_peer_zones()
+            .await
+            .into_keys()
+            .collect::<HashSet<_>>();
+        let local_peer_id = self.global_state.local_peer_id;
+        let sharded_wallets = self
+            .global_state
+            .read_wallet_shards()
+            .await
+            .get_sharded_wallets();
+
+        for sharded_wallet in sharded_wallets.into_iter() {
+            let wallet_id = sharded_wallet.wallet_id;
+            let orphaned = !sharded_wallet
+                .replicas
+                .iter()
+                .any(|replica| cluster_peers.contains(replica));
+            let held_locally = self
+                .global_state
+                .read_wallet_index()
+                .await
+                .get_wallet_version(&wallet_id)
+                .await
+                .is_some();
+            if !orphaned || held_locally {
+                continue;
+            }
+
+            let live_holders = sharded_wallet
+                .holders
+                .into_iter()
+                .filter(|holder| cluster_peers.contains(holder))
+                .collect::<BTreeSet<_>>();
+            if live_holders.iter().next() != Some(&local_peer_id)
+                || !self
+                    .global_state
+                    .write_wallet_shards()
+                    .await
+                    .begin_reassembly(&wallet_id)
+            {
+                continue;
+            }
+
+            log::info!(
+                "reassembling wallet {wallet_id} from the shards of {} peers",
+                live_holders.len()
+            );
+            for holder in live_holders
+                .into_iter()
+                .filter(|peer| *peer != local_peer_id)
+            {
+                self.network_channel
+                    .send(GossipOutbound::Request {
+                        peer_id: holder,
+                        message: GossipRequest::WalletShardRequest(WalletShardRequest {
+                            wallet_id,
+                            sender: local_peer_id,
+                        }),
+                    })
+                    .map_err(|err| GossipError::SendMessage(err.to_string()))?;
+            }
+
+            // The local peer may itself hold enough shards, e.g. in a cluster smaller than the code
+            self.try_finish_reassembly(wallet_id, Vec::new()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a request from a peer to store a set of wallet shards
+    async fn handle_replicate_shards(&self, req: ReplicateShardsBody) {
+        self.global_state
+            .write_wallet_shards()
+            .await
+            .add_shards(req.shards);
+    }
+
+    /// Handles a request from a cluster peer reassembling a wallet for the local peer's shards
+    async fn handle_wallet_shard_request(
+        &self,
+        req: WalletShardRequest,
+    ) -> Result<(), GossipError> {
+        let shards = self
+            .global_state
+            .read_wallet_shards()
+            .await
+            .get_shards(&req.wallet_id);
+        if shards.is_empty() {
+            return Ok(());
+        }
+
+        self.network_channel
+            .send(GossipOutbound::Request {
+                peer_id: req.sender,
+                message: GossipRequest::WalletShardResponse(WalletShardResponse {
+                    wallet_id: req.wallet_id,
+                    shards,
+                }),
+            })
+            .map_err(|err| GossipError::SendMessage(err.to_string()))
+    }
+
+    /// Handles shards sent by a cluster peer for a wallet the local peer is reassembling
+    async fn handle_wallet_shard_response(
+        &self,
+        resp: WalletShardResponse,
+    ) -> Result<(), GossipError> {
+        self.try_finish_reassembly(resp.wallet_id, resp.shards)
+            .await
+    }
+
+    /// Add shards to a wallet's reassembly, and replicate the wallet locally once enough
+    /// shards have been collected to decode it
+    async fn try_finish_reassembly(
+        &self,
+        wallet_id: WalletIdentifier,
+        shards: Vec<WalletShard>,
+    ) -> Result<(), GossipError> {
+        let wallet = {
+            let mut locked_shards = self.global_state.write_wallet_shards().await;
+            let wallet = locked_shards
+                .add_reassembly_shards(&wallet_id, shards)
+                .and_then(|collected| ErasureCoding::decode(&collected));
+            if wallet.is_some() {
+                locked_shards.finish_reassembly(&wallet_id);
+            }
+
+            wallet
+        }; // locked_shards released
+
+        match wallet {
+            Some(wallet) => {
+                log::info!("reassembled wallet {wallet_id} from its shards");
+                self.handle_replicate_request(ReplicateRequestBody {
+                    wallets: vec![wallet],
+                })
+                .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Get the zone of each known peer in the local cluster
+    async fn cluster_peer_zones(&self) -> HashMap<WrappedPeerId, Option<String>> {
+        let locked_peer_index = self.global_state.read_peer_index().await;
+        let mut zones = HashMap::new();
+        for peer_id in locked_peer_index
+            .get_all_cluster_peers(&self.global_state.local_cluster_id)
+            .await
+            .into_iter()
+        {
+            if let Some(info) = locked_peer_index.read_peer(&peer_id).await {
+                zones.insert(peer_id, info.get_zone());
+            }
+        }
+
+        zones
     }
 
     /// Send a request to the given peer to replicate a set of wallets
@@ -145,26 +545,39 @@ impl GossipProtocolExecutor {
         };
         self.network_channel
             .send(GossipOutbound::Pubsub {
-                topic: topic.clone(),
+                topic,
                 message: replicated_message,
             })
             .map_err(|err| GossipError::SendMessage(err.to_string()))?;
 
         // Broadcast a message requesting proofs for all new orders
+        let new_orders = req
+            .wallets
+            .iter()
+            .flat_map(|wallet| wallet.orders.keys().cloned())
+            .collect();
+        self.request_missing_validity_proofs(new_orders).await
+    }
+
+    /// Broadcast a request to the cluster for proofs of `VALID COMMITMENTS` for any of the
+    /// given orders that the local peer does not have a proof for
+    async fn request_missing_validity_proofs(
+        &self,
+        order_ids: Vec<OrderIdentifier>,
+    ) -> Result<(), GossipError> {
         let mut orders_needing_proofs = Vec::new();
         {
             let locked_order_state = self.global_state.read_order_book().await;
-            for wallet in req.wallets.iter() {
-                for order_id in wallet.orders.keys() {
-                    if !locked_order_state.has_validity_proof(order_id).await {
-                        orders_needing_proofs.push(*order_id);
-                    }
+            for order_id in order_ids.into_iter() {
+                if !locked_order_state.has_validity_proof(&order_id).await {
+                    orders_needing_proofs.push(order_id);
                 }
             }
         } // locked_order_state released
 
+        let cluster_id = self.global_state.local_cluster_id.clone();
         let proof_request = PubsubMessage::ClusterManagement {
-            cluster_id: self.global_state.local_cluster_id.clone(),
+            cluster_id: cluster_id.clone(),
             message: ClusterManagementMessage::RequestOrderValidityProof(ValidityProofRequest {
                 order_ids: orders_needing_proofs,
                 sender: self.global_state.local_peer_id,
@@ -172,12 +585,76 @@ impl GossipProtocolExecutor {
         };
         self.network_channel
             .send(GossipOutbound::Pubsub {
-                topic,
+                topic: cluster_id.get_management_topic(),
                 message: proof_request,
             })
-            .map_err(|err| GossipError::SendMessage(err.to_string()))?;
+            .map_err(|err| GossipError::SendMessage(err.to_string()))
+    }
 
-        Ok(())
+    /// Handles a request from a cluster peer for the deltas needed to repair its replica
+    /// of a wallet
+    ///
+    /// If the local delta log no longer reaches back to the peer's version, the whole
+    /// wallet is re-replicated to the peer instead
+    async fn handle_replica_repair_request(
+        &self,
+        req: ReplicaRepairRequest,
+    ) -> Result<(), GossipError> {
+        let locked_wallet_index = self.global_state.read_wallet_index().await;
+        match locked_wallet_index
+            .get_deltas_since(&req.wallet_id, req.since_version)
+            .await
+        {
+            Some(deltas) if deltas.is_empty() => Ok(()),
+            Some(deltas) => self
+                .network_channel
+                .send(GossipOutbound::Request {
+                    peer_id: req.sender,
+                    message: GossipRequest::ReplicaRepairResponse(ReplicaRepairResponse {
+                        wallet_id: req.wallet_id,
+                        deltas,
+                    }),
+                })
+                .map_err(|err| GossipError::SendMessage(err.to_string())),
+            None => {
+                let wallets = locked_wallet_index
+                    .get_wallet(&req.wallet_id)
+                    .await
+                    .into_iter()
+                    .collect();
+                self.send_replicate_request(req.sender, wallets)
+            }
+        }
+    }
+
+    /// Handles a set of deltas sent by a cluster peer to repair the local replica of a wallet
+    async fn handle_replica_repair_response(
+        &self,
+        resp: ReplicaRepairResponse,
+    ) -> Result<(), GossipError> {
+        // The repair waits behind any other task mutating the wallet
+        let res = self
+            .global_state
+            .wallet_manager()
+            .run_task(
+                &resp.wallet_id,
+                self.global_state
+                    .apply_wallet_deltas(&resp.wallet_id, resp.deltas),
+            )
+            .await;
+        match res {
+            Ok(new_orders) if new_orders.is_empty() => Ok(()),
+            Ok(new_orders) => self.request_missing_validity_proofs(new_orders).await,
+            Err(err) => {
+                // The replica is left untouched, the next heartbeat will request a fresh
+                // set of deltas from the replica's current version
+                log::warn!(
+                    "error repairing replica of wallet {}: {err}",
+                    resp.wallet_id
+                );
+                Ok(())
+            }
+        }
     }
 
     /// Handles an incoming job to update a wallet's replicas with a newly added peer
@@ -221,14 +698,80 @@ impl GossipProtocolExecutor {
         Ok(())
     }
 
+    /// Handles a job to elect a cluster leader, skipped if a leader is already known
+    async fn handle_elect_leader_job(&self) -> Result<(), GossipError> {
+        if self
+            .global_state
+            .read_cluster_leadership()
+            .await
+            .leader()
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        self.run_leader_election().await
+    }
+
+    /// Elect a new cluster leader from the local view of the cluster and announce it
+    pub(super) async fn run_leader_election(&self) -> Result<(), GossipError> {
+        let (leader, term) = self.global_state.elect_cluster_leader().await;
+        log::info!("elected cluster leader {leader} for term {term}");
+
+        self.announce_leader(leader, term)
+    }
+
+    /// Publish a leadership announcement to the cluster
+    fn announce_leader(&self, leader: WrappedPeerId, term: u64) -> Result<(), GossipError> {
+        let cluster_id = self.global_state.local_cluster_id.clone();
+        self.network_channel
+            .send(GossipOutbound::Pubsub {
+                topic: cluster_id.get_management_topic(),
+                message: PubsubMessage::ClusterManagement {
+                    cluster_id,
+                    message: ClusterManagementMessage::LeaderElected(LeaderElectedMessage {
+                        leader,
+                        term,
+                    }),
+                },
+            })
+            .map_err(|err| GossipError::SendMessage(err.to_string()))
+    }
+
+    /// Handles a leadership announcement from a cluster peer
+    async fn handle_leader_elected(
+        &self,
+        cluster_id: ClusterId,
+        announcement: LeaderElectedMessage,
+    ) {
+        // Ignore announcements for a different cluster
+        if cluster_id != self.global_state.local_cluster_id {
+            return;
+        }
+
+        let LeaderElectedMessage { leader, term } = announcement;
+        if self
+            .global_state
+            .write_cluster_leadership()
+            .await
+            .observe_announcement(leader, term)
+        {
+            log::info!("cluster leader is now {leader} for term {term}");
+        }
+    }
+
     /// Handle a message from a cluster peer that sends a proof of `VALID COMMITMENTS` for an order
+    ///
+    /// Proofs are shared by the cluster leader, so the local peer also requests the witness
+    /// that the leader proved against, which it needs to link commitments in later proofs
     async fn handle_updated_validity_proof(
         &self,
         order_id: OrderIdentifier,
         proof: ValidCommitmentsBundle,
-    ) {
+    ) -> Result<(), GossipError> {
         self.global_state
             .add_order_validity_proof(&order_id, proof)
-            .await
+            .await;
+        self.request_order_witness(order_id)
     }
 }
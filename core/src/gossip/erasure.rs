@@ -0,0 +1,229 @@
+//! Groups logic for erasure coding wallets into shards for replication
+//!
+//! Rather than shipping every wallet in full to each cluster peer, a cluster may be configured
+//! to encode each wallet into `n` Reed-Solomon shards, one per peer, any `k` of which reassemble
+//! the wallet. Each peer then stores roughly `1/k` of a wallet, and the cluster tolerates the loss
+//! of `n - k` shard holders. Should every peer holding a wallet in full fail, one of the remaining
+//! shard holders reassembles the wallet from its peers' shards and replicates it anew
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use crate::{gossip_api::cluster_management::WalletShard, state::wallet::Wallet};
+
+use super::types::WrappedPeerId;
+
+/// The maximum number of shards a wallet may be encoded into, bounded by the field size
+const MAX_TOTAL_SHARDS: usize = 256;
+
+/// The parameters of a k-of-n erasure code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErasureCoding {
+    /// The number of shards needed to reassemble a wallet
+    pub data_shards: usize,
+    /// The number of shards a wallet is encoded into
+    pub total_shards: usize,
+}
+
+impl ErasureCoding {
+    /// Constructor, validates that the code has at least one data and one parity shard
+    pub fn new(data_shards: usize, total_shards: usize) -> Result<Self, String> {
+        if data_shards == 0 || total_shards <= data_shards || total_shards > MAX_TOTAL_SHARDS {
+            return Err(format!(
+                "erasure coding requires 0 < k < n <= {}, got {}:{}",
+                MAX_TOTAL_SHARDS, data_shards, total_shards
+            ));
+        }
+
+        Ok(Self {
+            data_shards,
+            total_shards,
+        })
+    }
+
+    /// The number of parity shards in the code
+    fn parity_shards(&self) -> usize {
+        self.total_shards - self.data_shards
+    }
+
+    /// Encode a wallet into `total_shards` shards, assigning shard `i` to `holders[i]`
+    pub fn encode(
+        &self,
+        wallet: &Wallet,
+        holders: Vec<WrappedPeerId>,
+        replicas: Vec<WrappedPeerId>,
+    ) -> Vec<WalletShard> {
+        assert_eq!(holders.len(), self.total_shards, "one holder per shard");
+
+        // Split the serialized wallet into equal length data shards, zero padding the last
+        let wallet_bytes = serde_json::to_vec(wallet).unwrap();
+        let shard_len = ((wallet_bytes.len() + self.data_shards - 1) / self.data_shards).max(1);
+        let mut shards = vec![vec![0u8; shard_len]; self.total_shards];
+        for (shard, chunk) in shards.iter_mut().zip(wallet_bytes.chunks(shard_len)) {
+            shard[..chunk.len()].copy_from_slice(chunk);
+        }
+
+        ReedSolomon::new(self.data_shards, self.parity_shards())
+            .unwrap()
+            .encode(&mut shards)
+            .unwrap();
+
+        shards
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| WalletShard {
+                wallet_id: wallet.wallet_id,
+                version: wallet.metadata.version,
+                index,
+                data_shards: self.data_shards,
+                total_shards: self.total_shards,
+                wallet_len: wallet_bytes.len(),
+                holders: holders.clone(),
+                replicas: replicas.clone(),
+                data,
+            })
+            .collect()
+    }
+
+    /// Reassemble a wallet from its shards, returns `None` if fewer than `data_shards`
+    /// consistent shards are given
+    ///
+    /// The first shard fixes the wallet version and code, shards that disagree with it
+    /// are ignored
+    pub fn decode(shards: &[WalletShard]) -> Option<Wallet> {
+        let first = shards.first()?;
+        let code = Self::new(first.data_shards, first.total_shards).ok()?;
+
+        let mut slots: Vec<Option<Vec<u8>>> = vec![None; code.total_shards];
+        for shard in shards.iter().filter(|shard| {
+            shard.version == first.version
+                && shard.data_shards == first.data_shards
+                && shard.total_shards == first.total_shards
+                && shard.wallet_len == first.wallet_len
+                && shard.data.len() == first.data.len()
+                && shard.index < first.total_shards
+        }) {
+            slots[shard.index] = Some(shard.data.clone());
+        }
+
+        ReedSolomon::new(code.data_shards, code.parity_shards())
+            .ok()?
+            .reconstruct_data(&mut slots)
+            .ok()?;
+
+        let wallet_bytes = slots
+            .into_iter()
+            .take(code.data_shards)
+            .flat_map(|slot| slot.unwrap())
+            .take(first.wallet_len)
+            .collect::<Vec<u8>>();
+        serde_json::from_slice(&wallet_bytes).ok()
+    }
+}
+
+impl Display for ErasureCoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}:{}", self.data_shards, self.total_shards)
+    }
+}
+
+impl FromStr for ErasureCoding {
+    type Err = String;
+
+    /// Parse a code of the form `k:n`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (data_shards, total_shards) = s
+            .split_once(':')
+            .ok_or_else(|| format!("erasure coding must be of the form k:n, got {}", s))?;
+        let parse = |shards: &str| {
+            shards
+                .parse::<usize>()
+                .map_err(|err| format!("invalid shard count {}: {}", shards, err))
+        };
+
+        Self::new(parse(data_shards)?, parse(total_shards)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        str::FromStr,
+        sync::atomic::AtomicU32,
+    };
+
+    use circuits::types::keychain::KeyChain;
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
+    use rand_core::OsRng;
+    use uuid::Uuid;
+
+    use crate::{
+        gossip::types::WrappedPeerId,
+        state::wallet::{PrivateKeyChain, Wallet, WalletMetadata},
+    };
+
+    use super::ErasureCoding;
+
+    /// Build an empty wallet with random keys
+    fn empty_wallet() -> Wallet {
+        let mut rng = OsRng {};
+        Wallet {
+            wallet_id: Uuid::new_v4(),
+            orders: HashMap::new(),
+            balances: HashMap::new(),
+            fees: Vec::new(),
+            public_keys: KeyChain {
+                pk_root: Scalar::random(&mut rng),
+                pk_match: Scalar::random(&mut rng),
+                pk_settle: Scalar::random(&mut rng),
+                pk_view: Scalar::random(&mut rng),
+            },
+            secret_keys: PrivateKeyChain {
+                sk_root: None,
+                sk_match: Scalar::random(&mut rng),
+                sk_settle: Scalar::random(&mut rng),
+                sk_view: Scalar::random(&mut rng),
+            },
+            randomness: BigUint::from(0u8),
+            metadata: WalletMetadata {
+                replicas: HashSet::new(),
+                version: 3,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: AtomicU32::new(0),
+        }
+    }
+
+    /// Tests that a wallet survives the loss of any `n - k` shards, and no more
+    #[test]
+    fn test_reassemble_wallet() {
+        let code = ErasureCoding::from_str("3:5").unwrap();
+        assert!(ErasureCoding::from_str("3:3").is_err());
+        assert!(ErasureCoding::from_str("0:2").is_err());
+
+        let wallet = empty_wallet();
+        let holders = (0..5).map(|_| WrappedPeerId::random()).collect::<Vec<_>>();
+        let shards = code.encode(&wallet, holders, vec![] /* replicas */);
+        assert_eq!(shards.len(), 5);
+
+        // Any three shards reassemble the wallet
+        let surviving = vec![shards[4].clone(), shards[0].clone(), shards[2].clone()];
+        let reassembled = ErasureCoding::decode(&surviving).unwrap();
+        assert_eq!(reassembled.wallet_id, wallet.wallet_id);
+        assert_eq!(reassembled.metadata.version, 3);
+        assert_eq!(
+            serde_json::to_vec(&reassembled).unwrap(),
+            serde_json::to_vec(&wallet).unwrap()
+        );
+
+        // Two shards do not
+        assert!(ErasureCoding::decode(&shards[3..]).is_none());
+    }
+}
@@ -2,11 +2,16 @@
 
 use std::fmt;
 
+use crate::error::{ErrorCode, ErrorCoded};
+
 /// Defines an error for Gossip operation
 #[derive(Clone, Debug)]
 pub enum GossipError {
     /// An error resulting from a cancellation signal
     Cancelled(String),
+    /// A heartbeat was rejected for a bad signature, stale timestamp, or replayed
+    /// sequence number
+    HeartbeatRejected(String),
     /// An error occurred looking up a critical state element
     MissingState(String),
     /// An error parsing a gossip message
@@ -23,8 +28,25 @@ pub enum GossipError {
     ValidCommitmentVerification(String),
 }
 
+impl std::error::Error for GossipError {}
 impl fmt::Display for GossipError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+impl ErrorCoded for GossipError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            GossipError::Cancelled(_) => ErrorCode::Cancelled,
+            GossipError::HeartbeatRejected(_) => ErrorCode::Unauthorized,
+            GossipError::MissingState(_) => ErrorCode::NotFound,
+            GossipError::Parse(_) => ErrorCode::InvalidMessage,
+            GossipError::ServerSetup(_) => ErrorCode::Setup,
+            GossipError::SendMessage(_) => ErrorCode::Internal,
+            GossipError::StarknetRequest(_) => ErrorCode::Chain,
+            GossipError::TimerFailed(_) => ErrorCode::Internal,
+            GossipError::ValidCommitmentVerification(_) => ErrorCode::Proof,
+        }
+    }
+}
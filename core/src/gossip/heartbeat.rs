@@ -8,16 +8,21 @@ use std::{
 };
 
 use futures::executor::block_on;
-use tokio::sync::mpsc::UnboundedSender as TokioSender;
+use semver::Version;
 use tracing::log;
 
 use crate::{
+    clock::SharedClock,
     gossip_api::{
+        cluster_management::ReplicaRepairRequest,
         gossip::{GossipOutbound, GossipRequest, ManagerControlDirective},
         heartbeat::HeartbeatMessage,
         orderbook_management::OrderInfoRequest,
     },
+    job_queue::JobQueueSender,
     state::{
+        feature_flags::FeatureFlag,
+        versions::{local_version, minor_versions_behind},
         wallet::{WalletIdentifier, WalletMetadata},
         OrderIdentifier, RelayerState,
     },
@@ -27,7 +32,7 @@ use super::{
     errors::GossipError,
     jobs::GossipServerJob,
     server::GossipProtocolExecutor,
-    types::{ClusterId, PeerInfo, WrappedPeerId},
+    types::{ClusterId, PeerInfo, PeerLiveness, WrappedPeerId},
 };
 
 /**
@@ -51,6 +56,16 @@ pub(super) const CLUSTER_HEARTBEAT_FAILURE_MS: u64 = 7_000; // 7 seconds
 pub(super) const EXPIRY_INVISIBILITY_WINDOW_MS: u64 = 30_000; // 30 seconds
 /// The size of the peer expiry cache to keep around
 pub(super) const EXPIRY_CACHE_SIZE: usize = 100;
+/// The number of heartbeat intervals a peer may leave a heartbeat unanswered before
+/// the heartbeat counts as missed against the peer's reputation, and the peer becomes suspect
+pub(super) const MISSED_HEARTBEAT_MULTIPLE: u64 = 2;
+/// The maximum distance between a heartbeat's timestamp and the local clock for the
+/// heartbeat to be accepted
+///
+/// This is kept shorter than the expiry invisibility window, so that a heartbeat captured
+/// before a peer's expiry is stale by the time the peer may be indexed again, with a fresh
+/// replay window
+pub(super) const MAX_HEARTBEAT_CLOCK_SKEW_MS: u64 = 15_000; // 15 seconds
 
 // -----------
 // | Helpers |
@@ -64,6 +79,13 @@ fn get_current_time_seconds() -> u64 {
         .as_secs()
 }
 
+/// Whether a heartbeat timestamp, in unix milliseconds, is within the accepted skew of
+/// the given clock
+fn timestamp_within_skew(clock: &SharedClock, timestamp: u64) -> bool {
+    let now = clock.unix_time().as_millis() as u64;
+    now.abs_diff(timestamp) <= MAX_HEARTBEAT_CLOCK_SKEW_MS
+}
+
 /// Heartbeat implementation of the protocol executor
 impl GossipProtocolExecutor {
     /// Records a successful heartbeat
@@ -73,6 +95,45 @@ impl GossipProtocolExecutor {
             .await
             .record_heartbeat(&peer_id)
             .await;
+        self.global_state
+            .write_peer_reputation()
+            .await
+            .record_heartbeat(peer_id);
+    }
+
+    /// Check the stamp on a heartbeat from a peer; the signature must be the peer's, the
+    /// timestamp recent, and the sequence number unseen
+    pub(super) async fn verify_heartbeat(
+        &self,
+        peer_id: WrappedPeerId,
+        message: &HeartbeatMessage,
+    ) -> Result<(), GossipError> {
+        if !message.verify_signature(&peer_id) {
+            return Err(GossipError::HeartbeatRejected(format!(
+                "invalid signature from {peer_id}"
+            )));
+        }
+
+        if !timestamp_within_skew(&self.config.clock, message.timestamp) {
+            return Err(GossipError::HeartbeatRejected(format!(
+                "stale timestamp from {peer_id}"
+            )));
+        }
+
+        if !self
+            .global_state
+            .read_peer_index()
+            .await
+            .accept_heartbeat_sequence(&peer_id, message.sequence)
+            .await
+        {
+            return Err(GossipError::HeartbeatRejected(format!(
+                "replayed sequence number {} from {peer_id}",
+                message.sequence
+            )));
+        }
+
+        Ok(())
     }
 
     /// Sync the replication state when a heartbeat is received
@@ -80,9 +141,11 @@ impl GossipProtocolExecutor {
     ///  For each wallet that the local relayer manages:
     ///      1. Check if the peer sent a replication list for this wallet
     ///      2. Add any new peers from that list to the local state
+    ///      3. Request the missing deltas if the peer holds a newer version of the wallet
     /// TODO: There is probably a cleaner way to do this
     pub(super) async fn merge_state_from_message(
         &self,
+        peer_id: WrappedPeerId,
         message: HeartbeatMessage,
     ) -> Result<(), GossipError> {
         // Peer info is deserialized as a mapping keyed with strings instead of WrappedPeerId
@@ -94,12 +157,47 @@ impl GossipProtocolExecutor {
             incoming_peer_info.insert(peer_id, peer_info);
         }
 
+        // Record the version the peer runs before merging its state
+        self.record_peer_version(peer_id, &message.version).await;
+
         // Merge in state primitives from the heartbeat message
         self.merge_peer_index(&incoming_peer_info).await?;
-        self.merge_wallets(message.managed_wallets).await;
+        self.merge_wallets(peer_id, message.managed_wallets).await?;
         self.merge_order_book(message.orders).await
     }
 
+    /// Records the version a peer reported in its heartbeat, and warns if the local
+    /// relayer has fallen too far behind the version most of its cluster runs
+    async fn record_peer_version(&self, peer_id: WrappedPeerId, version: &str) {
+        let version = match Version::parse(version) {
+            Ok(version) => version,
+            Err(_) => return,
+        };
+
+        let cluster_peers = self
+            .global_state
+            .read_peer_index()
+            .await
+            .get_all_cluster_peers(&self.global_state.local_cluster_id)
+            .await;
+
+        let mut locked_versions = self.global_state.write_peer_versions().await;
+        locked_versions.record_version(peer_id, version);
+
+        let majority = match locked_versions.majority_version(&cluster_peers) {
+            Some(version) => version,
+            None => return,
+        };
+        let local = local_version();
+        if minor_versions_behind(&local, &majority) > self.config.max_version_lag
+            && locked_versions.mark_warned(&majority)
+        {
+            log::warn!(
+                "local relayer version {local} is behind the cluster majority version {majority}, consider upgrading"
+            );
+        }
+    }
+
     /// Merges the list of known peers from an incoming heartbeat with the local
     /// peer index
     async fn merge_peer_index(
@@ -132,25 +230,65 @@ impl GossipProtocolExecutor {
     /// stored wallet information
     ///
     /// In specific, the local peer must update its replicas list for any wallet it manages
-    async fn merge_wallets(&self, peer_wallets: HashMap<WalletIdentifier, WalletMetadata>) {
-        let locked_wallets = self.global_state.read_wallet_index().await;
-        let locked_peers = self.global_state.read_peer_index().await;
-        for (wallet_id, mut wallet_info) in peer_wallets.into_iter() {
-            // Filter out any replicas that we don't have peer info for
-            // This may happen for a multitude of reasons; one reason is that the local node
-            // has expired a peer, but the remote node has not
-            //
-            // In this case, we leave the expired peer in the invisibility window, waiting for
-            // the expired peer to expire on all other cluster peers
-            wallet_info
-                .replicas
-                .retain(|replica| locked_peers.contains_peer(replica));
-
-            // Merge with the local copy of the wallet
-            locked_wallets
-                .merge_metadata(&wallet_id, &wallet_info)
+    async fn merge_wallets(
+        &self,
+        peer_id: WrappedPeerId,
+        peer_wallets: HashMap<WalletIdentifier, WalletMetadata>,
+    ) -> Result<(), GossipError> {
+        let mut stale_replicas = Vec::new();
+        {
+            let locked_wallets = self.global_state.read_wallet_index().await;
+            let locked_peers = self.global_state.read_peer_index().await;
+
+            // Only cluster peers hold the wallet deltas needed to repair a replica
+            let is_cluster_peer = locked_peers
+                .get_peer_info(&peer_id)
                 .await
+                .map_or(false, |info| {
+                    info.get_cluster_id() == self.global_state.local_cluster_id
+                });
+
+            for (wallet_id, mut wallet_info) in peer_wallets.into_iter() {
+                // Filter out any replicas that we don't have peer info for
+                // This may happen for a multitude of reasons; one reason is that the local node
+                // has expired a peer, but the remote node has not
+                //
+                // In this case, we leave the expired peer in the invisibility window, waiting for
+                // the expired peer to expire on all other cluster peers
+                wallet_info
+                    .replicas
+                    .retain(|replica| locked_peers.contains_peer(replica));
+
+                // Merge with the local copy of the wallet
+                locked_wallets
+                    .merge_metadata(&wallet_id, &wallet_info)
+                    .await;
+
+                // Check whether the local replica is behind the peer's
+                if is_cluster_peer
+                    && let Some(local_version) = locked_wallets.get_wallet_version(&wallet_id).await
+                    && local_version < wallet_info.version
+                {
+                    stale_replicas.push((wallet_id, local_version));
+                }
+            }
+        } // locked_wallets, locked_peers released
+
+        // Request the missing deltas for each stale replica from the peer
+        for (wallet_id, since_version) in stale_replicas.into_iter() {
+            self.network_channel
+                .send(GossipOutbound::Request {
+                    peer_id,
+                    message: GossipRequest::ReplicaRepair(ReplicaRepairRequest {
+                        wallet_id,
+                        since_version,
+                        sender: self.global_state.local_peer_id,
+                    }),
+                })
+                .map_err(|err| GossipError::SendMessage(err.to_string()))?;
         }
+
+        Ok(())
     }
 
     /// Merges order book information from the incoming heartbeat request, requests order information
@@ -284,7 +422,12 @@ impl GossipProtocolExecutor {
         Ok(())
     }
 
-    /// Expires peers that have timed out due to consecutive failed heartbeats
+    /// Moves a peer through the liveness states by the time since its last successful
+    /// heartbeat, expiring the peer once it is dead
+    ///
+    /// A peer becomes suspect once it leaves a heartbeat unanswered for too long, and dead
+    /// once it times out or its reputation falls too low. When the last peer of a remote
+    /// cluster dies, the cluster's orders are pruned from the local book
     async fn maybe_expire_peer(&self, peer_id: WrappedPeerId) {
         let now = get_current_time_seconds();
         let peer_info = {
@@ -302,23 +445,110 @@ impl GossipProtocolExecutor {
         };
 
         // Expire cluster peers sooner than non-cluster peers
-        let same_cluster = peer_info
-            .get_cluster_id()
-            .eq(&self.global_state.local_cluster_id);
+        let peer_cluster = peer_info.get_cluster_id();
+        let same_cluster = peer_cluster.eq(&self.global_state.local_cluster_id);
         let last_heartbeat = now - peer_info.get_last_heartbeat();
 
-        #[allow(clippy::if_same_then_else)]
-        if same_cluster && last_heartbeat < CLUSTER_HEARTBEAT_FAILURE_MS / 1000 {
-            return;
-        } else if !same_cluster && last_heartbeat < HEARTBEAT_FAILURE_MS / 1000 {
+        // If the peer left the previous heartbeat unanswered for too long, count it against
+        // the peer's reputation, and prune the peer if its reputation has fallen too low
+        let heartbeat_interval_ms = if same_cluster {
+            CLUSTER_HEARTBEAT_INTERVAL_MS
+        } else {
+            HEARTBEAT_INTERVAL_MS
+        };
+        let missed_heartbeat =
+            last_heartbeat * 1000 > heartbeat_interval_ms * MISSED_HEARTBEAT_MULTIPLE;
+        let should_prune = {
+            let mut locked_reputation = self.global_state.write_peer_reputation().await;
+            if missed_heartbeat {
+                locked_reputation.record_missed_heartbeat(peer_id);
+            }
+
+            locked_reputation.should_prune(&peer_id)
+        }; // locked_reputation released
+
+        let failure_ms = if same_cluster {
+            CLUSTER_HEARTBEAT_FAILURE_MS
+        } else {
+            HEARTBEAT_FAILURE_MS
+        };
+        let timed_out = last_heartbeat >= failure_ms / 1000;
+
+        let liveness = if timed_out || should_prune {
+            PeerLiveness::Dead
+        } else if missed_heartbeat {
+            PeerLiveness::Suspect
+        } else {
+            PeerLiveness::Live
+        };
+        let prev_liveness = self
+            .global_state
+            .read_peer_index()
+            .await
+            .set_liveness(&peer_id, liveness)
+            .await;
+
+        if liveness == PeerLiveness::Suspect && prev_liveness != Some(PeerLiveness::Suspect) {
+            log::info!("peer {peer_id} is suspect, {last_heartbeat}s since last heartbeat");
+        }
+        if liveness != PeerLiveness::Dead {
             return;
         }
 
-        log::info!("Expiring peer");
+        if should_prune {
+            log::info!("Pruning peer {peer_id} for low reputation");
+        } else {
+            log::info!("Expiring peer");
+        }
 
         // Remove expired peers from global state
         self.global_state.remove_peer(&peer_id).await;
 
+        // If the expired peer led the cluster, elect a replacement from the remaining peers
+        if same_cluster
+            && self
+                .global_state
+                .write_cluster_leadership()
+                .await
+                .clear_leader(&peer_id)
+        {
+            log::info!("lost cluster leader {peer_id}, re-electing");
+            if let Err(e) = self.run_leader_election().await {
+                log::error!("error electing cluster leader: {e}");
+            }
+        }
+
+        // Replace the expired peer's replicas of any wallets it held
+        if same_cluster && let Err(e) = self.rereplicate_wallets().await {
+            log::error!("error re-replicating wallets: {e}");
+        }
+
+        // Replace the expired peer's shards, and reassemble any wallets it was the last
+        // peer to hold in full
+        if same_cluster && let Err(e) = self.distribute_wallet_shards().await {
+            log::error!("error distributing wallet shards: {e}");
+        }
+        if same_cluster && let Err(e) = self.reassemble_orphaned_wallets().await {
+            log::error!("error reassembling wallets: {e}");
+        }
+
+        // If no peer of a remote cluster remains alive, its orders can no longer be
+        // handshaked on, so prune them
+        if !same_cluster
+            && self
+                .global_state
+                .read_peer_index()
+                .await
+                .cluster_is_dead(&peer_cluster)
+                .await
+        {
+            let pruned = self.global_state.prune_cluster_orders(&peer_cluster).await;
+            log::info!(
+                "cluster {peer_cluster} is dead, pruned {} orders",
+                pruned.len()
+            );
+        }
+
         // Add peers to expiry cache for the duration of their invisibility window. This ensures that
         // we do not add the expired peer back to the global state until some time has elapsed. Without
         // this check, another peer may send us a heartbeat attesting to the expired peer's liveness,
@@ -343,7 +573,7 @@ impl HeartbeatTimer {
     /// The interval parameters specify how often the timers should cycle through all peers in their
     /// target list
     pub fn new(
-        job_queue: TokioSender<GossipServerJob>,
+        job_queue: JobQueueSender<GossipServerJob>,
         intra_cluster_interval_ms: u64,
         inter_cluster_interval_ms: u64,
         global_state: RelayerState,
@@ -396,7 +626,7 @@ impl HeartbeatTimer {
     /// to the heartbeat period constant defined above. That is, we specify the interval in between
     /// heartbeats for a given peer, and space out all heartbeats in that interval
     async fn inter_cluster_execution_loop(
-        job_queue: TokioSender<GossipServerJob>,
+        job_queue: JobQueueSender<GossipServerJob>,
         wait_period: Duration,
         global_state: RelayerState,
     ) -> GossipError {
@@ -411,11 +641,15 @@ impl HeartbeatTimer {
 
                 // Skip if we have overflowed the list or if the next peer is in the local peer's cluster;
                 // a separate timer will enqueue intra-cluster heartbeats at a faster rate
+                //
+                // Skip all non-cluster peers while the local peer is listen-only; it still
+                // answers their heartbeats, and still heartbeats its own cluster
                 let mut next_peer_id = None;
-                if let Some(peer_info) = next_peer {
-                    if peer_info.get_cluster_id() != local_cluster {
-                        next_peer_id = Some(peer_info.get_peer_id())
-                    }
+                if let Some(peer_info) = next_peer
+                    && peer_info.get_cluster_id() != local_cluster
+                    && !global_state.is_feature_enabled(FeatureFlag::GossipListenOnly)
+                {
+                    next_peer_id = Some(peer_info.get_peer_id())
                 }
 
                 (peer_info_locked.len(), next_peer_id)
@@ -446,7 +680,7 @@ impl HeartbeatTimer {
     /// Slightly more readable to break this out into its own method as opposed to
     /// adding more control flow statements above
     async fn intra_cluster_execution_loop(
-        job_queue: TokioSender<GossipServerJob>,
+        job_queue: JobQueueSender<GossipServerJob>,
         wait_period: Duration,
         global_state: RelayerState,
     ) -> GossipError {
@@ -486,3 +720,36 @@ impl HeartbeatTimer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::clock::{ManualClock, SharedClock};
+
+    use super::{timestamp_within_skew, MAX_HEARTBEAT_CLOCK_SKEW_MS};
+
+    /// Tests that a heartbeat timestamp is accepted only within the skew window of the
+    /// local clock, on either side of it
+    #[test]
+    fn test_heartbeat_clock_skew() {
+        let manual_clock = ManualClock::new(Duration::from_secs(1_000));
+        let clock: SharedClock = Arc::new(manual_clock.clone());
+        let timestamp = 1_000_000; // 1000 seconds in millis
+
+        assert!(timestamp_within_skew(&clock, timestamp));
+        assert!(timestamp_within_skew(
+            &clock,
+            timestamp + MAX_HEARTBEAT_CLOCK_SKEW_MS
+        ));
+        assert!(!timestamp_within_skew(
+            &clock,
+            timestamp + MAX_HEARTBEAT_CLOCK_SKEW_MS + 1
+        ));
+
+        manual_clock.advance(Duration::from_millis(MAX_HEARTBEAT_CLOCK_SKEW_MS));
+        assert!(timestamp_within_skew(&clock, timestamp));
+        manual_clock.advance(Duration::from_millis(1));
+        assert!(!timestamp_within_skew(&clock, timestamp));
+    }
+}
@@ -1,15 +1,23 @@
 //! Groups job definitions for the gossip server
 //! These jobs are enqueued for execution by other workers within the relayer
 
-use circuits::types::wallet::Nullifier;
+use circuits::types::{order::TimeInForce, wallet::Nullifier};
 use libp2p::request_response::ResponseChannel;
 
 use crate::{
     gossip_api::{
-        cluster_management::{ClusterJoinMessage, ReplicateRequestBody, ValidityProofRequest},
+        cluster_management::{
+            ClusterJoinMessage, LeaderElectedMessage, OrderBookDelta, ReplicaRepairRequest,
+            ReplicaRepairResponse, ReplicateRequestBody, ReplicateShardsBody, ValidityProofRequest,
+            WalletShardRequest, WalletShardResponse,
+        },
         gossip::AuthenticatedGossipResponse,
         heartbeat::{BootstrapRequest, HeartbeatMessage},
+        orderbook_management::{
+            IndicationOfInterest, OrderBookSnapshotChunk, OrderBookSnapshotRequest,
+        },
     },
+    job_queue::{BoundedJob, JobOverflowPolicy},
     proof_generation::jobs::ValidCommitmentsBundle,
     state::{wallet::WalletIdentifier, NetworkOrder, OrderIdentifier},
     types::SizedValidCommitmentsWitness,
@@ -50,6 +58,14 @@ pub enum GossipServerJob {
     OrderBookManagement(OrderBookManagementJob),
 }
 
+impl BoundedJob for GossipServerJob {
+    /// Gossip jobs are dropped under load; heartbeats are re-sent on the next interval and
+    /// order book state is reconciled by the periodic sync
+    fn overflow_policy(&self) -> JobOverflowPolicy {
+        JobOverflowPolicy::Drop
+    }
+}
+
 /// Defines a job type for a cluster management tasks
 #[derive(Clone, Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -66,10 +82,25 @@ pub enum ClusterManagementJob {
     ClusterJoinRequest(ClusterId, ClusterJoinMessage),
     /// Replicate a set of wallets forwarded from a peer
     ReplicateRequest(ReplicateRequestBody),
+    /// A cluster peer has requested the deltas needed to repair its replica of a wallet
+    ReplicaRepairRequest(ReplicaRepairRequest),
+    /// A cluster peer has sent deltas to repair the local replica of a wallet
+    ReplicaRepairResponse(ReplicaRepairResponse),
+    /// Store a set of erasure-coded wallet shards forwarded from a peer
+    ReplicateShards(ReplicateShardsBody),
+    /// A cluster peer reassembling a wallet has requested the shards the local peer holds
+    WalletShardRequest(WalletShardRequest),
+    /// A cluster peer has sent shards of a wallet the local peer is reassembling
+    WalletShardResponse(WalletShardResponse),
     /// Forward any known proofs of order validity to the sending cluster peer
     ShareValidityProofs(ValidityProofRequest),
     /// A proof has been shared by a cluster peer
     UpdateValidityProof(OrderIdentifier, ValidCommitmentsBundle),
+    /// Elect a cluster leader if none is known, e.g. once the local peer has
+    /// finished warming up
+    ElectLeader,
+    /// A cluster peer has announced a leader
+    LeaderElected(ClusterId, LeaderElectedMessage),
 }
 
 /// Defines a job type for local order book management
@@ -90,6 +121,8 @@ pub enum OrderBookManagementJob {
         order_id: OrderIdentifier,
         /// The info attached to the order
         info: Option<NetworkOrder>,
+        /// The peer that responded with the order info
+        sender: WrappedPeerId,
     },
     /// A new order has been added to the book, peers should place it in the
     /// received state in their local book
@@ -110,6 +143,12 @@ pub enum OrderBookManagementJob {
         cluster: ClusterId,
         /// The new proof of `VALID COMMITMENTS`
         proof: ValidCommitmentsBundle,
+        /// The indication of interest published with the proof, if any
+        ioi: Option<IndicationOfInterest>,
+        /// The time in force published with the proof
+        time_in_force: TimeInForce,
+        /// The peer that published the proof, if known
+        sender: Option<WrappedPeerId>,
     },
     /// A request for an order's witness to `VALID COMMITMENTS` has come in
     OrderWitness {
@@ -125,4 +164,22 @@ pub enum OrderBookManagementJob {
         /// The witness used to prove `VALID COMMITMENTS` for the order
         witness: SizedValidCommitmentsWitness,
     },
+    /// Begin syncing the order book from a snapshot held by a cluster peer
+    SyncOrderBook,
+    /// A cluster peer has requested a chunk of the local peer's order book snapshot
+    SnapshotRequest {
+        /// The requested chunk
+        request: OrderBookSnapshotRequest,
+        /// The channel to respond to the request on
+        response_channel: ResponseChannel<AuthenticatedGossipResponse>,
+    },
+    /// A chunk of an order book snapshot requested by the local peer has come in
+    SnapshotChunk {
+        /// The chunk of the snapshot
+        chunk: OrderBookSnapshotChunk,
+        /// The peer serving the snapshot
+        sender: WrappedPeerId,
+    },
+    /// A cluster peer serving an order book snapshot has published a delta
+    OrderBookDelta(OrderBookDelta),
 }
@@ -2,10 +2,14 @@
 //! application layer
 
 mod cluster;
+pub mod erasure;
 pub mod errors;
 mod heartbeat;
 pub mod jobs;
 mod orderbook;
+mod replication;
+pub mod reputation;
 pub mod server;
+mod sync;
 pub mod types;
 pub mod worker;
@@ -2,7 +2,9 @@
 //! events elsewhere in the local node or the network
 
 use circuits::{
-    types::wallet::Nullifier, verify_singleprover_proof, zk_gadgets::merkle::MerkleRoot,
+    types::{order::TimeInForce, wallet::Nullifier},
+    verify_singleprover_proof,
+    zk_gadgets::merkle::MerkleRoot,
 };
 use crypto::fields::{biguint_to_starknet_felt, scalar_to_biguint, starknet_felt_to_biguint};
 use futures::executor::block_on;
@@ -21,7 +23,7 @@ use crate::{
             AuthenticatedGossipResponse, GossipOutbound, GossipRequest, GossipResponse,
             PubsubMessage,
         },
-        orderbook_management::OrderInfoResponse,
+        orderbook_management::{IndicationOfInterest, OrderInfoResponse},
     },
     proof_generation::jobs::ValidCommitmentsBundle,
     state::{NetworkOrder, OrderIdentifier},
@@ -55,9 +57,9 @@ impl GossipProtocolExecutor {
                     .await
             }
 
-            OrderBookManagementJob::OrderInfoResponse { info, .. } => {
+            OrderBookManagementJob::OrderInfoResponse { info, sender, .. } => {
                 if let Some(order_info) = info {
-                    self.handle_order_info_response(order_info).await?;
+                    self.handle_order_info_response(order_info, sender).await?;
                 }
 
                 Ok(())
@@ -76,8 +78,11 @@ impl GossipProtocolExecutor {
                 order_id,
                 cluster,
                 proof,
+                ioi,
+                time_in_force,
+                sender,
             } => {
-                self.handle_new_validity_proof(order_id, cluster, proof)
+                self.handle_new_validity_proof(order_id, cluster, proof, ioi, time_in_force, sender)
                     .await
             }
 
@@ -94,6 +99,25 @@ impl GossipProtocolExecutor {
                     .await;
                 Ok(())
             }
+
+            OrderBookManagementJob::SyncOrderBook => self.begin_order_book_sync().await,
+
+            OrderBookManagementJob::SnapshotRequest {
+                request,
+                response_channel,
+            } => {
+                self.handle_snapshot_request(request, response_channel)
+                    .await
+            }
+
+            OrderBookManagementJob::SnapshotChunk { chunk, sender } => {
+                self.handle_snapshot_chunk(chunk, sender).await
+            }
+
+            OrderBookManagementJob::OrderBookDelta(delta) => {
+                self.handle_order_book_delta(delta).await;
+                Ok(())
+            }
         }
     }
 
@@ -127,6 +151,7 @@ impl GossipProtocolExecutor {
     async fn handle_order_info_response(
         &self,
         mut order_info: NetworkOrder,
+        sender: WrappedPeerId,
     ) -> Result<(), GossipError> {
         // If there is a proof attached to the order, verify it
         let is_local = order_info.cluster == self.global_state.local_cluster_id;
@@ -136,7 +161,7 @@ impl GossipProtocolExecutor {
                 let self_clone = self.clone();
 
                 tokio::task::spawn_blocking(move || {
-                    block_on(self_clone.verify_valid_commitments_proof(proof_bundle))
+                    block_on(self_clone.verify_valid_commitments_proof(proof_bundle, Some(sender)))
                 })
                 .await
                 .unwrap()?;
@@ -151,9 +176,10 @@ impl GossipProtocolExecutor {
         }
 
         order_info.local = is_local;
+        let order_id = order_info.id;
         self.global_state.add_order(order_info).await;
 
-        Ok(())
+        self.publish_order_book_delta(&order_id).await
     }
 
     /// Handles a newly discovered order added to the book
@@ -178,7 +204,8 @@ impl GossipProtocolExecutor {
                 is_local,
             ))
             .await;
-        Ok(())
+
+        self.publish_order_book_delta(&order_id).await
     }
 
     /// Handles a new validity proof attached to an order
@@ -190,6 +217,9 @@ impl GossipProtocolExecutor {
         order_id: OrderIdentifier,
         cluster: ClusterId,
         proof_bundle: ValidCommitmentsBundle,
+        ioi: Option<IndicationOfInterest>,
+        time_in_force: TimeInForce,
+        sender: Option<WrappedPeerId>,
     ) -> Result<(), GossipError> {
         let is_local = cluster.eq(&self.global_state.local_cluster_id);
 
@@ -199,7 +229,7 @@ impl GossipProtocolExecutor {
             let self_clone = self.clone();
 
             tokio::task::spawn_blocking(move || {
-                block_on(self_clone.verify_valid_commitments_proof(bundle_clone))
+                block_on(self_clone.verify_valid_commitments_proof(bundle_clone, sender))
             })
             .await
             .unwrap()?;
@@ -225,6 +255,23 @@ impl GossipProtocolExecutor {
         self.global_state
             .add_order_validity_proof(&order_id, proof_bundle)
             .await;
+        if let Some(ioi) = ioi {
+            self.global_state
+                .read_order_book()
+                .await
+                .attach_ioi(&order_id, ioi)
+                .await;
+        }
+
+        // A local order's time in force is read from its wallet, a cluster peer's copy of
+        // the proof does not override it
+        if !is_local {
+            self.global_state
+                .read_order_book()
+                .await
+                .attach_time_in_force(&order_id, time_in_force)
+                .await;
+        }
 
         // If the order is locally managed, also fetch the wintess used in the proof,
         // this is used for proof linking. I.e. the local node needs the commitment parameters
@@ -233,12 +280,15 @@ impl GossipProtocolExecutor {
             self.request_order_witness(order_id)?;
         }
 
-        Ok(())
+        self.publish_order_book_delta(&order_id).await
     }
 
     /// Requests a copy of the witness used in an order's validity proof for a locally
     /// managed order
-    fn request_order_witness(&self, order_id: OrderIdentifier) -> Result<(), GossipError> {
+    pub(super) fn request_order_witness(
+        &self,
+        order_id: OrderIdentifier,
+    ) -> Result<(), GossipError> {
         let message =
             ClusterManagementMessage::RequestOrderValidityWitness(ValidityWitnessRequest {
                 order_id,
@@ -317,9 +367,13 @@ impl GossipProtocolExecutor {
     ///
     /// Aside from proof verification, this involves validating the statement
     /// variables (e.g. merkle root) for the proof
+    ///
+    /// If the proof itself fails to verify, the peer that sent it (when known) is
+    /// penalized in the reputation tracker
     async fn verify_valid_commitments_proof(
         &self,
         proof_bundle: ValidCommitmentsBundle,
+        sender: Option<WrappedPeerId>,
     ) -> Result<(), GossipError> {
         // Check that the nullifier is unused
         if !self
@@ -350,6 +404,12 @@ impl GossipProtocolExecutor {
             proof_bundle.proof,
         ) {
             log::error!("Invalid proof of `VALID COMMITMENTS`");
+            if let Some(sender) = sender {
+                self.global_state
+                    .write_peer_reputation()
+                    .await
+                    .record_invalid_proof(sender);
+            }
             return Err(GossipError::ValidCommitmentVerification(e.to_string()));
         }
 
@@ -0,0 +1,127 @@
+//! Groups logic for choosing which cluster peers replicate a wallet
+//!
+//! Peers may be labelled with a failure domain (a region or availability zone). When the
+//! cluster is configured with a replication factor, replicas are chosen so that a wallet's
+//! replicas span as many distinct zones as possible, so that the loss of a single zone does
+//! not lose every replica of a wallet
+//!
+//! The same preference spreads a wallet's erasure-coded shards across zones, so that the
+//! loss of a single zone loses as few of a wallet's shards as possible
+
+use std::collections::HashSet;
+
+use super::types::WrappedPeerId;
+
+/// Choose up to `count` replicas from the given candidates, preferring peers in zones
+/// that are not already covered by a wallet's existing replicas
+///
+/// Peers without a zone label never count towards zone coverage, they are chosen only
+/// once every candidate in an uncovered zone has been taken. Candidates are considered
+/// in peer ID order so that the selection is deterministic for a given view of the cluster
+pub(super) fn select_replicas(
+    candidates: &[(WrappedPeerId, Option<String>)],
+    covered_zones: &HashSet<String>,
+    count: usize,
+) -> Vec<WrappedPeerId> {
+    let mut candidates = candidates.to_vec();
+    candidates.sort_by_key(|(peer_id, _)| *peer_id);
+
+    let mut covered_zones = covered_zones.clone();
+    let mut selected = Vec::new();
+    let mut fallback = Vec::new();
+    for (peer_id, zone) in candidates.into_iter() {
+        if selected.len() == count {
+            break;
+        }
+
+        match zone {
+            Some(zone) if !covered_zones.contains(&zone) => {
+                covered_zones.insert(zone);
+                selected.push(peer_id);
+            }
+            _ => fallback.push(peer_id),
+        }
+    }
+
+    let remaining = count - selected.len();
+    selected.extend(fallback.into_iter().take(remaining));
+    selected
+}
+
+/// Assign each of a wallet's `num_shards` shards to a cluster peer, spreading the shards
+/// across zones
+///
+/// Each peer is assigned at most one shard while there are more peers than shards; a cluster
+/// smaller than the code assigns the peers shards in turn. Returns an empty assignment if
+/// there are no candidates
+pub(super) fn assign_shard_holders(
+    candidates: &[(WrappedPeerId, Option<String>)],
+    num_shards: usize,
+) -> Vec<WrappedPeerId> {
+    let selected = select_replicas(candidates, &HashSet::new(), num_shards);
+    if selected.is_empty() {
+        return Vec::new();
+    }
+
+    (0..num_shards)
+        .map(|index| selected[index % selected.len()])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::gossip::types::WrappedPeerId;
+
+    use super::{assign_shard_holders, select_replicas};
+
+    /// Tests that replicas are spread across uncovered zones before doubling up in a zone
+    #[test]
+    fn test_prefers_uncovered_zones() {
+        let zone = |name: &str| Some(name.to_string());
+        let mut peers = (0..5).map(|_| WrappedPeerId::random()).collect::<Vec<_>>();
+        peers.sort();
+
+        let candidates = vec![
+            (peers[0], zone("us-east")),
+            (peers[1], zone("us-east")),
+            (peers[2], None),
+            (peers[3], zone("eu-west")),
+            (peers[4], zone("ap-south")),
+        ];
+        let covered = HashSet::from(["eu-west".to_string()]);
+
+        // The first two picks cover the two zones the wallet is missing
+        let selected = select_replicas(&candidates, &covered, 2 /* count */);
+        assert_eq!(selected, vec![peers[0], peers[4]]);
+
+        // Once every zone is covered, the remaining candidates fill in
+        let selected = select_replicas(&candidates, &covered, 4 /* count */);
+        assert_eq!(selected, vec![peers[0], peers[4], peers[1], peers[2]]);
+
+        // Requesting more replicas than there are candidates takes every candidate
+        let selected = select_replicas(&candidates, &covered, 10 /* count */);
+        assert_eq!(selected.len(), candidates.len());
+    }
+
+    /// Tests that shards are spread across distinct peers, wrapping around once every
+    /// peer holds a shard
+    #[test]
+    fn test_assign_shard_holders() {
+        let mut peers = (0..3).map(|_| WrappedPeerId::random()).collect::<Vec<_>>();
+        peers.sort();
+        let candidates = peers.iter().map(|peer| (*peer, None)).collect::<Vec<_>>();
+
+        let holders = assign_shard_holders(&candidates, 2 /* num_shards */);
+        assert_eq!(holders, vec![peers[0], peers[1]]);
+
+        let holders = assign_shard_holders(&candidates, 5 /* num_shards */);
+        assert_eq!(
+            holders,
+            vec![peers[0], peers[1], peers[2], peers[0], peers[1]]
+        );
+
+        assert!(assign_shard_holders(&[], 5 /* num_shards */).is_empty());
+    }
+}
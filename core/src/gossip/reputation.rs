@@ -0,0 +1,223 @@
+//! Groups logic for scoring the reputation of peers in the network
+//!
+//! A peer's reputation is built from three signals:
+//!     1. Heartbeat reliability; whether the peer answers heartbeats in a timely manner
+//!     2. Invalid proof submissions; proofs of `VALID COMMITMENTS` that fail verification
+//!     3. Handshake abandonment; MPCs that the peer begins but does not see through
+//!
+//! The score is consulted by the handshake manager when choosing counterparties, and by
+//! the gossip server when deciding whether to prune a peer from the local peer index
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::types::WrappedPeerId;
+
+/// The weight given to the most recent heartbeat outcome in the peer's heartbeat reliability
+const HEARTBEAT_SMOOTHING_FACTOR: f64 = 0.1;
+/// The weight given to the most recent handshake outcome in the peer's handshake reliability
+const HANDSHAKE_SMOOTHING_FACTOR: f64 = 0.2;
+/// The multiplicative penalty applied to a peer's score for each invalid proof it submits
+const INVALID_PROOF_PENALTY: f64 = 0.5;
+/// The minimum score a peer must hold to be chosen as a handshake counterparty
+pub const MIN_COUNTERPARTY_SCORE: f64 = 0.5;
+/// The score below which a peer is pruned from the local peer index
+pub const PRUNE_SCORE_THRESHOLD: f64 = 0.2;
+
+/// The reputation of a single peer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerReputation {
+    /// An exponentially weighted moving average of heartbeat outcomes, in [0, 1]
+    pub heartbeat_reliability: f64,
+    /// An exponentially weighted moving average of handshake outcomes, in [0, 1]
+    pub handshake_reliability: f64,
+    /// The number of invalid proofs the peer has submitted
+    pub invalid_proofs: u64,
+    /// The number of handshakes the peer has abandoned
+    pub abandoned_handshakes: u64,
+}
+
+impl Default for PeerReputation {
+    fn default() -> Self {
+        // Peers begin with full reputation, so that newly discovered peers are not
+        // penalized before they have had the chance to misbehave
+        Self {
+            heartbeat_reliability: 1.,
+            handshake_reliability: 1.,
+            invalid_proofs: 0,
+            abandoned_handshakes: 0,
+        }
+    }
+}
+
+impl PeerReputation {
+    /// The aggregate score of the peer, in [0, 1]
+    pub fn score(&self) -> f64 {
+        let proof_penalty = INVALID_PROOF_PENALTY.powf(self.invalid_proofs as f64);
+        self.heartbeat_reliability * self.handshake_reliability * proof_penalty
+    }
+
+    /// Fold a new outcome into an exponentially weighted moving average
+    fn smooth(average: f64, outcome: bool, smoothing_factor: f64) -> f64 {
+        let outcome = if outcome { 1. } else { 0. };
+        (1. - smoothing_factor) * average + smoothing_factor * outcome
+    }
+}
+
+/// Tracks the reputation of every peer the local node has interacted with
+///
+/// Reputations are retained after a peer is pruned, so that a pruned peer that is
+/// re-discovered through gossip does not return with a clean slate
+#[derive(Debug, Default)]
+pub struct PeerReputationTracker {
+    /// The reputation of each peer
+    reputations: HashMap<WrappedPeerId, PeerReputation>,
+}
+
+impl PeerReputationTracker {
+    /// Create a new, empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // -----------
+    // | Getters |
+    // -----------
+
+    /// Get the reputation of a peer, peers with no history have a default reputation
+    pub fn get_reputation(&self, peer_id: &WrappedPeerId) -> PeerReputation {
+        self.reputations.get(peer_id).cloned().unwrap_or_default()
+    }
+
+    /// Get the score of a peer
+    pub fn score(&self, peer_id: &WrappedPeerId) -> f64 {
+        self.reputations
+            .get(peer_id)
+            .map(|reputation| reputation.score())
+            .unwrap_or(1.)
+    }
+
+    /// Whether the peer is reputable enough to handshake with
+    pub fn is_eligible_counterparty(&self, peer_id: &WrappedPeerId) -> bool {
+        self.score(peer_id) >= MIN_COUNTERPARTY_SCORE
+    }
+
+    /// Whether the peer's reputation has fallen far enough that it should be pruned
+    pub fn should_prune(&self, peer_id: &WrappedPeerId) -> bool {
+        self.score(peer_id) < PRUNE_SCORE_THRESHOLD
+    }
+
+    // -----------
+    // | Setters |
+    // -----------
+
+    /// Record a heartbeat that the peer answered
+    pub fn record_heartbeat(&mut self, peer_id: WrappedPeerId) {
+        let reputation = self.reputations.entry(peer_id).or_default();
+        reputation.heartbeat_reliability = PeerReputation::smooth(
+            reputation.heartbeat_reliability,
+            true,
+            HEARTBEAT_SMOOTHING_FACTOR,
+        );
+    }
+
+    /// Record a heartbeat that the peer failed to answer in time
+    pub fn record_missed_heartbeat(&mut self, peer_id: WrappedPeerId) {
+        let reputation = self.reputations.entry(peer_id).or_default();
+        reputation.heartbeat_reliability = PeerReputation::smooth(
+            reputation.heartbeat_reliability,
+            false,
+            HEARTBEAT_SMOOTHING_FACTOR,
+        );
+    }
+
+    /// Record a proof submitted by the peer that failed verification
+    pub fn record_invalid_proof(&mut self, peer_id: WrappedPeerId) {
+        self.reputations.entry(peer_id).or_default().invalid_proofs += 1;
+    }
+
+    /// Record a handshake that the peer saw through to completion
+    pub fn record_completed_handshake(&mut self, peer_id: WrappedPeerId) {
+        let reputation = self.reputations.entry(peer_id).or_default();
+        reputation.handshake_reliability = PeerReputation::smooth(
+            reputation.handshake_reliability,
+            true,
+            HANDSHAKE_SMOOTHING_FACTOR,
+        );
+    }
+
+    /// Record a handshake that the peer abandoned
+    pub fn record_abandoned_handshake(&mut self, peer_id: WrappedPeerId) {
+        let reputation = self.reputations.entry(peer_id).or_default();
+        reputation.abandoned_handshakes += 1;
+        reputation.handshake_reliability = PeerReputation::smooth(
+            reputation.handshake_reliability,
+            false,
+            HANDSHAKE_SMOOTHING_FACTOR,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gossip::types::WrappedPeerId;
+
+    use super::{PeerReputationTracker, MIN_COUNTERPARTY_SCORE};
+
+    /// Tests that a peer with no history is fully reputable
+    #[test]
+    fn test_default_reputation() {
+        let tracker = PeerReputationTracker::new();
+        let peer_id = WrappedPeerId::random();
+
+        assert_eq!(tracker.score(&peer_id), 1.);
+        assert!(tracker.is_eligible_counterparty(&peer_id));
+        assert!(!tracker.should_prune(&peer_id));
+    }
+
+    /// Tests that invalid proofs quickly disqualify a peer
+    #[test]
+    fn test_invalid_proofs() {
+        let mut tracker = PeerReputationTracker::new();
+        let peer_id = WrappedPeerId::random();
+
+        tracker.record_invalid_proof(peer_id);
+        assert!(tracker.score(&peer_id) >= MIN_COUNTERPARTY_SCORE);
+
+        tracker.record_invalid_proof(peer_id);
+        assert!(!tracker.is_eligible_counterparty(&peer_id));
+
+        tracker.record_invalid_proof(peer_id);
+        assert!(tracker.should_prune(&peer_id));
+    }
+
+    /// Tests that a peer that misses heartbeats recovers once it becomes responsive
+    #[test]
+    fn test_heartbeat_recovery() {
+        let mut tracker = PeerReputationTracker::new();
+        let peer_id = WrappedPeerId::random();
+
+        for _ in 0..20 {
+            tracker.record_missed_heartbeat(peer_id);
+        }
+        assert!(tracker.should_prune(&peer_id));
+
+        for _ in 0..20 {
+            tracker.record_heartbeat(peer_id);
+        }
+        assert!(tracker.is_eligible_counterparty(&peer_id));
+    }
+
+    /// Tests that abandoned handshakes count against a peer
+    #[test]
+    fn test_abandoned_handshakes() {
+        let mut tracker = PeerReputationTracker::new();
+        let peer_id = WrappedPeerId::random();
+
+        for _ in 0..5 {
+            tracker.record_abandoned_handshake(peer_id);
+        }
+        assert!(!tracker.is_eligible_counterparty(&peer_id));
+        assert_eq!(tracker.get_reputation(&peer_id).abandoned_handshakes, 5);
+    }
+}
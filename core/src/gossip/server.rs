@@ -9,21 +9,23 @@ use starknet_providers::SequencerGatewayProvider;
 use std::{
     collections::HashMap,
     num::NonZeroUsize,
+    sync::{atomic::AtomicU64, Arc},
     thread::{self, Builder, JoinHandle},
     time::Duration,
 };
-use tokio::sync::mpsc::{UnboundedReceiver as TokioReceiver, UnboundedSender as TokioSender};
 use tracing::log;
+use uuid::Uuid;
 
 use crate::{
     default_wrapper::DefaultWrapper,
     gossip_api::{
-        cluster_management::{ClusterJoinMessage, ClusterManagementMessage},
+        cluster_management::{CacheSyncRequest, ClusterJoinMessage, ClusterManagementMessage},
         gossip::{
             GossipOutbound, GossipRequest, GossipResponse, ManagerControlDirective, PubsubMessage,
         },
         heartbeat::BootstrapRequest,
     },
+    job_queue::{JobQueueError, JobQueueReceiver, JobQueueSender},
     state::{new_async_shared, AsyncShared, RelayerState},
     CancelChannel,
 };
@@ -33,7 +35,8 @@ use super::{
     heartbeat::{
         HeartbeatTimer, CLUSTER_HEARTBEAT_INTERVAL_MS, EXPIRY_CACHE_SIZE, HEARTBEAT_INTERVAL_MS,
     },
-    jobs::GossipServerJob,
+    jobs::{ClusterManagementJob, GossipServerJob, OrderBookManagementJob},
+    sync::{OrderBookSync, ServedSnapshot, SNAPSHOT_CACHE_SIZE},
     types::WrappedPeerId,
     worker::GossipServerConfig,
 };
@@ -45,6 +48,12 @@ pub(super) const GOSSIP_EXECUTOR_N_BLOCKING_THREADS: usize = 5;
 /// The amount of time to wait for the node to find peers before sending
 /// pubsub messages associated with setup
 const PUBSUB_WARMUP_TIME_MS: u64 = 5_000; // 5 seconds
+/// The amount of time to wait after warmup for an existing cluster leader to announce
+/// itself before the local peer runs an election
+const LEADER_ANNOUNCEMENT_WAIT_MS: u64 = 2_000; // 2 seconds
+/// The interval at which the warmup directive is retried while the network manager's
+/// queue is full
+const WARMUP_DIRECTIVE_RETRY_MS: u64 = 100;
 
 /// Type alias for a shared LRU cache
 pub(super) type SharedLRUCache = AsyncShared<LruCache<WrappedPeerId, u64>>;
@@ -151,8 +160,25 @@ impl GossipServer {
             })
             .map_err(|err| GossipError::SendMessage(err.to_string()))?;
 
+        // Pull the cluster's completed order pairs into the handshake cache, so that the local
+        // peer does not re-attempt matches its cluster has already run; also buffered until
+        // the warmup period is complete
+        self.config
+            .network_sender
+            .send(GossipOutbound::Pubsub {
+                topic: self.config.cluster_id.get_management_topic(),
+                message: PubsubMessage::ClusterManagement {
+                    cluster_id: self.config.cluster_id.clone(),
+                    message: ClusterManagementMessage::CacheSyncRequest(CacheSyncRequest {
+                        sender: self.config.local_peer_id,
+                    }),
+                },
+            })
+            .map_err(|err| GossipError::SendMessage(err.to_string()))?;
+
         // Copy items so they may be moved into the spawned thread
         let network_sender_copy = self.config.network_sender.clone();
+        let job_sender_copy = self.config.job_sender.clone();
         // Spawn a thread to wait on a timeout and then signal to the network manager that it
         // may flush the pubsub buffer
         Builder::new()
@@ -160,11 +186,37 @@ impl GossipServer {
             .spawn(move || {
                 // Wait for the network to warmup
                 thread::sleep(Duration::from_millis(PUBSUB_WARMUP_TIME_MS));
-                network_sender_copy
-                    .send(GossipOutbound::ManagementMessage(
-                        ManagerControlDirective::GossipWarmupComplete,
+
+                // The directive is rejected rather than dropped by a full queue; it must be
+                // retried until enqueued, or the network manager never flushes its buffer
+                let mut directive = GossipOutbound::ManagementMessage(
+                    ManagerControlDirective::GossipWarmupComplete,
+                );
+                loop {
+                    match network_sender_copy.send(directive) {
+                        Ok(()) => break,
+                        Err(JobQueueError::Full(job)) => {
+                            directive = job;
+                            thread::sleep(Duration::from_millis(WARMUP_DIRECTIVE_RETRY_MS));
+                        }
+                        Err(JobQueueError::Closed(_)) => panic!("network manager queue closed"),
+                    }
+                }
+
+                // Heartbeats have discovered the cluster's peers by now, sync the order book
+                // from a snapshot held by one of them
+                job_sender_copy
+                    .send(GossipServerJob::OrderBookManagement(
+                        OrderBookManagementJob::SyncOrderBook,
                     ))
                     .unwrap();
+
+                // Give the cluster's leader time to announce itself in response to the join
+                // message, then elect a leader if none has been announced
+                thread::sleep(Duration::from_millis(LEADER_ANNOUNCEMENT_WAIT_MS));
+                job_sender_copy
+                    .send(GossipServerJob::Cluster(ClusterManagementJob::ElectLeader))
+                    .unwrap();
             })
             .map_err(|err| GossipError::ServerSetup(err.to_string()))?;
 
@@ -184,13 +236,19 @@ pub struct GossipProtocolExecutor {
     /// has had time to propagate
     pub(super) peer_expiry_cache: SharedLRUCache,
     /// The channel on which to receive jobs
-    pub(super) job_receiver: DefaultWrapper<Option<TokioReceiver<GossipServerJob>>>,
+    pub(super) job_receiver: DefaultWrapper<Option<JobQueueReceiver<GossipServerJob>>>,
     /// The channel to send outbound network requests on
-    pub(super) network_channel: TokioSender<GossipOutbound>,
+    pub(super) network_channel: JobQueueSender<GossipOutbound>,
     /// The global state of the relayer
     pub(super) global_state: RelayerState,
     /// A copy of the config passed to the worker
     pub(super) config: GossipServerConfig,
+    /// The order book snapshots the local peer is serving to bootstrapping cluster peers
+    pub(super) served_snapshots: AsyncShared<LruCache<Uuid, ServedSnapshot>>,
+    /// The sequence number of the last order book delta the local peer published
+    pub(super) delta_sequence: Arc<AtomicU64>,
+    /// The order book sync the local peer is running, if any
+    pub(super) order_book_sync: AsyncShared<Option<OrderBookSync>>,
     /// The channel that the coordinator thread uses to cancel gossip execution
     pub(super) cancel_channel: CancelChannel,
 }
@@ -198,8 +256,8 @@ pub struct GossipProtocolExecutor {
 impl GossipProtocolExecutor {
     /// Creates a new executor
     pub fn new(
-        network_channel: TokioSender<GossipOutbound>,
-        job_receiver: TokioReceiver<GossipServerJob>,
+        network_channel: JobQueueSender<GossipOutbound>,
+        job_receiver: JobQueueReceiver<GossipServerJob>,
         global_state: RelayerState,
         config: GossipServerConfig,
         cancel_channel: CancelChannel,
@@ -215,6 +273,11 @@ impl GossipProtocolExecutor {
             network_channel,
             global_state,
             config,
+            served_snapshots: new_async_shared(LruCache::new(
+                NonZeroUsize::new(SNAPSHOT_CACHE_SIZE).unwrap(),
+            )),
+            delta_sequence: Arc::new(AtomicU64::new(0)),
+            order_book_sync: new_async_shared(None),
             cancel_channel,
         })
     }
@@ -232,7 +295,7 @@ impl GossipProtocolExecutor {
     /// Runs the executor loop
     pub async fn execution_loop(
         mut self,
-        job_sender: TokioSender<GossipServerJob>,
+        job_sender: JobQueueSender<GossipServerJob>,
     ) -> Result<(), GossipError> {
         log::info!("Starting executor loop for heartbeat protocol executor...");
 
@@ -289,7 +352,9 @@ impl GossipProtocolExecutor {
             }
             GossipServerJob::ExecuteHeartbeat(peer_id) => self.send_heartbeat(peer_id).await,
             GossipServerJob::HandleHeartbeatReq {
-                message, channel, ..
+                peer_id,
+                message,
+                channel,
             } => {
                 // Respond on the channel given in the request
                 let heartbeat_resp =
@@ -303,11 +368,22 @@ impl GossipProtocolExecutor {
                     .map_err(|err| GossipError::SendMessage(err.to_string()));
 
                 // Merge newly discovered peers into local state
-                self.merge_state_from_message(message).await.and(res)
+                match self.verify_heartbeat(peer_id, &message).await {
+                    Ok(()) => self
+                        .merge_state_from_message(peer_id, message)
+                        .await
+                        .and(res),
+                    Err(e) => Err(e),
+                }
             }
             GossipServerJob::HandleHeartbeatResp { peer_id, message } => {
-                self.record_heartbeat(peer_id).await;
-                self.merge_state_from_message(message).await
+                match self.verify_heartbeat(peer_id, &message).await {
+                    Ok(()) => {
+                        self.record_heartbeat(peer_id).await;
+                        self.merge_state_from_message(peer_id, message).await
+                    }
+                    Err(e) => Err(e),
+                }
             }
             GossipServerJob::Cluster(job) => self.handle_cluster_management_job(job).await,
             GossipServerJob::OrderBookManagement(management_message) => {
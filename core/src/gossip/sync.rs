@@ -0,0 +1,564 @@
+//! Groups handlers for syncing the order book of a bootstrapping peer from a snapshot
+//! held by a cluster peer
+//!
+//! Rather than replaying the book one order at a time from heartbeats, a bootstrapping
+//! peer requests a snapshot of the book from a cluster peer in hashed chunks. While the
+//! serving peer holds a snapshot, it publishes a delta on the cluster management topic
+//! for each order it adds or verifies from gossip. The syncing peer buffers these deltas
+//! until the snapshot is complete, checks the snapshot against its own Merkle mirror,
+//! then applies the snapshot and every delta published after the snapshot was taken.
+//!
+//! Orders that are matched or cancelled are not broadcast as deltas, as each peer
+//! derives these transitions from on-chain events itself. Heartbeats continue to sync the
+//! book as before, so an abandoned or rejected sync falls back to them
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use curve25519_dalek::scalar::Scalar;
+use itertools::Itertools;
+use libp2p::request_response::ResponseChannel;
+use tracing::log;
+use uuid::Uuid;
+
+use crate::{
+    gossip_api::{
+        cluster_management::{ClusterManagementMessage, OrderBookDelta},
+        gossip::{
+            AuthenticatedGossipResponse, GossipOutbound, GossipRequest, GossipResponse,
+            PubsubMessage,
+        },
+        orderbook_management::{OrderBookSnapshotChunk, OrderBookSnapshotRequest},
+    },
+    state::{NetworkOrder, NetworkOrderState, OrderIdentifier},
+};
+
+use super::{errors::GossipError, server::GossipProtocolExecutor, types::WrappedPeerId};
+
+/// The number of orders in each chunk of a snapshot
+const SNAPSHOT_CHUNK_SIZE: usize = 100;
+/// The number of snapshots a peer holds while serving them
+pub(super) const SNAPSHOT_CACHE_SIZE: usize = 10;
+/// The amount of time a snapshot is held, and deltas published for it, after it is taken
+const SNAPSHOT_TTL_MS: u64 = 60_000; // 1 minute
+/// The number of deltas a syncing peer buffers before abandoning the sync
+const MAX_BUFFERED_DELTAS: usize = 10_000;
+
+/// A snapshot of the local order book, held while cluster peers fetch it
+#[derive(Clone, Debug)]
+pub(super) struct ServedSnapshot {
+    /// The time at which the snapshot was taken
+    taken_at: Instant,
+    /// The orders in the snapshot, chunked in order of their identifier
+    chunks: Vec<Vec<NetworkOrder>>,
+    /// The hash of each chunk
+    chunk_hashes: Vec<[u8; 32]>,
+    /// The sequence number of the last delta published before the snapshot was taken
+    delta_sequence: u64,
+    /// The root of the local Merkle mirror when the snapshot was taken
+    merkle_root: Scalar,
+}
+
+impl ServedSnapshot {
+    /// Whether the snapshot has outlived its TTL
+    fn is_expired(&self) -> bool {
+        self.taken_at.elapsed() >= Duration::from_millis(SNAPSHOT_TTL_MS)
+    }
+
+    /// Build the response carrying the given chunk of the snapshot
+    fn chunk(&self, snapshot_id: Uuid, chunk_index: usize) -> OrderBookSnapshotChunk {
+        OrderBookSnapshotChunk {
+            snapshot_id,
+            chunk_index,
+            chunk_hashes: self.chunk_hashes.clone(),
+            delta_sequence: self.delta_sequence,
+            merkle_root: self.merkle_root,
+            orders: self.chunks[chunk_index].clone(),
+        }
+    }
+}
+
+/// The state of an order book sync in progress on the local peer
+#[derive(Clone, Debug)]
+pub(super) struct OrderBookSync {
+    /// The cluster peer serving the snapshot
+    peer: WrappedPeerId,
+    /// The snapshot being fetched, unset until its first chunk arrives
+    snapshot_id: Option<Uuid>,
+    /// The hash of each chunk in the snapshot
+    chunk_hashes: Vec<[u8; 32]>,
+    /// The sequence number of the last delta the peer published before the snapshot
+    delta_sequence: u64,
+    /// The root of the peer's Merkle mirror when the snapshot was taken
+    merkle_root: Scalar,
+    /// The orders received so far
+    orders: Vec<NetworkOrder>,
+    /// The number of chunks received so far
+    chunks_received: usize,
+    /// The deltas published by the peer since the sync began
+    deltas: Vec<OrderBookDelta>,
+}
+
+impl OrderBookSync {
+    /// Begin a sync from the given peer
+    fn new(peer: WrappedPeerId) -> Self {
+        Self {
+            peer,
+            snapshot_id: None,
+            chunk_hashes: Vec::new(),
+            delta_sequence: 0,
+            merkle_root: Scalar::zero(),
+            orders: Vec::new(),
+            chunks_received: 0,
+            deltas: Vec::new(),
+        }
+    }
+
+    /// Whether every chunk of the snapshot has been received
+    fn is_complete(&self) -> bool {
+        self.snapshot_id.is_some() && self.chunks_received == self.chunk_hashes.len()
+    }
+
+    /// Accept a chunk of the snapshot, returning whether the chunk was the next expected
+    ///
+    /// A first chunk of a snapshot other than the one being fetched restarts the sync
+    /// from that snapshot; the serving peer sends one when the snapshot being fetched
+    /// has expired
+    fn accept_chunk(&mut self, chunk: OrderBookSnapshotChunk) -> bool {
+        if chunk.chunk_index == 0 && self.snapshot_id != Some(chunk.snapshot_id) {
+            self.snapshot_id = Some(chunk.snapshot_id);
+            self.chunk_hashes = chunk.chunk_hashes;
+            self.delta_sequence = chunk.delta_sequence;
+            self.merkle_root = chunk.merkle_root;
+            self.orders = chunk.orders;
+            self.chunks_received = 1;
+            return true;
+        }
+
+        if self.snapshot_id != Some(chunk.snapshot_id)
+            || chunk.chunk_index != self.chunks_received
+            || chunk.chunk_hashes != self.chunk_hashes
+        {
+            return false;
+        }
+
+        self.orders.extend(chunk.orders);
+        self.chunks_received += 1;
+        true
+    }
+
+    /// The deltas published after the snapshot was taken, in the order they were
+    /// published
+    fn deltas_after_snapshot(&mut self) -> Vec<OrderBookDelta> {
+        let delta_sequence = self.delta_sequence;
+        std::mem::take(&mut self.deltas)
+            .into_iter()
+            .filter(|delta| delta.sequence > delta_sequence)
+            .sorted_by_key(|delta| delta.sequence)
+            .collect_vec()
+    }
+}
+
+/// Chunk a snapshot of the order book in order of the orders' identifiers, returning
+/// the chunks and the hash of each
+///
+/// An empty book is chunked into a single empty chunk, so that every snapshot has a
+/// first chunk to serve
+fn chunk_snapshot(orders: Vec<NetworkOrder>) -> (Vec<Vec<NetworkOrder>>, Vec<[u8; 32]>) {
+    let mut chunks = orders
+        .into_iter()
+        .sorted_by_key(|order| order.id)
+        .chunks(SNAPSHOT_CHUNK_SIZE)
+        .into_iter()
+        .map(|chunk| chunk.collect_vec())
+        .collect_vec();
+    if chunks.is_empty() {
+        chunks.push(Vec::new());
+    }
+
+    let chunk_hashes = chunks
+        .iter()
+        .map(|chunk| OrderBookSnapshotChunk::hash_orders(chunk))
+        .collect_vec();
+    (chunks, chunk_hashes)
+}
+
+impl GossipProtocolExecutor {
+    // -----------
+    // | Serving |
+    // -----------
+
+    /// Handles a request from a cluster peer for a chunk of the local order book snapshot
+    ///
+    /// A request for a new snapshot, or for a snapshot that has expired, is served the
+    /// first chunk of a new snapshot
+    pub(super) async fn handle_snapshot_request(
+        &self,
+        request: OrderBookSnapshotRequest,
+        response_channel: ResponseChannel<AuthenticatedGossipResponse>,
+    ) -> Result<(), GossipError> {
+        let served_chunk = match request.snapshot_id {
+            Some(snapshot_id) => self
+                .served_snapshots
+                .write()
+                .await
+                .get(&snapshot_id)
+                .filter(|snapshot| {
+                    !snapshot.is_expired() && request.chunk_index < snapshot.chunks.len()
+                })
+                .map(|snapshot| snapshot.chunk(snapshot_id, request.chunk_index)),
+            None => None,
+        }; // served_snapshots lock released
+
+        let chunk = match served_chunk {
+            Some(chunk) => chunk,
+            None => self.take_snapshot().await,
+        };
+
+        self.network_channel
+            .send(GossipOutbound::Response {
+                channel: response_channel,
+                message: GossipResponse::OrderBookSnapshot(chunk),
+            })
+            .map_err(|err| GossipError::SendMessage(err.to_string()))
+    }
+
+    /// Take a snapshot of the local order book and hold it for cluster peers to fetch,
+    /// returning its first chunk
+    async fn take_snapshot(&self) -> OrderBookSnapshotChunk {
+        // Read the delta sequence before the book, so that a delta published while the
+        // snapshot is taken is replayed rather than missed
+        let delta_sequence = self.delta_sequence.load(Ordering::SeqCst);
+        let merkle_root = self.global_state.read_merkle_tree().await.root();
+        let orders = self
+            .global_state
+            .read_order_book()
+            .await
+            .get_order_book_snapshot()
+            .await
+            .into_values()
+            .collect_vec();
+
+        let (chunks, chunk_hashes) = chunk_snapshot(orders);
+        let snapshot = ServedSnapshot {
+            taken_at: Instant::now(),
+            chunks,
+            chunk_hashes,
+            delta_sequence,
+            merkle_root,
+        };
+
+        let snapshot_id = Uuid::new_v4();
+        let chunk = snapshot.chunk(snapshot_id, 0 /* chunk_index */);
+        self.served_snapshots
+            .write()
+            .await
+            .put(snapshot_id, snapshot);
+
+        chunk
+    }
+
+    /// Publish the current state of an order to the cluster, if the local peer is
+    /// serving any unexpired snapshot
+    pub(super) async fn publish_order_book_delta(
+        &self,
+        order_id: &OrderIdentifier,
+    ) -> Result<(), GossipError> {
+        let serving = self
+            .served_snapshots
+            .read()
+            .await
+            .iter()
+            .any(|(_, snapshot)| !snapshot.is_expired());
+        if !serving {
+            return Ok(());
+        }
+
+        let order = match self
+            .global_state
+            .read_order_book()
+            .await
+            .get_order_info(order_id)
+            .await
+        {
+            Some(order) => order,
+            None => return Ok(()),
+        };
+
+        let delta = OrderBookDelta {
+            sender: self.global_state.local_peer_id,
+            sequence: self.delta_sequence.fetch_add(1, Ordering::SeqCst) + 1,
+            order,
+        };
+        self.network_channel
+            .send(GossipOutbound::Pubsub {
+                topic: self.global_state.local_cluster_id.get_management_topic(),
+                message: PubsubMessage::ClusterManagement {
+                    cluster_id: self.global_state.local_cluster_id.clone(),
+                    message: ClusterManagementMessage::OrderBookDelta(delta),
+                },
+            })
+            .map_err(|err| GossipError::SendMessage(err.to_string()))
+    }
+
+    // -----------
+    // | Syncing |
+    // -----------
+
+    /// Begin syncing the order book from a cluster peer, preferring the cluster leader
+    ///
+    /// If no other cluster peer is known, the book is left to sync from heartbeats
+    pub(super) async fn begin_order_book_sync(&self) -> Result<(), GossipError> {
+        let local_peer_id = self.global_state.local_peer_id;
+        let leader = self.global_state.read_cluster_leadership().await.leader();
+        let cluster_peers = self
+            .global_state
+            .read_peer_index()
+            .await
+            .get_all_cluster_peers(&self.global_state.local_cluster_id)
+            .await
+            .into_iter()
+            .filter(|peer_id| *peer_id != local_peer_id)
+            .collect_vec();
+
+        let peer = match leader.filter(|leader| cluster_peers.contains(leader)) {
+            Some(leader) => leader,
+            None => match cluster_peers.first() {
+                Some(peer) => *peer,
+                None => {
+                    log::info!("no cluster peers to sync the order book from, skipping...");
+                    return Ok(());
+                }
+            },
+        };
+
+        log::info!("syncing order book from cluster peer {peer}");
+        *self.order_book_sync.write().await = Some(OrderBookSync::new(peer));
+        self.request_snapshot_chunk(peer, None /* snapshot_id */, 0 /* chunk_index */)
+    }
+
+    /// Request a chunk of an order book snapshot from a cluster peer
+    fn request_snapshot_chunk(
+        &self,
+        peer_id: WrappedPeerId,
+        snapshot_id: Option<Uuid>,
+        chunk_index: usize,
+    ) -> Result<(), GossipError> {
+        self.network_channel
+            .send(GossipOutbound::Request {
+                peer_id,
+                message: GossipRequest::OrderBookSnapshot(OrderBookSnapshotRequest {
+                    snapshot_id,
+                    chunk_index,
+                }),
+            })
+            .map_err(|err| GossipError::SendMessage(err.to_string()))
+    }
+
+    /// Handles a chunk of the snapshot the local peer is syncing from
+    pub(super) async fn handle_snapshot_chunk(
+        &self,
+        chunk: OrderBookSnapshotChunk,
+        sender: WrappedPeerId,
+    ) -> Result<(), GossipError> {
+        let mut locked_sync = self.order_book_sync.write().await;
+        if !matches!(locked_sync.as_ref(), Some(sync) if sync.peer == sender) {
+            return Ok(());
+        }
+
+        if !chunk.is_intact() {
+            *locked_sync = None;
+            return Err(GossipError::Parse(format!(
+                "chunk {} of order book snapshot from {sender} does not match its hash",
+                chunk.chunk_index
+            )));
+        }
+
+        let sync = locked_sync.as_mut().unwrap();
+        if !sync.accept_chunk(chunk) {
+            return Ok(());
+        }
+
+        if !sync.is_complete() {
+            return self.request_snapshot_chunk(sender, sync.snapshot_id, sync.chunks_received);
+        }
+
+        let sync = locked_sync.take().unwrap();
+        drop(locked_sync);
+        self.apply_order_book_sync(sync).await
+    }
+
+    /// Handles a delta published by a cluster peer, buffering it if the local peer is
+    /// syncing from that peer
+    pub(super) async fn handle_order_book_delta(&self, delta: OrderBookDelta) {
+        let mut locked_sync = self.order_book_sync.write().await;
+        let sync = match locked_sync.as_mut() {
+            Some(sync) if sync.peer == delta.sender => sync,
+            _ => return,
+        };
+
+        sync.deltas.push(delta);
+        if sync.deltas.len() > MAX_BUFFERED_DELTAS {
+            log::warn!("order book sync buffered too many deltas, abandoning sync");
+            *locked_sync = None;
+        }
+    }
+
+    /// Apply a completed snapshot and the deltas published after it to the local book
+    ///
+    /// The snapshot is discarded if its Merkle root is not one the local mirror accepts,
+    /// i.e. if the serving peer's view of the contract diverges from the local view
+    async fn apply_order_book_sync(&self, mut sync: OrderBookSync) -> Result<(), GossipError> {
+        let root_accepted = {
+            let locked_tree = self.global_state.read_merkle_tree().await;
+            locked_tree.root() == sync.merkle_root || locked_tree.is_valid_root(&sync.merkle_root)
+        }; // merkle_tree lock released
+        if !root_accepted {
+            log::warn!(
+                "order book snapshot from {} has an unknown merkle root, discarding",
+                sync.peer
+            );
+            return Ok(());
+        }
+
+        let deltas = sync.deltas_after_snapshot();
+        log::info!(
+            "synced {} orders and {} deltas from {}",
+            sync.orders.len(),
+            deltas.len(),
+            sync.peer
+        );
+
+        for order in std::mem::take(&mut sync.orders).into_iter() {
+            self.apply_synced_order(order).await?;
+        }
+        for delta in deltas.into_iter() {
+            self.apply_synced_order(delta.order).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply an order received from a cluster peer's snapshot or delta to the local book
+    ///
+    /// Proofs from cluster peers are trusted, as are proofs gossiped by them. An order
+    /// already in the book is only updated if the peer holds a proof the local peer lacks
+    async fn apply_synced_order(&self, mut order: NetworkOrder) -> Result<(), GossipError> {
+        let is_local = order.cluster == self.global_state.local_cluster_id;
+        let existing_state = self
+            .global_state
+            .read_order_book()
+            .await
+            .get_order_info(&order.id)
+            .await
+            .map(|info| info.state);
+
+        match (existing_state, order.valid_commit_proof.clone()) {
+            (None, proof) => {
+                order.local = is_local;
+                let order_id = order.id;
+                self.global_state.add_order(order).await;
+
+                // Locally managed orders need a copy of the witness for proof linking
+                if is_local && proof.is_some() {
+                    self.request_order_witness(order_id)?;
+                }
+            }
+
+            (Some(NetworkOrderState::Received), Some(proof)) => {
+                self.global_state
+                    .add_order_validity_proof(&order.id, proof)
+                    .await;
+                if is_local {
+                    self.request_order_witness(order.id)?;
+                }
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+    use itertools::Itertools;
+    use uuid::Uuid;
+
+    use crate::{
+        gossip::types::{ClusterId, WrappedPeerId},
+        gossip_api::orderbook_management::OrderBookSnapshotChunk,
+        state::NetworkOrder,
+    };
+
+    use super::{chunk_snapshot, OrderBookSync, SNAPSHOT_CHUNK_SIZE};
+
+    /// Build the given number of orders
+    fn orders(n_orders: usize) -> Vec<NetworkOrder> {
+        let cluster: ClusterId = "cluster".parse().unwrap();
+        (0..n_orders)
+            .map(|i| {
+                NetworkOrder::new(
+                    Uuid::new_v4(),
+                    Scalar::from(i as u64),
+                    cluster.clone(),
+                    false, /* local */
+                )
+            })
+            .collect_vec()
+    }
+
+    /// Tests that a snapshot is chunked in order, received in full, and that a tampered
+    /// chunk is detected
+    #[test]
+    fn test_snapshot_chunks() {
+        let (chunks, chunk_hashes) = chunk_snapshot(orders(2 * SNAPSHOT_CHUNK_SIZE + 1));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].len(), 1);
+
+        let snapshot_id = Uuid::new_v4();
+        let mut sync = OrderBookSync::new(WrappedPeerId::random());
+        for (chunk_index, orders) in chunks.into_iter().enumerate() {
+            let chunk = OrderBookSnapshotChunk {
+                snapshot_id,
+                chunk_index,
+                chunk_hashes: chunk_hashes.clone(),
+                delta_sequence: 0,
+                merkle_root: Scalar::zero(),
+                orders,
+            };
+            assert!(chunk.is_intact());
+            assert!(!sync.is_complete());
+            assert!(sync.accept_chunk(chunk));
+        }
+
+        assert!(sync.is_complete());
+        let ids = sync.orders.iter().map(|order| order.id).collect_vec();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let mut tampered = OrderBookSnapshotChunk {
+            snapshot_id,
+            chunk_index: 0,
+            chunk_hashes,
+            delta_sequence: 0,
+            merkle_root: Scalar::zero(),
+            orders: sync.orders[..SNAPSHOT_CHUNK_SIZE].to_vec(),
+        };
+        assert!(tampered.is_intact());
+        tampered.orders.pop();
+        assert!(!tampered.is_intact());
+    }
+
+    /// Tests that an empty book is served as a single empty chunk
+    #[test]
+    fn test_empty_snapshot() {
+        let (chunks, chunk_hashes) = chunk_snapshot(Vec::new());
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].is_empty());
+        assert_eq!(chunk_hashes.len(), 1);
+    }
+}
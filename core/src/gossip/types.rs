@@ -15,7 +15,71 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::gossip_api::cluster_management::CLUSTER_MANAGEMENT_TOPIC_PREFIX;
+use crate::gossip_api::cluster_management::{ClusterKeyRotation, CLUSTER_MANAGEMENT_TOPIC_PREFIX};
+
+/// The number of heartbeat sequence numbers below the highest seen that may still be
+/// accepted from a peer, heartbeats may arrive out of order as requests and responses
+/// are built concurrently
+const REPLAY_WINDOW_SIZE: u64 = 64;
+
+/// The liveness of a peer, as judged from the time since its last successful heartbeat
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerLiveness {
+    /// The peer has answered a heartbeat recently
+    Live,
+    /// The peer has left heartbeats unanswered for longer than expected, but has not
+    /// yet timed out; a suspect peer remains indexed and may recover
+    Suspect,
+    /// The peer has timed out, and is expired from the peer index
+    Dead,
+}
+
+impl Default for PeerLiveness {
+    fn default() -> Self {
+        PeerLiveness::Live
+    }
+}
+
+/// A sliding window over the heartbeat sequence numbers accepted from a peer, each
+/// sequence number is accepted at most once
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplayWindow {
+    /// The highest sequence number accepted
+    highest: u64,
+    /// A bitmap of the sequence numbers accepted in the window, bit `i` is set if
+    /// `highest - i` has been accepted
+    seen: u64,
+}
+
+impl ReplayWindow {
+    /// Accept a sequence number if it has not been seen and falls within the window
+    pub fn accept(&mut self, sequence: u64) -> bool {
+        // Sequence numbers begin at one
+        if sequence == 0 {
+            return false;
+        }
+
+        if sequence > self.highest {
+            let shift = sequence - self.highest;
+            self.seen = if shift >= REPLAY_WINDOW_SIZE {
+                0
+            } else {
+                self.seen << shift
+            };
+            self.seen |= 1;
+            self.highest = sequence;
+            return true;
+        }
+
+        let offset = self.highest - sequence;
+        if offset >= REPLAY_WINDOW_SIZE || self.seen & (1 << offset) != 0 {
+            return false;
+        }
+
+        self.seen |= 1 << offset;
+        true
+    }
+}
 
 /// Contains information about connected peers
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,11 +91,21 @@ pub struct PeerInfo {
     /// Last time a successful heartbeat was received from this peer
     #[serde(skip)]
     last_heartbeat: AtomicU64,
+    /// The liveness of the peer
+    #[serde(skip)]
+    liveness: PeerLiveness,
+    /// The heartbeat sequence numbers accepted from the peer
+    #[serde(skip)]
+    replay_window: ReplayWindow,
     /// The ID of the cluster the peer belongs to
     cluster_id: ClusterId,
     /// The signature of the peer's ID with their cluster private key, used to
     /// prove that the peer is a valid cluster member
     cluster_auth_signature: Vec<u8>,
+    /// The failure domain (region, availability zone, etc) the peer runs in, if the
+    /// peer was configured with one
+    #[serde(default)]
+    zone: Option<String>,
 }
 
 impl Default for PeerInfo {
@@ -40,8 +114,11 @@ impl Default for PeerInfo {
             peer_id: WrappedPeerId(PeerId::random()),
             addr: Multiaddr::empty(),
             last_heartbeat: AtomicU64::from(0u64),
+            liveness: PeerLiveness::default(),
+            replay_window: ReplayWindow::default(),
             cluster_id: ClusterId("0".to_string()),
             cluster_auth_signature: vec![],
+            zone: None,
         }
     }
 }
@@ -71,6 +148,9 @@ impl PeerInfo {
             cluster_id,
             cluster_auth_signature,
             last_heartbeat: AtomicU64::new(current_time_seconds()),
+            liveness: PeerLiveness::default(),
+            replay_window: ReplayWindow::default(),
+            zone: None,
         }
     }
 
@@ -121,6 +201,16 @@ impl PeerInfo {
         self.cluster_id.clone()
     }
 
+    /// Get the failure domain the peer runs in
+    pub fn get_zone(&self) -> Option<String> {
+        self.zone.clone()
+    }
+
+    /// Set the failure domain the peer runs in
+    pub fn set_zone(&mut self, zone: Option<String>) {
+        self.zone = zone;
+    }
+
     /// Records a successful heartbeat
     pub fn successful_heartbeat(&self) {
         self.last_heartbeat
@@ -131,6 +221,22 @@ impl PeerInfo {
     pub fn get_last_heartbeat(&self) -> u64 {
         self.last_heartbeat.load(Ordering::Relaxed)
     }
+
+    /// Get the liveness of the peer
+    pub fn get_liveness(&self) -> PeerLiveness {
+        self.liveness
+    }
+
+    /// Set the liveness of the peer, returning its previous liveness
+    pub fn set_liveness(&mut self, liveness: PeerLiveness) -> PeerLiveness {
+        std::mem::replace(&mut self.liveness, liveness)
+    }
+
+    /// Accept a heartbeat sequence number from the peer, returns false if the
+    /// sequence number is a replay or has fallen out of the replay window
+    pub fn accept_heartbeat_sequence(&mut self, sequence: u64) -> bool {
+        self.replay_window.accept(sequence)
+    }
 }
 
 /// Clones PeerInfo to reference the current time for the last heartbeat
@@ -142,6 +248,9 @@ impl Clone for PeerInfo {
             addr: self.addr.clone(),
             cluster_auth_signature: self.cluster_auth_signature.clone(),
             last_heartbeat: AtomicU64::new(self.last_heartbeat.load(Ordering::Relaxed)),
+            liveness: self.liveness,
+            replay_window: self.replay_window,
+            zone: self.zone.clone(),
         }
     }
 }
@@ -247,6 +356,18 @@ impl ClusterId {
         let decoded_key = base64::decode(&self.0).map_err(|_| SignatureError::new())?;
         PublicKey::from_bytes(&decoded_key)
     }
+
+    /// Verify that a key rotation announcement is for this cluster and is signed by the
+    /// cluster's master key, i.e. the key this cluster ID encodes
+    pub fn verify_key_rotation(&self, rotation: &ClusterKeyRotation) -> Result<(), SignatureError> {
+        if rotation.cluster_id != *self {
+            return Err(SignatureError::new());
+        }
+
+        let sig = Signature::from_bytes(&rotation.signature)?;
+        self.get_public_key()?
+            .verify_prehashed(rotation.digest(), None /* context */, &sig)
+    }
 }
 
 impl Display for ClusterId {
@@ -283,7 +404,7 @@ mod types_test {
     use libp2p::{identity::Keypair, Multiaddr, PeerId};
     use rand_core::OsRng;
 
-    use super::{ClusterId, PeerInfo, WrappedPeerId};
+    use super::{ClusterId, PeerInfo, PeerLiveness, ReplayWindow, WrappedPeerId};
 
     /// Tests that message serialization and deserialization works properly
     #[test]
@@ -300,7 +421,10 @@ mod types_test {
             cluster_id,
             cluster_auth_signature: Vec::new(),
             last_heartbeat: AtomicU64::new(0),
+            liveness: PeerLiveness::default(),
+            replay_window: ReplayWindow::default(),
             addr: Multiaddr::empty(),
+            zone: None,
         };
 
         let serialized = serde_json::to_string(&peer_info).unwrap();
@@ -308,4 +432,26 @@ mod types_test {
 
         assert_eq!(peer_info, deserialized)
     }
+
+    /// Tests that the replay window accepts each sequence number once, tolerates
+    /// reordering within the window, and rejects sequence numbers that fall out of it
+    #[test]
+    fn test_replay_window() {
+        let mut window = ReplayWindow::default();
+        assert!(!window.accept(0));
+
+        assert!(window.accept(1));
+        assert!(window.accept(3));
+        assert!(!window.accept(3));
+
+        // A delayed sequence number is accepted once
+        assert!(window.accept(2));
+        assert!(!window.accept(2));
+        assert!(!window.accept(1));
+
+        // Sequence numbers that fall out of the window are rejected, even if unseen
+        assert!(window.accept(100));
+        assert!(!window.accept(4));
+        assert!(window.accept(37));
+    }
 }
@@ -4,16 +4,18 @@ use futures::executor::block_on;
 use libp2p::Multiaddr;
 use std::thread::{Builder, JoinHandle};
 use tokio::runtime::Builder as RuntimeBuilder;
-use tokio::sync::mpsc::{UnboundedReceiver as TokioReceiver, UnboundedSender as TokioSender};
 
 use crate::default_wrapper::DefaultWrapper;
+use crate::job_queue::{JobQueueReceiver, JobQueueSender};
 use crate::starknet_client::client::StarknetClient;
 use crate::{
-    gossip_api::gossip::GossipOutbound, state::RelayerState, worker::Worker, CancelChannel,
+    clock::SharedClock, gossip_api::gossip::GossipOutbound, state::RelayerState, worker::Worker,
+    CancelChannel,
 };
 
 use super::server::{GOSSIP_EXECUTOR_N_BLOCKING_THREADS, GOSSIP_EXECUTOR_N_THREADS};
 use super::{
+    erasure::ErasureCoding,
     errors::GossipError,
     jobs::GossipServerJob,
     server::{GossipProtocolExecutor, GossipServer},
@@ -31,17 +33,28 @@ pub struct GossipServerConfig {
     pub cluster_id: ClusterId,
     /// The servers to bootstrap into the network with
     pub bootstrap_servers: Vec<(WrappedPeerId, Multiaddr)>,
+    /// The number of cluster peers each wallet is replicated to, `None` replicates
+    /// every wallet to every cluster peer
+    pub replication_factor: Option<usize>,
+    /// The erasure code wallets are sharded with across the cluster, `None` replicates
+    /// wallets only in full
+    pub erasure_coding: Option<ErasureCoding>,
+    /// The number of minor versions the local relayer may fall behind its cluster's
+    /// majority version before a warning is logged
+    pub max_version_lag: u64,
     /// The starknet client used to connect to sequencer gateway
     /// and jsonrpc nodes
     pub starknet_client: StarknetClient,
     /// A reference to the relayer-global state
     pub global_state: RelayerState,
+    /// The clock that heartbeat timestamps are checked against
+    pub clock: SharedClock,
     /// A job queue to send outbound heartbeat requests on
-    pub(crate) job_sender: TokioSender<GossipServerJob>,
+    pub(crate) job_sender: JobQueueSender<GossipServerJob>,
     /// A job queue to receive inbound heartbeat requests on
-    pub(crate) job_receiver: DefaultWrapper<Option<TokioReceiver<GossipServerJob>>>,
+    pub(crate) job_receiver: DefaultWrapper<Option<JobQueueReceiver<GossipServerJob>>>,
     /// A job queue to send outbound network requests on
-    pub network_sender: TokioSender<GossipOutbound>,
+    pub network_sender: JobQueueSender<GossipOutbound>,
     /// The channel on which the coordinator may mandate that the
     /// gossip server cancel its execution
     pub cancel_channel: CancelChannel,
@@ -59,7 +72,9 @@ impl Worker for GossipServer {
     }
 
     fn is_recoverable(&self) -> bool {
-        true
+        // The protocol executor consumes the job queue, so a failed server cannot be
+        // re-allocated
+        false
     }
 
     fn name(&self) -> String {
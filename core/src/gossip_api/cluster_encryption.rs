@@ -0,0 +1,216 @@
+//! Defines the encrypted envelope that intra-cluster pubsub messages are published in
+//!
+//! Cluster management messages are signed with the cluster keypair, but a signature alone leaves
+//! the message readable by every peer that relays the topic. Messages that require encryption are
+//! sealed with a symmetric key derived from the cluster's current epoch key, which only cluster
+//! members hold; the symmetric key therefore rotates along with the epoch key
+
+use std::convert::TryFrom;
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use ed25519_dalek::Keypair as SigKeypair;
+use hmac_sha256::HMAC;
+use serde::{Deserialize, Serialize};
+
+use super::gossip::AuthenticatedPubsubMessage;
+
+/// The domain separator the cluster's symmetric key is derived under
+const CLUSTER_KEY_DOMAIN: &[u8] = b"renegade-cluster-gossip-encryption";
+/// The length of the nonce a message is encrypted under
+const NONCE_LENGTH: usize = 12;
+
+/// A symmetric key shared by the members of a cluster, used to encrypt intra-cluster
+/// pubsub messages
+#[derive(Clone)]
+pub struct ClusterSymmetricKey {
+    /// The AEAD cipher keyed with the derived key
+    cipher: ChaCha20Poly1305,
+}
+
+impl ClusterSymmetricKey {
+    /// Derive the symmetric key of an epoch from the secret of the epoch's key
+    pub fn derive(epoch_key: &SigKeypair) -> Self {
+        let key_bytes = HMAC::mac(CLUSTER_KEY_DOMAIN, epoch_key.secret.as_bytes());
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key_bytes)),
+        }
+    }
+
+    /// Encrypt an authenticated message under a fresh random nonce
+    pub fn encrypt(&self, message: &AuthenticatedPubsubMessage) -> EncryptedPubsubMessage {
+        let (nonce, ciphertext) = self.encrypt_bytes(&serde_json::to_vec(message).unwrap());
+        EncryptedPubsubMessage { nonce, ciphertext }
+    }
+
+    /// Decrypt a message, returns `None` if the message was not encrypted under this key or
+    /// has been tampered with
+    pub fn decrypt(&self, message: &EncryptedPubsubMessage) -> Option<AuthenticatedPubsubMessage> {
+        let plaintext = self.decrypt_bytes(&message.nonce, &message.ciphertext)?;
+        serde_json::from_slice(&plaintext).ok()
+    }
+
+    /// Encrypt a secret, e.g. the key of the next epoch, under a fresh random nonce
+    pub fn seal_secret(&self, secret: &[u8]) -> SealedSecret {
+        let (nonce, ciphertext) = self.encrypt_bytes(secret);
+        SealedSecret { nonce, ciphertext }
+    }
+
+    /// Decrypt a sealed secret, returns `None` if it was not sealed under this key
+    pub fn open_secret(&self, sealed: &SealedSecret) -> Option<Vec<u8>> {
+        self.decrypt_bytes(&sealed.nonce, &sealed.ciphertext)
+    }
+
+    /// Encrypt a plaintext under a fresh random nonce, returning the nonce and ciphertext
+    fn encrypt_bytes(&self, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .expect("message exceeds the maximum AEAD plaintext length");
+
+        (nonce.to_vec(), ciphertext)
+    }
+
+    /// Decrypt a ciphertext, returns `None` if it does not authenticate under this key
+    fn decrypt_bytes(&self, nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        if nonce.len() != NONCE_LENGTH {
+            return None;
+        }
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()
+    }
+}
+
+/// A secret encrypted under a cluster's symmetric key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SealedSecret {
+    /// The nonce the secret was encrypted under
+    pub nonce: Vec<u8>,
+    /// The secret, encrypted and tagged
+    pub ciphertext: Vec<u8>,
+}
+
+/// An authenticated pubsub message encrypted under the cluster's symmetric key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptedPubsubMessage {
+    /// The nonce the message was encrypted under
+    pub nonce: Vec<u8>,
+    /// The serialized `AuthenticatedPubsubMessage`, encrypted and tagged
+    pub ciphertext: Vec<u8>,
+}
+
+/// The envelope a pubsub message is published in
+///
+/// The envelope is untagged so that plaintext messages keep the wire format of a bare
+/// `AuthenticatedPubsubMessage`, and remain readable by peers that predate encryption
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum PubsubEnvelope {
+    /// A message published in the clear, e.g. on a network-wide topic
+    Plaintext(AuthenticatedPubsubMessage),
+    /// A message encrypted for the members of the publisher's cluster
+    Encrypted(EncryptedPubsubMessage),
+}
+
+impl PubsubEnvelope {
+    /// Wrap an authenticated message, encrypting it if its body requires encryption
+    pub fn seal(message: AuthenticatedPubsubMessage, key: &ClusterSymmetricKey) -> Self {
+        if message.body.requires_encryption() {
+            PubsubEnvelope::Encrypted(key.encrypt(&message))
+        } else {
+            PubsubEnvelope::Plaintext(message)
+        }
+    }
+
+    /// Unwrap the authenticated message, decrypting it if necessary; returns `None` if an
+    /// encrypted message does not decrypt under the key
+    pub fn open(self, key: &ClusterSymmetricKey) -> Option<AuthenticatedPubsubMessage> {
+        match self {
+            PubsubEnvelope::Plaintext(message) => Some(message),
+            PubsubEnvelope::Encrypted(message) => key.decrypt(&message),
+        }
+    }
+}
+
+/// Explicit byte serialization and deserialization
+///
+/// libp2p gossipsub interface expects a type that can be cast
+/// to and from bytes
+impl From<PubsubEnvelope> for Vec<u8> {
+    fn from(envelope: PubsubEnvelope) -> Self {
+        serde_json::to_vec(&envelope).unwrap()
+    }
+}
+
+impl TryFrom<Vec<u8>> for PubsubEnvelope {
+    type Error = serde_json::Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        serde_json::from_slice(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ed25519_dalek::Keypair as SigKeypair;
+    use libp2p::PeerId;
+    use rand_core::OsRng;
+
+    use crate::{
+        gossip::types::{ClusterId, WrappedPeerId},
+        gossip_api::{
+            cluster_management::{ClusterManagementMessage, ReplicatedMessage},
+            gossip::{AuthenticatedPubsubMessage, PubsubMessage},
+        },
+    };
+
+    use super::{ClusterSymmetricKey, PubsubEnvelope};
+
+    /// Tests that cluster messages are sealed so that only the cluster may open them, and
+    /// that plaintext messages keep their wire format
+    #[test]
+    fn test_seal_cluster_message() {
+        let mut rng = OsRng {};
+        let cluster_keypair = SigKeypair::generate(&mut rng);
+        let key = ClusterSymmetricKey::derive(&cluster_keypair);
+        let other_key = ClusterSymmetricKey::derive(&SigKeypair::generate(&mut rng));
+
+        let body = PubsubMessage::ClusterManagement {
+            cluster_id: ClusterId::new(&cluster_keypair.public),
+            message: ClusterManagementMessage::Replicated(ReplicatedMessage {
+                wallets: vec![],
+                peer_id: WrappedPeerId(PeerId::random()),
+            }),
+        };
+        let message = AuthenticatedPubsubMessage::new_with_body(body, &cluster_keypair).unwrap();
+        let envelope = PubsubEnvelope::seal(message.clone(), &key);
+        assert!(matches!(envelope, PubsubEnvelope::Encrypted(_)));
+
+        let bytes: Vec<u8> = envelope.into();
+        let opened = PubsubEnvelope::try_from(bytes.clone())
+            .unwrap()
+            .open(&key)
+            .unwrap();
+        assert_eq!(opened.sig, message.sig);
+        assert!(opened.verify_cluster_auth(&cluster_keypair.public));
+        assert!(PubsubEnvelope::try_from(bytes)
+            .unwrap()
+            .open(&other_key)
+            .is_none());
+
+        // A bare authenticated message parses as a plaintext envelope
+        let plaintext = serde_json::to_vec(&message).unwrap();
+        assert!(matches!(
+            PubsubEnvelope::try_from(plaintext).unwrap(),
+            PubsubEnvelope::Plaintext(_)
+        ));
+    }
+}
@@ -1,13 +1,15 @@
 //! Groups message definitions for cluster management, mostly pubsub
 
+use ed25519_dalek::{Digest, Keypair, PublicKey, SecretKey, Sha512, SignatureError};
 use libp2p::Multiaddr;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     gossip::types::{ClusterId, PeerInfo, WrappedPeerId},
+    gossip_api::cluster_encryption::{ClusterSymmetricKey, SealedSecret},
     state::{
-        wallet::{Wallet, WalletIdentifier},
-        OrderIdentifier,
+        wallet::{Wallet, WalletDelta, WalletIdentifier},
+        NetworkOrder, OrderIdentifier,
     },
 };
 
@@ -19,6 +21,7 @@ pub const CLUSTER_MANAGEMENT_TOPIC_PREFIX: &str = "cluster-management";
 
 /// Represents a message containing cluster management information
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum ClusterManagementMessage {
     /// A cluster join message, indicating that a peer wishes to join
     Join(ClusterJoinMessage),
@@ -38,6 +41,12 @@ pub enum ClusterManagementMessage {
     /// The peers should cache this order pair as completed, and not initiate handshakes
     /// with other peers on this order
     CacheSync(OrderIdentifier, OrderIdentifier),
+    /// A request from a peer for the full set of order pairs its cluster peers have cached
+    /// as completed
+    ///
+    /// This request is sent during gossip bootstrap so that a restarting peer does not
+    /// re-attempt matches on pairs its cluster has already run
+    CacheSyncRequest(CacheSyncRequest),
     /// A request from a peer for a proof of `VALID COMMITMENTS` for a given order
     ///
     /// This request is sent when a peer replicates a wallet, but does not receive a proof
@@ -47,6 +56,18 @@ pub enum ClusterManagementMessage {
     /// A request from a peer to its cluster for a copy of the witness to `VALID COMMITMENTS`
     /// for a given order
     RequestOrderValidityWitness(ValidityWitnessRequest),
+    /// An announcement of the cluster leader for an election term
+    ///
+    /// The leader alone proves `VALID COMMITMENTS` for the cluster's wallets and replicates
+    /// wallets to joining peers
+    LeaderElected(LeaderElectedMessage),
+    /// An update to an order in the publisher's book, broadcast while the publisher
+    /// serves an order book snapshot so that the peers syncing from it may catch up
+    /// on changes made after the snapshot was taken
+    OrderBookDelta(OrderBookDelta),
+    /// An announcement that the cluster has rotated the key it signs gossip with to a
+    /// new epoch
+    KeyRotation(ClusterKeyRotation),
 }
 
 impl From<&ClusterManagementMessage> for Vec<u8> {
@@ -81,6 +102,110 @@ pub struct ReplicatedMessage {
     pub peer_id: WrappedPeerId,
 }
 
+/// Announces the leader of the publisher's cluster for a given election term
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LeaderElectedMessage {
+    /// The elected leader
+    pub leader: WrappedPeerId,
+    /// The election term the leader was elected in
+    pub term: u64,
+}
+
+/// Announces the key a cluster signs its gossip with from the given epoch on
+///
+/// The announcement is signed by the cluster's master key, so it is verified against
+/// the `ClusterId` rather than against any epoch key. The epoch's secret key is sealed
+/// under the symmetric key of the epoch before it, so that only peers holding the
+/// previous epoch's key may sign under the new one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClusterKeyRotation {
+    /// The cluster whose key is rotated
+    pub cluster_id: ClusterId,
+    /// The epoch the key is used in
+    pub epoch: u64,
+    /// The public key of the epoch
+    pub epoch_public_key: Vec<u8>,
+    /// The secret key of the epoch, sealed under the previous epoch's symmetric key
+    pub sealed_epoch_secret: SealedSecret,
+    /// A signature of the above fields by the cluster's master key
+    pub signature: Vec<u8>,
+}
+
+impl ClusterKeyRotation {
+    /// Construct an announcement of the given epoch key, sealing its secret under the
+    /// previous epoch's symmetric key and signing the announcement with the master key
+    pub fn new(
+        cluster_id: ClusterId,
+        epoch: u64,
+        epoch_key: &Keypair,
+        previous_epoch_key: &ClusterSymmetricKey,
+        master_key: &Keypair,
+    ) -> Result<Self, SignatureError> {
+        let mut rotation = Self {
+            cluster_id,
+            epoch,
+            epoch_public_key: epoch_key.public.as_bytes().to_vec(),
+            sealed_epoch_secret: previous_epoch_key.seal_secret(epoch_key.secret.as_bytes()),
+            signature: Vec::new(),
+        };
+        rotation.signature = master_key
+            .sign_prehashed(rotation.digest(), None /* context */)?
+            .to_bytes()
+            .to_vec();
+
+        Ok(rotation)
+    }
+
+    /// The digest of the announcement that the master key signs
+    pub fn digest(&self) -> Sha512 {
+        let mut hash_digest = Sha512::new();
+        hash_digest.update(
+            &serde_json::to_vec(&(
+                &self.cluster_id,
+                self.epoch,
+                &self.epoch_public_key,
+                &self.sealed_epoch_secret,
+            ))
+            .unwrap(),
+        );
+        hash_digest
+    }
+
+    /// Parse the announced epoch key
+    pub fn epoch_public_key(&self) -> Result<PublicKey, SignatureError> {
+        PublicKey::from_bytes(&self.epoch_public_key)
+    }
+
+    /// Open the epoch's secret key under the previous epoch's symmetric key, returns `None`
+    /// if the secret was not sealed under that key or is not the secret of the announced
+    /// public key
+    pub fn open_epoch_key(&self, previous_epoch_key: &ClusterSymmetricKey) -> Option<Keypair> {
+        let secret_bytes = previous_epoch_key.open_secret(&self.sealed_epoch_secret)?;
+        let secret = SecretKey::from_bytes(&secret_bytes).ok()?;
+        let public = PublicKey::from(&secret);
+        if public.as_bytes()[..] != self.epoch_public_key[..] {
+            return None;
+        }
+
+        Some(Keypair { secret, public })
+    }
+}
+
+/// The body of a cache sync request published to a cluster
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheSyncRequest {
+    /// The address that a response should be sent back to
+    pub sender: WrappedPeerId,
+}
+
+/// A response to a cache sync request, carrying the order pairs the sender has cached as
+/// completed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheSyncResponse {
+    /// The completed order pairs
+    pub completed_pairs: Vec<(OrderIdentifier, OrderIdentifier)>,
+}
+
 /// A message asking a peer to replicate a set of wallets
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ReplicateRequestBody {
@@ -88,6 +213,79 @@ pub struct ReplicateRequestBody {
     pub wallets: Vec<Wallet>,
 }
 
+/// A request from a cluster peer whose replica of a wallet has fallen behind, asking
+/// for the deltas it is missing
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplicaRepairRequest {
+    /// The wallet whose replica is stale
+    pub wallet_id: WalletIdentifier,
+    /// The version of the requester's replica
+    pub since_version: u64,
+    /// The address that a response should be sent back to
+    pub sender: WrappedPeerId,
+}
+
+/// A response to a replica repair request, carrying the deltas the requester is missing
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplicaRepairResponse {
+    /// The wallet being repaired
+    pub wallet_id: WalletIdentifier,
+    /// The deltas to apply to the stale replica, in version order
+    pub deltas: Vec<WalletDelta>,
+}
+
+/// One erasure-coded shard of a wallet, any `data_shards` of the wallet's `total_shards`
+/// shards reassemble the wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalletShard {
+    /// The wallet the shard was encoded from
+    pub wallet_id: WalletIdentifier,
+    /// The version of the wallet when the shard was encoded, only shards of the same version
+    /// may be reassembled together
+    pub version: u64,
+    /// The index of the shard in the encoding, the first `data_shards` shards hold the
+    /// serialized wallet and the rest hold parity
+    pub index: usize,
+    /// The number of shards needed to reassemble the wallet
+    pub data_shards: usize,
+    /// The total number of shards the wallet was encoded into
+    pub total_shards: usize,
+    /// The length of the serialized wallet, before it was padded to a multiple of the
+    /// shard length
+    pub wallet_len: usize,
+    /// The peer assigned each shard, indexed by shard
+    pub holders: Vec<WrappedPeerId>,
+    /// The peers that held the wallet in full when the shard was encoded
+    pub replicas: Vec<WrappedPeerId>,
+    /// The shard contents
+    pub data: Vec<u8>,
+}
+
+/// A message asking a peer to store a set of wallet shards
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplicateShardsBody {
+    /// The shards assigned to the recipient
+    pub shards: Vec<WalletShard>,
+}
+
+/// A request from a cluster peer reassembling a wallet for the shards the recipient holds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalletShardRequest {
+    /// The wallet being reassembled
+    pub wallet_id: WalletIdentifier,
+    /// The address that a response should be sent back to
+    pub sender: WrappedPeerId,
+}
+
+/// A response to a wallet shard request, carrying the shards the sender holds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalletShardResponse {
+    /// The wallet being reassembled
+    pub wallet_id: WalletIdentifier,
+    /// The shards of the wallet held by the sender
+    pub shards: Vec<WalletShard>,
+}
+
 /// A message asking a peer to prove they are part of a given cluster
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ClusterAuthRequest {
@@ -130,3 +328,15 @@ pub struct ValidityWitnessRequest {
     /// The address that a response should be sent back to
     pub sender: WrappedPeerId,
 }
+
+/// An update to an order in the publisher's order book
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderBookDelta {
+    /// The peer that published the delta
+    pub sender: WrappedPeerId,
+    /// The publisher's sequence number for the delta, strictly increasing across every
+    /// delta the publisher broadcasts
+    pub sequence: u64,
+    /// The order as it stands in the publisher's book after the update
+    pub order: NetworkOrder,
+}
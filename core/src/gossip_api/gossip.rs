@@ -1,23 +1,33 @@
 //! Groups API definitions for standard gossip network requests/responses
 
+use std::{convert::TryFrom, fmt::Debug, str::FromStr, sync::Arc};
+
 use ed25519_dalek::{Digest, Keypair as SigKeypair, PublicKey, Sha512, Signature, SignatureError};
 use libp2p::{request_response::ResponseChannel, Multiaddr};
 use portpicker::Port;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender as TokioSender;
 use uuid::Uuid;
 
 use crate::{
     gossip::types::{ClusterId, WrappedPeerId},
+    job_queue::{BoundedJob, JobOverflowPolicy, JobQueueSender},
     proof_generation::jobs::ValidCommitmentsBundle,
     state::OrderIdentifier,
     types::SizedValidCommitmentsWitness,
 };
 
 use super::{
-    cluster_management::{ClusterManagementMessage, ReplicateRequestBody},
-    handshake::HandshakeMessage,
+    cluster_management::{
+        CacheSyncResponse, ClusterManagementMessage, ReplicaRepairRequest, ReplicaRepairResponse,
+        ReplicateRequestBody, ReplicateShardsBody, WalletShardRequest, WalletShardResponse,
+    },
+    handshake::{BrokerMessage, HandshakeMessage},
     heartbeat::{BootstrapRequest, HeartbeatMessage},
-    orderbook_management::{OrderBookManagementMessage, OrderInfoRequest, OrderInfoResponse},
+    orderbook_management::{
+        OrderBookManagementMessage, OrderBookSnapshotChunk, OrderBookSnapshotRequest,
+        OrderInfoRequest, OrderInfoResponse,
+    },
 };
 
 /// Represents an outbound gossip message, either a request to a peer
@@ -51,6 +61,39 @@ pub enum GossipOutbound {
     ManagementMessage(ManagerControlDirective),
 }
 
+/// A channel on which a worker forwards outbound gossip to the network
+///
+/// In the relayer this is the network manager's work queue; the simulation harness injects a
+/// loopback network in its place that delivers messages to simulated peers in-process
+pub trait NetworkChannel: Debug + Send + Sync {
+    /// Forward a message to the network, failing if the network has shut down
+    fn send(&self, message: GossipOutbound) -> Result<(), String>;
+}
+
+/// A handle to a `NetworkChannel` implementation shared between threads
+pub type SharedNetworkChannel = Arc<dyn NetworkChannel>;
+
+impl NetworkChannel for JobQueueSender<GossipOutbound> {
+    fn send(&self, message: GossipOutbound) -> Result<(), String> {
+        JobQueueSender::send(self, message).map_err(|err| err.to_string())
+    }
+}
+
+impl BoundedJob for GossipOutbound {
+    /// Outbound gossip is dropped under load; heartbeats and order book gossip are re-sent
+    /// periodically, and requests awaiting a response time out
+    ///
+    /// Directives to the network manager are not re-sent, a lost warmup directive leaves the
+    /// pubsub buffer unflushed and a lost MPC net directive stalls its handshake; so they are
+    /// returned to the sender
+    fn overflow_policy(&self) -> JobOverflowPolicy {
+        match self {
+            GossipOutbound::ManagementMessage(..) => JobOverflowPolicy::Reject,
+            _ => JobOverflowPolicy::Drop,
+        }
+    }
+}
+
 /// A wrapper around the GossipRequest type that allows us to attach cluster signatures
 /// to each request
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -120,10 +163,35 @@ pub enum GossipRequest {
         /// The message contents
         message: HandshakeMessage,
     },
+    /// A request exchanged while a third peer brokers a handshake between two foreign
+    /// orders
+    BrokeredHandshake {
+        /// The request ID; shared by the broker and both managing peers
+        request_id: Uuid,
+        /// The message contents
+        message: BrokerMessage,
+    },
     /// A request for order information from a peer
     OrderInfo(OrderInfoRequest),
+    /// A request from a bootstrapping cluster peer for a chunk of the recipient's order
+    /// book snapshot
+    OrderBookSnapshot(OrderBookSnapshotRequest),
     /// A request that a peer replicate a set of wallets
     Replicate(ReplicateRequestBody),
+    /// A request from a cluster peer for the deltas needed to repair its stale replica
+    /// of a wallet
+    ReplicaRepair(ReplicaRepairRequest),
+    /// A pushed message carrying the deltas requested in a `ReplicaRepair` request
+    ReplicaRepairResponse(ReplicaRepairResponse),
+    /// A request that a peer store a set of erasure-coded wallet shards
+    ReplicateShards(ReplicateShardsBody),
+    /// A request from a cluster peer for the shards of a wallet it is reassembling
+    WalletShardRequest(WalletShardRequest),
+    /// A pushed message carrying the shards requested in a `WalletShardRequest`
+    WalletShardResponse(WalletShardResponse),
+    /// A pushed message carrying the completed order pairs requested in a cluster
+    /// `CacheSyncRequest`
+    CacheSyncResponse(CacheSyncResponse),
     /// A pushed message forwarded from the sender when a proof of `VALID COMMITMENTS` is
     /// requested, updated, or constructed for the first time
     ValidityProof {
@@ -143,6 +211,11 @@ pub enum GossipRequest {
         /// The witness used to prove `VALID COMMITMENTS`
         witness: SizedValidCommitmentsWitness,
     },
+    /// A batch of requests to the same peer, coalesced by the network manager
+    ///
+    /// Each request in the batch carries its own cluster signature, and only requests that
+    /// are answered with a simple ack may be batched
+    Batch(Vec<AuthenticatedGossipRequest>),
 }
 
 impl GossipRequest {
@@ -155,14 +228,135 @@ impl GossipRequest {
             GossipRequest::Bootstrap(..) => false,
             GossipRequest::Heartbeat(..) => false,
             GossipRequest::Handshake { .. } => false,
+            GossipRequest::BrokeredHandshake { .. } => false,
             GossipRequest::OrderInfo(..) => false,
+            GossipRequest::OrderBookSnapshot(..) => true,
             GossipRequest::Replicate(..) => false,
+            GossipRequest::ReplicaRepair(..) => true,
+            GossipRequest::ReplicaRepairResponse(..) => true,
+            GossipRequest::ReplicateShards(..) => true,
+            GossipRequest::WalletShardRequest(..) => true,
+            GossipRequest::WalletShardResponse(..) => true,
+            GossipRequest::CacheSyncResponse(..) => true,
+            GossipRequest::ValidityProof { .. } => true,
+            GossipRequest::ValidityWitness { .. } => true,
+            GossipRequest::Batch(..) => false,
+        }
+    }
+
+    /// Explicitly states which requests may be coalesced into a `Batch` by the network
+    /// manager; i.e. those that the recipient answers with an ack
+    ///
+    /// As above, the code here is intentionally verbose so that new request types are
+    /// defined with batching in mind
+    pub fn is_batchable(&self) -> bool {
+        match self {
+            GossipRequest::Bootstrap(..) => false,
+            GossipRequest::Heartbeat(..) => false,
+            GossipRequest::Handshake { .. } => false,
+            GossipRequest::BrokeredHandshake { .. } => false,
+            GossipRequest::OrderInfo(..) => false,
+            GossipRequest::OrderBookSnapshot(..) => false,
+            GossipRequest::Replicate(..) => true,
+            GossipRequest::ReplicaRepair(..) => true,
+            GossipRequest::ReplicaRepairResponse(..) => true,
+            GossipRequest::ReplicateShards(..) => true,
+            GossipRequest::WalletShardRequest(..) => true,
+            GossipRequest::WalletShardResponse(..) => true,
+            GossipRequest::CacheSyncResponse(..) => true,
             GossipRequest::ValidityProof { .. } => true,
             GossipRequest::ValidityWitness { .. } => true,
+            GossipRequest::Batch(..) => false,
+        }
+    }
+
+    /// The class of the request, inbound requests are rate limited per class
+    ///
+    /// As above, the code here is intentionally verbose so that new request types are
+    /// assigned a class with rate limiting in mind
+    pub fn request_type(&self) -> GossipRequestType {
+        match self {
+            GossipRequest::Bootstrap(..) => GossipRequestType::Bootstrap,
+            GossipRequest::Heartbeat(..) => GossipRequestType::Heartbeat,
+            GossipRequest::Handshake { .. } => GossipRequestType::Handshake,
+            GossipRequest::BrokeredHandshake { .. } => GossipRequestType::Handshake,
+            GossipRequest::OrderInfo(..) => GossipRequestType::OrderInfo,
+            GossipRequest::OrderBookSnapshot(..) => GossipRequestType::OrderBookSnapshot,
+            GossipRequest::Replicate(..) => GossipRequestType::Replication,
+            GossipRequest::ReplicaRepair(..) => GossipRequestType::Replication,
+            GossipRequest::ReplicaRepairResponse(..) => GossipRequestType::Replication,
+            GossipRequest::ReplicateShards(..) => GossipRequestType::Replication,
+            GossipRequest::WalletShardRequest(..) => GossipRequestType::Replication,
+            GossipRequest::WalletShardResponse(..) => GossipRequestType::Replication,
+            GossipRequest::CacheSyncResponse(..) => GossipRequestType::Replication,
+            GossipRequest::ValidityProof { .. } => GossipRequestType::Validity,
+            GossipRequest::ValidityWitness { .. } => GossipRequestType::Validity,
+            GossipRequest::Batch(..) => GossipRequestType::Batch,
+        }
+    }
+}
+
+/// The classes of gossip request, each of which is rate limited separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GossipRequestType {
+    /// Requests to bootstrap from the recipient
+    Bootstrap,
+    /// Heartbeat requests
+    Heartbeat,
+    /// Handshake messages, whether exchanged directly or through a broker
+    Handshake,
+    /// Requests for order information
+    OrderInfo,
+    /// Requests for a chunk of the recipient's order book snapshot
+    OrderBookSnapshot,
+    /// Wallet replication and repair messages between cluster peers
+    Replication,
+    /// Pushed validity proofs and witnesses
+    Validity,
+    /// Batches of requests; each request in the batch is also counted against its own class
+    Batch,
+}
+
+/// Every class of gossip request
+pub const ALL_GOSSIP_REQUEST_TYPES: [GossipRequestType; 8] = [
+    GossipRequestType::Bootstrap,
+    GossipRequestType::Heartbeat,
+    GossipRequestType::Handshake,
+    GossipRequestType::OrderInfo,
+    GossipRequestType::OrderBookSnapshot,
+    GossipRequestType::Replication,
+    GossipRequestType::Validity,
+    GossipRequestType::Batch,
+];
+
+impl GossipRequestType {
+    /// The name the class is configured by
+    pub fn name(&self) -> &'static str {
+        match self {
+            GossipRequestType::Bootstrap => "bootstrap",
+            GossipRequestType::Heartbeat => "heartbeat",
+            GossipRequestType::Handshake => "handshake",
+            GossipRequestType::OrderInfo => "order_info",
+            GossipRequestType::OrderBookSnapshot => "order_book_snapshot",
+            GossipRequestType::Replication => "replication",
+            GossipRequestType::Validity => "validity",
+            GossipRequestType::Batch => "batch",
         }
     }
 }
 
+impl FromStr for GossipRequestType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_GOSSIP_REQUEST_TYPES
+            .iter()
+            .find(|request_type| request_type.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown gossip request type: {}", s))
+    }
+}
+
 /// A wrapper around the `GossipResponse` type that allows us to attach signatures
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AuthenticatedGossipResponse {
@@ -240,8 +434,17 @@ pub enum GossipResponse {
         /// The message contents
         message: HandshakeMessage,
     },
+    /// A response from a peer to a broker's request
+    BrokeredHandshake {
+        /// The request ID; shared by the broker and both managing peers
+        request_id: Uuid,
+        /// The message contents
+        message: BrokerMessage,
+    },
     /// A response to a request for order information
     OrderInfo(OrderInfoResponse),
+    /// A response carrying a chunk of the sender's order book snapshot
+    OrderBookSnapshot(OrderBookSnapshotChunk),
 }
 
 impl GossipResponse {
@@ -254,7 +457,9 @@ impl GossipResponse {
             GossipResponse::Ack => false,
             GossipResponse::Heartbeat(..) => false,
             GossipResponse::Handshake { .. } => false,
+            GossipResponse::BrokeredHandshake { .. } => false,
             GossipResponse::OrderInfo(..) => false,
+            GossipResponse::OrderBookSnapshot(..) => true,
         }
     }
 }
@@ -320,9 +525,11 @@ impl From<AuthenticatedPubsubMessage> for Vec<u8> {
     }
 }
 
-impl From<Vec<u8>> for AuthenticatedPubsubMessage {
-    fn from(bytes: Vec<u8>) -> Self {
-        serde_json::from_slice(&bytes).unwrap()
+impl TryFrom<Vec<u8>> for AuthenticatedPubsubMessage {
+    type Error = serde_json::Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        serde_json::from_slice(&bytes)
     }
 }
 
@@ -352,6 +559,30 @@ impl PubsubMessage {
     /// to be defined with authentication in mind
     pub fn requires_cluster_auth(&self) -> bool {
         match self {
+            // A key rotation is signed by the cluster's master key in its body, so that peers
+            // still on an earlier epoch may verify it
+            PubsubMessage::ClusterManagement {
+                message: ClusterManagementMessage::KeyRotation(..),
+                ..
+            } => false,
+            PubsubMessage::ClusterManagement { .. } => true,
+            PubsubMessage::OrderBookManagement(..) => false,
+        }
+    }
+
+    /// Explicitly states which messages are encrypted for the publisher's cluster
+    ///
+    /// Messages published on a topic only the cluster subscribes to should be encrypted,
+    /// network-wide messages must remain readable by every peer
+    pub fn requires_encryption(&self) -> bool {
+        match self {
+            // A key rotation is published in the clear, peers still on the previous epoch
+            // cannot open a message sealed under the new epoch's key; the epoch secret it
+            // carries is sealed on its own
+            PubsubMessage::ClusterManagement {
+                message: ClusterManagementMessage::KeyRotation(..),
+                ..
+            } => false,
             PubsubMessage::ClusterManagement { .. } => true,
             PubsubMessage::OrderBookManagement(..) => false,
         }
@@ -385,6 +616,17 @@ pub enum ManagerControlDirective {
         /// The new address
         address: Multiaddr,
     },
+    /// A command signalling to the network manager to look up the peers that have published
+    /// provider records for the given order in the DHT
+    ///
+    /// Used when the local node has not learned of a manager for the order through gossip.
+    /// The providers found, possibly none, are sent on the response channel
+    FindOrderProviders {
+        /// The order to find the managers of
+        order_id: OrderIdentifier,
+        /// The channel on which to send the providers found
+        response_channel: TokioSender<Vec<WrappedPeerId>>,
+    },
     /// A command informing the network manager that the gossip protocol has warmed up
     /// in the network
     ///
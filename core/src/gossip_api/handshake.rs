@@ -1,6 +1,10 @@
 //! Groups API definitions for handshake request response
+use circuits::types::note::Note;
+use curve25519_dalek::scalar::Scalar;
+use libp2p::Multiaddr;
 use portpicker::Port;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{gossip::types::WrappedPeerId, state::OrderIdentifier};
 
@@ -23,6 +27,37 @@ pub enum HandshakeMessage {
         /// Set to `None` by the sender if all locally held orders are cached
         /// as already matched with the `peer_order`
         sender_order: OrderIdentifier,
+        /// A commitment to the size bucket of the sender's order
+        ///
+        /// When set, the sender requests that the peers compare order size buckets
+        /// before brokering an MPC
+        size_bucket_commitment: Option<Scalar>,
+    },
+    /// Sent in response to a proposal that carries a size bucket commitment, reveals
+    /// the size bucket of the responder's order so the proposer may check for overlap
+    SizeBucketChallenge {
+        /// The ID of the peer revealing its bucket
+        peer_id: WrappedPeerId,
+        /// The recipient's order, i.e. the order the proposer committed to
+        peer_order: OrderIdentifier,
+        /// The sender's order
+        sender_order: OrderIdentifier,
+        /// The size bucket of the sender's order
+        bucket: u8,
+    },
+    /// Opens the proposer's size bucket commitment after it has checked that the
+    /// two buckets overlap
+    SizeBucketOpening {
+        /// The ID of the peer opening its commitment
+        peer_id: WrappedPeerId,
+        /// The recipient's order
+        peer_order: OrderIdentifier,
+        /// The sender's order, i.e. the order the commitment was made to
+        sender_order: OrderIdentifier,
+        /// The size bucket of the sender's order
+        bucket: u8,
+        /// The blinder the bucket was committed under
+        blinder: Scalar,
     },
     /// Reject a proposed match candidate, this can happen for a number of reasons;
     /// e.g. the local peer has already cached the proposed order pair as matched,
@@ -58,6 +93,10 @@ pub enum HandshakeMessage {
         order1: OrderIdentifier,
         /// The second order to attempt to match
         order2: OrderIdentifier,
+        /// The ID of the net pooled from an earlier match between the peers that the sender
+        /// runs the match over, in which case `port` is unused and no net is brokered
+        #[serde(default)]
+        pooled_net: Option<Uuid>,
     },
 }
 
@@ -66,8 +105,93 @@ pub enum HandshakeMessage {
 pub enum MatchRejectionReason {
     /// The order pair is already cached by the rejecting peer
     Cached,
-    /// The local order proposed is not ready for scheduling
+    /// The local order proposed is not ready for scheduling, or its time in force has lapsed
     LocalOrderNotReady,
     /// The rejecting peer has not yet verified the proposer's proof of `VALID COMMITMENTS`
     NoValidityProof,
+    /// The proposer's reputation is too low for the rejecting peer to handshake with
+    LowReputation,
+    /// The rejecting peer is draining ahead of a shutdown and begins no new matches
+    Draining,
+    /// The rejecting peer's cluster access policy does not permit handshakes with the
+    /// proposer's cluster
+    ClusterNotPermitted,
+    /// The orders' size buckets do not overlap, or the proposer's bucket commitment
+    /// did not open correctly
+    SizeBucketMismatch,
+    /// The broker of the match asked a larger share of the relayer fee than the
+    /// rejecting peer pays brokers
+    BrokerFeeTooHigh,
+    /// The rejecting peer's order cannot be filled within its minimum fill size or price
+    /// protection band
+    ConstraintsUnsatisfiable,
+}
+
+/// The terms on which a broker arranges a handshake between two orders it does not
+/// manage
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BrokerFee {
+    /// The peer brokering the match, to which the fee notes are sent
+    pub broker: WrappedPeerId,
+    /// The share of each managing relayer's fee paid to the broker, in basis points
+    pub fee_bps: u16,
+}
+
+/// Enumerates the messages exchanged when a third peer brokers a handshake between two
+/// foreign orders
+///
+/// The broker asks the manager of the first order to listen for an MPC net, then passes
+/// the listener's port to the manager of the second order, which dials it. Once the match
+/// completes each managing peer sends the broker a note for its share of the relayer fee
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BrokerMessage {
+    /// A generic ACK to attest to liveness during brokering
+    Ack,
+    /// Propose that the recipient match its order against an order from another cluster,
+    /// listening for the counterparty's MPC connection
+    ProposeBrokeredMatch {
+        /// The recipient's order
+        local_order: OrderIdentifier,
+        /// The peer managing the counterparty order
+        counterparty: WrappedPeerId,
+        /// The counterparty order
+        counterparty_order: OrderIdentifier,
+        /// The broker's terms
+        fee: BrokerFee,
+    },
+    /// Sent by the listener in response to a proposal, the broker forwards the port to
+    /// the counterparty
+    AcceptBrokeredMatch {
+        /// The port the listener accepts the MPC connection on
+        port: Port,
+    },
+    /// Ask the recipient to dial the listener and match its order against the listener's
+    ExecuteBrokeredMatch {
+        /// The recipient's order
+        local_order: OrderIdentifier,
+        /// The peer managing the counterparty order, listening for the MPC connection
+        counterparty: WrappedPeerId,
+        /// The counterparty order
+        counterparty_order: OrderIdentifier,
+        /// The port the counterparty listens on
+        port: Port,
+        /// An address the counterparty is known to the broker at, for a recipient that
+        /// does not know the counterparty
+        counterparty_addr: Option<Multiaddr>,
+        /// The broker's terms
+        fee: BrokerFee,
+    },
+    /// Decline a brokered match, sent by either managing peer to the broker
+    RejectBrokeredMatch {
+        /// The reason the match is declined
+        reason: MatchRejectionReason,
+    },
+    /// Sent by the broker to the listener when the counterparty declines the match
+    AbandonBrokeredMatch,
+    /// The note paying the broker its share of a managing relayer's fee, sent once the
+    /// brokered match completes
+    BrokerFeeNote {
+        /// The fee note
+        note: Note,
+    },
 }
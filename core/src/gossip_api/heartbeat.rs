@@ -1,11 +1,13 @@
 //! Groups API definitions for heartbeat requests and responses
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use hmac_sha256::Hash;
+use libp2p::identity::{Keypair, PublicKey};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    gossip::types::{ClusterId, PeerInfo},
+    gossip::types::{ClusterId, PeerInfo, WrappedPeerId},
     state::{
         wallet::{WalletIdentifier, WalletMetadata},
         OrderIdentifier,
@@ -23,6 +25,84 @@ pub struct HeartbeatMessage {
     pub known_peers: HashMap<String, PeerInfo>,
     /// The local peer's orderbook
     pub orders: Vec<(OrderIdentifier, ClusterId)>,
+    /// The semantic version of the software the sender runs
+    #[serde(default)]
+    pub version: String,
+    /// The sender's heartbeat sequence number, strictly increasing across every
+    /// heartbeat the sender builds
+    pub sequence: u64,
+    /// The unix timestamp in milliseconds at which the sender built the heartbeat
+    pub timestamp: u64,
+    /// The protobuf encoding of the sender's libp2p public key
+    pub public_key: Vec<u8>,
+    /// The sender's signature of the digest of the heartbeat's contents
+    pub signature: Vec<u8>,
+}
+
+impl HeartbeatMessage {
+    /// The digest of the heartbeat that its sender signs
+    ///
+    /// The digest covers the sender's peer ID and every field of the heartbeat but the key
+    /// and signature. The maps and sets of the heartbeat do not serialize deterministically,
+    /// so they are encoded canonically, sorted by key, before they are hashed
+    fn digest(&self, sender: &WrappedPeerId) -> [u8; 32] {
+        let mut wallets = self
+            .managed_wallets
+            .iter()
+            .map(|(wallet_id, metadata)| {
+                let mut replicas = metadata
+                    .replicas
+                    .iter()
+                    .map(|peer_id| peer_id.to_string())
+                    .collect::<Vec<_>>();
+                replicas.sort();
+                let auto_resubmit = metadata.auto_resubmit.iter().collect::<BTreeMap<_, _>>();
+
+                (wallet_id, replicas, metadata.version, auto_resubmit)
+            })
+            .collect::<Vec<_>>();
+        wallets.sort_by_key(|(wallet_id, ..)| **wallet_id);
+
+        let known_peers = self.known_peers.iter().collect::<BTreeMap<_, _>>();
+        let mut orders = self.orders.iter().collect::<Vec<_>>();
+        orders.sort_by_key(|(order_id, cluster_id)| (*order_id, cluster_id.to_string()));
+
+        Hash::hash(
+            &serde_json::to_vec(&(
+                sender.to_string(),
+                wallets,
+                known_peers,
+                orders,
+                &self.version,
+                self.sequence,
+                self.timestamp,
+            ))
+            .unwrap(),
+        )
+    }
+
+    /// Stamp the heartbeat with a sequence number and timestamp, and sign its contents with
+    /// the sender's libp2p keypair
+    pub fn sign(&mut self, sequence: u64, timestamp: u64, keypair: &Keypair) {
+        let sender = WrappedPeerId(keypair.public().to_peer_id());
+        self.sequence = sequence;
+        self.timestamp = timestamp;
+        self.public_key = keypair.public().to_protobuf_encoding();
+        self.signature = keypair
+            .sign(&self.digest(&sender))
+            .expect("ed25519 signing is infallible");
+    }
+
+    /// Verify that the heartbeat's contents were signed by the given peer
+    pub fn verify_signature(&self, sender: &WrappedPeerId) -> bool {
+        let public_key = match PublicKey::from_protobuf_encoding(&self.public_key) {
+            Ok(key) => key,
+            Err(_) => return false,
+        };
+
+        public_key.to_peer_id() == sender.0
+            && public_key.verify(&self.digest(sender), &self.signature)
+    }
 }
 
 /// Defines a request to bootstrap the cluster state from the recipient
@@ -31,3 +111,81 @@ pub struct BootstrapRequest {
     /// The requester's peer ID
     pub peer_info: PeerInfo,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use libp2p::{identity::Keypair, PeerId};
+    use uuid::Uuid;
+
+    use crate::{
+        gossip::types::{ClusterId, PeerInfo, WrappedPeerId},
+        state::wallet::WalletMetadata,
+    };
+
+    use super::HeartbeatMessage;
+
+    /// Build a heartbeat holding several wallets, peers, and orders, signed by the given keypair
+    fn signed_heartbeat(keypair: &Keypair) -> HeartbeatMessage {
+        let cluster_id: ClusterId = "cluster".parse().unwrap();
+        let peer_ids = (0..5)
+            .map(|_| WrappedPeerId(PeerId::random()))
+            .collect::<Vec<_>>();
+
+        let mut heartbeat = HeartbeatMessage {
+            managed_wallets: (0..5)
+                .map(|_| {
+                    let metadata = WalletMetadata {
+                        replicas: peer_ids.iter().copied().collect::<HashSet<_>>(),
+                        version: 1,
+                        auto_resubmit: HashMap::new(),
+                    };
+                    (Uuid::new_v4(), metadata)
+                })
+                .collect(),
+            known_peers: peer_ids
+                .iter()
+                .map(|peer_id| (peer_id.to_string(), PeerInfo::default()))
+                .collect(),
+            orders: (0..5)
+                .map(|_| (Uuid::new_v4(), cluster_id.clone()))
+                .collect(),
+            version: "0.1.0".to_string(),
+            sequence: 0,
+            timestamp: 0,
+            public_key: Vec::new(),
+            signature: Vec::new(),
+        };
+        heartbeat.sign(1 /* sequence */, 1_000 /* timestamp */, keypair);
+        heartbeat
+    }
+
+    /// Tests that the signature survives a round trip through serialization, which reorders
+    /// the heartbeat's maps, and covers the body of the heartbeat
+    #[test]
+    fn test_signature_covers_body() {
+        let keypair = Keypair::generate_ed25519();
+        let sender = WrappedPeerId(keypair.public().to_peer_id());
+        let heartbeat = signed_heartbeat(&keypair);
+
+        let decoded: HeartbeatMessage =
+            serde_json::from_slice(&serde_json::to_vec(&heartbeat).unwrap()).unwrap();
+        assert!(decoded.verify_signature(&sender));
+        assert!(!decoded.verify_signature(&WrappedPeerId(PeerId::random())));
+
+        let mut tampered = heartbeat.clone();
+        tampered.orders.pop();
+        assert!(!tampered.verify_signature(&sender));
+
+        let mut tampered = heartbeat.clone();
+        let peer_id = WrappedPeerId(PeerId::random()).to_string();
+        tampered.known_peers.insert(peer_id, PeerInfo::default());
+        assert!(!tampered.verify_signature(&sender));
+
+        let mut tampered = heartbeat;
+        let wallet = tampered.managed_wallets.values_mut().next().unwrap();
+        wallet.replicas.insert(WrappedPeerId(PeerId::random()));
+        assert!(!tampered.verify_signature(&sender));
+    }
+}
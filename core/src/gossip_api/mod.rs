@@ -1,6 +1,8 @@
 //! Defines API types for gossip within the p2p network
 
+pub mod cluster_encryption;
 pub mod cluster_management;
+pub mod codec;
 pub mod gossip;
 pub mod handshake;
 pub mod heartbeat;
@@ -1,10 +1,18 @@
 //! Defines types related to orderbook message passing within the p2p network
 
-use circuits::types::wallet::Nullifier;
+use circuits::types::{
+    order::{Order, OrderSide, TimeInForce},
+    wallet::Nullifier,
+};
+use curve25519_dalek::scalar::Scalar;
+use hmac_sha256::Hash;
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
     gossip::types::ClusterId,
+    handshake::size_bucket::{buckets_overlap, size_bucket},
     proof_generation::jobs::ValidCommitmentsBundle,
     state::{NetworkOrder, OrderIdentifier},
 };
@@ -12,6 +20,36 @@ use crate::{
 /// The network pubsub topic to use for listening to orderbook changes
 pub const ORDER_BOOK_TOPIC: &str = "orderbook";
 
+/// An indication of interest (IoI) in an order, a partial and coarse reveal of the order
+/// that lets peers prioritize handshakes on pairs that are likely to cross
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndicationOfInterest {
+    /// The side of the market the order is on
+    pub side: OrderSide,
+    /// The mint of the order's base token
+    pub base_mint: BigUint,
+    /// The size bucket of the order's amount
+    pub size_bucket: u8,
+}
+
+impl IndicationOfInterest {
+    /// Build the IoI revealed for an order
+    pub fn from_order(order: &Order) -> Self {
+        Self {
+            side: order.side,
+            base_mint: order.base_mint.clone(),
+            size_bucket: size_bucket(order.amount),
+        }
+    }
+
+    /// Whether the orders behind two IoIs may plausibly cross
+    pub fn may_cross(&self, other: &IndicationOfInterest) -> bool {
+        self.side == other.side.opposite()
+            && self.base_mint == other.base_mint
+            && buckets_overlap(self.size_bucket, other.size_bucket)
+    }
+}
+
 /// The message type used to request order information from a peer
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OrderInfoRequest {
@@ -30,6 +68,48 @@ pub struct OrderInfoResponse {
     pub info: Option<NetworkOrder>,
 }
 
+/// The message type used to request a chunk of an order book snapshot from a cluster peer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderBookSnapshotRequest {
+    /// The snapshot to fetch a chunk of, `None` requests that the peer take a new snapshot
+    pub snapshot_id: Option<Uuid>,
+    /// The index of the requested chunk
+    pub chunk_index: usize,
+}
+
+/// A chunk of a peer's order book snapshot
+///
+/// If the requested snapshot has expired, the peer responds with the first chunk of a
+/// new snapshot instead
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderBookSnapshotChunk {
+    /// The identifier of the snapshot
+    pub snapshot_id: Uuid,
+    /// The index of this chunk in the snapshot
+    pub chunk_index: usize,
+    /// The hash of every chunk in the snapshot, in order
+    pub chunk_hashes: Vec<[u8; 32]>,
+    /// The sequence number of the last order book delta the peer published before
+    /// taking the snapshot
+    pub delta_sequence: u64,
+    /// The root of the peer's Merkle mirror when the snapshot was taken
+    pub merkle_root: Scalar,
+    /// The orders in the chunk, ordered by identifier
+    pub orders: Vec<NetworkOrder>,
+}
+
+impl OrderBookSnapshotChunk {
+    /// Hash the orders in a chunk
+    pub fn hash_orders(orders: &[NetworkOrder]) -> [u8; 32] {
+        Hash::hash(&serde_json::to_vec(orders).unwrap())
+    }
+
+    /// Whether the chunk's orders hash to the chunk's entry in the snapshot's hashes
+    pub fn is_intact(&self) -> bool {
+        self.chunk_hashes.get(self.chunk_index) == Some(&Self::hash_orders(&self.orders))
+    }
+}
+
 /// The message type attached to an OrderBookManagement pubsub message
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -53,5 +133,46 @@ pub enum OrderBookManagementMessage {
         cluster: ClusterId,
         /// The new proof of `VALID COMMITMENTS`
         proof: ValidCommitmentsBundle,
+        /// An indication of interest in the order, set only if the managing cluster
+        /// publishes IoIs
+        #[serde(default)]
+        ioi: Option<IndicationOfInterest>,
+        /// How long the order remains eligible for matching, peers do not schedule
+        /// handshakes on the order once it lapses
+        #[serde(default)]
+        time_in_force: TimeInForce,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use circuits::types::order::{Order, OrderSide};
+    use num_bigint::BigUint;
+
+    use super::IndicationOfInterest;
+
+    /// Build an order on the given side and base mint
+    fn order(side: OrderSide, base_mint: u64, amount: u64) -> Order {
+        Order {
+            base_mint: BigUint::from(base_mint),
+            side,
+            amount,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that IoIs cross only on opposite sides of the same base with similar sizes
+    #[test]
+    fn test_may_cross() {
+        let buy = IndicationOfInterest::from_order(&order(OrderSide::Buy, 1, 100));
+        let sell = IndicationOfInterest::from_order(&order(OrderSide::Sell, 1, 120));
+        assert!(buy.may_cross(&sell));
+        assert!(sell.may_cross(&buy));
+
+        assert!(!buy.may_cross(&buy));
+        let other_base = IndicationOfInterest::from_order(&order(OrderSide::Sell, 2, 100));
+        assert!(!buy.may_cross(&other_base));
+        let oversized = IndicationOfInterest::from_order(&order(OrderSide::Sell, 1, 1 << 40));
+        assert!(!buy.may_cross(&oversized));
+    }
+}
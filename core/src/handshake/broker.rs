@@ -0,0 +1,698 @@
+//! Implements brokered handshakes, in which a peer arranges a match between two orders
+//! managed by other clusters. The flow is:
+//!     1. The broker chooses a foreign order and a counterparty order from another cluster,
+//!        and asks the first order's manager to listen for an MPC net
+//!     2. The listener accepts, and the broker forwards its port to the counterparty
+//!        order's manager, which dials the listener
+//!     3. The managing peers run the match and settle it as they would any other, then
+//!        each sends the broker a note for its share of the relayer fee
+//!
+//! The broker fee notes are not committed to in `VALID MATCH ENCRYPTION`; a managing
+//! relayer pays the broker out of its own fee note by internal transfer
+
+use circuits::types::{
+    note::{Note, NoteType},
+    order::OrderSide,
+};
+use libp2p::{request_response::ResponseChannel, Multiaddr};
+use portpicker::Port;
+use std::time::Duration;
+use tracing::log;
+use uuid::Uuid;
+
+use crate::{
+    gossip::types::{ClusterId, WrappedPeerId},
+    gossip_api::{
+        gossip::{
+            AuthenticatedGossipResponse, GossipOutbound, GossipRequest, GossipResponse,
+            ManagerControlDirective,
+        },
+        handshake::{BrokerFee, BrokerMessage, MatchRejectionReason},
+    },
+    state::{NetworkOrderState, OrderIdentifier},
+    types::{SystemBusMessage, HANDSHAKE_STATUS_TOPIC},
+};
+
+use super::{
+    error::HandshakeManagerError,
+    manager::{HandshakeExecutor, HANDSHAKE_INVISIBILITY_WINDOW_MS},
+    r#match::HandshakeResult,
+};
+
+/// The denominator of a fee given in basis points
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// A handshake brokered by the local peer
+#[derive(Clone, Debug)]
+pub struct BrokeredMatch {
+    /// The peer listening for the MPC net
+    listener: WrappedPeerId,
+    /// The order managed by the listener
+    listener_order: OrderIdentifier,
+    /// The peer dialing the MPC net
+    dialer: WrappedPeerId,
+    /// The order managed by the dialer
+    dialer_order: OrderIdentifier,
+    /// The terms the match was brokered on
+    fee: BrokerFee,
+    /// The managing peers that have yet to send their fee notes
+    unpaid: Vec<WrappedPeerId>,
+}
+
+impl HandshakeExecutor {
+    // ----------
+    // | Broker |
+    // ----------
+
+    /// Broker a handshake between the given foreign order and an order from another
+    /// cluster, if the local peer brokers handshakes
+    pub(super) async fn broker_handshake(
+        &self,
+        order: OrderIdentifier,
+    ) -> Result<(), HandshakeManagerError> {
+        let fee_bps = match self.broker_fee_bps {
+            Some(fee_bps) => fee_bps,
+            None => return Ok(()),
+        };
+        if self.global_state.is_draining() {
+            return Ok(());
+        }
+
+        let counterparty_order = match self.choose_brokered_counterparty(order).await {
+            Some(order_id) => order_id,
+            None => return Ok(()),
+        };
+        let (listener, dialer) = match (
+            self.find_order_manager(order).await,
+            self.find_order_manager(counterparty_order).await,
+        ) {
+            (Some(listener), Some(dialer)) if listener != dialer => (listener, dialer),
+            _ => return Ok(()),
+        };
+
+        // Hide the pair from the scheduler while the managing peers attempt the match
+        self.handshake_cache.mark_invisible(
+            order,
+            counterparty_order,
+            Duration::from_millis(HANDSHAKE_INVISIBILITY_WINDOW_MS),
+        );
+
+        let request_id = self.rng.gen_uuid();
+        let fee = BrokerFee {
+            broker: self.global_state.local_peer_id(),
+            fee_bps,
+        };
+        self.brokered_matches.lock().unwrap().put(
+            request_id,
+            BrokeredMatch {
+                listener,
+                listener_order: order,
+                dialer,
+                dialer_order: counterparty_order,
+                fee: fee.clone(),
+                unpaid: vec![listener, dialer],
+            },
+        );
+
+        self.send_broker_message(
+            request_id,
+            listener,
+            BrokerMessage::ProposeBrokeredMatch {
+                local_order: order,
+                counterparty: dialer,
+                counterparty_order,
+                fee,
+            },
+            None, /* response_channel */
+        )
+    }
+
+    /// Chooses an order from another cluster to broker a match with the given order
+    ///
+    /// Orders whose IoIs may cross the given order's are chosen first, then orders whose
+    /// crossing is unknown. Unlike a local proposal, a pair whose IoIs cannot cross is never
+    /// brokered; the broker has no order of its own at stake to justify the attempt
+    async fn choose_brokered_counterparty(
+        &self,
+        order: OrderIdentifier,
+    ) -> Option<OrderIdentifier> {
+        let (order_info, candidates) = {
+            let locked_order_book = self.global_state.read_order_book().await;
+            let order_info = locked_order_book.get_order_info(&order).await?;
+
+            let mut candidates = Vec::new();
+            for order_id in locked_order_book.get_nonlocal_verified_orders().await {
+                if let Some(candidate) = locked_order_book.get_order_info(&order_id).await
+                    && candidate.cluster != order_info.cluster
+                {
+                    candidates.push(candidate);
+                }
+            }
+
+            (order_info, candidates)
+        }; // locked_order_book released
+
+        let locked_cluster_access = self.global_state.read_cluster_access().await;
+        let mut unknown_candidate = None;
+        for candidate in candidates.into_iter().filter(|candidate| {
+            locked_cluster_access.is_permitted(&candidate.cluster)
+                && !self.handshake_cache.contains(order, candidate.id)
+        }) {
+            match (order_info.ioi.as_ref(), candidate.ioi.as_ref()) {
+                (Some(ioi), Some(candidate_ioi)) if ioi.may_cross(candidate_ioi) => {
+                    return Some(candidate.id)
+                }
+                (Some(_), Some(_)) => {}
+                _ => {
+                    unknown_candidate.get_or_insert(candidate.id);
+                }
+            }
+        }
+
+        unknown_candidate
+    }
+
+    /// Forward the listener's port to the dialer once the listener accepts a brokered match
+    async fn handle_brokered_acceptance(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        port: Port,
+    ) -> Result<(), HandshakeManagerError> {
+        let brokered = self.get_brokered_match(&request_id, &peer_id)?;
+        if peer_id != brokered.listener {
+            return Err(HandshakeManagerError::InvalidRequest(
+                "brokered match accepted by a peer other than the listener".to_string(),
+            ));
+        }
+
+        // The dialer may not know the listener, which manages an order from another cluster
+        let counterparty_addr: Option<Multiaddr> = self
+            .global_state
+            .read_peer_index()
+            .await
+            .get_peer_info(&brokered.listener)
+            .await
+            .map(|info| info.get_addr());
+
+        self.send_broker_message(
+            request_id,
+            brokered.dialer,
+            BrokerMessage::ExecuteBrokeredMatch {
+                local_order: brokered.dialer_order,
+                counterparty: brokered.listener,
+                counterparty_order: brokered.listener_order,
+                port,
+                counterparty_addr,
+                fee: brokered.fee,
+            },
+            None, /* response_channel */
+        )
+    }
+
+    /// Handles a managing peer declining a brokered match
+    ///
+    /// If the dialer declines, the listener is told to stop listening for it
+    fn handle_brokered_rejection(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        reason: MatchRejectionReason,
+    ) -> Result<(), HandshakeManagerError> {
+        let brokered = self.get_brokered_match(&request_id, &peer_id)?;
+        self.brokered_matches.lock().unwrap().pop(&request_id);
+        log::info!("brokered match {request_id} declined by {peer_id}: {reason:?}");
+
+        if let MatchRejectionReason::Cached = reason {
+            self.handshake_cache
+                .mark_completed(brokered.listener_order, brokered.dialer_order);
+        }
+
+        if peer_id == brokered.dialer {
+            self.send_broker_message(
+                request_id,
+                brokered.listener,
+                BrokerMessage::AbandonBrokeredMatch,
+                None, /* response_channel */
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Collect a managing peer's fee note for a brokered match
+    fn handle_broker_fee_note(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        note: Note,
+    ) -> Result<(), HandshakeManagerError> {
+        let brokered = {
+            let mut locked_matches = self.brokered_matches.lock().unwrap();
+            let brokered = locked_matches.get_mut(&request_id).ok_or_else(|| {
+                HandshakeManagerError::InvalidRequest(format!(
+                    "fee note for unknown brokered match {request_id}"
+                ))
+            })?;
+            if !brokered.unpaid.contains(&peer_id) {
+                return Err(HandshakeManagerError::InvalidRequest(format!(
+                    "unexpected fee note from {peer_id}"
+                )));
+            }
+
+            brokered.unpaid.retain(|unpaid| *unpaid != peer_id);
+            let brokered = brokered.clone();
+            if brokered.unpaid.is_empty() {
+                locked_matches.pop(&request_id);
+            }
+
+            brokered
+        }; // locked_matches released
+
+        log::info!("received broker fee note for match {request_id} from {peer_id}");
+        self.system_bus.publish(
+            HANDSHAKE_STATUS_TOPIC.to_string(),
+            SystemBusMessage::BrokerFeeNoteReceived {
+                request_id,
+                order1: brokered.listener_order,
+                order2: brokered.dialer_order,
+                note,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Fetch a brokered match that the given peer is party to
+    fn get_brokered_match(
+        &self,
+        request_id: &Uuid,
+        peer_id: &WrappedPeerId,
+    ) -> Result<BrokeredMatch, HandshakeManagerError> {
+        self.brokered_matches
+            .lock()
+            .unwrap()
+            .peek(request_id)
+            .filter(|brokered| brokered.listener == *peer_id || brokered.dialer == *peer_id)
+            .cloned()
+            .ok_or_else(|| {
+                HandshakeManagerError::InvalidRequest(format!(
+                    "unknown brokered match {request_id}"
+                ))
+            })
+    }
+
+    // -----------------
+    // | Managing Peer |
+    // -----------------
+
+    /// Handle a message sent while brokering a handshake
+    pub(super) async fn handle_broker_message(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        message: BrokerMessage,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        match message {
+            // ACK does not need to be handled
+            BrokerMessage::Ack => Ok(()),
+
+            // A broker asks the local peer to listen for a counterparty's MPC connection
+            BrokerMessage::ProposeBrokeredMatch {
+                local_order,
+                counterparty,
+                counterparty_order,
+                fee,
+            } => {
+                self.handle_brokered_proposal(
+                    request_id,
+                    peer_id,
+                    local_order,
+                    counterparty,
+                    counterparty_order,
+                    fee,
+                    response_channel,
+                )
+                .await
+            }
+
+            // The listener has accepted a match the local peer brokered
+            BrokerMessage::AcceptBrokeredMatch { port } => {
+                self.handle_brokered_acceptance(request_id, peer_id, port)
+                    .await?;
+                self.ack_broker_message(request_id, peer_id, response_channel)
+            }
+
+            // A broker asks the local peer to dial a counterparty listening for the match
+            BrokerMessage::ExecuteBrokeredMatch {
+                local_order,
+                counterparty,
+                counterparty_order,
+                port,
+                counterparty_addr,
+                fee,
+            } => {
+                self.handle_execute_brokered_match(
+                    request_id,
+                    peer_id,
+                    local_order,
+                    (counterparty, counterparty_order, port, counterparty_addr),
+                    fee,
+                    response_channel,
+                )
+                .await
+            }
+
+            // A managing peer has declined a match the local peer brokered
+            BrokerMessage::RejectBrokeredMatch { reason } => {
+                self.handle_brokered_rejection(request_id, peer_id, reason)?;
+                self.ack_broker_message(request_id, peer_id, response_channel)
+            }
+
+            // The counterparty declined the match the local peer was listening for
+            BrokerMessage::AbandonBrokeredMatch => {
+                if let Some(state) = self.handshake_state_index.get_state(&request_id)
+                    && state.broker.map(|fee| fee.broker) == Some(peer_id)
+                    && let Some(state) = self.handshake_state_index.remove_handshake(&request_id)
+                    && let Some(channel) = state.cancel_channel
+                {
+                    let _ = channel.try_send(());
+                }
+
+                self.ack_broker_message(request_id, peer_id, response_channel)
+            }
+
+            // A managing peer has paid the local peer for a brokered match
+            BrokerMessage::BrokerFeeNote { note } => {
+                self.handle_broker_fee_note(request_id, peer_id, note)?;
+                self.ack_broker_message(request_id, peer_id, response_channel)
+            }
+        }
+    }
+
+    /// Handles a broker's proposal that the local peer listen for a counterparty's MPC
+    /// connection on a brokered match
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_brokered_proposal(
+        &self,
+        request_id: Uuid,
+        broker: WrappedPeerId,
+        local_order: OrderIdentifier,
+        counterparty: WrappedPeerId,
+        counterparty_order: OrderIdentifier,
+        fee: BrokerFee,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        if let Some(reason) = self
+            .check_brokered_match(broker, &fee, local_order, counterparty, counterparty_order)
+            .await?
+        {
+            let message = BrokerMessage::RejectBrokeredMatch { reason };
+            return self.send_broker_message(request_id, broker, message, response_channel);
+        }
+
+        self.handshake_state_index
+            .new_handshake(request_id, counterparty, counterparty_order, local_order)
+            .await?;
+        self.handshake_state_index.set_broker(&request_id, fee);
+
+        let port =
+            self.listen_for_mpc(request_id, counterparty, local_order, counterparty_order)?;
+        self.send_broker_message(
+            request_id,
+            broker,
+            BrokerMessage::AcceptBrokeredMatch { port },
+            response_channel,
+        )
+    }
+
+    /// Handles a broker's request that the local peer dial the listener of a brokered
+    /// match
+    ///
+    /// The counterparty is given as its peer ID, its order, the port it listens on, and
+    /// an address the broker knows it at
+    async fn handle_execute_brokered_match(
+        &self,
+        request_id: Uuid,
+        broker: WrappedPeerId,
+        local_order: OrderIdentifier,
+        counterparty: (WrappedPeerId, OrderIdentifier, Port, Option<Multiaddr>),
+        fee: BrokerFee,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        let (counterparty, counterparty_order, port, counterparty_addr) = counterparty;
+        if let Some(reason) = self
+            .check_brokered_match(broker, &fee, local_order, counterparty, counterparty_order)
+            .await?
+        {
+            let message = BrokerMessage::RejectBrokeredMatch { reason };
+            return self.send_broker_message(request_id, broker, message, response_channel);
+        }
+
+        self.handshake_state_index
+            .new_handshake(request_id, counterparty, counterparty_order, local_order)
+            .await?;
+        self.handshake_state_index.set_broker(&request_id, fee);
+        self.handshake_cache
+            .mark_completed(local_order, counterparty_order);
+
+        if let Some(address) = counterparty_addr {
+            self.network_channel
+                .send(GossipOutbound::ManagementMessage(
+                    ManagerControlDirective::NewAddr {
+                        peer_id: counterparty,
+                        address,
+                    },
+                ))
+                .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
+        }
+        self.dial_mpc(request_id, counterparty, port)?;
+
+        self.ack_broker_message(request_id, broker, response_channel)
+    }
+
+    /// Checks whether the local peer should take part in a brokered match, returning the
+    /// reason to decline it if not
+    ///
+    /// The broker and the counterparty are held to the same standard as a proposing peer
+    async fn check_brokered_match(
+        &self,
+        broker: WrappedPeerId,
+        fee: &BrokerFee,
+        local_order: OrderIdentifier,
+        counterparty: WrappedPeerId,
+        counterparty_order: OrderIdentifier,
+    ) -> Result<Option<MatchRejectionReason>, HandshakeManagerError> {
+        // The fee notes are paid to the peer that brokered the match
+        if fee.broker != broker {
+            return Err(HandshakeManagerError::InvalidRequest(
+                "broker fee names a peer other than the broker".to_string(),
+            ));
+        }
+
+        if self.global_state.is_draining() {
+            return Ok(Some(MatchRejectionReason::Draining));
+        }
+        if fee.fee_bps > self.max_broker_fee_bps {
+            return Ok(Some(MatchRejectionReason::BrokerFeeTooHigh));
+        }
+
+        {
+            let locked_reputation = self.global_state.read_peer_reputation().await;
+            if !locked_reputation.is_eligible_counterparty(&broker)
+                || !locked_reputation.is_eligible_counterparty(&counterparty)
+            {
+                return Ok(Some(MatchRejectionReason::LowReputation));
+            }
+        } // locked_reputation released
+
+        let counterparty_order_info = self
+            .global_state
+            .read_order_book()
+            .await
+            .get_order_info(&counterparty_order)
+            .await;
+        let counterparty_cluster = match counterparty_order_info {
+            Some(info) if info.state == NetworkOrderState::Verified => info.cluster,
+            _ => return Ok(Some(MatchRejectionReason::NoValidityProof)),
+        };
+        if !self
+            .global_state
+            .read_cluster_access()
+            .await
+            .is_permitted(&counterparty_cluster)
+        {
+            return Ok(Some(MatchRejectionReason::ClusterNotPermitted));
+        }
+
+        {
+            let locked_order_book = self.global_state.read_order_book().await;
+            if !locked_order_book
+                .order_ready_for_handshake(&local_order)
+                .await
+                || locked_order_book
+                    .is_order_expired(&local_order, self.clock.unix_secs())
+                    .await
+            {
+                return Ok(Some(MatchRejectionReason::LocalOrderNotReady));
+            }
+        } // locked_order_book released
+        if self
+            .handshake_cache
+            .contains(local_order, counterparty_order)
+        {
+            return Ok(Some(MatchRejectionReason::Cached));
+        }
+        if !self.local_order_admits_match(&local_order).await {
+            return Ok(Some(MatchRejectionReason::ConstraintsUnsatisfiable));
+        }
+
+        Ok(None)
+    }
+
+    /// Send the broker of a completed match a note for its share of the local relayer fee
+    pub(super) fn pay_broker(
+        &self,
+        request_id: Uuid,
+        party_id: u64,
+        broker: &BrokerFee,
+        peer_cluster_id: Option<&ClusterId>,
+        handshake_result: &HandshakeResult,
+    ) -> Result<(), HandshakeManagerError> {
+        let (_, _, relayer0_note, relayer1_note, _) = self.create_notes(
+            self.match_protocol_fee(&handshake_result.match_, peer_cluster_id),
+            &handshake_result.match_,
+            &handshake_result.party0_fee,
+            &handshake_result.party1_fee,
+            handshake_result.party0_randomness_hash,
+            handshake_result.party1_randomness_hash,
+        );
+        let relayer_note = if party_id == 0 {
+            relayer0_note
+        } else {
+            relayer1_note
+        };
+
+        self.send_broker_message(
+            request_id,
+            broker.broker,
+            BrokerMessage::BrokerFeeNote {
+                note: broker_fee_note(&relayer_note, broker.fee_bps),
+            },
+            None, /* response_channel */
+        )
+    }
+
+    // -----------
+    // | Helpers |
+    // -----------
+
+    /// Answer a broker message with an ack if it arrived as a request
+    fn ack_broker_message(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        if response_channel.is_none() {
+            return Ok(());
+        }
+
+        self.send_broker_message(request_id, peer_id, BrokerMessage::Ack, response_channel)
+    }
+
+    /// Sends a broker message as a request or response depending on whether the response
+    /// channel is None, see `send_request_response`
+    fn send_broker_message(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        message: BrokerMessage,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        let outbound = if let Some(channel) = response_channel {
+            GossipOutbound::Response {
+                channel,
+                message: GossipResponse::BrokeredHandshake {
+                    request_id,
+                    message,
+                },
+            }
+        } else {
+            GossipOutbound::Request {
+                peer_id,
+                message: GossipRequest::BrokeredHandshake {
+                    request_id,
+                    message,
+                },
+            }
+        };
+
+        self.network_channel
+            .send(outbound)
+            .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))
+    }
+}
+
+/// Build the note paying a broker its share of a managing relayer's fee note
+///
+/// Volumes are rounded down, and the note's randomness is offset from the relayer note's
+/// so that the two notes commit differently
+fn broker_fee_note(relayer_note: &Note, fee_bps: u16) -> Note {
+    let broker_share = |volume: u64| (volume as u128 * fee_bps as u128 / BPS_DENOMINATOR) as u64;
+
+    Note {
+        mint1: relayer_note.mint1.clone(),
+        volume1: broker_share(relayer_note.volume1),
+        direction1: OrderSide::Buy,
+        mint2: relayer_note.mint2.clone(),
+        volume2: broker_share(relayer_note.volume2),
+        direction2: OrderSide::Buy,
+        fee_mint: relayer_note.fee_mint.clone(),
+        fee_volume: 0,
+        fee_direction: OrderSide::Buy,
+        type_: NoteType::InternalTransfer,
+        randomness: relayer_note.randomness.clone() + 1u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::types::{
+        note::{Note, NoteType},
+        order::OrderSide,
+    };
+    use num_bigint::BigUint;
+
+    use super::broker_fee_note;
+
+    /// Tests that the broker is paid its share of each volume in the relayer note, rounded
+    /// down, under different randomness
+    #[test]
+    fn test_broker_fee_note() {
+        let relayer_note = Note {
+            mint1: BigUint::from(1u8),
+            volume1: 12_345,
+            direction1: OrderSide::Buy,
+            mint2: BigUint::from(2u8),
+            volume2: 0,
+            direction2: OrderSide::Buy,
+            fee_mint: BigUint::from(3u8),
+            fee_volume: 10,
+            fee_direction: OrderSide::Buy,
+            type_: NoteType::InternalTransfer,
+            randomness: BigUint::from(7u8),
+        };
+
+        let note = broker_fee_note(&relayer_note, 2_500 /* fee_bps */);
+        assert_eq!(note.volume1, 3_086);
+        assert_eq!(note.volume2, 0);
+        assert_eq!(note.fee_volume, 0);
+        assert_eq!(note.mint1, relayer_note.mint1);
+        assert_ne!(note.randomness, relayer_note.randomness);
+
+        let note = broker_fee_note(&relayer_note, 0 /* fee_bps */);
+        assert_eq!(note.volume1, 0);
+    }
+}
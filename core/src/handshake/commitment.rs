@@ -0,0 +1,121 @@
+//! Implements the commitment to a match ahead of its settlement
+//!
+//! The contract settles a match only if it was committed to before the proof of `VALID
+//! MATCH ENCRYPTION` finished, so a match is committed to as soon as the MPC completes.
+//! The commitment is a Pedersen commitment to the match tuple under the blinders that the
+//! proof's witness commitment uses, so the proof itself opens the commitment when the
+//! settlement is submitted; the match is revealed only to the contract's verifier, and
+//! only in committed form
+
+use circuits::zk_gadgets::match_commitment::MatchCommitment;
+use crypto::fields::starknet_felt_to_biguint;
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::log;
+use uuid::Uuid;
+
+use crate::{
+    error::{ErrorCode, ErrorCoded},
+    proof_generation::jobs::ValidMatchEncryptBundle,
+    starknet_client::contract_abi::commit_match_calldata,
+    types::SettlementStatus,
+};
+
+use super::{
+    error::HandshakeManagerError, journal::SettlementJournalEntry, manager::HandshakeExecutor,
+};
+
+/// The error message emitted when a settlement's match was never committed to
+const ERR_NOT_COMMITTED: &str = "the match was not committed to before settlement";
+/// The error message emitted when a proof does not open the match's commitment
+const ERR_OPENING_MISMATCH: &str = "the proof does not open the commitment posted for the match";
+
+impl HandshakeExecutor {
+    /// Commit to a journaled match on-chain, and journal the commitment
+    ///
+    /// A match whose commitment is already journaled is not committed to again. If the
+    /// commitment cannot be posted, or its transaction is rejected after broadcast, the
+    /// match is left in the journal without a commitment, to be committed to when the
+    /// relayer next starts
+    pub(super) async fn commit_match(
+        &self,
+        request_id: Uuid,
+    ) -> Result<MatchCommitment, HandshakeManagerError> {
+        let entry = self.settlement_journal.get(&request_id).ok_or_else(|| {
+            HandshakeManagerError::Journal(format!("no journal entry for {request_id}"))
+        })?;
+        if let Some(commitment) = entry.match_commitment {
+            return Ok(commitment);
+        }
+
+        let commitment = MatchCommitment::new(&entry.handshake_result.match_);
+        let [match_nullifier0, match_nullifier1] = entry.party_match_nullifiers;
+        let calldata = commit_match_calldata(match_nullifier0, match_nullifier1, &commitment);
+
+        // Report a transaction the sequencer rejects after it is broadcast
+        let (failure_sender, mut failure_receiver) = unbounded_channel();
+        let tx_hash = match self
+            .starknet_client
+            .commit_match(calldata, failure_sender)
+            .await
+        {
+            Ok(tx_hash) => tx_hash,
+            Err(err) => {
+                self.publish_settlement_status(
+                    &entry,
+                    SettlementStatus::Failed {
+                        code: err.error_code(),
+                        reason: err.to_string(),
+                    },
+                );
+                return Err(HandshakeManagerError::Starknet(err));
+            }
+        };
+
+        self.settlement_journal
+            .record_match_commitment(&request_id, commitment)?;
+
+        let tx_hash = format!("0x{}", starknet_felt_to_biguint(&tx_hash).to_str_radix(16));
+        log::info!("match {request_id} committed to in transaction {tx_hash}");
+        self.publish_settlement_status(&entry, SettlementStatus::Committed { tx_hash });
+
+        let self_clone = self.clone();
+        tokio::spawn(async move {
+            if let Some(job) = failure_receiver.recv().await {
+                if let Err(err) = self_clone
+                    .settlement_journal
+                    .clear_match_commitment(&request_id)
+                {
+                    log::error!("error clearing rejected commitment for match {request_id}: {err}");
+                }
+
+                self_clone.publish_settlement_status(
+                    &entry,
+                    SettlementStatus::Failed {
+                        code: ErrorCode::TransactionRejected,
+                        reason: job.reason,
+                    },
+                );
+            }
+        });
+
+        Ok(commitment)
+    }
+
+    /// Check that a proof of `VALID MATCH ENCRYPTION` opens the commitment posted for the
+    /// journaled match
+    pub(super) fn validate_match_opening(
+        &self,
+        entry: &SettlementJournalEntry,
+        bundle: &ValidMatchEncryptBundle,
+    ) -> Result<(), HandshakeManagerError> {
+        match entry.match_commitment {
+            Some(commitment) if commitment.is_opened_by(&bundle.commitment.match_res) => Ok(()),
+            Some(_) => Err(HandshakeManagerError::MatchCommitment(
+                ERR_OPENING_MISMATCH.to_string(),
+            )),
+            None => Err(HandshakeManagerError::MatchCommitment(
+                ERR_NOT_COMMITTED.to_string(),
+            )),
+        }
+    }
+}
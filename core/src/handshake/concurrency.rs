@@ -0,0 +1,119 @@
+//! Limits the number of match MPCs that run at once, both against any one peer and
+//! across all peers
+//!
+//! MPCs beyond either limit wait for a permit in the order they arrived, rather than
+//! being dropped or run on an oversubscribed blocking pool
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::gossip::types::WrappedPeerId;
+
+/// The permits held by a running MPC, released when dropped
+#[derive(Debug)]
+pub struct MpcPermit {
+    /// The permit on the MPCs run against the peer
+    _peer_permit: OwnedSemaphorePermit,
+    /// The permit on the MPCs run against all peers
+    _global_permit: OwnedSemaphorePermit,
+}
+
+/// Hands out permits to run match MPCs
+#[derive(Clone, Debug)]
+pub struct MpcConcurrencyLimiter {
+    /// The maximum number of MPCs run at once against a single peer
+    per_peer_limit: usize,
+    /// The semaphore bounding the MPCs run against all peers
+    global: Arc<Semaphore>,
+    /// The semaphore bounding the MPCs run against each peer
+    per_peer: Arc<Mutex<HashMap<WrappedPeerId, Arc<Semaphore>>>>,
+}
+
+impl MpcConcurrencyLimiter {
+    /// Constructor
+    pub fn new(global_limit: usize, per_peer_limit: usize) -> Self {
+        Self {
+            per_peer_limit,
+            global: Arc::new(Semaphore::new(global_limit)),
+            per_peer: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wait for a permit to run an MPC against the given peer
+    ///
+    /// The peer's permit is acquired first, so that an MPC queued behind the peer's limit
+    /// does not hold one of the global permits while it waits
+    pub async fn acquire(&self, peer_id: WrappedPeerId) -> MpcPermit {
+        let peer_semaphore = self.peer_semaphore(peer_id);
+        let peer_permit = peer_semaphore.acquire_owned().await.unwrap();
+        let global_permit = self.global.clone().acquire_owned().await.unwrap();
+
+        MpcPermit {
+            _peer_permit: peer_permit,
+            _global_permit: global_permit,
+        }
+    }
+
+    /// The number of MPCs that may be started against any peer before one must queue
+    pub fn available_global(&self) -> usize {
+        self.global.available_permits()
+    }
+
+    /// Fetch the semaphore for a peer, pruning those of peers with no MPCs running or
+    /// queued
+    fn peer_semaphore(&self, peer_id: WrappedPeerId) -> Arc<Semaphore> {
+        let mut per_peer = self.per_peer.lock().unwrap();
+        // Running and queued MPCs each hold a reference to their peer's semaphore
+        per_peer.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+
+        per_peer
+            .entry(peer_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_peer_limit)))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::gossip::types::WrappedPeerId;
+
+    use super::MpcConcurrencyLimiter;
+
+    /// The time to wait on a permit before deciding that the acquisition is queued
+    const QUEUED_TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// Tests that MPCs against one peer queue at the per-peer limit while MPCs against
+    /// another peer proceed, up to the global limit
+    #[tokio::test]
+    async fn test_limits() {
+        let limiter = MpcConcurrencyLimiter::new(3 /* global */, 2 /* per_peer */);
+        let peer1 = WrappedPeerId::random();
+        let peer2 = WrappedPeerId::random();
+
+        let permit1 = limiter.acquire(peer1).await;
+        let _permit2 = limiter.acquire(peer1).await;
+        assert!(timeout(QUEUED_TIMEOUT, limiter.acquire(peer1))
+            .await
+            .is_err());
+
+        let _permit3 = limiter.acquire(peer2).await;
+        assert_eq!(limiter.available_global(), 0);
+        assert!(timeout(QUEUED_TIMEOUT, limiter.acquire(peer2))
+            .await
+            .is_err());
+
+        // Releasing a permit on the first peer frees a slot for a queued MPC
+        drop(permit1);
+        assert!(timeout(QUEUED_TIMEOUT, limiter.acquire(peer1))
+            .await
+            .is_ok());
+    }
+}
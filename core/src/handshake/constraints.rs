@@ -0,0 +1,208 @@
+//! Enforces the minimum fill size and price protection band of local orders
+//!
+//! An order's constraints fall back to the relayer's defaults where the order does not set
+//! them. Before an MPC is initiated on a local order, the order is checked against its
+//! constraints so that handshakes on orders that cannot be filled within them are declined
+//! early; within the MPC, the constraints are enforced as bounds on the match by the match
+//! gadget
+//!
+//! A price protection band is drawn around the median price report of the order's pair.
+//! An order with a band is not matched while no median may be relied on
+
+use std::time::Duration;
+
+use circuits::{
+    types::{
+        order::{MatchConstraints, Order, OrderSide},
+        r#match::MatchBounds,
+    },
+    zk_gadgets::fixed_point::FixedPoint,
+};
+use crossbeam::channel;
+use tracing::log;
+
+use crate::{
+    price_reporter::{jobs::PriceReporterManagerJob, reporter::PriceReporterState, tokens::Token},
+    state::OrderIdentifier,
+};
+
+use super::{error::HandshakeManagerError, manager::HandshakeExecutor};
+
+/// The amount of time to wait on the price reporter for a median price
+const MEDIAN_PEEK_TIMEOUT_MS: u64 = 500;
+/// The number of basis points in the whole
+const BPS_PER_UNIT: f64 = 10_000.;
+
+/// Fill in the constraints an order does not set with the given defaults
+pub fn effective_constraints(
+    constraints: &MatchConstraints,
+    defaults: &MatchConstraints,
+) -> MatchConstraints {
+    MatchConstraints {
+        min_fill_size: constraints.min_fill_size.or(defaults.min_fill_size),
+        max_slippage_bps: constraints.max_slippage_bps.or(defaults.max_slippage_bps),
+    }
+}
+
+/// The band of prices within the given slippage of the median, in the units of the median
+pub fn price_band(median: f64, max_slippage_bps: u16) -> (f64, f64) {
+    let slippage = max_slippage_bps as f64 / BPS_PER_UNIT;
+    (median * (1. - slippage), median * (1. + slippage))
+}
+
+/// Whether an order's limit price leaves room for an execution price within the band
+///
+/// The execution price of a buy order is at most its limit price, and that of a sell
+/// order at least its limit price
+pub fn limit_admits_band(side: OrderSide, limit_price: f64, band: (f64, f64)) -> bool {
+    let (min_price, max_price) = band;
+    match side {
+        OrderSide::Buy => min_price <= limit_price,
+        OrderSide::Sell => limit_price <= max_price,
+    }
+}
+
+impl HandshakeExecutor {
+    /// Whether a local order may be filled within its constraints at the current median
+    /// price, checked before an MPC is initiated on the order
+    pub(super) async fn local_order_admits_match(&self, order_id: &OrderIdentifier) -> bool {
+        let (order, constraints) = match self.local_match_constraints(order_id).await {
+            Some(res) => res,
+            None => return false,
+        };
+
+        if let Some(min_fill_size) = constraints.min_fill_size && order.amount < min_fill_size {
+            return false;
+        }
+
+        match constraints.max_slippage_bps {
+            Some(max_slippage_bps) => match self.peek_atomic_median(&order) {
+                Some(median) => limit_admits_band(
+                    order.side,
+                    order.price.to_f64(),
+                    price_band(median, max_slippage_bps),
+                ),
+                None => {
+                    log::info!("no median price for order {order_id}, declining to match it");
+                    false
+                }
+            },
+            None => true,
+        }
+    }
+
+    /// The bounds a local order's constraints place on its match in the MPC
+    pub(super) async fn local_match_bounds(
+        &self,
+        order_id: &OrderIdentifier,
+    ) -> Result<MatchBounds, HandshakeManagerError> {
+        let (order, constraints) = match self.local_match_constraints(order_id).await {
+            Some(res) => res,
+            None => {
+                return Err(HandshakeManagerError::StateNotFound(
+                    "missing local order".to_string(),
+                ))
+            }
+        };
+
+        let mut bounds =
+            MatchBounds::unbounded().with_min_fill_size(constraints.min_fill_size.unwrap_or(0));
+        if let Some(max_slippage_bps) = constraints.max_slippage_bps {
+            let median = self.peek_atomic_median(&order).ok_or_else(|| {
+                HandshakeManagerError::StateNotFound(
+                    "no median price to bound the match by".to_string(),
+                )
+            })?;
+            let (min_price, max_price) = price_band(median, max_slippage_bps);
+            bounds = bounds.with_price_range(
+                FixedPoint::from_f64_round_down(min_price),
+                FixedPoint::from_f64_round_down(max_price),
+            );
+        }
+
+        Ok(bounds)
+    }
+
+    /// A local order and the constraints on its matches, `None` if the order is not
+    /// managed locally
+    async fn local_match_constraints(
+        &self,
+        order_id: &OrderIdentifier,
+    ) -> Option<(Order, MatchConstraints)> {
+        let order = self.global_state.get_local_order(order_id).await?;
+        let constraints =
+            effective_constraints(&order.constraints, &self.default_match_constraints);
+
+        Some((order, constraints))
+    }
+
+    /// Peek the median price of an order's pair in the units of the order's price, atomic
+    /// units of the quote per atomic unit of the base
+    ///
+    /// Returns `None` if the price reporter publishes no median that matching may proceed
+    /// at, or if the decimals of either token are unknown
+    fn peek_atomic_median(&self, order: &Order) -> Option<f64> {
+        let (sender, receiver) = channel::unbounded();
+        self.price_reporter_work_queue
+            .send(PriceReporterManagerJob::PeekMedian {
+                base_token: Token::from_mint(&order.base_mint),
+                quote_token: Token::from_mint(&order.quote_mint),
+                channel: sender,
+            })
+            .ok()?;
+
+        let report = match receiver
+            .recv_timeout(Duration::from_millis(MEDIAN_PEEK_TIMEOUT_MS))
+            .ok()?
+        {
+            PriceReporterState::Nominal(report) | PriceReporterState::Held(report, _) => report,
+            _ => return None,
+        };
+
+        report
+            .atomic_midpoint_fixed_point()
+            .map(|price| price.to_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::types::order::{MatchConstraints, OrderSide};
+
+    use super::{effective_constraints, limit_admits_band, price_band};
+
+    /// Tests that an order's own constraints take precedence over the defaults
+    #[test]
+    fn test_effective_constraints() {
+        let defaults = MatchConstraints {
+            min_fill_size: Some(10),
+            max_slippage_bps: Some(50),
+        };
+        let constraints = MatchConstraints {
+            min_fill_size: Some(100),
+            max_slippage_bps: None,
+        };
+
+        let res = effective_constraints(&constraints, &defaults);
+        assert_eq!(res.min_fill_size, Some(100));
+        assert_eq!(res.max_slippage_bps, Some(50));
+    }
+
+    /// Tests that a limit price admits the band only if an execution price within the
+    /// band is on the order's side of its limit
+    #[test]
+    fn test_limit_admits_band() {
+        // 1% either side of a median of 100
+        let band = price_band(100., 100 /* max_slippage_bps */);
+        assert!((band.0 - 99.).abs() < 1e-9);
+        assert!((band.1 - 101.).abs() < 1e-9);
+
+        assert!(limit_admits_band(OrderSide::Buy, 105., band));
+        assert!(limit_admits_band(OrderSide::Buy, 99.5, band));
+        assert!(!limit_admits_band(OrderSide::Buy, 98., band));
+
+        assert!(limit_admits_band(OrderSide::Sell, 95., band));
+        assert!(limit_admits_band(OrderSide::Sell, 100.5, band));
+        assert!(!limit_admits_band(OrderSide::Sell, 102., band));
+    }
+}
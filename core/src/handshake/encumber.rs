@@ -1,8 +1,12 @@
 //! Implements the handshake manager flow of encumbering a pair of wallets
 //! after a match has completed. This involves:
-//!     1. Creating notes for the wallets, relayers, and protocol
-//!     2. Proving `VALID MATCH ENCRYPTION`
-//!     3. Submitting the proofs and data to the contract
+//!     1. Committing to the match on-chain, see `commitment`
+//!     2. Creating notes for the wallets, relayers, and protocol
+//!     3. Proving `VALID MATCH ENCRYPTION`
+//!     4. Submitting the proofs and data to the contract, see `settlement`
+//!
+//! Each stage is recorded in the settlement journal before the next begins, so that
+//! settlements interrupted by a crash are resumed when the relayer restarts
 
 use std::convert::TryInto;
 
@@ -29,28 +33,46 @@ use curve25519_dalek::scalar::Scalar;
 use integration_helpers::mpc_network::field::get_ristretto_group_modulus;
 use mpc_ristretto::mpc_scalar::scalar_to_u64;
 use num_bigint::BigUint;
-use rand_core::OsRng;
 use tokio::sync::oneshot;
 use tracing::log;
+use uuid::Uuid;
 
 use crate::{
-    proof_generation::jobs::{ProofJob, ProofManagerJob},
-    PROTOCOL_FEE, PROTOCOL_SETTLE_KEY,
+    gossip::types::ClusterId,
+    proof_generation::jobs::{
+        ProofCancellationToken, ProofJob, ProofJobPriority, ProofManagerJob,
+        ValidMatchEncryptBundle,
+    },
 };
 
-use super::{error::HandshakeManagerError, manager::HandshakeExecutor, r#match::HandshakeResult};
+use super::{
+    error::HandshakeManagerError, journal::SettlementJournalEntry, manager::HandshakeExecutor,
+    r#match::HandshakeResult,
+};
 
 impl HandshakeExecutor {
     /// Entrypoint to the encumbering flow, creates notes, proves `VALID MATCH ENCRYPTION`,
     /// and submits the match bundle to the contract
+    ///
+    /// The match must already be journaled under the given request ID. The proof is
+    /// cancelled if any of the given match nullifiers is shot down while it is in flight.
+    /// The protocol fee is resolved from the fee schedule for the match's pair and the
+    /// clusters managing it
     pub(super) async fn submit_match(
         &self,
+        request_id: Uuid,
+        match_nullifiers: &[Scalar],
+        peer_cluster_id: Option<&ClusterId>,
         handshake_result: HandshakeResult,
     ) -> Result<(), HandshakeManagerError> {
+        let protocol_fee = self.match_protocol_fee(&handshake_result.match_, peer_cluster_id);
+        let protocol_settle_key = self.fee_schedule.protocol_settle_key();
+
         // Create notes for all parties from the match
         #[allow(unused)]
         let (party0_note, party1_note, relayer0_note, relayer1_note, protocol_note) = self
             .create_notes(
+                protocol_fee,
                 &handshake_result.match_,
                 &handshake_result.party0_fee,
                 &handshake_result.party1_fee,
@@ -64,47 +86,43 @@ impl HandshakeExecutor {
         // Encrypt the volumes of the first party's note under their key
         let pk_settle0_bigint = scalar_to_biguint(&handshake_result.pk_settle0);
         let (volume1_ciphertext1, randomness) =
-            Self::encrypt_scalar(party0_note.volume1.into(), &pk_settle0_bigint);
+            self.encrypt_scalar(party0_note.volume1.into(), &pk_settle0_bigint);
         randomness_values.push(randomness);
 
         let (volume2_ciphertext1, randomness) =
-            Self::encrypt_scalar(party0_note.volume2.into(), &pk_settle0_bigint);
+            self.encrypt_scalar(party0_note.volume2.into(), &pk_settle0_bigint);
         randomness_values.push(randomness);
 
         // Encrypt the volumes of the second party's note under their key
         let pk_settle1_bigint = scalar_to_biguint(&handshake_result.pk_settle1);
         let (volume1_ciphertext2, randomness) =
-            Self::encrypt_scalar(party1_note.volume1.into(), &pk_settle1_bigint);
+            self.encrypt_scalar(party1_note.volume1.into(), &pk_settle1_bigint);
         randomness_values.push(randomness);
 
         let (volume2_ciphertext2, randomness) =
-            Self::encrypt_scalar(party1_note.volume2.into(), &pk_settle1_bigint);
+            self.encrypt_scalar(party1_note.volume2.into(), &pk_settle1_bigint);
         randomness_values.push(randomness);
 
         // Encrypt the mints, volumes and randomness of the protocol note under the protocol key
-        let (mint1_protocol_ciphertext, randomness) = Self::encrypt_scalar(
-            biguint_to_scalar(&protocol_note.mint1),
-            &PROTOCOL_SETTLE_KEY,
-        );
+        let (mint1_protocol_ciphertext, randomness) =
+            self.encrypt_scalar(biguint_to_scalar(&protocol_note.mint1), protocol_settle_key);
         randomness_values.push(randomness);
 
-        let (mint2_protocol_ciphertext, randomness) = Self::encrypt_scalar(
-            biguint_to_scalar(&protocol_note.mint2),
-            &PROTOCOL_SETTLE_KEY,
-        );
+        let (mint2_protocol_ciphertext, randomness) =
+            self.encrypt_scalar(biguint_to_scalar(&protocol_note.mint2), protocol_settle_key);
         randomness_values.push(randomness);
 
         let (volume1_protocol_ciphertext, randomness) =
-            Self::encrypt_scalar(protocol_note.volume1.into(), &PROTOCOL_SETTLE_KEY);
+            self.encrypt_scalar(protocol_note.volume1.into(), protocol_settle_key);
         randomness_values.push(randomness);
 
         let (volume2_protocol_ciphertext, randomness) =
-            Self::encrypt_scalar(protocol_note.volume2.into(), &PROTOCOL_SETTLE_KEY);
+            self.encrypt_scalar(protocol_note.volume2.into(), protocol_settle_key);
         randomness_values.push(randomness);
 
-        let (randomness_protocol_ciphertext, encryption_randomness) = Self::encrypt_scalar(
+        let (randomness_protocol_ciphertext, encryption_randomness) = self.encrypt_scalar(
             biguint_to_scalar(&protocol_note.randomness),
-            &PROTOCOL_SETTLE_KEY,
+            protocol_settle_key,
         );
         randomness_values.push(encryption_randomness);
 
@@ -138,14 +156,14 @@ impl HandshakeExecutor {
             ),
             protocol_note_commit: Self::note_commit(
                 &protocol_note,
-                biguint_to_scalar(&PROTOCOL_SETTLE_KEY),
+                biguint_to_scalar(protocol_settle_key),
             ),
             pk_settle_party0: handshake_result.pk_settle0,
             pk_settle_party1: handshake_result.pk_settle1,
             pk_settle_relayer0: handshake_result.pk_settle_cluster0,
             pk_settle_relayer1: handshake_result.pk_settle_cluster1,
-            pk_settle_protocol: biguint_to_scalar(&PROTOCOL_SETTLE_KEY),
-            protocol_fee: *PROTOCOL_FEE,
+            pk_settle_protocol: biguint_to_scalar(protocol_settle_key),
+            protocol_fee,
             volume1_ciphertext1,
             volume2_ciphertext1,
             volume1_ciphertext2,
@@ -157,9 +175,119 @@ impl HandshakeExecutor {
             randomness_protocol_ciphertext,
         };
 
-        self.prove_valid_encryption(witness, statement).await?;
+        let bundle = self
+            .prove_valid_encryption(request_id, match_nullifiers, witness, statement)
+            .await?;
+
+        // Journal the proof ahead of submission so that a restart resumes from here rather
+        // than re-proving
+        self.settlement_journal
+            .record_encryption_proof(&request_id, bundle.clone())?;
+        self.submit_settlement(request_id, bundle).await
+    }
+
+    /// Replay the settlements left in the journal by a previous run
+    ///
+    /// Entries are replayed one at a time in the order they were journaled. A settlement
+    /// is abandoned if the local order's wallet no longer holds the match nullifier it
+    /// had when the match was made; the wallet has been updated on-chain since, and the
+    /// contract would reject the settlement. Otherwise it resumes from the last stage
+    /// recorded in the journal
+    pub(super) async fn replay_settlement_journal(&self) {
+        for entry in self.settlement_journal.pending_entries().into_iter() {
+            let request_id = entry.request_id;
+            let res = if !self.settlement_is_live(&entry).await {
+                log::warn!("abandoning journaled settlement {request_id}, the wallet has changed");
+                self.settlement_journal.remove(&request_id)
+            } else {
+                self.resume_settlement(entry).await
+            };
+
+            if let Err(e) = res {
+                log::error!("error replaying journaled settlement {request_id}: {e}");
+            }
+        }
+    }
+
+    /// Resume a journaled settlement from the last stage it reached
+    ///
+    /// A match journaled before its commitment was posted is committed to first
+    async fn resume_settlement(
+        &self,
+        entry: SettlementJournalEntry,
+    ) -> Result<(), HandshakeManagerError> {
+        let request_id = entry.request_id;
+        self.commit_match(request_id).await?;
+
+        if let Some(bundle) = entry.encryption_bundle {
+            log::info!("resuming journaled settlement {request_id} at submission");
+            self.submit_settlement(request_id, bundle).await
+        } else {
+            log::info!("resuming journaled settlement {request_id} at encryption");
+            self.submit_match(
+                request_id,
+                &[entry.local_match_nullifier],
+                entry.peer_cluster_id.as_ref(),
+                entry.handshake_result,
+            )
+            .await
+        }
+    }
+
+    /// Resolve the protocol fee charged on a match from the fee schedule
+    ///
+    /// The fee depends on the pair and the clusters managing the match; the local cluster
+    /// and, if known, the peer's
+    pub(super) fn match_protocol_fee(
+        &self,
+        match_res: &LinkableMatchResultCommitment,
+        peer_cluster_id: Option<&ClusterId>,
+    ) -> FixedPoint {
+        let base_mint = scalar_to_biguint(&match_res.base_mint.into());
+        let quote_mint = scalar_to_biguint(&match_res.quote_mint.into());
+        let mut clusters = vec![self.global_state.local_cluster_id.clone()];
+        clusters.extend(peer_cluster_id.cloned());
+
+        self.fee_schedule
+            .protocol_fee(&base_mint, &quote_mint, &clusters)
+    }
 
-        Ok(())
+    /// Check that a proven statement of `VALID MATCH ENCRYPTION` commits to the protocol
+    /// fee and settle key the fee schedule currently resolves for the journaled match
+    ///
+    /// The contract rejects a settlement proven against any other fee, e.g. one journaled
+    /// before the schedule changed
+    pub(super) fn validate_protocol_fee(
+        &self,
+        entry: &SettlementJournalEntry,
+        statement: &ValidMatchEncryptionStatement,
+    ) -> Result<(), HandshakeManagerError> {
+        let match_res = &entry.handshake_result.match_;
+        let mut clusters = vec![self.global_state.local_cluster_id.clone()];
+        clusters.extend(entry.peer_cluster_id.clone());
+
+        self.fee_schedule
+            .validate_statement(
+                statement,
+                &scalar_to_biguint(&match_res.base_mint.into()),
+                &scalar_to_biguint(&match_res.quote_mint.into()),
+                &clusters,
+            )
+            .map_err(|err| HandshakeManagerError::FeeSchedule(err.to_string()))
+    }
+
+    /// Whether the local wallet in a journaled settlement is still at the version the
+    /// match was made against
+    async fn settlement_is_live(&self, entry: &SettlementJournalEntry) -> bool {
+        let locked_wallet_index = self.global_state.read_wallet_index().await;
+        let wallet = match locked_wallet_index.get_wallet_for_order(&entry.local_order_id) {
+            Some(wallet_id) => locked_wallet_index.get_wallet(&wallet_id).await,
+            None => None,
+        };
+
+        wallet.map_or(false, |wallet| {
+            wallet.get_match_nullifier() == entry.local_match_nullifier
+        })
     }
 
     /// Generate a proof of `VALID MATCH ENCRYPTION` by forwarding a request to the proof manager
@@ -167,27 +295,50 @@ impl HandshakeExecutor {
     ///
     /// This code path is executed relatively infrequently (only when a valid match is found), so it
     /// is likely okay to directly block a thread in the pool. If this becomes an issue we can go async
+    ///
+    /// The job is registered with the state index under the match nullifiers, so that a shootdown
+    /// of either nullifier frees the prover rather than finishing a settlement the contract would
+    /// reject. A cancelled settlement is retired from the journal
     async fn prove_valid_encryption(
         &self,
+        request_id: Uuid,
+        match_nullifiers: &[Scalar],
         witness: ValidMatchEncryptionWitness,
         statement: ValidMatchEncryptionStatement,
-    ) -> Result<(), HandshakeManagerError> {
+    ) -> Result<ValidMatchEncryptBundle, HandshakeManagerError> {
         // Forward the job to the proof manager
+        let token = ProofCancellationToken::new();
+        self.handshake_state_index.register_settlement_proof(
+            request_id,
+            match_nullifiers,
+            token.clone(),
+        );
+
         let (response_channel_sender, response_channel_receiver) = oneshot::channel();
         self.proof_manager_work_queue
             .send(ProofManagerJob {
                 type_: ProofJob::ValidMatchEncrypt { witness, statement },
+                priority: ProofJobPriority::Settlement,
+                cancellation: Some(token.clone()),
                 response_channel: response_channel_sender,
             })
             .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
 
         // Await the proof manager's response
-        let _proof = response_channel_receiver
-            .await
-            .map_err(|err| HandshakeManagerError::ReceiveProof(err.to_string()))?;
+        let res = response_channel_receiver.await;
+        self.handshake_state_index
+            .settlement_proof_finished(&request_id, match_nullifiers);
+
+        if token.is_cancelled() {
+            self.settlement_journal.remove(&request_id)?;
+            return Err(HandshakeManagerError::Cancelled(
+                "settlement proof cancelled by nullifier shootdown".to_string(),
+            ));
+        }
+        let proof = res.map_err(|err| HandshakeManagerError::ReceiveProof(err.to_string()))?;
 
         log::info!("finished proving VALID MATCH ENCRYPTION, encumbering");
-        Ok(())
+        Ok(proof.into())
     }
 
     /// A wrapper around the `circuits` crate's note commitment helper that handles type conversion
@@ -202,8 +353,11 @@ impl HandshakeExecutor {
     ///     - Each of the parties receives a note for their side of the match (2)
     ///     - Each of the managing relayers receives a note for their fees (2)
     ///     - The protocol receives a note for its fee (1)
-    fn create_notes(
+    ///
+    /// The parties' notes are net of the given protocol fee
+    pub(super) fn create_notes(
         &self,
+        protocol_fee: FixedPoint,
         match_res: &LinkableMatchResultCommitment,
         party0_fee: &LinkableFeeCommitment,
         party1_fee: &LinkableFeeCommitment,
@@ -221,8 +375,8 @@ impl HandshakeExecutor {
         // Apply fees to the match
         let percent_fee0: FixedPoint = party0_fee.percentage_fee.into();
         let percent_fee1: FixedPoint = party1_fee.percentage_fee.into();
-        let party0_net_percentage = Scalar::one() - percent_fee0 - *PROTOCOL_FEE;
-        let party1_net_percentage = Scalar::one() - percent_fee1 - *PROTOCOL_FEE;
+        let party0_net_percentage = Scalar::one() - percent_fee0 - protocol_fee;
+        let party1_net_percentage = Scalar::one() - percent_fee1 - protocol_fee;
 
         let (party0_base_amount, party0_quote_amount, party1_base_amount, party1_quote_amount) =
             match match_direction {
@@ -332,8 +486,8 @@ impl HandshakeExecutor {
         };
 
         // Build the protocol note
-        let protocol_base_amount = scalar_to_u64(&(*PROTOCOL_FEE * base_amount_scalar).floor());
-        let protocol_quote_amount = scalar_to_u64(&(*PROTOCOL_FEE * quote_amount_scalar).floor());
+        let protocol_base_amount = scalar_to_u64(&(protocol_fee * base_amount_scalar).floor());
+        let protocol_quote_amount = scalar_to_u64(&(protocol_fee * quote_amount_scalar).floor());
 
         let protocol_note = Note {
             mint1: scalar_to_biguint(&match_res.base_mint.into()),
@@ -362,9 +516,8 @@ impl HandshakeExecutor {
     ///
     /// Return both the encryption (used as a public variable) and the randomness
     /// used to generate the encryption (used as a witness variable)
-    fn encrypt_scalar(val: Scalar, pubkey: &BigUint) -> (ElGamalCiphertext, Scalar) {
-        let mut rng = OsRng {};
-        let randomness = scalar_to_biguint(&Scalar::random(&mut rng));
+    fn encrypt_scalar(&self, val: Scalar, pubkey: &BigUint) -> (ElGamalCiphertext, Scalar) {
+        let randomness = scalar_to_biguint(&self.rng.gen_scalar());
 
         let field_mod = get_ristretto_group_modulus();
         let ciphertext1 =
@@ -382,3 +535,263 @@ impl HandshakeExecutor {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        env, fs,
+        sync::Arc,
+        time::Duration,
+    };
+
+    use circuits::{
+        singleprover_prove,
+        types::{
+            balance::Balance,
+            fee::Fee,
+            order::{MatchConstraints, Order},
+            r#match::MatchResult,
+        },
+        zk_gadgets::{comparators::EqZeroGadget, fixed_point::FixedPoint},
+        LinkableCommitment,
+    };
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
+    use tokio::sync::watch;
+    use uuid::Uuid;
+
+    use crate::{
+        clock::{ManualClock, SharedClock},
+        fee_schedule::FeeSchedule,
+        handshake::{
+            journal::{SettlementJournal, SettlementJournalEntry},
+            manager::{HandshakeExecutor, HandshakeExecutorConfig},
+            r#match::HandshakeResult,
+            state::HandshakeStateIndex,
+        },
+        job_queue::{job_queue, DEFAULT_JOB_QUEUE_CAPACITY, PROOF_MANAGER_QUEUE},
+        keychain,
+        proof_generation::proof_cache::ProofCache,
+        rng::WorkerRng,
+        simulation::chain::MockStarknetClient,
+        state::{
+            cluster_access::ClusterAccessPolicy,
+            feature_flags::FeatureFlags,
+            wallet::{Wallet, WalletMetadata},
+            RelayerState,
+        },
+        system_bus::SystemBus,
+        types::SystemBusMessage,
+    };
+
+    /// Build a wallet holding the given order
+    fn test_wallet(order_id: Uuid) -> Wallet {
+        let (public_keys, secret_keys) = keychain::derive_keychain(Scalar::from(7u64));
+        Wallet {
+            wallet_id: Uuid::new_v4(),
+            orders: HashMap::from([(
+                order_id,
+                Order {
+                    quote_mint: BigUint::from(1u8),
+                    base_mint: BigUint::from(2u8),
+                    price: FixedPoint::from(10f32),
+                    amount: 50,
+                    ..Default::default()
+                },
+            )]),
+            balances: HashMap::from([(
+                BigUint::from(1u8),
+                Balance {
+                    mint: BigUint::from(1u8),
+                    amount: 1_000,
+                },
+            )]),
+            fees: Vec::new(),
+            public_keys,
+            secret_keys,
+            randomness: BigUint::from(0u8),
+            metadata: WalletMetadata {
+                replicas: HashSet::new(),
+                version: 0,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: Default::default(),
+        }
+    }
+
+    /// Build the result of a match of 50 base for 500 quote
+    ///
+    /// The proof of `VALID MATCH MPC` is never verified during replay, a proof of a small
+    /// gadget stands in for it
+    fn handshake_result() -> HandshakeResult {
+        let match_res = MatchResult {
+            quote_mint: BigUint::from(1u8),
+            base_mint: BigUint::from(2u8),
+            quote_amount: 500,
+            base_amount: 50,
+            direction: 0,
+            execution_price: FixedPoint::from(10f32),
+            max_minus_min_amount: 0,
+            min_amount_order_index: 0,
+        };
+        let fee = Fee {
+            settle_key: BigUint::from(3u8),
+            gas_addr: BigUint::from(1u8),
+            gas_token_amount: 0,
+            percentage_fee: FixedPoint::from(0.001f32),
+        };
+        let (_, proof) =
+            singleprover_prove::<EqZeroGadget>(Scalar::zero(), true /* statement */).unwrap();
+
+        HandshakeResult {
+            match_: match_res.into(),
+            proof,
+            party0_fee: fee.clone().into(),
+            party1_fee: fee.into(),
+            party0_randomness_hash: LinkableCommitment::new(Scalar::from(4u8)),
+            party1_randomness_hash: LinkableCommitment::new(Scalar::from(5u8)),
+            pk_settle0: Scalar::from(6u8),
+            pk_settle1: Scalar::from(7u8),
+            pk_settle_cluster0: Scalar::from(8u8),
+            pk_settle_cluster1: Scalar::from(9u8),
+        }
+    }
+
+    /// Build a journal entry for a match on the given order, as journaled when the MPC
+    /// completes
+    fn journal_entry(
+        local_order_id: Uuid,
+        local_match_nullifier: Scalar,
+        created_at: u64,
+    ) -> SettlementJournalEntry {
+        SettlementJournalEntry {
+            request_id: Uuid::new_v4(),
+            local_order_id,
+            peer_order_id: Uuid::new_v4(),
+            local_match_nullifier,
+            party_match_nullifiers: [local_match_nullifier, Scalar::from(10u8)],
+            peer_cluster_id: None,
+            handshake_result: handshake_result(),
+            match_commitment: None,
+            encryption_bundle: None,
+            created_at,
+        }
+    }
+
+    /// Build an executor over the given journal, as a restarted relayer would
+    ///
+    /// The proof manager's queue is closed, so a replayed settlement stops short of
+    /// proving `VALID MATCH ENCRYPTION`, as it would if the relayer crashed again
+    fn setup_executor(
+        global_state: RelayerState,
+        system_bus: SystemBus<SystemBusMessage>,
+        chain: &MockStarknetClient,
+        clock: SharedClock,
+        journal: SettlementJournal,
+    ) -> HandshakeExecutor {
+        let (_, job_receiver) = job_queue("handshake", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (_, priority_job_receiver) = job_queue("priority", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (network_sender, _) = job_queue("network", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (price_reporter_sender, _) = job_queue("price", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (proof_manager_sender, _) = job_queue(PROOF_MANAGER_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        let (_, cancel_receiver) = watch::channel(());
+
+        HandshakeExecutor::new(HandshakeExecutorConfig {
+            job_channel: job_receiver,
+            priority_job_channel: priority_job_receiver,
+            network_channel: Arc::new(network_sender),
+            proof_manager_work_queue: proof_manager_sender,
+            global_state: global_state.clone(),
+            handshake_state_index: HandshakeStateIndex::new(global_state, clock.clone()),
+            system_bus,
+            mpc_timeout_ms: 1_000,
+            size_bucket_check: false,
+            handshake_prescreen: false,
+            max_concurrent_mpcs: 1,
+            max_concurrent_mpcs_per_peer: 1,
+            max_settlement_fee: None,
+            fee_schedule: FeeSchedule::default(),
+            broker_fee_bps: None,
+            max_broker_fee_bps: 0,
+            default_match_constraints: MatchConstraints::default(),
+            price_reporter_work_queue: price_reporter_sender,
+            starknet_client: Arc::new(chain.clone()),
+            rng: WorkerRng::new(Some(1)),
+            settlement_journal: journal,
+            handshake_cache_file: None,
+            clock,
+            cancel: cancel_receiver,
+        })
+        .unwrap()
+    }
+
+    /// Tests that replaying a journal left partially written by a crash resumes the live
+    /// settlement, abandons the stale one, and that replaying it again after a second
+    /// crash neither commits to the match twice nor loses the progress recorded
+    #[tokio::test]
+    async fn test_replay_partial_journal() {
+        let path = env::temp_dir()
+            .join(format!("settlement-journal-{}.json", Uuid::new_v4()))
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let order_id = Uuid::new_v4();
+        let wallet = test_wallet(order_id);
+        let live_entry = journal_entry(order_id, wallet.get_match_nullifier(), 1);
+        let stale_entry = journal_entry(order_id, Scalar::from(11u8), 2);
+
+        // Journal both matches as the MPCs complete, then crash before either is committed
+        let journal = SettlementJournal::open(Some(path.clone())).unwrap();
+        journal.record(live_entry.clone()).unwrap();
+        journal.record(stale_entry).unwrap();
+        drop(journal);
+
+        let system_bus = SystemBus::new();
+        let global_state = RelayerState::initialize_global_state(
+            false, /* debug */
+            vec![wallet],
+            "cluster".parse().unwrap(),
+            ClusterAccessPolicy::default(),
+            system_bus.clone(),
+            ProofCache::new(None /* cache_dir */).unwrap(),
+            FeatureFlags::new(&HashMap::new()),
+        );
+        let clock: SharedClock = Arc::new(ManualClock::new(Duration::from_secs(100)));
+        let chain = MockStarknetClient::new(1_000, 0, 0., WorkerRng::new(Some(1)), clock.clone());
+
+        let mut commitment = None;
+        for _ in 0..2 {
+            let journal = SettlementJournal::open(Some(path.clone())).unwrap();
+            setup_executor(
+                global_state.clone(),
+                system_bus.clone(),
+                &chain,
+                clock.clone(),
+                journal,
+            )
+            .replay_settlement_journal()
+            .await;
+
+            // The live match is committed to exactly once, across both replays
+            assert_eq!(chain.match_commitments(), 1);
+            assert!(chain.settlements().is_empty());
+
+            let pending = SettlementJournal::open(Some(path.clone()))
+                .unwrap()
+                .pending_entries();
+            assert_eq!(pending.len(), 1);
+            assert_eq!(pending[0].request_id, live_entry.request_id);
+            assert!(pending[0].match_commitment.is_some());
+            assert!(pending[0].encryption_bundle.is_none());
+
+            let recovered = pending[0].match_commitment;
+            assert!(commitment.is_none() || commitment == recovered);
+            commitment = recovered;
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
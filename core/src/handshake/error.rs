@@ -2,6 +2,11 @@
 
 use std::fmt::Display;
 
+use crate::{
+    error::{ErrorCode, ErrorCoded},
+    starknet_client::error::StarknetClientError,
+};
+
 /// The core error type for the handshake manager
 #[derive(Clone, Debug)]
 pub enum HandshakeManagerError {
@@ -14,6 +19,8 @@ pub enum HandshakeManagerError {
     MpcNetwork(String),
     /// An MpcShootdown request has stopped the handshake
     MpcShootdown,
+    /// The MPC did not complete within the configured timeout
+    MpcTimeout,
     /// Error verifying a proof
     VerificationError(String),
     /// Error awaiting a proof from the proof generation module
@@ -29,10 +36,47 @@ pub enum HandshakeManagerError {
     StateNotFound(String),
     /// Error resulting from a cancellation signal
     Cancelled(String),
+    /// Error reading or writing the settlement journal
+    Journal(String),
+    /// Error reading the persisted handshake cache
+    Cache(String),
+    /// A settlement could not be submitted before the network cleared of congestion
+    Settlement(String),
+    /// A settlement was built against a protocol fee the fee schedule does not resolve to
+    FeeSchedule(String),
+    /// A proof of `VALID MATCH ENCRYPTION` does not open the commitment posted for its match
+    MatchCommitment(String),
+    /// The StarkNet client failed to submit a settlement
+    Starknet(StarknetClientError),
 }
 
+impl std::error::Error for HandshakeManagerError {}
 impl Display for HandshakeManagerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+impl ErrorCoded for HandshakeManagerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            HandshakeManagerError::Multiprover(_) => ErrorCode::Proof,
+            HandshakeManagerError::InvalidRequest(_) => ErrorCode::BadRequest,
+            HandshakeManagerError::MpcNetwork(_) => ErrorCode::Network,
+            HandshakeManagerError::MpcShootdown => ErrorCode::Cancelled,
+            HandshakeManagerError::MpcTimeout => ErrorCode::Timeout,
+            HandshakeManagerError::VerificationError(_) => ErrorCode::Proof,
+            HandshakeManagerError::ReceiveProof(_) => ErrorCode::Proof,
+            HandshakeManagerError::SendMessage(_) => ErrorCode::Internal,
+            HandshakeManagerError::SetupError(_) => ErrorCode::Setup,
+            HandshakeManagerError::StateNotFound(_) => ErrorCode::NotFound,
+            HandshakeManagerError::Cancelled(_) => ErrorCode::Cancelled,
+            HandshakeManagerError::Journal(_) => ErrorCode::Storage,
+            HandshakeManagerError::Cache(_) => ErrorCode::Storage,
+            HandshakeManagerError::Settlement(_) => ErrorCode::Timeout,
+            HandshakeManagerError::FeeSchedule(_) => ErrorCode::Config,
+            HandshakeManagerError::MatchCommitment(_) => ErrorCode::Proof,
+            HandshakeManagerError::Starknet(err) => err.error_code(),
+        }
+    }
+}
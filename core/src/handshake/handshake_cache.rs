@@ -4,23 +4,41 @@
 //!
 //! The cache abstracts mostly over ordering semantics. We cache in pairs of orders and the
 //! caller should not have to implement messy logic to order the pairs correctly.
+//!
+//! The cache shared between handshake jobs is split into shards by order pair, each behind
+//! its own lock, so that concurrent jobs on different pairs rarely contend.
+//!
+//! If a cache file is configured, the completed pairs are persisted to it whenever a pair is
+//! completed and read back on startup, so that a restarted relayer does not re-attempt
+//! matches on dead pairs. Invisible pairs are transient and are not persisted.
 
 // TODO: Remove this lint allowance
 #![allow(dead_code)]
 
 use std::{
     cmp::{max, min},
-    hash::Hash,
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
     num::NonZeroUsize,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
     time::{Duration, Instant},
 };
 
 use lru::LruCache;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::log;
+
+use crate::clock::SharedClock;
 
-use crate::state::AsyncShared;
+use super::error::HandshakeManagerError;
 
-/// A type alias for a HandshakeCache shared between threads
-pub(super) type SharedHandshakeCache<O> = AsyncShared<HandshakeCache<O>>;
+/// The number of shards the shared handshake cache is split into
+pub const HANDSHAKE_CACHE_SHARDS: usize = 16;
+
+/// A type alias for a sharded HandshakeCache shared between threads
+pub(super) type SharedHandshakeCache<O> = Arc<ShardedHandshakeCache<O>>;
 
 /// Caches pairs of orders that have already been matched so that we may avoid attempting to
 /// match orders multiple times
@@ -35,6 +53,8 @@ pub struct HandshakeCache<O> {
     ///
     /// Entries are cached with the lower (abstract ordering) identifier stored first
     lru_cache: LruCache<(O, O), HandshakeCacheState>,
+    /// The clock against which invisibility windows are measured
+    clock: SharedClock,
 }
 
 /// Represents the state of an entry in the handshake cache for various types of caching
@@ -61,11 +81,12 @@ pub enum HandshakeCacheState {
 
 impl<O: Clone + Eq + Hash + Ord> HandshakeCache<O> {
     /// Create a new handshake cache with given capacity
-    pub fn new(max_size: usize) -> Self {
+    pub fn new(max_size: usize, clock: SharedClock) -> Self {
         Self {
             size: 0,
             max_size,
             lru_cache: LruCache::new(NonZeroUsize::new(max_size).unwrap()),
+            clock,
         }
     }
 
@@ -74,6 +95,11 @@ impl<O: Clone + Eq + Hash + Ord> HandshakeCache<O> {
         self.lru_cache.len()
     }
 
+    /// Returns whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.lru_cache.is_empty()
+    }
+
     /// Computes the cache tuple from a given pair of identifiers
     ///
     /// The ordering of identifiers in the cache tuple is defined abstractly by
@@ -98,11 +124,26 @@ impl<O: Clone + Eq + Hash + Ord> HandshakeCache<O> {
         self.lru_cache.push(
             Self::cache_tuple(o1, o2),
             HandshakeCacheState::Invisible {
-                until: Instant::now() + window,
+                until: self.clock.now() + window,
             },
         );
     }
 
+    /// Removes a pair from the cache, allowing it to be scheduled again
+    pub fn remove(&mut self, o1: O, o2: O) {
+        self.lru_cache.pop(&Self::cache_tuple(o1, o2));
+    }
+
+    /// Returns the completed pairs in the cache, least recently used first
+    pub fn completed_pairs(&self) -> Vec<(O, O)> {
+        self.lru_cache
+            .iter()
+            .rev()
+            .filter(|(_, state)| matches!(state, HandshakeCacheState::Completed))
+            .map(|(pair, _)| pair.clone())
+            .collect()
+    }
+
     /// Checks whether a given pair is cached
     pub fn contains(&self, o1: O, o2: O) -> bool {
         // If the cache contains the entry in the `Invisible` state and the invisibility window
@@ -112,9 +153,9 @@ impl<O: Clone + Eq + Hash + Ord> HandshakeCache<O> {
                 HandshakeCacheState::Completed => true,
                 HandshakeCacheState::Invisible { until } => {
                     // checked_duration_since will return none if the arg is later than
-                    // `Instant::now()`. If `is_none() == true` then the invisibility
+                    // the current time. If `is_none() == true` then the invisibility
                     // window has not elapsed and the entry is considered cached
-                    Instant::now().checked_duration_since(*until).is_none()
+                    self.clock.now().checked_duration_since(*until).is_none()
                 }
             }
         } else {
@@ -123,14 +164,159 @@ impl<O: Clone + Eq + Hash + Ord> HandshakeCache<O> {
     }
 }
 
+/// A `HandshakeCache` split into shards by order pair
+///
+/// Each shard is guarded by a synchronous lock that is only held for the duration of a
+/// single cache operation, never across an await point. The LRU policy is enforced per
+/// shard, so the cache as a whole evicts approximately least recently used pairs
+pub struct ShardedHandshakeCache<O> {
+    /// The shards of the cache, a pair is cached in the shard its cache tuple hashes to
+    shards: Vec<Mutex<HandshakeCache<O>>>,
+    /// The file the completed pairs are persisted to; the cache is held only in memory if unset
+    path: Option<String>,
+    /// Serializes writes to the cache file
+    persist_lock: Mutex<()>,
+}
+
+impl<O: Clone + Eq + Hash + Ord + Serialize + DeserializeOwned> ShardedHandshakeCache<O> {
+    /// Create a new sharded cache, splitting the given capacity evenly between shards
+    pub fn new(max_size: usize, num_shards: usize, clock: SharedClock) -> Self {
+        let shard_size = (max_size + num_shards - 1) / num_shards;
+        let shards = (0..num_shards)
+            .map(|_| Mutex::new(HandshakeCache::new(shard_size, clock.clone())))
+            .collect();
+
+        Self {
+            shards,
+            path: None,
+            persist_lock: Mutex::new(()),
+        }
+    }
+
+    /// Open a sharded cache persisted at the given path, reading any completed pairs left by
+    /// a previous run
+    pub fn open(
+        max_size: usize,
+        num_shards: usize,
+        path: Option<String>,
+        clock: SharedClock,
+    ) -> Result<Self, HandshakeManagerError> {
+        let mut cache = Self::new(max_size, num_shards, clock);
+        if let Some(path) = &path && Path::new(path).exists() {
+            let contents =
+                fs::read(path).map_err(|err| HandshakeManagerError::Cache(err.to_string()))?;
+            let pairs: Vec<(O, O)> = serde_json::from_slice(&contents)
+                .map_err(|err| HandshakeManagerError::Cache(err.to_string()))?;
+            for (o1, o2) in pairs.into_iter() {
+                cache.shard(&o1, &o2).mark_completed(o1, o2);
+            }
+        }
+
+        cache.path = path;
+        Ok(cache)
+    }
+
+    /// Lock the shard that the given pair is cached in
+    fn shard(&self, o1: &O, o2: &O) -> MutexGuard<'_, HandshakeCache<O>> {
+        let mut hasher = DefaultHasher::new();
+        HandshakeCache::cache_tuple(o1.clone(), o2.clone()).hash(&mut hasher);
+        let shard_idx = (hasher.finish() as usize) % self.shards.len();
+
+        self.shards[shard_idx].lock().unwrap()
+    }
+
+    /// Returns the number of elements currently cached across all shards
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Returns whether every shard is empty
+    pub fn is_empty(&self) -> bool {
+        self.shards
+            .iter()
+            .all(|shard| shard.lock().unwrap().is_empty())
+    }
+
+    /// Caches an entry
+    pub fn mark_completed(&self, o1: O, o2: O) {
+        self.shard(&o1, &o2).mark_completed(o1, o2);
+        self.persist();
+    }
+
+    /// Caches a set of entries, e.g. those pulled from a cluster peer
+    pub fn mark_completed_many(&self, pairs: Vec<(O, O)>) {
+        for (o1, o2) in pairs.into_iter() {
+            self.shard(&o1, &o2).mark_completed(o1, o2);
+        }
+        self.persist();
+    }
+
+    /// Returns the completed pairs across all shards
+    pub fn completed_pairs(&self) -> Vec<(O, O)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.lock().unwrap().completed_pairs())
+            .collect()
+    }
+
+    /// Mark the given pair as invisible for a duration
+    pub fn mark_invisible(&self, o1: O, o2: O, window: Duration) {
+        self.shard(&o1, &o2).mark_invisible(o1, o2, window)
+    }
+
+    /// Removes a pair from the cache, allowing it to be scheduled again
+    pub fn remove(&self, o1: O, o2: O) {
+        self.shard(&o1, &o2).remove(o1, o2)
+    }
+
+    /// Checks whether a given pair is cached
+    pub fn contains(&self, o1: O, o2: O) -> bool {
+        self.shard(&o1, &o2).contains(o1, o2)
+    }
+
+    /// Persist the completed pairs, replacing the previous contents of the cache file
+    ///
+    /// A failure to persist is logged rather than returned; the in-memory cache remains
+    /// authoritative and the next completion retries the write
+    fn persist(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let _locked_file = self.persist_lock.lock().unwrap();
+        let res = serde_json::to_vec(&self.completed_pairs())
+            .map_err(|err| err.to_string())
+            .and_then(|serialized| {
+                let tmp_path = format!("{path}.tmp");
+                fs::write(&tmp_path, serialized)
+                    .and_then(|_| fs::rename(&tmp_path, path))
+                    .map_err(|err| err.to_string())
+            });
+
+        if let Err(err) = res {
+            log::error!("error persisting handshake cache: {err}");
+        }
+    }
+}
+
 #[cfg(test)]
 mod handshake_cache_tests {
-    use super::HandshakeCache;
+    use std::{env, fs, sync::Arc, time::Duration};
+
+    use uuid::Uuid;
+
+    use crate::clock::{system_clock, ManualClock};
+
+    use super::{HandshakeCache, ShardedHandshakeCache};
 
     /// Tests that LRU is enforced on the cache
     #[test]
     fn test_lru_policy() {
-        let mut cache = HandshakeCache::new(2 /* max_size */);
+        let mut cache = HandshakeCache::new(2 /* max_size */, system_clock());
         cache.mark_completed(1, 1);
         cache.mark_completed(2, 2);
         cache.mark_completed(3, 3);
@@ -143,7 +329,7 @@ mod handshake_cache_tests {
     /// Tests that cache pushes and queries can occur in either key order
     #[test]
     fn test_cache_ordering() {
-        let mut cache = HandshakeCache::new(1 /* max_size */);
+        let mut cache = HandshakeCache::new(1 /* max_size */, system_clock());
         // Try the smaller value first
         cache.mark_completed(4, 5);
         assert!(cache.contains(4, 5));
@@ -155,4 +341,88 @@ mod handshake_cache_tests {
         assert!(cache.contains(6, 7));
         assert!(cache.contains(7, 6));
     }
+
+    /// Tests that a removed pair is no longer cached
+    #[test]
+    fn test_remove() {
+        let mut cache = HandshakeCache::new(2 /* max_size */, system_clock());
+        cache.mark_completed(1, 2);
+        cache.remove(2, 1);
+
+        assert!(!cache.contains(1, 2));
+    }
+
+    /// Tests that an invisible pair becomes visible once its window elapses
+    #[test]
+    fn test_invisibility_window() {
+        let clock = ManualClock::new(Duration::ZERO);
+        let mut cache = HandshakeCache::new(2 /* max_size */, Arc::new(clock.clone()));
+        cache.mark_invisible(1, 2, Duration::from_secs(30));
+
+        clock.advance(Duration::from_secs(29));
+        assert!(cache.contains(1, 2));
+
+        clock.advance(Duration::from_secs(1));
+        assert!(!cache.contains(1, 2));
+    }
+
+    /// Tests that pairs are found in their shard in either key order, and that the
+    /// capacity is split between shards
+    #[test]
+    fn test_sharded_cache() {
+        let cache = ShardedHandshakeCache::new(
+            64, /* max_size */
+            4,  /* num_shards */
+            system_clock(),
+        );
+        for i in 0..16 {
+            cache.mark_completed(i, i + 1);
+        }
+
+        for i in 0..16 {
+            assert!(cache.contains(i + 1, i));
+        }
+        assert_eq!(cache.len(), 16);
+
+        cache.remove(1, 0);
+        assert!(!cache.contains(0, 1));
+        assert_eq!(cache.len(), 15);
+    }
+
+    /// Tests that completed pairs survive a reopen of a persisted cache, and that invisible
+    /// pairs do not
+    #[test]
+    fn test_persisted_cache() {
+        let path = env::temp_dir()
+            .join(format!("handshake-cache-{}.json", Uuid::new_v4()))
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let cache = ShardedHandshakeCache::open(
+            16, /* max_size */
+            4,  /* num_shards */
+            Some(path.clone()),
+            system_clock(),
+        )
+        .unwrap();
+        cache.mark_invisible(5, 6, Duration::from_secs(30));
+        cache.mark_completed(1, 2);
+        cache.mark_completed_many(vec![(4, 3)]);
+        drop(cache);
+
+        let reopened = ShardedHandshakeCache::<u64>::open(
+            16, /* max_size */
+            4,  /* num_shards */
+            Some(path.clone()),
+            system_clock(),
+        )
+        .unwrap();
+        assert!(reopened.contains(2, 1));
+        assert!(reopened.contains(3, 4));
+        assert!(!reopened.contains(5, 6));
+        assert_eq!(reopened.len(), 2);
+
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -7,7 +7,11 @@ use uuid::Uuid;
 
 use crate::{
     gossip::types::WrappedPeerId,
-    gossip_api::{gossip::AuthenticatedGossipResponse, handshake::HandshakeMessage},
+    gossip_api::{
+        gossip::AuthenticatedGossipResponse,
+        handshake::{BrokerMessage, HandshakeMessage},
+    },
+    job_queue::{BoundedJob, JobOverflowPolicy},
     state::OrderIdentifier,
 };
 
@@ -35,6 +39,26 @@ pub enum HandshakeExecutionJob {
         /// as a new gossip request to the network manager directly
         response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
     },
+    /// A request to broker a handshake between a foreign order and an order from another
+    /// cluster
+    BrokerHandshake {
+        /// The foreign order to broker a match for
+        order: OrderIdentifier,
+    },
+    /// Process a message sent while brokering a handshake, either by the broker or to it
+    ProcessBrokerMessage {
+        /// The request identifier shared by the broker and both managing peers
+        request_id: Uuid,
+        /// The peer that sent the message
+        peer_id: WrappedPeerId,
+        /// The message contents
+        message: BrokerMessage,
+        /// The channel on which to send the response
+        ///
+        /// If the channel is `None`, the response should be forwarded
+        /// as a new gossip request to the network manager directly
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    },
     /// Indicates that the network manager has setup an MPC net and the receiving thread
     /// may begin executing a match over this network
     MpcNetSetup {
@@ -44,6 +68,9 @@ pub enum HandshakeExecutionJob {
         party_id: u64,
         /// The net that was setup for the party
         net: QuicTwoPartyNet,
+        /// The ID under which the net is pooled if it is reused from an earlier match and
+        /// already connected, `None` if it was freshly brokered for this handshake
+        pooled_net_id: Option<Uuid>,
     },
     /// Indicates that the local peer should halt any MPCs active on the given match nullifier
     ///
@@ -55,6 +82,14 @@ pub enum HandshakeExecutionJob {
         /// are to be terminated
         match_nullifier: Nullifier,
     },
+    /// Indicates that the local peer should cancel the given in-flight handshake
+    ///
+    /// This job is constructed when an operator cancels a handshake through the API, and
+    /// tears the handshake down as an `MpcShootdown` would
+    CancelHandshake {
+        /// The ID of the handshake request to cancel
+        request_id: Uuid,
+    },
     /// Indicates that a cluster replica has initiated a match on the given order pair.
     /// The local peer should not schedule this order pair for a match for some duration
     PeerMatchInProgress {
@@ -71,4 +106,27 @@ pub enum HandshakeExecutionJob {
         /// The second of the orders matched
         order2: OrderIdentifier,
     },
+    /// Update the handshake cache with the completed pairs pulled from a cluster peer
+    CacheEntries {
+        /// The order pairs the cluster peer has cached as completed
+        pairs: Vec<(OrderIdentifier, OrderIdentifier)>,
+    },
+    /// A cluster peer has requested the full set of completed pairs in the local cache
+    CacheSyncRequest {
+        /// The peer to send the completed pairs to
+        peer_id: WrappedPeerId,
+    },
+}
+
+impl BoundedJob for HandshakeExecutionJob {
+    /// Jobs that tear down an MPC or hand over its network must not be lost, so they are
+    /// returned to the sender; the rest are dropped and retried by the scheduler or peer
+    fn overflow_policy(&self) -> JobOverflowPolicy {
+        match self {
+            HandshakeExecutionJob::MpcNetSetup { .. }
+            | HandshakeExecutionJob::MpcShootdown { .. }
+            | HandshakeExecutionJob::CancelHandshake { .. } => JobOverflowPolicy::Reject,
+            _ => JobOverflowPolicy::Drop,
+        }
+    }
 }
@@ -0,0 +1,235 @@
+//! A write-ahead journal of match settlements that have not yet been submitted
+//!
+//! A match is journaled as soon as the MPC completes, and the journal entry is updated
+//! with the commitment to the match once it is posted, and with the proof of `VALID MATCH
+//! ENCRYPTION` before the settlement is submitted. If the
+//! relayer crashes in between, the entries left in the journal are replayed when the
+//! handshake manager next starts; each is either resumed from the last stage it reached
+//! or, if the local wallet has since changed underneath the match, abandoned.
+//!
+//! The journal is rewritten in full on every change; a temporary file is written and
+//! then renamed over the journal so that a crash mid-write never leaves a torn journal
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use circuits::zk_gadgets::match_commitment::MatchCommitment;
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    gossip::types::ClusterId, proof_generation::jobs::ValidMatchEncryptBundle,
+    state::OrderIdentifier,
+};
+
+use super::{error::HandshakeManagerError, r#match::HandshakeResult, state::HandshakeState};
+
+/// A match that has completed but whose settlement has not been submitted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettlementJournalEntry {
+    /// The request ID of the handshake that produced the match
+    pub request_id: Uuid,
+    /// The local order in the match
+    pub local_order_id: OrderIdentifier,
+    /// The counterparty's order in the match
+    pub peer_order_id: OrderIdentifier,
+    /// The match nullifier of the local order's wallet when the match was made
+    ///
+    /// If the wallet's nullifier has since changed, the wallet was updated and the
+    /// settlement can no longer be accepted
+    pub local_match_nullifier: Scalar,
+    /// The match nullifiers of the first and second party's wallets, in the order the
+    /// settlement calldata expects them
+    pub party_match_nullifiers: [Scalar; 2],
+    /// The cluster managing the counterparty's order, which the protocol fee is resolved
+    /// against; `None` if the peer was unknown or the entry predates the fee schedule
+    #[serde(default)]
+    pub peer_cluster_id: Option<ClusterId>,
+    /// The match result and the proof of `VALID MATCH MPC`
+    pub handshake_result: HandshakeResult,
+    /// The commitment to the match result posted to the contract, `None` until it has
+    /// been posted
+    #[serde(default)]
+    pub match_commitment: Option<MatchCommitment>,
+    /// The proof of `VALID MATCH ENCRYPTION` that is submitted with the match, `None`
+    /// until it has been proven
+    pub encryption_bundle: Option<ValidMatchEncryptBundle>,
+    /// The time at which the match was journaled, in seconds since the epoch
+    pub created_at: u64,
+}
+
+impl SettlementJournalEntry {
+    /// Create a journal entry for a freshly completed match, in which the local peer
+    /// played the given party
+    pub fn new(
+        handshake_state: &HandshakeState,
+        local_party_id: u64,
+        peer_cluster_id: Option<ClusterId>,
+        handshake_result: HandshakeResult,
+    ) -> Self {
+        let party_match_nullifiers = if local_party_id == 0 {
+            [
+                handshake_state.local_match_nullifier,
+                handshake_state.peer_match_nullifier,
+            ]
+        } else {
+            [
+                handshake_state.peer_match_nullifier,
+                handshake_state.local_match_nullifier,
+            ]
+        };
+
+        Self {
+            request_id: handshake_state.request_id,
+            local_order_id: handshake_state.local_order_id,
+            peer_order_id: handshake_state.peer_order_id,
+            local_match_nullifier: handshake_state.local_match_nullifier,
+            party_match_nullifiers,
+            peer_cluster_id,
+            handshake_result,
+            match_commitment: None,
+            encryption_bundle: None,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("negative timestamp")
+                .as_secs(),
+        }
+    }
+}
+
+/// The journal of pending settlements, persisted to a file if one is configured
+#[derive(Clone, Debug)]
+pub struct SettlementJournal {
+    /// The file the journal is persisted to; the journal is held only in memory if unset
+    path: Option<String>,
+    /// The pending settlements, keyed by request ID
+    entries: Arc<Mutex<BTreeMap<Uuid, SettlementJournalEntry>>>,
+}
+
+impl SettlementJournal {
+    /// Open the journal at the given path, reading any entries left by a previous run
+    pub fn open(path: Option<String>) -> Result<Self, HandshakeManagerError> {
+        let entries = match &path {
+            Some(path) if Path::new(path).exists() => {
+                let contents = fs::read(path)
+                    .map_err(|err| HandshakeManagerError::Journal(err.to_string()))?;
+                let entries: Vec<SettlementJournalEntry> = serde_json::from_slice(&contents)
+                    .map_err(|err| HandshakeManagerError::Journal(err.to_string()))?;
+                entries
+                    .into_iter()
+                    .map(|entry| (entry.request_id, entry))
+                    .collect()
+            }
+            _ => BTreeMap::new(),
+        };
+
+        Ok(Self {
+            path,
+            entries: Arc::new(Mutex::new(entries)),
+        })
+    }
+
+    /// The pending settlements, in the order the matches were journaled
+    ///
+    /// Ties are broken by request ID so that replay order is deterministic
+    pub fn pending_entries(&self) -> Vec<SettlementJournalEntry> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| (entry.created_at, entry.request_id));
+
+        entries
+    }
+
+    /// The journaled settlement with the given request ID, if it is still pending
+    pub fn get(&self, request_id: &Uuid) -> Option<SettlementJournalEntry> {
+        self.entries.lock().unwrap().get(request_id).cloned()
+    }
+
+    /// Journal a completed match
+    pub fn record(&self, entry: SettlementJournalEntry) -> Result<(), HandshakeManagerError> {
+        let mut locked_entries = self.entries.lock().unwrap();
+        locked_entries.insert(entry.request_id, entry);
+        self.flush(&locked_entries)
+    }
+
+    /// Attach the commitment posted to the contract to a journaled match
+    pub fn record_match_commitment(
+        &self,
+        request_id: &Uuid,
+        commitment: MatchCommitment,
+    ) -> Result<(), HandshakeManagerError> {
+        let mut locked_entries = self.entries.lock().unwrap();
+        let entry = locked_entries.get_mut(request_id).ok_or_else(|| {
+            HandshakeManagerError::Journal(format!("no journal entry for {request_id}"))
+        })?;
+        entry.match_commitment = Some(commitment);
+
+        self.flush(&locked_entries)
+    }
+
+    /// Detach the commitment from a journaled match whose commitment transaction was
+    /// rejected, so that the match is committed to again
+    pub fn clear_match_commitment(&self, request_id: &Uuid) -> Result<(), HandshakeManagerError> {
+        let mut locked_entries = self.entries.lock().unwrap();
+        match locked_entries.get_mut(request_id) {
+            Some(entry) if entry.match_commitment.is_some() => entry.match_commitment = None,
+            _ => return Ok(()),
+        }
+
+        self.flush(&locked_entries)
+    }
+
+    /// Attach the proof of `VALID MATCH ENCRYPTION` to a journaled match
+    pub fn record_encryption_proof(
+        &self,
+        request_id: &Uuid,
+        bundle: ValidMatchEncryptBundle,
+    ) -> Result<(), HandshakeManagerError> {
+        let mut locked_entries = self.entries.lock().unwrap();
+        let entry = locked_entries.get_mut(request_id).ok_or_else(|| {
+            HandshakeManagerError::Journal(format!("no journal entry for {request_id}"))
+        })?;
+        entry.encryption_bundle = Some(bundle);
+
+        self.flush(&locked_entries)
+    }
+
+    /// Remove a match from the journal once it is settled or abandoned
+    pub fn remove(&self, request_id: &Uuid) -> Result<(), HandshakeManagerError> {
+        let mut locked_entries = self.entries.lock().unwrap();
+        if locked_entries.remove(request_id).is_none() {
+            return Ok(());
+        }
+
+        self.flush(&locked_entries)
+    }
+
+    /// Persist the journal, replacing the previous contents of the journal file
+    fn flush(
+        &self,
+        entries: &BTreeMap<Uuid, SettlementJournalEntry>,
+    ) -> Result<(), HandshakeManagerError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let serialized = serde_json::to_vec(&entries.values().collect::<Vec<_>>())
+            .map_err(|err| HandshakeManagerError::Journal(err.to_string()))?;
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, serialized)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|err| HandshakeManagerError::Journal(err.to_string()))
+    }
+}
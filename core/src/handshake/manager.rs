@@ -1,38 +1,56 @@
 //! The handshake module handles the execution of handshakes from negotiating
 //! a pair of orders to match, all the way through settling any resulting match
 
-use crossbeam::channel::Sender as CrossbeamSender;
+use circuits::types::order::{MatchConstraints, Order};
+use curve25519_dalek::scalar::Scalar;
 use futures::executor::block_on;
 use libp2p::request_response::ResponseChannel;
-use portpicker::pick_unused_port;
-use std::{thread::JoinHandle, time::Duration};
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use lru::LruCache;
+use portpicker::{pick_unused_port, Port};
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+use tokio::sync::mpsc::unbounded_channel;
 use tracing::log;
 use uuid::Uuid;
 
 use crate::{
+    clock::SharedClock,
     default_wrapper::DefaultWrapper,
+    fee_schedule::FeeSchedule,
     gossip::types::WrappedPeerId,
     gossip_api::{
-        cluster_management::ClusterManagementMessage,
+        cluster_management::{CacheSyncResponse, ClusterManagementMessage},
         gossip::{
             AuthenticatedGossipResponse, ConnectionRole, GossipOutbound, GossipRequest,
-            GossipResponse, ManagerControlDirective, PubsubMessage,
+            GossipResponse, ManagerControlDirective, PubsubMessage, SharedNetworkChannel,
         },
         handshake::{HandshakeMessage, MatchRejectionReason},
     },
+    job_queue::{JobQueueReceiver, JobQueueSender},
+    price_reporter::jobs::PriceReporterManagerJob,
     proof_generation::jobs::ProofManagerJob,
-    state::{new_async_shared, NetworkOrderState, OrderIdentifier, RelayerState},
+    rng::WorkerRng,
+    starknet_client::client::SharedStarknetApi,
+    state::{feature_flags::FeatureFlag, NetworkOrderState, OrderIdentifier, RelayerState},
     system_bus::SystemBus,
     types::{SystemBusMessage, HANDSHAKE_STATUS_TOPIC},
     CancelChannel,
 };
 
 use super::{
+    broker::BrokeredMatch,
+    concurrency::MpcConcurrencyLimiter,
     error::HandshakeManagerError,
-    handshake_cache::{HandshakeCache, SharedHandshakeCache},
+    handshake_cache::{ShardedHandshakeCache, SharedHandshakeCache, HANDSHAKE_CACHE_SHARDS},
     jobs::HandshakeExecutionJob,
-    state::HandshakeStateIndex,
+    journal::{SettlementJournal, SettlementJournalEntry},
+    net_pool::{MpcNetPool, PooledMpcNet},
+    size_bucket::{buckets_overlap, commit_to_bucket, size_bucket, verify_bucket_opening},
+    state::{HandshakeState, HandshakeStateIndex},
     worker::HandshakeManagerConfig,
 };
 
@@ -43,10 +61,20 @@ pub(super) const HANDSHAKE_INVISIBILITY_WINDOW_MS: u64 = 120_000; // 2 minutes
 pub(super) const HANDSHAKE_CACHE_SIZE: usize = 500;
 /// How frequently a new handshake is initiated from the local peer
 pub(super) const HANDSHAKE_INTERVAL_MS: u64 = 2_000; // 2 seconds
-/// Number of nanoseconds in a millisecond, for convenience
-const NANOS_PER_MILLI: u64 = 1_000_000;
+/// The maximum random delay added to each handshake interval, so that the peers
+/// of a cluster do not schedule handshakes in lockstep
+pub(super) const HANDSHAKE_INTERVAL_JITTER_MS: u64 = 500;
 /// The number of threads executing handshakes
 pub(super) const HANDSHAKE_EXECUTOR_N_THREADS: usize = 8;
+/// The number of timed out MPCs on an order pair after which the pair is given up on
+const MAX_MPC_ATTEMPTS: u32 = 3;
+/// The delay before the first retry of a timed out MPC, doubled on each further retry
+const MPC_RETRY_BASE_BACKOFF_MS: u64 = 5_000; // 5 seconds
+/// The amount of time to wait on a DHT lookup of an order's managers before giving up
+/// on the order for this handshake interval
+const ORDER_PROVIDER_LOOKUP_TIMEOUT_MS: u64 = 5_000; // 5 seconds
+/// The number of brokered handshakes the local peer tracks while awaiting fee notes
+const BROKERED_MATCH_CACHE_SIZE: usize = 500;
 
 /// Manages requests to handshake from a peer and sends outbound requests to initiate
 /// a handshake
@@ -63,6 +91,60 @@ pub struct HandshakeManager {
     pub scheduler_handle: Option<JoinHandle<HandshakeManagerError>>,
 }
 
+/// The parameters a handshake executor is built from
+pub struct HandshakeExecutorConfig {
+    /// The queue on which other workers enqueue jobs for the executor
+    pub job_channel: JobQueueReceiver<HandshakeExecutionJob>,
+    /// The queue on which other workers enqueue high priority jobs; e.g. MPC shootdowns
+    pub priority_job_channel: JobQueueReceiver<HandshakeExecutionJob>,
+    /// The channel on which the executor may forward requests to the network
+    pub network_channel: SharedNetworkChannel,
+    /// The queue on which to send proof manager jobs
+    pub proof_manager_work_queue: JobQueueSender<ProofManagerJob>,
+    /// The global relayer state
+    pub global_state: RelayerState,
+    /// The index of in-flight handshakes
+    pub handshake_state_index: HandshakeStateIndex,
+    /// The system bus used to publish internal broadcast messages
+    pub system_bus: SystemBus<SystemBusMessage>,
+    /// The amount of time a match MPC may run before it is abandoned, in milliseconds
+    pub mpc_timeout_ms: u64,
+    /// Whether to commit to the local order's size bucket when proposing a match
+    pub size_bucket_check: bool,
+    /// Whether to drop order pairs that public order metadata shows cannot cross before
+    /// proposing a match on them
+    pub handshake_prescreen: bool,
+    /// The maximum number of match MPCs run at once across all peers
+    pub max_concurrent_mpcs: usize,
+    /// The maximum number of match MPCs run at once against a single peer
+    pub max_concurrent_mpcs_per_peer: usize,
+    /// The highest fee paid to settle a match, `None` if unbounded
+    pub max_settlement_fee: Option<u64>,
+    /// The schedule of protocol fees charged on matches
+    pub fee_schedule: FeeSchedule,
+    /// The share of the managing relayers' fees the local peer charges to broker a
+    /// handshake between foreign orders, `None` if the local peer does not broker
+    pub broker_fee_bps: Option<u16>,
+    /// The largest share of the local relayer fee paid to a peer that brokers a match
+    pub max_broker_fee_bps: u16,
+    /// The match constraints of local orders that do not set their own
+    pub default_match_constraints: MatchConstraints,
+    /// The work queue of the price reporter manager
+    pub price_reporter_work_queue: JobQueueSender<PriceReporterManagerJob>,
+    /// The client settlements are submitted to the contract through
+    pub starknet_client: SharedStarknetApi,
+    /// The source of randomness for request IDs and encryption blinders
+    pub rng: WorkerRng,
+    /// The write-ahead journal of matches whose settlement has not been submitted
+    pub settlement_journal: SettlementJournal,
+    /// The file that completed order pairs in the handshake cache are persisted to, if any
+    pub handshake_cache_file: Option<String>,
+    /// The clock that invisibility windows and failures are measured against
+    pub clock: SharedClock,
+    /// The channel on which the coordinator thread may cancel handshake execution
+    pub cancel: CancelChannel,
+}
+
 /// Manages the threaded execution of the handshake protocol
 #[derive(Clone)]
 pub struct HandshakeExecutor {
@@ -71,52 +153,169 @@ pub struct HandshakeExecutor {
     /// Stores the state of existing handshake executions
     pub(super) handshake_state_index: HandshakeStateIndex,
     /// The channel on which other workers enqueue jobs for the protocol executor
-    pub(super) job_channel: DefaultWrapper<Option<UnboundedReceiver<HandshakeExecutionJob>>>,
+    pub(super) job_channel: DefaultWrapper<Option<JobQueueReceiver<HandshakeExecutionJob>>>,
+    /// The channel on which other workers enqueue high priority jobs; e.g. MPC shootdowns
+    ///
+    /// Jobs on this channel preempt jobs on the standard job channel
+    pub(super) priority_job_channel:
+        DefaultWrapper<Option<JobQueueReceiver<HandshakeExecutionJob>>>,
     /// The channel on which the handshake executor may forward requests to the network
-    pub(super) network_channel: UnboundedSender<GossipOutbound>,
+    pub(super) network_channel: SharedNetworkChannel,
     /// The channel on which to send proof manager jobs
-    pub(super) proof_manager_work_queue: CrossbeamSender<ProofManagerJob>,
+    pub(super) proof_manager_work_queue: JobQueueSender<ProofManagerJob>,
     /// The global relayer state
     pub(super) global_state: RelayerState,
     /// The system bus used to publish internal broadcast messages
     pub(super) system_bus: SystemBus<SystemBusMessage>,
+    /// The amount of time a match MPC may run before it is abandoned
+    pub(super) mpc_timeout: Duration,
+    /// Whether to commit to the local order's size bucket when proposing a match
+    pub(super) size_bucket_check: bool,
+    /// Whether to drop order pairs that public order metadata shows cannot cross before
+    /// proposing a match on them
+    pub(super) handshake_prescreen: bool,
+    /// Bounds the match MPCs run at once, globally and against each peer
+    pub(super) mpc_limiter: MpcConcurrencyLimiter,
+    /// The source of randomness for request IDs and encryption blinders
+    pub(super) rng: WorkerRng,
+    /// The write-ahead journal of matches whose settlement has not been submitted
+    pub(super) settlement_journal: SettlementJournal,
+    /// The client settlements are submitted to the contract through
+    pub(super) starknet_client: SharedStarknetApi,
+    /// The highest fee paid to settle a match, `None` if unbounded
+    pub(super) max_settlement_fee: Option<u64>,
+    /// The schedule of protocol fees charged on matches
+    pub(super) fee_schedule: FeeSchedule,
+    /// The share of the managing relayers' fees the local peer charges to broker a
+    /// handshake between foreign orders, `None` if the local peer does not broker
+    pub(super) broker_fee_bps: Option<u16>,
+    /// The largest share of the local relayer fee paid to a peer that brokers a match
+    pub(super) max_broker_fee_bps: u16,
+    /// The match constraints of local orders that do not set their own
+    pub(super) default_match_constraints: MatchConstraints,
+    /// The work queue of the price reporter manager, used to peek the median prices that
+    /// price protection bands are drawn around
+    pub(super) price_reporter_work_queue: JobQueueSender<PriceReporterManagerJob>,
+    /// The handshakes the local peer has brokered, kept until both managing peers have
+    /// paid their fee notes or the entry is evicted
+    pub(super) brokered_matches: Arc<Mutex<LruCache<Uuid, BrokeredMatch>>>,
+    /// The MPC nets left open by completed matches, reused for later matches with the
+    /// same peer
+    pub(super) mpc_net_pool: Arc<MpcNetPool>,
+    /// The clock that invisibility windows and failures are measured against
+    pub(super) clock: SharedClock,
     /// The channel on which the coordinator thread may cancel handshake execution
     pub(super) cancel: CancelChannel,
 }
 
 impl HandshakeExecutor {
     /// Create a new protocol executor
-    pub fn new(
-        job_channel: UnboundedReceiver<HandshakeExecutionJob>,
-        network_channel: UnboundedSender<GossipOutbound>,
-        proof_manager_work_queue: CrossbeamSender<ProofManagerJob>,
-        global_state: RelayerState,
-        system_bus: SystemBus<SystemBusMessage>,
-        cancel: CancelChannel,
-    ) -> Result<Self, HandshakeManagerError> {
-        // Build the handshake cache and state machine structures
-        let handshake_cache = new_async_shared(HandshakeCache::new(HANDSHAKE_CACHE_SIZE));
-        let handshake_state_index = HandshakeStateIndex::new(global_state.clone());
+    pub fn new(config: HandshakeExecutorConfig) -> Result<Self, HandshakeManagerError> {
+        let HandshakeExecutorConfig {
+            job_channel,
+            priority_job_channel,
+            network_channel,
+            proof_manager_work_queue,
+            global_state,
+            handshake_state_index,
+            system_bus,
+            mpc_timeout_ms,
+            size_bucket_check,
+            handshake_prescreen,
+            max_concurrent_mpcs,
+            max_concurrent_mpcs_per_peer,
+            max_settlement_fee,
+            fee_schedule,
+            broker_fee_bps,
+            max_broker_fee_bps,
+            default_match_constraints,
+            price_reporter_work_queue,
+            starknet_client,
+            rng,
+            settlement_journal,
+            handshake_cache_file,
+            clock,
+            cancel,
+        } = config;
+
+        // Build the handshake cache
+        let handshake_cache = Arc::new(ShardedHandshakeCache::open(
+            HANDSHAKE_CACHE_SIZE,
+            HANDSHAKE_CACHE_SHARDS,
+            handshake_cache_file,
+            clock.clone(),
+        )?);
 
         Ok(Self {
             handshake_cache,
             handshake_state_index,
             job_channel: DefaultWrapper::new(Some(job_channel)),
+            priority_job_channel: DefaultWrapper::new(Some(priority_job_channel)),
             network_channel,
             proof_manager_work_queue,
             global_state,
             system_bus,
+            mpc_timeout: Duration::from_millis(mpc_timeout_ms),
+            size_bucket_check,
+            handshake_prescreen,
+            mpc_limiter: MpcConcurrencyLimiter::new(
+                max_concurrent_mpcs,
+                max_concurrent_mpcs_per_peer,
+            ),
+            rng,
+            settlement_journal,
+            starknet_client,
+            max_settlement_fee,
+            fee_schedule,
+            broker_fee_bps,
+            max_broker_fee_bps,
+            default_match_constraints,
+            price_reporter_work_queue,
+            brokered_matches: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(BROKERED_MATCH_CACHE_SIZE).unwrap(),
+            ))),
+            mpc_net_pool: Arc::new(MpcNetPool::new(clock.clone())),
+            clock,
             cancel,
         })
     }
 
     /// The main loop: dequeues jobs and forwards them to the thread pool
+    ///
+    /// The priority job channel is polled first on every iteration, so that
+    /// shootdowns are dispatched ahead of any backlog of standard handshake jobs
     pub async fn execution_loop(mut self) -> HandshakeManagerError {
         let mut job_channel = self.job_channel.take().unwrap();
+        let mut priority_job_channel = self.priority_job_channel.take().unwrap();
+
+        // Resume or abandon any settlements left pending by a previous run
+        let self_clone = self.clone();
+        tokio::task::spawn(async move { self_clone.replay_settlement_journal().await });
 
         loop {
             // Await the next job from the scheduler or elsewhere
             tokio::select! {
+                // Poll the branches in order rather than randomly, giving precedence
+                // to the coordinator's cancel signal and to the priority lane
+                biased;
+
+                // Await cancellation by the coordinator
+                _ = self.cancel.changed() => {
+                    log::info!("Handshake manager received cancel signal, shutting down...");
+                    return HandshakeManagerError::Cancelled("received cancel signal".to_string());
+                }
+
+                // Priority jobs are spawned onto the thread pool as standard jobs are, so
+                // that a slow priority job does not stall dispatch
+                Some(job) = priority_job_channel.recv() => {
+                    let self_clone = self.clone();
+                    tokio::task::spawn(async move {
+                        if let Err(e) = self_clone.handle_handshake_job(job).await {
+                            log::info!("error executing priority handshake job: {e}")
+                        }
+                    });
+                },
+
                 Some(job) = job_channel.recv() => {
                     let self_clone = self.clone();
                     tokio::task::spawn(async move {
@@ -125,12 +324,6 @@ impl HandshakeExecutor {
                         }
                     });
                 },
-
-                // Await cancellation by the coordinator
-                _ = self.cancel.changed() => {
-                    log::info!("Handshake manager received cancel signal, shutting down...");
-                    return HandshakeManagerError::Cancelled("received cancel signal".to_string());
-                }
             }
         }
     }
@@ -149,6 +342,9 @@ impl HandshakeExecutor {
                 self.perform_handshake(order).await
             }
 
+            // The timer thread has scheduled a brokered handshake on a foreign order
+            HandshakeExecutionJob::BrokerHandshake { order } => self.broker_handshake(order).await,
+
             // Indicates that a peer has sent a message during the course of a handshake
             HandshakeExecutionJob::ProcessHandshakeMessage {
                 request_id,
@@ -160,21 +356,50 @@ impl HandshakeExecutor {
                     .await
             }
 
+            // A message sent while brokering a handshake, by the local peer's broker or to
+            // the local peer as a broker
+            HandshakeExecutionJob::ProcessBrokerMessage {
+                request_id,
+                peer_id,
+                message,
+                response_channel,
+            } => {
+                self.handle_broker_message(request_id, peer_id, message, response_channel)
+                    .await
+            }
+
             // A peer has completed a match on the given order pair; cache this match pair as completed
             // and do not schedule the pair going forward
             HandshakeExecutionJob::CacheEntry { order1, order2 } => {
-                self.handshake_cache
-                    .write()
-                    .await
-                    .mark_completed(order1, order2);
+                self.handshake_cache.mark_completed(order1, order2);
+
+                Ok(())
+            }
+
+            // A cluster peer has sent the pairs it has cached as completed in response to the
+            // local peer's sync request on startup
+            HandshakeExecutionJob::CacheEntries { pairs } => {
+                log::info!("received {} completed pairs from cluster peer", pairs.len());
+                self.handshake_cache.mark_completed_many(pairs);
 
                 Ok(())
             }
 
+            // A restarting cluster peer has requested the local peer's completed pairs
+            HandshakeExecutionJob::CacheSyncRequest { peer_id } => self
+                .network_channel
+                .send(GossipOutbound::Request {
+                    peer_id,
+                    message: GossipRequest::CacheSyncResponse(CacheSyncResponse {
+                        completed_pairs: self.handshake_cache.completed_pairs(),
+                    }),
+                })
+                .map_err(|err| HandshakeManagerError::SendMessage(err.to_string())),
+
             // A peer has initiated a match on the given order pair; place this order pair in an invisibility
             // window, i.e. do not initiate matches on this pair
             HandshakeExecutionJob::PeerMatchInProgress { order1, order2 } => {
-                self.handshake_cache.write().await.mark_invisible(
+                self.handshake_cache.mark_invisible(
                     order1,
                     order2,
                     Duration::from_millis(HANDSHAKE_INVISIBILITY_WINDOW_MS),
@@ -189,12 +414,12 @@ impl HandshakeExecutor {
                 request_id,
                 party_id,
                 net,
+                pooled_net_id,
             } => {
                 // Fetch the local handshake state to get an order for the MPC
                 let order_state = self
                     .handshake_state_index
                     .get_state(&request_id)
-                    .await
                     .ok_or_else(|| {
                         HandshakeManagerError::InvalidRequest(format!(
                             "request_id: {:?}",
@@ -203,12 +428,16 @@ impl HandshakeExecutor {
                     })?;
 
                 // Mark the handshake cache entry as invisible to avoid re-scheduling
-                self.handshake_cache.write().await.mark_invisible(
+                self.handshake_cache.mark_invisible(
                     order_state.local_order_id,
                     order_state.peer_order_id,
                     Duration::from_millis(HANDSHAKE_INVISIBILITY_WINDOW_MS),
                 );
 
+                // Queue behind any MPCs already running against the peer or at the global
+                // limit, the permit is held until the MPC completes or times out
+                let _permit = self.mpc_limiter.acquire(order_state.peer_id).await;
+
                 // Publish an internal event signalling that a match is beginning
                 self.system_bus.publish(
                     HANDSHAKE_STATUS_TOPIC.to_string(),
@@ -218,66 +447,248 @@ impl HandshakeExecutor {
                     },
                 );
 
-                // Run the MPC match process
+                // Run the MPC match process, abandoning it if it overruns the timeout
                 let self_clone = self.clone();
-                let res = tokio::task::spawn_blocking(move || {
-                    block_on(self_clone.execute_match(request_id, party_id, net))
-                })
+                let res = match tokio::time::timeout(
+                    self.mpc_timeout,
+                    tokio::task::spawn_blocking(move || {
+                        block_on(self_clone.execute_match(
+                            request_id,
+                            party_id,
+                            net,
+                            pooled_net_id.is_some(), /* connected */
+                        ))
+                    }),
+                )
                 .await
-                .unwrap()?;
+                {
+                    Ok(join_res) => join_res.unwrap(),
+                    Err(_) => Err(HandshakeManagerError::MpcTimeout),
+                };
+                self.record_handshake_outcome(order_state.peer_id, &res)
+                    .await;
+                self.global_state
+                    .read_order_book()
+                    .await
+                    .mark_mpc_attempted(&order_state.local_order_id)
+                    .await;
+                if let Err(HandshakeManagerError::MpcTimeout) = res {
+                    self.handle_mpc_timeout(request_id, party_id, &order_state)
+                        .await;
+                }
+                let (res, net) = res?;
+
+                // Return the net to the pool for the next match with the peer; a net brokered
+                // for this handshake is pooled under the handshake's ID. Nets set up by a third
+                // peer's broker are not pooled
+                if let Some(net) = net && order_state.broker.is_none() {
+                    self.mpc_net_pool.check_in(
+                        order_state.peer_id,
+                        pooled_net_id.unwrap_or(request_id),
+                        party_id,
+                        net,
+                    );
+                }
+
+                // Journal the match before anything else so that it survives a crash; the
+                // peer's cluster is journaled with it so that a replay resolves the same fee
+                let peer_cluster_id = self
+                    .global_state
+                    .read_peer_index()
+                    .await
+                    .get_peer_info(&order_state.peer_id)
+                    .await
+                    .map(|info| info.get_cluster_id());
+                self.settlement_journal.record(SettlementJournalEntry::new(
+                    &order_state,
+                    party_id,
+                    peer_cluster_id.clone(),
+                    res.clone(),
+                ))?;
 
                 // Record the match in the cache
                 self.record_completed_match(request_id).await?;
+                self.handshake_state_index
+                    .clear_failures(&order_state.local_order_id, &order_state.peer_order_id);
+
+                // Pay the broker its share of the relayer fee if a third peer brokered the
+                // match; a failure to reach the broker does not hold up settlement
+                if let Some(broker) = order_state.broker.as_ref()
+                    && let Err(e) = self.pay_broker(
+                        request_id,
+                        party_id,
+                        broker,
+                        peer_cluster_id.as_ref(),
+                        &res,
+                    )
+                {
+                    log::warn!("error sending broker fee note: {e}");
+                }
+
+                // Commit to the match before proving it, the contract only settles a match
+                // committed to ahead of its proof
+                self.commit_match(request_id).await?;
 
                 // Submit the match to the contract
-                self.submit_match(res).await
+                self.submit_match(
+                    request_id,
+                    &[
+                        order_state.local_match_nullifier,
+                        order_state.peer_match_nullifier,
+                    ],
+                    peer_cluster_id.as_ref(),
+                    res,
+                )
+                .await
             }
 
             // Indicates that in-flight MPCs on the given nullifier should be terminated
             HandshakeExecutionJob::MpcShootdown { match_nullifier } => {
-                self.handshake_state_index
-                    .shootdown_nullifier(match_nullifier)
-                    .await
+                let shot_down = self
+                    .handshake_state_index
+                    .shootdown_nullifier(match_nullifier)?;
+                shot_down
+                    .iter()
+                    .for_each(|state| self.publish_handshake_cancelled(state));
+
+                Ok(())
+            }
+
+            // Indicates that an operator has cancelled the given handshake
+            HandshakeExecutionJob::CancelHandshake { request_id } => {
+                if let Some(state) = self.handshake_state_index.cancel_handshake(&request_id)? {
+                    log::info!("cancelled handshake {request_id}");
+                    self.publish_handshake_cancelled(&state);
+                }
+
+                Ok(())
             }
         }
     }
 
+    /// Publish an internal event indicating that an in-flight handshake was torn down
+    fn publish_handshake_cancelled(&self, state: &HandshakeState) {
+        self.system_bus.publish(
+            HANDSHAKE_STATUS_TOPIC.to_string(),
+            SystemBusMessage::HandshakeCancelled {
+                request_id: state.request_id,
+                local_order_id: state.local_order_id,
+                peer_order_id: state.peer_order_id,
+            },
+        );
+    }
+
     /// Perform a handshake with a peer
     pub async fn perform_handshake(
         &self,
         peer_order_id: OrderIdentifier,
     ) -> Result<(), HandshakeManagerError> {
         if let Some(local_order_id) = self.choose_match_proposal(peer_order_id).await {
-            // Choose a peer to match this order with
-            let managing_peer = self
-                .global_state
-                .get_peer_managing_order(&peer_order_id)
-                .await;
-            if managing_peer.is_none() {
-                // TODO: Lower the order priority for this order
-                return Ok(());
-            }
+            self.propose_order_pair(peer_order_id, local_order_id)
+                .await?;
+        }
 
-            // Send a handshake message to the given peer_id
-            // Panic if channel closed, no way to recover
-            let request_id = Uuid::new_v4();
-            self.network_channel
-                .send(GossipOutbound::Request {
-                    peer_id: managing_peer.unwrap(),
-                    message: GossipRequest::Handshake {
-                        request_id,
-                        message: HandshakeMessage::ProposeMatchCandidate {
-                            peer_id: self.global_state.local_peer_id(),
-                            sender_order: local_order_id,
-                            peer_order: peer_order_id,
-                        },
+        Ok(())
+    }
+
+    /// Find a peer managing the given order, falling back to the DHT if no manager of the
+    /// order is known through gossip
+    pub(super) async fn find_order_manager(
+        &self,
+        order_id: OrderIdentifier,
+    ) -> Option<WrappedPeerId> {
+        match self.global_state.get_peer_managing_order(&order_id).await {
+            Some(peer_id) => Some(peer_id),
+            None => self.lookup_order_provider(order_id).await,
+        }
+    }
+
+    /// Look up a manager of the given order from the provider records in the DHT
+    ///
+    /// Returns `None` if the lookup finds no provider that is an eligible counterparty, or
+    /// if it does not complete in time
+    async fn lookup_order_provider(&self, order_id: OrderIdentifier) -> Option<WrappedPeerId> {
+        let (response_sender, mut response_receiver) = unbounded_channel();
+        self.network_channel
+            .send(GossipOutbound::ManagementMessage(
+                ManagerControlDirective::FindOrderProviders {
+                    order_id,
+                    response_channel: response_sender,
+                },
+            ))
+            .ok()?;
+
+        let providers = tokio::time::timeout(
+            Duration::from_millis(ORDER_PROVIDER_LOOKUP_TIMEOUT_MS),
+            response_receiver.recv(),
+        )
+        .await
+        .ok()
+        .flatten()?;
+
+        let locked_reputation = self.global_state.read_peer_reputation().await;
+        providers
+            .into_iter()
+            .find(|peer_id| locked_reputation.is_eligible_counterparty(peer_id))
+    }
+
+    /// Propose a match on the given order pair to the peer managing the remote order
+    async fn propose_order_pair(
+        &self,
+        peer_order_id: OrderIdentifier,
+        local_order_id: OrderIdentifier,
+    ) -> Result<(), HandshakeManagerError> {
+        // Handshakes scheduled before the relayer began draining are dropped
+        if self.global_state.is_draining() {
+            return Ok(());
+        }
+
+        // Choose a peer to match this order with
+        let managing_peer = self.find_order_manager(peer_order_id).await;
+        if managing_peer.is_none() {
+            // TODO: Lower the order priority for this order
+            return Ok(());
+        }
+
+        // Commit to the local order's size bucket if the bucket check is enabled
+        let size_bucket_blinder = if self.size_bucket_check {
+            Some(self.rng.gen_scalar())
+        } else {
+            None
+        };
+        let size_bucket_commitment = match size_bucket_blinder {
+            Some(blinder) => self
+                .local_size_bucket(&local_order_id)
+                .await
+                .map(|bucket| commit_to_bucket(bucket, blinder)),
+            None => None,
+        };
+
+        // Send a handshake message to the given peer_id
+        // Panic if channel closed, no way to recover
+        let managing_peer = managing_peer.unwrap();
+        let request_id = self.rng.gen_uuid();
+        self.network_channel
+            .send(GossipOutbound::Request {
+                peer_id: managing_peer,
+                message: GossipRequest::Handshake {
+                    request_id,
+                    message: HandshakeMessage::ProposeMatchCandidate {
+                        peer_id: self.global_state.local_peer_id(),
+                        sender_order: local_order_id,
+                        peer_order: peer_order_id,
+                        size_bucket_commitment,
                     },
-                })
-                .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
+                },
+            })
+            .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
 
+        self.handshake_state_index
+            .new_handshake(request_id, managing_peer, peer_order_id, local_order_id)
+            .await?;
+        if size_bucket_commitment.is_some() {
             self.handshake_state_index
-                .new_handshake(request_id, peer_order_id, local_order_id)
-                .await?;
+                .set_local_size_bucket_blinder(&request_id, size_bucket_blinder.unwrap());
         }
 
         Ok(())
@@ -300,13 +711,42 @@ impl HandshakeExecutor {
                 peer_id,
                 peer_order: my_order,
                 sender_order,
+                size_bucket_commitment,
             } => {
                 self.handle_propose_match_candidate(
                     request_id,
                     peer_id,
                     my_order,
                     sender_order,
-                    response_channel.unwrap(),
+                    size_bucket_commitment,
+                    response_channel,
+                )
+                .await
+            }
+
+            // The responder to a proposal has revealed its order's size bucket, the local peer
+            // either opens its own bucket commitment or abandons the pair
+            HandshakeMessage::SizeBucketChallenge {
+                peer_id, bucket, ..
+            } => {
+                self.handle_size_bucket_challenge(request_id, peer_id, bucket)
+                    .await
+            }
+
+            // The proposer has opened its bucket commitment, the local peer checks the opening
+            // and the overlap before brokering an MPC
+            HandshakeMessage::SizeBucketOpening {
+                peer_id,
+                bucket,
+                blinder,
+                ..
+            } => {
+                self.handle_size_bucket_opening(
+                    request_id,
+                    peer_id,
+                    bucket,
+                    blinder,
+                    response_channel,
                 )
                 .await
             }
@@ -314,13 +754,19 @@ impl HandshakeExecutor {
             // A peer has rejected a proposed match candidate, this can happen for a number of reasons, enumerated
             // by the `reason` field in the message
             HandshakeMessage::RejectMatchCandidate {
-                peer_order,
-                sender_order,
-                reason,
-                ..
+                peer_id, reason, ..
             } => {
-                self.handle_proposal_rejection(peer_order, sender_order, reason)
-                    .await;
+                self.handle_proposal_rejection(request_id, reason).await;
+
+                // A rejection sent as a request (e.g. after a bucket check) must be answered
+                if response_channel.is_some() {
+                    self.send_request_response(
+                        request_id,
+                        peer_id,
+                        HandshakeMessage::Ack,
+                        response_channel,
+                    )?;
+                }
                 Ok(())
             }
 
@@ -332,6 +778,7 @@ impl HandshakeExecutor {
                 port,
                 order1,
                 order2,
+                pooled_net,
                 ..
             } => {
                 self.handle_execute_match(
@@ -340,6 +787,7 @@ impl HandshakeExecutor {
                     port,
                     order1,
                     order2,
+                    pooled_net,
                     response_channel,
                 )
                 .await
@@ -359,8 +807,39 @@ impl HandshakeExecutor {
         peer_id: WrappedPeerId,
         my_order: OrderIdentifier,
         sender_order: OrderIdentifier,
-        response_channel: ResponseChannel<AuthenticatedGossipResponse>,
+        size_bucket_commitment: Option<Scalar>,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
     ) -> Result<(), HandshakeManagerError> {
+        // A draining relayer only sees its in-flight MPCs through
+        if self.global_state.is_draining() {
+            return self.reject_match_proposal(
+                request_id,
+                peer_id,
+                sender_order,
+                my_order,
+                MatchRejectionReason::Draining,
+                response_channel,
+            );
+        }
+
+        // Do not commit MPC resources to a peer with a history of invalid proofs or
+        // abandoned handshakes
+        if !self
+            .global_state
+            .read_peer_reputation()
+            .await
+            .is_eligible_counterparty(&peer_id)
+        {
+            return self.reject_match_proposal(
+                request_id,
+                peer_id,
+                sender_order,
+                my_order,
+                MatchRejectionReason::LowReputation,
+                response_channel,
+            );
+        }
+
         // Only accept the proposed order pair if the peer's order has already been verified by
         // the local node
         let peer_order_info = self
@@ -370,10 +849,11 @@ impl HandshakeExecutor {
             .get_order_info(&sender_order)
             .await;
         if peer_order_info.is_none()
-            || peer_order_info.unwrap().state != NetworkOrderState::Verified
+            || peer_order_info.as_ref().unwrap().state != NetworkOrderState::Verified
         {
             return self.reject_match_proposal(
                 request_id,
+                peer_id,
                 sender_order,
                 my_order,
                 MatchRejectionReason::NoValidityProof,
@@ -381,17 +861,36 @@ impl HandshakeExecutor {
             );
         }
 
-        // Do not accept handshakes on local orders that we don't have
-        // validity proof or witness for
+        // Only handshake on orders managed by clusters the operator permits
         if !self
             .global_state
-            .read_order_book()
-            .await
-            .order_ready_for_handshake(&my_order)
+            .read_cluster_access()
             .await
+            .is_permitted(&peer_order_info.unwrap().cluster)
         {
             return self.reject_match_proposal(
                 request_id,
+                peer_id,
+                sender_order,
+                my_order,
+                MatchRejectionReason::ClusterNotPermitted,
+                response_channel,
+            );
+        }
+
+        // Do not accept handshakes on local orders that we don't have
+        // validity proof or witness for, or whose time in force has lapsed
+        let my_order_ready = {
+            let locked_order_book = self.global_state.read_order_book().await;
+            locked_order_book.order_ready_for_handshake(&my_order).await
+                && !locked_order_book
+                    .is_order_expired(&my_order, self.clock.unix_secs())
+                    .await
+        };
+        if !my_order_ready {
+            return self.reject_match_proposal(
+                request_id,
+                peer_id,
                 sender_order,
                 my_order,
                 MatchRejectionReason::LocalOrderNotReady,
@@ -399,21 +898,32 @@ impl HandshakeExecutor {
             );
         }
 
+        // Do not accept handshakes on local orders that cannot be filled within their
+        // minimum fill size and price protection band
+        if !self.local_order_admits_match(&my_order).await {
+            return self.reject_match_proposal(
+                request_id,
+                peer_id,
+                sender_order,
+                my_order,
+                MatchRejectionReason::ConstraintsUnsatisfiable,
+                response_channel,
+            );
+        }
+
         // Add an entry to the handshake state index
         self.handshake_state_index
-            .new_handshake(request_id, sender_order, my_order)
+            .new_handshake(request_id, peer_id, sender_order, my_order)
             .await?;
 
         // Check if the order pair has previously been matched, if so notify the peer and
         // terminate the handshake
-        let previously_matched = {
-            let locked_handshake_cache = self.handshake_cache.read().await;
-            locked_handshake_cache.contains(my_order, sender_order)
-        }; // locked_handshake_cache released
+        let previously_matched = self.handshake_cache.contains(my_order, sender_order);
 
         if previously_matched {
             return self.reject_match_proposal(
                 request_id,
+                peer_id,
                 sender_order,
                 my_order,
                 MatchRejectionReason::Cached,
@@ -421,6 +931,82 @@ impl HandshakeExecutor {
             );
         }
 
+        // If the proposer committed to a size bucket, reveal the local bucket and wait for
+        // the proposer to open its commitment before brokering an MPC
+        if let Some(commitment) = size_bucket_commitment
+            && let Some(bucket) = self.local_size_bucket(&my_order).await
+        {
+            self.handshake_state_index
+                .set_peer_size_bucket_commitment(&request_id, commitment);
+
+            let resp = HandshakeMessage::SizeBucketChallenge {
+                peer_id: self.global_state.local_peer_id(),
+                peer_order: sender_order,
+                sender_order: my_order,
+                bucket,
+            };
+            return self.send_request_response(request_id, peer_id, resp, response_channel);
+        }
+
+        self.accept_match_proposal(
+            request_id,
+            peer_id,
+            my_order,
+            sender_order,
+            response_channel,
+        )
+    }
+
+    /// Broker an MPC for an accepted order pair and notify the proposer to begin the match
+    ///
+    /// If a net pooled from an earlier match with the proposer is open, the match runs over
+    /// it rather than a freshly brokered net
+    fn accept_match_proposal(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        my_order: OrderIdentifier,
+        sender_order: OrderIdentifier,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        let pooled_net = self.mpc_net_pool.check_out_for_reuse(&peer_id);
+        let (local_port, pooled_net_id) = match pooled_net.as_ref() {
+            Some(pooled) => {
+                self.announce_match_in_progress(my_order, sender_order)?;
+                (0, Some(pooled.id))
+            }
+            None => (
+                self.listen_for_mpc(request_id, peer_id, my_order, sender_order)?,
+                None,
+            ),
+        };
+
+        let resp = HandshakeMessage::ExecuteMatch {
+            peer_id: self.global_state.local_peer_id(),
+            port: local_port,
+            previously_matched: false,
+            order1: my_order,
+            order2: sender_order,
+            pooled_net: pooled_net_id,
+        };
+        self.send_request_response(request_id, peer_id, resp, response_channel)?;
+
+        if let Some(pooled) = pooled_net {
+            self.run_pooled_mpc(request_id, pooled);
+        }
+
+        Ok(())
+    }
+
+    /// Broker an MPC net on which the local peer listens for the given peer, and announce
+    /// the match to the cluster; returns the port the peer should dial
+    pub(super) fn listen_for_mpc(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        my_order: OrderIdentifier,
+        peer_order: OrderIdentifier,
+    ) -> Result<Port, HandshakeManagerError> {
         // If the order pair has not been previously matched; broker an MPC connection
         // Choose a random open port to receive the connection on
         // the peer port can be a dummy value as the local node will take the role
@@ -437,30 +1023,31 @@ impl HandshakeExecutor {
                 },
             ))
             .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
+        self.announce_match_in_progress(my_order, peer_order)?;
 
-        // Send a pubsub message indicating intent to match on the given order pair
-        // Cluster peers will then avoid scheduling this match until the match either completes, or
-        // the cache entry's invisibility window times out
+        Ok(local_port)
+    }
+
+    /// Send a pubsub message indicating intent to match on the given order pair
+    ///
+    /// Cluster peers will then avoid scheduling this match until the match either completes,
+    /// or the cache entry's invisibility window times out
+    fn announce_match_in_progress(
+        &self,
+        my_order: OrderIdentifier,
+        peer_order: OrderIdentifier,
+    ) -> Result<(), HandshakeManagerError> {
         let cluster_id = { self.global_state.local_cluster_id.clone() };
         self.network_channel
             .send(GossipOutbound::Pubsub {
                 topic: cluster_id.get_management_topic(),
                 message: PubsubMessage::ClusterManagement {
                     cluster_id,
-                    message: ClusterManagementMessage::MatchInProgress(my_order, sender_order),
+                    message: ClusterManagementMessage::MatchInProgress(my_order, peer_order),
                 },
             })
             .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
 
-        let resp = HandshakeMessage::ExecuteMatch {
-            peer_id: self.global_state.local_peer_id(),
-            port: local_port,
-            previously_matched,
-            order1: my_order,
-            order2: sender_order,
-        };
-        self.send_request_response(request_id, peer_id, resp, Some(response_channel))?;
-
         Ok(())
     }
 
@@ -468,10 +1055,11 @@ impl HandshakeExecutor {
     fn reject_match_proposal(
         &self,
         request_id: Uuid,
+        peer_id: WrappedPeerId,
         peer_order: OrderIdentifier,
         local_order: OrderIdentifier,
         reason: MatchRejectionReason,
-        response_channel: ResponseChannel<AuthenticatedGossipResponse>,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
     ) -> Result<(), HandshakeManagerError> {
         let message = HandshakeMessage::RejectMatchCandidate {
             peer_id: self.global_state.local_peer_id,
@@ -480,31 +1068,165 @@ impl HandshakeExecutor {
             reason,
         };
 
-        self.network_channel
-            .send(GossipOutbound::Response {
-                channel: response_channel,
-                message: GossipResponse::Handshake {
-                    request_id,
-                    message,
-                },
-            })
-            .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))
+        self.send_request_response(request_id, peer_id, message, response_channel)
+    }
+
+    /// Handles the responder's size bucket, sent in reply to a proposal that carried a
+    /// bucket commitment
+    ///
+    /// If the buckets overlap the local peer opens its commitment, otherwise the order
+    /// pair is cached as matched and the responder is told to abandon the handshake
+    async fn handle_size_bucket_challenge(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        peer_bucket: u8,
+    ) -> Result<(), HandshakeManagerError> {
+        let state = self
+            .handshake_state_index
+            .get_state(&request_id)
+            .ok_or_else(|| {
+                HandshakeManagerError::InvalidRequest(format!("request_id {:?}", request_id))
+            })?;
+        let blinder = state.local_size_bucket_blinder.ok_or_else(|| {
+            HandshakeManagerError::InvalidRequest(
+                "size bucket challenge for a proposal without a bucket commitment".to_string(),
+            )
+        })?;
+        let local_bucket = self
+            .local_size_bucket(&state.local_order_id)
+            .await
+            .ok_or_else(|| {
+                HandshakeManagerError::StateNotFound(
+                    "missing validity proof witness for order".to_string(),
+                )
+            })?;
+
+        let message = if buckets_overlap(local_bucket, peer_bucket) {
+            HandshakeMessage::SizeBucketOpening {
+                peer_id: self.global_state.local_peer_id(),
+                peer_order: state.peer_order_id,
+                sender_order: state.local_order_id,
+                bucket: local_bucket,
+                blinder,
+            }
+        } else {
+            self.abandon_size_mismatch(&request_id, state.local_order_id, state.peer_order_id)
+                .await;
+            HandshakeMessage::RejectMatchCandidate {
+                peer_id: self.global_state.local_peer_id(),
+                peer_order: state.peer_order_id,
+                sender_order: state.local_order_id,
+                reason: MatchRejectionReason::SizeBucketMismatch,
+            }
+        };
+
+        self.send_request_response(
+            request_id, peer_id, message, None, /* response_channel */
+        )
+    }
+
+    /// Handles the proposer's opening of its size bucket commitment, brokering an MPC
+    /// if the opening is valid and the buckets overlap
+    async fn handle_size_bucket_opening(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        peer_bucket: u8,
+        blinder: Scalar,
+        response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
+    ) -> Result<(), HandshakeManagerError> {
+        let state = self
+            .handshake_state_index
+            .get_state(&request_id)
+            .ok_or_else(|| {
+                HandshakeManagerError::InvalidRequest(format!("request_id {:?}", request_id))
+            })?;
+        let commitment = state.peer_size_bucket_commitment.ok_or_else(|| {
+            HandshakeManagerError::InvalidRequest(
+                "size bucket opening for a proposal without a bucket commitment".to_string(),
+            )
+        })?;
+        let local_bucket = self
+            .local_size_bucket(&state.local_order_id)
+            .await
+            .ok_or_else(|| {
+                HandshakeManagerError::StateNotFound(
+                    "missing validity proof witness for order".to_string(),
+                )
+            })?;
+
+        if !verify_bucket_opening(commitment, peer_bucket, blinder)
+            || !buckets_overlap(local_bucket, peer_bucket)
+        {
+            self.abandon_size_mismatch(&request_id, state.local_order_id, state.peer_order_id)
+                .await;
+            return self.reject_match_proposal(
+                request_id,
+                peer_id,
+                state.peer_order_id,
+                state.local_order_id,
+                MatchRejectionReason::SizeBucketMismatch,
+                response_channel,
+            );
+        }
+
+        self.accept_match_proposal(
+            request_id,
+            peer_id,
+            state.local_order_id,
+            state.peer_order_id,
+            response_channel,
+        )
     }
 
     /// Handles a rejected match proposal, possibly updating the cache for a missing entry
-    async fn handle_proposal_rejection(
+    ///
+    /// The rejected orders are taken from the handshake's state rather than the message, so
+    /// that a peer cannot have the local peer cache a pair it never proposed
+    async fn handle_proposal_rejection(&self, request_id: Uuid, reason: MatchRejectionReason) {
+        let state = match self.handshake_state_index.get_state(&request_id) {
+            Some(state) => state,
+            None => return,
+        };
+
+        match reason {
+            // Update the local cache
+            MatchRejectionReason::Cached => self
+                .handshake_cache
+                .mark_completed(state.local_order_id, state.peer_order_id),
+            MatchRejectionReason::SizeBucketMismatch => {
+                self.abandon_size_mismatch(&request_id, state.local_order_id, state.peer_order_id)
+                    .await
+            }
+            _ => {}
+        }
+    }
+
+    /// Abandon a handshake on an order pair whose sizes are grossly mismatched
+    ///
+    /// The pair is cached as matched so that neither peer schedules it again
+    async fn abandon_size_mismatch(
         &self,
+        request_id: &Uuid,
         my_order: OrderIdentifier,
-        sender_order: OrderIdentifier,
-        reason: MatchRejectionReason,
+        peer_order: OrderIdentifier,
     ) {
-        if let MatchRejectionReason::Cached = reason {
-            // Update the local cache
-            self.handshake_cache
-                .write()
-                .await
-                .mark_completed(my_order, sender_order)
-        }
+        self.handshake_cache.mark_completed(my_order, peer_order);
+        self.handshake_state_index.remove_handshake(request_id);
+    }
+
+    /// Compute the size bucket of a locally managed order from its validity proof witness
+    async fn local_size_bucket(&self, order_id: &OrderIdentifier) -> Option<u8> {
+        let witness = self
+            .global_state
+            .read_order_book()
+            .await
+            .get_validity_proof_witness(order_id)
+            .await?;
+        let order: Order = witness.order.into();
+
+        Some(size_bucket(order.amount))
     }
 
     /// Handles the flow of executing a match after both parties have agreed on an order
@@ -516,14 +1238,65 @@ impl HandshakeExecutor {
         port: u16,
         order1: OrderIdentifier,
         order2: OrderIdentifier,
+        pooled_net: Option<Uuid>,
         response_channel: Option<ResponseChannel<AuthenticatedGossipResponse>>,
     ) -> Result<(), HandshakeManagerError> {
+        // The peer must execute the match on the orders that were proposed
+        let state = self
+            .handshake_state_index
+            .get_state(&request_id)
+            .ok_or_else(|| {
+                HandshakeManagerError::InvalidRequest(format!("request_id {:?}", request_id))
+            })?;
+        let proposed = (state.peer_order_id, state.local_order_id);
+        if (order1, order2) != proposed && (order2, order1) != proposed {
+            return Err(HandshakeManagerError::InvalidRequest(
+                "execute match on orders other than those proposed".to_string(),
+            ));
+        }
+
         // Cache the result of a handshake
         self.handshake_cache
-            .write()
-            .await
-            .mark_completed(order1, order2);
+            .mark_completed(state.local_order_id, state.peer_order_id);
+
+        // Run the match over the net the peer offered from the pool, if any
+        match pooled_net {
+            Some(id) => {
+                let pooled = self.mpc_net_pool.check_out(&peer_id, id).ok_or_else(|| {
+                    HandshakeManagerError::StateNotFound(format!("pooled MPC net {id}"))
+                })?;
+                self.run_pooled_mpc(request_id, pooled);
+            }
+            None => self.dial_mpc(request_id, peer_id, port)?,
+        };
+
+        // Send back an ack
+        self.send_request_response(request_id, peer_id, HandshakeMessage::Ack, response_channel)
+    }
+
+    /// Run the MPC for a handshake over a net taken from the pool
+    fn run_pooled_mpc(&self, request_id: Uuid, pooled: PooledMpcNet) {
+        let self_clone = self.clone();
+        tokio::task::spawn(async move {
+            let job = HandshakeExecutionJob::MpcNetSetup {
+                request_id,
+                party_id: pooled.party_id,
+                net: pooled.net,
+                pooled_net_id: Some(pooled.id),
+            };
+            if let Err(e) = self_clone.handle_handshake_job(job).await {
+                log::info!("error executing match over pooled net: {e}")
+            }
+        });
+    }
 
+    /// Broker an MPC net on which the local peer dials the given peer at the given port
+    pub(super) fn dial_mpc(
+        &self,
+        request_id: Uuid,
+        peer_id: WrappedPeerId,
+        port: Port,
+    ) -> Result<(), HandshakeManagerError> {
         // Choose a local port to execute the handshake on
         let local_port = pick_unused_port().expect("all ports used");
         self.network_channel
@@ -536,10 +1309,7 @@ impl HandshakeExecutor {
                     local_role: ConnectionRole::Dialer,
                 },
             ))
-            .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
-
-        // Send back an ack
-        self.send_request_response(request_id, peer_id, HandshakeMessage::Ack, response_channel)
+            .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))
     }
 
     /// Sends a request or response depending on whether the response channel is None
@@ -577,24 +1347,185 @@ impl HandshakeExecutor {
             .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))
     }
 
+    /// Handles an MPC that overran the configured timeout
+    ///
+    /// The MPC is shot down and the failure recorded against the order pair. If the local
+    /// peer proposed the pair, it re-proposes the pair after an exponential backoff until
+    /// the pair has failed `MAX_MPC_ATTEMPTS` times, after which both peers give up on it
+    async fn handle_mpc_timeout(
+        &self,
+        request_id: Uuid,
+        party_id: u64,
+        order_state: &HandshakeState,
+    ) {
+        let local_order_id = order_state.local_order_id;
+        let peer_order_id = order_state.peer_order_id;
+        log::warn!("MPC timed out on order pair ({local_order_id}, {peer_order_id})");
+
+        // The blocking MPC thread cannot be interrupted, signal it to stop at its next
+        // cancellation check
+        if let Some(state) = self.handshake_state_index.remove_handshake(&request_id)
+            && let Some(channel) = state.cancel_channel
+        {
+            let _ = channel.try_send(());
+        }
+
+        let attempt = self.handshake_state_index.record_failure(
+            local_order_id,
+            peer_order_id,
+            order_state.peer_id,
+            HandshakeManagerError::MpcTimeout,
+        );
+        self.system_bus.publish(
+            HANDSHAKE_STATUS_TOPIC.to_string(),
+            SystemBusMessage::HandshakeTimeout {
+                local_order_id,
+                peer_order_id,
+                attempt,
+            },
+        );
+
+        // The pair was cached for the duration of the MPC, clear it so that a retry is
+        // not rejected, or cache it as completed once the pair is given up on
+        let give_up = attempt >= MAX_MPC_ATTEMPTS;
+        if give_up {
+            self.handshake_cache
+                .mark_completed(local_order_id, peer_order_id);
+        } else {
+            self.handshake_cache.remove(local_order_id, peer_order_id);
+        }
+
+        // Only the proposer (the dialer of the MPC net) retries, so that the peers do not
+        // both propose the pair. A brokered pair is left for its broker to schedule again
+        if give_up
+            || party_id != ConnectionRole::Dialer.get_party_id()
+            || order_state.broker.is_some()
+        {
+            return;
+        }
+
+        let backoff = Duration::from_millis(MPC_RETRY_BASE_BACKOFF_MS * 2u64.pow(attempt - 1));
+        let self_clone = self.clone();
+        tokio::task::spawn(async move {
+            tokio::time::sleep(backoff).await;
+            if let Err(e) = self_clone
+                .propose_order_pair(peer_order_id, local_order_id)
+                .await
+            {
+                log::info!("error retrying handshake: {e}")
+            }
+        });
+    }
+
+    /// Update the counterparty's reputation with the outcome of an MPC
+    ///
+    /// Networking and verification failures are attributed to the peer; a shootdown
+    /// or local error is not the peer's fault and leaves its reputation unchanged
+    async fn record_handshake_outcome<T>(
+        &self,
+        peer_id: WrappedPeerId,
+        res: &Result<T, HandshakeManagerError>,
+    ) {
+        match res {
+            Ok(_) => self
+                .global_state
+                .write_peer_reputation()
+                .await
+                .record_completed_handshake(peer_id),
+            Err(HandshakeManagerError::MpcNetwork(_)) | Err(HandshakeManagerError::MpcTimeout) => {
+                // The peer's other pooled nets are likely as broken as this one
+                self.mpc_net_pool.invalidate(&peer_id);
+                self.global_state
+                    .write_peer_reputation()
+                    .await
+                    .record_abandoned_handshake(peer_id)
+            }
+            Err(HandshakeManagerError::VerificationError(_)) => self
+                .global_state
+                .write_peer_reputation()
+                .await
+                .record_abandoned_handshake(peer_id),
+            Err(_) => {}
+        }
+    }
+
     /// Chooses an order to match against a remote order
+    ///
+    /// If the peer's cluster published an indication of interest in the remote order, local
+    /// orders whose IoIs may cross it are chosen first, and orders that cannot cross it last
+    ///
+    /// When pre-screening is enabled, pairs that the public order metadata shows cannot be
+    /// matched are dropped rather than deprioritized: the remote order is skipped if its
+    /// managing cluster is not permitted, and local orders are skipped if their IoIs cannot
+    /// cross the remote order's. Pre-screening may be disabled so that the pairs the local
+    /// peer declines to handshake on reveal nothing of its orders
     async fn choose_match_proposal(&self, peer_order: OrderIdentifier) -> Option<OrderIdentifier> {
-        let locked_handshake_cache = self.handshake_cache.read().await;
-        let local_verified_orders = self
-            .global_state
-            .read_order_book()
-            .await
-            .get_local_scheduleable_orders()
-            .await;
+        let now = self.clock.unix_secs();
+        let (local_verified_orders, peer_ioi, peer_cluster) = {
+            let locked_order_book = self.global_state.read_order_book().await;
+            let mut unexpired_orders = Vec::new();
+            for order_id in locked_order_book.get_local_scheduleable_orders().await {
+                if !locked_order_book.is_order_expired(&order_id, now).await {
+                    unexpired_orders.push(order_id);
+                }
+            }
 
-        // Choose an order that isn't cached
-        for order_id in local_verified_orders.iter() {
-            if !locked_handshake_cache.contains(*order_id, peer_order) {
-                return Some(*order_id);
+            (
+                unexpired_orders,
+                locked_order_book.get_ioi(&peer_order).await,
+                locked_order_book
+                    .get_order_info(&peer_order)
+                    .await
+                    .map(|info| info.cluster),
+            )
+        }; // locked_order_book released
+
+        // The peer would reject a proposal on an order managed by a cluster the local
+        // operator does not permit, so there is no pair to schedule
+        if self.handshake_prescreen
+            && let Some(cluster) = peer_cluster.as_ref()
+            && !self
+                .global_state
+                .read_cluster_access()
+                .await
+                .is_permitted(cluster)
+        {
+            log::debug!("pre-screened order {peer_order}, its cluster is not permitted");
+            return None;
+        }
+
+        // Only consider orders that aren't cached, and that may be filled within their
+        // match constraints
+        let mut candidate_orders = Vec::new();
+        for order_id in local_verified_orders {
+            if !self.handshake_cache.contains(order_id, peer_order)
+                && self.local_order_admits_match(&order_id).await
+            {
+                candidate_orders.push(order_id);
+            }
+        }
+        let mut uncached_orders = candidate_orders.into_iter();
+        let peer_ioi = match peer_ioi {
+            Some(ioi) => ioi,
+            None => return uncached_orders.next(),
+        };
+
+        let mut unknown_candidate = None;
+        let mut non_crossing_candidate = None;
+        for order_id in uncached_orders {
+            match self.global_state.get_local_order_ioi(&order_id).await {
+                Some(ioi) if ioi.may_cross(&peer_ioi) => return Some(order_id),
+                Some(_) if self.handshake_prescreen => {}
+                Some(_) => {
+                    non_crossing_candidate.get_or_insert(order_id);
+                }
+                None => {
+                    unknown_candidate.get_or_insert(order_id);
+                }
             }
         }
 
-        None
+        unknown_candidate.or(non_crossing_candidate)
     }
 
     /// Record a match as completed in the various state objects
@@ -603,15 +1534,12 @@ impl HandshakeExecutor {
         let state = self
             .handshake_state_index
             .get_state(&request_id)
-            .await
             .ok_or_else(|| {
                 HandshakeManagerError::InvalidRequest(format!("request_id {:?}", request_id))
             })?;
 
         // Cache the order pair as completed
         self.handshake_cache
-            .write()
-            .await
             .mark_completed(state.local_order_id, state.peer_order_id);
 
         // Write to global state for debugging
@@ -620,7 +1548,7 @@ impl HandshakeExecutor {
             .await;
 
         // Update the state of the handshake in the completed state
-        self.handshake_state_index.completed(&request_id).await;
+        self.handshake_state_index.completed(&request_id);
 
         // Send a message to cluster peers indicating that the local peer has completed a match
         // Cluster peers should cache the matched order pair as completed and not initiate matches
@@ -656,10 +1584,17 @@ impl HandshakeExecutor {
 /// tell the manager to send outbound handshake requests
 #[derive(Clone)]
 pub struct HandshakeScheduler {
-    /// The UnboundedSender to enqueue jobs on
-    job_sender: UnboundedSender<HandshakeExecutionJob>,
+    /// The queue to enqueue jobs on
+    job_sender: JobQueueSender<HandshakeExecutionJob>,
     /// A copy of the relayer-global state
     global_state: RelayerState,
+    /// Whether to broker a handshake between foreign orders on each interval, alongside
+    /// the local peer's own handshake
+    broker_handshakes: bool,
+    /// The source of randomness for interval jitter and order sampling
+    rng: WorkerRng,
+    /// The clock that the handshake interval is measured against
+    clock: SharedClock,
     /// The cancel channel to receive cancel signals on
     cancel: CancelChannel,
 }
@@ -667,30 +1602,45 @@ pub struct HandshakeScheduler {
 impl HandshakeScheduler {
     /// Construct a new timer
     pub fn new(
-        job_sender: UnboundedSender<HandshakeExecutionJob>,
+        job_sender: JobQueueSender<HandshakeExecutionJob>,
         global_state: RelayerState,
+        broker_handshakes: bool,
+        rng: WorkerRng,
+        clock: SharedClock,
         cancel: CancelChannel,
     ) -> Self {
         Self {
             job_sender,
             global_state,
+            broker_handshakes,
+            rng,
+            clock,
             cancel,
         }
     }
 
     /// The execution loop of the timer, periodically enqueues handshake jobs
     pub async fn execution_loop(mut self) -> HandshakeManagerError {
-        let interval_seconds = HANDSHAKE_INTERVAL_MS / 1000;
-        let interval_nanos = (HANDSHAKE_INTERVAL_MS % 1000 * NANOS_PER_MILLI) as u32;
-
-        let refresh_interval = Duration::new(interval_seconds, interval_nanos);
-
         loop {
+            let jitter_ms = self.rng.gen_range(0..HANDSHAKE_INTERVAL_JITTER_MS);
+            let refresh_interval = Duration::from_millis(HANDSHAKE_INTERVAL_MS + jitter_ms);
+
             tokio::select! {
                 // Enqueue handshakes periodically according to a timer
-                _ = tokio::time::sleep(refresh_interval) => {
+                _ = self.clock.sleep(refresh_interval) => {
+                    // Stop scheduling handshakes once the relayer begins draining, or while
+                    // the local peer only responds to its peers' proposals
+                    if self.global_state.is_draining()
+                        || self
+                            .global_state
+                            .is_feature_enabled(FeatureFlag::HandshakeReceiveOnly)
+                    {
+                        continue;
+                    }
+
                     // Enqueue a job to handshake with the randomly selected peer
-                    if let Some(order) = self.global_state.choose_handshake_order().await {
+                    let now = self.clock.unix_secs();
+                    if let Some(order) = self.global_state.choose_handshake_order(&self.rng, now).await {
                         if let Err(e) = self
                             .job_sender
                             .send(HandshakeExecutionJob::PerformHandshake { order })
@@ -699,6 +1649,17 @@ impl HandshakeScheduler {
                             return e;
                         }
                     }
+
+                    // Broker a match between a foreign order and an order from another cluster
+                    if self.broker_handshakes
+                        && let Some(order) = self.global_state.choose_handshake_order(&self.rng, now).await
+                        && let Err(e) = self
+                            .job_sender
+                            .send(HandshakeExecutionJob::BrokerHandshake { order })
+                            .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))
+                    {
+                        return e;
+                    }
                 },
 
                 _ = self.cancel.changed() => {
@@ -709,3 +1670,151 @@ impl HandshakeScheduler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use circuits::types::order::MatchConstraints;
+    use curve25519_dalek::scalar::Scalar;
+    use libp2p::PeerId;
+    use tokio::{sync::watch, time::timeout};
+    use uuid::Uuid;
+
+    use crate::{
+        clock::{ManualClock, SharedClock},
+        fee_schedule::FeeSchedule,
+        gossip::types::WrappedPeerId,
+        gossip_api::gossip::{GossipOutbound, GossipRequest},
+        handshake::{
+            jobs::HandshakeExecutionJob, journal::SettlementJournal, state::HandshakeStateIndex,
+        },
+        job_queue::{
+            job_queue, JobQueueError, JobQueueReceiver, DEFAULT_JOB_QUEUE_CAPACITY,
+            PROOF_MANAGER_QUEUE,
+        },
+        proof_generation::{jobs::ProofCancellationToken, proof_cache::ProofCache},
+        rng::WorkerRng,
+        simulation::chain::MockStarknetClient,
+        state::{cluster_access::ClusterAccessPolicy, feature_flags::FeatureFlags, RelayerState},
+        system_bus::SystemBus,
+    };
+
+    use super::{HandshakeExecutor, HandshakeExecutorConfig};
+
+    /// The number of standard jobs queued ahead of the shootdown
+    const BACKLOG_SIZE: usize = 32;
+    /// The maximum time to wait for the executor to answer a cache sync request
+    const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Await the number of completed pairs in the next cache sync response sent to the
+    /// network
+    async fn next_synced_pairs(network_receiver: &mut JobQueueReceiver<GossipOutbound>) -> usize {
+        match timeout(RESPONSE_TIMEOUT, network_receiver.recv()).await {
+            Ok(Some(GossipOutbound::Request {
+                message: GossipRequest::CacheSyncResponse(response),
+                ..
+            })) => response.completed_pairs.len(),
+            _ => panic!("expected a cache sync response"),
+        }
+    }
+
+    /// Tests that an MPC shootdown enqueued behind a full backlog of standard jobs is run
+    /// before the backlog drains, with the executor's jobs spread across worker threads
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_shootdown_preempts_full_backlog() {
+        let system_bus = SystemBus::new();
+        let global_state = RelayerState::initialize_global_state(
+            false, /* debug */
+            vec![],
+            "cluster".parse().unwrap(),
+            ClusterAccessPolicy::default(),
+            system_bus.clone(),
+            ProofCache::new(None /* cache_dir */).unwrap(),
+            FeatureFlags::new(&HashMap::new()),
+        );
+        let clock: SharedClock = Arc::new(ManualClock::new(Duration::from_secs(100)));
+        let chain = MockStarknetClient::new(1_000, 0, 0., WorkerRng::new(Some(1)), clock.clone());
+
+        let (job_sender, job_receiver) = job_queue("handshake", BACKLOG_SIZE + 1);
+        let (priority_job_sender, priority_job_receiver) =
+            job_queue("priority", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (network_sender, mut network_receiver) =
+            job_queue("network", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (price_reporter_sender, _) = job_queue("price", DEFAULT_JOB_QUEUE_CAPACITY);
+        let (proof_manager_sender, _) = job_queue(PROOF_MANAGER_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        let (cancel_sender, cancel_receiver) = watch::channel(());
+
+        // A settlement proof in flight on the nullifier that is shot down
+        let handshake_state_index = HandshakeStateIndex::new(global_state.clone(), clock.clone());
+        let nullifier = Scalar::from(1u8);
+        let proof_token = ProofCancellationToken::new();
+        handshake_state_index.register_settlement_proof(
+            Uuid::new_v4(),
+            &[nullifier],
+            proof_token.clone(),
+        );
+
+        let executor = HandshakeExecutor::new(HandshakeExecutorConfig {
+            job_channel: job_receiver,
+            priority_job_channel: priority_job_receiver,
+            network_channel: Arc::new(network_sender),
+            proof_manager_work_queue: proof_manager_sender,
+            global_state,
+            handshake_state_index,
+            system_bus,
+            mpc_timeout_ms: 1_000,
+            size_bucket_check: false,
+            handshake_prescreen: false,
+            max_concurrent_mpcs: 1,
+            max_concurrent_mpcs_per_peer: 1,
+            max_settlement_fee: None,
+            fee_schedule: FeeSchedule::default(),
+            broker_fee_bps: None,
+            max_broker_fee_bps: 0,
+            default_match_constraints: MatchConstraints::default(),
+            price_reporter_work_queue: price_reporter_sender,
+            starknet_client: Arc::new(chain),
+            rng: WorkerRng::new(Some(1)),
+            settlement_journal: SettlementJournal::open(None /* path */).unwrap(),
+            handshake_cache_file: None,
+            clock,
+            cancel: cancel_receiver,
+        })
+        .unwrap();
+
+        // Fill the standard queue with cache entries followed by a cache sync, then queue
+        // the shootdown behind them
+        for _ in 0..BACKLOG_SIZE {
+            job_sender
+                .send(HandshakeExecutionJob::CacheEntry {
+                    order1: Uuid::new_v4(),
+                    order2: Uuid::new_v4(),
+                })
+                .unwrap();
+        }
+        let peer_id = WrappedPeerId(PeerId::random());
+        job_sender
+            .send(HandshakeExecutionJob::CacheSyncRequest { peer_id })
+            .unwrap();
+
+        // The standard queue is full, so the shootdown can only take the priority lane
+        let shootdown = HandshakeExecutionJob::MpcShootdown {
+            match_nullifier: nullifier,
+        };
+        let shootdown = match job_sender.send(shootdown) {
+            Err(JobQueueError::Full(job)) => job,
+            _ => panic!("expected the standard queue to be full"),
+        };
+        priority_job_sender.send(shootdown).unwrap();
+
+        tokio::spawn(executor.execution_loop());
+
+        // The sync is dispatched only once the whole backlog has been, by which time the
+        // proof on the shot down nullifier has been cancelled
+        next_synced_pairs(&mut network_receiver).await;
+        assert!(proof_token.is_cancelled());
+
+        cancel_sender.send(()).unwrap();
+    }
+}
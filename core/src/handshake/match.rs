@@ -5,15 +5,15 @@ use std::{cell::RefCell, rc::Rc};
 
 use circuits::{
     mpc::SharedFabric,
-    mpc_circuits::r#match::compute_match,
+    mpc_circuits::r#match::match_orders_with_bounds,
     multiprover_prove,
     types::{
-        balance::LinkableBalanceCommitment,
+        balance::{Balance, LinkableBalanceCommitment},
         fee::LinkableFeeCommitment,
         order::{LinkableOrderCommitment, Order},
         r#match::{
             AuthenticatedLinkableMatchResultCommitment, AuthenticatedMatchResult,
-            LinkableMatchResultCommitment,
+            LinkableMatchResultCommitment, MatchBounds,
         },
     },
     verify_collaborative_proof,
@@ -31,16 +31,17 @@ use mpc_ristretto::{
     fabric::AuthenticatedMpcFabric,
     network::{MpcNetwork, QuicTwoPartyNet},
 };
+use serde::{Deserialize, Serialize};
 use tracing::log;
 use uuid::Uuid;
 
-use crate::types::SizedValidCommitmentsWitness;
+use crate::{keychain, types::SizedValidCommitmentsWitness};
 
 use super::{error::HandshakeManagerError, manager::HandshakeExecutor, state::HandshakeState};
 
 /// The type returned by the match process, including the result, the validity proof, and
 /// all witness/statement variables that must be revealed to complete the match
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HandshakeResult {
     /// The plaintext, opened result of the match
     pub match_: LinkableMatchResultCommitment,
@@ -71,17 +72,20 @@ impl HandshakeExecutor {
     /// Spawns the match computation in a separate thread wrapped by a custom
     /// Tokio runtime. The QUIC implementation in quinn is async and expects
     /// to be run inside of a Tokio runtime
+    ///
+    /// `connected` indicates that the net was taken from the pool and is already connected
+    /// to the peer. The net is handed back after the match completes, for the caller to pool
     pub(super) async fn execute_match(
         &self,
         request_id: Uuid,
         party_id: u64,
         mpc_net: QuicTwoPartyNet,
-    ) -> Result<HandshakeResult, HandshakeManagerError> {
+        connected: bool,
+    ) -> Result<(HandshakeResult, Option<QuicTwoPartyNet>), HandshakeManagerError> {
         // Fetch the handshake state from the state index
         let handshake_state = self
             .handshake_state_index
             .get_state(&request_id)
-            .await
             .ok_or_else(|| {
                 HandshakeManagerError::StateNotFound(
                     "missing handshake state for request".to_string(),
@@ -93,19 +97,25 @@ impl HandshakeExecutor {
         // Record the match as in progress and tag it with a cancel channel that may be used to
         // abort the MPC
         self.handshake_state_index
-            .in_progress(&request_id, cancel_sender)
-            .await;
+            .in_progress(&request_id, cancel_sender);
 
         // Wrap the current thread's execution in a Tokio blocking thread
+        //
+        // The MPC is counted as in flight for its duration so that a draining relayer
+        // may wait for it to finish before shutting down
         let self_clone = self.clone();
+        self.global_state.mpc_started();
         let res = self_clone
             .execute_match_impl(
                 party_id,
                 handshake_state.to_owned(),
                 mpc_net,
+                connected,
                 cancel_receiver,
             )
-            .await?;
+            .await;
+        self.global_state.mpc_finished();
+        let res = res?;
 
         // Await MPC completion
         log::info!("Finished match!");
@@ -119,21 +129,25 @@ impl HandshakeExecutor {
         party_id: u64,
         handshake_state: HandshakeState,
         mut mpc_net: QuicTwoPartyNet,
+        connected: bool,
         cancel_channel: Receiver<()>,
-    ) -> Result<HandshakeResult, HandshakeManagerError> {
+    ) -> Result<(HandshakeResult, Option<QuicTwoPartyNet>), HandshakeManagerError> {
         log::info!("Matching order...");
-        // Connect the network
-        mpc_net
-            .connect()
-            .await
-            .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
+        // Connect the network, unless it was pooled from an earlier match
+        if !connected {
+            mpc_net
+                .connect()
+                .await
+                .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
+        }
 
         // Build a fabric
         // TODO: Replace the dummy beaver source
         let beaver_source = PartyIDBeaverSource::new(party_id);
+        let net = Rc::new(RefCell::new(mpc_net));
         let fabric = AuthenticatedMpcFabric::new_with_network(
             party_id,
-            Rc::new(RefCell::new(mpc_net)),
+            net.clone(),
             Rc::new(RefCell::new(beaver_source)),
         );
 
@@ -153,9 +167,16 @@ impl HandshakeExecutor {
                 )
             })?;
 
+        // Bound the match by the local order's minimum fill size and price protection band
+        let match_bounds = self
+            .local_match_bounds(&handshake_state.local_order_id)
+            .await?;
+
         // Run the mpc to get a match result
         let match_res = Self::execute_match_mpc(
             &commitments_witness.order.clone().into(),
+            &commitments_witness.balance.clone().into(),
+            &match_bounds,
             shared_fabric.clone(),
         )?;
 
@@ -180,33 +201,61 @@ impl HandshakeExecutor {
             return Err(HandshakeManagerError::MpcShootdown);
         }
 
-        self.build_handshake_result(
-            witness.match_res,
-            proof,
-            commitments_witness,
-            handshake_state,
-            shared_fabric,
-            cancel_channel,
-        )
-        .await
+        let res = self
+            .build_handshake_result(
+                witness.match_res,
+                proof,
+                commitments_witness,
+                handshake_state,
+                shared_fabric,
+                cancel_channel,
+            )
+            .await?;
+
+        // The fabric has been dropped, leaving the net free to be pooled
+        let net = Rc::try_unwrap(net).ok().map(RefCell::into_inner);
+        Ok((res, net))
     }
 
     /// Execute the match MPC over the provisioned QUIC stream
     fn execute_match_mpc<N: MpcNetwork + Send, S: SharedValueSource<Scalar>>(
         local_order: &Order,
+        local_balance: &Balance,
+        local_bounds: &MatchBounds,
         fabric: SharedFabric<N, S>,
     ) -> Result<AuthenticatedMatchResult<N, S>, HandshakeManagerError> {
-        // Allocate the orders in the MPC fabric
+        // Allocate the orders, the balances that capitalize them, and the bounds each party
+        // places on the match in the MPC fabric
         let shared_order1 = local_order
             .allocate(0 /* owning_party */, fabric.clone())
             .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
+        let shared_balance1 = local_balance
+            .allocate(0 /* owning_party */, fabric.clone())
+            .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
+        let shared_bounds1 = local_bounds
+            .allocate(0 /* owning_party */, fabric.clone())
+            .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
         let shared_order2 = local_order
             .allocate(1 /* owning_party */, fabric.clone())
             .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
+        let shared_balance2 = local_balance
+            .allocate(1 /* owning_party */, fabric.clone())
+            .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
+        let shared_bounds2 = local_bounds
+            .allocate(1 /* owning_party */, fabric.clone())
+            .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
 
         // Run the circuit
-        compute_match(&shared_order1, &shared_order2, fabric)
-            .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))
+        match_orders_with_bounds(
+            &shared_order1,
+            &shared_balance1,
+            &shared_bounds1,
+            &shared_order2,
+            &shared_balance2,
+            &shared_bounds2,
+            fabric,
+        )
+        .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))
     }
 
     /// Generates a collaborative proof of the validity of a given match result
@@ -302,7 +351,7 @@ impl HandshakeExecutor {
             .map_err(|err| HandshakeManagerError::MpcNetwork(err.to_string()))?;
 
         // Share the wallet public settle keys with the counterparty
-        let my_key = wallet.public_keys.pk_settle;
+        let my_key = keychain::settle_key(&wallet);
         let party0_key = fabric
             .borrow_fabric()
             .share_plaintext_scalar(0 /* owning_party */, my_key)
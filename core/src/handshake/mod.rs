@@ -1,10 +1,18 @@
 //! The handshake module handles performing MPC handshakes with peers
+mod broker;
+mod commitment;
+mod concurrency;
+mod constraints;
 mod encumber;
 pub mod error;
-mod handshake_cache;
+pub mod handshake_cache;
 pub mod jobs;
+pub mod journal;
 pub mod manager;
 pub mod r#match;
+mod net_pool;
+mod settlement;
+pub mod size_bucket;
 pub mod state;
 pub mod types;
 pub mod worker;
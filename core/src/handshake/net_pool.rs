@@ -0,0 +1,185 @@
+//! A pool of the MPC nets left open by completed matches, keyed by the peer at the other
+//! end of the net
+//!
+//! Brokering an MPC net dials a fresh QUIC connection for every match. A net whose match
+//! completes cleanly is returned to the pool, and the next match with the same peer may
+//! run over it rather than brokering a new net. A net is never returned to the pool after
+//! an error, timeout, or shootdown, as its stream may hold messages sent for the abandoned
+//! MPC, and an MPC network error invalidates every net pooled for the peer
+//!
+//! Both ends of a net are pooled under the ID of the handshake that brokered it. Only the
+//! peer that listened on the net offers it for reuse, so that the two peers never reuse the
+//! same net for different matches at once. The listener only offers nets that have been
+//! idle for less than half the idle timeout, so that the dialer still holds its end
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use mpc_ristretto::network::QuicTwoPartyNet;
+use uuid::Uuid;
+
+use crate::{clock::SharedClock, gossip::types::WrappedPeerId, gossip_api::gossip::ConnectionRole};
+
+/// The amount of time a pooled net may sit idle before it is closed
+const POOLED_NET_IDLE_TIMEOUT_MS: u64 = 60_000; // 1 minute
+/// The amount of time a pooled net may sit idle and still be offered for reuse
+const POOLED_NET_REUSE_WINDOW_MS: u64 = POOLED_NET_IDLE_TIMEOUT_MS / 2;
+
+/// An MPC net whose connection to the peer is established
+pub struct PooledMpcNet {
+    /// The ID of the handshake that brokered the net, shared by both ends of the net
+    pub id: Uuid,
+    /// The local peer's party ID in MPCs run over the net
+    pub party_id: u64,
+    /// The net itself
+    pub net: QuicTwoPartyNet,
+    /// The time at which the net was returned to the pool
+    idle_since: Instant,
+}
+
+/// The pool of established MPC nets, shared between handshakes
+pub struct MpcNetPool {
+    /// The pooled nets, indexed by the peer at the other end
+    nets: Mutex<HashMap<WrappedPeerId, Vec<PooledMpcNet>>>,
+    /// The clock that idle times are measured against
+    clock: SharedClock,
+}
+
+impl MpcNetPool {
+    /// Construct an empty pool
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            nets: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Return a net to the pool after a match completes on it cleanly
+    pub fn check_in(&self, peer_id: WrappedPeerId, id: Uuid, party_id: u64, net: QuicTwoPartyNet) {
+        let now = self.clock.now();
+        let mut locked_nets = self.nets.lock().unwrap();
+        let peer_nets = locked_nets.entry(peer_id).or_default();
+        peer_nets.retain(|pooled| !Self::is_expired(pooled, now));
+        peer_nets.push(PooledMpcNet {
+            id,
+            party_id,
+            net,
+            idle_since: now,
+        });
+    }
+
+    /// Take a net that the local peer listened on from the pool, to offer it to the peer
+    /// for reuse
+    pub fn check_out_for_reuse(&self, peer_id: &WrappedPeerId) -> Option<PooledMpcNet> {
+        let now = self.clock.now();
+        let reuse_window = Duration::from_millis(POOLED_NET_REUSE_WINDOW_MS);
+        let listener_party_id = ConnectionRole::Listener.get_party_id();
+
+        let mut locked_nets = self.nets.lock().unwrap();
+        let peer_nets = locked_nets.get_mut(peer_id)?;
+        peer_nets.retain(|pooled| !Self::is_expired(pooled, now));
+        let index = peer_nets.iter().position(|pooled| {
+            pooled.party_id == listener_party_id
+                && now.saturating_duration_since(pooled.idle_since) < reuse_window
+        })?;
+
+        Some(peer_nets.swap_remove(index))
+    }
+
+    /// Take the net that the peer offered for reuse from the pool, `None` if it has been
+    /// closed locally
+    pub fn check_out(&self, peer_id: &WrappedPeerId, id: Uuid) -> Option<PooledMpcNet> {
+        let now = self.clock.now();
+        let mut locked_nets = self.nets.lock().unwrap();
+        let peer_nets = locked_nets.get_mut(peer_id)?;
+        peer_nets.retain(|pooled| !Self::is_expired(pooled, now));
+        let index = peer_nets.iter().position(|pooled| pooled.id == id)?;
+
+        Some(peer_nets.swap_remove(index))
+    }
+
+    /// Close every net pooled for the peer
+    pub fn invalidate(&self, peer_id: &WrappedPeerId) {
+        self.nets.lock().unwrap().remove(peer_id);
+    }
+
+    /// Whether a pooled net has sat idle past the idle timeout
+    fn is_expired(pooled: &PooledMpcNet, now: Instant) -> bool {
+        now.saturating_duration_since(pooled.idle_since)
+            >= Duration::from_millis(POOLED_NET_IDLE_TIMEOUT_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use mpc_ristretto::network::QuicTwoPartyNet;
+    use uuid::Uuid;
+
+    use crate::{
+        clock::ManualClock, gossip::types::WrappedPeerId, gossip_api::gossip::ConnectionRole,
+    };
+
+    use super::{MpcNetPool, POOLED_NET_IDLE_TIMEOUT_MS, POOLED_NET_REUSE_WINDOW_MS};
+
+    /// Build an unconnected net for the given role
+    fn mock_net(role: ConnectionRole) -> (u64, QuicTwoPartyNet) {
+        let party_id = role.get_party_id();
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        (party_id, QuicTwoPartyNet::new(party_id, addr, addr))
+    }
+
+    /// Tests that only nets the local peer listened on are offered for reuse, and only
+    /// within the reuse window
+    #[test]
+    fn test_reuse_window() {
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+        let pool = MpcNetPool::new(Arc::new(clock.clone()));
+        let peer_id = WrappedPeerId::random();
+
+        let (party_id, net) = mock_net(ConnectionRole::Dialer);
+        pool.check_in(peer_id, Uuid::new_v4(), party_id, net);
+        assert!(pool.check_out_for_reuse(&peer_id).is_none());
+
+        let listened_id = Uuid::new_v4();
+        let (party_id, net) = mock_net(ConnectionRole::Listener);
+        pool.check_in(peer_id, listened_id, party_id, net);
+        clock.advance(Duration::from_millis(POOLED_NET_REUSE_WINDOW_MS - 1));
+        let reused = pool.check_out_for_reuse(&peer_id).unwrap();
+        assert_eq!(reused.id, listened_id);
+
+        // A net past the reuse window is not offered
+        pool.check_in(peer_id, reused.id, reused.party_id, reused.net);
+        clock.advance(Duration::from_millis(POOLED_NET_REUSE_WINDOW_MS));
+        assert!(pool.check_out_for_reuse(&peer_id).is_none());
+    }
+
+    /// Tests that the dialer may take the net offered to it until the idle timeout, and
+    /// that invalidation closes the peer's nets
+    #[test]
+    fn test_check_out_by_id() {
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+        let pool = MpcNetPool::new(Arc::new(clock.clone()));
+        let peer_id = WrappedPeerId::random();
+
+        let id = Uuid::new_v4();
+        let (party_id, net) = mock_net(ConnectionRole::Dialer);
+        pool.check_in(peer_id, id, party_id, net);
+        assert!(pool.check_out(&peer_id, Uuid::new_v4()).is_none());
+        let pooled = pool.check_out(&peer_id, id).unwrap();
+        assert!(pool.check_out(&peer_id, id).is_none());
+
+        pool.check_in(peer_id, id, pooled.party_id, pooled.net);
+        pool.invalidate(&peer_id);
+        assert!(pool.check_out(&peer_id, id).is_none());
+
+        let (party_id, net) = mock_net(ConnectionRole::Dialer);
+        pool.check_in(peer_id, id, party_id, net);
+        clock.advance(Duration::from_millis(POOLED_NET_IDLE_TIMEOUT_MS));
+        assert!(pool.check_out(&peer_id, id).is_none());
+    }
+}
@@ -0,0 +1,306 @@
+//! Implements the submission of a proven match settlement to the contract
+//!
+//! The proof of `VALID MATCH ENCRYPTION` is bundled with the `match` calldata and the
+//! fee of the resulting transaction is estimated before it is broadcast. A settlement
+//! whose estimated fee exceeds the configured ceiling, or that the sequencer turns away
+//! for load, is held back and retried with exponential backoff. Each stage is published
+//! to the system bus and recorded in the relayer state, where the API serves it
+
+use std::time::Duration;
+
+use crypto::fields::starknet_felt_to_biguint;
+use starknet::core::types::FieldElement as StarknetFieldElement;
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::log;
+use uuid::Uuid;
+
+use crate::{
+    error::{ErrorCode, ErrorCoded},
+    proof_generation::jobs::ValidMatchEncryptBundle,
+    starknet_client::{contract_abi::settle_match_calldata, error::StarknetClientError},
+    state::settlements::SettlementRecord,
+    types::{SettlementStatus, SystemBusMessage, SETTLEMENT_STATUS_TOPIC},
+};
+
+use super::{
+    error::HandshakeManagerError, journal::SettlementJournalEntry, manager::HandshakeExecutor,
+};
+
+/// The number of times a settlement is attempted while the network is congested
+const MAX_SETTLEMENT_ATTEMPTS: u32 = 5;
+/// The delay before the first retry of a congested settlement, doubled on each further retry
+const SETTLEMENT_RETRY_BASE_BACKOFF_MS: u64 = 10_000; // 10 seconds
+/// The substrings of a sequencer error that indicate it is shedding load
+const CONGESTION_MARKERS: &[&str] = &[
+    "429",
+    "too many requests",
+    "congest",
+    "service unavailable",
+    "timed out",
+];
+
+/// The error message emitted when the relayer has no account to settle from
+const ERR_NO_ACCOUNT: &str = "no starknet account configured to settle from";
+
+/// The outcome of a single settlement attempt
+enum SettlementAttempt {
+    /// The settlement was broadcast under the given hash
+    Submitted {
+        /// The hash of the transaction
+        tx_hash: StarknetFieldElement,
+        /// The fee estimated for the transaction, in wei
+        estimated_fee: u64,
+    },
+    /// The network is congested, the settlement may be retried
+    Congested(String),
+}
+
+impl HandshakeExecutor {
+    /// Submit a proven settlement and retire its journal entry
+    ///
+    /// A settlement whose statement commits to a protocol fee other than the one the fee
+    /// schedule resolves, or whose proof does not open the commitment posted for the match,
+    /// is retired without being submitted
+    ///
+    /// A settlement that is still congested after the last attempt is left in the journal
+    /// to be resumed when the relayer next starts, as is one that cannot be submitted for
+    /// lack of an account. A settlement the sequencer rejects outright is retired
+    pub(super) async fn submit_settlement(
+        &self,
+        request_id: Uuid,
+        bundle: ValidMatchEncryptBundle,
+    ) -> Result<(), HandshakeManagerError> {
+        let entry = self.settlement_journal.get(&request_id).ok_or_else(|| {
+            HandshakeManagerError::Journal(format!("no journal entry for {request_id}"))
+        })?;
+        if !self.starknet_client.account_enabled() {
+            self.publish_settlement_status(
+                &entry,
+                SettlementStatus::Failed {
+                    code: ErrorCode::Config,
+                    reason: ERR_NO_ACCOUNT.to_string(),
+                },
+            );
+            return Err(HandshakeManagerError::Starknet(
+                StarknetClientError::NoAccount,
+            ));
+        }
+
+        // A settlement proven against a fee the schedule no longer resolves to would be
+        // rejected by the contract
+        if let Err(err) = self.validate_protocol_fee(&entry, &bundle.statement) {
+            self.publish_settlement_status(
+                &entry,
+                SettlementStatus::Failed {
+                    code: err.error_code(),
+                    reason: err.to_string(),
+                },
+            );
+            self.settlement_journal.remove(&request_id)?;
+            return Err(err);
+        }
+
+        // The settlement reveals the match committed to ahead of the proof; a proof that does
+        // not open the commitment would be rejected by the contract
+        if let Err(err) = self.validate_match_opening(&entry, &bundle) {
+            self.publish_settlement_status(
+                &entry,
+                SettlementStatus::Failed {
+                    code: err.error_code(),
+                    reason: err.to_string(),
+                },
+            );
+            self.settlement_journal.remove(&request_id)?;
+            return Err(err);
+        }
+
+        let [match_nullifier0, match_nullifier1] = entry.party_match_nullifiers;
+        let calldata = settle_match_calldata(
+            match_nullifier0,
+            match_nullifier1,
+            &bundle.statement,
+            &bundle.proof.to_bytes(),
+        );
+
+        let mut attempt = 0;
+        let mut backoff_ms = SETTLEMENT_RETRY_BASE_BACKOFF_MS;
+        loop {
+            attempt += 1;
+            let reason = match self.attempt_settlement(&entry, calldata.clone()).await {
+                Ok(SettlementAttempt::Submitted {
+                    tx_hash,
+                    estimated_fee,
+                }) => {
+                    let tx_hash =
+                        format!("0x{}", starknet_felt_to_biguint(&tx_hash).to_str_radix(16));
+                    log::info!("settlement {request_id} submitted in transaction {tx_hash}");
+                    self.publish_settlement_status(
+                        &entry,
+                        SettlementStatus::Submitted {
+                            tx_hash,
+                            estimated_fee,
+                        },
+                    );
+
+                    return self.settlement_journal.remove(&request_id);
+                }
+                Ok(SettlementAttempt::Congested(reason)) => reason,
+                Err(err) => {
+                    self.publish_settlement_status(
+                        &entry,
+                        SettlementStatus::Failed {
+                            code: err.error_code(),
+                            reason: err.to_string(),
+                        },
+                    );
+                    self.settlement_journal.remove(&request_id)?;
+                    return Err(HandshakeManagerError::Starknet(err));
+                }
+            };
+
+            if attempt >= MAX_SETTLEMENT_ATTEMPTS {
+                let reason = format!("still congested after {attempt} attempts: {reason}");
+                self.publish_settlement_status(
+                    &entry,
+                    SettlementStatus::Failed {
+                        code: ErrorCode::Timeout,
+                        reason: reason.clone(),
+                    },
+                );
+                return Err(HandshakeManagerError::Settlement(reason));
+            }
+
+            log::info!("settlement {request_id} held back, retrying in {backoff_ms}ms: {reason}");
+            self.publish_settlement_status(
+                &entry,
+                SettlementStatus::Congested {
+                    reason,
+                    retry_in_ms: backoff_ms,
+                },
+            );
+            self.clock.sleep(Duration::from_millis(backoff_ms)).await;
+            backoff_ms *= 2;
+        }
+    }
+
+    /// Estimate the settlement's fee and, if the network is not congested, broadcast it
+    async fn attempt_settlement(
+        &self,
+        entry: &SettlementJournalEntry,
+        calldata: Vec<StarknetFieldElement>,
+    ) -> Result<SettlementAttempt, StarknetClientError> {
+        self.publish_settlement_status(entry, SettlementStatus::EstimatingFee);
+        let estimated_fee = match self
+            .starknet_client
+            .estimate_settle_match_fee(calldata.clone())
+            .await
+        {
+            Ok(fee) => fee,
+            Err(err) if is_congestion(&err) => {
+                return Ok(SettlementAttempt::Congested(err.to_string()))
+            }
+            Err(err) => return Err(err),
+        };
+
+        if let Some(max_fee) = self.max_settlement_fee && estimated_fee > max_fee {
+            return Ok(SettlementAttempt::Congested(format!(
+                "estimated fee {estimated_fee} exceeds the maximum of {max_fee}"
+            )));
+        }
+
+        // Report a transaction the sequencer rejects after it is broadcast
+        let (failure_sender, mut failure_receiver) = unbounded_channel();
+        let tx_hash = match self
+            .starknet_client
+            .settle_match(calldata, failure_sender)
+            .await
+        {
+            Ok(tx_hash) => tx_hash,
+            Err(err) if is_congestion(&err) => {
+                return Ok(SettlementAttempt::Congested(err.to_string()))
+            }
+            Err(err) => return Err(err),
+        };
+
+        let self_clone = self.clone();
+        let entry_clone = entry.clone();
+        tokio::spawn(async move {
+            if let Some(job) = failure_receiver.recv().await {
+                self_clone.publish_settlement_status(
+                    &entry_clone,
+                    SettlementStatus::Failed {
+                        code: ErrorCode::TransactionRejected,
+                        reason: job.reason,
+                    },
+                );
+            }
+        });
+
+        Ok(SettlementAttempt::Submitted {
+            tx_hash,
+            estimated_fee,
+        })
+    }
+
+    /// Publish the progress of a settlement to the system bus and record it in the
+    /// relayer state
+    pub(super) fn publish_settlement_status(
+        &self,
+        entry: &SettlementJournalEntry,
+        status: SettlementStatus,
+    ) {
+        self.global_state.record_settlement(SettlementRecord {
+            request_id: entry.request_id,
+            local_order_id: entry.local_order_id,
+            peer_order_id: entry.peer_order_id,
+            status: status.clone(),
+            updated_at: self.clock.unix_secs(),
+        });
+        self.system_bus.publish(
+            SETTLEMENT_STATUS_TOPIC.to_string(),
+            SystemBusMessage::SettlementProgress {
+                request_id: entry.request_id,
+                local_order_id: entry.local_order_id,
+                peer_order_id: entry.peer_order_id,
+                status,
+            },
+        );
+    }
+}
+
+/// Whether an error from the starknet client indicates that the network is congested,
+/// rather than that the settlement is invalid
+fn is_congestion(err: &StarknetClientError) -> bool {
+    match err {
+        StarknetClientError::Nonce(_) => true,
+        StarknetClientError::Transaction(reason) => {
+            let reason = reason.to_lowercase();
+            CONGESTION_MARKERS
+                .iter()
+                .any(|marker| reason.contains(marker))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::starknet_client::error::StarknetClientError;
+
+    use super::is_congestion;
+
+    /// Tests that load shedding by the sequencer is told apart from a rejected settlement
+    #[test]
+    fn test_is_congestion() {
+        assert!(is_congestion(&StarknetClientError::Transaction(
+            "HTTP 429 Too Many Requests".to_string()
+        )));
+        assert!(is_congestion(&StarknetClientError::Nonce(
+            "connection reset".to_string()
+        )));
+        assert!(!is_congestion(&StarknetClientError::Transaction(
+            "nullifier already used".to_string()
+        )));
+        assert!(!is_congestion(&StarknetClientError::NoAccount));
+    }
+}
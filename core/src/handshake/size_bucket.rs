@@ -0,0 +1,69 @@
+//! Defines the coarse order size buckets that peers may compare before committing
+//! to an MPC
+//!
+//! An order's bucket is the bit length of its amount, so each bucket spans a factor of
+//! two in size. The proposer of a match commits to its bucket, the responder reveals its
+//! own bucket in the clear, and the proposer then opens its commitment; only the coarse
+//! buckets are revealed, and a pair whose sizes are grossly mismatched is abandoned before
+//! any MPC resources are spent on it
+
+use circuits::native_helpers::compute_poseidon_hash;
+use curve25519_dalek::scalar::Scalar;
+
+/// The number of buckets by which two orders' buckets may differ and still be
+/// considered overlapping
+const SIZE_BUCKET_TOLERANCE: u8 = 2;
+
+/// Compute the size bucket of an order amount
+pub fn size_bucket(amount: u64) -> u8 {
+    (u64::BITS - amount.leading_zeros()) as u8
+}
+
+/// Whether two buckets are close enough that the orders in them may plausibly match
+pub fn buckets_overlap(bucket1: u8, bucket2: u8) -> bool {
+    bucket1.abs_diff(bucket2) <= SIZE_BUCKET_TOLERANCE
+}
+
+/// Commit to a size bucket under the given blinder
+pub fn commit_to_bucket(bucket: u8, blinder: Scalar) -> Scalar {
+    compute_poseidon_hash(&[Scalar::from(bucket as u64), blinder])
+}
+
+/// Check that a bucket and blinder open the given commitment
+pub fn verify_bucket_opening(commitment: Scalar, bucket: u8, blinder: Scalar) -> bool {
+    commit_to_bucket(bucket, blinder) == commitment
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::{buckets_overlap, commit_to_bucket, size_bucket, verify_bucket_opening};
+
+    /// Tests that buckets overlap only within the tolerance
+    #[test]
+    fn test_bucket_overlap() {
+        assert_eq!(size_bucket(0), 0);
+        assert_eq!(size_bucket(1), 1);
+        assert_eq!(size_bucket(1_000), size_bucket(1_023));
+
+        assert!(buckets_overlap(size_bucket(100), size_bucket(300)));
+        assert!(!buckets_overlap(size_bucket(100), size_bucket(100_000)));
+    }
+
+    /// Tests that a commitment only opens to the bucket and blinder it was made with
+    #[test]
+    fn test_bucket_opening() {
+        let bucket = 10;
+        let blinder = Scalar::from(42u64);
+        let commitment = commit_to_bucket(bucket, blinder);
+
+        assert!(verify_bucket_opening(commitment, bucket, blinder));
+        assert!(!verify_bucket_opening(commitment, bucket + 1, blinder));
+        assert!(!verify_bucket_opening(
+            commitment,
+            bucket,
+            Scalar::from(43u64)
+        ));
+    }
+}
@@ -2,8 +2,18 @@
 // TODO: Remove this lint allowance
 #![allow(dead_code)]
 
-use crate::state::{new_async_shared, AsyncShared, OrderIdentifier, RelayerState};
-use std::collections::{HashMap, HashSet};
+use crate::{
+    clock::SharedClock,
+    gossip::types::WrappedPeerId,
+    gossip_api::handshake::BrokerFee,
+    proof_generation::jobs::ProofCancellationToken,
+    state::{OrderIdentifier, RelayerState},
+};
+use dashmap::DashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use super::error::HandshakeManagerError;
 use crossbeam::channel::Sender;
@@ -13,24 +23,43 @@ use uuid::Uuid;
 /// Holds state information for all in-flight handshake correspondences
 ///
 /// Abstracts mostly over the concurrent access patterns used by the thread pool
-/// of handshake executors
+/// of handshake executors. Each map is sharded, so jobs on different handshakes
+/// rarely contend; no shard is locked across an await point, nor is more than one
+/// shard locked at once
 #[derive(Clone, Debug)]
 pub struct HandshakeStateIndex {
     /// The underlying map of request identifiers to state machine instances
-    state_map: AsyncShared<HashMap<Uuid, HandshakeState>>,
+    state_map: Arc<DashMap<Uuid, HandshakeState>>,
     /// A mapping from nullifier to a set of request_ids on that nullifier
-    nullifier_map: AsyncShared<HashMap<Scalar, HashSet<Uuid>>>,
+    nullifier_map: Arc<DashMap<Scalar, HashSet<Uuid>>>,
+    /// Failed match attempts, keyed by the (local, peer) order pair
+    ///
+    /// Records outlive the handshakes they were made on, so that repeated failures
+    /// on the same order pair may be counted across handshakes
+    failures: Arc<DashMap<(OrderIdentifier, OrderIdentifier), HandshakeFailure>>,
+    /// The in-flight settlement proofs of completed handshakes, keyed by the match
+    /// nullifiers they settle on
+    ///
+    /// A completed handshake is removed from the state map before it is settled, so its
+    /// settlement proofs are indexed separately in order that a nullifier shootdown may
+    /// cancel them
+    settlement_proofs: Arc<DashMap<Scalar, HashMap<Uuid, ProofCancellationToken>>>,
     /// A copy of the relayer global state
     global_state: RelayerState,
+    /// The clock that failures are timestamped with
+    clock: SharedClock,
 }
 
 impl HandshakeStateIndex {
     /// Creates a new instance of the state index
-    pub fn new(global_state: RelayerState) -> Self {
+    pub fn new(global_state: RelayerState, clock: SharedClock) -> Self {
         Self {
-            state_map: new_async_shared(HashMap::new()),
-            nullifier_map: new_async_shared(HashMap::new()),
+            state_map: Arc::new(DashMap::new()),
+            nullifier_map: Arc::new(DashMap::new()),
+            failures: Arc::new(DashMap::new()),
+            settlement_proofs: Arc::new(DashMap::new()),
             global_state,
+            clock,
         }
     }
 
@@ -43,6 +72,7 @@ impl HandshakeStateIndex {
     pub async fn new_handshake(
         &self,
         request_id: Uuid,
+        peer_id: WrappedPeerId,
         peer_order_id: OrderIdentifier,
         local_order_id: OrderIdentifier,
     ) -> Result<(), HandshakeManagerError> {
@@ -66,84 +96,208 @@ impl HandshakeStateIndex {
             })?;
 
         // Index by request ID
-        {
-            let mut locked_state = self.state_map.write().await;
-            locked_state.insert(
+        self.state_map.insert(
+            request_id,
+            HandshakeState::new(
                 request_id,
-                HandshakeState::new(
-                    request_id,
-                    peer_order_id,
-                    local_order_id,
-                    peer_nullifier,
-                    local_nullifier,
-                ),
-            );
-        } // locked_state released
+                peer_id,
+                peer_order_id,
+                local_order_id,
+                peer_nullifier,
+                local_nullifier,
+            ),
+        );
 
         // Index by nullifier
-        {
-            let mut locked_nullifier_map = self.nullifier_map.write().await;
-            locked_nullifier_map
-                .entry(local_nullifier)
-                .or_default()
-                .insert(request_id);
-            locked_nullifier_map
-                .entry(peer_nullifier)
-                .or_default()
-                .insert(request_id);
-        } // locked_nullifier_map released
+        self.nullifier_map
+            .entry(local_nullifier)
+            .or_default()
+            .insert(request_id);
+        self.nullifier_map
+            .entry(peer_nullifier)
+            .or_default()
+            .insert(request_id);
 
         Ok(())
     }
 
     /// Removes a handshake after processing is complete; either by match completion or error
-    pub async fn remove_handshake(&self, request_id: &Uuid) -> Option<HandshakeState> {
+    pub fn remove_handshake(&self, request_id: &Uuid) -> Option<HandshakeState> {
         // Remove from the state
-        let state = {
-            let mut locked_state = self.state_map.write().await;
-            locked_state.remove(request_id)
-        }; // locked_state released
+        let (_, state) = self.state_map.remove(request_id)?;
 
         // Remove from the nullifier index
-        if let Some(state) = state.clone() {
-            let mut locked_nullifier_map = self.nullifier_map.write().await;
-
-            if let Some(nullifier_set) = locked_nullifier_map.get_mut(&state.local_match_nullifier)
-            {
+        for nullifier in [state.local_match_nullifier, state.peer_match_nullifier].iter() {
+            if let Some(mut nullifier_set) = self.nullifier_map.get_mut(nullifier) {
                 nullifier_set.remove(request_id);
             }
+        }
 
-            if let Some(nullifier_set) = locked_nullifier_map.get_mut(&state.peer_match_nullifier) {
-                nullifier_set.remove(request_id);
-            }
-        } // locked_nullifier_map released
+        Some(state)
+    }
+
+    /// Index the settlement proof of a completed handshake under the match nullifiers it
+    /// settles on
+    pub fn register_settlement_proof(
+        &self,
+        request_id: Uuid,
+        match_nullifiers: &[Scalar],
+        token: ProofCancellationToken,
+    ) {
+        for nullifier in match_nullifiers.iter() {
+            self.settlement_proofs
+                .entry(*nullifier)
+                .or_default()
+                .insert(request_id, token.clone());
+        }
+    }
 
-        state
+    /// Remove a handshake's settlement proof from the index once it has finished
+    pub fn settlement_proof_finished(&self, request_id: &Uuid, match_nullifiers: &[Scalar]) {
+        for nullifier in match_nullifiers.iter() {
+            if let Some(mut proofs) = self.settlement_proofs.get_mut(nullifier) {
+                proofs.remove(request_id);
+            }
+            self.settlement_proofs
+                .remove_if(nullifier, |_, proofs| proofs.is_empty());
+        }
     }
 
-    /// Shootdown all active handshakes on a given nullifier
-    pub async fn shootdown_nullifier(
+    /// Shootdown all active handshakes on a given nullifier, and cancel the settlement
+    /// proofs of completed handshakes on it
+    ///
+    /// Returns the handshakes that were shot down
+    pub fn shootdown_nullifier(
         &self,
         nullifier: Scalar,
-    ) -> Result<(), HandshakeManagerError> {
-        let requests = {
-            let mut locked_nullifier_map = self.nullifier_map.write().await;
-            locked_nullifier_map.remove(&nullifier).unwrap_or_default()
-        }; // locked_nullifier_map released
-
-        // For each request, remove the state entry for the request and send a cancel signal
-        // over the request's cancel channel if one has already been allocated. The receiver
-        // of this channel is the worker running in the MPC runtime
+    ) -> Result<Vec<HandshakeState>, HandshakeManagerError> {
+        if let Some((_, proofs)) = self.settlement_proofs.remove(&nullifier) {
+            for token in proofs.values() {
+                token.cancel();
+            }
+        }
+
+        let requests = self
+            .nullifier_map
+            .remove(&nullifier)
+            .map(|(_, requests)| requests)
+            .unwrap_or_default();
+
+        let mut shot_down = Vec::with_capacity(requests.len());
         for request in requests.iter() {
-            if let Some(state) = self.remove_handshake(request).await
-            && let Some(channel) = state.cancel_channel
-            {
-                channel.send(())
-                    .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
+            if let Some(state) = self.teardown_handshake(request)? {
+                shot_down.push(state);
             }
         }
 
-        Ok(())
+        Ok(shot_down)
+    }
+
+    /// Cancel a single in-flight handshake, tearing it down as a nullifier shootdown would
+    ///
+    /// Returns the cancelled handshake, or `None` if the handshake is not in flight
+    pub fn cancel_handshake(
+        &self,
+        request_id: &Uuid,
+    ) -> Result<Option<HandshakeState>, HandshakeManagerError> {
+        self.teardown_handshake(request_id)
+    }
+
+    /// Remove the state entry for a handshake and send a cancel signal over its cancel
+    /// channel if one has already been allocated. The receiver of this channel is the
+    /// worker running in the MPC runtime
+    ///
+    /// The handshake is returned in the `Error` state
+    fn teardown_handshake(
+        &self,
+        request_id: &Uuid,
+    ) -> Result<Option<HandshakeState>, HandshakeManagerError> {
+        let mut state = match self.remove_handshake(request_id) {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+
+        if let Some(channel) = state.cancel_channel.take() {
+            channel
+                .send(())
+                .map_err(|err| HandshakeManagerError::SendMessage(err.to_string()))?;
+        }
+
+        state.error(HandshakeManagerError::MpcShootdown);
+        Ok(Some(state))
+    }
+
+    /// Record the blinder under which the local peer committed to its order's size bucket
+    pub fn set_local_size_bucket_blinder(&self, request_id: &Uuid, blinder: Scalar) {
+        if let Some(mut entry) = self.state_map.get_mut(request_id) {
+            entry.local_size_bucket_blinder = Some(blinder);
+        }
+    }
+
+    /// Record the remote peer's commitment to its order's size bucket
+    pub fn set_peer_size_bucket_commitment(&self, request_id: &Uuid, commitment: Scalar) {
+        if let Some(mut entry) = self.state_map.get_mut(request_id) {
+            entry.peer_size_bucket_commitment = Some(commitment);
+        }
+    }
+
+    /// Record the terms of the broker that arranged the handshake
+    pub fn set_broker(&self, request_id: &Uuid, fee: BrokerFee) {
+        if let Some(mut entry) = self.state_map.get_mut(request_id) {
+            entry.broker = Some(fee);
+        }
+    }
+
+    // ----------------------
+    // | Failure Accounting |
+    // ----------------------
+
+    /// Record a failed match attempt on an order pair, returning the number of
+    /// consecutive attempts on the pair that have failed
+    pub fn record_failure(
+        &self,
+        local_order_id: OrderIdentifier,
+        peer_order_id: OrderIdentifier,
+        peer_id: WrappedPeerId,
+        error: HandshakeManagerError,
+    ) -> u32 {
+        let timestamp = self.clock.unix_secs();
+
+        let mut entry = self
+            .failures
+            .entry((local_order_id, peer_order_id))
+            .or_insert_with(|| HandshakeFailure {
+                attempts: 0,
+                peer_id,
+                last_error: error.clone(),
+                timestamp,
+            });
+        entry.attempts += 1;
+        entry.peer_id = peer_id;
+        entry.last_error = error;
+        entry.timestamp = timestamp;
+
+        entry.attempts
+    }
+
+    /// Get the failure record of an order pair, if any attempt on it has failed
+    pub fn get_failure(
+        &self,
+        local_order_id: &OrderIdentifier,
+        peer_order_id: &OrderIdentifier,
+    ) -> Option<HandshakeFailure> {
+        self.failures
+            .get(&(*local_order_id, *peer_order_id))
+            .map(|failure| failure.clone())
+    }
+
+    /// Clear the failure record of an order pair, e.g. after a match on it succeeds
+    pub fn clear_failures(
+        &self,
+        local_order_id: &OrderIdentifier,
+        peer_order_id: &OrderIdentifier,
+    ) {
+        self.failures.remove(&(*local_order_id, *peer_order_id));
     }
 
     // --------------------
@@ -151,40 +305,47 @@ impl HandshakeStateIndex {
     // --------------------
 
     /// Gets the state of the given handshake
-    pub async fn get_state(&self, request_id: &Uuid) -> Option<HandshakeState> {
-        let locked_state = self.state_map.read().await;
-        locked_state.get(request_id).cloned()
+    pub fn get_state(&self, request_id: &Uuid) -> Option<HandshakeState> {
+        self.state_map
+            .get(request_id)
+            .map(|entry| entry.value().clone())
+    }
+
+    /// Gets the state of every in-flight handshake
+    pub fn list_handshakes(&self) -> Vec<HandshakeState> {
+        self.state_map
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
     }
 
     /// Transition the given handshake into the MatchInProgress state
-    pub async fn in_progress(&self, request_id: &Uuid, cancel_channel: Sender<()>) {
-        let mut locked_state = self.state_map.write().await;
-        if let Some(entry) = locked_state.get_mut(request_id) {
+    pub fn in_progress(&self, request_id: &Uuid, cancel_channel: Sender<()>) {
+        if let Some(mut entry) = self.state_map.get_mut(request_id) {
             entry.in_progress();
             entry.cancel_channel = Some(cancel_channel);
         }
     }
 
     /// Transition the given handshake into the Completed state
-    pub async fn completed(&self, request_id: &Uuid) {
-        let mut locked_state = self.state_map.write().await;
-        if let Some(entry) = locked_state.get_mut(request_id) {
+    pub fn completed(&self, request_id: &Uuid) {
+        // The entry's shard must be released before the handshake is removed below
+        if let Some(mut entry) = self.state_map.get_mut(request_id) {
             entry.completed()
         }
 
         // For now, we simply remove the handshake from the state
-        self.remove_handshake(request_id).await;
+        self.remove_handshake(request_id);
     }
 
     /// Transition the given handshake into the Error state
-    pub async fn error(&self, request_id: &Uuid, err: HandshakeManagerError) {
-        let mut locked_state = self.state_map.write().await;
-        if let Some(entry) = locked_state.get_mut(request_id) {
+    pub fn error(&self, request_id: &Uuid, err: HandshakeManagerError) {
+        if let Some(mut entry) = self.state_map.get_mut(request_id) {
             entry.error(err)
         }
 
         // For now we simply remove the handshake from the state
-        self.remove_handshake(request_id).await;
+        self.remove_handshake(request_id);
     }
 }
 
@@ -194,6 +355,8 @@ pub struct HandshakeState {
     /// The request identifier of the handshake, used to uniquely identify a handshake
     /// correspondence between peers
     pub request_id: Uuid,
+    /// The remote peer that the handshake is executed with
+    pub peer_id: WrappedPeerId,
     /// The identifier of the order that the remote peer has proposed for match
     pub peer_order_id: OrderIdentifier,
     /// The identifier of the order that the local peer has proposed for match
@@ -202,12 +365,34 @@ pub struct HandshakeState {
     pub peer_match_nullifier: Scalar,
     /// The match nullifier of the local peer's order
     pub local_match_nullifier: Scalar,
+    /// The blinder of the local peer's size bucket commitment, set when the local
+    /// peer proposed the match with a bucket check
+    pub local_size_bucket_blinder: Option<Scalar>,
+    /// The remote peer's size bucket commitment, set when the remote peer proposed
+    /// the match with a bucket check
+    pub peer_size_bucket_commitment: Option<Scalar>,
+    /// The terms of the broker that arranged the handshake, set when a third peer
+    /// brokered the match
+    pub broker: Option<BrokerFee>,
     /// The current state information of the
     pub state: State,
     /// The cancel channel that the coordinator may use to cancel MPC execution
     pub cancel_channel: Option<Sender<()>>,
 }
 
+/// A record of the failed match attempts on an order pair
+#[derive(Clone, Debug)]
+pub struct HandshakeFailure {
+    /// The number of consecutive attempts on the order pair that have failed
+    pub attempts: u32,
+    /// The peer that the most recent attempt was made with
+    pub peer_id: WrappedPeerId,
+    /// The error that ended the most recent attempt
+    pub last_error: HandshakeManagerError,
+    /// The timestamp (in seconds) of the most recent failure
+    pub timestamp: u64,
+}
+
 /// A state enumeration for the valid states a handshake may take
 #[derive(Clone, Debug)]
 pub enum State {
@@ -232,6 +417,7 @@ impl HandshakeState {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         request_id: Uuid,
+        peer_id: WrappedPeerId,
         peer_order_id: OrderIdentifier,
         local_order_id: OrderIdentifier,
         peer_match_nullifier: Scalar,
@@ -239,10 +425,14 @@ impl HandshakeState {
     ) -> Self {
         Self {
             request_id,
+            peer_id,
             peer_order_id,
             local_order_id,
             peer_match_nullifier,
             local_match_nullifier,
+            local_size_bucket_blinder: None,
+            peer_size_bucket_commitment: None,
+            broker: None,
             state: State::OrderNegotiation,
             cancel_channel: None,
         }
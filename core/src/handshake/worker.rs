@@ -2,17 +2,23 @@
 
 use std::thread::{Builder, JoinHandle};
 
-use crossbeam::channel::Sender as CrossbeamSender;
-use tokio::{
-    runtime::Builder as RuntimeBuilder,
-    sync::mpsc::{UnboundedReceiver, UnboundedSender},
-};
+use circuits::types::order::MatchConstraints;
+use tokio::runtime::Builder as RuntimeBuilder;
 use tracing::log;
 
 use crate::{
-    gossip_api::gossip::GossipOutbound,
-    handshake::manager::{HandshakeExecutor, HandshakeScheduler, HANDSHAKE_EXECUTOR_N_THREADS},
+    clock::SharedClock,
+    fee_schedule::FeeSchedule,
+    gossip_api::gossip::SharedNetworkChannel,
+    handshake::manager::{
+        HandshakeExecutor, HandshakeExecutorConfig, HandshakeScheduler,
+        HANDSHAKE_EXECUTOR_N_THREADS,
+    },
+    job_queue::{JobQueueReceiver, JobQueueSender},
+    price_reporter::jobs::PriceReporterManagerJob,
     proof_generation::jobs::ProofManagerJob,
+    rng::WorkerRng,
+    starknet_client::client::SharedStarknetApi,
     state::RelayerState,
     system_bus::SystemBus,
     types::SystemBusMessage,
@@ -20,24 +26,73 @@ use crate::{
     CancelChannel,
 };
 
-use super::{error::HandshakeManagerError, jobs::HandshakeExecutionJob, manager::HandshakeManager};
+use super::{
+    error::HandshakeManagerError, jobs::HandshakeExecutionJob, journal::SettlementJournal,
+    manager::HandshakeManager, state::HandshakeStateIndex,
+};
 
 /// The config type for the handshake manager
 #[derive(Debug)]
 pub struct HandshakeManagerConfig {
     /// The relayer-global state
     pub global_state: RelayerState,
+    /// The index of in-flight handshakes, shared with the API server so that it may
+    /// report on them
+    pub handshake_state_index: HandshakeStateIndex,
     /// The channel on which to send outbound network requests
-    pub network_channel: UnboundedSender<GossipOutbound>,
+    pub network_channel: SharedNetworkChannel,
     /// A sender on the handshake manager's job queue, used by the timer
     /// thread to enqueue outbound handshakes
-    pub job_sender: UnboundedSender<HandshakeExecutionJob>,
+    pub job_sender: JobQueueSender<HandshakeExecutionJob>,
     /// The job queue on which to receive handshake requests
-    pub job_receiver: Option<UnboundedReceiver<HandshakeExecutionJob>>,
+    pub job_receiver: Option<JobQueueReceiver<HandshakeExecutionJob>>,
+    /// The priority job queue, jobs enqueued here (e.g. MPC shootdowns) are
+    /// dequeued before any job on the standard queue
+    pub priority_job_receiver: Option<JobQueueReceiver<HandshakeExecutionJob>>,
     /// A sender to forward jobs to the proof manager on
-    pub proof_manager_sender: CrossbeamSender<ProofManagerJob>,
+    pub proof_manager_sender: JobQueueSender<ProofManagerJob>,
     /// The system bus to which all workers have access
     pub system_bus: SystemBus<SystemBusMessage>,
+    /// The amount of time a match MPC may run before it is abandoned, in milliseconds
+    pub mpc_timeout_ms: u64,
+    /// Whether to request a size bucket check when proposing a match
+    pub size_bucket_check: bool,
+    /// Whether to drop order pairs that IoIs and cluster access show cannot be matched
+    /// before proposing them
+    pub handshake_prescreen: bool,
+    /// The maximum number of match MPCs run at once across all peers
+    pub max_concurrent_mpcs: usize,
+    /// The maximum number of match MPCs run at once against a single peer
+    pub max_concurrent_mpcs_per_peer: usize,
+    /// The highest fee the manager pays to settle a match, `None` if unbounded
+    pub max_settlement_fee: Option<u64>,
+    /// The schedule of protocol fees charged on matches
+    pub fee_schedule: FeeSchedule,
+    /// The share of the managing relayers' fees, in basis points, that the manager
+    /// charges to broker handshakes between foreign orders; `None` if it does not broker
+    pub broker_fee_bps: Option<u16>,
+    /// The largest share of the local relayer fee, in basis points, paid to a broker
+    pub max_broker_fee_bps: u16,
+    /// The minimum fill size of local orders that do not set their own, `None` if unbounded
+    pub default_min_fill_size: Option<u64>,
+    /// The maximum slippage from the median price, in basis points, of local orders that
+    /// do not set their own, `None` if unbounded
+    pub default_max_slippage_bps: Option<u16>,
+    /// The work queue of the price reporter manager, from which the median prices that
+    /// price protection bands are drawn around are peeked
+    pub price_reporter_work_queue: JobQueueSender<PriceReporterManagerJob>,
+    /// The client used to submit settlements to the contract
+    pub starknet_client: SharedStarknetApi,
+    /// The seed for the manager's randomness; honored only in test builds so that
+    /// handshakes may be replayed exactly
+    pub rng_seed: Option<u64>,
+    /// The file that pending settlements are journaled to, if any
+    pub settlement_journal_file: Option<String>,
+    /// The file that completed order pairs in the handshake cache are persisted to, if any
+    pub handshake_cache_file: Option<String>,
+    /// The clock the handshake interval, invisibility windows, and failure records are
+    /// measured against
+    pub clock: SharedClock,
     /// The channel on which the coordinator may mandate that the
     /// handshake manager cancel its execution
     pub(crate) cancel_channel: CancelChannel,
@@ -49,19 +104,44 @@ impl Worker for HandshakeManager {
 
     fn new(mut config: Self::WorkerConfig) -> Result<Self, Self::Error> {
         // Start a timer thread, periodically asks workers to begin handshakes with peers
+        let rng = WorkerRng::new(config.rng_seed);
         let scheduler = HandshakeScheduler::new(
             config.job_sender.clone(),
             config.global_state.clone(),
+            config.broker_fee_bps.is_some(),
+            rng.fork(),
+            config.clock.clone(),
             config.cancel_channel.clone(),
         );
-        let executor = HandshakeExecutor::new(
-            config.job_receiver.take().unwrap(),
-            config.network_channel.clone(),
-            config.proof_manager_sender.clone(),
-            config.global_state.clone(),
-            config.system_bus.clone(),
-            config.cancel_channel.clone(),
-        )?;
+        let executor = HandshakeExecutor::new(HandshakeExecutorConfig {
+            job_channel: config.job_receiver.take().unwrap(),
+            priority_job_channel: config.priority_job_receiver.take().unwrap(),
+            network_channel: config.network_channel.clone(),
+            proof_manager_work_queue: config.proof_manager_sender.clone(),
+            global_state: config.global_state.clone(),
+            handshake_state_index: config.handshake_state_index.clone(),
+            system_bus: config.system_bus.clone(),
+            mpc_timeout_ms: config.mpc_timeout_ms,
+            size_bucket_check: config.size_bucket_check,
+            handshake_prescreen: config.handshake_prescreen,
+            max_concurrent_mpcs: config.max_concurrent_mpcs,
+            max_concurrent_mpcs_per_peer: config.max_concurrent_mpcs_per_peer,
+            max_settlement_fee: config.max_settlement_fee,
+            fee_schedule: config.fee_schedule.clone(),
+            broker_fee_bps: config.broker_fee_bps,
+            max_broker_fee_bps: config.max_broker_fee_bps,
+            default_match_constraints: MatchConstraints {
+                min_fill_size: config.default_min_fill_size,
+                max_slippage_bps: config.default_max_slippage_bps,
+            },
+            price_reporter_work_queue: config.price_reporter_work_queue.clone(),
+            starknet_client: config.starknet_client.clone(),
+            rng,
+            settlement_journal: SettlementJournal::open(config.settlement_journal_file.clone())?,
+            handshake_cache_file: config.handshake_cache_file.clone(),
+            clock: config.clock.clone(),
+            cancel: config.cancel_channel.clone(),
+        })?;
 
         Ok(HandshakeManager {
             config,
@@ -92,13 +172,16 @@ impl Worker for HandshakeManager {
 
         // Spawn both the executor and the scheduler in a thread
         let executor = self.executor.take().unwrap();
+        // Every permitted MPC must have a blocking thread to run on, otherwise it would
+        // spend its timeout waiting for one
+        let n_blocking_threads = HANDSHAKE_EXECUTOR_N_THREADS.max(self.config.max_concurrent_mpcs);
         let executor_handle = Builder::new()
             .name("handshake-executor-main".to_string())
             .spawn(move || {
                 // Build a Tokio runtime for the handshake manager
                 let runtime = RuntimeBuilder::new_multi_thread()
                     .enable_all()
-                    .max_blocking_threads(HANDSHAKE_EXECUTOR_N_THREADS)
+                    .max_blocking_threads(n_blocking_threads)
                     .build()
                     .unwrap();
 
@@ -0,0 +1,500 @@
+//! Bounded job queues between workers
+//!
+//! Each queue holds at most its capacity of jobs. A job enqueued on a full queue is handled
+//! by the job's overflow policy; it is either dropped, for jobs that are safe to lose, or
+//! returned to the sender. A stuck worker thereby applies backpressure to the workers that
+//! feed it, rather than growing its backlog without bound
+//!
+//! Each queue tracks its depth and the jobs it has turned away, which the
+//! `JobQueueMonitor` periodically publishes on the system bus
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{
+    self,
+    error::{TryRecvError, TrySendError},
+    Receiver, Sender,
+};
+use tracing::log;
+
+use crate::{
+    system_bus::SystemBus,
+    types::{SystemBusMessage, JOB_QUEUE_METRICS_TOPIC},
+};
+
+/// The default capacity of a job queue
+pub const DEFAULT_JOB_QUEUE_CAPACITY: usize = 1_024;
+/// The interval at which job queue metrics are published on the system bus
+const JOB_QUEUE_METRICS_INTERVAL_MS: u64 = 10_000; // 10 seconds
+
+/// The name of the network manager's outbound queue
+pub const NETWORK_QUEUE: &str = "network";
+/// The name of the gossip server's job queue
+pub const GOSSIP_QUEUE: &str = "gossip";
+/// The name of the handshake manager's job queue
+pub const HANDSHAKE_QUEUE: &str = "handshake";
+/// The name of the handshake manager's priority job queue
+pub const HANDSHAKE_PRIORITY_QUEUE: &str = "handshake-priority";
+/// The name of the price reporter manager's job queue
+pub const PRICE_REPORTER_QUEUE: &str = "price-reporter";
+/// The name of the proof manager's job queue
+pub const PROOF_MANAGER_QUEUE: &str = "proof-manager";
+
+/// The queues whose capacity may be configured
+const CONFIGURABLE_QUEUES: [&str; 6] = [
+    NETWORK_QUEUE,
+    GOSSIP_QUEUE,
+    HANDSHAKE_QUEUE,
+    HANDSHAKE_PRIORITY_QUEUE,
+    PRICE_REPORTER_QUEUE,
+    PROOF_MANAGER_QUEUE,
+];
+
+// -----------
+// | Configs |
+// -----------
+
+/// What a full queue does with a job enqueued on it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobOverflowPolicy {
+    /// Drop the job; for jobs that are safe to lose, e.g. gossip that is re-sent on the
+    /// next heartbeat
+    Drop,
+    /// Return the job to the sender as an error; for jobs whose sender must learn that
+    /// they were not enqueued, e.g. those made on behalf of an API request
+    Reject,
+}
+
+/// A job that may be enqueued on a bounded job queue
+pub trait BoundedJob {
+    /// What a full queue does with the job
+    fn overflow_policy(&self) -> JobOverflowPolicy;
+}
+
+/// The capacity of each job queue, keyed by queue name
+#[derive(Clone, Debug, Default)]
+pub struct JobQueueCapacities(HashMap<String, usize>);
+
+impl JobQueueCapacities {
+    /// The capacity of the named queue
+    pub fn capacity(&self, queue: &str) -> usize {
+        self.0
+            .get(queue)
+            .copied()
+            .unwrap_or(DEFAULT_JOB_QUEUE_CAPACITY)
+    }
+
+    /// Set the capacity of the named queue
+    pub fn set_capacity(&mut self, queue: &str, capacity: usize) -> Result<(), String> {
+        if !CONFIGURABLE_QUEUES.contains(&queue) {
+            return Err(format!(
+                "unknown job queue {queue}, expected one of {}",
+                CONFIGURABLE_QUEUES.join(", ")
+            ));
+        }
+        if capacity == 0 {
+            return Err(format!("capacity of job queue {queue} must be positive"));
+        }
+
+        self.0.insert(queue.to_string(), capacity);
+        Ok(())
+    }
+}
+
+// ----------
+// | Errors |
+// ----------
+
+/// The error returned when a job is not enqueued, holding the job
+pub enum JobQueueError<T> {
+    /// The queue is full and the job's overflow policy rejects it
+    Full(T),
+    /// The receiving worker has shut down
+    Closed(T),
+}
+
+impl<T> JobQueueError<T> {
+    /// Take the job that was not enqueued
+    pub fn into_job(self) -> T {
+        match self {
+            JobQueueError::Full(job) | JobQueueError::Closed(job) => job,
+        }
+    }
+}
+
+impl<T> Debug for JobQueueError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            JobQueueError::Full(_) => write!(f, "Full(..)"),
+            JobQueueError::Closed(_) => write!(f, "Closed(..)"),
+        }
+    }
+}
+
+impl<T> Display for JobQueueError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            JobQueueError::Full(_) => write!(f, "job queue is full"),
+            JobQueueError::Closed(_) => write!(f, "job queue is closed"),
+        }
+    }
+}
+impl<T> Error for JobQueueError<T> {}
+
+// -----------
+// | Metrics |
+// -----------
+
+/// The counters a queue's senders and receiver update
+#[derive(Debug, Default)]
+struct JobQueueCounters {
+    /// The number of jobs in the queue
+    depth: AtomicUsize,
+    /// The greatest depth the queue has reached
+    high_water: AtomicUsize,
+    /// The number of jobs dropped because the queue was full
+    dropped: AtomicU64,
+    /// The number of jobs returned to their sender because the queue was full
+    rejected: AtomicU64,
+}
+
+/// A snapshot of the depth of a job queue and of the jobs it has turned away
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobQueueMetrics {
+    /// The name of the queue
+    pub queue: String,
+    /// The capacity of the queue
+    pub capacity: usize,
+    /// The number of jobs in the queue
+    pub depth: usize,
+    /// The greatest depth the queue has reached
+    pub high_water: usize,
+    /// The number of jobs dropped because the queue was full
+    pub dropped: u64,
+    /// The number of jobs returned to their sender because the queue was full
+    pub rejected: u64,
+}
+
+/// A handle on a job queue's counters, from which its metrics are read
+#[derive(Clone, Debug)]
+pub struct JobQueueStats {
+    /// The name of the queue
+    name: String,
+    /// The capacity of the queue
+    capacity: usize,
+    /// The queue's counters
+    counters: Arc<JobQueueCounters>,
+}
+
+impl JobQueueStats {
+    /// Read the queue's metrics
+    pub fn metrics(&self) -> JobQueueMetrics {
+        JobQueueMetrics {
+            queue: self.name.clone(),
+            capacity: self.capacity,
+            depth: self.counters.depth.load(Ordering::Relaxed),
+            high_water: self.counters.high_water.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Periodically publishes the metrics of a set of job queues on the system bus
+#[derive(Clone, Debug)]
+pub struct JobQueueMonitor {
+    /// The queues to publish the metrics of
+    queues: Vec<JobQueueStats>,
+}
+
+impl JobQueueMonitor {
+    /// Constructor
+    pub fn new(queues: Vec<JobQueueStats>) -> Self {
+        Self { queues }
+    }
+
+    /// Read the metrics of every queue
+    pub fn metrics(&self) -> Vec<JobQueueMetrics> {
+        self.queues.iter().map(JobQueueStats::metrics).collect()
+    }
+
+    /// Publish the metrics of every queue on an interval, until the relayer exits
+    pub async fn run(self, system_bus: SystemBus<SystemBusMessage>) {
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(JOB_QUEUE_METRICS_INTERVAL_MS));
+        loop {
+            interval.tick().await;
+            system_bus.publish(
+                JOB_QUEUE_METRICS_TOPIC.to_string(),
+                SystemBusMessage::JobQueueMetrics {
+                    queues: self.metrics(),
+                },
+            );
+        }
+    }
+}
+
+// ----------
+// | Queues |
+// ----------
+
+/// Create a bounded job queue with the given name and capacity
+pub fn job_queue<T>(name: &str, capacity: usize) -> (JobQueueSender<T>, JobQueueReceiver<T>) {
+    let (sender, receiver) = mpsc::channel(capacity);
+    let stats = JobQueueStats {
+        name: name.to_string(),
+        capacity,
+        counters: Arc::new(JobQueueCounters::default()),
+    };
+
+    (
+        JobQueueSender {
+            sender,
+            stats: stats.clone(),
+        },
+        JobQueueReceiver { receiver, stats },
+    )
+}
+
+/// The sending half of a job queue
+///
+/// Sending never blocks; a job enqueued on a full queue is handled by its overflow policy
+pub struct JobQueueSender<T> {
+    /// The underlying channel
+    sender: Sender<T>,
+    /// The queue's counters
+    stats: JobQueueStats,
+}
+
+impl<T> Clone for JobQueueSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+impl<T> Debug for JobQueueSender<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "JobQueueSender({})", self.stats.name)
+    }
+}
+
+impl<T> JobQueueSender<T> {
+    /// A handle on the queue's counters
+    pub fn stats(&self) -> JobQueueStats {
+        self.stats.clone()
+    }
+
+    /// Enqueue a job, waiting for room if the queue is full rather than applying the job's
+    /// overflow policy; for producers that must not lose a job and can wait for the
+    /// receiver to catch up
+    ///
+    /// Errors only if the receiving worker has shut down
+    pub async fn send_waiting(&self, job: T) -> Result<(), JobQueueError<T>> {
+        let permit = match self.sender.reserve().await {
+            Ok(permit) => permit,
+            Err(_) => return Err(JobQueueError::Closed(job)),
+        };
+
+        let counters = &self.stats.counters;
+        let depth = counters.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        permit.send(job);
+        counters.high_water.fetch_max(depth, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl<T: BoundedJob> JobQueueSender<T> {
+    /// Enqueue a job
+    ///
+    /// Returns `Ok` if the job was enqueued, or was dropped under its overflow policy
+    pub fn send(&self, job: T) -> Result<(), JobQueueError<T>> {
+        // The depth is raised before the job is visible to the receiver, so that the
+        // receiver never lowers it below zero
+        let counters = &self.stats.counters;
+        let depth = counters.depth.fetch_add(1, Ordering::Relaxed) + 1;
+
+        match self.sender.try_send(job) {
+            Ok(()) => {
+                counters.high_water.fetch_max(depth, Ordering::Relaxed);
+                Ok(())
+            }
+
+            Err(TrySendError::Full(job)) => {
+                counters.depth.fetch_sub(1, Ordering::Relaxed);
+                match job.overflow_policy() {
+                    JobOverflowPolicy::Drop => {
+                        // Only the first drop is logged, the count is published thereafter
+                        if counters.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                            log::warn!("job queue {} is full, dropping jobs", self.stats.name);
+                        }
+                        Ok(())
+                    }
+                    JobOverflowPolicy::Reject => {
+                        counters.rejected.fetch_add(1, Ordering::Relaxed);
+                        Err(JobQueueError::Full(job))
+                    }
+                }
+            }
+
+            Err(TrySendError::Closed(job)) => {
+                counters.depth.fetch_sub(1, Ordering::Relaxed);
+                Err(JobQueueError::Closed(job))
+            }
+        }
+    }
+}
+
+/// The receiving half of a job queue
+pub struct JobQueueReceiver<T> {
+    /// The underlying channel
+    receiver: Receiver<T>,
+    /// The queue's counters
+    stats: JobQueueStats,
+}
+
+impl<T> Debug for JobQueueReceiver<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "JobQueueReceiver({})", self.stats.name)
+    }
+}
+
+impl<T> JobQueueReceiver<T> {
+    /// Dequeue the next job, or `None` once every sender has been dropped
+    pub async fn recv(&mut self) -> Option<T> {
+        let job = self.receiver.recv().await;
+        if job.is_some() {
+            self.stats.counters.depth.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        job
+    }
+
+    /// Dequeue the next job, blocking the calling thread until one arrives; returns `None`
+    /// once every sender has been dropped
+    ///
+    /// For workers that receive on a dedicated thread outside of a tokio runtime
+    pub fn blocking_recv(&mut self) -> Option<T> {
+        let job = self.receiver.blocking_recv();
+        if job.is_some() {
+            self.stats.counters.depth.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        job
+    }
+
+    /// Dequeue the next job if one is ready
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let job = self.receiver.try_recv()?;
+        self.stats.counters.depth.fetch_sub(1, Ordering::Relaxed);
+
+        Ok(job)
+    }
+
+    /// Close the queue to new jobs, jobs already enqueued may still be dequeued
+    pub fn close(&mut self) {
+        self.receiver.close()
+    }
+
+    /// A handle on the queue's counters
+    pub fn stats(&self) -> JobQueueStats {
+        self.stats.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{job_queue, BoundedJob, JobOverflowPolicy, JobQueueCapacities, JobQueueError};
+
+    /// A job with a fixed overflow policy
+    struct TestJob(JobOverflowPolicy);
+
+    impl BoundedJob for TestJob {
+        fn overflow_policy(&self) -> JobOverflowPolicy {
+            self.0
+        }
+    }
+
+    /// Tests that a full queue drops or rejects jobs by their policy, and that the
+    /// queue's metrics track its depth and the jobs it turned away
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let (sender, mut receiver) = job_queue("test", 2 /* capacity */);
+        for _ in 0..2 {
+            sender.send(TestJob(JobOverflowPolicy::Reject)).unwrap();
+        }
+
+        assert!(sender.send(TestJob(JobOverflowPolicy::Drop)).is_ok());
+        assert!(matches!(
+            sender.send(TestJob(JobOverflowPolicy::Reject)),
+            Err(JobQueueError::Full(_))
+        ));
+
+        let metrics = sender.stats().metrics();
+        assert_eq!(metrics.depth, 2);
+        assert_eq!(metrics.high_water, 2);
+        assert_eq!((metrics.dropped, metrics.rejected), (1, 1));
+
+        // Draining the queue makes room for new jobs
+        receiver.recv().await.unwrap();
+        assert_eq!(receiver.stats().metrics().depth, 1);
+        sender.send(TestJob(JobOverflowPolicy::Reject)).unwrap();
+
+        drop(receiver);
+        assert!(matches!(
+            sender.send(TestJob(JobOverflowPolicy::Drop)),
+            Err(JobQueueError::Closed(_))
+        ));
+    }
+
+    /// Tests that a job sent while the queue is full waits for room instead of being
+    /// dropped, and is enqueued once the receiver takes a job
+    #[tokio::test]
+    async fn test_send_waiting() {
+        let (sender, mut receiver) = job_queue("test", 1 /* capacity */);
+        sender.send(TestJob(JobOverflowPolicy::Drop)).unwrap();
+
+        let waiting_sender = sender.clone();
+        let waiting = tokio::spawn(async move {
+            waiting_sender
+                .send_waiting(TestJob(JobOverflowPolicy::Drop))
+                .await
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        assert_eq!(sender.stats().metrics().dropped, 0);
+
+        receiver.recv().await.unwrap();
+        assert!(waiting.await.unwrap().is_ok());
+        assert!(receiver.recv().await.is_some());
+        assert_eq!(receiver.stats().metrics().depth, 0);
+    }
+
+    /// Tests that only the capacities of known queues may be set, and only to a positive
+    /// capacity
+    #[test]
+    fn test_set_capacities() {
+        let mut capacities = JobQueueCapacities::default();
+        capacities.set_capacity("network", 4096).unwrap();
+        assert_eq!(capacities.capacity("network"), 4096);
+        assert_eq!(
+            capacities.capacity("gossip"),
+            super::DEFAULT_JOB_QUEUE_CAPACITY
+        );
+
+        assert!(capacities.set_capacity("unknown", 16).is_err());
+        assert!(capacities.set_capacity("gossip", 0).is_err());
+    }
+}
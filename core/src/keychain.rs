@@ -0,0 +1,382 @@
+//! Manages the key hierarchy of a wallet
+//!
+//! A wallet holds four keypairs, each secret key authorizing a narrower set of operations
+//! than the last: `sk_root` authorizes updates to the wallet, `sk_match` matching its orders,
+//! `sk_settle` settling its matches, and `sk_view` decrypting its state on-chain. Each public
+//! key is the Poseidon hash of its secret key, which is what the circuits check a witness'
+//! secret key against.
+//!
+//! `sk_view` also keys the encryption of the wallet posted on-chain with each update, from
+//! which a wallet may be recovered if the relayer's state is lost, see `crate::recovery`
+//!
+//! A relayer usually holds every key but `sk_root`. Root operations on such a wallet may
+//! instead be authorized by an external signer, e.g. a hardware wallet or signing service,
+//! that holds `sk_root` and computes authorizations on the relayer's behalf
+
+use std::{
+    fmt::{self, Debug, Display},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use circuits::{native_helpers::compute_poseidon_hash, types::keychain::KeyChain};
+use crypto::fields::{biguint_to_scalar, scalar_to_biguint};
+use curve25519_dalek::scalar::Scalar;
+use num_bigint::BigUint;
+use rand_core::{CryptoRng, RngCore};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::state::wallet::{PrivateKeyChain, Wallet, WalletIdentifier};
+
+/// The domain separator of `sk_root` in seed derivation
+const ROOT_KEY_DOMAIN: u64 = 0;
+/// The domain separator of `sk_match` in seed derivation
+const MATCH_KEY_DOMAIN: u64 = 1;
+/// The domain separator of `sk_settle` in seed derivation
+const SETTLE_KEY_DOMAIN: u64 = 2;
+/// The domain separator of `sk_view` in seed derivation
+const VIEW_KEY_DOMAIN: u64 = 3;
+
+/// An error deriving, checking, or using a wallet's keys
+#[derive(Clone, Debug)]
+pub enum KeychainError {
+    /// A root operation was requested on a wallet whose root key is neither held
+    /// locally nor by an external signer
+    NoRootKey,
+    /// A secret key does not derive the wallet's corresponding public key
+    KeyMismatch(String),
+    /// The external signer failed to authorize a root operation
+    ExternalSigner(String),
+}
+
+impl Display for KeychainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// --------------
+// | Derivation |
+// --------------
+
+/// Derive the public key of a secret key
+pub fn derive_public_key(secret_key: Scalar) -> Scalar {
+    compute_poseidon_hash(&[secret_key])
+}
+
+/// Derive a wallet's key hierarchy from a seed
+///
+/// Each secret key is the hash of the seed under the key's domain separator, so that no
+/// key reveals any other
+pub fn derive_keychain(seed: Scalar) -> (KeyChain, PrivateKeyChain) {
+    let derive_secret = |domain: u64| compute_poseidon_hash(&[seed, Scalar::from(domain)]);
+    let secret_keys = PrivateKeyChain {
+        sk_root: Some(derive_secret(ROOT_KEY_DOMAIN)),
+        sk_match: derive_secret(MATCH_KEY_DOMAIN),
+        sk_settle: derive_secret(SETTLE_KEY_DOMAIN),
+        sk_view: derive_secret(VIEW_KEY_DOMAIN),
+    };
+    let public_keys = KeyChain {
+        pk_root: derive_public_key(secret_keys.sk_root.unwrap()),
+        pk_match: derive_public_key(secret_keys.sk_match),
+        pk_settle: derive_public_key(secret_keys.sk_settle),
+        pk_view: derive_public_key(secret_keys.sk_view),
+    };
+
+    (public_keys, secret_keys)
+}
+
+/// Check that each secret key held derives the corresponding public key
+pub fn verify_keychain(
+    public_keys: &KeyChain,
+    secret_keys: &PrivateKeyChain,
+) -> Result<(), KeychainError> {
+    let mut pairs = vec![
+        ("match", secret_keys.sk_match, public_keys.pk_match),
+        ("settle", secret_keys.sk_settle, public_keys.pk_settle),
+        ("view", secret_keys.sk_view, public_keys.pk_view),
+    ];
+    if let Some(sk_root) = secret_keys.sk_root {
+        pairs.push(("root", sk_root, public_keys.pk_root));
+    }
+
+    for (name, secret_key, public_key) in pairs.into_iter() {
+        if derive_public_key(secret_key) != public_key {
+            return Err(KeychainError::KeyMismatch(format!(
+                "sk_{name} does not derive pk_{name}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// --------------
+// | Key Access |
+// --------------
+
+/// The match key of a wallet, as used in a witness to `VALID COMMITMENTS`
+///
+/// The key is checked against `pk_match`, a witness built with any other key would not
+/// satisfy the circuit
+pub fn match_key(wallet: &Wallet) -> Result<Scalar, KeychainError> {
+    let sk_match = wallet.secret_keys.sk_match;
+    if derive_public_key(sk_match) != wallet.public_keys.pk_match {
+        return Err(KeychainError::KeyMismatch(
+            "sk_match does not derive pk_match".to_string(),
+        ));
+    }
+
+    Ok(sk_match)
+}
+
+/// The key that a wallet's notes are encrypted to on settlement
+pub fn settle_key(wallet: &Wallet) -> Scalar {
+    wallet.public_keys.pk_settle
+}
+
+// ------------
+// | View Key |
+// ------------
+
+/// A sequence of scalars encrypted under a wallet's view key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalletCiphertext {
+    /// The nonce the keystream was derived under, drawn fresh for each ciphertext
+    pub nonce: Scalar,
+    /// The encrypted scalars
+    pub blocks: Vec<Scalar>,
+}
+
+/// The keystream scalar that masks the plaintext scalar at the given position
+///
+/// The keystream is keyed by `sk_view` rather than `pk_view`, which is public
+fn view_keystream(sk_view: Scalar, nonce: Scalar, position: usize) -> Scalar {
+    compute_poseidon_hash(&[sk_view, nonce, Scalar::from(position as u64)])
+}
+
+/// Encrypt a sequence of scalars under a view key
+pub fn encrypt_under_view_key<R: RngCore + CryptoRng>(
+    sk_view: Scalar,
+    plaintext: &[Scalar],
+    rng: &mut R,
+) -> WalletCiphertext {
+    let nonce = Scalar::random(rng);
+    let blocks = plaintext
+        .iter()
+        .enumerate()
+        .map(|(i, value)| value + view_keystream(sk_view, nonce, i))
+        .collect();
+
+    WalletCiphertext { nonce, blocks }
+}
+
+/// Decrypt a sequence of scalars under a view key
+///
+/// Decryption under the wrong key does not fail, it yields unrelated scalars; callers
+/// must check the plaintext against something the key commits to
+pub fn decrypt_under_view_key(sk_view: Scalar, ciphertext: &WalletCiphertext) -> Vec<Scalar> {
+    ciphertext
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| block - view_keystream(sk_view, ciphertext.nonce, i))
+        .collect()
+}
+
+// -------------------
+// | Root Operations |
+// -------------------
+
+/// Compute the authorization of a root operation from the root key
+///
+/// An authorization is the hash of the root key, the version of the wallet the operation
+/// applies to, and the operation's payload
+pub fn root_authorization(sk_root: Scalar, version: u64, payload: &[Scalar]) -> Scalar {
+    let mut preimage = vec![sk_root, Scalar::from(version)];
+    preimage.extend_from_slice(payload);
+    compute_poseidon_hash(&preimage)
+}
+
+/// A signer holding the root keys of wallets outside of the relayer
+#[async_trait]
+pub trait ExternalRootSigner: Debug + Send + Sync {
+    /// Compute the authorization of a root operation on the given wallet, see
+    /// `root_authorization`
+    async fn authorize(
+        &self,
+        wallet_id: WalletIdentifier,
+        pk_root: Scalar,
+        version: u64,
+        payload: &[Scalar],
+    ) -> Result<Scalar, KeychainError>;
+}
+
+/// The request sent to an HTTP signing service
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RootAuthorizationRequest {
+    /// The wallet the operation applies to
+    pub wallet_id: WalletIdentifier,
+    /// The root public key of the wallet, identifying the key to sign with
+    pub pk_root: BigUint,
+    /// The version of the wallet the operation applies to
+    pub version: u64,
+    /// The payload of the operation
+    pub payload: Vec<BigUint>,
+}
+
+/// The response from an HTTP signing service
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RootAuthorizationResponse {
+    /// The authorization of the operation
+    pub auth: BigUint,
+}
+
+/// An external signer reached over HTTP, e.g. a signing service fronting a hardware wallet
+///
+/// Each authorization is a `POST` of a `RootAuthorizationRequest` to the signer's URL
+#[derive(Clone, Debug)]
+pub struct HttpRootSigner {
+    /// The URL of the signing service
+    url: Url,
+    /// The client used to reach the signing service
+    client: reqwest::Client,
+}
+
+impl HttpRootSigner {
+    /// Constructor
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ExternalRootSigner for HttpRootSigner {
+    async fn authorize(
+        &self,
+        wallet_id: WalletIdentifier,
+        pk_root: Scalar,
+        version: u64,
+        payload: &[Scalar],
+    ) -> Result<Scalar, KeychainError> {
+        let req = RootAuthorizationRequest {
+            wallet_id,
+            pk_root: scalar_to_biguint(&pk_root),
+            version,
+            payload: payload.iter().map(scalar_to_biguint).collect(),
+        };
+        let resp: RootAuthorizationResponse = self
+            .client
+            .post(self.url.clone())
+            .json(&req)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|err| KeychainError::ExternalSigner(err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| KeychainError::ExternalSigner(err.to_string()))?;
+
+        Ok(biguint_to_scalar(&resp.auth))
+    }
+}
+
+/// Authorizes root operations on the relayer's wallets, with the root key if the relayer
+/// holds it and with the external signer otherwise
+#[derive(Clone, Debug, Default)]
+pub struct RootKeyManager {
+    /// The signer holding the root keys the relayer does not
+    external_signer: Option<Arc<dyn ExternalRootSigner>>,
+}
+
+impl RootKeyManager {
+    /// Constructor
+    pub fn new(external_signer: Option<Arc<dyn ExternalRootSigner>>) -> Self {
+        Self { external_signer }
+    }
+
+    /// Compute the authorization of a root operation on a wallet at its current version
+    pub async fn authorize(
+        &self,
+        wallet: &Wallet,
+        payload: &[Scalar],
+    ) -> Result<Scalar, KeychainError> {
+        let version = wallet.metadata.version;
+        if let Some(sk_root) = wallet.secret_keys.sk_root {
+            return Ok(root_authorization(sk_root, version, payload));
+        }
+
+        match self.external_signer.as_ref() {
+            Some(signer) => {
+                signer
+                    .authorize(
+                        wallet.wallet_id,
+                        wallet.public_keys.pk_root,
+                        version,
+                        payload,
+                    )
+                    .await
+            }
+            None => Err(KeychainError::NoRootKey),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+    use rand_core::OsRng;
+
+    use super::{
+        decrypt_under_view_key, derive_keychain, derive_public_key, encrypt_under_view_key,
+        verify_keychain, KeychainError,
+    };
+
+    /// Tests that a derived keychain verifies, and that a swapped secret key does not
+    #[test]
+    fn test_derive_and_verify() {
+        let mut rng = OsRng {};
+        let (public_keys, mut secret_keys) = derive_keychain(Scalar::random(&mut rng));
+        assert!(verify_keychain(&public_keys, &secret_keys).is_ok());
+        assert_eq!(
+            derive_public_key(secret_keys.sk_root.unwrap()),
+            public_keys.pk_root
+        );
+
+        // A relayer need not hold the root key
+        secret_keys.sk_root = None;
+        assert!(verify_keychain(&public_keys, &secret_keys).is_ok());
+
+        secret_keys.sk_settle = secret_keys.sk_view;
+        assert!(matches!(
+            verify_keychain(&public_keys, &secret_keys),
+            Err(KeychainError::KeyMismatch(_))
+        ));
+    }
+
+    /// Tests that a ciphertext decrypts under the view key it was encrypted to, and not
+    /// under another
+    #[test]
+    fn test_view_key_encryption() {
+        let mut rng = OsRng {};
+        let (_, secret_keys) = derive_keychain(Scalar::random(&mut rng));
+        let plaintext = (0..10)
+            .map(|_| Scalar::random(&mut rng))
+            .collect::<Vec<_>>();
+
+        let ciphertext = encrypt_under_view_key(secret_keys.sk_view, &plaintext, &mut rng);
+        assert_ne!(ciphertext.blocks, plaintext);
+        assert_eq!(
+            decrypt_under_view_key(secret_keys.sk_view, &ciphertext),
+            plaintext
+        );
+        assert_ne!(
+            decrypt_under_view_key(secret_keys.sk_match, &ciphertext),
+            plaintext
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! The relayer's workers and the types they exchange
+//!
+//! The relayer binary in `main.rs` allocates and coordinates the workers defined here;
+//! the library target exists so that other targets, e.g. the fuzz targets in `core/fuzz`,
+//! can exercise the relayer's types directly
+#![feature(let_chains)]
+#![feature(generic_const_exprs)]
+#![feature(const_likely)]
+#![allow(incomplete_features)]
+#![deny(unsafe_code)]
+#![deny(clippy::missing_docs_in_private_items)]
+
+pub mod api_server;
+pub mod chain_events;
+pub mod clock;
+pub mod config;
+pub mod default_wrapper;
+pub mod error;
+pub mod external_api;
+pub mod fee_schedule;
+pub mod gossip;
+pub mod gossip_api;
+pub mod handshake;
+pub mod job_queue;
+pub mod keychain;
+pub mod logging;
+pub mod network_manager;
+pub mod price_reporter;
+pub mod proof_generation;
+pub mod recovery;
+pub mod rng;
+pub mod simulation;
+pub mod starknet_client;
+pub mod state;
+pub mod system_bus;
+pub mod types;
+pub mod worker;
+
+use circuits::types::wallet::Wallet;
+use tokio::sync::watch::Receiver as WatchReceiver;
+
+#[macro_use]
+extern crate lazy_static;
+
+/// A type alias for an empty channel used to signal cancellation to workers
+pub(crate) type CancelChannel = WatchReceiver<()>;
+
+// --------------------
+// | Global Constants |
+// --------------------
+
+/// The system-wide value of MAX_BALANCES; the number of allowable balances a wallet holds
+pub(crate) const MAX_BALANCES: usize = 5;
+/// The system-wide value of MAX_ORDERS; the number of allowable orders a wallet holds
+pub(crate) const MAX_ORDERS: usize = 5;
+/// The system-wide value of MAX_FEES; the number of allowable fees a wallet holds
+pub(crate) const MAX_FEES: usize = 2;
+/// The height of the Merkle state tree used by the contract
+pub(crate) const MERKLE_HEIGHT: usize = 32;
+/// The number of historical roots the contract stores as being valid
+pub(crate) const MERKLE_ROOT_HISTORY_LENGTH: usize = 30;
+/// A type wrapper around the wallet type that adds the default generics above
+pub(crate) type SizedWallet = Wallet<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
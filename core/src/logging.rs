@@ -0,0 +1,157 @@
+//! Configures the relayer's log capture
+//!
+//! Logs are emitted through the `log` macros re-exported by `tracing` and collected by a
+//! `tracing-subscriber` registry, which writes them to stdout as either plain text or JSON.
+//! The level filter is held behind a reload layer so that it may be changed at runtime
+//! through the admin API without restarting the relayer
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+use tracing_log::LogTracer;
+use tracing_subscriber::{filter::EnvFilter, fmt, layer::SubscriberExt, reload, Layer, Registry};
+
+/// The filter applied when neither the config nor the environment sets one
+pub const DEFAULT_LOG_FILTER: &str = "info";
+/// The environment variable the filter is read from if the config does not set one
+pub const LOG_FILTER_ENV_VAR: &str = "RUST_LOG";
+
+/// The error type emitted when configuring log capture
+#[derive(Clone, Debug)]
+pub enum LoggingError {
+    /// The filter directives could not be parsed
+    InvalidFilter(String),
+    /// The log capture could not be installed, e.g. because another logger already was
+    Setup(String),
+}
+
+impl Display for LoggingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The format logs are written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogFormat {
+    /// Human readable lines of text
+    Text,
+    /// One JSON object per line, for log aggregation
+    Json,
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+/// The configuration of the relayer's log capture
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogConfig {
+    /// The format logs are written in
+    pub format: LogFormat,
+    /// The filter directives, e.g. `info,darkpool_relayer::gossip=debug`
+    pub filter: String,
+}
+
+impl LogConfig {
+    /// Validate the filter directives, returning the parsed filter
+    pub fn parse_filter(filter: &str) -> Result<EnvFilter, LoggingError> {
+        EnvFilter::try_new(filter).map_err(|err| LoggingError::InvalidFilter(err.to_string()))
+    }
+}
+
+/// A handle on the level filter of the installed log capture, through which the filter
+/// may be read and replaced at runtime
+#[derive(Clone, Debug)]
+pub struct LogFilterHandle {
+    /// The handle on the reload layer wrapping the filter
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// The directives the current filter was parsed from
+    ///
+    /// `EnvFilter` does not round trip through its `Display` implementation exactly, so the
+    /// directives are kept to report back as they were given
+    directives: Arc<RwLock<String>>,
+}
+
+impl LogFilterHandle {
+    /// The directives of the current filter
+    pub fn filter(&self) -> String {
+        self.directives.read().unwrap().clone()
+    }
+
+    /// Replace the current filter, leaving it unchanged if the directives do not parse
+    pub fn set_filter(&self, directives: &str) -> Result<(), LoggingError> {
+        let filter = LogConfig::parse_filter(directives)?;
+        self.handle
+            .reload(filter)
+            .map_err(|err| LoggingError::Setup(err.to_string()))?;
+        *self.directives.write().unwrap() = directives.to_string();
+
+        Ok(())
+    }
+}
+
+/// Install the global log capture, writing to stdout
+///
+/// Returns a handle through which the filter may be changed at runtime
+pub fn configure_log_capture(config: &LogConfig) -> Result<LogFilterHandle, LoggingError> {
+    let (filter, handle) = reload::Layer::new(LogConfig::parse_filter(&config.filter)?);
+    let fmt_layer = match config.format {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer().json().boxed(),
+    };
+    let subscriber = tracing_subscriber::registry().with(filter).with(fmt_layer);
+
+    // Forward records from the `log` macros at every level and leave filtering to the
+    // subscriber; the subscriber's own initializer caps `log` at the level enabled at
+    // startup, which a filter raised at runtime could not lift
+    LogTracer::init().map_err(|err| LoggingError::Setup(err.to_string()))?;
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|err| LoggingError::Setup(err.to_string()))?;
+
+    Ok(LogFilterHandle {
+        handle,
+        directives: Arc::new(RwLock::new(config.filter.clone())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{LogConfig, LogFormat};
+
+    /// Tests parsing the log format and filter directives
+    #[test]
+    fn test_parse_log_config() {
+        assert_eq!(LogFormat::from_str("json"), Ok(LogFormat::Json));
+        assert_eq!(
+            LogFormat::from_str(&LogFormat::Text.to_string()),
+            Ok(LogFormat::Text)
+        );
+        assert!(LogFormat::from_str("yaml").is_err());
+
+        assert!(LogConfig::parse_filter("info,darkpool_relayer::gossip=debug").is_ok());
+        assert!(LogConfig::parse_filter("darkpool_relayer::gossip=loud").is_err());
+    }
+}
@@ -6,97 +6,81 @@
 #![deny(unsafe_code)]
 #![deny(clippy::missing_docs_in_private_items)]
 
-mod api_server;
-mod chain_events;
-mod config;
-mod default_wrapper;
-mod error;
-mod external_api;
-mod gossip;
-mod gossip_api;
-mod handshake;
-mod network_manager;
-mod price_reporter;
-mod proof_generation;
-mod starknet_client;
-mod state;
-mod system_bus;
-mod types;
-mod worker;
-
-use std::{io::Write, process::exit, thread, time::Duration};
-
-use chrono::Local;
-use circuits::{types::wallet::Wallet, zk_gadgets::fixed_point::FixedPoint};
-use crossbeam::channel;
-use env_logger::Builder;
-use error::CoordinatorError;
-use gossip::worker::GossipServerConfig;
-use handshake::worker::HandshakeManagerConfig;
-use network_manager::worker::NetworkManagerConfig;
-use num_bigint::BigUint;
-use price_reporter::worker::PriceReporterManagerConfig;
+use std::{fs, process::exit, sync::Arc, thread, time::Duration};
+
+use ed25519_dalek::Keypair;
 use tokio::{
     select,
+    signal::unix::{signal, SignalKind},
     sync::{
-        mpsc,
-        watch::{self, Receiver as WatchReceiver},
+        mpsc::{self, Receiver as MpscReceiver, Sender as MpscSender},
+        watch::{self, Sender as WatchSender},
     },
+    time::{sleep, timeout},
 };
-use tracing::log::{self, LevelFilter};
+use tracing::log;
 
-use crate::{
-    api_server::worker::{ApiServer, ApiServerConfig},
+use darkpool_relayer::{
+    api_server::{
+        auth::ApiAuthenticator,
+        worker::{ApiServer, ApiServerConfig},
+    },
     chain_events::listener::{OnChainEventListener, OnChainEventListenerConfig},
-    gossip::{jobs::GossipServerJob, server::GossipServer},
+    clock::system_clock,
+    config::{self, Command, RelayerConfig, WalletCommand},
+    error::{CoordinatorError, ErrorCode},
+    gossip::{jobs::GossipServerJob, server::GossipServer, worker::GossipServerConfig},
     gossip_api::gossip::GossipOutbound,
-    handshake::{jobs::HandshakeExecutionJob, manager::HandshakeManager},
-    network_manager::manager::NetworkManager,
-    price_reporter::{jobs::PriceReporterManagerJob, manager::PriceReporterManager},
-    proof_generation::{proof_manager::ProofManager, worker::ProofManagerConfig},
+    handshake::{
+        jobs::HandshakeExecutionJob, manager::HandshakeManager, state::HandshakeStateIndex,
+        worker::HandshakeManagerConfig,
+    },
+    job_queue::{
+        job_queue, JobQueueMonitor, GOSSIP_QUEUE, HANDSHAKE_PRIORITY_QUEUE, HANDSHAKE_QUEUE,
+        NETWORK_QUEUE, PRICE_REPORTER_QUEUE, PROOF_MANAGER_QUEUE,
+    },
+    keychain::{ExternalRootSigner, HttpRootSigner, RootKeyManager},
+    logging::{configure_log_capture, LogConfig, LogFilterHandle},
+    network_manager::{manager::NetworkManager, worker::NetworkManagerConfig},
+    price_reporter::{
+        jobs::PriceReporterManagerJob, manager::PriceReporterManager,
+        worker::PriceReporterManagerConfig,
+    },
+    proof_generation::{
+        dead_letter::DeadLetterQueue, jobs::ProofManagerJob, proof_cache::ProofCache,
+        proof_manager::ProofManager, worker::ProofManagerConfig,
+    },
+    recovery::recover_wallet,
+    rng::WorkerRng,
     starknet_client::client::{StarknetClient, StarknetClientConfig},
-    state::RelayerState,
+    state::{
+        audit::AuditLog, cluster_access::ClusterAccessPolicy, expiry::OrderExpirySweeper,
+        export::OrderBookExporter, feature_flags::FeatureFlags, wallet::PrivateKeyChain,
+        RelayerState,
+    },
     system_bus::SystemBus,
-    types::SystemBusMessage,
-    worker::{watch_worker, Worker},
+    types::{SystemBusMessage, WORKER_STATUS_TOPIC},
+    worker::{
+        watch_worker, RestartPolicy, RestartTracker, Worker, WorkerCommand, WorkerControlMessage,
+        WorkerStatus,
+    },
 };
 
 #[cfg(feature = "debug-tui")]
-use crate::state::tui::StateTuiApp;
-
-#[macro_use]
-extern crate lazy_static;
-
-/// A type alias for an empty channel used to signal cancellation to workers
-pub(crate) type CancelChannel = WatchReceiver<()>;
+use darkpool_relayer::state::tui::StateTuiApp;
 
 // --------------------
 // | Global Constants |
 // --------------------
 
-// TODO: Move these constants to a more discoverable location
-lazy_static! {
-    /// The fee the protocol takes on a match; one basis point
-    static ref PROTOCOL_FEE: FixedPoint = FixedPoint::from_f32_round_down(0.0002);
-    /// The public settle key of the protocol wallet
-    /// Dummy value for now
-    static ref PROTOCOL_SETTLE_KEY: BigUint = BigUint::from(0u8);
-}
-
-/// The system-wide value of MAX_BALANCES; the number of allowable balances a wallet holds
-pub(crate) const MAX_BALANCES: usize = 5;
-/// The system-wide value of MAX_ORDERS; the number of allowable orders a wallet holds
-pub(crate) const MAX_ORDERS: usize = 5;
-/// The system-wide value of MAX_FEES; the number of allowable fees a wallet holds
-pub(crate) const MAX_FEES: usize = 2;
-/// The height of the Merkle state tree used by the contract
-pub(crate) const MERKLE_HEIGHT: usize = 32;
-/// The number of historical roots the contract stores as being valid
-pub(crate) const MERKLE_ROOT_HISTORY_LENGTH: usize = 30;
-/// A type wrapper around the wallet type that adds the default generics above
-pub(crate) type SizedWallet = Wallet<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
 /// The amount of time to wait between sending teardown signals and terminating execution
 const TERMINATION_TIMEOUT_MS: u64 = 10_000; // 10 seconds
+/// The maximum amount of time a draining relayer waits for in-flight MPCs to finish
+const DRAIN_TIMEOUT_MS: u64 = 60_000; // 1 minute
+/// The interval at which a draining relayer polls for in-flight MPCs to finish
+const DRAIN_POLL_INTERVAL_MS: u64 = 500;
+/// The maximum amount of time to wait for a single worker to exit after it is cancelled
+const WORKER_SHUTDOWN_TIMEOUT_MS: u64 = 2_000; // 2 seconds
 
 // --------------
 // | Entrypoint |
@@ -123,6 +107,33 @@ async fn main() -> Result<(), CoordinatorError> {
     // Parse command line arguments
     let args = config::parse_command_line_args().expect("error parsing command line args");
     let args_clone = args.clone();
+
+    // Print the hash of the prover parameters bundle if requested, otherwise verify the
+    // bundle before proving or verifying anything under it
+    let params_hash = args.params_bundle.hash();
+    if let Some(Command::ParamsHash { out_dir }) = args.command.as_ref() {
+        if let Some(dir) = out_dir {
+            args.params_bundle
+                .write_to_dir(dir)
+                .map_err(|err| CoordinatorError::ParamsVerification(err.to_string()))?;
+        }
+
+        println!("{}", params_hash);
+        return Ok(());
+    }
+
+    if let Some(Command::Wallet {
+        command: WalletCommand::Recover { keys_file },
+    }) = args.command.as_ref()
+    {
+        return run_wallet_recovery(&args, keys_file).await;
+    }
+
+    args.params_bundle
+        .verify(args.expected_params_hash.as_deref())
+        .map_err(|err| CoordinatorError::ParamsVerification(err.to_string()))?;
+    log::info!("Verified prover parameters bundle {}", params_hash);
+
     log::info!(
         "Relayer running with\n\t version: {}\n\t port: {}\n\t cluster: {:?}",
         args.version,
@@ -133,26 +144,65 @@ async fn main() -> Result<(), CoordinatorError> {
     // Build communication primitives
     // First, the global shared mpmc bus that all workers have access to
     let system_bus = SystemBus::<SystemBusMessage>::new();
-    let (network_sender, network_receiver) = mpsc::unbounded_channel::<GossipOutbound>();
+    // Then the bounded job queues between workers
+    let capacities = &args.job_queue_capacities;
+    let (network_sender, network_receiver) =
+        job_queue::<GossipOutbound>(NETWORK_QUEUE, capacities.capacity(NETWORK_QUEUE));
     let (gossip_worker_sender, gossip_worker_receiver) =
-        mpsc::unbounded_channel::<GossipServerJob>();
+        job_queue::<GossipServerJob>(GOSSIP_QUEUE, capacities.capacity(GOSSIP_QUEUE));
     let (handshake_worker_sender, handshake_worker_receiver) =
-        mpsc::unbounded_channel::<HandshakeExecutionJob>();
+        job_queue::<HandshakeExecutionJob>(HANDSHAKE_QUEUE, capacities.capacity(HANDSHAKE_QUEUE));
+    let (handshake_priority_sender, handshake_priority_receiver) = job_queue::<HandshakeExecutionJob>(
+        HANDSHAKE_PRIORITY_QUEUE,
+        capacities.capacity(HANDSHAKE_PRIORITY_QUEUE),
+    );
     let (price_reporter_worker_sender, price_reporter_worker_receiver) =
-        mpsc::unbounded_channel::<PriceReporterManagerJob>();
-    let (proof_generation_worker_sender, proof_generation_worker_receiver) = channel::unbounded();
+        job_queue::<PriceReporterManagerJob>(
+            PRICE_REPORTER_QUEUE,
+            capacities.capacity(PRICE_REPORTER_QUEUE),
+        );
+    let job_queue_monitor = JobQueueMonitor::new(vec![
+        network_sender.stats(),
+        gossip_worker_sender.stats(),
+        handshake_worker_sender.stats(),
+        handshake_priority_sender.stats(),
+        price_reporter_worker_sender.stats(),
+        proof_generation_worker_sender.stats(),
+    ]);
+    let (proof_generation_worker_sender, proof_generation_worker_receiver) =
+        job_queue::<ProofManagerJob>(
+            PROOF_MANAGER_QUEUE,
+            capacities.capacity(PROOF_MANAGER_QUEUE),
+        );
+    // The queue of proof jobs abandoned by the proof manager, inspectable via the API server
+    let dead_letter_queue = DeadLetterQueue::new();
+    // The cache of proofs of `VALID COMMITMENTS`, shared by the global state and the proof manager
+    let proof_cache =
+        ProofCache::new(args.proof_cache_dir.clone()).expect("failed to open proof cache");
+    // A channel on which the admin API and signal handler ask the coordinator to shut down
+    let (shutdown_sender, mut shutdown_receiver) = mpsc::unbounded_channel::<()>();
+    // A channel on which the admin API asks the coordinator to pause or resume workers
+    let (worker_control_sender, mut worker_control_receiver) =
+        mpsc::unbounded_channel::<WorkerControlMessage>();
 
     // Construct the global state and warm up the config orders by generating proofs of `VALID COMMITMENTS`
     let global_state = RelayerState::initialize_global_state(
         args.debug,
         args.wallets,
         args.cluster_id.clone(),
+        ClusterAccessPolicy::new(
+            args.cluster_allowlist.clone(),
+            args.cluster_denylist.clone(),
+        ),
         system_bus.clone(),
+        proof_cache.clone(),
+        FeatureFlags::new(&args.feature_flags),
     );
 
-    // Configure logging and TUI
+    // Configure logging and TUI; the TUI captures logs itself, in which case the log
+    // filter cannot be changed through the admin API
     #[cfg(feature = "debug-tui")]
-    {
+    let log_filter_handle = {
         if args.debug {
             // Build the TUI
             let tui = StateTuiApp::new(args_clone, global_state.clone());
@@ -164,15 +214,15 @@ async fn main() -> Result<(), CoordinatorError> {
                 join_handle.join();
                 exit(0);
             });
+
+            None
         } else {
-            configure_default_log_capture()
+            Some(configure_default_log_capture(&args.log_config))
         }
-    }
+    };
 
     #[cfg(not(feature = "debug-tui"))]
-    {
-        configure_default_log_capture();
-    }
+    let log_filter_handle = Some(configure_default_log_capture(&args.log_config));
 
     // Spawn a thread to sync the relayer-global state with on-chain state and
     // network state
@@ -181,6 +231,7 @@ async fn main() -> Result<(), CoordinatorError> {
         args.starknet_jsonrpc_node.clone().unwrap(),
         proof_generation_worker_sender.clone(),
         network_sender.clone(),
+        args.witness_check_sample_rate,
     );
 
     // ----------------
@@ -188,13 +239,12 @@ async fn main() -> Result<(), CoordinatorError> {
     // ----------------
 
     // Construct a starknet client that workers will use to communicate with Starknet
-    let starknet_client = StarknetClient::new(StarknetClientConfig {
-        chain: args.chain_id,
-        contract_addr: args.contract_address.clone(),
-        infura_api_key: None,
-        starknet_json_rpc_addr: args.starknet_jsonrpc_node.clone(),
-        starknet_pkey: None,
-    });
+    let starknet_client = build_starknet_client(&args);
+
+    // The API server signs the relayer's identity attestation, and the price reporter its
+    // medians, with the cluster keypair, which is moved into the network manager below
+    let api_cluster_keypair =
+        Arc::new(Keypair::from_bytes(&args.cluster_keypair.to_bytes()).unwrap());
 
     // Start the network manager
     let (network_cancel_sender, network_cancel_receiver) = watch::channel(());
@@ -202,6 +252,14 @@ async fn main() -> Result<(), CoordinatorError> {
         port: args.p2p_port,
         cluster_id: args.cluster_id.clone(),
         cluster_keypair: Some(args.cluster_keypair),
+        zone: args.zone,
+        dns_seeds: args.dns_seeds,
+        max_outbound_connections: args.max_outbound_connections,
+        relay_server: args.relay_server,
+        relay_addrs: args.relay_addrs,
+        rate_limits: args.gossip_rate_limits,
+        key_rotation: args.cluster_key_rotation,
+        clock: system_clock(),
         send_channel: Some(network_receiver),
         gossip_work_queue: gossip_worker_sender.clone(),
         handshake_work_queue: handshake_worker_sender.clone(),
@@ -216,7 +274,7 @@ async fn main() -> Result<(), CoordinatorError> {
 
     let (network_failure_sender, mut network_failure_receiver) =
         mpsc::channel(1 /* buffer size */);
-    watch_worker::<NetworkManager>(&mut network_manager, network_failure_sender);
+    watch_worker::<NetworkManager>(&mut network_manager, network_failure_sender.clone());
 
     // Start the gossip server
     let (gossip_cancel_sender, gossip_cancel_receiver) = watch::channel(());
@@ -225,8 +283,12 @@ async fn main() -> Result<(), CoordinatorError> {
         local_addr: network_manager.local_addr.clone(),
         cluster_id: args.cluster_id,
         bootstrap_servers: args.bootstrap_servers,
+        replication_factor: args.replication_factor,
+        erasure_coding: args.erasure_coding,
+        max_version_lag: args.max_version_lag,
         starknet_client: starknet_client.clone(),
         global_state: global_state.clone(),
+        clock: system_clock(),
         job_sender: gossip_worker_sender.clone(),
         job_receiver: Some(gossip_worker_receiver).into(),
         network_sender: network_sender.clone(),
@@ -238,17 +300,48 @@ async fn main() -> Result<(), CoordinatorError> {
         .expect("failed to start gossip server");
     let (gossip_failure_sender, mut gossip_failure_receiver) =
         mpsc::channel(1 /* buffer size */);
-    watch_worker::<GossipServer>(&mut gossip_server, gossip_failure_sender);
+    watch_worker::<GossipServer>(&mut gossip_server, gossip_failure_sender.clone());
+
+    // Resolve the protocol fee schedule, optionally taking the default fee and settle key
+    // from the contract so that settlements are proven against the fee it checks
+    let mut fee_schedule = args.fee_schedule.clone();
+    if args.fee_schedule_from_chain {
+        fee_schedule
+            .refresh_from_chain(&starknet_client)
+            .await
+            .map_err(|err| CoordinatorError::ConfigParse(err.to_string()))?;
+    }
 
-    // Start the handshake manager
+    // Start the handshake manager, its index of in-flight handshakes is shared with the
+    // API server
+    let handshake_state_index = HandshakeStateIndex::new(global_state.clone(), system_clock());
     let (handshake_cancel_sender, handshake_cancel_receiver) = watch::channel(());
     let mut handshake_manager = HandshakeManager::new(HandshakeManagerConfig {
         global_state: global_state.clone(),
-        network_channel: network_sender.clone(),
+        handshake_state_index: handshake_state_index.clone(),
+        network_channel: Arc::new(network_sender.clone()),
         job_receiver: Some(handshake_worker_receiver),
+        priority_job_receiver: Some(handshake_priority_receiver),
         job_sender: handshake_worker_sender.clone(),
         proof_manager_sender: proof_generation_worker_sender.clone(),
         system_bus: system_bus.clone(),
+        mpc_timeout_ms: args.mpc_timeout_ms,
+        size_bucket_check: args.size_bucket_check,
+        handshake_prescreen: args.handshake_prescreen,
+        max_concurrent_mpcs: args.max_concurrent_mpcs,
+        max_concurrent_mpcs_per_peer: args.max_concurrent_mpcs_per_peer,
+        max_settlement_fee: args.max_settlement_fee,
+        fee_schedule,
+        broker_fee_bps: args.broker_fee_bps,
+        max_broker_fee_bps: args.max_broker_fee_bps,
+        default_min_fill_size: args.default_min_fill_size,
+        default_max_slippage_bps: args.default_max_slippage_bps,
+        price_reporter_work_queue: price_reporter_worker_sender.clone(),
+        starknet_client: Arc::new(starknet_client.clone()),
+        rng_seed: args.rng_seed,
+        settlement_journal_file: args.settlement_journal_file,
+        handshake_cache_file: args.handshake_cache_file,
+        clock: system_clock(),
         cancel_channel: handshake_cancel_receiver,
     })
     .expect("failed to build handshake manager");
@@ -257,37 +350,60 @@ async fn main() -> Result<(), CoordinatorError> {
         .expect("failed to start handshake manager");
     let (handshake_failure_sender, mut handshake_failure_receiver) =
         mpsc::channel(1 /* buffer size */);
-    watch_worker::<HandshakeManager>(&mut handshake_manager, handshake_failure_sender);
+    watch_worker::<HandshakeManager>(&mut handshake_manager, handshake_failure_sender.clone());
 
-    // Start the price reporter manager
+    // Start the price reporter manager, unless it is disabled
+    //
+    // A disabled worker is never allocated, so the coordinator never receives a failure
+    // from it
     let (price_reporter_cancel_sender, price_reporter_cancel_receiver) = watch::channel(());
-    let mut price_reporter_manager = PriceReporterManager::new(PriceReporterManagerConfig {
-        system_bus: system_bus.clone(),
-        job_receiver: Some(price_reporter_worker_receiver).into(),
-        cancel_channel: price_reporter_cancel_receiver,
-        coinbase_api_key: args.coinbase_api_key,
-        coinbase_api_secret: args.coinbase_api_secret,
-        eth_websocket_addr: args.eth_websocket_addr,
-    })
-    .expect("failed to build price reporter manager");
-    price_reporter_manager
-        .start()
-        .expect("failed to start price reporter manager");
     let (price_reporter_failure_sender, mut price_reporter_failure_receiver) =
         mpsc::channel(1 /* buffer size */);
-    watch_worker::<PriceReporterManager>(
-        &mut price_reporter_manager,
-        price_reporter_failure_sender,
-    );
+    let mut price_reporter_manager = if args.disable_price_reporter {
+        None
+    } else {
+        let mut price_reporter_manager = PriceReporterManager::new(PriceReporterManagerConfig {
+            system_bus: system_bus.clone(),
+            job_receiver: Some(price_reporter_worker_receiver).into(),
+            cancel_channel: price_reporter_cancel_receiver,
+            exchange_region: args.exchange_region,
+            exchange_allowlist: args.exchange_allowlist,
+            coinbase_api_key: args.coinbase_api_key,
+            coinbase_api_secret: args.coinbase_api_secret,
+            eth_websocket_addr: args.eth_websocket_addr,
+            uniswap_fee_tier: args.uniswap_fee_tier,
+            uniswap_twap_window_secs: args.uniswap_twap_window_secs,
+            starknet_client: starknet_client.clone(),
+            token_registry_address: args.token_registry_address,
+            token_remap_file: args.token_remap_file,
+            circuit_breakers: args.price_circuit_breakers,
+            deviation_quarantine: args.price_deviation_quarantine,
+            price_history: args.price_history,
+            recorded_feed: None,
+            price_signing_keypair: args.sign_price_reports.then(|| api_cluster_keypair.clone()),
+        })
+        .expect("failed to build price reporter manager");
+        price_reporter_manager
+            .start()
+            .expect("failed to start price reporter manager");
+        watch_worker::<PriceReporterManager>(
+            &mut price_reporter_manager,
+            price_reporter_failure_sender.clone(),
+        );
+
+        Some(price_reporter_manager)
+    };
 
     // Start the on-chain event listener
     let (chain_listener_cancel_sender, chain_listener_cancel_receiver) = watch::channel(());
     let mut chain_listener = OnChainEventListener::new(OnChainEventListenerConfig {
         starknet_client: starknet_client.clone(),
         global_state: global_state.clone(),
-        handshake_manager_job_queue: handshake_worker_sender,
+        handshake_manager_job_queue: handshake_priority_sender.clone(),
         proof_generation_work_queue: proof_generation_worker_sender.clone(),
         network_manager_work_queue: network_sender.clone(),
+        backfill: !args.disable_chain_backfill,
+        checkpoint_file: args.chain_checkpoint_file.clone(),
         cancel_channel: chain_listener_cancel_receiver,
     })
     .expect("failed to build on-chain event listener");
@@ -296,28 +412,92 @@ async fn main() -> Result<(), CoordinatorError> {
         .expect("failed to start on-chain event listener");
     let (chain_listener_failure_sender, mut chain_listener_failure_receiver) =
         mpsc::channel(1 /* buffer_size */);
-    watch_worker::<OnChainEventListener>(&mut chain_listener, chain_listener_failure_sender);
+    watch_worker::<OnChainEventListener>(
+        &mut chain_listener,
+        chain_listener_failure_sender.clone(),
+    );
 
-    // Start the API server
+    // Export the order book on a schedule, if exports are configured; the exporter is also
+    // handed to the API server to serve ad hoc exports
+    let order_book_exporter = args.order_book_export.clone().map(|destination| {
+        OrderBookExporter::new(
+            destination,
+            args.order_book_export_interval,
+            global_state.clone(),
+            system_clock(),
+        )
+    });
+    if let Some(exporter) = order_book_exporter.clone() {
+        tokio::spawn(exporter.run_scheduled());
+    }
+
+    // Record the audited system bus topics, if auditing is configured; the log is also
+    // handed to the API server to serve queries of recent events
+    let audit_log = args
+        .audit_log
+        .clone()
+        .map(|config| AuditLog::open(config, system_clock()).expect("failed to open audit log"));
+    if let Some(audit_log) = audit_log.clone() {
+        tokio::spawn(audit_log.run(system_bus.clone()));
+    }
+
+    // Publish the depth of each job queue
+    tokio::spawn(job_queue_monitor.run(system_bus.clone()));
+
+    // Cancel orders whose time in force has lapsed
+    tokio::spawn(OrderExpirySweeper::new(global_state.clone(), system_clock()).run());
+
+    // Start the API server, unless it is disabled
     let (api_cancel_sender, api_cancel_receiver) = watch::channel(());
-    let mut api_server = ApiServer::new(ApiServerConfig {
-        http_port: args.http_port,
-        websocket_port: args.websocket_port,
-        global_state: global_state.clone(),
-        system_bus,
-        price_reporter_work_queue: price_reporter_worker_sender,
-        proof_generation_work_queue: proof_generation_worker_sender,
-        cancel_channel: api_cancel_receiver,
-    })
-    .expect("failed to build api server");
-    api_server.start().expect("failed to start api server");
     let (api_failure_sender, mut api_failure_receiver) = mpsc::channel(1 /* buffer_size */);
-    watch_worker::<ApiServer>(&mut api_server, api_failure_sender);
+    let mut api_server = if args.disable_api_server {
+        None
+    } else {
+        let mut api_server = ApiServer::new(ApiServerConfig {
+            http_port: args.http_port,
+            websocket_port: args.websocket_port,
+            api_authenticator: ApiAuthenticator::new(args.api_keys.clone(), system_clock()),
+            cluster_keypair: api_cluster_keypair,
+            relayer_version: args.version.clone(),
+            contact_info: args.contact_info.clone(),
+            order_eviction_policy: args.order_eviction_policy,
+            cross_preview_rate_limit: args.cross_preview_rate_limit,
+            root_key_manager: RootKeyManager::new(
+                args.external_root_signer
+                    .clone()
+                    .map(|url| Arc::new(HttpRootSigner::new(url)) as Arc<dyn ExternalRootSigner>),
+            ),
+            global_state: global_state.clone(),
+            handshake_state_index,
+            handshake_manager_work_queue: handshake_priority_sender,
+            system_bus: system_bus.clone(),
+            websocket_subscription_config: args.websocket_subscription_config,
+            price_reporter_work_queue: price_reporter_worker_sender,
+            proof_generation_work_queue: proof_generation_worker_sender,
+            dead_letter_queue: dead_letter_queue.clone(),
+            order_book_exporter,
+            audit_log,
+            starknet_client,
+            log_filter_handle,
+            debug: args.debug,
+            shutdown_channel: shutdown_sender.clone(),
+            worker_control_channel: worker_control_sender,
+            cancel_channel: api_cancel_receiver,
+        })
+        .expect("failed to build api server");
+        api_server.start().expect("failed to start api server");
+        watch_worker::<ApiServer>(&mut api_server, api_failure_sender.clone());
+
+        Some(api_server)
+    };
 
     // Start the proof generation module
     let (proof_manager_cancel_sender, proof_manager_cancel_receiver) = watch::channel(());
     let mut proof_manager = ProofManager::new(ProofManagerConfig {
-        job_queue: proof_generation_worker_receiver,
+        job_queue: Some(proof_generation_worker_receiver).into(),
+        dead_letter_queue,
+        proof_cache,
+        journal_file: args.proof_journal_file.clone(),
         cancel_channel: proof_manager_cancel_receiver,
     })
     .expect("failed to build proof generation module");
@@ -326,19 +506,43 @@ async fn main() -> Result<(), CoordinatorError> {
         .expect("failed to start proof generation module");
     let (proof_manager_failure_sender, mut proof_manager_failure_receiver) =
         mpsc::channel(1 /* buffer_size */);
-    watch_worker::<ProofManager>(&mut proof_manager, proof_manager_failure_sender);
+    watch_worker::<ProofManager>(&mut proof_manager, proof_manager_failure_sender.clone());
 
-    // For simplicity, we simply cancel all disabled workers, it is simpler to do this than work with
-    // a dynamic list of futures
-    //
-    // We can refactor this decision if it becomes a performance issue
-    if args.disable_api_server {
-        api_server.cleanup().unwrap();
-    }
+    // Drain and shut down the relayer when the process is asked to terminate
+    tokio::spawn(async move {
+        let mut sigterm = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+        sigterm.recv().await;
+        log::info!("Received SIGTERM");
 
-    if args.disable_price_reporter {
-        price_reporter_cancel_sender.send(()).unwrap();
-    }
+        // The coordinator may already be tearing down, in which case there is nothing to signal
+        let _ = shutdown_sender.send(());
+    });
+
+    // Track the restarts of each worker so that a worker failing repeatedly, e.g. on a
+    // dependency that is down, is restarted with backoff and eventually left down
+    let coordinator_rng = WorkerRng::new(args.rng_seed);
+    let mut network_restarts = restart_tracker(network_manager.restart_policy(), &coordinator_rng);
+    let mut gossip_restarts = restart_tracker(gossip_server.restart_policy(), &coordinator_rng);
+    let mut handshake_restarts =
+        restart_tracker(handshake_manager.restart_policy(), &coordinator_rng);
+    let mut price_reporter_restarts = restart_tracker(
+        price_reporter_manager
+            .as_ref()
+            .map(Worker::restart_policy)
+            .unwrap_or_default(),
+        &coordinator_rng,
+    );
+    let mut chain_listener_restarts =
+        restart_tracker(chain_listener.restart_policy(), &coordinator_rng);
+    let mut api_restarts = restart_tracker(
+        api_server
+            .as_ref()
+            .map(Worker::restart_policy)
+            .unwrap_or_default(),
+        &coordinator_rng,
+    );
+    let mut proof_manager_restarts =
+        restart_tracker(proof_manager.restart_policy(), &coordinator_rng);
 
     // Await module termination, and send a cancel signal for any modules that
     // have been detected to fault
@@ -348,44 +552,235 @@ async fn main() -> Result<(), CoordinatorError> {
                 _ = network_failure_receiver.recv() => {
                     network_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    network_manager = recover_worker(network_manager)?;
+                    network_manager = recover_worker(
+                        network_manager,
+                        &mut network_restarts,
+                        &network_failure_sender,
+                        &global_state,
+                        &system_bus,
+                    ).await?;
                 }
                 _ = gossip_failure_receiver.recv() => {
                     gossip_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    gossip_server = recover_worker(gossip_server)?;
+                    gossip_server = recover_worker(
+                        gossip_server,
+                        &mut gossip_restarts,
+                        &gossip_failure_sender,
+                        &global_state,
+                        &system_bus,
+                    ).await?;
                 }
                 _ = handshake_failure_receiver.recv() => {
                     handshake_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    handshake_manager = recover_worker(handshake_manager)?;
+                    handshake_manager = recover_worker(
+                        handshake_manager,
+                        &mut handshake_restarts,
+                        &handshake_failure_sender,
+                        &global_state,
+                        &system_bus,
+                    ).await?;
                 }
-                _ = price_reporter_failure_receiver.recv() => {
+                // Disabled workers are never watched, their branches are never taken
+                Some(_) = price_reporter_failure_receiver.recv() => {
                     price_reporter_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    price_reporter_manager = recover_worker(price_reporter_manager)?;
+                    if let Some(failed_worker) = price_reporter_manager.take() {
+                        price_reporter_manager = Some(recover_worker(
+                            failed_worker,
+                            &mut price_reporter_restarts,
+                            &price_reporter_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?);
+                    }
                 }
                 _= chain_listener_failure_receiver.recv() => {
                     chain_listener_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    chain_listener = recover_worker(chain_listener)?;
+                    chain_listener = recover_worker(
+                        chain_listener,
+                        &mut chain_listener_restarts,
+                        &chain_listener_failure_sender,
+                        &global_state,
+                        &system_bus,
+                    ).await?;
                 }
-                _ = api_failure_receiver.recv() => {
+                Some(_) = api_failure_receiver.recv() => {
                     api_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    api_server = recover_worker(api_server)?;
+                    if let Some(failed_worker) = api_server.take() {
+                        api_server = Some(recover_worker(
+                            failed_worker,
+                            &mut api_restarts,
+                            &api_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?);
+                    }
                 }
                 _ = proof_manager_failure_receiver.recv() => {
                     proof_manager_cancel_sender.send(())
                         .map_err(|err| CoordinatorError::CancelSend(err.to_string()))?;
-                    proof_manager = recover_worker(proof_manager)?;
+                    proof_manager = recover_worker(
+                        proof_manager,
+                        &mut proof_manager_restarts,
+                        &proof_manager_failure_sender,
+                        &global_state,
+                        &system_bus,
+                    ).await?;
+                }
+                // Pause or resume a worker at the operator's request
+                Some(message) = worker_control_receiver.recv() => {
+                    if message.worker == network_manager.name() {
+                        network_manager = control_worker(
+                            network_manager,
+                            message,
+                            &network_cancel_sender,
+                            &mut network_failure_receiver,
+                            &network_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?;
+                    } else if message.worker == gossip_server.name() {
+                        gossip_server = control_worker(
+                            gossip_server,
+                            message,
+                            &gossip_cancel_sender,
+                            &mut gossip_failure_receiver,
+                            &gossip_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?;
+                    } else if message.worker == handshake_manager.name() {
+                        handshake_manager = control_worker(
+                            handshake_manager,
+                            message,
+                            &handshake_cancel_sender,
+                            &mut handshake_failure_receiver,
+                            &handshake_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?;
+                    } else if message.worker == chain_listener.name() {
+                        chain_listener = control_worker(
+                            chain_listener,
+                            message,
+                            &chain_listener_cancel_sender,
+                            &mut chain_listener_failure_receiver,
+                            &chain_listener_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?;
+                    } else if message.worker == proof_manager.name() {
+                        proof_manager = control_worker(
+                            proof_manager,
+                            message,
+                            &proof_manager_cancel_sender,
+                            &mut proof_manager_failure_receiver,
+                            &proof_manager_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?;
+                    } else if is_named(price_reporter_manager.as_ref(), &message.worker) && let Some(worker) = price_reporter_manager.take() {
+                        price_reporter_manager = Some(control_worker(
+                            worker,
+                            message,
+                            &price_reporter_cancel_sender,
+                            &mut price_reporter_failure_receiver,
+                            &price_reporter_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?);
+                    } else if is_named(api_server.as_ref(), &message.worker) && let Some(worker) = api_server.take() {
+                        api_server = Some(control_worker(
+                            worker,
+                            message,
+                            &api_cancel_sender,
+                            &mut api_failure_receiver,
+                            &api_failure_sender,
+                            &global_state,
+                            &system_bus,
+                        ).await?);
+                    } else {
+                        // A disabled worker is never allocated, and may not be controlled
+                        let err = format!("no worker named {}", message.worker);
+                        let _ = message.response_channel.send(Err(err));
+                    }
+                }
+                _ = shutdown_receiver.recv() => {
+                    return Ok(());
                 }
             };
         }
     };
 
-    // Wait for an error, log the error, and teardown the relayer
+    // Wait for an error or a shutdown signal
     let loop_res: Result<(), CoordinatorError> = recovery_loop().await;
+    if loop_res.is_ok() {
+        log::info!("Draining relayer...");
+        global_state.begin_draining();
+        await_in_flight_mpcs(&global_state).await;
+        flush_wallet_snapshot(&global_state, args.wallet_file).await;
+
+        // Release the coordinator's failure senders, so that a worker that is not running,
+        // i.e. one that is disabled or was left down, reads as exited rather than timing out
+        drop((
+            network_failure_sender,
+            gossip_failure_sender,
+            handshake_failure_sender,
+            price_reporter_failure_sender,
+            chain_listener_failure_sender,
+            api_failure_sender,
+            proof_manager_failure_sender,
+        ));
+
+        // Cancel workers in dependency order; workers that accept external requests or begin
+        // new work go first, the workers they depend on go last so that in-flight work may
+        // still reach the network
+        let workers: [(&str, &WatchSender<()>, &mut MpscReceiver<()>); 7] = [
+            ("api-server", &api_cancel_sender, &mut api_failure_receiver),
+            (
+                "handshake-manager",
+                &handshake_cancel_sender,
+                &mut handshake_failure_receiver,
+            ),
+            (
+                "chain-listener",
+                &chain_listener_cancel_sender,
+                &mut chain_listener_failure_receiver,
+            ),
+            (
+                "price-reporter",
+                &price_reporter_cancel_sender,
+                &mut price_reporter_failure_receiver,
+            ),
+            (
+                "proof-manager",
+                &proof_manager_cancel_sender,
+                &mut proof_manager_failure_receiver,
+            ),
+            (
+                "gossip-server",
+                &gossip_cancel_sender,
+                &mut gossip_failure_receiver,
+            ),
+            (
+                "network-manager",
+                &network_cancel_sender,
+                &mut network_failure_receiver,
+            ),
+        ];
+        for (name, cancel_sender, exit_receiver) in workers {
+            shutdown_worker(name, cancel_sender, exit_receiver).await;
+        }
+
+        log::info!("Relayer shut down cleanly");
+        return Ok(());
+    }
+
+    // Log the error and teardown the relayer
     let err = loop_res.err().unwrap();
     log::info!("Error in coordinator thread: {:?}", err);
 
@@ -412,30 +807,290 @@ async fn main() -> Result<(), CoordinatorError> {
     Err(err)
 }
 
+/// Wait for the match MPCs in flight on the local node to finish, up to a timeout
+async fn await_in_flight_mpcs(global_state: &RelayerState) {
+    let drain = async {
+        while global_state.num_in_flight_mpcs() > 0 {
+            sleep(Duration::from_millis(DRAIN_POLL_INTERVAL_MS)).await;
+        }
+    };
+
+    if timeout(Duration::from_millis(DRAIN_TIMEOUT_MS), drain)
+        .await
+        .is_err()
+    {
+        log::warn!(
+            "timed out draining relayer with {} MPCs in flight",
+            global_state.num_in_flight_mpcs()
+        );
+    }
+}
+
+/// Construct the client used to communicate with Starknet
+fn build_starknet_client(args: &RelayerConfig) -> StarknetClient {
+    StarknetClient::new(StarknetClientConfig {
+        chain: args.chain_id,
+        contract_addr: args.contract_address.clone(),
+        infura_api_key: None,
+        starknet_json_rpc_addr: args.starknet_jsonrpc_node.clone(),
+        starknet_pkey: args.starknet_private_key.clone(),
+        starknet_account_addr: args.starknet_account_address.clone(),
+    })
+}
+
+/// Recover a wallet from chain and add it to the wallet file, so that the relayer manages
+/// it once started; the recovered wallet is printed
+async fn run_wallet_recovery(
+    args: &RelayerConfig,
+    keys_file: &str,
+) -> Result<(), CoordinatorError> {
+    let secret_keys: PrivateKeyChain = fs::read_to_string(keys_file)
+        .map_err(|err| err.to_string())
+        .and_then(|keys| serde_json::from_str(&keys).map_err(|err| err.to_string()))
+        .map_err(|err| CoordinatorError::ConfigParse(format!("{keys_file}: {err}")))?;
+
+    let wallet = recover_wallet(&build_starknet_client(args), secret_keys)
+        .await
+        .map_err(|err| CoordinatorError::WalletRecovery(err.to_string()))?;
+    let serialized = serde_json::to_string_pretty(&wallet)
+        .map_err(|err| CoordinatorError::WalletRecovery(err.to_string()))?;
+
+    match args.wallet_file.as_ref() {
+        Some(wallet_file) => {
+            let mut wallets = args.wallets.clone();
+            wallets.retain(|existing| existing.wallet_id != wallet.wallet_id);
+            wallets.push(wallet);
+
+            serde_json::to_string_pretty(&wallets)
+                .map_err(|err| err.to_string())
+                .and_then(|serialized| {
+                    fs::write(wallet_file, serialized).map_err(|err| err.to_string())
+                })
+                .map_err(CoordinatorError::WalletRecovery)?;
+        }
+        None => log::warn!("no wallet file configured, the recovered wallet is not persisted"),
+    }
+
+    println!("{}", serialized);
+    Ok(())
+}
+
+/// Write a snapshot of the managed wallets back to the wallet file, so that a restarted
+/// relayer picks up any updates the wallets received while it was running
+async fn flush_wallet_snapshot(global_state: &RelayerState, wallet_file: Option<String>) {
+    let wallet_file = match wallet_file {
+        Some(wallet_file) => wallet_file,
+        None => return,
+    };
+
+    let wallets = global_state
+        .read_wallet_index()
+        .await
+        .get_all_wallets()
+        .await;
+    let res = serde_json::to_string_pretty(&wallets)
+        .map_err(|err| err.to_string())
+        .and_then(|serialized| fs::write(&wallet_file, serialized).map_err(|err| err.to_string()));
+    if let Err(err) = res {
+        log::error!("error writing wallet snapshot to {wallet_file}: {err}");
+    }
+}
+
+/// Cancel a worker and wait for it to exit, up to a timeout
+async fn shutdown_worker(
+    name: &str,
+    cancel_sender: &WatchSender<()>,
+    exit_receiver: &mut MpscReceiver<()>,
+) {
+    if cancel_sender.send(()).is_err() {
+        // The worker has already dropped its cancel channel, i.e. it is not running
+        return;
+    }
+
+    let exit = timeout(
+        Duration::from_millis(WORKER_SHUTDOWN_TIMEOUT_MS),
+        exit_receiver.recv(),
+    );
+    if exit.await.is_err() {
+        log::warn!("timed out waiting for {name} to exit");
+    }
+}
+
+/// Whether the given worker is allocated and has the given name
+fn is_named<W: Worker>(worker: Option<&W>, name: &str) -> bool {
+    worker.map_or(false, |worker| worker.name() == name)
+}
+
 /// Configures the default log capture which logs to stdout
-fn configure_default_log_capture() {
-    Builder::new()
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "{} [{}] - {}",
-                Local::now().format("%Y-%m-%dT%H:%M:%S"),
-                record.level(),
-                record.args()
-            )
-        })
-        .filter(None, LevelFilter::Info)
-        .init();
+fn configure_default_log_capture(log_config: &LogConfig) -> LogFilterHandle {
+    configure_log_capture(log_config).expect("failed to configure log capture")
+}
+
+/// Build a tracker for a worker's restarts under the given policy
+fn restart_tracker(policy: RestartPolicy, rng: &WorkerRng) -> RestartTracker {
+    RestartTracker::new(policy, system_clock(), rng.fork())
+}
+
+/// Record a worker's status in the global state and publish it on the system bus
+fn report_worker_status(
+    worker: String,
+    status: WorkerStatus,
+    global_state: &RelayerState,
+    system_bus: &SystemBus<SystemBusMessage>,
+) {
+    global_state.set_worker_status(worker.clone(), status.clone());
+    system_bus.publish(
+        WORKER_STATUS_TOPIC.to_string(),
+        SystemBusMessage::WorkerStatusChanged { worker, status },
+    );
 }
 
 /// Attempt to recover a failed module by cleaning up its resources and re-allocating it
-fn recover_worker<W: Worker>(failed_worker: W) -> Result<W, CoordinatorError> {
+///
+/// The restart is delayed by the worker's backoff. If the worker has exhausted its
+/// restart budget it is left down and returned as is, and the relayer continues without
+/// it; a worker that is not recoverable at all is fatal to the relayer
+async fn recover_worker<W: Worker>(
+    failed_worker: W,
+    restarts: &mut RestartTracker,
+    failure_channel: &MpscSender<()>,
+    global_state: &RelayerState,
+    system_bus: &SystemBus<SystemBusMessage>,
+) -> Result<W, CoordinatorError> {
+    let name = failed_worker.name();
+    if is_paused(&name, global_state) {
+        // A paused worker exits at the operator's request, it is restarted once resumed
+        return Ok(failed_worker);
+    }
+
     if !failed_worker.is_recoverable() {
+        report_worker_status(
+            name.clone(),
+            WorkerStatus::Down {
+                code: ErrorCode::Internal,
+                reason: "worker is not recoverable".to_string(),
+            },
+            global_state,
+            system_bus,
+        );
         return Err(CoordinatorError::Recovery(format!(
             "worker {} is not recoverable",
-            failed_worker.name()
+            name
         )));
     }
 
-    Ok(failed_worker.recover())
+    let backoff = match restarts.next_backoff() {
+        Some(backoff) => backoff,
+        None => {
+            log::error!("worker {name} is crash looping, leaving it down");
+            report_worker_status(
+                name,
+                WorkerStatus::Down {
+                    code: ErrorCode::Internal,
+                    reason: "restart budget exhausted".to_string(),
+                },
+                global_state,
+                system_bus,
+            );
+            return Ok(failed_worker);
+        }
+    };
+
+    log::warn!(
+        "worker {name} failed, restarting in {}ms (attempt {})",
+        backoff.as_millis(),
+        restarts.num_restarts()
+    );
+    report_worker_status(
+        name.clone(),
+        WorkerStatus::Restarting {
+            attempt: restarts.num_restarts(),
+            backoff_ms: backoff.as_millis() as u64,
+        },
+        global_state,
+        system_bus,
+    );
+    sleep(backoff).await;
+
+    restart_worker(failed_worker, failure_channel, global_state, system_bus)
+}
+
+/// Clean up a worker that has exited, then re-allocate it, start it, and watch it
+fn restart_worker<W: Worker>(
+    mut failed_worker: W,
+    failure_channel: &MpscSender<()>,
+    global_state: &RelayerState,
+    system_bus: &SystemBus<SystemBusMessage>,
+) -> Result<W, CoordinatorError> {
+    let name = failed_worker.name();
+    failed_worker
+        .cleanup()
+        .map_err(|err| CoordinatorError::Worker {
+            worker: name.clone(),
+            source: err.into(),
+        })?;
+    let mut worker = failed_worker.recover();
+    worker.start().map_err(|err| CoordinatorError::Worker {
+        worker: name.clone(),
+        source: err.into(),
+    })?;
+    watch_worker(&mut worker, failure_channel.clone());
+
+    report_worker_status(name, WorkerStatus::Healthy, global_state, system_bus);
+    Ok(worker)
+}
+
+/// Whether the operator has paused the named worker
+fn is_paused(name: &str, global_state: &RelayerState) -> bool {
+    global_state.worker_statuses().get(name) == Some(&WorkerStatus::Paused)
+}
+
+/// Pause or resume a worker at the operator's request, responding with the worker's
+/// status once the command is applied
+///
+/// Pausing cancels the worker and leaves it down; resuming re-allocates it through the
+/// same path a failed worker is recovered through, without counting against its restart
+/// budget. Only workers that can be recovered after a cancellation may be paused
+async fn control_worker<W: Worker>(
+    worker: W,
+    message: WorkerControlMessage,
+    cancel_sender: &WatchSender<()>,
+    exit_receiver: &mut MpscReceiver<()>,
+    failure_channel: &MpscSender<()>,
+    global_state: &RelayerState,
+    system_bus: &SystemBus<SystemBusMessage>,
+) -> Result<W, CoordinatorError> {
+    let name = message.worker;
+    let paused = is_paused(&name, global_state);
+
+    let (worker, res) = match message.command {
+        WorkerCommand::Pause if paused => (worker, Ok(WorkerStatus::Paused)),
+        WorkerCommand::Pause if !worker.is_pausable() => {
+            (worker, Err(format!("worker {name} cannot be paused")))
+        }
+        WorkerCommand::Pause => {
+            log::info!("pausing worker {name}");
+
+            // The worker is marked paused before it is cancelled, so that its exit is not
+            // taken for a failure
+            report_worker_status(name.clone(), WorkerStatus::Paused, global_state, system_bus);
+            shutdown_worker(&name, cancel_sender, exit_receiver).await;
+            (worker, Ok(WorkerStatus::Paused))
+        }
+
+        WorkerCommand::Resume if !paused => (worker, Err(format!("worker {name} is not paused"))),
+        WorkerCommand::Resume if !worker.is_recoverable() => (
+            worker,
+            Err(format!("worker {name} has not yet exited, retry shortly")),
+        ),
+        WorkerCommand::Resume => {
+            log::info!("resuming worker {name}");
+            let worker = restart_worker(worker, failure_channel, global_state, system_bus)?;
+            (worker, Ok(WorkerStatus::Healthy))
+        }
+    };
+
+    // The requester may have given up waiting on the response
+    let _ = message.response_channel.send(res);
+    Ok(worker)
 }
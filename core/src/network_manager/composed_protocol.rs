@@ -4,30 +4,49 @@
 //!      1. RequestResponse: Used for p2p direct communication (e.g. heartbeat)
 //!      2. KAD: Used for peer discovery and application level routing information (e.g. wallet ownership)
 //!      3. GossipSub: a decentralized pubsub protocol, used for broadcast primitives.
+//!      4. Identify: used to learn the local node's publicly facing addresses.
+//!      5. Relay (v2): used to reach, and be reached by, peers behind a NAT through a
+//!         publicly reachable relay. The local node may optionally act as a relay itself.
+//!      6. DCUtR: used to upgrade a relayed connection to a direct one by hole punching.
 
 use async_trait::async_trait;
 use libp2p::{
     core::upgrade::{read_length_prefixed, write_length_prefixed},
+    dcutr::behaviour::{Behaviour as Dcutr, Event as DcutrEvent},
     futures::{AsyncRead, AsyncWrite, AsyncWriteExt},
     gossipsub::{Gossipsub, GossipsubConfig, GossipsubEvent, MessageAuthenticity},
     identify::{Behaviour as IdentifyProtocol, Config as IdentifyConfig, Event as IdentifyEvent},
     identity::Keypair,
-    kad::{record::store::MemoryStore, Kademlia, KademliaEvent},
+    kad::{
+        record::store::{MemoryStore, MemoryStoreConfig},
+        Kademlia, KademliaConfig, KademliaEvent,
+    },
+    relay::v2::{
+        client::{Client as RelayClient, Event as RelayClientEvent},
+        relay::{Event as RelayServerEvent, Relay},
+    },
     request_response::{
         ProtocolName, ProtocolSupport, RequestResponse, RequestResponseCodec, RequestResponseEvent,
     },
+    swarm::behaviour::toggle::Toggle,
     PeerId,
 };
 use libp2p_swarm_derive::NetworkBehaviour;
 use std::{
     fmt::{Display, Formatter},
     io::{Error as IoError, ErrorKind},
-    iter,
 };
 
-use crate::gossip_api::gossip::{AuthenticatedGossipRequest, AuthenticatedGossipResponse};
+use crate::gossip_api::{
+    codec::{decode_message, encode_message, WireEncoding},
+    gossip::{AuthenticatedGossipRequest, AuthenticatedGossipResponse},
+};
 
-use super::error::NetworkManagerError;
+use super::{
+    error::NetworkManagerError,
+    framing::{decode_frame, encode_frame},
+    providers::{MAX_PROVIDED_KEYS, PROVIDER_PUBLICATION_INTERVAL, PROVIDER_RECORD_TTL},
+};
 
 /**
  * Constants
@@ -52,28 +71,56 @@ pub struct ComposedNetworkBehavior {
     /// The identify protocol behavior, used for getting publicly facing information
     /// about the local node
     pub identify: IdentifyProtocol,
+    /// The relay client behavior, used to listen for and dial peers through a relay
+    /// when a direct connection cannot be made
+    pub relay_client: RelayClient,
+    /// The relay server behavior, enabled only if the local node is configured to relay
+    /// connections for other peers
+    pub relay_server: Toggle<Relay>,
+    /// The direct connection upgrade through relay behavior, used to hole punch a direct
+    /// connection to a peer that is connected through a relay
+    pub dcutr: Dcutr,
 }
 
 impl ComposedNetworkBehavior {
     /// Construct the behavior
+    ///
+    /// The local node advertises `protocol_version` via identify, and accepts request/response
+    /// substreams on every supported version
+    ///
+    /// The relay client is constructed alongside its transport, so it is passed in by the
+    /// caller; the relay server behavior is enabled if `relay_server` is set
     pub fn new(
         peer_id: PeerId,
         protocol_version: ProtocolVersion,
         keypair: Keypair,
+        relay_client: RelayClient,
+        relay_server: bool,
     ) -> Result<Self, NetworkManagerError> {
-        // Construct the point-to-point request response protocol
+        // Construct the point-to-point request response protocol, versions are listed in order
+        // of preference so that the newest version both peers support is negotiated
         let request_response = RequestResponse::new(
             RelayerGossipCodec::new(),
-            iter::once((
-                RelayerGossipProtocol::new(protocol_version),
-                ProtocolSupport::Full,
-            )),
+            SUPPORTED_PROTOCOL_VERSIONS
+                .iter()
+                .map(|version| (RelayerGossipProtocol::new(*version), ProtocolSupport::Full)),
             Default::default(),
         );
 
-        // Construct the peer info KDHT
-        let memory_store = MemoryStore::new(peer_id);
-        let kademlia_dht = Kademlia::new(peer_id, memory_store);
+        // Construct the peer info KDHT, the local node publishes a provider record for each
+        // order and wallet it manages
+        let memory_store = MemoryStore::with_config(
+            peer_id,
+            MemoryStoreConfig {
+                max_provided_keys: MAX_PROVIDED_KEYS,
+                ..Default::default()
+            },
+        );
+        let mut kademlia_config = KademliaConfig::default();
+        kademlia_config
+            .set_provider_record_ttl(Some(PROVIDER_RECORD_TTL))
+            .set_provider_publication_interval(Some(PROVIDER_PUBLICATION_INTERVAL));
+        let kademlia_dht = Kademlia::with_config(peer_id, memory_store, kademlia_config);
 
         // Construct the pubsub network behavior
         let pubsub = Gossipsub::new(
@@ -89,11 +136,19 @@ impl ComposedNetworkBehavior {
             keypair.public(),
         ));
 
+        // Relay connections for other peers only if configured to
+        let relay_server = Toggle::from(
+            relay_server.then(|| Relay::new(peer_id, Default::default() /* config */)),
+        );
+
         Ok(Self {
             request_response,
             kademlia_dht,
             pubsub,
             identify,
+            relay_client,
+            relay_server,
+            dcutr: Dcutr::new(),
         })
     }
 }
@@ -111,6 +166,12 @@ pub enum ComposedProtocolEvent {
     PubSub(GossipsubEvent),
     /// An event from the identify behavior
     Identify(IdentifyEvent),
+    /// An event from the relay client behavior; e.g. a reservation accepted by a relay
+    RelayClient(RelayClientEvent),
+    /// An event from the relay server behavior; e.g. a circuit opened for another peer
+    RelayServer(RelayServerEvent),
+    /// An event from the DCUtR behavior; e.g. a relayed connection upgraded to direct
+    Dcutr(DcutrEvent),
 }
 
 /// Composed event trait implementations; simply choose the correct enum value
@@ -142,21 +203,91 @@ impl From<IdentifyEvent> for ComposedProtocolEvent {
     }
 }
 
+impl From<RelayClientEvent> for ComposedProtocolEvent {
+    fn from(e: RelayClientEvent) -> Self {
+        ComposedProtocolEvent::RelayClient(e)
+    }
+}
+
+impl From<RelayServerEvent> for ComposedProtocolEvent {
+    fn from(e: RelayServerEvent) -> Self {
+        ComposedProtocolEvent::RelayServer(e)
+    }
+}
+
+impl From<DcutrEvent> for ComposedProtocolEvent {
+    fn from(e: DcutrEvent) -> Self {
+        ComposedProtocolEvent::Dcutr(e)
+    }
+}
+
 /**
  * Heartbeat protocol versioning, metadata, and codec
  */
 
+/// The version of the protocol the local node advertises
+pub const CURRENT_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::Version2;
+/// The versions of the protocol the local node speaks, in order of preference
+pub const SUPPORTED_PROTOCOL_VERSIONS: [ProtocolVersion; 3] = [
+    ProtocolVersion::Version2,
+    ProtocolVersion::Version1,
+    ProtocolVersion::Version0,
+];
+
 /// Specifies versioning information about the protocol
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProtocolVersion {
-    /// The version of the protocol in use
+    /// The initial version of the protocol, messages are sent as raw JSON
     Version0,
+    /// Messages are framed and compressed above a size threshold, and requests to the same
+    /// peer may be batched
+    Version1,
+    /// Messages are encoded in a compact binary format rather than JSON, and are otherwise
+    /// sent as in `Version1`
+    Version2,
+}
+
+impl ProtocolVersion {
+    /// Parse a version from the string a peer reports via identify, returns `None` if the
+    /// version is not supported by the local node
+    pub fn from_reported(reported: &str) -> Option<Self> {
+        SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|version| version.to_string() == reported)
+            .copied()
+    }
+
+    /// Whether messages sent with this version are framed and possibly compressed
+    pub fn supports_framing(&self) -> bool {
+        match self {
+            ProtocolVersion::Version0 => false,
+            ProtocolVersion::Version1 | ProtocolVersion::Version2 => true,
+        }
+    }
+
+    /// Whether requests sent with this version may be batched
+    pub fn supports_batching(&self) -> bool {
+        match self {
+            ProtocolVersion::Version0 => false,
+            ProtocolVersion::Version1 | ProtocolVersion::Version2 => true,
+        }
+    }
+
+    /// The encoding messages sent with this version are serialized with
+    pub fn wire_encoding(&self) -> WireEncoding {
+        match self {
+            ProtocolVersion::Version0 | ProtocolVersion::Version1 => WireEncoding::Json,
+            ProtocolVersion::Version2 => WireEncoding::Binary,
+        }
+    }
 }
 
 impl Display for ProtocolVersion {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
             ProtocolVersion::Version0 => "0.0.0",
+            ProtocolVersion::Version1 => "0.1.0",
+            ProtocolVersion::Version2 => "0.2.0",
         })
     }
 }
@@ -179,6 +310,8 @@ impl ProtocolName for RelayerGossipProtocol {
     fn protocol_name(&self) -> &[u8] {
         match self.version {
             ProtocolVersion::Version0 => b"/relayer-gossip/1.0",
+            ProtocolVersion::Version1 => b"/relayer-gossip/1.1",
+            ProtocolVersion::Version2 => b"/relayer-gossip/1.2",
         }
     }
 }
@@ -192,6 +325,40 @@ impl RelayerGossipCodec {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Read a message from the socket, unwrapping its frame if the protocol is framed
+    async fn read_message<T>(
+        protocol: &RelayerGossipProtocol,
+        io: &mut T,
+    ) -> Result<Vec<u8>, IoError>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let data = read_length_prefixed(io, MAX_MESSAGE_SIZE).await?;
+        if protocol.version.supports_framing() {
+            decode_frame(data, MAX_MESSAGE_SIZE)
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// Write a message to the socket, framing it if the protocol is framed
+    async fn write_message<T>(
+        protocol: &RelayerGossipProtocol,
+        io: &mut T,
+        data: &[u8],
+    ) -> Result<(), IoError>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        if protocol.version.supports_framing() {
+            write_length_prefixed(io, encode_frame(data)?).await?;
+        } else {
+            write_length_prefixed(io, data).await?;
+        }
+
+        io.close().await
+    }
 }
 
 #[async_trait]
@@ -203,43 +370,49 @@ impl RequestResponseCodec for RelayerGossipCodec {
     /// Deserializes a read request
     async fn read_request<T>(
         &mut self,
-        _: &RelayerGossipProtocol,
+        protocol: &RelayerGossipProtocol,
         io: &mut T,
     ) -> Result<Self::Request, IoError>
     where
         T: AsyncRead + Unpin + Send,
     {
-        let req_data = read_length_prefixed(io, MAX_MESSAGE_SIZE).await?;
+        let req_data = Self::read_message(protocol, io).await?;
         if req_data.is_empty() {
             return Err(IoError::new(ErrorKind::InvalidData, "empty request"));
         }
 
-        let deserialized: AuthenticatedGossipRequest = serde_json::from_slice(&req_data).unwrap();
-        Ok(deserialized)
+        decode_message(
+            protocol.version.wire_encoding(),
+            &req_data,
+            MAX_MESSAGE_SIZE,
+        )
     }
 
     /// Deserializes a read response
     async fn read_response<T>(
         &mut self,
-        _: &RelayerGossipProtocol,
+        protocol: &RelayerGossipProtocol,
         io: &mut T,
     ) -> Result<Self::Response, IoError>
     where
         T: AsyncRead + Unpin + Send,
     {
-        let resp_data = read_length_prefixed(io, MAX_MESSAGE_SIZE).await?;
+        let resp_data = Self::read_message(protocol, io).await?;
         if resp_data.is_empty() {
             return Err(IoError::new(ErrorKind::InvalidData, "empty response"));
         }
 
-        let deserialized: AuthenticatedGossipResponse = serde_json::from_slice(&resp_data).unwrap();
-        Ok(deserialized)
+        decode_message(
+            protocol.version.wire_encoding(),
+            &resp_data,
+            MAX_MESSAGE_SIZE,
+        )
     }
 
     /// Serializes a write request
     async fn write_request<T>(
         &mut self,
-        _: &RelayerGossipProtocol,
+        protocol: &RelayerGossipProtocol,
         io: &mut T,
         req: Self::Request,
     ) -> Result<(), IoError>
@@ -247,17 +420,14 @@ impl RequestResponseCodec for RelayerGossipCodec {
         T: AsyncWrite + Unpin + Send,
     {
         // Serialize the data and write to socket
-        let serialized = serde_json::to_string(&req).unwrap();
-        write_length_prefixed(io, serialized.as_bytes()).await?;
-
-        io.close().await?;
-        Ok(())
+        let serialized = encode_message(protocol.version.wire_encoding(), &req)?;
+        Self::write_message(protocol, io, &serialized).await
     }
 
     /// Serializes a write response
     async fn write_response<T>(
         &mut self,
-        _: &RelayerGossipProtocol,
+        protocol: &RelayerGossipProtocol,
         io: &mut T,
         resp: Self::Response,
     ) -> Result<(), IoError>
@@ -265,10 +435,7 @@ impl RequestResponseCodec for RelayerGossipCodec {
         T: AsyncWrite + Unpin + Send,
     {
         // Serialize the response and write to socket
-        let serialized = serde_json::to_string(&resp).unwrap();
-        write_length_prefixed(io, serialized.as_bytes()).await?;
-
-        io.close().await?;
-        Ok(())
+        let serialized = encode_message(protocol.version.wire_encoding(), &resp)?;
+        Self::write_message(protocol, io, &serialized).await
     }
 }
@@ -0,0 +1,207 @@
+//! Discovers peers beyond those given as bootstrap servers
+//!
+//! Discovery proceeds in three ways:
+//!     1. DNS seeds: each seed name is dialed as a `/dnsaddr` multiaddr, which the transport
+//!        resolves to the peers listed in the seed's `_dnsaddr` TXT records
+//!     2. Random walks: the local node periodically looks up the peers closest to a random
+//!        key in the Kademlia DHT, filling its routing table as it goes
+//!     3. Cluster lookups: every node provides a record keyed by its cluster ID, the local
+//!        node periodically looks up the providers of its own cluster's record and dials any
+//!        it is not connected to
+//!
+//! Peers dialed through discovery are capped at a configured number of outbound connections;
+//! dials requested elsewhere in the relayer are not subject to the cap
+
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use libp2p::{
+    kad::{record::Key as RecordKey, QueryId},
+    multiaddr::Protocol,
+    Multiaddr, PeerId,
+};
+
+use crate::gossip::types::ClusterId;
+
+/// The interval at which the local node walks the DHT and looks up its cluster peers
+pub(super) const DISCOVERY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Build the DHT key under which the members of a cluster are recorded
+pub fn cluster_provider_key(cluster_id: &ClusterId) -> RecordKey {
+    RecordKey::new(&format!("cluster/{}", cluster_id))
+}
+
+/// Build the multiaddr that a DNS seed name is dialed at
+pub fn dns_seed_addr(seed: &str) -> Multiaddr {
+    Multiaddr::empty().with(Protocol::Dnsaddr(seed.into()))
+}
+
+/// Tracks the state of peer discovery for the network manager's executor
+#[derive(Debug)]
+pub(super) struct PeerDiscovery {
+    /// The DNS seeds to dial when the local node has no connections
+    dns_seeds: Vec<Multiaddr>,
+    /// The DHT key under which the local cluster's members are recorded
+    cluster_key: RecordKey,
+    /// The maximum number of outbound connections to hold open to discovered peers
+    max_outbound_connections: usize,
+    /// The peers the local node holds an outbound connection to
+    outbound_peers: HashSet<PeerId>,
+    /// The peers dialed through discovery whose connections have not yet been established
+    pending_dials: HashSet<PeerId>,
+    /// The outstanding lookup of the local cluster's members, if any
+    cluster_lookup: Option<QueryId>,
+    /// The time at which discovery last ran
+    last_run: Option<Instant>,
+}
+
+impl PeerDiscovery {
+    /// Constructor
+    pub fn new(
+        dns_seeds: Vec<Multiaddr>,
+        cluster_id: &ClusterId,
+        max_outbound_connections: usize,
+    ) -> Self {
+        Self {
+            dns_seeds,
+            cluster_key: cluster_provider_key(cluster_id),
+            max_outbound_connections,
+            outbound_peers: HashSet::new(),
+            pending_dials: HashSet::new(),
+            cluster_lookup: None,
+            last_run: None,
+        }
+    }
+
+    /// The DNS seeds to dial
+    pub fn dns_seeds(&self) -> &[Multiaddr] {
+        &self.dns_seeds
+    }
+
+    /// The DHT key under which the local cluster's members are recorded
+    pub fn cluster_key(&self) -> &RecordKey {
+        &self.cluster_key
+    }
+
+    /// Whether discovery is due to run
+    pub fn discovery_due(&self) -> bool {
+        self.last_run
+            .map_or(true, |last| last.elapsed() >= DISCOVERY_INTERVAL)
+    }
+
+    /// Record that discovery has run, issuing the given cluster lookup
+    pub fn record_run(&mut self, cluster_lookup: QueryId) {
+        self.cluster_lookup = Some(cluster_lookup);
+        self.last_run = Some(Instant::now());
+    }
+
+    /// Whether the given query is the outstanding cluster lookup
+    pub fn is_cluster_lookup(&self, query_id: &QueryId) -> bool {
+        self.cluster_lookup.as_ref() == Some(query_id)
+    }
+
+    /// Complete the outstanding cluster lookup
+    pub fn complete_cluster_lookup(&mut self) {
+        self.cluster_lookup = None;
+    }
+
+    /// Select the peers to dial from a set of discovered peers, reserving an outbound slot
+    /// for each
+    ///
+    /// `is_connected` reports whether the local node is already connected to a peer
+    pub fn select_dials<F>(&mut self, peers: Vec<PeerId>, is_connected: F) -> Vec<PeerId>
+    where
+        F: Fn(&PeerId) -> bool,
+    {
+        let mut selected = Vec::new();
+        for peer_id in peers.into_iter() {
+            if self.outbound_peers.len() + self.pending_dials.len() >= self.max_outbound_connections
+            {
+                break;
+            }
+
+            if is_connected(&peer_id) || self.pending_dials.contains(&peer_id) {
+                continue;
+            }
+
+            self.pending_dials.insert(peer_id);
+            selected.push(peer_id);
+        }
+
+        selected
+    }
+
+    /// Record that a connection to a peer was established, returns whether the peer was
+    /// dialed through discovery
+    pub fn connection_established(&mut self, peer_id: PeerId, outbound: bool) -> bool {
+        if outbound {
+            self.outbound_peers.insert(peer_id);
+        }
+
+        self.pending_dials.remove(&peer_id)
+    }
+
+    /// Record that a dial to a peer failed
+    pub fn dial_failed(&mut self, peer_id: &PeerId) {
+        self.pending_dials.remove(peer_id);
+    }
+
+    /// Record that every connection to a peer was closed
+    pub fn connection_closed(&mut self, peer_id: &PeerId) {
+        self.outbound_peers.remove(peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::PeerId;
+    use std::str::FromStr;
+
+    use crate::gossip::types::ClusterId;
+
+    use super::{dns_seed_addr, PeerDiscovery};
+
+    /// Tests that discovered peers are dialed up to the outbound connection cap, and that
+    /// connected peers are skipped
+    #[test]
+    fn test_select_dials() {
+        let cluster_id = ClusterId::from_str("cluster").unwrap();
+        let mut discovery = PeerDiscovery::new(vec![], &cluster_id, 2 /* max_outbound */);
+
+        let connected = PeerId::random();
+        let peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let mut candidates = vec![connected];
+        candidates.extend(peers.iter().cloned());
+
+        let selected = discovery.select_dials(candidates.clone(), |peer| *peer == connected);
+        assert_eq!(selected, peers[..2].to_vec());
+
+        // No slots free up until a dial fails or a connection closes
+        assert!(discovery
+            .select_dials(candidates.clone(), |_| false)
+            .is_empty());
+        assert!(discovery.connection_established(peers[0], true /* outbound */));
+        discovery.dial_failed(&peers[1]);
+        assert_eq!(
+            discovery.select_dials(vec![peers[2]], |_| false),
+            vec![peers[2]]
+        );
+
+        discovery.connection_closed(&peers[0]);
+        assert_eq!(
+            discovery.select_dials(vec![peers[1]], |_| false),
+            vec![peers[1]]
+        );
+    }
+
+    /// Tests that a DNS seed name is dialed as a `dnsaddr` multiaddr
+    #[test]
+    fn test_dns_seed_addr() {
+        assert_eq!(
+            dns_seed_addr("seed.example.com").to_string(),
+            "/dnsaddr/seed.example.com"
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::fmt::Display;
 
+use crate::error::{ErrorCode, ErrorCoded};
+
 /// The generic error type for the network manager
 #[derive(Clone, Debug)]
 pub enum NetworkManagerError {
@@ -19,8 +21,22 @@ pub enum NetworkManagerError {
     SetupError(String),
 }
 
+impl std::error::Error for NetworkManagerError {}
 impl Display for NetworkManagerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+impl ErrorCoded for NetworkManagerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            NetworkManagerError::Authentication(_) => ErrorCode::Unauthorized,
+            NetworkManagerError::Cancelled(_) => ErrorCode::Cancelled,
+            NetworkManagerError::EnqueueJob(_) => ErrorCode::Internal,
+            NetworkManagerError::Network(_) => ErrorCode::Network,
+            NetworkManagerError::SerializeDeserialize(_) => ErrorCode::InvalidMessage,
+            NetworkManagerError::SetupError(_) => ErrorCode::Setup,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! The framing layer of the gossip protocol, negotiated from `ProtocolVersion::Version1`
+//! onwards
+//!
+//! On the wire, each framed message is prefixed with a single byte indicating whether the
+//! remainder is a raw or snappy compressed message; only messages above a size threshold are
+//! compressed. Above the wire, small requests to the same peer are coalesced into a single
+//! `GossipRequest::Batch` by the network manager's executor
+
+use libp2p::PeerId;
+use snap::raw::{decompress_len, Decoder, Encoder};
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind},
+    mem,
+};
+
+use crate::gossip_api::gossip::{AuthenticatedGossipRequest, GossipRequest};
+
+/// Messages at or below this size are sent uncompressed
+pub(super) const COMPRESSION_THRESHOLD_BYTES: usize = 1024;
+/// The maximum number of requests coalesced into a single batch
+pub(super) const MAX_BATCH_SIZE: usize = 32;
+
+/// The frame flag indicating that the payload is uncompressed
+const FRAME_FLAG_UNCOMPRESSED: u8 = 0;
+/// The frame flag indicating that the payload is snappy compressed
+const FRAME_FLAG_SNAPPY: u8 = 1;
+
+// -----------
+// | Framing |
+// -----------
+
+/// Frame a serialized message, compressing it if it is above the compression threshold
+pub(super) fn encode_frame(payload: &[u8]) -> Result<Vec<u8>, IoError> {
+    if payload.len() <= COMPRESSION_THRESHOLD_BYTES {
+        let mut frame = Vec::with_capacity(payload.len() + 1);
+        frame.push(FRAME_FLAG_UNCOMPRESSED);
+        frame.extend_from_slice(payload);
+        return Ok(frame);
+    }
+
+    let compressed = Encoder::new()
+        .compress_vec(payload)
+        .map_err(|err| IoError::new(ErrorKind::InvalidData, err))?;
+    let mut frame = Vec::with_capacity(compressed.len() + 1);
+    frame.push(FRAME_FLAG_SNAPPY);
+    frame.extend(compressed);
+    Ok(frame)
+}
+
+/// Recover the serialized message from a frame, refusing to decompress a payload larger
+/// than `max_size`
+pub(super) fn decode_frame(mut frame: Vec<u8>, max_size: usize) -> Result<Vec<u8>, IoError> {
+    if frame.is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidData, "empty frame"));
+    }
+
+    let payload = frame.split_off(1);
+    match frame[0] {
+        FRAME_FLAG_UNCOMPRESSED => Ok(payload),
+        FRAME_FLAG_SNAPPY => {
+            let decompressed_len = decompress_len(&payload)
+                .map_err(|err| IoError::new(ErrorKind::InvalidData, err))?;
+            if decompressed_len > max_size {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "decompressed frame exceeds maximum message size",
+                ));
+            }
+
+            Decoder::new()
+                .decompress_vec(&payload)
+                .map_err(|err| IoError::new(ErrorKind::InvalidData, err))
+        }
+        flag => Err(IoError::new(
+            ErrorKind::InvalidData,
+            format!("unknown frame flag {}", flag),
+        )),
+    }
+}
+
+// ------------
+// | Batching |
+// ------------
+
+/// Buffers outbound requests by peer until they are flushed as batches
+///
+/// The executor runs without a timer, so nothing is held back waiting for more requests;
+/// the executor pushes the requests already queued on its channel and flushes before it
+/// next blocks. Requests enqueued in a burst, e.g. proofs shared with cluster peers, are
+/// coalesced this way
+#[derive(Debug, Default)]
+pub(super) struct OutboundBatcher {
+    /// The requests awaiting a flush, indexed by the peer they are sent to
+    pending: HashMap<PeerId, Vec<AuthenticatedGossipRequest>>,
+}
+
+impl OutboundBatcher {
+    /// Constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffer a request to the given peer, returns the peer's batch if it is full
+    pub fn push(
+        &mut self,
+        peer_id: PeerId,
+        request: AuthenticatedGossipRequest,
+    ) -> Option<AuthenticatedGossipRequest> {
+        let pending = self.pending.entry(peer_id).or_default();
+        pending.push(request);
+
+        if pending.len() >= MAX_BATCH_SIZE {
+            self.take(&peer_id)
+        } else {
+            None
+        }
+    }
+
+    /// Take the batch buffered for a peer, if any
+    pub fn take(&mut self, peer_id: &PeerId) -> Option<AuthenticatedGossipRequest> {
+        self.pending.remove(peer_id).and_then(Self::into_request)
+    }
+
+    /// Take the batches buffered for all peers
+    pub fn drain(&mut self) -> Vec<(PeerId, AuthenticatedGossipRequest)> {
+        mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|(peer_id, requests)| {
+                Self::into_request(requests).map(|request| (peer_id, request))
+            })
+            .collect()
+    }
+
+    /// Wrap a set of buffered requests into a single request; a lone request is sent as is
+    fn into_request(
+        mut requests: Vec<AuthenticatedGossipRequest>,
+    ) -> Option<AuthenticatedGossipRequest> {
+        match requests.len() {
+            0 => None,
+            1 => requests.pop(),
+            // Each request in the batch is signed individually, the batch itself is not
+            _ => Some(AuthenticatedGossipRequest {
+                sig: Vec::new(),
+                body: GossipRequest::Batch(requests),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::PeerId;
+
+    use crate::gossip_api::{
+        cluster_management::ReplicateRequestBody,
+        gossip::{AuthenticatedGossipRequest, GossipRequest},
+    };
+
+    use super::{
+        decode_frame, encode_frame, OutboundBatcher, COMPRESSION_THRESHOLD_BYTES, MAX_BATCH_SIZE,
+    };
+
+    /// Build a request that may be batched
+    fn batchable_request() -> AuthenticatedGossipRequest {
+        AuthenticatedGossipRequest {
+            sig: Vec::new(),
+            body: GossipRequest::Replicate(ReplicateRequestBody {
+                wallets: Vec::new(),
+            }),
+        }
+    }
+
+    /// Tests that small and large messages are recovered from their frames, and that only
+    /// large messages are compressed
+    #[test]
+    fn test_frame_roundtrip() {
+        let small = vec![1u8; COMPRESSION_THRESHOLD_BYTES];
+        let small_frame = encode_frame(&small).unwrap();
+        assert_eq!(small_frame.len(), small.len() + 1);
+        assert_eq!(decode_frame(small_frame, usize::MAX).unwrap(), small);
+
+        let large = vec![1u8; 10 * COMPRESSION_THRESHOLD_BYTES];
+        let large_frame = encode_frame(&large).unwrap();
+        assert!(large_frame.len() < large.len());
+        assert_eq!(
+            decode_frame(large_frame.clone(), usize::MAX).unwrap(),
+            large
+        );
+
+        // A frame that decompresses beyond the size limit is rejected
+        assert!(decode_frame(large_frame, large.len() - 1).is_err());
+    }
+
+    /// Tests that requests are batched by peer, and flushed when a batch is full
+    #[test]
+    fn test_batcher() {
+        let peer1 = PeerId::random();
+        let peer2 = PeerId::random();
+        let mut batcher = OutboundBatcher::new();
+
+        assert!(batcher.push(peer1, batchable_request()).is_none());
+        assert!(batcher.push(peer1, batchable_request()).is_none());
+        assert!(batcher.push(peer2, batchable_request()).is_none());
+
+        let mut flushed = batcher.drain();
+        flushed.sort_by_key(|(peer_id, _)| *peer_id == peer2);
+        assert!(matches!(
+            &flushed[0],
+            (peer_id, AuthenticatedGossipRequest { body: GossipRequest::Batch(requests), .. })
+                if *peer_id == peer1 && requests.len() == 2
+        ));
+        assert!(matches!(
+            &flushed[1],
+            (peer_id, AuthenticatedGossipRequest { body: GossipRequest::Replicate(..), .. })
+                if *peer_id == peer2
+        ));
+        assert!(batcher.drain().is_empty());
+
+        // A full batch is returned as soon as it fills
+        for _ in 0..MAX_BATCH_SIZE - 1 {
+            assert!(batcher.push(peer1, batchable_request()).is_none());
+        }
+        assert!(batcher.push(peer1, batchable_request()).is_some());
+        assert!(batcher.take(&peer1).is_none());
+    }
+}
@@ -0,0 +1,417 @@
+//! Rotation of the key that the local cluster signs its gossip with
+//!
+//! The cluster keypair given in the config is the cluster's master key, and its public key is
+//! the `ClusterId`. Epoch zero signs with the master key itself, so that a cluster that never
+//! rotates signs as it always has. Each later epoch signs with a fresh random key, which is not
+//! derived from the master key; a compromised epoch key is thereby only useful until the
+//! cluster rotates past it, and reveals nothing of the keys of later epochs.
+//!
+//! Only a node configured to rotate on a schedule keeps the master key. It announces each new
+//! epoch in a `ClusterKeyRotation` that carries the epoch's public key signed by the master
+//! key, and the epoch's secret key sealed under the previous epoch's symmetric key. Its cluster
+//! peers verify only the master signature against the `ClusterId`, and open the new secret with
+//! the key of the epoch they hold; they never hold the master key past epoch zero. A peer that
+//! has missed an epoch cannot open the secret and must be re-provisioned with the current key.
+//!
+//! The symmetric key that cluster messages are encrypted under is derived from the epoch key,
+//! so it rotates along with it. The current announcement is re-published periodically so that a
+//! peer that missed it catches up. After a rotation, signatures and ciphertexts under the
+//! previous epoch's keys are accepted for a grace period, so that messages sent before the
+//! rotation reached every peer are not rejected
+
+use std::time::{Duration, Instant};
+
+use ed25519_dalek::{Keypair as SigKeypair, PublicKey, SignatureError};
+use rand_core::OsRng;
+
+use crate::{
+    clock::SharedClock,
+    gossip::types::ClusterId,
+    gossip_api::{
+        cluster_encryption::{ClusterSymmetricKey, PubsubEnvelope},
+        cluster_management::ClusterKeyRotation,
+        gossip::AuthenticatedPubsubMessage,
+    },
+};
+
+use super::error::NetworkManagerError;
+
+/// The interval at which the current epoch is re-announced to the cluster
+const ANNOUNCEMENT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The default period after a rotation in which the previous epoch's key is still accepted
+pub const DEFAULT_KEY_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60); // 5 minutes
+
+/// Error message emitted when a key rotation is not signed by the cluster's master key
+const ERR_ROTATION_SIGNATURE: &str = "key rotation is not signed by the cluster's master key";
+/// Error message emitted when the announced epoch secret does not open under the local key
+const ERR_ROTATION_SECRET: &str =
+    "announced epoch key is not sealed under the local peer's epoch key";
+
+/// The schedule on which the local cluster rotates its key
+#[derive(Clone, Copy, Debug)]
+pub struct ClusterKeyRotationConfig {
+    /// The interval at which the cluster advances to the next epoch, or `None` to rotate
+    /// only when a peer announces a rotation
+    ///
+    /// Only a node that rotates on a schedule keeps the cluster's master key
+    pub rotation_interval: Option<Duration>,
+    /// The period after a rotation in which the previous epoch's key is still accepted
+    pub grace_period: Duration,
+}
+
+impl Default for ClusterKeyRotationConfig {
+    fn default() -> Self {
+        Self {
+            rotation_interval: None,
+            grace_period: DEFAULT_KEY_GRACE_PERIOD,
+        }
+    }
+}
+
+/// The keys of the epoch before the current one, accepted until its grace period ends
+struct PreviousEpoch {
+    /// The public key of the epoch
+    public_key: PublicKey,
+    /// The symmetric key of the epoch
+    encryption_key: ClusterSymmetricKey,
+    /// The time at which the epoch's grace period ends
+    grace_end: Instant,
+}
+
+/// The local cluster's current epoch key, and the master key if the local peer rotates
+pub(super) struct ClusterKeyChain {
+    /// The ID of the local cluster
+    cluster_id: ClusterId,
+    /// The cluster's master key, which signs rotation announcements; held only if the
+    /// local peer rotates on a schedule
+    master_key: Option<SigKeypair>,
+    /// The current epoch
+    epoch: u64,
+    /// The key of the current epoch, which signs the local peer's gossip
+    epoch_key: SigKeypair,
+    /// The symmetric key derived from the current epoch's key
+    encryption_key: ClusterSymmetricKey,
+    /// The keys of the previous epoch, during its grace period
+    previous_epoch: Option<PreviousEpoch>,
+    /// The announcement of the current epoch, `None` in epoch zero
+    announcement: Option<ClusterKeyRotation>,
+    /// The time at which the cluster last advanced an epoch
+    last_rotation: Instant,
+    /// The time at which the current epoch was last announced
+    last_announced: Option<Instant>,
+    /// The rotation schedule
+    config: ClusterKeyRotationConfig,
+    /// The clock that rotations and grace periods are measured against
+    clock: SharedClock,
+}
+
+impl ClusterKeyChain {
+    /// Constructor, begins in epoch zero under the cluster keypair
+    ///
+    /// The cluster keypair is kept as the master key only if the config rotates on a schedule
+    pub fn new(
+        cluster_id: ClusterId,
+        cluster_keypair: SigKeypair,
+        config: ClusterKeyRotationConfig,
+        clock: SharedClock,
+    ) -> Self {
+        let master_key = config
+            .rotation_interval
+            .map(|_| SigKeypair::from_bytes(&cluster_keypair.to_bytes()).unwrap());
+
+        Self {
+            cluster_id,
+            master_key,
+            epoch: 0,
+            encryption_key: ClusterSymmetricKey::derive(&cluster_keypair),
+            epoch_key: cluster_keypair,
+            previous_epoch: None,
+            announcement: None,
+            last_rotation: clock.now(),
+            last_announced: None,
+            config,
+            clock,
+        }
+    }
+
+    /// The ID of the local cluster
+    pub fn cluster_id(&self) -> &ClusterId {
+        &self.cluster_id
+    }
+
+    /// The key the local peer signs its gossip with
+    pub fn signing_key(&self) -> &SigKeypair {
+        &self.epoch_key
+    }
+
+    /// The symmetric key the local peer encrypts cluster messages under
+    pub fn encryption_key(&self) -> &ClusterSymmetricKey {
+        &self.encryption_key
+    }
+
+    /// The current epoch
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Verify a signature under the keys currently accepted from the cluster; the current
+    /// epoch's key, and the previous epoch's key during its grace period
+    pub fn verify<F: Fn(&PublicKey) -> bool>(&self, verify_under: F) -> bool {
+        if verify_under(&self.epoch_key.public) {
+            return true;
+        }
+
+        match self.previous_epoch_in_grace() {
+            Some(previous) => verify_under(&previous.public_key),
+            None => false,
+        }
+    }
+
+    /// Open a pubsub envelope, decrypting it under the current epoch's symmetric key or the
+    /// previous epoch's during its grace period; returns `None` if it decrypts under neither
+    pub fn open(&self, envelope: PubsubEnvelope) -> Option<AuthenticatedPubsubMessage> {
+        match envelope {
+            PubsubEnvelope::Plaintext(message) => Some(message),
+            PubsubEnvelope::Encrypted(message) => {
+                self.encryption_key.decrypt(&message).or_else(|| {
+                    self.previous_epoch_in_grace()
+                        .and_then(|previous| previous.encryption_key.decrypt(&message))
+                })
+            }
+        }
+    }
+
+    /// Whether the cluster is due to advance to the next epoch
+    pub fn rotation_due(&self) -> bool {
+        self.master_key.is_some()
+            && self.config.rotation_interval.map_or(false, |interval| {
+                self.clock.now() >= self.last_rotation + interval
+            })
+    }
+
+    /// Whether the current epoch is due to be re-announced
+    ///
+    /// Epoch zero is never announced, every peer begins in it
+    pub fn announcement_due(&self) -> bool {
+        self.announcement.is_some()
+            && self.last_announced.map_or(true, |last| {
+                self.clock.now() >= last + ANNOUNCEMENT_INTERVAL
+            })
+    }
+
+    /// Advance to the next epoch under a fresh key, returning the announcement to publish
+    /// to the cluster
+    ///
+    /// Errors if the local peer does not hold the master key
+    pub fn rotate(&mut self) -> Result<ClusterKeyRotation, SignatureError> {
+        let master_key = self.master_key.as_ref().ok_or_else(SignatureError::new)?;
+        let epoch_key = SigKeypair::generate(&mut OsRng {});
+        let rotation = ClusterKeyRotation::new(
+            self.cluster_id.clone(),
+            self.epoch + 1,
+            &epoch_key,
+            &self.encryption_key,
+            master_key,
+        )?;
+
+        self.advance_to(rotation.clone(), epoch_key);
+        self.last_announced = Some(self.clock.now());
+        Ok(rotation)
+    }
+
+    /// The announcement of the current epoch to re-publish, `None` in epoch zero
+    pub fn announce(&mut self) -> Option<ClusterKeyRotation> {
+        self.last_announced = Some(self.clock.now());
+        self.announcement.clone()
+    }
+
+    /// Apply a rotation announced by a cluster peer
+    ///
+    /// Only the master key's signature is verified, the epoch key is taken from the
+    /// announcement. Returns whether the local peer advanced its epoch; announcements of the
+    /// current or an earlier epoch are ignored
+    pub fn apply_rotation(
+        &mut self,
+        rotation: &ClusterKeyRotation,
+    ) -> Result<bool, NetworkManagerError> {
+        self.cluster_id
+            .verify_key_rotation(rotation)
+            .map_err(|_| NetworkManagerError::Authentication(ERR_ROTATION_SIGNATURE.to_string()))?;
+        if rotation.epoch <= self.epoch {
+            return Ok(false);
+        }
+
+        let epoch_key = rotation
+            .open_epoch_key(&self.encryption_key)
+            .ok_or_else(|| NetworkManagerError::Authentication(ERR_ROTATION_SECRET.to_string()))?;
+
+        self.advance_to(rotation.clone(), epoch_key);
+        // The announcing peer has just published the epoch, there is no need to re-announce
+        // it until the interval elapses
+        self.last_announced = Some(self.clock.now());
+        Ok(true)
+    }
+
+    /// Advance to the announced epoch, beginning the grace period of the current epoch's keys
+    fn advance_to(&mut self, rotation: ClusterKeyRotation, epoch_key: SigKeypair) {
+        let now = self.clock.now();
+        let encryption_key = ClusterSymmetricKey::derive(&epoch_key);
+
+        self.previous_epoch = Some(PreviousEpoch {
+            public_key: self.epoch_key.public,
+            encryption_key: std::mem::replace(&mut self.encryption_key, encryption_key),
+            grace_end: now + self.config.grace_period,
+        });
+        self.epoch = rotation.epoch;
+        self.epoch_key = epoch_key;
+        self.announcement = Some(rotation);
+        self.last_rotation = now;
+    }
+
+    /// The previous epoch's keys, if its grace period has not yet ended
+    fn previous_epoch_in_grace(&self) -> Option<&PreviousEpoch> {
+        self.previous_epoch
+            .as_ref()
+            .filter(|previous| self.clock.now() < previous.grace_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use ed25519_dalek::{Digest, Keypair as SigKeypair, PublicKey, Sha512};
+    use libp2p::PeerId;
+    use rand_core::OsRng;
+
+    use crate::{
+        clock::ManualClock,
+        gossip::types::{ClusterId, WrappedPeerId},
+        gossip_api::{
+            cluster_encryption::PubsubEnvelope,
+            cluster_management::{ClusterKeyRotation, ClusterManagementMessage, ReplicatedMessage},
+            gossip::{AuthenticatedPubsubMessage, PubsubMessage},
+        },
+    };
+
+    use super::{ClusterKeyChain, ClusterKeyRotationConfig};
+
+    /// Build the key chains of two peers in the same cluster, the first of which rotates
+    /// every hour
+    fn setup() -> (ClusterKeyChain, ClusterKeyChain, ManualClock) {
+        let master_key = SigKeypair::generate(&mut OsRng {});
+        let cluster_id = ClusterId::new(&master_key.public);
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+
+        let build_chain = |rotation_interval| {
+            ClusterKeyChain::new(
+                cluster_id.clone(),
+                SigKeypair::from_bytes(&master_key.to_bytes()).unwrap(),
+                ClusterKeyRotationConfig {
+                    rotation_interval,
+                    grace_period: Duration::from_secs(60),
+                },
+                Arc::new(clock.clone()),
+            )
+        };
+        (
+            build_chain(Some(Duration::from_secs(60 * 60))),
+            build_chain(None),
+            clock,
+        )
+    }
+
+    /// Whether a message signed by the given key verifies under the key chain
+    fn verifies(chain: &ClusterKeyChain, signer: &SigKeypair) -> bool {
+        let digest = || {
+            let mut hash_digest = Sha512::new();
+            hash_digest.update(b"message");
+            hash_digest
+        };
+        let sig = signer.sign_prehashed(digest(), None).unwrap();
+        chain.verify(|key: &PublicKey| key.verify_prehashed(digest(), None, &sig).is_ok())
+    }
+
+    /// Seal a cluster message under the key chain's current symmetric key
+    fn seal(chain: &ClusterKeyChain) -> PubsubEnvelope {
+        let body = PubsubMessage::ClusterManagement {
+            cluster_id: chain.cluster_id.clone(),
+            message: ClusterManagementMessage::Replicated(ReplicatedMessage {
+                wallets: vec![],
+                peer_id: WrappedPeerId(PeerId::random()),
+            }),
+        };
+        let message = AuthenticatedPubsubMessage::new_with_body(body, chain.signing_key()).unwrap();
+        PubsubEnvelope::seal(message, chain.encryption_key())
+    }
+
+    /// Tests that a peer advances to an epoch announced by its cluster peer, and accepts the
+    /// previous epoch's keys only during the grace period
+    #[test]
+    fn test_rotation() {
+        let (mut rotating, mut peer, clock) = setup();
+        let old_key = SigKeypair::from_bytes(&rotating.signing_key().to_bytes()).unwrap();
+        let old_envelope = seal(&rotating);
+        assert!(!rotating.rotation_due());
+        assert!(!peer.rotation_due());
+
+        clock.advance(Duration::from_secs(60 * 60));
+        assert!(rotating.rotation_due());
+        assert!(!peer.rotation_due());
+        let rotation = rotating.rotate().unwrap();
+        assert_eq!(rotation.epoch, 1);
+        assert!(!rotating.rotation_due());
+
+        // The peer advances on the announcement, and ignores a replay of it
+        assert!(peer.apply_rotation(&rotation).unwrap());
+        assert!(!peer.apply_rotation(&rotation).unwrap());
+        assert_eq!(peer.signing_key().public, rotating.signing_key().public);
+        assert_ne!(peer.signing_key().public, old_key.public);
+
+        // The symmetric key rotates with the epoch key
+        assert!(peer.open(seal(&rotating)).is_some());
+        assert!(peer.open(old_envelope.clone()).is_some());
+
+        assert!(verifies(&peer, rotating.signing_key()));
+        assert!(verifies(&peer, &old_key));
+        clock.advance(Duration::from_secs(61));
+        assert!(verifies(&peer, rotating.signing_key()));
+        assert!(!verifies(&peer, &old_key));
+        assert!(peer.open(old_envelope).is_none());
+    }
+
+    /// Tests that a peer without the master key cannot rotate, and cannot open an epoch key
+    /// sealed under an epoch it does not hold
+    #[test]
+    fn test_peer_without_master_key() {
+        let (mut rotating, mut peer, clock) = setup();
+        assert!(peer.rotate().is_err());
+
+        clock.advance(Duration::from_secs(60 * 60));
+        rotating.rotate().unwrap();
+        clock.advance(Duration::from_secs(60 * 60));
+        let skipped = rotating.rotate().unwrap();
+
+        assert!(peer.apply_rotation(&skipped).is_err());
+        assert_eq!(peer.epoch(), 0);
+    }
+
+    /// Tests that a rotation not signed by the cluster's master key is rejected
+    #[test]
+    fn test_forged_rotation() {
+        let (_, mut peer, _) = setup();
+        let forger = SigKeypair::generate(&mut OsRng {});
+        let forged = ClusterKeyRotation::new(
+            peer.cluster_id.clone(),
+            1, /* epoch */
+            &forger,
+            peer.encryption_key(),
+            &forger,
+        )
+        .unwrap();
+
+        assert!(peer.apply_rotation(&forged).is_err());
+        assert_eq!(peer.epoch(), 0);
+    }
+}
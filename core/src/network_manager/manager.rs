@@ -1,24 +1,46 @@
 //! The network manager handles lower level interaction with the p2p network
 
-use ed25519_dalek::Keypair as SigKeypair;
-use futures::StreamExt;
+use futures::{executor::block_on, StreamExt};
 use itertools::Itertools;
 use libp2p::{
+    core::{
+        muxing::StreamMuxerBox,
+        transport::Boxed,
+        upgrade::{SelectUpgrade, Version},
+    },
+    dcutr::behaviour::Event as DcutrEvent,
+    dns::DnsConfig,
     gossipsub::{GossipsubEvent, GossipsubMessage, Sha256Topic},
+    identify::Event as IdentifyEvent,
     identity::Keypair,
+    kad::{record::Key as RecordKey, GetProvidersOk, KademliaEvent, QueryResult},
+    mplex::MplexConfig,
     multiaddr::Protocol,
+    noise::NoiseAuthenticated,
+    relay::v2::{
+        client::{ClientTransport, Event as RelayClientEvent},
+        relay::Event as RelayServerEvent,
+    },
     request_response::{RequestResponseEvent, RequestResponseMessage},
-    swarm::SwarmEvent,
-    Multiaddr, PeerId, Swarm,
+    swarm::{dial_opts::DialOpts, SwarmEvent},
+    tcp::{async_io::Transport as TcpTransport, Config as TcpConfig},
+    websocket::WsConfig,
+    yamux::YamuxConfig,
+    Multiaddr, PeerId, Swarm, Transport,
 };
 use libp2p_swarm::NetworkBehaviour;
 use mpc_ristretto::network::QuicTwoPartyNet;
 use portpicker::Port;
-use tokio::sync::mpsc::UnboundedSender as TokioSender;
 use tracing::log;
-
-use std::{net::SocketAddr, thread::JoinHandle};
-use tokio::sync::mpsc::UnboundedReceiver;
+use uuid::Uuid;
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    net::SocketAddr,
+    thread::JoinHandle,
+    time::Duration,
+};
 
 use crate::{
     default_wrapper::DefaultWrapper,
@@ -27,6 +49,7 @@ use crate::{
         types::{ClusterId, PeerInfo, WrappedPeerId},
     },
     gossip_api::{
+        cluster_encryption::PubsubEnvelope,
         cluster_management::{ClusterManagementMessage, ReplicatedMessage},
         gossip::{
             AuthenticatedGossipRequest, AuthenticatedGossipResponse, AuthenticatedPubsubMessage,
@@ -36,13 +59,21 @@ use crate::{
         orderbook_management::{OrderBookManagementMessage, OrderInfoResponse, ORDER_BOOK_TOPIC},
     },
     handshake::jobs::HandshakeExecutionJob,
-    state::RelayerState,
+    job_queue::{JobQueueReceiver, JobQueueSender},
+    state::{peer_auth::PeerAuthEventKind, RelayerState},
     CancelChannel,
 };
 
 use super::{
-    composed_protocol::{ComposedNetworkBehavior, ComposedProtocolEvent},
+    composed_protocol::{
+        ComposedNetworkBehavior, ComposedProtocolEvent, ProtocolVersion, CURRENT_PROTOCOL_VERSION,
+    },
+    discovery::PeerDiscovery,
     error::NetworkManagerError,
+    framing::OutboundBatcher,
+    key_rotation::ClusterKeyChain,
+    providers::{order_provider_key, wallet_provider_key, ProviderRecords},
+    rate_limit::{PeerRateLimiter, RateLimitDecision, BAN_SWEEP_INTERVAL},
     worker::NetworkManagerConfig,
 };
 
@@ -53,6 +84,16 @@ const ERR_NO_KNOWN_ADDR: &str = "no known address for peer";
 const ERR_PARSING_ADDR: &str = "could not parse Multiaddr to SocketAddr";
 /// Emitted when signature verification for an authenticated request fails
 const ERR_SIG_VERIFY: &str = "signature verification failed";
+/// Emitted when an encrypted pubsub message does not decrypt under the cluster's key
+const ERR_DECRYPT: &str = "pubsub message decryption failed";
+/// Emitted when a peer batches a request that may not be batched
+const ERR_NOT_BATCHABLE: &str = "request may not be batched";
+/// Emitted when a peer is reachable only through a relay it is already connected through,
+/// and hole punching did not yield a direct connection
+const ERR_NO_DIRECT_ADDR: &str = "no direct address for peer";
+
+/// The timeout applied to the connection upgrade of every transport
+const TRANSPORT_TIMEOUT: Duration = Duration::from_secs(20);
 
 // -----------
 // | Helpers |
@@ -71,6 +112,11 @@ fn multiaddr_to_socketaddr(mut addr: Multiaddr, port: Port) -> Option<SocketAddr
     None
 }
 
+/// Whether the multiaddr routes through a relay circuit rather than to the peer directly
+fn is_relayed(addr: &Multiaddr) -> bool {
+    addr.iter().any(|protoc| protoc == Protocol::P2pCircuit)
+}
+
 // -----------
 // | Manager |
 // -----------
@@ -98,17 +144,17 @@ impl NetworkManager {
     /// Setup global state after peer_id and address have been assigned
     pub(super) async fn update_global_state_after_startup(&self) {
         // Add self to peer info index
+        let mut local_info = PeerInfo::new_with_cluster_secret_key(
+            self.local_peer_id,
+            self.cluster_id.clone(),
+            self.local_addr.clone(),
+            self.config.cluster_keypair.as_ref().unwrap(),
+        );
+        local_info.set_zone(self.config.zone.clone());
+
         self.config
             .global_state
-            .add_single_peer(
-                self.local_peer_id,
-                PeerInfo::new_with_cluster_secret_key(
-                    self.local_peer_id,
-                    self.cluster_id.clone(),
-                    self.local_addr.clone(),
-                    self.config.cluster_keypair.as_ref().unwrap(),
-                ),
-            )
+            .add_single_peer(self.local_peer_id, local_info)
             .await;
     }
 
@@ -132,6 +178,37 @@ impl NetworkManager {
 
         Ok(())
     }
+
+    /// Build the transport the swarm dials and listens on
+    ///
+    /// Connections are made over TCP or websockets, or through a relay circuit if the peer
+    /// cannot be dialed directly; each is then authenticated with noise and multiplexed
+    pub(super) fn build_transport(
+        &self,
+        relay_transport: ClientTransport,
+    ) -> Result<Boxed<(PeerId, StreamMuxerBox)>, NetworkManagerError> {
+        let dns_tcp = block_on(DnsConfig::system(TcpTransport::new(
+            TcpConfig::default().nodelay(true),
+        )))
+        .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
+        let ws_dns_tcp = block_on(DnsConfig::system(TcpTransport::new(
+            TcpConfig::default().nodelay(true),
+        )))
+        .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
+        let noise = NoiseAuthenticated::xx(&self.local_keypair)
+            .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
+
+        Ok(relay_transport
+            .or_transport(dns_tcp.or_transport(WsConfig::new(ws_dns_tcp)))
+            .upgrade(Version::V1)
+            .authenticate(noise)
+            .multiplex(SelectUpgrade::new(
+                YamuxConfig::default(),
+                MplexConfig::default(),
+            ))
+            .timeout(TRANSPORT_TIMEOUT)
+            .boxed())
+    }
 }
 
 // ------------
@@ -147,6 +224,21 @@ struct BufferedPubsubMessage {
     pub message: PubsubMessage,
 }
 
+/// An MPC net brokered with a peer known only through a relay, awaiting a direct connection
+///
+/// The MPC net is dialed directly over QUIC, so it cannot be routed through a relay circuit;
+/// instead the peer is dialed through the relay and the net is brokered once DCUtR has hole
+/// punched a direct connection
+#[derive(Clone, Debug)]
+struct PendingMpcBroker {
+    /// The ID of the ongoing handshake
+    request_id: Uuid,
+    /// The port that the peer has exposed to dial on
+    peer_port: Port,
+    /// The local port that should be used to accept the stream
+    local_port: Port,
+}
+
 /// The executor abstraction runs in a thread separately from the network manager
 ///
 /// This allows the thread to take ownership of the executor object and perform
@@ -155,22 +247,35 @@ struct BufferedPubsubMessage {
 pub(super) struct NetworkManagerExecutor {
     /// The peer ID of the local node
     local_peer_id: WrappedPeerId,
-    /// The local cluster's keypair, used to sign and authenticate requests
-    cluster_key: SigKeypair,
+    /// The local cluster's current epoch key, used to sign and authenticate requests, and
+    /// the symmetric key derived from it, used to encrypt intra-cluster pubsub messages
+    cluster_keys: ClusterKeyChain,
     /// Whether or not the warmup period has already elapsed
     warmup_finished: bool,
     /// The messages buffered during the warmup period
     warmup_buffer: Vec<BufferedPubsubMessage>,
     /// The underlying swarm that manages low level network behavior
     swarm: Swarm<ComposedNetworkBehavior>,
+    /// The provider records the local node publishes in the DHT, and the outstanding
+    /// provider lookups
+    provider_records: ProviderRecords,
+    /// The outbound requests awaiting a flush as a batch
+    batcher: OutboundBatcher,
+    /// The connected peers that reported a protocol version that supports batching
+    batching_peers: HashSet<PeerId>,
+    /// The state of peer discovery via DNS seeds and the DHT
+    discovery: PeerDiscovery,
+    /// The MPC nets awaiting a direct connection to a peer known only through a relay
+    pending_mpc_brokers: HashMap<PeerId, Vec<PendingMpcBroker>>,
+    /// The per-peer rate limits on inbound requests, and the bans of peers exceeding them
+    rate_limiter: PeerRateLimiter,
     /// The channel to receive outbound requests on from other workers
-    send_channel: UnboundedReceiver<GossipOutbound>,
+    send_channel: JobQueueReceiver<GossipOutbound>,
     /// The sender for the gossip server's work queue
-    gossip_work_queue: TokioSender<GossipServerJob>,
+    gossip_work_queue: JobQueueSender<GossipServerJob>,
     /// The sender for the handshake manager's work queue
-    handshake_work_queue: TokioSender<HandshakeExecutionJob>,
+    handshake_work_queue: JobQueueSender<HandshakeExecutionJob>,
     /// A copy of the relayer-global state
-    #[allow(unused)]
     global_state: RelayerState,
     /// The cancel channel that the coordinator thread may use to cancel this worker
     cancel: DefaultWrapper<Option<CancelChannel>>,
@@ -181,20 +286,28 @@ impl NetworkManagerExecutor {
     #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         local_peer_id: WrappedPeerId,
-        cluster_key: SigKeypair,
+        cluster_keys: ClusterKeyChain,
         swarm: Swarm<ComposedNetworkBehavior>,
-        send_channel: UnboundedReceiver<GossipOutbound>,
-        gossip_work_queue: TokioSender<GossipServerJob>,
-        handshake_work_queue: TokioSender<HandshakeExecutionJob>,
+        send_channel: JobQueueReceiver<GossipOutbound>,
+        gossip_work_queue: JobQueueSender<GossipServerJob>,
+        handshake_work_queue: JobQueueSender<HandshakeExecutionJob>,
         global_state: RelayerState,
+        discovery: PeerDiscovery,
+        rate_limiter: PeerRateLimiter,
         cancel: CancelChannel,
     ) -> Self {
         Self {
             local_peer_id,
-            cluster_key,
+            cluster_keys,
             warmup_finished: false,
             warmup_buffer: Vec::new(),
             swarm,
+            provider_records: ProviderRecords::new(),
+            batcher: OutboundBatcher::new(),
+            batching_peers: HashSet::new(),
+            discovery,
+            pending_mpc_brokers: HashMap::new(),
+            rate_limiter,
             send_channel,
             gossip_work_queue,
             handshake_work_queue,
@@ -211,15 +324,39 @@ impl NetworkManagerExecutor {
     pub(super) async fn executor_loop(mut self) -> NetworkManagerError {
         log::info!("Starting executor loop for network manager...");
         let mut cancel_channel = self.cancel.take().unwrap();
+        let mut ban_sweep = tokio::time::interval(BAN_SWEEP_INTERVAL);
+        self.dial_dns_seeds();
 
         loop {
+            // Provider records are reconciled, and peers discovered, as the loop wakes up for
+            // other events
+            if self.provider_records.reconcile_due() {
+                self.reconcile_provider_records().await;
+            }
+            if self.discovery.discovery_due() {
+                self.run_discovery();
+            }
+            if self.cluster_keys.rotation_due() {
+                self.publish_cluster_key(true /* rotate */);
+            } else if self.cluster_keys.announcement_due() {
+                self.publish_cluster_key(false /* rotate */);
+            }
+
             tokio::select! {
                 // Handle network requests from worker components of the relayer
                 Some(message) = self.send_channel.recv() => {
-                    // Forward the message
-                    if let Err(err) = self.handle_outbound_message(message) {
-                        log::info!("Error sending outbound message: {}", err);
+                    // Forward the message along with any others already queued, so that
+                    // requests enqueued together may be batched
+                    let mut next_message = Some(message);
+                    while let Some(message) = next_message {
+                        if let Err(err) = self.handle_outbound_message(message) {
+                            log::info!("Error sending outbound message: {}", err);
+                        }
+
+                        next_message = self.send_channel.try_recv().ok();
                     }
+
+                    self.flush_batches();
                 },
 
                 // Handle network events and dispatch
@@ -235,11 +372,65 @@ impl NetworkManagerExecutor {
                         SwarmEvent::NewListenAddr { address, .. } => {
                             log::info!("Listening on {}/p2p/{}\n", address, self.local_peer_id);
                         },
+                        // The transport handshake has authenticated the peer's identity
+                        SwarmEvent::ConnectionEstablished { peer_id, endpoint, num_established, .. } => {
+                            if num_established.get() == 1 {
+                                self.handle_new_connection(
+                                    peer_id,
+                                    endpoint.get_remote_address().clone(),
+                                    endpoint.is_dialer(),
+                                ).await;
+                            }
+
+                            // A direct connection to a peer known only through a relay, e.g.
+                            // one hole punched by DCUtR, unblocks the MPC nets awaiting it
+                            if !is_relayed(endpoint.get_remote_address()) {
+                                self.broker_pending_mpc_nets(
+                                    peer_id,
+                                    endpoint.get_remote_address().clone(),
+                                );
+                            }
+
+                            self.global_state.record_peer_auth_event(
+                                WrappedPeerId(peer_id),
+                                PeerAuthEventKind::ConnectionEstablished {
+                                    remote_addr: endpoint.get_remote_address().to_string(),
+                                },
+                            );
+                        },
+                        SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+                            if num_established == 0 {
+                                self.batching_peers.remove(&peer_id);
+                                self.discovery.connection_closed(&peer_id);
+                                self.global_state.record_peer_auth_event(
+                                    WrappedPeerId(peer_id),
+                                    PeerAuthEventKind::ConnectionClosed,
+                                );
+                            }
+                        },
+                        SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), .. } => {
+                            self.discovery.dial_failed(&peer_id);
+
+                            // A failed hole punch dial leaves the relayed connection open, only
+                            // give up on the pending MPC nets if the peer is unreachable entirely
+                            if !self.swarm.is_connected(&peer_id) {
+                                self.drop_pending_mpc_nets(&peer_id, "dialing through relay failed");
+                            }
+                        },
                         // This catchall may be enabled for fine-grained libp2p introspection
                         _ => {  }
                     }
                 }
 
+                // Lift expired bans on a timer, so that a banned peer may reconnect even while
+                // the local node is otherwise idle
+                _ = ban_sweep.tick() => {
+                    for peer_id in self.rate_limiter.expire_bans().into_iter() {
+                        log::info!("lifting ban on peer {}", peer_id);
+                        self.swarm.unban_peer_id(peer_id);
+                    }
+                },
+
                 // Handle a cancel signal from the coordinator
                 _ = cancel_channel.changed() => {
                     return NetworkManagerError::Cancelled("received cancel signal".to_string())
@@ -269,36 +460,313 @@ impl NetworkManagerExecutor {
 
                 Ok(())
             }
-            // KAD events do nothing for now, routing tables are automatically updated by libp2p
-            ComposedProtocolEvent::Kademlia(_) => Ok(()),
+            // Routing tables are automatically updated by libp2p, only the results of provider
+            // lookups are handled
+            ComposedProtocolEvent::Kademlia(event) => {
+                self.handle_kademlia_event(event);
+                Ok(())
+            }
+
+            // The behavior automatically updates the `external_addresses` field in the swarm, we
+            // only audit the protocol information the peer reports
+            ComposedProtocolEvent::Identify(event) => {
+                if let IdentifyEvent::Received { peer_id, info } = event {
+                    self.audit_identify_info(WrappedPeerId(peer_id), info);
+                }
+
+                Ok(())
+            }
+
+            // Relay reservations and circuits are managed by the behaviors, they are only logged
+            ComposedProtocolEvent::RelayClient(event) => {
+                if let RelayClientEvent::ReservationReqAccepted { relay_peer_id, .. } = event {
+                    log::info!("circuit reserved on relay {}", relay_peer_id);
+                } else {
+                    log::debug!("relay client event: {:?}", event);
+                }
+
+                Ok(())
+            }
+            ComposedProtocolEvent::RelayServer(event) => {
+                log::debug!("relay server event: {:?}", event);
+                Ok(())
+            }
 
-            // Identify events do nothing for now, the behavior automatically updates the `external_addresses`
-            // field in the swarm
-            ComposedProtocolEvent::Identify(_) => Ok(()),
+            // A successful upgrade is handled when the direct connection is established
+            ComposedProtocolEvent::Dcutr(event) => {
+                if let DcutrEvent::DirectConnectionUpgradeFailed {
+                    remote_peer_id,
+                    error,
+                } = event
+                {
+                    log::info!("hole punching to {} failed: {}", remote_peer_id, error);
+                    self.drop_pending_mpc_nets(&remote_peer_id, "hole punching failed");
+                } else {
+                    log::debug!("dcutr event: {:?}", event);
+                }
+
+                Ok(())
+            }
         }
     }
 
+    /// Handle an event from the Kademlia DHT, returning the results of provider lookups
+    /// to the workers that issued them
+    fn handle_kademlia_event(&mut self, event: KademliaEvent) {
+        if let KademliaEvent::OutboundQueryProgressed {
+            id, result, step, ..
+        } = event
+        {
+            if self.discovery.is_cluster_lookup(&id) {
+                self.handle_cluster_lookup_result(result, step.last);
+                return;
+            }
+
+            match result {
+                QueryResult::GetProviders(Ok(GetProvidersOk::FoundProviders {
+                    providers, ..
+                })) => {
+                    let providers = providers
+                        .into_iter()
+                        .map(WrappedPeerId)
+                        .filter(|peer_id| *peer_id != self.local_peer_id)
+                        .collect_vec();
+                    self.provider_records.complete_lookup(&id, providers);
+                }
+                QueryResult::GetProviders(Err(err)) => {
+                    log::info!("provider lookup failed: {}", err);
+                    self.provider_records.complete_lookup(&id, Vec::new());
+                }
+                QueryResult::GetProviders(_) if step.last => {
+                    self.provider_records.complete_lookup(&id, Vec::new());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // ------------------
+    // | Peer Discovery |
+    // ------------------
+
+    /// Dial each of the DNS seeds, the transport resolves each to the peers it lists
+    fn dial_dns_seeds(&mut self) {
+        for seed in self.discovery.dns_seeds().to_vec().into_iter() {
+            if let Err(err) = self.swarm.dial(seed.clone()) {
+                log::info!("error dialing DNS seed {}: {}", seed, err);
+            }
+        }
+    }
+
+    /// Walk the DHT from a random key, and look up the members of the local cluster
+    ///
+    /// If the local node has lost all of its connections, the DNS seeds are dialed again
+    fn run_discovery(&mut self) {
+        if self.swarm.connected_peers().next().is_none() {
+            self.dial_dns_seeds();
+        }
+
+        let kademlia_dht = &mut self.swarm.behaviour_mut().kademlia_dht;
+        kademlia_dht.get_closest_peers(PeerId::random());
+        let cluster_lookup = kademlia_dht.get_providers(self.discovery.cluster_key().clone());
+        self.discovery.record_run(cluster_lookup);
+    }
+
+    /// Handle a result of the lookup of the local cluster's members, dialing any members
+    /// the local node is not connected to
+    fn handle_cluster_lookup_result(&mut self, result: QueryResult, last_step: bool) {
+        if last_step {
+            self.discovery.complete_cluster_lookup();
+        }
+
+        let providers = match result {
+            QueryResult::GetProviders(Ok(GetProvidersOk::FoundProviders { providers, .. })) => {
+                providers
+            }
+            QueryResult::GetProviders(Err(err)) => {
+                log::info!("cluster peer lookup failed: {}", err);
+                return;
+            }
+            _ => return,
+        };
+
+        let local_peer_id = *self.local_peer_id;
+        let candidates = providers
+            .into_iter()
+            .filter(|peer_id| *peer_id != local_peer_id)
+            .collect_vec();
+        let swarm = &self.swarm;
+        let dials = self
+            .discovery
+            .select_dials(candidates, |peer_id| swarm.is_connected(peer_id));
+
+        for peer_id in dials.into_iter() {
+            if let Err(err) = self.swarm.dial(DialOpts::peer_id(peer_id).build()) {
+                log::info!("error dialing discovered peer {}: {}", peer_id, err);
+                self.discovery.dial_failed(&peer_id);
+            }
+        }
+    }
+
+    /// Handle the first connection established to a peer
+    ///
+    /// The peer's address is added to the DHT; if the local node has not yet indexed the
+    /// peer, e.g. because it was found through a DNS seed or cluster lookup, a heartbeat is
+    /// sent so that the two peers sync their peer indices
+    async fn handle_new_connection(
+        &mut self,
+        peer_id: PeerId,
+        remote_addr: Multiaddr,
+        outbound: bool,
+    ) {
+        let dialed_by_discovery = self.discovery.connection_established(peer_id, outbound);
+
+        // Exempt known members of the local cluster from rate limit bans
+        let peer_info = self
+            .global_state
+            .read_peer_index()
+            .await
+            .get_peer_info(&WrappedPeerId(peer_id))
+            .await;
+        if let Some(info) = peer_info.as_ref()
+            && info.get_cluster_id() == *self.cluster_keys.cluster_id()
+        {
+            self.rate_limiter.add_cluster_peer(peer_id);
+        }
+
+        if !outbound {
+            return;
+        }
+
+        self.swarm
+            .behaviour_mut()
+            .kademlia_dht
+            .add_address(&peer_id, remote_addr);
+
+        if dialed_by_discovery || peer_info.is_none() {
+            if let Err(err) = self
+                .gossip_work_queue
+                .send(GossipServerJob::ExecuteHeartbeat(WrappedPeerId(peer_id)))
+            {
+                log::info!("error enqueuing heartbeat to new peer: {}", err);
+            }
+        }
+    }
+
+    /// Reconcile the provider records published by the local node with the orders and
+    /// wallets it currently manages
+    async fn reconcile_provider_records(&mut self) {
+        let mut desired_keys = {
+            let locked_order_book = self.global_state.read_order_book().await;
+            let locked_local_orders = locked_order_book.read_local_orders().await;
+            locked_local_orders
+                .iter()
+                .map(order_provider_key)
+                .collect::<HashSet<RecordKey>>()
+        }; // locked_order_book released
+        {
+            let locked_wallet_index = self.global_state.read_wallet_index().await;
+            for wallet in locked_wallet_index.get_all_wallets().await.iter() {
+                desired_keys.insert(wallet_provider_key(&wallet.public_keys));
+            }
+        } // locked_wallet_index released
+
+        // Every node provides its cluster's record so that cluster peers may find it
+        desired_keys.insert(self.discovery.cluster_key().clone());
+
+        let (added, removed) = self.provider_records.reconcile(desired_keys);
+        let kademlia_dht = &mut self.swarm.behaviour_mut().kademlia_dht;
+        for key in removed.iter() {
+            kademlia_dht.stop_providing(key);
+        }
+        for key in added.into_iter() {
+            if let Err(err) = kademlia_dht.start_providing(key.clone()) {
+                log::info!("error publishing provider record: {}", err);
+                self.provider_records.forget(&key);
+            }
+        }
+    }
+
+    /// Record the protocol information a peer reported via identify in the audit log, and
+    /// note whether requests to the peer may be batched
+    fn audit_identify_info(&mut self, peer_id: WrappedPeerId, info: libp2p::identify::Info) {
+        let identity_key_matches = info.public_key.to_peer_id() == *peer_id;
+
+        // A peer behind a NAT listens through a relay, record its relayed addresses so that
+        // other peers may reach it through the relay when dialing it directly fails
+        if identity_key_matches {
+            for addr in info.listen_addrs.iter().filter(|addr| is_relayed(addr)) {
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia_dht
+                    .add_address(&peer_id, addr.clone());
+            }
+        }
+        match ProtocolVersion::from_reported(&info.protocol_version) {
+            Some(version) if version.supports_batching() => {
+                self.batching_peers.insert(*peer_id);
+            }
+            Some(_) => {
+                self.batching_peers.remove(&*peer_id);
+            }
+            None => {
+                self.batching_peers.remove(&*peer_id);
+                self.global_state.record_peer_auth_event(
+                    peer_id,
+                    PeerAuthEventKind::ProtocolVersionMismatch {
+                        expected: CURRENT_PROTOCOL_VERSION.to_string(),
+                        reported: info.protocol_version.clone(),
+                    },
+                );
+            }
+        }
+
+        self.global_state.record_peer_auth_event(
+            peer_id,
+            PeerAuthEventKind::Identified {
+                protocol_version: info.protocol_version,
+                agent_version: info.agent_version,
+                identity_key_matches,
+            },
+        );
+    }
+
     /// Handles an outbound message from worker threads to other relayers
     fn handle_outbound_message(&mut self, msg: GossipOutbound) -> Result<(), NetworkManagerError> {
         match msg {
             GossipOutbound::Request { peer_id, message } => {
                 // Attach a signature if necessary
-                let req_body =
-                    AuthenticatedGossipRequest::new_with_body(message, &self.cluster_key)
-                        .map_err(|err| NetworkManagerError::Authentication(err.to_string()))?;
+                let req_body = AuthenticatedGossipRequest::new_with_body(
+                    message,
+                    self.cluster_keys.signing_key(),
+                )
+                .map_err(|err| NetworkManagerError::Authentication(err.to_string()))?;
+
+                // Buffer the request if it may be batched, otherwise flush any requests
+                // buffered for the peer first so that they are not reordered behind it
+                let batch =
+                    if req_body.body.is_batchable() && self.batching_peers.contains(&*peer_id) {
+                        self.batcher.push(*peer_id, req_body)
+                    } else {
+                        if let Some(batch) = self.batcher.take(&peer_id) {
+                            self.send_request(*peer_id, batch);
+                        }
+                        Some(req_body)
+                    };
 
-                self.swarm
-                    .behaviour_mut()
-                    .request_response
-                    .send_request(&peer_id, req_body);
+                if let Some(request) = batch {
+                    self.send_request(*peer_id, request);
+                }
 
                 Ok(())
             }
             GossipOutbound::Response { channel, message } => {
                 // Attach a signature if necessary
-                let req_body =
-                    AuthenticatedGossipResponse::new_with_body(message, &self.cluster_key)
-                        .map_err(|err| NetworkManagerError::Authentication(err.to_string()))?;
+                let req_body = AuthenticatedGossipResponse::new_with_body(
+                    message,
+                    self.cluster_keys.signing_key(),
+                )
+                .map_err(|err| NetworkManagerError::Authentication(err.to_string()))?;
 
                 self.swarm
                     .behaviour_mut()
@@ -315,6 +783,21 @@ impl NetworkManagerExecutor {
         }
     }
 
+    /// Send the requests buffered for each peer
+    fn flush_batches(&mut self) {
+        for (peer_id, request) in self.batcher.drain().into_iter() {
+            self.send_request(peer_id, request);
+        }
+    }
+
+    /// Send a request to a peer via the request/response protocol
+    fn send_request(&mut self, peer_id: PeerId, request: AuthenticatedGossipRequest) {
+        self.swarm
+            .behaviour_mut()
+            .request_response
+            .send_request(&peer_id, request);
+    }
+
     /// Forward an outbound pubsub message to the network
     fn forward_outbound_pubsub(
         &mut self,
@@ -329,20 +812,59 @@ impl NetworkManagerExecutor {
             return Ok(());
         }
 
-        // If we require a signature on the message attach one
-        let req_body = AuthenticatedPubsubMessage::new_with_body(message, &self.cluster_key)
-            .map_err(|err| NetworkManagerError::Authentication(err.to_string()))?;
+        // If we require a signature on the message attach one, then encrypt the message if it
+        // is intended only for the local cluster
+        let req_body =
+            AuthenticatedPubsubMessage::new_with_body(message, self.cluster_keys.signing_key())
+                .map_err(|err| NetworkManagerError::Authentication(err.to_string()))?;
+        let envelope = PubsubEnvelope::seal(req_body, self.cluster_keys.encryption_key());
 
         // Forward to the network
         let topic = Sha256Topic::new(topic);
         self.swarm
             .behaviour_mut()
             .pubsub
-            .publish(topic, req_body)
+            .publish(topic, envelope)
             .map_err(|err| NetworkManagerError::Network(err.to_string()))?;
         Ok(())
     }
 
+    /// Publish the local cluster's current key epoch to the cluster, first advancing to the
+    /// next epoch if `rotate` is set
+    fn publish_cluster_key(&mut self, rotate: bool) {
+        // Epoch zero has no announcement to re-publish
+        let announcement = if rotate {
+            self.cluster_keys.rotate().map(Some)
+        } else {
+            Ok(self.cluster_keys.announce())
+        };
+
+        let res = announcement
+            .map_err(|err| NetworkManagerError::Authentication(err.to_string()))
+            .and_then(|rotation| {
+                let rotation = match rotation {
+                    Some(rotation) => rotation,
+                    None => return Ok(()),
+                };
+                if rotate {
+                    log::info!("rotated cluster key to epoch {}", rotation.epoch);
+                }
+
+                let cluster_id = rotation.cluster_id.clone();
+                self.forward_outbound_pubsub(
+                    cluster_id.get_management_topic(),
+                    PubsubMessage::ClusterManagement {
+                        cluster_id,
+                        message: ClusterManagementMessage::KeyRotation(rotation),
+                    },
+                )
+            });
+
+        if let Err(err) = res {
+            log::info!("error publishing cluster key rotation: {}", err);
+        }
+    }
+
     // ------------------------------
     // | Control Directive Handlers |
     // ------------------------------
@@ -375,43 +897,57 @@ impl NetworkManagerExecutor {
                 local_port,
                 local_role,
             } => {
-                let party_id = local_role.get_party_id();
-                let local_addr: SocketAddr = format!("127.0.0.1:{:?}", local_port).parse().unwrap();
+                let broker = PendingMpcBroker {
+                    request_id,
+                    peer_port,
+                    local_port,
+                };
 
                 // Connect on a side-channel to the peer
-                let mpc_net = match local_role {
+                match local_role {
                     ConnectionRole::Dialer => {
-                        // Retrieve known dialable addresses for the peer from the network behavior
+                        // Retrieve known dialable addresses for the peer from the network behavior,
+                        // the MPC net cannot be routed through a relay so a direct address is
+                        // preferred
                         let all_peer_addrs = self.swarm.behaviour_mut().addresses_of_peer(&peer_id);
-                        let peer_multiaddr = all_peer_addrs.get(0).ok_or_else(|| {
-                            NetworkManagerError::Network(ERR_NO_KNOWN_ADDR.to_string())
-                        })?;
-                        let peer_addr = multiaddr_to_socketaddr(peer_multiaddr.clone(), peer_port)
-                            .ok_or_else(|| {
-                                NetworkManagerError::SerializeDeserialize(
-                                    ERR_PARSING_ADDR.to_string(),
-                                )
-                            })?;
-
-                        // Build an MPC net and dial the connection as the king party
-                        QuicTwoPartyNet::new(party_id, local_addr, peer_addr)
+                        if let Some(peer_multiaddr) =
+                            all_peer_addrs.iter().find(|addr| !is_relayed(addr))
+                        {
+                            self.dial_mpc_net(broker, peer_multiaddr.clone())
+                        } else if !all_peer_addrs.is_empty() {
+                            self.await_direct_connection(*peer_id, broker, all_peer_addrs)
+                        } else {
+                            Err(NetworkManagerError::Network(ERR_NO_KNOWN_ADDR.to_string()))
+                        }
                     }
                     ConnectionRole::Listener => {
                         // As the listener, the peer address is inconsequential, and can be a dummy value
+                        let party_id = local_role.get_party_id();
+                        let local_addr: SocketAddr =
+                            format!("127.0.0.1:{:?}", local_port).parse().unwrap();
                         let peer_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-                        QuicTwoPartyNet::new(party_id, local_addr, peer_addr)
+                        self.forward_mpc_net(
+                            request_id,
+                            party_id,
+                            QuicTwoPartyNet::new(party_id, local_addr, peer_addr),
+                        )
                     }
-                };
+                }
+            }
+
+            // Look up the managers of an order in the DHT, the result is returned when the
+            // Kademlia query completes
+            ManagerControlDirective::FindOrderProviders {
+                order_id,
+                response_channel,
+            } => {
+                let query_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .kademlia_dht
+                    .get_providers(order_provider_key(&order_id));
+                self.provider_records.add_lookup(query_id, response_channel);
 
-                // After the dependencies are injected into the network; forward it to the handshake manager to
-                // dial the peer and begin the MPC
-                self.handshake_work_queue
-                    .send(HandshakeExecutionJob::MpcNetSetup {
-                        request_id,
-                        party_id,
-                        net: mpc_net,
-                    })
-                    .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?;
                 Ok(())
             }
 
@@ -433,6 +969,103 @@ impl NetworkManagerExecutor {
         }
     }
 
+    // ---------------------
+    // | MPC Net Brokering |
+    // ---------------------
+
+    /// Build an MPC net that dials the peer at the given address as the king party, and
+    /// forward it to the handshake manager
+    fn dial_mpc_net(
+        &mut self,
+        broker: PendingMpcBroker,
+        peer_multiaddr: Multiaddr,
+    ) -> Result<(), NetworkManagerError> {
+        let party_id = ConnectionRole::Dialer.get_party_id();
+        let local_addr: SocketAddr = format!("127.0.0.1:{:?}", broker.local_port)
+            .parse()
+            .unwrap();
+        let peer_addr =
+            multiaddr_to_socketaddr(peer_multiaddr, broker.peer_port).ok_or_else(|| {
+                NetworkManagerError::SerializeDeserialize(ERR_PARSING_ADDR.to_string())
+            })?;
+
+        self.forward_mpc_net(
+            broker.request_id,
+            party_id,
+            QuicTwoPartyNet::new(party_id, local_addr, peer_addr),
+        )
+    }
+
+    /// Forward an MPC net to the handshake manager, which dials the peer and begins the MPC
+    fn forward_mpc_net(
+        &self,
+        request_id: Uuid,
+        party_id: u64,
+        net: QuicTwoPartyNet,
+    ) -> Result<(), NetworkManagerError> {
+        self.handshake_work_queue
+            .send(HandshakeExecutionJob::MpcNetSetup {
+                request_id,
+                party_id,
+                net,
+                pooled_net_id: None,
+            })
+            .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))
+    }
+
+    /// Dial a peer known only through a relay, deferring the MPC net until DCUtR has hole
+    /// punched a direct connection to the peer
+    fn await_direct_connection(
+        &mut self,
+        peer_id: PeerId,
+        broker: PendingMpcBroker,
+        relayed_addrs: Vec<Multiaddr>,
+    ) -> Result<(), NetworkManagerError> {
+        // A relay connection that is already open has had its upgrade attempted, unless a
+        // dial is outstanding on behalf of an earlier MPC net
+        if let Some(pending) = self.pending_mpc_brokers.get_mut(&peer_id) {
+            pending.push(broker);
+            return Ok(());
+        }
+        if self.swarm.is_connected(&peer_id) {
+            return Err(NetworkManagerError::Network(ERR_NO_DIRECT_ADDR.to_string()));
+        }
+
+        self.swarm
+            .dial(DialOpts::peer_id(peer_id).addresses(relayed_addrs).build())
+            .map_err(|err| NetworkManagerError::Network(err.to_string()))?;
+        self.pending_mpc_brokers.insert(peer_id, vec![broker]);
+
+        Ok(())
+    }
+
+    /// Build the MPC nets awaiting a direct connection to the peer, now established at the
+    /// given address
+    fn broker_pending_mpc_nets(&mut self, peer_id: PeerId, peer_addr: Multiaddr) {
+        for broker in self
+            .pending_mpc_brokers
+            .remove(&peer_id)
+            .unwrap_or_default()
+        {
+            if let Err(err) = self.dial_mpc_net(broker, peer_addr.clone()) {
+                log::info!("error brokering MPC net with {}: {}", peer_id, err);
+            }
+        }
+    }
+
+    /// Abandon the MPC nets awaiting a direct connection to the peer, their handshakes are
+    /// left to time out
+    fn drop_pending_mpc_nets(&mut self, peer_id: &PeerId, reason: &str) {
+        if let Some(pending) = self.pending_mpc_brokers.remove(peer_id) {
+            log::info!(
+                "dropping {} MPC net(s) awaiting a direct connection to {}: {}",
+                pending.len(),
+                peer_id,
+                reason
+            );
+        }
+    }
+
     // -----------------------------
     // | Request/Response Handlers |
     // -----------------------------
@@ -449,12 +1082,26 @@ impl NetworkManagerExecutor {
             RequestResponseMessage::Request {
                 request, channel, ..
             } => {
+                // Rate limit the request before the cost of verifying its signature is paid,
+                // dropping the request closes the channel
+                if !self.admit_request(peer_id, &request.body) {
+                    return Ok(());
+                }
+
                 // Authenticate the request
-                if !request.verify_cluster_auth(&self.cluster_key.public) {
+                if !self
+                    .cluster_keys
+                    .verify(|key| request.verify_cluster_auth(key))
+                {
+                    self.global_state.record_peer_auth_event(
+                        WrappedPeerId(peer_id),
+                        PeerAuthEventKind::ClusterAuthFailed,
+                    );
                     return Err(NetworkManagerError::Authentication(
                         ERR_SIG_VERIFY.to_string(),
                     ));
                 }
+                self.note_cluster_auth(peer_id, &request.body);
 
                 match request.body {
                     // Forward the bootstrap request directly to the gossip server
@@ -485,6 +1132,19 @@ impl NetworkManagerExecutor {
                         })
                         .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
 
+                    GossipRequest::BrokeredHandshake {
+                        request_id,
+                        message,
+                    } => self
+                        .handshake_work_queue
+                        .send(HandshakeExecutionJob::ProcessBrokerMessage {
+                            request_id,
+                            peer_id: WrappedPeerId(peer_id),
+                            message,
+                            response_channel: Some(channel),
+                        })
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
+
                     GossipRequest::OrderInfo(req) => self
                         .gossip_work_queue
                         .send(GossipServerJob::OrderBookManagement(
@@ -495,40 +1155,44 @@ impl NetworkManagerExecutor {
                         ))
                         .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
 
-                    GossipRequest::Replicate(replicate_message) => {
-                        self.gossip_work_queue
-                            .send(GossipServerJob::Cluster(
-                                ClusterManagementJob::ReplicateRequest(replicate_message),
-                            ))
-                            .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?;
+                    GossipRequest::OrderBookSnapshot(request) => self
+                        .gossip_work_queue
+                        .send(GossipServerJob::OrderBookManagement(
+                            OrderBookManagementJob::SnapshotRequest {
+                                request,
+                                response_channel: channel,
+                            },
+                        ))
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
 
-                        // Send a simple ack back to avoid closing the channel
-                        self.swarm
-                            .behaviour_mut()
-                            .request_response
-                            .send_response(channel, AuthenticatedGossipResponse::new_ack())
-                            .map_err(|_| {
-                                NetworkManagerError::Network("error sending Ack".to_string())
-                            })
-                    }
+                    GossipRequest::Batch(requests) => {
+                        for request in requests.into_iter() {
+                            if !self.admit_request(peer_id, &request.body) {
+                                continue;
+                            }
 
-                    GossipRequest::ValidityProof { order_id, proof } => {
-                        // TODO: Authenticate this
-                        self.gossip_work_queue
-                            .send(GossipServerJob::Cluster(
-                                ClusterManagementJob::UpdateValidityProof(order_id, proof),
-                            ))
-                            .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))
+                            if let Err(err) = self.forward_batchable_request(peer_id, request) {
+                                log::info!("error handling batched request: {}", err);
+                            }
+                        }
+
+                        self.handle_outbound_message(GossipOutbound::Response {
+                            channel,
+                            message: GossipResponse::Ack,
+                        })
                     }
 
-                    GossipRequest::ValidityWitness { order_id, witness } => {
-                        self.gossip_work_queue
-                            .send(GossipServerJob::OrderBookManagement(
-                                OrderBookManagementJob::OrderWitnessResponse { order_id, witness },
-                            ))
-                            .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?;
+                    // The remaining requests are all answered with an ack
+                    body => {
+                        self.forward_batchable_request(
+                            peer_id,
+                            AuthenticatedGossipRequest {
+                                sig: request.sig,
+                                body,
+                            },
+                        )?;
 
-                        // Send back an ack
+                        // Send a simple ack back to avoid closing the channel
                         self.handle_outbound_message(GossipOutbound::Response {
                             channel,
                             message: GossipResponse::Ack,
@@ -539,7 +1203,14 @@ impl NetworkManagerExecutor {
 
             // Handle inbound response
             RequestResponseMessage::Response { response, .. } => {
-                if !response.verify_cluster_auth(&self.cluster_key.public) {
+                if !self
+                    .cluster_keys
+                    .verify(|key| response.verify_cluster_auth(key))
+                {
+                    self.global_state.record_peer_auth_event(
+                        WrappedPeerId(peer_id),
+                        PeerAuthEventKind::ClusterAuthFailed,
+                    );
                     return Err(NetworkManagerError::Authentication(
                         ERR_SIG_VERIFY.to_string(),
                     ));
@@ -570,10 +1241,37 @@ impl NetworkManagerExecutor {
                         })
                         .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
 
+                    GossipResponse::BrokeredHandshake {
+                        request_id,
+                        message,
+                    } => self
+                        .handshake_work_queue
+                        .send(HandshakeExecutionJob::ProcessBrokerMessage {
+                            request_id,
+                            peer_id: WrappedPeerId(peer_id),
+                            message,
+                            response_channel: None,
+                        })
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
+
                     GossipResponse::OrderInfo(OrderInfoResponse { order_id, info }) => self
                         .gossip_work_queue
                         .send(GossipServerJob::OrderBookManagement(
-                            OrderBookManagementJob::OrderInfoResponse { order_id, info },
+                            OrderBookManagementJob::OrderInfoResponse {
+                                order_id,
+                                info,
+                                sender: WrappedPeerId(peer_id),
+                            },
+                        ))
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
+
+                    GossipResponse::OrderBookSnapshot(chunk) => self
+                        .gossip_work_queue
+                        .send(GossipServerJob::OrderBookManagement(
+                            OrderBookManagementJob::SnapshotChunk {
+                                chunk,
+                                sender: WrappedPeerId(peer_id),
+                            },
                         ))
                         .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string())),
                 }
@@ -581,6 +1279,120 @@ impl NetworkManagerExecutor {
         }
     }
 
+    /// Charge an inbound request against the peer's rate limits, returning whether it is
+    /// admitted
+    ///
+    /// A peer whose dropped requests push its spam score over the ban threshold is banned
+    /// at the swarm level; its connections are closed and it may not reconnect until the
+    /// ban is lifted. Peers of the local cluster are never banned, see `PeerRateLimiter`
+    fn admit_request(&mut self, peer_id: PeerId, request: &GossipRequest) -> bool {
+        let request_type = request.request_type();
+        match self.rate_limiter.check(&peer_id, request_type) {
+            RateLimitDecision::Admit => true,
+            RateLimitDecision::Drop => {
+                log::debug!(
+                    "dropping {} request from rate limited peer {}",
+                    request_type.name(),
+                    peer_id
+                );
+                false
+            }
+            RateLimitDecision::Ban(duration) => {
+                self.swarm.ban_peer_id(peer_id);
+                self.global_state.record_peer_auth_event(
+                    WrappedPeerId(peer_id),
+                    PeerAuthEventKind::Banned {
+                        duration_secs: duration.as_secs(),
+                    },
+                );
+                false
+            }
+        }
+    }
+
+    /// Exempt a peer from rate limit bans once it has proven membership of the local
+    /// cluster by signing a request under the cluster key
+    fn note_cluster_auth(&mut self, peer_id: PeerId, request: &GossipRequest) {
+        if request.requires_cluster_auth() {
+            self.rate_limiter.add_cluster_peer(peer_id);
+        }
+    }
+
+    /// Forward a request that is answered with an ack to the worker that handles it
+    ///
+    /// The request is authenticated here as a batch carries no signature of its own; a
+    /// request arriving alone is cheaply re-verified
+    fn forward_batchable_request(
+        &mut self,
+        peer_id: PeerId,
+        request: AuthenticatedGossipRequest,
+    ) -> Result<(), NetworkManagerError> {
+        if !request.body.is_batchable() {
+            return Err(NetworkManagerError::SerializeDeserialize(
+                ERR_NOT_BATCHABLE.to_string(),
+            ));
+        }
+
+        if !self
+            .cluster_keys
+            .verify(|key| request.verify_cluster_auth(key))
+        {
+            self.global_state.record_peer_auth_event(
+                WrappedPeerId(peer_id),
+                PeerAuthEventKind::ClusterAuthFailed,
+            );
+            return Err(NetworkManagerError::Authentication(
+                ERR_SIG_VERIFY.to_string(),
+            ));
+        }
+        self.note_cluster_auth(peer_id, &request.body);
+
+        let job = match request.body {
+            GossipRequest::Replicate(replicate_message) => {
+                GossipServerJob::Cluster(ClusterManagementJob::ReplicateRequest(replicate_message))
+            }
+            GossipRequest::ReplicaRepair(req) => {
+                GossipServerJob::Cluster(ClusterManagementJob::ReplicaRepairRequest(req))
+            }
+            GossipRequest::ReplicaRepairResponse(resp) => {
+                GossipServerJob::Cluster(ClusterManagementJob::ReplicaRepairResponse(resp))
+            }
+            GossipRequest::ReplicateShards(req) => {
+                GossipServerJob::Cluster(ClusterManagementJob::ReplicateShards(req))
+            }
+            GossipRequest::WalletShardRequest(req) => {
+                GossipServerJob::Cluster(ClusterManagementJob::WalletShardRequest(req))
+            }
+            GossipRequest::WalletShardResponse(resp) => {
+                GossipServerJob::Cluster(ClusterManagementJob::WalletShardResponse(resp))
+            }
+            // The handshake cache is owned by the handshake manager rather than the gossip
+            // server
+            GossipRequest::CacheSyncResponse(resp) => {
+                return self
+                    .handshake_work_queue
+                    .send(HandshakeExecutionJob::CacheEntries {
+                        pairs: resp.completed_pairs,
+                    })
+                    .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()));
+            }
+            GossipRequest::ValidityProof { order_id, proof } => {
+                GossipServerJob::Cluster(ClusterManagementJob::UpdateValidityProof(order_id, proof))
+            }
+            GossipRequest::ValidityWitness { order_id, witness } => {
+                GossipServerJob::OrderBookManagement(OrderBookManagementJob::OrderWitnessResponse {
+                    order_id,
+                    witness,
+                })
+            }
+            _ => unreachable!("request checked to be batchable above"),
+        };
+
+        self.gossip_work_queue
+            .send(job)
+            .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))
+    }
+
     // ------------------
     // | Pubsub Handlers |
     // -------------------
@@ -590,13 +1402,37 @@ impl NetworkManagerExecutor {
         &mut self,
         message: GossipsubMessage,
     ) -> Result<(), NetworkManagerError> {
-        // Deserialize into API types and verify auth
-        let event: AuthenticatedPubsubMessage = message.data.into();
-        if !event.verify_cluster_auth(&self.cluster_key.public) {
-            return Err(NetworkManagerError::Authentication(
-                ERR_SIG_VERIFY.to_string(),
-            ));
-        }
+        // Deserialize into API types, decrypting cluster messages, and verify auth
+        let source = message.source;
+        let envelope = PubsubEnvelope::try_from(message.data)
+            .map_err(|err| NetworkManagerError::SerializeDeserialize(err.to_string()))?;
+
+        // A message that does not decrypt under the cluster's key fails authentication just
+        // as a message with an invalid signature does
+        let event = match self.cluster_keys.open(envelope) {
+            Some(event)
+                if self
+                    .cluster_keys
+                    .verify(|key| event.verify_cluster_auth(key)) =>
+            {
+                event
+            }
+            opened => {
+                if let Some(source) = source {
+                    self.global_state.record_peer_auth_event(
+                        WrappedPeerId(source),
+                        PeerAuthEventKind::ClusterAuthFailed,
+                    );
+                }
+
+                let err = if opened.is_none() {
+                    ERR_DECRYPT
+                } else {
+                    ERR_SIG_VERIFY
+                };
+                return Err(NetworkManagerError::Authentication(err.to_string()));
+            }
+        };
 
         match event.body {
             PubsubMessage::ClusterManagement {
@@ -651,6 +1487,15 @@ impl NetworkManagerExecutor {
                         .send(HandshakeExecutionJob::PeerMatchInProgress { order1, order2 })
                         .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?,
 
+                    // Forward a restarting peer's request for the completed pairs to the handshake
+                    // manager, which owns the cache
+                    ClusterManagementMessage::CacheSyncRequest(req) => self
+                        .handshake_work_queue
+                        .send(HandshakeExecutionJob::CacheSyncRequest {
+                            peer_id: req.sender,
+                        })
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?,
+
                     // -------------
                     // | Orderbook |
                     // -------------
@@ -675,6 +1520,39 @@ impl NetworkManagerExecutor {
                             },
                         ))
                         .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?,
+
+                    // Forward a delta from a peer serving an order book snapshot to the gossip
+                    // server, which buffers it if the local peer is syncing from that peer
+                    ClusterManagementMessage::OrderBookDelta(delta) => self
+                        .gossip_work_queue
+                        .send(GossipServerJob::OrderBookManagement(
+                            OrderBookManagementJob::OrderBookDelta(delta),
+                        ))
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?,
+
+                    // ----------------
+                    // | Key Rotation |
+                    // ----------------
+
+                    // Advance the local key epoch to the epoch announced by a cluster peer
+                    ClusterManagementMessage::KeyRotation(rotation) => {
+                        if self.cluster_keys.apply_rotation(&rotation)? {
+                            log::info!("cluster rotated its key to epoch {}", rotation.epoch);
+                        }
+                    }
+
+                    // --------------
+                    // | Leadership |
+                    // --------------
+
+                    // Forward a leadership announcement to the gossip server to update the local
+                    // view of the cluster leader
+                    ClusterManagementMessage::LeaderElected(announcement) => self
+                        .gossip_work_queue
+                        .send(GossipServerJob::Cluster(
+                            ClusterManagementJob::LeaderElected(cluster_id, announcement),
+                        ))
+                        .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?,
                 }
             }
             PubsubMessage::OrderBookManagement(msg) => match msg {
@@ -697,6 +1575,8 @@ impl NetworkManagerExecutor {
                     order_id,
                     cluster,
                     proof,
+                    ioi,
+                    time_in_force,
                 } => self
                     .gossip_work_queue
                     .send(GossipServerJob::OrderBookManagement(
@@ -704,6 +1584,9 @@ impl NetworkManagerExecutor {
                             order_id,
                             cluster,
                             proof,
+                            ioi,
+                            time_in_force,
+                            sender: source.map(WrappedPeerId),
                         },
                     ))
                     .map_err(|err| NetworkManagerError::EnqueueJob(err.to_string()))?,
@@ -1,5 +1,10 @@
 //! Groups logic for the network manager
 mod composed_protocol;
+pub mod discovery;
 pub mod error;
+mod framing;
+pub mod key_rotation;
 pub mod manager;
+pub mod providers;
+pub mod rate_limit;
 pub mod worker;
@@ -0,0 +1,153 @@
+//! Manages the provider records the local node publishes in the Kademlia DHT
+//!
+//! The local node advertises itself as a provider for every order and wallet it manages, so
+//! that a peer that has not yet heard of an order through heartbeat gossip can still find a
+//! node to handshake with. Records are keyed by order ID, and by the public root key of each
+//! managed wallet.
+//!
+//! Kademlia re-publishes provided records on its own schedule and expires remote records after
+//! their TTL; the set of provided keys is reconciled against the global state periodically so
+//! that records are added for new orders and withdrawn for orders the node no longer manages
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use circuits::types::keychain::KeyChain;
+use crypto::fields::scalar_to_biguint;
+use libp2p::kad::{record::Key as RecordKey, QueryId};
+use tokio::sync::mpsc::UnboundedSender as TokioSender;
+
+use crate::{gossip::types::WrappedPeerId, state::OrderIdentifier};
+
+/// The time after which a provider record expires in remote peers' stores if it is not
+/// re-published
+pub(super) const PROVIDER_RECORD_TTL: Duration = Duration::from_secs(60 * 60); // 1 hour
+/// The interval at which Kademlia re-publishes the local node's provider records, well
+/// within the record TTL
+pub(super) const PROVIDER_PUBLICATION_INTERVAL: Duration = Duration::from_secs(15 * 60); // 15 minutes
+/// The interval at which the set of provided keys is reconciled against the global state
+pub(super) const PROVIDER_RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+/// The maximum number of keys the local store will provide for
+pub(super) const MAX_PROVIDED_KEYS: usize = 10_000;
+
+/// Build the DHT key under which the managers of an order are recorded
+pub fn order_provider_key(order_id: &OrderIdentifier) -> RecordKey {
+    RecordKey::new(&format!("order/{}", order_id))
+}
+
+/// Build the DHT key under which the managers of a wallet are recorded
+pub fn wallet_provider_key(keys: &KeyChain) -> RecordKey {
+    RecordKey::new(&format!("wallet/{:x}", scalar_to_biguint(&keys.pk_root)))
+}
+
+/// Tracks the provider records published by the local node and the outstanding provider
+/// lookups issued on behalf of other workers
+#[derive(Debug)]
+pub(super) struct ProviderRecords {
+    /// The keys the local node currently provides
+    provided_keys: HashSet<RecordKey>,
+    /// The time at which the provided keys were last reconciled with the global state
+    last_reconciled: Option<Instant>,
+    /// The outstanding lookups, keyed by Kademlia query, with the channel on which to
+    /// return the providers found
+    pending_lookups: HashMap<QueryId, TokioSender<Vec<WrappedPeerId>>>,
+}
+
+impl ProviderRecords {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            provided_keys: HashSet::new(),
+            last_reconciled: None,
+            pending_lookups: HashMap::new(),
+        }
+    }
+
+    /// Whether the provided keys are due to be reconciled with the global state
+    pub fn reconcile_due(&self) -> bool {
+        self.last_reconciled
+            .map_or(true, |last| last.elapsed() >= PROVIDER_RECONCILE_INTERVAL)
+    }
+
+    /// Reconcile the provided keys with the keys the local node should provide
+    ///
+    /// Returns the keys that must begin to be provided, and the keys that must be withdrawn
+    pub fn reconcile(
+        &mut self,
+        desired_keys: HashSet<RecordKey>,
+    ) -> (Vec<RecordKey>, Vec<RecordKey>) {
+        let added = desired_keys
+            .difference(&self.provided_keys)
+            .cloned()
+            .collect();
+        let removed = self
+            .provided_keys
+            .difference(&desired_keys)
+            .cloned()
+            .collect();
+
+        self.provided_keys = desired_keys;
+        self.last_reconciled = Some(Instant::now());
+        (added, removed)
+    }
+
+    /// Forget a key that could not be provided, so that the next reconciliation retries it
+    pub fn forget(&mut self, key: &RecordKey) {
+        self.provided_keys.remove(key);
+    }
+
+    /// Register an outstanding lookup
+    pub fn add_lookup(
+        &mut self,
+        query_id: QueryId,
+        response_channel: TokioSender<Vec<WrappedPeerId>>,
+    ) {
+        self.pending_lookups.insert(query_id, response_channel);
+    }
+
+    /// Complete an outstanding lookup, returning the providers to its requester
+    ///
+    /// Lookups that have already been completed are ignored, a query may report providers
+    /// over several steps and only the first set found is returned
+    pub fn complete_lookup(&mut self, query_id: &QueryId, providers: Vec<WrappedPeerId>) {
+        if let Some(response_channel) = self.pending_lookups.remove(query_id) {
+            let _ = response_channel.send(providers);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use uuid::Uuid;
+
+    use super::{order_provider_key, ProviderRecords};
+
+    /// Tests that reconciliation provides new keys and withdraws stale ones
+    #[test]
+    fn test_reconcile() {
+        let orders = (0..3).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let mut records = ProviderRecords::new();
+        assert!(records.reconcile_due());
+
+        let desired = orders[..2]
+            .iter()
+            .map(order_provider_key)
+            .collect::<HashSet<_>>();
+        let (added, removed) = records.reconcile(desired);
+        assert_eq!(added.len(), 2);
+        assert!(removed.is_empty());
+        assert!(!records.reconcile_due());
+
+        let desired = orders[1..]
+            .iter()
+            .map(order_provider_key)
+            .collect::<HashSet<_>>();
+        let (added, removed) = records.reconcile(desired);
+        assert_eq!(added, vec![order_provider_key(&orders[2])]);
+        assert_eq!(removed, vec![order_provider_key(&orders[0])]);
+    }
+}
@@ -0,0 +1,384 @@
+//! Per-peer rate limiting of inbound gossip requests, and temporary bans of the peers that
+//! repeatedly exceed their limits
+//!
+//! Each peer is given a token bucket for each class of request. A request that finds its
+//! bucket empty is dropped and adds a point to the peer's spam score, which decays over
+//! time; a peer that bursts past its limits briefly recovers, while one that floods the
+//! local node crosses the ban threshold and is banned at the swarm level. Each ban a peer
+//! earns lasts twice as long as its last, up to a maximum
+//!
+//! Peers of the local cluster are rate limited as any other peer but are never banned;
+//! banning a cluster peer would partition the cluster's replication and handshakes over
+//! what is more likely a burst of legitimate sync traffic than an attack
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use libp2p::PeerId;
+
+use crate::{clock::SharedClock, gossip_api::gossip::GossipRequestType};
+
+/// The rate at which a peer's spam score decays, in points per second
+const SCORE_DECAY_PER_SEC: f64 = 1.0;
+/// The longest ban given to a repeat offender
+const MAX_BAN_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long the state of a peer that sends no requests is kept; a peer's offenses are
+/// forgotten once it has been idle this long
+const PEER_STATE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// The interval at which expired bans are lifted and idle peers forgotten
+pub const BAN_SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The default spam score at which a peer is banned
+pub const DEFAULT_BAN_THRESHOLD: f64 = 100.;
+/// The default duration of a peer's first ban
+pub const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(60);
+
+/// The rate limit of a single class of request from a single peer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenBucketConfig {
+    /// The most requests the peer may send in a burst
+    pub burst: u32,
+    /// The number of requests per second that the peer's bucket refills by
+    pub per_second: f64,
+}
+
+impl TokenBucketConfig {
+    /// The default limit of a class of request
+    ///
+    /// The limits are generous enough that a cluster peer syncing or replicating a full
+    /// order book and wallet set never reaches them
+    pub fn default_for(request_type: GossipRequestType) -> Self {
+        let (burst, per_second) = match request_type {
+            GossipRequestType::Bootstrap => (4, 0.1),
+            GossipRequestType::Heartbeat => (20, 2.),
+            GossipRequestType::Handshake => (200, 50.),
+            GossipRequestType::OrderInfo => (100, 20.),
+            GossipRequestType::OrderBookSnapshot => (64, 16.),
+            GossipRequestType::Replication => (200, 50.),
+            GossipRequestType::Validity => (200, 50.),
+            GossipRequestType::Batch => (100, 20.),
+        };
+
+        Self { burst, per_second }
+    }
+}
+
+/// The configuration of inbound gossip rate limiting
+#[derive(Clone, Debug)]
+pub struct GossipRateLimitConfig {
+    /// The limit of each class of request, classes not given take their default limits
+    pub limits: HashMap<GossipRequestType, TokenBucketConfig>,
+    /// The spam score at which a peer is banned, each dropped request scores a point
+    pub ban_threshold: f64,
+    /// The duration of a peer's first ban, each further ban lasts twice as long as the last
+    pub ban_duration: Duration,
+}
+
+impl Default for GossipRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            limits: HashMap::new(),
+            ban_threshold: DEFAULT_BAN_THRESHOLD,
+            ban_duration: DEFAULT_BAN_DURATION,
+        }
+    }
+}
+
+impl GossipRateLimitConfig {
+    /// The limit of a class of request
+    pub fn limit(&self, request_type: GossipRequestType) -> TokenBucketConfig {
+        self.limits
+            .get(&request_type)
+            .copied()
+            .unwrap_or_else(|| TokenBucketConfig::default_for(request_type))
+    }
+}
+
+/// The outcome of rate limiting an inbound request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    /// The request is within the peer's limits
+    Admit,
+    /// The request exceeds the peer's limits, or the peer is banned; it is dropped
+    Drop,
+    /// The request exceeds the peer's limits and pushes its spam score over the ban
+    /// threshold; it is dropped and the peer banned for the given duration
+    Ban(Duration),
+}
+
+/// The tokens left in a peer's bucket for one class of request
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    /// The number of requests the peer may send before it is limited
+    tokens: f64,
+    /// The time at which the bucket was last refilled
+    last_refill: Instant,
+}
+
+/// The rate limiting state of a single peer
+#[derive(Clone, Debug)]
+struct PeerRateState {
+    /// The peer's bucket for each class of request it has sent
+    buckets: HashMap<GossipRequestType, TokenBucket>,
+    /// The peer's spam score as of `last_scored`
+    score: f64,
+    /// The time at which the score was last updated
+    last_scored: Instant,
+    /// The number of bans the peer has earned
+    offenses: u32,
+    /// The time at which the peer's current ban expires, if it is banned
+    banned_until: Option<Instant>,
+    /// The time at which the peer last sent a request
+    last_seen: Instant,
+}
+
+impl PeerRateState {
+    /// Create the state of a peer first seen at the given time
+    fn new(now: Instant) -> Self {
+        Self {
+            buckets: HashMap::new(),
+            score: 0.,
+            last_scored: now,
+            offenses: 0,
+            banned_until: None,
+            last_seen: now,
+        }
+    }
+}
+
+/// Limits the rate of inbound requests from each peer, and decides when a peer is banned
+#[derive(Debug)]
+pub struct PeerRateLimiter {
+    /// The limits and ban policy
+    config: GossipRateLimitConfig,
+    /// The clock that buckets refill and bans expire against
+    clock: SharedClock,
+    /// The rate limiting state of each peer that has sent a request
+    peers: HashMap<PeerId, PeerRateState>,
+    /// The peers known to be members of the local cluster, which are never banned
+    cluster_peers: HashSet<PeerId>,
+}
+
+impl PeerRateLimiter {
+    /// Constructor
+    pub fn new(config: GossipRateLimitConfig, clock: SharedClock) -> Self {
+        Self {
+            config,
+            clock,
+            peers: HashMap::new(),
+            cluster_peers: HashSet::new(),
+        }
+    }
+
+    /// Record that the peer is a member of the local cluster, exempting it from bans
+    pub fn add_cluster_peer(&mut self, peer_id: PeerId) {
+        self.cluster_peers.insert(peer_id);
+    }
+
+    /// Charge a request of the given class to the peer, deciding whether it is admitted
+    pub fn check(
+        &mut self,
+        peer_id: &PeerId,
+        request_type: GossipRequestType,
+    ) -> RateLimitDecision {
+        let now = self.clock.now();
+        let limit = self.config.limit(request_type);
+        let state = self
+            .peers
+            .entry(*peer_id)
+            .or_insert_with(|| PeerRateState::new(now));
+        state.last_seen = now;
+
+        // A request may arrive from a banned peer on a stream opened before the ban
+        if let Some(banned_until) = state.banned_until && now < banned_until {
+            return RateLimitDecision::Drop;
+        }
+
+        let bucket = state.buckets.entry(request_type).or_insert(TokenBucket {
+            tokens: limit.burst as f64,
+            last_refill: now,
+        });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            return RateLimitDecision::Admit;
+        }
+
+        let elapsed = now.duration_since(state.last_scored).as_secs_f64();
+        state.score = (state.score - elapsed * SCORE_DECAY_PER_SEC).max(0.) + 1.;
+        state.last_scored = now;
+        if state.score < self.config.ban_threshold {
+            return RateLimitDecision::Drop;
+        }
+
+        if self.cluster_peers.contains(peer_id) {
+            state.score = 0.;
+            return RateLimitDecision::Drop;
+        }
+
+        let duration = self
+            .config
+            .ban_duration
+            .saturating_mul(2u32.saturating_pow(state.offenses))
+            .min(MAX_BAN_DURATION);
+        state.offenses += 1;
+        state.score = 0.;
+        state.banned_until = Some(now + duration);
+
+        RateLimitDecision::Ban(duration)
+    }
+
+    /// Lift the bans that have expired, returning the peers whose bans were lifted
+    ///
+    /// The state of peers that have been idle long enough to have forgotten their offenses
+    /// is dropped. Called every `BAN_SWEEP_INTERVAL`
+    pub fn expire_bans(&mut self) -> Vec<PeerId> {
+        let now = self.clock.now();
+        let mut expired = Vec::new();
+        for (peer_id, state) in self.peers.iter_mut() {
+            if let Some(banned_until) = state.banned_until && banned_until <= now {
+                state.banned_until = None;
+                expired.push(*peer_id);
+            }
+        }
+
+        self.peers.retain(|_, state| {
+            state.banned_until.is_some() || now.duration_since(state.last_seen) < PEER_STATE_TTL
+        });
+
+        expired
+    }
+
+    /// Whether the peer is currently banned
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        let now = self.clock.now();
+        self.peers
+            .get(peer_id)
+            .and_then(|state| state.banned_until)
+            .map(|banned_until| now < banned_until)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use libp2p::PeerId;
+
+    use crate::{clock::ManualClock, gossip_api::gossip::GossipRequestType};
+
+    use super::{GossipRateLimitConfig, PeerRateLimiter, RateLimitDecision, TokenBucketConfig};
+
+    /// Build a limiter admitting bursts of two heartbeats, refilling one per second, and
+    /// banning a peer after three dropped requests
+    fn setup() -> (PeerRateLimiter, ManualClock) {
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+        let config = GossipRateLimitConfig {
+            limits: HashMap::from([(
+                GossipRequestType::Heartbeat,
+                TokenBucketConfig {
+                    burst: 2,
+                    per_second: 1.,
+                },
+            )]),
+            ban_threshold: 3.,
+            ban_duration: Duration::from_secs(60),
+        };
+
+        (PeerRateLimiter::new(config, Arc::new(clock.clone())), clock)
+    }
+
+    /// Tests that requests beyond the burst are dropped until the bucket refills, and that
+    /// each class of request is limited separately
+    #[test]
+    fn test_token_bucket() {
+        let (mut limiter, clock) = setup();
+        let peer_id = PeerId::random();
+
+        for expected in [
+            RateLimitDecision::Admit,
+            RateLimitDecision::Admit,
+            RateLimitDecision::Drop,
+        ] {
+            assert_eq!(
+                limiter.check(&peer_id, GossipRequestType::Heartbeat),
+                expected
+            );
+        }
+        assert_eq!(
+            limiter.check(&peer_id, GossipRequestType::OrderInfo),
+            RateLimitDecision::Admit
+        );
+        assert_eq!(
+            limiter.check(&PeerId::random(), GossipRequestType::Heartbeat),
+            RateLimitDecision::Admit
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            limiter.check(&peer_id, GossipRequestType::Heartbeat),
+            RateLimitDecision::Admit
+        );
+    }
+
+    /// Tests that a peer is banned once its spam score crosses the threshold, that the ban
+    /// is lifted once it expires, and that a repeat offender is banned for longer
+    #[test]
+    fn test_ban_repeat_offender() {
+        let (mut limiter, clock) = setup();
+        let peer_id = PeerId::random();
+
+        let flood = |limiter: &mut PeerRateLimiter| {
+            (0..10)
+                .map(|_| limiter.check(&peer_id, GossipRequestType::Heartbeat))
+                .find(|decision| matches!(decision, RateLimitDecision::Ban(_)))
+        };
+
+        assert_eq!(
+            flood(&mut limiter),
+            Some(RateLimitDecision::Ban(Duration::from_secs(60)))
+        );
+        assert!(limiter.is_banned(&peer_id));
+        assert_eq!(
+            limiter.check(&peer_id, GossipRequestType::OrderInfo),
+            RateLimitDecision::Drop
+        );
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(limiter.expire_bans(), vec![peer_id]);
+        assert!(!limiter.is_banned(&peer_id));
+
+        assert_eq!(
+            flood(&mut limiter),
+            Some(RateLimitDecision::Ban(Duration::from_secs(120)))
+        );
+
+        // A ban is not lifted before it expires
+        clock.advance(Duration::from_secs(119));
+        assert!(limiter.expire_bans().is_empty());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(limiter.expire_bans(), vec![peer_id]);
+    }
+
+    /// Tests that a cluster peer flooding the local node is rate limited but never banned
+    #[test]
+    fn test_cluster_peer_not_banned() {
+        let (mut limiter, _) = setup();
+        let peer_id = PeerId::random();
+        limiter.add_cluster_peer(peer_id);
+
+        let decisions = (0..20)
+            .map(|_| limiter.check(&peer_id, GossipRequestType::Heartbeat))
+            .collect::<Vec<_>>();
+        assert_eq!(decisions[..2], [RateLimitDecision::Admit; 2]);
+        assert!(decisions[2..]
+            .iter()
+            .all(|decision| *decision == RateLimitDecision::Drop));
+        assert!(!limiter.is_banned(&peer_id));
+    }
+}
@@ -4,14 +4,16 @@ use std::thread::{Builder, JoinHandle};
 
 use ed25519_dalek::Keypair;
 use futures::executor::block_on;
-use libp2p::{Multiaddr, Swarm};
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use libp2p::{multiaddr::Protocol, relay::v2::client::Client as RelayClient, Multiaddr, Swarm};
+use tokio::runtime::Builder as RuntimeBuilder;
 use tracing::log;
 
 use crate::{
+    clock::SharedClock,
     gossip::{jobs::GossipServerJob, types::ClusterId},
     gossip_api::gossip::GossipOutbound,
     handshake::jobs::HandshakeExecutionJob,
+    job_queue::{JobQueueReceiver, JobQueueSender},
     network_manager::composed_protocol::ComposedNetworkBehavior,
     state::RelayerState,
     worker::Worker,
@@ -19,9 +21,12 @@ use crate::{
 };
 
 use super::{
-    composed_protocol::ProtocolVersion,
+    composed_protocol::CURRENT_PROTOCOL_VERSION,
+    discovery::PeerDiscovery,
     error::NetworkManagerError,
+    key_rotation::{ClusterKeyChain, ClusterKeyRotationConfig},
     manager::{NetworkManager, NetworkManagerExecutor},
+    rate_limit::{GossipRateLimitConfig, PeerRateLimiter},
 };
 
 /// The worker configuration for the network manager
@@ -34,16 +39,34 @@ pub struct NetworkManagerConfig {
     /// The cluster keypair, wrapped in an option to allow the worker thread to
     /// take ownership of the keypair
     pub(crate) cluster_keypair: Option<Keypair>,
+    /// The schedule on which the cluster rotates the key it signs gossip with
+    pub(crate) key_rotation: ClusterKeyRotationConfig,
+    /// The failure domain the local peer runs in, advertised in its peer info
+    pub(crate) zone: Option<String>,
+    /// The DNS seeds to discover peers through, each dialed as a `/dnsaddr` multiaddr
+    pub(crate) dns_seeds: Vec<Multiaddr>,
+    /// The maximum number of outbound connections to peers found through discovery
+    pub(crate) max_outbound_connections: usize,
+    /// Whether the local peer relays connections for peers that cannot be dialed directly
+    pub(crate) relay_server: bool,
+    /// The relays to listen through, each a multiaddr ending in the relay's `/p2p` peer ID.
+    /// Peers that cannot dial the local peer directly may reach it through any of these
+    pub(crate) relay_addrs: Vec<Multiaddr>,
+    /// The per-peer rate limits on inbound gossip requests, and the ban policy for peers
+    /// that repeatedly exceed them
+    pub(crate) rate_limits: GossipRateLimitConfig,
+    /// The clock that rate limits refill and bans expire against
+    pub(crate) clock: SharedClock,
     /// The channel on which to receive requests from other workers
     /// for outbound traffic
     /// This is wrapped in an option to allow the worker thread to take
     /// ownership of the work queue once it is started. The coordinator
     /// will be left with `None` after this happens
-    pub(crate) send_channel: Option<UnboundedReceiver<GossipOutbound>>,
+    pub(crate) send_channel: Option<JobQueueReceiver<GossipOutbound>>,
     /// The work queue to forward inbound heartbeat requests to
-    pub(crate) gossip_work_queue: UnboundedSender<GossipServerJob>,
+    pub(crate) gossip_work_queue: JobQueueSender<GossipServerJob>,
     /// The work queue to forward inbound handshake requests to
-    pub(crate) handshake_work_queue: UnboundedSender<HandshakeExecutionJob>,
+    pub(crate) handshake_work_queue: JobQueueSender<HandshakeExecutionJob>,
     /// The global shared state of the local relayer
     pub(crate) global_state: RelayerState,
     /// The channel on which the coordinator can send a cancel signal to
@@ -87,14 +110,17 @@ impl Worker for NetworkManager {
     fn start(&mut self) -> Result<(), Self::Error> {
         // Build a transport and connect it to the P2P swarm
         // TODO: Migrate this to QUIC
-        let transport = block_on(libp2p::development_transport(self.local_keypair.clone()))
-            .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
+        let (relay_transport, relay_client) =
+            RelayClient::new_transport_and_behaviour(*self.local_peer_id);
+        let transport = self.build_transport(relay_transport)?;
 
         // Behavior is a composed behavior of RequestResponse with Kademlia
         let mut behavior = ComposedNetworkBehavior::new(
             *self.local_peer_id,
-            ProtocolVersion::Version0,
+            CURRENT_PROTOCOL_VERSION,
             self.local_keypair.clone(),
+            relay_client,
+            self.config.relay_server,
         )?;
 
         // Add any bootstrap addresses to the peer info table
@@ -130,6 +156,14 @@ impl Worker for NetworkManager {
             .listen_on(addr)
             .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
 
+        // Reserve a circuit on each relay so that peers unable to dial the local node directly
+        // may reach it, the relayed address is advertised once the reservation is accepted
+        for relay_addr in self.config.relay_addrs.iter() {
+            swarm
+                .listen_on(relay_addr.clone().with(Protocol::P2pCircuit))
+                .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
+        }
+
         // After assigning address and peer ID, update the global state
         block_on(self.update_global_state_after_startup());
 
@@ -139,20 +173,35 @@ impl Worker for NetworkManager {
         // Start up the worker thread
         let executor = NetworkManagerExecutor::new(
             self.local_peer_id,
-            self.config.cluster_keypair.take().unwrap(),
+            ClusterKeyChain::new(
+                self.cluster_id.clone(),
+                self.config.cluster_keypair.take().unwrap(),
+                self.config.key_rotation,
+                self.config.clock.clone(),
+            ),
             swarm,
             self.config.send_channel.take().unwrap(),
             self.config.gossip_work_queue.clone(),
             self.config.handshake_work_queue.clone(),
             self.config.global_state.clone(),
+            PeerDiscovery::new(
+                self.config.dns_seeds.clone(),
+                &self.cluster_id,
+                self.config.max_outbound_connections,
+            ),
+            PeerRateLimiter::new(self.config.rate_limits.clone(), self.config.clock.clone()),
             self.config.cancel_channel.clone(),
         );
 
         let thread_handle = Builder::new()
             .name("network-manager-main-loop".to_string())
             .spawn(move || {
-                // Block on this to execute the future in a separate thread
-                block_on(executor.executor_loop())
+                // The executor loop runs on its own runtime, which drives its timers
+                let runtime = RuntimeBuilder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                runtime.block_on(executor.executor_loop())
             })
             .map_err(|err| NetworkManagerError::SetupError(err.to_string()))?;
 
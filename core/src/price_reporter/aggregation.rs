@@ -0,0 +1,282 @@
+//! Defines time-windowed aggregations over the PriceReport streams of each Exchange. Whereas the
+//! median PriceReport reflects only the instantaneous midpoint, the TWAP and VWAP smooth prices over
+//! a trailing window, making them far more expensive to manipulate with a short-lived quote.
+//!
+//! The TWAP is computed per Exchange and then the median is taken across Exchanges. The VWAP
+//! weights every PriceReport in the window by its top-of-book volume; Exchanges that do not report
+//! volume do not contribute to the VWAP.
+use serde::{Deserialize, Serialize};
+use stats::median;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display},
+};
+
+use super::{exchanges::Exchange, reporter::PriceReport};
+
+/// The trailing windows over which PriceReports may be aggregated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PriceWindow {
+    /// A trailing one minute window
+    #[serde(rename = "1m")]
+    OneMinute,
+    /// A trailing five minute window
+    #[serde(rename = "5m")]
+    FiveMinutes,
+}
+
+impl PriceWindow {
+    /// The length of the window in milliseconds
+    pub fn duration_ms(&self) -> u128 {
+        match self {
+            PriceWindow::OneMinute => 60_000,
+            PriceWindow::FiveMinutes => 5 * 60_000,
+        }
+    }
+}
+
+impl Display for PriceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_str = match self {
+            PriceWindow::OneMinute => "1m",
+            PriceWindow::FiveMinutes => "5m",
+        };
+        write!(f, "{}", fmt_str)
+    }
+}
+
+/// All supported aggregation windows; the longest determines how much history is retained.
+pub static ALL_PRICE_WINDOWS: &[PriceWindow] = &[PriceWindow::OneMinute, PriceWindow::FiveMinutes];
+
+/// The method used to aggregate PriceReports within a window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AggregationMode {
+    /// Time-weighted average price
+    Twap,
+    /// Volume-weighted average price
+    Vwap,
+}
+
+impl Display for AggregationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_str = match self {
+            AggregationMode::Twap => "twap",
+            AggregationMode::Vwap => "vwap",
+        };
+        write!(f, "{}", fmt_str)
+    }
+}
+
+/// A per-Exchange ring buffer of recent PriceReports, retaining enough history to serve the
+/// longest aggregation window.
+#[derive(Clone, Debug, Default)]
+pub struct PriceHistory {
+    /// The PriceReports received from each Exchange, ordered by local timestamp
+    reports: HashMap<Exchange, VecDeque<PriceReport>>,
+}
+
+impl PriceHistory {
+    /// Create a new, empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The amount of history retained, i.e. the longest aggregation window
+    fn retention_ms() -> u128 {
+        ALL_PRICE_WINDOWS
+            .iter()
+            .map(|window| window.duration_ms())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Record a new PriceReport, pruning any reports that have aged out of every window
+    pub fn record_report(&mut self, price_report: &PriceReport) {
+        let exchange = match price_report.exchange {
+            Some(exchange) => exchange,
+            None => return,
+        };
+
+        let exchange_reports = self.reports.entry(exchange).or_default();
+        exchange_reports.push_back(price_report.clone());
+
+        // We keep the newest report that has fallen out of the retention window, as it defines the
+        // price at the start of the window for the TWAP
+        let cutoff = price_report
+            .local_timestamp
+            .saturating_sub(Self::retention_ms());
+        while exchange_reports.len() > 1 && exchange_reports[1].local_timestamp <= cutoff {
+            exchange_reports.pop_front();
+        }
+    }
+
+    /// Compute the aggregate price for the given mode and window, only including the given Exchanges
+    pub fn aggregate(
+        &self,
+        mode: AggregationMode,
+        window: PriceWindow,
+        exchanges: &[Exchange],
+        now: u128,
+    ) -> Option<f64> {
+        match mode {
+            AggregationMode::Twap => self.twap(window, exchanges, now),
+            AggregationMode::Vwap => self.vwap(window, exchanges, now),
+        }
+    }
+
+    /// Compute the median across Exchanges of each Exchange's time-weighted average price
+    pub fn twap(&self, window: PriceWindow, exchanges: &[Exchange], now: u128) -> Option<f64> {
+        let window_start = now.saturating_sub(window.duration_ms());
+        let exchange_twaps = exchanges
+            .iter()
+            .filter_map(|exchange| self.reports.get(exchange))
+            .filter_map(|reports| Self::exchange_twap(reports, window_start, now))
+            .collect::<Vec<f64>>();
+
+        median(exchange_twaps.into_iter())
+    }
+
+    /// Compute the time-weighted average price of a single Exchange's reports over
+    /// [window_start, now]; each price is held until the next report arrives
+    fn exchange_twap(
+        reports: &VecDeque<PriceReport>,
+        window_start: u128,
+        now: u128,
+    ) -> Option<f64> {
+        let mut weighted_sum = 0.;
+        let mut total_duration = 0.;
+        for (i, report) in reports.iter().enumerate() {
+            let held_until = reports
+                .get(i + 1)
+                .map(|next_report| next_report.local_timestamp)
+                .unwrap_or(now)
+                .min(now);
+            let held_from = report.local_timestamp.max(window_start);
+            if held_until <= held_from {
+                continue;
+            }
+
+            let duration = (held_until - held_from) as f64;
+            weighted_sum += report.midpoint_price * duration;
+            total_duration += duration;
+        }
+
+        if total_duration == 0. {
+            // No time has elapsed since the only report in the window; fall back to its price
+            return reports
+                .back()
+                .filter(|report| report.local_timestamp >= window_start)
+                .map(|report| report.midpoint_price);
+        }
+        Some(weighted_sum / total_duration)
+    }
+
+    /// Compute the volume-weighted average price over all reports in the window
+    pub fn vwap(&self, window: PriceWindow, exchanges: &[Exchange], now: u128) -> Option<f64> {
+        let window_start = now.saturating_sub(window.duration_ms());
+        let (weighted_sum, total_volume) = exchanges
+            .iter()
+            .filter_map(|exchange| self.reports.get(exchange))
+            .flatten()
+            .filter(|report| report.local_timestamp >= window_start)
+            .filter_map(|report| report.volume.map(|volume| (report.midpoint_price, volume)))
+            .fold((0., 0.), |(weighted_sum, total_volume), (price, volume)| {
+                (weighted_sum + price * volume, total_volume + volume)
+            });
+
+        if total_volume == 0. {
+            return None;
+        }
+        Some(weighted_sum / total_volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PriceHistory, PriceWindow};
+    use crate::price_reporter::{exchanges::Exchange, reporter::PriceReport};
+
+    /// Build a PriceReport for the given exchange
+    fn price_report(
+        exchange: Exchange,
+        midpoint_price: f64,
+        volume: Option<f64>,
+        local_timestamp: u128,
+    ) -> PriceReport {
+        PriceReport {
+            exchange: Some(exchange),
+            midpoint_price,
+            volume,
+            local_timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that the TWAP weights each price by the time it was held
+    #[test]
+    fn test_twap() {
+        let mut history = PriceHistory::new();
+        history.record_report(&price_report(Exchange::Binance, 100., None, 0));
+        history.record_report(&price_report(Exchange::Binance, 200., None, 45_000));
+
+        // 100 is held for 45s, 200 for 15s
+        let twap = history
+            .twap(
+                PriceWindow::OneMinute,
+                &[Exchange::Binance],
+                60_000, /* now */
+            )
+            .unwrap();
+        assert_eq!(twap, 125.);
+    }
+
+    /// Tests that a brief price spike barely moves the TWAP
+    #[test]
+    fn test_twap_resists_spike() {
+        let mut history = PriceHistory::new();
+        history.record_report(&price_report(Exchange::Kraken, 100., None, 0));
+        history.record_report(&price_report(Exchange::Kraken, 1000., None, 59_900));
+        history.record_report(&price_report(Exchange::Kraken, 100., None, 59_990));
+
+        let twap = history
+            .twap(
+                PriceWindow::OneMinute,
+                &[Exchange::Kraken],
+                60_000, /* now */
+            )
+            .unwrap();
+        assert!((twap - 101.35).abs() < 1e-6);
+    }
+
+    /// Tests that the VWAP weights by volume and ignores reports without volume
+    #[test]
+    fn test_vwap() {
+        let mut history = PriceHistory::new();
+        history.record_report(&price_report(Exchange::Binance, 100., Some(3.), 0));
+        history.record_report(&price_report(Exchange::Okx, 200., Some(1.), 1_000));
+        history.record_report(&price_report(Exchange::UniswapV3, 1000., None, 2_000));
+
+        let exchanges = [Exchange::Binance, Exchange::Okx, Exchange::UniswapV3];
+        let vwap = history
+            .vwap(PriceWindow::OneMinute, &exchanges, 10_000 /* now */)
+            .unwrap();
+        assert_eq!(vwap, 125.);
+    }
+
+    /// Tests that reports older than the window are excluded from the VWAP
+    #[test]
+    fn test_vwap_window() {
+        let mut history = PriceHistory::new();
+        history.record_report(&price_report(Exchange::Binance, 100., Some(1.), 0));
+        history.record_report(&price_report(Exchange::Binance, 200., Some(1.), 120_000));
+
+        let vwap = history
+            .vwap(
+                PriceWindow::OneMinute,
+                &[Exchange::Binance],
+                150_000, /* now */
+            )
+            .unwrap();
+        assert_eq!(vwap, 200.);
+    }
+}
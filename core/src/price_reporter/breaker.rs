@@ -0,0 +1,232 @@
+//! Defines a rate-of-change circuit breaker over the median price. A flash spike on a single
+//! venue can briefly drag the median; if the median moves further than the configured fraction
+//! within the configured window, the breaker trips and the last stable median is held for
+//! matching. The hold is lifted when the median returns to the stable price, or when enough
+//! Exchanges independently confirm the move.
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use super::{exchanges::Exchange, reporter::PriceReport};
+
+/// The default maximum move (as a fraction) of the median within the window before the breaker
+/// trips
+const DEFAULT_MAX_MOVE: f64 = 0.05;
+/// The default window (in milliseconds) over which the median's rate of change is measured
+const DEFAULT_WINDOW_MS: u128 = 10_000; // 10 seconds
+/// The default number of Exchanges that must independently report a move before it is accepted
+const DEFAULT_MIN_CONFIRMATIONS: usize = 2;
+
+/// The thresholds of the circuit breaker for a single token pair
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerConfig {
+    /// The maximum move (as a fraction) of the median within the window
+    pub max_move: f64,
+    /// The window (in milliseconds) over which the median's move is measured
+    pub window_ms: u128,
+    /// The number of Exchanges whose own price must have moved past `max_move` in the
+    /// direction of the median for the move to be accepted
+    pub min_confirmations: usize,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            max_move: DEFAULT_MAX_MOVE,
+            window_ms: DEFAULT_WINDOW_MS,
+            min_confirmations: DEFAULT_MIN_CONFIRMATIONS,
+        }
+    }
+}
+
+/// The result of passing a new median through the circuit breaker
+#[derive(Clone, Debug, PartialEq)]
+pub enum BreakerOutcome {
+    /// The median is accepted as the price for matching
+    Pass(PriceReport),
+    /// The breaker has tripped, matching should use the held stable price. Includes the
+    /// unconfirmed move (as a fraction) of the median away from the stable price.
+    Held(PriceReport, f64),
+}
+
+/// A rate-of-change circuit breaker over the median PriceReports of a single token pair.
+#[derive(Clone, Debug)]
+pub struct PriceCircuitBreaker {
+    /// The thresholds of the breaker
+    config: CircuitBreakerConfig,
+    /// The accepted medians within the window, as (local timestamp, midpoint price)
+    recent_medians: VecDeque<(u128, f64)>,
+    /// The last accepted median, None until the first median is seen
+    stable_report: Option<PriceReport>,
+    /// The unconfirmed move of the median while the breaker is tripped, None otherwise
+    held_move: Option<f64>,
+}
+
+impl PriceCircuitBreaker {
+    /// Create a new breaker with the given thresholds
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            recent_medians: VecDeque::new(),
+            stable_report: None,
+            held_move: None,
+        }
+    }
+
+    /// Returns the held stable price and the unconfirmed move if the breaker is tripped
+    pub fn held_state(&self) -> Option<(PriceReport, f64)> {
+        self.held_move
+            .zip(self.stable_report.clone())
+            .map(|(price_move, stable_report)| (stable_report, price_move))
+    }
+
+    /// Pass a new median through the breaker
+    ///
+    /// `current_price_reports` are the per-Exchange reports the median was computed from, and
+    /// are used to confirm a move that trips the breaker
+    pub fn record_median(
+        &mut self,
+        median_report: PriceReport,
+        current_price_reports: &HashMap<Exchange, PriceReport>,
+        now: u128,
+    ) -> BreakerOutcome {
+        while let Some((timestamp, _)) = self.recent_medians.front() {
+            if now.saturating_sub(*timestamp) <= self.config.window_ms {
+                break;
+            }
+            self.recent_medians.pop_front();
+        }
+
+        let stable_price = match &self.stable_report {
+            Some(stable_report) => stable_report.midpoint_price,
+            None => return self.accept(median_report, now),
+        };
+
+        // While the breaker is not tripped, the move is measured against every median accepted
+        // within the window so that a gradual drift is not mistaken for a spike
+        let window_move = self
+            .recent_medians
+            .iter()
+            .map(|(_, price)| relative_move(*price, median_report.midpoint_price).abs())
+            .fold(0., f64::max);
+        if self.held_move.is_none() && window_move <= self.config.max_move {
+            return self.accept(median_report, now);
+        }
+
+        // The spike reverted before it was confirmed
+        let price_move = relative_move(stable_price, median_report.midpoint_price);
+        if price_move.abs() <= self.config.max_move {
+            return self.accept(median_report, now);
+        }
+
+        let confirmations = current_price_reports
+            .values()
+            .filter(|price_report| **price_report != PriceReport::default())
+            .map(|price_report| relative_move(stable_price, price_report.midpoint_price))
+            .filter(|exchange_move| {
+                exchange_move.signum() == price_move.signum()
+                    && exchange_move.abs() > self.config.max_move
+            })
+            .count();
+        if confirmations >= self.config.min_confirmations {
+            // The move is the new market price, measure future moves from it
+            self.recent_medians.clear();
+            return self.accept(median_report, now);
+        }
+
+        self.held_move = Some(price_move);
+        BreakerOutcome::Held(self.stable_report.clone().unwrap(), price_move)
+    }
+
+    /// Accept a median as the new stable price
+    fn accept(&mut self, median_report: PriceReport, now: u128) -> BreakerOutcome {
+        self.recent_medians
+            .push_back((now, median_report.midpoint_price));
+        self.stable_report = Some(median_report.clone());
+        self.held_move = None;
+
+        BreakerOutcome::Pass(median_report)
+    }
+}
+
+/// The move (as a signed fraction) from one price to another
+fn relative_move(from: f64, to: f64) -> f64 {
+    (to - from) / from
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{BreakerOutcome, CircuitBreakerConfig, PriceCircuitBreaker};
+    use crate::price_reporter::{exchanges::Exchange, reporter::PriceReport};
+
+    /// Build a PriceReport at the given price
+    fn price_report(exchange: Option<Exchange>, midpoint_price: f64) -> PriceReport {
+        PriceReport {
+            exchange,
+            midpoint_price,
+            local_timestamp: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Build the per-Exchange reports from a list of prices
+    fn exchange_reports(prices: &[(Exchange, f64)]) -> HashMap<Exchange, PriceReport> {
+        prices
+            .iter()
+            .map(|(exchange, price)| (*exchange, price_report(Some(*exchange), *price)))
+            .collect()
+    }
+
+    /// Tests that a single-venue spike is held and released once it reverts
+    #[test]
+    fn test_spike_held_until_reverted() {
+        let mut breaker = PriceCircuitBreaker::new(CircuitBreakerConfig::default());
+        let reports = exchange_reports(&[(Exchange::Binance, 100.), (Exchange::Kraken, 100.)]);
+        breaker.record_median(price_report(None, 100.), &reports, 0 /* now */);
+
+        let reports = exchange_reports(&[(Exchange::Binance, 130.), (Exchange::Kraken, 100.)]);
+        let outcome =
+            breaker.record_median(price_report(None, 115.), &reports, 1_000 /* now */);
+        assert!(
+            matches!(outcome, BreakerOutcome::Held(ref stable, _) if stable.midpoint_price == 100.)
+        );
+        assert!(breaker.held_state().is_some());
+
+        let reports = exchange_reports(&[(Exchange::Binance, 101.), (Exchange::Kraken, 100.)]);
+        let outcome =
+            breaker.record_median(price_report(None, 100.5), &reports, 2_000 /* now */);
+        assert_eq!(outcome, BreakerOutcome::Pass(price_report(None, 100.5)));
+        assert!(breaker.held_state().is_none());
+    }
+
+    /// Tests that a move confirmed by enough exchanges is accepted
+    #[test]
+    fn test_confirmed_move_accepted() {
+        let mut breaker = PriceCircuitBreaker::new(CircuitBreakerConfig::default());
+        let reports = exchange_reports(&[(Exchange::Binance, 100.), (Exchange::Kraken, 100.)]);
+        breaker.record_median(price_report(None, 100.), &reports, 0 /* now */);
+
+        let reports = exchange_reports(&[(Exchange::Binance, 90.), (Exchange::Kraken, 89.)]);
+        let outcome =
+            breaker.record_median(price_report(None, 89.5), &reports, 1_000 /* now */);
+        assert_eq!(outcome, BreakerOutcome::Pass(price_report(None, 89.5)));
+    }
+
+    /// Tests that a move spread over more than the window does not trip the breaker
+    #[test]
+    fn test_gradual_move_passes() {
+        let config = CircuitBreakerConfig::default();
+        let mut breaker = PriceCircuitBreaker::new(config);
+        let reports = exchange_reports(&[(Exchange::Binance, 100.)]);
+
+        let mut price = 100.;
+        for step in 0..5 {
+            let now = step * (config.window_ms + 1);
+            let outcome = breaker.record_median(price_report(None, price), &reports, now);
+            assert_eq!(outcome, BreakerOutcome::Pass(price_report(None, price)));
+            price *= 1. + config.max_move * 0.9;
+        }
+    }
+}
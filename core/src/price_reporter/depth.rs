@@ -0,0 +1,177 @@
+//! Defines the order book depth reported by each Exchange alongside its midpoint, and the
+//! aggregation of depth across Exchanges. Whereas the midpoint prices a match, the depth bounds
+//! how large a match can be before it moves the external market; the handshake manager may use
+//! the aggregate liquidity within a price band to cap the size of a match.
+//!
+//! Not every feed reports the same number of levels: Coinbase and Okx report several levels per
+//! side, while Binance and Kraken report only the top of book.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{cmp::Ordering, collections::HashMap};
+
+use super::{exchanges::Exchange, tokens::Token};
+
+/// The number of levels per side retained in an OrderBookDepth.
+pub const DEPTH_LEVELS: usize = 10;
+
+/// A single price level of an order book.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepthLevel {
+    /// The price of the level, in units of the quote Token per base Token
+    pub price: f64,
+    /// The quantity quoted at the level, in units of the base Token
+    pub quantity: f64,
+}
+
+/// The top levels of each side of an order book, best level first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderBookDepth {
+    /// The bid levels, in descending order of price
+    pub bids: Vec<DepthLevel>,
+    /// The ask levels, in ascending order of price
+    pub asks: Vec<DepthLevel>,
+}
+
+impl OrderBookDepth {
+    /// Build a depth from unordered levels, retaining the best DEPTH_LEVELS per side. Levels
+    /// quoting a zero quantity are dropped.
+    pub fn from_levels(bids: Vec<DepthLevel>, asks: Vec<DepthLevel>) -> Self {
+        Self {
+            bids: Self::best_levels(bids, true /* descending */),
+            asks: Self::best_levels(asks, false /* descending */),
+        }
+    }
+
+    /// Build a depth containing only the top of book.
+    pub fn top_of_book(best_bid: DepthLevel, best_ask: DepthLevel) -> Self {
+        Self::from_levels(vec![best_bid], vec![best_ask])
+    }
+
+    /// Parse the levels of one side of a book given in the common exchange format: an array of
+    /// levels, each an array whose first two elements are the price and quantity as strings.
+    /// Trailing elements (e.g. order counts) are ignored. Returns None if any level is malformed.
+    pub fn parse_levels(levels: &Value) -> Option<Vec<DepthLevel>> {
+        levels
+            .as_array()?
+            .iter()
+            .map(|level| {
+                Some(DepthLevel {
+                    price: level[0].as_str()?.parse().ok()?,
+                    quantity: level[1].as_str()?.parse().ok()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Merge the depth of several Exchanges into a single book. Quantities quoted at the same
+    /// price on different Exchanges are summed.
+    pub fn aggregate<'a>(depths: impl IntoIterator<Item = &'a OrderBookDepth>) -> Self {
+        let mut bids = Vec::new();
+        let mut asks = Vec::new();
+        for depth in depths {
+            bids.extend_from_slice(&depth.bids);
+            asks.extend_from_slice(&depth.asks);
+        }
+
+        Self::from_levels(Self::merge_levels(bids), Self::merge_levels(asks))
+    }
+
+    /// The total bid quantity, in units of the base Token, quoted at or above the given price;
+    /// i.e. the amount that may be sold into the book without filling below the price.
+    pub fn bid_liquidity(&self, min_price: f64) -> f64 {
+        self.bids
+            .iter()
+            .filter(|level| level.price >= min_price)
+            .map(|level| level.quantity)
+            .sum()
+    }
+
+    /// The total ask quantity, in units of the base Token, quoted at or below the given price;
+    /// i.e. the amount that may be bought from the book without filling above the price.
+    pub fn ask_liquidity(&self, max_price: f64) -> f64 {
+        self.asks
+            .iter()
+            .filter(|level| level.price <= max_price)
+            .map(|level| level.quantity)
+            .sum()
+    }
+
+    /// Sort the levels best first and truncate to DEPTH_LEVELS.
+    fn best_levels(mut levels: Vec<DepthLevel>, descending: bool) -> Vec<DepthLevel> {
+        levels.retain(|level| level.quantity > 0.0);
+        levels.sort_by(|a, b| {
+            let ordering = a.price.partial_cmp(&b.price).unwrap_or(Ordering::Equal);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        levels.truncate(DEPTH_LEVELS);
+        levels
+    }
+
+    /// Sum the quantities of levels quoted at identical prices.
+    fn merge_levels(levels: Vec<DepthLevel>) -> Vec<DepthLevel> {
+        let mut merged: Vec<DepthLevel> = Vec::with_capacity(levels.len());
+        for level in levels {
+            match merged
+                .iter_mut()
+                .find(|existing| existing.price == level.price)
+            {
+                Some(existing) => existing.quantity += level.quantity,
+                None => merged.push(level),
+            }
+        }
+
+        merged
+    }
+}
+
+/// The depth reported by each Exchange for a token pair, and their aggregate.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderBookDepthReport {
+    /// The base Token
+    pub base_token: Token,
+    /// The quote Token
+    pub quote_token: Token,
+    /// The latest depth reported by each Exchange that reports depth
+    pub exchanges: HashMap<Exchange, OrderBookDepth>,
+    /// The depth of all Exchanges merged into a single book
+    pub aggregate: OrderBookDepth,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{DepthLevel, OrderBookDepth};
+
+    /// Shorthand for a DepthLevel
+    fn level(price: f64, quantity: f64) -> DepthLevel {
+        DepthLevel { price, quantity }
+    }
+
+    /// Tests parsing levels, and that aggregation sorts and merges levels across Exchanges
+    #[test]
+    fn test_aggregate_depth() {
+        let okx_depth = OrderBookDepth::from_levels(
+            OrderBookDepth::parse_levels(&json!([["99.5", "2", "0", "1"], ["100", "1", "0", "3"]]))
+                .unwrap(),
+            OrderBookDepth::parse_levels(&json!([["101", "4", "0", "2"]])).unwrap(),
+        );
+        assert_eq!(okx_depth.bids, vec![level(100., 1.), level(99.5, 2.)]);
+        assert!(OrderBookDepth::parse_levels(&json!([["100", 1]])).is_none());
+
+        let binance_depth = OrderBookDepth::top_of_book(level(100., 3.), level(100.5, 1.));
+        let aggregate = OrderBookDepth::aggregate([&okx_depth, &binance_depth]);
+
+        assert_eq!(aggregate.bids, vec![level(100., 4.), level(99.5, 2.)]);
+        assert_eq!(aggregate.asks, vec![level(100.5, 1.), level(101., 4.)]);
+        assert_eq!(aggregate.bid_liquidity(99.75), 4.);
+        assert_eq!(aggregate.ask_liquidity(101.), 5.);
+    }
+}
@@ -2,6 +2,8 @@
 use std::error::Error;
 use std::fmt::{self, Display};
 
+use crate::error::{ErrorCode, ErrorCoded};
+
 #[derive(Clone, Debug)]
 /// The core error type used by the ExchangeConnection. All thrown errors are handled by the
 /// PriceReporter, either for restarts or panics upon too many consecutive errors.
@@ -40,6 +42,12 @@ pub enum PriceReporterManagerError {
     /// Tried to query information from a PriceReporter that does not exist. Callers should send a
     /// StartPriceReporter job first
     PriceReporterNotCreated(String),
+    /// Fetching or parsing the on-chain token registry failed
+    TokenRegistry(String),
+    /// Reading or parsing the operator's token remap file failed
+    TokenRemap(String),
+    /// Reading or flushing the historical price store failed
+    PriceHistory(String),
     /// In one of the PriceReporters, one of the ExchangeConnections failed too many times in a
     /// row.
     _TooManyFailures(ExchangeConnectionError),
@@ -64,6 +72,15 @@ impl Display for PriceReporterManagerError {
             PriceReporterManagerError::PriceReporterNotCreated(err) => {
                 format!("PriceReporterNotCreated({})", err)
             }
+            PriceReporterManagerError::TokenRegistry(err) => {
+                format!("TokenRegistry({})", err)
+            }
+            PriceReporterManagerError::TokenRemap(err) => {
+                format!("TokenRemap({})", err)
+            }
+            PriceReporterManagerError::PriceHistory(err) => {
+                format!("PriceHistory({})", err)
+            }
             PriceReporterManagerError::_TooManyFailures(exchange_connection_error) => {
                 format!("TooManyFailures({})", exchange_connection_error)
             }
@@ -71,3 +88,19 @@ impl Display for PriceReporterManagerError {
         write!(f, "{}", display_string)
     }
 }
+
+impl ErrorCoded for PriceReporterManagerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            PriceReporterManagerError::Cancelled(_) => ErrorCode::Cancelled,
+            PriceReporterManagerError::ManagerSetup(_) => ErrorCode::Setup,
+            PriceReporterManagerError::AlreadyListening(_) => ErrorCode::Conflict,
+            PriceReporterManagerError::ListenerNotFound(_) => ErrorCode::NotFound,
+            PriceReporterManagerError::PriceReporterNotCreated(_) => ErrorCode::NotFound,
+            PriceReporterManagerError::TokenRegistry(_) => ErrorCode::Chain,
+            PriceReporterManagerError::TokenRemap(_) => ErrorCode::Config,
+            PriceReporterManagerError::PriceHistory(_) => ErrorCode::Storage,
+            PriceReporterManagerError::_TooManyFailures(_) => ErrorCode::Network,
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use futures::{stream::StreamExt, SinkExt};
 use ring_channel::{ring_channel, RingReceiver, RingSender};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{
     fmt::{self, Display},
     num::NonZeroUsize,
+    str::FromStr,
     time::{SystemTime, UNIX_EPOCH},
 };
 use tokio::time::{sleep, Duration};
@@ -15,7 +17,8 @@ use crate::price_reporter::worker::PriceReporterManagerConfig;
 use super::super::{
     errors::ExchangeConnectionError,
     exchanges::handlers_centralized::{
-        BinanceHandler, CentralizedExchangeHandler, CoinbaseHandler, KrakenHandler, OkxHandler,
+        BinanceHandler, BinanceUsHandler, BybitHandler, CentralizedExchangeHandler,
+        CoinbaseHandler, KrakenHandler, OkxHandler,
     },
     exchanges::handlers_decentralized::UniswapV3Handler,
     reporter::PriceReport,
@@ -37,10 +40,14 @@ pub fn get_current_time() -> u128 {
 /// The type of exchange. Note that `Exchange` is the abstract enum for all exchanges that are
 /// supported, whereas the `ExchangeConnection` is the actual instantiation of a websocket price
 /// stream from an `Exchange`.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Exchange {
     /// Binance.
     Binance,
+    /// Binance.US.
+    BinanceUs,
+    /// Bybit.
+    Bybit,
     /// Coinbase.
     Coinbase,
     /// Kraken.
@@ -54,6 +61,8 @@ impl Display for Exchange {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let fmt_str = match self {
             Exchange::Binance => String::from("binance"),
+            Exchange::BinanceUs => String::from("binanceus"),
+            Exchange::Bybit => String::from("bybit"),
             Exchange::Coinbase => String::from("coinbase"),
             Exchange::Kraken => String::from("kraken"),
             Exchange::Okx => String::from("okx"),
@@ -62,10 +71,23 @@ impl Display for Exchange {
         write!(f, "{}", fmt_str)
     }
 }
+impl FromStr for Exchange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_EXCHANGES
+            .iter()
+            .find(|exchange| exchange.to_string() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("unknown exchange: {}", s))
+    }
+}
 
 /// Every Exchange.
 pub static ALL_EXCHANGES: &[Exchange] = &[
     Exchange::Binance,
+    Exchange::BinanceUs,
+    Exchange::Bybit,
     Exchange::Coinbase,
     Exchange::Kraken,
     Exchange::Okx,
@@ -104,6 +126,10 @@ impl Display for ExchangeConnectionState {
 pub struct ExchangeConnection {
     /// The CentralizedExchangeHandler for Binance.
     binance_handler: Option<BinanceHandler>,
+    /// The CentralizedExchangeHandler for Binance.US.
+    binance_us_handler: Option<BinanceUsHandler>,
+    /// The CentralizedExchangeHandler for Bybit.
+    bybit_handler: Option<BybitHandler>,
     /// The CentralizedExchangeHandler for Coinbase.
     coinbase_handler: Option<CoinbaseHandler>,
     /// The CentralizedExchangeHandler for Kraken.
@@ -128,6 +154,13 @@ impl ExchangeConnection {
         let (mut price_report_sender, price_report_receiver) =
             ring_channel::<PriceReport>(NonZeroUsize::new(1).unwrap());
 
+        // A recorded feed stands in for every exchange, no connection is made
+        if let Some(recorded_feed) = config.recorded_feed.as_ref() {
+            let worker_handles =
+                recorded_feed.replay(base_token, quote_token, exchange, price_report_sender);
+            return Ok((price_report_receiver, worker_handles));
+        }
+
         // UniswapV3 logic is slightly different, as we use the web3 API wrapper for convenience,
         // rather than interacting directly over websockets.
         if exchange == Exchange::UniswapV3 {
@@ -142,31 +175,39 @@ impl ExchangeConnection {
         }
 
         // Get initial ExchangeHandler state and include in a new ExchangeConnection.
-        let mut exchange_connection = match exchange {
-            Exchange::Binance => ExchangeConnection {
-                binance_handler: Some(BinanceHandler::new(base_token, quote_token, config)),
-                coinbase_handler: None,
-                kraken_handler: None,
-                okx_handler: None,
-            },
-            Exchange::Coinbase => ExchangeConnection {
-                binance_handler: None,
-                coinbase_handler: Some(CoinbaseHandler::new(base_token, quote_token, config)),
-                kraken_handler: None,
-                okx_handler: None,
-            },
-            Exchange::Kraken => ExchangeConnection {
-                binance_handler: None,
-                coinbase_handler: None,
-                kraken_handler: Some(KrakenHandler::new(base_token, quote_token, config)),
-                okx_handler: None,
-            },
-            Exchange::Okx => ExchangeConnection {
-                binance_handler: None,
-                coinbase_handler: None,
-                kraken_handler: None,
-                okx_handler: Some(OkxHandler::new(base_token, quote_token, config)),
-            },
+        let mut exchange_connection = ExchangeConnection {
+            binance_handler: None,
+            binance_us_handler: None,
+            bybit_handler: None,
+            coinbase_handler: None,
+            kraken_handler: None,
+            okx_handler: None,
+        };
+        match exchange {
+            Exchange::Binance => {
+                exchange_connection.binance_handler =
+                    Some(BinanceHandler::new(base_token, quote_token, config))
+            }
+            Exchange::BinanceUs => {
+                exchange_connection.binance_us_handler =
+                    Some(BinanceUsHandler::new(base_token, quote_token, config))
+            }
+            Exchange::Bybit => {
+                exchange_connection.bybit_handler =
+                    Some(BybitHandler::new(base_token, quote_token, config))
+            }
+            Exchange::Coinbase => {
+                exchange_connection.coinbase_handler =
+                    Some(CoinbaseHandler::new(base_token, quote_token, config))
+            }
+            Exchange::Kraken => {
+                exchange_connection.kraken_handler =
+                    Some(KrakenHandler::new(base_token, quote_token, config))
+            }
+            Exchange::Okx => {
+                exchange_connection.okx_handler =
+                    Some(OkxHandler::new(base_token, quote_token, config))
+            }
             _ => unreachable!(),
         };
 
@@ -177,6 +218,16 @@ impl ExchangeConnection {
                 .as_mut()
                 .unwrap()
                 .pre_stream_price_report(),
+            Exchange::BinanceUs => exchange_connection
+                .binance_us_handler
+                .as_mut()
+                .unwrap()
+                .pre_stream_price_report(),
+            Exchange::Bybit => exchange_connection
+                .bybit_handler
+                .as_mut()
+                .unwrap()
+                .pre_stream_price_report(),
             Exchange::Coinbase => exchange_connection
                 .coinbase_handler
                 .as_mut()
@@ -215,6 +266,16 @@ impl ExchangeConnection {
                 .as_ref()
                 .unwrap()
                 .websocket_url(),
+            Exchange::BinanceUs => exchange_connection
+                .binance_us_handler
+                .as_ref()
+                .unwrap()
+                .websocket_url(),
+            Exchange::Bybit => exchange_connection
+                .bybit_handler
+                .as_ref()
+                .unwrap()
+                .websocket_url(),
             Exchange::Coinbase => exchange_connection
                 .coinbase_handler
                 .as_ref()
@@ -241,7 +302,8 @@ impl ExchangeConnection {
                 if exchange == Exchange::Binance {
                     println!(
                         "You are likely attempting to connect from an IP address \
-                        blacklisted by Binance (e.g., anything US-based)"
+                        blacklisted by Binance (e.g., anything US-based); US deployments \
+                        should set the `us` exchange region to use Binance.US instead"
                     );
                 }
                 println!("Cannot connect to the remote URL: {}", wss_url);
@@ -258,6 +320,16 @@ impl ExchangeConnection {
                 .as_ref()
                 .unwrap()
                 .websocket_subscribe(&mut socket),
+            Exchange::BinanceUs => exchange_connection
+                .binance_us_handler
+                .as_ref()
+                .unwrap()
+                .websocket_subscribe(&mut socket),
+            Exchange::Bybit => exchange_connection
+                .bybit_handler
+                .as_ref()
+                .unwrap()
+                .websocket_subscribe(&mut socket),
             Exchange::Coinbase => exchange_connection
                 .coinbase_handler
                 .as_ref()
@@ -299,6 +371,12 @@ impl ExchangeConnection {
                         .send(Message::Text("ping".to_string()))
                         .await
                         .unwrap();
+                } else if exchange == Exchange::Bybit {
+                    // Bybit expects an application-level ping rather than a ping frame
+                    socket_sink
+                        .send(Message::Text(json!({ "op": "ping" }).to_string()))
+                        .await
+                        .unwrap();
                 } else {
                     socket_sink.send(Message::Ping(vec![])).await.unwrap();
                 }
@@ -335,6 +413,10 @@ impl ExchangeConnection {
         let price_report = {
             if let Some(binance_handler) = &mut self.binance_handler {
                 binance_handler.handle_exchange_message(message_json)
+            } else if let Some(binance_us_handler) = &mut self.binance_us_handler {
+                binance_us_handler.handle_exchange_message(message_json)
+            } else if let Some(bybit_handler) = &mut self.bybit_handler {
+                bybit_handler.handle_exchange_message(message_json)
             } else if let Some(coinbase_handler) = &mut self.coinbase_handler {
                 coinbase_handler.handle_exchange_message(message_json)
             } else if let Some(kraken_handler) = &mut self.kraken_handler {
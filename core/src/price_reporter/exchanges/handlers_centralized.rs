@@ -10,6 +10,7 @@ use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use crate::price_reporter::worker::PriceReporterManagerConfig;
 
 use super::super::{
+    depth::{DepthLevel, OrderBookDepth},
     errors::ExchangeConnectionError,
     exchanges::{connection::get_current_time, Exchange},
     reporter::PriceReport,
@@ -19,6 +20,45 @@ use super::super::{
 /// WebSocket type for streams from all centralized exchanges.
 type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+/// Helper to compute the top-of-book volume from the quantities quoted at the best bid and offer.
+/// Volume is informational only, so an unparseable quantity yields None rather than an error.
+fn parse_top_of_book_volume(bid_quantity: &Value, offer_quantity: &Value) -> Option<f64> {
+    let bid_quantity = bid_quantity.as_str()?.parse::<f64>().ok()?;
+    let offer_quantity = offer_quantity.as_str()?.parse::<f64>().ok()?;
+    Some(bid_quantity + offer_quantity)
+}
+
+/// Helper to build the depth of an exchange that reports only the top of book. As with volume, an
+/// unparseable quantity yields None.
+fn parse_top_of_book_depth(
+    best_bid: f64,
+    bid_quantity: &Value,
+    best_offer: f64,
+    offer_quantity: &Value,
+) -> Option<OrderBookDepth> {
+    Some(OrderBookDepth::top_of_book(
+        DepthLevel {
+            price: best_bid,
+            quantity: bid_quantity.as_str()?.parse().ok()?,
+        },
+        DepthLevel {
+            price: best_offer,
+            quantity: offer_quantity.as_str()?.parse().ok()?,
+        },
+    ))
+}
+
+/// Helper to collect the levels of a locally mirrored order book side into DepthLevels.
+fn order_book_levels(order_book: &HashMap<String, f32>) -> Vec<DepthLevel> {
+    order_book
+        .iter()
+        .map(|(price_level, quantity)| DepthLevel {
+            price: price_level.parse().unwrap(),
+            quantity: *quantity as f64,
+        })
+        .collect()
+}
+
 /// The core trait that all centralized exchange handlers implement. This allows for creation of
 /// stateful elements (e.g., a local order book), websocket URLs, pre-websocket-stream one-off
 /// price reports, and handling of remote messages.
@@ -48,12 +88,32 @@ pub trait CentralizedExchangeHandler {
 }
 
 #[derive(Clone, Debug)]
-/// The message handler for Exchange::Binance.
+/// The message handler for Exchange::Binance. Binance.US serves the same API from its own hosts,
+/// so the handler also backs Exchange::BinanceUs.
 pub struct BinanceHandler {
     /// The base Token (e.g., WETH).
     base_token: Token,
     /// The quote Token (e.g., USDC).
     quote_token: Token,
+    /// The Binance venue streamed from, either Exchange::Binance or Exchange::BinanceUs.
+    exchange: Exchange,
+}
+impl BinanceHandler {
+    /// The host serving the websocket streams of the venue.
+    fn stream_host(&self) -> &'static str {
+        match self.exchange {
+            Exchange::BinanceUs => "stream.binance.us:9443",
+            _ => "stream.binance.com:443",
+        }
+    }
+
+    /// The host serving the REST API of the venue.
+    fn api_host(&self) -> &'static str {
+        match self.exchange {
+            Exchange::BinanceUs => "api.binance.us",
+            _ => "api.binance.com",
+        }
+    }
 }
 #[async_trait]
 impl CentralizedExchangeHandler for BinanceHandler {
@@ -61,14 +121,16 @@ impl CentralizedExchangeHandler for BinanceHandler {
         Self {
             base_token,
             quote_token,
+            exchange: Exchange::Binance,
         }
     }
 
     fn websocket_url(&self) -> String {
-        let base_ticker = self.base_token.get_exchange_ticker(Exchange::Binance);
-        let quote_ticker = self.quote_token.get_exchange_ticker(Exchange::Binance);
+        let base_ticker = self.base_token.get_exchange_ticker(self.exchange);
+        let quote_ticker = self.quote_token.get_exchange_ticker(self.exchange);
         format!(
-            "wss://stream.binance.com:443/ws/{}{}@bookTicker",
+            "wss://{}/ws/{}{}@bookTicker",
+            self.stream_host(),
             base_ticker.to_lowercase(),
             quote_ticker.to_lowercase()
         )
@@ -78,11 +140,13 @@ impl CentralizedExchangeHandler for BinanceHandler {
         &mut self,
     ) -> Result<Option<PriceReport>, ExchangeConnectionError> {
         // TODO: This is duplicate code, condense it.
-        let base_ticker = self.base_token.get_exchange_ticker(Exchange::Binance);
-        let quote_ticker = self.quote_token.get_exchange_ticker(Exchange::Binance);
+        let base_ticker = self.base_token.get_exchange_ticker(self.exchange);
+        let quote_ticker = self.quote_token.get_exchange_ticker(self.exchange);
         let request_url = format!(
-            "https://api.binance.com/api/v3/ticker/bookTicker?symbol={}{}",
-            base_ticker, quote_ticker
+            "https://{}/api/v3/ticker/bookTicker?symbol={}{}",
+            self.api_host(),
+            base_ticker,
+            quote_ticker
         );
         let message_resp = reqwest::get(request_url)
             .await
@@ -110,8 +174,15 @@ impl CentralizedExchangeHandler for BinanceHandler {
         Ok(Some(PriceReport {
             base_token: self.base_token.clone(),
             quote_token: self.quote_token.clone(),
-            exchange: Some(Exchange::Binance),
+            exchange: Some(self.exchange),
             midpoint_price: (best_bid + best_offer) / 2.0,
+            volume: parse_top_of_book_volume(&message_json["bidQty"], &message_json["askQty"]),
+            depth: parse_top_of_book_depth(
+                best_bid,
+                &message_json["bidQty"],
+                best_offer,
+                &message_json["askQty"],
+            ),
             reported_timestamp: None,
             local_timestamp: get_current_time(),
         }))
@@ -151,14 +222,59 @@ impl CentralizedExchangeHandler for BinanceHandler {
         Ok(Some(PriceReport {
             base_token: self.base_token.clone(),
             quote_token: self.quote_token.clone(),
-            exchange: Some(Exchange::Binance),
+            exchange: Some(self.exchange),
             midpoint_price: (best_bid + best_offer) / 2.0,
+            volume: parse_top_of_book_volume(&message_json["B"], &message_json["A"]),
+            depth: parse_top_of_book_depth(
+                best_bid,
+                &message_json["B"],
+                best_offer,
+                &message_json["A"],
+            ),
             reported_timestamp: None,
             local_timestamp: Default::default(),
         }))
     }
 }
 
+/// The message handler for Exchange::BinanceUs, a BinanceHandler pointed at the Binance.US hosts.
+#[derive(Clone, Debug)]
+pub struct BinanceUsHandler(BinanceHandler);
+#[async_trait]
+impl CentralizedExchangeHandler for BinanceUsHandler {
+    fn new(base_token: Token, quote_token: Token, _: PriceReporterManagerConfig) -> Self {
+        Self(BinanceHandler {
+            base_token,
+            quote_token,
+            exchange: Exchange::BinanceUs,
+        })
+    }
+
+    fn websocket_url(&self) -> String {
+        self.0.websocket_url()
+    }
+
+    async fn pre_stream_price_report(
+        &mut self,
+    ) -> Result<Option<PriceReport>, ExchangeConnectionError> {
+        self.0.pre_stream_price_report().await
+    }
+
+    async fn websocket_subscribe(
+        &self,
+        socket: &mut WebSocket,
+    ) -> Result<(), ExchangeConnectionError> {
+        self.0.websocket_subscribe(socket).await
+    }
+
+    fn handle_exchange_message(
+        &mut self,
+        message_json: Value,
+    ) -> Result<Option<PriceReport>, ExchangeConnectionError> {
+        self.0.handle_exchange_message(message_json)
+    }
+}
+
 /// The message handler for Exchange::Coinbase.
 #[derive(Clone, Debug)]
 pub struct CoinbaseHandler {
@@ -312,6 +428,11 @@ impl CentralizedExchangeHandler for CoinbaseHandler {
             quote_token: self.quote_token.clone(),
             exchange: Some(Exchange::Coinbase),
             midpoint_price: (best_bid + best_offer) / 2.0,
+            volume: None,
+            depth: Some(OrderBookDepth::from_levels(
+                order_book_levels(&self.order_book_bids),
+                order_book_levels(&self.order_book_offers),
+            )),
             reported_timestamp: Some(reported_timestamp.try_into().unwrap()),
             local_timestamp: Default::default(),
         }))
@@ -406,6 +527,13 @@ impl CentralizedExchangeHandler for KrakenHandler {
             quote_token: self.quote_token.clone(),
             exchange: Some(Exchange::Kraken),
             midpoint_price: (best_bid + best_offer) / 2.0,
+            volume: parse_top_of_book_volume(&message_json[1][3], &message_json[1][4]),
+            depth: parse_top_of_book_depth(
+                best_bid,
+                &message_json[1][3],
+                best_offer,
+                &message_json[1][4],
+            ),
             reported_timestamp: Some((reported_timestamp_seconds * 1000.0) as u128),
             local_timestamp: Default::default(),
         }))
@@ -449,7 +577,7 @@ impl CentralizedExchangeHandler for OkxHandler {
         let subscribe_str = json!({
             "op": "subscribe",
             "args": [{
-                "channel": "bbo-tbt",
+                "channel": "books5",
                 "instId": pair,
             }],
         })
@@ -498,8 +626,124 @@ impl CentralizedExchangeHandler for OkxHandler {
             quote_token: self.quote_token.clone(),
             exchange: Some(Exchange::Okx),
             midpoint_price: (best_bid + best_offer) / 2.0,
+            volume: parse_top_of_book_volume(
+                &message_json["data"][0]["bids"][0][1],
+                &message_json["data"][0]["asks"][0][1],
+            ),
+            depth: OrderBookDepth::parse_levels(&message_json["data"][0]["bids"])
+                .zip(OrderBookDepth::parse_levels(
+                    &message_json["data"][0]["asks"],
+                ))
+                .map(|(bids, asks)| OrderBookDepth::from_levels(bids, asks)),
             reported_timestamp: Some((reported_timestamp_seconds * 1000.0) as u128),
             local_timestamp: Default::default(),
         }))
     }
 }
+
+/// The message handler for Exchange::Bybit.
+#[derive(Clone, Debug)]
+pub struct BybitHandler {
+    /// The base Token (e.g., WETH).
+    base_token: Token,
+    /// The quote Token (e.g., USDC).
+    quote_token: Token,
+    /// The most recently reported best bid, as (price, quantity). Bybit may omit a side of the
+    /// book from an update if it has not changed.
+    best_bid: Option<(f64, Value)>,
+    /// The most recently reported best offer, as (price, quantity).
+    best_offer: Option<(f64, Value)>,
+}
+impl BybitHandler {
+    /// Parse the best level of one side of a Bybit order book update, if present.
+    fn parse_best_level(levels: &Value) -> Result<Option<(f64, Value)>, ExchangeConnectionError> {
+        let best_level = match levels.as_array().and_then(|levels| levels.first()) {
+            None => return Ok(None),
+            Some(best_level) => best_level,
+        };
+        match &best_level[0] {
+            Value::String(price) => {
+                Ok(Some((price.parse::<f64>().unwrap(), best_level[1].clone())))
+            }
+            _ => Err(ExchangeConnectionError::InvalidMessage(
+                best_level.to_string(),
+            )),
+        }
+    }
+}
+#[async_trait]
+impl CentralizedExchangeHandler for BybitHandler {
+    fn new(base_token: Token, quote_token: Token, _: PriceReporterManagerConfig) -> Self {
+        Self {
+            base_token,
+            quote_token,
+            best_bid: None,
+            best_offer: None,
+        }
+    }
+
+    fn websocket_url(&self) -> String {
+        String::from("wss://stream.bybit.com/v5/public/spot")
+    }
+
+    async fn pre_stream_price_report(
+        &mut self,
+    ) -> Result<Option<PriceReport>, ExchangeConnectionError> {
+        Ok(None)
+    }
+
+    async fn websocket_subscribe(
+        &self,
+        socket: &mut WebSocket,
+    ) -> Result<(), ExchangeConnectionError> {
+        let base_ticker = self.base_token.get_exchange_ticker(Exchange::Bybit);
+        let quote_ticker = self.quote_token.get_exchange_ticker(Exchange::Bybit);
+        let subscribe_str = json!({
+            "op": "subscribe",
+            "args": [format!("orderbook.1.{}{}", base_ticker, quote_ticker)],
+        })
+        .to_string();
+        socket
+            .send(Message::Text(subscribe_str))
+            .await
+            .map_err(|err| ExchangeConnectionError::ConnectionHangup(err.to_string()))?;
+        Ok(())
+    }
+
+    fn handle_exchange_message(
+        &mut self,
+        message_json: Value,
+    ) -> Result<Option<PriceReport>, ExchangeConnectionError> {
+        // Bybit acknowledges subscriptions and pings with an "op" message. Ignore these.
+        if !message_json["op"].is_null() {
+            return Ok(None);
+        }
+        let data = &message_json["data"];
+        if data.is_null() {
+            return Err(ExchangeConnectionError::InvalidMessage(
+                message_json.to_string(),
+            ));
+        }
+        if let Some(best_bid) = Self::parse_best_level(&data["b"])? {
+            self.best_bid = Some(best_bid);
+        }
+        if let Some(best_offer) = Self::parse_best_level(&data["a"])? {
+            self.best_offer = Some(best_offer);
+        }
+        let ((best_bid, bid_quantity), (best_offer, offer_quantity)) =
+            match (&self.best_bid, &self.best_offer) {
+                (Some(best_bid), Some(best_offer)) => (best_bid, best_offer),
+                _ => return Ok(None),
+            };
+        Ok(Some(PriceReport {
+            base_token: self.base_token.clone(),
+            quote_token: self.quote_token.clone(),
+            exchange: Some(Exchange::Bybit),
+            midpoint_price: (best_bid + best_offer) / 2.0,
+            volume: parse_top_of_book_volume(bid_quantity, offer_quantity),
+            depth: parse_top_of_book_depth(*best_bid, bid_quantity, *best_offer, offer_quantity),
+            reported_timestamp: message_json["ts"].as_u64().map(u128::from),
+            local_timestamp: Default::default(),
+        }))
+    }
+}
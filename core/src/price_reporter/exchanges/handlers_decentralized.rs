@@ -1,7 +1,13 @@
 use core::time::Duration;
 use futures::StreamExt;
 use ring_channel::RingSender;
-use std::{cmp::Ordering, convert::TryInto, str::FromStr};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    convert::TryInto,
+    fmt::{self, Display},
+    str::FromStr,
+};
 use web3::{
     self, ethabi,
     signing::keccak256,
@@ -21,6 +27,58 @@ use super::super::{
     tokens::Token,
 };
 
+/// The interval at which the UniswapV3 TWAP is re-read from the pool oracle, in milliseconds
+const TWAP_POLL_INTERVAL_MS: u64 = 12_000;
+/// The base of the UniswapV3 tick price; the price at tick `i` is `TICK_BASE^i`
+const TICK_BASE: f64 = 1.0001;
+
+/// A UniswapV3 pool fee tier. If no tier is configured, the pool with the highest TVL among all
+/// tiers is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UniswapFeeTier {
+    /// The 0.05% fee tier
+    Bps5,
+    /// The 0.3% fee tier
+    Bps30,
+    /// The 1% fee tier
+    Bps100,
+}
+
+impl UniswapFeeTier {
+    /// The fee in hundredths of a basis point, as the pool factory encodes it
+    pub fn fee(&self) -> u32 {
+        match self {
+            UniswapFeeTier::Bps5 => 500,
+            UniswapFeeTier::Bps30 => 3000,
+            UniswapFeeTier::Bps100 => 10_000,
+        }
+    }
+}
+
+impl Display for UniswapFeeTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_str = match self {
+            UniswapFeeTier::Bps5 => "0.05%",
+            UniswapFeeTier::Bps30 => "0.3%",
+            UniswapFeeTier::Bps100 => "1%",
+        };
+        write!(f, "{}", fmt_str)
+    }
+}
+
+impl FromStr for UniswapFeeTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_end_matches('%') {
+            "0.05" => Ok(UniswapFeeTier::Bps5),
+            "0.3" | "0.30" => Ok(UniswapFeeTier::Bps30),
+            "1" | "1.0" => Ok(UniswapFeeTier::Bps100),
+            _ => Err(format!("unsupported UniswapV3 fee tier: {}", s)),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 /// The core handler for UniswapV3, responsible for defining Swap event filters, streaming events,
 /// and parsing as PriceReports.
@@ -35,6 +93,9 @@ impl UniswapV3Handler {
     /// The standard ERC-20 JSON ABI. From:
     /// https://gist.github.com/veox/8800debbf56e24718f9f483e1e40c35c
     const ERC20_ABI: &str = r#"[{"constant":true,"inputs":[],"name":"name","outputs":[{"name":"","type":"string"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"_spender","type":"address"},{"name":"_value","type":"uint256"}],"name":"approve","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[],"name":"totalSupply","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"_from","type":"address"},{"name":"_to","type":"address"},{"name":"_value","type":"uint256"}],"name":"transferFrom","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[],"name":"decimals","outputs":[{"name":"","type":"uint8"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[{"name":"_owner","type":"address"}],"name":"balanceOf","outputs":[{"name":"balance","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":true,"inputs":[],"name":"symbol","outputs":[{"name":"","type":"string"}],"payable":false,"stateMutability":"view","type":"function"},{"constant":false,"inputs":[{"name":"_to","type":"address"},{"name":"_value","type":"uint256"}],"name":"transfer","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"nonpayable","type":"function"},{"constant":true,"inputs":[{"name":"_owner","type":"address"},{"name":"_spender","type":"address"}],"name":"allowance","outputs":[{"name":"","type":"uint256"}],"payable":false,"stateMutability":"view","type":"function"},{"payable":true,"stateMutability":"payable","type":"fallback"},{"anonymous":false,"inputs":[{"indexed":true,"name":"owner","type":"address"},{"indexed":true,"name":"spender","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Approval","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Transfer","type":"event"}]"#;
+    /// The JSON ABI of the UniswapV3 pool oracle's `observe` function. From:
+    /// https://docs.uniswap.org/contracts/v3/reference/core/interfaces/pool/IUniswapV3PoolDerivedState
+    const POOL_OBSERVE_ABI: &str = r#"[{"inputs":[{"internalType":"uint32[]","name":"secondsAgos","type":"uint32[]"}],"name":"observe","outputs":[{"internalType":"int56[]","name":"tickCumulatives","type":"int56[]"},{"internalType":"uint160[]","name":"secondsPerLiquidityCumulativeX128s","type":"uint160[]"}],"stateMutability":"view","type":"function"}]"#;
 
     /// Core entrypoint to start the price stream, given a RingSender.
     pub async fn start_price_stream(
@@ -53,6 +114,7 @@ impl UniswapV3Handler {
         let (pool_address, is_flipped) = Self::get_pool_address(
             base_token.clone(),
             quote_token.clone(),
+            config.uniswap_fee_tier,
             web3_connection.clone(),
         )
         .await?;
@@ -195,6 +257,142 @@ impl UniswapV3Handler {
         Ok(vec![worker_handle])
     }
 
+    /// Starts polling the pool oracle for the TWAP over the configured window, sending each
+    /// reading to the given RingSender. The TWAP is read from the same pool as the spot price.
+    pub async fn start_twap_stream(
+        base_token: Token,
+        quote_token: Token,
+        mut sender: RingSender<PriceReport>,
+        config: PriceReporterManagerConfig,
+    ) -> Result<WorkerHandles, ExchangeConnectionError> {
+        // Create the Web3 connection.
+        let ethereum_wss_url = config.eth_websocket_addr.unwrap();
+        let transport = web3::transports::WebSocket::new(&ethereum_wss_url)
+            .await
+            .map_err(|err| ExchangeConnectionError::HandshakeFailure(err.to_string()))?;
+        let web3_connection = Web3::new(transport);
+        let (pool_address, is_flipped) = Self::get_pool_address(
+            base_token.clone(),
+            quote_token.clone(),
+            config.uniswap_fee_tier,
+            web3_connection.clone(),
+        )
+        .await?;
+
+        let pool_contract = ethabi::Contract::load(Self::POOL_OBSERVE_ABI.as_bytes()).unwrap();
+        let observe_fn = pool_contract.function("observe").unwrap().clone();
+        let twap_window_secs = config.uniswap_twap_window_secs;
+
+        let worker_handle = tokio::spawn(async move {
+            loop {
+                let twap = match Self::fetch_twap(
+                    &web3_connection,
+                    pool_address,
+                    &observe_fn,
+                    twap_window_secs,
+                    is_flipped,
+                )
+                .await
+                {
+                    Ok(twap) => twap,
+                    Err(err) => return Err(err),
+                };
+
+                // Note that, as with the spot price, this price does not adjust for ERC-20
+                // decimals yet.
+                sender
+                    .send(PriceReport {
+                        base_token: base_token.clone(),
+                        quote_token: quote_token.clone(),
+                        exchange: Some(Exchange::UniswapV3),
+                        midpoint_price: twap,
+                        volume: None,
+                        depth: None,
+                        local_timestamp: get_current_time(),
+                        reported_timestamp: None,
+                    })
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(TWAP_POLL_INTERVAL_MS)).await;
+            }
+        });
+
+        Ok(vec![worker_handle])
+    }
+
+    /// Reads the tick cumulatives at the start and end of the TWAP window from the pool oracle,
+    /// and converts them to the TWAP.
+    async fn fetch_twap(
+        web3_connection: &Web3<web3::transports::WebSocket>,
+        pool_address: H160,
+        observe_fn: &ethabi::Function,
+        twap_window_secs: u32,
+        is_flipped: bool,
+    ) -> Result<f64, ExchangeConnectionError> {
+        let seconds_agos = ethabi::Token::Array(vec![
+            ethabi::Token::Uint(U256::from(twap_window_secs)),
+            ethabi::Token::Uint(U256::zero()),
+        ]);
+        let observe_call_request = web3::types::CallRequest::builder()
+            .to(pool_address)
+            .data(web3::types::Bytes(
+                observe_fn.encode_input(&[seconds_agos]).unwrap(),
+            ))
+            .build();
+        let observe_output = web3_connection
+            .eth()
+            .call(observe_call_request, None)
+            .await
+            .map_err(|err| ExchangeConnectionError::ConnectionHangup(err.to_string()))?;
+
+        let observe_output = observe_fn
+            .decode_output(&observe_output.0)
+            .map_err(|err| ExchangeConnectionError::InvalidMessage(err.to_string()))?;
+        // The int56 tick cumulatives are sign-extended to 256 bits, so their low 64 bits are
+        // their two's complement representation.
+        let tick_cumulatives = match observe_output.first() {
+            Some(ethabi::Token::Array(tick_cumulatives)) => tick_cumulatives
+                .iter()
+                .map(|tick_cumulative| match tick_cumulative {
+                    ethabi::Token::Int(tick_cumulative) => Some(tick_cumulative.low_u64() as i64),
+                    _ => None,
+                })
+                .collect::<Option<Vec<i64>>>(),
+            _ => None,
+        }
+        .filter(|tick_cumulatives| tick_cumulatives.len() == 2)
+        .ok_or_else(|| {
+            ExchangeConnectionError::InvalidMessage(
+                "malformed tick cumulatives from observe".to_string(),
+            )
+        })?;
+
+        Ok(Self::twap_from_tick_cumulatives(
+            tick_cumulatives[0],
+            tick_cumulatives[1],
+            twap_window_secs,
+            is_flipped,
+        ))
+    }
+
+    /// Converts the tick cumulatives at the start and end of a window into the TWAP over the
+    /// window. The pool oracle accumulates ticks, so this is the geometric mean of the price, as
+    /// per: https://docs.uniswap.org/concepts/protocol/oracle#deriving-price-from-a-tick
+    fn twap_from_tick_cumulatives(
+        start_tick_cumulative: i64,
+        end_tick_cumulative: i64,
+        twap_window_secs: u32,
+        is_flipped: bool,
+    ) -> f64 {
+        let mean_tick =
+            (end_tick_cumulative - start_tick_cumulative) as f64 / f64::from(twap_window_secs);
+        let price = TICK_BASE.powf(mean_tick);
+        if is_flipped {
+            1.0 / price
+        } else {
+            price
+        }
+    }
+
     /// Handles a Swap event log streamed from the web3 connection.
     fn handle_event(
         base_token: Token,
@@ -233,18 +431,21 @@ impl UniswapV3Handler {
             quote_token,
             exchange: Some(Exchange::UniswapV3),
             midpoint_price: price as f64,
+            volume: None,
+            depth: None,
             reported_timestamp: None,
             local_timestamp: Default::default(),
         })
     }
 
-    /// Given the base_token and quote_token, finds the address of the UniswapV3 pool with highest
-    /// TVL among all fee tiers (1bp, 5bp, 30bp, 100bp). In addition, we return a boolean
-    /// is_flipped that reflects whether the assets are flipped (i.e., quote per base) in the
-    /// Uniswap pool.
+    /// Given the base_token and quote_token, finds the address of the UniswapV3 pool in the given
+    /// fee tier, or if no tier is given, the pool with highest TVL among all fee tiers (1bp, 5bp,
+    /// 30bp, 100bp). In addition, we return a boolean is_flipped that reflects whether the assets
+    /// are flipped (i.e., quote per base) in the Uniswap pool.
     async fn get_pool_address(
         base_token: Token,
         quote_token: Token,
+        fee_tier: Option<UniswapFeeTier>,
         web3_connection: Web3<web3::transports::WebSocket>,
     ) -> Result<(H160, bool), ExchangeConnectionError> {
         let base_token_addr = H160::from_str(base_token.get_addr()).unwrap();
@@ -255,32 +456,19 @@ impl UniswapV3Handler {
         } else {
             (base_token_addr, quote_token_addr)
         };
+        // If a fee tier is configured, use its pool regardless of TVL.
+        if let Some(fee_tier) = fee_tier {
+            let pool_address = Self::derive_pool_address(first_token, second_token, fee_tier.fee());
+            return Ok((pool_address, is_flipped));
+        }
+
         // Derive all pool addresses from the following fee tiers:
         // HIGH = 10000
         // MEDIUM = 3000
         // LOW = 500
         // LOWEST = 100
-        let pool_addresses = [10_000_u32, 3000_u32, 500_u32, 100_u32].map(|fee_amt| {
-            let mut fee = [0_u8; 32];
-            fee[32 - 4..].clone_from_slice(&fee_amt.to_be_bytes());
-            let pool_address = create2::calc_addr_with_hash(
-                hex::decode(Self::FACTORY_ADDRESS).unwrap()[..20]
-                    .try_into()
-                    .unwrap(),
-                &keccak256(
-                    &[
-                        H256::from(first_token).as_bytes(),
-                        H256::from(second_token).as_bytes(),
-                        &fee,
-                    ]
-                    .concat()[..],
-                ),
-                hex::decode(Self::POOL_INIT_CODE_HASH).unwrap()[..32]
-                    .try_into()
-                    .unwrap(),
-            );
-            H160::from(pool_address)
-        });
+        let pool_addresses = [10_000_u32, 3000_u32, 500_u32, 100_u32]
+            .map(|fee_amt| Self::derive_pool_address(first_token, second_token, fee_amt));
 
         // Fetch the base balance from each pool address.
         let erc20_contract = ethabi::Contract::load(Self::ERC20_ABI.as_bytes()).unwrap();
@@ -317,4 +505,65 @@ impl UniswapV3Handler {
         }
         Ok((pool_addresses[max_pool_idx], is_flipped))
     }
+
+    /// Derives the create2 address of the UniswapV3 pool for the given (sorted) token pair and
+    /// fee, in hundredths of a basis point.
+    fn derive_pool_address(first_token: H160, second_token: H160, fee_amt: u32) -> H160 {
+        let mut fee = [0_u8; 32];
+        fee[32 - 4..].clone_from_slice(&fee_amt.to_be_bytes());
+        let pool_address = create2::calc_addr_with_hash(
+            hex::decode(Self::FACTORY_ADDRESS).unwrap()[..20]
+                .try_into()
+                .unwrap(),
+            &keccak256(
+                &[
+                    H256::from(first_token).as_bytes(),
+                    H256::from(second_token).as_bytes(),
+                    &fee,
+                ]
+                .concat()[..],
+            ),
+            hex::decode(Self::POOL_INIT_CODE_HASH).unwrap()[..32]
+                .try_into()
+                .unwrap(),
+        );
+        H160::from(pool_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{UniswapFeeTier, UniswapV3Handler};
+
+    /// Tests that the supported fee tiers parse from their percentages
+    #[test]
+    fn test_parse_fee_tier() {
+        assert_eq!(
+            UniswapFeeTier::from_str("0.05%").unwrap(),
+            UniswapFeeTier::Bps5
+        );
+        assert_eq!(UniswapFeeTier::from_str("0.3").unwrap().fee(), 3000);
+        assert_eq!(
+            UniswapFeeTier::from_str(&UniswapFeeTier::Bps100.to_string()).unwrap(),
+            UniswapFeeTier::Bps100
+        );
+        assert!(UniswapFeeTier::from_str("0.01%").is_err());
+    }
+
+    /// Tests converting tick cumulatives to a TWAP, in both pool orientations
+    #[test]
+    fn test_twap_from_tick_cumulatives() {
+        // A mean tick of zero is a price of one
+        let twap = UniswapV3Handler::twap_from_tick_cumulatives(1_000, 1_000, 60, false);
+        assert!((twap - 1.0).abs() < 1e-12);
+
+        // A mean tick of -6932 is a price of ~0.5
+        let twap = UniswapV3Handler::twap_from_tick_cumulatives(0, -6932 * 60, 60, false);
+        assert!((twap - 0.5).abs() < 1e-4);
+
+        let twap = UniswapV3Handler::twap_from_tick_cumulatives(0, -6932 * 60, 60, true);
+        assert!((twap - 2.0).abs() < 1e-3);
+    }
 }
@@ -6,6 +6,14 @@ mod connection;
 mod handlers_centralized;
 /// Defines message handlers for decentralized exchanges.
 mod handlers_decentralized;
+/// Defines the normalization of each exchange's prices into common units.
+mod normalize;
+/// Defines the regions a relayer may be deployed in, and the exchanges accessible from each.
+mod region;
 pub use connection::{
-    get_current_time, Exchange, ExchangeConnection, ExchangeConnectionState, ALL_EXCHANGES,
+    get_current_time, Exchange, ExchangeConnection, ExchangeConnectionState, WorkerHandles,
+    ALL_EXCHANGES,
 };
+pub use handlers_decentralized::{UniswapFeeTier, UniswapV3Handler};
+pub use normalize::{PriceNormalizer, PriceUnits};
+pub use region::ExchangeRegion;
@@ -0,0 +1,132 @@
+//! Normalizes the prices reported by each Exchange into a common unit, so that PriceReports from
+//! different Exchanges may be compared and aggregated.
+//!
+//! After normalization, `PriceReport::midpoint_price` and the prices of any depth levels are in
+//! whole units of the quote Token per whole unit of the base Token, and volumes and depth
+//! quantities are in whole units of the base Token. Centralized Exchanges already quote in whole
+//! units; UniswapV3 quotes in atomic units, unadjusted for the decimals of either Token.
+use super::{super::reporter::PriceReport, super::tokens::Token, Exchange};
+
+/// The units in which an Exchange quotes prices and quantities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceUnits {
+    /// Prices are in whole units of the quote Token per whole unit of the base Token.
+    Whole,
+    /// Prices are in atomic units of the quote Token per atomic unit of the base Token.
+    Atomic,
+}
+
+impl Exchange {
+    /// Returns the units in which the Exchange quotes prices and quantities.
+    pub fn price_units(&self) -> PriceUnits {
+        match self {
+            Exchange::UniswapV3 => PriceUnits::Atomic,
+            Exchange::Binance
+            | Exchange::BinanceUs
+            | Exchange::Bybit
+            | Exchange::Coinbase
+            | Exchange::Kraken
+            | Exchange::Okx => PriceUnits::Whole,
+        }
+    }
+}
+
+/// Normalizes the PriceReports of a single Exchange on a single Token pair.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PriceNormalizer {
+    /// The factor by which reported prices are multiplied.
+    price_scale: f64,
+    /// The factor by which reported base Token quantities are multiplied.
+    quantity_scale: f64,
+    /// Whether the decimals needed to normalize the Exchange's reports were available.
+    exact: bool,
+}
+
+impl PriceNormalizer {
+    /// Creates a new PriceNormalizer for the Exchange on the given pair.
+    ///
+    /// If the Exchange quotes in atomic units and the decimals of either Token are unknown, the
+    /// reports cannot be adjusted and pass through unscaled. This is only the case for pairs of
+    /// Unnamed Tokens, which are reported by UniswapV3 alone and so never compared across
+    /// Exchanges; `is_exact` reports whether the normalization was possible.
+    pub fn new(exchange: Exchange, base_token: &Token, quote_token: &Token) -> Self {
+        let identity = Self {
+            price_scale: 1.0,
+            quantity_scale: 1.0,
+            exact: true,
+        };
+        if exchange.price_units() == PriceUnits::Whole {
+            return identity;
+        }
+
+        match (base_token.get_decimals(), quote_token.get_decimals()) {
+            (Some(base_decimals), Some(quote_decimals)) => Self {
+                price_scale: 10_f64.powi(i32::from(base_decimals) - i32::from(quote_decimals)),
+                quantity_scale: 10_f64.powi(-i32::from(base_decimals)),
+                exact: true,
+            },
+            _ => Self {
+                exact: false,
+                ..identity
+            },
+        }
+    }
+
+    /// Returns true if reports are normalized into whole units, and false if the decimals of the
+    /// pair were unknown and reports pass through unscaled.
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    /// Normalizes a price, as reported by the Exchange, into whole units.
+    pub fn normalize_price(&self, price: f64) -> f64 {
+        price * self.price_scale
+    }
+
+    /// Normalizes a PriceReport from the Exchange in place.
+    pub fn normalize(&self, price_report: &mut PriceReport) {
+        price_report.midpoint_price = self.normalize_price(price_report.midpoint_price);
+        if let Some(volume) = price_report.volume.as_mut() {
+            *volume *= self.quantity_scale;
+        }
+        if let Some(depth) = price_report.depth.as_mut() {
+            for level in depth.bids.iter_mut().chain(depth.asks.iter_mut()) {
+                level.price = self.normalize_price(level.price);
+                level.quantity *= self.quantity_scale;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::price_reporter::{exchanges::Exchange, reporter::PriceReport, tokens::Token};
+
+    use super::PriceNormalizer;
+
+    /// Tests that UniswapV3 reports are adjusted for decimals and centralized reports are not
+    #[test]
+    fn test_normalize() {
+        let weth = Token::_from_ticker("WETH");
+        let usdc = Token::_from_ticker("USDC");
+        let report = |midpoint_price| PriceReport {
+            base_token: weth.clone(),
+            quote_token: usdc.clone(),
+            midpoint_price,
+            ..Default::default()
+        };
+
+        // 1500 USDC per WETH is 1.5e-9 atomic USDC per atomic WETH
+        let mut uniswap_report = report(1.5e-9);
+        PriceNormalizer::new(Exchange::UniswapV3, &weth, &usdc).normalize(&mut uniswap_report);
+        assert!((uniswap_report.midpoint_price - 1_500.).abs() < 1e-6);
+
+        let mut binance_report = report(1_500.);
+        PriceNormalizer::new(Exchange::Binance, &weth, &usdc).normalize(&mut binance_report);
+        assert_eq!(binance_report.midpoint_price, 1_500.);
+
+        let unnamed = Token::from_addr("0x0000000000000000000000000000000000000001");
+        assert!(!PriceNormalizer::new(Exchange::UniswapV3, &unnamed, &usdc).is_exact());
+        assert!(PriceNormalizer::new(Exchange::Binance, &unnamed, &usdc).is_exact());
+    }
+}
@@ -0,0 +1,86 @@
+//! Defines the regions a relayer may be deployed in. Not every Exchange serves every jurisdiction,
+//! so the region a relayer is deployed in restricts the Exchanges it streams prices from, and so
+//! the Exchanges its medians are computed over.
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use super::Exchange;
+
+/// The region a relayer is deployed in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExchangeRegion {
+    /// Outside the United States. Binance.US serves only US residents.
+    #[default]
+    Global,
+    /// The United States. Binance, Bybit, and Okx do not serve US residents.
+    Us,
+}
+
+impl ExchangeRegion {
+    /// Returns true if the Exchange may be streamed from in the region.
+    pub fn permits(&self, exchange: Exchange) -> bool {
+        match self {
+            ExchangeRegion::Global => exchange != Exchange::BinanceUs,
+            ExchangeRegion::Us => !matches!(
+                exchange,
+                Exchange::Binance | Exchange::Bybit | Exchange::Okx
+            ),
+        }
+    }
+}
+
+impl Display for ExchangeRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_str = match self {
+            ExchangeRegion::Global => "global",
+            ExchangeRegion::Us => "us",
+        };
+        write!(f, "{}", fmt_str)
+    }
+}
+
+impl FromStr for ExchangeRegion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "global" => Ok(ExchangeRegion::Global),
+            "us" => Ok(ExchangeRegion::Us),
+            _ => Err(format!("unknown exchange region: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::ALL_EXCHANGES, Exchange, ExchangeRegion};
+
+    /// Tests that exactly one of Binance and Binance.US is permitted in each region
+    #[test]
+    fn test_region_permits_one_binance() {
+        for region in [ExchangeRegion::Global, ExchangeRegion::Us] {
+            assert_ne!(
+                region.permits(Exchange::Binance),
+                region.permits(Exchange::BinanceUs)
+            );
+        }
+
+        let us_exchanges = ALL_EXCHANGES
+            .iter()
+            .copied()
+            .filter(|exchange| ExchangeRegion::Us.permits(*exchange))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            us_exchanges,
+            vec![
+                Exchange::BinanceUs,
+                Exchange::Coinbase,
+                Exchange::Kraken,
+                Exchange::UniswapV3
+            ]
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Defines the health scoring subsystem for ExchangeConnections. Each Exchange is scored on its
+//! staleness, its deviation from the cross-exchange median, and its recent connection error rate.
+//! Exchanges whose score falls below a threshold are excluded from the median computation until
+//! they recover.
+use serde::{Deserialize, Serialize};
+use stats::median;
+use std::collections::{HashMap, VecDeque};
+
+use super::{exchanges::Exchange, reporter::PriceReport};
+
+/// The age (in milliseconds) at which a single Exchange's latest PriceReport is considered fully
+/// stale. The staleness penalty scales linearly up to this age.
+const MAX_HEALTHY_STALENESS_MS: u128 = 10_000; // 10 seconds
+/// The deviation (as a fraction) from the median at which an Exchange's PriceReport is considered
+/// fully deviant. The deviation penalty scales linearly up to this deviation.
+const MAX_HEALTHY_DEVIATION: f64 = 0.02;
+/// The window (in milliseconds) over which connection errors are counted towards the error rate.
+const ERROR_WINDOW_MS: u128 = 60_000; // 1 minute
+/// The number of connection errors within the error window at which an Exchange's error penalty
+/// saturates.
+const MAX_HEALTHY_ERRORS: usize = 3;
+/// The minimum health score for an Exchange to be included in the median computation.
+const MIN_HEALTH_SCORE: f64 = 0.5;
+
+/// A point-in-time snapshot of an Exchange's health, as reported to consumers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeHealthReport {
+    /// The aggregate health score, in the range [0, 1]
+    pub score: f64,
+    /// Whether the Exchange is currently included in the median computation
+    pub healthy: bool,
+    /// The time in milliseconds since the last PriceReport from this Exchange, if any
+    pub staleness_ms: Option<u128>,
+    /// The most recent deviation (as a fraction) of this Exchange from the median
+    pub deviation: f64,
+    /// The number of connection errors within the error window
+    pub recent_errors: usize,
+}
+
+/// The health state tracked for a single Exchange.
+#[derive(Clone, Debug)]
+struct ExchangeHealth {
+    /// The local timestamp of the most recent PriceReport, None if no data has been reported
+    last_report_ms: Option<u128>,
+    /// The most recent deviation (as a fraction) from the median midpoint price
+    last_deviation: f64,
+    /// The timestamps of connection errors within the error window
+    error_timestamps: VecDeque<u128>,
+    /// Whether the Exchange was deemed healthy at the last evaluation
+    healthy: bool,
+}
+
+impl ExchangeHealth {
+    /// Construct a new health entry; Exchanges begin healthy so that a fresh connection does not
+    /// trigger a spurious health transition
+    fn new() -> Self {
+        Self {
+            last_report_ms: None,
+            last_deviation: 0.,
+            error_timestamps: VecDeque::new(),
+            healthy: true,
+        }
+    }
+
+    /// Drop all error timestamps that have fallen outside the error window
+    fn prune_errors(&mut self, now: u128) {
+        while let Some(timestamp) = self.error_timestamps.front() {
+            if now.saturating_sub(*timestamp) <= ERROR_WINDOW_MS {
+                break;
+            }
+            self.error_timestamps.pop_front();
+        }
+    }
+
+    /// Compute the health report for this Exchange at the given time
+    fn report(&self, now: u128) -> ExchangeHealthReport {
+        let staleness_ms = self
+            .last_report_ms
+            .map(|timestamp| now.saturating_sub(timestamp));
+
+        // An Exchange that has not yet reported is not penalized for staleness; it is already
+        // excluded from the median by virtue of having no data
+        let staleness_penalty = staleness_ms
+            .map(|staleness| (staleness as f64 / MAX_HEALTHY_STALENESS_MS as f64).min(1.))
+            .unwrap_or(0.);
+        let deviation_penalty = (self.last_deviation / MAX_HEALTHY_DEVIATION).min(1.);
+        let error_penalty =
+            (self.error_timestamps.len() as f64 / MAX_HEALTHY_ERRORS as f64).min(1.);
+
+        let score = (1. - staleness_penalty) * (1. - deviation_penalty) * (1. - error_penalty);
+        ExchangeHealthReport {
+            score,
+            healthy: score >= MIN_HEALTH_SCORE,
+            staleness_ms,
+            deviation: self.last_deviation,
+            recent_errors: self.error_timestamps.len(),
+        }
+    }
+}
+
+/// Tracks the health of every Exchange connected to by a single PriceReporter.
+#[derive(Clone, Debug)]
+pub struct ExchangeHealthTracker {
+    /// The health state of each Exchange
+    health: HashMap<Exchange, ExchangeHealth>,
+}
+
+impl ExchangeHealthTracker {
+    /// Create a new tracker for the given set of Exchanges
+    pub fn new(exchanges: &[Exchange]) -> Self {
+        Self {
+            health: exchanges
+                .iter()
+                .map(|exchange| (*exchange, ExchangeHealth::new()))
+                .collect(),
+        }
+    }
+
+    /// Record a new PriceReport from an Exchange
+    pub fn record_report(&mut self, price_report: &PriceReport) {
+        if let Some(exchange) = price_report.exchange
+            && let Some(health) = self.health.get_mut(&exchange)
+        {
+            health.last_report_ms = Some(price_report.local_timestamp);
+        }
+    }
+
+    /// Record a connection error on an Exchange at the given time
+    pub fn record_error(&mut self, exchange: Exchange, timestamp: u128) {
+        if let Some(health) = self.health.get_mut(&exchange) {
+            health.error_timestamps.push_back(timestamp);
+        }
+    }
+
+    /// Update each Exchange's deviation from the median of all reported midpoint prices
+    ///
+    /// The median here is taken over every Exchange that has reported, healthy or not, so that an
+    /// unhealthy Exchange that converges back to the market may recover
+    pub fn record_deviations(&mut self, current_price_reports: &HashMap<Exchange, PriceReport>) {
+        let reported_prices = current_price_reports
+            .values()
+            .filter(|price_report| **price_report != PriceReport::default())
+            .map(|price_report| price_report.midpoint_price)
+            .collect::<Vec<f64>>();
+        let median_price = match median(reported_prices.into_iter()) {
+            Some(median_price) if median_price != 0. => median_price,
+            _ => return,
+        };
+
+        for (exchange, price_report) in current_price_reports.iter() {
+            if *price_report == PriceReport::default() {
+                continue;
+            }
+
+            if let Some(health) = self.health.get_mut(exchange) {
+                health.last_deviation =
+                    (price_report.midpoint_price - median_price).abs() / median_price;
+            }
+        }
+    }
+
+    /// Re-evaluate the health of every Exchange at the given time, returning the health reports of
+    /// those Exchanges whose health status changed since the last evaluation
+    pub fn evaluate(&mut self, now: u128) -> Vec<(Exchange, ExchangeHealthReport)> {
+        let mut changes = Vec::new();
+        for (exchange, health) in self.health.iter_mut() {
+            health.prune_errors(now);
+            let report = health.report(now);
+            if report.healthy != health.healthy {
+                health.healthy = report.healthy;
+                changes.push((*exchange, report));
+            }
+        }
+
+        changes
+    }
+
+    /// Returns whether the given Exchange was healthy at the last evaluation
+    pub fn is_healthy(&self, exchange: &Exchange) -> bool {
+        self.health
+            .get(exchange)
+            .map(|health| health.healthy)
+            .unwrap_or(false)
+    }
+
+    /// Filter a set of PriceReports down to those from Exchanges that are currently healthy
+    pub fn filter_healthy(
+        &self,
+        current_price_reports: &HashMap<Exchange, PriceReport>,
+    ) -> HashMap<Exchange, PriceReport> {
+        current_price_reports
+            .iter()
+            .filter(|(exchange, _)| self.is_healthy(exchange))
+            .map(|(exchange, price_report)| (*exchange, price_report.clone()))
+            .collect()
+    }
+
+    /// Get the health report for every tracked Exchange at the given time
+    pub fn health_reports(&self, now: u128) -> HashMap<Exchange, ExchangeHealthReport> {
+        self.health
+            .iter()
+            .map(|(exchange, health)| {
+                let mut report = health.report(now);
+                // Report the status that is actually applied to the median computation
+                report.healthy = health.healthy;
+                (*exchange, report)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{ExchangeHealthTracker, ERROR_WINDOW_MS, MAX_HEALTHY_ERRORS};
+    use crate::price_reporter::{exchanges::Exchange, reporter::PriceReport};
+
+    /// Build a PriceReport for the given exchange
+    fn price_report(exchange: Exchange, midpoint_price: f64, local_timestamp: u128) -> PriceReport {
+        PriceReport {
+            exchange: Some(exchange),
+            midpoint_price,
+            local_timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that an exchange deviating from the median is excluded
+    #[test]
+    fn test_deviant_exchange_excluded() {
+        let exchanges = [Exchange::Binance, Exchange::Kraken, Exchange::Okx];
+        let mut tracker = ExchangeHealthTracker::new(&exchanges);
+
+        let reports: HashMap<_, _> = vec![
+            (Exchange::Binance, price_report(Exchange::Binance, 100., 0)),
+            (Exchange::Kraken, price_report(Exchange::Kraken, 100.1, 0)),
+            (Exchange::Okx, price_report(Exchange::Okx, 150., 0)),
+        ]
+        .into_iter()
+        .collect();
+        for report in reports.values() {
+            tracker.record_report(report);
+        }
+        tracker.record_deviations(&reports);
+
+        let changes = tracker.evaluate(0 /* now */);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, Exchange::Okx);
+
+        let healthy_reports = tracker.filter_healthy(&reports);
+        assert!(healthy_reports.contains_key(&Exchange::Binance));
+        assert!(healthy_reports.contains_key(&Exchange::Kraken));
+        assert!(!healthy_reports.contains_key(&Exchange::Okx));
+    }
+
+    /// Tests that a stale exchange is excluded and recovers when it reports again
+    #[test]
+    fn test_stale_exchange_recovers() {
+        let mut tracker = ExchangeHealthTracker::new(&[Exchange::Binance]);
+        tracker.record_report(&price_report(Exchange::Binance, 100., 0));
+
+        tracker.evaluate(60_000 /* now */);
+        assert!(!tracker.is_healthy(&Exchange::Binance));
+
+        tracker.record_report(&price_report(Exchange::Binance, 100., 60_000));
+        let changes = tracker.evaluate(60_000 /* now */);
+        assert_eq!(changes.len(), 1);
+        assert!(tracker.is_healthy(&Exchange::Binance));
+    }
+
+    /// Tests that connection errors count against an exchange only within the error window
+    #[test]
+    fn test_error_window() {
+        let mut tracker = ExchangeHealthTracker::new(&[Exchange::Coinbase]);
+        tracker.record_report(&price_report(Exchange::Coinbase, 100., 0));
+        for _ in 0..MAX_HEALTHY_ERRORS {
+            tracker.record_error(Exchange::Coinbase, 0 /* timestamp */);
+        }
+
+        tracker.evaluate(0 /* now */);
+        assert!(!tracker.is_healthy(&Exchange::Coinbase));
+
+        // Refresh the report so that staleness does not factor in
+        let now = ERROR_WINDOW_MS + 1;
+        tracker.record_report(&price_report(Exchange::Coinbase, 100., now));
+        tracker.evaluate(now);
+        assert!(tracker.is_healthy(&Exchange::Coinbase));
+    }
+}
@@ -0,0 +1,506 @@
+//! Defines the historical price store. The midpoint of each Exchange and the median of each token
+//! pair are sampled into bounded ring buffers, from which OHLC candles and recent history are
+//! served, so that the prices a relayer observed may be charted without an external pipeline.
+//!
+//! Samples are taken at a fixed resolution; within a resolution bucket only the latest midpoint is
+//! kept. If a directory is configured, the history of each pair is periodically flushed to a file
+//! in it, and read back when the relayer restarts.
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Display},
+    fs,
+    path::Path,
+};
+use tracing::log;
+
+use super::{
+    errors::PriceReporterManagerError, exchanges::Exchange, reporter::PriceReport, tokens::Token,
+};
+
+/// The default duration (in milliseconds) for which history is retained
+const DEFAULT_RETENTION_MS: u64 = 24 * 60 * 60 * 1000; // 1 day
+/// The resolution (in milliseconds) at which midpoints are sampled
+const SAMPLE_RESOLUTION_MS: u64 = 1000; // 1 second
+/// The extension of the files that the history of each pair is flushed to
+const HISTORY_FILE_EXTENSION: &str = "json";
+/// The maximum number of points of each series returned from a single history query
+pub const MAX_HISTORY_POINTS: usize = 1000;
+/// The maximum number of candles returned from a single candle query
+pub const MAX_CANDLES: usize = 1440;
+
+/// The configuration of the historical price store
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceHistoryConfig {
+    /// The directory that the history of each pair is flushed to, if `None` history is held
+    /// only in memory
+    pub dir: Option<String>,
+    /// The duration (in milliseconds) for which history is retained
+    pub retention_ms: u64,
+}
+
+impl Default for PriceHistoryConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            retention_ms: DEFAULT_RETENTION_MS,
+        }
+    }
+}
+
+/// The intervals that OHLC candles may span
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CandleInterval {
+    /// One minute candles
+    #[serde(rename = "1m")]
+    OneMinute,
+    /// Five minute candles
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    /// Fifteen minute candles
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    /// One hour candles
+    #[serde(rename = "1h")]
+    OneHour,
+    /// One day candles
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl CandleInterval {
+    /// The length of the interval in milliseconds
+    pub fn duration_ms(&self) -> u64 {
+        match self {
+            CandleInterval::OneMinute => 60_000,
+            CandleInterval::FiveMinutes => 5 * 60_000,
+            CandleInterval::FifteenMinutes => 15 * 60_000,
+            CandleInterval::OneHour => 60 * 60_000,
+            CandleInterval::OneDay => 24 * 60 * 60_000,
+        }
+    }
+}
+
+impl Display for CandleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_str = match self {
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::FifteenMinutes => "15m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::OneDay => "1d",
+        };
+        write!(f, "{}", fmt_str)
+    }
+}
+
+/// A single sampled midpoint
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PricePoint {
+    /// The start of the resolution bucket the midpoint was sampled in, in milliseconds since the
+    /// epoch
+    pub timestamp: u64,
+    /// The latest midpoint observed in the bucket
+    pub price: f64,
+}
+
+/// An OHLC candle over the midpoints sampled in a single interval
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PriceCandle {
+    /// The start of the interval, in milliseconds since the epoch
+    pub start: u64,
+    /// The first midpoint sampled in the interval
+    pub open: f64,
+    /// The highest midpoint sampled in the interval
+    pub high: f64,
+    /// The lowest midpoint sampled in the interval
+    pub low: f64,
+    /// The last midpoint sampled in the interval
+    pub close: f64,
+    /// The number of midpoints sampled in the interval
+    pub num_samples: usize,
+}
+
+impl PriceCandle {
+    /// Open a new candle at the given sample
+    fn open(start: u64, price: f64) -> Self {
+        Self {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            num_samples: 1,
+        }
+    }
+
+    /// Extend the candle with a later sample
+    fn extend(&mut self, price: f64) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.num_samples += 1;
+    }
+}
+
+/// A ring buffer of sampled midpoints, ordered by timestamp
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct PriceSeries {
+    /// The sampled midpoints
+    points: VecDeque<PricePoint>,
+}
+
+impl PriceSeries {
+    /// Sample a midpoint, pruning the points that have aged out of the retention period
+    fn record(&mut self, timestamp: u64, price: f64, retention_ms: u64) {
+        let bucket = timestamp - timestamp % SAMPLE_RESOLUTION_MS;
+        match self.points.back_mut() {
+            // A midpoint received out of order is dropped rather than rewriting history
+            Some(last) if bucket < last.timestamp => return,
+            Some(last) if bucket == last.timestamp => last.price = price,
+            _ => self.points.push_back(PricePoint {
+                timestamp: bucket,
+                price,
+            }),
+        }
+
+        let cutoff = timestamp.saturating_sub(retention_ms);
+        while let Some(first) = self.points.front() && first.timestamp < cutoff {
+            self.points.pop_front();
+        }
+    }
+
+    /// The most recent points sampled at or after the given time, oldest first
+    fn since(&self, since: u64, limit: usize) -> Vec<PricePoint> {
+        let num_since = self
+            .points
+            .iter()
+            .rev()
+            .take_while(|point| point.timestamp >= since)
+            .count();
+        let skip = self.points.len() - num_since.min(limit);
+        self.points.iter().skip(skip).copied().collect()
+    }
+
+    /// The candles of the given interval over the points sampled in `[start, end)`; intervals in
+    /// which no midpoint was sampled have no candle
+    fn candles(&self, interval: CandleInterval, start: u64, end: u64) -> Vec<PriceCandle> {
+        let interval_ms = interval.duration_ms();
+        let mut candles: Vec<PriceCandle> = Vec::new();
+        for point in self
+            .points
+            .iter()
+            .filter(|point| point.timestamp >= start && point.timestamp < end)
+        {
+            let candle_start = point.timestamp - point.timestamp % interval_ms;
+            match candles.last_mut() {
+                Some(candle) if candle.start == candle_start => candle.extend(point.price),
+                _ => candles.push(PriceCandle::open(candle_start, point.price)),
+            }
+        }
+
+        let skip = candles.len().saturating_sub(MAX_CANDLES);
+        candles.split_off(skip)
+    }
+}
+
+/// The sampled history of a single token pair
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PairPriceHistory {
+    /// The median midpoint across Exchanges
+    pub median: Vec<PricePoint>,
+    /// The midpoint of each Exchange
+    pub exchanges: HashMap<Exchange, Vec<PricePoint>>,
+}
+
+/// The ring buffers of a single token pair, in the form they are flushed to disk
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct PairHistory {
+    /// The base Token
+    base_token: Token,
+    /// The quote Token
+    quote_token: Token,
+    /// The median midpoint across Exchanges
+    median: PriceSeries,
+    /// The midpoint of each Exchange
+    exchanges: HashMap<Exchange, PriceSeries>,
+}
+
+/// The historical price store, holding the sampled history of every token pair
+#[derive(Debug)]
+pub struct PriceHistoryStore {
+    /// The configuration of the store
+    config: PriceHistoryConfig,
+    /// The history of each base/quote token pair
+    pairs: HashMap<(Token, Token), PairHistory>,
+    /// The pairs that have been sampled since they were last flushed
+    dirty: HashSet<(Token, Token)>,
+}
+
+impl PriceHistoryStore {
+    /// Open the store, reading back the history of each pair flushed to the configured directory
+    ///
+    /// A history file that cannot be read is logged and skipped, its pair starts afresh
+    pub fn open(config: PriceHistoryConfig) -> Result<Self, PriceReporterManagerError> {
+        let mut pairs = HashMap::new();
+        if let Some(dir) = config.dir.as_ref() && Path::new(dir).exists() {
+            let entries = fs::read_dir(dir)
+                .map_err(|err| PriceReporterManagerError::PriceHistory(err.to_string()))?;
+            for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                if path.extension().and_then(|ext| ext.to_str()) != Some(HISTORY_FILE_EXTENSION) {
+                    continue;
+                }
+
+                match fs::read(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|contents| {
+                        serde_json::from_slice::<PairHistory>(&contents)
+                            .map_err(|err| err.to_string())
+                    }) {
+                    Ok(history) => {
+                        pairs.insert(
+                            (history.base_token.clone(), history.quote_token.clone()),
+                            history,
+                        );
+                    }
+                    Err(e) => log::error!("Error reading price history {}: {e}", path.display()),
+                }
+            }
+        }
+
+        Ok(Self {
+            config,
+            pairs,
+            dirty: HashSet::new(),
+        })
+    }
+
+    /// Sample the midpoint of a PriceReport, either into the median series of its pair or into
+    /// the series of the Exchange it came from
+    pub fn record(&mut self, price_report: &PriceReport) {
+        if price_report.midpoint_price == 0. || !price_report.midpoint_price.is_finite() {
+            return;
+        }
+
+        let pair = (
+            price_report.base_token.clone(),
+            price_report.quote_token.clone(),
+        );
+        let history = self
+            .pairs
+            .entry(pair.clone())
+            .or_insert_with(|| PairHistory {
+                base_token: pair.0.clone(),
+                quote_token: pair.1.clone(),
+                ..Default::default()
+            });
+        let series = match price_report.exchange {
+            Some(exchange) => history.exchanges.entry(exchange).or_default(),
+            None => &mut history.median,
+        };
+        series.record(
+            price_report.local_timestamp as u64,
+            price_report.midpoint_price,
+            self.config.retention_ms,
+        );
+        self.dirty.insert(pair);
+    }
+
+    /// The most recent history of a pair sampled at or after the given time, at most `limit`
+    /// points per series
+    pub fn history(
+        &self,
+        base_token: &Token,
+        quote_token: &Token,
+        since: u64,
+        limit: usize,
+    ) -> PairPriceHistory {
+        let limit = limit.min(MAX_HISTORY_POINTS);
+        match self.pairs.get(&(base_token.clone(), quote_token.clone())) {
+            Some(history) => PairPriceHistory {
+                median: history.median.since(since, limit),
+                exchanges: history
+                    .exchanges
+                    .iter()
+                    .map(|(exchange, series)| (*exchange, series.since(since, limit)))
+                    .collect(),
+            },
+            None => PairPriceHistory::default(),
+        }
+    }
+
+    /// The OHLC candles of a pair over `[start, end)`, of the median if no Exchange is given
+    pub fn candles(
+        &self,
+        base_token: &Token,
+        quote_token: &Token,
+        exchange: Option<Exchange>,
+        interval: CandleInterval,
+        start: u64,
+        end: u64,
+    ) -> Vec<PriceCandle> {
+        let history = match self.pairs.get(&(base_token.clone(), quote_token.clone())) {
+            Some(history) => history,
+            None => return Vec::new(),
+        };
+
+        let series = match exchange {
+            Some(exchange) => history.exchanges.get(&exchange),
+            None => Some(&history.median),
+        };
+        series
+            .map(|series| series.candles(interval, start, end))
+            .unwrap_or_default()
+    }
+
+    /// Flush the history of each pair sampled since the last flush to the configured directory,
+    /// a no-op if no directory is configured
+    pub fn flush(&mut self) -> Result<(), PriceReporterManagerError> {
+        let dir = match self.config.dir.as_ref() {
+            Some(dir) => Path::new(dir),
+            None => return Ok(()),
+        };
+        fs::create_dir_all(dir)
+            .map_err(|err| PriceReporterManagerError::PriceHistory(err.to_string()))?;
+
+        for pair in self.dirty.drain() {
+            let history = match self.pairs.get(&pair) {
+                Some(history) => history,
+                None => continue,
+            };
+            let serialized = serde_json::to_vec(history)
+                .map_err(|err| PriceReporterManagerError::PriceHistory(err.to_string()))?;
+
+            // Write to a temporary file first so that a crash mid-flush leaves the previous
+            // history intact
+            let file_name = format!(
+                "{}-{}.{}",
+                pair.0.get_addr(),
+                pair.1.get_addr(),
+                HISTORY_FILE_EXTENSION
+            );
+            let path = dir.join(&file_name);
+            let tmp_path = dir.join(format!("{}.tmp", file_name));
+            fs::write(&tmp_path, serialized)
+                .and_then(|_| fs::rename(&tmp_path, &path))
+                .map_err(|err| PriceReporterManagerError::PriceHistory(err.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use uuid::Uuid;
+
+    use super::{CandleInterval, PriceHistoryConfig, PriceHistoryStore};
+    use crate::price_reporter::{exchanges::Exchange, reporter::PriceReport, tokens::Token};
+
+    /// Build a PriceReport for the WETH/USDC pair
+    fn price_report(
+        exchange: Option<Exchange>,
+        midpoint_price: f64,
+        timestamp: u128,
+    ) -> PriceReport {
+        PriceReport {
+            base_token: Token::_from_ticker("WETH"),
+            quote_token: Token::_from_ticker("USDC"),
+            exchange,
+            midpoint_price,
+            local_timestamp: timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that samples within a resolution bucket collapse to the latest, and that samples
+    /// older than the retention period are pruned
+    #[test]
+    fn test_sample_and_retain() {
+        let config = PriceHistoryConfig {
+            dir: None,
+            retention_ms: 10_000,
+        };
+        let mut store = PriceHistoryStore::open(config).unwrap();
+        store.record(&price_report(None, 100., 0));
+        store.record(&price_report(None, 101., 500));
+        store.record(&price_report(None, 102., 1_000));
+
+        let (weth, usdc) = (Token::_from_ticker("WETH"), Token::_from_ticker("USDC"));
+        let history = store.history(&weth, &usdc, 0 /* since */, usize::MAX);
+        assert_eq!(history.median.len(), 2);
+        assert_eq!(history.median[0].price, 101.);
+        assert_eq!(history.median[1].price, 102.);
+
+        store.record(&price_report(None, 103., 10_500));
+        let history = store.history(&weth, &usdc, 0 /* since */, usize::MAX);
+        assert_eq!(history.median.len(), 2);
+        assert_eq!(history.median[0].timestamp, 1_000);
+    }
+
+    /// Tests that candles take the open, high, low, and close of the samples in each interval,
+    /// and are built per exchange
+    #[test]
+    fn test_candles() {
+        let mut store = PriceHistoryStore::open(PriceHistoryConfig::default()).unwrap();
+        let binance = Some(Exchange::Binance);
+        for (price, timestamp) in [(100., 0), (105., 10_000), (98., 20_000), (101., 30_000)] {
+            store.record(&price_report(binance, price, timestamp));
+        }
+        store.record(&price_report(binance, 102., 60_000));
+
+        let (weth, usdc) = (Token::_from_ticker("WETH"), Token::_from_ticker("USDC"));
+        let candles = store.candles(
+            &weth,
+            &usdc,
+            binance,
+            CandleInterval::OneMinute,
+            0,
+            u64::MAX,
+        );
+        assert_eq!(candles.len(), 2);
+        assert_eq!(
+            (
+                candles[0].open,
+                candles[0].high,
+                candles[0].low,
+                candles[0].close
+            ),
+            (100., 105., 98., 101.)
+        );
+        assert_eq!(candles[0].num_samples, 4);
+        assert_eq!(candles[1].start, 60_000);
+
+        // No median has been sampled
+        assert!(store
+            .candles(&weth, &usdc, None, CandleInterval::OneMinute, 0, u64::MAX)
+            .is_empty());
+    }
+
+    /// Tests that flushed history is read back when the store is reopened
+    #[test]
+    fn test_flush_and_reopen() {
+        let dir = env::temp_dir().join(format!("price-history-{}", Uuid::new_v4()));
+        let config = PriceHistoryConfig {
+            dir: Some(dir.display().to_string()),
+            ..Default::default()
+        };
+
+        let mut store = PriceHistoryStore::open(config.clone()).unwrap();
+        store.record(&price_report(None, 100., 0));
+        store.record(&price_report(Some(Exchange::Kraken), 99., 0));
+        store.flush().unwrap();
+
+        let reopened = PriceHistoryStore::open(config).unwrap();
+        let (weth, usdc) = (Token::_from_ticker("WETH"), Token::_from_ticker("USDC"));
+        let history = reopened.history(&weth, &usdc, 0 /* since */, usize::MAX);
+        assert_eq!(history.median[0].price, 100.);
+        assert_eq!(history.exchanges[&Exchange::Kraken][0].price, 99.);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
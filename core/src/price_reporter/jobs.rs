@@ -4,10 +4,19 @@ use crossbeam::channel::Sender;
 use ring_channel::RingReceiver;
 use std::collections::{HashMap, HashSet};
 
+use crate::{
+    external_api::http::price_report::SignedPriceReport,
+    job_queue::{BoundedJob, JobOverflowPolicy},
+};
+
 use super::{
-    exchanges::{Exchange, ExchangeConnectionState},
+    aggregation::{AggregationMode, PriceWindow},
+    depth::OrderBookDepthReport,
+    exchanges::Exchange,
+    health::ExchangeHealthReport,
+    history::{CandleInterval, PairPriceHistory, PriceCandle},
     manager::PriceReporterListenerID,
-    reporter::{PriceReport, PriceReporterState},
+    reporter::{AllExchangeStates, PriceReport, PriceReporterState},
     tokens::Token,
 };
 
@@ -53,14 +62,24 @@ pub enum PriceReporterManagerJob {
         /// The return channel for the price report
         channel: Sender<PriceReporterState>,
     },
+    /// Peek at the latest median price report signed with the cluster key, `None` if price
+    /// signing is disabled or no median has yet been published for the pair
+    PeekSignedMedian {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The return channel for the signed price report
+        channel: Sender<Option<SignedPriceReport>>,
+    },
     /// Peek at each ExchangeConnectionState
     PeekAllExchanges {
         /// The base Token
         base_token: Token,
         /// The quote Token
         quote_token: Token,
-        /// The return channel for the ExchangeConnectionStates
-        channel: Sender<HashMap<Exchange, ExchangeConnectionState>>,
+        /// The return channel for the ExchangeConnectionStates and the decentralized reference
+        channel: Sender<AllExchangeStates>,
     },
     /// Create a forked median receiver
     CreateNewMedianReceiver {
@@ -71,6 +90,32 @@ pub enum PriceReporterManagerJob {
         /// The return channel for the new receiver
         channel: Sender<RingReceiver<PriceReport>>,
     },
+    /// Peek at the TWAP or VWAP over a trailing window
+    PeekAggregate {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The aggregation mode (TWAP or VWAP)
+        mode: AggregationMode,
+        /// The trailing window to aggregate over
+        window: PriceWindow,
+        /// The return channel for the aggregate price report
+        channel: Sender<PriceReporterState>,
+    },
+    /// Create a forked receiver streaming the TWAP or VWAP over a trailing window
+    CreateNewAggregateReceiver {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The aggregation mode (TWAP or VWAP)
+        mode: AggregationMode,
+        /// The trailing window to aggregate over
+        window: PriceWindow,
+        /// The return channel for the new receiver
+        channel: Sender<RingReceiver<PriceReport>>,
+    },
     /// Get all the exchanges that this price reporter supports
     GetSupportedExchanges {
         /// The base Token
@@ -89,4 +134,61 @@ pub enum PriceReporterManagerJob {
         /// The return channel for the healthy exchanges
         channel: Sender<HashSet<Exchange>>,
     },
+    /// Peek at the health score of each Exchange
+    PeekExchangeHealth {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The return channel for the health reports
+        channel: Sender<HashMap<Exchange, ExchangeHealthReport>>,
+    },
+    /// Peek at the order book depth of each Exchange and their aggregate
+    PeekDepth {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The return channel for the depth report
+        channel: Sender<OrderBookDepthReport>,
+    },
+    /// Peek at the recent midpoint history of the median and of each Exchange
+    PeekPriceHistory {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The time (in milliseconds since the epoch) from which to return history
+        since: u64,
+        /// The maximum number of points of each series to return
+        limit: usize,
+        /// The return channel for the history
+        channel: Sender<PairPriceHistory>,
+    },
+    /// Peek at the OHLC candles of the median or of a single Exchange
+    PeekPriceCandles {
+        /// The base Token
+        base_token: Token,
+        /// The quote Token
+        quote_token: Token,
+        /// The Exchange to build candles for, if `None` candles are built over the median
+        exchange: Option<Exchange>,
+        /// The interval each candle spans
+        interval: CandleInterval,
+        /// The start of the range to return candles over, in milliseconds since the epoch
+        start: u64,
+        /// The end (exclusive) of the range to return candles over, in milliseconds since the
+        /// epoch
+        end: u64,
+        /// The return channel for the candles
+        channel: Sender<Vec<PriceCandle>>,
+    },
+}
+
+impl BoundedJob for PriceReporterManagerJob {
+    /// Every job awaits a response on its channel, so a job that cannot be enqueued is
+    /// returned to its sender rather than leaving the sender waiting
+    fn overflow_policy(&self) -> JobOverflowPolicy {
+        JobOverflowPolicy::Reject
+    }
 }
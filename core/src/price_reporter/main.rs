@@ -43,11 +43,11 @@ async fn poll_or_stream_prices(should_poll: bool) {
             println!("{}", "-".repeat(80));
             println!(
                 "{:<14} | {:<14} | {:<14} | {:<14} | {:<14}",
-                format!("{}", exchange_states.get(&Exchange::Binance).unwrap()),
-                format!("{}", exchange_states.get(&Exchange::Coinbase).unwrap()),
-                format!("{}", exchange_states.get(&Exchange::Kraken).unwrap()),
-                format!("{}", exchange_states.get(&Exchange::Okx).unwrap()),
-                format!("{}", exchange_states.get(&Exchange::UniswapV3).unwrap()),
+                format!("{}", exchange_states.exchanges.get(&Exchange::Binance).unwrap()),
+                format!("{}", exchange_states.exchanges.get(&Exchange::Coinbase).unwrap()),
+                format!("{}", exchange_states.exchanges.get(&Exchange::Kraken).unwrap()),
+                format!("{}", exchange_states.exchanges.get(&Exchange::Okx).unwrap()),
+                format!("{}", exchange_states.exchanges.get(&Exchange::UniswapV3).unwrap()),
             );
             thread::sleep(time::Duration::from_millis(100));
         });
@@ -1,29 +1,62 @@
 //! Defines the PriceReporterManagerExecutor, the handler that is responsible for executing
 //! individual PriceReporterManagerJobs.
 use crossbeam::channel::{self, Sender};
-use futures::StreamExt;
+use ed25519_dalek::Keypair;
+use futures::{future, StreamExt};
 use ring_channel::RingReceiver;
 use std::{
     collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
     thread::JoinHandle,
+    time::Duration,
+};
+use tokio::{
+    runtime::Runtime,
+    signal::unix::{signal, Signal, SignalKind},
+    time,
 };
-use tokio::{runtime::Runtime, sync::mpsc::UnboundedReceiver as TokioReceiver};
 use tracing::log;
 use uuid::Uuid;
 
-use crate::{system_bus::SystemBus, types::SystemBusMessage, CancelChannel};
+use crate::{
+    external_api::http::price_report::{PriceAttestation, SignedPriceReport},
+    gossip::types::ClusterId,
+    job_queue::JobQueueReceiver,
+    system_bus::SystemBus,
+    types::SystemBusMessage,
+    CancelChannel,
+};
 
 use super::{
+    aggregation::{AggregationMode, PriceWindow},
+    depth::OrderBookDepthReport,
     errors::PriceReporterManagerError,
-    exchanges::{Exchange, ExchangeConnectionState},
+    exchanges::Exchange,
+    health::ExchangeHealthReport,
+    history::{CandleInterval, PairPriceHistory, PriceCandle, PriceHistoryStore},
     jobs::PriceReporterManagerJob,
-    reporter::{PriceReport, PriceReporter, PriceReporterState},
-    tokens::Token,
+    registry::fetch_token_registry,
+    remap::TokenRemapFile,
+    reporter::{AllExchangeStates, PriceReport, PriceReporter, PriceReporterState},
+    tokens::{update_token_registry, update_token_remap, Token},
     worker::PriceReporterManagerConfig,
 };
 
+/// The interval at which the on-chain token registry is re-read
+const TOKEN_REGISTRY_REFRESH_INTERVAL_MS: u64 = 5 * 60 * 1000; // 5 minutes
+/// The interval at which the token remap file is checked for modifications
+const TOKEN_REMAP_POLL_INTERVAL_MS: u64 = 5 * 1000; // 5 seconds
+/// The interval at which the historical price store is flushed to disk
+const PRICE_HISTORY_FLUSH_INTERVAL_MS: u64 = 30 * 1000; // 30 seconds
+
 /// A listener ID on a PriceReporter is just a UUID.
 pub type PriceReporterListenerID = Uuid;
+/// The latest signed median of each base/quote token pair, shared with the tasks that sign them
+type SignedPriceReports = Arc<RwLock<HashMap<(Token, Token), SignedPriceReport>>>;
+/// The historical price store, shared with the tasks that sample into it
+type SharedPriceHistory = Arc<RwLock<PriceHistoryStore>>;
+/// The slot in which a cancelled executor hands its job queue back to the manager
+pub(super) type ReturnedJobReceiver = Arc<Mutex<Option<JobQueueReceiver<PriceReporterManagerJob>>>>;
 
 /// The PriceReporterManager worker is a wrapper around the PriceReporterManagerExecutor, handling
 /// and dispatching jobs to the executor for spin-up and shut-down of individual PriceReporters.
@@ -34,48 +67,116 @@ pub struct PriceReporterManager {
     pub(super) manager_executor_handle: Option<JoinHandle<PriceReporterManagerError>>,
     /// The tokio runtime that the manager runs inside of
     pub(super) manager_runtime: Option<Runtime>,
+    /// The job queue handed back by the executor when it is cancelled, from which the
+    /// manager may be recovered
+    pub(super) returned_job_receiver: ReturnedJobReceiver,
 }
 
 /// The actual executor that handles incoming jobs, to create and destroy PriceReporters, and peek
 /// at PriceReports.
 pub struct PriceReporterManagerExecutor {
     /// The channel along which jobs are passed to the price reporter
-    pub(super) job_receiver: TokioReceiver<PriceReporterManagerJob>,
+    pub(super) job_receiver: JobQueueReceiver<PriceReporterManagerJob>,
     /// The channel on which the coordinator may cancel execution
     cancel_channel: CancelChannel,
+    /// The slot in which the job queue is handed back to the manager on cancellation
+    returned_job_receiver: ReturnedJobReceiver,
     /// The global system bus
     pub(super) system_bus: SystemBus<SystemBusMessage>,
     /// The map between base/quote token pairs and the instantiated PriceReporter
     pub(super) spawned_price_reporters: HashMap<(Token, Token), PriceReporter>,
     /// The map between base/quote token pairs and the set of registered listeners
     pub(super) registered_listeners: HashMap<(Token, Token), HashSet<PriceReporterListenerID>>,
+    /// The operator's token remap file, if one is configured
+    token_remap: Option<TokenRemapFile>,
+    /// The latest signed median of each pair, empty unless price signing is enabled
+    signed_price_reports: SignedPriceReports,
+    /// The sampled midpoint history of every pair
+    price_history: SharedPriceHistory,
     /// The manager config
     config: PriceReporterManagerConfig,
 }
 impl PriceReporterManagerExecutor {
     /// Creates the executor for the PriceReporterManager worker.
     pub(super) fn new(
-        job_receiver: TokioReceiver<PriceReporterManagerJob>,
+        job_receiver: JobQueueReceiver<PriceReporterManagerJob>,
         config: PriceReporterManagerConfig,
         cancel_channel: CancelChannel,
+        returned_job_receiver: ReturnedJobReceiver,
         system_bus: SystemBus<SystemBusMessage>,
     ) -> Result<Self, PriceReporterManagerError> {
         let spawned_price_reporters = HashMap::new();
         let registered_listeners = HashMap::new();
+        let token_remap = config.token_remap_file.clone().map(TokenRemapFile::new);
+        let price_history = PriceHistoryStore::open(config.price_history.clone())?;
         Ok(Self {
             job_receiver,
             cancel_channel,
+            returned_job_receiver,
             system_bus,
             spawned_price_reporters,
             registered_listeners,
+            token_remap,
+            signed_price_reports: Arc::new(RwLock::new(HashMap::new())),
+            price_history: Arc::new(RwLock::new(price_history)),
             config,
         })
     }
 
     /// The execution loop for the price reporter
     pub(super) async fn execution_loop(mut self) -> Result<(), PriceReporterManagerError> {
+        // The first tick completes immediately, so the registry is read once at startup
+        let mut registry_refresh =
+            time::interval(Duration::from_millis(TOKEN_REGISTRY_REFRESH_INTERVAL_MS));
+        let registry_enabled = self.config.token_registry_address.is_some();
+
+        // SIGHUP is only claimed when a remap file is configured, otherwise it keeps its default
+        // disposition
+        let remap_enabled = self.token_remap.is_some();
+        let mut remap_poll = time::interval(Duration::from_millis(TOKEN_REMAP_POLL_INTERVAL_MS));
+        let mut hangup = if remap_enabled {
+            Some(
+                signal(SignalKind::hangup())
+                    .map_err(|err| PriceReporterManagerError::ManagerSetup(err.to_string()))?,
+            )
+        } else {
+            None
+        };
+
+        let history_enabled = self.config.price_history.dir.is_some();
+        let mut history_flush =
+            time::interval(Duration::from_millis(PRICE_HISTORY_FLUSH_INTERVAL_MS));
+
         loop {
             tokio::select! {
+                // Refresh the token registry from on-chain state
+                _ = registry_refresh.tick(), if registry_enabled => {
+                    if let Err(e) = self.refresh_token_registry().await {
+                        log::error!("Error refreshing token registry: {e}");
+                    }
+                },
+
+                // Reload the token remap file if it has changed on disk; the first tick
+                // completes immediately, so the file is read once at startup
+                _ = remap_poll.tick(), if remap_enabled => {
+                    if self.token_remap.as_ref().unwrap().modified_since_read() {
+                        self.reload_token_remap();
+                    }
+                },
+
+                // Reload the token remap file at the operator's request
+                _ = recv_signal(&mut hangup) => {
+                    log::info!("received SIGHUP, reloading token remap file");
+                    self.reload_token_remap();
+                },
+
+                // Flush the sampled price history to disk
+                _ = history_flush.tick(), if history_enabled => {
+                    if let Err(e) = self.price_history.write().unwrap().flush() {
+                        log::error!("Error flushing price history: {e}");
+                    }
+                },
+
                 // Dequeue the next job from elsewhere in the local node
                 Some(job) = self.job_receiver.recv() => {
                     if let Err(e) = self.handle_job(job) {
@@ -83,15 +184,59 @@ impl PriceReporterManagerExecutor {
                     }
                 },
 
-                // Await cancellation by the coordinator
+                // Await cancellation by the coordinator, handing back the job queue so that
+                // jobs enqueued while the manager is down are served once it is recovered
                 _ = self.cancel_channel.changed() => {
                     log::info!("PriceReporterManager cancelled, shutting down...");
+                    if let Err(e) = self.price_history.write().unwrap().flush() {
+                        log::error!("Error flushing price history: {e}");
+                    }
+                    *self.returned_job_receiver.lock().unwrap() = Some(self.job_receiver);
                     return Err(PriceReporterManagerError::Cancelled("received cancel signal".to_string()));
                 }
             }
         }
     }
 
+    /// Read the on-chain token registry and merge it with the local token definitions
+    async fn refresh_token_registry(&self) -> Result<(), PriceReporterManagerError> {
+        let registry_address = match self.config.token_registry_address.as_ref() {
+            Some(address) => address,
+            None => return Ok(()),
+        };
+
+        let onchain_entries =
+            fetch_token_registry(&self.config.starknet_client, registry_address).await?;
+        let num_onchain = onchain_entries.len();
+        let num_tokens = update_token_registry(onchain_entries);
+        log::info!(
+            "refreshed token registry: {num_onchain} on-chain entries, {num_tokens} named tokens"
+        );
+
+        Ok(())
+    }
+
+    /// Re-read the token remap file and merge it into the token registry
+    ///
+    /// A remap file that fails to parse is logged and ignored, leaving the previous remap in place
+    fn reload_token_remap(&mut self) {
+        let token_remap = match self.token_remap.as_mut() {
+            Some(token_remap) => token_remap,
+            None => return,
+        };
+
+        match token_remap.read() {
+            Ok(remap_entries) => {
+                let num_remapped = remap_entries.len();
+                let num_tokens = update_token_remap(remap_entries);
+                log::info!(
+                    "reloaded token remap: {num_remapped} remapped entries, {num_tokens} named tokens"
+                );
+            }
+            Err(e) => log::error!("Error reloading token remap file: {e}"),
+        }
+    }
+
     /// Handles a job for the PriceReporterManager worker.
     pub(super) fn handle_job(
         &mut self,
@@ -115,6 +260,11 @@ impl PriceReporterManagerExecutor {
                 quote_token,
                 channel,
             } => self.peek_median(base_token, quote_token, channel),
+            PriceReporterManagerJob::PeekSignedMedian {
+                base_token,
+                quote_token,
+                channel,
+            } => self.peek_signed_median(base_token, quote_token, channel),
             PriceReporterManagerJob::PeekAllExchanges {
                 base_token,
                 quote_token,
@@ -125,6 +275,20 @@ impl PriceReporterManagerExecutor {
                 quote_token,
                 channel,
             } => self.create_new_median_receiver(base_token, quote_token, channel),
+            PriceReporterManagerJob::PeekAggregate {
+                base_token,
+                quote_token,
+                mode,
+                window,
+                channel,
+            } => self.peek_aggregate(base_token, quote_token, mode, window, channel),
+            PriceReporterManagerJob::CreateNewAggregateReceiver {
+                base_token,
+                quote_token,
+                mode,
+                window,
+                channel,
+            } => self.create_new_aggregate_receiver(base_token, quote_token, mode, window, channel),
             PriceReporterManagerJob::GetSupportedExchanges {
                 base_token,
                 quote_token,
@@ -135,6 +299,40 @@ impl PriceReporterManagerExecutor {
                 quote_token,
                 channel,
             } => self.get_healthy_exchanges(base_token, quote_token, channel),
+            PriceReporterManagerJob::PeekExchangeHealth {
+                base_token,
+                quote_token,
+                channel,
+            } => self.peek_exchange_health(base_token, quote_token, channel),
+            PriceReporterManagerJob::PeekDepth {
+                base_token,
+                quote_token,
+                channel,
+            } => self.peek_depth(base_token, quote_token, channel),
+            PriceReporterManagerJob::PeekPriceHistory {
+                base_token,
+                quote_token,
+                since,
+                limit,
+                channel,
+            } => self.peek_price_history(base_token, quote_token, since, limit, channel),
+            PriceReporterManagerJob::PeekPriceCandles {
+                base_token,
+                quote_token,
+                exchange,
+                interval,
+                start,
+                end,
+                channel,
+            } => self.peek_price_candles(
+                base_token,
+                quote_token,
+                exchange,
+                interval,
+                start,
+                end,
+                channel,
+            ),
         }
     }
 
@@ -193,6 +391,9 @@ impl PriceReporterManagerExecutor {
             quote_token.get_addr()
         );
         let config_clone = self.config.clone();
+        let signing_keypair = self.config.price_signing_keypair.clone();
+        let signed_price_reports = self.signed_price_reports.clone();
+        let price_history = self.price_history.clone();
         self.spawned_price_reporters
             .entry((base_token.clone(), quote_token.clone()))
             .or_insert_with(|| {
@@ -200,16 +401,31 @@ impl PriceReporterManagerExecutor {
                 let price_reporter =
                     PriceReporter::new(base_token.clone(), quote_token.clone(), config_clone);
                 // Stream all median PriceReports to the system bus, only if the midpoint price
-                // changes
+                // changes, signing each one published if price signing is enabled; every
+                // median is sampled into the price history
                 let mut median_receiver = price_reporter.create_new_median_receiver();
                 let system_bus_clone = system_bus.clone();
+                let price_reporter_clone = price_reporter.clone();
+                let price_history_clone = price_history.clone();
                 tokio::spawn(async move {
                     let mut last_median_price_report = PriceReport::default();
                     loop {
                         let median_price_report = median_receiver.next().await.unwrap();
+                        price_history_clone
+                            .write()
+                            .unwrap()
+                            .record(&median_price_report);
                         if median_price_report.midpoint_price
                             != last_median_price_report.midpoint_price
                         {
+                            if let Some(keypair) = signing_keypair.as_ref() {
+                                sign_median(
+                                    &median_price_report,
+                                    &price_reporter_clone,
+                                    keypair,
+                                    &signed_price_reports,
+                                );
+                            }
                             system_bus_clone.publish(
                                 median_price_report_topic.clone(),
                                 SystemBusMessage::PriceReportMedian(median_price_report.clone()),
@@ -219,7 +435,7 @@ impl PriceReporterManagerExecutor {
                     }
                 });
                 // Stream all individual Exchange PriceReports to the system bus, only if the
-                // midpoint price changes
+                // midpoint price changes, sampling each into the price history
                 for exchange in price_reporter.supported_exchanges.iter() {
                     let mut exchange_receiver =
                         price_reporter.create_new_exchange_receiver(*exchange);
@@ -230,10 +446,12 @@ impl PriceReporterManagerExecutor {
                         quote_token.get_addr()
                     );
                     let system_bus_clone = system_bus.clone();
+                    let price_history_clone = price_history.clone();
                     tokio::spawn(async move {
                         let mut last_price_report = PriceReport::default();
                         loop {
                             let price_report = exchange_receiver.next().await.unwrap();
+                            price_history_clone.write().unwrap().record(&price_report);
                             if price_report.midpoint_price != last_price_report.midpoint_price {
                                 system_bus_clone.publish(
                                     exchange_price_report_topic.clone(),
@@ -313,12 +531,31 @@ impl PriceReporterManagerExecutor {
         Ok(())
     }
 
+    /// Handler for PeekSignedMedian job.
+    fn peek_signed_median(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        channel: Sender<Option<SignedPriceReport>>,
+    ) -> Result<(), PriceReporterManagerError> {
+        // Start the PriceReporter so that a first request for a pair begins its signing
+        self.get_price_reporter_or_create(base_token.clone(), quote_token.clone())?;
+        let signed_report = self
+            .signed_price_reports
+            .read()
+            .unwrap()
+            .get(&(base_token, quote_token))
+            .cloned();
+        channel.send(signed_report).unwrap();
+        Ok(())
+    }
+
     /// Handler for PeekAllExchanges job.
     fn peek_all_exchanges(
         &mut self,
         base_token: Token,
         quote_token: Token,
-        channel: Sender<HashMap<Exchange, ExchangeConnectionState>>,
+        channel: Sender<AllExchangeStates>,
     ) -> Result<(), PriceReporterManagerError> {
         let price_reporter = self.get_price_reporter_or_create(base_token, quote_token)?;
         channel.send(price_reporter.peek_all_exchanges()).unwrap();
@@ -339,6 +576,38 @@ impl PriceReporterManagerExecutor {
         Ok(())
     }
 
+    /// Handler for PeekAggregate job.
+    fn peek_aggregate(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        mode: AggregationMode,
+        window: PriceWindow,
+        channel: Sender<PriceReporterState>,
+    ) -> Result<(), PriceReporterManagerError> {
+        let price_reporter = self.get_price_reporter_or_create(base_token, quote_token)?;
+        channel
+            .send(price_reporter.peek_aggregate(mode, window))
+            .unwrap();
+        Ok(())
+    }
+
+    /// Handler for CreateNewAggregateReceiver job.
+    fn create_new_aggregate_receiver(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        mode: AggregationMode,
+        window: PriceWindow,
+        channel: Sender<RingReceiver<PriceReport>>,
+    ) -> Result<(), PriceReporterManagerError> {
+        let price_reporter = self.get_price_reporter_or_create(base_token, quote_token)?;
+        channel
+            .send(price_reporter.create_new_aggregate_receiver(mode, window))
+            .unwrap();
+        Ok(())
+    }
+
     /// Handler for GetSupportedExchanges job.
     fn get_supported_exchanges(
         &mut self,
@@ -366,4 +635,119 @@ impl PriceReporterManagerExecutor {
             .unwrap();
         Ok(())
     }
+
+    /// Handler for PeekExchangeHealth job.
+    fn peek_exchange_health(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        channel: Sender<HashMap<Exchange, ExchangeHealthReport>>,
+    ) -> Result<(), PriceReporterManagerError> {
+        let price_reporter = self.get_price_reporter_or_create(base_token, quote_token)?;
+        channel.send(price_reporter.peek_exchange_health()).unwrap();
+        Ok(())
+    }
+
+    /// Handler for PeekDepth job.
+    fn peek_depth(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        channel: Sender<OrderBookDepthReport>,
+    ) -> Result<(), PriceReporterManagerError> {
+        let price_reporter = self.get_price_reporter_or_create(base_token, quote_token)?;
+        channel.send(price_reporter.peek_depth()).unwrap();
+        Ok(())
+    }
+
+    /// Handler for PeekPriceHistory job.
+    fn peek_price_history(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        since: u64,
+        limit: usize,
+        channel: Sender<PairPriceHistory>,
+    ) -> Result<(), PriceReporterManagerError> {
+        // Start the PriceReporter so that a first request for a pair begins its sampling
+        self.get_price_reporter_or_create(base_token.clone(), quote_token.clone())?;
+        let history =
+            self.price_history
+                .read()
+                .unwrap()
+                .history(&base_token, &quote_token, since, limit);
+        channel.send(history).unwrap();
+        Ok(())
+    }
+
+    /// Handler for PeekPriceCandles job.
+    #[allow(clippy::too_many_arguments)]
+    fn peek_price_candles(
+        &mut self,
+        base_token: Token,
+        quote_token: Token,
+        exchange: Option<Exchange>,
+        interval: CandleInterval,
+        start: u64,
+        end: u64,
+        channel: Sender<Vec<PriceCandle>>,
+    ) -> Result<(), PriceReporterManagerError> {
+        self.get_price_reporter_or_create(base_token.clone(), quote_token.clone())?;
+        let candles = self.price_history.read().unwrap().candles(
+            &base_token,
+            &quote_token,
+            exchange,
+            interval,
+            start,
+            end,
+        );
+        channel.send(candles).unwrap();
+        Ok(())
+    }
+}
+
+/// Sign a median PriceReport over the exchanges currently feeding the median, and record it
+/// as the latest signed median of its pair
+fn sign_median(
+    median_price_report: &PriceReport,
+    price_reporter: &PriceReporter,
+    keypair: &Keypair,
+    signed_price_reports: &SignedPriceReports,
+) {
+    let mut exchanges = price_reporter
+        .get_healthy_exchanges()
+        .into_iter()
+        .collect::<Vec<_>>();
+    exchanges.sort();
+
+    let attestation = PriceAttestation {
+        cluster_id: ClusterId::new(&keypair.public),
+        base_token: median_price_report.base_token.clone(),
+        quote_token: median_price_report.quote_token.clone(),
+        midpoint_price: median_price_report.midpoint_price,
+        exchanges,
+        timestamp: median_price_report.local_timestamp as u64,
+    };
+    match SignedPriceReport::sign(attestation, keypair) {
+        Ok(signed_report) => {
+            signed_price_reports.write().unwrap().insert(
+                (
+                    median_price_report.base_token.clone(),
+                    median_price_report.quote_token.clone(),
+                ),
+                signed_report,
+            );
+        }
+        Err(e) => log::error!("Error signing median price report: {e}"),
+    }
+}
+
+/// Await the next delivery of a signal, or never resolve if the signal is not being listened for
+async fn recv_signal(signal: &mut Option<Signal>) {
+    match signal {
+        Some(signal) => {
+            signal.recv().await;
+        }
+        None => future::pending().await,
+    }
 }
@@ -1,10 +1,19 @@
 //! The price reporter module manages all external price feeds, including PriceReporter spin-up and
 //! tear-down, websocket connections to all exchanges (both centralized and decentralized), and
 //! aggregation of individual PriceReports into medians.
+pub mod aggregation;
+pub mod breaker;
+pub mod depth;
 pub mod errors;
 pub mod exchanges;
+pub mod health;
+pub mod history;
 pub mod jobs;
 pub mod manager;
+pub mod quarantine;
+pub mod registry;
+pub mod remap;
+pub mod replay;
 pub mod reporter;
 pub mod tokens;
 pub mod worker;
@@ -0,0 +1,291 @@
+//! Defines the cross-exchange deviation quarantine. An Exchange whose midpoint deviates from the
+//! median of the other Exchanges by more than the configured number of basis points for longer
+//! than the configured window is quarantined, and excluded from the median until the quarantine
+//! period has elapsed and its midpoint has returned within the band.
+use serde::{Deserialize, Serialize};
+use stats::median;
+use std::collections::{HashMap, HashSet};
+
+use super::{exchanges::Exchange, reporter::PriceReport};
+
+/// The default maximum deviation (in basis points) of an Exchange from the median of the others
+const DEFAULT_MAX_DEVIATION_BPS: u32 = 100;
+/// The default duration (in milliseconds) an Exchange may deviate before it is quarantined
+const DEFAULT_DEVIATION_WINDOW_MS: u128 = 10_000; // 10 seconds
+/// The default minimum duration (in milliseconds) of a quarantine
+const DEFAULT_QUARANTINE_MS: u128 = 60_000; // 1 minute
+/// The minimum number of other Exchanges that must report for a deviation to be judged; with
+/// fewer there is no majority to tell which venue has diverged
+const MIN_REFERENCE_EXCHANGES: usize = 2;
+/// The number of basis points in a unit
+const BPS_PER_UNIT: f64 = 10_000.;
+
+/// The thresholds of the deviation quarantine
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviationQuarantineConfig {
+    /// The maximum deviation (in basis points) of an Exchange from the median of the others
+    pub max_deviation_bps: u32,
+    /// The duration (in milliseconds) an Exchange may deviate before it is quarantined
+    pub window_ms: u128,
+    /// The minimum duration (in milliseconds) of a quarantine
+    pub quarantine_ms: u128,
+}
+
+impl Default for DeviationQuarantineConfig {
+    fn default() -> Self {
+        Self {
+            max_deviation_bps: DEFAULT_MAX_DEVIATION_BPS,
+            window_ms: DEFAULT_DEVIATION_WINDOW_MS,
+            quarantine_ms: DEFAULT_QUARANTINE_MS,
+        }
+    }
+}
+
+/// An Exchange entering or leaving quarantine
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuarantineTransition {
+    /// The Exchange that was quarantined or released
+    pub exchange: Exchange,
+    /// The deviation (in basis points) of the Exchange from the median of the others at the time
+    /// of the transition
+    pub deviation_bps: f64,
+    /// Whether the Exchange entered quarantine; false if it was released
+    pub quarantined: bool,
+}
+
+/// The deviation state of a single Exchange
+#[derive(Clone, Debug, Default)]
+struct DeviationState {
+    /// The time (in milliseconds) at which the Exchange began deviating, None if it is within
+    /// the band
+    deviating_since: Option<u128>,
+    /// The time (in milliseconds) at which the Exchange was quarantined, None if it is not
+    quarantined_since: Option<u128>,
+}
+
+/// Tracks the deviation of each Exchange of a single token pair from the median of the others
+#[derive(Clone, Debug)]
+pub struct DeviationQuarantine {
+    /// The thresholds of the quarantine
+    config: DeviationQuarantineConfig,
+    /// The deviation state of each Exchange that has reported
+    state: HashMap<Exchange, DeviationState>,
+}
+
+impl DeviationQuarantine {
+    /// Create a new quarantine with the given thresholds
+    pub fn new(config: DeviationQuarantineConfig) -> Self {
+        Self {
+            config,
+            state: HashMap::new(),
+        }
+    }
+
+    /// Returns whether the given Exchange is currently quarantined
+    pub fn is_quarantined(&self, exchange: &Exchange) -> bool {
+        self.state
+            .get(exchange)
+            .map(|state| state.quarantined_since.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Re-evaluate the deviation of each Exchange at the given time, returning the Exchanges that
+    /// entered or left quarantine
+    ///
+    /// Exchanges are judged in order of their distance from the median of every reporting
+    /// Exchange, and an Exchange found deviating is left out of the reference median of those
+    /// judged after it; otherwise, with few venues, a single outlier would drag the reference of
+    /// the others far enough that they appear to deviate as well. Quarantined Exchanges are
+    /// never part of the reference.
+    pub fn evaluate(
+        &mut self,
+        current_price_reports: &HashMap<Exchange, PriceReport>,
+        now: u128,
+    ) -> Vec<QuarantineTransition> {
+        let reported_prices = current_price_reports
+            .iter()
+            .filter(|(_, price_report)| {
+                **price_report != PriceReport::default() && price_report.midpoint_price != 0.
+            })
+            .map(|(exchange, price_report)| (*exchange, price_report.midpoint_price))
+            .collect::<HashMap<Exchange, f64>>();
+        let overall_median = match median(reported_prices.values().copied()) {
+            Some(overall_median) => overall_median,
+            None => return Vec::new(),
+        };
+
+        let mut judge_order = reported_prices.keys().copied().collect::<Vec<Exchange>>();
+        judge_order.sort_by(|a, b| {
+            let distance = |exchange: &Exchange| (reported_prices[exchange] - overall_median).abs();
+            distance(b).total_cmp(&distance(a))
+        });
+
+        let mut excluded = reported_prices
+            .keys()
+            .filter(|exchange| self.is_quarantined(exchange))
+            .copied()
+            .collect::<HashSet<Exchange>>();
+        let mut transitions = Vec::new();
+        for exchange in judge_order.into_iter() {
+            let reference_prices = reported_prices
+                .iter()
+                .filter(|(other, _)| **other != exchange && !excluded.contains(other))
+                .map(|(_, price)| *price)
+                .collect::<Vec<f64>>();
+            let deviation_bps = if reference_prices.len() < MIN_REFERENCE_EXCHANGES {
+                None
+            } else {
+                median(reference_prices.into_iter())
+                    .filter(|reference| *reference != 0.)
+                    .map(|reference| {
+                        (reported_prices[&exchange] - reference).abs() / reference * BPS_PER_UNIT
+                    })
+            };
+
+            let state = self.state.entry(exchange).or_default();
+            match deviation_bps {
+                Some(deviation_bps) if deviation_bps > self.config.max_deviation_bps as f64 => {
+                    excluded.insert(exchange);
+                    let deviating_since = *state.deviating_since.get_or_insert(now);
+                    if state.quarantined_since.is_none()
+                        && now - deviating_since >= self.config.window_ms
+                    {
+                        state.quarantined_since = Some(now);
+                        transitions.push(QuarantineTransition {
+                            exchange,
+                            deviation_bps,
+                            quarantined: true,
+                        });
+                    }
+                }
+                // An Exchange that cannot be judged is treated as within the band, but is only
+                // released from quarantine once it can be judged again
+                _ => {
+                    state.deviating_since = None;
+                    if let (Some(deviation_bps), Some(quarantined_since)) =
+                        (deviation_bps, state.quarantined_since)
+                        && now - quarantined_since >= self.config.quarantine_ms
+                    {
+                        state.quarantined_since = None;
+                        transitions.push(QuarantineTransition {
+                            exchange,
+                            deviation_bps,
+                            quarantined: false,
+                        });
+                    }
+                }
+            }
+        }
+
+        transitions
+    }
+
+    /// Filter a set of PriceReports down to those from Exchanges that are not quarantined
+    pub fn filter_quarantined(
+        &self,
+        current_price_reports: &HashMap<Exchange, PriceReport>,
+    ) -> HashMap<Exchange, PriceReport> {
+        current_price_reports
+            .iter()
+            .filter(|(exchange, _)| !self.is_quarantined(exchange))
+            .map(|(exchange, price_report)| (*exchange, price_report.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{DeviationQuarantine, DeviationQuarantineConfig};
+    use crate::price_reporter::{exchanges::Exchange, reporter::PriceReport};
+
+    /// Build a set of PriceReports from (exchange, midpoint) pairs
+    fn price_reports(prices: &[(Exchange, f64)]) -> HashMap<Exchange, PriceReport> {
+        prices
+            .iter()
+            .map(|(exchange, midpoint_price)| {
+                (
+                    *exchange,
+                    PriceReport {
+                        exchange: Some(*exchange),
+                        midpoint_price: *midpoint_price,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Tests that a deviating exchange is quarantined only once it has deviated for the window,
+    /// and that the exchanges it drags the reference away from are not
+    #[test]
+    fn test_quarantine_after_window() {
+        let config = DeviationQuarantineConfig::default();
+        let mut quarantine = DeviationQuarantine::new(config);
+        let reports = price_reports(&[
+            (Exchange::Binance, 100.),
+            (Exchange::Kraken, 100.05),
+            (Exchange::Okx, 103.),
+        ]);
+
+        assert!(quarantine.evaluate(&reports, 0 /* now */).is_empty());
+        assert!(!quarantine.is_quarantined(&Exchange::Okx));
+
+        let transitions = quarantine.evaluate(&reports, config.window_ms);
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].exchange, Exchange::Okx);
+        assert!(transitions[0].quarantined);
+        assert!(transitions[0].deviation_bps > config.max_deviation_bps as f64);
+
+        let filtered = quarantine.filter_quarantined(&reports);
+        assert!(filtered.contains_key(&Exchange::Binance));
+        assert!(filtered.contains_key(&Exchange::Kraken));
+        assert!(!filtered.contains_key(&Exchange::Okx));
+    }
+
+    /// Tests that a quarantined exchange is held for the quarantine period, and released once it
+    /// has returned within the band
+    #[test]
+    fn test_release() {
+        let config = DeviationQuarantineConfig::default();
+        let mut quarantine = DeviationQuarantine::new(config);
+        let deviant = price_reports(&[
+            (Exchange::Binance, 100.),
+            (Exchange::Kraken, 100.),
+            (Exchange::Okx, 97.),
+        ]);
+        quarantine.evaluate(&deviant, 0 /* now */);
+        quarantine.evaluate(&deviant, config.window_ms);
+        assert!(quarantine.is_quarantined(&Exchange::Okx));
+
+        // Recovered, but the quarantine period has not elapsed
+        let recovered = price_reports(&[
+            (Exchange::Binance, 100.),
+            (Exchange::Kraken, 100.),
+            (Exchange::Okx, 100.01),
+        ]);
+        assert!(quarantine
+            .evaluate(&recovered, config.window_ms + 1)
+            .is_empty());
+
+        let transitions = quarantine.evaluate(&recovered, config.window_ms + config.quarantine_ms);
+        assert_eq!(transitions.len(), 1);
+        assert!(!transitions[0].quarantined);
+        assert!(!quarantine.is_quarantined(&Exchange::Okx));
+    }
+
+    /// Tests that a deviation is not judged without enough other exchanges for a reference
+    #[test]
+    fn test_insufficient_reference() {
+        let config = DeviationQuarantineConfig::default();
+        let mut quarantine = DeviationQuarantine::new(config);
+        let reports = price_reports(&[(Exchange::Binance, 100.), (Exchange::Okx, 150.)]);
+
+        quarantine.evaluate(&reports, 0 /* now */);
+        assert!(quarantine.evaluate(&reports, config.window_ms).is_empty());
+        assert!(!quarantine.is_quarantined(&Exchange::Okx));
+        assert!(!quarantine.is_quarantined(&Exchange::Binance));
+    }
+}
@@ -0,0 +1,174 @@
+//! Reads the canonical token registry from the on-chain registry contract. The registry is polled
+//! by the PriceReporterManager and merged with the local ERC20_DATA, so that tokens listed on-chain
+//! become Named Tokens without redeploying the relayer.
+//!
+//! The registry contract's view function returns a felt array of the form
+//! `[num_entries, entry_0, entry_1, ...]`, where each entry is laid out as (ERC-20 Address,
+//! Decimals, ERC-20 Ticker, Binance Ticker, Coinbase Ticker, Kraken Ticker, Okx Ticker). Tickers
+//! are encoded as Cairo short strings, with a zero felt indicating that the Exchange does not list
+//! the token. The contract predates the Binance.US and Bybit connections; their tickers for tokens
+//! listed only on-chain may be supplied through the token remap file.
+use crypto::fields::{starknet_felt_to_biguint, starknet_felt_to_u64};
+use starknet::core::{
+    types::{BlockId, CallFunction, FieldElement as StarknetFieldElement},
+    utils::get_selector_from_name,
+};
+use starknet_providers::Provider;
+use std::{collections::HashMap, convert::TryFrom, str::FromStr};
+
+use crate::starknet_client::client::StarknetClient;
+
+use super::{errors::PriceReporterManagerError, exchanges::Exchange, tokens::TokenRegistryEntry};
+
+/// The registry contract's view function that returns all listed tokens
+const TOKEN_REGISTRY_FUNCTION: &str = "get_token_registry";
+/// The Exchanges whose tickers the registry contract encodes, in the order they appear in an entry
+const REGISTRY_EXCHANGES: [Exchange; 4] = [
+    Exchange::Binance,
+    Exchange::Coinbase,
+    Exchange::Kraken,
+    Exchange::Okx,
+];
+/// The number of felts used to encode a single registry entry
+const REGISTRY_ENTRY_LEN: usize = 3 + REGISTRY_EXCHANGES.len();
+
+/// Fetch and parse the token registry from the contract at the given address
+pub async fn fetch_token_registry(
+    starknet_client: &StarknetClient,
+    registry_address: &str,
+) -> Result<Vec<TokenRegistryEntry>, PriceReporterManagerError> {
+    let contract_address = StarknetFieldElement::from_str(registry_address)
+        .map_err(|err| PriceReporterManagerError::TokenRegistry(err.to_string()))?;
+    let call = CallFunction {
+        contract_address,
+        entry_point_selector: get_selector_from_name(TOKEN_REGISTRY_FUNCTION).unwrap(),
+        calldata: vec![],
+    };
+
+    let res = starknet_client
+        .get_gateway_client()
+        .call_contract(call, BlockId::Pending)
+        .await
+        .map_err(|err| PriceReporterManagerError::TokenRegistry(err.to_string()))?;
+
+    parse_registry_response(&res.result)
+}
+
+/// Parse the raw felt array returned by the registry contract into registry entries
+fn parse_registry_response(
+    felts: &[StarknetFieldElement],
+) -> Result<Vec<TokenRegistryEntry>, PriceReporterManagerError> {
+    let (num_entries, entries) = felts.split_first().ok_or_else(|| {
+        PriceReporterManagerError::TokenRegistry("empty registry response".to_string())
+    })?;
+
+    let num_entries = starknet_felt_to_u64(num_entries) as usize;
+    if entries.len() != num_entries * REGISTRY_ENTRY_LEN {
+        return Err(PriceReporterManagerError::TokenRegistry(format!(
+            "expected {} registry entries, got {} felts",
+            num_entries,
+            entries.len()
+        )));
+    }
+
+    entries
+        .chunks_exact(REGISTRY_ENTRY_LEN)
+        .map(parse_registry_entry)
+        .collect()
+}
+
+/// Parse a single registry entry from its felt encoding
+fn parse_registry_entry(
+    felts: &[StarknetFieldElement],
+) -> Result<TokenRegistryEntry, PriceReporterManagerError> {
+    let addr = format!("0x{:040x}", starknet_felt_to_biguint(&felts[0]));
+    let decimals = u8::try_from(starknet_felt_to_u64(&felts[1]))
+        .map_err(|err| PriceReporterManagerError::TokenRegistry(err.to_string()))?;
+    let ticker = parse_short_string(&felts[2])?.ok_or_else(|| {
+        PriceReporterManagerError::TokenRegistry(format!("token {} has no ticker", addr))
+    })?;
+
+    let mut exchange_tickers = HashMap::new();
+    for (exchange, felt) in REGISTRY_EXCHANGES.iter().zip(felts[3..].iter()) {
+        if let Some(exchange_ticker) = parse_short_string(felt)? {
+            exchange_tickers.insert(*exchange, exchange_ticker);
+        }
+    }
+
+    Ok(TokenRegistryEntry {
+        addr,
+        decimals,
+        ticker,
+        exchange_tickers,
+    })
+}
+
+/// Decode a Cairo short string, returning `None` for the zero felt
+fn parse_short_string(
+    felt: &StarknetFieldElement,
+) -> Result<Option<String>, PriceReporterManagerError> {
+    let bytes = felt
+        .to_bytes_be()
+        .iter()
+        .copied()
+        .skip_while(|byte| *byte == 0)
+        .collect::<Vec<u8>>();
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+
+    String::from_utf8(bytes)
+        .map(Some)
+        .map_err(|err| PriceReporterManagerError::TokenRegistry(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto::fields::biguint_to_starknet_felt;
+    use num_bigint::BigUint;
+    use starknet::core::types::FieldElement as StarknetFieldElement;
+    use std::str::FromStr;
+
+    use super::parse_registry_response;
+    use crate::price_reporter::exchanges::Exchange;
+
+    /// Encode a short string as a felt
+    fn short_string(s: &str) -> StarknetFieldElement {
+        biguint_to_starknet_felt(&BigUint::from_bytes_be(s.as_bytes()))
+    }
+
+    /// Tests parsing a registry response with a single entry
+    #[test]
+    fn test_parse_registry_entry() {
+        let felts = vec![
+            StarknetFieldElement::from(1u8), /* num_entries */
+            StarknetFieldElement::from_str("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap(),
+            StarknetFieldElement::from(18u8),
+            short_string("WETH"),
+            short_string("ETH"),
+            StarknetFieldElement::from(0u8),
+            short_string("ETH"),
+            short_string("ETH"),
+        ];
+
+        let entries = parse_registry_response(&felts).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        let entry = &entries[0];
+        assert_eq!(entry.addr, "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        assert_eq!(entry.decimals, 18);
+        assert_eq!(entry.ticker, "WETH");
+        assert_eq!(
+            entry.exchange_tickers.get(&Exchange::Binance).unwrap(),
+            "ETH"
+        );
+        assert!(!entry.exchange_tickers.contains_key(&Exchange::Coinbase));
+    }
+
+    /// Tests that a response with a mismatched length is rejected
+    #[test]
+    fn test_parse_registry_bad_length() {
+        let felts = vec![StarknetFieldElement::from(2u8), short_string("WETH")];
+        assert!(parse_registry_response(&felts).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Reads the operator's token remap file, a JSON list of ERC-20 tokens that are merged into the
+//! token registry on top of the on-chain registry and the local ERC20_DATA. Each entry gives the
+//! ERC-20 ticker, address and decimals of a token, and optionally its ticker on each centralized
+//! Exchange, e.g.
+//!
+//! ```json
+//! [{ "ticker": "ARB", "address": "0xb50721...", "decimals": 18,
+//!    "exchangeTickers": { "Binance": "ARB", "Okx": "ARB" } }]
+//! ```
+//!
+//! The file is re-read when the relayer receives SIGHUP, or when its modification time changes.
+//! A reload only affects PriceReporters started afterwards; running PriceReporters keep the
+//! Exchange connections they were started with.
+use serde::Deserialize;
+use std::{collections::HashMap, convert::TryFrom, fs, time::SystemTime};
+
+use super::{errors::PriceReporterManagerError, exchanges::Exchange, tokens::TokenRegistryEntry};
+
+/// The number of hex digits in an ERC-20 address
+const ERC20_ADDR_HEX_DIGITS: usize = 40;
+
+/// A single entry in the token remap file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemapFileEntry {
+    /// The ERC-20 ticker
+    ticker: String,
+    /// The ERC-20 address
+    address: String,
+    /// The ERC-20 `decimals` field
+    decimals: u8,
+    /// The ticker used by each Exchange that lists the token
+    #[serde(default)]
+    exchange_tickers: HashMap<Exchange, String>,
+}
+
+impl TryFrom<RemapFileEntry> for TokenRegistryEntry {
+    type Error = PriceReporterManagerError;
+
+    fn try_from(entry: RemapFileEntry) -> Result<Self, Self::Error> {
+        let valid_addr = entry
+            .address
+            .strip_prefix("0x")
+            .map(|hex_addr| {
+                hex_addr.len() == ERC20_ADDR_HEX_DIGITS
+                    && hex_addr.chars().all(|c| c.is_ascii_hexdigit())
+            })
+            .unwrap_or(false);
+        if !valid_addr {
+            return Err(PriceReporterManagerError::TokenRemap(format!(
+                "invalid ERC-20 address {} for {}",
+                entry.address, entry.ticker
+            )));
+        }
+
+        if entry.exchange_tickers.contains_key(&Exchange::UniswapV3) {
+            return Err(PriceReporterManagerError::TokenRemap(format!(
+                "{} lists a ticker for {}, which is indexed by address",
+                entry.ticker,
+                Exchange::UniswapV3
+            )));
+        }
+
+        Ok(TokenRegistryEntry {
+            addr: entry.address.to_lowercase(),
+            decimals: entry.decimals,
+            ticker: entry.ticker,
+            exchange_tickers: entry.exchange_tickers,
+        })
+    }
+}
+
+/// The token remap file, along with the modification time of the version last read
+#[derive(Clone, Debug)]
+pub struct TokenRemapFile {
+    /// The path to the remap file
+    path: String,
+    /// The modification time of the file when it was last read, `None` if it has not been read
+    last_modified: Option<SystemTime>,
+}
+
+impl TokenRemapFile {
+    /// Constructor
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            last_modified: None,
+        }
+    }
+
+    /// Whether the file has been modified since it was last read
+    pub fn modified_since_read(&self) -> bool {
+        match fs::metadata(&self.path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => self.last_modified != Some(modified),
+            // The file may be mid-replace; wait for it to reappear
+            Err(_) => false,
+        }
+    }
+
+    /// Read and parse the remap file
+    pub fn read(&mut self) -> Result<Vec<TokenRegistryEntry>, PriceReporterManagerError> {
+        // Record the modification time before reading, so that a write racing the read is
+        // picked up on the next check
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| PriceReporterManagerError::TokenRemap(err.to_string()))?;
+        let contents = fs::read(&self.path)
+            .map_err(|err| PriceReporterManagerError::TokenRemap(err.to_string()))?;
+        self.last_modified = Some(modified);
+
+        parse_token_remap(&contents)
+    }
+}
+
+/// Parse the contents of a token remap file into registry entries
+fn parse_token_remap(
+    contents: &[u8],
+) -> Result<Vec<TokenRegistryEntry>, PriceReporterManagerError> {
+    let entries: Vec<RemapFileEntry> = serde_json::from_slice(contents)
+        .map_err(|err| PriceReporterManagerError::TokenRemap(err.to_string()))?;
+    entries
+        .into_iter()
+        .map(TokenRegistryEntry::try_from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_token_remap;
+    use crate::price_reporter::exchanges::Exchange;
+
+    /// Tests parsing a remap file with a single entry
+    #[test]
+    fn test_parse_remap_entry() {
+        let contents = br#"[{
+            "ticker": "ARB",
+            "address": "0xB50721BCf8d664c30412Cfbc6cf7a15145234ad1",
+            "decimals": 18,
+            "exchangeTickers": { "Binance": "ARB" }
+        }]"#;
+
+        let entries = parse_token_remap(contents).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].addr,
+            "0xb50721bcf8d664c30412cfbc6cf7a15145234ad1"
+        );
+        assert_eq!(entries[0].decimals, 18);
+        assert_eq!(
+            entries[0].exchange_tickers.get(&Exchange::Binance),
+            Some(&"ARB".to_string())
+        );
+    }
+
+    /// Tests that an entry with a malformed address is rejected
+    #[test]
+    fn test_parse_remap_invalid_addr() {
+        let contents = br#"[{ "ticker": "ARB", "address": "0x1234", "decimals": 18 }]"#;
+        assert!(parse_token_remap(contents).is_err());
+    }
+}
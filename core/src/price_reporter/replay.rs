@@ -0,0 +1,197 @@
+//! Replays a recorded price feed in place of live exchange connections
+//!
+//! A recorded feed is a file of JSON-serialized `PriceReport`s, one per line, in the order
+//! they were received. When the price reporter manager is configured with a recorded feed,
+//! each exchange connection replays the feed's reports for its pair and exchange instead of
+//! connecting to the exchange. The recorded gaps between reports are measured against the
+//! feed's clock, so that a feed paced by a `ManualClock` advances only when its driver does.
+//! Reports are re-stamped with the local time as they are replayed, so that the reporter's
+//! staleness checks apply to the replay as they would to a live feed
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::Arc,
+    time::Duration,
+};
+
+use ring_channel::RingSender;
+
+use crate::clock::SharedClock;
+
+use super::{
+    errors::ExchangeConnectionError,
+    exchanges::{get_current_time, Exchange, WorkerHandles},
+    reporter::PriceReport,
+    tokens::Token,
+};
+
+/// A recorded sequence of price reports and the clock it is replayed against
+#[derive(Clone, Debug)]
+pub struct RecordedFeed {
+    /// The recorded reports, in the order they were received
+    reports: Arc<Vec<PriceReport>>,
+    /// The clock that the gaps between reports are replayed against
+    clock: SharedClock,
+}
+
+impl RecordedFeed {
+    /// Constructor
+    pub fn new(reports: Vec<PriceReport>, clock: SharedClock) -> Self {
+        Self {
+            reports: Arc::new(reports),
+            clock,
+        }
+    }
+
+    /// Load a feed from a file of JSON-serialized reports, one per line
+    ///
+    /// Blank lines are skipped
+    pub fn load(path: &str, clock: SharedClock) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| format!("cannot open {path}: {err}"))?;
+
+        let mut reports = Vec::new();
+        for (line_number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|err| format!("cannot read {path}: {err}"))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let report: PriceReport = serde_json::from_str(&line)
+                .map_err(|err| format!("{path}:{}: {err}", line_number + 1))?;
+            reports.push(report);
+        }
+
+        Ok(Self::new(reports, clock))
+    }
+
+    /// The recorded reports, in the order they were received
+    pub fn reports(&self) -> &[PriceReport] {
+        &self.reports
+    }
+
+    /// The token pair of the first report in the feed, if any
+    pub fn pair(&self) -> Option<(Token, Token)> {
+        self.reports
+            .first()
+            .map(|report| (report.base_token.clone(), report.quote_token.clone()))
+    }
+
+    /// The time spanned by the feed, from its first report to its last
+    pub fn duration(&self) -> Duration {
+        match (self.reports.first(), self.reports.last()) {
+            (Some(first), Some(last)) => Duration::from_millis(
+                last.local_timestamp.saturating_sub(first.local_timestamp) as u64,
+            ),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// The feed's reports for the given pair from the given exchange
+    pub fn reports_for(
+        &self,
+        base_token: &Token,
+        quote_token: &Token,
+        exchange: Exchange,
+    ) -> Vec<PriceReport> {
+        self.reports
+            .iter()
+            .filter(|report| {
+                &report.base_token == base_token
+                    && &report.quote_token == quote_token
+                    && report.exchange == Some(exchange)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Replay the feed's reports for the given pair and exchange onto the sender
+    ///
+    /// The gaps between reports are measured from the start of the feed as a whole, so that
+    /// the connections of a pair replay in step with one another. Once its reports are
+    /// exhausted the connection stays open but silent, as a live exchange that stopped
+    /// publishing would
+    pub fn replay(
+        &self,
+        base_token: Token,
+        quote_token: Token,
+        exchange: Exchange,
+        mut price_report_sender: RingSender<PriceReport>,
+    ) -> WorkerHandles {
+        let feed_start = self
+            .reports
+            .first()
+            .map(|report| report.local_timestamp)
+            .unwrap_or_default();
+        let reports = self.reports_for(&base_token, &quote_token, exchange);
+        let clock = self.clock.clone();
+
+        let worker_handle = tokio::spawn(async move {
+            let mut elapsed_ms = 0;
+            for mut report in reports.into_iter() {
+                let offset_ms = report.local_timestamp.saturating_sub(feed_start) as u64;
+                if offset_ms > elapsed_ms {
+                    clock
+                        .sleep(Duration::from_millis(offset_ms - elapsed_ms))
+                        .await;
+                    elapsed_ms = offset_ms;
+                }
+
+                report.local_timestamp = get_current_time();
+                price_report_sender
+                    .send(report)
+                    .map_err(|err| ExchangeConnectionError::ConnectionHangup(err.to_string()))?;
+            }
+
+            futures::future::pending::<Result<(), ExchangeConnectionError>>().await
+        });
+
+        vec![worker_handle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        clock::system_clock,
+        price_reporter::{exchanges::Exchange, reporter::PriceReport, tokens::Token},
+    };
+
+    use super::RecordedFeed;
+
+    /// Build a report of the given price from the given exchange at the given time
+    fn report(exchange: Exchange, price: f64, local_timestamp: u128) -> PriceReport {
+        PriceReport {
+            base_token: Token::from_addr("0x01"),
+            quote_token: Token::from_addr("0x02"),
+            exchange: Some(exchange),
+            midpoint_price: price,
+            local_timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that a feed is split by exchange and spans its first to its last report
+    #[test]
+    fn test_reports_for_exchange() {
+        let feed = RecordedFeed::new(
+            vec![
+                report(Exchange::Binance, 100.0, 1_000),
+                report(Exchange::Okx, 101.0, 1_500),
+                report(Exchange::Binance, 102.0, 3_000),
+            ],
+            system_clock(),
+        );
+
+        let (base, quote) = feed.pair().unwrap();
+        let binance = feed.reports_for(&base, &quote, Exchange::Binance);
+        assert_eq!(binance.len(), 2);
+        assert_eq!(binance[1].midpoint_price, 102.0);
+        assert!(feed
+            .reports_for(&quote, &base, Exchange::Binance)
+            .is_empty());
+        assert_eq!(feed.duration(), Duration::from_millis(2_000));
+    }
+}
@@ -10,12 +10,28 @@ use std::{
     iter::FromIterator,
     num::NonZeroUsize,
     sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use circuits::zk_gadgets::fixed_point::FixedPoint;
+
+use crate::{
+    system_bus::SystemBus,
+    types::{SystemBusMessage, EXCHANGE_HEALTH_TOPIC, PRICE_DEVIATION_TOPIC},
 };
 
 use super::{
+    aggregation::{AggregationMode, PriceHistory, PriceWindow},
+    breaker::{BreakerOutcome, PriceCircuitBreaker},
+    depth::{OrderBookDepth, OrderBookDepthReport},
     errors::ExchangeConnectionError,
-    exchanges::{get_current_time, Exchange, ExchangeConnection, ExchangeConnectionState},
-    tokens::Token,
+    exchanges::{
+        get_current_time, Exchange, ExchangeConnection, ExchangeConnectionState, PriceNormalizer,
+        UniswapV3Handler,
+    },
+    health::{ExchangeHealthReport, ExchangeHealthTracker},
+    quarantine::{DeviationQuarantine, QuarantineTransition},
+    tokens::{decimal_adjustment, Token},
     worker::PriceReporterManagerConfig,
 };
 
@@ -33,12 +49,59 @@ static MAX_DEVIATION: f64 = 0.02; // TODO: Refactor
 /// If an ExchangeConnection returns an Error, we try to restart it. After
 /// MAX_CONNECTION_FAILURES, we panic the relayer entirely.
 static MAX_CONNECTION_FAILURES: usize = 5;
+/// The delay before the UniswapV3 TWAP stream is restarted after a failure, in milliseconds. The
+/// TWAP is only a reference price, so failures are retried indefinitely rather than counted.
+static TWAP_RESTART_DELAY_MS: u64 = 30_000;
 
 /// Helper function to construct a RingChannel of size 1.
 fn new_ring_channel<T>() -> (RingSender<T>, RingReceiver<T>) {
     ring_channel::<T>(NonZeroUsize::new(1).unwrap())
 }
 
+/// The ring buffer senders for each streamed (AggregationMode, PriceWindow) pair.
+type AggregateSenders = HashMap<(AggregationMode, PriceWindow), Vec<RingSender<PriceReport>>>;
+
+/// Helper function to publish a set of ExchangeHealth transitions to the system bus.
+fn publish_health_changes(
+    system_bus: &SystemBus<SystemBusMessage>,
+    base_token: &Token,
+    quote_token: &Token,
+    changes: Vec<(Exchange, ExchangeHealthReport)>,
+) {
+    for (exchange, health) in changes.into_iter() {
+        system_bus.publish(
+            EXCHANGE_HEALTH_TOPIC.to_string(),
+            SystemBusMessage::ExchangeHealthChanged {
+                base_token: base_token.clone(),
+                quote_token: quote_token.clone(),
+                exchange,
+                health,
+            },
+        );
+    }
+}
+
+/// Helper function to publish a set of quarantine transitions to the system bus.
+fn publish_quarantine_changes(
+    system_bus: &SystemBus<SystemBusMessage>,
+    base_token: &Token,
+    quote_token: &Token,
+    changes: Vec<QuarantineTransition>,
+) {
+    for transition in changes.into_iter() {
+        system_bus.publish(
+            PRICE_DEVIATION_TOPIC.to_string(),
+            SystemBusMessage::PriceDeviationAlert {
+                base_token: base_token.clone(),
+                quote_token: quote_token.clone(),
+                exchange: transition.exchange,
+                deviation_bps: transition.deviation_bps,
+                quarantined: transition.quarantined,
+            },
+        );
+    }
+}
+
 /// The PriceReport is the universal format for price feeds from all external exchanges.
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -50,14 +113,70 @@ pub struct PriceReport {
     /// The Exchange that this PriceReport came from. If the PriceReport is a median aggregate,
     /// then the exchange is None.
     pub exchange: Option<Exchange>,
-    /// The midpoint price of the exchange's order book.
+    /// The midpoint price of the exchange's order book, in whole units of the quote Token per
+    /// whole unit of the base Token (see `exchanges::PriceNormalizer`). When converted for use in
+    /// circuits, the price is rounded down to `DEFAULT_PRECISION` (32) fractional bits.
     pub midpoint_price: f64,
+    /// The combined quantity (in units of the base Token) quoted at the best bid and offer, if
+    /// the exchange reports it. Used as the weight for VWAP aggregation.
+    #[serde(default)]
+    pub volume: Option<f64>,
+    /// The top levels of the exchange's order book, if the exchange reports them. Aggregate
+    /// PriceReports carry no depth; see `PriceReporter::peek_depth` for the aggregate depth.
+    #[serde(default)]
+    pub depth: Option<OrderBookDepth>,
     /// The time that this update was received by the relayer node.
     pub local_timestamp: u128,
     /// The time that this update was generated by the exchange, if available.
     pub reported_timestamp: Option<u128>,
 }
 
+impl PriceReport {
+    /// Returns the midpoint price as a FixedPoint, in whole units of the quote Token per whole
+    /// unit of the base Token, rounded down to `DEFAULT_PRECISION` fractional bits.
+    pub fn midpoint_fixed_point(&self) -> FixedPoint {
+        FixedPoint::from_f64_round_down(self.midpoint_price)
+    }
+
+    /// Returns the midpoint price as a FixedPoint in atomic units of the quote Token per atomic
+    /// unit of the base Token, the units of an order's price in the circuits, or None if the
+    /// decimals of either Token are unknown.
+    ///
+    /// Prices of pairs whose quote Token has fewer decimals than the base Token are small in
+    /// atomic units, so the rounding to `DEFAULT_PRECISION` fractional bits is coarser relative
+    /// to the price than in whole units.
+    pub fn atomic_midpoint_fixed_point(&self) -> Option<FixedPoint> {
+        let adjustment = decimal_adjustment(&self.base_token, &self.quote_token)?;
+        Some(FixedPoint::from_f64_round_down(
+            self.midpoint_price / adjustment,
+        ))
+    }
+}
+
+/// The decentralized reference price, read from the UniswapV3 pool oracle. The reference is not
+/// included in the median; it is reported alongside the individual Exchanges so that the median
+/// of the centralized Exchanges may be sanity-checked against it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecentralizedReferencePrice {
+    /// The UniswapV3 TWAP over the configured window.
+    pub twap_report: PriceReport,
+    /// The deviation of the current median from the TWAP, as a fraction. None if the median
+    /// cannot currently be computed.
+    pub median_deviation: Option<f64>,
+}
+
+/// The latest state of every ExchangeConnection, along with the decentralized reference price.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllExchangeStates {
+    /// The ExchangeConnectionState of each Exchange.
+    pub exchanges: HashMap<Exchange, ExchangeConnectionState>,
+    /// The decentralized reference price, if UniswapV3 supports the Token pair and a TWAP has
+    /// been read.
+    pub decentralized_reference: Option<DecentralizedReferencePrice>,
+}
+
 /// The state of the PriceReporter. The Nominal state means that enough ExchangeConnections are
 /// reporting recent prices, so it is OK to proceed with MPCs at the given median price.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -73,6 +192,10 @@ pub enum PriceReporterState {
     /// There has been too much deviation in the prices between the exchanges; holding off until
     /// prices stabilize. Includes the current deviation as a fraction.
     TooMuchDeviation(PriceReport, f64),
+    /// The median moved faster than the circuit breaker allows, and the move has not yet been
+    /// confirmed by enough Exchanges; matching proceeds at the held last stable median. Includes
+    /// the unconfirmed move of the median as a fraction.
+    Held(PriceReport, f64),
 }
 impl Display for PriceReporterState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -87,6 +210,9 @@ impl Display for PriceReporterState {
             PriceReporterState::TooMuchDeviation(price_report, _) => {
                 format!("TooMuchDeviation({:?})", price_report)
             }
+            PriceReporterState::Held(price_report, _) => {
+                format!("Held({:?})", price_report)
+            }
         };
         write!(f, "{}", fmt_str)
     }
@@ -112,8 +238,26 @@ pub struct PriceReporter {
     /// data from each ExchangeConnection, not too much variance, etc.), the median PriceReport
     /// will be sent to each each RingSender<PriceReport>.
     price_report_median_senders: Arc<RwLock<Vec<RingSender<PriceReport>>>>,
+    /// Thread-safe rate-of-change circuit breaker over the median. While the breaker is tripped,
+    /// the last stable median is streamed in place of the live median.
+    circuit_breaker: Arc<RwLock<PriceCircuitBreaker>>,
     /// The latest PriceReport for each Exchange. Used in order to .peek() at each data stream.
     price_report_exchanges_latest: Arc<RwLock<HashMap<Exchange, PriceReport>>>,
+    /// Thread-safe health tracker for each Exchange. Unhealthy Exchanges are excluded from the
+    /// median computation until they recover.
+    exchange_health: Arc<RwLock<ExchangeHealthTracker>>,
+    /// Thread-safe quarantine of Exchanges whose midpoint persistently deviates from the median
+    /// of the others. Quarantined Exchanges are excluded from the median computation.
+    deviation_quarantine: Arc<RwLock<DeviationQuarantine>>,
+    /// Thread-safe trailing history of PriceReports from each Exchange, from which the TWAP and
+    /// VWAP are computed.
+    price_history: Arc<RwLock<PriceHistory>>,
+    /// Thread-safe map of senders for each streamed TWAP/VWAP. Aggregates are recomputed and sent
+    /// whenever a new PriceReport arrives.
+    price_report_aggregate_senders: Arc<RwLock<AggregateSenders>>,
+    /// The latest UniswapV3 TWAP, read from the pool oracle. Used as a decentralized reference
+    /// price, and never included in the median.
+    decentralized_reference: Arc<RwLock<Option<PriceReport>>>,
 }
 
 impl PriceReporter {
@@ -121,15 +265,14 @@ impl PriceReporter {
     pub fn new(base_token: Token, quote_token: Token, config: PriceReporterManagerConfig) -> Self {
         // Pre-compute some data about the Token pair.
         let is_named = base_token.is_named() && quote_token.is_named();
-        let (base_token_decimals, quote_token_decimals) =
-            (base_token.get_decimals(), quote_token.get_decimals());
 
         // We create an aggregate RingBuffer<PriceReport> that unifies all ExchangeConnection
         // streams.
         let (all_price_reports_sender, mut all_price_reports_receiver) =
             new_ring_channel::<PriceReport>();
 
-        // Derive the supported exchanges.
+        // Derive the supported exchanges. Each quote Token has its own median, so only the
+        // Exchanges that list the pair in the quote Token itself are included.
         let base_token_supported_exchanges = base_token.supported_exchanges();
         let quote_token_supported_exchanges = quote_token.supported_exchanges();
         let supported_exchanges = base_token_supported_exchanges
@@ -138,6 +281,15 @@ impl PriceReporter {
             .filter(|exchange| config.exchange_configured(*exchange))
             .collect::<HashSet<Exchange>>();
 
+        // Track the health of each exchange; scores are updated as PriceReports and connection
+        // errors arrive
+        let exchange_health = Arc::new(RwLock::new(ExchangeHealthTracker::new(
+            &supported_exchanges
+                .iter()
+                .copied()
+                .collect::<Vec<Exchange>>(),
+        )));
+
         // Connect to all the exchanges, and pipe the price report stream from each connection into
         // the aggregate ring buffer created previously.
 
@@ -186,6 +338,7 @@ impl PriceReporter {
             let quote_token = quote_token.clone();
             let all_price_reports_sender = all_price_reports_sender.clone();
             let config_clone = config.clone();
+            let exchange_health_clone = exchange_health.clone();
 
             let exchange_connection_worker_handle = tokio::spawn(async move {
                 let mut num_failures = 0;
@@ -196,19 +349,29 @@ impl PriceReporter {
                             exchange, MAX_CONNECTION_FAILURES
                         );
                     }
-                    let base_token = base_token.clone();
-                    let quote_token = quote_token.clone();
-                    let all_price_reports_sender = all_price_reports_sender.clone();
-                    let config_clone = config_clone.clone();
                     let exchange_connection_handle = tokio::spawn(connect_to_exchange(
-                        base_token,
-                        quote_token,
+                        base_token.clone(),
+                        quote_token.clone(),
                         exchange,
-                        all_price_reports_sender,
-                        config_clone,
+                        all_price_reports_sender.clone(),
+                        config_clone.clone(),
                     ));
                     let exchange_connection_error =
                         exchange_connection_handle.await.unwrap().unwrap_err();
+
+                    // Count the failure against the exchange's health
+                    let health_changes = {
+                        let mut locked_health = exchange_health_clone.write().unwrap();
+                        locked_health.record_error(exchange, get_current_time());
+                        locked_health.evaluate(get_current_time())
+                    }; // locked_health released
+                    publish_health_changes(
+                        &config_clone.system_bus,
+                        &base_token,
+                        &quote_token,
+                        health_changes,
+                    );
+
                     println!(
                         "Restarting the ExchangeConnection to {}, as it failed with {}. \
                         There are now {} failures.",
@@ -241,19 +404,26 @@ impl PriceReporter {
                 .insert(*exchange, vec![]);
         }
         let price_report_exchanges_senders_clone = price_report_exchanges_senders.clone();
+        let normalizers: HashMap<Exchange, PriceNormalizer> = active_exchanges
+            .iter()
+            .map(|exchange| {
+                let normalizer = PriceNormalizer::new(*exchange, &base_token, &quote_token);
+                if !normalizer.is_exact() {
+                    log::warn!(
+                        "decimals of {base_token}-{quote_token} unknown, {exchange} prices are not normalized"
+                    );
+                }
+                (*exchange, normalizer)
+            })
+            .collect();
         tokio::spawn(async move {
             loop {
                 // Receive a new (Exchange, PriceReport) from the aggregate stream.
                 let mut price_report = all_price_reports_receiver.next().await.unwrap();
                 let exchange = price_report.exchange.unwrap();
-                // If the exchange is UniswapV3 and the token pair is Named, adjust the reported price
-                // for the decimals.
-                if exchange == Exchange::UniswapV3 && is_named {
-                    price_report.midpoint_price *= 10_f64.powf(
-                        f64::from(base_token_decimals.unwrap())
-                            - f64::from(quote_token_decimals.unwrap()),
-                    );
-                }
+                // Normalize the report into whole units, so that it is comparable across
+                // Exchanges.
+                normalizers[&exchange].normalize(&mut price_report);
                 // Send this PriceReport to every RingSender<PriceReport> in
                 // price_report_exchanges_senders.
                 for sender in price_report_exchanges_senders_clone
@@ -322,6 +492,28 @@ impl PriceReporter {
         let base_token_clone = base_token.clone();
         let quote_token_clone = quote_token.clone();
         let active_exchanges_clone = active_exchanges.clone();
+        let exchange_health_clone = exchange_health.clone();
+        let system_bus = config.system_bus.clone();
+
+        // Only Named pairs are quoted by enough venues for a move to be confirmed, so the breaker
+        // is not applied to Unnamed pairs
+        let circuit_breaker = Arc::new(RwLock::new(PriceCircuitBreaker::new(
+            config.circuit_breaker_config(&base_token, &quote_token),
+        )));
+        let circuit_breaker_clone = circuit_breaker.clone();
+
+        // As with the breaker, Unnamed pairs have too few venues to judge a deviation against
+        let deviation_quarantine = Arc::new(RwLock::new(DeviationQuarantine::new(
+            config.deviation_quarantine,
+        )));
+        let deviation_quarantine_clone = deviation_quarantine.clone();
+
+        // The median loop also feeds the trailing price history, and streams any TWAP/VWAP that a
+        // consumer has subscribed to
+        let price_history = Arc::new(RwLock::new(PriceHistory::new()));
+        let price_report_aggregate_senders = Arc::new(RwLock::new(AggregateSenders::new()));
+        let price_history_clone = price_history.clone();
+        let price_report_aggregate_senders_clone = price_report_aggregate_senders.clone();
 
         tokio::spawn(async move {
             let mut current_price_reports = HashMap::<Exchange, PriceReport>::new();
@@ -331,25 +523,167 @@ impl PriceReporter {
             loop {
                 futures::select! {
                     price_report = price_report_median_receivers.next() => {
-                        current_price_reports.insert(price_report.clone().unwrap().exchange.unwrap(), price_report.unwrap());
-                        let price_reporter_state = Self::compute_price_reporter_state(base_token_clone.clone(), quote_token_clone.clone(), current_price_reports.clone());
-                        if let PriceReporterState::Nominal(price_report) = price_reporter_state {
+                        let price_report = price_report.unwrap();
+                        current_price_reports.insert(price_report.exchange.unwrap(), price_report.clone());
+
+                        // Re-score the exchanges and exclude unhealthy feeds from the median
+                        let (health_changes, healthy_price_reports) = {
+                            let mut locked_health = exchange_health_clone.write().unwrap();
+                            locked_health.record_report(&price_report);
+                            locked_health.record_deviations(&current_price_reports);
+                            let changes = locked_health.evaluate(get_current_time());
+
+                            let healthy_price_reports = if is_named {
+                                locked_health.filter_healthy(&current_price_reports)
+                            } else {
+                                current_price_reports.clone()
+                            };
+                            (changes, healthy_price_reports)
+                        }; // locked_health released
+                        publish_health_changes(&system_bus, &base_token_clone, &quote_token_clone, health_changes);
+
+                        // Quarantine feeds that persistently deviate from the others
+                        let healthy_price_reports = if is_named {
+                            let (quarantine_changes, unquarantined_price_reports) = {
+                                let mut locked_quarantine = deviation_quarantine_clone.write().unwrap();
+                                let changes = locked_quarantine.evaluate(&healthy_price_reports, get_current_time());
+                                (changes, locked_quarantine.filter_quarantined(&healthy_price_reports))
+                            }; // locked_quarantine released
+                            publish_quarantine_changes(&system_bus, &base_token_clone, &quote_token_clone, quarantine_changes);
+                            unquarantined_price_reports
+                        } else {
+                            healthy_price_reports
+                        };
+
+                        let aggregate_exchanges = healthy_price_reports.keys().copied().collect::<Vec<Exchange>>();
+                        let price_reporter_state = Self::compute_price_reporter_state(base_token_clone.clone(), quote_token_clone.clone(), healthy_price_reports.clone());
+                        if let PriceReporterState::Nominal(median_report) = price_reporter_state {
+                            // Pass the median through the circuit breaker; while tripped, the held
+                            // stable price is streamed in its place
+                            let matching_report = if is_named {
+                                match circuit_breaker_clone.write().unwrap().record_median(median_report, &healthy_price_reports, get_current_time()) {
+                                    BreakerOutcome::Pass(report) | BreakerOutcome::Held(report, _) => report,
+                                }
+                            } else {
+                                median_report
+                            };
+
                             for sender in price_report_median_senders_clone.write().unwrap().iter_mut() {
-                                sender.send(price_report.clone()).unwrap();
+                                sender.send(matching_report.clone()).unwrap();
                             }
                         }
+
+                        // Update the trailing history and stream the subscribed aggregates
+                        {
+                            let mut locked_history = price_history_clone.write().unwrap();
+                            locked_history.record_report(&price_report);
+                            for ((mode, window), senders) in price_report_aggregate_senders_clone.write().unwrap().iter_mut() {
+                                if senders.is_empty() {
+                                    continue;
+                                }
+
+                                if let PriceReporterState::Nominal(aggregate_report) = Self::compute_aggregate_state(
+                                    base_token_clone.clone(),
+                                    quote_token_clone.clone(),
+                                    &locked_history,
+                                    *mode,
+                                    *window,
+                                    &aggregate_exchanges,
+                                ) {
+                                    for sender in senders.iter_mut() {
+                                        sender.send(aggregate_report.clone()).unwrap();
+                                    }
+                                }
+                            }
+                        } // locked_history released
                     }
                 }
             }
         });
 
+        // If UniswapV3 supports the Token pair, poll the pool oracle for the reference TWAP.
+        let decentralized_reference = Arc::new(RwLock::new(None));
+        if active_exchanges.contains(&Exchange::UniswapV3) {
+            let base_token = base_token.clone();
+            let quote_token = quote_token.clone();
+            let config = config.clone();
+            let decentralized_reference = decentralized_reference.clone();
+            let twap_normalizer =
+                PriceNormalizer::new(Exchange::UniswapV3, &base_token, &quote_token);
+            tokio::spawn(async move {
+                loop {
+                    let (sender, mut receiver) = new_ring_channel::<PriceReport>();
+                    let twap_stream = UniswapV3Handler::start_twap_stream(
+                        base_token.clone(),
+                        quote_token.clone(),
+                        sender,
+                        config.clone(),
+                    )
+                    .await;
+
+                    // The stream's worker holds the only sender, so the receiver closes when
+                    // the worker fails
+                    let twap_stream_error = match twap_stream {
+                        Ok(_worker_handles) => {
+                            while let Some(mut twap_report) = receiver.next().await {
+                                twap_normalizer.normalize(&mut twap_report);
+                                *decentralized_reference.write().unwrap() = Some(twap_report);
+                            }
+                            ExchangeConnectionError::ConnectionHangup(
+                                "UniswapV3 TWAP sender was dropped".to_string(),
+                            )
+                        }
+                        Err(err) => err,
+                    };
+
+                    println!(
+                        "Restarting the UniswapV3 TWAP stream, as it failed with {}.",
+                        twap_stream_error
+                    );
+                    tokio::time::sleep(Duration::from_millis(TWAP_RESTART_DELAY_MS)).await;
+                }
+            });
+        }
+
         Self {
             base_token,
             quote_token,
             supported_exchanges,
             price_report_exchanges_senders,
             price_report_median_senders,
+            circuit_breaker,
             price_report_exchanges_latest,
+            exchange_health,
+            deviation_quarantine,
+            price_history,
+            price_report_aggregate_senders,
+            decentralized_reference,
+        }
+    }
+
+    /// Given the trailing PriceReport history, compute the PriceReporterState of the requested
+    /// aggregate over the given Exchanges.
+    fn compute_aggregate_state(
+        base_token: Token,
+        quote_token: Token,
+        price_history: &PriceHistory,
+        mode: AggregationMode,
+        window: PriceWindow,
+        exchanges: &[Exchange],
+    ) -> PriceReporterState {
+        let now = get_current_time();
+        match price_history.aggregate(mode, window, exchanges, now) {
+            Some(aggregate_price) => PriceReporterState::Nominal(PriceReport {
+                base_token,
+                quote_token,
+                exchange: None,
+                midpoint_price: aggregate_price,
+                volume: None,
+                depth: None,
+                local_timestamp: now,
+                reported_timestamp: None,
+            }),
+            None => PriceReporterState::NotEnoughDataReported(0),
         }
     }
 
@@ -408,6 +742,8 @@ impl PriceReporter {
             quote_token,
             exchange: None,
             midpoint_price: median_midpoint_price as f64,
+            volume: None,
+            depth: None,
             local_timestamp: median_local_timestamp as u128,
             reported_timestamp: median_reported_timestamp,
         };
@@ -437,13 +773,32 @@ impl PriceReporter {
     }
 
     /// Returns if this PriceReport is of a "Named" token pair (as opposed to an "Unnamed" pair).
-    /// If the PriceReport is Named, then the prices are denominated in USD and largely derived
-    /// from centralized exchanges. If the PriceReport is Unnamed, then the prices are derived from
+    /// If the PriceReport is Named, then the prices are denominated in the quote Token and largely
+    /// derived from centralized exchanges. If the PriceReport is Unnamed, then the prices are derived from
     /// UniswapV3 and do not do fixed-point decimals adjustment.
     pub fn _is_named(&self) -> bool {
         self.base_token.is_named() && self.quote_token.is_named()
     }
 
+    /// The latest PriceReport of each Exchange that may contribute to the median; for Named
+    /// pairs, unhealthy and quarantined Exchanges are excluded.
+    fn usable_price_reports(&self) -> HashMap<Exchange, PriceReport> {
+        let latest_price_reports = self.price_report_exchanges_latest.read().unwrap().clone();
+        if !self._is_named() {
+            return latest_price_reports;
+        }
+
+        let healthy_price_reports = self
+            .exchange_health
+            .read()
+            .unwrap()
+            .filter_healthy(&latest_price_reports);
+        self.deviation_quarantine
+            .read()
+            .unwrap()
+            .filter_quarantined(&healthy_price_reports)
+    }
+
     /// Creates a new RingReceiver<PriceReport> that streams all raw PriceReports from the
     /// specified Exchange.
     pub fn create_new_exchange_receiver(&self, exchange: Exchange) -> RingReceiver<PriceReport> {
@@ -458,24 +813,144 @@ impl PriceReporter {
     /// Creates a new RingReceiver<PriceReport> that streams all valid median PriceReports.
     /// Importantly, note that this RingReceiver only streams _valid_ medians: If there is not
     /// enough data or too much deviation, then streaming will be paused until the
-    /// ExchangeConnections recover to a Nominal state.
+    /// ExchangeConnections recover to a Nominal state. While the circuit breaker is tripped, the
+    /// held stable median is streamed instead.
     pub fn create_new_median_receiver(&self) -> RingReceiver<PriceReport> {
         let (sender, receiver) = new_ring_channel::<PriceReport>();
         (*self.price_report_median_senders.write().unwrap()).push(sender);
         receiver
     }
 
-    /// Non-blocking report of the latest PriceReporterState for the median.
-    pub fn peek_median(&self) -> PriceReporterState {
-        Self::compute_price_reporter_state(
+    /// Creates a new RingReceiver<PriceReport> that streams the given TWAP or VWAP each time a new
+    /// PriceReport arrives. As with the median, the aggregate is only streamed while it can be
+    /// computed from the trailing window.
+    pub fn create_new_aggregate_receiver(
+        &self,
+        mode: AggregationMode,
+        window: PriceWindow,
+    ) -> RingReceiver<PriceReport> {
+        let (sender, receiver) = new_ring_channel::<PriceReport>();
+        self.price_report_aggregate_senders
+            .write()
+            .unwrap()
+            .entry((mode, window))
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    /// Non-blocking report of the TWAP over the given trailing window.
+    pub fn peek_twap(&self, window: PriceWindow) -> PriceReporterState {
+        self.peek_aggregate(AggregationMode::Twap, window)
+    }
+
+    /// Non-blocking report of the VWAP over the given trailing window.
+    pub fn peek_vwap(&self, window: PriceWindow) -> PriceReporterState {
+        self.peek_aggregate(AggregationMode::Vwap, window)
+    }
+
+    /// Non-blocking report of the given aggregate over the given trailing window. As with the
+    /// median, unhealthy and quarantined Exchanges are excluded.
+    pub fn peek_aggregate(&self, mode: AggregationMode, window: PriceWindow) -> PriceReporterState {
+        let exchanges = self
+            .usable_price_reports()
+            .into_keys()
+            .collect::<Vec<Exchange>>();
+
+        Self::compute_aggregate_state(
             self.base_token.clone(),
             self.quote_token.clone(),
-            self.price_report_exchanges_latest.read().unwrap().clone(),
+            &self.price_history.read().unwrap(),
+            mode,
+            window,
+            &exchanges,
         )
     }
 
+    /// Non-blocking report of the latest PriceReporterState for the median. Unhealthy and
+    /// quarantined Exchanges are excluded from the median, and the held stable median is reported
+    /// while the circuit breaker is tripped.
+    pub fn peek_median(&self) -> PriceReporterState {
+        let price_reports = self.usable_price_reports();
+
+        let price_reporter_state = Self::compute_price_reporter_state(
+            self.base_token.clone(),
+            self.quote_token.clone(),
+            price_reports,
+        );
+
+        match (
+            price_reporter_state,
+            self.circuit_breaker.read().unwrap().held_state(),
+        ) {
+            (PriceReporterState::Nominal(_), Some((stable_report, price_move)))
+                if self._is_named() =>
+            {
+                PriceReporterState::Held(stable_report, price_move)
+            }
+            (price_reporter_state, _) => price_reporter_state,
+        }
+    }
+
+    /// Non-blocking report of the latest order book depth of each Exchange that reports depth,
+    /// along with their aggregate. As with the median, unhealthy and quarantined Exchanges are
+    /// excluded.
+    pub fn peek_depth(&self) -> OrderBookDepthReport {
+        let price_reports = self.usable_price_reports();
+
+        let exchanges = price_reports
+            .into_iter()
+            .filter_map(|(exchange, price_report)| Some((exchange, price_report.depth?)))
+            .collect::<HashMap<Exchange, OrderBookDepth>>();
+        let aggregate = OrderBookDepth::aggregate(exchanges.values());
+
+        OrderBookDepthReport {
+            base_token: self.base_token.clone(),
+            quote_token: self.quote_token.clone(),
+            exchanges,
+            aggregate,
+        }
+    }
+
+    /// Non-blocking report of the latest health score for all exchanges.
+    pub fn peek_exchange_health(&self) -> HashMap<Exchange, ExchangeHealthReport> {
+        self.exchange_health
+            .read()
+            .unwrap()
+            .health_reports(get_current_time())
+    }
+
+    /// Non-blocking report of the latest ExchangeConnectionState for all exchanges, along with the
+    /// decentralized reference price and the deviation of the median from it.
+    pub fn peek_all_exchanges(&self) -> AllExchangeStates {
+        let decentralized_reference =
+            self.decentralized_reference
+                .read()
+                .unwrap()
+                .clone()
+                .map(|twap_report| {
+                    let median_deviation = match self.peek_median() {
+                        PriceReporterState::Nominal(median_report)
+                        | PriceReporterState::Held(median_report, _) => Some(
+                            (median_report.midpoint_price - twap_report.midpoint_price).abs()
+                                / twap_report.midpoint_price,
+                        ),
+                        _ => None,
+                    };
+                    DecentralizedReferencePrice {
+                        twap_report,
+                        median_deviation,
+                    }
+                });
+
+        AllExchangeStates {
+            exchanges: self.peek_exchange_states(),
+            decentralized_reference,
+        }
+    }
+
     /// Non-blocking report of the latest ExchangeConnectionState for all exchanges.
-    pub fn peek_all_exchanges(&self) -> HashMap<Exchange, ExchangeConnectionState> {
+    fn peek_exchange_states(&self) -> HashMap<Exchange, ExchangeConnectionState> {
         let price_reports = self.price_report_exchanges_latest.read().unwrap().clone();
         let mut exchange_connection_states = HashMap::<Exchange, ExchangeConnectionState>::new();
         for (exchange, price_report) in price_reports {
@@ -498,13 +973,17 @@ impl PriceReporter {
         self.supported_exchanges.clone()
     }
 
-    /// Get all Exchanges that are currently in a healthy state.
+    /// Get all Exchanges that are currently in a healthy state; i.e. reporting data and not
+    /// excluded by the health tracker.
     pub fn get_healthy_exchanges(&self) -> HashSet<Exchange> {
+        let locked_health = self.exchange_health.read().unwrap();
         HashSet::from_iter(
-            self.peek_all_exchanges()
+            self.peek_exchange_states()
                 .iter()
                 .filter_map(|(exchange, state)| match state {
-                    ExchangeConnectionState::Nominal(_) => Some(exchange),
+                    ExchangeConnectionState::Nominal(_) if locked_health.is_healthy(exchange) => {
+                        Some(exchange)
+                    }
                     _ => None,
                 })
                 .copied(),
@@ -6,19 +6,28 @@
 //! Tokens fall under two different categories: "Named Tokens" that have centralized and
 //! decentralized exchange price feed support, and "Unnamed Tokens" that only have decentralized
 //! exchange price feed support. We explicitly name all Named Tokens below, as the relayer need to
-//! manually map these ERC-20 addresses into websocket subscription requests.
+//! manually map these ERC-20 addresses into websocket subscription requests. Further Named Tokens
+//! may be listed in the on-chain token registry, which is merged in at runtime (see
+//! `update_token_registry`), or in the operator's token remap file (see `update_token_remap`).
 //!
 //! In general, Named Tokens use all exchanges where they are listed, whereas Unnamed Tokens only
 //! use Uniswap V3 for the price feed.
 use bimap::BiMap;
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     fmt::{self, Display},
+    sync::{Mutex, RwLock},
 };
 
 use super::exchanges::{Exchange, ALL_EXCHANGES};
 
+/// Error message emitted when the token registry lock is poisoned
+const ERR_TOKEN_REGISTRY_POISONED: &str = "token registry lock poisoned";
+/// Error message emitted when the token registry sources lock is poisoned
+const ERR_REGISTRY_SOURCES_POISONED: &str = "token registry sources lock poisoned";
+
 /// A helper enum to describe the state of each ticker on each Exchange. Same means that the ERC-20
 /// and Exchange tickers are the same, Renamed means that the Exchange ticker is different from the
 /// underlying ERC-20, and Unsupported  means that the asset is not supported on the Exchange.
@@ -32,14 +41,10 @@ enum ExchangeTicker {
     Unsupported,
 }
 
-// We populate three global heap-allocated structs for convenience and metadata lookup. The first is
-// a bidirectinal map between the ERC-20 contract address and the ERC-20 ticker. The second is a
-// HashMap between the ERC-20 contract address and the number of decimals (fixed-point offset). The
-// third is a HashMap between the ERC-20 ticker and each Exchange's expected name for each ticker.
-
-/// The raw ERC-20 data to be parsed as heap-allocated global structs. The layout of ERC20_DATA is
+/// The raw ERC-20 data used to seed the token registry; these local definitions override any
+/// conflicting entries in the on-chain registry. The layout of ERC20_DATA is
 /// (ERC-20 Address, Decimals, ERC-20 Ticker, Binance Ticker, Coinbase Ticker, Kraken Ticker, Okx
-/// Ticker).
+/// Ticker, Binance.US Ticker, Bybit Ticker).
 static ERC20_DATA: &[(
     &str,
     u8,
@@ -48,6 +53,8 @@ static ERC20_DATA: &[(
     ExchangeTicker,
     ExchangeTicker,
     ExchangeTicker,
+    ExchangeTicker,
+    ExchangeTicker,
 )] = &[
     /* L1 */
     (
@@ -58,6 +65,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Renamed("BTC"),
         ExchangeTicker::Renamed("BTC"),
         ExchangeTicker::Renamed("BTC"),
+        ExchangeTicker::Renamed("BTC"),
+        ExchangeTicker::Renamed("BTC"),
     ),
     (
         "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
@@ -67,6 +76,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Renamed("ETH"),
         ExchangeTicker::Renamed("ETH"),
         ExchangeTicker::Renamed("ETH"),
+        ExchangeTicker::Renamed("ETH"),
+        ExchangeTicker::Renamed("ETH"),
     ),
     (
         "0xb8c77482e45f1f44de1745f52c74426c631bdd52",
@@ -76,6 +87,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x7d1afa7b718fb893db30a3abc0cfc608aacfebb0",
@@ -85,6 +98,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x4e15361fd6b4bb609fa63c81a2be19d873717870",
@@ -94,6 +109,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x6810e776880c02933d47db1b9fc05908e5386b96",
@@ -103,6 +120,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     /* LSDs */
     (
@@ -113,6 +132,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0x5a98fcbea516cf06857215779fd812ca3bef1b32",
@@ -122,6 +143,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     /* Stables */
     (
@@ -132,6 +155,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Renamed("USD"),
         ExchangeTicker::Renamed("USD"),
         ExchangeTicker::Renamed("USDT"),
+        ExchangeTicker::Renamed("USD"),
+        ExchangeTicker::Same,
     ),
     (
         "0xdac17f958d2ee523a2206206994597c13d831ec7",
@@ -141,6 +166,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x4fabb145d64652a948d72533023f6e7a623c7c53",
@@ -150,6 +177,19 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+    ),
+    (
+        "0x6b175474e89094c44da98b954eedeac495271d0f",
+        18,
+        "DAI",
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     /* Oracles */
     (
@@ -160,6 +200,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0x514910771af9ca656af840dff83e8264ecf986ca",
@@ -169,6 +211,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     /* DeFi Trading */
     (
@@ -179,6 +223,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xd533a949740bb3306d119cc777fa900ba034cd52",
@@ -188,6 +234,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x92d6c1e31e14520e676a687f0a93788b716beff5",
@@ -197,6 +245,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     (
         "0x6b3595068778dd592e39a122f4f5a5cf09c90fe2",
@@ -206,6 +256,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x111111111117dc0aa78b770fa6a738034120c302",
@@ -215,6 +267,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xba100000625a3754423978a60c9317c58a424e3d",
@@ -224,6 +278,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0xb3999f658c0391d94a37f7ff328f3fec942bcadc",
@@ -233,6 +289,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     (
         "0xbc396689893d065f41bc2c6ecbee5e0085233447",
@@ -242,6 +300,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     (
         "0x4691937a7508860f876c9c0a2a617e7d9e945d4b",
@@ -251,6 +311,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     (
         "0xe41d2489571d322189246dafa5ebde1f4699f498",
@@ -260,6 +322,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     /* DeFi Lending */
     (
@@ -270,6 +334,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xc00e94cb662c3520282e6f5717214004a7f26888",
@@ -279,6 +345,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x9f8f72aa9304c8b593d555f12ef6589cc3a579a2",
@@ -288,6 +356,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x0bc529c00c6401aef6d220be8c6ea1667f6ad93e",
@@ -297,6 +367,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x090185f2135308bad17527004364ebcc2d37e5f6",
@@ -306,6 +378,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     /* DeFi Lending Undercollateralized */
     (
@@ -316,6 +390,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0x33349b282065b0284d756f0577fb39c158f935e6",
@@ -325,6 +401,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     /* DeFi Other */
     (
@@ -335,6 +413,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x221657776846890989a759ba2973e427dff5c9bb",
@@ -344,6 +424,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0x77777feddddffc19ff86db637967013e6c6a116c",
@@ -353,6 +435,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     /* Bridges */
     (
@@ -363,6 +447,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0xaf5191b0de278c7286d6c7cc6ab6bb8a73ba2cd6",
@@ -372,6 +458,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     (
         "0x4a220e6096b25eadb88358cb44068a3248254675",
@@ -381,6 +469,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     /* L2s */
     (
@@ -391,6 +481,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x42bbfa2e77757c645eeaad1655e0911a7553efbc",
@@ -400,6 +492,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     /* NFTs */
     (
@@ -410,6 +504,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xbb0e17ef65f82ab018d8edd776e8dd940327b28b",
@@ -419,6 +515,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xf629cbd94d3791c9250152bd8dfbdf380e2a3b9c",
@@ -428,6 +526,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xba5bde662c17e2adff1075610382b9b691296350",
@@ -437,6 +537,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     /* Misc */
     (
@@ -447,6 +549,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x7a58c0be72be218b41c608b7fe7c5bb630736c71",
@@ -456,6 +560,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Unsupported,
         ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
     ),
     (
         "0xd26114cd6ee289accf82350c8d8487fedb8a0c07",
@@ -465,6 +571,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xc944e90c64b2c07662a292be6244bdf05cda44a7",
@@ -474,6 +582,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0xc18360217d8f7ab5e7c516566761ea12ce7f9d72",
@@ -483,6 +593,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x0f5d2fb29fb7d3cfee444a200298f468908cc942",
@@ -492,6 +604,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x15d4c048f83bd7e37d49ea4c83a07267ec4203da",
@@ -501,6 +615,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
     (
         "0x31c8eacbffdd875c74b94b077895bd78cf1e64a3",
@@ -510,6 +626,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0x18aaa7115705e8be94bffebde57af9bfc265b998",
@@ -519,6 +637,8 @@ static ERC20_DATA: &[(
         ExchangeTicker::Unsupported,
         ExchangeTicker::Same,
         ExchangeTicker::Unsupported,
+        ExchangeTicker::Same,
+        ExchangeTicker::Unsupported,
     ),
     (
         "0x0d8775f648430679a709e98d2b0cb6250d2887ef",
@@ -528,71 +648,203 @@ static ERC20_DATA: &[(
         ExchangeTicker::Same,
         ExchangeTicker::Same,
         ExchangeTicker::Same,
+        ExchangeTicker::Same,
+        ExchangeTicker::Same,
     ),
 ];
 
+/// The Exchanges that are indexed by ticker rather than by ERC-20 address, in the order that their
+/// tickers appear in ERC20_DATA.
+pub(super) const CENTRALIZED_EXCHANGES: [Exchange; 6] = [
+    Exchange::Binance,
+    Exchange::Coinbase,
+    Exchange::Kraken,
+    Exchange::Okx,
+    Exchange::BinanceUs,
+    Exchange::Bybit,
+];
+
+/// The ERC-20 tickers of the tokens that pairs may be quoted in, in order of precedence. A pair of
+/// two quote tokens is quoted in the one that appears first, e.g. WETH-USDC rather than USDC-WETH,
+/// so that orders on either side of the pair meet in the same book.
+pub const QUOTE_TOKEN_TICKERS: [&str; 4] = ["USDC", "USDT", "DAI", "WETH"];
+
 lazy_static! {
-    static ref ADDR_TICKER_BIMAP: BiMap<String, String> = {
-        let mut addr_ticker_bimap = BiMap::<String, String>::new();
-        for (addr, _, ticker, _, _, _, _) in ERC20_DATA.iter() {
-            addr_ticker_bimap.insert(String::from(*addr), String::from(*ticker));
+    /// The global token registry. This is seeded from the local ERC20_DATA and may be extended at
+    /// runtime by entries read from the on-chain token registry.
+    static ref TOKEN_REGISTRY: RwLock<TokenRegistry> =
+        RwLock::new(TokenRegistry::from_entries(local_registry_entries()));
+    /// The dynamic entries the token registry is built from, retained so that the registry may
+    /// be rebuilt when either source changes
+    static ref REGISTRY_SOURCES: Mutex<RegistrySources> = Mutex::new(RegistrySources::default());
+}
+
+/// The dynamic sources of token registry entries, merged with the local ERC20_DATA
+#[derive(Clone, Debug, Default)]
+struct RegistrySources {
+    /// The entries read from the on-chain token registry
+    onchain_entries: Vec<TokenRegistryEntry>,
+    /// The entries read from the operator's token remap file
+    remap_entries: Vec<TokenRegistryEntry>,
+}
+
+impl RegistrySources {
+    /// Merge the sources with the local ERC20_DATA into a registry. On-chain entries are
+    /// overridden by the local ERC20_DATA, which is in turn overridden by the remap file
+    fn build_registry(&self) -> TokenRegistry {
+        let mut entries = self.onchain_entries.clone();
+        entries.extend(local_registry_entries());
+        entries.extend(self.remap_entries.iter().cloned());
+
+        TokenRegistry::from_entries(entries)
+    }
+}
+
+/// A single entry in the token registry, describing an ERC-20 token and its ticker on each
+/// centralized Exchange.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenRegistryEntry {
+    /// The ERC-20 address of the token
+    pub addr: String,
+    /// The ERC-20 `decimals` field
+    pub decimals: u8,
+    /// The ERC-20 ticker
+    pub ticker: String,
+    /// The ticker used by each Exchange that lists the token
+    pub exchange_tickers: HashMap<Exchange, String>,
+}
+
+/// The lookup tables backing the Token abstraction. The first is a bidirectional map between the
+/// ERC-20 contract address and the ERC-20 ticker. The second is a HashMap between the ERC-20
+/// contract address and the number of decimals (fixed-point offset). The third is a HashMap between
+/// the ERC-20 ticker and each Exchange's expected name for each ticker.
+#[derive(Clone, Debug, Default)]
+struct TokenRegistry {
+    /// The bidirectional map between ERC-20 address and ERC-20 ticker
+    addr_ticker_bimap: BiMap<String, String>,
+    /// The map between ERC-20 address and decimals
+    addr_decimals_map: HashMap<String, u8>,
+    /// The map from Exchange to the map between ERC-20 ticker and Exchange ticker
+    exchange_tickers: HashMap<Exchange, HashMap<String, String>>,
+}
+
+impl TokenRegistry {
+    /// Build a registry from a list of entries; later entries take precedence over earlier ones
+    fn from_entries(entries: Vec<TokenRegistryEntry>) -> Self {
+        let mut registry = Self::default();
+        for entry in entries.into_iter() {
+            registry.insert(entry);
         }
-        addr_ticker_bimap
-    };
-    static ref ADDR_DECIMALS_MAP: HashMap<String, u8> = {
-        let mut addr_decimals_map = HashMap::<String, u8>::new();
-        for (addr, decimals, _, _, _, _, _) in ERC20_DATA.iter() {
-            addr_decimals_map.insert(String::from(*addr), *decimals);
+        registry
+    }
+
+    /// Insert an entry into the registry, replacing any existing entry with the same address or
+    /// ticker
+    fn insert(&mut self, entry: TokenRegistryEntry) {
+        let addr = entry.addr.to_lowercase();
+
+        // Drop the Exchange tickers of any entry that this one displaces
+        let displaced_tickers = [
+            self.addr_ticker_bimap.get_by_left(&addr).cloned(),
+            Some(entry.ticker.clone()),
+        ];
+        for exchange_tickers in self.exchange_tickers.values_mut() {
+            for ticker in displaced_tickers.iter().flatten() {
+                exchange_tickers.remove(ticker);
+            }
         }
-        addr_decimals_map
-    };
-    static ref EXCHANGE_TICKERS: HashMap<Exchange, HashMap<String, String>> = {
-        let mut exchange_tickers = HashMap::<Exchange, HashMap<String, String>>::new();
-        for exchange in [
-            Exchange::Binance,
-            Exchange::Coinbase,
-            Exchange::Kraken,
-            Exchange::Okx,
-        ] {
-            exchange_tickers.insert(exchange, HashMap::<String, String>::new());
+
+        self.addr_ticker_bimap
+            .insert(addr.clone(), entry.ticker.clone());
+        self.addr_decimals_map.insert(addr, entry.decimals);
+        for (exchange, exchange_ticker) in entry.exchange_tickers.into_iter() {
+            self.exchange_tickers
+                .entry(exchange)
+                .or_default()
+                .insert(entry.ticker.clone(), exchange_ticker);
         }
-        for (_, _, erc20_ticker, binance_ticker, coinbase_ticker, kraken_ticker, okx_ticker) in
-            ERC20_DATA.iter()
-        {
-            let process_ticker = move |ticker: ExchangeTicker| -> Option<&'static str> {
-                match ticker {
-                    ExchangeTicker::Same => Some(erc20_ticker),
-                    ExchangeTicker::Renamed(ticker) => Some(ticker),
-                    ExchangeTicker::Unsupported => None,
+    }
+}
+
+/// Parse the local ERC20_DATA into registry entries
+fn local_registry_entries() -> Vec<TokenRegistryEntry> {
+    ERC20_DATA
+        .iter()
+        .map(
+            |(
+                addr,
+                decimals,
+                erc20_ticker,
+                binance_ticker,
+                coinbase_ticker,
+                kraken_ticker,
+                okx_ticker,
+                binance_us_ticker,
+                bybit_ticker,
+            )| {
+                let exchange_tickers = CENTRALIZED_EXCHANGES
+                    .iter()
+                    .zip([
+                        *binance_ticker,
+                        *coinbase_ticker,
+                        *kraken_ticker,
+                        *okx_ticker,
+                        *binance_us_ticker,
+                        *bybit_ticker,
+                    ])
+                    .filter_map(|(exchange, ticker)| {
+                        let exchange_ticker = match ticker {
+                            ExchangeTicker::Same => *erc20_ticker,
+                            ExchangeTicker::Renamed(ticker) => ticker,
+                            ExchangeTicker::Unsupported => return None,
+                        };
+                        Some((*exchange, String::from(exchange_ticker)))
+                    })
+                    .collect();
+
+                TokenRegistryEntry {
+                    addr: String::from(*addr),
+                    decimals: *decimals,
+                    ticker: String::from(*erc20_ticker),
+                    exchange_tickers,
                 }
-            };
-            if let Some(binance_ticker) = process_ticker(*binance_ticker) {
-                exchange_tickers
-                    .get_mut(&Exchange::Binance)
-                    .unwrap()
-                    .insert(String::from(*erc20_ticker), String::from(binance_ticker));
-            }
-            if let Some(coinbase_ticker) = process_ticker(*coinbase_ticker) {
-                exchange_tickers
-                    .get_mut(&Exchange::Coinbase)
-                    .unwrap()
-                    .insert(String::from(*erc20_ticker), String::from(coinbase_ticker));
-            }
-            if let Some(kraken_ticker) = process_ticker(*kraken_ticker) {
-                exchange_tickers
-                    .get_mut(&Exchange::Kraken)
-                    .unwrap()
-                    .insert(String::from(*erc20_ticker), String::from(kraken_ticker));
-            }
-            if let Some(okx_ticker) = process_ticker(*okx_ticker) {
-                exchange_tickers
-                    .get_mut(&Exchange::Okx)
-                    .unwrap()
-                    .insert(String::from(*erc20_ticker), String::from(okx_ticker));
-            }
-        }
-        exchange_tickers
-    };
+            },
+        )
+        .collect()
+}
+
+/// Replace the on-chain portion of the token registry with the given entries. The local
+/// ERC20_DATA is applied on top of the on-chain entries, so that local definitions always override
+/// the on-chain registry.
+///
+/// Returns the number of tokens in the merged registry.
+pub fn update_token_registry(onchain_entries: Vec<TokenRegistryEntry>) -> usize {
+    let mut sources = REGISTRY_SOURCES
+        .lock()
+        .expect(ERR_REGISTRY_SOURCES_POISONED);
+    sources.onchain_entries = onchain_entries;
+    replace_registry(sources.build_registry())
+}
+
+/// Replace the entries read from the operator's token remap file. Remapped entries override both
+/// the on-chain registry and the local ERC20_DATA, so that an operator may list a new asset or
+/// correct an existing one without recompiling the relayer.
+///
+/// Returns the number of tokens in the merged registry.
+pub fn update_token_remap(remap_entries: Vec<TokenRegistryEntry>) -> usize {
+    let mut sources = REGISTRY_SOURCES
+        .lock()
+        .expect(ERR_REGISTRY_SOURCES_POISONED);
+    sources.remap_entries = remap_entries;
+    replace_registry(sources.build_registry())
+}
+
+/// Swap in a rebuilt registry, returning the number of tokens it holds
+fn replace_registry(registry: TokenRegistry) -> usize {
+    let num_tokens = registry.addr_ticker_bimap.len();
+    *TOKEN_REGISTRY.write().expect(ERR_TOKEN_REGISTRY_POISONED) = registry;
+
+    num_tokens
 }
 
 /// The core Token abstraction, used for unambiguous definition of an ERC-20 asset.
@@ -616,14 +868,23 @@ impl Token {
         }
     }
 
+    /// Given an order mint, i.e. the ERC-20 contract address as an integer, returns a new Token.
+    pub fn from_mint(mint: &BigUint) -> Self {
+        Self {
+            addr: format!("0x{:040x}", mint),
+        }
+    }
+
     /// Given an ERC-20 ticker, returns a new Token.
     pub fn _from_ticker(ticker: &str) -> Self {
-        let addr = ADDR_TICKER_BIMAP
+        let addr = TOKEN_REGISTRY
+            .read()
+            .expect(ERR_TOKEN_REGISTRY_POISONED)
+            .addr_ticker_bimap
             .get_by_right(&String::from(ticker))
+            .cloned()
             .expect("Ticker is not supported; specify unnamed token by ERC-20 address using from_addr instead.");
-        Self {
-            addr: addr.to_string(),
-        }
+        Self { addr }
     }
 
     /// Returns the ERC-20 address.
@@ -633,15 +894,37 @@ impl Token {
 
     /// Returns the ERC-20 ticker, if available. Note that it is OK if certain Tickers do not have
     /// any ERC-20 ticker, as we support long-tail assets.
-    pub fn get_ticker(&self) -> Option<&str> {
-        ADDR_TICKER_BIMAP
+    pub fn get_ticker(&self) -> Option<String> {
+        TOKEN_REGISTRY
+            .read()
+            .expect(ERR_TOKEN_REGISTRY_POISONED)
+            .addr_ticker_bimap
             .get_by_left(&self.addr)
-            .map(|ticker| &**ticker)
+            .cloned()
     }
 
     /// Returns the ERC-20 `decimals` field, if available.
     pub fn get_decimals(&self) -> Option<u8> {
-        ADDR_DECIMALS_MAP.get(self.get_addr()).copied()
+        TOKEN_REGISTRY
+            .read()
+            .expect(ERR_TOKEN_REGISTRY_POISONED)
+            .addr_decimals_map
+            .get(self.get_addr())
+            .copied()
+    }
+
+    /// Converts an amount in atomic units of the Token, i.e. an ERC-20 balance, into whole units
+    /// of the Token, if the decimals are available.
+    pub fn convert_to_decimal(&self, amount: u128) -> Option<f64> {
+        let decimals = self.get_decimals()?;
+        Some(amount as f64 / 10_f64.powi(i32::from(decimals)))
+    }
+
+    /// Converts an amount in whole units of the Token into atomic units, rounding down, if the
+    /// decimals are available.
+    pub fn convert_from_decimal(&self, amount: f64) -> Option<u128> {
+        let decimals = self.get_decimals()?;
+        Some((amount * 10_f64.powi(i32::from(decimals))).floor() as u128)
     }
 
     /// Returns true if the Token has a Renegade-native ticker.
@@ -649,23 +932,51 @@ impl Token {
         self.get_ticker().is_some()
     }
 
+    /// Returns the precedence of the Token as a quote currency, lower values taking precedence, or
+    /// None if pairs may not be quoted in the Token.
+    pub fn quote_precedence(&self) -> Option<usize> {
+        let ticker = self.get_ticker()?;
+        QUOTE_TOKEN_TICKERS
+            .iter()
+            .position(|quote_ticker| *quote_ticker == ticker)
+    }
+
+    /// Returns true if pairs may be quoted in the Token.
+    pub fn is_quote(&self) -> bool {
+        self.quote_precedence().is_some()
+    }
+
     /// Returns the set of Exchanges that support this token.
+    ///
+    /// An Exchange that lists the Token only under the ERC-20 ticker of another registered token
+    /// (e.g. USDC under USDT) does not support it: its markets are quoted in the other token, so
+    /// including them would blend the medians of pairs with different quotes.
     pub fn supported_exchanges(&self) -> HashSet<Exchange> {
         let mut supported_exchanges = HashSet::<Exchange>::new();
         supported_exchanges.insert(Exchange::UniswapV3);
-        if !self.is_named() {
-            return supported_exchanges;
-        }
+        let ticker = match self.get_ticker() {
+            Some(ticker) => ticker,
+            None => return supported_exchanges,
+        };
+
+        let registry = TOKEN_REGISTRY.read().expect(ERR_TOKEN_REGISTRY_POISONED);
         for exchange in ALL_EXCHANGES.iter() {
             if *exchange == Exchange::UniswapV3 {
                 continue;
             }
-            if EXCHANGE_TICKERS
+
+            let exchange_ticker = match registry
+                .exchange_tickers
                 .get(exchange)
-                .unwrap()
-                .get(self.get_ticker().unwrap())
-                .is_some()
+                .and_then(|tickers| tickers.get(&ticker))
             {
+                Some(exchange_ticker) => exchange_ticker,
+                None => continue,
+            };
+
+            let is_proxy = *exchange_ticker != ticker
+                && registry.addr_ticker_bimap.contains_right(exchange_ticker);
+            if !is_proxy {
                 supported_exchanges.insert(*exchange);
             }
         }
@@ -677,16 +988,18 @@ impl Token {
     /// address. If the ticker is not supported by the Exchange, returns None.
     pub fn get_exchange_ticker(&self, exchange: Exchange) -> String {
         // If there is not a Renegade-native ticker, then the token must be Unnamed.
-        if !self.is_named() {
+        let ticker = self.get_ticker().unwrap_or_else(|| {
             panic!(
                 "Tried to get_exchange_ticker({}) for an unnamed Token.",
                 exchange
-            );
-        }
-        EXCHANGE_TICKERS
+            )
+        });
+        TOKEN_REGISTRY
+            .read()
+            .expect(ERR_TOKEN_REGISTRY_POISONED)
+            .exchange_tickers
             .get(&exchange)
-            .unwrap()
-            .get(self.get_ticker().unwrap())
+            .and_then(|tickers| tickers.get(&ticker))
             .cloned()
             .unwrap_or_else(|| {
                 panic!(
@@ -697,3 +1010,98 @@ impl Token {
             })
     }
 }
+
+/// Returns the factor that converts a price in atomic units of the quote Token per atomic unit of
+/// the base Token into whole units of the quote Token per whole unit of the base Token, i.e.
+/// 10^(base decimals - quote decimals), or None if the decimals of either Token are unknown.
+pub fn decimal_adjustment(base: &Token, quote: &Token) -> Option<f64> {
+    let base_decimals = i32::from(base.get_decimals()?);
+    let quote_decimals = i32::from(quote.get_decimals()?);
+    Some(10_f64.powi(base_decimals - quote_decimals))
+}
+
+/// Validate that an order may be placed on the given pair: the quote must be a quote token, and a
+/// pair of two quote tokens must be quoted in the one that takes precedence
+pub fn validate_pair(base: &Token, quote: &Token) -> Result<(), String> {
+    if base == quote {
+        return Err(format!("base and quote are the same token {}", base));
+    }
+
+    let quote_precedence = quote.quote_precedence().ok_or_else(|| {
+        format!(
+            "{} is not a quote token, expected one of {}",
+            quote,
+            QUOTE_TOKEN_TICKERS.join(", ")
+        )
+    })?;
+    if let Some(base_precedence) = base.quote_precedence() && base_precedence < quote_precedence {
+        return Err(format!("pair must be quoted in {}, not {}", base, quote));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use crate::price_reporter::exchanges::Exchange;
+
+    use super::{decimal_adjustment, validate_pair, Token};
+
+    /// Tests that an order mint resolves to the Token at the same address
+    #[test]
+    fn test_from_mint() {
+        let dai_addr = "0x6b175474e89094c44da98b954eedeac495271d0f";
+        let mint = BigUint::parse_bytes(&dai_addr.as_bytes()[2..], 16).unwrap();
+
+        let token = Token::from_mint(&mint);
+        assert_eq!(token, Token::from_addr(dai_addr));
+        assert_eq!(token.get_ticker(), Some("DAI".to_string()));
+        assert!(token.is_quote());
+    }
+
+    /// Tests that pairs quoted in each quote token are valid, and that pairs of two quote tokens
+    /// must be quoted in the one that takes precedence
+    #[test]
+    fn test_validate_pair() {
+        let wbtc = Token::_from_ticker("WBTC");
+        let weth = Token::_from_ticker("WETH");
+        for quote in ["USDC", "USDT", "DAI", "WETH"] {
+            assert!(validate_pair(&wbtc, &Token::_from_ticker(quote)).is_ok());
+        }
+
+        assert!(validate_pair(&weth, &Token::_from_ticker("DAI")).is_ok());
+        assert!(validate_pair(&Token::_from_ticker("DAI"), &weth).is_err());
+        assert!(validate_pair(&weth, &weth).is_err());
+        assert!(validate_pair(&weth, &Token::_from_ticker("LDO")).is_err());
+    }
+
+    /// Tests that an Exchange listing a token only under another token's ticker does not support it
+    #[test]
+    fn test_proxied_exchanges_unsupported() {
+        let usdc = Token::_from_ticker("USDC").supported_exchanges();
+        assert!(usdc.contains(&Exchange::Coinbase));
+        assert!(!usdc.contains(&Exchange::Binance));
+        assert!(!usdc.contains(&Exchange::Okx));
+
+        let usdt = Token::_from_ticker("USDT").supported_exchanges();
+        assert!(usdt.contains(&Exchange::Binance));
+        assert!(usdt.contains(&Exchange::Okx));
+    }
+
+    /// Tests conversions between atomic and whole units of a Token and pair
+    #[test]
+    fn test_decimal_conversions() {
+        let wbtc = Token::_from_ticker("WBTC");
+        let usdc = Token::_from_ticker("USDC");
+
+        assert_eq!(wbtc.convert_to_decimal(150_000_000), Some(1.5));
+        assert_eq!(usdc.convert_from_decimal(1.5), Some(1_500_000));
+        assert_eq!(decimal_adjustment(&wbtc, &usdc), Some(100.));
+
+        let unnamed = Token::from_addr("0x0000000000000000000000000000000000000001");
+        assert_eq!(unnamed.convert_to_decimal(1), None);
+        assert_eq!(decimal_adjustment(&wbtc, &unnamed), None);
+    }
+}
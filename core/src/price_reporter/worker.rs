@@ -1,36 +1,76 @@
 //! Defines the Worker logic for the PriceReporterManger, which simply dispatches jobs to the
 //! PriceReporterManagerExecutor.
-use std::thread::{self, JoinHandle};
-use tokio::{runtime::Builder as TokioBuilder, sync::mpsc::UnboundedReceiver as TokioReceiver};
+use ed25519_dalek::Keypair;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+use tokio::runtime::Builder as TokioBuilder;
 
 use crate::{
-    default_wrapper::DefaultWrapper, system_bus::SystemBus, types::SystemBusMessage,
+    default_wrapper::DefaultWrapper, job_queue::JobQueueReceiver,
+    starknet_client::client::StarknetClient, system_bus::SystemBus, types::SystemBusMessage,
     worker::Worker, CancelChannel,
 };
 
 use super::{
+    breaker::CircuitBreakerConfig,
     errors::PriceReporterManagerError,
-    exchanges::Exchange,
+    exchanges::{Exchange, ExchangeRegion, UniswapFeeTier},
+    history::PriceHistoryConfig,
     jobs::PriceReporterManagerJob,
     manager::{PriceReporterManager, PriceReporterManagerExecutor},
+    quarantine::DeviationQuarantineConfig,
+    replay::RecordedFeed,
+    tokens::Token,
 };
 
 /// The number of threads backing the price reporter manager
 const PRICE_REPORTER_MANAGER_NUM_THREADS: usize = 2;
 
 /// The config passed from the coordinator to the PriceReporterManager
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct PriceReporterManagerConfig {
     /// The global system bus
     pub(crate) system_bus: SystemBus<SystemBusMessage>,
     /// The receiver for jobs from other workers
-    pub(crate) job_receiver: DefaultWrapper<Option<TokioReceiver<PriceReporterManagerJob>>>,
+    pub(crate) job_receiver: DefaultWrapper<Option<JobQueueReceiver<PriceReporterManagerJob>>>,
+    /// The region the relayer is deployed in; Exchanges that do not serve the region are never
+    /// connected to
+    pub(crate) exchange_region: ExchangeRegion,
+    /// The Exchanges the operator allows prices to be streamed from, if `None` every Exchange
+    /// permitted in the region is used
+    pub(crate) exchange_allowlist: Option<HashSet<Exchange>>,
     /// The coinbase API key that the price reporter may use
     pub(crate) coinbase_api_key: Option<String>,
     /// The coinbase API secret that the price reporter may use
     pub(crate) coinbase_api_secret: Option<String>,
     /// The ethereum RPC node websocket addresses for on-chain data
     pub(crate) eth_websocket_addr: Option<String>,
+    /// The UniswapV3 fee tier to read prices from, if `None` the pool with the highest TVL is used
+    pub(crate) uniswap_fee_tier: Option<UniswapFeeTier>,
+    /// The window over which the UniswapV3 reference TWAP is computed, in seconds
+    pub(crate) uniswap_twap_window_secs: u32,
+    /// The starknet client used to read the on-chain token registry
+    pub(crate) starknet_client: StarknetClient,
+    /// The address of the on-chain token registry contract, if `None` only the local token
+    /// definitions are used
+    pub(crate) token_registry_address: Option<String>,
+    /// The operator's token remap file, re-read on SIGHUP or when it changes
+    pub(crate) token_remap_file: Option<String>,
+    /// The circuit breaker thresholds for each (base, quote) ticker pair; pairs without an
+    /// entry use the default thresholds
+    pub(crate) circuit_breakers: HashMap<(String, String), CircuitBreakerConfig>,
+    /// The thresholds at which an Exchange deviating from the others is quarantined
+    pub(crate) deviation_quarantine: DeviationQuarantineConfig,
+    /// The retention and on-disk location of the historical price store
+    pub(crate) price_history: PriceHistoryConfig,
+    /// A recorded feed to replay in place of connecting to the exchanges, if any
+    pub(crate) recorded_feed: Option<RecordedFeed>,
+    /// The cluster keypair that each published median is signed with, if price signing is
+    /// enabled
+    pub(crate) price_signing_keypair: Option<Arc<Keypair>>,
     /// The channel on which the coordinator may mandate that the price reporter manager cancel its
     /// execution
     pub(crate) cancel_channel: CancelChannel,
@@ -38,11 +78,18 @@ pub struct PriceReporterManagerConfig {
 
 impl PriceReporterManagerConfig {
     /// Returns true if the necessary configuration information is present
-    /// for a given exchange
+    /// for a given exchange, and the exchange may be used in this deployment
     ///
     /// For example; we do not connect to Coinbase if a Coinbase API key
-    /// and secret is not provided
+    /// and secret is not provided. A replayed exchange needs no configuration
     pub(crate) fn exchange_configured(&self, exchange: Exchange) -> bool {
+        if !self.exchange_permitted(exchange) {
+            return false;
+        }
+        if self.recorded_feed.is_some() {
+            return true;
+        }
+
         match exchange {
             Exchange::Coinbase => {
                 self.coinbase_api_key.is_some() && self.coinbase_api_secret.is_some()
@@ -51,6 +98,30 @@ impl PriceReporterManagerConfig {
             _ => true,
         }
     }
+
+    /// Returns true if the exchange serves the deployment's region and is allowlisted by the
+    /// operator, so that medians are only computed over legally accessible venues
+    pub(crate) fn exchange_permitted(&self, exchange: Exchange) -> bool {
+        self.exchange_region.permits(exchange)
+            && self
+                .exchange_allowlist
+                .as_ref()
+                .map(|allowlist| allowlist.contains(&exchange))
+                .unwrap_or(true)
+    }
+
+    /// Returns the circuit breaker thresholds configured for the given token pair
+    pub(crate) fn circuit_breaker_config(
+        &self,
+        base_token: &Token,
+        quote_token: &Token,
+    ) -> CircuitBreakerConfig {
+        base_token
+            .get_ticker()
+            .zip(quote_token.get_ticker())
+            .and_then(|pair| self.circuit_breakers.get(&pair).copied())
+            .unwrap_or_default()
+    }
 }
 
 impl Worker for PriceReporterManager {
@@ -62,13 +133,21 @@ impl Worker for PriceReporterManager {
             config,
             manager_executor_handle: None,
             manager_runtime: None,
+            returned_job_receiver: Arc::new(Mutex::new(None)),
         })
     }
 
     fn is_recoverable(&self) -> bool {
         // Recovery for each PriceReporter is implemented via Error propagation; all panics in the
-        // PriceReporterManager are unrecoverable
-        false
+        // PriceReporterManager are unrecoverable. A manager cancelled by the coordinator hands
+        // back its job queue, and may be re-allocated around it
+        self.returned_job_receiver.lock().unwrap().is_some()
+    }
+
+    fn is_pausable(&self) -> bool {
+        // Pausing the manager lets the operator take it down while upstream exchange APIs
+        // are under maintenance
+        true
     }
 
     fn name(&self) -> String {
@@ -93,6 +172,7 @@ impl Worker for PriceReporterManager {
             self.config.job_receiver.take().unwrap(),
             self.config.clone(),
             self.config.cancel_channel.clone(),
+            self.returned_job_receiver.clone(),
             self.config.system_bus.clone(),
         )?;
 
@@ -120,7 +200,25 @@ impl Worker for PriceReporterManager {
         Ok(())
     }
 
+    fn recover(self) -> Self
+    where
+        Self: Sized,
+    {
+        // The cancel signal that stopped the previous executor is marked as seen, so that it
+        // does not cancel the recovered one
+        let mut config = self.config;
+        config.job_receiver = self.returned_job_receiver.lock().unwrap().take().into();
+        config.cancel_channel.borrow_and_update();
+
+        Self::new(config).unwrap()
+    }
+
     fn cleanup(&mut self) -> Result<(), Self::Error> {
-        unimplemented!();
+        // The coordinator cleans up from within its own runtime, where a runtime may not
+        // be dropped with a blocking shutdown
+        if let Some(runtime) = self.manager_runtime.take() {
+            runtime.shutdown_background();
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,144 @@
+//! Defines the dead-letter queue of proof jobs that the proof manager abandoned
+//!
+//! A proof job that exceeds its time budget cannot be forcibly stopped, so the proof
+//! manager abandons it; the job's requester is notified by the closing of its response
+//! channel and a record of the job is placed here for operators to inspect. Records hold
+//! only the job's metadata; witnesses are secret and are never retained
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::log;
+
+/// The maximum number of records retained in the dead-letter queue, older records
+/// are evicted first
+const MAX_DEAD_LETTERS: usize = 100;
+/// The dead-letter depth at or above which each new record raises an alert
+pub(crate) const DEAD_LETTER_ALERT_DEPTH: usize = 5;
+
+/// The reason a proof job was moved to the dead-letter queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeadLetterReason {
+    /// The job did not finish within its time budget and was abandoned
+    TimedOut,
+    /// The thread proving the job panicked
+    Panicked,
+}
+
+/// A record of a single abandoned proof job
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The statement the job was proving, e.g. `VALID COMMITMENTS`
+    pub job_type: String,
+    /// Why the job was abandoned
+    pub reason: DeadLetterReason,
+    /// The time budget the job was allotted, in milliseconds
+    pub budget_ms: u64,
+    /// The timestamp (in seconds) at which the job was abandoned
+    pub timestamp: u64,
+}
+
+/// A bounded queue of abandoned proof jobs, shared between the proof manager and
+/// the API server
+#[derive(Clone, Debug, Default)]
+pub struct DeadLetterQueue {
+    /// The abandoned jobs, oldest first
+    dead_letters: Arc<RwLock<VecDeque<DeadLetter>>>,
+}
+
+impl DeadLetterQueue {
+    /// Create a new, empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of records in the queue
+    pub fn depth(&self) -> usize {
+        self.dead_letters.read().unwrap().len()
+    }
+
+    /// Get a copy of the records in the queue, oldest first
+    pub fn get_dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.read().unwrap().iter().cloned().collect()
+    }
+
+    /// Record an abandoned job, alerting if the queue has grown past the alert depth
+    pub fn push(&self, job_type: String, reason: DeadLetterReason, budget_ms: u64) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("negative timestamp")
+            .as_secs();
+
+        let depth = {
+            let mut locked_dead_letters = self.dead_letters.write().unwrap();
+            locked_dead_letters.push_back(DeadLetter {
+                job_type: job_type.clone(),
+                reason,
+                budget_ms,
+                timestamp,
+            });
+            if locked_dead_letters.len() > MAX_DEAD_LETTERS {
+                locked_dead_letters.pop_front();
+            }
+
+            locked_dead_letters.len()
+        }; // locked_dead_letters released
+
+        if depth >= DEAD_LETTER_ALERT_DEPTH {
+            log::error!(
+                "proof manager dead-letter queue at depth {depth}, most recently abandoned {job_type} job ({reason:?})"
+            );
+        } else {
+            log::warn!("proof manager abandoned {job_type} job ({reason:?})");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeadLetterQueue, DeadLetterReason, MAX_DEAD_LETTERS};
+
+    /// Tests that records are shared between clones of the queue
+    #[test]
+    fn test_shared_queue() {
+        let queue = DeadLetterQueue::new();
+        let api_handle = queue.clone();
+
+        queue.push(
+            "VALID COMMITMENTS".to_string(),
+            DeadLetterReason::TimedOut,
+            1_000, /* budget_ms */
+        );
+
+        let dead_letters = api_handle.get_dead_letters();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].reason, DeadLetterReason::TimedOut);
+    }
+
+    /// Tests that the queue evicts its oldest records once full
+    #[test]
+    fn test_queue_bounded() {
+        let queue = DeadLetterQueue::new();
+        queue.push(
+            "VALID WALLET CREATE".to_string(),
+            DeadLetterReason::Panicked,
+            1_000, /* budget_ms */
+        );
+        for _ in 0..MAX_DEAD_LETTERS {
+            queue.push(
+                "VALID COMMITMENTS".to_string(),
+                DeadLetterReason::TimedOut,
+                1_000, /* budget_ms */
+            );
+        }
+
+        assert_eq!(queue.depth(), MAX_DEAD_LETTERS);
+        assert!(queue
+            .get_dead_letters()
+            .iter()
+            .all(|dead_letter| dead_letter.job_type == "VALID COMMITMENTS"));
+    }
+}
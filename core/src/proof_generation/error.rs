@@ -2,13 +2,19 @@
 
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
+use crate::error::{ErrorCode, ErrorCoded};
+
 /// The abstract error type the proof manager emits
 #[derive(Clone, Debug)]
 pub enum ProofManagerError {
+    /// Error reading or writing the proof cache
+    Cache(String),
     /// The coordinator cancelled the proof manager's execution
     Cancelled(String),
     /// The job queue has been closed, recv fails
     JobQueueClosed(String),
+    /// Error reading or writing the job journal
+    Journal(String),
     /// Error proving a statement
     Prover(String),
     /// An error receiving on a channel
@@ -17,10 +23,29 @@ pub enum ProofManagerError {
     Response(String),
     /// Error setting up the proof generation manager
     Setup(String),
+    /// A proof failed verification
+    Verifier(String),
 }
 
+impl std::error::Error for ProofManagerError {}
 impl Display for ProofManagerError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         write!(f, "{:?}", self)
     }
 }
+
+impl ErrorCoded for ProofManagerError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ProofManagerError::Cache(_) => ErrorCode::Storage,
+            ProofManagerError::Cancelled(_) => ErrorCode::Cancelled,
+            ProofManagerError::JobQueueClosed(_) => ErrorCode::Internal,
+            ProofManagerError::Journal(_) => ErrorCode::Storage,
+            ProofManagerError::Prover(_) => ErrorCode::Proof,
+            ProofManagerError::RecvError(_) => ErrorCode::Internal,
+            ProofManagerError::Response(_) => ErrorCode::Internal,
+            ProofManagerError::Setup(_) => ErrorCode::Setup,
+            ProofManagerError::Verifier(_) => ErrorCode::Proof,
+        }
+    }
+}
@@ -6,21 +6,52 @@
 
 use circuits::{
     types::{fee::Fee, keychain::KeyChain},
+    verify_singleprover_proof,
     zk_circuits::{
         valid_commitments::{ValidCommitmentsStatement, ValidCommitmentsWitnessCommitment},
         valid_match_encryption::{
             ValidMatchEncryptionStatement, ValidMatchEncryptionWitness,
             ValidMatchEncryptionWitnessCommitment,
         },
+        valid_settle::ValidSettleWitnessCommitment,
         valid_wallet_create::{ValidWalletCreateCommitment, ValidWalletCreateStatement},
+        valid_wallet_update::{ValidWalletUpdateStatement, ValidWalletUpdateWitnessCommitment},
     },
 };
 use curve25519_dalek::scalar::Scalar;
+use hmac_sha256::Hash;
 use mpc_bulletproof::r1cs::R1CSProof;
 use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use tokio::sync::oneshot::Sender;
 
-use crate::{types::SizedValidCommitmentsWitness, MAX_BALANCES, MAX_FEES, MAX_ORDERS};
+use crate::{
+    job_queue::{BoundedJob, JobOverflowPolicy},
+    types::{
+        SizedValidCommitmentsWitness, SizedValidSettle, SizedValidSettleStatement,
+        SizedValidSettleWitness, SizedValidWalletUpdateWitness,
+    },
+    MAX_BALANCES, MAX_FEES, MAX_ORDERS,
+};
+
+use super::error::ProofManagerError;
+
+/// The time budget for a proof of `VALID WALLET CREATE`
+const VALID_WALLET_CREATE_BUDGET_MS: u64 = 30_000; // 30 seconds
+/// The time budget for a proof of `VALID COMMITMENTS`
+const VALID_COMMITMENTS_BUDGET_MS: u64 = 60_000; // 1 minute
+/// The time budget for a proof of `VALID MATCH ENCRYPTION`
+const VALID_MATCH_ENCRYPTION_BUDGET_MS: u64 = 60_000; // 1 minute
+/// The time budget for a proof of `VALID WALLET UPDATE`
+const VALID_WALLET_UPDATE_BUDGET_MS: u64 = 60_000; // 1 minute
+/// The time budget for a proof of `VALID SETTLE`
+const VALID_SETTLE_BUDGET_MS: u64 = 120_000; // 2 minutes
 
 // ----------------------
 // | Proof Return Types |
@@ -68,6 +99,36 @@ pub struct ValidMatchEncryptBundle {
     pub proof: R1CSProof,
 }
 
+/// The response type for a request to generate a proof of `VALID WALLET UPDATE`
+#[derive(Clone, Debug)]
+pub struct ValidWalletUpdateBundle {
+    /// A commitment to the witness type of `VALID WALLET UPDATE`
+    pub commitment: ValidWalletUpdateWitnessCommitment<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
+    /// The statement (public variables) used to prove `VALID WALLET UPDATE`
+    pub statement: ValidWalletUpdateStatement,
+    /// The proof itself
+    pub proof: R1CSProof,
+}
+
+/// The response type for a request to generate a proof of `VALID SETTLE`
+#[derive(Clone, Debug)]
+pub struct ValidSettleBundle {
+    /// A commitment to the witness type of `VALID SETTLE`
+    pub commitment: ValidSettleWitnessCommitment<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
+    /// The statement (public variables) used to prove `VALID SETTLE`
+    pub statement: SizedValidSettleStatement,
+    /// The proof itself
+    pub proof: R1CSProof,
+}
+
+impl ValidSettleBundle {
+    /// Verify the proof of `VALID SETTLE` against its statement and witness commitment
+    pub fn verify(self) -> Result<(), ProofManagerError> {
+        verify_singleprover_proof::<SizedValidSettle>(self.statement, self.commitment, self.proof)
+            .map_err(|err| ProofManagerError::Verifier(err.to_string()))
+    }
+}
+
 /// The bundle returned by the proof generation module
 #[derive(Clone, Debug)]
 #[allow(clippy::large_enum_variant, clippy::enum_variant_names)]
@@ -79,6 +140,10 @@ pub enum ProofBundle {
     ValidCommitments(ValidCommitmentsBundle),
     /// A witness commitment, statement, and proof of `VALID MATCH ENCRYPTION`
     ValidMatchEncryption(ValidMatchEncryptBundle),
+    /// A witness commitment, statement, and proof of `VALID WALLET UPDATE`
+    ValidWalletUpdate(ValidWalletUpdateBundle),
+    /// A witness commitment, statement, and proof of `VALID SETTLE`
+    ValidSettle(ValidSettleBundle),
 }
 
 /// Unsafe cast implementations, will panic if type is incorrect
@@ -118,17 +183,107 @@ impl From<ProofBundle> for ValidMatchEncryptBundle {
     }
 }
 
+impl From<ProofBundle> for ValidWalletUpdateBundle {
+    fn from(bundle: ProofBundle) -> Self {
+        if let ProofBundle::ValidWalletUpdate(b) = bundle {
+            b
+        } else {
+            panic!(
+                "Proof bundle is not of type ValidWalletUpdate: {:?}",
+                bundle
+            )
+        }
+    }
+}
+
+impl From<ProofBundle> for ValidSettleBundle {
+    fn from(bundle: ProofBundle) -> Self {
+        if let ProofBundle::ValidSettle(b) = bundle {
+            b
+        } else {
+            panic!("Proof bundle is not of type ValidSettle: {:?}", bundle)
+        }
+    }
+}
+
+/// The priority of a job in the proof manager's queue; higher priority jobs are
+/// dequeued first, and jobs of equal priority in the order they were enqueued
+///
+/// Variants are declared lowest priority first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ProofJobPriority {
+    /// Proofs that nobody is waiting on, e.g. warming up proofs at startup or
+    /// refreshing them after the Merkle root changes
+    Background,
+    /// Proofs that settle a completed handshake
+    Settlement,
+    /// Proofs of wallet updates a user is waiting on
+    UserInitiated,
+}
+
+/// A token with which the requester of a proof may cancel it
+///
+/// A job cancelled before it starts is never run; a job cancelled while proving is
+/// abandoned, and its result discarded
+#[derive(Clone, Debug, Default)]
+pub struct ProofCancellationToken(Arc<AtomicBool>);
+
+impl ProofCancellationToken {
+    /// Constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the job
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the job has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 /// Represents a job enqueued in the proof manager's work queue
 #[derive(Debug)]
 pub struct ProofManagerJob {
     /// The type of job being requested
     pub type_: ProofJob,
+    /// The priority of the job
+    pub priority: ProofJobPriority,
+    /// The token on which the requester may cancel the job, if it is cancellable
+    pub cancellation: Option<ProofCancellationToken>,
     /// The response channel to send the proof back along
     pub response_channel: Sender<ProofBundle>,
 }
 
+impl ProofManagerJob {
+    /// Whether the requester has cancelled the job
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .map(ProofCancellationToken::is_cancelled)
+            .unwrap_or(false)
+    }
+}
+
+impl BoundedJob for ProofManagerJob {
+    /// Every requester awaits the proof on the job's response channel, so a job that does not
+    /// fit is returned to its requester rather than left to hang
+    fn overflow_policy(&self) -> JobOverflowPolicy {
+        JobOverflowPolicy::Reject
+    }
+}
+
+/// The key under which a job is deduplicated, a hash of the job's contents
+///
+/// Two jobs share a key exactly when they prove the same statement from the same witness
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProofJobKey([u8; 32]);
+
 /// The job type and parameterization
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant, clippy::enum_variant_names)]
 pub enum ProofJob {
     /// A request has to create a new wallet
@@ -164,4 +319,51 @@ pub enum ProofJob {
         /// The statement (public variables) to use in the proof of `VALID MATCH ENCRYPTION`
         statement: ValidMatchEncryptionStatement,
     },
+    /// A request to create a proof of `VALID WALLET UPDATE` for a user-initiated transition
+    /// between two versions of a wallet
+    ValidWalletUpdate {
+        /// The witness to use in the proof of `VALID WALLET UPDATE`
+        witness: SizedValidWalletUpdateWitness,
+        /// The statement (public variables) to use in the proof of `VALID WALLET UPDATE`
+        statement: ValidWalletUpdateStatement,
+    },
+    /// A request to create a proof of `VALID SETTLE` for the application of a note to
+    /// the wallet it was issued to
+    ValidSettle {
+        /// The witness to use in the proof of `VALID SETTLE`
+        witness: SizedValidSettleWitness,
+        /// The statement (public variables) to use in the proof of `VALID SETTLE`
+        statement: SizedValidSettleStatement,
+    },
+}
+
+impl ProofJob {
+    /// The name of the statement the job proves
+    pub fn statement_name(&self) -> &'static str {
+        match self {
+            ProofJob::ValidWalletCreate { .. } => "VALID WALLET CREATE",
+            ProofJob::ValidCommitments { .. } => "VALID COMMITMENTS",
+            ProofJob::ValidMatchEncrypt { .. } => "VALID MATCH ENCRYPTION",
+            ProofJob::ValidWalletUpdate { .. } => "VALID WALLET UPDATE",
+            ProofJob::ValidSettle { .. } => "VALID SETTLE",
+        }
+    }
+
+    /// The idempotency key of the job
+    pub fn idempotency_key(&self) -> ProofJobKey {
+        ProofJobKey(Hash::hash(&serde_json::to_vec(self).unwrap()))
+    }
+
+    /// The amount of time the job may run before the proof manager abandons it
+    pub fn time_budget(&self) -> Duration {
+        let budget_ms = match self {
+            ProofJob::ValidWalletCreate { .. } => VALID_WALLET_CREATE_BUDGET_MS,
+            ProofJob::ValidCommitments { .. } => VALID_COMMITMENTS_BUDGET_MS,
+            ProofJob::ValidMatchEncrypt { .. } => VALID_MATCH_ENCRYPTION_BUDGET_MS,
+            ProofJob::ValidWalletUpdate { .. } => VALID_WALLET_UPDATE_BUDGET_MS,
+            ProofJob::ValidSettle { .. } => VALID_SETTLE_BUDGET_MS,
+        };
+
+        Duration::from_millis(budget_ms)
+    }
 }
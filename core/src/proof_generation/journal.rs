@@ -0,0 +1,284 @@
+//! A write-ahead journal of the proof manager's jobs, so that jobs survive a restart
+//!
+//! Each job is appended to the journal as it is taken off the proof manager's queue, and
+//! marked finished once the manager is done with it; whether the job was proven, failed,
+//! was cancelled, or was abandoned. The jobs left unfinished when the relayer crashes are
+//! replayed when the proof manager next starts, so every job accepted by the manager is
+//! run at least once.
+//!
+//! A replayed job has no requester waiting on it. Jobs are keyed by a hash of their
+//! contents, so a requester that re-submits the same job after the restart is attached to
+//! the replayed job, or served its proof if it has already been proven, rather than
+//! proving the statement twice.
+//!
+//! The journal is append-only and compacted each time it is opened. It holds the jobs'
+//! witnesses in plaintext, including wallet secrets, and must be protected accordingly
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::log;
+
+use super::{
+    error::ProofManagerError,
+    jobs::{ProofJob, ProofJobKey, ProofJobPriority},
+};
+
+/// A single line in the journal
+#[derive(Debug, Serialize, Deserialize)]
+enum JournalRecord {
+    /// A job was taken off the queue
+    Enqueued {
+        /// The idempotency key of the job
+        key: ProofJobKey,
+        /// The priority the job was requested at
+        priority: ProofJobPriority,
+        /// The job itself
+        job: ProofJob,
+    },
+    /// The proof manager is done with the job
+    Finished {
+        /// The idempotency key of the job
+        key: ProofJobKey,
+    },
+}
+
+/// A job left unfinished by a previous run of the relayer
+#[derive(Clone, Debug)]
+pub struct RecoveredJob {
+    /// The idempotency key of the job
+    pub key: ProofJobKey,
+    /// The priority the job was requested at
+    pub priority: ProofJobPriority,
+    /// The job itself
+    pub job: ProofJob,
+}
+
+/// The journal of the proof manager's unfinished jobs, persisted to a file if one is
+/// configured
+#[derive(Clone, Debug)]
+pub struct ProofJournal {
+    /// The journal file opened for appending; the journal is disabled if unset
+    file: Option<Arc<Mutex<File>>>,
+    /// The jobs left unfinished by the previous run, taken once on startup
+    recovered: Arc<Mutex<Vec<RecoveredJob>>>,
+}
+
+impl ProofJournal {
+    /// Open the journal at the given path, reading the jobs left unfinished by a previous
+    /// run and compacting the journal down to them
+    pub fn open(path: Option<String>) -> Result<Self, ProofManagerError> {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                return Ok(Self {
+                    file: None,
+                    recovered: Arc::default(),
+                })
+            }
+        };
+
+        let recovered = if Path::new(&path).exists() {
+            let contents = fs::read_to_string(&path)
+                .map_err(|err| ProofManagerError::Journal(err.to_string()))?;
+            Self::replay(&contents)?
+        } else {
+            Vec::new()
+        };
+
+        // Rewrite the journal with only the unfinished jobs, then rename it into place so
+        // that a crash mid-compaction never loses a job
+        let mut compacted = Vec::new();
+        for job in recovered.iter() {
+            let record = JournalRecord::Enqueued {
+                key: job.key,
+                priority: job.priority,
+                job: job.job.clone(),
+            };
+            compacted.extend(Self::encode(&record)?);
+        }
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, compacted)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|err| ProofManagerError::Journal(err.to_string()))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|err| ProofManagerError::Journal(err.to_string()))?;
+        if !recovered.is_empty() {
+            log::info!("recovered {} unfinished proof jobs", recovered.len());
+        }
+
+        Ok(Self {
+            file: Some(Arc::new(Mutex::new(file))),
+            recovered: Arc::new(Mutex::new(recovered)),
+        })
+    }
+
+    /// Take the jobs left unfinished by the previous run, in the order they were enqueued
+    pub fn take_recovered(&self) -> Vec<RecoveredJob> {
+        std::mem::take(&mut *self.recovered.lock().unwrap())
+    }
+
+    /// Journal a job taken off the queue
+    pub fn record_enqueued(
+        &self,
+        key: ProofJobKey,
+        priority: ProofJobPriority,
+        job: &ProofJob,
+    ) -> Result<(), ProofManagerError> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+
+        self.append(&JournalRecord::Enqueued {
+            key,
+            priority,
+            job: job.clone(),
+        })
+    }
+
+    /// Mark a job finished, it is not replayed after a restart
+    pub fn record_finished(&self, key: ProofJobKey) -> Result<(), ProofManagerError> {
+        self.append(&JournalRecord::Finished { key })
+    }
+
+    /// Append a record to the journal and sync it to disk
+    fn append(&self, record: &JournalRecord) -> Result<(), ProofManagerError> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let encoded = Self::encode(record)?;
+        let mut locked_file = file.lock().unwrap();
+        locked_file
+            .write_all(&encoded)
+            .and_then(|_| locked_file.sync_data())
+            .map_err(|err| ProofManagerError::Journal(err.to_string()))
+    }
+
+    /// Encode a record as a line of the journal
+    fn encode(record: &JournalRecord) -> Result<Vec<u8>, ProofManagerError> {
+        let mut encoded = serde_json::to_vec(record)
+            .map_err(|err| ProofManagerError::Journal(err.to_string()))?;
+        encoded.push(b'\n');
+        Ok(encoded)
+    }
+
+    /// Replay the journal's records, returning the jobs that were never finished
+    ///
+    /// A job enqueued more than once under the same key is recovered once, at the position
+    /// it was first enqueued
+    fn replay(contents: &str) -> Result<Vec<RecoveredJob>, ProofManagerError> {
+        let lines = contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut sequence = 0u64;
+        let mut unfinished: HashMap<ProofJobKey, (u64, RecoveredJob)> = HashMap::new();
+        for (i, line) in lines.iter().enumerate() {
+            let record = match serde_json::from_str::<JournalRecord>(line) {
+                Ok(record) => record,
+                // The final record may have been torn by a crash mid-append
+                Err(err) if i == lines.len() - 1 => {
+                    log::warn!("discarding torn record at the end of the proof journal: {err}");
+                    break;
+                }
+                Err(err) => return Err(ProofManagerError::Journal(err.to_string())),
+            };
+
+            match record {
+                JournalRecord::Enqueued { key, priority, job } => {
+                    unfinished.entry(key).or_insert_with(|| {
+                        sequence += 1;
+                        (sequence, RecoveredJob { key, priority, job })
+                    });
+                }
+                JournalRecord::Finished { key } => {
+                    unfinished.remove(&key);
+                }
+            }
+        }
+
+        let mut recovered = unfinished.into_values().collect::<Vec<_>>();
+        recovered.sort_by_key(|(sequence, _)| *sequence);
+        Ok(recovered.into_iter().map(|(_, job)| job).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::types::keychain::KeyChain;
+    use curve25519_dalek::scalar::Scalar;
+
+    use crate::proof_generation::jobs::{ProofJob, ProofJobPriority};
+
+    use super::ProofJournal;
+
+    /// Build a job tagged by its randomness
+    fn job(tag: u64) -> ProofJob {
+        ProofJob::ValidWalletCreate {
+            fees: Vec::new(),
+            keys: KeyChain {
+                pk_root: Scalar::zero(),
+                pk_match: Scalar::zero(),
+                pk_settle: Scalar::zero(),
+                pk_view: Scalar::zero(),
+            },
+            randomness: Scalar::from(tag),
+        }
+    }
+
+    /// Tests that jobs left unfinished are recovered in order after a reopen, and that
+    /// finished jobs, duplicates, and a torn final record are not
+    #[test]
+    fn test_recover_unfinished() {
+        let path = std::env::temp_dir().join(format!("proof-journal-{}", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap().to_string();
+
+        let journal = ProofJournal::open(Some(path.clone())).unwrap();
+        assert!(journal.take_recovered().is_empty());
+
+        let jobs = (0..3).map(job).collect::<Vec<_>>();
+        for job in jobs.iter() {
+            journal
+                .record_enqueued(job.idempotency_key(), ProofJobPriority::Settlement, job)
+                .unwrap();
+        }
+        journal
+            .record_enqueued(
+                jobs[2].idempotency_key(),
+                ProofJobPriority::Settlement,
+                &jobs[2],
+            )
+            .unwrap();
+        journal.record_finished(jobs[1].idempotency_key()).unwrap();
+        drop(journal);
+
+        let mut contents = std::fs::read_to_string(&path).unwrap();
+        contents.push_str("{\"Enqueued\":{\"key\"");
+        std::fs::write(&path, contents).unwrap();
+
+        let recovered = ProofJournal::open(Some(path.clone()))
+            .unwrap()
+            .take_recovered()
+            .into_iter()
+            .map(|job| job.key)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            recovered,
+            vec![jobs[0].idempotency_key(), jobs[2].idempotency_key()]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
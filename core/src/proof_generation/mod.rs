@@ -1,6 +1,10 @@
 //! The proof generation worker handles the core of generating single-prover
 //! proofs for wallet updates
+pub mod dead_letter;
 pub mod error;
 pub mod jobs;
+pub mod journal;
+pub mod proof_cache;
 pub mod proof_manager;
+mod queue;
 pub mod worker;
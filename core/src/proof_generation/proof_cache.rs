@@ -0,0 +1,487 @@
+//! A cache of proofs of `VALID COMMITMENTS`, held in memory and optionally persisted to disk
+//!
+//! A proof of `VALID COMMITMENTS` depends only on the wallet, the Merkle root it is opened
+//! against, and the order, balance and fee selected from the wallet. Proofs are therefore
+//! cached under the (wallet commitment, Merkle root) pair, so that a relayer restarting
+//! against an unchanged wallet and root serves its proofs from the cache instead of
+//! re-proving every order.
+//!
+//! The commitments in the proof are linkable: the match MPC commits to the order and balance
+//! with the randomness in the witness the proof was generated from. A cached proof is only
+//! served for the exact witness it was proven from, and `find_witness` recovers that witness
+//! for a caller that has assembled an equivalent witness with fresh randomness.
+//!
+//! The cache directory holds the witnesses in plaintext, including the wallet's secret keys,
+//! and must be protected accordingly
+
+use std::{
+    fs,
+    io::ErrorKind,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use circuits::{
+    native_helpers::compute_wallet_commitment,
+    types::{balance::Balance, fee::Fee, order::Order},
+    zk_circuits::valid_commitments::ValidCommitmentsStatement,
+};
+use crypto::fields::prime_field_to_scalar;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use tracing::log;
+
+use crate::types::SizedValidCommitmentsWitness;
+
+use super::{error::ProofManagerError, jobs::ValidCommitmentsBundle};
+
+/// The number of (wallet commitment, Merkle root) pairs held in the cache
+const PROOF_CACHE_SIZE: usize = 1_000;
+/// The extension of the files cache entries are persisted to
+const CACHE_FILE_EXTENSION: &str = "json";
+
+/// The key a set of cached proofs is stored under
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProofCacheKey {
+    /// The commitment to the wallet the proofs are of
+    wallet_commitment: [u8; 32],
+    /// The Merkle root the wallet is opened against
+    merkle_root: [u8; 32],
+}
+
+impl ProofCacheKey {
+    /// Build the key for a witness and statement
+    pub fn new(
+        witness: &SizedValidCommitmentsWitness,
+        statement: &ValidCommitmentsStatement,
+    ) -> Self {
+        let wallet_commitment = prime_field_to_scalar(&compute_wallet_commitment(&witness.wallet));
+        Self {
+            wallet_commitment: wallet_commitment.to_bytes(),
+            merkle_root: statement.merkle_root.to_bytes(),
+        }
+    }
+
+    /// The name of the file the entries under this key are persisted to
+    fn file_name(&self) -> String {
+        format!(
+            "{}-{}.{}",
+            hex::encode(self.wallet_commitment),
+            hex::encode(self.merkle_root),
+            CACHE_FILE_EXTENSION
+        )
+    }
+}
+
+/// A cached proof along with the witness it was proven from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedValidCommitments {
+    /// The witness the proof was generated from
+    pub witness: SizedValidCommitmentsWitness,
+    /// The proof and the commitment to the witness
+    pub bundle: ValidCommitmentsBundle,
+}
+
+impl CachedValidCommitments {
+    /// Whether the cached proof is of the given statement
+    fn proves_statement(&self, statement: &ValidCommitmentsStatement) -> bool {
+        let cached_statement = &self.bundle.statement;
+        cached_statement.nullifier == statement.nullifier
+            && cached_statement.merkle_root == statement.merkle_root
+            && cached_statement.pk_settle == statement.pk_settle
+    }
+
+    /// Whether the cached witness holds the same values as the given witness, disregarding
+    /// the randomness the values are committed with
+    fn matches_values(&self, witness: &SizedValidCommitmentsWitness) -> bool {
+        let cached = &self.witness;
+        Order::from(cached.order.clone()) == Order::from(witness.order.clone())
+            && Balance::from(cached.balance.clone()) == Balance::from(witness.balance.clone())
+            && Balance::from(cached.fee_balance.clone())
+                == Balance::from(witness.fee_balance.clone())
+            && Fee::from(cached.fee.clone()) == Fee::from(witness.fee.clone())
+            && cached.sk_match == witness.sk_match
+    }
+
+    /// Whether the cached witness is exactly the given witness
+    fn matches_exactly(&self, witness: &SizedValidCommitmentsWitness) -> bool {
+        // The witness types do not implement `PartialEq`, compare their serialization
+        match (
+            serde_json::to_vec(&self.witness),
+            serde_json::to_vec(witness),
+        ) {
+            (Ok(cached), Ok(given)) => cached == given,
+            _ => false,
+        }
+    }
+}
+
+/// An LRU cache of proofs of `VALID COMMITMENTS`, persisted to a directory if one is
+/// configured
+#[derive(Clone, Debug)]
+pub struct ProofCache {
+    /// The directory entries are persisted to; the cache is held only in memory if unset
+    cache_dir: Option<PathBuf>,
+    /// The cached proofs
+    entries: Arc<Mutex<LruCache<ProofCacheKey, Vec<CachedValidCommitments>>>>,
+}
+
+impl ProofCache {
+    /// Open the cache, reading any entries persisted to the cache directory by a previous run
+    pub fn new(cache_dir: Option<String>) -> Result<Self, ProofManagerError> {
+        let mut entries = LruCache::new(NonZeroUsize::new(PROOF_CACHE_SIZE).unwrap());
+        let cache_dir = cache_dir.map(PathBuf::from);
+        if let Some(dir) = &cache_dir {
+            fs::create_dir_all(dir).map_err(|err| ProofManagerError::Cache(err.to_string()))?;
+            for dir_entry in
+                fs::read_dir(dir).map_err(|err| ProofManagerError::Cache(err.to_string()))?
+            {
+                let path = dir_entry
+                    .map_err(|err| ProofManagerError::Cache(err.to_string()))?
+                    .path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some(CACHE_FILE_EXTENSION) {
+                    continue;
+                }
+
+                match Self::read_entries(&path) {
+                    Ok(cached) if !cached.is_empty() => {
+                        let key =
+                            ProofCacheKey::new(&cached[0].witness, &cached[0].bundle.statement);
+                        entries.push(key, cached);
+                    }
+                    Ok(_) => {}
+                    Err(err) => log::warn!("skipping proof cache file {:?}: {}", path, err),
+                }
+            }
+        }
+
+        Ok(Self {
+            cache_dir,
+            entries: Arc::new(Mutex::new(entries)),
+        })
+    }
+
+    /// Get a cached proof of the statement, generated from exactly the given witness
+    pub fn get(
+        &self,
+        witness: &SizedValidCommitmentsWitness,
+        statement: &ValidCommitmentsStatement,
+    ) -> Option<ValidCommitmentsBundle> {
+        let key = ProofCacheKey::new(witness, statement);
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&key)?
+            .iter()
+            .find(|cached| cached.proves_statement(statement) && cached.matches_exactly(witness))
+            .map(|cached| cached.bundle.clone())
+    }
+
+    /// Find a cached witness that holds the same values as the given witness and whose
+    /// proof is of the given statement
+    ///
+    /// Proving from the returned witness in place of the given one is served from the cache
+    pub fn find_witness(
+        &self,
+        witness: &SizedValidCommitmentsWitness,
+        statement: &ValidCommitmentsStatement,
+    ) -> Option<SizedValidCommitmentsWitness> {
+        let key = ProofCacheKey::new(witness, statement);
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&key)?
+            .iter()
+            .find(|cached| cached.proves_statement(statement) && cached.matches_values(witness))
+            .map(|cached| cached.witness.clone())
+    }
+
+    /// Cache a proof along with the witness it was generated from
+    pub fn insert(
+        &self,
+        witness: SizedValidCommitmentsWitness,
+        bundle: ValidCommitmentsBundle,
+    ) -> Result<(), ProofManagerError> {
+        let key = ProofCacheKey::new(&witness, &bundle.statement);
+        let mut locked_entries = self.entries.lock().unwrap();
+
+        let mut cached = locked_entries.pop(&key).unwrap_or_default();
+        cached.retain(|entry| {
+            !(entry.proves_statement(&bundle.statement) && entry.matches_values(&witness))
+        });
+        cached.push(CachedValidCommitments { witness, bundle });
+        self.persist(&key, &cached)?;
+
+        if let Some((evicted_key, _)) = locked_entries.push(key, cached) {
+            if evicted_key != key {
+                self.remove_file(&evicted_key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evict the proof of the statement generated from exactly the given witness, e.g. once
+    /// the witness is found to be corrupt
+    pub fn remove(
+        &self,
+        witness: &SizedValidCommitmentsWitness,
+        statement: &ValidCommitmentsStatement,
+    ) -> Result<(), ProofManagerError> {
+        let key = ProofCacheKey::new(witness, statement);
+        let mut locked_entries = self.entries.lock().unwrap();
+
+        let mut cached = match locked_entries.pop(&key) {
+            Some(cached) => cached,
+            None => return Ok(()),
+        };
+        cached
+            .retain(|entry| !(entry.proves_statement(statement) && entry.matches_exactly(witness)));
+
+        if cached.is_empty() {
+            return self.remove_file(&key);
+        }
+
+        self.persist(&key, &cached)?;
+        locked_entries.push(key, cached);
+        Ok(())
+    }
+
+    /// Read the entries persisted to a cache file
+    fn read_entries(path: &Path) -> Result<Vec<CachedValidCommitments>, ProofManagerError> {
+        let contents = fs::read(path).map_err(|err| ProofManagerError::Cache(err.to_string()))?;
+        serde_json::from_slice(&contents).map_err(|err| ProofManagerError::Cache(err.to_string()))
+    }
+
+    /// Persist the entries under a key, replacing the previous contents of its file
+    fn persist(
+        &self,
+        key: &ProofCacheKey,
+        cached: &[CachedValidCommitments],
+    ) -> Result<(), ProofManagerError> {
+        let dir = match &self.cache_dir {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+
+        let serialized =
+            serde_json::to_vec(cached).map_err(|err| ProofManagerError::Cache(err.to_string()))?;
+        let path = dir.join(key.file_name());
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serialized)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|err| ProofManagerError::Cache(err.to_string()))
+    }
+
+    /// Remove the file an evicted key was persisted to
+    fn remove_file(&self, key: &ProofCacheKey) -> Result<(), ProofManagerError> {
+        let dir = match &self.cache_dir {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+
+        match fs::remove_file(dir.join(key.file_name())) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(ProofManagerError::Cache(err.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        env, fs,
+    };
+
+    use circuits::{
+        singleprover_prove,
+        types::{
+            balance::Balance,
+            fee::Fee,
+            order::{Order, OrderSide},
+        },
+        zk_circuits::valid_commitments::{ValidCommitmentsStatement, ValidCommitmentsWitness},
+        zk_gadgets::fixed_point::FixedPoint,
+        LinkableCommitment,
+    };
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
+    use uuid::Uuid;
+
+    use crate::{
+        keychain,
+        proof_generation::jobs::ValidCommitmentsBundle,
+        state::wallet::{MerkleAuthenticationPath, Wallet, WalletMetadata},
+        types::{SizedValidCommitments, SizedValidCommitmentsWitness},
+        MERKLE_HEIGHT,
+    };
+
+    use super::ProofCache;
+
+    /// Build a wallet holding a single sell order of the given amount, and balances that
+    /// cover it and its fee
+    fn test_wallet(amount: u64) -> Wallet {
+        let (public_keys, secret_keys) = keychain::derive_keychain(Scalar::from(42u64));
+        let order = Order {
+            quote_mint: 2u8.into(),
+            base_mint: 1u8.into(),
+            side: OrderSide::Sell,
+            price: FixedPoint::from(10f32),
+            amount,
+            timestamp: 0,
+            time_in_force: Default::default(),
+            constraints: Default::default(),
+        };
+        let balances = [1u8, 2u8].into_iter().map(|mint| {
+            (
+                BigUint::from(mint),
+                Balance {
+                    mint: mint.into(),
+                    amount: 10,
+                },
+            )
+        });
+
+        Wallet {
+            wallet_id: Uuid::new_v4(),
+            orders: HashMap::from([(Uuid::new_v4(), order)]),
+            balances: balances.collect(),
+            fees: vec![Fee {
+                settle_key: 0u8.into(),
+                gas_addr: 2u8.into(),
+                gas_token_amount: 1,
+                percentage_fee: FixedPoint::from(0.01f32),
+            }],
+            public_keys,
+            secret_keys,
+            randomness: BigUint::from(7u8),
+            metadata: WalletMetadata {
+                replicas: HashSet::new(),
+                version: 0,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: Default::default(),
+        }
+    }
+
+    /// Build a witness to `VALID COMMITMENTS` for the wallet's order, committed to with
+    /// fresh randomness
+    fn build_witness(wallet: &Wallet) -> (SizedValidCommitmentsWitness, ValidCommitmentsStatement) {
+        let merkle_path = MerkleAuthenticationPath::new(
+            [Scalar::zero(); MERKLE_HEIGHT],
+            BigUint::from(0u8),
+            wallet.get_commitment(),
+        );
+        let order = wallet.orders.values().next().unwrap().clone();
+        let balance = wallet.balances[&order.base_mint].clone();
+        let fee = wallet.fees[0].clone();
+        let fee_balance = wallet.balances[&fee.gas_addr].clone();
+
+        let witness = ValidCommitmentsWitness {
+            wallet: wallet.clone().into(),
+            order: order.into(),
+            balance: balance.into(),
+            fee: fee.into(),
+            fee_balance: fee_balance.into(),
+            wallet_opening: merkle_path.clone().into(),
+            randomness_hash: LinkableCommitment::new(Scalar::from(7u8)),
+            sk_match: keychain::match_key(wallet).unwrap(),
+        };
+        let statement = ValidCommitmentsStatement {
+            nullifier: wallet.get_match_nullifier(),
+            merkle_root: merkle_path.compute_root(),
+            pk_settle: keychain::settle_key(wallet),
+        };
+
+        (witness, statement)
+    }
+
+    /// Prove `VALID COMMITMENTS` from the given witness
+    fn prove(
+        witness: &SizedValidCommitmentsWitness,
+        statement: ValidCommitmentsStatement,
+    ) -> ValidCommitmentsBundle {
+        let (commitment, proof) =
+            singleprover_prove::<SizedValidCommitments>(witness.clone(), statement).unwrap();
+        ValidCommitmentsBundle {
+            commitment,
+            statement,
+            proof,
+        }
+    }
+
+    /// Tests that a proof is served only for the exact witness it was proven from, and
+    /// that an equivalent witness recovers that witness
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let cache = ProofCache::new(None /* cache_dir */).unwrap();
+        let wallet = test_wallet(5 /* amount */);
+        let (witness, statement) = build_witness(&wallet);
+        assert!(cache.get(&witness, &statement).is_none());
+        assert!(cache.find_witness(&witness, &statement).is_none());
+
+        let bundle = prove(&witness, statement);
+        cache.insert(witness.clone(), bundle).unwrap();
+        let cached = cache.get(&witness, &statement).unwrap();
+        assert_eq!(cached.statement.nullifier, statement.nullifier);
+
+        // A witness holding the same values under fresh randomness misses, but recovers
+        // the cached witness, which hits
+        let (fresh_witness, _) = build_witness(&wallet);
+        assert!(cache.get(&fresh_witness, &statement).is_none());
+        let found = cache.find_witness(&fresh_witness, &statement).unwrap();
+        assert!(cache.get(&found, &statement).is_some());
+
+        // The same witness opened against another Merkle root misses
+        let other_statement = ValidCommitmentsStatement {
+            merkle_root: Scalar::from(1u8),
+            ..statement
+        };
+        assert!(cache.get(&witness, &other_statement).is_none());
+        assert!(cache.find_witness(&witness, &other_statement).is_none());
+    }
+
+    /// Tests that a cached proof stops being served once the witness it was proven from
+    /// changes, whether the wallet is updated, the proof is replaced by one from a new
+    /// witness, or the witness is evicted; and that a reopened cache agrees
+    #[test]
+    fn test_invalidation_on_witness_change() {
+        let cache_dir = env::temp_dir().join(format!("proof-cache-{}", Uuid::new_v4()));
+        let cache = ProofCache::new(Some(cache_dir.display().to_string())).unwrap();
+
+        let wallet = test_wallet(5 /* amount */);
+        let (witness, statement) = build_witness(&wallet);
+        let bundle = prove(&witness, statement);
+        cache.insert(witness.clone(), bundle.clone()).unwrap();
+
+        // A witness of the updated wallet misses the proof of the old one
+        let (updated_witness, updated_statement) = build_witness(&test_wallet(6 /* amount */));
+        assert!(cache
+            .find_witness(&updated_witness, &updated_statement)
+            .is_none());
+
+        // Caching a proof from a new witness of the same values replaces the old proof
+        let (fresh_witness, _) = build_witness(&wallet);
+        cache.insert(fresh_witness.clone(), bundle).unwrap();
+        assert!(cache.get(&witness, &statement).is_none());
+        assert!(cache.get(&fresh_witness, &statement).is_some());
+
+        let reopened = ProofCache::new(Some(cache_dir.display().to_string())).unwrap();
+        assert!(reopened.get(&witness, &statement).is_none());
+        assert!(reopened.get(&fresh_witness, &statement).is_some());
+
+        // Evicting the witness removes it from memory and from disk
+        cache.remove(&fresh_witness, &statement).unwrap();
+        assert!(cache.find_witness(&witness, &statement).is_none());
+
+        let reopened = ProofCache::new(Some(cache_dir.display().to_string())).unwrap();
+        assert!(reopened.find_witness(&witness, &statement).is_none());
+
+        fs::remove_dir_all(cache_dir).unwrap();
+    }
+}
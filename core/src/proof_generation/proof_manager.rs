@@ -2,7 +2,13 @@
 //! happen to the state. It provides an abstracted messaging interface for other
 //! workers to submit proof requests to.
 
-use std::{convert::TryInto, sync::Arc, thread::JoinHandle};
+use std::{
+    convert::TryInto,
+    num::NonZeroUsize,
+    sync::Arc,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
 
 use circuits::{
     native_helpers::compute_wallet_commitment,
@@ -16,26 +22,37 @@ use circuits::{
         valid_wallet_create::{
             ValidWalletCreate, ValidWalletCreateStatement, ValidWalletCreateWitness,
         },
+        valid_wallet_update::{ValidWalletUpdate, ValidWalletUpdateStatement},
     },
-    MAX_BALANCES, MAX_ORDERS,
+    ConstraintProfile, SingleProverCircuit, MAX_BALANCES, MAX_ORDERS,
 };
-use crossbeam::channel::Receiver;
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender};
 use crypto::fields::prime_field_to_scalar;
 use curve25519_dalek::scalar::Scalar;
+use lru::LruCache;
 use rayon::ThreadPool;
 use tracing::log;
 
 use crate::{
-    proof_generation::jobs::ProofJob, types::SizedValidCommitmentsWitness, CancelChannel,
-    SizedWallet, MAX_FEES,
+    job_queue::JobQueueReceiver,
+    proof_generation::jobs::ProofJob,
+    types::{
+        SizedValidCommitmentsWitness, SizedValidSettle, SizedValidSettleStatement,
+        SizedValidSettleWitness, SizedValidWalletUpdateWitness,
+    },
+    CancelChannel, SizedWallet, MAX_FEES,
 };
 
 use super::{
+    dead_letter::{DeadLetterQueue, DeadLetterReason},
     error::ProofManagerError,
     jobs::{
-        ProofBundle, ProofManagerJob, ValidCommitmentsBundle, ValidMatchEncryptBundle,
-        ValidWalletCreateBundle,
+        ProofBundle, ProofJobKey, ProofManagerJob, ValidCommitmentsBundle, ValidMatchEncryptBundle,
+        ValidSettleBundle, ValidWalletCreateBundle, ValidWalletUpdateBundle,
     },
+    journal::ProofJournal,
+    proof_cache::ProofCache,
+    queue::{JournaledJob, PendingJobs},
 };
 
 // -------------
@@ -45,6 +62,10 @@ use super::{
 const ERR_SENDING_RESPONSE: &str = "error sending proof response, channel closed";
 /// The number of threads to allocate towards the proof generation worker pool
 pub(crate) const PROOF_GENERATION_N_THREADS: usize = 2;
+/// The interval at which the proof manager checks whether a running job has been cancelled
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The number of proofs of recovered jobs held for their requesters to re-submit
+const RECOVERED_PROOFS_CACHE_SIZE: usize = 100;
 
 // --------------------
 // | Proof Generation |
@@ -57,23 +78,82 @@ pub struct ProofManager {
     /// The queue on which the proof manager receives new jobs
     /// TODO: Remove this lint allowance
     #[allow(dead_code)]
-    pub(crate) job_queue: Option<Receiver<ProofManagerJob>>,
+    pub(crate) job_queue: Option<JobQueueReceiver<ProofManagerJob>>,
     /// The handle of the main driver thread in the proof generation module
     pub(crate) join_handle: Option<JoinHandle<ProofManagerError>>,
+    /// The handle of the thread that journals jobs as they are taken off the queue
+    pub(crate) intake_join_handle: Option<JoinHandle<ProofManagerError>>,
     /// The threadpool of workers generating proofs for the system
     pub(crate) thread_pool: Arc<ThreadPool>,
+    /// The queue of jobs abandoned for exceeding their time budget
+    pub(crate) dead_letter_queue: DeadLetterQueue,
+    /// The cache of proofs of `VALID COMMITMENTS`
+    pub(crate) proof_cache: ProofCache,
+    /// The write-ahead journal of jobs taken off the queue
+    pub(crate) journal: ProofJournal,
     /// The channel on which a coordinator may cancel execution
     pub(crate) cancel_channel: CancelChannel,
 }
 
 impl ProofManager {
+    /// The intake loop takes jobs off the queue as they arrive and journals them, so that a
+    /// job survives a restart even if it arrives while the execution loop is busy proving
+    pub(crate) fn intake_loop(
+        mut job_queue: JobQueueReceiver<ProofManagerJob>,
+        journal: ProofJournal,
+        intake_queue: Sender<JournaledJob>,
+    ) -> ProofManagerError {
+        loop {
+            let job = match job_queue.blocking_recv() {
+                Some(job) => job,
+                None => {
+                    return ProofManagerError::JobQueueClosed(
+                        "every job sender has been dropped".to_string(),
+                    )
+                }
+            };
+
+            // A job that cannot be journaled is still run, it is only lost if the relayer
+            // crashes before it finishes
+            let key = job.type_.idempotency_key();
+            if let Err(e) = journal.record_enqueued(key, job.priority, &job.type_) {
+                log::error!("error journaling proof job: {e}");
+            }
+
+            let journaled = JournaledJob {
+                key,
+                recovered: false,
+                job,
+            };
+            if intake_queue.send(journaled).is_err() {
+                return ProofManagerError::JobQueueClosed("execution loop has stopped".to_string());
+            }
+        }
+    }
+
     /// The execution loop blocks on the job queue then schedules proof generation
     /// jobs onto a thread pool
+    ///
+    /// Jobs waiting on a prover run highest priority first, see `ProofJobPriority`. Each
+    /// job is given a time budget; a job that overruns its budget is abandoned and moved
+    /// to the dead-letter queue. A job cancelled by its requester is skipped if it has not
+    /// started, and otherwise abandoned without a dead letter. Proving cannot be
+    /// interrupted, so an abandoned job continues to occupy its pool thread until it
+    /// finishes, but its result is discarded and the manager moves on to the next job
+    ///
+    /// Every job is marked finished in the journal once the manager is done with it, see
+    /// `ProofJournal` for how jobs left unfinished by a crash are replayed
     pub(crate) fn execution_loop(
-        job_queue: Receiver<ProofManagerJob>,
+        job_queue: Receiver<JournaledJob>,
         thread_pool: Arc<ThreadPool>,
+        dead_letter_queue: DeadLetterQueue,
+        proof_cache: ProofCache,
+        journal: ProofJournal,
         cancel_channel: CancelChannel,
     ) -> Result<(), ProofManagerError> {
+        let mut pending = PendingJobs::default();
+        let mut recovered_proofs =
+            LruCache::new(NonZeroUsize::new(RECOVERED_PROOFS_CACHE_SIZE).unwrap());
         loop {
             // Check the cancel channel before blocking on a job
             if cancel_channel
@@ -86,62 +166,246 @@ impl ProofManager {
                 ));
             }
 
-            // Dequeue the next job and hand it to the thread pool
-            let job = job_queue
-                .recv()
-                .map_err(|err| ProofManagerError::JobQueueClosed(err.to_string()))?;
+            // Block on the job queue only if no jobs are pending, then take in every job
+            // enqueued since so that the highest priority among them runs next
+            if pending.is_empty() {
+                let job = job_queue
+                    .recv()
+                    .map_err(|err| ProofManagerError::JobQueueClosed(err.to_string()))?;
+                Self::admit_job(job, &mut pending, &mut recovered_proofs, &journal);
+            }
+            while let Ok(job) = job_queue.try_recv() {
+                Self::admit_job(job, &mut pending, &mut recovered_proofs, &journal);
+            }
 
-            thread_pool.install(move || {
-                if let Err(e) = Self::handle_proof_job(job) {
-                    println!("Error handling proof manager job: {}", e)
-                }
+            let JournaledJob {
+                key,
+                recovered,
+                job,
+            } = match pending.pop() {
+                Some(job) => job,
+                // Every admitted job was answered without proving
+                None => continue,
+            };
+            if job.is_cancelled() {
+                log::info!("skipping cancelled proof of {}", job.type_.statement_name());
+                Self::finish_job(&journal, key);
+                continue;
+            }
+
+            // Hand the job to the thread pool
+            let ProofManagerJob {
+                type_,
+                cancellation,
+                response_channel,
+                ..
+            } = job;
+            let statement_name = type_.statement_name();
+            let time_budget = type_.time_budget();
+
+            let (result_sender, result_receiver) = channel::bounded(1 /* capacity */);
+            let job_cache = proof_cache.clone();
+            thread_pool.spawn(move || {
+                // The receiver is dropped if the job is abandoned, in which case there is
+                // nobody left to deliver the result to
+                let _ = result_sender.send(Self::handle_proof_job(type_, &job_cache));
             });
+
+            // Wait on the result, polling for cancellation if the job is cancellable
+            let deadline = Instant::now() + time_budget;
+            let reason = loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let wait = match cancellation {
+                    Some(_) => remaining.min(CANCELLATION_POLL_INTERVAL),
+                    None => remaining,
+                };
+
+                match result_receiver.recv_timeout(wait) {
+                    Ok(Ok(proof_bundle)) => {
+                        if let Err(proof_bundle) = response_channel.send(proof_bundle) {
+                            // Nobody is waiting on a recovered job, hold its proof for the
+                            // requester to re-submit
+                            if recovered {
+                                recovered_proofs.put(key, proof_bundle);
+                            } else {
+                                log::error!(
+                                    "Error handling proof manager job: {}",
+                                    ProofManagerError::Response(ERR_SENDING_RESPONSE.to_string())
+                                );
+                            }
+                        }
+                        break None;
+                    }
+                    Ok(Err(e)) => {
+                        log::error!("Error handling proof manager job: {}", e);
+                        break None;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Some(token) = cancellation.as_ref() && token.is_cancelled() {
+                            log::info!("abandoning cancelled proof of {}", statement_name);
+                            break None;
+                        }
+                        if wait == remaining {
+                            break Some(DeadLetterReason::TimedOut);
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break Some(DeadLetterReason::Panicked),
+                }
+            };
+
+            // The response channel of an abandoned job is dropped, notifying the requester
+            // that no proof is coming
+            if let Some(reason) = reason {
+                dead_letter_queue.push(
+                    statement_name.to_string(),
+                    reason,
+                    time_budget.as_millis() as u64,
+                );
+            }
+            Self::finish_job(&journal, key);
+        }
+    }
+
+    /// Admit a job to the pending jobs, or answer it without proving if it duplicates a
+    /// recovered job that has already been proven
+    ///
+    /// A requester that re-submits a recovered job that has not yet run takes its place,
+    /// so the statement is proven once and the proof delivered to the requester
+    fn admit_job(
+        job: JournaledJob,
+        pending: &mut PendingJobs,
+        recovered_proofs: &mut LruCache<ProofJobKey, ProofBundle>,
+        journal: &ProofJournal,
+    ) {
+        if !job.recovered {
+            if let Some(proof_bundle) = recovered_proofs.pop(&job.key) {
+                log::info!(
+                    "serving recovered proof of {}",
+                    job.job.type_.statement_name()
+                );
+                let _ = job.job.response_channel.send(proof_bundle);
+                Self::finish_job(journal, job.key);
+                return;
+            }
+
+            if pending.remove_recovered(&job.key) {
+                log::info!(
+                    "re-submitted proof of {} replaces its recovered job",
+                    job.job.type_.statement_name()
+                );
+            }
+        }
+
+        pending.push(job);
+    }
+
+    /// Mark a job finished in the journal
+    fn finish_job(journal: &ProofJournal, key: ProofJobKey) {
+        // The job is re-run after a restart if this fails, which is safe if wasteful
+        if let Err(e) = journal.record_finished(key) {
+            log::error!("error journaling finished proof job: {e}");
         }
     }
 
     /// The main job handler, run by a thread in the pool
-    fn handle_proof_job(job: ProofManagerJob) -> Result<(), ProofManagerError> {
-        match job.type_ {
+    fn handle_proof_job(
+        job: ProofJob,
+        proof_cache: &ProofCache,
+    ) -> Result<ProofBundle, ProofManagerError> {
+        Ok(match job {
             ProofJob::ValidWalletCreate {
                 fees,
                 keys,
                 randomness,
             } => {
                 // Prove `VALID WALLET CREATE`
-                let proof_bundle = Self::prove_valid_wallet_create(fees, keys, randomness)?;
-                job.response_channel
-                    .send(ProofBundle::ValidWalletCreate(proof_bundle))
-                    .map_err(|_| ProofManagerError::Response(ERR_SENDING_RESPONSE.to_string()))?
+                ProofBundle::ValidWalletCreate(Self::prove_valid_wallet_create(
+                    fees, keys, randomness,
+                )?)
             }
 
             ProofJob::ValidCommitments { witness, statement } => {
                 // Prove `VALID COMMITMENTS`
-                let proof_bundle = Self::prove_valid_commitments(witness, statement)?;
-                job.response_channel
-                    .send(ProofBundle::ValidCommitments(proof_bundle))
-                    .map_err(|_| ProofManagerError::Response(ERR_SENDING_RESPONSE.to_string()))?
+                ProofBundle::ValidCommitments(Self::prove_valid_commitments(
+                    witness,
+                    statement,
+                    proof_cache,
+                )?)
             }
 
             ProofJob::ValidMatchEncrypt { statement, witness } => {
                 // Prove `VALID MATCH ENCRYPTION`
-                let proof_bundle = Self::prove_valid_match_encrypt(statement, witness)?;
-                job.response_channel
-                    .send(ProofBundle::ValidMatchEncryption(proof_bundle))
-                    .map_err(|_| ProofManagerError::Response(ERR_SENDING_RESPONSE.to_string()))?;
+                ProofBundle::ValidMatchEncryption(Self::prove_valid_match_encrypt(
+                    statement, witness,
+                )?)
             }
-        };
 
-        Ok(())
+            ProofJob::ValidWalletUpdate { witness, statement } => {
+                // Prove `VALID WALLET UPDATE`
+                ProofBundle::ValidWalletUpdate(Self::prove_valid_wallet_update(witness, statement)?)
+            }
+
+            ProofJob::ValidSettle { witness, statement } => {
+                // Prove `VALID SETTLE`
+                ProofBundle::ValidSettle(Self::prove_valid_settle(witness, statement)?)
+            }
+        })
     }
 
-    /// Create a proof of `VALID WALLET CREATE`
-    fn prove_valid_wallet_create(
+    /// Dry run a job, applying its circuit's constraints to the witness and statement
+    /// without proving the statement
+    ///
+    /// Returns the size of the circuit and whether the witness satisfies it
+    pub fn dry_run(job: ProofJob) -> Result<ConstraintProfile, ProofManagerError> {
+        match job {
+            ProofJob::ValidWalletCreate {
+                fees,
+                keys,
+                randomness,
+            } => {
+                let (witness, statement) = Self::build_valid_wallet_create(fees, keys, randomness)?;
+                ValidWalletCreate::<MAX_BALANCES, MAX_ORDERS, MAX_FEES>::constraint_profile(
+                    witness, statement,
+                )
+            }
+            ProofJob::ValidCommitments { witness, statement } => {
+                ValidCommitments::<MAX_BALANCES, MAX_ORDERS, MAX_FEES>::constraint_profile(
+                    witness, statement,
+                )
+            }
+            ProofJob::ValidMatchEncrypt { statement, witness } => {
+                ValidMatchEncryption::<252 /* SCALAR_BITS */>::constraint_profile(
+                    witness, statement,
+                )
+            }
+            ProofJob::ValidWalletUpdate { witness, statement } => {
+                ValidWalletUpdate::<MAX_BALANCES, MAX_ORDERS, MAX_FEES>::constraint_profile(
+                    witness, statement,
+                )
+            }
+            ProofJob::ValidSettle { witness, statement } => {
+                SizedValidSettle::constraint_profile(witness, statement)
+            }
+        }
+        .map_err(|err| ProofManagerError::Prover(err.to_string()))
+    }
+
+    /// Build the witness and statement of `VALID WALLET CREATE` for a new, empty wallet
+    fn build_valid_wallet_create(
         fees: Vec<Fee>,
         keys: KeyChain,
         randomness: Scalar,
-    ) -> Result<ValidWalletCreateBundle, ProofManagerError> {
+    ) -> Result<
+        (
+            ValidWalletCreateWitness<MAX_FEES>,
+            ValidWalletCreateStatement,
+        ),
+        ProofManagerError,
+    > {
         // Build an empty wallet and compute its commitment
-        let sized_fees: [Fee; MAX_FEES] = fees.try_into().unwrap();
+        let sized_fees: [Fee; MAX_FEES] = fees.try_into().map_err(|_| {
+            ProofManagerError::Prover(format!("expected {MAX_FEES} fees for a new wallet"))
+        })?;
         let empty_wallet = SizedWallet {
             balances: vec![Balance::default(); MAX_BALANCES].try_into().unwrap(),
             orders: vec![Order::default(); MAX_ORDERS].try_into().unwrap(),
@@ -162,6 +426,16 @@ impl ProofManager {
             wallet_randomness: randomness,
         };
 
+        Ok((witness, statement))
+    }
+
+    /// Create a proof of `VALID WALLET CREATE`
+    fn prove_valid_wallet_create(
+        fees: Vec<Fee>,
+        keys: KeyChain,
+        randomness: Scalar,
+    ) -> Result<ValidWalletCreateBundle, ProofManagerError> {
+        let (witness, statement) = Self::build_valid_wallet_create(fees, keys, randomness)?;
         let (commitment, proof) = singleprover_prove::<
             ValidWalletCreate<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
         >(witness, statement)
@@ -174,23 +448,37 @@ impl ProofManager {
         })
     }
 
-    /// Create a proof of `VALID COMMITMENTS`
+    /// Create a proof of `VALID COMMITMENTS`, or serve it from the cache if the same
+    /// witness has already been proven against the statement
     #[allow(clippy::too_many_arguments)]
     fn prove_valid_commitments(
         witness: SizedValidCommitmentsWitness,
         statement: ValidCommitmentsStatement,
+        proof_cache: &ProofCache,
     ) -> Result<ValidCommitmentsBundle, ProofManagerError> {
+        if let Some(bundle) = proof_cache.get(&witness, &statement) {
+            log::info!("serving proof of VALID COMMITMENTS from cache");
+            return Ok(bundle);
+        }
+
         // Prove the statement `VALID COMMITMENTS`
         let (witness_comm, proof) = singleprover_prove::<
             ValidCommitments<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
-        >(witness, statement)
+        >(witness.clone(), statement)
         .map_err(|err| ProofManagerError::Prover(err.to_string()))?;
 
-        Ok(ValidCommitmentsBundle {
+        let bundle = ValidCommitmentsBundle {
             commitment: witness_comm,
             statement,
             proof,
-        })
+        };
+
+        // A failure to cache the proof does not invalidate it
+        if let Err(err) = proof_cache.insert(witness, bundle.clone()) {
+            log::warn!("error caching proof of VALID COMMITMENTS: {}", err);
+        }
+
+        Ok(bundle)
     }
 
     /// Create a proof of `VALID MATCH ENCRYPTION`
@@ -212,4 +500,202 @@ impl ProofManager {
             proof,
         })
     }
+
+    /// Create a proof of `VALID WALLET UPDATE`
+    fn prove_valid_wallet_update(
+        witness: SizedValidWalletUpdateWitness,
+        statement: ValidWalletUpdateStatement,
+    ) -> Result<ValidWalletUpdateBundle, ProofManagerError> {
+        let (witness_comm, proof) = singleprover_prove::<
+            ValidWalletUpdate<MAX_BALANCES, MAX_ORDERS, MAX_FEES>,
+        >(witness, statement.clone())
+        .map_err(|err| ProofManagerError::Prover(err.to_string()))?;
+
+        Ok(ValidWalletUpdateBundle {
+            commitment: witness_comm,
+            statement,
+            proof,
+        })
+    }
+
+    /// Create a proof of `VALID SETTLE`
+    fn prove_valid_settle(
+        witness: SizedValidSettleWitness,
+        statement: SizedValidSettleStatement,
+    ) -> Result<ValidSettleBundle, ProofManagerError> {
+        let (witness_comm, proof) =
+            singleprover_prove::<SizedValidSettle>(witness, statement.clone())
+                .map_err(|err| ProofManagerError::Prover(err.to_string()))?;
+
+        Ok(ValidSettleBundle {
+            commitment: witness_comm,
+            statement,
+            proof,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::{
+        native_helpers::{
+            compute_note_commitment, compute_note_redeem_nullifier, compute_poseidon_hash,
+            compute_wallet_commitment, compute_wallet_match_nullifier,
+            compute_wallet_spend_nullifier,
+        },
+        types::{
+            balance::Balance,
+            fee::Fee,
+            keychain::KeyChain,
+            note::{Note, NoteType},
+            order::{Order, OrderSide},
+        },
+        zk_gadgets::{elgamal::ElGamalCiphertext, merkle::MerkleOpening},
+        MAX_BALANCES, MAX_ORDERS,
+    };
+    use crypto::fields::{prime_field_to_scalar, scalar_to_prime_field};
+    use curve25519_dalek::scalar::Scalar;
+
+    use crate::{
+        proof_generation::{jobs::ValidSettleBundle, proof_cache::ProofCache},
+        types::{SizedValidSettleStatement, SizedValidSettleWitness},
+        SizedWallet, MAX_FEES,
+    };
+
+    use super::{ProofJob, ProofManager};
+
+    /// The number of ciphertexts in the encryption of a wallet
+    const WALLET_CIPHERTEXTS: usize = 2 * MAX_BALANCES + 8 * MAX_ORDERS + 4 * MAX_FEES + 5;
+
+    /// Build a witness and statement of `VALID SETTLE` for a note that buys one unit of
+    /// mint 2 for four units of mint 1, paying a fee of three units of mint 2
+    ///
+    /// The state tree holds only the pre-settlement wallet and the note, side by side
+    fn settle_witness_and_statement() -> (SizedValidSettleWitness, SizedValidSettleStatement) {
+        let sk_settle = Scalar::from(1729u64);
+        let mut balances = vec![Balance::default(); MAX_BALANCES];
+        balances[0] = Balance {
+            mint: 1u8.into(),
+            amount: 10,
+        };
+        balances[1] = Balance {
+            mint: 2u8.into(),
+            amount: 10,
+        };
+        let mut orders = vec![Order::default(); MAX_ORDERS];
+        orders[0] = Order {
+            quote_mint: 1u8.into(),
+            base_mint: 2u8.into(),
+            side: OrderSide::Buy,
+            amount: 5,
+            ..Default::default()
+        };
+
+        let pre_wallet = SizedWallet {
+            balances: balances.try_into().unwrap(),
+            orders: orders.try_into().unwrap(),
+            fees: vec![Fee::default(); MAX_FEES].try_into().unwrap(),
+            keys: KeyChain {
+                pk_root: Scalar::one(),
+                pk_match: Scalar::one(),
+                pk_settle: compute_poseidon_hash(&[sk_settle]),
+                pk_view: Scalar::one(),
+            },
+            randomness: Scalar::from(42u64),
+        };
+        let note = Note {
+            mint1: 2u8.into(),
+            volume1: 1,
+            direction1: OrderSide::Buy,
+            mint2: 1u8.into(),
+            volume2: 4,
+            direction2: OrderSide::Sell,
+            fee_mint: 2u8.into(),
+            fee_volume: 3,
+            fee_direction: OrderSide::Sell,
+            type_: NoteType::Match,
+            randomness: 7u8.into(),
+        };
+
+        let mut post_wallet = pre_wallet.clone();
+        post_wallet.balances[0].amount = 6;
+        post_wallet.balances[1].amount = 8;
+        post_wallet.orders[0].amount = 4;
+        post_wallet.randomness += Scalar::from(2u64);
+
+        // Place the wallet and the note in a tree of height one
+        let pre_wallet_commit = compute_wallet_commitment(&pre_wallet);
+        let note_commit = compute_note_commitment(&note, pre_wallet.keys.pk_settle);
+        let wallet_leaf = prime_field_to_scalar(&pre_wallet_commit);
+        let note_leaf = prime_field_to_scalar(&note_commit);
+        let merkle_root = compute_poseidon_hash(&[wallet_leaf, note_leaf]);
+
+        let statement = SizedValidSettleStatement {
+            post_wallet_commit: prime_field_to_scalar(&compute_wallet_commitment(&post_wallet)),
+            post_wallet_ciphertext: vec![
+                ElGamalCiphertext {
+                    partial_shared_secret: Scalar::one(),
+                    encrypted_message: Scalar::one(),
+                };
+                WALLET_CIPHERTEXTS
+            ]
+            .try_into()
+            .unwrap(),
+            wallet_spend_nullifier: prime_field_to_scalar(&compute_wallet_spend_nullifier(
+                &pre_wallet,
+                pre_wallet_commit,
+            )),
+            wallet_match_nullifier: prime_field_to_scalar(&compute_wallet_match_nullifier(
+                &pre_wallet,
+                pre_wallet_commit,
+            )),
+            note_redeem_nullifier: prime_field_to_scalar(&compute_note_redeem_nullifier(
+                note_commit,
+                scalar_to_prime_field(&pre_wallet.keys.pk_settle),
+            )),
+            merkle_root,
+            type_: note.type_,
+        };
+        let witness = SizedValidSettleWitness {
+            pre_wallet,
+            pre_wallet_opening: MerkleOpening {
+                elems: vec![note_leaf],
+                indices: vec![Scalar::zero()],
+            },
+            post_wallet,
+            note,
+            note_commitment: note_leaf,
+            note_opening: MerkleOpening {
+                elems: vec![wallet_leaf],
+                indices: vec![Scalar::one()],
+            },
+            sk_settle,
+        };
+
+        (witness, statement)
+    }
+
+    /// Prove `VALID SETTLE` through the proof manager's job handler
+    fn prove_settle() -> ValidSettleBundle {
+        let (witness, statement) = settle_witness_and_statement();
+        let proof_cache = ProofCache::new(None).unwrap();
+        ProofManager::handle_proof_job(ProofJob::ValidSettle { witness, statement }, &proof_cache)
+            .unwrap()
+            .into()
+    }
+
+    /// Tests that a proof of `VALID SETTLE` generated by the proof manager verifies
+    #[test]
+    fn test_valid_settle_round_trip() {
+        prove_settle().verify().unwrap();
+    }
+
+    /// Tests that a proof of `VALID SETTLE` does not verify against a different statement
+    #[test]
+    fn test_valid_settle_wrong_statement() {
+        let mut bundle = prove_settle();
+        bundle.statement.post_wallet_commit += Scalar::one();
+
+        assert!(bundle.verify().is_err());
+    }
 }
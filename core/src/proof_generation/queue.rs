@@ -0,0 +1,190 @@
+//! The proof manager's queue of jobs waiting on a prover, ordered by priority and then
+//! by arrival
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+use tokio::sync::oneshot;
+
+use super::{
+    jobs::{ProofJobKey, ProofJobPriority, ProofManagerJob},
+    journal::RecoveredJob,
+};
+
+/// A job taken off the proof manager's queue and journaled
+#[derive(Debug)]
+pub(super) struct JournaledJob {
+    /// The idempotency key of the job
+    pub key: ProofJobKey,
+    /// Whether the job was replayed from the journal, in which case nobody is waiting on
+    /// its response channel
+    pub recovered: bool,
+    /// The job itself
+    pub job: ProofManagerJob,
+}
+
+impl From<RecoveredJob> for JournaledJob {
+    fn from(recovered: RecoveredJob) -> Self {
+        // The receiver is dropped, the result of a replayed job is only delivered if its
+        // requester re-submits it
+        let (response_channel, _) = oneshot::channel();
+        Self {
+            key: recovered.key,
+            recovered: true,
+            job: ProofManagerJob {
+                type_: recovered.job,
+                priority: recovered.priority,
+                cancellation: None,
+                response_channel,
+            },
+        }
+    }
+}
+
+/// A job waiting in the queue
+#[derive(Debug)]
+struct QueuedJob {
+    /// The priority of the job
+    priority: ProofJobPriority,
+    /// The order in which the job arrived, used to break ties between jobs of equal
+    /// priority
+    sequence: u64,
+    /// The job itself
+    job: JournaledJob,
+}
+
+impl QueuedJob {
+    /// The key that the queue orders jobs by, greatest first
+    fn key(&self) -> (ProofJobPriority, Reverse<u64>) {
+        (self.priority, Reverse(self.sequence))
+    }
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for QueuedJob {}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The jobs waiting on a prover
+#[derive(Debug, Default)]
+pub(super) struct PendingJobs {
+    /// The sequence number to assign the next job
+    next_sequence: u64,
+    /// The jobs, ordered by priority and then by arrival
+    heap: BinaryHeap<QueuedJob>,
+}
+
+impl PendingJobs {
+    /// Whether any jobs are waiting
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Enqueue a job
+    pub fn push(&mut self, job: JournaledJob) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.heap.push(QueuedJob {
+            priority: job.job.priority,
+            sequence,
+            job,
+        });
+    }
+
+    /// Dequeue the highest priority job, the earliest to arrive among equals
+    pub fn pop(&mut self) -> Option<JournaledJob> {
+        self.heap.pop().map(|queued| queued.job)
+    }
+
+    /// Remove a recovered job with the given key that has not yet run, returning whether
+    /// one was waiting
+    pub fn remove_recovered(&mut self, key: &ProofJobKey) -> bool {
+        let queued = std::mem::take(&mut self.heap).into_vec();
+        let n_queued = queued.len();
+        self.heap = queued
+            .into_iter()
+            .filter(|queued| !(queued.job.recovered && &queued.job.key == key))
+            .collect();
+
+        self.heap.len() != n_queued
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::types::keychain::KeyChain;
+    use curve25519_dalek::scalar::Scalar;
+    use tokio::sync::oneshot;
+
+    use crate::proof_generation::jobs::{ProofJob, ProofJobPriority, ProofManagerJob};
+
+    use super::{JournaledJob, PendingJobs};
+
+    /// Build a job of the given priority, tagged by its randomness
+    fn job(priority: ProofJobPriority, tag: u64) -> JournaledJob {
+        let (response_channel, _) = oneshot::channel();
+        let type_ = ProofJob::ValidWalletCreate {
+            fees: Vec::new(),
+            keys: KeyChain {
+                pk_root: Scalar::zero(),
+                pk_match: Scalar::zero(),
+                pk_settle: Scalar::zero(),
+                pk_view: Scalar::zero(),
+            },
+            randomness: Scalar::from(tag),
+        };
+
+        JournaledJob {
+            key: type_.idempotency_key(),
+            recovered: false,
+            job: ProofManagerJob {
+                type_,
+                priority,
+                cancellation: None,
+                response_channel,
+            },
+        }
+    }
+
+    /// Tests that jobs are dequeued highest priority first, and in arrival order among
+    /// jobs of equal priority
+    #[test]
+    fn test_priority_order() {
+        let mut pending = PendingJobs::default();
+        pending.push(job(ProofJobPriority::Background, 0));
+        pending.push(job(ProofJobPriority::Settlement, 1));
+        pending.push(job(ProofJobPriority::UserInitiated, 2));
+        pending.push(job(ProofJobPriority::Settlement, 3));
+        pending.push(job(ProofJobPriority::Background, 4));
+
+        let mut order = Vec::new();
+        while let Some(next) = pending.pop() {
+            if let ProofJob::ValidWalletCreate { randomness, .. } = next.job.type_ {
+                order.push(randomness);
+            }
+        }
+
+        let expected: Vec<Scalar> = [2u64, 1, 3, 0, 4]
+            .iter()
+            .map(|tag| Scalar::from(*tag))
+            .collect();
+        assert_eq!(order, expected);
+    }
+}
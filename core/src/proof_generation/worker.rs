@@ -6,25 +6,39 @@ use std::{
     thread::{Builder, JoinHandle},
 };
 
-use crossbeam::channel::Receiver;
+use crossbeam::channel;
 use rayon::ThreadPoolBuilder;
 
-use crate::{worker::Worker, CancelChannel};
+use crate::{
+    default_wrapper::DefaultWrapper, job_queue::JobQueueReceiver, worker::Worker, CancelChannel,
+};
 
 use super::{
+    dead_letter::DeadLetterQueue,
     error::ProofManagerError,
     jobs::ProofManagerJob,
+    journal::ProofJournal,
+    proof_cache::ProofCache,
     proof_manager::{ProofManager, PROOF_GENERATION_N_THREADS},
 };
 
 /// The name of the main worker thread
 const MAIN_THREAD_NAME: &str = "proof-generation-main";
+/// The name of the thread that journals jobs as they arrive
+const INTAKE_THREAD_NAME: &str = "proof-generation-intake";
 
 /// The configuration of the manager, used to hold work queues and tunables
 #[derive(Clone, Debug)]
 pub struct ProofManagerConfig {
     /// The job queue on which the manager may receive proof generation jobs
-    pub job_queue: Receiver<ProofManagerJob>,
+    pub job_queue: DefaultWrapper<Option<JobQueueReceiver<ProofManagerJob>>>,
+    /// The queue on which to record jobs abandoned for exceeding their time budget
+    pub dead_letter_queue: DeadLetterQueue,
+    /// The cache of proofs of `VALID COMMITMENTS`, shared with the global state
+    pub proof_cache: ProofCache,
+    /// The file that jobs are journaled to so that they survive a restart; jobs are not
+    /// journaled if unset
+    pub journal_file: Option<String>,
     /// The cancel channel that the coordinator uses to signal to the proof generation
     /// module that it should shut down
     pub cancel_channel: CancelChannel,
@@ -34,7 +48,7 @@ impl Worker for ProofManager {
     type WorkerConfig = ProofManagerConfig;
     type Error = ProofManagerError;
 
-    fn new(config: Self::WorkerConfig) -> Result<Self, Self::Error>
+    fn new(mut config: Self::WorkerConfig) -> Result<Self, Self::Error>
     where
         Self: Sized,
     {
@@ -43,11 +57,16 @@ impl Worker for ProofManager {
             .num_threads(PROOF_GENERATION_N_THREADS)
             .build()
             .map_err(|err| ProofManagerError::Setup(err.to_string()))?;
+        let journal = ProofJournal::open(config.journal_file)?;
 
         Ok(Self {
-            job_queue: Some(config.job_queue),
+            job_queue: config.job_queue.take(),
             join_handle: None,
+            intake_join_handle: None,
             thread_pool: Arc::new(proof_generation_thread_pool),
+            dead_letter_queue: config.dead_letter_queue,
+            proof_cache: config.proof_cache,
+            journal,
             cancel_channel: config.cancel_channel,
         })
     }
@@ -64,16 +83,40 @@ impl Worker for ProofManager {
         // Take ownership of the thread pool and job queue
         let job_queue = self.job_queue.take().unwrap();
         let thread_pool = self.thread_pool.clone();
+        let dead_letter_queue = self.dead_letter_queue.clone();
+        let proof_cache = self.proof_cache.clone();
         let cancel_channel = self.cancel_channel.clone();
+
+        // Replay the jobs left unfinished by the previous run ahead of any new jobs
+        let (intake_sender, intake_receiver) = channel::unbounded();
+        for recovered in self.journal.take_recovered() {
+            intake_sender.send(recovered.into()).unwrap();
+        }
+
+        let intake_journal = self.journal.clone();
+        let intake_handle = Builder::new()
+            .name(INTAKE_THREAD_NAME.to_string())
+            .spawn(move || Self::intake_loop(job_queue, intake_journal, intake_sender))
+            .map_err(|err| ProofManagerError::Setup(err.to_string()))?;
+
+        let journal = self.journal.clone();
         let handle = Builder::new()
             .name(MAIN_THREAD_NAME.to_string())
             .spawn(move || {
-                Self::execution_loop(job_queue, thread_pool, cancel_channel)
-                    .err()
-                    .unwrap()
+                Self::execution_loop(
+                    intake_receiver,
+                    thread_pool,
+                    dead_letter_queue,
+                    proof_cache,
+                    journal,
+                    cancel_channel,
+                )
+                .err()
+                .unwrap()
             })
             .map_err(|err| ProofManagerError::Setup(err.to_string()))?;
 
+        self.intake_join_handle = Some(intake_handle);
         self.join_handle = Some(handle);
         Ok(())
     }
@@ -83,6 +126,9 @@ impl Worker for ProofManager {
     }
 
     fn join(&mut self) -> Vec<JoinHandle<Self::Error>> {
-        vec![self.join_handle.take().unwrap()]
+        vec![
+            self.join_handle.take().unwrap(),
+            self.intake_join_handle.take().unwrap(),
+        ]
     }
 }
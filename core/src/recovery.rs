@@ -0,0 +1,387 @@
+//! Recovers a managed wallet from on-chain data and its keys alone
+//!
+//! Each wallet update posts the new wallet encrypted under its view key, which the
+//! contract emits in a `Wallet_ciphertext_posted` event. Recovery replays the contract's
+//! leaf insertions into a fresh mirror of the commitment tree, trial-decrypts every posted
+//! ciphertext with the view key, and keeps the wallet whose commitment was inserted last.
+//! Neither the relayer's state nor the chain listener's mirror is consulted, both may be
+//! lost or out of sync when recovery is needed
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::{TryFrom, TryInto},
+    fmt::{self, Display},
+    str::FromStr,
+    sync::atomic::AtomicU32,
+};
+
+use circuits::{
+    native_helpers::compute_wallet_commitment,
+    types::{
+        balance::Balance,
+        fee::Fee,
+        keychain::{KeyChain, NUM_KEYS},
+        order::{MatchConstraints, Order, OrderSide, TimeInForce},
+    },
+    zk_gadgets::fixed_point::FixedPoint,
+};
+use crypto::fields::{
+    biguint_to_scalar, prime_field_to_scalar, scalar_to_biguint, starknet_felt_to_scalar,
+    starknet_felt_to_u64,
+};
+use curve25519_dalek::scalar::Scalar;
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use starknet::core::types::FieldElement as StarknetFieldElement;
+use starknet_providers::jsonrpc::models::EventFilter;
+use uuid::Uuid;
+
+use crate::{
+    chain_events::listener::{
+        EVENT_CHUNK_SIZE, MERKLE_VALUE_INSERTED_EVENT_SELECTOR,
+        WALLET_CIPHERTEXT_POSTED_EVENT_SELECTOR,
+    },
+    keychain::{
+        decrypt_under_view_key, derive_public_key, encrypt_under_view_key, verify_keychain,
+        WalletCiphertext,
+    },
+    starknet_client::{client::StarknetClient, contract_abi::parse_wallet_ciphertext},
+    state::{
+        merkle::{reduce_to_starknet_field, MerkleTreeMirror},
+        wallet::{
+            MerkleAuthenticationPath, PrivateKeyChain, Wallet, WalletIdentifier, WalletMetadata,
+        },
+    },
+    SizedWallet, MAX_BALANCES, MAX_FEES, MAX_ORDERS,
+};
+
+/// The number of scalars a wallet is encoded as; its identifier, two per balance, six
+/// per order, four per fee, its keys, and its randomness
+const WALLET_SCALARS: usize = 1 + MAX_BALANCES * 2 + MAX_ORDERS * 6 + MAX_FEES * 4 + NUM_KEYS + 1;
+
+/// An error recovering a wallet
+#[derive(Clone, Debug)]
+pub enum RecoveryError {
+    /// Recovery requires a JSON-RPC node to scan historical events
+    NoJsonRpc,
+    /// The configured contract address could not be parsed
+    ContractAddress(String),
+    /// An error fetching events from the JSON-RPC node
+    Rpc(String),
+    /// The contract's leaf insertions could not be replayed into the tree mirror
+    MerkleMirror(String),
+    /// No posted ciphertext decrypts under the view key to a wallet in the commitment tree
+    NotFound,
+    /// The recovered wallet's public keys do not match the given secret keys
+    Keychain(String),
+}
+
+impl Display for RecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// ------------
+// | Encoding |
+// ------------
+
+/// Encrypt a wallet under its view key, for posting alongside its commitment
+pub fn encrypt_wallet<R: RngCore + CryptoRng>(wallet: &Wallet, rng: &mut R) -> WalletCiphertext {
+    let circuit_wallet: SizedWallet = wallet.clone().into();
+    let plaintext = wallet_to_scalars(wallet.wallet_id, &circuit_wallet);
+    encrypt_under_view_key(wallet.secret_keys.sk_view, &plaintext, rng)
+}
+
+/// Encode a wallet and its identifier as scalars
+fn wallet_to_scalars(wallet_id: WalletIdentifier, wallet: &SizedWallet) -> Vec<Scalar> {
+    let mut scalars = vec![Scalar::from(wallet_id.as_u128())];
+    for balance in wallet.balances.iter() {
+        scalars.push(biguint_to_scalar(&balance.mint));
+        scalars.push(Scalar::from(balance.amount));
+    }
+
+    for order in wallet.orders.iter() {
+        scalars.extend_from_slice(&[
+            biguint_to_scalar(&order.quote_mint),
+            biguint_to_scalar(&order.base_mint),
+            Scalar::from(order.side as u64),
+            order.price.into(),
+            Scalar::from(order.amount),
+            Scalar::from(order.timestamp),
+        ]);
+    }
+
+    for fee in wallet.fees.iter() {
+        scalars.extend_from_slice(&[
+            biguint_to_scalar(&fee.settle_key),
+            biguint_to_scalar(&fee.gas_addr),
+            Scalar::from(fee.gas_token_amount),
+            fee.percentage_fee.into(),
+        ]);
+    }
+
+    scalars.extend(Into::<Vec<Scalar>>::into(wallet.keys));
+    scalars.push(wallet.randomness);
+    scalars
+}
+
+/// Decode a wallet and its identifier from scalars
+///
+/// Returns `None` if the scalars do not encode a wallet, as is the case for a ciphertext
+/// decrypted under the wrong key
+fn wallet_from_scalars(scalars: &[Scalar]) -> Option<(WalletIdentifier, SizedWallet)> {
+    if scalars.len() != WALLET_SCALARS {
+        return None;
+    }
+
+    let to_u64 = |value: Scalar| u64::try_from(&scalar_to_biguint(&value)).ok();
+    let wallet_id = Uuid::from_u128(u128::try_from(&scalar_to_biguint(&scalars[0])).ok()?);
+    let mut values = scalars[1..].iter().copied();
+
+    let mut balances = Vec::with_capacity(MAX_BALANCES);
+    for _ in 0..MAX_BALANCES {
+        balances.push(Balance {
+            mint: scalar_to_biguint(&values.next()?),
+            amount: to_u64(values.next()?)?,
+        });
+    }
+
+    let mut orders = Vec::with_capacity(MAX_ORDERS);
+    for _ in 0..MAX_ORDERS {
+        let quote_mint = scalar_to_biguint(&values.next()?);
+        let base_mint = scalar_to_biguint(&values.next()?);
+        let side = match to_u64(values.next()?)? {
+            0 => OrderSide::Buy,
+            1 => OrderSide::Sell,
+            _ => return None,
+        };
+
+        orders.push(Order {
+            quote_mint,
+            base_mint,
+            side,
+            price: FixedPoint::from(values.next()?),
+            amount: to_u64(values.next()?)?,
+            timestamp: to_u64(values.next()?)?,
+            time_in_force: TimeInForce::default(),
+            constraints: MatchConstraints::default(),
+        });
+    }
+
+    let mut fees = Vec::with_capacity(MAX_FEES);
+    for _ in 0..MAX_FEES {
+        fees.push(Fee {
+            settle_key: scalar_to_biguint(&values.next()?),
+            gas_addr: scalar_to_biguint(&values.next()?),
+            gas_token_amount: to_u64(values.next()?)?,
+            percentage_fee: FixedPoint::from(values.next()?),
+        });
+    }
+
+    let keys = KeyChain::try_from(values.by_ref().take(NUM_KEYS).collect_vec()).ok()?;
+    let randomness = values.next()?;
+
+    Some((
+        wallet_id,
+        SizedWallet {
+            balances: balances.try_into().ok()?,
+            orders: orders.try_into().ok()?,
+            fees: fees.try_into().ok()?,
+            keys,
+            randomness,
+        },
+    ))
+}
+
+/// Decrypt a posted ciphertext under a view key, returning the wallet if the ciphertext
+/// was encrypted to the key
+fn try_decrypt_wallet(
+    ciphertext: &WalletCiphertext,
+    sk_view: Scalar,
+) -> Option<(WalletIdentifier, SizedWallet)> {
+    let (wallet_id, wallet) = wallet_from_scalars(&decrypt_under_view_key(sk_view, ciphertext))?;
+    if wallet.keys.pk_view != derive_public_key(sk_view) {
+        return None;
+    }
+
+    Some((wallet_id, wallet))
+}
+
+// ------------
+// | Recovery |
+// ------------
+
+/// Recover the latest state of the wallet that the given keys belong to from chain
+///
+/// The returned wallet holds an opening of its commitment against the current root, its
+/// orders are assigned fresh identifiers as order identifiers are never posted
+pub async fn recover_wallet(
+    starknet_client: &StarknetClient,
+    secret_keys: PrivateKeyChain,
+) -> Result<Wallet, RecoveryError> {
+    if !starknet_client.jsonrpc_enabled() {
+        return Err(RecoveryError::NoJsonRpc);
+    }
+
+    let contract_address = StarknetFieldElement::from_str(&starknet_client.config.contract_addr)
+        .map_err(|err| RecoveryError::ContractAddress(err.to_string()))?;
+    let filter = EventFilter {
+        from_block: None,
+        to_block: None,
+        address: Some(contract_address),
+        keys: Some(vec![
+            *MERKLE_VALUE_INSERTED_EVENT_SELECTOR,
+            *WALLET_CIPHERTEXT_POSTED_EVENT_SELECTOR,
+        ]),
+    };
+
+    // Replay the commitment tree, decrypting every ciphertext along the way
+    let mut mirror = MerkleTreeMirror::new();
+    let mut decrypted = Vec::new();
+    let mut pagination_token = Some("0".to_string());
+    while pagination_token.is_some() {
+        let events_batch = starknet_client
+            .get_jsonrpc_client()
+            .get_events(filter.clone(), pagination_token, EVENT_CHUNK_SIZE)
+            .await
+            .map_err(|err| RecoveryError::Rpc(err.to_string()))?;
+
+        for event in events_batch.events.iter() {
+            if event.keys[0] == *MERKLE_VALUE_INSERTED_EVENT_SELECTOR {
+                let index = starknet_felt_to_u64(&event.data[0]);
+                let value = starknet_felt_to_scalar(&event.data[1]);
+                mirror
+                    .insert(index, value)
+                    .map_err(|err| RecoveryError::MerkleMirror(err.to_string()))?;
+            } else if let Some(wallet) = parse_wallet_ciphertext(&event.data)
+                .and_then(|ciphertext| try_decrypt_wallet(&ciphertext, secret_keys.sk_view))
+            {
+                decrypted.push(wallet);
+            }
+        }
+
+        pagination_token = events_batch.continuation_token;
+    }
+
+    // Keep the decrypted wallet whose commitment was inserted last; a ciphertext whose
+    // update was never applied has no leaf
+    let (leaf_index, commitment, wallet_id, circuit_wallet) = decrypted
+        .into_iter()
+        .filter_map(|(wallet_id, circuit_wallet)| {
+            let commitment = prime_field_to_scalar(&compute_wallet_commitment(&circuit_wallet));
+            let leaf_index = mirror.find_leaf(&reduce_to_starknet_field(&commitment))?;
+            Some((leaf_index, commitment, wallet_id, circuit_wallet))
+        })
+        .max_by_key(|(leaf_index, ..)| *leaf_index)
+        .ok_or(RecoveryError::NotFound)?;
+
+    verify_keychain(&circuit_wallet.keys, &secret_keys)
+        .map_err(|err| RecoveryError::Keychain(err.to_string()))?;
+    let opening = mirror.opening(leaf_index).ok_or(RecoveryError::NotFound)?;
+
+    Ok(Wallet {
+        wallet_id,
+        orders: circuit_wallet
+            .orders
+            .iter()
+            .filter(|order| **order != Order::default())
+            .map(|order| (Uuid::new_v4(), order.clone()))
+            .collect(),
+        balances: circuit_wallet
+            .balances
+            .iter()
+            .filter(|balance| **balance != Balance::default())
+            .map(|balance| (balance.mint.clone(), balance.clone()))
+            .collect(),
+        fees: circuit_wallet
+            .fees
+            .iter()
+            .filter(|fee| **fee != Fee::default())
+            .cloned()
+            .collect_vec(),
+        public_keys: circuit_wallet.keys,
+        secret_keys,
+        randomness: scalar_to_biguint(&circuit_wallet.randomness),
+        metadata: WalletMetadata {
+            replicas: HashSet::new(),
+            version: 0,
+            auto_resubmit: HashMap::new(),
+        },
+        merkle_proof: Some(MerkleAuthenticationPath::new(
+            opening.path_siblings,
+            opening.leaf_index,
+            commitment,
+        )),
+        proof_staleness: AtomicU32::new(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::{
+        types::{
+            balance::Balance,
+            fee::Fee,
+            order::{MatchConstraints, Order, OrderSide, TimeInForce},
+        },
+        zk_gadgets::fixed_point::FixedPoint,
+    };
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
+    use rand_core::OsRng;
+    use uuid::Uuid;
+
+    use crate::{keychain::derive_keychain, SizedWallet};
+
+    use super::{encrypt_under_view_key, try_decrypt_wallet, wallet_to_scalars};
+
+    /// Tests that a wallet encrypted under its view key decrypts to the same wallet, and
+    /// that it does not decrypt under another key
+    #[test]
+    fn test_wallet_ciphertext_roundtrip() {
+        let mut rng = OsRng {};
+        let (public_keys, secret_keys) = derive_keychain(Scalar::random(&mut rng));
+
+        let mut wallet = SizedWallet {
+            balances: Default::default(),
+            orders: Default::default(),
+            fees: Default::default(),
+            keys: public_keys,
+            randomness: Scalar::random(&mut rng),
+        };
+        wallet.balances[0] = Balance {
+            mint: BigUint::from(2u8),
+            amount: 50,
+        };
+        wallet.orders[1] = Order {
+            quote_mint: BigUint::from(1u8),
+            base_mint: BigUint::from(2u8),
+            side: OrderSide::Sell,
+            price: FixedPoint::from_integer(20),
+            amount: 10,
+            timestamp: 1_680_000_000,
+            time_in_force: TimeInForce::GoodTilCancelled,
+            constraints: MatchConstraints::default(),
+        };
+        wallet.fees[0] = Fee {
+            settle_key: BigUint::from(7u8),
+            gas_addr: BigUint::from(1u8),
+            gas_token_amount: 3,
+            percentage_fee: FixedPoint::from_f32_round_down(0.01),
+        };
+
+        let wallet_id = Uuid::new_v4();
+        let ciphertext = encrypt_under_view_key(
+            secret_keys.sk_view,
+            &wallet_to_scalars(wallet_id, &wallet),
+            &mut rng,
+        );
+
+        assert_eq!(
+            try_decrypt_wallet(&ciphertext, secret_keys.sk_view),
+            Some((wallet_id, wallet))
+        );
+        assert!(try_decrypt_wallet(&ciphertext, secret_keys.sk_match).is_none());
+    }
+}
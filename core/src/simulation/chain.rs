@@ -0,0 +1,203 @@
+//! A mock of the contract that settlements are submitted to
+//!
+//! The mock accepts every settlement it is not configured to turn away, recording it in
+//! place of broadcasting a transaction. Fees are sampled around a base fee, and a
+//! configurable fraction of fee estimates fail as a congested sequencer's would, so that
+//! the settlement backoff may be exercised. Match commitments are always accepted, and only
+//! counted
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use crypto::fields::biguint_to_starknet_felt;
+use num_bigint::BigUint;
+use starknet::core::types::FieldElement as StarknetFieldElement;
+use tokio::sync::mpsc::UnboundedSender as TokioSender;
+
+use crate::{
+    clock::SharedClock,
+    rng::WorkerRng,
+    starknet_client::{
+        client::StarknetApi, error::StarknetClientError, transaction_manager::TransactionFailedJob,
+    },
+};
+
+/// The error a congested fee estimate fails with, matched by the settlement backoff
+const ERR_CONGESTED: &str = "503 service unavailable: sequencer congested";
+/// The resolution at which the congestion rate is sampled
+const CONGESTION_RESOLUTION: u64 = 10_000;
+/// The offset that match commitment transactions are numbered from, apart from settlements
+const COMMITMENT_TX_HASH_OFFSET: u64 = 1 << 32;
+
+/// A settlement accepted by the mock chain
+#[derive(Clone, Debug)]
+pub struct MockSettlement {
+    /// The hash the settlement's transaction was assigned
+    pub tx_hash: StarknetFieldElement,
+    /// The fee the settlement was estimated at, in wei
+    pub fee: u64,
+    /// The number of calldata words in the settlement, proof included
+    pub calldata_len: usize,
+    /// The time at which the settlement was accepted, in seconds since the epoch
+    pub submitted_at: u64,
+}
+
+/// The mutable state of the mock chain, shared between clones
+#[derive(Debug, Default)]
+struct MockChainState {
+    /// The settlements accepted so far, in the order they were submitted
+    settlements: Vec<MockSettlement>,
+    /// The fee of the most recent estimate, charged to the next settlement
+    last_estimate: u64,
+    /// The number of fee estimates turned away for congestion
+    congested_estimates: u64,
+    /// The number of match commitments accepted so far
+    match_commitments: u64,
+}
+
+/// A mock of the contract that records settlements in place of submitting them
+#[derive(Clone, Debug)]
+pub struct MockStarknetClient {
+    /// The fee every estimate is sampled above, in wei
+    base_fee: u64,
+    /// The maximum amount sampled on top of the base fee, in wei
+    fee_jitter: u64,
+    /// The fraction of fee estimates that fail as congested
+    congestion_rate: f64,
+    /// The source of randomness for fees and congestion
+    rng: WorkerRng,
+    /// The clock settlements are timestamped with
+    clock: SharedClock,
+    /// The settlements and counters recorded so far
+    state: Arc<Mutex<MockChainState>>,
+}
+
+impl MockStarknetClient {
+    /// Constructor
+    pub fn new(
+        base_fee: u64,
+        fee_jitter: u64,
+        congestion_rate: f64,
+        rng: WorkerRng,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            base_fee,
+            fee_jitter,
+            congestion_rate: congestion_rate.clamp(0., 1.),
+            rng,
+            clock,
+            state: Arc::new(Mutex::new(MockChainState::default())),
+        }
+    }
+
+    /// The settlements accepted so far, in the order they were submitted
+    pub fn settlements(&self) -> Vec<MockSettlement> {
+        self.state.lock().unwrap().settlements.clone()
+    }
+
+    /// The number of fee estimates turned away for congestion
+    pub fn congested_estimates(&self) -> u64 {
+        self.state.lock().unwrap().congested_estimates
+    }
+
+    /// The number of match commitments accepted so far
+    pub fn match_commitments(&self) -> u64 {
+        self.state.lock().unwrap().match_commitments
+    }
+}
+
+#[async_trait]
+impl StarknetApi for MockStarknetClient {
+    fn account_enabled(&self) -> bool {
+        true
+    }
+
+    async fn commit_match(
+        &self,
+        _calldata: Vec<StarknetFieldElement>,
+        _failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let mut locked_state = self.state.lock().unwrap();
+        locked_state.match_commitments += 1;
+
+        // Commitments are numbered apart from settlements, so that settlement hashes are
+        // unaffected by them
+        Ok(biguint_to_starknet_felt(&BigUint::from(
+            COMMITMENT_TX_HASH_OFFSET + locked_state.match_commitments,
+        )))
+    }
+
+    async fn estimate_settle_match_fee(
+        &self,
+        _calldata: Vec<StarknetFieldElement>,
+    ) -> Result<u64, StarknetClientError> {
+        let threshold = (self.congestion_rate * CONGESTION_RESOLUTION as f64) as u64;
+        let mut locked_state = self.state.lock().unwrap();
+        if self.rng.gen_range(0..CONGESTION_RESOLUTION) < threshold {
+            locked_state.congested_estimates += 1;
+            return Err(StarknetClientError::Transaction(ERR_CONGESTED.to_string()));
+        }
+
+        let fee = self.base_fee + self.rng.gen_range(0..self.fee_jitter + 1);
+        locked_state.last_estimate = fee;
+        Ok(fee)
+    }
+
+    async fn settle_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        _failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let mut locked_state = self.state.lock().unwrap();
+
+        // Transactions are numbered in the order they are accepted, so that a run's hashes
+        // are reproducible
+        let tx_hash =
+            biguint_to_starknet_felt(&BigUint::from(locked_state.settlements.len() as u64 + 1));
+        let settlement = MockSettlement {
+            tx_hash,
+            fee: locked_state.last_estimate,
+            calldata_len: calldata.len(),
+            submitted_at: self.clock.unix_secs(),
+        };
+        locked_state.settlements.push(settlement);
+
+        Ok(tx_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use crate::{clock::ManualClock, rng::WorkerRng, starknet_client::client::StarknetApi};
+
+    use super::MockStarknetClient;
+
+    /// Tests that the mock charges fees within the configured range and numbers its
+    /// transactions in order
+    #[tokio::test]
+    async fn test_mock_settlement() {
+        let clock = Arc::new(ManualClock::new(Duration::from_secs(100)));
+        let chain = MockStarknetClient::new(1_000, 10, 0., WorkerRng::new(Some(1)), clock);
+
+        let (failure_sender, _failure_receiver) = unbounded_channel();
+        for _ in 0..3 {
+            let fee = chain.estimate_settle_match_fee(vec![]).await.unwrap();
+            assert!((1_000..=1_010).contains(&fee));
+            chain
+                .settle_match(vec![], failure_sender.clone())
+                .await
+                .unwrap();
+        }
+
+        let settlements = chain.settlements();
+        assert_eq!(settlements.len(), 3);
+        assert_ne!(settlements[0].tx_hash, settlements[2].tx_hash);
+        assert_eq!(settlements[1].submitted_at, 100);
+        assert_eq!(chain.congested_estimates(), 0);
+    }
+}
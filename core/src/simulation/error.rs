@@ -0,0 +1,20 @@
+//! Groups error types originating from the simulation harness
+
+use std::fmt::Display;
+
+/// The core error type for the simulation harness
+#[derive(Clone, Debug)]
+pub enum SimulationError {
+    /// The recorded price feed could not be loaded, or holds no reports
+    PriceFeed(String),
+    /// A simulated worker could not be built or started
+    Setup(String),
+    /// A synthetic wallet could not be placed in the simulated state
+    OrderPlacement(String),
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
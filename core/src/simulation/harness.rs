@@ -0,0 +1,501 @@
+//! Allocates the simulated relayers and drives them through the rounds of a run
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel;
+use ed25519_dalek::{PublicKey, SecretKey};
+use tokio::sync::watch::{self, Sender as WatchSender};
+use tracing::log;
+
+use crate::{
+    clock::{system_clock, Clock, ManualClock, SharedClock},
+    fee_schedule::FeeSchedule,
+    gossip::types::ClusterId,
+    handshake::{
+        jobs::HandshakeExecutionJob, manager::HandshakeManager, state::HandshakeStateIndex,
+        worker::HandshakeManagerConfig,
+    },
+    job_queue::{
+        job_queue, JobQueueSender, DEFAULT_JOB_QUEUE_CAPACITY, HANDSHAKE_PRIORITY_QUEUE,
+        HANDSHAKE_QUEUE, PRICE_REPORTER_QUEUE, PROOF_MANAGER_QUEUE,
+    },
+    price_reporter::{
+        exchanges::ExchangeRegion, history::PriceHistoryConfig, jobs::PriceReporterManagerJob,
+        manager::PriceReporterManager, quarantine::DeviationQuarantineConfig, replay::RecordedFeed,
+        reporter::PriceReporterState, tokens::Token, worker::PriceReporterManagerConfig,
+    },
+    proof_generation::{
+        dead_letter::DeadLetterQueue, jobs::ProofManagerJob, proof_cache::ProofCache,
+        proof_manager::ProofManager, worker::ProofManagerConfig,
+    },
+    rng::WorkerRng,
+    starknet_client::{
+        client::{StarknetClient, StarknetClientConfig},
+        ChainId,
+    },
+    state::{
+        cluster_access::ClusterAccessPolicy,
+        feature_flags::FeatureFlags,
+        merkle::{reduce_to_starknet_field, MerkleTreeMirror},
+        wallet::MerkleAuthenticationPath,
+        NetworkOrder, RelayerState,
+    },
+    system_bus::SystemBus,
+    worker::Worker,
+};
+
+use super::{
+    chain::MockStarknetClient,
+    error::SimulationError,
+    network::LoopbackNetwork,
+    order_flow::SyntheticOrderFlow,
+    report::{PriceSummary, SettlementSummary, SimulationReport},
+    SimulationConfig,
+};
+
+/// The contract address given to the price reporter's unused starknet client
+const UNUSED_CONTRACT_ADDR: &str = "0x0";
+/// The time to wait for the price reporter to answer a peek at the median
+const PRICE_PEEK_TIMEOUT_MS: u64 = 1_000;
+
+/// A relayer in the simulated network, a cluster of one
+struct SimulatedRelayer {
+    /// The relayer's state
+    state: RelayerState,
+    /// The source of the relayer's synthetic orders
+    order_flow: SyntheticOrderFlow,
+    /// The relayer's handshake manager, held so that its threads outlive the run
+    _handshake_manager: HandshakeManager,
+    /// The sender on the handshake manager's priority queue, held so that the queue
+    /// stays open
+    _priority_job_sender: JobQueueSender<HandshakeExecutionJob>,
+}
+
+/// A simulation run and the workers it drives
+pub struct Simulation {
+    /// The parameters of the run
+    config: SimulationConfig,
+    /// The clock the run's workers are driven by
+    clock: Arc<ManualClock>,
+    /// The source of randomness for order placement
+    rng: WorkerRng,
+    /// The pair the replayed feed quotes
+    pair: (Token, Token),
+    /// The number of rounds to run
+    rounds: u64,
+    /// The network connecting the relayers
+    network: LoopbackNetwork,
+    /// The mock contract that settlements are submitted to
+    chain: MockStarknetClient,
+    /// The commitment tree shared by all relayers' wallets
+    merkle_tree: MerkleTreeMirror,
+    /// The queue of the proof manager shared by all relayers
+    proof_manager_queue: JobQueueSender<ProofManagerJob>,
+    /// The queue of the price reporter manager replaying the feed
+    price_reporter_queue: JobQueueSender<PriceReporterManagerJob>,
+    /// The simulated relayers
+    relayers: Vec<SimulatedRelayer>,
+    /// The senders used to cancel every worker once the run ends
+    cancel_senders: Vec<WatchSender<()>>,
+    /// The shared workers, held so that their threads outlive the run
+    _shared_workers: (ProofManager, PriceReporterManager),
+    /// The number of orders placed so far
+    orders_placed: u64,
+    /// The number of orders that could not be proven so far
+    orders_unproven: u64,
+    /// The prices observed so far
+    prices: PriceSummary,
+}
+
+impl Simulation {
+    /// Allocate and start the workers of a run
+    pub fn new(config: SimulationConfig) -> Result<Self, SimulationError> {
+        let rng = WorkerRng::new(Some(config.seed));
+
+        // Replay the feed against a manual clock started at the feed's first report
+        let feed = RecordedFeed::load(&config.price_feed, system_clock())
+            .map_err(SimulationError::PriceFeed)?;
+        let pair = feed
+            .pair()
+            .ok_or_else(|| SimulationError::PriceFeed("feed holds no reports".to_string()))?;
+        let feed_start = feed.reports()[0].local_timestamp as u64;
+        let clock = Arc::new(ManualClock::new(Duration::from_millis(feed_start)));
+        let shared_clock: SharedClock = clock.clone();
+        let feed = RecordedFeed::new(feed.reports().to_vec(), shared_clock.clone());
+
+        let rounds = config.rounds.unwrap_or_else(|| {
+            let round_interval_ms = config.round_interval_ms.max(1);
+            (feed.duration().as_millis() as u64 + round_interval_ms - 1) / round_interval_ms
+        });
+
+        let mut cancel_senders = Vec::new();
+
+        // Start the price reporter manager on the replayed feed
+        let (price_reporter_queue, price_reporter_receiver) =
+            job_queue(PRICE_REPORTER_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        let (price_reporter_cancel_sender, price_reporter_cancel_receiver) = watch::channel(());
+        cancel_senders.push(price_reporter_cancel_sender);
+        let mut price_reporter_manager = PriceReporterManager::new(PriceReporterManagerConfig {
+            system_bus: SystemBus::new(),
+            job_receiver: Some(price_reporter_receiver).into(),
+            exchange_region: ExchangeRegion::Global,
+            exchange_allowlist: None,
+            coinbase_api_key: None,
+            coinbase_api_secret: None,
+            eth_websocket_addr: None,
+            uniswap_fee_tier: None,
+            uniswap_twap_window_secs: 0,
+            starknet_client: StarknetClient::new(StarknetClientConfig {
+                chain: ChainId::AlphaGoerli,
+                contract_addr: UNUSED_CONTRACT_ADDR.to_string(),
+                starknet_json_rpc_addr: None,
+                infura_api_key: None,
+                starknet_pkey: None,
+                starknet_account_addr: None,
+            }),
+            token_registry_address: None,
+            token_remap_file: None,
+            circuit_breakers: HashMap::new(),
+            deviation_quarantine: DeviationQuarantineConfig::default(),
+            price_history: PriceHistoryConfig::default(),
+            recorded_feed: Some(feed),
+            price_signing_keypair: None,
+            cancel_channel: price_reporter_cancel_receiver,
+        })
+        .map_err(|err| SimulationError::Setup(err.to_string()))?;
+        price_reporter_manager
+            .start()
+            .map_err(|err| SimulationError::Setup(err.to_string()))?;
+
+        // Start a proof manager shared by every relayer
+        let (proof_manager_queue, proof_manager_receiver) =
+            job_queue(PROOF_MANAGER_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        let (proof_manager_cancel_sender, proof_manager_cancel_receiver) = watch::channel(());
+        cancel_senders.push(proof_manager_cancel_sender);
+        let mut proof_manager = ProofManager::new(ProofManagerConfig {
+            job_queue: Some(proof_manager_receiver).into(),
+            dead_letter_queue: DeadLetterQueue::new(),
+            proof_cache: ProofCache::new(None)
+                .map_err(|err| SimulationError::Setup(err.to_string()))?,
+            journal_file: None,
+            cancel_channel: proof_manager_cancel_receiver,
+        })
+        .map_err(|err| SimulationError::Setup(err.to_string()))?;
+        proof_manager
+            .start()
+            .map_err(|err| SimulationError::Setup(err.to_string()))?;
+
+        // Start each relayer's handshake manager on the loopback network and mock chain
+        let network = LoopbackNetwork::new();
+        let chain = MockStarknetClient::new(
+            config.base_fee,
+            config.fee_jitter,
+            config.congestion_rate,
+            rng.fork(),
+            shared_clock.clone(),
+        );
+
+        let mut relayers = Vec::with_capacity(config.relayers);
+        for _ in 0..config.relayers {
+            let relayer = Self::start_relayer(
+                &config,
+                &pair,
+                &rng,
+                &shared_clock,
+                &network,
+                &chain,
+                &proof_manager_queue,
+                &price_reporter_queue,
+                &mut cancel_senders,
+            )?;
+            relayers.push(relayer);
+        }
+
+        Ok(Self {
+            config,
+            clock,
+            rng,
+            pair,
+            rounds,
+            network,
+            chain,
+            merkle_tree: MerkleTreeMirror::new(),
+            proof_manager_queue,
+            price_reporter_queue,
+            relayers,
+            cancel_senders,
+            _shared_workers: (proof_manager, price_reporter_manager),
+            orders_placed: 0,
+            orders_unproven: 0,
+            prices: PriceSummary::default(),
+        })
+    }
+
+    /// Allocate a relayer's state and start its handshake manager
+    #[allow(clippy::too_many_arguments)]
+    fn start_relayer(
+        config: &SimulationConfig,
+        pair: &(Token, Token),
+        rng: &WorkerRng,
+        clock: &SharedClock,
+        network: &LoopbackNetwork,
+        chain: &MockStarknetClient,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+        price_reporter_queue: &JobQueueSender<PriceReporterManagerJob>,
+        cancel_senders: &mut Vec<WatchSender<()>>,
+    ) -> Result<SimulatedRelayer, SimulationError> {
+        // Derive the relayer's cluster from the seed, so that a run's clusters are
+        // reproducible
+        let cluster_secret = SecretKey::from_bytes(&rng.gen_scalar().to_bytes())
+            .map_err(|err| SimulationError::Setup(err.to_string()))?;
+        let cluster_id = ClusterId::new(&PublicKey::from(&cluster_secret));
+
+        let system_bus = SystemBus::new();
+        let state = RelayerState::initialize_global_state(
+            false, /* debug */
+            vec![],
+            cluster_id,
+            ClusterAccessPolicy::new(None, vec![]),
+            system_bus.clone(),
+            ProofCache::new(None).map_err(|err| SimulationError::Setup(err.to_string()))?,
+            FeatureFlags::new(&HashMap::new()),
+        );
+
+        let (job_sender, job_receiver) = job_queue(HANDSHAKE_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        let (priority_job_sender, priority_job_receiver) =
+            job_queue(HANDSHAKE_PRIORITY_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        let (cancel_sender, cancel_receiver) = watch::channel(());
+        cancel_senders.push(cancel_sender);
+
+        let network_channel = network.join(state.local_peer_id(), job_sender.clone());
+        let mut handshake_manager = HandshakeManager::new(HandshakeManagerConfig {
+            global_state: state.clone(),
+            handshake_state_index: HandshakeStateIndex::new(state.clone(), clock.clone()),
+            network_channel,
+            job_sender,
+            job_receiver: Some(job_receiver),
+            priority_job_receiver: Some(priority_job_receiver),
+            proof_manager_sender: proof_manager_queue.clone(),
+            system_bus,
+            mpc_timeout_ms: config.mpc_timeout_ms,
+            size_bucket_check: config.size_bucket_check,
+            handshake_prescreen: true,
+            max_concurrent_mpcs: config.max_concurrent_mpcs,
+            max_concurrent_mpcs_per_peer: config.max_concurrent_mpcs,
+            max_settlement_fee: config.max_settlement_fee,
+            fee_schedule: FeeSchedule::default(),
+            broker_fee_bps: None,
+            max_broker_fee_bps: 0,
+            default_min_fill_size: None,
+            default_max_slippage_bps: None,
+            price_reporter_work_queue: price_reporter_queue.clone(),
+            starknet_client: Arc::new(chain.clone()),
+            rng_seed: Some(rng.gen_range(0..u64::MAX)),
+            settlement_journal_file: None,
+            handshake_cache_file: None,
+            clock: clock.clone(),
+            cancel_channel: cancel_receiver,
+        })
+        .map_err(|err| SimulationError::Setup(err.to_string()))?;
+        handshake_manager
+            .start()
+            .map_err(|err| SimulationError::Setup(err.to_string()))?;
+
+        let (base_token, quote_token) = pair;
+        Ok(SimulatedRelayer {
+            state,
+            order_flow: SyntheticOrderFlow::new(
+                base_token,
+                quote_token,
+                config.spread_bps,
+                config.max_order_amount,
+                rng.fork(),
+            ),
+            _handshake_manager: handshake_manager,
+            _priority_job_sender: priority_job_sender,
+        })
+    }
+
+    /// Run the simulation to completion, then cancel its workers and summarize the run
+    pub async fn run(mut self) -> Result<SimulationReport, SimulationError> {
+        let wall_start = Instant::now();
+        let round_wall_time = Duration::from_millis(self.config.round_wall_time_ms);
+
+        // Place each relayer's initial orders at the feed's opening price
+        let opening_price = self.wait_for_price(round_wall_time).await?;
+        for relayer_index in 0..self.relayers.len() {
+            for _ in 0..self.config.initial_orders {
+                self.place_order(relayer_index, opening_price).await?;
+            }
+        }
+
+        for round in 0..self.rounds {
+            // A round that finds no median prices its orders at the last one observed
+            if let Some(price) = self.peek_median().or(self.prices.last) {
+                self.prices.observe(price);
+                for _ in 0..self.config.orders_per_round {
+                    let relayer_index = self.rng.gen_range(0..self.relayers.len() as u64) as usize;
+                    self.place_order(relayer_index, price).await?;
+                }
+            }
+
+            // Advancing the clock fires the relayers' scheduled handshakes and releases
+            // the next stretch of the feed, both of which run out in wall time
+            self.clock
+                .advance(Duration::from_millis(self.config.round_interval_ms));
+            tokio::time::sleep(round_wall_time).await;
+            log::info!(
+                "simulation round {round} complete, {} orders placed",
+                self.orders_placed
+            );
+        }
+
+        // Let the matches of the last round settle
+        tokio::time::sleep(Duration::from_millis(self.config.mpc_timeout_ms)).await;
+        let report = self.summarize(wall_start);
+        for cancel_sender in self.cancel_senders.iter() {
+            let _ = cancel_sender.send(());
+        }
+
+        Ok(report)
+    }
+
+    /// Wait for the price reporter to produce its first median, advancing the clock
+    /// through the feed until it does
+    async fn wait_for_price(&mut self, poll_interval: Duration) -> Result<f64, SimulationError> {
+        for _ in 0..=self.rounds {
+            if let Some(price) = self.peek_median() {
+                self.prices.observe(price);
+                return Ok(price);
+            }
+
+            tokio::time::sleep(poll_interval).await;
+            self.clock
+                .advance(Duration::from_millis(self.config.round_interval_ms));
+        }
+
+        Err(SimulationError::PriceFeed(
+            "price reporter produced no median from the feed".to_string(),
+        ))
+    }
+
+    /// Peek at the price reporter's median for the simulated pair
+    ///
+    /// A median the reporter holds back as stale or deviating is used all the same; the
+    /// simulation only prices synthetic orders with it
+    fn peek_median(&self) -> Option<f64> {
+        let (base_token, quote_token) = self.pair.clone();
+        let (sender, receiver) = channel::unbounded();
+        self.price_reporter_queue
+            .send(PriceReporterManagerJob::PeekMedian {
+                base_token,
+                quote_token,
+                channel: sender,
+            })
+            .ok()?;
+
+        match receiver
+            .recv_timeout(Duration::from_millis(PRICE_PEEK_TIMEOUT_MS))
+            .ok()?
+        {
+            PriceReporterState::Nominal(report)
+            | PriceReporterState::DataTooStale(report, _)
+            | PriceReporterState::TooMuchDeviation(report, _)
+            | PriceReporterState::Held(report, _) => Some(report.midpoint_price),
+            PriceReporterState::NotEnoughDataReported(_) => None,
+        }
+    }
+
+    /// Place a synthetic order with the given relayer
+    ///
+    /// The order's wallet is committed to the shared tree and proven, then the proven
+    /// order is seeded into every other relayer's order book as gossip would carry it
+    async fn place_order(
+        &mut self,
+        relayer_index: usize,
+        median_price: f64,
+    ) -> Result<(), SimulationError> {
+        let relayer = &self.relayers[relayer_index];
+        let wallet = relayer
+            .order_flow
+            .next_wallet(median_price, self.clock.unix_time().as_millis() as u64);
+        let wallet_id = wallet.wallet_id;
+        let match_nullifier = wallet.get_match_nullifier();
+        let n_orders = wallet.orders.len() as u64;
+
+        // Commit the wallet to the shared tree
+        let commitment = wallet.get_commitment();
+        let leaf_index = self.merkle_tree.num_leaves();
+        self.merkle_tree
+            .insert(leaf_index, reduce_to_starknet_field(&commitment))
+            .map_err(|err| SimulationError::OrderPlacement(err.to_string()))?;
+        let opening = self.merkle_tree.opening(leaf_index).ok_or_else(|| {
+            SimulationError::OrderPlacement(format!("no opening for leaf {leaf_index}"))
+        })?;
+        let merkle_path =
+            MerkleAuthenticationPath::new(opening.path_siblings, opening.leaf_index, commitment);
+
+        relayer.state.add_wallets(vec![wallet]).await;
+        let proofs = relayer
+            .state
+            .prove_wallet_orders(&wallet_id, merkle_path, &self.proof_manager_queue)
+            .await;
+        self.orders_placed += n_orders;
+        self.orders_unproven += n_orders - proofs.len() as u64;
+
+        let local_peer_id = relayer.state.local_peer_id();
+        let local_cluster_id = relayer.state.local_cluster_id.clone();
+        for (order_id, proof) in proofs.into_iter() {
+            self.network.register_order(order_id, local_peer_id);
+            for peer in self
+                .relayers
+                .iter()
+                .filter(|peer| peer.state.local_peer_id() != local_peer_id)
+            {
+                peer.state
+                    .add_order(NetworkOrder::new(
+                        order_id,
+                        match_nullifier,
+                        local_cluster_id.clone(),
+                        false, /* local */
+                    ))
+                    .await;
+                peer.state
+                    .add_order_validity_proof(&order_id, proof.clone())
+                    .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Summarize the run so far
+    fn summarize(&self, wall_start: Instant) -> SimulationReport {
+        let statuses = self
+            .relayers
+            .iter()
+            .flat_map(|relayer| relayer.state.settlement_records())
+            .map(|record| record.status)
+            .collect::<Vec<_>>();
+
+        SimulationReport {
+            seed: self.config.seed,
+            relayers: self.relayers.len(),
+            rounds: self.rounds,
+            orders_placed: self.orders_placed,
+            orders_unproven: self.orders_unproven,
+            wall_time_ms: wall_start.elapsed().as_millis() as u64,
+            prices: self.prices.clone(),
+            traffic: self.network.stats().into(),
+            settlements: SettlementSummary::new(
+                &self.chain.settlements(),
+                &statuses,
+                self.chain.congested_estimates(),
+            ),
+        }
+    }
+}
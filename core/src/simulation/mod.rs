@@ -0,0 +1,63 @@
+//! A deterministic harness that runs the matching and pricing stack without a network or
+//! a chain, so that strategy and protocol changes may be benchmarked against one another
+//!
+//! The harness starts a set of relayers in a single process, each with its own state and
+//! handshake manager. Handshakes travel over an in-process loopback network in place of
+//! libp2p, settlements are recorded by a mock of the contract in place of being submitted,
+//! and prices come from a recorded feed replayed through the price reporter in place of
+//! live exchange connections. Synthetic wallets are generated around the replayed median
+//! each round, proven by a shared proof manager, and seeded into every relayer's order book.
+//!
+//! The scheduler, settlement backoff, and price replay all run against a single manual
+//! clock that the harness advances round by round, and every random choice is drawn from
+//! the run's seed; the binary must be built with the `deterministic-rng` feature for the
+//! seed to be honored. The MPCs and proofs themselves run for real over localhost, so the
+//! wall time a run takes, and which handshakes complete within the MPC timeout, reflect the
+//! machine the run is made on
+pub mod chain;
+pub mod error;
+pub mod harness;
+pub mod network;
+pub mod order_flow;
+pub mod report;
+
+/// The parameters of a simulation run
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// The seed that every random choice in the run is drawn from
+    pub seed: u64,
+    /// The file holding the recorded price feed to replay, as JSON-serialized price
+    /// reports, one per line
+    pub price_feed: String,
+    /// The number of relayers to simulate, each a cluster of one
+    pub relayers: usize,
+    /// The number of orders each relayer manages before the first round
+    pub initial_orders: usize,
+    /// The number of orders placed across the network in each round
+    pub orders_per_round: usize,
+    /// The number of rounds to run; if `None`, rounds are run until the feed is exhausted
+    pub rounds: Option<u64>,
+    /// The simulated time that elapses between rounds, in milliseconds
+    pub round_interval_ms: u64,
+    /// The wall time given to each round for its handshakes and settlements to run, in
+    /// milliseconds
+    pub round_wall_time_ms: u64,
+    /// The maximum distance of a synthetic order's price from the median, in basis points
+    pub spread_bps: u64,
+    /// The maximum size of a synthetic order, in units of the base token
+    pub max_order_amount: u64,
+    /// The fee the mock chain charges every settlement at least, in wei
+    pub base_fee: u64,
+    /// The most the mock chain charges a settlement above the base fee, in wei
+    pub fee_jitter: u64,
+    /// The fraction of fee estimates the mock chain fails as congested
+    pub congestion_rate: f64,
+    /// The highest fee a relayer pays to settle a match, `None` if unbounded
+    pub max_settlement_fee: Option<u64>,
+    /// The amount of time a match MPC may run before it is abandoned, in milliseconds
+    pub mpc_timeout_ms: u64,
+    /// The maximum number of match MPCs each relayer runs at once
+    pub max_concurrent_mpcs: usize,
+    /// Whether relayers request a size bucket check when proposing a match
+    pub size_bucket_check: bool,
+}
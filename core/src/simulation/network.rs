@@ -0,0 +1,249 @@
+//! An in-process network connecting the simulated relayers' handshake managers
+//!
+//! Each simulated relayer sends its outbound gossip through its own endpoint on the
+//! loopback network. Handshake requests are delivered straight onto the recipient's job
+//! queue; no response channel is handed out, so the recipient's replies travel back as
+//! requests as well. MPC nets are brokered on localhost exactly as the network manager
+//! brokers them between peers, and order provider lookups are answered from the order
+//! placements the harness registers. Every other message, e.g. cluster pubsub, is dropped;
+//! each simulated relayer is a cluster of one, and order proofs are seeded directly into
+//! the simulated order books
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use mpc_ristretto::network::QuicTwoPartyNet;
+
+use crate::{
+    gossip::types::WrappedPeerId,
+    gossip_api::gossip::{
+        ConnectionRole, GossipOutbound, GossipRequest, ManagerControlDirective, NetworkChannel,
+        SharedNetworkChannel,
+    },
+    handshake::jobs::HandshakeExecutionJob,
+    job_queue::JobQueueSender,
+    state::OrderIdentifier,
+};
+
+/// The error emitted when a message is addressed to a peer not on the network
+const ERR_UNKNOWN_PEER: &str = "peer is not on the loopback network";
+/// The error emitted when a response is sent on the loopback network
+const ERR_NO_RESPONSES: &str = "the loopback network does not carry responses";
+
+/// Counts of the traffic carried by the loopback network
+#[derive(Debug, Default)]
+struct TrafficCounters {
+    /// The handshake messages delivered
+    handshake_messages: AtomicU64,
+    /// The MPC nets brokered
+    mpc_nets: AtomicU64,
+    /// The order provider lookups answered
+    provider_lookups: AtomicU64,
+    /// The messages dropped
+    dropped: AtomicU64,
+}
+
+/// A snapshot of the traffic carried by the loopback network
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrafficStats {
+    /// The handshake messages delivered
+    pub handshake_messages: u64,
+    /// The MPC nets brokered
+    pub mpc_nets: u64,
+    /// The order provider lookups answered
+    pub provider_lookups: u64,
+    /// The messages dropped
+    pub dropped: u64,
+}
+
+/// The in-process network shared by every simulated relayer
+#[derive(Clone, Debug, Default)]
+pub struct LoopbackNetwork {
+    /// The handshake job queue of each relayer on the network
+    peers: Arc<RwLock<HashMap<WrappedPeerId, JobQueueSender<HandshakeExecutionJob>>>>,
+    /// The relayer managing each order placed on the network
+    order_managers: Arc<RwLock<HashMap<OrderIdentifier, WrappedPeerId>>>,
+    /// The traffic carried so far
+    counters: Arc<TrafficCounters>,
+}
+
+impl LoopbackNetwork {
+    /// Create a network with no peers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a relayer to the network, returning the endpoint it sends through
+    pub fn join(
+        &self,
+        peer_id: WrappedPeerId,
+        handshake_queue: JobQueueSender<HandshakeExecutionJob>,
+    ) -> SharedNetworkChannel {
+        self.peers.write().unwrap().insert(peer_id, handshake_queue);
+        Arc::new(LoopbackEndpoint {
+            local_peer: peer_id,
+            network: self.clone(),
+        })
+    }
+
+    /// Record the relayer managing an order, so that lookups of the order resolve to it
+    pub fn register_order(&self, order_id: OrderIdentifier, manager: WrappedPeerId) {
+        self.order_managers
+            .write()
+            .unwrap()
+            .insert(order_id, manager);
+    }
+
+    /// The traffic carried so far
+    pub fn stats(&self) -> TrafficStats {
+        TrafficStats {
+            handshake_messages: self.counters.handshake_messages.load(Ordering::Relaxed),
+            mpc_nets: self.counters.mpc_nets.load(Ordering::Relaxed),
+            provider_lookups: self.counters.provider_lookups.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Enqueue a job on a relayer's handshake job queue
+    fn deliver(&self, peer_id: &WrappedPeerId, job: HandshakeExecutionJob) -> Result<(), String> {
+        self.peers
+            .read()
+            .unwrap()
+            .get(peer_id)
+            .ok_or_else(|| ERR_UNKNOWN_PEER.to_string())?
+            .send(job)
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// A relayer's connection to the loopback network
+#[derive(Debug)]
+struct LoopbackEndpoint {
+    /// The relayer sending through the endpoint
+    local_peer: WrappedPeerId,
+    /// The network the endpoint is connected to
+    network: LoopbackNetwork,
+}
+
+impl LoopbackEndpoint {
+    /// Build an MPC net on localhost for the given connection role, as the network
+    /// manager does when brokering a net between peers on the same host
+    fn broker_mpc_net(
+        &self,
+        peer_port: u16,
+        local_port: u16,
+        local_role: ConnectionRole,
+    ) -> (u64, QuicTwoPartyNet) {
+        let party_id = local_role.get_party_id();
+        let local_addr: SocketAddr = format!("127.0.0.1:{:?}", local_port).parse().unwrap();
+        let peer_addr: SocketAddr = match local_role {
+            ConnectionRole::Dialer => format!("127.0.0.1:{:?}", peer_port).parse().unwrap(),
+            // As the listener, the peer address is inconsequential
+            ConnectionRole::Listener => "127.0.0.1:0".parse().unwrap(),
+        };
+
+        (
+            party_id,
+            QuicTwoPartyNet::new(party_id, local_addr, peer_addr),
+        )
+    }
+}
+
+impl NetworkChannel for LoopbackEndpoint {
+    fn send(&self, message: GossipOutbound) -> Result<(), String> {
+        let counters = &self.network.counters;
+        match message {
+            GossipOutbound::Request {
+                peer_id,
+                message:
+                    GossipRequest::Handshake {
+                        request_id,
+                        message,
+                    },
+            } => {
+                counters.handshake_messages.fetch_add(1, Ordering::Relaxed);
+                self.network.deliver(
+                    &peer_id,
+                    HandshakeExecutionJob::ProcessHandshakeMessage {
+                        request_id,
+                        peer_id: self.local_peer,
+                        message,
+                        response_channel: None,
+                    },
+                )
+            }
+
+            GossipOutbound::Request {
+                peer_id,
+                message:
+                    GossipRequest::BrokeredHandshake {
+                        request_id,
+                        message,
+                    },
+            } => {
+                counters.handshake_messages.fetch_add(1, Ordering::Relaxed);
+                self.network.deliver(
+                    &peer_id,
+                    HandshakeExecutionJob::ProcessBrokerMessage {
+                        request_id,
+                        peer_id: self.local_peer,
+                        message,
+                        response_channel: None,
+                    },
+                )
+            }
+
+            GossipOutbound::Response { .. } => Err(ERR_NO_RESPONSES.to_string()),
+
+            GossipOutbound::ManagementMessage(ManagerControlDirective::BrokerMpcNet {
+                request_id,
+                peer_port,
+                local_port,
+                local_role,
+                ..
+            }) => {
+                counters.mpc_nets.fetch_add(1, Ordering::Relaxed);
+                let (party_id, net) = self.broker_mpc_net(peer_port, local_port, local_role);
+                self.network.deliver(
+                    &self.local_peer,
+                    HandshakeExecutionJob::MpcNetSetup {
+                        request_id,
+                        party_id,
+                        net,
+                        pooled_net_id: None,
+                    },
+                )
+            }
+
+            GossipOutbound::ManagementMessage(ManagerControlDirective::FindOrderProviders {
+                order_id,
+                response_channel,
+            }) => {
+                counters.provider_lookups.fetch_add(1, Ordering::Relaxed);
+                let providers = self
+                    .network
+                    .order_managers
+                    .read()
+                    .unwrap()
+                    .get(&order_id)
+                    .copied()
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                response_channel
+                    .send(providers)
+                    .map_err(|err| err.to_string())
+            }
+
+            _ => {
+                counters.dropped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Generates the synthetic wallets and orders that the simulated relayers match
+//!
+//! Each synthetic wallet holds a single order on the simulated pair, priced within a
+//! spread of the prevailing median and funded well beyond what the order could spend, so
+//! that every generated order is provable. All choices are drawn from the harness's seeded
+//! randomness, so that a seed reproduces the same order flow
+
+use std::collections::HashMap;
+
+use circuits::{
+    types::{
+        balance::Balance,
+        fee::Fee,
+        order::{MatchConstraints, Order, OrderSide, TimeInForce},
+    },
+    zk_gadgets::fixed_point::FixedPoint,
+};
+use num_bigint::BigUint;
+
+use crate::{
+    keychain::derive_keychain,
+    price_reporter::tokens::Token,
+    rng::WorkerRng,
+    state::wallet::{Wallet, WalletMetadata},
+};
+
+/// The balance of each mint a synthetic wallet is funded with
+const SYNTHETIC_BALANCE: u64 = 1_000_000_000_000;
+/// The gas a synthetic wallet's fee pays to its relayer, in units of the quote mint
+const SYNTHETIC_GAS_AMOUNT: u64 = 1;
+/// The percentage fee a synthetic wallet pays to its relayer
+const SYNTHETIC_PERCENTAGE_FEE: f32 = 0.0002;
+/// The number of basis points in a unit
+const BPS_PER_UNIT: f64 = 10_000.;
+
+/// A seeded source of synthetic wallets on a single pair
+#[derive(Clone, Debug)]
+pub struct SyntheticOrderFlow {
+    /// The mint of the pair's base token
+    base_mint: BigUint,
+    /// The mint of the pair's quote token
+    quote_mint: BigUint,
+    /// The maximum distance of an order's price from the median, in basis points
+    spread_bps: u64,
+    /// The maximum size of an order, in units of the base token
+    max_amount: u64,
+    /// The source of randomness for the order flow
+    rng: WorkerRng,
+}
+
+impl SyntheticOrderFlow {
+    /// Constructor
+    pub fn new(
+        base_token: &Token,
+        quote_token: &Token,
+        spread_bps: u64,
+        max_amount: u64,
+        rng: WorkerRng,
+    ) -> Self {
+        Self {
+            base_mint: token_mint(base_token),
+            quote_mint: token_mint(quote_token),
+            spread_bps,
+            max_amount: max_amount.max(1),
+            rng,
+        }
+    }
+
+    /// Generate a wallet holding a single order priced around the given median
+    pub fn next_wallet(&self, median_price: f64, timestamp: u64) -> Wallet {
+        let side = if self.rng.gen_range(0..2) == 0 {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+
+        // Buyers bid up to the spread above the median and sellers ask down to the spread
+        // below it, so that orders on opposite sides cross
+        let offset_bps = self.rng.gen_range(0..self.spread_bps + 1) as f64;
+        let price = match side {
+            OrderSide::Buy => median_price * (1. + offset_bps / BPS_PER_UNIT),
+            OrderSide::Sell => median_price * (1. - offset_bps / BPS_PER_UNIT),
+        };
+
+        let order = Order {
+            quote_mint: self.quote_mint.clone(),
+            base_mint: self.base_mint.clone(),
+            side,
+            price: FixedPoint::from_f64_round_down(price),
+            amount: 1 + self.rng.gen_range(0..self.max_amount),
+            timestamp,
+            time_in_force: TimeInForce::GoodTilCancelled,
+            constraints: MatchConstraints::default(),
+        };
+
+        let balances = [&self.base_mint, &self.quote_mint]
+            .into_iter()
+            .map(|mint| {
+                (
+                    mint.clone(),
+                    Balance {
+                        mint: mint.clone(),
+                        amount: SYNTHETIC_BALANCE,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let (public_keys, secret_keys) = derive_keychain(self.rng.gen_scalar());
+        Wallet {
+            wallet_id: self.rng.gen_uuid(),
+            orders: HashMap::from([(self.rng.gen_uuid(), order)]),
+            balances,
+            fees: vec![Fee {
+                settle_key: BigUint::from(0u8),
+                gas_addr: self.quote_mint.clone(),
+                gas_token_amount: SYNTHETIC_GAS_AMOUNT,
+                percentage_fee: FixedPoint::from_f32_round_down(SYNTHETIC_PERCENTAGE_FEE),
+            }],
+            public_keys,
+            secret_keys,
+            randomness: BigUint::from(self.rng.gen_range(0..u64::MAX)),
+            metadata: WalletMetadata {
+                replicas: Default::default(),
+                version: 0,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: Default::default(),
+        }
+    }
+}
+
+/// The mint of a token, parsed from its hex address
+fn token_mint(token: &Token) -> BigUint {
+    let addr = token.get_addr();
+    BigUint::parse_bytes(addr.trim_start_matches("0x").as_bytes(), 16).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use circuits::types::order::OrderSide;
+
+    use crate::{price_reporter::tokens::Token, rng::WorkerRng};
+
+    use super::SyntheticOrderFlow;
+
+    /// Tests that synthetic orders are priced within the spread and reproduced by a seed
+    #[test]
+    fn test_synthetic_orders() {
+        let base = Token::from_addr("0x01");
+        let quote = Token::from_addr("0x02");
+        let flow = SyntheticOrderFlow::new(&base, &quote, 50, 10, WorkerRng::new(Some(7)));
+        let replayed = SyntheticOrderFlow::new(&base, &quote, 50, 10, WorkerRng::new(Some(7)));
+
+        for timestamp in 0..20 {
+            let wallet = flow.next_wallet(100., timestamp);
+            let replayed_wallet = replayed.next_wallet(100., timestamp);
+            assert_eq!(wallet.wallet_id, replayed_wallet.wallet_id);
+
+            let order = wallet.orders.values().next().unwrap();
+            let price = order.price.to_f64();
+            match order.side {
+                OrderSide::Buy => assert!((100. ..=100.51).contains(&price)),
+                OrderSide::Sell => assert!((99.49..=100.).contains(&price)),
+            }
+            assert!((1..=10).contains(&order.amount));
+        }
+    }
+}
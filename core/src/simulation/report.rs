@@ -0,0 +1,137 @@
+//! The summary of a simulation run, printed by the simulation binary
+
+use serde::Serialize;
+
+use crate::types::SettlementStatus;
+
+use super::{chain::MockSettlement, network::TrafficStats};
+
+/// The prices the simulated relayers observed from the replayed feed
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PriceSummary {
+    /// The number of rounds in which the price reporter produced a median
+    pub samples: u64,
+    /// The lowest median observed
+    pub min: Option<f64>,
+    /// The highest median observed
+    pub max: Option<f64>,
+    /// The last median observed
+    pub last: Option<f64>,
+}
+
+impl PriceSummary {
+    /// Record a median observed in a round
+    pub fn observe(&mut self, price: f64) {
+        self.samples += 1;
+        self.min = Some(self.min.map_or(price, |min| min.min(price)));
+        self.max = Some(self.max.map_or(price, |max| max.max(price)));
+        self.last = Some(price);
+    }
+}
+
+/// The settlements the mock chain accepted and the relayers gave up on
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SettlementSummary {
+    /// The settlements accepted by the mock chain
+    pub submitted: u64,
+    /// The settlements the relayers recorded as failed
+    pub failed: u64,
+    /// The settlements still in progress when the run ended
+    pub pending: u64,
+    /// The fee estimates the mock chain turned away for congestion
+    pub congested_estimates: u64,
+    /// The fees paid across all accepted settlements, in wei
+    pub total_fee: u64,
+    /// The mean fee of an accepted settlement, in wei
+    pub mean_fee: Option<f64>,
+}
+
+impl SettlementSummary {
+    /// Summarize the settlements accepted by the mock chain and the final status of each
+    /// settlement recorded by the relayers
+    pub fn new(
+        accepted: &[MockSettlement],
+        statuses: &[SettlementStatus],
+        congested_estimates: u64,
+    ) -> Self {
+        let total_fee = accepted
+            .iter()
+            .map(|settlement| settlement.fee)
+            .sum::<u64>();
+        let mean_fee = if accepted.is_empty() {
+            None
+        } else {
+            Some(total_fee as f64 / accepted.len() as f64)
+        };
+
+        let failed = statuses
+            .iter()
+            .filter(|status| matches!(status, SettlementStatus::Failed { .. }))
+            .count() as u64;
+        let pending = statuses
+            .iter()
+            .filter(|status| {
+                !matches!(
+                    status,
+                    SettlementStatus::Failed { .. } | SettlementStatus::Submitted { .. }
+                )
+            })
+            .count() as u64;
+
+        Self {
+            submitted: accepted.len() as u64,
+            failed,
+            pending,
+            congested_estimates,
+            total_fee,
+            mean_fee,
+        }
+    }
+}
+
+/// The traffic carried between the simulated relayers
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TrafficSummary {
+    /// The handshake messages delivered
+    pub handshake_messages: u64,
+    /// The MPC nets brokered
+    pub mpc_nets: u64,
+    /// The order provider lookups answered
+    pub provider_lookups: u64,
+    /// The messages dropped
+    pub dropped: u64,
+}
+
+impl From<TrafficStats> for TrafficSummary {
+    fn from(stats: TrafficStats) -> Self {
+        Self {
+            handshake_messages: stats.handshake_messages,
+            mpc_nets: stats.mpc_nets,
+            provider_lookups: stats.provider_lookups,
+            dropped: stats.dropped,
+        }
+    }
+}
+
+/// The summary of a simulation run
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SimulationReport {
+    /// The seed the run was driven by
+    pub seed: u64,
+    /// The number of relayers simulated
+    pub relayers: usize,
+    /// The number of rounds run
+    pub rounds: u64,
+    /// The number of synthetic orders placed
+    pub orders_placed: u64,
+    /// The number of synthetic orders that could not be proven
+    pub orders_unproven: u64,
+    /// The wall-clock duration of the run, in milliseconds
+    pub wall_time_ms: u64,
+    /// The prices observed from the replayed feed
+    pub prices: PriceSummary,
+    /// The traffic carried between the relayers
+    pub traffic: TrafficSummary,
+    /// The settlements of the matches found
+    pub settlements: SettlementSummary,
+}
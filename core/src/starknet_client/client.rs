@@ -1,16 +1,37 @@
 //! A wrapper around the starknet client made available by:
 //! https://docs.rs/starknet-core/latest/starknet_core/
 
-use std::{str::FromStr, sync::Arc};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    str::FromStr,
+    sync::Arc,
+};
 
+use async_trait::async_trait;
+use circuits::zk_circuits::valid_wallet_update::ValidWalletUpdateStatement;
 use reqwest::Url;
-use starknet::core::types::FieldElement as StarknetFieldElement;
+use starknet::{
+    accounts::{Account, Call, SingleOwnerAccount},
+    core::{types::FieldElement as StarknetFieldElement, utils::get_selector_from_name},
+    signers::{LocalWallet, SigningKey},
+};
 use starknet_providers::{
     jsonrpc::{HttpTransport, JsonRpcClient},
     SequencerGatewayProvider,
 };
 
-use super::ChainId;
+use tokio::sync::mpsc::UnboundedSender as TokioSender;
+
+use crate::keychain::WalletCiphertext;
+
+use super::{
+    contract_abi::{
+        update_wallet_calldata, COMMIT_MATCH_FUNCTION, MATCH_FUNCTION, UPDATE_WALLET_FUNCTION,
+    },
+    error::StarknetClientError,
+    transaction_manager::{StarknetAccount, TransactionFailedJob, TransactionManager},
+    ChainId,
+};
 
 /// The config type for the client, consists of secrets needed to connect to
 /// the gateway and API server, as well as keys for sending transactions
@@ -30,6 +51,8 @@ pub struct StarknetClientConfig {
     pub infura_api_key: Option<String>,
     /// The starknet signing key, used to submit transactions on-chain
     pub starknet_pkey: Option<String>,
+    /// The address of the starknet account that `starknet_pkey` signs for
+    pub starknet_account_addr: Option<String>,
 }
 
 impl StarknetClientConfig {
@@ -38,6 +61,11 @@ impl StarknetClientConfig {
         self.starknet_json_rpc_addr.is_some()
     }
 
+    /// Whether or not the config holds an account to submit transactions from
+    pub fn account_enabled(&self) -> bool {
+        self.starknet_pkey.is_some() && self.starknet_account_addr.is_some()
+    }
+
     /// Build a gateway client from the config values
     pub fn new_gateway_client(&self) -> SequencerGatewayProvider {
         match self.chain {
@@ -60,6 +88,46 @@ impl StarknetClientConfig {
     }
 }
 
+/// The contract operations a worker settles matches through
+///
+/// Workers hold the client behind this trait rather than the concrete `StarknetClient`, so
+/// that a mock chain may be injected in its place; e.g. by the simulation harness
+#[async_trait]
+pub trait StarknetApi: Debug + Send + Sync {
+    /// Whether the client holds an account to submit transactions from
+    fn account_enabled(&self) -> bool;
+
+    /// Commit to a match encoded as by `commit_match_calldata`, returning the hash of the
+    /// transaction
+    ///
+    /// If the transaction fails after it is broadcast, a job describing the failure is
+    /// sent on `failure_queue`
+    async fn commit_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError>;
+
+    /// Estimate the fee, in wei, of settling a match encoded as by `settle_match_calldata`
+    async fn estimate_settle_match_fee(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+    ) -> Result<u64, StarknetClientError>;
+
+    /// Submit a match settlement to the contract, returning the hash of the transaction
+    ///
+    /// If the transaction fails after it is broadcast, a job describing the failure is
+    /// sent on `failure_queue`
+    async fn settle_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError>;
+}
+
+/// A handle to a `StarknetApi` implementation shared between threads
+pub type SharedStarknetApi = Arc<dyn StarknetApi>;
+
 /// A wrapper around the concrete JSON-RPC client that provides helpers for common
 /// Renegade-specific access patterns
 #[derive(Clone)]
@@ -72,6 +140,19 @@ pub struct StarknetClient {
     gateway_client: Arc<SequencerGatewayProvider>,
     /// The client used to send starknet JSON-RPC requests
     jsonrpc_client: Option<Arc<JsonRpcClient<HttpTransport>>>,
+    /// The manager that assigns nonces to, and follows, transactions sent from the
+    /// configured account
+    transaction_manager: TransactionManager,
+}
+
+/// The config holds the signing key, so only the chain and contract are printed
+impl Debug for StarknetClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("StarknetClient")
+            .field("chain", &self.config.chain)
+            .field("contract_address", &self.contract_address)
+            .finish()
+    }
 }
 
 impl StarknetClient {
@@ -79,6 +160,7 @@ impl StarknetClient {
     pub fn new(config: StarknetClientConfig) -> Self {
         let gateway_client = Arc::new(config.new_gateway_client());
         let jsonrpc_client = config.new_jsonrpc_client().map(Arc::new);
+        let transaction_manager = TransactionManager::new(gateway_client.clone());
 
         // Parse the contract address
         let contract_address: StarknetFieldElement =
@@ -91,6 +173,7 @@ impl StarknetClient {
             contract_address,
             gateway_client,
             jsonrpc_client,
+            transaction_manager,
         }
     }
 
@@ -108,4 +191,149 @@ impl StarknetClient {
     pub fn get_jsonrpc_client(&self) -> &JsonRpcClient<HttpTransport> {
         self.jsonrpc_client.as_ref().unwrap()
     }
+
+    /// Get the manager that tracks transactions sent from the configured account
+    pub fn get_transaction_manager(&self) -> &TransactionManager {
+        &self.transaction_manager
+    }
+
+    /// Submit a wallet update to the contract, returning the hash of the transaction
+    ///
+    /// The update nullifies the old wallet under its match and spend nullifiers, and
+    /// inserts the commitment to the new wallet into the state tree, applying the
+    /// statement's external transfer. The new wallet's ciphertext under its view key is
+    /// posted alongside, so that the wallet may be recovered from chain
+    ///
+    /// If the transaction fails after it is broadcast, a job describing the failure is
+    /// sent on `failure_queue`
+    pub async fn update_wallet(
+        &self,
+        statement: &ValidWalletUpdateStatement,
+        ciphertext: &WalletCiphertext,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let account = Arc::new(self.build_account()?);
+        let call = Call {
+            to: self.contract_address,
+            selector: get_selector_from_name(UPDATE_WALLET_FUNCTION).unwrap(),
+            calldata: update_wallet_calldata(statement, Some(ciphertext)),
+        };
+        self.transaction_manager
+            .submit(account, vec![call], failure_queue)
+            .await
+    }
+
+    /// Commit to a match ahead of its settlement, returning the hash of the transaction
+    ///
+    /// If the transaction fails after it is broadcast, a job describing the failure is
+    /// sent on `failure_queue`
+    pub async fn commit_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let account = Arc::new(self.build_account()?);
+        let call = Call {
+            to: self.contract_address,
+            selector: get_selector_from_name(COMMIT_MATCH_FUNCTION).unwrap(),
+            calldata,
+        };
+        self.transaction_manager
+            .submit(account, vec![call], failure_queue)
+            .await
+    }
+
+    /// Estimate the fee, in wei, of settling a match encoded as by `settle_match_calldata`
+    pub async fn estimate_settle_match_fee(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+    ) -> Result<u64, StarknetClientError> {
+        let account = self.build_account()?;
+        let estimate = account
+            .execute(vec![self.settle_match_call(calldata)])
+            .estimate_fee()
+            .await
+            .map_err(|err| StarknetClientError::Transaction(err.to_string()))?;
+
+        Ok(estimate.overall_fee)
+    }
+
+    /// Submit a match settlement to the contract, returning the hash of the transaction
+    ///
+    /// If the transaction fails after it is broadcast, a job describing the failure is
+    /// sent on `failure_queue`
+    pub async fn settle_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let account = Arc::new(self.build_account()?);
+        self.transaction_manager
+            .submit(
+                account,
+                vec![self.settle_match_call(calldata)],
+                failure_queue,
+            )
+            .await
+    }
+
+    /// Build a call to the contract's `match` entrypoint
+    fn settle_match_call(&self, calldata: Vec<StarknetFieldElement>) -> Call {
+        Call {
+            to: self.contract_address,
+            selector: get_selector_from_name(MATCH_FUNCTION).unwrap(),
+            calldata,
+        }
+    }
+
+    /// Build an account from the configured signing key and address
+    fn build_account(&self) -> Result<StarknetAccount, StarknetClientError> {
+        if !self.config.account_enabled() {
+            return Err(StarknetClientError::NoAccount);
+        }
+
+        let pkey = StarknetFieldElement::from_str(self.config.starknet_pkey.as_ref().unwrap())
+            .map_err(|err| StarknetClientError::Parse(err.to_string()))?;
+        let account_addr =
+            StarknetFieldElement::from_str(self.config.starknet_account_addr.as_ref().unwrap())
+                .map_err(|err| StarknetClientError::Parse(err.to_string()))?;
+        let signer = LocalWallet::from(SigningKey::from_secret_scalar(pkey));
+
+        Ok(SingleOwnerAccount::new(
+            self.config.new_gateway_client(),
+            signer,
+            account_addr,
+            self.config.chain.into(),
+        ))
+    }
+}
+
+#[async_trait]
+impl StarknetApi for StarknetClient {
+    fn account_enabled(&self) -> bool {
+        self.config.account_enabled()
+    }
+
+    async fn commit_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        StarknetClient::commit_match(self, calldata, failure_queue).await
+    }
+
+    async fn estimate_settle_match_fee(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+    ) -> Result<u64, StarknetClientError> {
+        StarknetClient::estimate_settle_match_fee(self, calldata).await
+    }
+
+    async fn settle_match(
+        &self,
+        calldata: Vec<StarknetFieldElement>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        StarknetClient::settle_match(self, calldata, failure_queue).await
+    }
 }
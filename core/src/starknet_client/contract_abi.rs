@@ -0,0 +1,441 @@
+//! Calldata encoding for the transactions the relayer submits to the darkpool contract
+//!
+//! The contract reads its arguments positionally, so each encoding below must match the
+//! argument order of the corresponding Cairo entrypoint exactly. Every value is reduced
+//! into the Starknet field, the contract does not yet support values outside of it. The
+//! exception is a wallet ciphertext, which must be recovered exactly and so is split
+//! into limbs that fit in the field
+//!
+//! The encodings are checked against fixtures exported from the contract repo, found in
+//! `resources/contract_abi/calldata_fixtures.json`. When an entrypoint's signature changes
+//! in the contract, the fixtures should be re-exported alongside the change here
+
+use circuits::{
+    zk_circuits::{
+        valid_match_encryption::ValidMatchEncryptionStatement,
+        valid_wallet_create::ValidWalletCreateStatement,
+        valid_wallet_update::ValidWalletUpdateStatement,
+    },
+    zk_gadgets::{elgamal::ElGamalCiphertext, match_commitment::MatchCommitment},
+};
+use crypto::fields::{
+    biguint_to_scalar, biguint_to_starknet_felt, scalar_to_biguint, starknet_felt_to_biguint,
+    starknet_felt_to_u64,
+};
+use curve25519_dalek::scalar::Scalar;
+use num_bigint::BigUint;
+use starknet::core::types::FieldElement as StarknetFieldElement;
+
+use crate::keychain::WalletCiphertext;
+
+/// The name of the contract entrypoint that creates a new wallet
+pub const NEW_WALLET_FUNCTION: &str = "new_wallet";
+/// The name of the contract entrypoint that applies a wallet update
+pub const UPDATE_WALLET_FUNCTION: &str = "update_wallet";
+/// The name of the contract entrypoint that settles a match
+pub const MATCH_FUNCTION: &str = "match";
+/// The name of the contract entrypoint that commits to a match ahead of its settlement
+pub const COMMIT_MATCH_FUNCTION: &str = "commit_match";
+
+/// The number of bits in each limb of an encoded wallet ciphertext or curve point
+const CIPHERTEXT_LIMB_BITS: usize = 128;
+/// The number of bytes packed into each word of an encoded proof, the most that always
+/// fit in the Starknet field
+const PROOF_WORD_BYTES: usize = 31;
+
+/// Encode the arguments to `new_wallet`
+///
+/// Layout: `[wallet_commitment]`
+pub fn new_wallet_calldata(statement: &ValidWalletCreateStatement) -> Vec<StarknetFieldElement> {
+    encode_scalars(&[statement.wallet_commitment])
+}
+
+/// Encode the arguments to `update_wallet`
+///
+/// Layout: `[new_wallet_commitment, match_nullifier, spend_nullifier, mint, volume,
+/// direction]`, where the last three are the external transfer, zero if the update
+/// moves no funds, followed by the new wallet's ciphertext as encoded by
+/// `wallet_ciphertext_calldata`
+pub fn update_wallet_calldata(
+    statement: &ValidWalletUpdateStatement,
+    ciphertext: Option<&WalletCiphertext>,
+) -> Vec<StarknetFieldElement> {
+    let (mint, volume, direction) = statement.external_transfer;
+    let mut calldata = encode_scalars(&[
+        statement.new_wallet_commitment,
+        statement.wallet_match_nullifier,
+        statement.wallet_spend_nullifier,
+        mint,
+        volume,
+        direction,
+    ]);
+    calldata.extend(wallet_ciphertext_calldata(ciphertext));
+
+    calldata
+}
+
+/// Encode a wallet ciphertext
+///
+/// Layout: `[len, nonce_lo, nonce_hi, block0_lo, block0_hi, ...]`, where `len` counts the
+/// limbs that follow and each scalar is split into its low and high 128 bits. An absent
+/// ciphertext is encoded as `[0]`. The contract emits this encoding unchanged in its
+/// `Wallet_ciphertext_posted` event
+pub fn wallet_ciphertext_calldata(
+    ciphertext: Option<&WalletCiphertext>,
+) -> Vec<StarknetFieldElement> {
+    let ciphertext = match ciphertext {
+        Some(ciphertext) => ciphertext,
+        None => return vec![StarknetFieldElement::from(0u8)],
+    };
+
+    let limb_mask = (BigUint::from(1u8) << CIPHERTEXT_LIMB_BITS) - 1u8;
+    let limbs: Vec<StarknetFieldElement> = std::iter::once(&ciphertext.nonce)
+        .chain(ciphertext.blocks.iter())
+        .flat_map(|value| {
+            let value = scalar_to_biguint(value);
+            [&value & &limb_mask, value >> CIPHERTEXT_LIMB_BITS]
+        })
+        .map(|limb| biguint_to_starknet_felt(&limb))
+        .collect();
+
+    let mut calldata = vec![StarknetFieldElement::from(limbs.len() as u64)];
+    calldata.extend(limbs);
+    calldata
+}
+
+/// Parse a wallet ciphertext from its encoding, see `wallet_ciphertext_calldata`
+///
+/// Returns `None` if the encoding is malformed or holds no ciphertext
+pub fn parse_wallet_ciphertext(felts: &[StarknetFieldElement]) -> Option<WalletCiphertext> {
+    let (len, limbs) = felts.split_first()?;
+    let len = starknet_felt_to_u64(len) as usize;
+    if len == 0 || len % 2 != 0 || limbs.len() != len {
+        return None;
+    }
+
+    let mut scalars = limbs.chunks(2).map(|limbs| {
+        let lo = starknet_felt_to_biguint(&limbs[0]);
+        let hi = starknet_felt_to_biguint(&limbs[1]);
+        biguint_to_scalar(&((hi << CIPHERTEXT_LIMB_BITS) + lo))
+    });
+
+    Some(WalletCiphertext {
+        nonce: scalars.next()?,
+        blocks: scalars.collect(),
+    })
+}
+
+/// Encode the arguments to `match`
+///
+/// Layout: `[match_nullifier0, match_nullifier1]`, then the note commitments of the first
+/// party, second party, first relayer, second relayer, and protocol, then the nine
+/// ciphertexts of the statement in declaration order, each as `[partial_shared_secret,
+/// encrypted_message]`
+pub fn match_calldata(
+    match_nullifier0: Scalar,
+    match_nullifier1: Scalar,
+    statement: &ValidMatchEncryptionStatement,
+) -> Vec<StarknetFieldElement> {
+    let mut scalars = vec![
+        match_nullifier0,
+        match_nullifier1,
+        statement.party0_note_commit,
+        statement.party1_note_commit,
+        statement.relayer0_note_commit,
+        statement.relayer1_note_commit,
+        statement.protocol_note_commit,
+    ];
+    for ciphertext in [
+        &statement.volume1_ciphertext1,
+        &statement.volume2_ciphertext1,
+        &statement.volume1_ciphertext2,
+        &statement.volume2_ciphertext2,
+        &statement.mint1_protocol_ciphertext,
+        &statement.volume1_protocol_ciphertext,
+        &statement.mint2_protocol_ciphertext,
+        &statement.volume2_protocol_ciphertext,
+        &statement.randomness_protocol_ciphertext,
+    ]
+    .iter()
+    {
+        scalars.extend_from_slice(&ciphertext_scalars(ciphertext));
+    }
+
+    encode_scalars(&scalars)
+}
+
+/// Encode the arguments to `match` followed by the serialized proof of `VALID MATCH
+/// ENCRYPTION` over its statement, as encoded by `proof_calldata`
+pub fn settle_match_calldata(
+    match_nullifier0: Scalar,
+    match_nullifier1: Scalar,
+    statement: &ValidMatchEncryptionStatement,
+    proof: &[u8],
+) -> Vec<StarknetFieldElement> {
+    let mut calldata = match_calldata(match_nullifier0, match_nullifier1, statement);
+    calldata.extend(proof_calldata(proof));
+
+    calldata
+}
+
+/// Encode the arguments to `commit_match`
+///
+/// Layout: `[match_nullifier0, match_nullifier1, len, comm0_lo, comm0_hi, ...]`, where
+/// `len` counts the limbs that follow and each compressed point of the match commitment
+/// is read as a little-endian integer and split into its low and high 128 bits. The
+/// contract holds the commitment under the nullifiers until the match is settled
+pub fn commit_match_calldata(
+    match_nullifier0: Scalar,
+    match_nullifier1: Scalar,
+    commitment: &MatchCommitment,
+) -> Vec<StarknetFieldElement> {
+    let limb_mask = (BigUint::from(1u8) << CIPHERTEXT_LIMB_BITS) - 1u8;
+    let limbs: Vec<StarknetFieldElement> = commitment
+        .commitments
+        .iter()
+        .flat_map(|point| {
+            let value = BigUint::from_bytes_le(point.as_bytes());
+            [&value & &limb_mask, value >> CIPHERTEXT_LIMB_BITS]
+        })
+        .map(|limb| biguint_to_starknet_felt(&limb))
+        .collect();
+
+    let mut calldata = encode_scalars(&[match_nullifier0, match_nullifier1]);
+    calldata.push(StarknetFieldElement::from(limbs.len() as u64));
+    calldata.extend(limbs);
+    calldata
+}
+
+/// Encode a serialized proof
+///
+/// Layout: `[n_bytes, n_words, word0, word1, ...]`, where each word holds the next 31
+/// bytes of the proof, big-endian, and the last word holds whatever bytes remain
+pub fn proof_calldata(proof: &[u8]) -> Vec<StarknetFieldElement> {
+    let words = proof
+        .chunks(PROOF_WORD_BYTES)
+        .map(|chunk| biguint_to_starknet_felt(&BigUint::from_bytes_be(chunk)))
+        .collect::<Vec<_>>();
+
+    let mut calldata = vec![
+        StarknetFieldElement::from(proof.len() as u64),
+        StarknetFieldElement::from(words.len() as u64),
+    ];
+    calldata.extend(words);
+
+    calldata
+}
+
+/// The scalars an ElGamal ciphertext is encoded as
+fn ciphertext_scalars(ciphertext: &ElGamalCiphertext) -> [Scalar; 2] {
+    [
+        ciphertext.partial_shared_secret,
+        ciphertext.encrypted_message,
+    ]
+}
+
+/// Encode a sequence of scalars as calldata
+fn encode_scalars(scalars: &[Scalar]) -> Vec<StarknetFieldElement> {
+    scalars.iter().map(scalar_to_starknet_felt_mod).collect()
+}
+
+/// Reduce a scalar into the Starknet field
+fn scalar_to_starknet_felt_mod(value: &Scalar) -> StarknetFieldElement {
+    let modulus = starknet_felt_to_biguint(&StarknetFieldElement::MAX) + 1u8;
+    biguint_to_starknet_felt(&(scalar_to_biguint(value) % modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use circuits::{
+        zk_circuits::{
+            valid_match_encryption::ValidMatchEncryptionStatement,
+            valid_wallet_create::ValidWalletCreateStatement,
+            valid_wallet_update::ValidWalletUpdateStatement,
+        },
+        zk_gadgets::{elgamal::ElGamalCiphertext, fixed_point::FixedPoint},
+    };
+    use crypto::fields::{biguint_to_scalar, biguint_to_starknet_felt};
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
+    use serde::Deserialize;
+    use starknet::core::types::FieldElement as StarknetFieldElement;
+
+    use crate::keychain::WalletCiphertext;
+
+    use super::{
+        match_calldata, new_wallet_calldata, parse_wallet_ciphertext, proof_calldata,
+        update_wallet_calldata, wallet_ciphertext_calldata, MATCH_FUNCTION, NEW_WALLET_FUNCTION,
+        UPDATE_WALLET_FUNCTION,
+    };
+
+    /// The fixtures exported from the contract repo
+    const CALLDATA_FIXTURES: &str =
+        include_str!("../../resources/contract_abi/calldata_fixtures.json");
+
+    /// A single test vector; the inputs to an encoding and the calldata the contract
+    /// expects for them, both hex encoded
+    #[derive(Debug, Deserialize)]
+    struct CalldataFixture {
+        /// What the vector exercises
+        description: String,
+        /// The encoding's inputs, in the order documented on the fixture's entrypoint
+        inputs: Vec<String>,
+        /// The expected calldata
+        calldata: Vec<String>,
+    }
+
+    /// Parse the fixtures, keyed by entrypoint name
+    fn load_fixtures() -> HashMap<String, Vec<CalldataFixture>> {
+        serde_json::from_str(CALLDATA_FIXTURES).unwrap()
+    }
+
+    /// Parse a hex encoded scalar
+    fn parse_scalar(hex: &str) -> Scalar {
+        let value = BigUint::parse_bytes(hex.trim_start_matches("0x").as_bytes(), 16).unwrap();
+        biguint_to_scalar(&value)
+    }
+
+    /// Build a ciphertext from two consecutive inputs
+    fn parse_ciphertext(inputs: &[Scalar]) -> ElGamalCiphertext {
+        ElGamalCiphertext {
+            partial_shared_secret: inputs[0],
+            encrypted_message: inputs[1],
+        }
+    }
+
+    /// Check every fixture of an entrypoint against the given encoding
+    fn check_fixtures<F>(entrypoint: &str, encode: F)
+    where
+        F: Fn(&[Scalar]) -> Vec<StarknetFieldElement>,
+    {
+        let fixtures = load_fixtures();
+        let vectors = fixtures
+            .get(entrypoint)
+            .unwrap_or_else(|| panic!("no fixtures for {entrypoint}"));
+        assert!(!vectors.is_empty());
+
+        for vector in vectors.iter() {
+            let inputs: Vec<Scalar> = vector.inputs.iter().map(|s| parse_scalar(s)).collect();
+            let expected: Vec<StarknetFieldElement> = vector
+                .calldata
+                .iter()
+                .map(|s| StarknetFieldElement::from_str(s).unwrap())
+                .collect();
+            assert_eq!(
+                encode(&inputs),
+                expected,
+                "{entrypoint}: {}",
+                vector.description
+            );
+        }
+    }
+
+    /// Tests the encoding of `new_wallet` against the contract's fixtures
+    #[test]
+    fn test_new_wallet_calldata() {
+        check_fixtures(NEW_WALLET_FUNCTION, |inputs| {
+            new_wallet_calldata(&ValidWalletCreateStatement {
+                wallet_commitment: inputs[0],
+            })
+        });
+    }
+
+    /// Tests the encoding of `update_wallet` against the contract's fixtures
+    ///
+    /// Inputs: `[timestamp, pk_root, new_wallet_commitment, spend_nullifier,
+    /// match_nullifier, merkle_root, mint, volume, direction]`, optionally followed by the
+    /// nonce and blocks of a wallet ciphertext
+    #[test]
+    fn test_update_wallet_calldata() {
+        check_fixtures(UPDATE_WALLET_FUNCTION, |inputs| {
+            let ciphertext = (inputs.len() > 9).then(|| WalletCiphertext {
+                nonce: inputs[9],
+                blocks: inputs[10..].to_vec(),
+            });
+
+            update_wallet_calldata(
+                &ValidWalletUpdateStatement {
+                    timestamp: inputs[0],
+                    pk_root: inputs[1],
+                    new_wallet_commitment: inputs[2],
+                    wallet_spend_nullifier: inputs[3],
+                    wallet_match_nullifier: inputs[4],
+                    merkle_root: inputs[5],
+                    external_transfer: (inputs[6], inputs[7], inputs[8]),
+                },
+                ciphertext.as_ref(),
+            )
+        });
+    }
+
+    /// Tests that a wallet ciphertext is recovered exactly from its encoding, including
+    /// scalars outside of the Starknet field
+    #[test]
+    fn test_wallet_ciphertext_roundtrip() {
+        let ciphertext = WalletCiphertext {
+            nonce: -Scalar::one(),
+            blocks: vec![Scalar::zero(), Scalar::from(5u8), -Scalar::from(2u8)],
+        };
+
+        let encoded = wallet_ciphertext_calldata(Some(&ciphertext));
+        assert_eq!(encoded.len(), 9);
+        assert_eq!(parse_wallet_ciphertext(&encoded), Some(ciphertext));
+
+        assert!(parse_wallet_ciphertext(&wallet_ciphertext_calldata(None)).is_none());
+        assert!(parse_wallet_ciphertext(&encoded[..8]).is_none());
+    }
+
+    /// Tests the encoding of `match` against the contract's fixtures
+    ///
+    /// Inputs: `[match_nullifier0, match_nullifier1]`, then the statement's fields in
+    /// declaration order with each ciphertext as two consecutive inputs
+    #[test]
+    fn test_match_calldata() {
+        check_fixtures(MATCH_FUNCTION, |inputs| {
+            let statement = ValidMatchEncryptionStatement {
+                party0_note_commit: inputs[2],
+                party1_note_commit: inputs[3],
+                relayer0_note_commit: inputs[4],
+                relayer1_note_commit: inputs[5],
+                protocol_note_commit: inputs[6],
+                pk_settle_party0: inputs[7],
+                pk_settle_party1: inputs[8],
+                pk_settle_relayer0: inputs[9],
+                pk_settle_relayer1: inputs[10],
+                pk_settle_protocol: inputs[11],
+                protocol_fee: FixedPoint::from(inputs[12]),
+                volume1_ciphertext1: parse_ciphertext(&inputs[13..15]),
+                volume2_ciphertext1: parse_ciphertext(&inputs[15..17]),
+                volume1_ciphertext2: parse_ciphertext(&inputs[17..19]),
+                volume2_ciphertext2: parse_ciphertext(&inputs[19..21]),
+                mint1_protocol_ciphertext: parse_ciphertext(&inputs[21..23]),
+                volume1_protocol_ciphertext: parse_ciphertext(&inputs[23..25]),
+                mint2_protocol_ciphertext: parse_ciphertext(&inputs[25..27]),
+                volume2_protocol_ciphertext: parse_ciphertext(&inputs[27..29]),
+                randomness_protocol_ciphertext: parse_ciphertext(&inputs[29..31]),
+            };
+
+            match_calldata(inputs[0], inputs[1], &statement)
+        });
+    }
+
+    /// Tests that a proof is packed into length-prefixed 31 byte words
+    #[test]
+    fn test_proof_calldata() {
+        let proof = (0..64u8).collect::<Vec<_>>();
+        let encoded = proof_calldata(&proof);
+
+        assert_eq!(encoded.len(), 5);
+        assert_eq!(encoded[0], StarknetFieldElement::from(64u8));
+        assert_eq!(encoded[1], StarknetFieldElement::from(3u8));
+        assert_eq!(
+            encoded[2],
+            biguint_to_starknet_felt(&BigUint::from_bytes_be(&proof[..31]))
+        );
+        assert_eq!(encoded[4], StarknetFieldElement::from(0x3e3fu16));
+
+        assert_eq!(proof_calldata(&[]), vec![StarknetFieldElement::ZERO; 2]);
+    }
+}
@@ -0,0 +1,36 @@
+//! Defines error types emitted by the starknet client
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::error::{ErrorCode, ErrorCoded};
+
+/// The error type returned by the starknet client
+#[derive(Clone, Debug)]
+pub enum StarknetClientError {
+    /// The client is not configured with an account to send transactions from
+    NoAccount,
+    /// An error reading the account's nonce from the chain
+    Nonce(String),
+    /// A config value could not be parsed
+    Parse(String),
+    /// An error submitting a transaction to the sequencer
+    Transaction(String),
+}
+
+impl std::error::Error for StarknetClientError {}
+impl Display for StarknetClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl ErrorCoded for StarknetClientError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            StarknetClientError::NoAccount => ErrorCode::Config,
+            StarknetClientError::Nonce(_) => ErrorCode::Chain,
+            StarknetClientError::Parse(_) => ErrorCode::Config,
+            StarknetClientError::Transaction(_) => ErrorCode::TransactionRejected,
+        }
+    }
+}
@@ -8,6 +8,9 @@ use serde::{Deserialize, Serialize};
 use starknet::core::types::FieldElement as StarknetFieldElement;
 
 pub mod client;
+pub mod contract_abi;
+pub mod error;
+pub mod transaction_manager;
 
 /// Starknet mainnet chain-id
 /// TODO: use `starknet-rs` implementation once we upgrade versions
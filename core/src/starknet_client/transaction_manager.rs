@@ -0,0 +1,621 @@
+//! Tracks transactions submitted by the relayer's account
+//!
+//! The manager serializes nonce assignment so that concurrent submissions do not race
+//! for the same nonce, and follows each transaction until the sequencer accepts it. A
+//! transaction rejected for its fee is re-broadcast under the same nonce with a bumped
+//! fee; any other rejection is terminal and is sent back as a job to the worker that
+//! submitted the transaction
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use starknet::{
+    accounts::{Account, Call, SingleOwnerAccount},
+    core::types::{BlockId, FieldElement as StarknetFieldElement, TransactionStatus},
+    signers::LocalWallet,
+};
+use starknet_providers::SequencerGatewayProvider;
+use tokio::sync::{mpsc::UnboundedSender as TokioSender, Mutex as TokioMutex, RwLock};
+use tracing::log;
+
+use super::error::StarknetClientError;
+
+/// The account type that the relayer submits transactions from
+pub(super) type StarknetAccount = SingleOwnerAccount<SequencerGatewayProvider, LocalWallet>;
+
+/// The percentage of the estimated fee set as the max fee of a first broadcast
+const INITIAL_FEE_MARGIN_PERCENT: u64 = 120;
+/// The percentage that a transaction's max fee is bumped by on each fee rejection
+const FEE_BUMP_PERCENT: u64 = 150;
+/// The number of times a transaction is re-broadcast with a bumped fee before the
+/// manager gives up on it
+const MAX_FEE_BUMPS: usize = 3;
+/// The interval at which the status of a pending transaction is polled
+const TRANSACTION_POLL_INTERVAL_MS: u64 = 10_000; // 10 seconds
+/// The amount of time a transaction may remain pending before it is considered failed
+const TRANSACTION_TIMEOUT_MS: u64 = 30 * 60 * 1000; // 30 minutes
+
+/// The account operations that the manager submits transactions through
+///
+/// Implemented by the relayer's account; the manager is generic over the trait so that a
+/// mock account may be injected in its place
+#[async_trait]
+pub trait TransactionSubmitter: Send + Sync {
+    /// Read the account's next nonce from the pending block
+    async fn get_nonce(&self) -> Result<StarknetFieldElement, StarknetClientError>;
+
+    /// Estimate the overall fee of a set of calls under the given nonce
+    async fn estimate_fee(
+        &self,
+        calls: &[Call],
+        nonce: StarknetFieldElement,
+    ) -> Result<u64, StarknetClientError>;
+
+    /// Broadcast a set of calls under the given nonce and max fee, returning the hash of
+    /// the transaction
+    async fn send(
+        &self,
+        calls: &[Call],
+        nonce: StarknetFieldElement,
+        max_fee: u64,
+    ) -> Result<StarknetFieldElement, StarknetClientError>;
+}
+
+#[async_trait]
+impl TransactionSubmitter for StarknetAccount {
+    async fn get_nonce(&self) -> Result<StarknetFieldElement, StarknetClientError> {
+        Account::get_nonce(self, BlockId::Pending)
+            .await
+            .map_err(|err| StarknetClientError::Nonce(err.to_string()))
+    }
+
+    async fn estimate_fee(
+        &self,
+        calls: &[Call],
+        nonce: StarknetFieldElement,
+    ) -> Result<u64, StarknetClientError> {
+        self.execute(calls.to_vec())
+            .nonce(nonce)
+            .estimate_fee()
+            .await
+            .map(|estimate| estimate.overall_fee)
+            .map_err(|err| StarknetClientError::Transaction(err.to_string()))
+    }
+
+    async fn send(
+        &self,
+        calls: &[Call],
+        nonce: StarknetFieldElement,
+        max_fee: u64,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        self.execute(calls.to_vec())
+            .nonce(nonce)
+            .max_fee(StarknetFieldElement::from(max_fee))
+            .send()
+            .await
+            .map(|res| res.transaction_hash)
+            .map_err(|err| StarknetClientError::Transaction(err.to_string()))
+    }
+}
+
+/// A source of the statuses of broadcast transactions
+#[async_trait]
+pub trait TransactionStatusSource: Send + Sync {
+    /// Get the status of a transaction, along with the sequencer's reason for rejecting
+    /// it, if any
+    async fn get_transaction_status(
+        &self,
+        tx_hash: StarknetFieldElement,
+    ) -> Result<(TransactionStatus, Option<String>), String>;
+}
+
+#[async_trait]
+impl TransactionStatusSource for SequencerGatewayProvider {
+    async fn get_transaction_status(
+        &self,
+        tx_hash: StarknetFieldElement,
+    ) -> Result<(TransactionStatus, Option<String>), String> {
+        let status = SequencerGatewayProvider::get_transaction_status(self, tx_hash)
+            .await
+            .map_err(|err| err.to_string())?;
+        let reason = status
+            .transaction_failure_reason
+            .and_then(|failure| failure.error_message);
+
+        Ok((status.status, reason))
+    }
+}
+
+/// A job sent back to the worker that submitted a transaction once the transaction has
+/// failed terminally
+#[derive(Clone, Debug)]
+pub struct TransactionFailedJob {
+    /// The hash of the last broadcast of the transaction
+    pub tx_hash: StarknetFieldElement,
+    /// The nonce the transaction was submitted under
+    pub nonce: StarknetFieldElement,
+    /// The reason the transaction failed
+    pub reason: String,
+}
+
+/// A transaction that has been broadcast but not yet accepted
+#[derive(Clone, Debug)]
+pub struct PendingTransaction {
+    /// The nonce the transaction was submitted under
+    pub nonce: StarknetFieldElement,
+    /// The max fee of the latest broadcast
+    pub max_fee: u64,
+    /// The number of times the transaction's fee has been bumped
+    pub fee_bumps: usize,
+}
+
+/// Serializes nonce assignment for the relayer's account and follows submitted
+/// transactions to acceptance
+#[derive(Clone)]
+pub struct TransactionManager {
+    /// The gateway client used to poll the status of pending transactions
+    gateway_client: Arc<dyn TransactionStatusSource>,
+    /// The nonce to assign to the next transaction, `None` if it must be re-read from
+    /// the chain
+    ///
+    /// The lock is held across a broadcast so that nonces are assigned in the order
+    /// that transactions are submitted
+    next_nonce: Arc<TokioMutex<Option<StarknetFieldElement>>>,
+    /// The transactions that have been broadcast but not yet accepted, keyed by the
+    /// hash of their latest broadcast
+    pending: Arc<RwLock<HashMap<StarknetFieldElement, PendingTransaction>>>,
+}
+
+impl TransactionManager {
+    /// Constructor
+    pub fn new(gateway_client: Arc<dyn TransactionStatusSource>) -> Self {
+        Self {
+            gateway_client,
+            next_nonce: Arc::new(TokioMutex::new(None)),
+            pending: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Get a snapshot of the transactions that have not yet been accepted
+    pub async fn pending_transactions(&self) -> HashMap<StarknetFieldElement, PendingTransaction> {
+        self.pending.read().await.clone()
+    }
+
+    /// Assign the next nonce to a set of calls and broadcast them, returning the hash of
+    /// the transaction
+    ///
+    /// The transaction is followed in the background after it is broadcast; if it fails
+    /// terminally a `TransactionFailedJob` is sent on `failure_queue`. The queue is
+    /// dropped without a job once the transaction is accepted
+    pub async fn submit<A: TransactionSubmitter + 'static>(
+        &self,
+        account: Arc<A>,
+        calls: Vec<Call>,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let mut locked_nonce = self.next_nonce.lock().await;
+        let nonce = match *locked_nonce {
+            Some(nonce) => nonce,
+            None => account.get_nonce().await?,
+        };
+
+        let estimated_fee = account.estimate_fee(&calls, nonce).await?;
+        let max_fee = estimated_fee * INITIAL_FEE_MARGIN_PERCENT / 100;
+
+        let (tx_hash, pending) = match broadcast(&*account, &calls, nonce, max_fee, 0).await {
+            Ok(res) => res,
+            Err(err) => {
+                // The nonce may have been consumed out from under the manager, re-read it
+                // before the next submission
+                *locked_nonce = None;
+                return Err(err);
+            }
+        };
+
+        *locked_nonce = Some(nonce + StarknetFieldElement::ONE);
+        self.pending.write().await.insert(tx_hash, pending);
+        drop(locked_nonce);
+
+        let self_clone = self.clone();
+        tokio::spawn(async move {
+            self_clone
+                .follow_transaction(account, calls, tx_hash, failure_queue)
+                .await
+        });
+
+        Ok(tx_hash)
+    }
+
+    /// Poll a transaction until it is accepted, re-broadcasting it on fee rejection
+    async fn follow_transaction<A: TransactionSubmitter>(
+        &self,
+        account: Arc<A>,
+        calls: Vec<Call>,
+        mut tx_hash: StarknetFieldElement,
+        failure_queue: TokioSender<TransactionFailedJob>,
+    ) {
+        let max_polls = TRANSACTION_TIMEOUT_MS / TRANSACTION_POLL_INTERVAL_MS;
+        for _ in 0..max_polls {
+            tokio::time::sleep(Duration::from_millis(TRANSACTION_POLL_INTERVAL_MS)).await;
+
+            let (status, failure_reason) =
+                match self.gateway_client.get_transaction_status(tx_hash).await {
+                    Ok(status) => status,
+                    Err(err) => {
+                        log::warn!("error polling status of transaction {tx_hash}: {err}");
+                        continue;
+                    }
+                };
+
+            match status {
+                TransactionStatus::AcceptedOnL2 | TransactionStatus::AcceptedOnL1 => {
+                    self.pending.write().await.remove(&tx_hash);
+                    return;
+                }
+                TransactionStatus::Rejected => {
+                    let reason =
+                        failure_reason.unwrap_or_else(|| "transaction rejected".to_string());
+
+                    match self.rebroadcast(&*account, &calls, tx_hash, &reason).await {
+                        Ok(new_hash) => {
+                            log::info!("re-broadcast transaction {tx_hash} as {new_hash}");
+                            tx_hash = new_hash;
+                        }
+                        Err(reason) => {
+                            self.fail_transaction(tx_hash, reason, &failure_queue).await;
+                            return;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let reason = "transaction was not accepted before timing out".to_string();
+        self.fail_transaction(tx_hash, reason, &failure_queue).await;
+    }
+
+    /// Re-broadcast a rejected transaction under its original nonce with a bumped fee,
+    /// returning the hash of the new broadcast
+    ///
+    /// Returns an error holding the terminal failure reason if the rejection was not for
+    /// the transaction's fee, or if its fee has already been bumped too many times
+    async fn rebroadcast<A: TransactionSubmitter + ?Sized>(
+        &self,
+        account: &A,
+        calls: &[Call],
+        tx_hash: StarknetFieldElement,
+        reason: &str,
+    ) -> Result<StarknetFieldElement, String> {
+        let pending = self
+            .pending
+            .read()
+            .await
+            .get(&tx_hash)
+            .cloned()
+            .ok_or_else(|| reason.to_string())?;
+        if !is_fee_rejection(reason) || pending.fee_bumps >= MAX_FEE_BUMPS {
+            return Err(reason.to_string());
+        }
+
+        let max_fee = pending.max_fee * FEE_BUMP_PERCENT / 100;
+        let (new_hash, new_pending) = broadcast(
+            account,
+            calls,
+            pending.nonce,
+            max_fee,
+            pending.fee_bumps + 1,
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+
+        let mut locked_pending = self.pending.write().await;
+        locked_pending.remove(&tx_hash);
+        locked_pending.insert(new_hash, new_pending);
+
+        Ok(new_hash)
+    }
+
+    /// Stop tracking a transaction and send its failure back to the submitting worker
+    async fn fail_transaction(
+        &self,
+        tx_hash: StarknetFieldElement,
+        reason: String,
+        failure_queue: &TokioSender<TransactionFailedJob>,
+    ) {
+        log::error!("transaction {tx_hash} failed: {reason}");
+        let pending = self.pending.write().await.remove(&tx_hash);
+
+        // A rejected transaction does not consume its nonce, so later nonces assigned by
+        // the manager are no longer contiguous; re-read the nonce before the next submission
+        *self.next_nonce.lock().await = None;
+
+        if let Some(pending) = pending {
+            let _ = failure_queue.send(TransactionFailedJob {
+                tx_hash,
+                nonce: pending.nonce,
+                reason,
+            });
+        }
+    }
+}
+
+/// Broadcast a set of calls under the given nonce and max fee, bumping the fee and
+/// retrying if the gateway rejects the fee outright
+async fn broadcast<A: TransactionSubmitter + ?Sized>(
+    account: &A,
+    calls: &[Call],
+    nonce: StarknetFieldElement,
+    mut max_fee: u64,
+    mut fee_bumps: usize,
+) -> Result<(StarknetFieldElement, PendingTransaction), StarknetClientError> {
+    loop {
+        match account.send(calls, nonce, max_fee).await {
+            Ok(tx_hash) => {
+                return Ok((
+                    tx_hash,
+                    PendingTransaction {
+                        nonce,
+                        max_fee,
+                        fee_bumps,
+                    },
+                ))
+            }
+            Err(StarknetClientError::Transaction(reason))
+                if is_fee_rejection(&reason) && fee_bumps < MAX_FEE_BUMPS =>
+            {
+                max_fee = max_fee * FEE_BUMP_PERCENT / 100;
+                fee_bumps += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Whether a rejection reason reported by the sequencer is due to the transaction's fee
+fn is_fee_rejection(reason: &str) -> bool {
+    reason.to_lowercase().contains("fee")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+    use starknet::{
+        accounts::Call,
+        core::types::{FieldElement as StarknetFieldElement, TransactionStatus},
+    };
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use crate::starknet_client::error::StarknetClientError;
+
+    use super::{
+        TransactionManager, TransactionStatusSource, TransactionSubmitter, FEE_BUMP_PERCENT,
+        INITIAL_FEE_MARGIN_PERCENT, MAX_FEE_BUMPS,
+    };
+
+    /// The fee the mock account estimates for every transaction
+    const ESTIMATED_FEE: u64 = 1_000;
+    /// A rejection reason the manager treats as a fee rejection
+    const FEE_REJECTION: &str = "max fee is too low";
+    /// A rejection reason the manager treats as terminal
+    const TERMINAL_REJECTION: &str = "invalid signature";
+
+    /// An account that accepts every broadcast not scripted to be rejected
+    #[derive(Default)]
+    struct MockAccount {
+        /// The nonce the chain reports for the account
+        chain_nonce: Mutex<u64>,
+        /// The number of times the nonce was read from the chain
+        nonce_reads: Mutex<usize>,
+        /// The reasons to reject the next broadcasts with, in order
+        rejections: Mutex<VecDeque<String>>,
+        /// The nonce and max fee of every accepted broadcast
+        sent: Mutex<Vec<(StarknetFieldElement, u64)>>,
+    }
+
+    impl MockAccount {
+        /// Reject the next `n` broadcasts with the given reason
+        fn reject_next(&self, n: usize, reason: &str) {
+            let mut rejections = self.rejections.lock().unwrap();
+            rejections.extend((0..n).map(|_| reason.to_string()));
+        }
+    }
+
+    #[async_trait]
+    impl TransactionSubmitter for MockAccount {
+        async fn get_nonce(&self) -> Result<StarknetFieldElement, StarknetClientError> {
+            *self.nonce_reads.lock().unwrap() += 1;
+            Ok(StarknetFieldElement::from(
+                *self.chain_nonce.lock().unwrap(),
+            ))
+        }
+
+        async fn estimate_fee(
+            &self,
+            _calls: &[Call],
+            _nonce: StarknetFieldElement,
+        ) -> Result<u64, StarknetClientError> {
+            Ok(ESTIMATED_FEE)
+        }
+
+        async fn send(
+            &self,
+            _calls: &[Call],
+            nonce: StarknetFieldElement,
+            max_fee: u64,
+        ) -> Result<StarknetFieldElement, StarknetClientError> {
+            if let Some(reason) = self.rejections.lock().unwrap().pop_front() {
+                return Err(StarknetClientError::Transaction(reason));
+            }
+
+            let mut sent = self.sent.lock().unwrap();
+            sent.push((nonce, max_fee));
+            Ok(StarknetFieldElement::from(sent.len() as u64))
+        }
+    }
+
+    /// A status source for transactions that are never polled within a test
+    struct UnpolledStatusSource;
+
+    #[async_trait]
+    impl TransactionStatusSource for UnpolledStatusSource {
+        async fn get_transaction_status(
+            &self,
+            _tx_hash: StarknetFieldElement,
+        ) -> Result<(TransactionStatus, Option<String>), String> {
+            Err("transaction status is not polled in tests".to_string())
+        }
+    }
+
+    /// Build a manager and an account whose chain nonce is the given value
+    fn setup(chain_nonce: u64) -> (TransactionManager, Arc<MockAccount>) {
+        let account = MockAccount {
+            chain_nonce: Mutex::new(chain_nonce),
+            ..Default::default()
+        };
+        (
+            TransactionManager::new(Arc::new(UnpolledStatusSource)),
+            Arc::new(account),
+        )
+    }
+
+    /// A single call to submit
+    fn calls() -> Vec<Call> {
+        vec![Call {
+            to: StarknetFieldElement::ONE,
+            selector: StarknetFieldElement::ONE,
+            calldata: Vec::new(),
+        }]
+    }
+
+    /// Submit a transaction, discarding its failure queue
+    async fn submit(
+        manager: &TransactionManager,
+        account: &Arc<MockAccount>,
+    ) -> Result<StarknetFieldElement, StarknetClientError> {
+        let (failure_sender, _) = unbounded_channel();
+        manager
+            .submit(account.clone(), calls(), failure_sender)
+            .await
+    }
+
+    /// Tests that consecutive submissions are assigned consecutive nonces, reading the
+    /// nonce from the chain only once
+    #[tokio::test]
+    async fn test_sequential_nonces() {
+        let (manager, account) = setup(5 /* chain_nonce */);
+        for _ in 0..3 {
+            submit(&manager, &account).await.unwrap();
+        }
+
+        let nonces: Vec<_> = account.sent.lock().unwrap().iter().map(|s| s.0).collect();
+        assert_eq!(nonces, [5u64, 6, 7].map(StarknetFieldElement::from));
+        assert_eq!(*account.nonce_reads.lock().unwrap(), 1);
+
+        let pending = manager.pending_transactions().await;
+        assert_eq!(pending.len(), 3);
+    }
+
+    /// Tests that the nonce is re-read from the chain once a broadcast fails or a broadcast
+    /// transaction fails terminally, so that the gap left by the failure is filled
+    #[tokio::test]
+    async fn test_nonce_gap_reread() {
+        let (manager, account) = setup(5 /* chain_nonce */);
+        submit(&manager, &account).await.unwrap();
+        *account.chain_nonce.lock().unwrap() = 6;
+
+        // A terminal rejection of the broadcast itself does not consume the nonce
+        account.reject_next(1, TERMINAL_REJECTION);
+        assert!(submit(&manager, &account).await.is_err());
+        let tx_hash = submit(&manager, &account).await.unwrap();
+        assert_eq!(*account.nonce_reads.lock().unwrap(), 2);
+        assert_eq!(
+            account.sent.lock().unwrap()[1].0,
+            StarknetFieldElement::from(6u64)
+        );
+
+        // A transaction rejected after it was broadcast leaves a gap at its nonce
+        let (failure_sender, mut failure_receiver) = unbounded_channel();
+        manager
+            .fail_transaction(tx_hash, TERMINAL_REJECTION.to_string(), &failure_sender)
+            .await;
+        let job = failure_receiver.try_recv().unwrap();
+        assert_eq!(job.tx_hash, tx_hash);
+        assert_eq!(job.nonce, StarknetFieldElement::from(6u64));
+        assert!(!manager.pending_transactions().await.contains_key(&tx_hash));
+
+        submit(&manager, &account).await.unwrap();
+        assert_eq!(*account.nonce_reads.lock().unwrap(), 3);
+        assert_eq!(
+            account.sent.lock().unwrap()[2].0,
+            StarknetFieldElement::from(6u64)
+        );
+    }
+
+    /// Tests that a transaction rejected for its fee is replaced by a broadcast under the
+    /// same nonce with a bumped fee, and that any other rejection is terminal
+    #[tokio::test]
+    async fn test_fee_bump_replacement() {
+        let (manager, account) = setup(0 /* chain_nonce */);
+        let tx_hash = submit(&manager, &account).await.unwrap();
+        let initial_fee = ESTIMATED_FEE * INITIAL_FEE_MARGIN_PERCENT / 100;
+
+        let res = manager
+            .rebroadcast(&*account, &calls(), tx_hash, TERMINAL_REJECTION)
+            .await;
+        assert_eq!(res, Err(TERMINAL_REJECTION.to_string()));
+        assert!(manager.pending_transactions().await.contains_key(&tx_hash));
+
+        let new_hash = manager
+            .rebroadcast(&*account, &calls(), tx_hash, FEE_REJECTION)
+            .await
+            .unwrap();
+        let pending = manager.pending_transactions().await;
+        assert!(!pending.contains_key(&tx_hash));
+
+        let replacement = &pending[&new_hash];
+        assert_eq!(replacement.nonce, StarknetFieldElement::ZERO);
+        assert_eq!(replacement.max_fee, initial_fee * FEE_BUMP_PERCENT / 100);
+        assert_eq!(replacement.fee_bumps, 1);
+    }
+
+    /// Tests that a transaction's fee is bumped at most `MAX_FEE_BUMPS` times, whether it
+    /// is rejected by the gateway on broadcast or by the sequencer once broadcast
+    #[tokio::test]
+    async fn test_fee_bump_limit() {
+        let (manager, account) = setup(0 /* chain_nonce */);
+
+        // Rejected on broadcast, the fee is bumped up to the limit and then given up on
+        account.reject_next(MAX_FEE_BUMPS + 1, FEE_REJECTION);
+        assert!(submit(&manager, &account).await.is_err());
+        assert!(account.sent.lock().unwrap().is_empty());
+
+        account.reject_next(MAX_FEE_BUMPS, FEE_REJECTION);
+        let mut tx_hash = submit(&manager, &account).await.unwrap();
+        let pending = manager.pending_transactions().await;
+        assert_eq!(pending[&tx_hash].fee_bumps, MAX_FEE_BUMPS);
+
+        // A transaction broadcast without a bump may be re-broadcast up to the limit
+        tx_hash = submit(&manager, &account).await.unwrap();
+        for _ in 0..MAX_FEE_BUMPS {
+            tx_hash = manager
+                .rebroadcast(&*account, &calls(), tx_hash, FEE_REJECTION)
+                .await
+                .unwrap();
+        }
+
+        let res = manager
+            .rebroadcast(&*account, &calls(), tx_hash, FEE_REJECTION)
+            .await;
+        assert_eq!(res, Err(FEE_REJECTION.to_string()));
+        assert_eq!(
+            manager.pending_transactions().await[&tx_hash].fee_bumps,
+            MAX_FEE_BUMPS
+        );
+    }
+}
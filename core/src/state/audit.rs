@@ -0,0 +1,466 @@
+//! Persists selected system bus messages to an audit log, giving operators a durable
+//! record of handshakes, matches, and wallet updates
+//!
+//! The log is a JSON-lines file: each line is an `AuditRecord` holding one event along with
+//! a checksum chained over the previous record's checksum, so that a record altered or
+//! removed after the fact breaks the chain. When the active file would grow past its size
+//! limit it is rotated to `<path>.1`, shifting older files up by one and dropping the oldest;
+//! each file begins with a record carrying the checksum it chains from, so every file may be
+//! verified on its own.
+//!
+//! Records are appended and synced one at a time, so a crash can at most leave a torn final
+//! line; a torn line is discarded when the log is next opened.
+
+use futures::{stream::select_all, StreamExt};
+use hmac_sha256::Hash;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::{self, Display},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tracing::log;
+
+use crate::{
+    clock::SharedClock,
+    system_bus::SystemBus,
+    types::{SystemBusMessage, HANDSHAKE_STATUS_TOPIC, SETTLEMENT_STATUS_TOPIC},
+};
+
+/// The number of recent events held in memory to serve queries
+const RECENT_EVENTS_CAPACITY: usize = 1_024;
+/// The checksum the first record ever written chains from
+const GENESIS_CHECKSUM: [u8; 32] = [0u8; 32];
+
+/// The topics audited when none are configured; every wallet's update topic is audited
+/// through a wildcard subscription
+pub fn default_audit_topics() -> Vec<String> {
+    vec![
+        HANDSHAKE_STATUS_TOPIC.to_string(),
+        SETTLEMENT_STATUS_TOPIC.to_string(),
+        "wallet-updates-*".to_string(),
+    ]
+}
+
+/// An error reading or writing the audit log
+#[derive(Clone, Debug)]
+pub enum AuditLogError {
+    /// Reading, writing, or rotating a log file failed
+    Io(String),
+    /// Serializing or parsing a record failed
+    Serialize(String),
+    /// The record with the given sequence number does not match its checksum, or does not
+    /// chain from the record before it
+    ChecksumMismatch(u64),
+}
+
+impl Error for AuditLogError {}
+impl Display for AuditLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The configuration of the audit log
+#[derive(Clone, Debug)]
+pub struct AuditLogConfig {
+    /// The active log file, rotated files are written alongside it
+    pub path: String,
+    /// The system bus topics audited, a topic ending in `*` audits every topic with the
+    /// preceding prefix
+    pub topics: Vec<String>,
+    /// The size past which the active file is rotated
+    pub max_file_bytes: u64,
+    /// The number of files kept, including the active file
+    pub max_files: usize,
+}
+
+/// A system bus message recorded in the audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// The position of the event in the log, counted from the first event ever written
+    pub sequence: u64,
+    /// The time the event was recorded, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// The topic subscription the message was received on
+    pub topic: String,
+    /// The message published to the system bus
+    pub message: SystemBusMessage,
+}
+
+/// A single line of the audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct AuditRecord {
+    /// The event recorded
+    event: AuditEvent,
+    /// The checksum of the previous record, hex encoded
+    prev_checksum: String,
+    /// The checksum over the previous checksum and the event, hex encoded
+    checksum: String,
+}
+
+impl AuditRecord {
+    /// Verify the record's checksum, returning it decoded
+    fn verify(&self) -> Result<[u8; 32], AuditLogError> {
+        let mismatch = || AuditLogError::ChecksumMismatch(self.event.sequence);
+        let prev_checksum: [u8; 32] = hex::decode(&self.prev_checksum)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(mismatch)?;
+
+        let checksum = compute_checksum(&prev_checksum, &self.event)?;
+        if hex::encode(checksum) != self.checksum {
+            return Err(mismatch());
+        }
+
+        Ok(checksum)
+    }
+}
+
+/// Compute the checksum of an event chained from the previous record's checksum
+fn compute_checksum(
+    prev_checksum: &[u8; 32],
+    event: &AuditEvent,
+) -> Result<[u8; 32], AuditLogError> {
+    let serialized =
+        serde_json::to_vec(event).map_err(|err| AuditLogError::Serialize(err.to_string()))?;
+    Ok(Hash::hash(
+        &[prev_checksum.as_slice(), &serialized].concat(),
+    ))
+}
+
+/// The mutable state of the audit log
+#[derive(Debug)]
+struct AuditLogState {
+    /// The sequence number of the next event
+    next_sequence: u64,
+    /// The checksum of the last record written
+    last_checksum: [u8; 32],
+    /// The size of the active file, in bytes
+    file_bytes: u64,
+    /// The most recent events, oldest first
+    recent_events: VecDeque<AuditEvent>,
+}
+
+/// An append-only, rotating, checksummed log of system bus messages
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    /// The configuration of the log
+    config: AuditLogConfig,
+    /// The clock that events are timestamped by
+    clock: SharedClock,
+    /// The mutable state of the log, locked across each append so that records are
+    /// written in sequence
+    state: Arc<Mutex<AuditLogState>>,
+}
+
+impl AuditLog {
+    /// Open the audit log at the configured path, verifying the records written by a
+    /// previous run and continuing the chain from the last of them
+    pub fn open(config: AuditLogConfig, clock: SharedClock) -> Result<Self, AuditLogError> {
+        let mut state = AuditLogState {
+            next_sequence: 0,
+            last_checksum: GENESIS_CHECKSUM,
+            file_bytes: 0,
+            recent_events: VecDeque::new(),
+        };
+
+        if Path::new(&config.path).exists() {
+            let contents = fs::read_to_string(&config.path)
+                .map_err(|err| AuditLogError::Io(err.to_string()))?;
+
+            let mut lines = contents.split_inclusive('\n').peekable();
+            let mut valid_bytes = 0;
+            let mut prev_checksum: Option<[u8; 32]> = None;
+            while let Some(line) = lines.next() {
+                let record = match serde_json::from_str::<AuditRecord>(line) {
+                    Ok(record) => record,
+                    // A crash mid-append leaves a torn final line, discard it
+                    Err(_) if lines.peek().is_none() && !line.ends_with('\n') => {
+                        log::warn!("discarding torn record at the end of the audit log");
+                        break;
+                    }
+                    Err(err) => return Err(AuditLogError::Serialize(err.to_string())),
+                };
+
+                // Each record after the first in the file must chain from the one before it
+                let checksum = record.verify()?;
+                if let Some(prev) = prev_checksum && hex::encode(prev) != record.prev_checksum {
+                    return Err(AuditLogError::ChecksumMismatch(record.event.sequence));
+                }
+
+                prev_checksum = Some(checksum);
+                valid_bytes += line.len();
+                state.next_sequence = record.event.sequence + 1;
+                state.last_checksum = checksum;
+                state.recent_events.push_back(record.event);
+                if state.recent_events.len() > RECENT_EVENTS_CAPACITY {
+                    state.recent_events.pop_front();
+                }
+            }
+
+            // Rewrite the file without a torn final line so that appends begin on a new line
+            if valid_bytes < contents.len() {
+                let tmp_path = format!("{}.tmp", config.path);
+                fs::write(&tmp_path, &contents[..valid_bytes])
+                    .and_then(|_| fs::rename(&tmp_path, &config.path))
+                    .map_err(|err| AuditLogError::Io(err.to_string()))?;
+            }
+            state.file_bytes = valid_bytes as u64;
+        }
+
+        Ok(Self {
+            config,
+            clock,
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// Record the messages published to the configured topics until the system bus closes
+    pub async fn run(self, system_bus: SystemBus<SystemBusMessage>) {
+        let readers = self.config.topics.iter().map(|topic| {
+            let topic = topic.clone();
+            system_bus
+                .subscribe(topic.clone())
+                .map(move |message| (topic.clone(), message))
+        });
+
+        let mut messages = select_all(readers);
+        while let Some((topic, message)) = messages.next().await {
+            if let Err(err) = self.append(topic, message) {
+                log::error!("error appending to audit log: {}", err);
+            }
+        }
+    }
+
+    /// Append a message to the log, returning the recorded event
+    pub fn append(
+        &self,
+        topic: String,
+        message: SystemBusMessage,
+    ) -> Result<AuditEvent, AuditLogError> {
+        let mut locked_state = self.state.lock().unwrap();
+        let event = AuditEvent {
+            sequence: locked_state.next_sequence,
+            timestamp: self.clock.unix_time().as_millis() as u64,
+            topic,
+            message,
+        };
+
+        let checksum = compute_checksum(&locked_state.last_checksum, &event)?;
+        let record = AuditRecord {
+            event: event.clone(),
+            prev_checksum: hex::encode(locked_state.last_checksum),
+            checksum: hex::encode(checksum),
+        };
+        let mut line =
+            serde_json::to_vec(&record).map_err(|err| AuditLogError::Serialize(err.to_string()))?;
+        line.push(b'\n');
+
+        if locked_state.file_bytes > 0
+            && locked_state.file_bytes + line.len() as u64 > self.config.max_file_bytes
+        {
+            self.rotate()?;
+            locked_state.file_bytes = 0;
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.path)
+            .and_then(|mut file| {
+                file.write_all(&line)?;
+                file.sync_data()
+            })
+            .map_err(|err| AuditLogError::Io(err.to_string()))?;
+
+        locked_state.next_sequence += 1;
+        locked_state.last_checksum = checksum;
+        locked_state.file_bytes += line.len() as u64;
+        locked_state.recent_events.push_back(event.clone());
+        if locked_state.recent_events.len() > RECENT_EVENTS_CAPACITY {
+            locked_state.recent_events.pop_front();
+        }
+
+        Ok(event)
+    }
+
+    /// The most recent events, newest first, optionally filtered to a single topic
+    pub fn recent_events(&self, topic: Option<&str>, limit: usize) -> Vec<AuditEvent> {
+        self.state
+            .lock()
+            .unwrap()
+            .recent_events
+            .iter()
+            .rev()
+            .filter(|event| topic.map(|topic| event.topic == topic).unwrap_or(true))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Rotate the active file to `<path>.1`, shifting older files up by one and dropping
+    /// the oldest
+    fn rotate(&self) -> Result<(), AuditLogError> {
+        let path = &self.config.path;
+        let rotated_path = |index: usize| format!("{path}.{index}");
+        let io_err = |err: std::io::Error| AuditLogError::Io(err.to_string());
+
+        let n_rotated = self.config.max_files.saturating_sub(1);
+        if n_rotated == 0 {
+            return fs::remove_file(path).map_err(io_err);
+        }
+
+        let oldest = rotated_path(n_rotated);
+        if Path::new(&oldest).exists() {
+            fs::remove_file(&oldest).map_err(io_err)?;
+        }
+        for index in (1..n_rotated).rev() {
+            let from = rotated_path(index);
+            if Path::new(&from).exists() {
+                fs::rename(&from, rotated_path(index + 1)).map_err(io_err)?;
+            }
+        }
+
+        fs::rename(path, rotated_path(1)).map_err(io_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path, sync::Arc, time::Duration};
+    use uuid::Uuid;
+
+    use crate::{
+        clock::{ManualClock, SharedClock},
+        types::{SystemBusMessage, HANDSHAKE_STATUS_TOPIC, SETTLEMENT_STATUS_TOPIC},
+    };
+
+    use super::{AuditLog, AuditLogConfig, AuditLogError};
+
+    /// Build a config for a log in a fresh temporary file
+    fn temp_config(max_file_bytes: u64, max_files: usize) -> AuditLogConfig {
+        let path = env::temp_dir().join(format!("audit-log-{}", Uuid::new_v4()));
+        AuditLogConfig {
+            path: path.to_str().unwrap().to_string(),
+            topics: vec![HANDSHAKE_STATUS_TOPIC.to_string()],
+            max_file_bytes,
+            max_files,
+        }
+    }
+
+    /// A message to record
+    fn handshake_message() -> SystemBusMessage {
+        SystemBusMessage::HandshakeCompleted {
+            local_order_id: Uuid::new_v4(),
+            peer_order_id: Uuid::new_v4(),
+        }
+    }
+
+    /// A clock for the tests
+    fn clock() -> SharedClock {
+        Arc::new(ManualClock::new(Duration::from_secs(1_000)))
+    }
+
+    /// Tests that a reopened log verifies its records and continues their sequence
+    #[test]
+    fn test_reopen_continues_chain() {
+        let config = temp_config(u64::MAX, 1);
+        let log = AuditLog::open(config.clone(), clock()).unwrap();
+        log.append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+        log.append(SETTLEMENT_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+
+        let reopened = AuditLog::open(config.clone(), clock()).unwrap();
+        let event = reopened
+            .append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+        assert_eq!(event.sequence, 2);
+
+        let handshake_events = reopened.recent_events(Some(HANDSHAKE_STATUS_TOPIC), 10);
+        assert_eq!(handshake_events.len(), 2);
+        assert_eq!(handshake_events[0].sequence, 2);
+
+        // The records appended after reopening chain from those before
+        AuditLog::open(config.clone(), clock()).unwrap();
+        fs::remove_file(config.path).unwrap();
+    }
+
+    /// Tests that an altered record fails verification when the log is opened
+    #[test]
+    fn test_tampered_record() {
+        let config = temp_config(u64::MAX, 1);
+        let log = AuditLog::open(config.clone(), clock()).unwrap();
+        log.append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+        log.append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+
+        let contents = fs::read_to_string(&config.path).unwrap();
+        let tampered = contents.replacen(HANDSHAKE_STATUS_TOPIC, SETTLEMENT_STATUS_TOPIC, 1);
+        fs::write(&config.path, tampered).unwrap();
+
+        let res = AuditLog::open(config.clone(), clock());
+        assert!(matches!(res, Err(AuditLogError::ChecksumMismatch(0))));
+        fs::remove_file(config.path).unwrap();
+    }
+
+    /// Tests that a torn final record is discarded
+    #[test]
+    fn test_torn_record_discarded() {
+        let config = temp_config(u64::MAX, 1);
+        let log = AuditLog::open(config.clone(), clock()).unwrap();
+        log.append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+
+        let mut contents = fs::read_to_string(&config.path).unwrap();
+        contents.push_str("{\"event\":");
+        fs::write(&config.path, contents).unwrap();
+
+        let reopened = AuditLog::open(config.clone(), clock()).unwrap();
+        let event = reopened
+            .append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+            .unwrap();
+        assert_eq!(event.sequence, 1);
+
+        // The records appended after reopening chain from those before
+        AuditLog::open(config.clone(), clock()).unwrap();
+        fs::remove_file(config.path).unwrap();
+    }
+
+    /// Tests that the active file is rotated once it reaches its size limit, and that
+    /// only the configured number of files are kept
+    #[test]
+    fn test_rotation() {
+        let config = temp_config(1 /* max_file_bytes */, 3 /* max_files */);
+        let log = AuditLog::open(config.clone(), clock()).unwrap();
+        for _ in 0..4 {
+            log.append(HANDSHAKE_STATUS_TOPIC.to_string(), handshake_message())
+                .unwrap();
+        }
+
+        // Each file holds a single record, the oldest record was dropped
+        let rotated1 = format!("{}.1", config.path);
+        let rotated2 = format!("{}.2", config.path);
+        assert!(!Path::new(&format!("{}.3", config.path)).exists());
+        assert!(fs::read_to_string(&rotated2)
+            .unwrap()
+            .contains("\"sequence\":1"));
+
+        // A rotated file verifies on its own
+        let rotated_config = AuditLogConfig {
+            path: rotated1.clone(),
+            ..config.clone()
+        };
+        AuditLog::open(rotated_config, clock()).unwrap();
+
+        for path in [config.path, rotated1, rotated2] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
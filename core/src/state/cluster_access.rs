@@ -0,0 +1,69 @@
+//! Defines the operator's policy on which counterparty clusters the local node
+//! handshakes with
+//!
+//! The policy is seeded from the relayer config and may be replaced at runtime
+//! through the admin API
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::gossip::types::ClusterId;
+
+/// An allowlist and denylist of counterparty clusters
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ClusterAccessPolicy {
+    /// If set, the only clusters the local node handshakes with
+    pub allowlist: Option<HashSet<ClusterId>>,
+    /// Clusters the local node never handshakes with, takes precedence over
+    /// the allowlist
+    pub denylist: HashSet<ClusterId>,
+}
+
+impl ClusterAccessPolicy {
+    /// Construct a policy from the given lists
+    pub fn new(allowlist: Option<Vec<ClusterId>>, denylist: Vec<ClusterId>) -> Self {
+        Self {
+            allowlist: allowlist.map(|clusters| clusters.into_iter().collect()),
+            denylist: denylist.into_iter().collect(),
+        }
+    }
+
+    /// Whether the local node may handshake with peers in the given cluster
+    pub fn is_permitted(&self, cluster_id: &ClusterId) -> bool {
+        if self.denylist.contains(cluster_id) {
+            return false;
+        }
+
+        self.allowlist
+            .as_ref()
+            .map_or(true, |allowlist| allowlist.contains(cluster_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::gossip::types::ClusterId;
+
+    use super::ClusterAccessPolicy;
+
+    /// Tests that the denylist takes precedence over the allowlist
+    #[test]
+    fn test_denylist_precedence() {
+        let cluster1 = ClusterId::from_str("cluster1").unwrap();
+        let cluster2 = ClusterId::from_str("cluster2").unwrap();
+        let cluster3 = ClusterId::from_str("cluster3").unwrap();
+
+        let open_policy = ClusterAccessPolicy::default();
+        assert!(open_policy.is_permitted(&cluster1));
+
+        let policy = ClusterAccessPolicy::new(
+            Some(vec![cluster1.clone(), cluster2.clone()]),
+            vec![cluster2.clone()],
+        );
+        assert!(policy.is_permitted(&cluster1));
+        assert!(!policy.is_permitted(&cluster2));
+        assert!(!policy.is_permitted(&cluster3));
+    }
+}
@@ -0,0 +1,58 @@
+//! Sweeps the order book for orders whose time in force has lapsed
+//!
+//! The handshake scheduler never schedules an expired order, but an expired order stays
+//! in the `Verified` state until it is swept; the sweeper transitions it to `Cancelled` so
+//! that it leaves the verified set and its cancellation is published on the system bus
+
+use std::time::Duration;
+use tracing::log;
+
+use crate::clock::SharedClock;
+
+use super::{OrderIdentifier, RelayerState};
+
+/// The interval between sweeps of the order book, in milliseconds
+const EXPIRY_SWEEP_INTERVAL_MS: u64 = 5_000;
+
+/// Periodically cancels the orders in the book whose time in force has lapsed
+#[derive(Clone, Debug)]
+pub struct OrderExpirySweeper {
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+    /// The clock that expiries are measured against and sweeps are scheduled by
+    clock: SharedClock,
+}
+
+impl OrderExpirySweeper {
+    /// Constructor
+    pub fn new(global_state: RelayerState, clock: SharedClock) -> Self {
+        Self {
+            global_state,
+            clock,
+        }
+    }
+
+    /// Sweep the order book at each interval
+    pub async fn run(self) {
+        loop {
+            self.clock
+                .sleep(Duration::from_millis(EXPIRY_SWEEP_INTERVAL_MS))
+                .await;
+
+            let expired_orders = self.sweep().await;
+            if !expired_orders.is_empty() {
+                log::info!("cancelled {} expired orders", expired_orders.len());
+            }
+        }
+    }
+
+    /// Cancel the orders whose time in force has lapsed, returning their IDs
+    pub async fn sweep(&self) -> Vec<OrderIdentifier> {
+        let now = self.clock.unix_secs();
+        self.global_state
+            .write_order_book()
+            .await
+            .cancel_expired_orders(now)
+            .await
+    }
+}
@@ -0,0 +1,364 @@
+//! Exports dumps of the network order book for offline analytics
+//!
+//! A dump is a gzip-compressed JSON-lines file: the first line is an `ExportHeader` recording
+//! the schema version the dump was written with, and each following line is an `ExportedOrder`.
+//! Only order metadata is exported; witnesses and proofs never leave the relayer, nor does
+//! whether an order is managed by the local cluster, as this would deanonymize the local
+//! cluster's users.
+//!
+//! Dumps are backward compatible: fields are only ever added to the schema, always with a
+//! default, and the schema version is bumped when they are. A reader at a given version can
+//! therefore read any dump written at that version or earlier.
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::log;
+
+use crate::{clock::SharedClock, gossip::types::ClusterId};
+
+use super::{NetworkOrder, OrderIdentifier, RelayerState};
+
+/// The current version of the dump schema
+pub const ORDER_BOOK_EXPORT_SCHEMA_VERSION: u32 = 1;
+/// The prefix of the name of each dump
+const EXPORT_FILE_PREFIX: &str = "order-book";
+/// The extension of each dump
+const EXPORT_FILE_EXTENSION: &str = "jsonl.gz";
+
+/// An error exporting or reading a dump of the order book
+#[derive(Clone, Debug)]
+pub enum OrderBookExportError {
+    /// Serializing or compressing the dump failed
+    Serialize(String),
+    /// Writing the dump to the export directory failed
+    Write(String),
+    /// Uploading the dump to the object store failed
+    Upload(String),
+    /// Decompressing or parsing a dump failed
+    Parse(String),
+    /// The dump was written with a newer schema than this reader supports
+    UnsupportedVersion(u32),
+}
+
+impl Error for OrderBookExportError {}
+impl Display for OrderBookExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The first line of a dump
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportHeader {
+    /// The version of the schema the dump was written with
+    pub schema_version: u32,
+    /// The time the dump was taken, in milliseconds since the unix epoch
+    pub exported_at: u128,
+    /// The number of orders in the dump
+    pub num_orders: usize,
+}
+
+/// The metadata of a single order in a dump
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedOrder {
+    /// The identifier of the order
+    pub id: OrderIdentifier,
+    /// The cluster known to manage the order
+    pub cluster: ClusterId,
+    /// The state of the order via the local peer, without the state's details
+    pub state: String,
+    /// Whether the local peer has verified a proof of `VALID COMMITMENTS` for the order
+    pub verified: bool,
+}
+
+impl From<&NetworkOrder> for ExportedOrder {
+    fn from(order: &NetworkOrder) -> Self {
+        Self {
+            id: order.id,
+            cluster: order.cluster.clone(),
+            state: order.state.to_string(),
+            verified: order.valid_commit_proof.is_some(),
+        }
+    }
+}
+
+/// A dump of the order book, as read back by `read_dump`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderBookDump {
+    /// The header of the dump
+    pub header: ExportHeader,
+    /// The orders in the dump
+    pub orders: Vec<ExportedOrder>,
+}
+
+/// Where dumps are written to
+#[derive(Clone, Debug)]
+pub enum ExportDestination {
+    /// A local directory, dumps are written as files within it
+    Directory(PathBuf),
+    /// An object store endpoint, each dump is `PUT` to the endpoint under its file name
+    ObjectStore(Url),
+}
+
+impl Display for ExportDestination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportDestination::Directory(dir) => write!(f, "{}", dir.display()),
+            ExportDestination::ObjectStore(endpoint) => write!(f, "{}", endpoint),
+        }
+    }
+}
+
+/// A summary of a completed export
+#[derive(Clone, Debug)]
+pub struct ExportSummary {
+    /// The location the dump was written to
+    pub location: String,
+    /// The header written to the dump
+    pub header: ExportHeader,
+}
+
+/// Exports dumps of the order book, both on a schedule and on demand
+#[derive(Clone, Debug)]
+pub struct OrderBookExporter {
+    /// Where dumps are written to
+    destination: ExportDestination,
+    /// The interval between scheduled exports, scheduled exports are disabled if `None`
+    interval: Option<Duration>,
+    /// A copy of the relayer-global state
+    global_state: RelayerState,
+    /// The clock that dumps are timestamped and scheduled by
+    clock: SharedClock,
+}
+
+impl OrderBookExporter {
+    /// Constructor
+    pub fn new(
+        destination: ExportDestination,
+        interval: Option<Duration>,
+        global_state: RelayerState,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            destination,
+            interval,
+            global_state,
+            clock,
+        }
+    }
+
+    /// Export the order book at each interval, returns immediately if scheduled exports
+    /// are disabled
+    pub async fn run_scheduled(self) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        loop {
+            self.clock.sleep(interval).await;
+            match self.export().await {
+                Ok(summary) => log::info!(
+                    "exported {} orders to {}",
+                    summary.header.num_orders,
+                    summary.location
+                ),
+                Err(err) => log::error!("error exporting order book: {}", err),
+            }
+        }
+    }
+
+    /// Take a dump of the order book and write it to the destination
+    pub async fn export(&self) -> Result<ExportSummary, OrderBookExportError> {
+        let orders = {
+            let locked_order_book = self.global_state.read_order_book().await;
+            locked_order_book.get_order_book_snapshot().await
+        }; // locked_order_book released
+
+        let mut exported_orders = orders.values().map(ExportedOrder::from).collect::<Vec<_>>();
+        exported_orders.sort_by_key(|order| order.id);
+
+        let exported_at = self.clock.unix_time().as_millis();
+        let header = ExportHeader {
+            schema_version: ORDER_BOOK_EXPORT_SCHEMA_VERSION,
+            exported_at,
+            num_orders: exported_orders.len(),
+        };
+        let dump = write_dump(&header, &exported_orders)?;
+
+        let file_name = format!(
+            "{}-v{}-{}.{}",
+            EXPORT_FILE_PREFIX,
+            ORDER_BOOK_EXPORT_SCHEMA_VERSION,
+            exported_at,
+            EXPORT_FILE_EXTENSION
+        );
+        let location = match &self.destination {
+            ExportDestination::Directory(dir) => Self::write_file(dir, &file_name, dump)?,
+            ExportDestination::ObjectStore(endpoint) => {
+                Self::upload(endpoint, &file_name, dump).await?
+            }
+        };
+
+        Ok(ExportSummary { location, header })
+    }
+
+    /// Write a dump into the export directory, returning the path it was written to
+    fn write_file(
+        dir: &Path,
+        file_name: &str,
+        dump: Vec<u8>,
+    ) -> Result<String, OrderBookExportError> {
+        fs::create_dir_all(dir).map_err(|err| OrderBookExportError::Write(err.to_string()))?;
+
+        // Write to a temporary file first so that readers never see a partial dump
+        let path = dir.join(file_name);
+        let tmp_path = dir.join(format!("{}.tmp", file_name));
+        fs::write(&tmp_path, dump)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|err| OrderBookExportError::Write(err.to_string()))?;
+
+        Ok(path.display().to_string())
+    }
+
+    /// Upload a dump to the object store, returning the URL it was uploaded to
+    async fn upload(
+        endpoint: &Url,
+        file_name: &str,
+        dump: Vec<u8>,
+    ) -> Result<String, OrderBookExportError> {
+        let url = endpoint
+            .join(file_name)
+            .map_err(|err| OrderBookExportError::Upload(err.to_string()))?;
+        reqwest::Client::new()
+            .put(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/gzip")
+            .body(dump)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|err| OrderBookExportError::Upload(err.to_string()))?;
+
+        Ok(url.to_string())
+    }
+}
+
+/// Serialize and compress a dump
+fn write_dump(
+    header: &ExportHeader,
+    orders: &[ExportedOrder],
+) -> Result<Vec<u8>, OrderBookExportError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut write_line = |line: Result<Vec<u8>, serde_json::Error>| {
+        let line = line.map_err(|err| OrderBookExportError::Serialize(err.to_string()))?;
+        encoder
+            .write_all(&line)
+            .and_then(|_| encoder.write_all(b"\n"))
+            .map_err(|err| OrderBookExportError::Serialize(err.to_string()))
+    };
+
+    write_line(serde_json::to_vec(header))?;
+    for order in orders.iter() {
+        write_line(serde_json::to_vec(order))?;
+    }
+
+    encoder
+        .finish()
+        .map_err(|err| OrderBookExportError::Serialize(err.to_string()))
+}
+
+/// Decompress and parse a dump written at the current schema version or earlier
+pub fn read_dump(dump: &[u8]) -> Result<OrderBookDump, OrderBookExportError> {
+    let mut lines = BufReader::new(GzDecoder::new(dump)).lines();
+    let mut next_line = || -> Option<Result<String, OrderBookExportError>> {
+        lines
+            .next()
+            .map(|line| line.map_err(|err| OrderBookExportError::Parse(err.to_string())))
+    };
+
+    let header_line =
+        next_line().ok_or_else(|| OrderBookExportError::Parse("dump is empty".to_string()))??;
+    let header: ExportHeader = serde_json::from_str(&header_line)
+        .map_err(|err| OrderBookExportError::Parse(err.to_string()))?;
+    if header.schema_version > ORDER_BOOK_EXPORT_SCHEMA_VERSION {
+        return Err(OrderBookExportError::UnsupportedVersion(
+            header.schema_version,
+        ));
+    }
+
+    let mut orders = Vec::with_capacity(header.num_orders);
+    while let Some(line) = next_line() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+
+        orders.push(
+            serde_json::from_str(&line)
+                .map_err(|err| OrderBookExportError::Parse(err.to_string()))?,
+        );
+    }
+
+    Ok(OrderBookDump { header, orders })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    use crate::gossip::types::ClusterId;
+
+    use super::{
+        read_dump, write_dump, ExportHeader, ExportedOrder, OrderBookExportError,
+        ORDER_BOOK_EXPORT_SCHEMA_VERSION,
+    };
+
+    /// Tests that a dump reads back as it was written
+    #[test]
+    fn test_dump_roundtrip() {
+        let orders = vec![ExportedOrder {
+            id: Uuid::new_v4(),
+            cluster: ClusterId::from_str("cluster").unwrap(),
+            state: "Verified".to_string(),
+            verified: true,
+        }];
+        let header = ExportHeader {
+            schema_version: ORDER_BOOK_EXPORT_SCHEMA_VERSION,
+            exported_at: 1_000,
+            num_orders: orders.len(),
+        };
+
+        let dump = read_dump(&write_dump(&header, &orders).unwrap()).unwrap();
+        assert_eq!(dump.header, header);
+        assert_eq!(dump.orders, orders);
+    }
+
+    /// Tests that a dump written with a newer schema is rejected
+    #[test]
+    fn test_dump_newer_version() {
+        let header = ExportHeader {
+            schema_version: ORDER_BOOK_EXPORT_SCHEMA_VERSION + 1,
+            exported_at: 1_000,
+            num_orders: 0,
+        };
+
+        let res = read_dump(&write_dump(&header, &[]).unwrap());
+        assert!(matches!(
+            res,
+            Err(OrderBookExportError::UnsupportedVersion(version))
+                if version == ORDER_BOOK_EXPORT_SCHEMA_VERSION + 1
+        ));
+    }
+}
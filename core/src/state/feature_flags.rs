@@ -0,0 +1,171 @@
+//! Defines the feature flags that gate risky or partially rolled out behavior
+//!
+//! Each flag is declared below with its default; the defaults may be overridden in the
+//! relayer config and flags may be toggled at runtime through the admin API. Flags are
+//! held in atomics so that they may be checked on hot paths without taking a lock
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// A feature that may be switched on or off at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureFlag {
+    /// Whether the relayer quotes and fills requests-for-quote
+    RfqMode,
+    /// Whether orders managed by the local cluster may be crossed against one another
+    /// without a handshake
+    InternalCrossing,
+    /// Whether match MPCs and gossip are dialed over QUIC
+    QuicTransport,
+    /// Whether indications of interest in local orders are published alongside their
+    /// validity proofs
+    PublishIois,
+    /// Whether the handshake manager only responds to peers' match proposals, without
+    /// scheduling handshakes of its own
+    HandshakeReceiveOnly,
+    /// Whether the gossip server only responds to peers outside the local cluster, without
+    /// heartbeating them
+    GossipListenOnly,
+}
+
+/// Every feature flag, in declaration order
+pub static ALL_FEATURE_FLAGS: &[FeatureFlag] = &[
+    FeatureFlag::RfqMode,
+    FeatureFlag::InternalCrossing,
+    FeatureFlag::QuicTransport,
+    FeatureFlag::PublishIois,
+    FeatureFlag::HandshakeReceiveOnly,
+    FeatureFlag::GossipListenOnly,
+];
+
+impl FeatureFlag {
+    /// The name of the flag, as used in the config and the API
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureFlag::RfqMode => "rfq_mode",
+            FeatureFlag::InternalCrossing => "internal_crossing",
+            FeatureFlag::QuicTransport => "quic_transport",
+            FeatureFlag::PublishIois => "publish_iois",
+            FeatureFlag::HandshakeReceiveOnly => "handshake_receive_only",
+            FeatureFlag::GossipListenOnly => "gossip_listen_only",
+        }
+    }
+
+    /// Whether the flag is enabled when the config does not set it
+    pub fn default_enabled(&self) -> bool {
+        match self {
+            FeatureFlag::RfqMode => false,
+            FeatureFlag::InternalCrossing => false,
+            FeatureFlag::QuicTransport => false,
+            FeatureFlag::PublishIois => false,
+            FeatureFlag::HandshakeReceiveOnly => false,
+            FeatureFlag::GossipListenOnly => false,
+        }
+    }
+}
+
+impl Display for FeatureFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for FeatureFlag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_FEATURE_FLAGS
+            .iter()
+            .find(|flag| flag.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown feature flag: {}", s))
+    }
+}
+
+/// The current state of every feature flag, shared between workers
+#[derive(Clone, Debug)]
+pub struct FeatureFlags {
+    /// Whether each flag is enabled, indexed by the flag's discriminant
+    enabled: Arc<Vec<AtomicBool>>,
+}
+
+impl FeatureFlags {
+    /// Construct the flags from their defaults, with the given overrides applied
+    pub fn new(overrides: &HashMap<FeatureFlag, bool>) -> Self {
+        let enabled = ALL_FEATURE_FLAGS
+            .iter()
+            .map(|flag| {
+                let enabled = overrides
+                    .get(flag)
+                    .copied()
+                    .unwrap_or_else(|| flag.default_enabled());
+                AtomicBool::new(enabled)
+            })
+            .collect();
+
+        Self {
+            enabled: Arc::new(enabled),
+        }
+    }
+
+    /// Whether the given feature is enabled
+    #[inline]
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.enabled[flag as usize].load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable a feature, returning whether it was previously enabled
+    pub fn set(&self, flag: FeatureFlag, enabled: bool) -> bool {
+        self.enabled[flag as usize].swap(enabled, Ordering::Relaxed)
+    }
+
+    /// A snapshot of the state of every flag
+    pub fn snapshot(&self) -> BTreeMap<FeatureFlag, bool> {
+        ALL_FEATURE_FLAGS
+            .iter()
+            .map(|flag| (*flag, self.is_enabled(*flag)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use super::{FeatureFlag, FeatureFlags, ALL_FEATURE_FLAGS};
+
+    /// Tests that overrides are applied over the defaults and that flags toggle
+    #[test]
+    fn test_overrides_and_toggle() {
+        let overrides = HashMap::from([(FeatureFlag::InternalCrossing, true)]);
+        let flags = FeatureFlags::new(&overrides);
+        assert!(flags.is_enabled(FeatureFlag::InternalCrossing));
+        assert_eq!(
+            flags.is_enabled(FeatureFlag::RfqMode),
+            FeatureFlag::RfqMode.default_enabled()
+        );
+
+        let previous = flags.set(FeatureFlag::InternalCrossing, false);
+        assert!(previous);
+        assert!(!flags.clone().is_enabled(FeatureFlag::InternalCrossing));
+    }
+
+    /// Tests that every flag's name parses back to the flag, and that the declaration order
+    /// matches the discriminants the flags are indexed by
+    #[test]
+    fn test_flag_names() {
+        for (index, flag) in ALL_FEATURE_FLAGS.iter().enumerate() {
+            assert_eq!(FeatureFlag::from_str(flag.name()).unwrap(), *flag);
+            assert_eq!(*flag as usize, index);
+        }
+    }
+}
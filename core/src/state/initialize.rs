@@ -1,45 +1,57 @@
 //! Handles state sync and startup when the node first comes online
 
 use circuits::{
-    native_helpers::compute_poseidon_hash,
-    zk_circuits::valid_commitments::{ValidCommitmentsStatement, ValidCommitmentsWitness},
+    native_helpers::{compute_poseidon_hash, compute_wallet_commitment},
+    types::{balance::Balance, fee::Fee, order::Order, wallet::Nullifier},
+    zk_circuits::valid_commitments::{
+        ValidCommitments, ValidCommitmentsStatement, ValidCommitmentsWitness,
+    },
     zk_gadgets::merkle::MerkleOpening,
     LinkableCommitment,
 };
-use crossbeam::channel::Sender as CrossbeamSender;
 use crypto::fields::{
-    biguint_to_scalar, biguint_to_starknet_felt, scalar_to_biguint, starknet_felt_to_biguint,
-    starknet_felt_to_scalar, starknet_felt_to_u64,
+    biguint_to_scalar, biguint_to_starknet_felt, prime_field_to_scalar, scalar_to_biguint,
+    starknet_felt_to_biguint, starknet_felt_to_scalar, starknet_felt_to_u64,
 };
 use curve25519_dalek::scalar::Scalar;
+use futures::future::join_all;
 use num_bigint::BigUint;
+use rand::{thread_rng, Rng};
 use reqwest::Url;
 use starknet::core::{types::FieldElement as StarknetFieldElement, utils::get_selector_from_name};
 use starknet_providers::jsonrpc::{models::EventFilter, HttpTransport, JsonRpcClient};
 use std::{
     collections::{HashMap, HashSet},
     convert::TryInto,
+    future::Future,
     str::FromStr,
     thread::Builder as ThreadBuilder,
+    time::{Duration, Instant},
 };
-use tokio::{
-    runtime::Builder as RuntimeBuilder,
-    sync::{mpsc::UnboundedSender, oneshot},
-};
+use tokio::{runtime::Builder as RuntimeBuilder, sync::oneshot};
 use tracing::log;
 
 use crate::{
     error::CoordinatorError,
     gossip_api::{
+        cluster_management::{ClusterManagementMessage, ValidityProofRequest},
         gossip::{GossipOutbound, PubsubMessage},
         orderbook_management::{OrderBookManagementMessage, ORDER_BOOK_TOPIC},
     },
-    proof_generation::jobs::{ProofJob, ProofManagerJob, ValidCommitmentsBundle},
+    job_queue::JobQueueSender,
+    keychain,
+    proof_generation::jobs::{
+        ProofBundle, ProofJob, ProofJobPriority, ProofManagerJob, ValidCommitmentsBundle,
+    },
+    types::SizedValidCommitmentsWitness,
     MERKLE_HEIGHT,
 };
 
 use super::{
-    wallet::{MerkleAuthenticationPath, Wallet},
+    merkle::{reduce_to_starknet_field, EMPTY_LEAF_VALUE},
+    orderbook::OrderIdentifier,
+    wallet::{MerkleAuthenticationPath, Wallet, WalletIdentifier, WalletIndex},
+    wallet_manager::WalletTaskError,
     MerkleTreeCoords, NetworkOrder, RelayerState,
 };
 
@@ -47,6 +59,26 @@ use super::{
 const ERR_STATE_INIT_FAILED: &str = "state initialization thread panic";
 /// The name of the thread initialized to generate proofs of `VALID COMMITMENTS` at startup
 const STATE_INIT_THREAD: &str = "state-init";
+/// The maximum amount of time to wait for a cluster leader to be elected before proving,
+/// after which the local peer proves its wallets' orders itself
+const LEADER_ELECTION_TIMEOUT_MS: u64 = 10_000; // 10 seconds
+/// The interval at which to poll for an elected cluster leader
+const LEADER_POLL_INTERVAL_MS: u64 = 500;
+
+/// Integrity check failure: the witness wallet does not commit to the stored wallet
+const ERR_WALLET_MISMATCH: &str = "witness wallet does not match stored wallet";
+/// Integrity check failure: the wallet can no longer cover the order
+const ERR_NO_BALANCE_OR_FEE: &str = "no balance and fee found for order";
+/// Integrity check failure: the witness order, balance, or fee differ from the wallet's
+const ERR_ORDER_BALANCE_FEE_MISMATCH: &str = "witness order, balance, or fee does not match wallet";
+/// Integrity check failure: the witness match key differs from the wallet's
+const ERR_KEY_MISMATCH: &str = "witness sk_match does not match wallet";
+/// Integrity check failure: the witness randomness hash differs from the wallet's
+const ERR_RANDOMNESS_MISMATCH: &str = "witness randomness hash does not match wallet";
+/// Integrity check failure: the witness Merkle opening differs from the wallet's
+const ERR_OPENING_MISMATCH: &str = "witness Merkle opening does not match wallet";
+/// Integrity check failure: the witness does not satisfy the circuit constraints
+const ERR_CONSTRAINTS_UNSATISFIED: &str = "witness does not satisfy VALID COMMITMENTS";
 
 lazy_static! {
     /// The event selector for internal node changes
@@ -55,13 +87,6 @@ lazy_static! {
     /// The event selector for Merkle value insertion
     static ref VALUE_INSERTED_EVENT_SELECTOR: StarknetFieldElement =
         get_selector_from_name("Merkle_value_inserted").unwrap();
-    /// The value of an empty leaf in the Merkle tree
-    static ref EMPTY_LEAF_VALUE: Scalar = {
-        let val_bigint = BigUint::from_str(
-            "306932273398430716639340090025251549301604242969558673011416862133942957551"
-        ).unwrap();
-        biguint_to_scalar(&val_bigint)
-    };
     /// The default values of an authentication path; i.e. the values in the path before any
     /// path elements are changed by insertions
     ///
@@ -91,8 +116,9 @@ impl RelayerState {
         &self,
         contract_address: String,
         starknet_api_gateway: String,
-        proof_manager_queue: CrossbeamSender<ProofManagerJob>,
-        network_sender: UnboundedSender<GossipOutbound>,
+        proof_manager_queue: JobQueueSender<ProofManagerJob>,
+        network_sender: JobQueueSender<GossipOutbound>,
+        witness_check_sample_rate: f64,
     ) {
         // Spawn the helpers in a thread
         let self_clone = self.clone();
@@ -108,6 +134,7 @@ impl RelayerState {
                     starknet_api_gateway,
                     proof_manager_queue,
                     network_sender,
+                    witness_check_sample_rate,
                 ))
             })
             .expect(ERR_STATE_INIT_FAILED);
@@ -118,115 +145,517 @@ impl RelayerState {
         &self,
         contract_address: String,
         starknet_api_gateway: String,
-        proof_manager_queue: CrossbeamSender<ProofManagerJob>,
-        network_sender: UnboundedSender<GossipOutbound>,
+        proof_manager_queue: JobQueueSender<ProofManagerJob>,
+        network_sender: JobQueueSender<GossipOutbound>,
+        witness_check_sample_rate: f64,
     ) -> Result<(), CoordinatorError> {
         // Build a starknet RPC client
         let starknet_client = JsonRpcClient::new(HttpTransport::new(
             Url::parse(&starknet_api_gateway).unwrap(),
         ));
 
+        // Only the cluster leader proves `VALID COMMITMENTS`, followers receive the proofs
+        // when the leader gossips them
+        let is_leader = self.await_cluster_leader().await;
+
+        // Warm up each wallet on its own task queue, concurrently; a wallet that fails to
+        // warm up is frozen without holding up the others
+        let wallet_ids = self.read_wallet_index().await.get_all_wallet_ids();
+        let warm_up_results = join_all(wallet_ids.iter().map(|wallet_id| {
+            self.wallet_manager().run_task(
+                wallet_id,
+                self.warm_up_wallet(
+                    wallet_id,
+                    contract_address.clone(),
+                    &starknet_client,
+                    is_leader,
+                    &proof_manager_queue,
+                    witness_check_sample_rate,
+                ),
+            )
+        }))
+        .await;
+
         // Store a handle to the response channels for each proof; await them one by one
         let mut proof_response_channels = Vec::new();
+        let mut orders_needing_proofs = Vec::new();
+        for (wallet_id, res) in wallet_ids.iter().zip(warm_up_results.into_iter()) {
+            match res {
+                Ok((response_channels, unproven_orders)) => {
+                    self.wallet_manager().mark_ready(wallet_id);
+                    proof_response_channels.extend(response_channels);
+                    orders_needing_proofs.extend(unproven_orders);
+                }
+                Err(WalletTaskError::Task(e)) => {
+                    log::error!("error warming up wallet {wallet_id}, freezing it: {e}");
+                    self.wallet_manager().freeze(wallet_id, e.to_string());
+                }
+                Err(e) => log::warn!("skipping warm-up of wallet {wallet_id}: {e}"),
+            }
+        }
 
-        {
-            // Iterate over all orders in all managed wallets and generate proofs
-            let locked_wallet_index = self.read_wallet_index().await;
-            for wallet in locked_wallet_index.get_all_wallets().await.into_iter() {
-                // Build a Merkle authentication path for the wallet and attach it to the state
-                let merkle_path = self
-                    .build_merkle_authentication_path(
-                        &wallet,
-                        contract_address.clone(),
-                        &starknet_client,
-                    )
-                    .await?;
+        // Followers request any proofs the leader has already generated; proofs generated
+        // later are gossiped by the leader as they complete
+        if !orders_needing_proofs.is_empty() {
+            self.request_cluster_proofs(orders_needing_proofs, &network_sender);
+        }
+
+        self.attach_and_gossip_proofs(proof_response_channels, &network_sender)
+            .await;
+
+        Ok(())
+    }
+
+    /// Recover a wallet's authentication path and index its orders in the order book,
+    /// enqueueing proofs of `VALID COMMITMENTS` for them if the local peer is the cluster
+    /// leader
+    ///
+    /// Returns the response channels of the enqueued proofs, and the orders left for the
+    /// cluster leader to prove
+    #[allow(clippy::too_many_arguments)]
+    async fn warm_up_wallet(
+        &self,
+        wallet_id: &WalletIdentifier,
+        contract_address: String,
+        starknet_client: &JsonRpcClient<HttpTransport>,
+        is_leader: bool,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+        witness_check_sample_rate: f64,
+    ) -> Result<
+        (
+            Vec<(OrderIdentifier, oneshot::Receiver<ProofBundle>)>,
+            Vec<OrderIdentifier>,
+        ),
+        CoordinatorError,
+    > {
+        let mut proof_response_channels = Vec::new();
+        let mut orders_needing_proofs = Vec::new();
+
+        let locked_wallet_index = self.read_wallet_index().await;
+        let wallet = locked_wallet_index
+            .get_wallet(wallet_id)
+            .await
+            .ok_or_else(|| CoordinatorError::StateInit(format!("wallet {wallet_id} not found")))?;
+
+        // Build a Merkle authentication path for the wallet and attach it to the state
+        let merkle_path = self
+            .build_merkle_authentication_path(&wallet, contract_address, starknet_client)
+            .await?;
+
+        locked_wallet_index
+            .add_wallet_merkle_proof(&wallet.wallet_id, merkle_path.clone())
+            .await;
 
-                locked_wallet_index
-                    .add_wallet_merkle_proof(&wallet.wallet_id, merkle_path.clone())
+        log::info!(
+            "successfully recovered wallet {} authentication path from Starknet",
+            wallet.wallet_id
+        );
+
+        let match_nullifier = wallet.get_match_nullifier();
+        for (order_id, order) in wallet.orders.iter() {
+            // Add the order to the book
+            {
+                self.write_order_book()
+                    .await
+                    .add_order(
+                        NetworkOrder::new(
+                            *order_id,
+                            match_nullifier,
+                            self.local_cluster_id.clone(),
+                            true, /* local */
+                        )
+                        .with_time_in_force(order.time_in_force),
+                    )
                     .await;
+            } // order_book lock released
+
+            if !is_leader {
+                orders_needing_proofs.push(*order_id);
+                continue;
+            }
+
+            if let Some(response_receiver) = self
+                .prove_order_at_startup(
+                    &locked_wallet_index,
+                    &wallet,
+                    order_id,
+                    &merkle_path,
+                    witness_check_sample_rate,
+                    proof_manager_queue,
+                )
+                .await
+            {
+                // Store a handle to the response channel
+                proof_response_channels.push((*order_id, response_receiver));
+            } else {
+                println!("Skipping wallet validity proof; no balance and fee found");
+                continue;
+            }
+        }
+
+        Ok((proof_response_channels, orders_needing_proofs))
+    }
+
+    /// Re-prove `VALID COMMITMENTS` for a wallet's orders after a version of the wallet
+    /// is nullified on-chain
+    ///
+    /// The orders are re-indexed under the wallet's current match nullifier and proven
+    /// against a freshly recovered authentication path. If the local replica still holds
+    /// the spent version, it has not yet received the update and there is nothing to prove
+    pub async fn reprove_wallet_orders(
+        &self,
+        wallet_id: &WalletIdentifier,
+        spent_nullifier: Nullifier,
+        contract_address: String,
+        starknet_client: &JsonRpcClient<HttpTransport>,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+        network_sender: &JobQueueSender<GossipOutbound>,
+    ) -> Result<(), CoordinatorError> {
+        let task = async {
+            let wallet = match self.read_wallet_index().await.get_wallet(wallet_id).await {
+                Some(wallet) => wallet,
+                None => return Ok(()),
+            };
+
+            let match_nullifier = wallet.get_match_nullifier();
+            if match_nullifier == spent_nullifier {
+                log::warn!("local replica of wallet {wallet_id} is stale, deferring re-prove");
+                return Ok(());
+            }
 
-                log::info!(
-                    "successfully recovered wallet {} authentication path from Starknet",
-                    wallet.wallet_id
-                );
-
-                // Generate a merkle proof of inclusion for this wallet in the contract state
-                let merkle_root = merkle_path.compute_root();
-                let wallet_opening: MerkleOpening = merkle_path.into();
-
-                let match_nullifier = wallet.get_match_nullifier();
-                for (order_id, order) in wallet.orders.iter() {
-                    // Add the order to the book
-                    {
-                        self.write_order_book()
-                            .await
-                            .add_order(NetworkOrder::new(
+            // Regenerate any opted-in orders that the user's wallet update cancelled, so
+            // that they are proved alongside the wallet's remaining orders
+            let resubmitted_orders = self.resubmit_cancelled_orders(wallet_id).await;
+            let wallet = if resubmitted_orders.is_empty() {
+                wallet
+            } else {
+                log::info!("resubmitting orders {resubmitted_orders:?} in wallet {wallet_id}");
+                match self.read_wallet_index().await.get_wallet(wallet_id).await {
+                    Some(wallet) => wallet,
+                    None => return Ok(()),
+                }
+            };
+            self.reprove_current_wallet_orders(
+                wallet,
+                contract_address,
+                starknet_client,
+                proof_manager_queue,
+                network_sender,
+            )
+            .await
+        };
+
+        self.run_wallet_task(wallet_id, task).await
+    }
+
+    /// Re-prove `VALID COMMITMENTS` for a wallet's orders after the spend of its match
+    /// nullifier is rolled back by a chain re-org
+    ///
+    /// The spend cancelled the orders, and the re-org may have moved the wallet's
+    /// commitment in the tree; the orders are re-indexed under the nullifier and proven
+    /// against a freshly recovered authentication path. If the local replica has moved
+    /// past the version the nullifier belongs to, there is nothing to restore
+    pub async fn restore_wallet_orders(
+        &self,
+        wallet_id: &WalletIdentifier,
+        reorged_nullifier: Nullifier,
+        contract_address: String,
+        starknet_client: &JsonRpcClient<HttpTransport>,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+        network_sender: &JobQueueSender<GossipOutbound>,
+    ) -> Result<(), CoordinatorError> {
+        let task = async {
+            let wallet = match self.read_wallet_index().await.get_wallet(wallet_id).await {
+                Some(wallet) if wallet.get_match_nullifier() == reorged_nullifier => wallet,
+                _ => return Ok(()),
+            };
+
+            self.reprove_current_wallet_orders(
+                wallet,
+                contract_address,
+                starknet_client,
+                proof_manager_queue,
+                network_sender,
+            )
+            .await
+        };
+
+        self.run_wallet_task(wallet_id, task).await
+    }
+
+    /// Run a task on a wallet's task queue, skipping it if the wallet is frozen or no
+    /// longer managed locally
+    async fn run_wallet_task<F>(
+        &self,
+        wallet_id: &WalletIdentifier,
+        task: F,
+    ) -> Result<(), CoordinatorError>
+    where
+        F: Future<Output = Result<(), CoordinatorError>>,
+    {
+        match self.wallet_manager().run_task(wallet_id, task).await {
+            Ok(()) => Ok(()),
+            Err(WalletTaskError::Task(e)) => Err(e),
+            Err(e) => {
+                log::warn!("skipping task on wallet {wallet_id}: {e}");
+                Ok(())
+            }
+        }
+    }
+
+    /// Index a wallet's orders under its current match nullifier and prove them against a
+    /// freshly recovered authentication path
+    async fn reprove_current_wallet_orders(
+        &self,
+        wallet: Wallet,
+        contract_address: String,
+        starknet_client: &JsonRpcClient<HttpTransport>,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+        network_sender: &JobQueueSender<GossipOutbound>,
+    ) -> Result<(), CoordinatorError> {
+        let wallet_id = &wallet.wallet_id;
+        let match_nullifier = wallet.get_match_nullifier();
+
+        let merkle_path = self
+            .build_merkle_authentication_path(&wallet, contract_address, starknet_client)
+            .await?;
+
+        // Only the cluster leader proves, followers receive the proofs over gossip
+        let is_leader = self.is_local_cluster_leader().await;
+        let mut proof_response_channels = Vec::new();
+        {
+            let locked_wallet_index = self.read_wallet_index().await;
+            locked_wallet_index
+                .add_wallet_merkle_proof(wallet_id, merkle_path.clone())
+                .await;
+
+            for (order_id, order) in wallet.orders.iter() {
+                {
+                    self.write_order_book()
+                        .await
+                        .add_order(
+                            NetworkOrder::new(
                                 *order_id,
                                 match_nullifier,
                                 self.local_cluster_id.clone(),
                                 true, /* local */
-                            ))
+                            )
+                            .with_time_in_force(order.time_in_force),
+                        )
+                        .await;
+                } // order_book lock released
+
+                if !is_leader {
+                    continue;
+                }
+
+                match build_commitments_witness(
+                    &locked_wallet_index,
+                    &wallet,
+                    order_id,
+                    &merkle_path,
+                )
+                .await
+                {
+                    Some((witness, statement)) => {
+                        let response_receiver = self
+                            .enqueue_commitments_proof(
+                                order_id,
+                                witness,
+                                statement,
+                                proof_manager_queue,
+                            )
                             .await;
-                    } // order_book lock released
+                        proof_response_channels.push((*order_id, response_receiver));
+                    }
+                    None => {
+                        log::warn!("cannot re-prove order {order_id}; no balance and fee found")
+                    }
+                }
+            }
+        } // locked_wallet_index released
+
+        self.attach_and_gossip_proofs(proof_response_channels, network_sender)
+            .await;
+        Ok(())
+    }
 
-                    if let Some((_, balance, fee, fee_balance)) = locked_wallet_index
-                        .get_order_balance_and_fee(&wallet.wallet_id, order_id)
+    /// Prove `VALID COMMITMENTS` for each order in a locally managed wallet against an
+    /// authentication path the caller already holds, returning the proofs once attached to
+    /// the order book
+    ///
+    /// Unlike the startup flow, nothing is read from chain and no proof is gossiped; the
+    /// caller is responsible for distributing the proofs, e.g. the simulation harness seeds
+    /// them directly into its simulated peers' order books
+    pub(crate) async fn prove_wallet_orders(
+        &self,
+        wallet_id: &WalletIdentifier,
+        merkle_path: MerkleAuthenticationPath,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+    ) -> Vec<(OrderIdentifier, ValidCommitmentsBundle)> {
+        let mut proof_response_channels = Vec::new();
+        {
+            let locked_wallet_index = self.read_wallet_index().await;
+            let wallet = match locked_wallet_index.get_wallet(wallet_id).await {
+                Some(wallet) => wallet,
+                None => return Vec::new(),
+            };
+            locked_wallet_index
+                .add_wallet_merkle_proof(wallet_id, merkle_path.clone())
+                .await;
+
+            let match_nullifier = wallet.get_match_nullifier();
+            for (order_id, order) in wallet.orders.iter() {
+                {
+                    self.write_order_book()
                         .await
-                    {
-                        // Construct the witness and statement to generate a commitments proof from
-                        let randomness_hash =
-                            compute_poseidon_hash(&[biguint_to_scalar(&wallet.randomness)]);
-                        let witness = ValidCommitmentsWitness {
-                            wallet: wallet.clone().into(),
-                            order: order.clone().into(),
-                            balance: balance.clone().into(),
-                            fee: fee.clone().into(),
-                            fee_balance: fee_balance.clone().into(),
-                            wallet_opening: wallet_opening.clone(),
-                            randomness_hash: LinkableCommitment::new(randomness_hash),
-                            sk_match: wallet.secret_keys.sk_match,
-                        };
-
-                        let statement = ValidCommitmentsStatement {
-                            nullifier: match_nullifier,
-                            merkle_root,
-                            pk_settle: wallet.public_keys.pk_settle,
-                        };
-
-                        // Create a job and a response channel to get proofs back on, and forward the job
-                        let (response_sender, response_receiver) = oneshot::channel();
-                        proof_manager_queue
-                            .send(ProofManagerJob {
-                                type_: ProofJob::ValidCommitments {
-                                    witness: witness.clone(),
-                                    statement,
-                                },
-                                response_channel: response_sender,
-                            })
-                            .unwrap();
-
-                        // Store a handle to the response channel
-                        proof_response_channels.push((*order_id, response_receiver));
+                        .add_order(
+                            NetworkOrder::new(
+                                *order_id,
+                                match_nullifier,
+                                self.local_cluster_id.clone(),
+                                true, /* local */
+                            )
+                            .with_time_in_force(order.time_in_force),
+                        )
+                        .await;
+                } // order_book lock released
 
-                        // Attach a copy of the witness to the locally managed state
-                        // This witness is reference by match computations which compute linkable commitments
-                        // to the order and balance; i.e. they commit with the same randomness
-                        {
-                            self.read_order_book()
-                                .await
-                                .attach_validity_proof_witness(order_id, witness.clone())
-                                .await;
-                        } // order_book lock released
-                    } else {
-                        println!("Skipping wallet validity proof; no balance and fee found");
-                        continue;
+                match build_commitments_witness(
+                    &locked_wallet_index,
+                    &wallet,
+                    order_id,
+                    &merkle_path,
+                )
+                .await
+                {
+                    Some((witness, statement)) => {
+                        let response_receiver = self
+                            .enqueue_commitments_proof(
+                                order_id,
+                                witness,
+                                statement,
+                                proof_manager_queue,
+                            )
+                            .await;
+                        proof_response_channels.push((*order_id, response_receiver));
                     }
+                    None => log::warn!("cannot prove order {order_id}; no balance and fee found"),
                 }
             }
         } // locked_wallet_index released
 
-        // Await a proof response for each order then attach it to the order index entry
+        let mut proofs = Vec::with_capacity(proof_response_channels.len());
+        for (order_id, receiver) in proof_response_channels.into_iter() {
+            let proof_bundle: ValidCommitmentsBundle = match receiver.await {
+                Ok(bundle) => bundle.into(),
+                Err(_) => {
+                    log::error!("proof manager dropped the proof of order {order_id}");
+                    continue;
+                }
+            };
+
+            self.add_order_validity_proof(&order_id, proof_bundle.clone())
+                .await;
+            proofs.push((order_id, proof_bundle));
+        }
+
+        proofs
+    }
+
+    /// Wait for the cluster to elect a leader, returning whether the local peer is the leader
+    ///
+    /// If no leader is elected within the timeout the local peer assumes leadership for the
+    /// purpose of proving, so that its orders are not left unproven
+    async fn await_cluster_leader(&self) -> bool {
+        let deadline = Instant::now() + Duration::from_millis(LEADER_ELECTION_TIMEOUT_MS);
+        while Instant::now() < deadline {
+            if let Some(leader) = self.read_cluster_leadership().await.leader() {
+                return leader == self.local_peer_id;
+            }
+
+            tokio::time::sleep(Duration::from_millis(LEADER_POLL_INTERVAL_MS)).await;
+        }
+
+        log::warn!("no cluster leader elected, proving orders locally");
+        true
+    }
+
+    /// Request proofs of `VALID COMMITMENTS` for the given orders from the cluster
+    fn request_cluster_proofs(
+        &self,
+        order_ids: Vec<OrderIdentifier>,
+        network_sender: &JobQueueSender<GossipOutbound>,
+    ) {
+        let message = GossipOutbound::Pubsub {
+            topic: self.local_cluster_id.get_management_topic(),
+            message: PubsubMessage::ClusterManagement {
+                cluster_id: self.local_cluster_id.clone(),
+                message: ClusterManagementMessage::RequestOrderValidityProof(
+                    ValidityProofRequest {
+                        order_ids,
+                        sender: self.local_peer_id,
+                    },
+                ),
+            },
+        };
+        network_sender.send(message).unwrap()
+    }
+
+    /// Forward a proof of `VALID COMMITMENTS` to the proof manager and attach the witness to the
+    /// order book, returning the channel on which the proof will be sent
+    async fn enqueue_commitments_proof(
+        &self,
+        order_id: &OrderIdentifier,
+        witness: SizedValidCommitmentsWitness,
+        statement: ValidCommitmentsStatement,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+    ) -> oneshot::Receiver<ProofBundle> {
+        // If an equivalent witness has already been proven against the statement, e.g. by a
+        // previous run, use it so that the proof is served from the cache. The cached proof
+        // commits with the cached witness' randomness, so the two must be attached together
+        let witness = self
+            .proof_cache
+            .find_witness(&witness, &statement)
+            .unwrap_or(witness);
+
+        // Create a job and a response channel to get proofs back on, and forward the job; at
+        // startup every order is proven at once, so wait for room in the queue rather than
+        // have the job rejected
+        let (response_sender, response_receiver) = oneshot::channel();
+        proof_manager_queue
+            .send_waiting(ProofManagerJob {
+                type_: ProofJob::ValidCommitments {
+                    witness: witness.clone(),
+                    statement,
+                },
+                priority: ProofJobPriority::Background,
+                cancellation: None,
+                response_channel: response_sender,
+            })
+            .await
+            .unwrap();
+
+        // Attach a copy of the witness to the locally managed state
+        // This witness is reference by match computations which compute linkable commitments
+        // to the order and balance; i.e. they commit with the same randomness
+        {
+            self.read_order_book()
+                .await
+                .attach_validity_proof_witness(order_id, witness)
+                .await;
+        } // order_book lock released
+
+        response_receiver
+    }
+
+    /// Await a proof response for each order then attach it to the order index entry and
+    /// gossip the proof to the network
+    async fn attach_and_gossip_proofs(
+        &self,
+        proof_response_channels: Vec<(OrderIdentifier, oneshot::Receiver<ProofBundle>)>,
+        network_sender: &JobQueueSender<GossipOutbound>,
+    ) {
         for (order_id, receiver) in proof_response_channels.into_iter() {
             // Await a proof
             let proof_bundle: ValidCommitmentsBundle = receiver.await.unwrap().into();
@@ -243,24 +672,89 @@ impl RelayerState {
                         order_id,
                         cluster: self.local_cluster_id.clone(),
                         proof: proof_bundle,
+                        ioi: self.get_publishable_ioi(&order_id).await,
+                        time_in_force: self.get_order_time_in_force(&order_id).await,
                     },
                 ),
             };
             network_sender.send(message).unwrap()
         }
+    }
 
-        Ok(())
+    /// Enqueue a proof of `VALID COMMITMENTS` for an order at startup, returning `None` if
+    /// the wallet has no balance and fee that can cover the order
+    ///
+    /// A witness loaded from the proof cache by a previous run is re-validated before it is
+    /// proven from: each loaded witness is structurally checked against the wallet, and a
+    /// random `sample_rate` fraction of them are checked for constraint satisfaction. An
+    /// order whose loaded witness fails either check is quarantined, the witness is evicted
+    /// from the cache, and the order is proven from a freshly built witness instead
+    async fn prove_order_at_startup(
+        &self,
+        wallet_index: &WalletIndex,
+        wallet: &Wallet,
+        order_id: &OrderIdentifier,
+        merkle_path: &MerkleAuthenticationPath,
+        sample_rate: f64,
+        proof_manager_queue: &JobQueueSender<ProofManagerJob>,
+    ) -> Option<oneshot::Receiver<ProofBundle>> {
+        // Construct the witness and statement to generate a commitments proof from
+        let (witness, statement) =
+            build_commitments_witness(wallet_index, wallet, order_id, merkle_path).await?;
+
+        if let Some(loaded_witness) = self.proof_cache.find_witness(&witness, &statement)
+            && let Err(e) = check_witness_integrity(
+                wallet_index,
+                &loaded_witness,
+                wallet,
+                order_id,
+                merkle_path,
+                sample_rate,
+            )
+            .await
+        {
+            log::warn!("loaded witness for order {order_id} failed integrity check: {e}");
+            self.write_order_book()
+                .await
+                .quarantine_order(order_id)
+                .await;
+
+            if let Err(e) = self.proof_cache.remove(&loaded_witness, &statement) {
+                log::warn!("error evicting witness for order {order_id} from proof cache: {e}");
+            }
+        }
+
+        Some(
+            self.enqueue_commitments_proof(order_id, witness, statement, proof_manager_queue)
+                .await,
+        )
     }
 
     /// Searches on-chain state for the insertion of the given wallet, then finds the most
     /// recent updates of the path's siblings and creates a Merkle authentication path
-    async fn build_merkle_authentication_path(
+    pub async fn build_merkle_authentication_path(
         &self,
         wallet: &Wallet,
         contract_address: String,
         starknet_client: &JsonRpcClient<HttpTransport>,
     ) -> Result<MerkleAuthenticationPath, CoordinatorError> {
-        // Find the wallet in the commitment tree
+        // Serve the path from the local mirror of the tree if it is in sync with the chain
+        {
+            let locked_tree = self.read_merkle_tree().await;
+            if locked_tree.is_consistent()
+                && let Some(path) = locked_tree
+                    .find_leaf(&reduce_to_starknet_field(&wallet.get_commitment()))
+                    .and_then(|leaf_index| locked_tree.opening(leaf_index))
+            {
+                return Ok(MerkleAuthenticationPath::new(
+                    path.path_siblings,
+                    path.leaf_index,
+                    wallet.get_commitment(),
+                ));
+            }
+        } // locked_tree released
+
+        // Otherwise, find the wallet in the commitment tree
         let leaf_index = self
             .find_wallet_in_merkle_tree(wallet, contract_address.clone(), starknet_client)
             .await?;
@@ -396,3 +890,303 @@ impl RelayerState {
         Ok(result_map)
     }
 }
+
+/// Construct the witness and statement for a proof of `VALID COMMITMENTS` on the given order
+///
+/// Returns `None` if the wallet has no balance and fee that can cover the order, or if the
+/// relayer's match key for the wallet does not derive its `pk_match`
+async fn build_commitments_witness(
+    wallet_index: &WalletIndex,
+    wallet: &Wallet,
+    order_id: &OrderIdentifier,
+    merkle_path: &MerkleAuthenticationPath,
+) -> Option<(SizedValidCommitmentsWitness, ValidCommitmentsStatement)> {
+    let (order, balance, fee, fee_balance) = wallet_index
+        .get_order_balance_and_fee(&wallet.wallet_id, order_id)
+        .await?;
+    let sk_match = keychain::match_key(wallet)
+        .map_err(|err| log::error!("cannot prove VALID COMMITMENTS for order {order_id}: {err}"))
+        .ok()?;
+
+    let randomness_hash = compute_poseidon_hash(&[biguint_to_scalar(&wallet.randomness)]);
+    let witness = ValidCommitmentsWitness {
+        wallet: wallet.clone().into(),
+        order: order.into(),
+        balance: balance.into(),
+        fee: fee.into(),
+        fee_balance: fee_balance.into(),
+        wallet_opening: merkle_path.clone().into(),
+        randomness_hash: LinkableCommitment::new(randomness_hash),
+        sk_match,
+    };
+
+    let statement = ValidCommitmentsStatement {
+        nullifier: wallet.get_match_nullifier(),
+        merkle_root: merkle_path.compute_root(),
+        pk_settle: keychain::settle_key(wallet),
+    };
+
+    Some((witness, statement))
+}
+
+/// Check that a witness to `VALID COMMITMENTS` is consistent with the wallet it proves
+/// membership of
+async fn check_witness_structure(
+    wallet_index: &WalletIndex,
+    witness: &SizedValidCommitmentsWitness,
+    wallet: &Wallet,
+    order_id: &OrderIdentifier,
+    merkle_path: &MerkleAuthenticationPath,
+) -> Result<(), String> {
+    // The committed wallet must be the wallet authenticated in the Merkle tree
+    let witness_commitment = prime_field_to_scalar(&compute_wallet_commitment(&witness.wallet));
+    if witness_commitment != wallet.get_commitment() {
+        return Err(ERR_WALLET_MISMATCH.to_string());
+    }
+
+    let (order, balance, fee, fee_balance) = wallet_index
+        .get_order_balance_and_fee(&wallet.wallet_id, order_id)
+        .await
+        .ok_or_else(|| ERR_NO_BALANCE_OR_FEE.to_string())?;
+
+    if Order::from(witness.order.clone()) != order
+        || Balance::from(witness.balance.clone()) != balance
+        || Fee::from(witness.fee.clone()) != fee
+        || Balance::from(witness.fee_balance.clone()) != fee_balance
+    {
+        return Err(ERR_ORDER_BALANCE_FEE_MISMATCH.to_string());
+    }
+
+    if witness.sk_match != keychain::match_key(wallet).map_err(|err| err.to_string())? {
+        return Err(ERR_KEY_MISMATCH.to_string());
+    }
+
+    let randomness_hash = compute_poseidon_hash(&[biguint_to_scalar(&wallet.randomness)]);
+    if witness.randomness_hash.val != randomness_hash {
+        return Err(ERR_RANDOMNESS_MISMATCH.to_string());
+    }
+
+    let expected_opening: MerkleOpening = merkle_path.clone().into();
+    if witness.wallet_opening.elems != expected_opening.elems
+        || witness.wallet_opening.indices != expected_opening.indices
+    {
+        return Err(ERR_OPENING_MISMATCH.to_string());
+    }
+
+    Ok(())
+}
+
+/// Check that a witness to `VALID COMMITMENTS` is consistent with the wallet it proves
+/// membership of, and with probability `sample_rate` that it satisfies the circuit
+async fn check_witness_integrity(
+    wallet_index: &WalletIndex,
+    witness: &SizedValidCommitmentsWitness,
+    wallet: &Wallet,
+    order_id: &OrderIdentifier,
+    merkle_path: &MerkleAuthenticationPath,
+    sample_rate: f64,
+) -> Result<(), String> {
+    check_witness_structure(wallet_index, witness, wallet, order_id, merkle_path).await?;
+    if !thread_rng().gen_bool(sample_rate) {
+        return Ok(());
+    }
+
+    let statement = ValidCommitmentsStatement {
+        nullifier: wallet.get_match_nullifier(),
+        merkle_root: merkle_path.compute_root(),
+        pk_settle: keychain::settle_key(wallet),
+    };
+    if ValidCommitments::constraints_satisfied(witness.clone(), statement) {
+        Ok(())
+    } else {
+        Err(ERR_CONSTRAINTS_UNSATISFIED.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use circuits::{
+        singleprover_prove,
+        types::{
+            balance::Balance,
+            fee::Fee,
+            order::{Order, OrderSide},
+        },
+        zk_gadgets::fixed_point::FixedPoint,
+        LinkableCommitment,
+    };
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
+    use uuid::Uuid;
+
+    use crate::{
+        job_queue::{job_queue, DEFAULT_JOB_QUEUE_CAPACITY, PROOF_MANAGER_QUEUE},
+        keychain,
+        proof_generation::{
+            jobs::{ProofJob, ValidCommitmentsBundle},
+            proof_cache::ProofCache,
+        },
+        state::{
+            cluster_access::ClusterAccessPolicy,
+            feature_flags::FeatureFlags,
+            wallet::{MerkleAuthenticationPath, Wallet, WalletMetadata},
+            NetworkOrder, RelayerState,
+        },
+        system_bus::SystemBus,
+        types::SizedValidCommitments,
+        MERKLE_HEIGHT,
+    };
+
+    use super::build_commitments_witness;
+
+    /// Build a wallet holding a single sell order, and balances that cover it and its fee
+    fn test_wallet() -> Wallet {
+        let (public_keys, secret_keys) = keychain::derive_keychain(Scalar::from(42u64));
+        let order = Order {
+            quote_mint: 2u8.into(),
+            base_mint: 1u8.into(),
+            side: OrderSide::Sell,
+            price: FixedPoint::from(10f32),
+            amount: 5,
+            timestamp: 0,
+            time_in_force: Default::default(),
+            constraints: Default::default(),
+        };
+        let balances = [1u8, 2u8].into_iter().map(|mint| {
+            (
+                BigUint::from(mint),
+                Balance {
+                    mint: mint.into(),
+                    amount: 10,
+                },
+            )
+        });
+
+        Wallet {
+            wallet_id: Uuid::new_v4(),
+            orders: HashMap::from([(Uuid::new_v4(), order)]),
+            balances: balances.collect(),
+            fees: vec![Fee {
+                settle_key: 0u8.into(),
+                gas_addr: 2u8.into(),
+                gas_token_amount: 1,
+                percentage_fee: FixedPoint::from(0.01f32),
+            }],
+            public_keys,
+            secret_keys,
+            randomness: BigUint::from(7u8),
+            metadata: WalletMetadata {
+                replicas: HashSet::new(),
+                version: 0,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: Default::default(),
+        }
+    }
+
+    /// Tests that a corrupted witness loaded from the proof cache is quarantined, evicted
+    /// from the cache, and re-proven from a freshly built witness
+    #[tokio::test]
+    async fn test_corrupted_witness_reproved() {
+        let wallet = test_wallet();
+        let order_id = *wallet.orders.keys().next().unwrap();
+        let merkle_path = MerkleAuthenticationPath::new(
+            [Scalar::zero(); MERKLE_HEIGHT],
+            BigUint::from(0u8),
+            wallet.get_commitment(),
+        );
+
+        let proof_cache = ProofCache::new(None /* cache_dir */).unwrap();
+        let state = RelayerState::initialize_global_state(
+            false, /* debug */
+            vec![wallet.clone()],
+            "cluster".parse().unwrap(),
+            ClusterAccessPolicy::default(),
+            SystemBus::new(),
+            proof_cache.clone(),
+            FeatureFlags::new(&HashMap::new()),
+        );
+        state
+            .add_order(NetworkOrder::new(
+                order_id,
+                wallet.get_match_nullifier(),
+                state.local_cluster_id.clone(),
+                true, /* local */
+            ))
+            .await;
+
+        // Cache a proof generated from a witness whose randomness hash is corrupted, as if
+        // loaded from a previous run
+        let (witness, statement) = build_commitments_witness(
+            &*state.read_wallet_index().await,
+            &wallet,
+            &order_id,
+            &merkle_path,
+        )
+        .await
+        .unwrap();
+        let mut corrupted_witness = witness.clone();
+        corrupted_witness.randomness_hash = LinkableCommitment::new(Scalar::one());
+
+        let (commitment, proof) =
+            singleprover_prove::<SizedValidCommitments>(corrupted_witness.clone(), statement)
+                .unwrap();
+        proof_cache
+            .insert(
+                corrupted_witness.clone(),
+                ValidCommitmentsBundle {
+                    commitment,
+                    statement,
+                    proof,
+                },
+            )
+            .unwrap();
+        assert!(proof_cache.find_witness(&witness, &statement).is_some());
+
+        // Warm up the order; the loaded witness fails the structural check regardless of
+        // the sample rate
+        let (job_sender, mut job_receiver) =
+            job_queue(PROOF_MANAGER_QUEUE, DEFAULT_JOB_QUEUE_CAPACITY);
+        state
+            .prove_order_at_startup(
+                &*state.read_wallet_index().await,
+                &wallet,
+                &order_id,
+                &merkle_path,
+                0., /* sample_rate */
+                &job_sender,
+            )
+            .await
+            .unwrap();
+
+        // The corrupted witness is evicted and the order proven from the fresh witness
+        assert!(proof_cache.find_witness(&witness, &statement).is_none());
+        assert!(proof_cache.get(&corrupted_witness, &statement).is_none());
+
+        let job = job_receiver.try_recv().unwrap();
+        match job.type_ {
+            ProofJob::ValidCommitments {
+                witness: proven_witness,
+                ..
+            } => assert_eq!(
+                proven_witness.randomness_hash.val,
+                witness.randomness_hash.val
+            ),
+            _ => panic!("expected a proof of VALID COMMITMENTS"),
+        }
+
+        let attached_witness = state
+            .read_order_book()
+            .await
+            .get_validity_proof_witness(&order_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            attached_witness.randomness_hash.val,
+            witness.randomness_hash.val
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Tracks the elected leader of the local cluster
+//!
+//! The leader is the only cluster peer that generates proofs of `VALID COMMITMENTS` for
+//! the cluster's wallets and that replicates wallets to newly joined peers; followers
+//! receive proof bundles and wallets from the leader instead of duplicating the work.
+//!
+//! Elections are term based; a peer that detects the loss of the leader begins a new term
+//! and announces the live cluster peer with the lowest peer ID as leader. Announcements for
+//! a higher term always win, ties within a term are broken in favor of the lower peer ID so
+//! that concurrent elections converge on a single leader
+
+use crate::gossip::types::WrappedPeerId;
+
+/// The leadership state of the local cluster, as seen by the local peer
+#[derive(Clone, Debug, Default)]
+pub struct ClusterLeadership {
+    /// The current election term
+    term: u64,
+    /// The leader for the current term, `None` if no leader is known
+    leader: Option<WrappedPeerId>,
+}
+
+impl ClusterLeadership {
+    /// Create a new leadership tracker with no known leader
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current election term
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+
+    /// The leader of the current term, if one is known
+    pub fn leader(&self) -> Option<WrappedPeerId> {
+        self.leader
+    }
+
+    /// Whether the given peer is the current leader
+    pub fn is_leader(&self, peer_id: &WrappedPeerId) -> bool {
+        self.leader.as_ref() == Some(peer_id)
+    }
+
+    /// Begin a new term with the given candidate as leader, returning the new term
+    pub fn start_election(&mut self, candidate: WrappedPeerId) -> u64 {
+        self.term += 1;
+        self.leader = Some(candidate);
+        self.term
+    }
+
+    /// Apply a leadership announcement from a cluster peer
+    ///
+    /// Returns whether the announcement changed the local view of the leader
+    pub fn observe_announcement(&mut self, leader: WrappedPeerId, term: u64) -> bool {
+        let accept = if term == self.term {
+            self.leader
+                .map_or(true, |current_leader| leader < current_leader)
+        } else {
+            term > self.term
+        };
+
+        if accept {
+            self.term = term;
+            self.leader = Some(leader);
+        }
+
+        accept
+    }
+
+    /// Forget the leader if it is the given peer, e.g. after the peer's heartbeats
+    /// have stopped
+    ///
+    /// Returns whether the leader was cleared
+    pub fn clear_leader(&mut self, peer_id: &WrappedPeerId) -> bool {
+        if !self.is_leader(peer_id) {
+            return false;
+        }
+
+        self.leader = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gossip::types::WrappedPeerId;
+
+    use super::ClusterLeadership;
+
+    /// Tests that a higher term always supersedes the current leader, and that a stale
+    /// term never does
+    #[test]
+    fn test_term_ordering() {
+        let mut leadership = ClusterLeadership::new();
+        let peer1 = WrappedPeerId::random();
+        let peer2 = WrappedPeerId::random();
+
+        let term = leadership.start_election(peer1);
+        assert!(leadership.observe_announcement(peer2, term + 1));
+        assert!(leadership.is_leader(&peer2));
+
+        assert!(!leadership.observe_announcement(peer1, term));
+        assert!(leadership.is_leader(&peer2));
+    }
+
+    /// Tests that concurrent elections in the same term converge on the lower peer ID
+    #[test]
+    fn test_tie_break() {
+        let mut peers = vec![WrappedPeerId::random(), WrappedPeerId::random()];
+        peers.sort();
+        let (lower, higher) = (peers[0], peers[1]);
+
+        let mut leadership1 = ClusterLeadership::new();
+        let mut leadership2 = ClusterLeadership::new();
+        let term = leadership1.start_election(lower);
+        leadership2.start_election(higher);
+
+        assert!(!leadership1.observe_announcement(higher, term));
+        assert!(leadership2.observe_announcement(lower, term));
+        assert_eq!(leadership1.leader(), leadership2.leader());
+    }
+
+    /// Tests that losing the leader clears it only if the lost peer is the leader
+    #[test]
+    fn test_clear_leader() {
+        let mut leadership = ClusterLeadership::new();
+        let leader = WrappedPeerId::random();
+        leadership.start_election(leader);
+
+        assert!(!leadership.clear_leader(&WrappedPeerId::random()));
+        assert!(leadership.clear_leader(&leader));
+        assert!(leadership.leader().is_none());
+    }
+}
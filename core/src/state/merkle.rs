@@ -0,0 +1,382 @@
+//! A local mirror of the contract's wallet commitment tree
+//!
+//! The mirror is built by replaying the contract's leaf insertion events in order, and
+//! maintains the tree incrementally; only the nodes that differ from an empty subtree
+//! are stored. This lets the relayer serve Merkle openings for any inserted leaf without
+//! scanning the chain for the latest value of each sibling.
+//!
+//! The contract keeps a history of its most recent `MERKLE_ROOT_HISTORY_LENGTH` roots, a
+//! proof is accepted against any of them. The mirror records the roots the contract
+//! announces so that it can check both its own consistency with the chain and whether a
+//! given root is still accepted
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+use circuits::native_helpers::compute_poseidon_hash;
+use crypto::fields::{biguint_to_scalar, scalar_to_biguint, starknet_felt_to_biguint};
+use curve25519_dalek::scalar::Scalar;
+use num_bigint::BigUint;
+use starknet::core::types::FieldElement as StarknetFieldElement;
+
+use crate::{MERKLE_HEIGHT, MERKLE_ROOT_HISTORY_LENGTH};
+
+use super::{wallet::MerkleAuthenticationPath, MerkleTreeCoords};
+
+lazy_static! {
+    /// The value of an empty leaf in the Merkle tree
+    pub(super) static ref EMPTY_LEAF_VALUE: Scalar = {
+        let val_bigint = BigUint::from_str(
+            "306932273398430716639340090025251549301604242969558673011416862133942957551"
+        ).unwrap();
+        biguint_to_scalar(&val_bigint)
+    };
+    /// The value of an empty subtree rooted at each height of the tree, indexed by height
+    static ref EMPTY_SUBTREE_VALUES: Vec<Scalar> = {
+        let mut values = vec![*EMPTY_LEAF_VALUE; MERKLE_HEIGHT + 1];
+        for height in (0..MERKLE_HEIGHT).rev() {
+            values[height] = compute_poseidon_hash(&[values[height + 1], values[height + 1]]);
+        }
+
+        values
+    };
+}
+
+/// The error type returned when an insertion cannot be applied to the mirror
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MerkleMirrorError {
+    /// An insertion skipped over leaves that the mirror has not seen, some insertion
+    /// events were missed
+    InsertionGap {
+        /// The index of the next leaf the mirror expected
+        expected: u64,
+        /// The index of the inserted leaf
+        received: u64,
+    },
+    /// A replayed insertion holds a different value than the mirror's leaf
+    ConflictingLeaf(u64),
+    /// The tree has no free leaves
+    TreeFull,
+}
+
+impl Display for MerkleMirrorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            MerkleMirrorError::InsertionGap { expected, received } => write!(
+                f,
+                "insertion gap, expected leaf {}, got {}",
+                expected, received
+            ),
+            MerkleMirrorError::ConflictingLeaf(index) => {
+                write!(f, "conflicting value inserted at leaf {}", index)
+            }
+            MerkleMirrorError::TreeFull => write!(f, "Merkle tree is full"),
+        }
+    }
+}
+
+/// A mirror of the contract's commitment tree, maintained from insertion events
+#[derive(Clone, Debug, Default)]
+pub struct MerkleTreeMirror {
+    /// The nodes of the tree that differ from the empty subtree at their height
+    nodes: HashMap<MerkleTreeCoords, Scalar>,
+    /// The index of the next leaf to be inserted
+    next_index: u64,
+    /// The most recent roots announced by the contract, oldest first
+    onchain_roots: VecDeque<Scalar>,
+    /// The number of roots announced by the contract since the mirror was built
+    roots_observed: u64,
+    /// Whether the mirror's root matched the latest root announced by the contract
+    consistent: bool,
+}
+
+impl MerkleTreeMirror {
+    /// Construct an empty mirror
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of leaves inserted into the tree
+    pub fn num_leaves(&self) -> u64 {
+        self.next_index
+    }
+
+    /// The number of roots the contract has announced since the mirror was built
+    pub fn roots_observed(&self) -> u64 {
+        self.roots_observed
+    }
+
+    /// The root of the mirrored tree
+    pub fn root(&self) -> Scalar {
+        self.get_node(0 /* height */, BigUint::from(0u8))
+    }
+
+    /// Whether the mirror's root matched the latest root announced by the contract
+    ///
+    /// Openings served by an inconsistent mirror may not verify against any root the
+    /// contract accepts
+    pub fn is_consistent(&self) -> bool {
+        self.consistent
+    }
+
+    /// Whether the given root is among the roots the contract currently accepts
+    pub fn is_valid_root(&self, root: &Scalar) -> bool {
+        self.onchain_roots.contains(&reduce_to_starknet_field(root))
+    }
+
+    /// Insert a leaf into the tree, replaying an insertion event from the contract
+    ///
+    /// Insertions must be replayed in order; an insertion of a leaf the mirror already
+    /// holds is ignored so that overlapping event ranges may be replayed safely
+    pub fn insert(&mut self, index: u64, value: Scalar) -> Result<(), MerkleMirrorError> {
+        if index < self.next_index {
+            return if self.get_node(MERKLE_HEIGHT, BigUint::from(index)) == value {
+                Ok(())
+            } else {
+                Err(MerkleMirrorError::ConflictingLeaf(index))
+            };
+        }
+
+        if index > self.next_index {
+            return Err(MerkleMirrorError::InsertionGap {
+                expected: self.next_index,
+                received: index,
+            });
+        }
+
+        if index >= 1u64 << MERKLE_HEIGHT {
+            return Err(MerkleMirrorError::TreeFull);
+        }
+
+        // Hash up the path from the new leaf to the root
+        let mut current_index = BigUint::from(index);
+        let mut current_value = value;
+        self.nodes.insert(
+            MerkleTreeCoords::new(MERKLE_HEIGHT, current_index.clone()),
+            current_value,
+        );
+
+        for height in (1..MERKLE_HEIGHT + 1).rev() {
+            let is_left = &current_index % 2u8 == BigUint::from(0u8);
+            let sibling = self.get_node(height, sibling_index(&current_index));
+            current_value = if is_left {
+                compute_poseidon_hash(&[current_value, sibling])
+            } else {
+                compute_poseidon_hash(&[sibling, current_value])
+            };
+
+            current_index >>= 1;
+            self.nodes.insert(
+                MerkleTreeCoords::new(height - 1, current_index.clone()),
+                current_value,
+            );
+        }
+
+        self.next_index += 1;
+        // The new root has not yet been confirmed by the contract
+        self.consistent = false;
+        Ok(())
+    }
+
+    /// Record a root announced by the contract, returning whether the mirror's root
+    /// matches it
+    pub fn observe_onchain_root(&mut self, root: Scalar) -> bool {
+        self.onchain_roots.push_back(root);
+        self.roots_observed += 1;
+        if self.onchain_roots.len() > MERKLE_ROOT_HISTORY_LENGTH {
+            self.onchain_roots.pop_front();
+        }
+
+        self.consistent = reduce_to_starknet_field(&self.root()) == root;
+        self.consistent
+    }
+
+    /// Roll the mirror back to an earlier state of the contract's tree, undoing the
+    /// insertions and roots of blocks abandoned by a chain re-org
+    ///
+    /// The tree is rebuilt from the leaves that remain; roots that have fallen out of the
+    /// history cannot be restored, so the history may be shorter than the contract's
+    pub fn rollback(&mut self, num_leaves: u64, roots_observed: u64) {
+        if num_leaves < self.next_index {
+            let leaves = (0..num_leaves)
+                .map(|index| self.get_node(MERKLE_HEIGHT, BigUint::from(index)))
+                .collect::<Vec<_>>();
+            self.nodes.clear();
+            self.next_index = 0;
+            for (index, leaf) in leaves.into_iter().enumerate() {
+                self.insert(index as u64, leaf)
+                    .expect("replaying retained leaves cannot fail");
+            }
+        }
+
+        let stale_roots = self.roots_observed.saturating_sub(roots_observed);
+        for _ in 0..stale_roots {
+            self.onchain_roots.pop_back();
+        }
+        self.roots_observed = self.roots_observed.min(roots_observed);
+
+        self.consistent = self
+            .onchain_roots
+            .back()
+            .map(|root| reduce_to_starknet_field(&self.root()) == *root)
+            .unwrap_or(false);
+    }
+
+    /// Find the index of the first leaf holding the given value
+    pub fn find_leaf(&self, value: &Scalar) -> Option<u64> {
+        self.nodes
+            .iter()
+            .filter(|(coords, leaf)| coords.height == MERKLE_HEIGHT && *leaf == value)
+            .filter_map(|(coords, _)| u64::try_from(&coords.index).ok())
+            .min()
+    }
+
+    /// Build an opening of the leaf at the given index against the current root
+    ///
+    /// Returns `None` if no leaf has been inserted at the index
+    pub fn opening(&self, leaf_index: u64) -> Option<MerkleAuthenticationPath> {
+        if leaf_index >= self.next_index {
+            return None;
+        }
+
+        let leaf_index = BigUint::from(leaf_index);
+        let mut current_index = leaf_index.clone();
+        let mut path_siblings = [Scalar::zero(); MERKLE_HEIGHT];
+        for (i, height) in (1..MERKLE_HEIGHT + 1).rev().enumerate() {
+            path_siblings[i] = self.get_node(height, sibling_index(&current_index));
+            current_index >>= 1;
+        }
+
+        let value = self.get_node(MERKLE_HEIGHT, leaf_index.clone());
+        Some(MerkleAuthenticationPath::new(
+            path_siblings,
+            leaf_index,
+            value,
+        ))
+    }
+
+    /// Get the value of the node at the given coordinates
+    fn get_node(&self, height: usize, index: BigUint) -> Scalar {
+        self.nodes
+            .get(&MerkleTreeCoords::new(height, index))
+            .cloned()
+            .unwrap_or(EMPTY_SUBTREE_VALUES[height])
+    }
+}
+
+/// Get the index of a node's sibling at the same height
+fn sibling_index(index: &BigUint) -> BigUint {
+    if index % 2u8 == BigUint::from(0u8) {
+        index + 1u8
+    } else {
+        index - 1u8
+    }
+}
+
+/// Reduce a scalar into the Starknet field, the form in which the contract emits leaves
+/// and roots
+pub(crate) fn reduce_to_starknet_field(value: &Scalar) -> Scalar {
+    let modulus = starknet_felt_to_biguint(&StarknetFieldElement::MAX) + 1u8;
+    biguint_to_scalar(&(scalar_to_biguint(value) % modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+
+    use crate::MERKLE_ROOT_HISTORY_LENGTH;
+
+    use super::{reduce_to_starknet_field, MerkleMirrorError, MerkleTreeMirror};
+
+    /// Tests that openings served by the mirror hash to the mirror's root
+    #[test]
+    fn test_openings_match_root() {
+        let mut mirror = MerkleTreeMirror::new();
+        let leaves = (1..6u64).map(Scalar::from).collect::<Vec<_>>();
+        for (index, leaf) in leaves.iter().enumerate() {
+            mirror.insert(index as u64, *leaf).unwrap();
+        }
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let opening = mirror.opening(index as u64).unwrap();
+            assert_eq!(opening.value, *leaf);
+            assert_eq!(opening.compute_root(), mirror.root());
+            assert_eq!(mirror.find_leaf(leaf), Some(index as u64));
+        }
+
+        assert!(mirror.opening(leaves.len() as u64).is_none());
+    }
+
+    /// Tests that replayed insertions are ignored and skipped insertions are rejected
+    #[test]
+    fn test_insertion_order() {
+        let mut mirror = MerkleTreeMirror::new();
+        let leaf = Scalar::from(42u64);
+        mirror.insert(0, leaf).unwrap();
+        let root = mirror.root();
+
+        mirror.insert(0, leaf).unwrap();
+        assert_eq!(mirror.root(), root);
+        assert_eq!(
+            mirror.insert(0, Scalar::one()),
+            Err(MerkleMirrorError::ConflictingLeaf(0))
+        );
+        assert_eq!(
+            mirror.insert(2, leaf),
+            Err(MerkleMirrorError::InsertionGap {
+                expected: 1,
+                received: 2
+            })
+        );
+        assert_eq!(mirror.num_leaves(), 1);
+    }
+
+    /// Tests that the mirror tracks consistency with, and validity against, on-chain roots
+    #[test]
+    fn test_onchain_roots() {
+        let mut mirror = MerkleTreeMirror::new();
+        mirror.insert(0, Scalar::from(1u64)).unwrap();
+        let first_root = mirror.root();
+        assert!(mirror.observe_onchain_root(reduce_to_starknet_field(&first_root)));
+
+        mirror.insert(1, Scalar::from(2u64)).unwrap();
+        assert!(!mirror.is_consistent());
+        assert!(!mirror.observe_onchain_root(Scalar::one()));
+        assert!(mirror.is_valid_root(&first_root));
+
+        // The first root falls out of the history once enough roots are announced
+        for _ in 0..MERKLE_ROOT_HISTORY_LENGTH {
+            mirror.observe_onchain_root(Scalar::one());
+        }
+        assert!(!mirror.is_valid_root(&first_root));
+    }
+
+    /// Tests that rolling back the mirror undoes the insertions and roots after a state
+    #[test]
+    fn test_rollback() {
+        let mut mirror = MerkleTreeMirror::new();
+        mirror.insert(0, Scalar::from(1u64)).unwrap();
+        mirror.insert(1, Scalar::from(2u64)).unwrap();
+        let checkpoint_root = mirror.root();
+        mirror.observe_onchain_root(reduce_to_starknet_field(&checkpoint_root));
+        let checkpoint_roots = mirror.roots_observed();
+
+        mirror.insert(2, Scalar::from(3u64)).unwrap();
+        let abandoned_root = mirror.root();
+        mirror.observe_onchain_root(reduce_to_starknet_field(&abandoned_root));
+
+        mirror.rollback(2 /* num_leaves */, checkpoint_roots);
+        assert_eq!(mirror.num_leaves(), 2);
+        assert_eq!(mirror.root(), checkpoint_root);
+        assert!(mirror.is_consistent());
+        assert!(!mirror.is_valid_root(&abandoned_root));
+
+        // The canonical chain's leaf may then be inserted in place of the abandoned one
+        mirror.insert(2, Scalar::from(4u64)).unwrap();
+        assert_ne!(mirror.root(), abandoned_root);
+    }
+}
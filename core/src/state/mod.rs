@@ -1,17 +1,31 @@
 //! Groups state object definitions and handles logic for serializing access to shared
 //! global state elements
+pub mod audit;
+pub mod cluster_access;
+pub mod expiry;
+pub mod export;
+pub mod feature_flags;
 mod initialize;
+pub mod leader;
+pub mod merkle;
 mod orderbook;
+pub mod peer_auth;
 pub mod peers;
 mod priority;
+pub mod settlements;
 #[allow(clippy::module_inception)]
 mod state;
 pub mod tui;
+pub mod versions;
 pub mod wallet;
+pub mod wallet_manager;
+pub mod wallet_shards;
 
 use num_bigint::BigUint;
 
-pub use self::orderbook::{NetworkOrder, NetworkOrderBook, NetworkOrderState, OrderIdentifier};
+pub use self::orderbook::{
+    NetworkOrder, NetworkOrderBook, NetworkOrderState, OrderBookFilter, OrderIdentifier,
+};
 pub use self::state::*;
 
 /// A wrapper representing the coordinates of a value in a Merkle tree
@@ -13,19 +13,25 @@
 // TODO: Remove this lint allowance
 #![allow(unused)]
 
-use circuits::{types::wallet::Nullifier, zk_circuits::valid_commitments::ValidCommitmentsWitness};
+use circuits::{
+    types::{order::TimeInForce, wallet::Nullifier},
+    zk_circuits::valid_commitments::ValidCommitmentsWitness,
+};
 use futures::stream::{futures_unordered::FuturesUnordered, iter as to_stream, StreamExt};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
     fmt::{Display, Formatter, Result as FmtResult},
+    mem,
+    ops::Bound,
 };
 use tokio::sync::{RwLockReadGuard, RwLockWriteGuard};
 use uuid::Uuid;
 
 use crate::{
     gossip::types::{ClusterId, WrappedPeerId},
+    gossip_api::orderbook_management::IndicationOfInterest,
     proof_generation::jobs::ValidCommitmentsBundle,
     system_bus::SystemBus,
     types::{SizedValidCommitmentsWitness, SystemBusMessage, ORDER_STATE_CHANGE_TOPIC},
@@ -100,6 +106,18 @@ pub struct NetworkOrder {
     /// Skip serialization to avoid sending witness, the serialized type will have `None` in place
     #[serde(skip)]
     pub valid_commit_witness: Option<SizedValidCommitmentsWitness>,
+    /// The indication of interest published for the order, if its managing cluster
+    /// publishes IoIs
+    #[serde(default)]
+    pub ioi: Option<IndicationOfInterest>,
+    /// How long the order remains eligible for matching, as set by its owner for local
+    /// orders or as published by the managing cluster for others
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    /// Whether a match MPC on the order has concluded on the local node, after which an
+    /// immediate-or-cancel order expires
+    #[serde(default)]
+    pub mpc_attempted: bool,
 }
 
 impl NetworkOrder {
@@ -118,6 +136,24 @@ impl NetworkOrder {
             state: NetworkOrderState::Received,
             valid_commit_proof: None,
             valid_commit_witness: None,
+            ioi: None,
+            time_in_force: TimeInForce::default(),
+            mpc_attempted: false,
+        }
+    }
+
+    /// Set the time in force of the order
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    /// Whether the order's time in force has lapsed at the given unix timestamp, in seconds
+    pub fn is_expired(&self, now: u64) -> bool {
+        match self.time_in_force {
+            TimeInForce::GoodTilCancelled => false,
+            TimeInForce::GoodTilDate(expiry) => expiry <= now,
+            TimeInForce::ImmediateOrCancel => self.mpc_attempted,
         }
     }
 
@@ -193,11 +229,51 @@ impl Display for NetworkOrderState {
     }
 }
 
+/// A filter over the orders in the book, each field left as `None` matches all orders
+#[derive(Clone, Debug, Default)]
+pub struct OrderBookFilter {
+    /// Match only orders in the given state
+    ///
+    /// States are compared by variant, so `Matched` matches orders regardless of which
+    /// node matched them
+    pub state: Option<NetworkOrderState>,
+    /// Match only orders managed by the given cluster
+    pub cluster: Option<ClusterId>,
+    /// Match only locally (or only non-locally) managed orders
+    pub local: Option<bool>,
+    /// Match only orders under the given wallet match nullifier
+    pub match_nullifier: Option<Nullifier>,
+}
+
+impl OrderBookFilter {
+    /// Whether the given order passes the filter
+    pub fn matches(&self, order: &NetworkOrder) -> bool {
+        self.state
+            .map(|state| mem::discriminant(&state) == mem::discriminant(&order.state))
+            .unwrap_or(true)
+            && self
+                .cluster
+                .as_ref()
+                .map(|cluster| *cluster == order.cluster)
+                .unwrap_or(true)
+            && self.local.map(|local| local == order.local).unwrap_or(true)
+            && self
+                .match_nullifier
+                .map(|nullifier| nullifier == order.match_nullifier)
+                .unwrap_or(true)
+    }
+}
+
 /// Represents the order index, a collection of known orders allocated in the network
 #[derive(Clone, Debug)]
 pub struct NetworkOrderBook {
     /// The mapping from order identifier to order information
     order_map: HashMap<OrderIdentifier, AsyncShared<NetworkOrder>>,
+    /// The identifiers of all orders in the book, ordered so that pages of the book
+    /// may be read starting from a cursor
+    order_ids: BTreeSet<OrderIdentifier>,
+    /// A mapping from cluster ID to the orders managed by the cluster
+    orders_by_cluster: HashMap<ClusterId, BTreeSet<OrderIdentifier>>,
     /// A mapping from the wallet match nullifier to the order
     orders_by_nullifier: HashMap<Nullifier, AsyncShared<HashSet<OrderIdentifier>>>,
     /// A list of order IDs maintained locally
@@ -213,6 +289,8 @@ impl NetworkOrderBook {
     pub fn new(system_bus: SystemBus<SystemBusMessage>) -> Self {
         Self {
             order_map: HashMap::new(),
+            order_ids: BTreeSet::new(),
+            orders_by_cluster: HashMap::new(),
             orders_by_nullifier: HashMap::new(),
             local_orders: new_async_shared(HashSet::new()),
             verified_orders: new_async_shared(HashSet::new()),
@@ -302,6 +380,15 @@ impl NetworkOrderBook {
         }
     }
 
+    /// Whether the order's time in force has lapsed at the given unix timestamp, in seconds;
+    /// unknown orders are not expired
+    pub async fn is_order_expired(&self, order_id: &OrderIdentifier, now: u64) -> bool {
+        match self.order_map.get(order_id) {
+            Some(order) => order.read().await.is_expired(now),
+            None => false,
+        }
+    }
+
     /// Fetch the match nullifier for an order
     pub async fn get_match_nullifier(&self, order_id: &OrderIdentifier) -> Option<Nullifier> {
         self.read_order(order_id)
@@ -311,6 +398,11 @@ impl NetworkOrderBook {
             .map(|proof| proof.statement.nullifier)
     }
 
+    /// Fetch the indication of interest published for an order, if any
+    pub async fn get_ioi(&self, order_id: &OrderIdentifier) -> Option<IndicationOfInterest> {
+        self.read_order(order_id).await?.ioi.clone()
+    }
+
     /// Fetch all orders under a given nullifier
     pub async fn get_orders_by_nullifier(&self, nullifier: Nullifier) -> Vec<OrderIdentifier> {
         if let Some(set) = self.read_nullifier_order_set(&nullifier).await {
@@ -435,6 +527,71 @@ impl NetworkOrderBook {
         res
     }
 
+    /// Fetch a page of the orders in the book that pass the given filter
+    ///
+    /// Orders are returned in order of their identifier, beginning after `cursor` if one is
+    /// given. Alongside the page, returns the cursor from which to fetch the next page, or
+    /// `None` if there are no further matching orders
+    pub async fn get_orders_page(
+        &self,
+        filter: &OrderBookFilter,
+        cursor: Option<OrderIdentifier>,
+        limit: usize,
+    ) -> (Vec<NetworkOrder>, Option<OrderIdentifier>) {
+        // Iterate over the narrowest index that the filter permits
+        let candidates = self.get_filter_candidates(filter).await;
+        let lower_bound = cursor.map(Bound::Excluded).unwrap_or(Bound::Unbounded);
+
+        // Fetch one more order than requested to determine whether a next page exists
+        let mut page = Vec::with_capacity(limit + 1);
+        for order_id in candidates.range((lower_bound, Bound::Unbounded)) {
+            if let Some(order) = self.read_order(order_id).await && filter.matches(&order) {
+                page.push(order.clone());
+            }
+
+            if page.len() > limit {
+                break;
+            }
+        }
+
+        let next_cursor = if page.len() > limit {
+            page.truncate(limit);
+            page.last().map(|order| order.id)
+        } else {
+            None
+        };
+
+        (page, next_cursor)
+    }
+
+    /// Fetch the set of order IDs from the narrowest index the filter allows; every order
+    /// that passes the filter is guaranteed to be in this set
+    async fn get_filter_candidates(&self, filter: &OrderBookFilter) -> BTreeSet<OrderIdentifier> {
+        if let Some(nullifier) = filter.match_nullifier {
+            return self
+                .read_nullifier_order_set(&nullifier)
+                .await
+                .map(|orders| orders.iter().cloned().collect())
+                .unwrap_or_default();
+        }
+
+        if let Some(cluster) = filter.cluster.as_ref() {
+            return self
+                .orders_by_cluster
+                .get(cluster)
+                .cloned()
+                .unwrap_or_default();
+        }
+
+        match (filter.local, filter.state) {
+            (Some(true), _) => self.read_local_orders().await.iter().cloned().collect(),
+            (_, Some(NetworkOrderState::Verified)) => {
+                self.read_verified_orders().await.iter().cloned().collect()
+            }
+            _ => self.order_ids.clone(),
+        }
+    }
+
     // -----------
     // | Setters |
     // -----------
@@ -457,6 +614,13 @@ impl NetworkOrderBook {
             .await
             .insert(order.id);
 
+        // Add entries in the ordered and by-cluster indices
+        self.order_ids.insert(order.id);
+        self.orders_by_cluster
+            .entry(order.cluster.clone())
+            .or_default()
+            .insert(order.id);
+
         // Add an entry in the order index
         self.order_map.insert(order.id, new_async_shared(order));
     }
@@ -490,6 +654,44 @@ impl NetworkOrderBook {
         }
     }
 
+    /// Attach an indication of interest to an order
+    pub async fn attach_ioi(&self, order_id: &OrderIdentifier, ioi: IndicationOfInterest) {
+        if let Some(mut locked_order) = self.write_order(order_id).await {
+            locked_order.ioi = Some(ioi);
+        }
+    }
+
+    /// Set the time in force of an order
+    pub async fn attach_time_in_force(
+        &self,
+        order_id: &OrderIdentifier,
+        time_in_force: TimeInForce,
+    ) {
+        if let Some(mut locked_order) = self.write_order(order_id).await {
+            locked_order.time_in_force = time_in_force;
+        }
+    }
+
+    /// Record that a match MPC on an order has concluded on the local node
+    pub async fn mark_mpc_attempted(&self, order_id: &OrderIdentifier) {
+        if let Some(mut locked_order) = self.write_order(order_id).await {
+            locked_order.mpc_attempted = true;
+        }
+    }
+
+    /// Quarantine a locally managed order whose witness is inconsistent with its wallet
+    ///
+    /// Both the witness and any proof of `VALID COMMITMENTS` generated from it are dropped, and
+    /// the order is moved back to `Received` so that it is not matched until it is re-proven
+    pub async fn quarantine_order(&mut self, order_id: &OrderIdentifier) {
+        if let Some(mut locked_order) = self.write_order(order_id).await {
+            locked_order.valid_commit_witness = None;
+            locked_order.valid_commit_proof = None;
+        } // locked_order released
+
+        self.transition_order_received(order_id).await;
+    }
+
     /// Add an order to the verified orders list
     async fn add_verified_order(&self, order_id: Uuid) {
         if !self.read_verified_orders().await.contains(&order_id) {
@@ -517,7 +719,7 @@ impl NetworkOrderBook {
             let prev_state = order.state;
             order.transition_received();
 
-            self.remove_verified_order(order_id);
+            self.remove_verified_order(order_id).await;
 
             self.system_bus.publish(
                 ORDER_STATE_CHANGE_TOPIC.to_string(),
@@ -536,6 +738,8 @@ impl NetworkOrderBook {
         order_id: &OrderIdentifier,
         proof: ValidCommitmentsBundle,
     ) {
+        // The proof updates the order's match nullifier, index the order under it
+        let match_nullifier = proof.statement.nullifier;
         if let Some(mut order) = self.write_order(order_id).await {
             let prev_state = order.state;
             order.transition_verified(proof);
@@ -550,7 +754,13 @@ impl NetworkOrderBook {
                     new_state: order.state,
                 },
             );
-        }
+        } else {
+            return;
+        } // order released
+
+        self.write_nullifier_order_set(match_nullifier)
+            .await
+            .insert(*order_id);
     }
 
     /// Transitions the state of an order from `Verified` to `Matched`
@@ -591,6 +801,90 @@ impl NetworkOrderBook {
         }
     }
 
+    /// Cancel the orders indexed under a spent match nullifier, returning the IDs of
+    /// the cancelled orders that are locally managed
+    ///
+    /// A spent nullifier never becomes valid again, so its index entry is dropped. Orders
+    /// that have since been re-proven under a different nullifier are left untouched
+    pub async fn cancel_orders_by_nullifier(
+        &mut self,
+        nullifier: Nullifier,
+    ) -> Vec<OrderIdentifier> {
+        let order_ids = match self.orders_by_nullifier.remove(&nullifier) {
+            Some(order_set) => order_set.read().await.iter().cloned().collect_vec(),
+            None => return Vec::new(),
+        };
+
+        let mut cancelled_local_orders = Vec::new();
+        for order_id in order_ids.iter() {
+            let order_info = match self.get_order_info(order_id).await {
+                Some(info) if info.match_nullifier == nullifier => info,
+                _ => continue,
+            };
+
+            self.transition_cancelled(order_id).await;
+            if order_info.local {
+                cancelled_local_orders.push(*order_id);
+            }
+        }
+
+        cancelled_local_orders
+    }
+
+    /// Prune the non-local orders managed by a cluster, returning the IDs of the pruned
+    /// orders
+    ///
+    /// Only orders that are still open, i.e. `Received` or `Verified`, are pruned
+    pub async fn prune_cluster_orders(&mut self, cluster_id: &ClusterId) -> Vec<OrderIdentifier> {
+        let mut pruned_orders = Vec::new();
+        for (order_id, cluster) in self.get_order_owner_pairs().await.into_iter() {
+            if &cluster != cluster_id {
+                continue;
+            }
+
+            let order_info = match self.get_order_info(&order_id).await {
+                Some(info) => info,
+                None => continue,
+            };
+            if !order_info.local
+                && matches!(
+                    order_info.state,
+                    NetworkOrderState::Received | NetworkOrderState::Verified
+                )
+            {
+                self.transition_pruned(&order_id).await;
+                pruned_orders.push(order_id);
+            }
+        }
+
+        pruned_orders
+    }
+
+    /// Cancel the open orders whose time in force has lapsed at the given unix timestamp,
+    /// in seconds, returning the IDs of the cancelled orders
+    ///
+    /// Only orders that are still open, i.e. `Received` or `Verified`, are cancelled
+    pub async fn cancel_expired_orders(&mut self, now: u64) -> Vec<OrderIdentifier> {
+        let mut expired_orders = Vec::new();
+        for (order_id, order) in self.order_map.iter() {
+            let locked_order = order.read().await;
+            if locked_order.is_expired(now)
+                && matches!(
+                    locked_order.state,
+                    NetworkOrderState::Received | NetworkOrderState::Verified
+                )
+            {
+                expired_orders.push(*order_id);
+            }
+        }
+
+        for order_id in expired_orders.iter() {
+            self.transition_cancelled(order_id).await;
+        }
+
+        expired_orders
+    }
+
     /// Transitions the state of an order to `Pruned`
     pub async fn transition_pruned(&mut self, order_id: &OrderIdentifier) {
         if let Some(mut order) = self.write_order(order_id).await {
@@ -610,3 +904,196 @@ impl NetworkOrderBook {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+    use itertools::Itertools;
+    use uuid::Uuid;
+
+    use crate::{gossip::types::ClusterId, system_bus::SystemBus};
+
+    use circuits::types::order::TimeInForce;
+
+    use super::{NetworkOrder, NetworkOrderBook, NetworkOrderState, OrderBookFilter};
+
+    /// Build an order book with the given number of orders, alternating between two clusters
+    async fn build_order_book(n_orders: usize) -> NetworkOrderBook {
+        let mut order_book = NetworkOrderBook::new(SystemBus::new());
+        for i in 0..n_orders {
+            let cluster: ClusterId = if i % 2 == 0 { "even" } else { "odd" }.parse().unwrap();
+            order_book
+                .add_order(NetworkOrder::new(
+                    Uuid::new_v4(),
+                    Scalar::from(i as u64),
+                    cluster,
+                    i % 2 == 0, /* local */
+                ))
+                .await;
+        }
+
+        order_book
+    }
+
+    /// Tests that paging through the book visits every order exactly once, in order
+    #[tokio::test]
+    async fn test_pagination() {
+        let order_book = build_order_book(25).await;
+        let filter = OrderBookFilter::default();
+
+        let mut cursor = None;
+        let mut seen = Vec::new();
+        loop {
+            let (page, next_cursor) = order_book.get_orders_page(&filter, cursor, 10).await;
+            assert!(page.len() <= 10);
+            seen.extend(page.into_iter().map(|order| order.id));
+
+            if next_cursor.is_none() {
+                break;
+            }
+            cursor = next_cursor;
+        }
+
+        let expected = order_book.order_ids.iter().cloned().collect_vec();
+        assert_eq!(seen, expected);
+    }
+
+    /// Tests that spending a nullifier cancels only the orders still indexed under it
+    #[tokio::test]
+    async fn test_cancel_orders_by_nullifier() {
+        let mut order_book = NetworkOrderBook::new(SystemBus::new());
+        let spent_nullifier = Scalar::from(1u64);
+        let cluster: ClusterId = "cluster".parse().unwrap();
+
+        let local_order = NetworkOrder::new(Uuid::new_v4(), spent_nullifier, cluster.clone(), true);
+        let remote_order =
+            NetworkOrder::new(Uuid::new_v4(), spent_nullifier, cluster.clone(), false);
+        let unrelated_order = NetworkOrder::new(Uuid::new_v4(), Scalar::from(2u64), cluster, true);
+        for order in [&local_order, &remote_order, &unrelated_order] {
+            order_book.add_order(order.clone()).await;
+        }
+
+        let cancelled = order_book.cancel_orders_by_nullifier(spent_nullifier).await;
+        assert_eq!(cancelled, vec![local_order.id]);
+
+        for (order_id, expected_state) in [
+            (local_order.id, NetworkOrderState::Cancelled),
+            (remote_order.id, NetworkOrderState::Cancelled),
+            (unrelated_order.id, NetworkOrderState::Received),
+        ] {
+            let state = order_book.get_order_info(&order_id).await.unwrap().state;
+            assert_eq!(state, expected_state);
+        }
+        assert!(order_book
+            .get_orders_by_nullifier(spent_nullifier)
+            .await
+            .is_empty());
+    }
+
+    /// Tests that filters restrict the returned orders
+    #[tokio::test]
+    async fn test_filters() {
+        let order_book = build_order_book(10).await;
+
+        let cluster_filter = OrderBookFilter {
+            cluster: Some("odd".parse().unwrap()),
+            ..Default::default()
+        };
+        let (page, next_cursor) = order_book.get_orders_page(&cluster_filter, None, 100).await;
+        assert_eq!(page.len(), 5);
+        assert!(next_cursor.is_none());
+        assert!(page.iter().all(|order| !order.local));
+
+        let nullifier_filter = OrderBookFilter {
+            match_nullifier: Some(Scalar::from(4u64)),
+            local: Some(true),
+            ..Default::default()
+        };
+        let (page, _) = order_book
+            .get_orders_page(&nullifier_filter, None, 100)
+            .await;
+        assert_eq!(page.len(), 1);
+
+        let state_filter = OrderBookFilter {
+            state: Some(NetworkOrderState::Verified),
+            ..Default::default()
+        };
+        let (page, _) = order_book.get_orders_page(&state_filter, None, 100).await;
+        assert!(page.is_empty());
+    }
+
+    /// Tests that pruning a cluster prunes only its open, non-local orders
+    #[tokio::test]
+    async fn test_prune_cluster_orders() {
+        let mut order_book = build_order_book(6).await;
+        let odd_cluster: ClusterId = "odd".parse().unwrap();
+
+        // Match one of the odd cluster's orders, it should be left in place
+        let odd_orders = order_book
+            .get_order_owner_pairs()
+            .await
+            .into_iter()
+            .filter(|(_, cluster)| cluster == &odd_cluster)
+            .map(|(order_id, _)| order_id)
+            .collect_vec();
+        let matched_order = odd_orders[0];
+        order_book.write_order(&matched_order).await.unwrap().state = NetworkOrderState::Matched {
+            by_local_node: false,
+        };
+
+        let mut pruned = order_book.prune_cluster_orders(&odd_cluster).await;
+        let mut expected = odd_orders[1..].to_vec();
+        pruned.sort();
+        expected.sort();
+        assert_eq!(pruned, expected);
+
+        for order in order_book.get_order_book_snapshot().await.values() {
+            let expect_pruned = expected.contains(&order.id);
+            assert_eq!(order.state == NetworkOrderState::Pruned, expect_pruned);
+        }
+    }
+
+    /// Tests that the sweep cancels only open orders whose time in force has lapsed
+    #[tokio::test]
+    async fn test_cancel_expired_orders() {
+        let mut order_book = NetworkOrderBook::new(SystemBus::new());
+        let cluster: ClusterId = "cluster".parse().unwrap();
+        let order = |time_in_force| {
+            NetworkOrder::new(Uuid::new_v4(), Scalar::from(1u64), cluster.clone(), true)
+                .with_time_in_force(time_in_force)
+        };
+
+        let resting_order = order(TimeInForce::GoodTilCancelled);
+        let expired_order = order(TimeInForce::GoodTilDate(100));
+        let open_order = order(TimeInForce::GoodTilDate(200));
+        let ioc_order = order(TimeInForce::ImmediateOrCancel);
+        for order in [&resting_order, &expired_order, &open_order, &ioc_order] {
+            order_book.add_order(order.clone()).await;
+        }
+
+        // The immediate-or-cancel order lapses once an MPC on it has concluded
+        assert_eq!(
+            order_book.cancel_expired_orders(150).await,
+            vec![expired_order.id]
+        );
+        order_book.mark_mpc_attempted(&ioc_order.id).await;
+        assert_eq!(
+            order_book.cancel_expired_orders(150).await,
+            vec![ioc_order.id]
+        );
+
+        // Cancelled orders are not cancelled again
+        assert_eq!(
+            order_book.cancel_expired_orders(250).await,
+            vec![open_order.id]
+        );
+        assert_eq!(
+            order_book
+                .get_order_info(&resting_order.id)
+                .await
+                .unwrap()
+                .state,
+            NetworkOrderState::Received
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Groups type definitions for the peer authentication audit log
+//!
+//! Each connection to a remote peer is authenticated at several layers: the transport
+//! handshake authenticates the peer's libp2p identity, the identify protocol reports the
+//! peer's protocol version, and cluster auth signatures prove the peer's cluster membership.
+//! The audit log records the outcome of each of these checks so that operators may
+//! investigate suspicious connections after the fact
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Formatter, Result as FmtResult},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::log;
+
+use crate::gossip::types::WrappedPeerId;
+
+/// The maximum number of events retained in the audit log, older events are
+/// evicted first
+const MAX_AUDIT_LOG_EVENTS: usize = 1_000;
+/// The transport security protocol negotiated on all libp2p connections
+pub const NOISE_TRANSPORT_SECURITY: &str = "noise";
+
+/// The result of verifying a peer's cluster membership
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClusterAuthStatus {
+    /// The peer has not yet presented a cluster auth signature
+    Unverified,
+    /// The peer's cluster auth signature verified against its claimed cluster
+    Verified,
+    /// The peer presented a cluster auth signature that did not verify
+    Failed,
+}
+
+/// The authentication details of a connection to a single peer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerConnectionAuth {
+    /// The security protocol that authenticated the transport, e.g. noise
+    pub transport_security: String,
+    /// The remote address of the most recent connection to the peer
+    pub remote_addr: Option<String>,
+    /// Whether the peer currently holds an open connection to the local node
+    pub connected: bool,
+    /// The protocol version reported by the peer via identify
+    pub protocol_version: Option<String>,
+    /// The agent version reported by the peer via identify
+    pub agent_version: Option<String>,
+    /// Whether the public key the peer reported via identify hashes to its
+    /// transport-authenticated peer ID
+    pub identity_key_matches: Option<bool>,
+    /// The result of the most recent cluster membership verification
+    pub cluster_auth: ClusterAuthStatus,
+    /// The timestamp (in seconds) at which this record was last updated
+    pub last_updated: u64,
+}
+
+impl Default for PeerConnectionAuth {
+    fn default() -> Self {
+        Self {
+            transport_security: NOISE_TRANSPORT_SECURITY.to_string(),
+            remote_addr: None,
+            connected: false,
+            protocol_version: None,
+            agent_version: None,
+            identity_key_matches: None,
+            cluster_auth: ClusterAuthStatus::Unverified,
+            last_updated: 0,
+        }
+    }
+}
+
+/// An authentication-relevant event on a peer connection
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerAuthEventKind {
+    /// A connection was established and its transport authenticated
+    ConnectionEstablished {
+        /// The remote address of the connection
+        remote_addr: String,
+    },
+    /// All connections to the peer were closed
+    ConnectionClosed,
+    /// The peer reported its protocol information via identify
+    Identified {
+        /// The protocol version reported by the peer
+        protocol_version: String,
+        /// The agent version reported by the peer
+        agent_version: String,
+        /// Whether the reported public key matches the peer's ID
+        identity_key_matches: bool,
+    },
+    /// The peer reported a protocol version that differs from the local node's
+    ProtocolVersionMismatch {
+        /// The protocol version expected by the local node
+        expected: String,
+        /// The protocol version reported by the peer
+        reported: String,
+    },
+    /// The peer's cluster membership was verified
+    ClusterAuthVerified,
+    /// The peer's cluster membership could not be verified
+    ClusterAuthFailed,
+    /// The peer was banned for repeatedly exceeding its gossip rate limits
+    Banned {
+        /// The duration of the ban, in seconds
+        duration_secs: u64,
+    },
+}
+
+impl PeerAuthEventKind {
+    /// Whether the event is indicative of a suspicious connection
+    pub fn is_suspicious(&self) -> bool {
+        matches!(
+            self,
+            PeerAuthEventKind::ProtocolVersionMismatch { .. }
+                | PeerAuthEventKind::ClusterAuthFailed
+                | PeerAuthEventKind::Banned { .. }
+                | PeerAuthEventKind::Identified {
+                    identity_key_matches: false,
+                    ..
+                }
+        )
+    }
+}
+
+impl Display for PeerAuthEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            PeerAuthEventKind::ConnectionEstablished { remote_addr } => write!(
+                f,
+                "connection established via {} from {}",
+                NOISE_TRANSPORT_SECURITY, remote_addr
+            ),
+            PeerAuthEventKind::ConnectionClosed => write!(f, "connection closed"),
+            PeerAuthEventKind::Identified {
+                protocol_version,
+                agent_version,
+                identity_key_matches,
+            } => write!(
+                f,
+                "identified with protocol version {}, agent {}, key matches: {}",
+                protocol_version, agent_version, identity_key_matches
+            ),
+            PeerAuthEventKind::ProtocolVersionMismatch { expected, reported } => write!(
+                f,
+                "protocol version mismatch, expected {}, got {}",
+                expected, reported
+            ),
+            PeerAuthEventKind::ClusterAuthVerified => write!(f, "cluster auth verified"),
+            PeerAuthEventKind::ClusterAuthFailed => write!(f, "cluster auth failed"),
+            PeerAuthEventKind::Banned { duration_secs } => write!(
+                f,
+                "banned for {}s after exceeding gossip rate limits",
+                duration_secs
+            ),
+        }
+    }
+}
+
+/// A single entry in the audit log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerAuthEvent {
+    /// The timestamp (in seconds) at which the event was recorded
+    pub timestamp: u64,
+    /// The peer that the event pertains to
+    pub peer_id: WrappedPeerId,
+    /// The event itself
+    pub kind: PeerAuthEventKind,
+}
+
+/// The audit log of peer authentication events, along with the latest
+/// authentication details of each peer
+#[derive(Debug, Default)]
+pub struct PeerAuthAuditLog {
+    /// The latest authentication details for each peer
+    connections: HashMap<WrappedPeerId, PeerConnectionAuth>,
+    /// A bounded log of recent events, oldest first
+    events: VecDeque<PeerAuthEvent>,
+}
+
+impl PeerAuthAuditLog {
+    /// Create a new, empty audit log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event, updating the peer's authentication details
+    pub fn record_event(&mut self, peer_id: WrappedPeerId, kind: PeerAuthEventKind) {
+        let timestamp = current_time_seconds();
+        if kind.is_suspicious() {
+            log::warn!("peer auth audit: {}: {}", peer_id, kind);
+        } else {
+            log::info!("peer auth audit: {}: {}", peer_id, kind);
+        }
+
+        let auth = self.connections.entry(peer_id).or_default();
+        auth.last_updated = timestamp;
+        match &kind {
+            PeerAuthEventKind::ConnectionEstablished { remote_addr } => {
+                auth.connected = true;
+                auth.remote_addr = Some(remote_addr.clone());
+            }
+            PeerAuthEventKind::ConnectionClosed => auth.connected = false,
+            PeerAuthEventKind::Identified {
+                protocol_version,
+                agent_version,
+                identity_key_matches,
+            } => {
+                auth.protocol_version = Some(protocol_version.clone());
+                auth.agent_version = Some(agent_version.clone());
+                auth.identity_key_matches = Some(*identity_key_matches);
+            }
+            PeerAuthEventKind::ProtocolVersionMismatch { reported, .. } => {
+                auth.protocol_version = Some(reported.clone());
+            }
+            PeerAuthEventKind::ClusterAuthVerified => {
+                auth.cluster_auth = ClusterAuthStatus::Verified
+            }
+            PeerAuthEventKind::ClusterAuthFailed => auth.cluster_auth = ClusterAuthStatus::Failed,
+            PeerAuthEventKind::Banned { .. } => {}
+        }
+
+        self.events.push_back(PeerAuthEvent {
+            timestamp,
+            peer_id,
+            kind,
+        });
+        if self.events.len() > MAX_AUDIT_LOG_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    /// Get the latest authentication details for a peer
+    pub fn get_connection_auth(&self, peer_id: &WrappedPeerId) -> Option<PeerConnectionAuth> {
+        self.connections.get(peer_id).cloned()
+    }
+
+    /// Get the recorded events for a peer, oldest first
+    pub fn get_peer_events(&self, peer_id: &WrappedPeerId) -> Vec<PeerAuthEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.peer_id == *peer_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Get the current unix timestamp in seconds
+fn current_time_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("negative timestamp")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gossip::types::WrappedPeerId;
+
+    use super::{ClusterAuthStatus, PeerAuthAuditLog, PeerAuthEventKind, MAX_AUDIT_LOG_EVENTS};
+
+    /// Tests that events update the peer's authentication details
+    #[test]
+    fn test_record_events() {
+        let mut audit_log = PeerAuthAuditLog::new();
+        let peer_id = WrappedPeerId::random();
+
+        audit_log.record_event(
+            peer_id,
+            PeerAuthEventKind::ConnectionEstablished {
+                remote_addr: "/ip4/127.0.0.1/tcp/8000".to_string(),
+            },
+        );
+        audit_log.record_event(peer_id, PeerAuthEventKind::ClusterAuthFailed);
+
+        let auth = audit_log.get_connection_auth(&peer_id).unwrap();
+        assert!(auth.connected);
+        assert_eq!(auth.cluster_auth, ClusterAuthStatus::Failed);
+        assert_eq!(audit_log.get_peer_events(&peer_id).len(), 2);
+        assert!(audit_log
+            .get_connection_auth(&WrappedPeerId::random())
+            .is_none());
+    }
+
+    /// Tests that the log evicts its oldest events once full
+    #[test]
+    fn test_log_bounded() {
+        let mut audit_log = PeerAuthAuditLog::new();
+        let first_peer = WrappedPeerId::random();
+        audit_log.record_event(first_peer, PeerAuthEventKind::ConnectionClosed);
+
+        let second_peer = WrappedPeerId::random();
+        for _ in 0..MAX_AUDIT_LOG_EVENTS {
+            audit_log.record_event(second_peer, PeerAuthEventKind::ConnectionClosed);
+        }
+
+        assert!(audit_log.get_peer_events(&first_peer).is_empty());
+        assert_eq!(
+            audit_log.get_peer_events(&second_peer).len(),
+            MAX_AUDIT_LOG_EVENTS
+        );
+    }
+}
@@ -8,7 +8,7 @@ use std::{
 };
 use tokio::sync::{RwLockReadGuard, RwLockWriteGuard};
 
-use crate::gossip::types::{ClusterId, PeerInfo, WrappedPeerId};
+use crate::gossip::types::{ClusterId, PeerInfo, PeerLiveness, WrappedPeerId};
 
 use super::{new_async_shared, AsyncShared};
 
@@ -104,6 +104,20 @@ impl PeerIndex {
         }
     }
 
+    /// Returns whether every known peer in the given cluster is dead, or no peers in the
+    /// cluster remain indexed
+    pub async fn cluster_is_dead(&self, cluster_id: &ClusterId) -> bool {
+        for peer_id in self.get_all_cluster_peers(cluster_id).await.iter() {
+            if let Some(info) = self.read_peer(peer_id).await
+                && info.get_liveness() != PeerLiveness::Dead
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
     /// Returns a random cluster peer for the given cluster
     pub async fn sample_cluster_peer(&self, cluster_id: &ClusterId) -> Option<WrappedPeerId> {
         let cluster_peers = self.read_cluster_peers(cluster_id).await?;
@@ -177,10 +191,32 @@ impl PeerIndex {
         Some(entry)
     }
 
-    /// Record a successful heartbeat for a peer
+    /// Record a successful heartbeat for a peer, the peer is live again
     pub async fn record_heartbeat(&self, peer_id: &WrappedPeerId) {
-        if let Some(peer_info_guard) = self.write_peer(peer_id).await {
+        if let Some(mut peer_info_guard) = self.write_peer(peer_id).await {
             peer_info_guard.successful_heartbeat();
+            peer_info_guard.set_liveness(PeerLiveness::Live);
+        }
+    }
+
+    /// Set the liveness of a peer, returning its previous liveness if the peer is indexed
+    pub async fn set_liveness(
+        &self,
+        peer_id: &WrappedPeerId,
+        liveness: PeerLiveness,
+    ) -> Option<PeerLiveness> {
+        Some(self.write_peer(peer_id).await?.set_liveness(liveness))
+    }
+
+    /// Accept a heartbeat sequence number from a peer, returning false if the heartbeat
+    /// is a replay
+    ///
+    /// A peer that is not yet indexed has no sequence numbers to check against, its
+    /// heartbeats are accepted
+    pub async fn accept_heartbeat_sequence(&self, peer_id: &WrappedPeerId, sequence: u64) -> bool {
+        match self.write_peer(peer_id).await {
+            Some(mut peer_info_guard) => peer_info_guard.accept_heartbeat_sequence(sequence),
+            None => true,
         }
     }
 }
@@ -0,0 +1,131 @@
+//! Records the progress of recent match settlements, so that the transaction hash a
+//! match settled under may be looked up after the fact
+//!
+//! The log holds a bounded number of settlements; once full, the settlement least
+//! recently updated is evicted to make room
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::types::SettlementStatus;
+
+use super::OrderIdentifier;
+
+/// The number of settlements held in the log
+const MAX_SETTLEMENT_RECORDS: usize = 1_000;
+
+/// The latest status of a match settlement
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettlementRecord {
+    /// The request ID of the handshake that produced the match
+    pub request_id: Uuid,
+    /// The local order in the match
+    pub local_order_id: OrderIdentifier,
+    /// The counterparty's order in the match
+    pub peer_order_id: OrderIdentifier,
+    /// The stage the settlement has reached
+    pub status: SettlementStatus,
+    /// The time at which the status was recorded, in seconds since the epoch
+    pub updated_at: u64,
+}
+
+/// A bounded log of recent settlements, keyed by request ID
+#[derive(Clone, Debug, Default)]
+pub struct SettlementLog {
+    /// The latest record of each settlement
+    records: HashMap<Uuid, SettlementRecord>,
+    /// The request IDs in the log, least recently updated first
+    order: VecDeque<Uuid>,
+}
+
+impl SettlementLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The record of the given settlement, if it is still in the log
+    pub fn get(&self, request_id: &Uuid) -> Option<SettlementRecord> {
+        self.records.get(request_id).cloned()
+    }
+
+    /// Every record in the log, most recently updated first
+    pub fn records(&self) -> Vec<SettlementRecord> {
+        self.order
+            .iter()
+            .rev()
+            .filter_map(|request_id| self.records.get(request_id).cloned())
+            .collect()
+    }
+
+    /// Record the latest status of a settlement, evicting the least recently updated
+    /// settlement if the log is full
+    pub fn record(&mut self, record: SettlementRecord) {
+        let request_id = record.request_id;
+        if self.records.insert(request_id, record).is_some() {
+            self.order.retain(|id| id != &request_id);
+        } else if self.order.len() >= MAX_SETTLEMENT_RECORDS
+            && let Some(evicted) = self.order.pop_front()
+        {
+            self.records.remove(&evicted);
+        }
+
+        self.order.push_back(request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::types::SettlementStatus;
+
+    use super::{SettlementLog, SettlementRecord, MAX_SETTLEMENT_RECORDS};
+
+    /// Build a record of a settlement in the given status
+    fn record(request_id: Uuid, status: SettlementStatus) -> SettlementRecord {
+        SettlementRecord {
+            request_id,
+            local_order_id: Uuid::new_v4(),
+            peer_order_id: Uuid::new_v4(),
+            status,
+            updated_at: 0,
+        }
+    }
+
+    /// Tests that a settlement's record is replaced on update, and that the least recently
+    /// updated settlement is evicted once the log is full
+    #[test]
+    fn test_settlement_log_eviction() {
+        let mut log = SettlementLog::new();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        log.record(record(first, SettlementStatus::EstimatingFee));
+        log.record(record(second, SettlementStatus::EstimatingFee));
+        log.record(record(
+            first,
+            SettlementStatus::Submitted {
+                tx_hash: "0x1".to_string(),
+                estimated_fee: 10,
+            },
+        ));
+
+        let records = log.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].request_id, first);
+        assert!(matches!(
+            log.get(&first).unwrap().status,
+            SettlementStatus::Submitted { .. }
+        ));
+
+        // Fill the log, the second settlement is now the least recently updated
+        for _ in 0..MAX_SETTLEMENT_RECORDS - 1 {
+            log.record(record(Uuid::new_v4(), SettlementStatus::EstimatingFee));
+        }
+        assert!(log.get(&second).is_none());
+        assert!(log.get(&first).is_some());
+        assert_eq!(log.records().len(), MAX_SETTLEMENT_RECORDS);
+    }
+}
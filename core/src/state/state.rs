@@ -2,30 +2,52 @@
 //! is passed around throughout the code
 
 use crate::{
-    gossip::types::{ClusterId, PeerInfo, WrappedPeerId},
-    gossip_api::heartbeat::HeartbeatMessage,
-    proof_generation::jobs::ValidCommitmentsBundle,
+    gossip::{
+        reputation::PeerReputationTracker,
+        types::{ClusterId, PeerInfo, WrappedPeerId},
+    },
+    gossip_api::{heartbeat::HeartbeatMessage, orderbook_management::IndicationOfInterest},
+    proof_generation::{jobs::ValidCommitmentsBundle, proof_cache::ProofCache},
+    rng::WorkerRng,
     state::orderbook::NetworkOrder,
     system_bus::SystemBus,
     types::SystemBusMessage,
+    worker::WorkerStatus,
+};
+use circuits::types::{
+    order::{Order, TimeInForce},
+    wallet::Nullifier,
 };
-use circuits::types::wallet::Nullifier;
 use libp2p::{
     identity::{self, Keypair},
     Multiaddr,
 };
-use rand::{distributions::WeightedIndex, prelude::Distribution, thread_rng};
+use rand::{distributions::WeightedIndex, seq::SliceRandom, thread_rng};
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::{RwLock as AsyncRwLock, RwLockReadGuard, RwLockWriteGuard};
+use uuid::Uuid;
 
 use super::{
+    cluster_access::ClusterAccessPolicy,
+    feature_flags::{FeatureFlag, FeatureFlags},
+    leader::ClusterLeadership,
+    merkle::MerkleTreeMirror,
     orderbook::{NetworkOrderBook, OrderIdentifier},
+    peer_auth::{PeerAuthAuditLog, PeerAuthEvent, PeerAuthEventKind, PeerConnectionAuth},
     peers::PeerIndex,
     priority::HandshakePriorityStore,
-    wallet::{Wallet, WalletIndex},
+    settlements::{SettlementLog, SettlementRecord},
+    versions::{local_version, PeerVersionIndex, RELAYER_VERSION},
+    wallet::{OrderSlot, Wallet, WalletDelta, WalletDeltaError, WalletIdentifier, WalletIndex},
+    wallet_manager::{WalletLifecycle, WalletManager},
+    wallet_shards::WalletShardStore,
 };
 
 // -----------------------
@@ -55,6 +77,11 @@ pub struct RelayerState {
     pub local_peer_id: WrappedPeerId,
     /// The local libp2p keypair generated at startup
     pub local_keypair: Keypair,
+    /// The sequence number of the last heartbeat built by the local peer
+    ///
+    /// The local peer ID is generated at startup, so a restarted relayer never reuses
+    /// sequence numbers under the same ID
+    heartbeat_sequence: Arc<AtomicU64>,
     /// The cluster id of the local relayer
     pub local_cluster_id: ClusterId,
     /// The listening address of the local relayer
@@ -65,6 +92,10 @@ pub struct RelayerState {
     pub local_addr: AsyncShared<Multiaddr>,
     /// The list of wallets managed by the sending relayer
     wallet_index: AsyncShared<WalletIndex>,
+    /// The lifecycle of each managed wallet and the queue of tasks that mutate it
+    wallet_manager: WalletManager,
+    /// The erasure-coded wallet shards held by the local peer
+    wallet_shards: AsyncShared<WalletShardStore>,
     /// The set of peers known to the sending relayer
     peer_index: AsyncShared<PeerIndex>,
     /// The order book and indexing structure for orders in the network
@@ -74,6 +105,35 @@ pub struct RelayerState {
     matched_order_pairs: AsyncShared<Vec<(OrderIdentifier, OrderIdentifier)>>,
     /// Priorities for scheduling handshakes with each peer
     pub handshake_priorities: AsyncShared<HandshakePriorityStore>,
+    /// The reputation of each peer the local node has interacted with
+    peer_reputation: AsyncShared<PeerReputationTracker>,
+    /// The software version each known peer reports
+    peer_versions: AsyncShared<PeerVersionIndex>,
+    /// The operator's policy on which counterparty clusters to handshake with
+    cluster_access: AsyncShared<ClusterAccessPolicy>,
+    /// The elected leader of the local cluster
+    cluster_leadership: AsyncShared<ClusterLeadership>,
+    /// A mirror of the contract's commitment tree, maintained by the on-chain event listener
+    merkle_tree: AsyncShared<MerkleTreeMirror>,
+    /// The audit log of authentication events on peer connections
+    ///
+    /// This is recorded to from within the network manager's synchronous handlers,
+    /// so it is held behind a blocking lock
+    peer_auth_log: Shared<PeerAuthAuditLog>,
+    /// Whether the relayer is draining ahead of a shutdown; a draining relayer
+    /// finishes its in-flight MPCs but begins no new handshakes
+    draining: Arc<AtomicBool>,
+    /// The number of match MPCs currently executing on the local node
+    in_flight_mpcs: Arc<AtomicUsize>,
+    /// The cache of proofs of `VALID COMMITMENTS`, shared with the proof manager
+    pub(crate) proof_cache: ProofCache,
+    /// The feature flags in effect, toggleable through the admin API
+    feature_flags: FeatureFlags,
+    /// The status of each worker that has failed since startup, as recorded by the
+    /// coordinator; workers that have never failed are absent
+    worker_statuses: Shared<BTreeMap<String, WorkerStatus>>,
+    /// The progress of recent match settlements, recorded by the handshake manager
+    settlements: Shared<SettlementLog>,
 }
 
 impl RelayerState {
@@ -82,21 +142,30 @@ impl RelayerState {
         debug: bool,
         wallets: Vec<Wallet>,
         cluster_id: ClusterId,
+        cluster_access: ClusterAccessPolicy,
         system_bus: SystemBus<SystemBusMessage>,
+        proof_cache: ProofCache,
+        feature_flags: FeatureFlags,
     ) -> Self {
         // Generate an keypair on curve 25519 for the local peer
         let local_keypair = identity::Keypair::generate_ed25519();
         let local_peer_id = WrappedPeerId(local_keypair.public().to_peer_id());
 
-        // Setup initial wallets
+        // Setup initial wallets, each syncs as its order proofs are warmed up
         let mut wallet_index = WalletIndex::new(local_peer_id);
+        let wallet_manager = WalletManager::new();
         for wallet in wallets.into_iter() {
+            wallet_manager.register(wallet.wallet_id, WalletLifecycle::Syncing);
             wallet_index.add_wallet(wallet);
         }
 
         // Setup the peer index
         let peer_index = PeerIndex::new();
 
+        // Setup the version index, the local peer runs the local version
+        let mut peer_versions = PeerVersionIndex::new();
+        peer_versions.record_version(local_peer_id, local_version());
+
         // Setup the order book
         let order_book = NetworkOrderBook::new(system_bus);
 
@@ -104,13 +173,28 @@ impl RelayerState {
             debug,
             local_peer_id,
             local_keypair,
+            heartbeat_sequence: Arc::new(AtomicU64::new(0)),
             local_cluster_id: cluster_id,
             local_addr: new_async_shared(Multiaddr::empty()),
             wallet_index: new_async_shared(wallet_index),
+            wallet_manager,
+            wallet_shards: new_async_shared(WalletShardStore::new()),
             matched_order_pairs: new_async_shared(vec![]),
             peer_index: new_async_shared(peer_index),
             order_book: new_async_shared(order_book),
             handshake_priorities: new_async_shared(HandshakePriorityStore::new()),
+            peer_reputation: new_async_shared(PeerReputationTracker::new()),
+            peer_versions: new_async_shared(peer_versions),
+            cluster_access: new_async_shared(cluster_access),
+            cluster_leadership: new_async_shared(ClusterLeadership::new()),
+            merkle_tree: new_async_shared(MerkleTreeMirror::new()),
+            peer_auth_log: Arc::new(RwLock::new(PeerAuthAuditLog::new())),
+            draining: Arc::new(AtomicBool::new(false)),
+            in_flight_mpcs: Arc::new(AtomicUsize::new(0)),
+            proof_cache,
+            feature_flags,
+            worker_statuses: Arc::new(RwLock::new(BTreeMap::new())),
+            settlements: Arc::new(RwLock::new(SettlementLog::new())),
         }
     }
 
@@ -118,6 +202,11 @@ impl RelayerState {
     // | Getters |
     // -----------
 
+    /// Get the manager of the local wallets' lifecycles and task queues
+    pub fn wallet_manager(&self) -> &WalletManager {
+        &self.wallet_manager
+    }
+
     /// Get the local peer's ID
     pub fn local_peer_id(&self) -> WrappedPeerId {
         self.local_peer_id
@@ -132,15 +221,81 @@ impl RelayerState {
             .unwrap()
     }
 
+    /// Get the latest authentication details recorded for a peer's connection
+    pub fn get_peer_connection_auth(&self, peer_id: &WrappedPeerId) -> Option<PeerConnectionAuth> {
+        self.peer_auth_log
+            .read()
+            .unwrap()
+            .get_connection_auth(peer_id)
+    }
+
+    /// Get the authentication events recorded in the audit log for a peer
+    pub fn get_peer_auth_events(&self, peer_id: &WrappedPeerId) -> Vec<PeerAuthEvent> {
+        self.peer_auth_log.read().unwrap().get_peer_events(peer_id)
+    }
+
+    /// Whether the relayer is draining ahead of a shutdown
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Whether the given feature is enabled
+    pub fn is_feature_enabled(&self, flag: FeatureFlag) -> bool {
+        self.feature_flags.is_enabled(flag)
+    }
+
+    /// The feature flags in effect
+    pub fn feature_flags(&self) -> &FeatureFlags {
+        &self.feature_flags
+    }
+
+    /// The status of each worker that has failed since startup
+    pub fn worker_statuses(&self) -> BTreeMap<String, WorkerStatus> {
+        self.worker_statuses.read().unwrap().clone()
+    }
+
+    /// The records of recent match settlements, most recently updated first
+    pub fn settlement_records(&self) -> Vec<SettlementRecord> {
+        self.settlements.read().unwrap().records()
+    }
+
+    /// The record of the given match settlement, if it is recent enough to be held
+    pub fn get_settlement_record(&self, request_id: &Uuid) -> Option<SettlementRecord> {
+        self.settlements.read().unwrap().get(request_id)
+    }
+
+    /// The number of match MPCs currently executing on the local node
+    pub fn num_in_flight_mpcs(&self) -> usize {
+        self.in_flight_mpcs.load(Ordering::Relaxed)
+    }
+
     /// Sample an order for handshake
-    pub async fn choose_handshake_order(&self) -> Option<OrderIdentifier> {
+    ///
+    /// Orders managed by clusters that the cluster access policy does not permit, and
+    /// orders whose time in force has lapsed at `now` (a unix timestamp in seconds), are
+    /// never sampled
+    pub async fn choose_handshake_order(
+        &self,
+        rng: &WorkerRng,
+        now: u64,
+    ) -> Option<OrderIdentifier> {
         // Read the set of orders that are verified and thereby ready for batch
         let verified_orders = {
-            self.read_order_book()
-                .await
-                .get_nonlocal_verified_orders()
-                .await
-        };
+            let locked_order_book = self.read_order_book().await;
+            let locked_cluster_access = self.read_cluster_access().await;
+
+            let mut permitted_orders = Vec::new();
+            for order_id in locked_order_book.get_nonlocal_verified_orders().await {
+                if let Some(order_info) = locked_order_book.get_order_info(&order_id).await
+                    && locked_cluster_access.is_permitted(&order_info.cluster)
+                    && !order_info.is_expired(now)
+                {
+                    permitted_orders.push(order_id);
+                }
+            }
+
+            permitted_orders
+        }; // locked_order_book, locked_cluster_access released
         if verified_orders.is_empty() {
             return None;
         }
@@ -156,13 +311,76 @@ impl RelayerState {
         } // locked_priority_store released
 
         // Sample a random priority-weighted order from the result
-        let mut rng = thread_rng();
         let distribution = WeightedIndex::new(&priorities).unwrap();
-        Some(*verified_orders.get(distribution.sample(&mut rng)).unwrap())
+        Some(*verified_orders.get(rng.sample(&distribution)).unwrap())
+    }
+
+    /// Describe the orders occupying a local wallet's order slots, oldest first; `None` if
+    /// the wallet is not managed locally
+    pub async fn get_order_slots(&self, wallet_id: &WalletIdentifier) -> Option<Vec<OrderSlot>> {
+        let wallet = self.read_wallet_index().await.get_wallet(wallet_id).await?;
+        let locked_order_book = self.read_order_book().await;
+
+        let mut slots = Vec::with_capacity(wallet.orders.len());
+        for (order_id, order) in wallet.orders.iter() {
+            let state = locked_order_book
+                .read_order(order_id)
+                .await
+                .map(|order_info| order_info.state);
+            let auto_resubmit = wallet.metadata.auto_resubmit.contains_key(order_id);
+            slots.push(OrderSlot::new(*order_id, order, state, auto_resubmit));
+        }
+
+        slots.sort_by_key(|slot| slot.timestamp);
+        Some(slots)
+    }
+
+    /// Get a locally managed order from its wallet
+    pub async fn get_local_order(&self, order_id: &OrderIdentifier) -> Option<Order> {
+        let locked_wallet_index = self.read_wallet_index().await;
+        let wallet_id = locked_wallet_index.get_wallet_for_order(order_id)?;
+        let locked_wallet = locked_wallet_index.read_wallet(&wallet_id).await?;
+        locked_wallet.orders.get(order_id).cloned()
+    }
+
+    /// Build the indication of interest in a locally managed order from its wallet
+    pub async fn get_local_order_ioi(
+        &self,
+        order_id: &OrderIdentifier,
+    ) -> Option<IndicationOfInterest> {
+        self.get_local_order(order_id)
+            .await
+            .as_ref()
+            .map(IndicationOfInterest::from_order)
+    }
+
+    /// The indication of interest to publish alongside a local order's validity proof;
+    /// `None` unless IoI publication is enabled
+    pub async fn get_publishable_ioi(
+        &self,
+        order_id: &OrderIdentifier,
+    ) -> Option<IndicationOfInterest> {
+        if !self.is_feature_enabled(FeatureFlag::PublishIois) {
+            return None;
+        }
+
+        self.get_local_order_ioi(order_id).await
+    }
+
+    /// The time in force of an order in the book, good 'til cancelled if the order is unknown
+    pub async fn get_order_time_in_force(&self, order_id: &OrderIdentifier) -> TimeInForce {
+        self.read_order_book()
+            .await
+            .get_order_info(order_id)
+            .await
+            .map(|order| order.time_in_force)
+            .unwrap_or_default()
     }
 
     /// Get a peer in the cluster that manages the given order, used to dial during
     /// handshake scheduling
+    ///
+    /// Peers whose reputation has fallen below the counterparty threshold are not chosen
     pub async fn get_peer_managing_order(
         &self,
         order_id: &OrderIdentifier,
@@ -176,11 +394,86 @@ impl RelayerState {
                 .cluster
         };
 
-        // Get a peer in this cluster
-        self.read_peer_index()
+        // Get a reputable peer in this cluster
+        let cluster_peers = self
+            .read_peer_index()
+            .await
+            .get_all_cluster_peers(&managing_cluster)
+            .await;
+        let eligible_peers = {
+            let locked_reputation = self.read_peer_reputation().await;
+            cluster_peers
+                .into_iter()
+                .filter(|peer_id| locked_reputation.is_eligible_counterparty(peer_id))
+                .collect::<Vec<_>>()
+        }; // locked_reputation released
+
+        eligible_peers.choose(&mut thread_rng()).cloned()
+    }
+
+    /// Whether the local peer should act as the cluster leader
+    ///
+    /// Before any leader is known, every peer acts as leader so that a lone
+    /// peer, or a cluster mid-election, does not stall
+    pub async fn is_local_cluster_leader(&self) -> bool {
+        self.read_cluster_leadership()
+            .await
+            .leader()
+            .map_or(true, |leader| leader == self.local_peer_id)
+    }
+
+    // ------------------------
+    // | Lifecycle Management |
+    // ------------------------
+
+    /// Place the relayer into the draining state, no new handshakes are begun after this
+    pub fn begin_draining(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Record the status of a worker as seen by the coordinator
+    pub fn set_worker_status(&self, worker: String, status: WorkerStatus) {
+        self.worker_statuses.write().unwrap().insert(worker, status);
+    }
+
+    /// Record the latest status of a match settlement
+    pub fn record_settlement(&self, record: SettlementRecord) {
+        self.settlements.write().unwrap().record(record);
+    }
+
+    /// Record the start of a match MPC
+    pub fn mpc_started(&self) {
+        self.in_flight_mpcs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the end of a match MPC, whether it succeeded or not
+    pub fn mpc_finished(&self) {
+        self.in_flight_mpcs.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // ----------------------
+    // | Leadership Setters |
+    // ----------------------
+
+    /// Begin a new election term, electing the live cluster peer with the lowest peer ID
+    ///
+    /// Returns the elected leader and the new term, to be announced to the cluster
+    pub async fn elect_cluster_leader(&self) -> (WrappedPeerId, u64) {
+        let candidate = self
+            .read_peer_index()
             .await
-            .sample_cluster_peer(&managing_cluster)
+            .get_all_cluster_peers(&self.local_cluster_id)
             .await
+            .into_iter()
+            .chain(std::iter::once(self.local_peer_id))
+            .min()
+            .unwrap();
+
+        let term = self
+            .write_cluster_leadership()
+            .await
+            .start_election(candidate);
+        (candidate, term)
     }
 
     // ----------------------
@@ -202,13 +495,25 @@ impl RelayerState {
         let mut locked_peer_index = self.write_peer_index().await;
         for peer in peer_ids.iter() {
             // Skip this peer if peer info wasn't sent, or if their cluster auth signature doesn't verify
-            if let Some(info) = peer_info.get(peer) && info.verify_cluster_auth_sig().is_ok() {
-                // Record a dummy heartbeat to setup the initial state
-                info.successful_heartbeat();
-                locked_peer_index.add_peer(info.clone()).await
-            } else {
+            let info = match peer_info.get(peer) {
+                Some(info) => info,
+                None => continue,
+            };
+
+            if info.verify_cluster_auth_sig().is_err() {
+                self.record_peer_auth_event(*peer, PeerAuthEventKind::ClusterAuthFailed);
                 continue;
             }
+
+            // Only record successful verifications for newly indexed peers, heartbeats
+            // re-send the full peer list and would otherwise flood the audit log
+            if !locked_peer_index.contains_peer(peer) {
+                self.record_peer_auth_event(*peer, PeerAuthEventKind::ClusterAuthVerified);
+            }
+
+            // Record a dummy heartbeat to setup the initial state
+            info.successful_heartbeat();
+            locked_peer_index.add_peer(info.clone()).await
         }
     }
 
@@ -221,6 +526,24 @@ impl RelayerState {
             .await
             .remove_peer_replicas(peer)
             .await;
+        // Drop the expired peer's version from the version distribution
+        self.write_peer_versions().await.remove_peer(peer);
+    }
+
+    /// Prune the open, non-local orders of a cluster that is no longer contactable
+    pub async fn prune_cluster_orders(&self, cluster_id: &ClusterId) -> Vec<OrderIdentifier> {
+        self.write_order_book()
+            .await
+            .prune_cluster_orders(cluster_id)
+            .await
+    }
+
+    /// Record an authentication event on a peer connection to the audit log
+    pub fn record_peer_auth_event(&self, peer_id: WrappedPeerId, kind: PeerAuthEventKind) {
+        self.peer_auth_log
+            .write()
+            .unwrap()
+            .record_event(peer_id, kind)
     }
 
     // ----------------------
@@ -248,13 +571,41 @@ impl RelayerState {
             .await
     }
 
-    /// Nullify all orders with a given nullifier
-    pub async fn nullify_orders(&self, nullifier: Nullifier) {
-        let mut locked_order_book = self.write_order_book().await;
-        let orders_to_nullify = locked_order_book.get_orders_by_nullifier(nullifier).await;
-        for order_id in orders_to_nullify.into_iter() {
-            locked_order_book.transition_cancelled(&order_id).await;
+    /// Cancel all orders with a given nullifier, returning the IDs of the locally
+    /// managed wallets whose orders were cancelled
+    pub async fn nullify_orders(&self, nullifier: Nullifier) -> Vec<WalletIdentifier> {
+        let cancelled_local_orders = self
+            .write_order_book()
+            .await
+            .cancel_orders_by_nullifier(nullifier)
+            .await;
+
+        let locked_wallet_index = self.read_wallet_index().await;
+        cancelled_local_orders
+            .iter()
+            .filter_map(|order_id| locked_wallet_index.get_wallet_for_order(order_id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// The locally managed wallets whose current version is nullified by the given match
+    /// nullifier
+    pub async fn get_wallets_by_match_nullifier(
+        &self,
+        nullifier: Nullifier,
+    ) -> Vec<WalletIdentifier> {
+        let locked_wallet_index = self.read_wallet_index().await;
+        let mut wallet_ids = Vec::new();
+        for wallet_id in locked_wallet_index.get_all_wallet_ids() {
+            if let Some(wallet) = locked_wallet_index.get_wallet(&wallet_id).await
+                && wallet.get_match_nullifier() == nullifier
+            {
+                wallet_ids.push(wallet_id);
+            }
         }
+
+        wallet_ids
     }
 
     // ------------------------
@@ -267,26 +618,99 @@ impl RelayerState {
     /// wallet is given in plaintext
     ///
     /// This may happen at startup when a relayer advertises its presence to
-    /// cluster peers; or when a new wallet is created on a remote cluster peer
+    /// cluster peers; or when a new wallet is created on a remote cluster peer.
+    /// Each wallet is registered as ready, a fresh copy of a frozen wallet thaws it
     pub async fn add_wallets(&self, wallets: Vec<Wallet>) {
         let mut locked_wallet_index = self.write_wallet_index().await;
         let mut locked_order_book = self.write_order_book().await;
 
         for wallet in wallets.into_iter() {
             let wallet_match_nullifier = wallet.get_match_nullifier();
+            self.wallet_manager
+                .register(wallet.wallet_id, WalletLifecycle::Ready);
             locked_wallet_index.add_wallet(wallet.clone());
 
-            for order_id in wallet.orders.into_keys() {
+            for (order_id, order) in wallet.orders.into_iter() {
                 locked_order_book
-                    .add_order(NetworkOrder::new(
-                        order_id,
+                    .add_order(
+                        NetworkOrder::new(
+                            order_id,
+                            wallet_match_nullifier,
+                            self.local_cluster_id.clone(),
+                            true, /* local */
+                        )
+                        .with_time_in_force(order.time_in_force),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    /// Apply deltas received from a cluster peer to repair the local replica of a wallet
+    ///
+    /// Orders the deltas add to the wallet are indexed in the order book as local orders,
+    /// and orders the deltas remove are cancelled. Returns the IDs of the added orders
+    pub async fn apply_wallet_deltas(
+        &self,
+        wallet_id: &WalletIdentifier,
+        deltas: Vec<WalletDelta>,
+    ) -> Result<Vec<OrderIdentifier>, WalletDeltaError> {
+        let mut locked_wallet_index = self.write_wallet_index().await;
+        let mut locked_order_book = self.write_order_book().await;
+
+        let (added_orders, removed_orders) =
+            locked_wallet_index.apply_deltas(wallet_id, deltas).await?;
+        let wallet = locked_wallet_index
+            .read_wallet(wallet_id)
+            .await
+            .ok_or(WalletDeltaError::MissingWallet(*wallet_id))?;
+        let wallet_match_nullifier = wallet.get_match_nullifier();
+
+        for order_id in removed_orders.iter() {
+            locked_order_book.transition_cancelled(order_id).await;
+        }
+        for order_id in added_orders.iter() {
+            let time_in_force = wallet
+                .orders
+                .get(order_id)
+                .map(|order| order.time_in_force)
+                .unwrap_or_default();
+            locked_order_book
+                .add_order(
+                    NetworkOrder::new(
+                        *order_id,
                         wallet_match_nullifier,
                         self.local_cluster_id.clone(),
                         true, /* local */
-                    ))
-                    .await;
-            }
+                    )
+                    .with_time_in_force(time_in_force),
+                )
+                .await;
         }
+
+        Ok(added_orders)
+    }
+
+    /// Regenerate the auto-resubmit orders that a wallet update has removed from a
+    /// local wallet, returning the IDs of the regenerated orders
+    ///
+    /// Orders removed because the local node matched them are filled rather than
+    /// cancelled, so they are not resubmitted
+    pub async fn resubmit_cancelled_orders(
+        &self,
+        wallet_id: &WalletIdentifier,
+    ) -> Vec<OrderIdentifier> {
+        let matched_orders: HashSet<OrderIdentifier> = self
+            .read_matched_order_pairs()
+            .await
+            .iter()
+            .flat_map(|(o1, o2)| [*o1, *o2])
+            .collect();
+
+        self.write_wallet_index()
+            .await
+            .resubmit_missing_orders(wallet_id, &matched_orders)
+            .await
     }
 
     /// Mark an order pair as matched, this is both for bookkeeping and for
@@ -320,6 +744,16 @@ impl RelayerState {
         self.wallet_index.write().await
     }
 
+    /// Acquire a read lock on `wallet_shards`
+    pub async fn read_wallet_shards(&self) -> RwLockReadGuard<WalletShardStore> {
+        self.wallet_shards.read().await
+    }
+
+    /// Acquire a write lock on `wallet_shards`
+    pub async fn write_wallet_shards(&self) -> RwLockWriteGuard<WalletShardStore> {
+        self.wallet_shards.write().await
+    }
+
     /// Acquire a read lock on `known_peers`
     pub async fn read_peer_index(&self) -> RwLockReadGuard<PeerIndex> {
         self.peer_index.read().await
@@ -341,7 +775,6 @@ impl RelayerState {
     }
 
     /// Acquire a read lock on `matched_order_pairs`
-    #[allow(unused)]
     pub async fn read_matched_order_pairs(
         &self,
     ) -> RwLockReadGuard<Vec<(OrderIdentifier, OrderIdentifier)>> {
@@ -355,6 +788,56 @@ impl RelayerState {
         self.matched_order_pairs.write().await
     }
 
+    /// Acquire a read lock on `cluster_leadership`
+    pub async fn read_cluster_leadership(&self) -> RwLockReadGuard<ClusterLeadership> {
+        self.cluster_leadership.read().await
+    }
+
+    /// Acquire a write lock on `cluster_leadership`
+    pub async fn write_cluster_leadership(&self) -> RwLockWriteGuard<ClusterLeadership> {
+        self.cluster_leadership.write().await
+    }
+
+    /// Acquire a read lock on `merkle_tree`
+    pub async fn read_merkle_tree(&self) -> RwLockReadGuard<MerkleTreeMirror> {
+        self.merkle_tree.read().await
+    }
+
+    /// Acquire a write lock on `merkle_tree`
+    pub async fn write_merkle_tree(&self) -> RwLockWriteGuard<MerkleTreeMirror> {
+        self.merkle_tree.write().await
+    }
+
+    /// Acquire a read lock on `peer_reputation`
+    pub async fn read_peer_reputation(&self) -> RwLockReadGuard<PeerReputationTracker> {
+        self.peer_reputation.read().await
+    }
+
+    /// Acquire a write lock on `peer_reputation`
+    pub async fn write_peer_reputation(&self) -> RwLockWriteGuard<PeerReputationTracker> {
+        self.peer_reputation.write().await
+    }
+
+    /// Acquire a read lock on `peer_versions`
+    pub async fn read_peer_versions(&self) -> RwLockReadGuard<PeerVersionIndex> {
+        self.peer_versions.read().await
+    }
+
+    /// Acquire a write lock on `peer_versions`
+    pub async fn write_peer_versions(&self) -> RwLockWriteGuard<PeerVersionIndex> {
+        self.peer_versions.write().await
+    }
+
+    /// Acquire a read lock on `cluster_access`
+    pub async fn read_cluster_access(&self) -> RwLockReadGuard<ClusterAccessPolicy> {
+        self.cluster_access.read().await
+    }
+
+    /// Acquire a write lock on `cluster_access`
+    pub async fn write_cluster_access(&self) -> RwLockWriteGuard<ClusterAccessPolicy> {
+        self.cluster_access.write().await
+    }
+
     /// Acquire a read lock on `handshake_priorities`
     pub async fn read_handshake_priorities(&self) -> RwLockReadGuard<HandshakePriorityStore> {
         self.handshake_priorities.read().await
@@ -383,10 +866,25 @@ impl RelayerState {
         // Get a list of all orders in the book
         let order_info = self.read_order_book().await.get_order_owner_pairs().await;
 
-        HeartbeatMessage {
+        let mut message = HeartbeatMessage {
             managed_wallets: wallet_info,
             known_peers: peer_info,
             orders: order_info,
-        }
+            version: RELAYER_VERSION.to_string(),
+            sequence: 0,
+            timestamp: 0,
+            public_key: Vec::new(),
+            signature: Vec::new(),
+        };
+
+        // Stamp the heartbeat so that recipients may reject replays of it
+        let sequence = self.heartbeat_sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("negative timestamp")
+            .as_millis() as u64;
+        message.sign(sequence, timestamp, &self.local_keypair);
+
+        message
     }
 }
@@ -0,0 +1,137 @@
+//! Tracks the software versions that peers report in their heartbeats
+//!
+//! A peer's version is taken only from heartbeats the peer itself signed, never from the
+//! peer lists other peers gossip, so that no peer may misreport another's version
+
+use std::collections::{BTreeMap, HashMap};
+
+use semver::Version;
+
+use crate::gossip::types::WrappedPeerId;
+
+/// The semantic version of the local relayer, as reported in its heartbeats
+pub const RELAYER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The parsed semantic version of the local relayer
+pub fn local_version() -> Version {
+    Version::parse(RELAYER_VERSION).expect("crate version is a valid semantic version")
+}
+
+/// The number of minor versions that `local` lags behind `other`
+///
+/// Any lag in major version is unbounded, a major upgrade is never a small step
+pub fn minor_versions_behind(local: &Version, other: &Version) -> u64 {
+    if other.major > local.major {
+        u64::MAX
+    } else if other.major < local.major {
+        0
+    } else {
+        other.minor.saturating_sub(local.minor)
+    }
+}
+
+/// An index of the software version each known peer last reported
+#[derive(Debug, Default)]
+pub struct PeerVersionIndex {
+    /// The version reported by each peer
+    versions: HashMap<WrappedPeerId, Version>,
+    /// The majority version that the local relayer was last warned of lagging behind,
+    /// used to warn once per majority rather than once per heartbeat
+    warned_majority: Option<Version>,
+}
+
+impl PeerVersionIndex {
+    /// Constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the version a peer reported
+    pub fn record_version(&mut self, peer_id: WrappedPeerId, version: Version) {
+        self.versions.insert(peer_id, version);
+    }
+
+    /// Remove an expired peer from the index
+    pub fn remove_peer(&mut self, peer_id: &WrappedPeerId) {
+        self.versions.remove(peer_id);
+    }
+
+    /// Get the version a peer last reported
+    pub fn get_version(&self, peer_id: &WrappedPeerId) -> Option<Version> {
+        self.versions.get(peer_id).cloned()
+    }
+
+    /// The number of known peers running each version
+    pub fn distribution(&self) -> BTreeMap<Version, usize> {
+        let mut distribution = BTreeMap::new();
+        for version in self.versions.values() {
+            *distribution.entry(version.clone()).or_insert(0) += 1;
+        }
+
+        distribution
+    }
+
+    /// The version most commonly run by the given peers, ties broken toward the newer
+    /// version
+    ///
+    /// Peers that have not reported a version are ignored
+    pub fn majority_version(&self, peers: &[WrappedPeerId]) -> Option<Version> {
+        let mut counts: BTreeMap<&Version, usize> = BTreeMap::new();
+        for version in peers.iter().filter_map(|peer| self.versions.get(peer)) {
+            *counts.entry(version).or_insert(0) += 1;
+        }
+
+        // `max_by_key` returns the last maximum, i.e. the newest of the tied versions
+        counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(version, _)| version.clone())
+    }
+
+    /// Mark that the local relayer has been warned of lagging behind the given majority
+    /// version, returns false if it already had been
+    pub fn mark_warned(&mut self, majority: &Version) -> bool {
+        if self.warned_majority.as_ref() == Some(majority) {
+            return false;
+        }
+
+        self.warned_majority = Some(majority.clone());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use crate::gossip::types::WrappedPeerId;
+
+    use super::{minor_versions_behind, PeerVersionIndex};
+
+    /// Tests the majority version of a set of peers, and the lag measured against it
+    #[test]
+    fn test_majority_version() {
+        let mut index = PeerVersionIndex::new();
+        let peers = (0..5).map(|_| WrappedPeerId::random()).collect::<Vec<_>>();
+        let old = Version::new(0, 1, 0);
+        let new = Version::new(0, 3, 2);
+
+        index.record_version(peers[0], old.clone());
+        index.record_version(peers[1], new.clone());
+        assert_eq!(index.majority_version(&peers), Some(new.clone()));
+
+        index.record_version(peers[2], old.clone());
+        assert_eq!(index.majority_version(&peers), Some(old.clone()));
+        assert_eq!(index.distribution().get(&old), Some(&2));
+
+        // Only the given peers are counted
+        assert_eq!(index.majority_version(&peers[1..2]), Some(new.clone()));
+
+        assert_eq!(minor_versions_behind(&old, &new), 2);
+        assert_eq!(minor_versions_behind(&new, &old), 0);
+        assert_eq!(
+            minor_versions_behind(&new, &Version::new(1, 0, 0)),
+            u64::MAX
+        );
+    }
+}
@@ -1,9 +1,9 @@
 //! Groups state primitives for indexing and tracking wallet information
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     convert::TryInto,
-    fmt::{Formatter, Result as FmtResult},
+    fmt::{Display, Formatter, Result as FmtResult},
     iter,
     str::FromStr,
     sync::atomic::{AtomicU32, Ordering},
@@ -12,6 +12,7 @@ use std::{
 use circuits::{
     native_helpers::{
         compute_poseidon_hash, compute_wallet_commitment, compute_wallet_match_nullifier,
+        compute_wallet_spend_nullifier,
     },
     types::{
         balance::Balance,
@@ -40,8 +41,15 @@ use crate::{
     MERKLE_ROOT_HISTORY_LENGTH,
 };
 
-use super::{new_async_shared, orderbook::OrderIdentifier, AsyncShared, MerkleTreeCoords};
+use super::{
+    new_async_shared,
+    orderbook::{NetworkOrderState, OrderIdentifier},
+    AsyncShared, MerkleTreeCoords,
+};
 
+/// The number of deltas retained per wallet for replica repair; peers further behind
+/// than this are repaired by re-replicating the whole wallet
+const MAX_WALLET_DELTAS: usize = 100;
 /// The staleness factor; the ratio of the root history that has elapsed before a new proof of
 /// `VALID COMMITMENTS` is required for an order
 const ROOT_HISTORY_STALENESS_FACTOR: f32 = 0.75;
@@ -375,6 +383,15 @@ impl Wallet {
         ))
     }
 
+    /// Computes the spend nullifier of the wallet
+    pub fn get_spend_nullifier(&self) -> Nullifier {
+        let circuit_wallet: SizedCircuitWallet = self.clone().into();
+        prime_field_to_scalar(&compute_wallet_spend_nullifier(
+            &circuit_wallet,
+            compute_wallet_commitment(&circuit_wallet),
+        ))
+    }
+
     /// Decides whether the wallet's orders need new commitment proofs
     ///
     /// When the Merkle roots get too stale, we need to re-prove the
@@ -394,6 +411,168 @@ impl Wallet {
 pub struct WalletMetadata {
     /// The peers which are believed by the local node to be replicating a given wallet
     pub replicas: HashSet<WrappedPeerId>,
+    /// The version of the wallet's contents, incremented by each applied delta
+    ///
+    /// Cluster peers compare versions in heartbeats to detect a stale replica
+    #[serde(default)]
+    pub version: u64,
+    /// The orders the user has opted into automatic resubmission, each with the order as
+    /// it stood when the user opted in
+    #[serde(default)]
+    pub auto_resubmit: HashMap<OrderIdentifier, Order>,
+}
+
+/// A single versioned update to the contents of a wallet
+///
+/// Deltas are exchanged between cluster peers to repair a replica that has fallen behind,
+/// without re-replicating the whole wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalletDelta {
+    /// The version of the wallet after the delta is applied
+    pub version: u64,
+    /// Orders created or modified by the update
+    pub updated_orders: HashMap<OrderIdentifier, Order>,
+    /// Orders removed by the update
+    pub removed_orders: Vec<OrderIdentifier>,
+    /// Balances created or modified by the update, keyed by mint
+    #[serde(
+        serialize_with = "serialize_balances",
+        deserialize_with = "deserialize_balances"
+    )]
+    pub updated_balances: HashMap<BigUint, Balance>,
+    /// The mints of the balances removed by the update
+    pub removed_balances: Vec<BigUint>,
+    /// The wallet's fees after the update, `None` if the fees are unchanged
+    pub fees: Option<Vec<Fee>>,
+    /// The wallet randomness after the update
+    pub randomness: BigUint,
+}
+
+impl WalletDelta {
+    /// Apply the delta to a wallet, the caller is responsible for checking the version
+    pub fn apply(&self, wallet: &mut Wallet) {
+        for order_id in self.removed_orders.iter() {
+            wallet.orders.remove(order_id);
+        }
+        wallet.orders.extend(self.updated_orders.clone());
+
+        for mint in self.removed_balances.iter() {
+            wallet.balances.remove(mint);
+        }
+        wallet.balances.extend(self.updated_balances.clone());
+
+        if let Some(fees) = &self.fees {
+            wallet.fees = fees.clone();
+        }
+        wallet.randomness = self.randomness.clone();
+        wallet.metadata.version = self.version;
+    }
+}
+
+/// The error type returned when a set of deltas cannot be applied to a wallet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalletDeltaError {
+    /// The wallet is not indexed locally
+    MissingWallet(WalletIdentifier),
+    /// The deltas do not follow contiguously from the local version of the wallet
+    VersionGap {
+        /// The version the next delta was expected to have
+        expected: u64,
+        /// The version of the next delta
+        received: u64,
+    },
+}
+
+impl Display for WalletDeltaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            WalletDeltaError::MissingWallet(wallet_id) => {
+                write!(f, "wallet {} not found", wallet_id)
+            }
+            WalletDeltaError::VersionGap { expected, received } => write!(
+                f,
+                "delta version gap, expected {}, got {}",
+                expected, received
+            ),
+        }
+    }
+}
+
+/// An order occupying one of a wallet's `MAX_ORDERS` order slots
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderSlot {
+    /// The order occupying the slot
+    pub order_id: OrderIdentifier,
+    /// The timestamp the order was placed at
+    pub timestamp: u64,
+    /// The state of the order in the local order book, `None` if the order is not indexed
+    pub state: Option<NetworkOrderState>,
+    /// Whether the order is dormant; i.e. it holds a slot without being ready to match
+    pub dormant: bool,
+    /// Whether the user has opted the order into automatic resubmission
+    pub auto_resubmit: bool,
+}
+
+impl OrderSlot {
+    /// Constructor
+    pub fn new(
+        order_id: OrderIdentifier,
+        order: &Order,
+        state: Option<NetworkOrderState>,
+        auto_resubmit: bool,
+    ) -> Self {
+        let dormant = order.amount == 0 || state != Some(NetworkOrderState::Verified);
+        Self {
+            order_id,
+            timestamp: order.timestamp,
+            state,
+            dormant,
+            auto_resubmit,
+        }
+    }
+
+    /// Whether the order may be cancelled to free its slot for a new order
+    ///
+    /// Matched orders are settling, and orders opted into automatic resubmission would
+    /// be resubmitted into the slot, so neither is evicted
+    pub fn is_evictable(&self) -> bool {
+        !self.auto_resubmit && !matches!(self.state, Some(NetworkOrderState::Matched { .. }))
+    }
+}
+
+/// The policy applied when an order is placed in a wallet with no free order slot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderEvictionPolicy {
+    /// Reject the new order
+    Reject,
+    /// Cancel the wallet's oldest unmatched order to free a slot for the new order
+    EvictOldest,
+}
+
+impl OrderEvictionPolicy {
+    /// Choose the order to evict from a full wallet's slots, if the policy permits one
+    pub fn choose_eviction(&self, slots: &[OrderSlot]) -> Option<OrderIdentifier> {
+        match self {
+            OrderEvictionPolicy::Reject => None,
+            OrderEvictionPolicy::EvictOldest => slots
+                .iter()
+                .filter(|slot| slot.is_evictable())
+                .min_by_key(|slot| slot.timestamp)
+                .map(|slot| slot.order_id),
+        }
+    }
+}
+
+impl FromStr for OrderEvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(OrderEvictionPolicy::Reject),
+            "evict_oldest" => Ok(OrderEvictionPolicy::EvictOldest),
+            _ => Err(format!("unknown order eviction policy: {}", s)),
+        }
+    }
 }
 
 // ------------------
@@ -410,6 +589,8 @@ pub struct WalletIndex {
     wallet_map: HashMap<Uuid, AsyncShared<Wallet>>,
     /// A reverse index mapping from order to wallet
     order_to_wallet: HashMap<OrderIdentifier, WalletIdentifier>,
+    /// A bounded log of the most recent deltas applied to each wallet, oldest first
+    delta_log: HashMap<WalletIdentifier, VecDeque<WalletDelta>>,
 }
 
 impl WalletIndex {
@@ -419,6 +600,7 @@ impl WalletIndex {
             peer_id,
             wallet_map: HashMap::new(),
             order_to_wallet: HashMap::new(),
+            delta_log: HashMap::new(),
         }
     }
 
@@ -485,6 +667,41 @@ impl WalletIndex {
         res
     }
 
+    /// Get the version of the local copy of a wallet
+    pub async fn get_wallet_version(&self, wallet_id: &WalletIdentifier) -> Option<u64> {
+        self.read_wallet(wallet_id)
+            .await
+            .map(|locked_wallet| locked_wallet.metadata.version)
+    }
+
+    /// Get the deltas that bring a replica at `since_version` up to the local version
+    ///
+    /// Returns `None` if the delta log no longer reaches back to `since_version`, in
+    /// which case the replica must be repaired by re-replicating the whole wallet
+    pub async fn get_deltas_since(
+        &self,
+        wallet_id: &WalletIdentifier,
+        since_version: u64,
+    ) -> Option<Vec<WalletDelta>> {
+        let local_version = self.get_wallet_version(wallet_id).await?;
+        if since_version >= local_version {
+            return Some(Vec::new());
+        }
+
+        let deltas = self
+            .delta_log
+            .get(wallet_id)?
+            .iter()
+            .filter(|delta| delta.version > since_version)
+            .cloned()
+            .collect_vec();
+        if deltas.first()?.version != since_version + 1 {
+            return None;
+        }
+
+        Some(deltas)
+    }
+
     /// Get a balance and a fee for a given order in a given wallet
     ///
     /// Returns a 4-tuple of (order, balance, fee, fee_balance) where fee_balance is the
@@ -525,6 +742,14 @@ impl WalletIndex {
             return None;
         }
 
+        // If the fee is paid in the mint the order spends, e.g. in the quote of a buy order,
+        // the balance must cover both
+        if fee.gas_addr == order_mint
+            && balance.amount < order_amount.saturating_add(fee.gas_token_amount)
+        {
+            return None;
+        }
+
         Some((
             order.clone(),
             balance.clone(),
@@ -544,12 +769,73 @@ impl WalletIndex {
             self.order_to_wallet.insert(*order_id, wallet.wallet_id);
         }
 
+        // A full copy of the wallet supersedes any deltas logged against a previous copy
+        self.delta_log.remove(&wallet.wallet_id);
+
         // Index the wallet
         wallet.metadata.replicas.insert(self.peer_id);
         self.wallet_map
             .insert(wallet.wallet_id, new_async_shared(wallet));
     }
 
+    /// Apply a contiguous sequence of deltas to a wallet
+    ///
+    /// Deltas at or below the local version are skipped as already applied. The remaining
+    /// deltas are validated before any are applied, so that the wallet is either brought
+    /// fully up to date or left untouched. Returns the IDs of orders newly added to the wallet
+    /// and the IDs of orders removed from it
+    pub async fn apply_deltas(
+        &mut self,
+        wallet_id: &WalletIdentifier,
+        deltas: Vec<WalletDelta>,
+    ) -> Result<(Vec<OrderIdentifier>, Vec<OrderIdentifier>), WalletDeltaError> {
+        let wallet = self
+            .wallet_map
+            .get(wallet_id)
+            .cloned()
+            .ok_or(WalletDeltaError::MissingWallet(*wallet_id))?;
+        let mut locked_wallet = wallet.write().await;
+
+        // Validate that the deltas follow on from the local version without gaps
+        let local_version = locked_wallet.metadata.version;
+        let deltas = deltas
+            .into_iter()
+            .filter(|delta| delta.version > local_version)
+            .collect_vec();
+        for (expected, delta) in (local_version + 1..).zip(deltas.iter()) {
+            if delta.version != expected {
+                return Err(WalletDeltaError::VersionGap {
+                    expected,
+                    received: delta.version,
+                });
+            }
+        }
+
+        let prev_orders: HashSet<OrderIdentifier> = locked_wallet.orders.keys().cloned().collect();
+        for delta in deltas.iter() {
+            delta.apply(&mut locked_wallet);
+        }
+
+        // Re-index the orders that entered or left the wallet
+        let curr_orders: HashSet<OrderIdentifier> = locked_wallet.orders.keys().cloned().collect();
+        let added_orders = curr_orders.difference(&prev_orders).cloned().collect_vec();
+        let removed_orders = prev_orders.difference(&curr_orders).cloned().collect_vec();
+        for order_id in removed_orders.iter() {
+            self.order_to_wallet.remove(order_id);
+        }
+        for order_id in added_orders.iter() {
+            self.order_to_wallet.insert(*order_id, *wallet_id);
+        }
+
+        let log = self.delta_log.entry(*wallet_id).or_default();
+        log.extend(deltas);
+        while log.len() > MAX_WALLET_DELTAS {
+            log.pop_front();
+        }
+
+        Ok((added_orders, removed_orders))
+    }
+
     /// Add a given peer as a replica of a wallet
     pub async fn add_replica(&self, wallet_id: &WalletIdentifier, peer_id: WrappedPeerId) {
         if let Some(wallet) = self.wallet_map.get(wallet_id) {
@@ -568,6 +854,79 @@ impl WalletIndex {
         }
     }
 
+    /// Opt an order into or out of automatic resubmission, returning whether the order
+    /// was found in the wallet
+    ///
+    /// Opting in snapshots the order's current parameters, which are used to regenerate
+    /// the order should a wallet update cancel it
+    pub async fn set_auto_resubmit(
+        &self,
+        wallet_id: &WalletIdentifier,
+        order_id: &OrderIdentifier,
+        enabled: bool,
+    ) -> bool {
+        let mut locked_wallet = match self.write_wallet(wallet_id).await {
+            Some(wallet) => wallet,
+            None => return false,
+        };
+
+        let order = match locked_wallet.orders.get(order_id) {
+            Some(order) => order.clone(),
+            None => return false,
+        };
+        if enabled {
+            locked_wallet
+                .metadata
+                .auto_resubmit
+                .insert(*order_id, order);
+        } else {
+            locked_wallet.metadata.auto_resubmit.remove(order_id);
+        }
+
+        true
+    }
+
+    /// Regenerate the auto-resubmit orders that are missing from a wallet, skipping
+    /// the given orders, and returning the IDs of the regenerated orders
+    ///
+    /// Orders that no longer fit in the wallet are dropped from the policy
+    pub async fn resubmit_missing_orders(
+        &mut self,
+        wallet_id: &WalletIdentifier,
+        skip: &HashSet<OrderIdentifier>,
+    ) -> Vec<OrderIdentifier> {
+        let mut resubmitted = Vec::new();
+        {
+            let mut locked_wallet = match self.write_wallet(wallet_id).await {
+                Some(wallet) => wallet,
+                None => return resubmitted,
+            };
+
+            let missing_orders = locked_wallet
+                .metadata
+                .auto_resubmit
+                .iter()
+                .filter(|(order_id, _)| !locked_wallet.orders.contains_key(order_id))
+                .map(|(order_id, order)| (*order_id, order.clone()))
+                .collect_vec();
+
+            for (order_id, order) in missing_orders.into_iter() {
+                if skip.contains(&order_id) || locked_wallet.orders.len() >= MAX_ORDERS {
+                    locked_wallet.metadata.auto_resubmit.remove(&order_id);
+                    continue;
+                }
+
+                locked_wallet.orders.insert(order_id, order);
+                resubmitted.push(order_id);
+            }
+        } // locked_wallet released
+
+        for order_id in resubmitted.iter() {
+            self.order_to_wallet.insert(*order_id, *wallet_id);
+        }
+        resubmitted
+    }
+
     /// Merge metadata for a given wallet into the local wallet state
     pub async fn merge_metadata(&self, wallet_id: &WalletIdentifier, metadata: &WalletMetadata) {
         if let Some(wallet) = self.wallet_map.get(wallet_id) {
@@ -602,10 +961,313 @@ impl WalletIndex {
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::atomic::AtomicU32,
+    };
+
+    use circuits::{
+        types::{
+            balance::Balance,
+            fee::Fee,
+            keychain::KeyChain,
+            order::{MatchConstraints, Order, OrderSide, TimeInForce},
+        },
+        zk_gadgets::fixed_point::FixedPoint,
+    };
     use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigUint;
     use rand_core::OsRng;
+    use uuid::Uuid;
+
+    use crate::{gossip::types::WrappedPeerId, state::orderbook::NetworkOrderState};
+
+    use super::{
+        OrderEvictionPolicy, OrderSlot, PrivateKeyChain, Wallet, WalletDelta, WalletDeltaError,
+        WalletIndex, WalletMetadata,
+    };
 
-    use super::PrivateKeyChain;
+    /// Build an empty wallet with random keys
+    fn empty_wallet() -> Wallet {
+        let mut rng = OsRng {};
+        Wallet {
+            wallet_id: Uuid::new_v4(),
+            orders: HashMap::new(),
+            balances: HashMap::new(),
+            fees: Vec::new(),
+            public_keys: KeyChain {
+                pk_root: Scalar::random(&mut rng),
+                pk_match: Scalar::random(&mut rng),
+                pk_settle: Scalar::random(&mut rng),
+                pk_view: Scalar::random(&mut rng),
+            },
+            secret_keys: PrivateKeyChain {
+                sk_root: None,
+                sk_match: Scalar::random(&mut rng),
+                sk_settle: Scalar::random(&mut rng),
+                sk_view: Scalar::random(&mut rng),
+            },
+            randomness: BigUint::from(0u8),
+            metadata: WalletMetadata {
+                replicas: HashSet::new(),
+                version: 0,
+                auto_resubmit: HashMap::new(),
+            },
+            merkle_proof: None,
+            proof_staleness: AtomicU32::new(0),
+        }
+    }
+
+    /// Build a delta that adds a single new order to a wallet
+    fn add_order_delta(version: u64) -> WalletDelta {
+        WalletDelta {
+            version,
+            updated_orders: HashMap::from([(Uuid::new_v4(), Order::default())]),
+            removed_orders: Vec::new(),
+            updated_balances: HashMap::new(),
+            removed_balances: Vec::new(),
+            fees: None,
+            randomness: BigUint::from(version),
+        }
+    }
+
+    /// Tests that contiguous deltas are applied and indexed
+    #[tokio::test]
+    async fn test_apply_deltas() {
+        let mut index = WalletIndex::new(WrappedPeerId::random());
+        let wallet = empty_wallet();
+        let wallet_id = wallet.wallet_id;
+        index.add_wallet(wallet);
+
+        let deltas = vec![add_order_delta(1), add_order_delta(2)];
+        let (added, removed) = index.apply_deltas(&wallet_id, deltas).await.unwrap();
+
+        assert_eq!(added.len(), 2);
+        assert!(removed.is_empty());
+        assert_eq!(index.get_wallet_version(&wallet_id).await, Some(2));
+        assert_eq!(index.get_wallet_for_order(&added[0]), Some(wallet_id));
+        assert_eq!(
+            index.get_deltas_since(&wallet_id, 1).await.unwrap().len(),
+            1
+        );
+    }
+
+    /// Tests that a gap in delta versions leaves the wallet untouched
+    #[tokio::test]
+    async fn test_apply_deltas_gap() {
+        let mut index = WalletIndex::new(WrappedPeerId::random());
+        let wallet = empty_wallet();
+        let wallet_id = wallet.wallet_id;
+        index.add_wallet(wallet);
+
+        let deltas = vec![add_order_delta(1), add_order_delta(3)];
+        let res = index.apply_deltas(&wallet_id, deltas).await;
+
+        assert_eq!(
+            res,
+            Err(WalletDeltaError::VersionGap {
+                expected: 2,
+                received: 3
+            })
+        );
+        assert_eq!(index.get_wallet_version(&wallet_id).await, Some(0));
+        assert!(index
+            .get_wallet(&wallet_id)
+            .await
+            .unwrap()
+            .orders
+            .is_empty());
+    }
+
+    /// Tests that a replica behind the retained delta log cannot be repaired incrementally
+    #[tokio::test]
+    async fn test_deltas_since_truncated() {
+        let mut index = WalletIndex::new(WrappedPeerId::random());
+        let mut wallet = empty_wallet();
+        wallet.metadata.version = 5;
+        let wallet_id = wallet.wallet_id;
+        index.add_wallet(wallet);
+
+        index
+            .apply_deltas(&wallet_id, vec![add_order_delta(6)])
+            .await
+            .unwrap();
+
+        assert!(index.get_deltas_since(&wallet_id, 2).await.is_none());
+        assert_eq!(
+            index.get_deltas_since(&wallet_id, 5).await.unwrap().len(),
+            1
+        );
+        assert!(index
+            .get_deltas_since(&wallet_id, 6)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Tests that an opted-in order removed by a wallet update is regenerated, and that
+    /// skipped orders are dropped from the policy
+    #[tokio::test]
+    async fn test_resubmit_missing_orders() {
+        let mut index = WalletIndex::new(WrappedPeerId::random());
+        let wallet = empty_wallet();
+        let wallet_id = wallet.wallet_id;
+        index.add_wallet(wallet);
+
+        let delta = add_order_delta(1);
+        let order_id = *delta.updated_orders.keys().next().unwrap();
+        index.apply_deltas(&wallet_id, vec![delta]).await.unwrap();
+        assert!(index.set_auto_resubmit(&wallet_id, &order_id, true).await);
+
+        let mut removal = add_order_delta(2);
+        removal.updated_orders.clear();
+        removal.removed_orders.push(order_id);
+        index.apply_deltas(&wallet_id, vec![removal]).await.unwrap();
+
+        let resubmitted = index
+            .resubmit_missing_orders(&wallet_id, &HashSet::new())
+            .await;
+        assert_eq!(resubmitted, vec![order_id]);
+        assert_eq!(index.get_wallet_for_order(&order_id), Some(wallet_id));
+
+        // An order that was filled rather than cancelled is not resubmitted
+        index
+            .write_wallet(&wallet_id)
+            .await
+            .unwrap()
+            .orders
+            .remove(&order_id);
+        let resubmitted = index
+            .resubmit_missing_orders(&wallet_id, &HashSet::from([order_id]))
+            .await;
+        assert!(resubmitted.is_empty());
+        assert!(!index.set_auto_resubmit(&wallet_id, &order_id, true).await);
+    }
+
+    /// Tests that an order quoted in a token other than USDC is matched against the balance of
+    /// its quote, and that a fee paid in the same token must be covered alongside the order
+    #[tokio::test]
+    async fn test_order_balance_and_fee_non_usdc_quote() {
+        let weth = BigUint::parse_bytes(b"c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", 16).unwrap();
+        let dai = BigUint::parse_bytes(b"6b175474e89094c44da98b954eedeac495271d0f", 16).unwrap();
+
+        let mut index = WalletIndex::new(WrappedPeerId::random());
+        let mut wallet = empty_wallet();
+        let wallet_id = wallet.wallet_id;
+
+        // Buy 10 WETH at 2 DAI each, paying a 5 DAI fee
+        let buy_id = Uuid::new_v4();
+        let sell_id = Uuid::new_v4();
+        let order = Order {
+            quote_mint: dai.clone(),
+            base_mint: weth.clone(),
+            side: OrderSide::Buy,
+            price: FixedPoint::from_integer(2),
+            amount: 10,
+            timestamp: 0,
+            time_in_force: TimeInForce::GoodTilCancelled,
+            constraints: MatchConstraints::default(),
+        };
+        wallet.orders.insert(buy_id, order.clone());
+        wallet.orders.insert(
+            sell_id,
+            Order {
+                side: OrderSide::Sell,
+                ..order
+            },
+        );
+        wallet.fees.push(Fee {
+            settle_key: BigUint::from(0u8),
+            gas_addr: dai.clone(),
+            gas_token_amount: 5,
+            percentage_fee: FixedPoint::from_integer(0),
+        });
+        wallet.balances.insert(
+            dai.clone(),
+            Balance {
+                mint: dai.clone(),
+                amount: 22,
+            },
+        );
+        wallet.balances.insert(
+            weth.clone(),
+            Balance {
+                mint: weth.clone(),
+                amount: 10,
+            },
+        );
+        index.add_wallet(wallet);
+
+        // The DAI balance covers the order but not the fee on top of it
+        assert!(index
+            .get_order_balance_and_fee(&wallet_id, &buy_id)
+            .await
+            .is_none());
+
+        // The sell order spends WETH, so the fee is covered separately
+        let (_, balance, _, fee_balance) = index
+            .get_order_balance_and_fee(&wallet_id, &sell_id)
+            .await
+            .unwrap();
+        assert_eq!(balance.mint, weth);
+        assert_eq!(fee_balance.mint, dai);
+
+        index
+            .write_wallet(&wallet_id)
+            .await
+            .unwrap()
+            .balances
+            .get_mut(&dai)
+            .unwrap()
+            .amount = 25;
+        let (_, balance, fee, _) = index
+            .get_order_balance_and_fee(&wallet_id, &buy_id)
+            .await
+            .unwrap();
+        assert_eq!(balance.mint, dai);
+        assert_eq!(fee.gas_addr, dai);
+    }
+
+    /// Tests that the oldest evictable order is chosen for eviction, and that matched and
+    /// auto-resubmitted orders are never evicted
+    #[test]
+    fn test_choose_eviction() {
+        let slot = |timestamp: u64, state: Option<NetworkOrderState>, auto_resubmit: bool| {
+            let order = Order {
+                amount: 1,
+                timestamp,
+                ..Default::default()
+            };
+            OrderSlot::new(Uuid::new_v4(), &order, state, auto_resubmit)
+        };
+
+        let matched = slot(
+            0,
+            Some(NetworkOrderState::Matched {
+                by_local_node: true,
+            }),
+            false,
+        );
+        let resubmitted = slot(1, Some(NetworkOrderState::Verified), true);
+        let oldest_unmatched = slot(2, Some(NetworkOrderState::Verified), false);
+        let dormant = slot(3, Some(NetworkOrderState::Received), false);
+        assert!(!oldest_unmatched.dormant);
+        assert!(dormant.dormant);
+
+        let mut slots = vec![matched, resubmitted, dormant, oldest_unmatched.clone()];
+        assert_eq!(
+            OrderEvictionPolicy::EvictOldest.choose_eviction(&slots),
+            Some(oldest_unmatched.order_id)
+        );
+        assert!(OrderEvictionPolicy::Reject
+            .choose_eviction(&slots)
+            .is_none());
+
+        slots.truncate(2);
+        assert!(OrderEvictionPolicy::EvictOldest
+            .choose_eviction(&slots)
+            .is_none());
+    }
 
     /// Test serialization/deserialization of a PrivateKeyChain
     #[test]
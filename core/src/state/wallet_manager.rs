@@ -0,0 +1,256 @@
+//! Tracks the lifecycle of each locally managed wallet and serializes the tasks that
+//! mutate it
+//!
+//! Every task that mutates a wallet -- proof warm-up, user updates, replica repair, and
+//! re-proving after a spend -- runs on the wallet's task queue, so that at most one task
+//! mutates a wallet at a time while tasks on different wallets proceed independently.
+//! Tasks are run in the order they are queued.
+//!
+//! A wallet whose state is found to be corrupt is frozen; no further tasks run on it, and
+//! its failure does not hold up tasks on other wallets. A frozen wallet is thawed when a
+//! fresh copy of it is registered, e.g. by recovering it from chain
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex as AsyncMutex;
+
+use super::{wallet::WalletIdentifier, Shared};
+
+/// The stage of its lifecycle that a locally managed wallet is in
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalletLifecycle {
+    /// The wallet's authentication path and order proofs are being built
+    Syncing,
+    /// The wallet's orders are proven and may be matched
+    Ready,
+    /// A task is mutating the wallet
+    Updating,
+    /// The wallet's state was found to be corrupt, no tasks run on it
+    Frozen {
+        /// The failure that froze the wallet
+        reason: String,
+    },
+}
+
+/// The error type returned when a task is run on a wallet
+#[derive(Clone, Debug)]
+pub enum WalletTaskError<E> {
+    /// The wallet is not managed locally
+    NotFound(WalletIdentifier),
+    /// The wallet is frozen, the task was not run
+    Frozen(String),
+    /// The task itself failed
+    Task(E),
+}
+
+impl<E: Display> Display for WalletTaskError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            WalletTaskError::NotFound(wallet_id) => write!(f, "wallet {wallet_id} not found"),
+            WalletTaskError::Frozen(reason) => write!(f, "wallet is frozen: {reason}"),
+            WalletTaskError::Task(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// A wallet's lifecycle and the queue its tasks wait on
+#[derive(Debug)]
+struct ManagedWallet {
+    /// The stage of its lifecycle the wallet is in
+    lifecycle: WalletLifecycle,
+    /// The queue of tasks on the wallet; a task holds the lock while it runs
+    task_queue: Arc<AsyncMutex<()>>,
+}
+
+/// The lifecycles and task queues of the locally managed wallets
+#[derive(Clone, Debug)]
+pub struct WalletManager {
+    /// The managed wallets, indexed by ID
+    wallets: Shared<HashMap<WalletIdentifier, ManagedWallet>>,
+}
+
+impl WalletManager {
+    /// Construct a manager with no wallets
+    pub fn new() -> Self {
+        Self {
+            wallets: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Register a wallet in the given stage of its lifecycle
+    ///
+    /// Registering a wallet that is already managed resets its lifecycle, thawing it if
+    /// it was frozen; tasks already queued on it still run in order
+    pub fn register(&self, wallet_id: WalletIdentifier, lifecycle: WalletLifecycle) {
+        self.wallets
+            .write()
+            .unwrap()
+            .entry(wallet_id)
+            .and_modify(|wallet| wallet.lifecycle = lifecycle.clone())
+            .or_insert_with(|| ManagedWallet {
+                lifecycle,
+                task_queue: Arc::new(AsyncMutex::new(())),
+            });
+    }
+
+    /// The stage of its lifecycle a wallet is in, `None` if it is not managed locally
+    pub fn lifecycle(&self, wallet_id: &WalletIdentifier) -> Option<WalletLifecycle> {
+        self.wallets
+            .read()
+            .unwrap()
+            .get(wallet_id)
+            .map(|wallet| wallet.lifecycle.clone())
+    }
+
+    /// Mark a wallet that has finished syncing as ready
+    pub fn mark_ready(&self, wallet_id: &WalletIdentifier) {
+        self.set_lifecycle(wallet_id, WalletLifecycle::Ready);
+    }
+
+    /// Freeze a wallet whose state was found to be corrupt
+    pub fn freeze(&self, wallet_id: &WalletIdentifier, reason: String) {
+        self.set_lifecycle(wallet_id, WalletLifecycle::Frozen { reason });
+    }
+
+    /// Run a task that mutates a wallet once the tasks queued ahead of it finish
+    ///
+    /// A ready wallet is marked as updating while the task runs and returned to ready
+    /// once it finishes, whether or not it succeeds; the caller decides whether a failure
+    /// should freeze the wallet. The task is not run if the wallet is frozen by the time
+    /// it reaches the front of the queue
+    pub async fn run_task<T, E, F>(
+        &self,
+        wallet_id: &WalletIdentifier,
+        task: F,
+    ) -> Result<T, WalletTaskError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let task_queue = match self.wallets.read().unwrap().get(wallet_id) {
+            Some(wallet) => wallet.task_queue.clone(),
+            None => return Err(WalletTaskError::NotFound(*wallet_id)),
+        };
+        let _guard = task_queue.lock().await;
+
+        let prev_lifecycle = match self.lifecycle(wallet_id) {
+            Some(WalletLifecycle::Frozen { reason }) => {
+                return Err(WalletTaskError::Frozen(reason))
+            }
+            Some(WalletLifecycle::Ready) => {
+                self.set_lifecycle(wallet_id, WalletLifecycle::Updating);
+                WalletLifecycle::Ready
+            }
+            Some(lifecycle) => lifecycle,
+            None => return Err(WalletTaskError::NotFound(*wallet_id)),
+        };
+
+        let res = task.await;
+
+        // The wallet may have been frozen or re-registered while the task ran
+        if self.lifecycle(wallet_id) == Some(WalletLifecycle::Updating) {
+            self.set_lifecycle(wallet_id, prev_lifecycle);
+        }
+
+        res.map_err(WalletTaskError::Task)
+    }
+
+    /// Set the lifecycle of a managed wallet, wallets that are not managed are ignored
+    fn set_lifecycle(&self, wallet_id: &WalletIdentifier, lifecycle: WalletLifecycle) {
+        if let Some(wallet) = self.wallets.write().unwrap().get_mut(wallet_id) {
+            wallet.lifecycle = lifecycle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::sync::Notify;
+    use uuid::Uuid;
+
+    use super::{WalletLifecycle, WalletManager, WalletTaskError};
+
+    /// Tests that tasks on a wallet run one at a time and mark the wallet as updating
+    #[tokio::test]
+    async fn test_tasks_serialized() {
+        let manager = WalletManager::new();
+        let wallet_id = Uuid::new_v4();
+        manager.register(wallet_id, WalletLifecycle::Ready);
+
+        let release = Arc::new(Notify::new());
+        let manager_clone = manager.clone();
+        let release_clone = release.clone();
+        let first_task = tokio::spawn(async move {
+            manager_clone
+                .run_task(&wallet_id, async {
+                    release_clone.notified().await;
+                    Ok::<_, ()>(1)
+                })
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            manager.lifecycle(&wallet_id),
+            Some(WalletLifecycle::Updating)
+        );
+
+        // The second task waits on the first
+        let manager_clone = manager.clone();
+        let second_task = tokio::spawn(async move {
+            manager_clone
+                .run_task(&wallet_id, async { Ok::<_, ()>(2) })
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!second_task.is_finished());
+
+        release.notify_one();
+        assert_eq!(first_task.await.unwrap().unwrap(), 1);
+        assert_eq!(second_task.await.unwrap().unwrap(), 2);
+        assert_eq!(manager.lifecycle(&wallet_id), Some(WalletLifecycle::Ready));
+    }
+
+    /// Tests that a frozen wallet runs no tasks while other wallets are unaffected, and
+    /// that re-registering the wallet thaws it
+    #[tokio::test]
+    async fn test_frozen_wallet_isolated() {
+        let manager = WalletManager::new();
+        let frozen_wallet = Uuid::new_v4();
+        let healthy_wallet = Uuid::new_v4();
+        manager.register(frozen_wallet, WalletLifecycle::Syncing);
+        manager.register(healthy_wallet, WalletLifecycle::Syncing);
+
+        let res = manager
+            .run_task(&frozen_wallet, async { Err::<(), _>("corrupt wallet") })
+            .await;
+        assert!(matches!(res, Err(WalletTaskError::Task(_))));
+        manager.freeze(&frozen_wallet, "corrupt wallet".to_string());
+
+        let res = manager
+            .run_task(&frozen_wallet, async { Ok::<_, ()>(()) })
+            .await;
+        assert!(matches!(res, Err(WalletTaskError::Frozen(_))));
+        assert!(manager
+            .run_task(&healthy_wallet, async { Ok::<_, ()>(()) })
+            .await
+            .is_ok());
+        assert_eq!(
+            manager.lifecycle(&healthy_wallet),
+            Some(WalletLifecycle::Syncing)
+        );
+
+        manager.register(frozen_wallet, WalletLifecycle::Ready);
+        assert!(manager
+            .run_task(&frozen_wallet, async { Ok::<_, ()>(()) })
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+//! Stores the erasure-coded wallet shards held by the local peer
+//!
+//! When the cluster replicates wallets as erasure-coded shards, each peer holds one (or, in a
+//! cluster smaller than the code, several) shards of each wallet. The leader additionally
+//! records the holder assignment of each wallet it encoded, so that it can re-encode a wallet
+//! once it changes or loses a holder. A peer reassembling a wallet collects its peers' shards
+//! here until enough have arrived to decode the wallet
+
+use std::collections::HashMap;
+
+use crate::{gossip::types::WrappedPeerId, gossip_api::cluster_management::WalletShard};
+
+use super::wallet::WalletIdentifier;
+
+/// The assignment of a wallet's shards to cluster peers, as made by the encoding peer
+#[derive(Clone, Debug)]
+pub struct ShardAssignment {
+    /// The version of the wallet when it was encoded
+    pub version: u64,
+    /// The peer assigned each shard, indexed by shard
+    pub holders: Vec<WrappedPeerId>,
+}
+
+/// The shard placement of a wallet, as recorded in the shards the local peer holds
+#[derive(Clone, Debug)]
+pub struct ShardedWallet {
+    /// The wallet the shards were encoded from
+    pub wallet_id: WalletIdentifier,
+    /// The peer assigned each shard, indexed by shard
+    pub holders: Vec<WrappedPeerId>,
+    /// The peers that held the wallet in full when it was encoded
+    pub replicas: Vec<WrappedPeerId>,
+}
+
+/// The wallet shards held by the local peer
+#[derive(Clone, Debug, Default)]
+pub struct WalletShardStore {
+    /// The shards held by the local peer, keyed by wallet
+    shards: HashMap<WalletIdentifier, Vec<WalletShard>>,
+    /// The shard assignments made by the local peer while it led the cluster
+    assignments: HashMap<WalletIdentifier, ShardAssignment>,
+    /// The shards collected so far for each wallet the local peer is reassembling
+    reassemblies: HashMap<WalletIdentifier, Vec<WalletShard>>,
+}
+
+impl WalletShardStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // -----------
+    // | Getters |
+    // -----------
+
+    /// The shards of the given wallet held by the local peer
+    pub fn get_shards(&self, wallet_id: &WalletIdentifier) -> Vec<WalletShard> {
+        self.shards.get(wallet_id).cloned().unwrap_or_default()
+    }
+
+    /// The placement of each wallet the local peer holds shards of
+    pub fn get_sharded_wallets(&self) -> Vec<ShardedWallet> {
+        self.shards
+            .iter()
+            .filter_map(|(wallet_id, shards)| {
+                shards.first().map(|shard| ShardedWallet {
+                    wallet_id: *wallet_id,
+                    holders: shard.holders.clone(),
+                    replicas: shard.replicas.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The shard assignment the local peer made for the given wallet, if any
+    pub fn get_assignment(&self, wallet_id: &WalletIdentifier) -> Option<&ShardAssignment> {
+        self.assignments.get(wallet_id)
+    }
+
+    // -----------
+    // | Setters |
+    // -----------
+
+    /// Add shards assigned to the local peer
+    ///
+    /// Shards of a newer version of a wallet replace any held shards of an older version,
+    /// shards of an older version than those held are dropped
+    pub fn add_shards(&mut self, shards: Vec<WalletShard>) {
+        for shard in shards.into_iter() {
+            let held = self.shards.entry(shard.wallet_id).or_default();
+            match held.first().map(|existing| existing.version) {
+                Some(version) if version > shard.version => continue,
+                Some(version) if version < shard.version => held.clear(),
+                _ => held.retain(|existing| existing.index != shard.index),
+            }
+
+            held.push(shard);
+        }
+    }
+
+    /// Record the shard assignment the local peer made for a wallet
+    pub fn set_assignment(&mut self, wallet_id: WalletIdentifier, assignment: ShardAssignment) {
+        self.assignments.insert(wallet_id, assignment);
+    }
+
+    /// Begin reassembling a wallet from the shards the local peer holds
+    ///
+    /// Returns `false` if the wallet is already being reassembled
+    pub fn begin_reassembly(&mut self, wallet_id: &WalletIdentifier) -> bool {
+        if self.reassemblies.contains_key(wallet_id) {
+            return false;
+        }
+
+        self.reassemblies
+            .insert(*wallet_id, self.get_shards(wallet_id));
+        true
+    }
+
+    /// Add shards received from a peer to an in-progress reassembly, returning every shard
+    /// collected so far
+    ///
+    /// Returns `None` if the wallet is not being reassembled
+    pub fn add_reassembly_shards(
+        &mut self,
+        wallet_id: &WalletIdentifier,
+        shards: Vec<WalletShard>,
+    ) -> Option<Vec<WalletShard>> {
+        let collected = self.reassemblies.get_mut(wallet_id)?;
+        for shard in shards.into_iter() {
+            if !collected
+                .iter()
+                .any(|existing| existing.index == shard.index)
+            {
+                collected.push(shard);
+            }
+        }
+
+        Some(collected.clone())
+    }
+
+    /// End the reassembly of a wallet
+    pub fn finish_reassembly(&mut self, wallet_id: &WalletIdentifier) {
+        self.reassemblies.remove(wallet_id);
+    }
+}
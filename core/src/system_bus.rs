@@ -5,19 +5,29 @@
 //! The implementation of the bus is such that if there are no subscribers to
 //! a given topic; a publish action is a no-op. Consequently, a new subscriber
 //! will not see historical messages
+//!
+//! A subscription to a topic ending in `*` is a wildcard subscription; it receives the
+//! messages published to every topic beginning with the preceding prefix, e.g. a
+//! subscriber to `wallet-updates-*` receives updates to every wallet
+//!
+//! Each subscriber reads from its own bounded buffer, so a slow subscriber never blocks
+//! the publisher or holds messages for other subscribers. When a subscriber's buffer is
+//! full, its overflow policy decides which message is dropped, and the drop is counted
+//! against the subscriber as lag
 
 // TODO: Remove this lint allowance
 #![allow(dead_code)]
 
-use bus::{Bus, BusReader};
 use futures::Stream;
+use serde::{Deserialize, Serialize};
 use std::{
-    cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display},
     pin::Pin,
+    str::FromStr,
     sync::{
-        atomic::{AtomicU16, Ordering},
-        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
     },
     task::{Context, Poll, Waker},
 };
@@ -25,196 +35,259 @@ use tokio::macros::support::poll_fn;
 
 use crate::state::Shared;
 
-/// The number of messages to buffer inside a single topic's bus
-const BUS_BUFFER_SIZE: usize = 10;
+/// The number of messages buffered for a subscriber that does not configure its buffer
+pub const DEFAULT_SUBSCRIBER_BUFFER_SIZE: usize = 64;
+/// The suffix that marks a subscription as a wildcard over a topic prefix
+const WILDCARD_SUFFIX: char = '*';
+
+/// What a subscriber's buffer does with a message published while the buffer is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest buffered message to make room, the subscriber skips ahead
+    DropOldest,
+    /// Drop the published message, the subscriber sees the backlog but misses new messages
+    DropNewest,
+}
 
-/// A wrapper around `BusReader` that allows us to store topic-relevant information,
-/// add reference counts, and build pollable methods around reading
-///
-/// The trait bounds on the message (Clone + Sync) are required by the Bus implementation
-#[derive(Debug)]
-pub struct TopicReader<M> {
-    /// The name of the topic that this reader listens to
-    topic_name: String,
-    /// The underlying bus reader for the topic's bus
-    reader: BusReader<M>,
-    /// A buffered message; used when a call to has_next returns a value
-    buffered_message: RefCell<Option<M>>,
-    /// When the reader is dropped we decrement this value in the parent struct so that
-    /// the topic may be deallocated if we are the last reader
-    num_readers: Arc<AtomicU16>,
-    /// The list of wakers on the given topic; the reader should add itself to the waker
-    /// queue when it is polled and returns pending
-    topic_wakers: Shared<Vec<Waker>>,
-    /// A reference to the system bus's topic mesh; readers hold this reference so that they
-    /// may deallocate the topic when dropped if they are the last reader on the topic
-    topic_mesh: Shared<HashMap<String, Shared<TopicFabric<M>>>>,
+impl Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowPolicy::DropOldest => write!(f, "drop_oldest"),
+            OverflowPolicy::DropNewest => write!(f, "drop_newest"),
+        }
+    }
 }
 
-impl<M> Unpin for TopicReader<M> {}
-
-impl<M: Clone + Sync> TopicReader<M> {
-    /// Construct a new reader for a topic
-    pub fn new(
-        topic_name: String,
-        bus_reader: BusReader<M>,
-        num_readers: Arc<AtomicU16>,
-        topic_wakers: Shared<Vec<Waker>>,
-        topic_mesh: Shared<HashMap<String, Shared<TopicFabric<M>>>>,
-    ) -> Self {
-        // Increment num_readers in the topic mesh now that the local thread is reading
-        num_readers.fetch_add(1 /* val */, Ordering::Relaxed);
-        Self {
-            topic_name,
-            reader: bus_reader,
-            buffered_message: RefCell::new(None),
-            num_readers,
-            topic_wakers,
-            topic_mesh,
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop_oldest" => Ok(OverflowPolicy::DropOldest),
+            "drop_newest" => Ok(OverflowPolicy::DropNewest),
+            _ => Err(format!("unknown overflow policy: {}", s)),
         }
     }
+}
 
-    /// Check whether there is a message on the bus, does not block
-    ///
-    /// The bus primitive we use here does not support a `has_next` method;
-    /// instead we can do a non-blocking attempted recv. If this returns a value
-    /// we buffer it so that it can be consumed by the next call to `next_message`
-    pub fn has_next(&mut self) -> bool {
-        // If we've previously buffered a message
-        if self.buffered_message.borrow().is_some() {
-            return true;
+/// The configuration of a single subscription's buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionConfig {
+    /// The number of messages buffered before the overflow policy applies
+    pub buffer_size: usize,
+    /// What to do with a message published while the buffer is full
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_SUBSCRIBER_BUFFER_SIZE,
+            overflow_policy: OverflowPolicy::DropOldest,
         }
+    }
+}
 
-        // If we call `try_recv` and it returns a message; we must buffer that
-        // message for the next call to `next_message`
-        if let Ok(message) = self.reader.try_recv() {
-            self.buffered_message.replace(Some(message));
-            true
-        } else {
-            false
+/// A snapshot of the lag of a single subscriber
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriberMetrics {
+    /// The topic, or wildcard pattern, subscribed to
+    pub topic: String,
+    /// The number of messages buffered and not yet read
+    pub buffered: usize,
+    /// The number of messages the subscriber's buffer holds
+    pub capacity: usize,
+    /// The number of messages dropped for the subscriber because its buffer was full
+    pub dropped: u64,
+}
+
+/// Whether a subscription is a wildcard subscription
+fn is_wildcard(topic: &str) -> bool {
+    topic.ends_with(WILDCARD_SUFFIX)
+}
+
+/// Whether a wildcard pattern matches a topic
+fn wildcard_matches(pattern: &str, topic: &str) -> bool {
+    topic.starts_with(pattern.trim_end_matches(WILDCARD_SUFFIX))
+}
+
+/// The bounded buffer between the bus and a single subscriber
+#[derive(Debug)]
+struct SubscriberBuffer<M> {
+    /// The topic, or wildcard pattern, that the subscriber listens to
+    topic: String,
+    /// The configuration of the buffer
+    config: SubscriptionConfig,
+    /// The messages published and not yet read
+    messages: Mutex<VecDeque<M>>,
+    /// The waker of the subscriber's task, if it is waiting on a message
+    waker: Mutex<Option<Waker>>,
+    /// The number of messages dropped because the buffer was full
+    dropped: AtomicU64,
+}
+
+impl<M> SubscriberBuffer<M> {
+    /// Construct a new buffer for a subscription
+    fn new(topic: String, config: SubscriptionConfig) -> Self {
+        Self {
+            topic,
+            config,
+            messages: Mutex::new(VecDeque::new()),
+            waker: Mutex::new(None),
+            dropped: AtomicU64::new(0),
         }
     }
 
-    /// Awaits the next message published onto the bus
-    pub async fn next_message(&mut self) -> M {
-        poll_fn(|ctx| self.poll_bus(ctx)).await
+    /// The number of messages the buffer holds, at least one
+    fn capacity(&self) -> usize {
+        self.config.buffer_size.max(1)
     }
 
-    /// Poll the underlying bus, wrapped in `PollFn` to give an async interface
-    /// to the reader
-    fn poll_bus(&mut self, cx: &mut Context<'_>) -> Poll<M> {
-        // If we have previously buffered a message for delivery; take ownership of
-        // the message and leave `None` in its place
-        if self.buffered_message.borrow().is_some() {
-            return Poll::Ready(self.buffered_message.take().unwrap());
+    /// Buffer a message, applying the overflow policy if the buffer is full, and wake
+    /// the subscriber
+    fn push(&self, message: M) {
+        {
+            let mut messages = self.messages.lock().expect("messages lock poisoned");
+            if messages.len() >= self.capacity() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                match self.config.overflow_policy {
+                    OverflowPolicy::DropOldest => {
+                        messages.pop_front();
+                    }
+                    OverflowPolicy::DropNewest => return,
+                }
+            }
+
+            messages.push_back(message);
+        } // messages released
+
+        if let Some(waker) = self.waker.lock().expect("waker lock poisoned").take() {
+            waker.wake();
         }
+    }
 
-        // Otherwise, poll the bus
-        if let Ok(message) = self.reader.try_recv() {
-            Poll::Ready(message)
-        } else {
-            // Add the local task waker to the list of waiting wakers for the topic
-            let mut locked_topic_wakers = self
-                .topic_wakers
-                .write()
-                .expect("topic_wakers lock poisoned");
-            locked_topic_wakers.push(cx.waker().clone());
-
-            Poll::Pending
+    /// Take the next buffered message, or register the waker to be woken when one is
+    /// published
+    ///
+    /// The waker is registered under the messages lock, so that a message published after
+    /// the buffer is found empty always finds the waker
+    fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<M> {
+        let mut messages = self.messages.lock().expect("messages lock poisoned");
+        if let Some(message) = messages.pop_front() {
+            return Poll::Ready(message);
+        }
+
+        self.waker
+            .lock()
+            .expect("waker lock poisoned")
+            .replace(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// A snapshot of the buffer's lag
+    fn metrics(&self) -> SubscriberMetrics {
+        SubscriberMetrics {
+            topic: self.topic.clone(),
+            buffered: self.messages.lock().expect("messages lock poisoned").len(),
+            capacity: self.capacity(),
+            dropped: self.dropped.load(Ordering::Relaxed),
         }
     }
 }
 
-impl<M: Clone + Sync> Stream for TopicReader<M> {
-    type Item = M;
+/// The subscribers to each topic, keyed by the topic or wildcard pattern subscribed to
+#[derive(Debug)]
+struct SubscriberMesh<M> {
+    /// Subscribers to a single topic
+    exact: HashMap<String, Vec<Arc<SubscriberBuffer<M>>>>,
+    /// Subscribers to a wildcard pattern
+    wildcard: HashMap<String, Vec<Arc<SubscriberBuffer<M>>>>,
+}
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.poll_bus(cx).map(|message| Some(message))
+impl<M> SubscriberMesh<M> {
+    /// Construct an empty mesh
+    fn new() -> Self {
+        Self {
+            exact: HashMap::new(),
+            wildcard: HashMap::new(),
+        }
     }
-}
 
-/// A reference counting `Drop` implementation for the reader; if the reader is the last
-/// reader on a given topic, it should clean up the topic from the system bus's mesh
-impl<M> Drop for TopicReader<M> {
-    fn drop(&mut self) {
-        // If there was only one reader (local thread) before this reader was dropped, deallocate the
-        // topic in the pubsub mesh
-        let prev_num_readers = self.num_readers.fetch_sub(1 /* val */, Ordering::Relaxed);
-        if prev_num_readers == 1 {
-            let mut locked_mesh = self.topic_mesh.write().expect("topic_mesh lock poisoned");
-
-            // Ensure that no new subscriptions have come in since we acquired the lock
-            // If so, abort the deallocation of the topic, otherwise zombie readers will
-            // exist for the deallocated topic
-            let new_num_readers = self.num_readers.load(Ordering::Relaxed);
-            if new_num_readers > 0 {
-                return;
-            }
+    /// The map that a subscription to the given topic is stored in
+    fn entries(&self, topic: &str) -> &HashMap<String, Vec<Arc<SubscriberBuffer<M>>>> {
+        if is_wildcard(topic) {
+            &self.wildcard
+        } else {
+            &self.exact
+        }
+    }
 
-            locked_mesh.remove(&self.topic_name);
-        } // locked_mesh released here
+    /// The map that a subscription to the given topic is stored in, mutably
+    fn entries_mut(&mut self, topic: &str) -> &mut HashMap<String, Vec<Arc<SubscriberBuffer<M>>>> {
+        if is_wildcard(topic) {
+            &mut self.wildcard
+        } else {
+            &mut self.exact
+        }
     }
 }
 
-/// An implementation of a single-producer, multi-consumer topic specific bus
+/// A subscriber's handle on its buffer, pollable for the messages published to the topic
+/// it subscribed to
 #[derive(Debug)]
-pub struct TopicFabric<M> {
-    /// The name of the topic that this fabric is allocated for
-    topic_name: String,
-    /// The broadcast primitive underlying a shared bus
-    bus: Bus<M>,
-    /// The number of readers on the given topic
-    num_readers: Arc<AtomicU16>,
-    /// The wakers for the tasks that are actively waiting on the topic
-    wakers: Shared<Vec<Waker>>,
-    /// A reference to the parent mesh that this topic is stored in
-    ///
-    /// Topics store these references so that they may deallocate themselves
-    /// when the last listener is dropped
-    topic_mesh: Shared<HashMap<String, Shared<TopicFabric<M>>>>,
+pub struct TopicReader<M> {
+    /// The subscriber's buffer
+    buffer: Arc<SubscriberBuffer<M>>,
+    /// A reference to the system bus's subscriber mesh; readers hold this reference so that
+    /// they may remove themselves when dropped, deallocating the topic if they are its last
+    /// reader
+    mesh: Shared<SubscriberMesh<M>>,
 }
 
-impl<M: Clone + Sync> TopicFabric<M> {
-    /// Construct a new fabric for a registered topic
-    pub fn new(
-        topic_name: String,
-        topic_mesh: Shared<HashMap<String, Shared<TopicFabric<M>>>>,
-    ) -> Self {
-        Self {
-            topic_name,
-            bus: Bus::new(BUS_BUFFER_SIZE),
-            num_readers: Arc::new(AtomicU16::new(0 /* val */)),
-            wakers: Arc::new(RwLock::new(Vec::new())),
-            topic_mesh,
-        }
+impl<M> TopicReader<M> {
+    /// Check whether there is a message buffered for the reader, does not block
+    pub fn has_next(&mut self) -> bool {
+        !self
+            .buffer
+            .messages
+            .lock()
+            .expect("messages lock poisoned")
+            .is_empty()
     }
 
-    /// Add a new reader to the fabric
-    pub fn new_reader(&mut self) -> TopicReader<M> {
-        TopicReader::new(
-            self.topic_name.clone(),
-            self.bus.add_rx(),
-            self.num_readers.clone(),
-            self.wakers.clone(),
-            self.topic_mesh.clone(),
-        )
+    /// Awaits the next message published onto the bus
+    pub async fn next_message(&mut self) -> M {
+        poll_fn(|ctx| self.buffer.poll_pop(ctx)).await
     }
 
-    /// Write a message onto the topic bus
-    pub fn write_message(&mut self, message: M) {
-        // Push the message onto the bus
-        self.bus.broadcast(message);
+    /// The number of messages dropped for the reader because it fell behind
+    pub fn dropped(&self) -> u64 {
+        self.buffer.dropped.load(Ordering::Relaxed)
+    }
+}
 
-        // Wake all the readers waiting on a message
-        let mut locked_wakers = self.wakers.write().expect("wakers lock poisoned");
-        for waker in locked_wakers.drain(0..) {
-            waker.wake()
-        }
+impl<M> Stream for TopicReader<M> {
+    type Item = M;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.buffer.poll_pop(cx).map(Some)
     }
+}
 
-    /// Get the number of readers of the topic
-    pub fn num_readers(&self) -> u16 {
-        self.num_readers.load(Ordering::Relaxed)
+/// Remove the reader's buffer from the mesh when it is dropped, deallocating the topic if
+/// the reader was its last
+impl<M> Drop for TopicReader<M> {
+    fn drop(&mut self) {
+        let mut locked_mesh = self.mesh.write().expect("mesh lock poisoned");
+        let topic = &self.buffer.topic;
+        let entries = locked_mesh.entries_mut(topic);
+        if let Some(subscribers) = entries.get_mut(topic) {
+            subscribers.retain(|buffer| !Arc::ptr_eq(buffer, &self.buffer));
+            if subscribers.is_empty() {
+                entries.remove(topic);
+            }
+        }
     }
 }
 
@@ -223,89 +296,95 @@ impl<M: Clone + Sync> TopicFabric<M> {
 /// Note that publishing to a topic with no subscribers is a no-op
 #[derive(Clone, Debug)]
 pub struct SystemBus<M> {
-    /// The topic mesh connects publishers to subscribers, it is concretely implemented
-    /// as a mapping from topic name (String) to a bus (single-producer, multi-consumer)
-    topic_mesh: Shared<HashMap<String, Shared<TopicFabric<M>>>>,
+    /// The subscriber mesh connects publishers to the buffers of each subscriber
+    mesh: Shared<SubscriberMesh<M>>,
 }
 
-impl<M: Clone + Sync> SystemBus<M> {
+impl<M: Clone> SystemBus<M> {
     /// Construct a new system bus
     pub fn new() -> Self {
         Self {
-            topic_mesh: Arc::new(RwLock::new(HashMap::new())),
+            mesh: Arc::new(RwLock::new(SubscriberMesh::new())),
         }
     }
 
-    /// Acquire a read lock on the topic mesh
-    fn read_topic_mesh(&self) -> RwLockReadGuard<HashMap<String, Shared<TopicFabric<M>>>> {
-        self.topic_mesh.read().expect("topic_mesh lock poisoned")
+    /// Acquire a read lock on the subscriber mesh
+    fn read_mesh(&self) -> RwLockReadGuard<SubscriberMesh<M>> {
+        self.mesh.read().expect("mesh lock poisoned")
     }
 
-    /// Acquire a write lock on the topic mesh
-    fn write_topic_mesh(&self) -> RwLockWriteGuard<HashMap<String, Shared<TopicFabric<M>>>> {
-        self.topic_mesh.write().expect("topic_mesh lock poisoned")
+    /// Acquire a write lock on the subscriber mesh
+    fn write_mesh(&self) -> RwLockWriteGuard<SubscriberMesh<M>> {
+        self.mesh.write().expect("mesh lock poisoned")
     }
 
-    /// Publish a message onto a topic; blocks if the buffer is full
+    /// Publish a message onto a topic; never blocks on a slow subscriber
     pub fn publish(&self, topic: String, message: M) {
-        let locked_mesh = self.read_topic_mesh();
-        let topic_entry = locked_mesh.get(&topic);
-
-        // If the topic is not registered, there are no listeners, short circuit
-        if topic_entry.is_none() {
-            return;
+        let locked_mesh = self.read_mesh();
+        let exact_subscribers = locked_mesh.exact.get(&topic).into_iter().flatten();
+        let wildcard_subscribers = locked_mesh
+            .wildcard
+            .iter()
+            .filter(|(pattern, _)| wildcard_matches(pattern, &topic))
+            .flat_map(|(_, subscribers)| subscribers.iter());
+
+        for subscriber in exact_subscribers.chain(wildcard_subscribers) {
+            subscriber.push(message.clone());
         }
-
-        // Otherwise, lock the topic and push a message onto it
-        let mut locked_topic = topic_entry
-            .unwrap()
-            .write()
-            .expect("topic_entry lock poisoned");
-        locked_topic.write_message(message);
     }
 
-    /// Subscribe to a topic, returns a pollable future
+    /// Subscribe to a topic or wildcard pattern with the default buffer configuration,
+    /// returns a pollable future
     pub fn subscribe(&self, topic: String) -> TopicReader<M> {
-        // If the topic is not yet registered, create one
-        let contains_topic = { self.read_topic_mesh().contains_key(&topic) };
-        if !contains_topic {
-            let mut locked_mesh = self.write_topic_mesh();
-            locked_mesh.insert(
-                topic.clone(),
-                Arc::new(RwLock::new(TopicFabric::new(
-                    topic.clone(),
-                    self.topic_mesh.clone(),
-                ))),
-            );
-        } // locked_mesh released
-
-        // Build a reader on the topic of interest and return it as a pollable to the subscriber
-        let locked_mesh = self.read_topic_mesh();
-        let mut locked_topic = locked_mesh
-            .get(&topic)
-            .unwrap()
-            .write()
-            .expect("topic_entry lock poisoned");
-
-        locked_topic.new_reader()
-    }
-
-    /// Returns the number of listeners on a topic
-    pub fn num_listeners(&self, topic: &String) -> u16 {
-        if let Some(topic_entry) = self.read_topic_mesh().get(topic) {
-            let locked_topic = topic_entry.write().expect("topic_entry lock poisoned");
-            locked_topic.num_readers()
-        } else {
-            0
+        self.subscribe_with_config(topic, SubscriptionConfig::default())
+    }
+
+    /// Subscribe to a topic or wildcard pattern with the given buffer configuration
+    pub fn subscribe_with_config(
+        &self,
+        topic: String,
+        config: SubscriptionConfig,
+    ) -> TopicReader<M> {
+        let buffer = Arc::new(SubscriberBuffer::new(topic.clone(), config));
+        self.write_mesh()
+            .entries_mut(&topic)
+            .entry(topic)
+            .or_default()
+            .push(buffer.clone());
+
+        TopicReader {
+            buffer,
+            mesh: self.mesh.clone(),
         }
     }
 
+    /// Returns the number of listeners on a topic or wildcard pattern
+    pub fn num_listeners(&self, topic: &String) -> u16 {
+        self.read_mesh()
+            .entries(topic)
+            .get(topic)
+            .map(|subscribers| subscribers.len() as u16)
+            .unwrap_or_default()
+    }
+
     /// Returns whether or not the given topic has been subscribed to by any readers
     ///
     /// This method is implemented mostly for testing purposes, i.e. to give us an idea of
     /// whether the topic is allocated in the underlying mesh
     pub fn has_listeners(&self, topic: &String) -> bool {
-        self.read_topic_mesh().contains_key(topic)
+        self.read_mesh().entries(topic).contains_key(topic)
+    }
+
+    /// A snapshot of the lag of every subscriber on the bus
+    pub fn metrics(&self) -> Vec<SubscriberMetrics> {
+        let locked_mesh = self.read_mesh();
+        locked_mesh
+            .exact
+            .values()
+            .chain(locked_mesh.wildcard.values())
+            .flatten()
+            .map(|subscriber| subscriber.metrics())
+            .collect()
     }
 }
 
@@ -313,7 +392,7 @@ impl<M: Clone + Sync> SystemBus<M> {
 mod system_bus_tests {
     use rand::{thread_rng, RngCore};
 
-    use super::SystemBus;
+    use super::{OverflowPolicy, SubscriptionConfig, SystemBus};
 
     const TEST_TOPIC: &str = "test topic";
 
@@ -448,4 +527,66 @@ mod system_bus_tests {
         drop(reader2);
         assert!(!pubsub.has_listeners(&TEST_TOPIC.to_string()));
     }
+
+    /// Tests that a wildcard subscriber receives messages on every topic with its prefix,
+    /// alongside exact subscribers
+    #[tokio::test]
+    async fn test_wildcard_subscription() {
+        let pubsub = SystemBus::<u64>::new();
+        let mut wildcard_reader = pubsub.subscribe("wallet-updates-*".to_string());
+        let mut exact_reader = pubsub.subscribe("wallet-updates-1".to_string());
+
+        pubsub.publish("wallet-updates-1".to_string(), 1);
+        pubsub.publish("wallet-updates-2".to_string(), 2);
+        pubsub.publish("order-state".to_string(), 3);
+
+        assert_eq!(1, wildcard_reader.next_message().await);
+        assert_eq!(2, wildcard_reader.next_message().await);
+        assert!(!wildcard_reader.has_next());
+
+        assert_eq!(1, exact_reader.next_message().await);
+        assert!(!exact_reader.has_next());
+
+        drop(wildcard_reader);
+        assert!(!pubsub.has_listeners(&"wallet-updates-*".to_string()));
+    }
+
+    /// Tests that a full buffer drops messages according to its overflow policy, and that
+    /// the drops are counted as lag
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let pubsub = SystemBus::<u64>::new();
+        let mut drop_oldest = pubsub.subscribe_with_config(
+            TEST_TOPIC.to_string(),
+            SubscriptionConfig {
+                buffer_size: 2,
+                overflow_policy: OverflowPolicy::DropOldest,
+            },
+        );
+        let mut drop_newest = pubsub.subscribe_with_config(
+            TEST_TOPIC.to_string(),
+            SubscriptionConfig {
+                buffer_size: 2,
+                overflow_policy: OverflowPolicy::DropNewest,
+            },
+        );
+
+        for message in 1..=3 {
+            pubsub.publish(TEST_TOPIC.to_string(), message);
+        }
+
+        assert_eq!(drop_oldest.dropped(), 1);
+        assert_eq!(2, drop_oldest.next_message().await);
+        assert_eq!(3, drop_oldest.next_message().await);
+
+        assert_eq!(drop_newest.dropped(), 1);
+        assert_eq!(1, drop_newest.next_message().await);
+        assert_eq!(2, drop_newest.next_message().await);
+
+        let metrics = pubsub.metrics();
+        assert_eq!(metrics.len(), 2);
+        assert!(metrics
+            .iter()
+            .all(|subscriber| subscriber.dropped == 1 && subscriber.buffered == 0));
+    }
 }
@@ -1,11 +1,24 @@
 //! Groups type definitions relevant to all modules and at the top level
 
-use circuits::zk_circuits::valid_commitments::{ValidCommitments, ValidCommitmentsWitness};
+use circuits::{
+    types::note::Note,
+    zk_circuits::{
+        valid_commitments::{ValidCommitments, ValidCommitmentsWitness},
+        valid_settle::{ValidSettle, ValidSettleStatement, ValidSettleWitness},
+        valid_wallet_update::ValidWalletUpdateWitness,
+    },
+};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    price_reporter::reporter::PriceReport,
-    state::{NetworkOrderState, OrderIdentifier},
+    error::ErrorCode,
+    job_queue::JobQueueMetrics,
+    price_reporter::{
+        exchanges::Exchange, health::ExchangeHealthReport, reporter::PriceReport, tokens::Token,
+    },
+    state::{wallet::WalletIdentifier, NetworkOrderState, OrderIdentifier},
+    worker::WorkerStatus,
     MAX_BALANCES, MAX_FEES, MAX_ORDERS,
 };
 
@@ -17,6 +30,15 @@ use crate::{
 pub type SizedValidCommitments = ValidCommitments<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
 /// A `VALID COMMITMENTS` witness with default const generic sizing parameters
 pub type SizedValidCommitmentsWitness = ValidCommitmentsWitness<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
+/// A `VALID WALLET UPDATE` witness with default const generic sizing parameters
+pub type SizedValidWalletUpdateWitness =
+    ValidWalletUpdateWitness<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
+/// `VALID SETTLE` with default state element sizing
+pub type SizedValidSettle = ValidSettle<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
+/// A `VALID SETTLE` witness with default const generic sizing parameters
+pub type SizedValidSettleWitness = ValidSettleWitness<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
+/// A `VALID SETTLE` statement with default const generic sizing parameters
+pub type SizedValidSettleStatement = ValidSettleStatement<MAX_BALANCES, MAX_ORDERS, MAX_FEES>;
 
 // ----------------------
 // | Pubsub Topic Names |
@@ -27,6 +49,23 @@ pub type SizedValidCommitmentsWitness = ValidCommitmentsWitness<MAX_BALANCES, MA
 pub const HANDSHAKE_STATUS_TOPIC: &str = "handshakes";
 /// The topic published to when a state change occurs on an order
 pub const ORDER_STATE_CHANGE_TOPIC: &str = "order-state";
+/// The topic published to when an exchange connection transitions between
+/// healthy and unhealthy in a price reporter
+pub const EXCHANGE_HEALTH_TOPIC: &str = "exchange-health";
+/// The topic published to when an exchange is quarantined for deviating from the other
+/// exchanges, or released from quarantine
+pub const PRICE_DEVIATION_TOPIC: &str = "price-deviation";
+/// The topic published to when the coordinator restarts a failed worker or gives up on it
+pub const WORKER_STATUS_TOPIC: &str = "worker-status";
+/// The topic published to as the settlement of a completed match progresses
+pub const SETTLEMENT_STATUS_TOPIC: &str = "settlements";
+/// The topic published to periodically with the depth of each worker's job queue
+pub const JOB_QUEUE_METRICS_TOPIC: &str = "job-queue-metrics";
+
+/// The topic published to as a user-initiated update to the given wallet progresses
+pub fn wallet_update_topic(wallet_id: &WalletIdentifier) -> String {
+    format!("wallet-updates-{wallet_id}")
+}
 
 // ----------------------------
 // | System Bus Message Types |
@@ -50,6 +89,37 @@ pub enum SystemBusMessage {
         /// The order_id of the remote peer
         peer_order_id: OrderIdentifier,
     },
+    /// A message indicating that the MPC of a handshake timed out
+    HandshakeTimeout {
+        /// The order_id of the local party
+        local_order_id: OrderIdentifier,
+        /// The order_id of the remote peer
+        peer_order_id: OrderIdentifier,
+        /// The number of consecutive attempts on the order pair that have failed
+        attempt: u32,
+    },
+    /// A message indicating that an in-flight handshake was torn down, either because
+    /// one of its orders' match nullifiers was spent or because an operator cancelled it
+    HandshakeCancelled {
+        /// The request ID of the handshake
+        request_id: Uuid,
+        /// The order_id of the local party
+        local_order_id: OrderIdentifier,
+        /// The order_id of the remote peer
+        peer_order_id: OrderIdentifier,
+    },
+    /// A message indicating that a managing relayer has paid the local peer its share of
+    /// the fee on a match the local peer brokered
+    BrokerFeeNoteReceived {
+        /// The request ID of the brokered handshake
+        request_id: Uuid,
+        /// The first order in the brokered pair
+        order1: OrderIdentifier,
+        /// The second order in the brokered pair
+        order2: OrderIdentifier,
+        /// The fee note
+        note: Note,
+    },
     /// A message indicating that an order has changed state in the local order
     /// book
     OrderStateChange {
@@ -64,6 +134,121 @@ pub enum SystemBusMessage {
     PriceReportMedian(PriceReport),
     /// A message indicating that a new individual exchange PriceReport has been published
     PriceReportExchange(PriceReport),
+    /// A message indicating that an exchange's health has changed such that it is either
+    /// newly excluded from, or newly included in, the median price computation
+    ExchangeHealthChanged {
+        /// The base token of the price reporter
+        base_token: Token,
+        /// The quote token of the price reporter
+        quote_token: Token,
+        /// The exchange whose health changed
+        exchange: Exchange,
+        /// The health report of the exchange at the time of the transition
+        health: ExchangeHealthReport,
+    },
+    /// A message indicating that an exchange's midpoint deviated from the median of the other
+    /// exchanges for too long and was quarantined, or that it was released from quarantine
+    PriceDeviationAlert {
+        /// The base token of the price reporter
+        base_token: Token,
+        /// The quote token of the price reporter
+        quote_token: Token,
+        /// The exchange that was quarantined or released
+        exchange: Exchange,
+        /// The deviation of the exchange from the median of the others, in basis points
+        deviation_bps: f64,
+        /// Whether the exchange was quarantined; false if it was released
+        quarantined: bool,
+    },
+    /// A message indicating that a user-initiated wallet update has progressed
+    WalletUpdateProgress {
+        /// The wallet being updated
+        wallet_id: WalletIdentifier,
+        /// The identifier returned to the user when the update was requested
+        task_id: Uuid,
+        /// The stage the update has reached
+        status: WalletUpdateStatus,
+    },
+    /// A message indicating that the settlement of a completed match has progressed
+    SettlementProgress {
+        /// The request ID of the handshake that produced the match
+        request_id: Uuid,
+        /// The order_id of the local party
+        local_order_id: OrderIdentifier,
+        /// The order_id of the remote peer
+        peer_order_id: OrderIdentifier,
+        /// The stage the settlement has reached
+        status: SettlementStatus,
+    },
+    /// A message indicating that a worker has failed, been restarted, or been left down
+    WorkerStatusChanged {
+        /// The name of the worker
+        worker: String,
+        /// The status of the worker
+        status: WorkerStatus,
+    },
+    /// A message reporting the depth of each job queue and the jobs each has turned away
+    JobQueueMetrics {
+        /// The metrics of each queue
+        queues: Vec<JobQueueMetrics>,
+    },
+}
+
+/// The stages of a user-initiated wallet update
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum WalletUpdateStatus {
+    /// The proof of `VALID WALLET UPDATE` is being generated
+    Proving,
+    /// The update is being submitted to the contract
+    Submitting,
+    /// The update was accepted by the sequencer under the given transaction hash
+    Submitted {
+        /// The hash of the transaction, hex encoded
+        tx_hash: String,
+    },
+    /// The update failed, either before it was submitted or when the sequencer
+    /// rejected the submitted transaction
+    Failed {
+        /// The machine-readable classification of the failure
+        code: ErrorCode,
+        /// The reason the update failed
+        reason: String,
+    },
+}
+
+/// The stages of a match settlement
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SettlementStatus {
+    /// The match was committed to on-chain under the given transaction hash, ahead of its
+    /// settlement
+    Committed {
+        /// The hash of the transaction, hex encoded
+        tx_hash: String,
+    },
+    /// The fee of the settlement transaction is being estimated
+    EstimatingFee,
+    /// The network is congested, the settlement is retried after the given delay
+    Congested {
+        /// The reason the settlement was held back
+        reason: String,
+        /// The delay before the next attempt, in milliseconds
+        retry_in_ms: u64,
+    },
+    /// The settlement was accepted by the sequencer under the given transaction hash
+    Submitted {
+        /// The hash of the transaction, hex encoded
+        tx_hash: String,
+        /// The fee estimated for the transaction, in wei
+        estimated_fee: u64,
+    },
+    /// The settlement failed, either before it was submitted or when the sequencer
+    /// rejected the submitted transaction
+    Failed {
+        /// The machine-readable classification of the failure
+        code: ErrorCode,
+        /// The reason the settlement failed
+        reason: String,
+    },
 }
 
 /// A wrapper around a SystemBusMessage containing the topic, used for serializing websocket
@@ -1,12 +1,30 @@
 //! Defines the `Worker` trait; abstracting over worker-specific functionalities to allow
 //! the coordinator thread to start, cleanup, and restart workers
 
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::VecDeque,
     fmt::Debug,
     thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use tokio::sync::mpsc::Sender;
+use tokio::sync::{mpsc::Sender, oneshot};
+
+use crate::{
+    clock::SharedClock,
+    error::{ErrorCode, WorkerError},
+    rng::WorkerRng,
+};
+
+/// The default backoff before the first restart of a failed worker
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The default cap on the backoff between restarts of a failed worker
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// The default number of restarts a worker may make within the restart window
+const DEFAULT_MAX_RESTARTS: usize = 5;
+/// The default window over which a worker's restarts are counted
+const DEFAULT_RESTART_WINDOW: Duration = Duration::from_secs(600);
 
 /// The Worker trait abstracts over worker functionality with a series of callbacks that
 /// allow a worker to be started, cleaned up, and restarted
@@ -14,7 +32,7 @@ pub trait Worker {
     /// The configuration needed to spawn the implementing worker
     type WorkerConfig;
     /// The error type that results from an invalid startup or cleanup
-    type Error: 'static + Send + Clone + Debug;
+    type Error: 'static + Send + Clone + Debug + Into<WorkerError>;
 
     /// Create a new instance of the implementing worker
     fn new(config: Self::WorkerConfig) -> Result<Self, Self::Error>
@@ -35,6 +53,17 @@ pub trait Worker {
     /// Returns whether or not the implementing type is recoverable
     fn is_recoverable(&self) -> bool;
 
+    /// Returns whether the operator may pause the worker at runtime, i.e. whether the worker
+    /// can be recovered after the coordinator cancels it
+    fn is_pausable(&self) -> bool {
+        false
+    }
+
+    /// The backoff and restart budget the coordinator applies when recovering the worker
+    fn restart_policy(&self) -> RestartPolicy {
+        RestartPolicy::default()
+    }
+
     /// Recover the worker by re-allocating it
     ///
     /// This method consumes the worker instance so that it can transfer ownership
@@ -73,3 +102,179 @@ pub fn watch_worker<W: Worker>(worker: &mut W, failure_channel: Sender<()>) {
             .unwrap();
     }
 }
+
+/// The health of a worker as seen by the coordinator
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkerStatus {
+    /// The worker is running
+    Healthy,
+    /// The worker failed and will be restarted once its backoff elapses
+    Restarting {
+        /// The number of restarts, including this one, within the restart window
+        attempt: usize,
+        /// The backoff before the restart, in milliseconds
+        backoff_ms: u64,
+    },
+    /// The worker failed and will not be restarted; the relayer runs without it
+    Down {
+        /// The machine-readable classification of the failure
+        code: ErrorCode,
+        /// The reason the worker is not restarted
+        reason: String,
+    },
+    /// The worker was paused by the operator and does not run until it is resumed
+    Paused,
+}
+
+/// An operator's command to pause or resume a worker at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerCommand {
+    /// Cancel the worker and leave it down until it is resumed
+    Pause,
+    /// Re-allocate and start a paused worker
+    Resume,
+}
+
+/// A request to the coordinator to pause or resume a worker
+#[derive(Debug)]
+pub struct WorkerControlMessage {
+    /// The name of the worker, as reported in the worker statuses
+    pub worker: String,
+    /// The command to apply to the worker
+    pub command: WorkerCommand,
+    /// The channel on which the coordinator responds with the worker's status once the
+    /// command is applied, or the reason the command was refused
+    pub response_channel: oneshot::Sender<Result<WorkerStatus, String>>,
+}
+
+/// The backoff and restart budget applied when recovering a failed worker
+///
+/// The backoff doubles with each restart within the window, and a worker that restarts
+/// more than `max_restarts` times within the window is considered to be crash-looping
+/// and is left down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// The backoff before the first restart within the window
+    pub initial_backoff: Duration,
+    /// The cap on the backoff between restarts
+    pub max_backoff: Duration,
+    /// The number of restarts allowed within the window
+    pub max_restarts: usize,
+    /// The window over which restarts are counted
+    pub window: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            window: DEFAULT_RESTART_WINDOW,
+        }
+    }
+}
+
+/// Tracks the restarts of a single worker against its restart policy
+#[derive(Debug)]
+pub struct RestartTracker {
+    /// The policy restarts are checked against
+    policy: RestartPolicy,
+    /// The times of the restarts within the window, oldest first
+    restarts: VecDeque<Instant>,
+    /// The clock restarts are timed against
+    clock: SharedClock,
+    /// The source of jitter for the backoff
+    rng: WorkerRng,
+}
+
+impl RestartTracker {
+    /// Constructor
+    pub fn new(policy: RestartPolicy, clock: SharedClock, rng: WorkerRng) -> Self {
+        Self {
+            policy,
+            restarts: VecDeque::new(),
+            clock,
+            rng,
+        }
+    }
+
+    /// The number of restarts within the window
+    pub fn num_restarts(&self) -> usize {
+        self.restarts.len()
+    }
+
+    /// Record a restart, returning the backoff to wait before it, or `None` if the restart
+    /// budget for the window is exhausted
+    ///
+    /// The backoff is jittered uniformly over its upper half so that workers failing
+    /// together, e.g. on a shared dependency going down, do not restart in lockstep
+    pub fn next_backoff(&mut self) -> Option<Duration> {
+        let now = self.clock.now();
+        let window = self.policy.window;
+        while self
+            .restarts
+            .front()
+            .map_or(false, |oldest| now.duration_since(*oldest) > window)
+        {
+            self.restarts.pop_front();
+        }
+
+        if self.restarts.len() >= self.policy.max_restarts {
+            return None;
+        }
+
+        let exponent = self.restarts.len().min(u32::BITS as usize - 1) as u32;
+        let backoff = self
+            .policy
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.policy.max_backoff);
+        let backoff_ms = backoff.as_millis() as u64;
+        let jitter_ms = self.rng.gen_range(0..backoff_ms / 2 + 1);
+        self.restarts.push_back(now);
+
+        Some(Duration::from_millis(
+            backoff_ms - backoff_ms / 2 + jitter_ms,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::{clock::ManualClock, rng::WorkerRng};
+
+    use super::{RestartPolicy, RestartTracker};
+
+    /// Tests that the backoff grows within the window, and that the budget is exhausted
+    /// by a crash loop and replenished once the window passes
+    #[test]
+    fn test_restart_backoff() {
+        let clock = ManualClock::new(Duration::ZERO);
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(4),
+            max_restarts: 4,
+            window: Duration::from_secs(60),
+        };
+        let mut tracker =
+            RestartTracker::new(policy, Arc::new(clock.clone()), WorkerRng::new(None));
+
+        for expected_secs in [1, 2, 4, 4] {
+            let backoff = tracker.next_backoff().unwrap();
+            let expected = Duration::from_secs(expected_secs);
+            assert!(backoff <= expected && backoff >= expected / 2);
+            clock.advance(Duration::from_secs(1));
+        }
+        assert!(tracker.next_backoff().is_none());
+
+        clock.advance(policy.window);
+        assert_eq!(tracker.num_restarts(), 4);
+        let backoff = tracker.next_backoff().unwrap();
+        assert!(backoff <= policy.initial_backoff);
+        assert_eq!(tracker.num_restarts(), 1);
+    }
+}
@@ -2,6 +2,8 @@
 #![allow(non_snake_case)]
 #![allow(missing_docs)]
 
+use ark_crypto_primitives::sponge::poseidon::find_poseidon_ark_and_mds;
+use ark_ff::PrimeField;
 use memoize::memoize;
 use num_bigint::BigUint;
 
@@ -775,6 +777,60 @@ pub fn POSEIDON_ROUND_CONSTANTS_T_3() -> Vec<Vec<DalekRistrettoField>> {
     ]
 }
 
+/// The number of full rounds used by the wider permutations
+pub const POSEIDON_FULL_ROUNDS_WIDE: usize = 8;
+/// The number of partial rounds used by the t = 5 permutation
+pub const POSEIDON_PARTIAL_ROUNDS_T_5: usize = 56;
+/// The number of partial rounds used by the t = 9 permutation
+pub const POSEIDON_PARTIAL_ROUNDS_T_9: usize = 57;
+
+/// Below are the parameters for the wider permutations, t = 5 (4-1 hash) and t = 9 (8-1 hash)
+///
+/// Rather than storing these as literals, they are generated from the Grain LFSR described in
+/// the Poseidon paper (appendix F), which is the same procedure the reference scripts follow:
+///     https://eprint.iacr.org/2019/458.pdf
+/// The round numbers were generated by
+///     python3 calc_round_numbers.py
+/// taking the output for t = 5 and t = 9, \alpha = 5; for t = 3 the same script gives the
+/// R_f = 8, R_p = 56 used above
+#[memoize]
+pub fn POSEIDON_MDS_MATRIX_T_5() -> Vec<Vec<DalekRistrettoField>> {
+    generate_poseidon_constants(5 /* width */, POSEIDON_PARTIAL_ROUNDS_T_5).1
+}
+
+/// Round constants for t = 5 (4-1 hash)
+#[memoize]
+pub fn POSEIDON_ROUND_CONSTANTS_T_5() -> Vec<Vec<DalekRistrettoField>> {
+    generate_poseidon_constants(5 /* width */, POSEIDON_PARTIAL_ROUNDS_T_5).0
+}
+
+/// MDS matrix for t = 9 (8-1 hash)
+#[memoize]
+pub fn POSEIDON_MDS_MATRIX_T_9() -> Vec<Vec<DalekRistrettoField>> {
+    generate_poseidon_constants(9 /* width */, POSEIDON_PARTIAL_ROUNDS_T_9).1
+}
+
+/// Round constants for t = 9 (8-1 hash)
+#[memoize]
+pub fn POSEIDON_ROUND_CONSTANTS_T_9() -> Vec<Vec<DalekRistrettoField>> {
+    generate_poseidon_constants(9 /* width */, POSEIDON_PARTIAL_ROUNDS_T_9).0
+}
+
+/// Generates the round constants and MDS matrix, in that order, for a permutation of the
+/// given width with a capacity of one element
+fn generate_poseidon_constants(
+    width: usize,
+    partial_rounds: usize,
+) -> (Vec<Vec<DalekRistrettoField>>, Vec<Vec<DalekRistrettoField>>) {
+    find_poseidon_ark_and_mds::<DalekRistrettoField>(
+        DalekRistrettoField::MODULUS_BIT_SIZE as u64, /* prime_bits */
+        width - 1,                                    /* rate */
+        POSEIDON_FULL_ROUNDS_WIDE as u64,             /* full_rounds */
+        partial_rounds as u64,                        /* partial_rounds */
+        0,                                            /* skip_matrices */
+    )
+}
+
 /// Converts a literal hexadecimal string to a field element through BigUint
 /// this function should only ever be called on the constants above, so we panic
 /// if parsing fails
@@ -784,7 +840,11 @@ fn field_element_from_hex_string(byte_string: &[u8]) -> DalekRistrettoField {
 
 #[cfg(test)]
 mod test {
-    use super::{POSEIDON_MDS_MATRIX_T_3, POSEIDON_ROUND_CONSTANTS_T_3};
+    use super::{
+        POSEIDON_FULL_ROUNDS_WIDE, POSEIDON_MDS_MATRIX_T_3, POSEIDON_MDS_MATRIX_T_5,
+        POSEIDON_MDS_MATRIX_T_9, POSEIDON_PARTIAL_ROUNDS_T_5, POSEIDON_PARTIAL_ROUNDS_T_9,
+        POSEIDON_ROUND_CONSTANTS_T_3, POSEIDON_ROUND_CONSTANTS_T_5, POSEIDON_ROUND_CONSTANTS_T_9,
+    };
 
     #[test]
     fn test_parsing() {
@@ -792,4 +852,31 @@ mod test {
         POSEIDON_MDS_MATRIX_T_3();
         POSEIDON_ROUND_CONSTANTS_T_3();
     }
+
+    /// Tests that the generated parameters are shaped for their permutation width
+    #[test]
+    fn test_generated_dimensions() {
+        for (width, partial_rounds, mds, round_constants) in [
+            (
+                5,
+                POSEIDON_PARTIAL_ROUNDS_T_5,
+                POSEIDON_MDS_MATRIX_T_5(),
+                POSEIDON_ROUND_CONSTANTS_T_5(),
+            ),
+            (
+                9,
+                POSEIDON_PARTIAL_ROUNDS_T_9,
+                POSEIDON_MDS_MATRIX_T_9(),
+                POSEIDON_ROUND_CONSTANTS_T_9(),
+            ),
+        ] {
+            assert_eq!(mds.len(), width);
+            assert!(mds.iter().all(|row| row.len() == width));
+            assert_eq!(
+                round_constants.len(),
+                POSEIDON_FULL_ROUNDS_WIDE + partial_rounds
+            );
+            assert!(round_constants.iter().all(|row| row.len() == width));
+        }
+    }
 }
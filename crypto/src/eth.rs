@@ -0,0 +1,593 @@
+//! Ethereum compatible hashing and signatures over secp256k1
+//!
+//! Provides keccak256, the derivation of Ethereum addresses from secp256k1 keys, and the
+//! signing and verification of EIP-712 typed data, so that wallet operations and
+//! settlements may be authorized by Ethereum keys
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+use k256::ecdsa::{recoverable, signature::DigestSigner, SigningKey, VerifyingKey};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+/// The byte length of an Ethereum address
+const ADDRESS_LEN: usize = 20;
+/// The byte length of a keccak256 digest, and of each encoded EIP-712 member
+const WORD_LEN: usize = 32;
+/// The byte length of an Ethereum signature, encoded as `r || s || v`
+pub const SIGNATURE_LEN: usize = 65;
+/// The offset Ethereum adds to the recovery ID of a signature to give its `v`
+const RECOVERY_ID_OFFSET: u8 = 27;
+/// The prefix of an EIP-712 signing preimage, i.e. the EIP-191 version byte for
+/// structured data
+const EIP712_PREFIX: [u8; 2] = [0x19, 0x01];
+/// The encoded type of an EIP-712 domain that binds a name, version, chain and
+/// verifying contract
+const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+// ----------
+// | Errors |
+// ----------
+
+/// The error type for Ethereum hashing and signature helpers
+#[derive(Clone, Debug)]
+pub enum EthError {
+    /// An address could not be parsed from its hex encoding
+    AddressParse(String),
+    /// A signature could not be parsed, or no key could be recovered from it
+    InvalidSignature(String),
+    /// An error signing a message
+    Signing(String),
+}
+
+impl Display for EthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?}", self)
+    }
+}
+impl Error for EthError {}
+
+// ----------------------
+// | Hashes & Addresses |
+// ----------------------
+
+/// Hash the input with keccak256, the hash Ethereum uses throughout
+pub fn keccak256(data: &[u8]) -> [u8; WORD_LEN] {
+    Keccak256::digest(data).into()
+}
+
+/// An Ethereum address, the last 20 bytes of the keccak256 hash of an uncompressed
+/// secp256k1 public key
+///
+/// Serialized as its EIP-55 checksummed hex encoding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EthAddress(pub [u8; ADDRESS_LEN]);
+
+impl EthAddress {
+    /// Derive the address of a public key
+    pub fn from_verifying_key(key: &VerifyingKey) -> Self {
+        // The uncompressed encoding is prefixed with a tag byte that is not hashed
+        let point = key.to_encoded_point(false /* compress */);
+        let hash = keccak256(&point.as_bytes()[1..]);
+
+        Self(hash[WORD_LEN - ADDRESS_LEN..].try_into().unwrap())
+    }
+
+    /// Derive the address of a secret key
+    pub fn from_signing_key(key: &SigningKey) -> Self {
+        Self::from_verifying_key(&key.verifying_key())
+    }
+
+    /// The EIP-55 checksummed hex encoding of the address, in which the case of each
+    /// letter is given by the hash of the lowercase encoding
+    pub fn to_checksum_string(&self) -> String {
+        let lowercase = to_hex(&self.0);
+        let hash = keccak256(lowercase.as_bytes());
+
+        let checksummed: String = lowercase
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+
+        format!("0x{checksummed}")
+    }
+}
+
+impl Display for EthAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.to_checksum_string())
+    }
+}
+
+impl FromStr for EthAddress {
+    type Err = EthError;
+
+    /// Parse an address from its hex encoding; the case of the encoding is not checked
+    /// against its checksum
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = from_hex(s.strip_prefix("0x").unwrap_or(s))
+            .ok_or_else(|| EthError::AddressParse(format!("invalid hex: {s}")))?;
+        let bytes: [u8; ADDRESS_LEN] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            EthError::AddressParse(format!("expected {ADDRESS_LEN} bytes, got {}", bytes.len()))
+        })?;
+
+        Ok(Self(bytes))
+    }
+}
+
+impl TryFrom<String> for EthAddress {
+    type Error = EthError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<EthAddress> for String {
+    fn from(address: EthAddress) -> Self {
+        address.to_checksum_string()
+    }
+}
+
+// ---------------------
+// | EIP-712 Encodings |
+// ---------------------
+
+/// A struct that may be hashed and signed as EIP-712 typed data
+pub trait Eip712Struct {
+    /// The EIP-712 encoding of the struct's type, followed by the encodings of the struct
+    /// types it references sorted by name, e.g.
+    /// `Mail(Person from,Person to,string contents)Person(string name,address wallet)`
+    const ENCODED_TYPE: &'static str;
+
+    /// The EIP-712 encoding of each of the struct's members, in the order of its type
+    fn encode_members(&self) -> Vec<[u8; WORD_LEN]>;
+
+    /// The EIP-712 `hashStruct` of the struct, which is also the encoding of the struct
+    /// as a member of another
+    fn struct_hash(&self) -> [u8; WORD_LEN] {
+        let mut preimage = keccak256(Self::ENCODED_TYPE.as_bytes()).to_vec();
+        for member in self.encode_members() {
+            preimage.extend_from_slice(&member);
+        }
+
+        keccak256(&preimage)
+    }
+}
+
+/// Encode an unsigned integer member, e.g. a `uint256`; the value must fit in 256 bits
+pub fn encode_uint(value: &BigUint) -> [u8; WORD_LEN] {
+    let bytes = value.to_bytes_be();
+    assert!(bytes.len() <= WORD_LEN, "value exceeds 256 bits");
+
+    let mut word = [0u8; WORD_LEN];
+    word[WORD_LEN - bytes.len()..].copy_from_slice(&bytes);
+    word
+}
+
+/// Encode an unsigned integer member that fits in a `u64`
+pub fn encode_u64(value: u64) -> [u8; WORD_LEN] {
+    encode_uint(&BigUint::from(value))
+}
+
+/// Encode a `bool` member
+pub fn encode_bool(value: bool) -> [u8; WORD_LEN] {
+    encode_u64(value as u64)
+}
+
+/// Encode an `address` member
+pub fn encode_address(address: &EthAddress) -> [u8; WORD_LEN] {
+    let mut word = [0u8; WORD_LEN];
+    word[WORD_LEN - ADDRESS_LEN..].copy_from_slice(&address.0);
+    word
+}
+
+/// Encode a dynamic `bytes` member, which is encoded as its hash
+pub fn encode_bytes(value: &[u8]) -> [u8; WORD_LEN] {
+    keccak256(value)
+}
+
+/// Encode a `string` member, which is encoded as the hash of its UTF-8 bytes
+pub fn encode_string(value: &str) -> [u8; WORD_LEN] {
+    encode_bytes(value.as_bytes())
+}
+
+/// The domain that EIP-712 signatures are bound to, so that a signature for one
+/// application, chain, or contract may not be replayed against another
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip712Domain {
+    /// The name of the signing domain, e.g. the protocol
+    pub name: String,
+    /// The version of the signing domain
+    pub version: String,
+    /// The ID of the chain that signatures are valid on
+    pub chain_id: u64,
+    /// The address of the contract that verifies signatures
+    pub verifying_contract: EthAddress,
+}
+
+impl Eip712Struct for Eip712Domain {
+    const ENCODED_TYPE: &'static str = EIP712_DOMAIN_TYPE;
+
+    fn encode_members(&self) -> Vec<[u8; WORD_LEN]> {
+        vec![
+            encode_string(&self.name),
+            encode_string(&self.version),
+            encode_u64(self.chain_id),
+            encode_address(&self.verifying_contract),
+        ]
+    }
+}
+
+impl Eip712Domain {
+    /// The domain separator, mixed into the digest of every message signed in the domain
+    pub fn separator(&self) -> [u8; WORD_LEN] {
+        self.struct_hash()
+    }
+}
+
+/// The preimage of the digest that is signed for a message in a domain
+fn eip712_preimage<T: Eip712Struct>(domain: &Eip712Domain, message: &T) -> Vec<u8> {
+    let mut preimage = EIP712_PREFIX.to_vec();
+    preimage.extend_from_slice(&domain.separator());
+    preimage.extend_from_slice(&message.struct_hash());
+
+    preimage
+}
+
+/// The digest that is signed for a message in a domain
+pub fn eip712_digest<T: Eip712Struct>(domain: &Eip712Domain, message: &T) -> [u8; WORD_LEN] {
+    keccak256(&eip712_preimage(domain, message))
+}
+
+// --------------
+// | Signatures |
+// --------------
+
+/// A recoverable secp256k1 signature in Ethereum's encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EthSignature {
+    /// The `r` component of the signature
+    pub r: [u8; WORD_LEN],
+    /// The `s` component of the signature
+    pub s: [u8; WORD_LEN],
+    /// The recovery ID of the signature, offset by 27
+    pub v: u8,
+}
+
+impl EthSignature {
+    /// Encode the signature as `r || s || v`
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+        let mut bytes = [0u8; SIGNATURE_LEN];
+        bytes[..WORD_LEN].copy_from_slice(&self.r);
+        bytes[WORD_LEN..2 * WORD_LEN].copy_from_slice(&self.s);
+        bytes[2 * WORD_LEN] = self.v;
+
+        bytes
+    }
+
+    /// Decode a signature encoded as `r || s || v`; `v` may be given either as the
+    /// recovery ID or offset by 27
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EthError> {
+        if bytes.len() != SIGNATURE_LEN {
+            return Err(EthError::InvalidSignature(format!(
+                "expected {SIGNATURE_LEN} bytes, got {}",
+                bytes.len()
+            )));
+        }
+
+        let v = match bytes[2 * WORD_LEN] {
+            v @ (0 | 1) => v + RECOVERY_ID_OFFSET,
+            v => v,
+        };
+
+        Ok(Self {
+            r: bytes[..WORD_LEN].try_into().unwrap(),
+            s: bytes[WORD_LEN..2 * WORD_LEN].try_into().unwrap(),
+            v,
+        })
+    }
+
+    /// Convert the signature to the form keys are recovered from
+    fn to_recoverable(self) -> Result<recoverable::Signature, EthError> {
+        let recovery_id = self
+            .v
+            .checked_sub(RECOVERY_ID_OFFSET)
+            .ok_or_else(|| EthError::InvalidSignature(format!("invalid v: {}", self.v)))?;
+
+        let mut bytes = self.to_bytes();
+        bytes[2 * WORD_LEN] = recovery_id;
+        recoverable::Signature::try_from(bytes.as_slice())
+            .map_err(|err| EthError::InvalidSignature(err.to_string()))
+    }
+}
+
+impl From<recoverable::Signature> for EthSignature {
+    fn from(signature: recoverable::Signature) -> Self {
+        let bytes: &[u8] = signature.as_ref();
+        Self {
+            r: bytes[..WORD_LEN].try_into().unwrap(),
+            s: bytes[WORD_LEN..2 * WORD_LEN].try_into().unwrap(),
+            v: bytes[2 * WORD_LEN] + RECOVERY_ID_OFFSET,
+        }
+    }
+}
+
+/// Sign a message as EIP-712 typed data in the given domain
+pub fn sign_typed_data<T: Eip712Struct>(
+    key: &SigningKey,
+    domain: &Eip712Domain,
+    message: &T,
+) -> Result<EthSignature, EthError> {
+    let digest = Keccak256::new_with_prefix(eip712_preimage(domain, message));
+    let signature: recoverable::Signature = key
+        .try_sign_digest(digest)
+        .map_err(|err| EthError::Signing(err.to_string()))?;
+
+    Ok(signature.into())
+}
+
+/// Recover the address that signed a message as EIP-712 typed data in the given domain
+pub fn recover_typed_data_signer<T: Eip712Struct>(
+    domain: &Eip712Domain,
+    message: &T,
+    signature: &EthSignature,
+) -> Result<EthAddress, EthError> {
+    let digest = Keccak256::new_with_prefix(eip712_preimage(domain, message));
+    let key = signature
+        .to_recoverable()?
+        .recover_verifying_key_from_digest(digest)
+        .map_err(|err| EthError::InvalidSignature(err.to_string()))?;
+
+    Ok(EthAddress::from_verifying_key(&key))
+}
+
+/// Verify that a message was signed as EIP-712 typed data in the given domain by the
+/// given address
+pub fn verify_typed_data<T: Eip712Struct>(
+    domain: &Eip712Domain,
+    message: &T,
+    signature: &EthSignature,
+    signer: &EthAddress,
+) -> bool {
+    recover_typed_data_signer(domain, message, signature)
+        .map(|recovered| recovered == *signer)
+        .unwrap_or(false)
+}
+
+// -----------
+// | Helpers |
+// -----------
+
+/// Encode bytes as lowercase hex, without a prefix
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decode hex of either case, without a prefix
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+// ---------
+// | Tests |
+// ---------
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::SigningKey;
+
+    use super::{
+        eip712_digest, encode_address, encode_string, from_hex, keccak256,
+        recover_typed_data_signer, sign_typed_data, verify_typed_data, Eip712Domain, Eip712Struct,
+        EthAddress, EthSignature,
+    };
+
+    /// A person in the example of the EIP-712 specification
+    struct Person {
+        /// The person's name
+        name: String,
+        /// The person's wallet
+        wallet: EthAddress,
+    }
+
+    impl Eip712Struct for Person {
+        const ENCODED_TYPE: &'static str = "Person(string name,address wallet)";
+
+        fn encode_members(&self) -> Vec<[u8; 32]> {
+            vec![encode_string(&self.name), encode_address(&self.wallet)]
+        }
+    }
+
+    /// A mail in the example of the EIP-712 specification
+    struct Mail {
+        /// The sender of the mail
+        from: Person,
+        /// The recipient of the mail
+        to: Person,
+        /// The contents of the mail
+        contents: String,
+    }
+
+    impl Eip712Struct for Mail {
+        const ENCODED_TYPE: &'static str =
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)";
+
+        fn encode_members(&self) -> Vec<[u8; 32]> {
+            vec![
+                self.from.struct_hash(),
+                self.to.struct_hash(),
+                encode_string(&self.contents),
+            ]
+        }
+    }
+
+    /// Decode a hex string with a prefix
+    fn hex(s: &str) -> Vec<u8> {
+        from_hex(s.strip_prefix("0x").unwrap()).unwrap()
+    }
+
+    /// The domain of the example of the EIP-712 specification
+    fn example_domain() -> Eip712Domain {
+        Eip712Domain {
+            name: "Ether Mail".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            verifying_contract: "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+                .parse()
+                .unwrap(),
+        }
+    }
+
+    /// The message of the example of the EIP-712 specification
+    fn example_mail() -> Mail {
+        Mail {
+            from: Person {
+                name: "Cow".to_string(),
+                wallet: "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+                    .parse()
+                    .unwrap(),
+            },
+            to: Person {
+                name: "Bob".to_string(),
+                wallet: "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+                    .parse()
+                    .unwrap(),
+            },
+            contents: "Hello, Bob!".to_string(),
+        }
+    }
+
+    /// Tests keccak256 against the hash of the empty string
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            keccak256(&[]).to_vec(),
+            hex("0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+    }
+
+    /// Tests deriving and checksumming an address from a known secret key
+    #[test]
+    fn test_address_derivation() {
+        let key = SigningKey::from_bytes(&hex(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        ))
+        .unwrap();
+
+        let address = EthAddress::from_signing_key(&key);
+        assert_eq!(
+            address.to_string(),
+            "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+        );
+
+        // An address round trips through its checksummed encoding, and parses in any case
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let parsed: EthAddress = checksummed.to_lowercase().parse().unwrap();
+        assert_eq!(parsed.to_string(), checksummed);
+        assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"
+            .parse::<EthAddress>()
+            .is_err());
+    }
+
+    /// Tests the hashes of the example of the EIP-712 specification, and recovering its
+    /// signer from its signature
+    #[test]
+    fn test_eip712_example() {
+        let domain = example_domain();
+        let mail = example_mail();
+
+        assert_eq!(
+            domain.separator().to_vec(),
+            hex("0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        assert_eq!(
+            mail.struct_hash().to_vec(),
+            hex("0xc52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+        );
+        assert_eq!(
+            eip712_digest(&domain, &mail).to_vec(),
+            hex("0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+
+        let signature = EthSignature {
+            r: hex("0x4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d")
+                .try_into()
+                .unwrap(),
+            s: hex("0x07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562")
+                .try_into()
+                .unwrap(),
+            v: 28,
+        };
+        let signer = recover_typed_data_signer(&domain, &mail, &signature).unwrap();
+        assert_eq!(signer, mail.from.wallet);
+
+        // The signer's key is the hash of "cow"
+        let key = SigningKey::from_bytes(&keccak256(b"cow")).unwrap();
+        assert_eq!(EthAddress::from_signing_key(&key), signer);
+    }
+
+    /// Tests that a signature verifies for its signer, message, and domain only
+    #[test]
+    fn test_sign_verify() {
+        let key = SigningKey::from_bytes(&keccak256(b"signer")).unwrap();
+        let signer = EthAddress::from_signing_key(&key);
+        let domain = example_domain();
+        let mail = example_mail();
+
+        let signature = sign_typed_data(&key, &domain, &mail).unwrap();
+        assert!(verify_typed_data(&domain, &mail, &signature, &signer));
+        assert_eq!(
+            EthSignature::from_bytes(&signature.to_bytes()).unwrap(),
+            signature
+        );
+
+        // A different signer, message, or domain does not verify
+        assert!(!verify_typed_data(
+            &domain,
+            &mail,
+            &signature,
+            &mail.from.wallet
+        ));
+
+        let mut other_mail = example_mail();
+        other_mail.contents = "Goodbye, Bob!".to_string();
+        assert!(!verify_typed_data(
+            &domain,
+            &other_mail,
+            &signature,
+            &signer
+        ));
+
+        let mut other_domain = example_domain();
+        other_domain.chain_id = 5;
+        assert!(!verify_typed_data(
+            &other_domain,
+            &mail,
+            &signature,
+            &signer
+        ));
+    }
+}
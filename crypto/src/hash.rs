@@ -7,7 +7,11 @@ use ark_crypto_primitives::sponge::{
 use itertools::Itertools;
 
 use crate::{
-    constants::{POSEIDON_MDS_MATRIX_T_3, POSEIDON_ROUND_CONSTANTS_T_3},
+    constants::{
+        POSEIDON_FULL_ROUNDS_WIDE, POSEIDON_MDS_MATRIX_T_3, POSEIDON_MDS_MATRIX_T_5,
+        POSEIDON_MDS_MATRIX_T_9, POSEIDON_PARTIAL_ROUNDS_T_5, POSEIDON_PARTIAL_ROUNDS_T_9,
+        POSEIDON_ROUND_CONSTANTS_T_3, POSEIDON_ROUND_CONSTANTS_T_5, POSEIDON_ROUND_CONSTANTS_T_9,
+    },
     fields::DalekRistrettoField,
 };
 
@@ -50,3 +54,46 @@ pub fn default_poseidon_params() -> PoseidonConfig<DalekRistrettoField> {
         1,                              /* capacity */
     )
 }
+
+/// Returns the arkworks params for the t = 5 permutation, absorbing four elements
+/// per permutation
+pub fn poseidon_params_t5() -> PoseidonConfig<DalekRistrettoField> {
+    PoseidonConfig::new(
+        POSEIDON_FULL_ROUNDS_WIDE,      /* full_rounds */
+        POSEIDON_PARTIAL_ROUNDS_T_5,    /* partial_rounds */
+        5,                              /* alpha */
+        POSEIDON_MDS_MATRIX_T_5(),      /* mds matrix */
+        POSEIDON_ROUND_CONSTANTS_T_5(), /* round constants */
+        4,                              /* rate */
+        1,                              /* capacity */
+    )
+}
+
+/// Returns the arkworks params for the t = 9 permutation, absorbing eight elements
+/// per permutation
+pub fn poseidon_params_t9() -> PoseidonConfig<DalekRistrettoField> {
+    PoseidonConfig::new(
+        POSEIDON_FULL_ROUNDS_WIDE,      /* full_rounds */
+        POSEIDON_PARTIAL_ROUNDS_T_9,    /* partial_rounds */
+        5,                              /* alpha */
+        POSEIDON_MDS_MATRIX_T_9(),      /* mds matrix */
+        POSEIDON_ROUND_CONSTANTS_T_9(), /* round constants */
+        8,                              /* rate */
+        1,                              /* capacity */
+    )
+}
+
+/// Returns the params of the narrowest permutation that absorbs `arity` elements in a
+/// single permutation, or of the widest permutation if none does
+///
+/// Wider permutations cost more constraints per permutation but far fewer per element
+/// absorbed, so long inputs (e.g. whole wallets) should be hashed with the widest params
+pub fn poseidon_params_for_arity(arity: usize) -> PoseidonConfig<DalekRistrettoField> {
+    if arity <= 2 {
+        default_poseidon_params()
+    } else if arity <= 4 {
+        poseidon_params_t5()
+    } else {
+        poseidon_params_t9()
+    }
+}
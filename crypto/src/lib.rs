@@ -3,5 +3,7 @@
 #![deny(clippy::missing_docs_in_private_items)]
 
 pub mod constants;
+pub mod eth;
 pub mod fields;
 pub mod hash;
+pub mod params;
@@ -0,0 +1,108 @@
+//! Defines the serialized form of the Poseidon parameters, as distributed in the prover
+//! parameters bundle
+
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fields::{biguint_to_prime_field, prime_field_to_biguint, DalekRistrettoField},
+    hash::{default_poseidon_params, poseidon_params_t5, poseidon_params_t9},
+};
+
+/// The parameters of a single Poseidon permutation, with field elements in canonical form
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoseidonParams {
+    /// The number of full rounds
+    pub full_rounds: usize,
+    /// The number of partial rounds
+    pub partial_rounds: usize,
+    /// The exponent of the s-box
+    pub alpha: u64,
+    /// The number of elements absorbed per permutation
+    pub rate: usize,
+    /// The number of elements in the state that are neither absorbed into nor squeezed from
+    pub capacity: usize,
+    /// The MDS matrix applied in the mix layer
+    pub mds_matrix: Vec<Vec<BigUint>>,
+    /// The round constants, one row per round
+    pub round_constants: Vec<Vec<BigUint>>,
+}
+
+impl From<&PoseidonConfig<DalekRistrettoField>> for PoseidonParams {
+    fn from(config: &PoseidonConfig<DalekRistrettoField>) -> Self {
+        Self {
+            full_rounds: config.full_rounds,
+            partial_rounds: config.partial_rounds,
+            alpha: config.alpha,
+            rate: config.rate,
+            capacity: config.capacity,
+            mds_matrix: to_biguint_matrix(&config.mds),
+            round_constants: to_biguint_matrix(&config.ark),
+        }
+    }
+}
+
+impl PoseidonParams {
+    /// Convert the parameters into the arkworks config used by the native hasher
+    pub fn to_config(&self) -> PoseidonConfig<DalekRistrettoField> {
+        PoseidonConfig::new(
+            self.full_rounds,
+            self.partial_rounds,
+            self.alpha,
+            to_field_matrix(&self.mds_matrix),
+            to_field_matrix(&self.round_constants),
+            self.rate,
+            self.capacity,
+        )
+    }
+}
+
+/// The parameters of every Poseidon permutation the relayer uses, narrowest first
+pub fn builtin_poseidon_params() -> Vec<PoseidonParams> {
+    [
+        default_poseidon_params(),
+        poseidon_params_t5(),
+        poseidon_params_t9(),
+    ]
+    .iter()
+    .map(PoseidonParams::from)
+    .collect()
+}
+
+/// Convert a matrix of field elements to their canonical integer representation
+fn to_biguint_matrix(matrix: &[Vec<DalekRistrettoField>]) -> Vec<Vec<BigUint>> {
+    matrix
+        .iter()
+        .map(|row| row.iter().map(prime_field_to_biguint).collect())
+        .collect()
+}
+
+/// Convert a matrix of canonical integers to field elements
+fn to_field_matrix(matrix: &[Vec<BigUint>]) -> Vec<Vec<DalekRistrettoField>> {
+    matrix
+        .iter()
+        .map(|row| row.iter().map(biguint_to_prime_field).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hash::poseidon_params_for_arity;
+
+    use super::{builtin_poseidon_params, PoseidonParams};
+
+    /// Tests that the serialized parameters convert back to the configs they were built from
+    #[test]
+    fn test_params_roundtrip() {
+        let params = builtin_poseidon_params();
+        assert_eq!(params.len(), 3);
+
+        for (arity, params) in [2, 4, 8].iter().zip(params.iter()) {
+            let config = poseidon_params_for_arity(*arity);
+            assert_eq!(PoseidonParams::from(&config), *params);
+            assert_eq!(params.to_config().ark, config.ark);
+            assert_eq!(params.to_config().mds, config.mds);
+        }
+    }
+}